@@ -0,0 +1,164 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate rucene;
+
+use rucene::core::codec::CodecEnum;
+use rucene::core::index::{CheckIndex, SegmentStatus};
+use rucene::core::store::directory::FSDirectory;
+use rucene::error::Result;
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: check_index <index_path> [-exorcise] [-fast]
+
+  -exorcise: actually write a new segments_N file, removing any problematic segments.
+             All the documents of those segments are lost. Back up the index first
+             and never run this while an IndexWriter is open on the index!
+  -fast: only verify the checksums of the index files.";
+
+fn print_segment(seg: &SegmentStatus) {
+    println!(
+        "  {}: codec={}, max_doc={}, del_count={}, compound={}, files={}, size={:.3} MB",
+        seg.name,
+        seg.codec,
+        seg.max_doc,
+        seg.del_count,
+        seg.compound,
+        seg.num_files,
+        seg.size_in_bytes as f64 / 1024.0 / 1024.0
+    );
+    if !seg.diagnostics.is_empty() {
+        println!("    diagnostics = {:?}", seg.diagnostics);
+    }
+    if let Some(ref s) = seg.postings {
+        println!(
+            "    postings: {} terms, {} term/doc pairs, {} positions",
+            s.term_count, s.tot_freq, s.tot_pos
+        );
+    }
+    if let Some(ref s) = seg.stored_fields {
+        println!(
+            "    stored fields: {} fields over {} docs",
+            s.tot_fields, s.doc_count
+        );
+    }
+    if let Some(ref s) = seg.term_vectors {
+        println!(
+            "    term vectors: {} vector fields over {} docs",
+            s.tot_vector_fields, s.doc_count
+        );
+    }
+    if let Some(ref s) = seg.doc_values {
+        println!(
+            "    doc values: {} fields ({} numeric, {} binary, {} sorted, {} sorted numeric, {} \
+             sorted set)",
+            s.tot_value_fields,
+            s.tot_numeric_fields,
+            s.tot_binary_fields,
+            s.tot_sorted_fields,
+            s.tot_sorted_numeric_fields,
+            s.tot_sorted_set_fields
+        );
+    }
+    if let Some(ref s) = seg.points {
+        println!(
+            "    points: {} fields, {} points",
+            s.tot_value_fields, s.tot_value_points
+        );
+    }
+    if seg.is_clean() {
+        println!("    OK");
+    } else {
+        for error in &seg.errors {
+            println!("    FAILED: {}", error);
+        }
+    }
+}
+
+fn run(path: &str, exorcise: bool, fast: bool) -> Result<bool> {
+    let directory = Arc::new(FSDirectory::new(path)?);
+    let mut checker = CheckIndex::<_, CodecEnum>::new(directory);
+    checker.set_checksums_only(fast);
+
+    let status = checker.check_index()?;
+    if status.missing_segments {
+        println!("ERROR: could not read any segments file in {}", path);
+        return Ok(false);
+    }
+    println!(
+        "Checking {:?}: {} segments",
+        status.segments_file_name, status.num_segments
+    );
+    for seg in &status.segment_infos {
+        print_segment(seg);
+    }
+
+    if status.clean {
+        println!("No problems were detected with this index.");
+        return Ok(true);
+    }
+    println!(
+        "WARNING: {} broken segments (containing {} documents) detected",
+        status.num_bad_segments, status.tot_lose_doc_count
+    );
+    if exorcise {
+        checker.exorcise_index(&status)?;
+        println!(
+            "Wrote new segments file, {} documents were lost",
+            status.tot_lose_doc_count
+        );
+    } else {
+        println!(
+            "WARNING: would write new segments file, and {} documents would be lost, if -exorcise \
+             were specified",
+            status.tot_lose_doc_count
+        );
+    }
+    Ok(false)
+}
+
+fn main() {
+    let mut path = None;
+    let mut exorcise = false;
+    let mut fast = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-exorcise" => exorcise = true,
+            "-fast" => fast = true,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    match run(&path, exorcise, fast) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("ERROR: {:?}", e);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate rucene;
+
+use rucene::core::store::lock::{
+    lock_stress_test, LockFactory, NativeFSLockFactory, SimpleFSLockFactory,
+};
+use rucene::error::Result;
+
+use std::env;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: lock_stress_test <id> <verifier_address> <native|simple> <lock_dir> \
+                     <sleep_ms> <count>
+
+  id: unique client id, in [0..255]
+  verifier_address: address of the running lock_verify_server, e.g. host:4444
+  native|simple: the lock factory to test
+  lock_dir: directory in which to create the lock file
+  sleep_ms: milliseconds to hold the lock, and to wait between attempts
+  count: number of attempts to obtain the lock
+
+  Run several clients at once, from different hosts for a shared file system.";
+
+fn run(
+    id: i32,
+    verifier: &str,
+    lock_factory: Arc<dyn LockFactory>,
+    lock_dir: &str,
+    sleep_ms: u64,
+    count: usize,
+) -> Result<()> {
+    println!(
+        "Connecting to server {} and waiting for the other clients...",
+        verifier
+    );
+    let obtained = lock_stress_test(
+        id,
+        verifier,
+        lock_factory,
+        Path::new(lock_dir),
+        Duration::from_millis(sleep_ms),
+        count,
+    )?;
+    println!(
+        "Finished, obtained the lock {} times out of {}.",
+        obtained, count
+    );
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 6 {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+    let lock_factory: Arc<dyn LockFactory> = match args[2].as_str() {
+        "native" => Arc::new(NativeFSLockFactory),
+        "simple" => Arc::new(SimpleFSLockFactory),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    let (id, sleep_ms, count) = match (
+        args[0].parse::<i32>(),
+        args[4].parse::<u64>(),
+        args[5].parse::<usize>(),
+    ) {
+        (Ok(id), Ok(sleep_ms), Ok(count)) if (0..256).contains(&id) => (id, sleep_ms, count),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = run(id, &args[1], lock_factory, &args[3], sleep_ms, count) {
+        eprintln!("ERROR: {:?}", e);
+        process::exit(2);
+    }
+}
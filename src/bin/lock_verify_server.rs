@@ -0,0 +1,49 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate rucene;
+
+use rucene::core::store::lock::LockVerifyServer;
+use rucene::error::Result;
+
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: lock_verify_server <bind_address> <max_clients>
+
+  Serves the lock_stress_test clients, failing if two of them ever hold the
+  lock at the same time. Start it before the clients, e.g.
+  lock_verify_server 0.0.0.0:4444 2";
+
+fn run(addr: &str, max_clients: usize) -> Result<()> {
+    let server = LockVerifyServer::bind(addr)?;
+    println!("Listening on {}...", server.local_addr()?);
+    server.run(max_clients)?;
+    println!("Server terminated, no lock violation detected.");
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let max_clients = match args.get(1).map(|s| s.parse::<usize>()) {
+        Some(Ok(n)) if args.len() == 2 && n > 0 => n,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = run(&args[0], max_clients) {
+        eprintln!("ERROR: {:?}", e);
+        process::exit(2);
+    }
+}
@@ -15,9 +15,11 @@ mod token_stream;
 
 pub(crate) use self::token_stream::{BinaryTokenStream, StringTokenStream};
 
+pub use self::token_stream::PreTokenizedTokenStream;
+
 mod whitespace_tokenizer;
 
-pub use self::whitespace_tokenizer::WhitespaceTokenizer;
+pub use self::whitespace_tokenizer::{WhitespaceAnalyzer, WhitespaceTokenizer};
 
 use crate::error::Error;
 use crate::Result;
@@ -76,6 +78,22 @@ impl Token {
         }
     }
 
+    /// Creates a token at `position` increments from the previous one.
+    pub fn with_term(
+        term: &[u8],
+        position: usize,
+        start_offset: usize,
+        end_offset: usize,
+    ) -> Token {
+        Token {
+            term: term.to_vec(),
+            position,
+            start_offset,
+            end_offset,
+            payload: Vec::with_capacity(0),
+        }
+    }
+
     pub fn clear(&mut self) {
         self.position = 1;
         self.start_offset = 0;
@@ -202,3 +220,18 @@ pub trait TokenStream: Debug {
         self.token_mut().end();
     }
 }
+
+/// An `Analyzer` builds the `TokenStream` that analyzes a text of a field, it
+/// is used where text has to be analyzed again after indexing, e.g. to find
+/// the offsets of query terms in stored text for highlighting.
+///
+/// The offsets of the tokens must be computed the same way as at index time.
+pub trait Analyzer {
+    fn token_stream(&self, field: &str, text: &str) -> Result<Box<dyn TokenStream>>;
+}
+
+impl<F: Fn(&str, &str) -> Result<Box<dyn TokenStream>>> Analyzer for F {
+    fn token_stream(&self, field: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        self(field, text)
+    }
+}
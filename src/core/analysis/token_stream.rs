@@ -14,7 +14,7 @@
 use crate::core::analysis::{Token, TokenStream, MAX_WORD_LEN};
 
 use crate::core::util::BytesRef;
-use crate::Result;
+use crate::error::{Error::IllegalArgument, Result};
 use std::cmp::Ordering;
 use std::collections::HashSet;
 
@@ -121,6 +121,80 @@ impl TokenStream for BinaryTokenStream {
     }
 }
 
+/// A `TokenStream` over a list of tokens produced outside of rucene, e.g. by
+/// an external NLP pipeline.
+///
+/// The `position` of each token is its position increment, the first one
+/// must be at least 1, and the offsets must not go backwards.
+#[derive(Debug)]
+pub struct PreTokenizedTokenStream {
+    token: Token,
+    tokens: Vec<Token>,
+    upto: usize,
+    final_offset: usize,
+}
+
+impl PreTokenizedTokenStream {
+    pub fn new(tokens: Vec<Token>) -> Result<Self> {
+        if let Some(first) = tokens.first() {
+            if first.position == 0 {
+                return Err(IllegalArgument(
+                    "first position increment must be > 0 (got 0)".into(),
+                ));
+            }
+        }
+        let mut last_start_offset = 0;
+        for token in &tokens {
+            if token.start_offset < last_start_offset || token.end_offset < token.start_offset {
+                return Err(IllegalArgument(format!(
+                    "offsets must not go backwards and end_offset must be >= start_offset, got \
+                     start_offset={}, end_offset={} for term {:?}",
+                    token.start_offset,
+                    token.end_offset,
+                    String::from_utf8_lossy(&token.term)
+                )));
+            }
+            last_start_offset = token.start_offset;
+        }
+        let final_offset = tokens.iter().map(|t| t.end_offset).max().unwrap_or(0);
+        Ok(PreTokenizedTokenStream {
+            token: Token::new(),
+            tokens,
+            upto: 0,
+            final_offset,
+        })
+    }
+}
+
+impl TokenStream for PreTokenizedTokenStream {
+    fn next_token(&mut self) -> Result<bool> {
+        if self.upto >= self.tokens.len() {
+            return Ok(false);
+        }
+        self.token.clone_from(&self.tokens[self.upto]);
+        self.upto += 1;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_token();
+        self.token.set_offset(self.final_offset, self.final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.upto = 0;
+        Ok(())
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub struct Word {
     value: String,
@@ -11,12 +11,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::analysis::{Token, TokenStream};
+use crate::core::analysis::{Analyzer, Token, TokenStream};
 
 use crate::Result;
 
 use std::fmt;
-use std::io::Read;
+use std::io::{Cursor, Read};
 
 use unicode_reader::CodePoints;
 
@@ -79,6 +79,17 @@ impl WhitespaceTokenizer {
     }
 }
 
+/// An `Analyzer` that tokenizes every field with a `WhitespaceTokenizer`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct WhitespaceAnalyzer;
+
+impl Analyzer for WhitespaceAnalyzer {
+    fn token_stream(&self, _field: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        let reader = Cursor::new(text.as_bytes().to_vec());
+        Ok(Box::new(WhitespaceTokenizer::new(Box::new(reader))))
+    }
+}
+
 impl TokenStream for WhitespaceTokenizer {
     fn next_token(&mut self) -> Result<bool> {
         self.clear_token();
@@ -132,8 +143,7 @@ impl TokenStream for WhitespaceTokenizer {
 
     fn end(&mut self) -> Result<()> {
         self.end_token();
-
-        Ok(())
+        self.token.set_offset(self.final_offset, self.final_offset)
     }
 
     fn reset(&mut self) -> Result<()> {
@@ -0,0 +1,388 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{
+    get_last_commit_generation, INDEX_FILE_PENDING_SEGMENTS, INDEX_FILE_SEGMENTS,
+};
+use crate::core::codec::{
+    check_footer, check_header, checksum_entire_file, write_footer, write_header, Codec,
+};
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::writer::{
+    CommitPoint, IndexDeletionPolicy, IndexWriter, SnapshotDeletionPolicy,
+};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::store::IOContext;
+use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState};
+use crate::Result;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// File name prefix of the backup manifests, followed by the generation of
+/// the backed up commit.
+pub const BACKUP_PREFIX: &str = "backup_";
+
+const CODEC_NAME: &str = "backup";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// A file of a backed up commit, with the checksum of its codec footer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackupFile {
+    pub name: String,
+    pub length: u64,
+    pub checksum: i64,
+}
+
+/// The files of a backed up commit, the `segments_N` file last.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackupManifest {
+    pub generation: i64,
+    pub files: Vec<BackupFile>,
+    pub user_data: HashMap<String, String>,
+}
+
+/// Backs up the commits of an index to the `target` directory, and restores
+/// them.
+///
+/// The commit is held by a `SnapshotDeletionPolicy` while it's copied, and
+/// only the files not part of a previous backup are copied, the backups
+/// sharing the segments they have in common. Every file is verified against
+/// the checksum of its codec footer, on both sides of the copy. A backup is
+/// complete once its manifest, the `backup_N` file listing its files, is
+/// written.
+///
+/// ```rust,ignore
+/// let policy = Arc::new(SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy));
+/// config.index_deletion_policy = policy.clone();
+/// ...
+/// let backup = IndexBackup::new(Arc::new(backup_directory));
+/// let manifest = backup.backup(&writer, &policy)?;
+/// ...
+/// backup.restore(manifest.generation, &empty_directory)?;
+/// ```
+pub struct IndexBackup<T: Directory> {
+    target: Arc<T>,
+}
+
+impl<T: Directory> IndexBackup<T> {
+    pub fn new(target: Arc<T>) -> Self {
+        IndexBackup { target }
+    }
+
+    /// Backs up the last commit of `writer`, whose deletion policy must be
+    /// `policy`.
+    pub fn backup<D, C, MS, MP, P>(
+        &self,
+        writer: &IndexWriter<D, C, MS, MP>,
+        policy: &SnapshotDeletionPolicy<P>,
+    ) -> Result<BackupManifest>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+        P: IndexDeletionPolicy,
+    {
+        let commit = policy.snapshot()?;
+        let res = self.backup_commit(writer.directory().as_ref(), &commit);
+        policy.release(&commit)?;
+        writer.delete_unused_files()?;
+        res
+    }
+
+    /// Backs up `commit` of the `source` directory, whose files mustn't be
+    /// deleted in the meantime.
+    pub fn backup_commit<D: Directory>(
+        &self,
+        source: &D,
+        commit: &CommitPoint,
+    ) -> Result<BackupManifest> {
+        let mut backed_up: HashMap<String, BackupFile> = HashMap::new();
+        for manifest in self.list()? {
+            if manifest.generation == commit.generation() {
+                return Ok(manifest);
+            }
+            for file in manifest.files {
+                backed_up.insert(file.name.clone(), file);
+            }
+        }
+        let existing: HashSet<String> = self.target.list_all()?.into_iter().collect();
+
+        let mut names: Vec<&String> = commit.file_names().iter().collect();
+        names.sort_by_key(|name| (name.starts_with(INDEX_FILE_SEGMENTS), name.as_str()));
+        let mut files = Vec::with_capacity(names.len());
+        let mut copied = HashSet::new();
+        for name in names {
+            let input = source.open_input(name, &IOContext::READ_ONCE)?;
+            let file = BackupFile {
+                name: name.clone(),
+                length: input.len(),
+                checksum: checksum_entire_file(input.as_ref())?,
+            };
+            if backed_up.get(name) != Some(&file) || !existing.contains(name) {
+                if existing.contains(name) {
+                    // left over by an interrupted backup
+                    self.target.delete_file(name)?;
+                }
+                copy_verified(source, self.target.as_ref(), &file, name)?;
+                copied.insert(name.clone());
+            }
+            files.push(file);
+        }
+        self.target.sync(&copied)?;
+
+        let manifest = BackupManifest {
+            generation: commit.generation(),
+            files,
+            user_data: commit.user_data().clone(),
+        };
+        self.write_manifest(&manifest)?;
+        Ok(manifest)
+    }
+
+    /// The backups in `target`, the oldest first.
+    pub fn list(&self) -> Result<Vec<BackupManifest>> {
+        self.generations()?
+            .into_iter()
+            .map(|gen| self.manifest(gen))
+            .collect()
+    }
+
+    /// The backup of the commit `generation`.
+    pub fn manifest(&self, generation: i64) -> Result<BackupManifest> {
+        let file_name = format!("{}{}", BACKUP_PREFIX, generation);
+        let mut input = self
+            .target
+            .open_checksum_input(&file_name, &IOContext::READ)?;
+        check_header(&mut input, CODEC_NAME, VERSION_START, VERSION_CURRENT)?;
+        let generation = input.read_vlong()?;
+        let count = input.read_vint()?;
+        let mut files = Vec::with_capacity(count.max(0) as usize);
+        for _ in 0..count {
+            files.push(BackupFile {
+                name: input.read_string()?,
+                length: input.read_vlong()? as u64,
+                checksum: input.read_long()?,
+            });
+        }
+        let count = input.read_vint()?;
+        let mut user_data = HashMap::with_capacity(count.max(0) as usize);
+        for _ in 0..count {
+            let key = input.read_string()?;
+            user_data.insert(key, input.read_string()?);
+        }
+        check_footer(&mut input)?;
+        Ok(BackupManifest {
+            generation,
+            files,
+            user_data,
+        })
+    }
+
+    /// Checks that all the files of the backup `generation` are in `target`
+    /// and match their checksums.
+    pub fn verify(&self, generation: i64) -> Result<()> {
+        for file in self.manifest(generation)?.files {
+            let input = self.target.open_input(&file.name, &IOContext::READ_ONCE)?;
+            check_file(&file, input.len(), checksum_entire_file(input.as_ref())?)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the backup `generation` into `dest`, which mustn't contain
+    /// an index. The `segments_N` file is written last, once the other files
+    /// are verified and synced.
+    pub fn restore<D: Directory>(&self, generation: i64, dest: &D) -> Result<()> {
+        if get_last_commit_generation(&dest.list_all()?)? >= 0 {
+            return Err(IllegalState(format!(
+                "can't restore backup {} in directory {}: it already contains an index",
+                generation, dest
+            )));
+        }
+        let manifest = self.manifest(generation)?;
+        let (segments_files, files): (Vec<&BackupFile>, Vec<&BackupFile>) = manifest
+            .files
+            .iter()
+            .partition(|f| f.name.starts_with(INDEX_FILE_SEGMENTS));
+        if segments_files.len() != 1 {
+            return Err(CorruptIndex(format!(
+                "backup {} doesn't have a single segments file",
+                generation
+            )));
+        }
+
+        let mut restored = HashSet::with_capacity(files.len());
+        for file in files {
+            copy_verified(self.target.as_ref(), dest, file, &file.name)?;
+            restored.insert(file.name.clone());
+        }
+        dest.sync(&restored)?;
+
+        let segments_file = segments_files[0];
+        let pending = format!(
+            "{}{}",
+            INDEX_FILE_PENDING_SEGMENTS,
+            &segments_file.name[INDEX_FILE_SEGMENTS.len()..]
+        );
+        copy_verified(self.target.as_ref(), dest, segments_file, &pending)?;
+        let mut pending_files = HashSet::with_capacity(1);
+        pending_files.insert(pending.clone());
+        dest.sync(&pending_files)?;
+        dest.rename(&pending, &segments_file.name)?;
+        dest.sync_metadata()
+    }
+
+    /// Deletes the backup `generation`, and the files no other backup
+    /// refers to.
+    pub fn delete(&self, generation: i64) -> Result<()> {
+        let mut deleted = None;
+        let mut referenced = HashSet::new();
+        for manifest in self.list()? {
+            if manifest.generation == generation {
+                deleted = Some(manifest);
+            } else {
+                referenced.extend(manifest.files.into_iter().map(|f| f.name));
+            }
+        }
+        let deleted = deleted.ok_or_else(|| {
+            IllegalArgument(format!("there is no backup of generation {}", generation))
+        })?;
+        // the manifest first, so that the backup is never seen incomplete
+        self.target
+            .delete_file(&format!("{}{}", BACKUP_PREFIX, generation))?;
+        for file in deleted.files {
+            if !referenced.contains(&file.name) {
+                self.target.delete_file(&file.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generations(&self) -> Result<Vec<i64>> {
+        let mut gens = vec![];
+        for file in self.target.list_all()? {
+            if let Some(gen) = file.strip_prefix(BACKUP_PREFIX) {
+                if let Ok(gen) = gen.parse::<i64>() {
+                    gens.push(gen);
+                }
+            }
+        }
+        gens.sort_unstable();
+        Ok(gens)
+    }
+
+    fn write_manifest(&self, manifest: &BackupManifest) -> Result<()> {
+        let file_name = format!("{}{}", BACKUP_PREFIX, manifest.generation);
+        {
+            let mut output = self.target.create_output(&file_name, &IOContext::Default)?;
+            write_header(&mut output, CODEC_NAME, VERSION_CURRENT)?;
+            output.write_vlong(manifest.generation)?;
+            output.write_vint(manifest.files.len() as i32)?;
+            for file in &manifest.files {
+                output.write_string(&file.name)?;
+                output.write_vlong(file.length as i64)?;
+                output.write_long(file.checksum)?;
+            }
+            output.write_vint(manifest.user_data.len() as i32)?;
+            for (key, value) in &manifest.user_data {
+                output.write_string(key)?;
+                output.write_string(value)?;
+            }
+            write_footer(&mut output)?;
+        }
+
+        let mut sync_files = HashSet::with_capacity(1);
+        sync_files.insert(file_name);
+        self.target.sync(&sync_files)?;
+        self.target.sync_metadata()
+    }
+}
+
+// copies `file` from `source` to `dest` under the name `dest_name`, and
+// checks the copy against the checksum of `file`
+fn copy_verified<S: Directory, D: Directory>(
+    source: &S,
+    dest: &D,
+    file: &BackupFile,
+    dest_name: &str,
+) -> Result<()> {
+    dest.copy_from(source, &file.name, dest_name, &IOContext::Default)?;
+    let input = dest.open_input(dest_name, &IOContext::READ_ONCE)?;
+    check_file(file, input.len(), checksum_entire_file(input.as_ref())?)
+}
+
+fn check_file(file: &BackupFile, length: u64, checksum: i64) -> Result<()> {
+    if length != file.length || checksum != file.checksum {
+        return Err(CorruptIndex(format!(
+            "file '{}' doesn't match its backup: length={} checksum=0x{:X}, expected \
+             length={} checksum=0x{:X}",
+            file.name, length, checksum, file.length, file.checksum
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::index::reader::IndexReader;
+    use crate::core::index::tests::{add_id_doc, snapshot_writer, InMemoryReader};
+    use crate::core::store::directory::{ByteBuffersDirectory, TrackingDirectoryWrapper};
+
+    #[test]
+    fn test_incremental_backup() {
+        let (writer, policy) = snapshot_writer();
+        let target = Arc::new(TrackingDirectoryWrapper::new(Arc::new(
+            ByteBuffersDirectory::new(),
+        )));
+        let backup = IndexBackup::new(Arc::clone(&target));
+
+        add_id_doc(&writer, "1");
+        add_id_doc(&writer, "2");
+        writer.commit().unwrap();
+        let first = backup.backup(&writer, &policy).unwrap();
+        assert_eq!(policy.snapshot_count().unwrap(), 0);
+        let first_files: HashSet<String> = first.files.iter().map(|f| f.name.clone()).collect();
+
+        add_id_doc(&writer, "3");
+        writer.commit().unwrap();
+        target.clear_create_files();
+        let second = backup.backup(&writer, &policy).unwrap();
+        assert!(second.generation > first.generation);
+        assert_eq!(backup.list().unwrap(), vec![first.clone(), second.clone()]);
+        // only the new segment, the segments file and the manifest are copied
+        let created = target.get_create_files();
+        assert!(created.is_disjoint(&first_files));
+        assert!(created.contains(&format!("{}{}", BACKUP_PREFIX, second.generation)));
+        backup.verify(second.generation).unwrap();
+
+        // the files of the second backup survive the deletion of the first
+        backup.delete(first.generation).unwrap();
+        assert_eq!(backup.list().unwrap(), vec![second.clone()]);
+        backup.verify(second.generation).unwrap();
+        assert!(backup.delete(first.generation).is_err());
+
+        let restored = Arc::new(ByteBuffersDirectory::new());
+        backup
+            .restore(second.generation, restored.as_ref())
+            .unwrap();
+        let reader = InMemoryReader::open(Arc::clone(&restored)).unwrap();
+        assert_eq!(reader.num_docs(), 3);
+        assert!(backup
+            .restore(second.generation, restored.as_ref())
+            .is_err());
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::Analyzer;
+use crate::Result;
+
+use std::cmp::Ordering;
+
+/// A class assigned to a text, with a score between 0 and 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassificationResult {
+    pub class: String,
+    pub score: f64,
+}
+
+/// Assigns classes to texts, based on the training documents of an index.
+///
+/// The training documents have the text in an indexed `text_field`, and their
+/// class in a `class_field` indexed as a single term and stored, e.g. a
+/// `StringField`.
+pub trait Classifier {
+    /// Returns the classes that `text` may belong to, the most likely first,
+    /// with scores summing to 1.
+    fn classes(&self, text: &str) -> Result<Vec<ClassificationResult>>;
+
+    /// Returns the most likely class of `text`, or `None` if there is no
+    /// training document related to it.
+    fn assign_class(&self, text: &str) -> Result<Option<ClassificationResult>> {
+        Ok(self.classes(text)?.into_iter().next())
+    }
+}
+
+// the terms of `text` in `field`, repeated as often as they occur
+pub(crate) fn analyze(analyzer: &dyn Analyzer, field: &str, text: &str) -> Result<Vec<Vec<u8>>> {
+    let mut token_stream = analyzer.token_stream(field, text)?;
+    token_stream.reset()?;
+    let mut terms = vec![];
+    while token_stream.next_token()? {
+        terms.push(token_stream.token().term.clone());
+    }
+    token_stream.end()?;
+    Ok(terms)
+}
+
+// sorts the results by decreasing score, then by class
+pub(crate) fn sort_results(results: &mut [ClassificationResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.class.cmp(&b.class))
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+    use crate::core::doc::{Field, FieldType, Fieldable, StringField, TEXT_FIELD_TYPE};
+    use crate::core::index::tests::{in_memory_writer, InMemoryIndexWriter, InMemoryReader};
+
+    /// An index of texts in the "text" field, classified in the "class"
+    /// field as "sport" or "tech".
+    pub fn training_index() -> (InMemoryIndexWriter, InMemoryReader) {
+        let writer = in_memory_writer();
+        let training = [
+            ("the team won the football match", "sport"),
+            ("the striker scored a goal in the match", "sport"),
+            ("the tennis player won the final", "sport"),
+            ("a new phone with a faster chip", "tech"),
+            ("the software update fixes the phone battery", "tech"),
+            ("the chip maker released a new processor", "tech"),
+        ];
+        let text_field_type = FieldType {
+            stored: true,
+            ..TEXT_FIELD_TYPE
+        };
+        for (text, class) in &training {
+            let token_stream = WhitespaceAnalyzer.token_stream("text", text).unwrap();
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(Field::new(
+                    "text".into(),
+                    text_field_type.clone(),
+                    Some((*text).into()),
+                    Some(token_stream),
+                )),
+                Box::new(StringField::new("class", class, true)),
+            ];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        let reader = writer.get_reader(true, false).unwrap();
+        (writer, reader)
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::classification::{analyze, sort_results, ClassificationResult, Classifier};
+use crate::core::codec::Codec;
+use crate::core::index::reader::IndexReader;
+use crate::core::index::Term;
+use crate::core::search::collector::TopDocsCollector;
+use crate::core::search::query::{BooleanQuery, Query, TermQuery};
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// Assigns the classes of the `k` training documents most similar to a
+/// text, each class scoring the sum of the scores of its documents.
+///
+/// The similar documents are found MoreLikeThis-style: the terms of the text
+/// are weighted by `tf * idf` in `text_field`, and the `max_query_terms`
+/// heaviest ones make a disjunction, each term boosted by its weight.
+///
+/// ```rust,ignore
+/// let classifier = KNearestNeighborClassifier::new(reader, "body", "category").with_k(5)?;
+/// let class = classifier.assign_class("the striker scored")?;
+/// ```
+pub struct KNearestNeighborClassifier<
+    C: Codec,
+    R: IndexReader<Codec = C> + ?Sized,
+    IR: Deref<Target = R>,
+> {
+    reader: IR,
+    analyzer: Box<dyn Analyzer>,
+    text_field: String,
+    class_field: String,
+    k: usize,
+    min_term_freq: usize,
+    min_doc_freq: i32,
+    max_query_terms: usize,
+}
+
+impl<C: Codec, R: IndexReader<Codec = C> + ?Sized, IR: Deref<Target = R>>
+    KNearestNeighborClassifier<C, R, IR>
+{
+    pub fn new(reader: IR, text_field: &str, class_field: &str) -> Self {
+        KNearestNeighborClassifier {
+            reader,
+            analyzer: Box::new(WhitespaceAnalyzer),
+            text_field: text_field.to_string(),
+            class_field: class_field.to_string(),
+            k: 10,
+            min_term_freq: 1,
+            min_doc_freq: 1,
+            max_query_terms: 25,
+        }
+    }
+
+    /// The analyzer of the classified texts, which should be the one of the
+    /// text field.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// The number of similar documents voting for their class.
+    pub fn with_k(mut self, k: usize) -> Result<Self> {
+        if k == 0 {
+            return Err(IllegalArgument("k must be at least 1".into()));
+        }
+        self.k = k;
+        Ok(self)
+    }
+
+    /// The terms occurring fewer times in the text are ignored.
+    pub fn with_min_term_freq(mut self, min_term_freq: usize) -> Self {
+        self.min_term_freq = min_term_freq;
+        self
+    }
+
+    /// The terms found in fewer training documents are ignored.
+    pub fn with_min_doc_freq(mut self, min_doc_freq: i32) -> Self {
+        self.min_doc_freq = min_doc_freq;
+        self
+    }
+
+    /// The maximum number of terms of the similarity query.
+    pub fn with_max_query_terms(mut self, max_query_terms: usize) -> Self {
+        self.max_query_terms = max_query_terms;
+        self
+    }
+
+    // the disjunction of the heaviest terms of `text`, or `None` if none of
+    // them qualifies
+    fn like_query(&self, text: &str) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut term_freqs: HashMap<Vec<u8>, usize> = HashMap::new();
+        for term in analyze(self.analyzer.as_ref(), &self.text_field, text)? {
+            *term_freqs.entry(term).or_insert(0) += 1;
+        }
+
+        let num_docs = self.reader.num_docs() as f32;
+        let mut weighted = Vec::with_capacity(term_freqs.len());
+        for (bytes, freq) in term_freqs {
+            if freq < self.min_term_freq {
+                continue;
+            }
+            let term = Term::new(self.text_field.clone(), bytes);
+            let doc_freq = self.reader.doc_freq(&term)?;
+            if doc_freq == 0 || doc_freq < self.min_doc_freq {
+                continue;
+            }
+            // the idf of `ClassicSimilarity`
+            let idf = 1.0 + (num_docs / (doc_freq as f32 + 1.0)).ln();
+            weighted.push((term, freq as f32 * idf));
+        }
+        if weighted.is_empty() {
+            return Ok(None);
+        }
+        weighted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        weighted.truncate(self.max_query_terms);
+
+        let max_weight = weighted[0].1;
+        let clauses = weighted
+            .into_iter()
+            .map(|(term, weight)| -> Box<dyn Query<C>> {
+                Box::new(TermQuery::new(term, weight / max_weight, None))
+            })
+            .collect();
+        Ok(Some(BooleanQuery::build(
+            vec![],
+            clauses,
+            vec![],
+            vec![],
+            1,
+        )?))
+    }
+}
+
+impl<C: Codec, R: IndexReader<Codec = C> + ?Sized, IR: Deref<Target = R>> Classifier
+    for KNearestNeighborClassifier<C, R, IR>
+{
+    fn classes(&self, text: &str) -> Result<Vec<ClassificationResult>> {
+        let query = match self.like_query(text)? {
+            Some(query) => query,
+            None => return Ok(vec![]),
+        };
+        let searcher = DefaultIndexSearcher::new(&*self.reader, None);
+        let mut collector = TopDocsCollector::new(self.k);
+        searcher.search(query.as_ref(), &mut collector)?;
+
+        let fields = [self.class_field.clone()];
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        let mut total = 0.0;
+        for hit in collector.top_docs().score_docs() {
+            let doc = self.reader.document(hit.doc_id(), &fields)?;
+            if let Some(class) = doc.get_string(&self.class_field) {
+                *scores.entry(class.to_string()).or_insert(0.0) += f64::from(hit.score());
+                total += f64::from(hit.score());
+            }
+        }
+
+        let mut results: Vec<ClassificationResult> = scores
+            .into_iter()
+            .map(|(class, score)| ClassificationResult {
+                class,
+                score: if total > 0.0 { score / total } else { 0.0 },
+            })
+            .collect();
+        sort_results(&mut results);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::classification::tests::training_index;
+
+    #[test]
+    fn test_knn_classifier() {
+        let (_writer, reader) = training_index();
+        let classifier = KNearestNeighborClassifier::new(&reader, "text", "class").with_k(0);
+        assert!(classifier.is_err());
+        let classifier = KNearestNeighborClassifier::new(&reader, "text", "class")
+            .with_k(3)
+            .unwrap();
+
+        let result = classifier
+            .assign_class("who scored the winning goal of the match")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.class, "sport");
+        let results = classifier.classes("a phone with a new chip").unwrap();
+        assert_eq!(results[0].class, "tech");
+        let total: f64 = results.iter().map(|r| r.score).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        assert_eq!(classifier.assign_class("unrelated words").unwrap(), None);
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::classification::{analyze, sort_results, ClassificationResult, Classifier};
+use crate::core::codec::{Codec, TermIterator, Terms};
+use crate::core::index::reader::IndexReader;
+use crate::core::index::Term;
+use crate::core::search::query::{BooleanQuery, Query, TermQuery};
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::Result;
+
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// A naive Bayes classifier computing its probabilities from the term
+/// statistics of the training documents.
+///
+/// The log-probability of a class `c` given the terms `t` of a text is, up to
+/// a constant, `log(P(c)) + sum(log(P(t|c)))`, where `P(c)` is the fraction
+/// of the training documents in `c`, and `P(t|c)` is the number of documents
+/// of `c` containing `t`, plus one for smoothing, divided by the estimated
+/// number of terms of `c`: the average number of unique terms per document
+/// times the number of documents of `c`, plus the number of training
+/// documents. The scores are these probabilities normalized to sum to 1.
+///
+/// ```rust,ignore
+/// let classifier = SimpleNaiveBayesClassifier::new(reader, "body", "category");
+/// let class = classifier.assign_class("the striker scored")?;
+/// ```
+pub struct SimpleNaiveBayesClassifier<
+    C: Codec,
+    R: IndexReader<Codec = C> + ?Sized,
+    IR: Deref<Target = R>,
+> {
+    reader: IR,
+    analyzer: Box<dyn Analyzer>,
+    text_field: String,
+    class_field: String,
+}
+
+impl<C: Codec, R: IndexReader<Codec = C> + ?Sized, IR: Deref<Target = R>>
+    SimpleNaiveBayesClassifier<C, R, IR>
+{
+    pub fn new(reader: IR, text_field: &str, class_field: &str) -> Self {
+        SimpleNaiveBayesClassifier {
+            reader,
+            analyzer: Box::new(WhitespaceAnalyzer),
+            text_field: text_field.to_string(),
+            class_field: class_field.to_string(),
+        }
+    }
+
+    /// The analyzer of the classified texts, which should be the one of the
+    /// text field.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    // the classes of the training documents, with their number of documents
+    fn class_doc_freqs(&self) -> Result<HashMap<Vec<u8>, i32>> {
+        let mut classes = HashMap::new();
+        for leaf in self.reader.leaves() {
+            if let Some(terms) = leaf.reader.terms(&self.class_field)? {
+                let mut terms_iter = terms.iterator()?;
+                while let Some(class) = terms_iter.next()? {
+                    *classes.entry(class).or_insert(0) += terms_iter.doc_freq()?;
+                }
+            }
+        }
+        Ok(classes)
+    }
+}
+
+impl<C: Codec, R: IndexReader<Codec = C> + ?Sized, IR: Deref<Target = R>> Classifier
+    for SimpleNaiveBayesClassifier<C, R, IR>
+{
+    fn classes(&self, text: &str) -> Result<Vec<ClassificationResult>> {
+        let mut term_freqs: HashMap<Vec<u8>, usize> = HashMap::new();
+        for term in analyze(self.analyzer.as_ref(), &self.text_field, text)? {
+            *term_freqs.entry(term).or_insert(0) += 1;
+        }
+        let classes = self.class_doc_freqs()?;
+        let docs_with_class = self.reader.doc_count(&self.class_field)?;
+        let text_doc_count = self.reader.doc_count(&self.text_field)?;
+        if term_freqs.is_empty() || classes.is_empty() || docs_with_class <= 0 {
+            return Ok(vec![]);
+        }
+        let avg_unique_terms = if text_doc_count > 0 {
+            self.reader.sum_doc_freq(&self.text_field)?.max(0) as f64 / f64::from(text_doc_count)
+        } else {
+            0.0
+        };
+
+        let searcher = DefaultIndexSearcher::new(&*self.reader, None);
+        let term_query = |field: &str, bytes: &[u8]| -> Box<dyn Query<C>> {
+            Box::new(TermQuery::new(
+                Term::new(field.to_string(), bytes.to_vec()),
+                1.0,
+                None,
+            ))
+        };
+        let mut log_probs = Vec::with_capacity(classes.len());
+        for (class, class_doc_freq) in classes {
+            let class_doc_freq = f64::from(class_doc_freq);
+            let mut log_prob = (class_doc_freq / f64::from(docs_with_class)).ln();
+            let class_terms = avg_unique_terms * class_doc_freq + f64::from(docs_with_class);
+            for (term, freq) in &term_freqs {
+                let query = BooleanQuery::build(
+                    vec![
+                        term_query(&self.text_field, term),
+                        term_query(&self.class_field, &class),
+                    ],
+                    vec![],
+                    vec![],
+                    vec![],
+                    0,
+                )?;
+                let hits = f64::from(searcher.count(query.as_ref())?);
+                log_prob += *freq as f64 * ((hits + 1.0) / class_terms).ln();
+            }
+            log_probs.push((String::from_utf8_lossy(&class).into_owned(), log_prob));
+        }
+
+        // normalizes exp(log_prob), shifted by the max to avoid underflows
+        let max = log_probs
+            .iter()
+            .map(|(_, p)| *p)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let total: f64 = log_probs.iter().map(|(_, p)| (p - max).exp()).sum();
+        let mut results: Vec<ClassificationResult> = log_probs
+            .into_iter()
+            .map(|(class, p)| ClassificationResult {
+                class,
+                score: (p - max).exp() / total,
+            })
+            .collect();
+        sort_results(&mut results);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::classification::tests::training_index;
+
+    #[test]
+    fn test_naive_bayes_classifier() {
+        let (_writer, reader) = training_index();
+        let classifier = SimpleNaiveBayesClassifier::new(&reader, "text", "class");
+
+        let results = classifier.classes("the team scored a goal").unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].class, "sport");
+        assert!(results[0].score > results[1].score);
+        let total: f64 = results.iter().map(|r| r.score).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let result = classifier
+            .assign_class("a faster processor chip")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.class, "tech");
+
+        // the priors decide for unknown terms, the classes are balanced here
+        let results = classifier.classes("unrelated").unwrap();
+        assert!((results[0].score - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, RwLock};
+
+use crate::core::codec::doc_values::PerFieldDocValuesFormat;
+use crate::core::codec::field_infos::Lucene60FieldInfosFormat;
+use crate::core::codec::norms::Lucene53NormsFormat;
+use crate::core::codec::points::Lucene60PointsFormat;
+use crate::core::codec::postings::{PerFieldFieldsReader, PerFieldPostingsFormat};
+use crate::core::codec::segment_infos::Lucene62SegmentInfoFormat;
+use crate::core::codec::stored_fields::{Lucene50StoredFieldsFormat, StoredFieldCompressMode};
+use crate::core::codec::term_vectors::CompressingTermVectorsFormat;
+use crate::core::codec::vectors::HnswVectorsFormat;
+use crate::core::codec::{
+    Codec, CodecEnum, Lucene50CompoundFormat, Lucene50LiveDocsFormat, Lucene62Codec,
+};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::error::{Error, Result};
+
+/// Builds a new instance of a registered codec.
+pub type CodecConstructor = Arc<dyn Fn() -> CodecEnum + Send + Sync>;
+
+const MAX_CODEC_NAME_LENGTH: usize = 128;
+
+lazy_static::lazy_static! {
+    static ref CODECS: RwLock<HashMap<String, CodecConstructor>> = {
+        let mut codecs: HashMap<String, CodecConstructor> = HashMap::new();
+        codecs.insert(
+            "Lucene62".to_string(),
+            Arc::new(|| CodecEnum::Lucene62(Lucene62Codec::default())),
+        );
+        RwLock::new(codecs)
+    };
+}
+
+/// Registers a codec constructor under `name`.
+///
+/// The name of a codec is written into every segment it encodes, and
+/// `codec_for_name` resolves it through this registry when the segment is
+/// read back, so the constructor must return a codec whose `name()` is
+/// `name`, and the codec must stay registered for as long as such segments
+/// exist. Registering an already registered name is an error.
+pub fn register_codec<F>(name: &str, constructor: F) -> Result<()>
+where
+    F: Fn() -> CodecEnum + Send + Sync + 'static,
+{
+    check_codec_name(name)?;
+    let mut codecs = CODECS.write()?;
+    if codecs.contains_key(name) {
+        return Err(IllegalArgument(format!(
+            "codec '{}' is already registered",
+            name
+        )));
+    }
+    codecs.insert(name.to_string(), Arc::new(constructor));
+    Ok(())
+}
+
+/// Returns the names of all registered codecs, sorted.
+pub fn available_codecs() -> Vec<String> {
+    let mut names: Vec<String> = CODECS
+        .read()
+        .map(|codecs| codecs.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// looks up a codec by name
+pub fn codec_for_name(name: &str) -> Result<CodecEnum> {
+    let constructor = CODECS.read()?.get(name).cloned();
+    match constructor {
+        Some(constructor) => {
+            let codec = constructor();
+            if codec.name() != name {
+                return Err(IllegalArgument(format!(
+                    "codec registered as '{}' is named '{}'",
+                    name,
+                    codec.name()
+                )));
+            }
+            Ok(codec)
+        }
+        None => Err(IllegalArgument(format!(
+            "Invalid codec name: {}, available codecs: {:?}",
+            name,
+            available_codecs()
+        ))),
+    }
+}
+
+fn check_codec_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.len() >= MAX_CODEC_NAME_LENGTH
+        || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+    {
+        return Err(IllegalArgument(format!(
+            "illegal codec name '{}', must be ascii alphanumeric and shorter than {}",
+            name, MAX_CODEC_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// A codec encoding segments with the formats of a `Lucene62Codec` under its
+/// own name, registered through `register_codec`.
+///
+/// This is how third-party crates plug in codecs with customized formats, e.g.
+/// a different stored fields compression mode, without adding a variant to
+/// `CodecEnum`.
+#[derive(Clone)]
+pub struct CustomCodec {
+    name: String,
+    codec: Lucene62Codec,
+}
+
+impl CustomCodec {
+    pub fn new(name: &str, codec: Lucene62Codec) -> Result<CustomCodec> {
+        check_codec_name(name)?;
+        Ok(CustomCodec {
+            name: name.to_string(),
+            codec,
+        })
+    }
+
+    /// Returns this codec with stored fields compressed in `mode`.
+    pub fn with_stored_fields_mode(mut self, mode: StoredFieldCompressMode) -> Self {
+        self.codec = self.codec.with_stored_fields_mode(mode);
+        self
+    }
+}
+
+impl Codec for CustomCodec {
+    type FieldsProducer = Arc<PerFieldFieldsReader>;
+    type PostingFmt = PerFieldPostingsFormat;
+    type DVFmt = PerFieldDocValuesFormat;
+    type StoredFmt = Lucene50StoredFieldsFormat;
+    type TVFmt = CompressingTermVectorsFormat;
+    type FieldFmt = Lucene60FieldInfosFormat;
+    type SegmentFmt = Lucene62SegmentInfoFormat;
+    type NormFmt = Lucene53NormsFormat;
+    type LiveDocFmt = Lucene50LiveDocsFormat;
+    type CompoundFmt = Lucene50CompoundFormat;
+    type PointFmt = Lucene60PointsFormat;
+    type VectorsFmt = HnswVectorsFormat;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn postings_format(&self) -> Self::PostingFmt {
+        self.codec.postings_format()
+    }
+
+    fn doc_values_format(&self) -> Self::DVFmt {
+        self.codec.doc_values_format()
+    }
+
+    fn stored_fields_format(&self) -> Self::StoredFmt {
+        self.codec.stored_fields_format()
+    }
+
+    fn term_vectors_format(&self) -> Self::TVFmt {
+        self.codec.term_vectors_format()
+    }
+
+    fn field_infos_format(&self) -> Self::FieldFmt {
+        self.codec.field_infos_format()
+    }
+
+    fn segment_info_format(&self) -> Self::SegmentFmt {
+        self.codec.segment_info_format()
+    }
+
+    fn norms_format(&self) -> Self::NormFmt {
+        self.codec.norms_format()
+    }
+
+    fn live_docs_format(&self) -> Self::LiveDocFmt {
+        self.codec.live_docs_format()
+    }
+
+    fn compound_format(&self) -> Self::CompoundFmt {
+        self.codec.compound_format()
+    }
+
+    fn points_format(&self) -> Self::PointFmt {
+        self.codec.points_format()
+    }
+
+    fn vectors_format(&self) -> Self::VectorsFmt {
+        self.codec.vectors_format()
+    }
+}
+
+impl TryFrom<String> for CustomCodec {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match codec_for_name(&value)? {
+            CodecEnum::Custom(c) => Ok(c),
+            _ => Err(CorruptIndex(format!(
+                "codec '{}' is not a custom codec",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_codec() {
+        assert!(available_codecs().contains(&"Lucene62".to_string()));
+        assert_eq!(codec_for_name("Lucene62").unwrap().name(), "Lucene62");
+        assert!(codec_for_name("RegistryTest").is_err());
+
+        register_codec("RegistryTest", || {
+            let codec = Lucene62Codec::default()
+                .with_stored_fields_mode(StoredFieldCompressMode::BestCompression);
+            CodecEnum::Custom(CustomCodec::new("RegistryTest", codec).unwrap())
+        })
+        .unwrap();
+        assert_eq!(
+            codec_for_name("RegistryTest").unwrap().name(),
+            "RegistryTest"
+        );
+        assert_eq!(
+            CodecEnum::try_from("RegistryTest".to_string())
+                .unwrap()
+                .name(),
+            "RegistryTest"
+        );
+
+        // names are unique and restricted to ascii word characters
+        assert!(register_codec("RegistryTest", CodecEnum::default).is_err());
+        assert!(register_codec("Lucene62", CodecEnum::default).is_err());
+        assert!(register_codec("bad name", CodecEnum::default).is_err());
+
+        // the constructed codec must carry the registered name
+        register_codec("RegistryMismatch", CodecEnum::default).unwrap();
+        assert!(codec_for_name("RegistryMismatch").is_err());
+    }
+}
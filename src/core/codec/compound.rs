@@ -19,6 +19,7 @@ use std::sync::Arc;
 use crate::core::codec::{codec_util, Codec};
 use crate::core::store::directory::Directory;
 use crate::core::store::io::{DataInput, DataOutput, IndexInput, IndexOutput};
+use crate::core::store::lock::Lock;
 use crate::core::store::IOContext;
 
 use crate::core::codec::segment_infos::{segment_file_name, strip_segment_name, SegmentInfo};
@@ -322,6 +323,12 @@ impl<D: Directory> Directory for Lucene50CompoundReader<D> {
     fn rename(&self, _source: &str, _dest: &str) -> Result<()> {
         unimplemented!()
     }
+
+    fn obtain_lock(&self, _name: &str) -> Result<Box<dyn Lock>> {
+        Err(Error::UnsupportedOperation(
+            "compound files are read-only and can't be locked".into(),
+        ))
+    }
 }
 
 impl<D: Directory> fmt::Display for Lucene50CompoundReader<D> {
@@ -25,13 +25,13 @@ use crate::core::index::merge::{
 use crate::core::index::reader::ReaderSlice;
 use crate::core::search::NO_MORE_DOCS;
 use crate::core::store::directory::Directory;
-use crate::core::util::bkd::LongBitSet;
 use crate::core::util::packed::{
     get_mutable_by_ratio, Mutable, MutableEnum, PackedLongValues, PackedLongValuesBuilder,
     PackedLongValuesBuilderType, Reader, COMPACT, DEFAULT_PAGE_SIZE,
 };
 use crate::core::util::{
-    BitsMut, BitsRequired, BytesRef, DocId, LongValues, MatchNoBits, Numeric, ReusableIterator,
+    BitsMut, BitsRequired, BytesRef, DocId, LongBitSet, LongValues, MatchNoBits, Numeric,
+    ReusableIterator,
 };
 
 use crate::Result;
@@ -15,20 +15,24 @@ use crate::core::codec::doc_values::lucene54::{
     Lucene54DocValuesConsumer, Lucene54DocValuesFormat,
 };
 use crate::core::codec::doc_values::{
-    BinaryDocValues, DocValuesConsumer, NumericDocValues, SortedDocValues, SortedNumericDocValues,
-    SortedSetDocValues,
+    distinct_producers, BinaryDocValuesProvider, DocValuesProducer, NumericDocValuesProvider,
+    SortedDocValuesProvider, SortedNumericDocValuesProvider, SortedSetDocValuesProvider,
 };
 use crate::core::codec::doc_values::{
-    BinaryDocValuesProvider, DocValuesProducer, NumericDocValuesProvider, SortedDocValuesProvider,
-    SortedNumericDocValuesProvider, SortedSetDocValuesProvider,
+    BinaryDocValues, DocValuesConsumer, NumericDocValues, SortedDocValues, SortedNumericDocValues,
+    SortedSetDocValues,
 };
 use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::lucene80::Lucene80DocValuesFormat;
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::{SimpleTextDocValuesFormat, SimpleTextDocValuesWriter};
 use crate::core::codec::*;
 use crate::core::doc::DocValuesType;
 use crate::core::index::merge::MergeState;
 use crate::core::store::directory::Directory;
-use crate::core::util::{BitsMut, BytesRef, Numeric, ReusableIterator};
+use crate::core::util::{
+    Accountable, BitsMut, BytesRef, NamedAccountable, Numeric, ReusableIterator,
+};
 
 use crate::error::Error::{IllegalArgument, IllegalState};
 use crate::Result;
@@ -71,8 +75,52 @@ pub fn get_full_segment_suffix(outer_segment_suffix: &str, segment_suffix: Strin
     }
 }
 
-#[derive(Default, Clone, Copy)]
-pub struct PerFieldDocValuesFormat;
+/// Enables per field docvalues support.
+///
+/// All fields use the default `Lucene54` format, or the one set with
+/// `with_default_format`, unless another format is set for them with
+/// `with_field_format`. The format name and segment suffix of each field are
+/// recorded in its `FieldInfo`, so segments are read back whatever the
+/// configuration.
+#[derive(Clone)]
+pub struct PerFieldDocValuesFormat {
+    field_formats: Arc<HashMap<String, DocValuesFormatEnum>>,
+    default_format: Arc<DocValuesFormatEnum>,
+}
+
+impl Default for PerFieldDocValuesFormat {
+    fn default() -> Self {
+        PerFieldDocValuesFormat {
+            field_formats: Arc::new(HashMap::new()),
+            default_format: Arc::new(DocValuesFormatEnum::Lucene54(
+                Lucene54DocValuesFormat::default(),
+            )),
+        }
+    }
+}
+
+impl PerFieldDocValuesFormat {
+    /// Returns this format with the doc values of fields without a format of
+    /// their own written by `format`.
+    pub fn with_default_format(mut self, format: DocValuesFormatEnum) -> Self {
+        self.default_format = Arc::new(format);
+        self
+    }
+
+    /// Returns this format with the doc values of `field` written by `format`.
+    pub fn with_field_format(mut self, field: &str, format: DocValuesFormatEnum) -> Self {
+        Arc::make_mut(&mut self.field_formats).insert(field.to_string(), format);
+        self
+    }
+
+    /// The format the doc values of `field` are written with.
+    pub fn doc_values_format_for_field(&self, field: &str) -> DocValuesFormatEnum {
+        self.field_formats
+            .get(field)
+            .cloned()
+            .unwrap_or_else(|| self.default_format.as_ref().clone())
+    }
+}
 
 impl DocValuesFormat for PerFieldDocValuesFormat {
     fn name(&self) -> &str {
@@ -93,19 +141,25 @@ impl DocValuesFormat for PerFieldDocValuesFormat {
     ) -> Result<DocValuesConsumerEnum<D, DW, C>> {
         Ok(DocValuesConsumerEnum::PerField(DocValuesFieldsWriter::new(
             state,
+            self.clone(),
         )))
     }
 }
 
+#[derive(Clone)]
 pub enum DocValuesFormatEnum {
     Lucene54(Lucene54DocValuesFormat),
+    Lucene80(Lucene80DocValuesFormat),
     PerField(PerFieldDocValuesFormat),
+    SimpleText(SimpleTextDocValuesFormat),
 }
 
 impl DocValuesFormat for DocValuesFormatEnum {
     fn name(&self) -> &str {
         match self {
             DocValuesFormatEnum::Lucene54(d) => d.name(),
+            DocValuesFormatEnum::Lucene80(d) => d.name(),
+            DocValuesFormatEnum::SimpleText(d) => d.name(),
             DocValuesFormatEnum::PerField(d) => d.name(),
         }
     }
@@ -116,6 +170,8 @@ impl DocValuesFormat for DocValuesFormatEnum {
     ) -> Result<Box<dyn DocValuesProducer>> {
         match self {
             DocValuesFormatEnum::Lucene54(d) => d.fields_producer(state),
+            DocValuesFormatEnum::Lucene80(d) => d.fields_producer(state),
+            DocValuesFormatEnum::SimpleText(d) => d.fields_producer(state),
             DocValuesFormatEnum::PerField(d) => d.fields_producer(state),
         }
     }
@@ -126,6 +182,8 @@ impl DocValuesFormat for DocValuesFormatEnum {
     ) -> Result<DocValuesConsumerEnum<D, DW, C>> {
         match self {
             DocValuesFormatEnum::Lucene54(d) => d.fields_consumer(state),
+            DocValuesFormatEnum::Lucene80(d) => d.fields_consumer(state),
+            DocValuesFormatEnum::SimpleText(d) => d.fields_consumer(state),
             DocValuesFormatEnum::PerField(d) => d.fields_consumer(state),
         }
     }
@@ -134,6 +192,7 @@ impl DocValuesFormat for DocValuesFormatEnum {
 pub enum DocValuesConsumerEnum<D: Directory, DW: Directory, C: Codec> {
     Lucene54(Lucene54DocValuesConsumer<DW::IndexOutput>),
     PerField(DocValuesFieldsWriter<D, DW, C>),
+    SimpleText(SimpleTextDocValuesWriter<DW::IndexOutput>),
 }
 
 impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsumerEnum<D, DW, C> {
@@ -144,6 +203,7 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
     ) -> Result<()> {
         match self {
             DocValuesConsumerEnum::Lucene54(d) => d.add_numeric_field(field_info, values),
+            DocValuesConsumerEnum::SimpleText(d) => d.add_numeric_field(field_info, values),
             DocValuesConsumerEnum::PerField(d) => d.add_numeric_field(field_info, values),
         }
     }
@@ -155,6 +215,7 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
     ) -> Result<()> {
         match self {
             DocValuesConsumerEnum::Lucene54(d) => d.add_binary_field(field_info, values),
+            DocValuesConsumerEnum::SimpleText(d) => d.add_binary_field(field_info, values),
             DocValuesConsumerEnum::PerField(d) => d.add_binary_field(field_info, values),
         }
     }
@@ -169,6 +230,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.add_sorted_field(field_info, values, doc_to_ord)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.add_sorted_field(field_info, values, doc_to_ord)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.add_sorted_field(field_info, values, doc_to_ord)
             }
@@ -185,6 +249,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.add_sorted_numeric_field(field_info, values, doc_to_value_count)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.add_sorted_numeric_field(field_info, values, doc_to_value_count)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.add_sorted_numeric_field(field_info, values, doc_to_value_count)
             }
@@ -202,6 +269,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.add_sorted_set_field(field_info, values, doc_to_ord_count, ords)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.add_sorted_set_field(field_info, values, doc_to_ord_count, ords)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.add_sorted_set_field(field_info, values, doc_to_ord_count, ords)
             }
@@ -214,6 +284,7 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
     ) -> Result<()> {
         match self {
             DocValuesConsumerEnum::Lucene54(d) => d.merge(merge_state),
+            DocValuesConsumerEnum::SimpleText(d) => d.merge(merge_state),
             DocValuesConsumerEnum::PerField(d) => d.merge(merge_state),
         }
     }
@@ -229,6 +300,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.merge_numeric_field(field_info, merge_state, to_merge, docs_with_field)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.merge_numeric_field(field_info, merge_state, to_merge, docs_with_field)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.merge_numeric_field(field_info, merge_state, to_merge, docs_with_field)
             }
@@ -246,6 +320,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.merge_binary_field(field_info, merge_state, to_merge, docs_with_field)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.merge_binary_field(field_info, merge_state, to_merge, docs_with_field)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.merge_binary_field(field_info, merge_state, to_merge, docs_with_field)
             }
@@ -262,6 +339,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.merge_sorted_field(field_info, merge_state, to_merge)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.merge_sorted_field(field_info, merge_state, to_merge)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.merge_sorted_field(field_info, merge_state, to_merge)
             }
@@ -278,6 +358,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.merge_sorted_set_field(field_info, merge_state, to_merge)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.merge_sorted_set_field(field_info, merge_state, to_merge)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.merge_sorted_set_field(field_info, merge_state, to_merge)
             }
@@ -294,6 +377,9 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesConsumer for DocValuesConsu
             DocValuesConsumerEnum::Lucene54(d) => {
                 d.merge_sorted_numeric_field(field_info, merge_state, to_merge)
             }
+            DocValuesConsumerEnum::SimpleText(d) => {
+                d.merge_sorted_numeric_field(field_info, merge_state, to_merge)
+            }
             DocValuesConsumerEnum::PerField(d) => {
                 d.merge_sorted_numeric_field(field_info, merge_state, to_merge)
             }
@@ -306,7 +392,12 @@ pub fn doc_values_format_for_name(format: &str) -> Result<DocValuesFormatEnum> {
         "Lucene54" => Ok(DocValuesFormatEnum::Lucene54(
             Lucene54DocValuesFormat::default(),
         )),
-        _ => unimplemented!(),
+        "Lucene80" => Ok(DocValuesFormatEnum::Lucene80(Lucene80DocValuesFormat)),
+        "SimpleText" => Ok(DocValuesFormatEnum::SimpleText(SimpleTextDocValuesFormat)),
+        _ => Err(IllegalArgument(format!(
+            "unknown doc values format: '{}'",
+            format
+        ))),
     }
 }
 
@@ -366,6 +457,25 @@ impl DocValuesFieldsReader {
     }
 }
 
+impl Accountable for DocValuesFieldsReader {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + distinct_producers(&self.fields)
+                .iter()
+                .map(|(p, _)| p.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        distinct_producers(&self.fields)
+            .into_iter()
+            .map(|(p, names)| {
+                NamedAccountable::new(format!("fields {}", names.join(", ")), p.as_ref())
+            })
+            .collect()
+    }
+}
+
 impl DocValuesProducer for DocValuesFieldsReader {
     fn get_numeric(&self, field: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
         match self.fields.get(&field.name) {
@@ -464,14 +574,16 @@ pub struct DocValuesFieldsWriter<D: Directory, DW: Directory, C: Codec> {
     formats: HashMap<String, ConsumerAndSuffix<D, DW, C>>,
     suffixes: HashMap<String, i32>,
     segment_write_state: SegmentWriteState<D, DW, C>,
+    format: PerFieldDocValuesFormat,
 }
 
 impl<D: Directory, DW: Directory, C: Codec> DocValuesFieldsWriter<D, DW, C> {
-    pub fn new(state: &SegmentWriteState<D, DW, C>) -> Self {
+    pub fn new(state: &SegmentWriteState<D, DW, C>, format: PerFieldDocValuesFormat) -> Self {
         DocValuesFieldsWriter {
             formats: HashMap::new(),
             suffixes: HashMap::new(),
             segment_write_state: state.clone(),
+            format,
         }
     }
 
@@ -483,14 +595,13 @@ impl<D: Directory, DW: Directory, C: Codec> DocValuesFieldsWriter<D, DW, C> {
                 format = Some(doc_values_format_for_name(&format_name)?);
             }
         }
-        if format.is_none() {
-            // TODO hard code for `PerFieldDocValuesFormat.getDocValuesFormatForField`
-            format = Some(DocValuesFormatEnum::Lucene54(
-                Lucene54DocValuesFormat::default(),
-            ));
+        let format = format.unwrap_or_else(|| self.format.doc_values_format_for_field(&field.name));
+        if let DocValuesFormatEnum::PerField(_) = format {
+            return Err(IllegalState(format!(
+                "per field doc values format can't be nested, field={}",
+                field.name
+            )));
         }
-
-        let format = format.unwrap();
         let format_name = format.name().to_string();
         let prev = field.put_attribute(PER_FIELD_VALUE_FORMAT_KEY.to_string(), format_name.clone());
         if field.dv_gen == -1 && prev.is_some() {
@@ -19,7 +19,7 @@ use crate::core::doc::DocValuesType;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::DocIterator;
 use crate::core::search::NO_MORE_DOCS;
-use crate::core::util::DocId;
+use crate::core::util::{BitsMut, DocId};
 use crate::Result;
 
 pub struct DocValuesIterator {
@@ -154,3 +154,61 @@ impl DocIterator for DocValuesIterator {
         self.cost as usize
     }
 }
+
+/// Iterates the documents of a segment that have a binary doc value, in doc id
+/// order, exposing the value of the current document.
+pub struct BinaryDocValuesIterator {
+    values: Box<dyn BinaryDocValues>,
+    docs_with_field: Box<dyn BitsMut>,
+    max_doc: DocId,
+    doc: DocId,
+    value: Vec<u8>,
+}
+
+impl BinaryDocValuesIterator {
+    pub fn new<C: Codec>(field: &str, leaf_reader: &LeafReaderContext<'_, C>) -> Result<Self> {
+        Ok(BinaryDocValuesIterator {
+            values: leaf_reader.reader.get_binary_doc_values(field)?,
+            docs_with_field: leaf_reader.reader.get_docs_with_field(field)?,
+            max_doc: leaf_reader.reader.max_doc(),
+            doc: -1,
+            value: Vec::with_capacity(0),
+        })
+    }
+
+    /// The value of the current document.
+    pub fn value(&self) -> &[u8] {
+        debug_assert!(self.doc >= 0 && self.doc != NO_MORE_DOCS);
+        &self.value
+    }
+}
+
+impl DocIterator for BinaryDocValuesIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let next = self.doc + 1;
+        self.advance(next)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = target;
+        while target < self.max_doc {
+            if self.docs_with_field.get(target as usize) {
+                self.value = self.values.get(target)?;
+                self.doc = target;
+                return Ok(target);
+            }
+            target += 1;
+        }
+        self.value.clear();
+        self.doc = NO_MORE_DOCS;
+        Ok(NO_MORE_DOCS)
+    }
+
+    fn cost(&self) -> usize {
+        self.max_doc as usize
+    }
+}
@@ -16,7 +16,7 @@ use crate::core::codec::doc_values::{
     SortedNumericDocValuesProvider, SortedSetDocValuesProvider,
 };
 use crate::core::codec::field_infos::FieldInfo;
-use crate::core::util::BitsMut;
+use crate::core::util::{Accountable, BitsMut};
 
 use crate::Result;
 use std::sync::Arc;
@@ -26,7 +26,7 @@ use std::sync::Arc;
 ///
 /// NOTE: the returned instance must always be thread-safe, this is different from
 /// the Lucene restraint
-pub trait DocValuesProducer: Send + Sync {
+pub trait DocValuesProducer: Send + Sync + Accountable {
     /// Returns `NumericDocValues` for this field.
     fn get_numeric(&self, field_info: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>>;
 
@@ -56,3 +56,22 @@ pub trait DocValuesProducer: Send + Sync {
     /// Returns an instance optimized for merging.
     fn get_merge_instance(&self) -> Result<Box<dyn DocValuesProducer>>;
 }
+
+/// Groups the per field `producers` by producer, so that a producer shared by
+/// several fields is only accounted once.
+pub(crate) fn distinct_producers<'a>(
+    producers: impl IntoIterator<Item = (&'a String, &'a Arc<dyn DocValuesProducer>)>,
+) -> Vec<(&'a Arc<dyn DocValuesProducer>, Vec<&'a str>)> {
+    let mut distinct: Vec<(&Arc<dyn DocValuesProducer>, Vec<&str>)> = Vec::new();
+    for (field, producer) in producers {
+        let ptr = Arc::as_ptr(producer) as *const ();
+        match distinct
+            .iter_mut()
+            .find(|(p, _)| Arc::as_ptr(p) as *const () == ptr)
+        {
+            Some((_, fields)) => fields.push(field),
+            None => distinct.push((producer, vec![field])),
+        }
+    }
+    distinct
+}
@@ -29,12 +29,11 @@ use crate::core::util::packed::{
 };
 use crate::core::util::packed::{COMPACT, FAST};
 use crate::core::util::BitsRequired;
-use crate::core::util::{BitSet, BitSetIterator, FixedBitSet};
+use crate::core::util::{BitSet, BitSetIterator, FixedBitSet, ImmutableBitSet};
 use crate::core::util::{
     Bits, BytesRef, DocId, Numeric, PagedBytes, PagedBytesDataInput, ReusableIterator, VariantValue,
 };
-use crate::core::util::{ByteBlockPool, DirectTrackingAllocator};
-use crate::core::util::{BytesRefHash, DirectByteStartArray, DEFAULT_CAPACITY};
+use crate::core::util::{ByteBlockPool, BytesRefHash, DEFAULT_CAPACITY};
 use crate::core::util::{Sorter, BINARY_SORT_THRESHOLD};
 
 use crate::error::Error;
@@ -416,12 +415,13 @@ impl<'a> Iterator for NumericDocValuesIter<'a> {
 
     fn next(&mut self) -> Option<Result<Numeric>> {
         if self.upto < self.max_doc {
-            let current = if self.docs_with_field.get(self.upto) {
-                let v = self.values_iter.next().unwrap();
-                Numeric::Long(v)
-            } else {
-                Numeric::Null
-            };
+            let current =
+                if self.upto < self.docs_with_field.len() && self.docs_with_field.get(self.upto) {
+                    let v = self.values_iter.next().unwrap();
+                    Numeric::Long(v)
+                } else {
+                    Numeric::Null
+                };
             self.upto += 1;
             Some(Ok(current))
         } else {
@@ -805,8 +805,6 @@ pub struct SortedDocValuesWriter {
     field_info: FieldInfo,
     docs_with_field: FixedBitSet,
     hash: BytesRefHash,
-    // the hash.pool is pointed to this, so it must be boxed
-    _bytes_block_pool: Box<ByteBlockPool>,
 
     final_ords: Option<PackedLongValues>,
     final_ord_map: Vec<i32>,
@@ -814,13 +812,7 @@ pub struct SortedDocValuesWriter {
 
 impl SortedDocValuesWriter {
     pub fn new(field_info: &FieldInfo) -> SortedDocValuesWriter {
-        let mut bytes_block_pool =
-            Box::new(ByteBlockPool::new(Box::new(DirectTrackingAllocator::new())));
-        let hash = BytesRefHash::new(
-            bytes_block_pool.as_mut(),
-            DEFAULT_CAPACITY,
-            Box::new(DirectByteStartArray::new(DEFAULT_CAPACITY)),
-        );
+        let hash = BytesRefHash::with_capacity(DEFAULT_CAPACITY);
         SortedDocValuesWriter {
             pending: PackedLongValuesBuilder::new(
                 DEFAULT_PAGE_SIZE,
@@ -830,14 +822,16 @@ impl SortedDocValuesWriter {
             field_info: field_info.clone(),
             docs_with_field: FixedBitSet::new(64),
             hash,
-            _bytes_block_pool: bytes_block_pool,
             final_ords: None,
             final_ord_map: vec![],
         }
     }
 
     pub fn add_value(&mut self, doc_id: DocId, value: &BytesRef) -> Result<()> {
-        if doc_id < self.pending.size() as DocId {
+        // only docs with a value are buffered in `pending`
+        if (doc_id as usize) < self.docs_with_field.len()
+            && self.docs_with_field.get(doc_id as usize)
+        {
             return Err(Error::RuntimeError(format!(
                 "DocValuesField {} appears more than once in this document (only one value is \
                  allowed per field)",
@@ -858,14 +852,14 @@ impl SortedDocValuesWriter {
             )));
         }
 
-        self.add_one_value(value);
+        self.add_one_value(value)?;
         self.docs_with_field.ensure_capacity(doc_id as usize);
         self.docs_with_field.set(doc_id as usize);
         Ok(())
     }
 
-    fn add_one_value(&mut self, value: &BytesRef) {
-        let mut term_id = self.hash.add(value) as i64;
+    fn add_one_value(&mut self, value: &BytesRef) -> Result<()> {
+        let mut term_id = self.hash.add(value)? as i64;
         if term_id < 0 {
             term_id = -term_id - 1;
         } else {
@@ -876,6 +870,7 @@ impl SortedDocValuesWriter {
         }
 
         self.pending.add(term_id);
+        Ok(())
     }
 
     fn sort_doc_values(
@@ -905,7 +900,10 @@ impl DocValuesWriter for SortedDocValuesWriter {
         consumer: &mut W,
     ) -> Result<()> {
         let max_doc = state.segment_info.max_doc();
-        debug_assert!(self.pending.size() == max_doc as i64);
+        debug_assert_eq!(
+            self.pending.size(),
+            self.docs_with_field.cardinality() as i64
+        );
 
         let value_count = self.hash.len();
 
@@ -964,7 +962,7 @@ impl DocValuesWriter for SortedDocValuesWriter {
         let doc_id_iter = BitSetIterator::new(&self.docs_with_field);
         for doc in doc_id_iter {
             let i = value_iter.next().unwrap() as usize;
-            data[doc as usize] = self.final_ord_map[i];
+            data[doc as usize] = ord_map[i];
         }
         self.final_ord_map = ord_map;
 
@@ -1048,13 +1046,14 @@ impl<'a> Iterator for SortedOrdsIterator<'a> {
         if self.doc_upto >= self.max_doc {
             None
         } else {
-            self.doc_upto += 1;
-            let ord = if self.docs_with_field.get(self.doc_upto as usize) {
+            let doc = self.doc_upto as usize;
+            let ord = if doc < self.docs_with_field.len() && self.docs_with_field.get(doc) {
                 let i = self.iter.next().unwrap();
                 self.ord_map[i as usize]
             } else {
                 -1
             };
+            self.doc_upto += 1;
             Some(Ok(Numeric::Int(ord)))
         }
     }
@@ -1102,19 +1101,11 @@ pub struct SortedSetDocValuesWriter {
     current_values: Vec<i64>,
     max_count: i32,
     hash: BytesRefHash,
-    // the hash.pool is pointed to this, so it must be boxed
-    _bytes_block_pool: Box<ByteBlockPool>,
 }
 
 impl SortedSetDocValuesWriter {
     pub fn new(field_info: &FieldInfo) -> SortedSetDocValuesWriter {
-        let mut bytes_block_pool =
-            Box::new(ByteBlockPool::new(Box::new(DirectTrackingAllocator::new())));
-        let hash = BytesRefHash::new(
-            bytes_block_pool.as_mut(),
-            DEFAULT_CAPACITY,
-            Box::new(DirectByteStartArray::new(DEFAULT_CAPACITY)),
-        );
+        let hash = BytesRefHash::with_capacity(DEFAULT_CAPACITY);
         SortedSetDocValuesWriter {
             pending: PackedLongValuesBuilder::new(
                 DEFAULT_PAGE_SIZE,
@@ -1131,7 +1122,6 @@ impl SortedSetDocValuesWriter {
             current_values: vec![],
             max_count: 0,
             hash,
-            _bytes_block_pool: bytes_block_pool,
         }
     }
 
@@ -1159,8 +1149,7 @@ impl SortedSetDocValuesWriter {
             self.current_doc += 1;
         }
 
-        self.add_one_value(value);
-        Ok(())
+        self.add_one_value(value)
     }
 
     // finalize currentDoc: this sorts the values in the current doc
@@ -1185,8 +1174,8 @@ impl SortedSetDocValuesWriter {
         self.current_doc += 1;
     }
 
-    fn add_one_value(&mut self, value: &BytesRef) {
-        let mut term_id = self.hash.add(value) as i64;
+    fn add_one_value(&mut self, value: &BytesRef) -> Result<()> {
+        let mut term_id = self.hash.add(value)? as i64;
         if term_id < 0 {
             term_id = -term_id - 1;
         } else {
@@ -1197,6 +1186,7 @@ impl SortedSetDocValuesWriter {
         }
 
         self.current_values.push(term_id);
+        Ok(())
     }
 
     fn sort_doc_values(
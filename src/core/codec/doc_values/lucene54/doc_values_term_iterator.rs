@@ -148,7 +148,7 @@ impl CompressedBinaryTermIterator {
     fn binary_search_index(&self, text: &[u8]) -> Result<i64> {
         let mut low = 0_i64;
         let mut high = self.num_reverse_index_values - 1;
-        while low < high {
+        while low <= high {
             let mid = low + (high - low) / 2;
             let start = self.reverse_index.term_addresses.get64(mid)?;
             let scratch = self.reverse_index.terms.fill(start);
@@ -36,11 +36,13 @@ pub struct Lucene54DocValuesConsumer<O: IndexOutput> {
     data: O,
     meta: O,
     max_doc: i32,
+    version: i32,
 }
 
 impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
     pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
         state: &SegmentWriteState<D, DW, C>,
+        version: i32,
         data_codec: &str,
         data_extension: &str,
         meta_codec: &str,
@@ -55,7 +57,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         codec_util::write_index_header(
             &mut data,
             data_codec,
-            Lucene54DocValuesFormat::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -69,7 +71,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
         codec_util::write_index_header(
             &mut meta,
             meta_codec,
-            Lucene54DocValuesFormat::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -80,6 +82,7 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
             data,
             meta,
             max_doc,
+            version,
         })
     }
 }
@@ -407,20 +410,24 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
             num_docs_with_value,
             Lucene54DocValuesFormat::DIRECT_MONOTONIC_BLOCK_SHIFT,
         )?;
+        // index of the first doc id of each block of docs
+        let mut jump_table = vec![];
+        let mut index = 0;
         let mut doc_id = 0;
         for nv in values {
             let nv = nv?;
-            match number_type {
-                NumberType::VALUE => {
-                    if !nv.is_null() {
-                        doc_ids_writer.add(doc_id)?;
-                    }
-                }
-                NumberType::ORDINAL => {
-                    if nv.long_value() != -1i64 {
-                        doc_ids_writer.add(doc_id)?;
-                    }
+            let has_value = match number_type {
+                NumberType::VALUE => !nv.is_null(),
+                NumberType::ORDINAL => nv.long_value() != -1i64,
+            };
+            if has_value {
+                while (jump_table.len() as i64) << Lucene54DocValuesFormat::JUMP_TABLE_BLOCK_SHIFT
+                    <= doc_id
+                {
+                    jump_table.push(index);
                 }
+                doc_ids_writer.add(doc_id)?;
+                index += 1;
             }
 
             doc_id += 1;
@@ -428,6 +435,17 @@ impl<O: IndexOutput> Lucene54DocValuesConsumer<O> {
 
         doc_ids_writer.finish()?;
 
+        if self.version >= Lucene54DocValuesFormat::VERSION_JUMP_TABLES {
+            self.meta
+                .write_vint(Lucene54DocValuesFormat::JUMP_TABLE_BLOCK_SHIFT)?;
+            self.meta.write_vint(jump_table.len() as i32)?;
+            let mut previous = 0;
+            for start in jump_table {
+                self.meta.write_vlong(start - previous)?;
+                previous = start;
+            }
+        }
+
         Ok(doc_id)
     }
 
@@ -21,8 +21,18 @@ use crate::core::store::directory::Directory;
 
 use crate::Result;
 
-#[derive(Copy, Clone, Default)]
-pub struct Lucene54DocValuesFormat;
+#[derive(Copy, Clone)]
+pub struct Lucene54DocValuesFormat {
+    version: i32,
+}
+
+impl Default for Lucene54DocValuesFormat {
+    fn default() -> Self {
+        Lucene54DocValuesFormat {
+            version: Self::VERSION_CURRENT,
+        }
+    }
+}
 
 impl Lucene54DocValuesFormat {
     const DATA_CODEC: &'static str = "Lucene54DocValuesData";
@@ -30,7 +40,9 @@ impl Lucene54DocValuesFormat {
     const META_CODEC: &'static str = "Lucene54DocValuesMetadata";
     const META_EXTENSION: &'static str = "dvm";
     pub const VERSION_START: i32 = 0;
-    pub const VERSION_CURRENT: i32 = 0;
+    // sparse numerics have a jump table
+    pub const VERSION_JUMP_TABLES: i32 = 1;
+    pub const VERSION_CURRENT: i32 = Self::VERSION_JUMP_TABLES;
 
     // indicates docvalues type
     pub const NUMERIC: u8 = 0;
@@ -92,6 +104,17 @@ impl Lucene54DocValuesFormat {
     // addressing uses 16k blocks
     pub const MONOTONIC_BLOCK_SIZE: i32 = 16384;
     pub const DIRECT_MONOTONIC_BLOCK_SHIFT: i32 = 16;
+
+    // sparse numerics jump to blocks of 64k docs
+    pub const JUMP_TABLE_BLOCK_SHIFT: i32 = 16;
+
+    /// Creates a format writing doc values that Java Lucene 6 can read, whose sparse
+    /// numerics have no jump tables.
+    pub fn java_lucene66() -> Self {
+        Lucene54DocValuesFormat {
+            version: Self::VERSION_START,
+        }
+    }
 }
 
 impl DocValuesFormat for Lucene54DocValuesFormat {
@@ -119,6 +142,7 @@ impl DocValuesFormat for Lucene54DocValuesFormat {
         Ok(DocValuesConsumerEnum::Lucene54(
             Lucene54DocValuesConsumer::new(
                 state,
+                self.version,
                 Self::DATA_CODEC,
                 Self::DATA_EXTENSION,
                 Self::META_CODEC,
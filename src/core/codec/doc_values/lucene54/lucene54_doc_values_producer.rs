@@ -26,14 +26,16 @@ use crate::core::util::{
     packed::{
         DirectMonotonicReader, DirectReader, MixinMonotonicLongValues, MonotonicBlockPackedReader,
     },
-    BitsMut, LiveBits, MatchAllBits, MatchNoBits, PagedBytes, PagedBytesReader, SparseBits,
+    Accountable, BitsMut, LiveBits, MatchAllBits, MatchNoBits, NamedAccountable, PagedBytes,
+    PagedBytesReader, SparseBits, SparseJumpTable,
 };
 
 use crate::error::Error::{CorruptIndex, IllegalArgument};
 use crate::Result;
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::mem;
+use std::sync::{Arc, PoisonError, RwLock};
 
 pub struct Lucene54DocValuesProducer {
     #[allow(dead_code)]
@@ -85,6 +87,7 @@ impl Lucene54DocValuesProducer {
             &mut checksum_input,
             &state.field_infos,
             &state.segment_info,
+            version,
             &mut numerics,
             &mut binaries,
             &mut sorted_sets,
@@ -156,6 +159,7 @@ impl Lucene54DocValuesProducer {
         meta: &mut dyn IndexInput,
         infos: &FieldInfos,
         segment_info: &SegmentInfo<D, C>,
+        version: i32,
         numerics: &mut HashMap<String, Arc<NumericEntry>>,
         binaries: &mut HashMap<String, BinaryEntry>,
         sorted_sets: &mut HashMap<String, SortedSetEntry>,
@@ -175,8 +179,12 @@ impl Lucene54DocValuesProducer {
             let dv_type = meta.read_byte()?;
             match dv_type {
                 Lucene54DocValuesFormat::NUMERIC => {
-                    let entry =
-                        Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)?;
+                    let entry = Lucene54DocValuesProducer::read_numeric_entry(
+                        info,
+                        segment_info,
+                        meta,
+                        version,
+                    )?;
                     match entry {
                         Some(n) => numerics.insert(info.name.clone(), n),
                         _ => unreachable!(),
@@ -193,6 +201,7 @@ impl Lucene54DocValuesProducer {
                         info,
                         segment_info,
                         meta,
+                        version,
                         binaries,
                         ords,
                     )?;
@@ -208,6 +217,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                                 binaries,
                                 ords,
                                 ord_indexes,
@@ -219,6 +229,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                                 binaries,
                                 ords,
                             )?;
@@ -243,6 +254,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                                 binaries,
                                 ords,
                             )?;
@@ -276,6 +288,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                             )?;
 
                             match entry {
@@ -301,6 +314,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                             )?;
                             match entry {
                                 Some(n) => ord_indexes.insert(info.name.clone(), n),
@@ -326,6 +340,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                             )?;
                             match entry {
                                 Some(n) => ords.insert(info.name.clone(), n),
@@ -350,6 +365,7 @@ impl Lucene54DocValuesProducer {
                                 info,
                                 segment_info,
                                 meta,
+                                version,
                             )? {
                                 numerics.insert(info.name.clone(), n);
                             } else {
@@ -380,6 +396,7 @@ impl Lucene54DocValuesProducer {
         info: &FieldInfo,
         segment_info: &SegmentInfo<D, C>,
         meta: &mut dyn IndexInput,
+        version: i32,
     ) -> Result<Option<Arc<NumericEntry>>> {
         let mut entry = NumericEntry::new();
         entry.format = meta.read_vint()?;
@@ -393,6 +410,17 @@ impl Lucene54DocValuesProducer {
                 block_shift,
             )?);
             entry.monotonic_meta = Some(Arc::clone(&monotonic_meta));
+            if version >= Lucene54DocValuesFormat::VERSION_JUMP_TABLES {
+                let shift = meta.read_vint()?;
+                let num_blocks = meta.read_vint()?;
+                let mut starts = Vec::with_capacity(num_blocks as usize);
+                let mut start = 0;
+                for _ in 0..num_blocks {
+                    start += meta.read_vlong()?;
+                    starts.push(start);
+                }
+                entry.jump_table = Some(SparseJumpTable::new(shift, starts));
+            }
         }
         entry.offset = meta.read_long()?;
         entry.count = meta.read_vlong()?;
@@ -471,8 +499,12 @@ impl Lucene54DocValuesProducer {
                     )));
                 }
                 // NOTE: Better way to handle the list?
-                entry.non_missing_values =
-                    Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)?;
+                entry.non_missing_values = Lucene54DocValuesProducer::read_numeric_entry(
+                    info,
+                    segment_info,
+                    meta,
+                    version,
+                )?;
             }
             _ => {
                 return Err(CorruptIndex(format!("unknown format: {}", entry.format)));
@@ -567,6 +599,7 @@ impl Lucene54DocValuesProducer {
         info: &FieldInfo,
         segment_info: &SegmentInfo<D, C>,
         meta: &mut dyn IndexInput,
+        version: i32,
         binaries: &mut HashMap<String, BinaryEntry>,
         ords: &mut HashMap<String, Arc<NumericEntry>>,
     ) -> Result<()> {
@@ -601,7 +634,9 @@ impl Lucene54DocValuesProducer {
             )));
         }
 
-        if let Some(n) = Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)? {
+        if let Some(n) =
+            Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta, version)?
+        {
             ords.insert(info.name.clone(), n);
         } else {
             unreachable!();
@@ -613,6 +648,7 @@ impl Lucene54DocValuesProducer {
         info: &FieldInfo,
         segment_info: &SegmentInfo<D, C>,
         meta: &mut dyn IndexInput,
+        version: i32,
         binaries: &mut HashMap<String, BinaryEntry>,
         ords: &mut HashMap<String, Arc<NumericEntry>>,
         ord_indexes: &mut HashMap<String, Arc<NumericEntry>>,
@@ -649,7 +685,9 @@ impl Lucene54DocValuesProducer {
             )));
         }
 
-        if let Some(n1) = Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)? {
+        if let Some(n1) =
+            Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta, version)?
+        {
             ords.insert(info.name.clone(), n1);
         } else {
             unreachable!();
@@ -669,7 +707,9 @@ impl Lucene54DocValuesProducer {
             )));
         }
 
-        if let Some(n2) = Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)? {
+        if let Some(n2) =
+            Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta, version)?
+        {
             ord_indexes.insert(info.name.clone(), n2);
         } else {
             unreachable!();
@@ -681,6 +721,7 @@ impl Lucene54DocValuesProducer {
         info: &FieldInfo,
         segment_info: &SegmentInfo<D, C>,
         meta: &mut dyn IndexInput,
+        version: i32,
         binaries: &mut HashMap<String, BinaryEntry>,
         ords: &mut HashMap<String, Arc<NumericEntry>>,
     ) -> Result<()> {
@@ -715,7 +756,9 @@ impl Lucene54DocValuesProducer {
             )));
         }
 
-        if let Some(n) = Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta)? {
+        if let Some(n) =
+            Lucene54DocValuesProducer::read_numeric_entry(info, segment_info, meta, version)?
+        {
             ords.insert(info.name.clone(), n);
         } else {
             unreachable!();
@@ -835,7 +878,12 @@ impl Lucene54DocValuesProducer {
 
         if let Some(ref meta) = entry.monotonic_meta {
             let doc_ids = DirectMonotonicReader::get_instance(meta.as_ref(), &doc_ids_data)?;
-            SparseBits::new(i64::from(self.max_doc), entry.num_docs_with_value, doc_ids)
+            let bits =
+                SparseBits::new(i64::from(self.max_doc), entry.num_docs_with_value, doc_ids)?;
+            Ok(match entry.jump_table {
+                Some(ref jump_table) => bits.with_jump_table(jump_table.clone()),
+                None => bits,
+            })
         } else {
             unreachable!();
         }
@@ -1131,6 +1179,49 @@ impl Lucene54DocValuesProducer {
     }
 }
 
+impl Accountable for Lucene54DocValuesProducer {
+    fn ram_bytes_used(&self) -> usize {
+        let addresses = self
+            .address_instances
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let reverse_indexes = self
+            .reverse_index_instances
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        mem::size_of::<Self>()
+            + addresses
+                .values()
+                .map(|a| a.ram_bytes_used())
+                .sum::<usize>()
+            + reverse_indexes
+                .values()
+                .map(|r| r.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources: Vec<NamedAccountable> = self
+            .address_instances
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(field, a)| NamedAccountable::new(format!("addresses field '{}'", field), a))
+            .collect();
+        resources.extend(
+            self.reverse_index_instances
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(field, r)| {
+                    NamedAccountable::new(format!("terms index field '{}'", field), r)
+                }),
+        );
+        resources.sort_by(|a, b| a.name.cmp(&b.name));
+        resources
+    }
+}
+
 impl DocValuesProducer for Lucene54DocValuesProducer {
     fn get_numeric(&self, field: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
         let link = self.numerics.get(&field.name).ok_or_else(|| {
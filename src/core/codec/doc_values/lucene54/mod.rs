@@ -35,7 +35,9 @@ use crate::core::codec::doc_values::{
     BinaryDocValues, BinaryDocValuesProvider, NumericDocValues, NumericDocValuesProvider,
     SortedDocValues, SortedNumericDocValues, SortedSetDocValues, NO_MORE_ORDS,
 };
+use crate::core::codec::lucene80::{Lucene80SortedDocValues, Lucene80SortedSetDocValues};
 use crate::core::codec::posting_iterator::EmptyPostingIterator;
+use crate::core::codec::simple_text::{SimpleTextSortedDocValues, SimpleTextSortedSetDocValues};
 use crate::core::codec::terms::{EmptyTermIterator, OrdTermState, SeekStatus, TermIterator};
 use crate::core::codec::Codec;
 use crate::core::index::reader::SearchLeafReader;
@@ -44,8 +46,8 @@ use crate::core::util::packed::{
     DirectMonotonicMeta, DirectPackedReader, MixinMonotonicLongValues, MonotonicBlockPackedReader,
 };
 use crate::core::util::{
-    Bits, BitsMut, CloneableLongValues, DocId, LiveBits, LongValues, MatchAllBits, MatchNoBits,
-    PagedBytesReader, SparseBits,
+    Accountable, Bits, BitsMut, CloneableLongValues, DocId, LiveBits, LongValues, MatchAllBits,
+    MatchNoBits, PagedBytesReader, SparseBits, SparseJumpTable,
 };
 use crate::Result;
 use std::sync::Arc;
@@ -439,6 +441,12 @@ pub struct ReverseTermsIndex {
     pub terms: PagedBytesReader,
 }
 
+impl Accountable for ReverseTermsIndex {
+    fn ram_bytes_used(&self) -> usize {
+        self.term_addresses.ram_bytes_used() + self.terms.ram_bytes_used()
+    }
+}
+
 /// meta-data entry for a numeric docvalues field
 struct NumericEntry {
     /// offset to the bitset representing docsWithField, or -1 if no documents have missing
@@ -455,6 +463,7 @@ struct NumericEntry {
     number_type: NumberType,
     table: Vec<i64>,
     monotonic_meta: Option<Arc<DirectMonotonicMeta>>,
+    jump_table: Option<SparseJumpTable>,
     non_missing_values: Option<Arc<NumericEntry>>,
 }
 
@@ -473,6 +482,7 @@ impl NumericEntry {
             number_type: NumberType::VALUE,
             table: Vec::new(),
             monotonic_meta: None,
+            jump_table: None,
             non_missing_values: None,
         }
     }
@@ -740,6 +750,10 @@ pub enum DocValuesTermIteratorEnum {
     Sorted(SortedDocValuesTermIterator<TailoredSortedDocValues>),
     SortedSetAddr(SortedSetDocValuesTermIterator<AddressedRandomAccessOrds>),
     SortedSetTable(SortedSetDocValuesTermIterator<TabledRandomAccessOrds>),
+    SimpleTextSorted(SortedDocValuesTermIterator<SimpleTextSortedDocValues>),
+    SimpleTextSortedSet(SortedSetDocValuesTermIterator<SimpleTextSortedSetDocValues>),
+    Lucene80Sorted(SortedDocValuesTermIterator<Lucene80SortedDocValues>),
+    Lucene80SortedSet(SortedSetDocValuesTermIterator<Lucene80SortedSetDocValues>),
     Empty(EmptyTermIterator),
 }
 
@@ -759,6 +773,22 @@ impl DocValuesTermIterator {
     pub fn sorted_set_table(d: SortedSetDocValuesTermIterator<TabledRandomAccessOrds>) -> Self {
         DocValuesTermIterator(DocValuesTermIteratorEnum::SortedSetTable(d))
     }
+    pub fn simple_text_sorted(d: SortedDocValuesTermIterator<SimpleTextSortedDocValues>) -> Self {
+        DocValuesTermIterator(DocValuesTermIteratorEnum::SimpleTextSorted(d))
+    }
+    pub fn simple_text_sorted_set(
+        d: SortedSetDocValuesTermIterator<SimpleTextSortedSetDocValues>,
+    ) -> Self {
+        DocValuesTermIterator(DocValuesTermIteratorEnum::SimpleTextSortedSet(d))
+    }
+    pub fn lucene80_sorted(d: SortedDocValuesTermIterator<Lucene80SortedDocValues>) -> Self {
+        DocValuesTermIterator(DocValuesTermIteratorEnum::Lucene80Sorted(d))
+    }
+    pub fn lucene80_sorted_set(
+        d: SortedSetDocValuesTermIterator<Lucene80SortedSetDocValues>,
+    ) -> Self {
+        DocValuesTermIterator(DocValuesTermIteratorEnum::Lucene80SortedSet(d))
+    }
     pub fn empty() -> Self {
         DocValuesTermIterator(DocValuesTermIteratorEnum::Empty(EmptyTermIterator {}))
     }
@@ -773,6 +803,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.next(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.next(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.next(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.next(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.next(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.next(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.next(),
             DocValuesTermIteratorEnum::Empty(t) => t.next(),
         }
     }
@@ -783,6 +817,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact(text),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.seek_exact(text),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.seek_exact(text),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.seek_exact(text),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.seek_exact(text),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_exact(text),
         }
     }
@@ -793,6 +831,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_ceil(text),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.seek_ceil(text),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.seek_ceil(text),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.seek_ceil(text),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.seek_ceil(text),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_ceil(text),
         }
     }
@@ -803,6 +845,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact_ord(ord),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.seek_exact_ord(ord),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.seek_exact_ord(ord),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.seek_exact_ord(ord),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.seek_exact_ord(ord),
             DocValuesTermIteratorEnum::Empty(t) => t.seek_exact_ord(ord),
         }
     }
@@ -813,6 +859,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.seek_exact_state(text, state),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.seek_exact_state(text, state),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.seek_exact_state(text, state),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.seek_exact_state(text, state),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.seek_exact_state(text, state),
             DocValuesTermIteratorEnum::Empty(_) => unreachable!(),
         }
     }
@@ -823,6 +873,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.term(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.term(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.term(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.term(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.term(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.term(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.term(),
             DocValuesTermIteratorEnum::Empty(t) => t.term(),
         }
     }
@@ -833,6 +887,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.ord(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.ord(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.ord(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.ord(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.ord(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.ord(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.ord(),
             DocValuesTermIteratorEnum::Empty(t) => t.ord(),
         }
     }
@@ -843,6 +901,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.doc_freq(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.doc_freq(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.doc_freq(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.doc_freq(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.doc_freq(),
             DocValuesTermIteratorEnum::Empty(t) => t.doc_freq(),
         }
     }
@@ -853,6 +915,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.total_term_freq(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.total_term_freq(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.total_term_freq(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.total_term_freq(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.total_term_freq(),
             DocValuesTermIteratorEnum::Empty(t) => t.total_term_freq(),
         }
     }
@@ -863,6 +929,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.postings(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.postings(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.postings(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.postings(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.postings(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.postings(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.postings(),
             DocValuesTermIteratorEnum::Empty(t) => t.postings(),
         }
     }
@@ -873,6 +943,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.postings_with_flags(flags),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.postings_with_flags(flags),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.postings_with_flags(flags),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.postings_with_flags(flags),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.postings_with_flags(flags),
             DocValuesTermIteratorEnum::Empty(t) => t.postings_with_flags(flags),
         }
     }
@@ -883,6 +957,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.term_state(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.term_state(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.term_state(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.term_state(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.term_state(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.term_state(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.term_state(),
             DocValuesTermIteratorEnum::Empty(_) => unimplemented!(),
         }
     }
@@ -893,6 +971,10 @@ impl TermIterator for DocValuesTermIterator {
             DocValuesTermIteratorEnum::Sorted(t) => t.is_empty(),
             DocValuesTermIteratorEnum::SortedSetAddr(t) => t.is_empty(),
             DocValuesTermIteratorEnum::SortedSetTable(t) => t.is_empty(),
+            DocValuesTermIteratorEnum::SimpleTextSorted(t) => t.is_empty(),
+            DocValuesTermIteratorEnum::SimpleTextSortedSet(t) => t.is_empty(),
+            DocValuesTermIteratorEnum::Lucene80Sorted(t) => t.is_empty(),
+            DocValuesTermIteratorEnum::Lucene80SortedSet(t) => t.is_empty(),
             DocValuesTermIteratorEnum::Empty(t) => t.is_empty(),
         }
     }
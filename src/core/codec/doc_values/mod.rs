@@ -15,13 +15,12 @@ pub mod lucene54;
 
 pub(crate) mod doc_values_format;
 
-pub(crate) use self::doc_values_format::{
-    DocValuesConsumerEnum, DocValuesFormat, DocValuesFormatEnum, PerFieldDocValuesFormat,
-};
+pub(crate) use self::doc_values_format::{DocValuesConsumerEnum, DocValuesFormat};
+pub use self::doc_values_format::{DocValuesFormatEnum, PerFieldDocValuesFormat};
 
 mod doc_values_producer;
 
-pub(crate) use self::doc_values_producer::DocValuesProducer;
+pub(crate) use self::doc_values_producer::{distinct_producers, DocValuesProducer};
 
 mod doc_values_consumer;
 
@@ -39,6 +38,7 @@ pub(crate) use self::doc_values_writer::{
 
 mod doc_values_iterator;
 
+pub use self::doc_values_iterator::BinaryDocValuesIterator;
 pub(crate) use self::doc_values_iterator::*;
 
 use crate::core::codec::doc_values::lucene54::DocValuesTermIterator;
@@ -13,6 +13,7 @@
 
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
 use crate::core::codec::segment_infos::{segment_file_name, SegmentInfo};
+use crate::core::codec::vectors::VectorSimilarity;
 use crate::core::codec::{codec_util, Codec};
 use crate::core::doc::{DocValuesType, IndexOptions};
 use crate::core::store::directory::Directory;
@@ -20,7 +21,10 @@ use crate::core::store::io::{
     BufferedChecksumIndexInput, ChecksumIndexInput, DataOutput, IndexInput,
 };
 use crate::core::store::IOContext;
-use crate::error::{Error::CorruptIndex, Result};
+use crate::error::{
+    Error::{CorruptIndex, IllegalArgument},
+    Result,
+};
 
 /// Encodes/decodes `FieldInfos`
 pub trait FieldInfosFormat {
@@ -50,12 +54,19 @@ const EXTENSION: &str = "fnm";
 // Codec header
 const CODEC_NAME: &str = "Lucene60FieldInfos";
 const FORMAT_START: i32 = 0;
-const FORMAT_CURRENT: i32 = FORMAT_START;
+/// Fields may be flagged as the soft deletes field, which is not supported and ignored
+const FORMAT_SOFT_DELETES: i32 = 1;
+/// Points record how many of their dimensions are indexed
+const FORMAT_SELECTIVE_INDEXING: i32 = 2;
+/// Fields record the dimension and similarity of their vectors
+const FORMAT_VECTORS: i32 = 3;
+const FORMAT_CURRENT: i32 = FORMAT_VECTORS;
 
 // Field flags
 const STORE_TERM_VECTOR: u8 = 0x1;
 const OMIT_NORMS: u8 = 0x2;
 const STORE_PAYLOADS: u8 = 0x4;
+const SOFT_DELETES_FIELD: u8 = 0x8;
 
 fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
     input: &mut T,
@@ -63,7 +74,7 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
     suffix: &str,
 ) -> Result<Vec<FieldInfo>> {
     let mut infos: Vec<FieldInfo> = Vec::new();
-    codec_util::check_index_header(
+    let version = codec_util::check_index_header(
         input,
         CODEC_NAME,
         FORMAT_START,
@@ -83,6 +94,12 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
             )));
         }
         let bits = input.read_byte()?;
+        if bits & SOFT_DELETES_FIELD != 0 && version < FORMAT_SOFT_DELETES {
+            return Err(CorruptIndex(format!(
+                "invalid field bits for field: {}, bits={}",
+                name, bits
+            )));
+        }
         let store_term_vector = (bits & STORE_TERM_VECTOR) != 0;
         let omit_norms = (bits & OMIT_NORMS) != 0;
         let store_payloads = (bits & STORE_PAYLOADS) != 0;
@@ -91,13 +108,24 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
         let dv_gen = input.read_long()?;
         let attributes = input.read_map_of_strings()?;
         let point_dimension_count = input.read_vint()?;
-        let point_num_bytes = if point_dimension_count != 0 {
-            input.read_vint()?
-        } else {
-            0
-        };
+        let mut point_index_dimension_count = point_dimension_count;
+        let mut point_num_bytes = 0;
+        if point_dimension_count != 0 {
+            if version >= FORMAT_SELECTIVE_INDEXING {
+                point_index_dimension_count = input.read_vint()?;
+            }
+            point_num_bytes = input.read_vint()?;
+        }
+        let mut vector_dimension = 0;
+        let mut vector_similarity = VectorSimilarity::Euclidean;
+        if version >= FORMAT_VECTORS {
+            vector_dimension = input.read_vint()?;
+            if vector_dimension != 0 {
+                vector_similarity = VectorSimilarity::from_byte(input.read_byte()?)?;
+            }
+        }
 
-        let info = FieldInfo::new(
+        let mut info = FieldInfo::new(
             name,
             field_number as u32,
             store_term_vector,
@@ -108,8 +136,10 @@ fn read_field_infos_from_index<T: IndexInput + ?Sized, D: Directory, C: Codec>(
             dv_gen,
             attributes,
             point_dimension_count as u32,
+            point_index_dimension_count as u32,
             point_num_bytes as u32,
         )?;
+        info.set_vector_dimension(vector_dimension as u32, vector_similarity)?;
         infos.push(info);
     }
     Ok(infos)
@@ -182,8 +212,28 @@ fn doc_values_byte(dv_type: DocValuesType) -> u8 {
     }
 }
 
-#[derive(Copy, Clone, Default)]
-pub struct Lucene60FieldInfosFormat;
+#[derive(Copy, Clone)]
+pub struct Lucene60FieldInfosFormat {
+    format: i32,
+}
+
+impl Default for Lucene60FieldInfosFormat {
+    fn default() -> Self {
+        Lucene60FieldInfosFormat {
+            format: FORMAT_CURRENT,
+        }
+    }
+}
+
+impl Lucene60FieldInfosFormat {
+    /// Creates a format writing field infos that Java Lucene 6 can read, which
+    /// requires all dimensions of points to be indexed and no vectors.
+    pub fn java_lucene66() -> Self {
+        Lucene60FieldInfosFormat {
+            format: FORMAT_START,
+        }
+    }
+}
 
 impl FieldInfosFormat for Lucene60FieldInfosFormat {
     fn read<D: Directory, DW: Directory, C: Codec>(
@@ -216,12 +266,34 @@ impl FieldInfosFormat for Lucene60FieldInfosFormat {
         infos: &FieldInfos,
         context: &IOContext,
     ) -> Result<()> {
+        if self.format < FORMAT_SELECTIVE_INDEXING {
+            if let Some(fi) = infos
+                .by_number
+                .values()
+                .find(|fi| fi.point_index_dimension_count != fi.point_dimension_count)
+            {
+                return Err(IllegalArgument(format!(
+                    "field '{}' indexes {} of its {} point dimensions, which field infos format \
+                     {} can't record",
+                    fi.name, fi.point_index_dimension_count, fi.point_dimension_count, self.format
+                )));
+            }
+        }
+        if self.format < FORMAT_VECTORS {
+            if let Some(fi) = infos.by_number.values().find(|fi| fi.vector_dimension > 0) {
+                return Err(IllegalArgument(format!(
+                    "field '{}' has vectors, which field infos format {} can't record",
+                    fi.name, self.format
+                )));
+            }
+        }
+
         let file_name = segment_file_name(&segment_info.name, segment_suffix, EXTENSION);
         let mut output = directory.create_output(&file_name, context)?;
         codec_util::write_index_header(
             &mut output,
             CODEC_NAME,
-            FORMAT_CURRENT,
+            self.format,
             segment_info.get_id(),
             segment_suffix,
         )?;
@@ -255,8 +327,17 @@ impl FieldInfosFormat for Lucene60FieldInfosFormat {
             let point_dimension_count = fi.point_dimension_count;
             output.write_vint(point_dimension_count as i32)?;
             if point_dimension_count > 0 {
+                if self.format >= FORMAT_SELECTIVE_INDEXING {
+                    output.write_vint(fi.point_index_dimension_count as i32)?;
+                }
                 output.write_vint(fi.point_num_bytes as i32)?;
             }
+            if self.format >= FORMAT_VECTORS {
+                output.write_vint(fi.vector_dimension as i32)?;
+                if fi.vector_dimension > 0 {
+                    output.write_byte(fi.vector_similarity.as_byte())?;
+                }
+            }
         }
 
         codec_util::write_footer(&mut output)
@@ -31,7 +31,7 @@ use serde::{Serialize, Serializer};
 
 // use crate::core::attribute::{OffsetAttribute, PayloadAttribute, PositionIncrementAttribute};
 use crate::core::codec::points::{MAX_DIMENSIONS, MAX_NUM_BYTES};
-use crate::core::codec::postings::{PER_FIELD_POSTING_FORMAT_KEY, PER_FIELD_POSTING_SUFFIX_KEY};
+use crate::core::codec::vectors::{VectorSimilarity, MAX_VECTOR_DIMENSION};
 use crate::core::doc::{DocValuesType, IndexOptions};
 
 /// Access to the Field Info file that describes document fields and whether or
@@ -51,7 +51,12 @@ pub struct FieldInfo {
     pub attributes: Arc<RwLock<HashMap<String, String>>>,
     pub dv_gen: i64,
     pub point_dimension_count: u32,
+    /// how many of the leading point dimensions are indexed, the others are only stored
+    pub point_index_dimension_count: u32,
     pub point_num_bytes: u32,
+    /// number of dimensions of the vectors of the field, 0 if it has none
+    pub vector_dimension: u32,
+    pub vector_similarity: VectorSimilarity,
 }
 
 impl Serialize for FieldInfo {
@@ -59,7 +64,7 @@ impl Serialize for FieldInfo {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("FieldInfo", 11)?;
+        let mut s = serializer.serialize_struct("FieldInfo", 14)?;
         s.serialize_field("name", &self.name)?;
         s.serialize_field("number", &self.number)?;
         s.serialize_field("doc_values_type", &self.doc_values_type)?;
@@ -70,7 +75,13 @@ impl Serialize for FieldInfo {
         s.serialize_field("attributes", &*self.attributes.read().unwrap())?;
         s.serialize_field("dv_gen", &self.dv_gen)?;
         s.serialize_field("point_dimension_count", &self.point_dimension_count)?;
+        s.serialize_field(
+            "point_index_dimension_count",
+            &self.point_index_dimension_count,
+        )?;
         s.serialize_field("point_num_bytes", &self.point_num_bytes)?;
+        s.serialize_field("vector_dimension", &self.vector_dimension)?;
+        s.serialize_field("vector_similarity", &self.vector_similarity)?;
         s.end()
     }
 }
@@ -88,6 +99,7 @@ impl FieldInfo {
         dv_gen: i64,
         attributes: HashMap<String, String>,
         point_dimension_count: u32,
+        point_index_dimension_count: u32,
         point_num_bytes: u32,
     ) -> Result<FieldInfo> {
         let info = FieldInfo {
@@ -101,7 +113,10 @@ impl FieldInfo {
             attributes: Arc::new(RwLock::new(attributes)),
             dv_gen,
             point_dimension_count,
+            point_index_dimension_count,
             point_num_bytes,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         };
 
         info.check_consistency()?;
@@ -158,6 +173,22 @@ impl FieldInfo {
             )));
         }
 
+        if self.point_index_dimension_count > self.point_dimension_count
+            || (self.point_index_dimension_count == 0) != (self.point_dimension_count == 0)
+        {
+            return Err(IllegalState(format!(
+                "Illegal State: pointIndexDimensionCount must be in 1..={} for field '{}', got {}",
+                self.point_dimension_count, self.name, self.point_index_dimension_count
+            )));
+        }
+
+        if self.vector_dimension > MAX_VECTOR_DIMENSION {
+            return Err(IllegalState(format!(
+                "Illegal State: vector dimension must be <= {} for field '{}', got {}",
+                MAX_VECTOR_DIMENSION, self.name, self.vector_dimension
+            )));
+        }
+
         if self.dv_gen != -1
             && match self.doc_values_type {
                 DocValuesType::Null => true,
@@ -219,16 +250,51 @@ impl FieldInfo {
         }
     }
 
-    pub fn set_dimensions(&mut self, dimension_count: u32, dimension_num_bytes: u32) -> Result<()> {
+    pub fn set_dimensions(
+        &mut self,
+        dimension_count: u32,
+        index_dimension_count: u32,
+        dimension_num_bytes: u32,
+    ) -> Result<()> {
         if self.point_dimension_count == 0 && dimension_count > 0 {
             self.point_dimension_count = dimension_count;
+            self.point_index_dimension_count = index_dimension_count;
             self.point_num_bytes = dimension_num_bytes;
         } else if dimension_count != 0
             && (self.point_dimension_count != dimension_count
+                || self.point_index_dimension_count != index_dimension_count
                 || self.point_num_bytes != dimension_num_bytes)
         {
             return Err(IllegalArgument(format!(
-                "cannot change field '{}' dimension count or dimension_num_bytes",
+                "cannot change field '{}' dimension count, index dimension count or \
+                 dimension_num_bytes",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Records the vectors of this field, fails if it already has vectors of
+    /// another dimension or similarity.
+    pub fn set_vector_dimension(
+        &mut self,
+        dimension: u32,
+        similarity: VectorSimilarity,
+    ) -> Result<()> {
+        if dimension > MAX_VECTOR_DIMENSION {
+            return Err(IllegalArgument(format!(
+                "vector dimension must be <= {}, got {}",
+                MAX_VECTOR_DIMENSION, dimension
+            )));
+        }
+        if self.vector_dimension == 0 {
+            self.vector_dimension = dimension;
+            self.vector_similarity = similarity;
+        } else if dimension != 0
+            && (self.vector_dimension != dimension || self.vector_similarity != similarity)
+        {
+            return Err(IllegalArgument(format!(
+                "cannot change field '{}' vector dimension or similarity",
                 self.name
             )));
         }
@@ -242,6 +308,7 @@ impl FieldInfo {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn update(
         &mut self,
         store_term_vector: bool,
@@ -249,6 +316,7 @@ impl FieldInfo {
         store_payloads: bool,
         index_options: IndexOptions,
         dimension_count: u32,
+        index_dimension_count: u32,
         dimension_num_bytes: u32,
     ) -> Result<()> {
         if self.index_options != index_options {
@@ -262,7 +330,7 @@ impl FieldInfo {
             }
         }
 
-        self.set_dimensions(dimension_count, dimension_num_bytes)?;
+        self.set_dimensions(dimension_count, index_dimension_count, dimension_num_bytes)?;
 
         // if updated field data is not for indexing, leave the updates out
         // once vector, always vector
@@ -381,6 +449,7 @@ pub struct FieldInfos {
     pub has_norms: bool,
     pub has_doc_values: bool,
     pub has_point_values: bool,
+    pub has_vector_values: bool,
 
     pub by_number: BTreeMap<u32, Arc<FieldInfo>>,
     pub by_name: HashMap<String, Arc<FieldInfo>>,
@@ -391,7 +460,7 @@ impl Serialize for FieldInfos {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("FieldInfos", 9)?;
+        let mut s = serializer.serialize_struct("FieldInfos", 10)?;
         s.serialize_field("has_freq", &self.has_freq)?;
         s.serialize_field("has_prox", &self.has_prox)?;
         s.serialize_field("has_payloads", &self.has_payloads)?;
@@ -400,6 +469,7 @@ impl Serialize for FieldInfos {
         s.serialize_field("has_norms", &self.has_norms)?;
         s.serialize_field("has_doc_values", &self.has_doc_values)?;
         s.serialize_field("has_point_values", &self.has_point_values)?;
+        s.serialize_field("has_vector_values", &self.has_vector_values)?;
 
         let fields: HashMap<&String, &FieldInfo> = self
             .by_name
@@ -431,23 +501,12 @@ impl FieldInfos {
         let mut has_norms = false;
         let mut has_doc_values = false;
         let mut has_point_values = false;
+        let mut has_vector_values = false;
 
         let mut by_number: BTreeMap<u32, Arc<FieldInfo>> = BTreeMap::new();
         let mut by_name: HashMap<String, Arc<FieldInfo>> = HashMap::new();
         let mut max_number = 0;
 
-        let mut infos = infos;
-        for info in &mut infos {
-            if info.index_options != IndexOptions::Null {
-                // force put attr...
-                info.put_attribute(
-                    PER_FIELD_POSTING_FORMAT_KEY.to_string(),
-                    "Lucene50".to_string(),
-                );
-                info.put_attribute(PER_FIELD_POSTING_SUFFIX_KEY.to_string(), "0".to_string());
-            }
-        }
-
         for info in infos {
             let info = Arc::new(info);
             let number = info.number;
@@ -464,6 +523,7 @@ impl FieldInfos {
                 has_doc_values |= !info.doc_values_type.null();
                 has_payloads |= info.has_store_payloads;
                 has_point_values |= info.point_dimension_count != 0;
+                has_vector_values |= info.vector_dimension != 0;
             }
 
             if let Some(previous) = by_number.insert(number, info.clone()) {
@@ -491,6 +551,7 @@ impl FieldInfos {
             has_norms,
             has_doc_values,
             has_point_values,
+            has_vector_values,
             by_number,
             by_name,
         })
@@ -568,6 +629,7 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
                 HashMap::new(),
                 0,
                 0,
+                0,
             )?;
             self.global_field_numbers.as_ref().verify_consistent(
                 field_number,
@@ -589,8 +651,22 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
             fi.index_options,
             fi.doc_values_type,
             fi.point_dimension_count,
+            fi.point_index_dimension_count,
             fi.point_num_bytes,
-        )
+        )?;
+        if fi.vector_dimension > 0 {
+            self.global_field_numbers.as_ref().set_vector_dimension(
+                fi.number,
+                &fi.name,
+                fi.vector_dimension,
+                fi.vector_similarity,
+            )?;
+            self.by_name
+                .get_mut(&fi.name)
+                .unwrap()
+                .set_vector_dimension(fi.vector_dimension, fi.vector_similarity)?;
+        }
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -604,6 +680,7 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
         index_options: IndexOptions,
         doc_values: DocValuesType,
         dimension_count: u32,
+        index_dimension_count: u32,
         dimension_num_bytes: u32,
     ) -> Result<()> {
         if self.by_name.contains_key(name) {
@@ -614,6 +691,7 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
                 store_payloads,
                 index_options,
                 dimension_count,
+                index_dimension_count,
                 dimension_num_bytes,
             )?;
             if doc_values != DocValuesType::Null {
@@ -655,6 +733,7 @@ impl<T: AsRef<FieldNumbers>> FieldInfosBuilder<T> {
                 -1,
                 HashMap::new(),
                 dimension_count,
+                index_dimension_count,
                 dimension_num_bytes,
             )?;
             debug_assert!(!self.by_name.contains_key(name));
@@ -730,6 +809,20 @@ impl FieldNumbers {
             .set_dimensions(number, name, dimension_count, num_bytes)
     }
 
+    /// Records the vectors of the field, fails if the field already had
+    /// vectors of another dimension or similarity.
+    pub fn set_vector_dimension(
+        &self,
+        number: u32,
+        name: &str,
+        dimension: u32,
+        similarity: VectorSimilarity,
+    ) -> Result<()> {
+        self.inner
+            .lock()?
+            .set_vector_dimension(number, name, dimension, similarity)
+    }
+
     fn verify_consistent(&self, number: u32, name: &str, dv_type: DocValuesType) -> Result<()> {
         self.inner.lock()?.verify_consistent(number, name, dv_type)
     }
@@ -758,6 +851,7 @@ struct FieldNumbersInner {
     // sessions:
     doc_values_type: HashMap<String, DocValuesType>,
     dimensions: HashMap<String, FieldDimensions>,
+    vector_dimensions: HashMap<String, (u32, VectorSimilarity)>,
     // TODO: we should similarly catch an attempt to turn
     // norms back on after they were already ommitted; today
     // we silently discard the norm but this is badly trappy
@@ -771,6 +865,7 @@ impl FieldNumbersInner {
             name_to_number: HashMap::new(),
             doc_values_type: HashMap::new(),
             dimensions: HashMap::new(),
+            vector_dimensions: HashMap::new(),
             lowest_unassigned_field_number: 0,
         }
     }
@@ -938,6 +1033,7 @@ impl FieldNumbersInner {
         self.name_to_number.clear();
         self.doc_values_type.clear();
         self.dimensions.clear();
+        self.vector_dimensions.clear();
     }
 
     pub fn set_doc_values_type(
@@ -983,6 +1079,33 @@ impl FieldNumbersInner {
         );
         Ok(())
     }
+
+    pub fn set_vector_dimension(
+        &mut self,
+        number: u32,
+        name: &str,
+        dimension: u32,
+        similarity: VectorSimilarity,
+    ) -> Result<()> {
+        self.verify_consistent(number, name, DocValuesType::Null)?;
+        match self.vector_dimensions.entry(name.to_string()) {
+            Entry::Occupied(entry) => {
+                if *entry.get() != (dimension, similarity) {
+                    return Err(IllegalArgument(format!(
+                        "cannot change vector dimension or similarity from {:?} to {:?} for \
+                         field '{}'",
+                        entry.get(),
+                        (dimension, similarity),
+                        name
+                    )));
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((dimension, similarity));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl AsRef<FieldNumbers> for FieldNumbers {
@@ -24,7 +24,7 @@ use crate::core::store::IOContext;
 use crate::core::util::to_base36;
 use crate::core::util::{bits2words, BitSet, FixedBitSet, ImmutableBitSet};
 use crate::core::util::{Bits, BitsRef};
-use crate::error::Error::CorruptIndex;
+use crate::error::Error::{CorruptIndex, IllegalArgument};
 use crate::Result;
 
 /// Format for live/deleted documents
@@ -79,7 +79,9 @@ impl LiveDocsFormat for Lucene50LiveDocsFormat {
 
     fn new_live_docs_from_existing(&self, existing: &dyn Bits) -> Result<BitsRef> {
         // existing is type of FixedBitSet
-        Ok(existing.clone_box())
+        existing
+            .clone_box()
+            .ok_or_else(|| IllegalArgument("live docs must be a FixedBitSet".into()))
     }
 
     fn read_live_docs<D: Directory, C: Codec>(
@@ -132,7 +134,10 @@ impl LiveDocsFormat for Lucene50LiveDocsFormat {
     ) -> Result<()> {
         let gen = info.next_write_del_gen();
         let name = file_name_from_generation(&info.info.name, EXTENSION, gen as u64);
-        let fbs = bits.as_bit_set().as_fixed_bit_set();
+        let fbs = bits
+            .as_bit_set()
+            .ok_or_else(|| IllegalArgument("live docs must be a FixedBitSet".into()))?
+            .as_fixed_bit_set();
 
         if fbs.len() - fbs.cardinality() != (info.del_count() + new_del_count) as usize {
             return Err(CorruptIndex(format!(
@@ -0,0 +1,297 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::search::NO_MORE_DOCS;
+use crate::core::store::io::RandomAccessInput;
+use crate::core::util::{BitsMut, DocId};
+use crate::error::Error::CorruptIndex;
+use crate::Result;
+
+use std::sync::Arc;
+
+/// Sparse blocks hold at most this many docs as a list of shorts.
+const MAX_ARRAY_LENGTH: u32 = (1 << 12) - 1;
+/// Number of docs in a block, a block holding all of them has no data.
+const BLOCK_SIZE: u32 = 1 << 16;
+const DENSE_BLOCK_LONGS: u64 = 1024;
+/// The block that `NO_MORE_DOCS` falls in, it ends the set.
+const LAST_BLOCK: usize = (NO_MORE_DOCS >> 16) as usize;
+
+#[derive(Copy, Clone, Debug)]
+struct DisiBlock {
+    /// number of docs in the previous blocks
+    index: i64,
+    /// start of the block data, after the block header
+    offset: u64,
+    cardinality: u32,
+}
+
+/// Random access to a doc id set written by Java Lucene's `IndexedDISI`, that
+/// also gives the index of each doc in the set.
+///
+/// The docs are split in blocks of 65536, each starting with its id and
+/// cardinality. Blocks with at most 4095 docs list them as shorts, full blocks
+/// have no data and the others are a bitmap, optionally preceded by a table of
+/// the number of docs before every `2^dense_rank_power` docs of the block.
+/// The block headers are read up front, the jump table at the end of the set
+/// is not needed for that and is ignored.
+pub struct IndexedDISI {
+    slice: Arc<dyn RandomAccessInput>,
+    blocks: Vec<Option<DisiBlock>>,
+    dense_rank_power: i32,
+}
+
+impl IndexedDISI {
+    pub fn new(
+        slice: Arc<dyn RandomAccessInput>,
+        dense_rank_power: i8,
+        max_doc: DocId,
+    ) -> Result<IndexedDISI> {
+        let dense_rank_power = i32::from(dense_rank_power);
+        if dense_rank_power != -1 && !(7..=15).contains(&dense_rank_power) {
+            return Err(CorruptIndex(format!(
+                "invalid dense rank power: {}",
+                dense_rank_power
+            )));
+        }
+        let rank_length = if dense_rank_power == -1 {
+            0
+        } else {
+            DENSE_BLOCK_LONGS >> (dense_rank_power - 7)
+        };
+
+        let mut blocks = vec![None; (max_doc as usize >> 16) + 1];
+        let mut offset = 0u64;
+        let mut index = 0i64;
+        loop {
+            let block = slice.read_short(offset)? as u16 as usize;
+            let cardinality = u32::from(slice.read_short(offset + 2)? as u16) + 1;
+            offset += 4;
+            if block < blocks.len() {
+                blocks[block] = Some(DisiBlock {
+                    index,
+                    offset,
+                    cardinality,
+                });
+            } else if block != LAST_BLOCK {
+                return Err(CorruptIndex(format!(
+                    "doc id set block {} is out of bounds for max_doc {}",
+                    block, max_doc
+                )));
+            }
+            if block == LAST_BLOCK {
+                break;
+            }
+            index += i64::from(cardinality);
+            offset += if cardinality <= MAX_ARRAY_LENGTH {
+                u64::from(cardinality) * 2
+            } else if cardinality == BLOCK_SIZE {
+                0
+            } else {
+                rank_length + DENSE_BLOCK_LONGS * 8
+            };
+        }
+
+        Ok(IndexedDISI {
+            slice,
+            blocks,
+            dense_rank_power,
+        })
+    }
+
+    /// Returns the index of `doc` in the set, or `None` if it isn't in it.
+    pub fn index_of(&self, doc: DocId) -> Result<Option<i64>> {
+        let block = match self.blocks.get((doc >> 16) as usize) {
+            Some(Some(block)) => block,
+            _ => return Ok(None),
+        };
+        let target = (doc & 0xFFFF) as u32;
+        if block.cardinality <= MAX_ARRAY_LENGTH {
+            let (mut low, mut high) = (0u32, block.cardinality);
+            while low < high {
+                let mid = (low + high) >> 1;
+                let value =
+                    u32::from(self.slice.read_short(block.offset + u64::from(mid) * 2)? as u16);
+                if value < target {
+                    low = mid + 1;
+                } else if value > target {
+                    high = mid;
+                } else {
+                    return Ok(Some(block.index + i64::from(mid)));
+                }
+            }
+            Ok(None)
+        } else if block.cardinality == BLOCK_SIZE {
+            Ok(Some(block.index + i64::from(target)))
+        } else {
+            let (mut rank, first_word, bits_offset) = if self.dense_rank_power == -1 {
+                (0, 0, block.offset)
+            } else {
+                let rank_index = u64::from(target >> self.dense_rank_power);
+                let rank = self.slice.read_short(block.offset + rank_index * 2)? as u16;
+                (
+                    i64::from(rank),
+                    rank_index << (self.dense_rank_power - 6),
+                    block.offset + (DENSE_BLOCK_LONGS >> (self.dense_rank_power - 7)),
+                )
+            };
+            let target_word = u64::from(target >> 6);
+            let word = self.slice.read_long(bits_offset + target_word * 8)? as u64;
+            let bit = 1u64 << (target & 63);
+            if word & bit == 0 {
+                return Ok(None);
+            }
+            for i in first_word..target_word {
+                rank += i64::from(self.slice.read_long(bits_offset + i * 8)?.count_ones());
+            }
+            rank += i64::from((word & (bit - 1)).count_ones());
+            Ok(Some(block.index + rank))
+        }
+    }
+}
+
+/// The docs in an `IndexedDISI` as `BitsMut`.
+pub struct IndexedDISIBits {
+    disi: Arc<IndexedDISI>,
+    max_doc: DocId,
+}
+
+impl IndexedDISIBits {
+    pub fn new(disi: Arc<IndexedDISI>, max_doc: DocId) -> Self {
+        IndexedDISIBits { disi, max_doc }
+    }
+}
+
+impl BitsMut for IndexedDISIBits {
+    fn get(&mut self, index: usize) -> bool {
+        self.disi.index_of(index as DocId).unwrap().is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.max_doc as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct BytesInput(Vec<u8>);
+
+    impl RandomAccessInput for BytesInput {
+        fn read_byte(&self, pos: u64) -> Result<u8> {
+            Ok(self.0[pos as usize])
+        }
+
+        fn read_short(&self, pos: u64) -> Result<i16> {
+            let pos = pos as usize;
+            Ok(i16::from_be_bytes([self.0[pos], self.0[pos + 1]]))
+        }
+
+        fn read_int(&self, pos: u64) -> Result<i32> {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&self.0[pos as usize..pos as usize + 4]);
+            Ok(i32::from_be_bytes(bytes))
+        }
+
+        fn read_long(&self, pos: u64) -> Result<i64> {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&self.0[pos as usize..pos as usize + 8]);
+            Ok(i64::from_be_bytes(bytes))
+        }
+    }
+
+    /// Writes `docs` the way Java Lucene's `IndexedDISI.writeBitSet` does,
+    /// without the jump table.
+    fn write(docs: &[DocId], dense_rank_power: i8) -> Vec<u8> {
+        let mut out = vec![];
+        let mut flush = |block: usize, buffer: &[u64], cardinality: u32| {
+            out.extend_from_slice(&(block as u16).to_be_bytes());
+            out.extend_from_slice(&((cardinality - 1) as u16).to_be_bytes());
+            if cardinality > MAX_ARRAY_LENGTH {
+                if cardinality != BLOCK_SIZE {
+                    if dense_rank_power != -1 {
+                        let longs_per_rank = 1usize << (dense_rank_power - 6);
+                        let mut count = 0u16;
+                        for (i, word) in buffer.iter().enumerate() {
+                            if i % longs_per_rank == 0 {
+                                out.extend_from_slice(&count.to_be_bytes());
+                            }
+                            count += word.count_ones() as u16;
+                        }
+                    }
+                    for word in buffer {
+                        out.extend_from_slice(&word.to_be_bytes());
+                    }
+                }
+            } else {
+                for (i, word) in buffer.iter().enumerate() {
+                    for bit in 0..64 {
+                        if word & (1 << bit) != 0 {
+                            out.extend_from_slice(&((i * 64 + bit) as u16).to_be_bytes());
+                        }
+                    }
+                }
+            }
+        };
+        let mut buffer = vec![0u64; DENSE_BLOCK_LONGS as usize];
+        let mut current = usize::MAX;
+        let mut cardinality = 0;
+        for &doc in docs.iter().chain(&[NO_MORE_DOCS]) {
+            let block = (doc >> 16) as usize;
+            if block != current {
+                if current != usize::MAX {
+                    flush(current, &buffer, cardinality);
+                }
+                buffer.iter_mut().for_each(|w| *w = 0);
+                current = block;
+                cardinality = 0;
+            }
+            let bit = (doc & 0xFFFF) as usize;
+            buffer[bit >> 6] |= 1 << (bit & 63);
+            cardinality += 1;
+        }
+        flush(current, &buffer, cardinality);
+        out
+    }
+
+    fn check(docs: &[DocId], max_doc: DocId, dense_rank_power: i8) -> Result<()> {
+        let slice = Arc::new(BytesInput(write(docs, dense_rank_power)));
+        let disi = IndexedDISI::new(slice, dense_rank_power, max_doc)?;
+        let mut expected = docs.iter().enumerate().peekable();
+        for doc in 0..max_doc {
+            let index = match expected.peek() {
+                Some((i, &d)) if d == doc => {
+                    let index = *i as i64;
+                    expected.next();
+                    Some(index)
+                }
+                _ => None,
+            };
+            assert_eq!(disi.index_of(doc)?, index, "doc {}", doc);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_of_in_all_block_kinds() -> Result<()> {
+        let max_doc = 4 << 16;
+        // a sparse block, a dense block, a full block and an empty block
+        let mut docs: Vec<DocId> = (0..100).map(|i| i * 13).collect();
+        docs.extend((0..20_000).map(|i| (1 << 16) + i * 3));
+        docs.extend((2 << 16)..(3 << 16));
+        check(&docs, max_doc, 9)?;
+        check(&docs, max_doc, 15)?;
+        check(&docs, max_doc, -1)
+    }
+}
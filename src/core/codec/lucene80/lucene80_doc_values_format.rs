@@ -0,0 +1,964 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::lucene54::{
+    DocValuesTermIterator, SortedDocValuesTermIterator, SortedSetDocValuesTermIterator,
+};
+use crate::core::codec::doc_values::{
+    BinaryDocValues, BinaryDocValuesProvider, DocValuesConsumerEnum, DocValuesFormat,
+    DocValuesProducer, NumericDocValues, NumericDocValuesProvider, SortedDocValues,
+    SortedDocValuesProvider, SortedNumericDocValues, SortedNumericDocValuesProvider,
+    SortedSetDocValues, SortedSetDocValuesProvider, NO_MORE_ORDS,
+};
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::lucene80::{IndexedDISI, IndexedDISIBits};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::{codec_util, Codec};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{IndexInput, RandomAccessInput};
+use crate::core::util::packed::{
+    DirectMonotonicMeta, DirectMonotonicReader, DirectPackedReader, DirectReader,
+    MixinMonotonicLongValues,
+};
+use crate::core::util::{Accountable, BitsMut, DocId, LongValues, MatchAllBits, MatchNoBits};
+use crate::error::Error::{CorruptIndex, IllegalArgument, UnsupportedOperation};
+use crate::Result;
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::mem;
+use std::sync::Arc;
+
+const DATA_CODEC: &str = "Lucene80DocValuesData";
+const DATA_EXTENSION: &str = "dvd";
+const META_CODEC: &str = "Lucene80DocValuesMetadata";
+const META_EXTENSION: &str = "dvm";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+const NUMERIC: u8 = 0;
+const BINARY: u8 = 1;
+const SORTED: u8 = 2;
+const SORTED_SET: u8 = 3;
+const SORTED_NUMERIC: u8 = 4;
+
+/// The doc values format of Java Lucene 8.0 to 8.4, only reading is supported.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Lucene80DocValuesFormat;
+
+impl DocValuesFormat for Lucene80DocValuesFormat {
+    fn name(&self) -> &str {
+        "Lucene80"
+    }
+
+    fn fields_producer<'a, D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'a, D, DW, C>,
+    ) -> Result<Box<dyn DocValuesProducer>> {
+        Ok(Box::new(Lucene80DocValuesProducer::new(state)?))
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        _state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<DocValuesConsumerEnum<D, DW, C>> {
+        Err(UnsupportedOperation(
+            "Lucene80DocValuesFormat is read only".into(),
+        ))
+    }
+}
+
+#[derive(Copy, Clone)]
+struct DocsWithFieldEntry {
+    /// -2 if no doc has a value, -1 if all docs have one
+    offset: i64,
+    length: i64,
+    dense_rank_power: i8,
+}
+
+impl DocsWithFieldEntry {
+    fn read(meta: &mut dyn IndexInput) -> Result<DocsWithFieldEntry> {
+        let offset = meta.read_long()?;
+        let length = meta.read_long()?;
+        // the jump table entry count, we don't use the jump table
+        meta.read_short()?;
+        let dense_rank_power = meta.read_byte()? as i8;
+        Ok(DocsWithFieldEntry {
+            offset,
+            length,
+            dense_rank_power,
+        })
+    }
+}
+
+struct AddressesEntry {
+    offset: i64,
+    length: i64,
+    meta: DirectMonotonicMeta,
+}
+
+struct NumericEntry {
+    docs_with_field: DocsWithFieldEntry,
+    num_values: i64,
+    table: Option<Arc<Vec<i64>>>,
+    /// values are split in blocks of `1 << block_shift` with their own
+    /// bits per value, -1 if they aren't
+    block_shift: i32,
+    bits_per_value: u8,
+    min_value: i64,
+    gcd: i64,
+    values_offset: i64,
+    values_length: i64,
+}
+
+struct BinaryEntry {
+    docs_with_field: DocsWithFieldEntry,
+    data_offset: i64,
+    data_length: i64,
+    min_length: i32,
+    /// only set when the values don't all have the same length
+    addresses: Option<AddressesEntry>,
+}
+
+struct TermsDictEntry {
+    size: i64,
+    block_shift: i32,
+    addresses: AddressesEntry,
+    data_offset: i64,
+    data_length: i64,
+}
+
+struct SortedEntry {
+    docs_with_field: DocsWithFieldEntry,
+    bits_per_value: u8,
+    ords_offset: i64,
+    ords_length: i64,
+    /// only set for sorted set fields where some docs have several values
+    addresses: Option<AddressesEntry>,
+    terms_dict: TermsDictEntry,
+}
+
+struct SortedNumericEntry {
+    numeric: NumericEntry,
+    /// only set when some docs have several values
+    addresses: Option<AddressesEntry>,
+}
+
+enum DocValuesEntry {
+    Numeric(NumericEntry),
+    Binary(BinaryEntry),
+    Sorted(SortedEntry),
+    SortedSet(SortedEntry),
+    SortedNumeric(SortedNumericEntry),
+}
+
+impl DocValuesEntry {
+    fn docs_with_field(&self) -> &DocsWithFieldEntry {
+        match self {
+            DocValuesEntry::Numeric(e) => &e.docs_with_field,
+            DocValuesEntry::Binary(e) => &e.docs_with_field,
+            DocValuesEntry::Sorted(e) | DocValuesEntry::SortedSet(e) => &e.docs_with_field,
+            DocValuesEntry::SortedNumeric(e) => &e.numeric.docs_with_field,
+        }
+    }
+}
+
+fn read_fields(
+    meta: &mut dyn IndexInput,
+    infos: &FieldInfos,
+) -> Result<HashMap<String, DocValuesEntry>> {
+    let mut fields = HashMap::new();
+    loop {
+        let field_number = meta.read_int()?;
+        if field_number == -1 {
+            break;
+        }
+        let info = infos
+            .field_info_by_number(field_number as u32)
+            .ok_or_else(|| CorruptIndex(format!("invalid field number: {}", field_number)))?;
+        let entry = match meta.read_byte()? {
+            NUMERIC => DocValuesEntry::Numeric(read_numeric(meta)?),
+            BINARY => DocValuesEntry::Binary(read_binary(meta)?),
+            SORTED => DocValuesEntry::Sorted(read_sorted(meta)?),
+            SORTED_SET => DocValuesEntry::SortedSet(read_sorted_set(meta)?),
+            SORTED_NUMERIC => DocValuesEntry::SortedNumeric(read_sorted_numeric(meta)?),
+            t => {
+                return Err(CorruptIndex(format!(
+                    "invalid doc values type {} for field '{}'",
+                    t, info.name
+                )));
+            }
+        };
+        fields.insert(info.name.clone(), entry);
+    }
+    Ok(fields)
+}
+
+fn read_numeric(meta: &mut dyn IndexInput) -> Result<NumericEntry> {
+    let docs_with_field = DocsWithFieldEntry::read(meta)?;
+    let num_values = meta.read_long()?;
+    let table_size = meta.read_int()?;
+    if table_size > 256 {
+        return Err(CorruptIndex(format!("invalid table size: {}", table_size)));
+    }
+    let table = if table_size >= 0 {
+        let mut table = Vec::with_capacity(table_size as usize);
+        for _ in 0..table_size {
+            table.push(meta.read_long()?);
+        }
+        Some(Arc::new(table))
+    } else {
+        None
+    };
+    let block_shift = if table_size < -1 { -2 - table_size } else { -1 };
+    let bits_per_value = meta.read_byte()?;
+    let min_value = meta.read_long()?;
+    let gcd = meta.read_long()?;
+    let values_offset = meta.read_long()?;
+    let values_length = meta.read_long()?;
+    // the offset of the jump table of the blocks, we scan them instead
+    meta.read_long()?;
+    Ok(NumericEntry {
+        docs_with_field,
+        num_values,
+        table,
+        block_shift,
+        bits_per_value,
+        min_value,
+        gcd,
+        values_offset,
+        values_length,
+    })
+}
+
+fn read_addresses(meta: &mut dyn IndexInput, num_values: i64) -> Result<AddressesEntry> {
+    let offset = meta.read_long()?;
+    let block_shift = meta.read_vint()?;
+    let addresses_meta = DirectMonotonicReader::load_meta(meta, num_values, block_shift)?;
+    let length = meta.read_long()?;
+    Ok(AddressesEntry {
+        offset,
+        length,
+        meta: addresses_meta,
+    })
+}
+
+fn read_binary(meta: &mut dyn IndexInput) -> Result<BinaryEntry> {
+    let data_offset = meta.read_long()?;
+    let data_length = meta.read_long()?;
+    let docs_with_field = DocsWithFieldEntry::read(meta)?;
+    let num_docs_with_field = meta.read_int()?;
+    let min_length = meta.read_int()?;
+    let max_length = meta.read_int()?;
+    let addresses = if min_length < max_length {
+        Some(read_addresses(meta, i64::from(num_docs_with_field) + 1)?)
+    } else {
+        None
+    };
+    Ok(BinaryEntry {
+        docs_with_field,
+        data_offset,
+        data_length,
+        min_length,
+        addresses,
+    })
+}
+
+fn read_sorted(meta: &mut dyn IndexInput) -> Result<SortedEntry> {
+    let docs_with_field = DocsWithFieldEntry::read(meta)?;
+    // the number of docs with a value
+    meta.read_int()?;
+    let bits_per_value = meta.read_byte()?;
+    let ords_offset = meta.read_long()?;
+    let ords_length = meta.read_long()?;
+    let terms_dict = read_terms_dict(meta)?;
+    Ok(SortedEntry {
+        docs_with_field,
+        bits_per_value,
+        ords_offset,
+        ords_length,
+        addresses: None,
+        terms_dict,
+    })
+}
+
+fn read_sorted_set(meta: &mut dyn IndexInput) -> Result<SortedEntry> {
+    match meta.read_byte()? {
+        0 => return read_sorted(meta),
+        1 => {}
+        v => return Err(CorruptIndex(format!("invalid multi valued flag: {}", v))),
+    }
+    let docs_with_field = DocsWithFieldEntry::read(meta)?;
+    let bits_per_value = meta.read_byte()?;
+    let ords_offset = meta.read_long()?;
+    let ords_length = meta.read_long()?;
+    let num_docs_with_field = meta.read_int()?;
+    let addresses = read_addresses(meta, i64::from(num_docs_with_field) + 1)?;
+    let terms_dict = read_terms_dict(meta)?;
+    Ok(SortedEntry {
+        docs_with_field,
+        bits_per_value,
+        ords_offset,
+        ords_length,
+        addresses: Some(addresses),
+        terms_dict,
+    })
+}
+
+fn read_terms_dict(meta: &mut dyn IndexInput) -> Result<TermsDictEntry> {
+    let size = meta.read_vlong()?;
+    let block_shift = meta.read_int()?;
+    let addresses_block_shift = meta.read_int()?;
+    let num_blocks = (size + (1 << block_shift) - 1) >> block_shift;
+    let addresses_meta = DirectMonotonicReader::load_meta(meta, num_blocks, addresses_block_shift)?;
+    // the max term length
+    meta.read_int()?;
+    let data_offset = meta.read_long()?;
+    let data_length = meta.read_long()?;
+    let addresses_offset = meta.read_long()?;
+    let addresses_length = meta.read_long()?;
+    // the terms index is only used to seek by term, we binary search the
+    // blocks instead, skip its metadata
+    let index_shift = meta.read_int()?;
+    let index_size = (size + (1 << index_shift) - 1) >> index_shift;
+    DirectMonotonicReader::load_meta(meta, 1 + index_size, addresses_block_shift)?;
+    for _ in 0..4 {
+        meta.read_long()?;
+    }
+    Ok(TermsDictEntry {
+        size,
+        block_shift,
+        addresses: AddressesEntry {
+            offset: addresses_offset,
+            length: addresses_length,
+            meta: addresses_meta,
+        },
+        data_offset,
+        data_length,
+    })
+}
+
+fn read_sorted_numeric(meta: &mut dyn IndexInput) -> Result<SortedNumericEntry> {
+    let numeric = read_numeric(meta)?;
+    let num_docs_with_field = meta.read_int()?;
+    let addresses = if i64::from(num_docs_with_field) != numeric.num_values {
+        Some(read_addresses(meta, i64::from(num_docs_with_field) + 1)?)
+    } else {
+        None
+    };
+    Ok(SortedNumericEntry { numeric, addresses })
+}
+
+/// `DocValuesProducer` for `Lucene80DocValuesFormat`
+pub struct Lucene80DocValuesProducer {
+    max_doc: DocId,
+    data: Box<dyn IndexInput>,
+    fields: Arc<HashMap<String, DocValuesEntry>>,
+}
+
+impl Lucene80DocValuesProducer {
+    fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Lucene80DocValuesProducer> {
+        let meta_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            META_EXTENSION,
+        );
+        let mut meta = state
+            .directory
+            .open_checksum_input(&meta_name, state.context)?;
+        let version = codec_util::check_index_header(
+            &mut meta,
+            META_CODEC,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        let fields = read_fields(&mut meta, &state.field_infos)?;
+        codec_util::check_footer(&mut meta)?;
+
+        let data_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            DATA_EXTENSION,
+        );
+        let mut data = state.directory.open_input(&data_name, state.context)?;
+        let data_version = codec_util::check_index_header(
+            data.as_mut(),
+            DATA_CODEC,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        if version != data_version {
+            return Err(CorruptIndex(format!(
+                "Format versions mismatch: meta={}, data={}",
+                version, data_version
+            )));
+        }
+        codec_util::retrieve_checksum(data.as_mut())?;
+
+        Ok(Lucene80DocValuesProducer {
+            max_doc: state.segment_info.max_doc(),
+            data,
+            fields: Arc::new(fields),
+        })
+    }
+
+    fn entry(&self, field_info: &FieldInfo) -> Result<&DocValuesEntry> {
+        self.fields.get(&field_info.name).ok_or_else(|| {
+            IllegalArgument(format!("no doc values for field '{}'", field_info.name))
+        })
+    }
+
+    fn wrong_type(field_info: &FieldInfo) -> crate::error::Error {
+        IllegalArgument(format!(
+            "field '{}' has doc values of another type",
+            field_info.name
+        ))
+    }
+
+    fn random_access_slice(&self, offset: i64, length: i64) -> Result<Arc<dyn RandomAccessInput>> {
+        Ok(Arc::from(self.data.random_access_slice(offset, length)?))
+    }
+
+    fn docs_with_field(&self, entry: &DocsWithFieldEntry) -> Result<DocsWithField> {
+        Ok(match entry.offset {
+            -2 => DocsWithField::Empty,
+            -1 => DocsWithField::Dense,
+            offset => DocsWithField::Sparse(Arc::new(IndexedDISI::new(
+                self.random_access_slice(offset, entry.length)?,
+                entry.dense_rank_power,
+                self.max_doc,
+            )?)),
+        })
+    }
+
+    fn numeric_values(&self, entry: &NumericEntry) -> Result<NumericValues> {
+        if entry.bits_per_value == 0 {
+            return Ok(NumericValues::Constant(entry.min_value));
+        }
+        let slice = self.random_access_slice(entry.values_offset, entry.values_length)?;
+        if entry.block_shift >= 0 {
+            let num_blocks = (entry.num_values + (1 << entry.block_shift) - 1) >> entry.block_shift;
+            let mut blocks = Vec::with_capacity(num_blocks as usize);
+            let mut offset = 0u64;
+            for _ in 0..num_blocks {
+                let bits_per_value = slice.read_byte(offset)?;
+                let delta = slice.read_long(offset + 1)?;
+                offset += 9;
+                let values = if bits_per_value == 0 {
+                    None
+                } else {
+                    let length = slice.read_int(offset)?;
+                    offset += 4;
+                    let values = DirectReader::get_instance(
+                        Arc::clone(&slice),
+                        i32::from(bits_per_value),
+                        offset as i64,
+                    )?;
+                    offset += length as u64;
+                    Some(values)
+                };
+                blocks.push((values, delta));
+            }
+            return Ok(NumericValues::Blocks {
+                blocks: Arc::new(blocks),
+                shift: entry.block_shift,
+                gcd: entry.gcd,
+            });
+        }
+        let values = DirectReader::get_instance(slice, i32::from(entry.bits_per_value), 0)?;
+        Ok(match &entry.table {
+            Some(table) => NumericValues::Table(values, Arc::clone(table)),
+            None => NumericValues::Delta {
+                values,
+                gcd: entry.gcd,
+                min: entry.min_value,
+            },
+        })
+    }
+
+    fn addresses(&self, entry: &AddressesEntry) -> Result<MixinMonotonicLongValues> {
+        let slice = self.random_access_slice(entry.offset, entry.length)?;
+        DirectMonotonicReader::get_instance(&entry.meta, &slice)
+    }
+
+    fn ords(&self, entry: &SortedEntry) -> Result<Option<DirectPackedReader>> {
+        if entry.bits_per_value == 0 {
+            return Ok(None);
+        }
+        let slice = self.random_access_slice(entry.ords_offset, entry.ords_length)?;
+        Ok(Some(DirectReader::get_instance(
+            slice,
+            i32::from(entry.bits_per_value),
+            0,
+        )?))
+    }
+
+    fn terms_dict(&self, entry: &TermsDictEntry) -> Result<TermsDict> {
+        Ok(TermsDict {
+            size: entry.size,
+            block_shift: entry.block_shift,
+            addresses: self.addresses(&entry.addresses)?,
+            data: self
+                .data
+                .slice("terms", entry.data_offset, entry.data_length)?,
+        })
+    }
+
+    fn sorted_set(&self, entry: &SortedEntry) -> Result<Lucene80SortedSetDocValues> {
+        let addresses = match &entry.addresses {
+            Some(addresses) => Some(self.addresses(addresses)?),
+            None => None,
+        };
+        Ok(Lucene80SortedSetDocValues {
+            docs_with_field: self.docs_with_field(&entry.docs_with_field)?,
+            ords: self.ords(entry)?,
+            addresses,
+            terms_dict: self.terms_dict(&entry.terms_dict)?,
+            upto: 0,
+            end: 0,
+        })
+    }
+}
+
+impl Accountable for Lucene80DocValuesProducer {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self
+                .fields
+                .keys()
+                .map(|k| k.capacity() + mem::size_of::<(String, DocValuesEntry)>())
+                .sum::<usize>()
+    }
+}
+
+impl DocValuesProducer for Lucene80DocValuesProducer {
+    fn get_numeric(&self, field_info: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
+        match self.entry(field_info)? {
+            DocValuesEntry::Numeric(entry) => Ok(Arc::new(Lucene80NumericDocValues {
+                docs_with_field: self.docs_with_field(&entry.docs_with_field)?,
+                values: self.numeric_values(entry)?,
+            })),
+            _ => Err(Self::wrong_type(field_info)),
+        }
+    }
+
+    fn get_binary(&self, field_info: &FieldInfo) -> Result<Arc<dyn BinaryDocValuesProvider>> {
+        match self.entry(field_info)? {
+            DocValuesEntry::Binary(entry) => {
+                let lengths = match &entry.addresses {
+                    Some(addresses) => BinaryLengths::Variable(self.addresses(addresses)?),
+                    None => BinaryLengths::Fixed(entry.min_length),
+                };
+                Ok(Arc::new(Lucene80BinaryDocValues {
+                    docs_with_field: self.docs_with_field(&entry.docs_with_field)?,
+                    data: self
+                        .data
+                        .slice("binary", entry.data_offset, entry.data_length)?,
+                    lengths,
+                }))
+            }
+            _ => Err(Self::wrong_type(field_info)),
+        }
+    }
+
+    fn get_sorted(&self, field: &FieldInfo) -> Result<Arc<dyn SortedDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::Sorted(entry) => Ok(Arc::new(Lucene80SortedDocValues {
+                docs_with_field: self.docs_with_field(&entry.docs_with_field)?,
+                ords: self.ords(entry)?,
+                terms_dict: self.terms_dict(&entry.terms_dict)?,
+            })),
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_sorted_numeric(
+        &self,
+        field: &FieldInfo,
+    ) -> Result<Arc<dyn SortedNumericDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::SortedNumeric(entry) => {
+                let addresses = match &entry.addresses {
+                    Some(addresses) => Some(self.addresses(addresses)?),
+                    None => None,
+                };
+                Ok(Arc::new(Lucene80SortedNumericDocValues {
+                    docs_with_field: self.docs_with_field(&entry.numeric.docs_with_field)?,
+                    values: self.numeric_values(&entry.numeric)?,
+                    addresses,
+                    start: 0,
+                    end: 0,
+                }))
+            }
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_sorted_set(&self, field: &FieldInfo) -> Result<Arc<dyn SortedSetDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::SortedSet(entry) => Ok(Arc::new(self.sorted_set(entry)?)),
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_docs_with_field(&self, field: &FieldInfo) -> Result<Box<dyn BitsMut>> {
+        let entry = self.entry(field)?.docs_with_field();
+        let len = self.max_doc as usize;
+        Ok(match self.docs_with_field(entry)? {
+            DocsWithField::Empty => Box::new(MatchNoBits::new(len)),
+            DocsWithField::Dense => Box::new(MatchAllBits::new(len)),
+            DocsWithField::Sparse(disi) => Box::new(IndexedDISIBits::new(disi, self.max_doc)),
+        })
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        codec_util::checksum_entire_file(self.data.as_ref())?;
+        Ok(())
+    }
+
+    fn get_merge_instance(&self) -> Result<Box<dyn DocValuesProducer>> {
+        Ok(Box::new(Lucene80DocValuesProducer {
+            max_doc: self.max_doc,
+            data: self.data.as_ref().clone()?,
+            fields: Arc::clone(&self.fields),
+        }))
+    }
+}
+
+/// Which docs have a value, and the index of their values.
+#[derive(Clone)]
+enum DocsWithField {
+    Empty,
+    Dense,
+    Sparse(Arc<IndexedDISI>),
+}
+
+impl DocsWithField {
+    fn index_of(&self, doc: DocId) -> Result<Option<i64>> {
+        match self {
+            DocsWithField::Empty => Ok(None),
+            DocsWithField::Dense => Ok(Some(i64::from(doc))),
+            DocsWithField::Sparse(disi) => disi.index_of(doc),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum NumericValues {
+    Constant(i64),
+    Table(DirectPackedReader, Arc<Vec<i64>>),
+    Delta {
+        values: DirectPackedReader,
+        gcd: i64,
+        min: i64,
+    },
+    Blocks {
+        blocks: Arc<Vec<(Option<DirectPackedReader>, i64)>>,
+        shift: i32,
+        gcd: i64,
+    },
+}
+
+impl NumericValues {
+    fn get(&self, index: i64) -> Result<i64> {
+        match self {
+            NumericValues::Constant(value) => Ok(*value),
+            NumericValues::Table(values, table) => Ok(table[values.get64(index)? as usize]),
+            NumericValues::Delta { values, gcd, min } => Ok(gcd * values.get64(index)? + min),
+            NumericValues::Blocks { blocks, shift, gcd } => {
+                let (values, delta) = &blocks[(index >> shift) as usize];
+                let value = match values {
+                    Some(values) => values.get64(index & ((1 << shift) - 1))?,
+                    None => 0,
+                };
+                Ok(gcd * value + delta)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Lucene80NumericDocValues {
+    docs_with_field: DocsWithField,
+    values: NumericValues,
+}
+
+impl NumericDocValues for Lucene80NumericDocValues {
+    fn get(&self, doc_id: DocId) -> Result<i64> {
+        match self.docs_with_field.index_of(doc_id)? {
+            Some(index) => self.values.get(index),
+            None => Ok(0),
+        }
+    }
+}
+
+impl NumericDocValuesProvider for Lucene80NumericDocValues {
+    fn get(&self) -> Result<Box<dyn NumericDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+enum BinaryLengths {
+    Fixed(i32),
+    Variable(MixinMonotonicLongValues),
+}
+
+pub struct Lucene80BinaryDocValues {
+    docs_with_field: DocsWithField,
+    data: Box<dyn IndexInput>,
+    lengths: BinaryLengths,
+}
+
+impl BinaryDocValues for Lucene80BinaryDocValues {
+    fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+        let index = match self.docs_with_field.index_of(doc_id)? {
+            Some(index) => index,
+            None => return Ok(vec![]),
+        };
+        let (start, end) = match &self.lengths {
+            BinaryLengths::Fixed(length) => {
+                let length = i64::from(*length);
+                (index * length, (index + 1) * length)
+            }
+            BinaryLengths::Variable(addresses) => {
+                (addresses.get64(index)?, addresses.get64(index + 1)?)
+            }
+        };
+        let mut value = vec![0u8; (end - start) as usize];
+        self.data.seek(start)?;
+        self.data.read_exact(&mut value)?;
+        Ok(value)
+    }
+}
+
+impl BinaryDocValuesProvider for Lucene80BinaryDocValues {
+    fn get(&self) -> Result<Box<dyn BinaryDocValues>> {
+        Ok(Box::new(Lucene80BinaryDocValues {
+            docs_with_field: self.docs_with_field.clone(),
+            data: self.data.as_ref().clone()?,
+            lengths: self.lengths.clone(),
+        }))
+    }
+}
+
+/// The sorted terms of a field, stored in blocks of `1 << block_shift` terms
+/// which are prefix compressed against the previous term.
+struct TermsDict {
+    size: i64,
+    block_shift: i32,
+    addresses: MixinMonotonicLongValues,
+    data: Box<dyn IndexInput>,
+}
+
+impl TermsDict {
+    fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+        if ord < 0 || ord >= self.size {
+            return Err(IllegalArgument(format!(
+                "ord {} is out of bounds for {} terms",
+                ord, self.size
+            )));
+        }
+        let block = ord >> self.block_shift;
+        self.data.seek(self.addresses.get64(block)?)?;
+        let mut term = vec![0u8; self.data.read_vint()? as usize];
+        self.data.read_exact(&mut term)?;
+        for _ in (block << self.block_shift)..ord {
+            let token = self.data.read_byte()?;
+            let mut prefix_length = (token & 0x0F) as usize;
+            let mut suffix_length = 1 + (token >> 4) as usize;
+            if prefix_length == 15 {
+                prefix_length += self.data.read_vint()? as usize;
+            }
+            if suffix_length == 16 {
+                suffix_length += self.data.read_vint()? as usize;
+            }
+            term.resize(prefix_length + suffix_length, 0);
+            self.data.read_exact(&mut term[prefix_length..])?;
+        }
+        Ok(term)
+    }
+
+    fn try_clone(&self) -> Result<TermsDict> {
+        Ok(TermsDict {
+            size: self.size,
+            block_shift: self.block_shift,
+            addresses: self.addresses.clone(),
+            data: self.data.as_ref().clone()?,
+        })
+    }
+}
+
+pub struct Lucene80SortedDocValues {
+    docs_with_field: DocsWithField,
+    ords: Option<DirectPackedReader>,
+    terms_dict: TermsDict,
+}
+
+impl BinaryDocValues for Lucene80SortedDocValues {
+    fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+        let ord = self.get_ord(doc_id)?;
+        if ord < 0 {
+            Ok(vec![])
+        } else {
+            self.lookup_ord(ord)
+        }
+    }
+}
+
+impl SortedDocValues for Lucene80SortedDocValues {
+    fn get_ord(&mut self, doc_id: DocId) -> Result<i32> {
+        match (self.docs_with_field.index_of(doc_id)?, &self.ords) {
+            (None, _) => Ok(-1),
+            (Some(index), Some(ords)) => Ok(ords.get64(index)? as i32),
+            (Some(_), None) => Ok(0),
+        }
+    }
+
+    fn lookup_ord(&mut self, ord: i32) -> Result<Vec<u8>> {
+        self.terms_dict.lookup_ord(i64::from(ord))
+    }
+
+    fn value_count(&self) -> usize {
+        self.terms_dict.size as usize
+    }
+
+    fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+        Ok(DocValuesTermIterator::lucene80_sorted(
+            SortedDocValuesTermIterator::new(self),
+        ))
+    }
+}
+
+impl SortedDocValuesProvider for Lucene80SortedDocValues {
+    fn get(&self) -> Result<Box<dyn SortedDocValues>> {
+        Ok(Box::new(Lucene80SortedDocValues {
+            docs_with_field: self.docs_with_field.clone(),
+            ords: self.ords.clone(),
+            terms_dict: self.terms_dict.try_clone()?,
+        }))
+    }
+}
+
+pub struct Lucene80SortedNumericDocValues {
+    docs_with_field: DocsWithField,
+    values: NumericValues,
+    /// the start of the values of each doc, `None` if docs have one value
+    addresses: Option<MixinMonotonicLongValues>,
+    start: i64,
+    end: i64,
+}
+
+impl SortedNumericDocValues for Lucene80SortedNumericDocValues {
+    fn set_document(&mut self, doc: DocId) -> Result<()> {
+        let (start, end) = match (self.docs_with_field.index_of(doc)?, &self.addresses) {
+            (None, _) => (0, 0),
+            (Some(index), Some(addresses)) => {
+                (addresses.get64(index)?, addresses.get64(index + 1)?)
+            }
+            (Some(index), None) => (index, index + 1),
+        };
+        self.start = start;
+        self.end = end;
+        Ok(())
+    }
+
+    fn value_at(&mut self, index: usize) -> Result<i64> {
+        self.values.get(self.start + index as i64)
+    }
+
+    fn count(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+}
+
+impl SortedNumericDocValuesProvider for Lucene80SortedNumericDocValues {
+    fn get(&self) -> Result<Box<dyn SortedNumericDocValues>> {
+        Ok(Box::new(Lucene80SortedNumericDocValues {
+            docs_with_field: self.docs_with_field.clone(),
+            values: self.values.clone(),
+            addresses: self.addresses.clone(),
+            start: 0,
+            end: 0,
+        }))
+    }
+}
+
+pub struct Lucene80SortedSetDocValues {
+    docs_with_field: DocsWithField,
+    ords: Option<DirectPackedReader>,
+    /// the start of the ords of each doc, `None` if docs have one value
+    addresses: Option<MixinMonotonicLongValues>,
+    terms_dict: TermsDict,
+    upto: i64,
+    end: i64,
+}
+
+impl SortedSetDocValues for Lucene80SortedSetDocValues {
+    fn set_document(&mut self, doc: DocId) -> Result<()> {
+        let (upto, end) = match (self.docs_with_field.index_of(doc)?, &self.addresses) {
+            (None, _) => (0, 0),
+            (Some(index), Some(addresses)) => {
+                (addresses.get64(index)?, addresses.get64(index + 1)?)
+            }
+            (Some(index), None) => (index, index + 1),
+        };
+        self.upto = upto;
+        self.end = end;
+        Ok(())
+    }
+
+    fn next_ord(&mut self) -> Result<i64> {
+        if self.upto == self.end {
+            return Ok(NO_MORE_ORDS);
+        }
+        self.upto += 1;
+        match &self.ords {
+            Some(ords) => ords.get64(self.upto - 1),
+            None => Ok(0),
+        }
+    }
+
+    fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+        self.terms_dict.lookup_ord(ord)
+    }
+
+    fn get_value_count(&self) -> usize {
+        self.terms_dict.size as usize
+    }
+
+    fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+        Ok(DocValuesTermIterator::lucene80_sorted_set(
+            SortedSetDocValuesTermIterator::new(self),
+        ))
+    }
+}
+
+impl SortedSetDocValuesProvider for Lucene80SortedSetDocValues {
+    fn get(&self) -> Result<Box<dyn SortedSetDocValues>> {
+        Ok(Box::new(Lucene80SortedSetDocValues {
+            docs_with_field: self.docs_with_field.clone(),
+            ords: self.ords.clone(),
+            addresses: self.addresses.clone(),
+            terms_dict: self.terms_dict.try_clone()?,
+            upto: 0,
+            end: 0,
+        }))
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::{EmptyNumericDocValues, NumericDocValues};
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::lucene80::IndexedDISI;
+use crate::core::codec::norms::{NormsConsumerEnum, NormsFormat, NormsProducer};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::{codec_util, Codec};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{IndexInput, RandomAccessInput};
+use crate::core::util::DocId;
+use crate::error::Error::{CorruptIndex, IllegalArgument, UnsupportedOperation};
+use crate::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const DATA_CODEC: &str = "Lucene80NormsData";
+const DATA_EXTENSION: &str = "nvd";
+const METADATA_CODEC: &str = "Lucene80NormsMetadata";
+const METADATA_EXTENSION: &str = "nvm";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// The norms format of Java Lucene 8.0 to 8.4, only reading is supported.
+#[derive(Copy, Clone, Default)]
+pub struct Lucene80NormsFormat;
+
+impl NormsFormat for Lucene80NormsFormat {
+    type NormsProducer = Lucene80NormsProducer;
+
+    fn norms_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::NormsProducer> {
+        Lucene80NormsProducer::new(state)
+    }
+
+    fn norms_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        _state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<NormsConsumerEnum<DW::IndexOutput>> {
+        Err(UnsupportedOperation(
+            "Lucene80NormsFormat is read only".into(),
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct NormsEntry {
+    /// -2 if no doc has a norm, -1 if all docs have one
+    docs_with_field_offset: i64,
+    docs_with_field_length: i64,
+    dense_rank_power: i8,
+    num_docs_with_field: i32,
+    bytes_per_norm: u8,
+    /// the norm of all docs when `bytes_per_norm` is 0
+    norms_offset: i64,
+}
+
+/// Reader for `Lucene80NormsFormat`
+pub struct Lucene80NormsProducer {
+    max_doc: DocId,
+    data: Box<dyn IndexInput>,
+    entries: HashMap<i32, NormsEntry>,
+}
+
+impl Lucene80NormsProducer {
+    fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Lucene80NormsProducer> {
+        let meta_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            METADATA_EXTENSION,
+        );
+        let mut checksum_input = state
+            .directory
+            .open_checksum_input(&meta_name, state.context)?;
+        let meta_version = codec_util::check_index_header(
+            &mut checksum_input,
+            METADATA_CODEC,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        let entries = Self::read_fields(&mut checksum_input, &state.field_infos)?;
+        codec_util::check_footer(&mut checksum_input)?;
+
+        let data_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            DATA_EXTENSION,
+        );
+        let mut data = state.directory.open_input(&data_name, state.context)?;
+        let data_version = codec_util::check_index_header(
+            data.as_mut(),
+            DATA_CODEC,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        if data_version != meta_version {
+            return Err(CorruptIndex(format!(
+                "Format versions mismatch: meta={}, data={}",
+                meta_version, data_version
+            )));
+        }
+        codec_util::retrieve_checksum(data.as_mut())?;
+
+        Ok(Lucene80NormsProducer {
+            max_doc: state.segment_info.max_doc(),
+            data,
+            entries,
+        })
+    }
+
+    fn read_fields<T: IndexInput + ?Sized>(
+        input: &mut T,
+        infos: &FieldInfos,
+    ) -> Result<HashMap<i32, NormsEntry>> {
+        let mut entries = HashMap::new();
+        loop {
+            let field_num = input.read_int()?;
+            if field_num == -1 {
+                break;
+            }
+            let field_info = infos
+                .field_info_by_number(field_num as u32)
+                .ok_or_else(|| CorruptIndex(format!("Invalid field number: {}", field_num)))?;
+            if !field_info.has_norms() {
+                return Err(CorruptIndex(format!("Invalid field: {}", field_info.name)));
+            }
+            let docs_with_field_offset = input.read_long()?;
+            let docs_with_field_length = input.read_long()?;
+            // the jump table entry count, we don't use the jump table
+            input.read_short()?;
+            let dense_rank_power = input.read_byte()? as i8;
+            let num_docs_with_field = input.read_int()?;
+            let bytes_per_norm = input.read_byte()?;
+            match bytes_per_norm {
+                0 | 1 | 2 | 4 | 8 => {}
+                _ => {
+                    return Err(CorruptIndex(format!(
+                        "Invalid bytes per norm: {}, field: {}",
+                        bytes_per_norm, field_info.name
+                    )));
+                }
+            }
+            let norms_offset = input.read_long()?;
+            entries.insert(
+                field_num,
+                NormsEntry {
+                    docs_with_field_offset,
+                    docs_with_field_length,
+                    dense_rank_power,
+                    num_docs_with_field,
+                    bytes_per_norm,
+                    norms_offset,
+                },
+            );
+        }
+        Ok(entries)
+    }
+}
+
+impl NormsProducer for Lucene80NormsProducer {
+    fn norms(&self, field: &FieldInfo) -> Result<Box<dyn NumericDocValues>> {
+        let entry = self
+            .entries
+            .get(&(field.number as i32))
+            .ok_or_else(|| IllegalArgument(format!("no norms for field '{}'", field.name)))?;
+        if entry.docs_with_field_offset == -2 {
+            return Ok(Box::new(EmptyNumericDocValues));
+        }
+        let docs_with_field = if entry.docs_with_field_offset == -1 {
+            None
+        } else {
+            let slice = self
+                .data
+                .random_access_slice(entry.docs_with_field_offset, entry.docs_with_field_length)?;
+            Some(IndexedDISI::new(
+                Arc::from(slice),
+                entry.dense_rank_power,
+                self.max_doc,
+            )?)
+        };
+        let norms = if entry.bytes_per_norm == 0 {
+            None
+        } else {
+            Some(self.data.random_access_slice(
+                entry.norms_offset,
+                i64::from(entry.num_docs_with_field) * i64::from(entry.bytes_per_norm),
+            )?)
+        };
+        Ok(Box::new(Lucene80NormValues {
+            docs_with_field,
+            norms,
+            bytes_per_norm: entry.bytes_per_norm,
+            constant: entry.norms_offset,
+        }))
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        codec_util::checksum_entire_file(self.data.as_ref())?;
+        Ok(())
+    }
+}
+
+struct Lucene80NormValues {
+    /// `None` if all docs have a norm
+    docs_with_field: Option<IndexedDISI>,
+    /// `None` if all docs have the same norm
+    norms: Option<Box<dyn RandomAccessInput>>,
+    bytes_per_norm: u8,
+    constant: i64,
+}
+
+impl NumericDocValues for Lucene80NormValues {
+    fn get(&self, doc_id: DocId) -> Result<i64> {
+        let index = match &self.docs_with_field {
+            Some(disi) => match disi.index_of(doc_id)? {
+                Some(index) => index as u64,
+                None => return Ok(0),
+            },
+            None => doc_id as u64,
+        };
+        let norms = match &self.norms {
+            Some(norms) => norms,
+            None => return Ok(self.constant),
+        };
+        match self.bytes_per_norm {
+            1 => norms.read_byte(index).map(i64::from),
+            2 => norms.read_short(index << 1).map(i64::from),
+            4 => norms.read_int(index << 2).map(i64::from),
+            _ => norms.read_long(index << 3),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::postings::{
+    FieldsConsumerEnum, Lucene50PostingsFormat, PerFieldFieldsReader, PostingsFormat,
+    PostingsFormatEnum,
+};
+use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::Codec;
+use crate::core::store::directory::Directory;
+use crate::error::Error::{CorruptIndex, UnsupportedOperation};
+use crate::Result;
+
+use std::sync::Arc;
+
+/// Reads the per field postings of Java Lucene 8.0 to 8.3 indexes, where all
+/// fields use the `Lucene50` postings format with impacts in the skip data.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Lucene80PostingsFormat;
+
+impl PostingsFormat for Lucene80PostingsFormat {
+    type FieldsProducer = Arc<PerFieldFieldsReader>;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        let reader = PerFieldFieldsReader::with_format_resolver(state, |format| match format {
+            "Lucene50" => Ok(PostingsFormatEnum::Lucene50(
+                Lucene50PostingsFormat::java_lucene80(),
+            )),
+            _ => Err(CorruptIndex(format!(
+                "unsupported postings format in a Lucene80 segment: '{}'",
+                format
+            ))),
+        })?;
+        Ok(Arc::new(reader))
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        _state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        Err(UnsupportedOperation(
+            "Lucene80PostingsFormat is read only".into(),
+        ))
+    }
+
+    fn name(&self) -> &str {
+        "PerField40"
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read support for indexes written by Java Lucene 8.0 to 8.3.
+//!
+//! Segments of these versions use the `Lucene80` codec, which shares most of
+//! its formats with `Lucene62Codec` and only differs in its segment info,
+//! norms, doc values and the impacts in the postings skip data. Writing with
+//! these formats is not supported.
+
+mod indexed_disi;
+
+pub use self::indexed_disi::{IndexedDISI, IndexedDISIBits};
+
+mod lucene80_doc_values_format;
+
+pub use self::lucene80_doc_values_format::{
+    Lucene80BinaryDocValues, Lucene80DocValuesFormat, Lucene80DocValuesProducer,
+    Lucene80NumericDocValues, Lucene80SortedDocValues, Lucene80SortedNumericDocValues,
+    Lucene80SortedSetDocValues,
+};
+
+mod lucene80_norms_format;
+
+pub use self::lucene80_norms_format::{Lucene80NormsFormat, Lucene80NormsProducer};
+
+mod lucene80_postings_format;
+
+pub use self::lucene80_postings_format::Lucene80PostingsFormat;
+
+use crate::core::codec::doc_values::PerFieldDocValuesFormat;
+use crate::core::codec::field_infos::Lucene60FieldInfosFormat;
+use crate::core::codec::points::Lucene60PointsFormat;
+use crate::core::codec::postings::PerFieldFieldsReader;
+use crate::core::codec::segment_infos::Lucene70SegmentInfoFormat;
+use crate::core::codec::stored_fields::Lucene50StoredFieldsFormat;
+use crate::core::codec::term_vectors::{term_vectors_format, CompressingTermVectorsFormat};
+use crate::core::codec::vectors::HnswVectorsFormat;
+use crate::core::codec::{Codec, Lucene50CompoundFormat, Lucene50LiveDocsFormat};
+use crate::error::Error::CorruptIndex;
+use crate::error::{Error, Result};
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// A read only codec for segments written by Java Lucene 8.0 to 8.3.
+///
+/// Segments written by Lucene 8.4 and later use the `Lucene84` codec and are
+/// rejected. Soft deletes are ignored, so soft deleted docs stay visible, and
+/// Lucene 8 encodes the BM25 norms differently, so scores differ from the ones
+/// Java Lucene computes.
+#[derive(Clone)]
+pub struct Lucene80Codec {
+    term_vector_format: CompressingTermVectorsFormat,
+}
+
+impl Default for Lucene80Codec {
+    fn default() -> Lucene80Codec {
+        Lucene80Codec {
+            term_vector_format: term_vectors_format(),
+        }
+    }
+}
+
+impl Codec for Lucene80Codec {
+    type FieldsProducer = Arc<PerFieldFieldsReader>;
+    type PostingFmt = Lucene80PostingsFormat;
+    type DVFmt = PerFieldDocValuesFormat;
+    type StoredFmt = Lucene50StoredFieldsFormat;
+    type TVFmt = CompressingTermVectorsFormat;
+    type FieldFmt = Lucene60FieldInfosFormat;
+    type SegmentFmt = Lucene70SegmentInfoFormat;
+    type NormFmt = Lucene80NormsFormat;
+    type LiveDocFmt = Lucene50LiveDocsFormat;
+    type CompoundFmt = Lucene50CompoundFormat;
+    type PointFmt = Lucene60PointsFormat;
+    type VectorsFmt = HnswVectorsFormat;
+
+    fn name(&self) -> &str {
+        "Lucene80"
+    }
+
+    fn postings_format(&self) -> Self::PostingFmt {
+        Lucene80PostingsFormat
+    }
+
+    fn doc_values_format(&self) -> Self::DVFmt {
+        PerFieldDocValuesFormat::default()
+    }
+
+    fn stored_fields_format(&self) -> Self::StoredFmt {
+        Lucene50StoredFieldsFormat::new(None)
+    }
+
+    fn term_vectors_format(&self) -> Self::TVFmt {
+        self.term_vector_format.clone()
+    }
+
+    fn field_infos_format(&self) -> Self::FieldFmt {
+        Lucene60FieldInfosFormat::default()
+    }
+
+    fn segment_info_format(&self) -> Self::SegmentFmt {
+        Lucene70SegmentInfoFormat
+    }
+
+    fn norms_format(&self) -> Self::NormFmt {
+        Lucene80NormsFormat
+    }
+
+    fn live_docs_format(&self) -> Self::LiveDocFmt {
+        Lucene50LiveDocsFormat {}
+    }
+
+    fn compound_format(&self) -> Self::CompoundFmt {
+        Lucene50CompoundFormat {}
+    }
+
+    fn points_format(&self) -> Self::PointFmt {
+        Lucene60PointsFormat::default()
+    }
+
+    fn vectors_format(&self) -> Self::VectorsFmt {
+        HnswVectorsFormat::default()
+    }
+}
+
+impl TryFrom<String> for Lucene80Codec {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        if value.as_str() == "Lucene80" {
+            Ok(Self::default())
+        } else {
+            Err(CorruptIndex(format!(
+                "unknown codec name, expected 'Lucene80' got {:?}",
+                value
+            )))
+        }
+    }
+}
@@ -13,18 +13,22 @@
 
 pub mod doc_values;
 pub mod field_infos;
+pub mod lucene80;
 pub mod norms;
 pub mod points;
 pub mod postings;
 pub mod segment_infos;
+pub mod simple_text;
 pub mod stored_fields;
 pub mod term_vectors;
+pub mod vectors;
 
 mod codec_util;
 
 pub use codec_util::{
-    check_checksum, check_header, check_header_no_magic, check_index_header_suffix, footer_length,
-    validate_footer, write_footer, write_header, write_index_header, CODEC_MAGIC,
+    check_checksum, check_footer, check_header, check_header_no_magic, check_index_header_suffix,
+    checksum_entire_file, footer_length, validate_footer, write_footer, write_header,
+    write_index_header, CODEC_MAGIC,
 };
 
 mod matching_reader;
@@ -68,8 +72,15 @@ pub use sorter::{
 
 mod posting_iterator;
 
+mod codec_registry;
+
+pub use codec_registry::{
+    available_codecs, codec_for_name, register_codec, CodecConstructor, CustomCodec,
+};
+
 pub use posting_iterator::{EmptyPostingIterator, PostingIterator, PostingIteratorFlags};
 
+use crate::core::codec::doc_values::lucene54::Lucene54DocValuesFormat;
 use crate::core::codec::doc_values::{
     DocValuesFormat, DocValuesFormatEnum, PerFieldDocValuesFormat,
 };
@@ -77,17 +88,19 @@ use crate::core::codec::field_infos::{FieldInfosFormat, Lucene60FieldInfosFormat
 use crate::core::codec::norms::{Lucene53NormsFormat, NormsFormat};
 use crate::core::codec::points::{Lucene60PointsFormat, PointsFormat};
 use crate::core::codec::postings::{
-    FieldsProducer, PerFieldFieldsReader, PerFieldPostingsFormat, PostingsFormat,
+    FieldsProducer, Lucene50PostingsFormat, PerFieldFieldsReader, PerFieldPostingsFormat,
+    PostingsFormat, PostingsFormatEnum,
 };
 use crate::core::codec::stored_fields::{
     Lucene50StoredFieldsFormat, StoredFieldCompressMode, StoredFieldsFormat,
 };
+use crate::core::codec::vectors::{HnswVectorsFormat, VectorsFormat};
 use crate::core::codec::term_vectors::{
     term_vectors_format, CompressingTermVectorsFormat, TermVectorsFormat, TermVectorsReader,
 };
 
 use crate::core::codec::segment_infos::{Lucene62SegmentInfoFormat, SegmentInfoFormat};
-use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::error::Error::CorruptIndex;
 use crate::error::{Error, Result};
 use std::convert::TryFrom;
 use std::sync::Arc;
@@ -102,6 +115,7 @@ pub const LONG_BYTES: i32 = 8;
 /// Note, when extending this class, the name `get_name` is
 /// written into the index. In order for the segment to be read, the
 /// name must resolve to your implementation via {@link TryFrom::try_from(String)}.
+/// `CodecEnum` resolves names through the codec registry, see `register_codec`.
 pub trait Codec: TryFrom<String, Error = Error> + 'static {
     type FieldsProducer: FieldsProducer + Clone;
     type PostingFmt: PostingsFormat<FieldsProducer = Self::FieldsProducer>;
@@ -114,6 +128,7 @@ pub trait Codec: TryFrom<String, Error = Error> + 'static {
     type LiveDocFmt: LiveDocsFormat;
     type CompoundFmt: CompoundFormat;
     type PointFmt: PointsFormat;
+    type VectorsFmt: VectorsFormat;
 
     fn name(&self) -> &str;
     fn postings_format(&self) -> Self::PostingFmt;
@@ -126,6 +141,7 @@ pub trait Codec: TryFrom<String, Error = Error> + 'static {
     fn live_docs_format(&self) -> Self::LiveDocFmt;
     fn compound_format(&self) -> Self::CompoundFmt;
     fn points_format(&self) -> Self::PointFmt;
+    fn vectors_format(&self) -> Self::VectorsFmt;
 }
 
 pub type CodecFieldsProducer<C> = <<C as Codec>::PostingFmt as PostingsFormat>::FieldsProducer;
@@ -140,9 +156,22 @@ pub type CodecTVFields<C> =
     <<<C as Codec>::TVFmt as TermVectorsFormat>::TVReader as TermVectorsReader>::Fields;
 pub type CodecNormsProducer<C> = <<C as Codec>::NormFmt as NormsFormat>::NormsProducer;
 pub type CodecPointsReader<C> = <<C as Codec>::PointFmt as PointsFormat>::Reader;
+pub type CodecVectorsReader<C> = <<C as Codec>::VectorsFmt as VectorsFormat>::Reader;
 
+#[derive(Clone)]
 pub enum CodecEnum {
     Lucene62(Lucene62Codec),
+    Custom(CustomCodec),
+}
+
+impl CodecEnum {
+    /// Returns a copy of this codec with stored fields compressed in `mode`.
+    pub fn with_stored_fields_mode(&self, mode: StoredFieldCompressMode) -> CodecEnum {
+        match self {
+            CodecEnum::Lucene62(c) => CodecEnum::Lucene62(c.clone().with_stored_fields_mode(mode)),
+            CodecEnum::Custom(c) => CodecEnum::Custom(c.clone().with_stored_fields_mode(mode)),
+        }
+    }
 }
 
 impl Codec for CodecEnum {
@@ -157,55 +186,66 @@ impl Codec for CodecEnum {
     type LiveDocFmt = Lucene50LiveDocsFormat;
     type CompoundFmt = Lucene50CompoundFormat;
     type PointFmt = Lucene60PointsFormat;
+    type VectorsFmt = HnswVectorsFormat;
 
     fn name(&self) -> &str {
         match self {
             CodecEnum::Lucene62(c) => c.name(),
+            CodecEnum::Custom(c) => c.name(),
         }
     }
     fn postings_format(&self) -> Self::PostingFmt {
         match self {
             CodecEnum::Lucene62(c) => c.postings_format(),
+            CodecEnum::Custom(c) => c.postings_format(),
         }
     }
     fn doc_values_format(&self) -> Self::DVFmt {
         match self {
             CodecEnum::Lucene62(c) => DocValuesFormatEnum::PerField(c.doc_values_format()),
+            CodecEnum::Custom(c) => DocValuesFormatEnum::PerField(c.doc_values_format()),
         }
     }
     fn stored_fields_format(&self) -> Self::StoredFmt {
         match self {
             CodecEnum::Lucene62(c) => c.stored_fields_format(),
+            CodecEnum::Custom(c) => c.stored_fields_format(),
         }
     }
     fn term_vectors_format(&self) -> Self::TVFmt {
         match self {
             CodecEnum::Lucene62(c) => c.term_vectors_format(),
+            CodecEnum::Custom(c) => c.term_vectors_format(),
         }
     }
     fn field_infos_format(&self) -> Self::FieldFmt {
         match self {
             CodecEnum::Lucene62(c) => c.field_infos_format(),
+            CodecEnum::Custom(c) => c.field_infos_format(),
         }
     }
     fn segment_info_format(&self) -> Self::SegmentFmt {
         match self {
             CodecEnum::Lucene62(c) => c.segment_info_format(),
+            CodecEnum::Custom(c) => c.segment_info_format(),
         }
     }
     fn norms_format(&self) -> Self::NormFmt {
         match self {
             CodecEnum::Lucene62(c) => c.norms_format(),
+            CodecEnum::Custom(c) => c.norms_format(),
         }
     }
     fn live_docs_format(&self) -> Self::LiveDocFmt {
         match self {
             CodecEnum::Lucene62(c) => c.live_docs_format(),
+            CodecEnum::Custom(c) => c.live_docs_format(),
         }
     }
     fn compound_format(&self) -> Self::CompoundFmt {
         match self {
             CodecEnum::Lucene62(c) => c.compound_format(),
+            CodecEnum::Custom(c) => c.compound_format(),
         }
     }
 
@@ -213,6 +253,15 @@ impl Codec for CodecEnum {
     fn points_format(&self) -> Self::PointFmt {
         match self {
             CodecEnum::Lucene62(c) => c.points_format(),
+            CodecEnum::Custom(c) => c.points_format(),
+        }
+    }
+
+    /// Encodes/decodes per-document vectors
+    fn vectors_format(&self) -> Self::VectorsFmt {
+        match self {
+            CodecEnum::Lucene62(c) => c.vectors_format(),
+            CodecEnum::Custom(c) => c.vectors_format(),
         }
     }
 }
@@ -221,25 +270,13 @@ impl TryFrom<String> for CodecEnum {
     type Error = Error;
 
     fn try_from(value: String) -> Result<Self> {
-        match value.as_str() {
-            "Lucene62" => Ok(CodecEnum::Lucene62(Lucene62Codec::try_from(value)?)),
-            _ => return Err(IllegalArgument(format!("Invalid codec name: {}", value))),
-        }
-    }
-}
-
-/// looks up a codec by name
-pub fn codec_for_name(name: &str) -> Result<CodecEnum> {
-    match name {
-        "Lucene62" => Ok(CodecEnum::Lucene62(Lucene62Codec::try_from(
-            name.to_string(),
-        )?)),
-        _ => return Err(IllegalArgument(format!("Invalid codec name: {}", name))),
+        codec_for_name(&value)
     }
 }
 
 /// Implements the Lucene 6.2 index format, with configurable per-field postings
 /// and docvalues formats.
+#[derive(Clone)]
 pub struct Lucene62Codec {
     postings_format: PerFieldPostingsFormat,
     field_infos_format: Lucene60FieldInfosFormat,
@@ -251,6 +288,7 @@ pub struct Lucene62Codec {
     stored_fields_format: Lucene50StoredFieldsFormat,
     norms_format: Lucene53NormsFormat,
     points_format: Lucene60PointsFormat,
+    vectors_format: HnswVectorsFormat,
 }
 
 impl Default for Lucene62Codec {
@@ -267,11 +305,79 @@ impl Default for Lucene62Codec {
             )),
             doc_values_format: PerFieldDocValuesFormat::default(),
             norms_format: Lucene53NormsFormat::default(),
-            points_format: Lucene60PointsFormat {},
+            points_format: Lucene60PointsFormat::default(),
+            vectors_format: HnswVectorsFormat::default(),
         }
     }
 }
 
+impl Lucene62Codec {
+    /// Creates a codec writing segments byte-compatible with Java Lucene 6.6, so that
+    /// indexes built by rucene can be searched by Java Lucene 6.5 and later 6.x
+    /// releases. The segments are read back by the default codec, like any other
+    /// `Lucene62` segment.
+    ///
+    /// Setting other postings, doc values or points formats, or zstd compression,
+    /// writes files Java Lucene can't read. Points must index all their dimensions,
+    /// and soft deleted documents are live for Java Lucene 6.
+    pub fn java_lucene66() -> Lucene62Codec {
+        Lucene62Codec {
+            postings_format: PerFieldPostingsFormat::default().with_default_format(
+                PostingsFormatEnum::Lucene50(Lucene50PostingsFormat::java_lucene66()),
+            ),
+            doc_values_format: PerFieldDocValuesFormat::default().with_default_format(
+                DocValuesFormatEnum::Lucene54(Lucene54DocValuesFormat::java_lucene66()),
+            ),
+            field_infos_format: Lucene60FieldInfosFormat::java_lucene66(),
+            norms_format: Lucene53NormsFormat::java_lucene66(),
+            points_format: Lucene60PointsFormat::java_lucene66(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns this codec with stored fields compressed in `mode`.
+    pub fn with_stored_fields_mode(mut self, mode: StoredFieldCompressMode) -> Self {
+        self.stored_fields_format = Lucene50StoredFieldsFormat::new(Some(mode));
+        self
+    }
+
+    /// Returns this codec with the postings of each field written by `format`.
+    pub fn with_postings_format(mut self, format: PerFieldPostingsFormat) -> Self {
+        self.postings_format = format;
+        self
+    }
+
+    /// Returns this codec with the doc values of each field written by `format`.
+    pub fn with_doc_values_format(mut self, format: PerFieldDocValuesFormat) -> Self {
+        self.doc_values_format = format;
+        self
+    }
+
+    /// Returns this codec with points written by `format`, e.g. with another leaf size.
+    pub fn with_points_format(mut self, format: Lucene60PointsFormat) -> Self {
+        self.points_format = format;
+        self
+    }
+
+    /// Returns this codec with vectors written by `format`, e.g. with another
+    /// graph connectivity.
+    pub fn with_vectors_format(mut self, format: HnswVectorsFormat) -> Self {
+        self.vectors_format = format;
+        self
+    }
+
+    /// Returns this codec with term vectors compressed with zstd at `level`.
+    ///
+    /// Unlike stored fields, segments don't record how their term vectors are
+    /// compressed, so a codec using this must be registered under its own
+    /// name, see `CustomCodec`.
+    #[cfg(feature = "zstd")]
+    pub fn with_zstd_term_vectors(mut self, level: i32) -> Self {
+        self.term_vector_format = term_vectors::zstd_term_vectors_format(level);
+        self
+    }
+}
+
 impl Codec for Lucene62Codec {
     type FieldsProducer = Arc<PerFieldFieldsReader>;
     type PostingFmt = PerFieldPostingsFormat;
@@ -284,17 +390,18 @@ impl Codec for Lucene62Codec {
     type LiveDocFmt = Lucene50LiveDocsFormat;
     type CompoundFmt = Lucene50CompoundFormat;
     type PointFmt = Lucene60PointsFormat;
+    type VectorsFmt = HnswVectorsFormat;
 
     fn name(&self) -> &str {
         "Lucene62"
     }
 
     fn postings_format(&self) -> Self::PostingFmt {
-        self.postings_format
+        self.postings_format.clone()
     }
 
     fn doc_values_format(&self) -> Self::DVFmt {
-        self.doc_values_format
+        self.doc_values_format.clone()
     }
 
     fn stored_fields_format(&self) -> Self::StoredFmt {
@@ -328,6 +435,10 @@ impl Codec for Lucene62Codec {
     fn points_format(&self) -> Self::PointFmt {
         self.points_format
     }
+
+    fn vectors_format(&self) -> Self::VectorsFmt {
+        self.vectors_format
+    }
 }
 
 impl TryFrom<String> for Lucene62Codec {
@@ -23,7 +23,7 @@ use crate::core::index::reader::{IndexReader, ReaderSlice};
 use crate::core::index::writer::INDEX_MAX_POSITION;
 use crate::core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use crate::core::store::directory::Directory;
-use crate::core::util::DocId;
+use crate::core::util::{Accountable, DocId, NamedAccountable};
 
 use crate::error::Error::{CorruptIndex, UnsupportedOperation};
 use crate::Result;
@@ -97,6 +97,23 @@ where
     }
 }
 
+impl<T> Accountable for MultiFields<T>
+where
+    T: FieldsProducer,
+{
+    fn ram_bytes_used(&self) -> usize {
+        self.subs.iter().map(|s| s.ram_bytes_used()).sum()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.subs
+            .iter()
+            .enumerate()
+            .map(|(i, s)| NamedAccountable::new(format!("sub {}", i), s))
+            .collect()
+    }
+}
+
 impl<T> FieldsProducer for MultiFields<T>
 where
     T: FieldsProducer,
@@ -25,10 +25,41 @@ pub const DATA_EXTENSION: &str = "nvd";
 pub const METADATA_CODEC: &str = "Lucene53NormsMetadata";
 pub const METADATA_EXTENSION: &str = "nvm";
 pub const VERSION_START: i32 = 0;
-pub const VERSION_CURRENT: i32 = VERSION_START;
+// norms of fields that few documents have are stored sparsely
+pub const VERSION_SPARSE: i32 = 1;
+pub const VERSION_CURRENT: i32 = VERSION_SPARSE;
 
-#[derive(Copy, Clone, Default)]
-pub struct Lucene53NormsFormat;
+// require at least 1024 docs to avoid flipping back and forth when doing NRT search
+pub const SPARSE_MIN_DOCS: i32 = 1024;
+pub const SPARSE_DENSITY_RATIO: i32 = 16;
+
+/// Lucene 5.3 norms format.
+///
+/// Norms of a field are stored with the smallest of 0 (constant), 1, 2, 4 or
+/// 8 bytes per document. Since `VERSION_SPARSE`, when at most one in
+/// `SPARSE_DENSITY_RATIO` documents of a segment has a non-zero norm for a
+/// field, only the ids and norms of those documents are stored.
+#[derive(Copy, Clone)]
+pub struct Lucene53NormsFormat {
+    version: i32,
+}
+
+impl Default for Lucene53NormsFormat {
+    fn default() -> Self {
+        Lucene53NormsFormat {
+            version: VERSION_CURRENT,
+        }
+    }
+}
+
+impl Lucene53NormsFormat {
+    /// Writes norms Java Lucene 6.6 can read, never sparse.
+    pub fn java_lucene66() -> Self {
+        Lucene53NormsFormat {
+            version: VERSION_START,
+        }
+    }
+}
 
 impl NormsFormat for Lucene53NormsFormat {
     type NormsProducer = Lucene53NormsProducer;
@@ -51,6 +82,7 @@ impl NormsFormat for Lucene53NormsFormat {
     ) -> Result<NormsConsumerEnum<DW::IndexOutput>> {
         Ok(NormsConsumerEnum::Lucene53(Lucene53NormsConsumer::new(
             state,
+            self.version,
             DATA_CODEC,
             DATA_EXTENSION,
             METADATA_CODEC,
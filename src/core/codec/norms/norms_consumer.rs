@@ -29,11 +29,13 @@ pub struct Lucene53NormsConsumer<O: IndexOutput> {
     data: O,
     meta: O,
     max_doc: i32,
+    version: i32,
 }
 
 impl<O: IndexOutput> Lucene53NormsConsumer<O> {
     pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
         state: &SegmentWriteState<D, DW, C>,
+        version: i32,
         data_codec: &str,
         data_extension: &str,
         meta_codec: &str,
@@ -48,7 +50,7 @@ impl<O: IndexOutput> Lucene53NormsConsumer<O> {
         codec_util::write_index_header(
             &mut data,
             data_codec,
-            norms::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -62,7 +64,7 @@ impl<O: IndexOutput> Lucene53NormsConsumer<O> {
         codec_util::write_index_header(
             &mut meta,
             meta_codec,
-            norms::VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -73,6 +75,7 @@ impl<O: IndexOutput> Lucene53NormsConsumer<O> {
             data,
             meta,
             max_doc,
+            version,
         })
     }
 }
@@ -89,28 +92,72 @@ impl<O: IndexOutput> Lucene53NormsConsumer<O> {
         max_value: i64,
         values: &mut impl ReusableIterator<Item = Result<Numeric>>,
     ) -> Result<()> {
-        let len = if min_value >= i8::min_value() as i64 && max_value <= i8::max_value() as i64 {
+        let len = Self::bytes_per_value(min_value, max_value);
+        self.meta.write_byte(len)?;
+        self.meta.write_long(self.data.file_pointer())?;
+        while let Some(Ok(nv)) = values.next() {
+            self.write_value(len, nv)?;
+        }
+        values.reset();
+        Ok(())
+    }
+
+    /// Writes the ids of the documents with a non-zero norm, followed by
+    /// their norms unless they are all the same.
+    fn add_sparse(
+        &mut self,
+        min_value: i64,
+        max_value: i64,
+        num_docs_with_value: i32,
+        values: &mut impl ReusableIterator<Item = Result<Numeric>>,
+    ) -> Result<()> {
+        let docs_offset = self.data.file_pointer();
+        let mut doc = 0;
+        for nv in &mut *values {
+            if nv?.long_value() != 0 {
+                self.data.write_int(doc)?;
+            }
+            doc += 1;
+        }
+        values.reset();
+        if min_value == max_value {
+            self.add_constant(min_value)?;
+        } else {
+            let len = Self::bytes_per_value(min_value, max_value);
+            self.meta.write_byte(len)?;
+            self.meta.write_long(self.data.file_pointer())?;
+            for nv in &mut *values {
+                let nv = nv?;
+                if nv.long_value() != 0 {
+                    self.write_value(len, nv)?;
+                }
+            }
+            values.reset();
+        }
+        self.meta.write_int(num_docs_with_value)?;
+        self.meta.write_long(docs_offset)
+    }
+
+    fn bytes_per_value(min_value: i64, max_value: i64) -> u8 {
+        if min_value >= i8::MIN as i64 && max_value <= i8::MAX as i64 {
             1
-        } else if min_value >= i16::min_value() as i64 && max_value <= i16::max_value() as i64 {
+        } else if min_value >= i16::MIN as i64 && max_value <= i16::MAX as i64 {
             2
-        } else if min_value >= i32::min_value() as i64 && max_value <= i32::max_value() as i64 {
+        } else if min_value >= i32::MIN as i64 && max_value <= i32::MAX as i64 {
             4
         } else {
             8
-        };
-        self.meta.write_byte(len as u8)?;
-        self.meta.write_long(self.data.file_pointer())?;
-        while let Some(Ok(nv)) = values.next() {
-            match len {
-                1 => self.data.write_byte(nv.byte_value() as u8)?,
-                2 => self.data.write_short(nv.short_value())?,
-                4 => self.data.write_int(nv.int_value())?,
-                8 => self.data.write_long(nv.long_value())?,
-                _ => unreachable!(),
-            }
         }
-        values.reset();
-        Ok(())
+    }
+
+    fn write_value(&mut self, len: u8, nv: Numeric) -> Result<()> {
+        match len {
+            1 => self.data.write_byte(nv.byte_value() as u8),
+            2 => self.data.write_short(nv.short_value()),
+            4 => self.data.write_int(nv.int_value()),
+            8 => self.data.write_long(nv.long_value()),
+            _ => unreachable!(),
+        }
     }
 }
 
@@ -121,13 +168,22 @@ impl<O: IndexOutput> NormsConsumer for Lucene53NormsConsumer<O> {
         values: &mut impl ReusableIterator<Item = Result<Numeric>>,
     ) -> Result<()> {
         self.meta.write_vint(field_info.number as i32)?;
-        let mut min_value = i64::max_value();
-        let mut max_value = i64::min_value();
+        let mut min_value = i64::MAX;
+        let mut max_value = i64::MIN;
         let mut count = 0;
+        // min and max of the non-zero norms, zero meaning missing
+        let mut sparse_min_value = i64::MAX;
+        let mut sparse_max_value = i64::MIN;
+        let mut num_docs_with_value = 0;
         while let Some(nv) = values.next() {
             let v = nv?.long_value();
             min_value = v.min(min_value);
             max_value = v.max(max_value);
+            if v != 0 {
+                sparse_min_value = v.min(sparse_min_value);
+                sparse_max_value = v.max(sparse_max_value);
+                num_docs_with_value += 1;
+            }
             count += 1;
         }
         values.reset();
@@ -139,9 +195,23 @@ impl<O: IndexOutput> NormsConsumer for Lucene53NormsConsumer<O> {
         }
         if min_value == max_value {
             self.add_constant(min_value)?;
+        } else if self.version >= norms::VERSION_SPARSE
+            && self.max_doc >= norms::SPARSE_MIN_DOCS
+            && num_docs_with_value * norms::SPARSE_DENSITY_RATIO <= self.max_doc
+        {
+            return self.add_sparse(
+                sparse_min_value,
+                sparse_max_value,
+                num_docs_with_value,
+                values,
+            );
         } else {
             self.add_byte(min_value, max_value, values)?;
         }
+        if self.version >= norms::VERSION_SPARSE {
+            // all docs have a norm
+            self.meta.write_int(-1)?;
+        }
         Ok(())
     }
 }
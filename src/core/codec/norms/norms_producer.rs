@@ -13,7 +13,7 @@
 
 use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
-use crate::core::codec::norms::norms::{VERSION_CURRENT, VERSION_START};
+use crate::core::codec::norms::norms::{VERSION_CURRENT, VERSION_SPARSE, VERSION_START};
 use crate::core::codec::norms::NormsProducer;
 use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState};
 use crate::core::codec::{codec_util, Codec};
@@ -28,6 +28,9 @@ use std::collections::HashMap;
 struct NormsEntry {
     bytes_per_value: u8,
     offset: u64,
+    // -1 if all docs have a norm
+    num_docs_with_value: i32,
+    docs_offset: u64,
 }
 
 /// Reader for `Lucene53NormsFormat`
@@ -65,7 +68,12 @@ impl Lucene53NormsProducer {
             &state.segment_suffix,
         )?;
         let mut entries = HashMap::new();
-        Self::read_fields(&mut checksum_input, &state.field_infos, &mut entries)?;
+        Self::read_fields(
+            &mut checksum_input,
+            &state.field_infos,
+            meta_version,
+            &mut entries,
+        )?;
         codec_util::check_footer(&mut checksum_input)?;
 
         let data_name = segment_file_name(
@@ -102,6 +110,7 @@ impl Lucene53NormsProducer {
     fn read_fields<T: IndexInput + ?Sized>(
         input: &mut T,
         infos: &FieldInfos,
+        version: i32,
         norms: &mut HashMap<i32, NormsEntry>,
     ) -> Result<()> {
         loop {
@@ -123,11 +132,26 @@ impl Lucene53NormsProducer {
                 }
             }
             let offset = input.read_long()? as u64;
+            let mut num_docs_with_value = -1;
+            let mut docs_offset = 0;
+            if version >= VERSION_SPARSE {
+                num_docs_with_value = input.read_int()?;
+                if num_docs_with_value >= 0 {
+                    docs_offset = input.read_long()? as u64;
+                } else if num_docs_with_value != -1 {
+                    return Err(CorruptIndex(format!(
+                        "Invalid number of docs with norms: {}",
+                        num_docs_with_value
+                    )));
+                }
+            }
             norms.insert(
                 field_info.number as i32,
                 NormsEntry {
                     bytes_per_value,
                     offset,
+                    num_docs_with_value,
+                    docs_offset,
                 },
             );
         }
@@ -135,11 +159,41 @@ impl Lucene53NormsProducer {
     }
 }
 
+impl Lucene53NormsProducer {
+    fn sparse_norms(&self, entry: &NormsEntry) -> Result<SparseNumericDocValues> {
+        let num_docs = entry.num_docs_with_value;
+        let docs = self
+            .data
+            .random_access_slice(entry.docs_offset as i64, i64::from(num_docs) * 4)?;
+        let values = if entry.bytes_per_value == 0 {
+            None
+        } else {
+            Some(self.data.random_access_slice(
+                entry.offset as i64,
+                i64::from(num_docs) * i64::from(entry.bytes_per_value),
+            )?)
+        };
+        Ok(SparseNumericDocValues {
+            docs,
+            values,
+            bytes_per_value: entry.bytes_per_value,
+            constant: entry.offset as i64,
+            num_docs,
+            index: 0,
+        })
+    }
+}
+
 impl NormsProducer for Lucene53NormsProducer {
     fn norms(&self, field: &FieldInfo) -> Result<Box<dyn NumericDocValues>> {
         debug_assert!(self.entries.contains_key(&(field.number as i32)));
 
         let entry = &self.entries[&(field.number as i32)];
+        if entry.num_docs_with_value >= 0 {
+            return self
+                .sparse_norms(entry)
+                .map(|v| Box::new(v) as Box<dyn NumericDocValues>);
+        }
         if entry.bytes_per_value == 0 {
             return Ok(Box::new(ScalarNumericDocValue(entry.offset as i64)));
         }
@@ -224,3 +278,69 @@ where
         (&self.consumer)(self.input.as_ref(), doc_id)
     }
 }
+
+/// Norms of the few docs of a sparse field, the other docs have a norm of 0.
+struct SparseNumericDocValues {
+    // sorted ids of the docs with a norm
+    docs: Box<dyn RandomAccessInput>,
+    // `None` if all the docs have the `constant` norm
+    values: Option<Box<dyn RandomAccessInput>>,
+    bytes_per_value: u8,
+    constant: i64,
+    num_docs: i32,
+    // lower bound of the next lookup in `get_mut`, docs are usually visited in order
+    index: i32,
+}
+
+impl SparseNumericDocValues {
+    /// Returns the index of the first doc in `docs[from..]` that is >= `doc_id`.
+    fn lower_bound(&self, mut from: i32, doc_id: DocId) -> Result<i32> {
+        let mut to = self.num_docs;
+        while from < to {
+            let mid = from + (to - from) / 2;
+            if self.docs.read_int(u64::from(mid as u32) << 2)? < doc_id {
+                from = mid + 1;
+            } else {
+                to = mid;
+            }
+        }
+        Ok(from)
+    }
+
+    fn value(&self, index: i32, doc_id: DocId) -> Result<i64> {
+        if index == self.num_docs || self.docs.read_int(u64::from(index as u32) << 2)? != doc_id {
+            return Ok(0);
+        }
+        let values = match &self.values {
+            Some(values) => values,
+            None => return Ok(self.constant),
+        };
+        let index = u64::from(index as u32);
+        match self.bytes_per_value {
+            1 => values.read_byte(index).map(i64::from),
+            2 => values.read_short(index << 1).map(i64::from),
+            4 => values.read_int(index << 2).map(i64::from),
+            8 => values.read_long(index << 3),
+            x => Err(CorruptIndex(format!("Invalid norm bytes size: {}", x))),
+        }
+    }
+}
+
+impl NumericDocValues for SparseNumericDocValues {
+    fn get(&self, doc_id: DocId) -> Result<i64> {
+        let index = self.lower_bound(0, doc_id)?;
+        self.value(index, doc_id)
+    }
+
+    fn get_mut(&mut self, doc_id: DocId) -> Result<i64> {
+        let from = if self.index > 0
+            && self.docs.read_int(u64::from(self.index as u32 - 1) << 2)? < doc_id
+        {
+            self.index
+        } else {
+            0
+        };
+        self.index = self.lower_bound(from, doc_id)?;
+        self.value(self.index, doc_id)
+    }
+}
@@ -27,6 +27,7 @@ use crate::core::util::DocId;
 
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::SimpleTextPointsWriter;
 use crate::core::codec::{Codec, CodecPointsReader};
 use crate::core::index::merge::DocMap;
 use crate::core::index::merge::{LiveDocsDocMap, MergePointValuesEnum, MergeState};
@@ -69,7 +70,7 @@ pub trait PointsFormat {
     fn fields_writer<D: Directory, DW: Directory, C: Codec>(
         &self,
         state: &SegmentWriteState<D, DW, C>,
-    ) -> Result<Lucene60PointsWriter<D, DW, C>>;
+    ) -> Result<PointsWriterEnum<D, DW, C>>;
 
     /// Reads a segment.  NOTE: by the time this call
     /// returns, it must hold open any files it will need to
@@ -135,6 +136,13 @@ impl<P: PointsReader, MP: MutablePointsReader> PointValues for PointsReaderEnum<
         }
     }
 
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        match self {
+            PointsReaderEnum::Simple(s) => s.num_index_dimensions(field_name),
+            PointsReaderEnum::Mutable(m) => m.num_index_dimensions(field_name),
+        }
+    }
+
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
         match self {
             PointsReaderEnum::Simple(s) => s.bytes_per_dimension(field_name),
@@ -218,6 +226,54 @@ pub trait PointsWriter {
     fn finish(&mut self) -> Result<()>;
 }
 
+/// Enum dispatch of the `PointsWriter` of each `PointsFormat`
+pub enum PointsWriterEnum<D: Directory, DW: Directory, C: Codec> {
+    Lucene60(Lucene60PointsWriter<D, DW, C>),
+    SimpleText(SimpleTextPointsWriter<DW::IndexOutput>),
+}
+
+impl<D: Directory, DW: Directory, C: Codec> PointsWriter for PointsWriterEnum<D, DW, C> {
+    fn write_field<P, MP>(
+        &mut self,
+        field_info: &FieldInfo,
+        values: PointsReaderEnum<P, MP>,
+    ) -> Result<()>
+    where
+        P: PointsReader,
+        MP: MutablePointsReader,
+    {
+        match self {
+            PointsWriterEnum::Lucene60(w) => w.write_field(field_info, values),
+            PointsWriterEnum::SimpleText(w) => w.write_field(field_info, values),
+        }
+    }
+
+    fn merge_one_field<D1: Directory, C1: Codec>(
+        &mut self,
+        merge_state: &MergeState<D1, C1>,
+        field_info: &FieldInfo,
+    ) -> Result<()> {
+        match self {
+            PointsWriterEnum::Lucene60(w) => w.merge_one_field(merge_state, field_info),
+            PointsWriterEnum::SimpleText(w) => w.merge_one_field(merge_state, field_info),
+        }
+    }
+
+    fn merge<D1: Directory, C1: Codec>(&mut self, merge_state: &MergeState<D1, C1>) -> Result<()> {
+        match self {
+            PointsWriterEnum::Lucene60(w) => w.merge(merge_state),
+            PointsWriterEnum::SimpleText(w) => w.merge(merge_state),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self {
+            PointsWriterEnum::Lucene60(w) => w.finish(),
+            PointsWriterEnum::SimpleText(w) => w.finish(),
+        }
+    }
+}
+
 pub fn merge_point_values<D: Directory, C: Codec, P: PointsWriter>(
     writer: &mut P,
     merge_state: &MergeState<D, C>,
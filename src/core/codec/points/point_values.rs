@@ -57,15 +57,20 @@ pub trait PointValues {
     /// to test whether each document is deleted, if necessary.
     fn intersect(&self, field_name: &str, visitor: &mut impl IntersectVisitor) -> Result<()>;
 
-    /// Returns minimum value for each dimension, packed, or null if `size` is 0
+    /// Returns minimum value for each indexed dimension, packed, or null if `size` is 0
     fn min_packed_value(&self, field_name: &str) -> Result<Vec<u8>>;
 
-    /// Returns maximum value for each dimension, packed, or null if `size` is 0
+    /// Returns maximum value for each indexed dimension, packed, or null if `size` is 0
     fn max_packed_value(&self, field_name: &str) -> Result<Vec<u8>>;
 
-    /// Returns how many dimensions were indexed
+    /// Returns how many dimensions are stored for each point
     fn num_dimensions(&self, field_name: &str) -> Result<usize>;
 
+    /// Returns how many of the leading dimensions were indexed
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        self.num_dimensions(field_name)
+    }
+
     /// Returns the number of bytes per dimension
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize>;
 
@@ -96,6 +101,10 @@ impl<T: PointValues + 'static> PointValues for Arc<T> {
         (**self).num_dimensions(field_name)
     }
 
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        (**self).num_index_dimensions(field_name)
+    }
+
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
         (**self).bytes_per_dimension(field_name)
     }
@@ -177,12 +186,12 @@ pub fn point_values_min_packed_value<C: Codec>(
                 if min_value.is_empty() {
                     min_value = leaf_min_value.clone();
                 } else {
-                    let num_dimensions = values.num_dimensions(field)?;
+                    let num_dimensions = values.num_index_dimensions(field)?;
                     let num_bytes_per_dimension = values.bytes_per_dimension(field)?;
                     for i in 0..num_dimensions {
                         let offset: usize = i * num_bytes_per_dimension;
-                        if leaf_min_value[offset..offset + num_dimensions]
-                            < min_value[offset..offset + num_dimensions]
+                        if leaf_min_value[offset..offset + num_bytes_per_dimension]
+                            < min_value[offset..offset + num_bytes_per_dimension]
                         {
                             min_value[offset..offset + num_bytes_per_dimension].copy_from_slice(
                                 &leaf_min_value[offset..offset + num_bytes_per_dimension],
@@ -219,7 +228,7 @@ pub fn point_values_max_packed_value<C: Codec>(
                 if max_value.is_empty() {
                     max_value = leaf_max_value.clone();
                 } else {
-                    let num_dimensions = values.num_dimensions(field)?;
+                    let num_dimensions = values.num_index_dimensions(field)?;
                     let num_bytes_per_dimension = values.bytes_per_dimension(field)?;
                     for i in 0..num_dimensions {
                         let offset: usize = i * num_bytes_per_dimension;
@@ -288,6 +288,10 @@ where
         self.point_values.num_dimensions(field_name)
     }
 
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        self.point_values.num_index_dimensions(field_name)
+    }
+
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
         self.point_values.bytes_per_dimension(field_name)
     }
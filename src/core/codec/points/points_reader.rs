@@ -14,14 +14,17 @@
 use crate::error::{Error, Result};
 
 use crate::core::codec::field_infos::FieldInfos;
-use crate::core::codec::points::Lucene60PointsWriter;
 use crate::core::codec::points::PointsFormat;
 use crate::core::codec::points::{IntersectVisitor, PointValues};
+use crate::core::codec::points::{Lucene60PointsWriter, PointsWriterEnum};
 use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
 use crate::core::codec::{codec_util, Codec};
 use crate::core::store::directory::Directory;
 use crate::core::store::io::DataInput;
-use crate::core::util::bkd::BKDReader;
+use crate::core::util::bkd::{
+    BKDReader, BKD_VERSION_CURRENT, BKD_VERSION_PACKED_INDEX, DEFAULT_MAX_MB_SORT_IN_HEAP,
+    DEFAULT_MAX_POINTS_IN_LEAF_NODE,
+};
 
 use std::any::Any;
 use std::collections::HashMap;
@@ -41,16 +44,88 @@ pub const DATA_VERSION_CURRENT: i32 = DATA_VERSION_START;
 pub const INDEX_VERSION_START: i32 = 0;
 pub const INDEX_VERSION_CURRENT: i32 = INDEX_VERSION_START;
 
-#[derive(Copy, Clone)]
-pub struct Lucene60PointsFormat;
+/// Lucene 6.0 point format, which encodes dimensional values in a block KD-tree
+/// structure for fast 1D range and N dimensional shape intersection filtering.
+///
+/// The leaf size and the heap used to sort the points at flush and merge time
+/// are writer settings only: readers get the leaf size from the BKD trees, so
+/// segments written with any settings can be read back by any instance.
+#[derive(Copy, Clone, Debug)]
+pub struct Lucene60PointsFormat {
+    max_points_in_leaf_node: i32,
+    max_mb_sort_in_heap: f64,
+    bkd_version: i32,
+}
+
+impl Default for Lucene60PointsFormat {
+    fn default() -> Self {
+        Lucene60PointsFormat {
+            max_points_in_leaf_node: DEFAULT_MAX_POINTS_IN_LEAF_NODE,
+            max_mb_sort_in_heap: DEFAULT_MAX_MB_SORT_IN_HEAP as f64,
+            bkd_version: BKD_VERSION_CURRENT,
+        }
+    }
+}
+
+impl Lucene60PointsFormat {
+    /// Creates a format writing at most `max_points_in_leaf_node` points per
+    /// leaf block and sorting up to `max_mb_sort_in_heap` MB of points in heap.
+    ///
+    /// Smaller leaves make queries scan fewer points per matching leaf at the
+    /// cost of a larger index, which is loaded in heap when a segment is
+    /// opened, and less compression of the leaf blocks. The sort buffer must
+    /// hold at least one leaf per indexed dimension.
+    pub fn new(max_points_in_leaf_node: i32, max_mb_sort_in_heap: f64) -> Result<Self> {
+        if max_points_in_leaf_node <= 0 {
+            return Err(Error::IllegalArgument(format!(
+                "max_points_in_leaf_node must be > 0, got {}",
+                max_points_in_leaf_node
+            )));
+        }
+        if max_mb_sort_in_heap < 0.0 {
+            return Err(Error::IllegalArgument(format!(
+                "max_mb_sort_in_heap must be >= 0.0, got {}",
+                max_mb_sort_in_heap
+            )));
+        }
+        Ok(Lucene60PointsFormat {
+            max_points_in_leaf_node,
+            max_mb_sort_in_heap,
+            bkd_version: BKD_VERSION_CURRENT,
+        })
+    }
+
+    /// Creates a format with the default settings writing BKD trees that Java
+    /// Lucene 6.4 and later can read, whose leaves don't store their bounds. All
+    /// dimensions of the points must be indexed.
+    pub fn java_lucene66() -> Self {
+        Lucene60PointsFormat {
+            bkd_version: BKD_VERSION_PACKED_INDEX,
+            ..Self::default()
+        }
+    }
+
+    pub fn max_points_in_leaf_node(&self) -> i32 {
+        self.max_points_in_leaf_node
+    }
+
+    pub fn max_mb_sort_in_heap(&self) -> f64 {
+        self.max_mb_sort_in_heap
+    }
+}
 
 impl PointsFormat for Lucene60PointsFormat {
     type Reader = Lucene60PointsReader;
     fn fields_writer<D: Directory, DW: Directory, C: Codec>(
         &self,
         state: &SegmentWriteState<D, DW, C>,
-    ) -> Result<Lucene60PointsWriter<D, DW, C>> {
-        Lucene60PointsWriter::new(state)
+    ) -> Result<PointsWriterEnum<D, DW, C>> {
+        Ok(PointsWriterEnum::Lucene60(Lucene60PointsWriter::new(
+            state,
+            self.max_points_in_leaf_node,
+            self.max_mb_sort_in_heap,
+            self.bkd_version,
+        )?))
     }
 
     fn fields_reader<'a, D: Directory, DW: Directory, C: Codec>(
@@ -185,7 +260,16 @@ impl PointValues for Lucene60PointsReader {
         // now all docs having this point field were deleted in this segment:
         Ok(self
             .bkd_reader(field_name)?
-            .map(|reader| reader.num_dims)
+            .map(|reader| reader.num_data_dims)
+            .unwrap_or(0usize))
+    }
+
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        // Schema ghost corner case!  This field did index points in the past, but
+        // now all docs having this point field were deleted in this segment:
+        Ok(self
+            .bkd_reader(field_name)?
+            .map(|reader| reader.num_index_dims)
             .unwrap_or(0usize))
     }
 
@@ -26,9 +26,7 @@ use crate::core::codec::{codec_util, Codec};
 use crate::core::index::merge::{LiveDocsDocMap, MergeState};
 use crate::core::store::directory::Directory;
 use crate::core::store::io::DataOutput;
-use crate::core::util::bkd::{
-    BKDWriter, DEFAULT_MAX_MB_SORT_IN_HEAP, DEFAULT_MAX_POINTS_IN_LEAF_NODE,
-};
+use crate::core::util::bkd::BKDWriter;
 use crate::core::util::DocId;
 
 use crate::error::Error;
@@ -45,15 +43,22 @@ pub struct Lucene60PointsWriter<D: Directory, DW: Directory, C: Codec> {
     write_state: SegmentWriteState<D, DW, C>,
     max_points_in_leaf_node: i32,
     max_mb_sort_in_heap: f64,
+    bkd_version: i32,
     finished: bool,
 }
 
 impl<D: Directory, DW: Directory, C: Codec> Lucene60PointsWriter<D, DW, C> {
+    /// Creates a writer building its BKD trees with at most `max_points_in_leaf_node`
+    /// points per leaf block, sorting up to `max_mb_sort_in_heap` MB of points in heap,
+    /// in `bkd_version`.
     pub fn new(
         write_state: &SegmentWriteState<D, DW, C>,
+        max_points_in_leaf_node: i32,
+        max_mb_sort_in_heap: f64,
+        bkd_version: i32,
     ) -> Result<Lucene60PointsWriter<D, DW, C>> {
         let write_state = write_state.clone();
-        debug_assert!(write_state.field_infos.has_doc_values);
+        debug_assert!(write_state.field_infos.has_point_values);
         let data_file_name = segment_file_name(
             &write_state.segment_info.name,
             &write_state.segment_suffix,
@@ -74,8 +79,9 @@ impl<D: Directory, DW: Directory, C: Codec> Lucene60PointsWriter<D, DW, C> {
             data_out,
             index_fps: BTreeMap::new(),
             write_state,
-            max_points_in_leaf_node: DEFAULT_MAX_POINTS_IN_LEAF_NODE,
-            max_mb_sort_in_heap: DEFAULT_MAX_MB_SORT_IN_HEAP as f64,
+            max_points_in_leaf_node,
+            max_mb_sort_in_heap,
+            bkd_version,
             finished: false,
         })
     }
@@ -98,11 +104,13 @@ impl<D: Directory, DW: Directory, C: Codec> PointsWriter for Lucene60PointsWrite
             Arc::clone(&self.write_state.directory),
             &self.write_state.segment_info.name,
             field_info.point_dimension_count as usize,
+            field_info.point_index_dimension_count as usize,
             field_info.point_num_bytes as usize,
             self.max_points_in_leaf_node,
             self.max_mb_sort_in_heap,
             values.size(&field_info.name)?,
             single_value_per_doc,
+            self.bkd_version,
         )?;
 
         match values {
@@ -178,11 +186,13 @@ impl<D: Directory, DW: Directory, C: Codec> PointsWriter for Lucene60PointsWrite
                         Arc::clone(&self.write_state.directory),
                         &self.write_state.segment_info.name,
                         field_info.point_dimension_count as usize,
+                        field_info.point_index_dimension_count as usize,
                         field_info.point_num_bytes as usize,
                         self.max_points_in_leaf_node,
                         self.max_mb_sort_in_heap,
                         total_max_size,
                         single_value_per_doc,
+                        self.bkd_version,
                     )?;
                     let mut bkd_readers = vec![];
                     let mut doc_maps: Vec<&LiveDocsDocMap> = vec![];
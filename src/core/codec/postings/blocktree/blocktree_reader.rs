@@ -16,6 +16,7 @@ use std::collections::btree_map::Keys;
 use std::collections::BTreeMap;
 use std::fmt::Write;
 use std::io::Read;
+use std::mem;
 use std::ops::DerefMut;
 use std::string::ToString;
 use std::sync::Arc;
@@ -25,7 +26,8 @@ use crate::core::codec::postings::blocktree::{
     BlockTermState, SegmentTermsIterFrame, MAX_LONGS_SIZE,
 };
 use crate::core::codec::postings::{
-    FieldsProducer, Lucene50PostingIterator, Lucene50PostingsReader, Lucene50PostingsReaderRef,
+    FieldsProducer, Impacts, Lucene50PostingIterator, Lucene50PostingsReader,
+    Lucene50PostingsReaderRef,
 };
 use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState};
 use crate::core::codec::{codec_util, Codec};
@@ -37,7 +39,7 @@ use crate::core::util::fst::{
     Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, DirectionalBytesReader,
     FSTBytesReader, OutputFactory, FST,
 };
-use crate::core::util::UnsignedShift;
+use crate::core::util::{Accountable, BloomFilter, NamedAccountable, UnsignedShift};
 use crate::error::{
     Error::{CorruptIndex, IllegalState, UnsupportedOperation},
     Result,
@@ -362,6 +364,27 @@ impl BlockTreeTermsReader {
     pub fn keys(&self) -> Keys<String, FieldReaderRef> {
         self.fields.keys()
     }
+
+    /// Attaches a bloom filter of the terms of `field`, `seek_exact` then
+    /// skips the terms dictionary for terms the filter knows are absent.
+    pub(crate) fn set_bloom_filter(&mut self, field: &str, filter: Arc<BloomFilter>) -> Result<()> {
+        match self.fields.get_mut(field) {
+            Some(reader) => match Arc::get_mut(reader) {
+                Some(reader) => {
+                    reader.bloom_filter = Some(filter);
+                    Ok(())
+                }
+                None => Err(IllegalState(format!(
+                    "field reader of {} is already shared",
+                    field
+                ))),
+            },
+            None => Err(CorruptIndex(format!(
+                "bloom filter for unknown field: {}",
+                field
+            ))),
+        }
+    }
 }
 
 impl FieldsProducer for BlockTreeTermsReader {
@@ -372,6 +395,19 @@ impl FieldsProducer for BlockTreeTermsReader {
     }
 }
 
+impl Accountable for BlockTreeTermsReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.fields.values().map(|f| f.ram_bytes_used()).sum()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.fields
+            .iter()
+            .map(|(name, reader)| NamedAccountable::new(format!("field '{}'", name), reader))
+            .collect()
+    }
+}
+
 impl Fields for BlockTreeTermsReader {
     type Terms = FieldReaderRef;
     fn fields(&self) -> Vec<String> {
@@ -406,10 +442,36 @@ pub struct FieldReader {
     terms_in: IndexInputRef,
     postings_reader: Lucene50PostingsReaderRef,
     parent: BlockTreeTermsReader,
+    bloom_filter: Option<Arc<BloomFilter>>,
 }
 
 pub type FieldReaderRef = Arc<FieldReader>;
 
+impl Accountable for FieldReader {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.root_code.capacity()
+            + self.min_term.capacity()
+            + self.max_term.capacity()
+            + self
+                .index
+                .as_ref()
+                .map_or(0, |index| index.ram_bytes_used())
+            + self.bloom_filter.as_ref().map_or(0, |f| f.ram_bytes_used())
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut children = vec![];
+        if let Some(ref index) = self.index {
+            children.push(NamedAccountable::new("term index", index));
+        }
+        if let Some(ref bloom_filter) = self.bloom_filter {
+            children.push(NamedAccountable::new("bloom filter", bloom_filter));
+        }
+        children
+    }
+}
+
 impl FieldReader {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -435,7 +497,9 @@ impl FieldReader {
         let index = if index_in.is_some() {
             let mut clone = index_in.unwrap().clone()?;
             clone.seek(index_start_fp)?;
-            Some(Arc::new(FST::from_input(
+            // the terms index stays in the (memory mapped) index file, so that
+            // opening many fields and segments doesn't fill the heap
+            Some(Arc::new(FST::from_input_off_heap(
                 clone.as_mut(),
                 ByteSequenceOutputFactory {},
             )?))
@@ -458,6 +522,7 @@ impl FieldReader {
             terms_in,
             postings_reader,
             parent,
+            bloom_filter: None,
         })
     }
 
@@ -483,6 +548,10 @@ impl FieldReader {
     pub fn parent(&self) -> &BlockTreeTermsReader {
         &self.parent
     }
+
+    pub fn bloom_filter(&self) -> Option<&BloomFilter> {
+        self.bloom_filter.as_deref()
+    }
 }
 
 impl<'a> Terms for FieldReader {
@@ -910,6 +979,7 @@ impl ToString for Stats {
 /// This implementation skips any auto-prefix terms it encounters.
 pub struct SegmentTermIterator {
     iter: Box<SegmentTermIteratorInner>,
+    bloom_filter: Option<Arc<BloomFilter>>,
 }
 
 impl SegmentTermIterator {
@@ -925,7 +995,8 @@ impl SegmentTermIterator {
             postings_reader,
             field_info,
         ));
-        Self { iter }
+        let bloom_filter = field_reader.bloom_filter.clone();
+        Self { iter, bloom_filter }
     }
 }
 
@@ -940,6 +1011,11 @@ impl TermIterator for SegmentTermIterator {
 
     #[inline]
     fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        if let Some(filter) = &self.bloom_filter {
+            if !filter.may_contain(text) {
+                return Ok(false);
+            }
+        }
         self.iter.seek_exact(text)
     }
 
@@ -988,6 +1064,11 @@ impl TermIterator for SegmentTermIterator {
         self.iter.postings_with_flags(flags)
     }
 
+    #[inline]
+    fn impacts(&mut self) -> Result<Option<Impacts>> {
+        self.iter.impacts()
+    }
+
     #[inline]
     fn term_state(&mut self) -> Result<Self::TermState> {
         self.iter.term_state()
@@ -1806,6 +1887,16 @@ impl TermIterator for SegmentTermIteratorInner {
         )
     }
 
+    fn impacts(&mut self) -> Result<Option<Impacts>> {
+        debug_assert!(!self.eof);
+        self.stack[self.current_frame_ord].decode_metadata()?;
+        let impacts = self.postings_reader.impacts(
+            self.field_info.as_ref(),
+            &self.stack[self.current_frame_ord].state,
+        )?;
+        Ok(Some(impacts))
+    }
+
     fn term_state(&mut self) -> Result<Self::TermState> {
         self.stack[self.current_frame_ord].decode_metadata()?;
         Ok(self.stack[self.current_frame_ord].state.clone())
@@ -12,7 +12,9 @@
 // limitations under the License.
 
 use crate::core::codec::codec_util::{write_footer, write_index_header};
+use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::norms::NormsProducer;
 use crate::core::codec::postings::blocktree::*;
 use crate::core::codec::postings::{
     FieldsConsumer, PostingsWriterBase, DEFAULT_DOC_TERM_FREQ, DEFAULT_SEGMENT_DOC_FREQ,
@@ -26,7 +28,7 @@ use crate::core::store::io::{DataOutput, IndexOutput, RAMOutputStream};
 use crate::core::util::fst::BytesRefFSTIterator;
 use crate::core::util::fst::FstBuilder;
 use crate::core::util::fst::{ByteSequenceOutput, ByteSequenceOutputFactory};
-use crate::core::util::fst::{InputType, FST};
+use crate::core::util::fst::{InputType, FST, FST_VERSION_CURRENT};
 use crate::core::util::DocId;
 use crate::core::util::{to_ints_ref, IntsRefBuilder};
 use crate::core::util::{FixedBitSet, ImmutableBitSet};
@@ -135,6 +137,7 @@ pub struct BlockTreeTermsWriter<T: PostingsWriterBase, O: IndexOutput> {
     scratch_bytes: RAMOutputStream,
     scratch_ints_ref: IntsRefBuilder,
     closed: bool,
+    fst_version: i32,
 }
 
 impl<T: PostingsWriterBase, O: IndexOutput> BlockTreeTermsWriter<T, O> {
@@ -191,9 +194,16 @@ impl<T: PostingsWriterBase, O: IndexOutput> BlockTreeTermsWriter<T, O> {
             scratch_bytes: RAMOutputStream::new(false),
             scratch_ints_ref: IntsRefBuilder::new(),
             closed: false,
+            fst_version: FST_VERSION_CURRENT,
         })
     }
 
+    /// Returns this writer saving the terms index FSTs in `version`.
+    pub(crate) fn with_fst_version(mut self, version: i32) -> Self {
+        self.fst_version = version;
+        self
+    }
+
     fn write_trailer(out: &mut impl IndexOutput, dir_start: i64) -> Result<()> {
         out.write_long(dir_start)
     }
@@ -272,7 +282,7 @@ impl<T: PostingsWriterBase, O: IndexOutput> BlockTreeTermsWriter<T, O> {
 }
 
 impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWriter<T, O> {
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         let mut last_field = String::new();
         for field in fields.fields() {
             debug_assert!(last_field < field);
@@ -280,6 +290,10 @@ impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWri
             if let Some(terms) = fields.terms(&field)? {
                 let mut terms_iter = terms.iterator()?;
                 let field_info = self.field_infos.field_info_by_name(&field).unwrap().clone();
+                let field_norms = match norms {
+                    Some(norms) if field_info.has_norms() => Some(norms.norms(&field_info)?),
+                    _ => None,
+                };
                 let mut terms_writer = TermsWriter::new(field_info, self);
 
                 while let Some(term) = terms_iter.next()? {
@@ -288,6 +302,7 @@ impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWri
                         &mut terms_iter,
                         DEFAULT_SEGMENT_DOC_FREQ,
                         DEFAULT_DOC_TERM_FREQ,
+                        field_norms.as_deref(),
                     )?;
                 }
                 terms_writer.finish()?;
@@ -710,6 +725,7 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
         terms_iter: &mut impl TermIterator,
         doc_freq_limit: i32,
         term_freq_limit: i32,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<()> {
         if let Some(state) = self.block_tree_writer.postings_writer.write_term(
             text,
@@ -717,6 +733,7 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
             &mut self.docs_seen,
             doc_freq_limit,
             term_freq_limit,
+            norms,
         )? {
             assert_ne!(state.doc_freq, 0);
             assert!(
@@ -816,10 +833,10 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
             // write fst to index
             let start_fp = self.block_tree_writer.index_out.file_pointer();
             self.index_start_fp = start_fp;
-            root.index
-                .as_mut()
-                .unwrap()
-                .save(&mut self.block_tree_writer.index_out)?;
+            root.index.as_mut().unwrap().save_with_version(
+                &mut self.block_tree_writer.index_out,
+                self.block_tree_writer.fst_version,
+            )?;
 
             debug_assert!(self.first_pending_term.is_some());
             let min_term = self.first_pending_term.take().unwrap();
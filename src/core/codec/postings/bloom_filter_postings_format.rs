@@ -0,0 +1,233 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::codec_util;
+use crate::core::codec::norms::NormsProducer;
+use crate::core::codec::postings::blocktree::{BlockTreeTermsReader, BlockTreeTermsWriter};
+use crate::core::codec::postings::{
+    FieldsConsumer, FieldsConsumerEnum, Lucene50PostingsFormat, Lucene50PostingsWriter,
+    PostingsFormat,
+};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::{Codec, Fields, PostingIteratorFlags, TermIterator, Terms};
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::util::BloomFilter;
+use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState};
+use crate::Result;
+
+use std::sync::Arc;
+
+/// Extension of the bloom filters file
+pub const BLOOM_EXTENSION: &str = "blm";
+const BLOOM_CODEC_NAME: &str = "BloomFilter";
+
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// The default false positive probability of the bloom filters.
+pub const DEFAULT_BLOOM_FPP: f64 = 0.01;
+
+/// A `PostingsFormat` which keeps a bloom filter of the terms of each field
+/// next to the terms dictionary of its delegate.
+///
+/// `seek_exact` of a term the filter knows is absent returns without any
+/// access to the terms dictionary. This is meant for primary-key fields:
+/// most segments don't contain a given id, which makes the term lookups of
+/// `update_document`, delete-by-term and id queries mostly memory-only.
+///
+/// The filters are kept in memory, about `1.44 * log2(1 / fpp)` bits per
+/// term, so only use this format for fields which are looked up by exact
+/// term.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct BloomFilterPostingsFormat {
+    delegate: Lucene50PostingsFormat,
+    // the bits of the false positive probability, to keep the format `Eq` and `Hash`
+    fpp_bits: u64,
+}
+
+impl Default for BloomFilterPostingsFormat {
+    fn default() -> Self {
+        BloomFilterPostingsFormat {
+            delegate: Lucene50PostingsFormat::default(),
+            fpp_bits: DEFAULT_BLOOM_FPP.to_bits(),
+        }
+    }
+}
+
+impl BloomFilterPostingsFormat {
+    /// Creates a format writing the terms with `delegate`, and bloom filters
+    /// with a false positive probability of about `fpp`.
+    pub fn new(delegate: Lucene50PostingsFormat, fpp: f64) -> Result<BloomFilterPostingsFormat> {
+        if !(fpp > 0.0 && fpp < 1.0) {
+            return Err(IllegalArgument(format!(
+                "bloom filter false positive probability must be in (0, 1), got {}",
+                fpp
+            )));
+        }
+        Ok(BloomFilterPostingsFormat {
+            delegate,
+            fpp_bits: fpp.to_bits(),
+        })
+    }
+
+    pub fn fpp(&self) -> f64 {
+        f64::from_bits(self.fpp_bits)
+    }
+}
+
+impl PostingsFormat for BloomFilterPostingsFormat {
+    type FieldsProducer = BlockTreeTermsReader;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        let bloom_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            BLOOM_EXTENSION,
+        );
+        let mut input = state
+            .directory
+            .open_checksum_input(&bloom_name, state.context)?;
+        codec_util::check_index_header(
+            &mut input,
+            BLOOM_CODEC_NAME,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        let delegate = input.read_string()?;
+        if delegate != self.delegate.name() {
+            return Err(CorruptIndex(format!(
+                "unknown bloom filter delegate postings format: {}",
+                delegate
+            )));
+        }
+        let num_fields = input.read_vint()?;
+        let mut filters = Vec::with_capacity(num_fields.max(0) as usize);
+        for _ in 0..num_fields {
+            let number = input.read_vint()?;
+            let filter = BloomFilter::read(&mut input)?;
+            match state.field_infos.by_number.get(&(number as u32)) {
+                Some(info) => filters.push((info.name.clone(), filter)),
+                None => {
+                    return Err(CorruptIndex(format!(
+                        "invalid bloom filter field number: {}",
+                        number
+                    )));
+                }
+            }
+        }
+        codec_util::check_footer(&mut input)?;
+
+        let mut reader = self.delegate.fields_producer(state)?;
+        for (field, filter) in filters {
+            reader.set_bloom_filter(&field, Arc::new(filter))?;
+        }
+        Ok(reader)
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        Ok(FieldsConsumerEnum::BloomFilter(BloomFilterFieldsWriter {
+            delegate: self.delegate.terms_writer(state)?,
+            delegate_name: self.delegate.name().to_string(),
+            state: state.clone(),
+            fpp: self.fpp(),
+        }))
+    }
+
+    fn name(&self) -> &str {
+        BLOOM_CODEC_NAME
+    }
+}
+
+/// `FieldsConsumer` for `BloomFilterPostingsFormat`
+pub struct BloomFilterFieldsWriter<D: Directory, DW: Directory, C: Codec> {
+    delegate: BlockTreeTermsWriter<Lucene50PostingsWriter<DW::IndexOutput>, DW::IndexOutput>,
+    delegate_name: String,
+    state: SegmentWriteState<D, DW, C>,
+    fpp: f64,
+}
+
+impl<D: Directory, DW: Directory, C: Codec> BloomFilterFieldsWriter<D, DW, C> {
+    fn build_filter(&self, terms: &impl Terms) -> Result<Option<BloomFilter>> {
+        let mut hashes = vec![];
+        let mut terms_iter = terms.iterator()?;
+        while let Some(term) = terms_iter.next()? {
+            // skip terms without docs, like the delegate does, e.g. all
+            // deleted ones on merge
+            let mut postings = terms_iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+            if postings.next()? != NO_MORE_DOCS {
+                hashes.push(BloomFilter::hash(&term));
+            }
+        }
+        if hashes.is_empty() {
+            return Ok(None);
+        }
+        let mut filter = BloomFilter::new(hashes.len(), self.fpp);
+        for hash in hashes {
+            filter.add_hash(hash);
+        }
+        Ok(Some(filter))
+    }
+}
+
+impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for BloomFilterFieldsWriter<D, DW, C> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
+        self.delegate.write(fields, norms)?;
+
+        let mut filters = vec![];
+        for field in fields.fields() {
+            if let Some(terms) = fields.terms(&field)? {
+                if let Some(filter) = self.build_filter(&terms)? {
+                    let number = match self.state.field_infos.field_info_by_name(&field) {
+                        Some(info) => info.number,
+                        None => return Err(IllegalState(format!("unknown field: {}", field))),
+                    };
+                    filters.push((number, filter));
+                }
+            }
+        }
+
+        let bloom_name = segment_file_name(
+            &self.state.segment_info.name,
+            &self.state.segment_suffix,
+            BLOOM_EXTENSION,
+        );
+        let mut out = self
+            .state
+            .directory
+            .create_output(&bloom_name, &self.state.context)?;
+        codec_util::write_index_header(
+            &mut out,
+            BLOOM_CODEC_NAME,
+            VERSION_CURRENT,
+            self.state.segment_info.get_id(),
+            &self.state.segment_suffix,
+        )?;
+        out.write_string(&self.delegate_name)?;
+        out.write_vint(filters.len() as i32)?;
+        for (number, filter) in &filters {
+            out.write_vint(*number as i32)?;
+            filter.write(&mut out)?;
+        }
+        codec_util::write_footer(&mut out)
+    }
+}
@@ -0,0 +1,515 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::codec_util;
+use crate::core::codec::norms::NormsProducer;
+use crate::core::codec::postings::blocktree::{
+    BlockTreeTermsReader, BlockTreeTermsWriter, FieldReaderRef,
+};
+use crate::core::codec::postings::{
+    FieldsConsumer, FieldsConsumerEnum, FieldsProducer, Lucene50PostingsFormat,
+    Lucene50PostingsWriter, PostingsFormat,
+};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::{
+    Codec, Fields, PostingIterator, PostingIteratorFlags, TermIterator, Terms,
+};
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::util::fst::{
+    Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, FSTBytesReader, FstBuilder,
+    InputType, OutputFactory, END_LABEL, FST,
+};
+use crate::core::util::{to_ints_ref, Accountable, DocId, IntsRefBuilder, NamedAccountable};
+use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState};
+use crate::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Extension of the completion dictionaries file
+pub const COMPLETION_EXTENSION: &str = "lkp";
+const COMPLETION_CODEC_NAME: &str = "CompletionDictionary";
+const COMPLETION_POSTINGS_NAME: &str = "Completion50";
+
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// Separates the context from the input in the terms of completion fields.
+pub const CONTEXT_SEPARATOR: u8 = 0x1d;
+
+/// Encodes the suggestion `input` under `context` as a term of a completion
+/// field, an empty `context` is the suggestion without context.
+pub fn completion_term(context: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+    if context.contains(&CONTEXT_SEPARATOR) {
+        return Err(IllegalArgument(format!(
+            "completion context {:?} contains the reserved byte {:#x}",
+            context, CONTEXT_SEPARATOR
+        )));
+    }
+    let mut term = Vec::with_capacity(context.len() + 1 + input.len());
+    term.extend_from_slice(context);
+    term.push(CONTEXT_SEPARATOR);
+    term.extend_from_slice(input);
+    Ok(term)
+}
+
+/// Encodes the weight of a suggestion as the payload of its term.
+pub fn completion_payload(weight: u32) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(5);
+    // writing to a `Vec` can't fail
+    let _ = payload.write_vint(weight as i32);
+    payload
+}
+
+/// A `PostingsFormat` for suggestion fields, it writes the terms with its
+/// delegate and builds an FST of the suggestions of each field next to it.
+///
+/// Every term of a completion field is a suggestion encoded by
+/// `completion_term`, with its weight in the payload of each position, see
+/// `completion_payload`. The FST maps each suggestion and doc to its weight,
+/// so that `CompletionTerms::lookup` finds the completions of a prefix in
+/// memory. Like the postings, the FSTs are rebuilt on merge, and dropped
+/// with the segment.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+pub struct CompletionPostingsFormat {
+    delegate: Lucene50PostingsFormat,
+}
+
+impl CompletionPostingsFormat {
+    pub fn new(delegate: Lucene50PostingsFormat) -> CompletionPostingsFormat {
+        CompletionPostingsFormat { delegate }
+    }
+}
+
+impl PostingsFormat for CompletionPostingsFormat {
+    type FieldsProducer = CompletionFieldsReader;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            COMPLETION_EXTENSION,
+        );
+        let mut input = state.directory.open_checksum_input(&name, state.context)?;
+        codec_util::check_index_header(
+            &mut input,
+            COMPLETION_CODEC_NAME,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        let delegate = input.read_string()?;
+        if delegate != self.delegate.name() {
+            return Err(CorruptIndex(format!(
+                "unknown completion delegate postings format: {}",
+                delegate
+            )));
+        }
+        let num_fields = input.read_vint()?;
+        let mut dictionaries = HashMap::new();
+        for _ in 0..num_fields {
+            let number = input.read_vint()?;
+            let dictionary = FST::from_input(&mut input, ByteSequenceOutputFactory {})?;
+            match state.field_infos.by_number.get(&(number as u32)) {
+                Some(info) => {
+                    dictionaries.insert(info.name.clone(), Arc::new(dictionary));
+                }
+                None => {
+                    return Err(CorruptIndex(format!(
+                        "invalid completion field number: {}",
+                        number
+                    )));
+                }
+            }
+        }
+        codec_util::check_footer(&mut input)?;
+
+        Ok(CompletionFieldsReader {
+            delegate: self.delegate.fields_producer(state)?,
+            dictionaries,
+        })
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        Ok(FieldsConsumerEnum::Completion(CompletionFieldsWriter {
+            delegate: self.delegate.terms_writer(state)?,
+            delegate_name: self.delegate.name().to_string(),
+            state: state.clone(),
+        }))
+    }
+
+    fn name(&self) -> &str {
+        COMPLETION_POSTINGS_NAME
+    }
+}
+
+/// `FieldsConsumer` for `CompletionPostingsFormat`
+pub struct CompletionFieldsWriter<D: Directory, DW: Directory, C: Codec> {
+    delegate: BlockTreeTermsWriter<Lucene50PostingsWriter<DW::IndexOutput>, DW::IndexOutput>,
+    delegate_name: String,
+    state: SegmentWriteState<D, DW, C>,
+}
+
+impl<D: Directory, DW: Directory, C: Codec> CompletionFieldsWriter<D, DW, C> {
+    fn build_dictionary(
+        &self,
+        field: &str,
+        terms: &impl Terms,
+    ) -> Result<Option<FST<ByteSequenceOutputFactory>>> {
+        // the inputs of the FST are the terms followed by the doc, so that
+        // every doc of a suggestion has its own weight
+        let mut entries = vec![];
+        let mut terms_iter = terms.iterator()?;
+        while let Some(term) = terms_iter.next()? {
+            if !term.contains(&CONTEXT_SEPARATOR) {
+                return Err(IllegalArgument(format!(
+                    "term {:?} of completion field '{}' isn't a completion term",
+                    term, field
+                )));
+            }
+            let mut postings = terms_iter.postings_with_flags(PostingIteratorFlags::PAYLOADS)?;
+            loop {
+                let doc = postings.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                let mut weight = 0;
+                if terms.has_positions()? {
+                    for _ in 0..postings.freq()? {
+                        postings.next_position()?;
+                        let payload = postings.payload()?;
+                        if !payload.is_empty() {
+                            weight = weight.max(payload.as_slice().read_vint()? as u32);
+                        }
+                    }
+                }
+                let mut input = term.clone();
+                input.extend_from_slice(&(doc as u32).to_be_bytes());
+                entries.push((input, weight));
+            }
+        }
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory {});
+        builder.init();
+        for (input, weight) in entries {
+            builder.add(
+                to_ints_ref(&input, &mut scratch_ints_ref),
+                ByteSequenceOutput::new(completion_payload(weight)),
+            )?;
+        }
+        builder.finish()
+    }
+}
+
+impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for CompletionFieldsWriter<D, DW, C> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
+        self.delegate.write(fields, norms)?;
+
+        let mut dictionaries = vec![];
+        for field in fields.fields() {
+            if let Some(terms) = fields.terms(&field)? {
+                if let Some(dictionary) = self.build_dictionary(&field, &terms)? {
+                    let number = match self.state.field_infos.field_info_by_name(&field) {
+                        Some(info) => info.number,
+                        None => return Err(IllegalState(format!("unknown field: {}", field))),
+                    };
+                    dictionaries.push((number, dictionary));
+                }
+            }
+        }
+
+        let name = segment_file_name(
+            &self.state.segment_info.name,
+            &self.state.segment_suffix,
+            COMPLETION_EXTENSION,
+        );
+        let mut out = self
+            .state
+            .directory
+            .create_output(&name, &self.state.context)?;
+        codec_util::write_index_header(
+            &mut out,
+            COMPLETION_CODEC_NAME,
+            VERSION_CURRENT,
+            self.state.segment_info.get_id(),
+            &self.state.segment_suffix,
+        )?;
+        out.write_string(&self.delegate_name)?;
+        out.write_vint(dictionaries.len() as i32)?;
+        for (number, dictionary) in &dictionaries {
+            out.write_vint(*number as i32)?;
+            dictionary.save(&mut out)?;
+        }
+        codec_util::write_footer(&mut out)
+    }
+}
+
+/// `FieldsProducer` for `CompletionPostingsFormat`
+pub struct CompletionFieldsReader {
+    delegate: BlockTreeTermsReader,
+    dictionaries: HashMap<String, Arc<FST<ByteSequenceOutputFactory>>>,
+}
+
+impl Accountable for CompletionFieldsReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.delegate.ram_bytes_used()
+            + self
+                .dictionaries
+                .values()
+                .map(|fst| fst.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut children = vec![NamedAccountable::new("delegate", &self.delegate)];
+        let mut dictionaries: Vec<_> = self
+            .dictionaries
+            .iter()
+            .map(|(name, fst)| NamedAccountable::new(format!("completions '{}'", name), fst))
+            .collect();
+        dictionaries.sort_by(|a, b| a.name.cmp(&b.name));
+        children.extend(dictionaries);
+        children
+    }
+}
+
+impl FieldsProducer for CompletionFieldsReader {
+    fn check_integrity(&self) -> Result<()> {
+        self.delegate.check_integrity()
+    }
+}
+
+impl Fields for CompletionFieldsReader {
+    type Terms = CompletionTerms;
+
+    fn fields(&self) -> Vec<String> {
+        self.delegate.fields()
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        Ok(self.delegate.terms(field)?.map(|terms| CompletionTerms {
+            terms,
+            dictionary: self.dictionaries.get(field).map(Arc::clone),
+        }))
+    }
+
+    fn size(&self) -> usize {
+        self.delegate.size()
+    }
+}
+
+/// A completion of `CompletionTerms::lookup`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Completion {
+    pub input: Vec<u8>,
+    pub context: Vec<u8>,
+    pub weight: u32,
+    pub doc: DocId,
+}
+
+/// The terms of a field of `CompletionFieldsReader`, with the FST of its
+/// suggestions.
+pub struct CompletionTerms {
+    terms: FieldReaderRef,
+    dictionary: Option<Arc<FST<ByteSequenceOutputFactory>>>,
+}
+
+impl CompletionTerms {
+    /// Returns the `num` completions of `prefix` with the highest weights,
+    /// in any of `contexts`, or in any context when `contexts` is empty.
+    ///
+    /// The docs are the ones of this segment, deleted ones included.
+    pub fn lookup(&self, prefix: &[u8], contexts: &[&[u8]], num: usize) -> Result<Vec<Completion>> {
+        let dictionary = match self.dictionary {
+            Some(ref dictionary) => dictionary,
+            None => return Ok(vec![]),
+        };
+        let targets = contexts
+            .iter()
+            .map(|context| completion_term(context, prefix))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut completions = vec![];
+        let mut collector = CompletionCollector {
+            dictionary,
+            reader: dictionary.bytes_reader(),
+            prefix,
+            targets: &targets,
+            path: vec![],
+            completions: &mut completions,
+        };
+        collector.collect(&dictionary.root_arc(), &dictionary.outputs().empty())?;
+
+        completions.sort_by(|a, b| {
+            b.weight
+                .cmp(&a.weight)
+                .then_with(|| a.input.cmp(&b.input))
+                .then_with(|| a.context.cmp(&b.context))
+                .then_with(|| a.doc.cmp(&b.doc))
+        });
+        completions.truncate(num);
+        Ok(completions)
+    }
+}
+
+// walks the FST depth first, skipping the arcs which can't lead to a target
+struct CompletionCollector<'a> {
+    dictionary: &'a FST<ByteSequenceOutputFactory>,
+    reader: FSTBytesReader,
+    prefix: &'a [u8],
+    targets: &'a [Vec<u8>],
+    path: Vec<u8>,
+    completions: &'a mut Vec<Completion>,
+}
+
+impl<'a> CompletionCollector<'a> {
+    fn collect(
+        &mut self,
+        follow: &FSTArc<ByteSequenceOutput>,
+        output: &ByteSequenceOutput,
+    ) -> Result<()> {
+        let mut arc = self
+            .dictionary
+            .read_first_target_arc(follow, &mut self.reader)?;
+        loop {
+            let arc_output = match arc.output {
+                Some(ref o) => self.dictionary.outputs().add(output, o),
+                None => output.clone(),
+            };
+            if arc.label == END_LABEL {
+                self.add(&arc_output)?;
+            } else {
+                self.path.push(arc.label as u8);
+                if self.accepts_prefix() {
+                    self.collect(&arc, &arc_output)?;
+                }
+                self.path.pop();
+            }
+            if arc.is_last() {
+                return Ok(());
+            }
+            self.dictionary.read_next_arc(&mut arc, &mut self.reader)?;
+        }
+    }
+
+    // whether the path is a prefix of a match, or starts with one
+    fn accepts_prefix(&self) -> bool {
+        fn compatible(path: &[u8], target: &[u8]) -> bool {
+            let len = path.len().min(target.len());
+            path[..len] == target[..len]
+        }
+        if self.targets.is_empty() {
+            match self.path.iter().position(|b| *b == CONTEXT_SEPARATOR) {
+                Some(sep) => compatible(&self.path[sep + 1..], self.prefix),
+                None => true,
+            }
+        } else {
+            self.targets.iter().any(|t| compatible(&self.path, t))
+        }
+    }
+
+    fn add(&mut self, output: &ByteSequenceOutput) -> Result<()> {
+        if self.path.len() < 4 {
+            return Err(CorruptIndex("invalid completion dictionary".into()));
+        }
+        let term_len = self.path.len() - 4;
+        let term = &self.path[..term_len];
+        let sep = match term.iter().position(|b| *b == CONTEXT_SEPARATOR) {
+            Some(sep) => sep,
+            None => return Err(CorruptIndex("invalid completion dictionary".into())),
+        };
+        let input = &term[sep + 1..];
+        let matches = if self.targets.is_empty() {
+            input.starts_with(self.prefix)
+        } else {
+            self.targets.iter().any(|t| term.starts_with(t))
+        };
+        if matches {
+            let mut doc = [0u8; 4];
+            doc.copy_from_slice(&self.path[term_len..]);
+            self.completions.push(Completion {
+                input: input.to_vec(),
+                context: term[..sep].to_vec(),
+                weight: output.inner().read_vint()? as u32,
+                doc: u32::from_be_bytes(doc) as DocId,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Terms for CompletionTerms {
+    type Iterator = <FieldReaderRef as Terms>::Iterator;
+
+    fn iterator(&self) -> Result<Self::Iterator> {
+        self.terms.iterator()
+    }
+
+    fn size(&self) -> Result<i64> {
+        self.terms.size()
+    }
+
+    fn sum_total_term_freq(&self) -> Result<i64> {
+        self.terms.sum_total_term_freq()
+    }
+
+    fn sum_doc_freq(&self) -> Result<i64> {
+        self.terms.sum_doc_freq()
+    }
+
+    fn doc_count(&self) -> Result<i32> {
+        self.terms.doc_count()
+    }
+
+    fn has_freqs(&self) -> Result<bool> {
+        self.terms.has_freqs()
+    }
+
+    fn has_offsets(&self) -> Result<bool> {
+        self.terms.has_offsets()
+    }
+
+    fn has_positions(&self) -> Result<bool> {
+        self.terms.has_positions()
+    }
+
+    fn has_payloads(&self) -> Result<bool> {
+        self.terms.has_payloads()
+    }
+
+    fn min(&self) -> Result<Option<Vec<u8>>> {
+        self.terms.min()
+    }
+
+    fn max(&self) -> Result<Option<Vec<u8>>> {
+        self.terms.max()
+    }
+
+    fn stats(&self) -> Result<String> {
+        self.terms.stats()
+    }
+}
@@ -0,0 +1,601 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::postings::{
+    FieldsConsumerEnum, FieldsProducer, Lucene50PostingsFormat, PostingsFormat,
+};
+use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::{
+    Codec, Fields, OrdTermState, PostingIterator, PostingIteratorFlags, SeekStatus, TermIterator,
+    Terms,
+};
+use crate::core::search::{DocIterator, Payload, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::util::{Accountable, DocId, NamedAccountable};
+use crate::error::Error::IllegalState;
+use crate::Result;
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::Arc;
+
+const DIRECT_NAME: &str = "Direct";
+
+/// A `PostingsFormat` which writes the terms with its delegate, but loads
+/// them with all their postings into flat in-memory arrays when a segment is
+/// opened.
+///
+/// Nothing is decoded at search time, which makes this the fastest format
+/// for small and hot fields, e.g. filters or enumerations, at the price of
+/// RAM: every doc id, frequency, position and offset takes four bytes.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+pub struct DirectPostingsFormat {
+    delegate: Lucene50PostingsFormat,
+}
+
+impl DirectPostingsFormat {
+    pub fn new(delegate: Lucene50PostingsFormat) -> DirectPostingsFormat {
+        DirectPostingsFormat { delegate }
+    }
+}
+
+impl PostingsFormat for DirectPostingsFormat {
+    type FieldsProducer = DirectFieldsReader;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        let delegate = self.delegate.fields_producer(state)?;
+        DirectFieldsReader::new(&delegate)
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        self.delegate.fields_consumer(state)
+    }
+
+    fn name(&self) -> &str {
+        DIRECT_NAME
+    }
+}
+
+/// `FieldsProducer` for `DirectPostingsFormat`, holding all the terms and
+/// postings of a segment in memory.
+pub struct DirectFieldsReader {
+    fields: BTreeMap<String, Arc<DirectTerms>>,
+}
+
+impl DirectFieldsReader {
+    pub(crate) fn with_terms(fields: BTreeMap<String, Arc<DirectTerms>>) -> DirectFieldsReader {
+        DirectFieldsReader { fields }
+    }
+
+    fn new<F: Fields>(delegate: &F) -> Result<DirectFieldsReader> {
+        let mut fields = BTreeMap::new();
+        for field in delegate.fields() {
+            if let Some(terms) = delegate.terms(&field)? {
+                fields.insert(field, Arc::new(DirectTerms::load(&terms)?));
+            }
+        }
+        Ok(DirectFieldsReader { fields })
+    }
+}
+
+impl Accountable for DirectFieldsReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.fields.values().map(|t| t.ram_bytes_used()).sum()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.fields
+            .iter()
+            .map(|(name, terms)| NamedAccountable::new(format!("field '{}'", name), terms))
+            .collect()
+    }
+}
+
+impl FieldsProducer for DirectFieldsReader {
+    fn check_integrity(&self) -> Result<()> {
+        // everything was read and verified on open
+        Ok(())
+    }
+}
+
+impl Fields for DirectFieldsReader {
+    type Terms = Arc<DirectTerms>;
+
+    fn fields(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        Ok(self.fields.get(field).map(Arc::clone))
+    }
+
+    fn size(&self) -> usize {
+        self.fields.len()
+    }
+}
+
+/// The terms of a field of `DirectFieldsReader`.
+///
+/// terms, and per term postings, are stored back to back in flat arrays,
+/// `term_starts` and `doc_starts` hold the start of each term (with a
+/// trailing end), `position_starts` the start of the positions of each
+/// posting and `payload_starts` the start of the payload of each position.
+pub struct DirectTerms {
+    has_freqs: bool,
+    has_positions: bool,
+    has_offsets: bool,
+    has_payloads: bool,
+    sum_total_term_freq: i64,
+    sum_doc_freq: i64,
+    doc_count: i32,
+    term_bytes: Vec<u8>,
+    term_starts: Vec<usize>,
+    total_term_freqs: Vec<i64>,
+    doc_starts: Vec<usize>,
+    docs: Vec<DocId>,
+    freqs: Vec<i32>,
+    position_starts: Vec<usize>,
+    positions: Vec<i32>,
+    start_offsets: Vec<i32>,
+    end_offsets: Vec<i32>,
+    payload_starts: Vec<usize>,
+    payload_bytes: Vec<u8>,
+}
+
+impl DirectTerms {
+    pub(crate) fn new(
+        has_freqs: bool,
+        has_positions: bool,
+        has_offsets: bool,
+        has_payloads: bool,
+    ) -> DirectTerms {
+        DirectTerms {
+            has_freqs,
+            has_positions,
+            has_offsets,
+            has_payloads,
+            sum_total_term_freq: if has_freqs { 0 } else { -1 },
+            sum_doc_freq: 0,
+            doc_count: 0,
+            term_bytes: vec![],
+            term_starts: vec![0],
+            total_term_freqs: vec![],
+            doc_starts: vec![0],
+            docs: vec![],
+            freqs: vec![],
+            position_starts: vec![0],
+            positions: vec![],
+            start_offsets: vec![],
+            end_offsets: vec![],
+            payload_starts: vec![0],
+            payload_bytes: vec![],
+        }
+    }
+
+    fn load<T: Terms>(terms: &T) -> Result<DirectTerms> {
+        let mut direct = DirectTerms::new(
+            terms.has_freqs()?,
+            terms.has_positions()?,
+            terms.has_offsets()?,
+            terms.has_payloads()?,
+        );
+        let flags = if direct.has_offsets && direct.has_payloads {
+            PostingIteratorFlags::ALL
+        } else if direct.has_offsets {
+            PostingIteratorFlags::OFFSETS
+        } else if direct.has_payloads {
+            PostingIteratorFlags::PAYLOADS
+        } else if direct.has_positions {
+            PostingIteratorFlags::POSITIONS
+        } else if direct.has_freqs {
+            PostingIteratorFlags::FREQS
+        } else {
+            PostingIteratorFlags::NONE
+        };
+
+        let mut iter = terms.iterator()?;
+        while let Some(term) = iter.next()? {
+            direct.start_term(&term);
+            let mut postings = iter.postings_with_flags(flags)?;
+            loop {
+                let doc = postings.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                let freq = if direct.has_freqs {
+                    postings.freq()?
+                } else {
+                    1
+                };
+                direct.start_doc(doc, freq);
+                if direct.has_positions {
+                    for _ in 0..freq {
+                        let position = postings.next_position()?;
+                        let (start_offset, end_offset) = if direct.has_offsets {
+                            (postings.start_offset()?, postings.end_offset()?)
+                        } else {
+                            (-1, -1)
+                        };
+                        let payload = if direct.has_payloads {
+                            postings.payload()?
+                        } else {
+                            vec![]
+                        };
+                        direct.add_position(position, start_offset, end_offset, &payload);
+                    }
+                }
+                direct.finish_doc();
+            }
+            direct.finish_term(iter.total_term_freq()?);
+        }
+        direct.finish(
+            terms.sum_total_term_freq()?,
+            terms.sum_doc_freq()?,
+            terms.doc_count()?,
+        );
+        Ok(direct)
+    }
+
+    /// Starts the next term, terms must be added in order.
+    pub(crate) fn start_term(&mut self, term: &[u8]) {
+        self.term_bytes.extend_from_slice(term);
+        self.term_starts.push(self.term_bytes.len());
+    }
+
+    /// Adds a doc to the postings of the current term, `freq` is ignored
+    /// without freqs.
+    pub(crate) fn start_doc(&mut self, doc: DocId, freq: i32) {
+        self.docs.push(doc);
+        if self.has_freqs {
+            self.freqs.push(freq);
+        }
+    }
+
+    /// Adds the next position of the current doc, the offsets and payload
+    /// are ignored if the field has none.
+    pub(crate) fn add_position(
+        &mut self,
+        position: i32,
+        start_offset: i32,
+        end_offset: i32,
+        payload: &[u8],
+    ) {
+        self.positions.push(position);
+        if self.has_offsets {
+            self.start_offsets.push(start_offset);
+            self.end_offsets.push(end_offset);
+        }
+        if self.has_payloads {
+            self.payload_bytes.extend_from_slice(payload);
+            self.payload_starts.push(self.payload_bytes.len());
+        }
+    }
+
+    pub(crate) fn finish_doc(&mut self) {
+        if self.has_positions {
+            self.position_starts.push(self.positions.len());
+        }
+    }
+
+    pub(crate) fn finish_term(&mut self, total_term_freq: i64) {
+        self.total_term_freqs.push(total_term_freq);
+        self.doc_starts.push(self.docs.len());
+    }
+
+    /// Sets the statistics of the field once all terms are added.
+    pub(crate) fn finish(&mut self, sum_total_term_freq: i64, sum_doc_freq: i64, doc_count: i32) {
+        self.sum_total_term_freq = sum_total_term_freq;
+        self.sum_doc_freq = sum_doc_freq;
+        self.doc_count = doc_count;
+    }
+
+    fn num_terms(&self) -> usize {
+        self.total_term_freqs.len()
+    }
+
+    fn term(&self, ord: usize) -> &[u8] {
+        &self.term_bytes[self.term_starts[ord]..self.term_starts[ord + 1]]
+    }
+
+    /// Binary searches the ord of `text`, or the ord it would be inserted at.
+    fn search(&self, text: &[u8]) -> std::result::Result<usize, usize> {
+        let (mut low, mut high) = (0, self.num_terms());
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.term(mid).cmp(text) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+}
+
+impl Accountable for DirectTerms {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.term_bytes.capacity()
+            + self.payload_bytes.capacity()
+            + (self.term_starts.capacity()
+                + self.doc_starts.capacity()
+                + self.position_starts.capacity()
+                + self.payload_starts.capacity())
+                * mem::size_of::<usize>()
+            + self.total_term_freqs.capacity() * mem::size_of::<i64>()
+            + (self.docs.capacity()
+                + self.freqs.capacity()
+                + self.positions.capacity()
+                + self.start_offsets.capacity()
+                + self.end_offsets.capacity())
+                * mem::size_of::<i32>()
+    }
+}
+
+impl Terms for Arc<DirectTerms> {
+    type Iterator = DirectTermIterator;
+
+    fn iterator(&self) -> Result<Self::Iterator> {
+        Ok(DirectTermIterator {
+            terms: Arc::clone(self),
+            ord: -1,
+        })
+    }
+
+    fn size(&self) -> Result<i64> {
+        Ok(self.num_terms() as i64)
+    }
+
+    fn sum_total_term_freq(&self) -> Result<i64> {
+        Ok(self.sum_total_term_freq)
+    }
+
+    fn sum_doc_freq(&self) -> Result<i64> {
+        Ok(self.sum_doc_freq)
+    }
+
+    fn doc_count(&self) -> Result<i32> {
+        Ok(self.doc_count)
+    }
+
+    fn has_freqs(&self) -> Result<bool> {
+        Ok(self.has_freqs)
+    }
+
+    fn has_offsets(&self) -> Result<bool> {
+        Ok(self.has_offsets)
+    }
+
+    fn has_positions(&self) -> Result<bool> {
+        Ok(self.has_positions)
+    }
+
+    fn has_payloads(&self) -> Result<bool> {
+        Ok(self.has_payloads)
+    }
+
+    fn min(&self) -> Result<Option<Vec<u8>>> {
+        Ok(if self.num_terms() > 0 {
+            Some(self.term(0).to_vec())
+        } else {
+            None
+        })
+    }
+
+    fn max(&self) -> Result<Option<Vec<u8>>> {
+        Ok(if self.num_terms() > 0 {
+            Some(self.term(self.num_terms() - 1).to_vec())
+        } else {
+            None
+        })
+    }
+}
+
+/// `TermIterator` of `DirectTerms`, positioned by term ord.
+pub struct DirectTermIterator {
+    terms: Arc<DirectTerms>,
+    ord: i64,
+}
+
+impl DirectTermIterator {
+    fn current(&self) -> Result<usize> {
+        if self.ord < 0 || self.ord as usize >= self.terms.num_terms() {
+            return Err(IllegalState("DirectTermIterator is unpositioned".into()));
+        }
+        Ok(self.ord as usize)
+    }
+}
+
+impl TermIterator for DirectTermIterator {
+    type Postings = DirectPostingIterator;
+    type TermState = OrdTermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        let num_terms = self.terms.num_terms() as i64;
+        if self.ord < num_terms {
+            self.ord += 1;
+        }
+        Ok(if self.ord < num_terms {
+            Some(self.terms.term(self.ord as usize).to_vec())
+        } else {
+            None
+        })
+    }
+
+    fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        match self.terms.search(text) {
+            Ok(ord) => {
+                self.ord = ord as i64;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        let (ord, status) = match self.terms.search(text) {
+            Ok(ord) => (ord, SeekStatus::Found),
+            Err(ord) if ord < self.terms.num_terms() => (ord, SeekStatus::NotFound),
+            Err(ord) => (ord, SeekStatus::End),
+        };
+        self.ord = ord as i64;
+        Ok(status)
+    }
+
+    fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+        debug_assert!(ord >= 0 && (ord as usize) < self.terms.num_terms());
+        self.ord = ord;
+        Ok(())
+    }
+
+    fn seek_exact_state(&mut self, _text: &[u8], state: &Self::TermState) -> Result<()> {
+        self.seek_exact_ord(state.ord)
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        Ok(self.terms.term(self.current()?))
+    }
+
+    fn ord(&self) -> Result<i64> {
+        Ok(self.ord)
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        let ord = self.current()?;
+        Ok((self.terms.doc_starts[ord + 1] - self.terms.doc_starts[ord]) as i32)
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        Ok(self.terms.total_term_freqs[self.current()?])
+    }
+
+    fn postings_with_flags(&mut self, _flags: u16) -> Result<Self::Postings> {
+        let ord = self.current()?;
+        let start = self.terms.doc_starts[ord];
+        Ok(DirectPostingIterator {
+            terms: Arc::clone(&self.terms),
+            start,
+            upto: start,
+            next_upto: start,
+            end: self.terms.doc_starts[ord + 1],
+            doc: -1,
+            position_upto: 0,
+        })
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        Ok(OrdTermState {
+            ord: self.current()? as i64,
+        })
+    }
+}
+
+/// `PostingIterator` over the in-memory postings of a term of `DirectTerms`.
+pub struct DirectPostingIterator {
+    terms: Arc<DirectTerms>,
+    start: usize,
+    // index of the current posting
+    upto: usize,
+    next_upto: usize,
+    end: usize,
+    doc: DocId,
+    // index of the next position of the current posting
+    position_upto: usize,
+}
+
+impl DirectPostingIterator {
+    fn move_to(&mut self, upto: usize) -> DocId {
+        if upto >= self.end {
+            self.next_upto = self.end;
+            self.doc = NO_MORE_DOCS;
+        } else {
+            self.upto = upto;
+            self.next_upto = upto + 1;
+            self.doc = self.terms.docs[upto];
+            if self.terms.has_positions {
+                self.position_upto = self.terms.position_starts[upto];
+            }
+        }
+        self.doc
+    }
+}
+
+impl DocIterator for DirectPostingIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        Ok(self.move_to(self.next_upto))
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let docs = &self.terms.docs[self.next_upto..self.end];
+        let upto = self.next_upto + docs.partition_point(|d| *d < target);
+        Ok(self.move_to(upto))
+    }
+
+    fn cost(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl PostingIterator for DirectPostingIterator {
+    fn freq(&self) -> Result<i32> {
+        Ok(if self.terms.has_freqs {
+            self.terms.freqs[self.upto]
+        } else {
+            1
+        })
+    }
+
+    fn next_position(&mut self) -> Result<i32> {
+        if !self.terms.has_positions {
+            return Ok(-1);
+        }
+        let position = self.terms.positions[self.position_upto];
+        self.position_upto += 1;
+        Ok(position)
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        Ok(if self.terms.has_offsets && self.position_upto > 0 {
+            self.terms.start_offsets[self.position_upto - 1]
+        } else {
+            -1
+        })
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        Ok(if self.terms.has_offsets && self.position_upto > 0 {
+            self.terms.end_offsets[self.position_upto - 1]
+        } else {
+            -1
+        })
+    }
+
+    fn payload(&self) -> Result<Payload> {
+        if !self.terms.has_payloads || self.position_upto == 0 {
+            return Ok(Payload::new());
+        }
+        let start = self.terms.payload_starts[self.position_upto - 1];
+        let end = self.terms.payload_starts[self.position_upto];
+        Ok(self.terms.payload_bytes[start..end].to_vec())
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::postings::blocktree::{
+    BlockTermState, FieldReaderRef, SegmentTermIterator,
+};
+use crate::core::codec::postings::{
+    CompletionTerms, DirectPostingIterator, DirectTermIterator, DirectTerms, FSTFieldReader,
+    FSTTermIterator, Impacts, Lucene50PostingIterator,
+};
+use crate::core::codec::{
+    OrdTermState, PostingIterator, SeekStatus, TermIterator, TermState, Terms,
+};
+use crate::core::search::{DocIterator, Payload};
+use crate::core::util::DocId;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::sync::Arc;
+
+/// `Terms` impl for `FieldsProducerEnum`
+pub enum FieldTermsEnum {
+    Lucene50(FieldReaderRef),
+    Direct(Arc<DirectTerms>),
+    FST(Arc<FSTFieldReader>),
+    Completion(CompletionTerms),
+}
+
+impl Terms for FieldTermsEnum {
+    type Iterator = FieldTermIterEnum;
+
+    fn iterator(&self) -> Result<Self::Iterator> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => Ok(FieldTermIterEnum::Lucene50(i.iterator()?)),
+            FieldTermsEnum::Direct(i) => Ok(FieldTermIterEnum::Direct(i.iterator()?)),
+            FieldTermsEnum::FST(i) => Ok(FieldTermIterEnum::FST(i.iterator()?)),
+            FieldTermsEnum::Completion(i) => Ok(FieldTermIterEnum::Lucene50(i.iterator()?)),
+        }
+    }
+
+    fn size(&self) -> Result<i64> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.size(),
+            FieldTermsEnum::Direct(i) => i.size(),
+            FieldTermsEnum::FST(i) => i.size(),
+            FieldTermsEnum::Completion(i) => i.size(),
+        }
+    }
+
+    fn sum_total_term_freq(&self) -> Result<i64> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.sum_total_term_freq(),
+            FieldTermsEnum::Direct(i) => i.sum_total_term_freq(),
+            FieldTermsEnum::FST(i) => i.sum_total_term_freq(),
+            FieldTermsEnum::Completion(i) => i.sum_total_term_freq(),
+        }
+    }
+
+    fn sum_doc_freq(&self) -> Result<i64> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.sum_doc_freq(),
+            FieldTermsEnum::Direct(i) => i.sum_doc_freq(),
+            FieldTermsEnum::FST(i) => i.sum_doc_freq(),
+            FieldTermsEnum::Completion(i) => i.sum_doc_freq(),
+        }
+    }
+
+    fn doc_count(&self) -> Result<i32> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.doc_count(),
+            FieldTermsEnum::Direct(i) => i.doc_count(),
+            FieldTermsEnum::FST(i) => i.doc_count(),
+            FieldTermsEnum::Completion(i) => i.doc_count(),
+        }
+    }
+
+    fn has_freqs(&self) -> Result<bool> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.has_freqs(),
+            FieldTermsEnum::Direct(i) => i.has_freqs(),
+            FieldTermsEnum::FST(i) => i.has_freqs(),
+            FieldTermsEnum::Completion(i) => i.has_freqs(),
+        }
+    }
+
+    fn has_offsets(&self) -> Result<bool> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.has_offsets(),
+            FieldTermsEnum::Direct(i) => i.has_offsets(),
+            FieldTermsEnum::FST(i) => i.has_offsets(),
+            FieldTermsEnum::Completion(i) => i.has_offsets(),
+        }
+    }
+
+    fn has_positions(&self) -> Result<bool> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.has_positions(),
+            FieldTermsEnum::Direct(i) => i.has_positions(),
+            FieldTermsEnum::FST(i) => i.has_positions(),
+            FieldTermsEnum::Completion(i) => i.has_positions(),
+        }
+    }
+
+    fn has_payloads(&self) -> Result<bool> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.has_payloads(),
+            FieldTermsEnum::Direct(i) => i.has_payloads(),
+            FieldTermsEnum::FST(i) => i.has_payloads(),
+            FieldTermsEnum::Completion(i) => i.has_payloads(),
+        }
+    }
+
+    fn min(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.min(),
+            FieldTermsEnum::Direct(i) => i.min(),
+            FieldTermsEnum::FST(i) => i.min(),
+            FieldTermsEnum::Completion(i) => i.min(),
+        }
+    }
+
+    fn max(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.max(),
+            FieldTermsEnum::Direct(i) => i.max(),
+            FieldTermsEnum::FST(i) => i.max(),
+            FieldTermsEnum::Completion(i) => i.max(),
+        }
+    }
+
+    fn stats(&self) -> Result<String> {
+        match self {
+            FieldTermsEnum::Lucene50(i) => i.stats(),
+            FieldTermsEnum::Direct(i) => i.stats(),
+            FieldTermsEnum::FST(i) => i.stats(),
+            FieldTermsEnum::Completion(i) => i.stats(),
+        }
+    }
+}
+
+/// `TermIterator` impl for `FieldTermsEnum`
+pub enum FieldTermIterEnum {
+    Lucene50(SegmentTermIterator),
+    Direct(DirectTermIterator),
+    FST(FSTTermIterator),
+}
+
+impl TermIterator for FieldTermIterEnum {
+    type Postings = FieldPostingIterEnum;
+    type TermState = FieldTermStateEnum;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.next(),
+            FieldTermIterEnum::Direct(i) => i.next(),
+            FieldTermIterEnum::FST(i) => i.next(),
+        }
+    }
+
+    fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.seek_exact(text),
+            FieldTermIterEnum::Direct(i) => i.seek_exact(text),
+            FieldTermIterEnum::FST(i) => i.seek_exact(text),
+        }
+    }
+
+    fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.seek_ceil(text),
+            FieldTermIterEnum::Direct(i) => i.seek_ceil(text),
+            FieldTermIterEnum::FST(i) => i.seek_ceil(text),
+        }
+    }
+
+    fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.seek_exact_ord(ord),
+            FieldTermIterEnum::Direct(i) => i.seek_exact_ord(ord),
+            FieldTermIterEnum::FST(i) => i.seek_exact_ord(ord),
+        }
+    }
+
+    fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
+        match (self, state) {
+            (FieldTermIterEnum::Lucene50(i), FieldTermStateEnum::Lucene50(s)) => {
+                i.seek_exact_state(text, s)
+            }
+            (FieldTermIterEnum::Direct(i), FieldTermStateEnum::Direct(s)) => {
+                i.seek_exact_state(text, s)
+            }
+            (FieldTermIterEnum::FST(i), FieldTermStateEnum::Lucene50(s)) => {
+                i.seek_exact_state(text, s)
+            }
+            _ => Err(IllegalArgument(
+                "term state of another postings format".into(),
+            )),
+        }
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.term(),
+            FieldTermIterEnum::Direct(i) => i.term(),
+            FieldTermIterEnum::FST(i) => i.term(),
+        }
+    }
+
+    fn ord(&self) -> Result<i64> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.ord(),
+            FieldTermIterEnum::Direct(i) => i.ord(),
+            FieldTermIterEnum::FST(i) => i.ord(),
+        }
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.doc_freq(),
+            FieldTermIterEnum::Direct(i) => i.doc_freq(),
+            FieldTermIterEnum::FST(i) => i.doc_freq(),
+        }
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.total_term_freq(),
+            FieldTermIterEnum::Direct(i) => i.total_term_freq(),
+            FieldTermIterEnum::FST(i) => i.total_term_freq(),
+        }
+    }
+
+    fn postings(&mut self) -> Result<Self::Postings> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => Ok(FieldPostingIterEnum::Lucene50(i.postings()?)),
+            FieldTermIterEnum::Direct(i) => Ok(FieldPostingIterEnum::Direct(i.postings()?)),
+            FieldTermIterEnum::FST(i) => Ok(FieldPostingIterEnum::Lucene50(i.postings()?)),
+        }
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => Ok(FieldPostingIterEnum::Lucene50(
+                i.postings_with_flags(flags)?,
+            )),
+            FieldTermIterEnum::Direct(i) => {
+                Ok(FieldPostingIterEnum::Direct(i.postings_with_flags(flags)?))
+            }
+            FieldTermIterEnum::FST(i) => Ok(FieldPostingIterEnum::Lucene50(
+                i.postings_with_flags(flags)?,
+            )),
+        }
+    }
+
+    fn impacts(&mut self) -> Result<Option<Impacts>> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.impacts(),
+            FieldTermIterEnum::Direct(i) => i.impacts(),
+            FieldTermIterEnum::FST(i) => i.impacts(),
+        }
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => Ok(FieldTermStateEnum::Lucene50(i.term_state()?)),
+            FieldTermIterEnum::Direct(i) => Ok(FieldTermStateEnum::Direct(i.term_state()?)),
+            FieldTermIterEnum::FST(i) => Ok(FieldTermStateEnum::Lucene50(i.term_state()?)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            FieldTermIterEnum::Lucene50(i) => i.is_empty(),
+            FieldTermIterEnum::Direct(i) => i.is_empty(),
+            FieldTermIterEnum::FST(i) => i.is_empty(),
+        }
+    }
+}
+
+/// `TermState` impl for `FieldTermIterEnum`
+#[derive(Clone)]
+pub enum FieldTermStateEnum {
+    Lucene50(BlockTermState),
+    Direct(OrdTermState),
+}
+
+impl TermState for FieldTermStateEnum {}
+
+/// `PostingIterator` impl for `FieldTermIterEnum`
+pub enum FieldPostingIterEnum {
+    Lucene50(Lucene50PostingIterator),
+    Direct(DirectPostingIterator),
+}
+
+impl PostingIterator for FieldPostingIterEnum {
+    fn freq(&self) -> Result<i32> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.freq(),
+            FieldPostingIterEnum::Direct(i) => i.freq(),
+        }
+    }
+
+    fn next_position(&mut self) -> Result<i32> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.next_position(),
+            FieldPostingIterEnum::Direct(i) => i.next_position(),
+        }
+    }
+
+    fn start_offset(&self) -> Result<i32> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.start_offset(),
+            FieldPostingIterEnum::Direct(i) => i.start_offset(),
+        }
+    }
+
+    fn end_offset(&self) -> Result<i32> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.end_offset(),
+            FieldPostingIterEnum::Direct(i) => i.end_offset(),
+        }
+    }
+
+    fn payload(&self) -> Result<Payload> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.payload(),
+            FieldPostingIterEnum::Direct(i) => i.payload(),
+        }
+    }
+}
+
+impl DocIterator for FieldPostingIterEnum {
+    fn doc_id(&self) -> DocId {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.doc_id(),
+            FieldPostingIterEnum::Direct(i) => i.doc_id(),
+        }
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.next(),
+            FieldPostingIterEnum::Direct(i) => i.next(),
+        }
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.advance(target),
+            FieldPostingIterEnum::Direct(i) => i.advance(target),
+        }
+    }
+
+    fn slow_advance(&mut self, target: DocId) -> Result<DocId> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.slow_advance(target),
+            FieldPostingIterEnum::Direct(i) => i.slow_advance(target),
+        }
+    }
+
+    fn cost(&self) -> usize {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.cost(),
+            FieldPostingIterEnum::Direct(i) => i.cost(),
+        }
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.matches(),
+            FieldPostingIterEnum::Direct(i) => i.matches(),
+        }
+    }
+
+    fn match_cost(&self) -> f32 {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.match_cost(),
+            FieldPostingIterEnum::Direct(i) => i.match_cost(),
+        }
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.approximate_next(),
+            FieldPostingIterEnum::Direct(i) => i.approximate_next(),
+        }
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        match self {
+            FieldPostingIterEnum::Lucene50(i) => i.approximate_advance(target),
+            FieldPostingIterEnum::Direct(i) => i.approximate_advance(target),
+        }
+    }
+}
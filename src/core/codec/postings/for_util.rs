@@ -229,6 +229,7 @@ impl ForUtilInstance {
             };
             p.parse_from(input, encoded_size as usize, num_bits, format)?;
         } else if by_simd {
+            input.prefill(SIMD_ENCODE_SIZE[num_bits])?;
             let encoded = unsafe { input.get_and_advance(SIMD_ENCODE_SIZE[num_bits]) };
             let decoded = unsafe {
                 from_raw_parts_mut(decoded.as_mut_ptr() as *mut u32, BLOCK_SIZE as usize)
@@ -0,0 +1,776 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::codec_util;
+use crate::core::codec::doc_values::NumericDocValues;
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::norms::NormsProducer;
+use crate::core::codec::postings::blocktree::BlockTermState;
+use crate::core::codec::postings::{
+    lucene50_decode_term, FieldsConsumer, FieldsConsumerEnum, FieldsProducer, Impacts,
+    Lucene50PostingIterator, Lucene50PostingsReader, Lucene50PostingsReaderRef,
+    Lucene50PostingsWriter, PostingsFormat, PostingsWriterBase, DEFAULT_DOC_TERM_FREQ,
+    DEFAULT_SEGMENT_DOC_FREQ, VERSION_CURRENT as POSTINGS_VERSION_CURRENT,
+};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::{Codec, Fields, SeekStatus, TermIterator, Terms};
+use crate::core::doc::IndexOptions;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput, IndexOutput};
+use crate::core::util::fst::{
+    Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, FSTBytesReader, FstBuilder,
+    InputType, OutputFactory, END_LABEL, FST,
+};
+use crate::core::util::{
+    to_ints_ref, Accountable, DocId, FixedBitSet, ImmutableBitSet, IntsRefBuilder, NamedAccountable,
+};
+use crate::error::Error::{CorruptIndex, IllegalState, UnsupportedOperation};
+use crate::Result;
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::Arc;
+
+/// Extension of the FST terms dictionary file
+pub const FST_TERMS_EXTENSION: &str = "tfp";
+const FST_TERMS_CODEC_NAME: &str = "FSTTerms";
+const FST_POSTINGS_NAME: &str = "FST50";
+
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// A `PostingsFormat` which keeps the whole terms dictionary of each field
+/// in memory as an FST, mapping every term to its stats and postings
+/// metadata, and writes the postings like `Lucene50`.
+///
+/// Unlike the block tree dictionary, which only keeps the prefixes of its
+/// blocks in memory and scans a block on disk for every lookup, an exact
+/// term lookup here is a single FST walk without any IO. This suits high
+/// cardinality key fields, e.g. ids, which are mostly looked up by exact
+/// term. The FSTs are usually bigger than the block tree index, so don't
+/// use this format for large full text fields.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+pub struct FSTPostingsFormat;
+
+impl PostingsFormat for FSTPostingsFormat {
+    type FieldsProducer = FSTTermsReader;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        let postings_reader = Lucene50PostingsReader::open(state, false)?;
+        FSTTermsReader::new(postings_reader, state)
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        let postings_writer = Lucene50PostingsWriter::new(state, POSTINGS_VERSION_CURRENT)?;
+        Ok(FieldsConsumerEnum::FST(FSTTermsWriter::new(
+            state,
+            postings_writer,
+        )?))
+    }
+
+    fn name(&self) -> &str {
+        FST_POSTINGS_NAME
+    }
+}
+
+struct FSTFieldMetaData {
+    number: u32,
+    index_options: IndexOptions,
+    num_terms: i64,
+    sum_total_term_freq: i64,
+    sum_doc_freq: i64,
+    doc_count: i32,
+    longs_size: usize,
+    min_term: Vec<u8>,
+    max_term: Vec<u8>,
+    index: FST<ByteSequenceOutputFactory>,
+}
+
+/// FST based terms dictionary writer.
+///
+/// The output of each term in the FST is its doc freq, total term freq and
+/// the metadata encoded by the postings writer. The FSTs of all fields are
+/// written to the terms file on close.
+pub struct FSTTermsWriter<O: IndexOutput> {
+    terms_out: O,
+    postings_writer: Lucene50PostingsWriter<O>,
+    field_infos: FieldInfos,
+    max_doc: DocId,
+    fields: Vec<FSTFieldMetaData>,
+    closed: bool,
+}
+
+impl<O: IndexOutput> FSTTermsWriter<O> {
+    pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
+        state: &SegmentWriteState<D, DW, C>,
+        postings_writer: Lucene50PostingsWriter<O>,
+    ) -> Result<FSTTermsWriter<O>> {
+        let terms_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            FST_TERMS_EXTENSION,
+        );
+        let mut terms_out = state.directory.create_output(&terms_name, &state.context)?;
+        codec_util::write_index_header(
+            &mut terms_out,
+            FST_TERMS_CODEC_NAME,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+
+        let mut postings_writer = postings_writer;
+        postings_writer.init(&mut terms_out, state)?;
+
+        Ok(FSTTermsWriter {
+            terms_out,
+            postings_writer,
+            field_infos: state.field_infos.clone(),
+            max_doc: state.segment_info.max_doc,
+            fields: vec![],
+            closed: false,
+        })
+    }
+
+    fn write_field(
+        &mut self,
+        field_info: &FieldInfo,
+        terms: &impl Terms,
+        norms: Option<&dyn NumericDocValues>,
+    ) -> Result<()> {
+        let longs_size = self.postings_writer.set_field(field_info) as usize;
+        let mut longs = vec![0i64; longs_size];
+        let mut docs_seen = FixedBitSet::new(self.max_doc as usize);
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory {});
+        builder.init();
+
+        let mut num_terms = 0;
+        let mut sum_total_term_freq = 0;
+        let mut sum_doc_freq = 0;
+        let mut min_term = None;
+        let mut max_term = vec![];
+        let mut terms_iter = terms.iterator()?;
+        while let Some(term) = terms_iter.next()? {
+            let state = match self.postings_writer.write_term(
+                &term,
+                &mut terms_iter,
+                &mut docs_seen,
+                DEFAULT_SEGMENT_DOC_FREQ,
+                DEFAULT_DOC_TERM_FREQ,
+                norms,
+            )? {
+                Some(state) => state,
+                None => continue,
+            };
+
+            let mut output = vec![];
+            output.write_vint(state.doc_freq)?;
+            if field_info.index_options != IndexOptions::Docs {
+                output.write_vlong(state.total_term_freq - i64::from(state.doc_freq))?;
+                sum_total_term_freq += state.total_term_freq;
+            }
+            let mut meta_bytes = vec![];
+            self.postings_writer.encode_term(
+                &mut longs,
+                &mut meta_bytes,
+                field_info,
+                &state,
+                true,
+            )?;
+            for l in &longs {
+                output.write_vlong(*l)?;
+            }
+            output.extend_from_slice(&meta_bytes);
+            builder.add(
+                to_ints_ref(&term, &mut scratch_ints_ref),
+                ByteSequenceOutput::new(output),
+            )?;
+
+            num_terms += 1;
+            sum_doc_freq += i64::from(state.doc_freq);
+            if min_term.is_none() {
+                min_term = Some(term.clone());
+            }
+            max_term = term;
+        }
+
+        if let (Some(min_term), Some(index)) = (min_term, builder.finish()?) {
+            self.fields.push(FSTFieldMetaData {
+                number: field_info.number,
+                index_options: field_info.index_options,
+                num_terms,
+                sum_total_term_freq,
+                sum_doc_freq,
+                doc_count: docs_seen.cardinality() as i32,
+                longs_size,
+                min_term,
+                max_term,
+                index,
+            });
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        let fields = mem::take(&mut self.fields);
+        self.terms_out.write_vint(fields.len() as i32)?;
+        for field in &fields {
+            self.terms_out.write_vint(field.number as i32)?;
+            self.terms_out.write_vlong(field.num_terms)?;
+            if field.index_options != IndexOptions::Docs {
+                self.terms_out.write_vlong(field.sum_total_term_freq)?;
+            }
+            self.terms_out.write_vlong(field.sum_doc_freq)?;
+            self.terms_out.write_vint(field.doc_count)?;
+            self.terms_out.write_vint(field.longs_size as i32)?;
+            write_bytes_ref(&mut self.terms_out, &field.min_term)?;
+            write_bytes_ref(&mut self.terms_out, &field.max_term)?;
+            field.index.save(&mut self.terms_out)?;
+        }
+        codec_util::write_footer(&mut self.terms_out)?;
+
+        self.postings_writer.close()
+    }
+}
+
+fn write_bytes_ref(out: &mut impl DataOutput, bytes: &[u8]) -> Result<()> {
+    out.write_vint(bytes.len() as i32)?;
+    out.write_bytes(bytes, 0, bytes.len())
+}
+
+fn read_bytes_ref(input: &mut impl DataInput) -> Result<Vec<u8>> {
+    let len = input.read_vint()?;
+    if len < 0 {
+        return Err(CorruptIndex(format!("invalid term length: {}", len)));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl<O: IndexOutput> FieldsConsumer for FSTTermsWriter<O> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
+        for field in fields.fields() {
+            if let Some(terms) = fields.terms(&field)? {
+                let field_info = match self.field_infos.field_info_by_name(&field) {
+                    Some(info) => info.clone(),
+                    None => return Err(IllegalState(format!("unknown field: {}", field))),
+                };
+                let field_norms = match norms {
+                    Some(norms) if field_info.has_norms() => Some(norms.norms(&field_info)?),
+                    _ => None,
+                };
+                self.write_field(&field_info, &terms, field_norms.as_deref())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<O: IndexOutput> Drop for FSTTermsWriter<O> {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::error!("drop FSTTermsWriter failed by '{:?}'", e);
+        }
+    }
+}
+
+/// FST based terms dictionary reader, all the FSTs are loaded on open.
+pub struct FSTTermsReader {
+    fields: BTreeMap<String, Arc<FSTFieldReader>>,
+    postings_reader: Lucene50PostingsReaderRef,
+}
+
+impl FSTTermsReader {
+    pub fn new<D: Directory, DW: Directory, C: Codec>(
+        postings_reader: Lucene50PostingsReader,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<FSTTermsReader> {
+        let terms_name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            FST_TERMS_EXTENSION,
+        );
+        let mut input = state
+            .directory
+            .open_checksum_input(&terms_name, state.context)?;
+        codec_util::check_index_header(
+            &mut input,
+            FST_TERMS_CODEC_NAME,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        postings_reader.init(&mut input, state)?;
+        let postings_reader = Arc::new(postings_reader);
+
+        let num_fields = input.read_vint()?;
+        if num_fields < 0 {
+            return Err(CorruptIndex(format!("invalid num_fields: {}", num_fields)));
+        }
+        let mut fields = BTreeMap::new();
+        for _ in 0..num_fields {
+            let number = input.read_vint()?;
+            let field_info = match state.field_infos.by_number.get(&(number as u32)) {
+                Some(info) => Arc::clone(info),
+                None => {
+                    return Err(CorruptIndex(format!("invalid field number: {}", number)));
+                }
+            };
+            let num_terms = input.read_vlong()?;
+            let sum_total_term_freq = if field_info.index_options != IndexOptions::Docs {
+                input.read_vlong()?
+            } else {
+                -1
+            };
+            let sum_doc_freq = input.read_vlong()?;
+            let doc_count = input.read_vint()?;
+            let longs_size = input.read_vint()? as usize;
+            let min_term = read_bytes_ref(&mut input)?;
+            let max_term = read_bytes_ref(&mut input)?;
+            let index = FST::from_input(&mut input, ByteSequenceOutputFactory {})?;
+            let reader = FSTFieldReader {
+                field_info,
+                num_terms,
+                sum_total_term_freq,
+                sum_doc_freq,
+                doc_count,
+                longs_size,
+                min_term,
+                max_term,
+                index,
+                postings_reader: Arc::clone(&postings_reader),
+            };
+            if fields
+                .insert(reader.field_info.name.clone(), Arc::new(reader))
+                .is_some()
+            {
+                return Err(CorruptIndex(format!("duplicate field: {}", number)));
+            }
+        }
+        codec_util::check_footer(&mut input)?;
+
+        Ok(FSTTermsReader {
+            fields,
+            postings_reader,
+        })
+    }
+}
+
+impl FieldsProducer for FSTTermsReader {
+    fn check_integrity(&self) -> Result<()> {
+        // the terms file was verified on open
+        self.postings_reader.check_integrity()
+    }
+}
+
+impl Accountable for FSTTermsReader {
+    fn ram_bytes_used(&self) -> usize {
+        self.fields.values().map(|f| f.ram_bytes_used()).sum()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.fields
+            .iter()
+            .map(|(name, reader)| NamedAccountable::new(format!("field '{}'", name), reader))
+            .collect()
+    }
+}
+
+impl Accountable for FSTFieldReader {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.min_term.capacity()
+            + self.max_term.capacity()
+            + self.index.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        vec![NamedAccountable::new("terms", &self.index)]
+    }
+}
+
+impl Fields for FSTTermsReader {
+    type Terms = Arc<FSTFieldReader>;
+
+    fn fields(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        Ok(self.fields.get(field).map(Arc::clone))
+    }
+
+    fn size(&self) -> usize {
+        self.fields.len()
+    }
+}
+
+/// The terms of a field of `FSTTermsReader`.
+pub struct FSTFieldReader {
+    field_info: Arc<FieldInfo>,
+    num_terms: i64,
+    sum_total_term_freq: i64,
+    sum_doc_freq: i64,
+    doc_count: i32,
+    longs_size: usize,
+    min_term: Vec<u8>,
+    max_term: Vec<u8>,
+    index: FST<ByteSequenceOutputFactory>,
+    postings_reader: Lucene50PostingsReaderRef,
+}
+
+impl FSTFieldReader {
+    pub fn field_info(&self) -> &FieldInfo {
+        self.field_info.as_ref()
+    }
+
+    /// Decodes the output of a term in the FST.
+    fn decode_term_state(&self, output: &[u8]) -> Result<BlockTermState> {
+        let mut input = output;
+        let mut state = BlockTermState::new();
+        state.doc_freq = input.read_vint()?;
+        state.total_term_freq = if self.field_info.index_options != IndexOptions::Docs {
+            i64::from(state.doc_freq) + input.read_vlong()?
+        } else {
+            -1
+        };
+        let mut longs = vec![0i64; self.longs_size];
+        for l in &mut longs {
+            *l = input.read_vlong()?;
+        }
+        lucene50_decode_term(&longs, &mut input, &self.field_info, &mut state, true)?;
+        Ok(state)
+    }
+}
+
+impl Terms for Arc<FSTFieldReader> {
+    type Iterator = FSTTermIterator;
+
+    fn iterator(&self) -> Result<Self::Iterator> {
+        Ok(FSTTermIterator {
+            terms: Arc::clone(self),
+            arcs: vec![],
+            outputs: vec![],
+            term: vec![],
+            state: None,
+            eof: false,
+        })
+    }
+
+    fn size(&self) -> Result<i64> {
+        Ok(self.num_terms)
+    }
+
+    fn sum_total_term_freq(&self) -> Result<i64> {
+        Ok(self.sum_total_term_freq)
+    }
+
+    fn sum_doc_freq(&self) -> Result<i64> {
+        Ok(self.sum_doc_freq)
+    }
+
+    fn doc_count(&self) -> Result<i32> {
+        Ok(self.doc_count)
+    }
+
+    fn has_freqs(&self) -> Result<bool> {
+        Ok(self.field_info.index_options.has_freqs())
+    }
+
+    fn has_offsets(&self) -> Result<bool> {
+        Ok(self.field_info.index_options.has_offsets())
+    }
+
+    fn has_positions(&self) -> Result<bool> {
+        Ok(self.field_info.index_options.has_positions())
+    }
+
+    fn has_payloads(&self) -> Result<bool> {
+        Ok(self.field_info.has_store_payloads)
+    }
+
+    fn min(&self) -> Result<Option<Vec<u8>>> {
+        Ok(Some(self.min_term.clone()))
+    }
+
+    fn max(&self) -> Result<Option<Vec<u8>>> {
+        Ok(Some(self.max_term.clone()))
+    }
+}
+
+/// `TermIterator` of `FSTFieldReader`.
+///
+/// `seek_exact` is a plain FST lookup, enumerating walks the FST arcs in
+/// order, keeping the arcs from the root to the current term on a stack.
+pub struct FSTTermIterator {
+    terms: Arc<FSTFieldReader>,
+    // the root arc, the arcs of the current term, and the final `END_LABEL` arc,
+    // outputs[i] is the output of the path up to arcs[i]
+    outputs: Vec<ByteSequenceOutput>,
+    // empty after an exact seek, which doesn't walk the arcs
+    arcs: Vec<FSTArc<ByteSequenceOutput>>,
+    term: Vec<u8>,
+    state: Option<BlockTermState>,
+    eof: bool,
+}
+
+impl FSTTermIterator {
+    fn push(&mut self, arc: FSTArc<ByteSequenceOutput>) {
+        let output = match (self.outputs.last(), &arc.output) {
+            (Some(prefix), Some(output)) => self.terms.index.outputs().add(prefix, output),
+            (Some(prefix), None) => prefix.clone(),
+            (None, _) => self.terms.index.outputs().empty(),
+        };
+        if !self.arcs.is_empty() && arc.label != END_LABEL {
+            self.term.push(arc.label as u8);
+        }
+        self.arcs.push(arc);
+        self.outputs.push(output);
+    }
+
+    fn pop(&mut self) -> FSTArc<ByteSequenceOutput> {
+        let arc = self.arcs.pop().unwrap();
+        self.outputs.pop();
+        if !self.arcs.is_empty() && arc.label != END_LABEL {
+            self.term.pop();
+        }
+        arc
+    }
+
+    fn rewind(&mut self) {
+        self.unposition();
+        let root = self.terms.index.root_arc();
+        self.push(root);
+    }
+
+    // follows the first arcs from the top of the stack down to a final arc
+    fn push_first(&mut self, reader: &mut FSTBytesReader) -> Result<()> {
+        loop {
+            let arc = self
+                .terms
+                .index
+                .read_first_target_arc(self.arcs.last().unwrap(), reader)?;
+            let end = arc.label == END_LABEL;
+            self.push(arc);
+            if end {
+                return Ok(());
+            }
+        }
+    }
+
+    // moves to the first term after the subtree of the top of the stack
+    fn push_next(&mut self, reader: &mut FSTBytesReader) -> Result<bool> {
+        while self.arcs.len() > 1 {
+            let mut arc = self.pop();
+            if !arc.is_last() {
+                self.terms.index.read_next_arc(&mut arc, reader)?;
+                let end = arc.label == END_LABEL;
+                self.push(arc);
+                if !end {
+                    self.push_first(reader)?;
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn load_state(&mut self, found: bool) -> Result<bool> {
+        if found {
+            let state = self
+                .terms
+                .decode_term_state(self.outputs.last().unwrap().inner())?;
+            self.state = Some(state);
+        } else {
+            self.unposition();
+            self.eof = true;
+        }
+        Ok(found)
+    }
+
+    fn position_exact(&mut self, text: &[u8], state: BlockTermState) {
+        self.unposition();
+        self.term.extend_from_slice(text);
+        self.state = Some(state);
+    }
+
+    fn unposition(&mut self) {
+        self.arcs.clear();
+        self.outputs.clear();
+        self.term.clear();
+        self.state = None;
+        self.eof = false;
+    }
+
+    fn current(&self) -> Result<&BlockTermState> {
+        match self.state {
+            Some(ref state) => Ok(state),
+            None => Err(IllegalState("FSTTermIterator is unpositioned".into())),
+        }
+    }
+}
+
+impl TermIterator for FSTTermIterator {
+    type Postings = Lucene50PostingIterator;
+    type TermState = BlockTermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.eof {
+            return Ok(None);
+        }
+        let mut reader = self.terms.index.bytes_reader();
+        let found = if self.state.is_none() {
+            // not started
+            self.rewind();
+            self.push_first(&mut reader)?;
+            true
+        } else if self.arcs.is_empty() {
+            // walk to the term of the last exact seek first
+            let term = mem::take(&mut self.term);
+            match self.seek_ceil(&term)? {
+                SeekStatus::Found => self.push_next(&mut reader)?,
+                SeekStatus::NotFound => return Ok(Some(self.term.clone())),
+                SeekStatus::End => return Ok(None),
+            }
+        } else {
+            self.push_next(&mut reader)?
+        };
+        Ok(if self.load_state(found)? {
+            Some(self.term.clone())
+        } else {
+            None
+        })
+    }
+
+    fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
+        match self.terms.index.get(text)? {
+            Some(output) => {
+                let state = self.terms.decode_term_state(output.inner())?;
+                self.position_exact(text, state);
+                Ok(true)
+            }
+            None => {
+                self.unposition();
+                Ok(false)
+            }
+        }
+    }
+
+    fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
+        let mut reader = self.terms.index.bytes_reader();
+        self.rewind();
+        for &b in text {
+            let label = i32::from(b);
+            let mut arc = self
+                .terms
+                .index
+                .read_first_target_arc(self.arcs.last().unwrap(), &mut reader)?;
+            while arc.label == END_LABEL || arc.label < label {
+                if arc.is_last() {
+                    // all the terms with the current prefix are smaller
+                    let found = self.push_next(&mut reader)?;
+                    return Ok(if self.load_state(found)? {
+                        SeekStatus::NotFound
+                    } else {
+                        SeekStatus::End
+                    });
+                }
+                self.terms.index.read_next_arc(&mut arc, &mut reader)?;
+            }
+            let greater = arc.label > label;
+            self.push(arc);
+            if greater {
+                self.push_first(&mut reader)?;
+                self.load_state(true)?;
+                return Ok(SeekStatus::NotFound);
+            }
+        }
+        let arc = self
+            .terms
+            .index
+            .read_first_target_arc(self.arcs.last().unwrap(), &mut reader)?;
+        let found = arc.label == END_LABEL;
+        self.push(arc);
+        if !found {
+            self.push_first(&mut reader)?;
+        }
+        self.load_state(true)?;
+        Ok(if found {
+            SeekStatus::Found
+        } else {
+            SeekStatus::NotFound
+        })
+    }
+
+    fn seek_exact_ord(&mut self, _ord: i64) -> Result<()> {
+        Err(UnsupportedOperation(
+            "FSTTermIterator::seek_exact_ord".into(),
+        ))
+    }
+
+    fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
+        self.position_exact(text, state.clone());
+        Ok(())
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        self.current()?;
+        Ok(&self.term)
+    }
+
+    fn ord(&self) -> Result<i64> {
+        Err(UnsupportedOperation(String::new()))
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        Ok(self.current()?.doc_freq)
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        Ok(self.current()?.total_term_freq)
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        let state = self.current()?;
+        self.terms
+            .postings_reader
+            .postings(&self.terms.field_info, state, flags)
+    }
+
+    fn impacts(&mut self) -> Result<Option<Impacts>> {
+        let state = self.current()?;
+        let impacts = self
+            .terms
+            .postings_reader
+            .impacts(&self.terms.field_info, state)?;
+        Ok(Some(impacts))
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        Ok(self.current()?.clone())
+    }
+}
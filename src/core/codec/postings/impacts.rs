@@ -0,0 +1,208 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::postings::skip_reader::Lucene50SkipReader;
+use crate::core::search::NO_MORE_DOCS;
+use crate::core::store::io::{ByteArrayDataInput, DataInput, DataOutput};
+use crate::core::util::DocId;
+
+use crate::Result;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// A term frequency and a norm that bound the score of the docs of a block of
+/// postings: none of them has a higher freq for the same or a more competitive norm.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Impact {
+    pub freq: i32,
+    pub norm: i64,
+}
+
+impl Impact {
+    pub fn new(freq: i32, norm: i64) -> Impact {
+        Impact { freq, norm }
+    }
+}
+
+/// Accumulates the (freq, norm) pairs of the docs of a block of postings and keeps the
+/// competitive ones. A higher norm is the more competitive, the similarities of this
+/// crate encode shorter fields with higher norms.
+#[derive(Clone)]
+pub struct CompetitiveImpactAccumulator {
+    /// the max freq of each norm fitting in a byte, the common case
+    max_freqs: [i32; 256],
+    /// the max freq of the other norms
+    other_freqs: BTreeMap<i64, i32>,
+}
+
+impl Default for CompetitiveImpactAccumulator {
+    fn default() -> Self {
+        CompetitiveImpactAccumulator {
+            max_freqs: [0; 256],
+            other_freqs: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompetitiveImpactAccumulator {
+    pub fn clear(&mut self) {
+        self.max_freqs = [0; 256];
+        self.other_freqs.clear();
+    }
+
+    /// Accumulates the freq and norm of a doc.
+    pub fn add(&mut self, freq: i32, norm: i64) {
+        if (0..256).contains(&norm) {
+            let max_freq = &mut self.max_freqs[norm as usize];
+            *max_freq = (*max_freq).max(freq);
+        } else {
+            let max_freq = self.other_freqs.entry(norm).or_insert(0);
+            *max_freq = (*max_freq).max(freq);
+        }
+    }
+
+    /// Accumulates all the pairs of `other`.
+    pub fn add_all(&mut self, other: &CompetitiveImpactAccumulator) {
+        for (max_freq, &freq) in self.max_freqs.iter_mut().zip(other.max_freqs.iter()) {
+            *max_freq = (*max_freq).max(freq);
+        }
+        for (&norm, &freq) in &other.other_freqs {
+            self.add(freq, norm);
+        }
+    }
+
+    /// Returns the competitive pairs, by increasing freq and decreasing norm.
+    pub fn competitive_impacts(&self) -> Vec<Impact> {
+        let mut pairs: Vec<Impact> = self
+            .max_freqs
+            .iter()
+            .enumerate()
+            .filter(|(_, &freq)| freq > 0)
+            .map(|(norm, &freq)| Impact::new(freq, norm as i64))
+            .chain(
+                self.other_freqs
+                    .iter()
+                    .map(|(&norm, &freq)| Impact::new(freq, norm)),
+            )
+            .collect();
+        pairs.sort_by_key(|pair| Reverse(pair.norm));
+
+        let mut impacts: Vec<Impact> = Vec::with_capacity(pairs.len());
+        for pair in pairs {
+            if impacts.last().is_none_or(|last| pair.freq > last.freq) {
+                impacts.push(pair);
+            }
+        }
+        impacts
+    }
+}
+
+/// Writes impacts sorted by increasing freq and decreasing norm, each as the delta
+/// from the previous one: when the norm decreases by one, the common case, the
+/// delta of the norm is folded in the vint of the freq.
+pub(crate) fn write_impacts(impacts: &[Impact], out: &mut impl DataOutput) -> Result<()> {
+    let mut previous = Impact::new(0, 0);
+    for impact in impacts {
+        debug_assert!(impact.freq > previous.freq);
+        let freq_delta = impact.freq - previous.freq - 1;
+        let norm_delta = impact.norm - previous.norm;
+        if norm_delta == -1 {
+            out.write_vint(freq_delta << 1)?;
+        } else {
+            out.write_vint((freq_delta << 1) | 1)?;
+            out.write_zlong(norm_delta)?;
+        }
+        previous = *impact;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_impacts(data: &[u8]) -> Result<Vec<Impact>> {
+    let mut input = ByteArrayDataInput::new(data);
+    let mut impacts = vec![];
+    let mut previous = Impact::new(0, 0);
+    while !input.eof() {
+        let code = input.read_vint()?;
+        let freq = previous.freq + 1 + (code >> 1);
+        let norm = if code & 1 == 0 {
+            previous.norm - 1
+        } else {
+            previous.norm + input.read_zlong()?
+        };
+        previous = Impact::new(freq, norm);
+        impacts.push(previous);
+    }
+    Ok(impacts)
+}
+
+/// The impacts of the postings of a term, read from its skip data.
+///
+/// Each level groups the docs of the postings in blocks, the higher levels in larger
+/// blocks, and gives the competitive impacts of the block holding the target of the
+/// last call to `advance_shallow`. They let a scorer bound the score of the docs up to
+/// `doc_id_up_to(level)` without decoding them. `advance_shallow` must be called
+/// before reading the levels.
+pub struct Impacts {
+    /// `None` when the postings are too short to have skip data
+    skipper: Option<Lucene50SkipReader>,
+}
+
+impl Impacts {
+    pub(crate) fn new(skipper: Option<Lucene50SkipReader>) -> Impacts {
+        Impacts { skipper }
+    }
+
+    /// Moves the levels to the blocks holding `target`, which must not be lower than
+    /// the target of the previous call.
+    pub fn advance_shallow(&mut self, target: DocId) -> Result<()> {
+        if let Some(skipper) = &mut self.skipper {
+            // skipping to the first doc would load no entry
+            let target = target.max(1);
+            if target > skipper.next_skip_doc() {
+                skipper.skip_to(target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of levels, at least one.
+    pub fn num_levels(&self) -> usize {
+        match &self.skipper {
+            Some(skipper) => skipper.impact_levels().max(1),
+            None => 1,
+        }
+    }
+
+    /// The last doc of the block of `level`, `NO_MORE_DOCS` for the docs after the
+    /// last full block of postings.
+    pub fn doc_id_up_to(&self, level: usize) -> DocId {
+        debug_assert!(level < self.num_levels());
+        match &self.skipper {
+            Some(skipper) if skipper.impact_levels() > 0 => skipper.skip_doc(level),
+            _ => NO_MORE_DOCS,
+        }
+    }
+
+    /// The competitive impacts of the block of `level`. They are empty when unknown,
+    /// for the docs after the last full block of postings, which can score anything.
+    pub fn impacts(&self, level: usize) -> Result<Vec<Impact>> {
+        debug_assert!(level < self.num_levels());
+        match &self.skipper {
+            Some(skipper) if skipper.impact_levels() > 0 => {
+                read_impacts(skipper.impact_data(level))
+            }
+            _ => Ok(vec![]),
+        }
+    }
+}
@@ -19,26 +19,70 @@ pub(crate) use self::for_util::{ForUtil, MAX_ENCODED_SIZE};
 
 mod posting_format;
 
-use self::posting_format::{Lucene50PostingsFormat, BLOCK_SIZE};
+pub use self::posting_format::Lucene50PostingsFormat;
+
+use self::posting_format::BLOCK_SIZE;
 
 mod per_field_postings_format;
 
-pub(crate) use self::per_field_postings_format::{
-    PerFieldFieldsReader, PerFieldFieldsWriter, PerFieldPostingsFormat,
-    PER_FIELD_POSTING_FORMAT_KEY, PER_FIELD_POSTING_SUFFIX_KEY,
+pub use self::per_field_postings_format::PerFieldFieldsReader;
+
+pub use self::per_field_postings_format::PerFieldPostingsFormat;
+
+pub(crate) use self::per_field_postings_format::PerFieldFieldsWriter;
+
+mod bloom_filter_postings_format;
+
+pub use self::bloom_filter_postings_format::{
+    BloomFilterPostingsFormat, BLOOM_EXTENSION, DEFAULT_BLOOM_FPP,
+};
+
+pub(crate) use self::bloom_filter_postings_format::BloomFilterFieldsWriter;
+
+mod direct_postings_format;
+
+pub use self::direct_postings_format::{
+    DirectFieldsReader, DirectPostingIterator, DirectPostingsFormat, DirectTermIterator,
+    DirectTerms,
+};
+
+mod fst_postings_format;
+
+pub use self::fst_postings_format::{
+    FSTFieldReader, FSTPostingsFormat, FSTTermIterator, FSTTermsReader, FSTTermsWriter,
+    FST_TERMS_EXTENSION,
+};
+
+mod completion_postings_format;
+
+pub use self::completion_postings_format::{
+    completion_payload, completion_term, Completion, CompletionFieldsReader,
+    CompletionPostingsFormat, CompletionTerms, COMPLETION_EXTENSION, CONTEXT_SEPARATOR,
+};
+
+pub(crate) use self::completion_postings_format::CompletionFieldsWriter;
+
+mod field_terms_enum;
+
+pub use self::field_terms_enum::{
+    FieldPostingIterEnum, FieldTermIterEnum, FieldTermStateEnum, FieldTermsEnum,
 };
 
 mod posting_reader;
 
 pub(crate) use self::posting_reader::{
     lucene50_decode_term, EncodeType, Lucene50PostingIterator, Lucene50PostingsReader,
-    Lucene50PostingsReaderRef, VERSION_START,
+    Lucene50PostingsReaderRef, VERSION_CURRENT, VERSION_START,
 };
 
 mod posting_writer;
 
 pub(crate) use self::posting_writer::{EfWriterMeta, Lucene50PostingsWriter};
 
+mod impacts;
+
+pub use self::impacts::{CompetitiveImpactAccumulator, Impact, Impacts};
+
 mod skip_reader;
 mod skip_writer;
 mod terms_hash;
@@ -57,19 +101,22 @@ mod simd_block_decoder;
 
 pub(crate) use self::simd_block_decoder::SIMDBlockDecoder;
 
+use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::codec::field_infos::FieldInfo;
 use crate::core::codec::multi_fields::{MappedMultiFields, MultiFields};
+use crate::core::codec::norms::NormsProducer;
 use crate::core::codec::postings::blocktree::{
-    BlockTermState, BlockTreeTermsReader, BlockTreeTermsWriter, FieldReaderRef,
+    BlockTermState, BlockTreeTermsReader, BlockTreeTermsWriter,
 };
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::{SimpleTextFieldsWriter, SimpleTextPostingsFormat};
 use crate::core::codec::{Codec, Fields, TermIterator};
 use crate::core::index::merge::MergeState;
 use crate::core::index::reader::ReaderSlice;
 use crate::core::store::directory::Directory;
 use crate::core::store::io::{DataOutput, IndexOutput};
 use crate::core::util::over_size;
-use crate::core::util::FixedBitSet;
+use crate::core::util::{Accountable, FixedBitSet, NamedAccountable};
 
 use crate::error::Error::IllegalArgument;
 use crate::Result;
@@ -119,8 +166,14 @@ pub trait PostingsFormat {
 }
 
 /// composite `PostingsFormat` use for `CodecEnum`
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum PostingsFormatEnum {
     Lucene50(Lucene50PostingsFormat),
+    BloomFilter(BloomFilterPostingsFormat),
+    Direct(DirectPostingsFormat),
+    FST(FSTPostingsFormat),
+    Completion(CompletionPostingsFormat),
+    SimpleText(SimpleTextPostingsFormat),
 }
 
 impl PostingsFormat for PostingsFormatEnum {
@@ -134,6 +187,19 @@ impl PostingsFormat for PostingsFormatEnum {
             PostingsFormatEnum::Lucene50(f) => {
                 Ok(FieldsProducerEnum::Lucene50(f.fields_producer(state)?))
             }
+            PostingsFormatEnum::BloomFilter(f) => {
+                Ok(FieldsProducerEnum::Lucene50(f.fields_producer(state)?))
+            }
+            PostingsFormatEnum::Direct(f) => {
+                Ok(FieldsProducerEnum::Direct(f.fields_producer(state)?))
+            }
+            PostingsFormatEnum::FST(f) => Ok(FieldsProducerEnum::FST(f.fields_producer(state)?)),
+            PostingsFormatEnum::Completion(f) => {
+                Ok(FieldsProducerEnum::Completion(f.fields_producer(state)?))
+            }
+            PostingsFormatEnum::SimpleText(f) => {
+                Ok(FieldsProducerEnum::Direct(f.open_reader(state)?))
+            }
         }
     }
 
@@ -143,12 +209,22 @@ impl PostingsFormat for PostingsFormatEnum {
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
         match self {
             PostingsFormatEnum::Lucene50(f) => f.fields_consumer(state),
+            PostingsFormatEnum::BloomFilter(f) => f.fields_consumer(state),
+            PostingsFormatEnum::Direct(f) => f.fields_consumer(state),
+            PostingsFormatEnum::FST(f) => f.fields_consumer(state),
+            PostingsFormatEnum::Completion(f) => f.fields_consumer(state),
+            PostingsFormatEnum::SimpleText(f) => f.fields_consumer(state),
         }
     }
 
     fn name(&self) -> &str {
         match self {
             PostingsFormatEnum::Lucene50(f) => f.name(),
+            PostingsFormatEnum::BloomFilter(f) => f.name(),
+            PostingsFormatEnum::Direct(f) => f.name(),
+            PostingsFormatEnum::FST(f) => f.name(),
+            PostingsFormatEnum::Completion(f) => f.name(),
+            PostingsFormatEnum::SimpleText(f) => f.name(),
         }
     }
 }
@@ -158,6 +234,15 @@ pub fn postings_format_for_name(name: &str) -> Result<PostingsFormatEnum> {
         "Lucene50" => Ok(PostingsFormatEnum::Lucene50(
             Lucene50PostingsFormat::default(),
         )),
+        "BloomFilter" => Ok(PostingsFormatEnum::BloomFilter(
+            BloomFilterPostingsFormat::default(),
+        )),
+        "Direct" => Ok(PostingsFormatEnum::Direct(DirectPostingsFormat::default())),
+        "FST50" => Ok(PostingsFormatEnum::FST(FSTPostingsFormat)),
+        "Completion50" => Ok(PostingsFormatEnum::Completion(
+            CompletionPostingsFormat::default(),
+        )),
+        "SimpleText" => Ok(PostingsFormatEnum::SimpleText(SimpleTextPostingsFormat)),
         _ => {
             return Err(IllegalArgument(format!(
                 "Invalid postings format: {}",
@@ -194,14 +279,21 @@ pub trait FieldsConsumer {
     ///
     /// - The provided Fields instance is limited: you cannot call any methods that return
     ///   statistics/counts; you cannot pass a non-null live docs when pulling docs/positions enums.
-    fn write(&mut self, fields: &impl Fields) -> Result<()>;
+    ///
+    /// - `norms` give the norms of the fields that have them, to compute the impacts of the
+    ///   postings.
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()>;
 
     /// Merges in the fields from the readers in
     /// <code>mergeState</code>. The default implementation skips
     /// and maps around deleted documents, and calls {@link #write(Fields)}.
     /// Implementations can override this method for more sophisticated
     /// merging (bulk-byte copying, etc).
-    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &mut MergeState<D, C>) -> Result<()> {
+    fn merge<D: Directory, C: Codec>(
+        &mut self,
+        merge_state: &mut MergeState<D, C>,
+        norms: Option<&dyn NormsProducer>,
+    ) -> Result<()> {
         let mut fields = vec![];
         let mut slices = vec![];
 
@@ -219,36 +311,49 @@ pub trait FieldsConsumer {
 
         let fields = MultiFields::new(fields, slices);
         let merged_fields = MappedMultiFields::new(merge_state, fields);
-        self.write(&merged_fields)
+        self.write(&merged_fields, norms)
     }
 }
 
 pub enum FieldsConsumerEnum<D: Directory, DW: Directory, C: Codec> {
     Lucene50(BlockTreeTermsWriter<Lucene50PostingsWriter<DW::IndexOutput>, DW::IndexOutput>),
     PerField(PerFieldFieldsWriter<D, DW, C>),
+    BloomFilter(BloomFilterFieldsWriter<D, DW, C>),
+    FST(FSTTermsWriter<DW::IndexOutput>),
+    Completion(CompletionFieldsWriter<D, DW, C>),
+    SimpleText(SimpleTextFieldsWriter<DW::IndexOutput>),
 }
 
 impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for FieldsConsumerEnum<D, DW, C> {
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         match self {
-            FieldsConsumerEnum::Lucene50(w) => w.write(fields),
-            FieldsConsumerEnum::PerField(w) => w.write(fields),
+            FieldsConsumerEnum::Lucene50(w) => w.write(fields, norms),
+            FieldsConsumerEnum::PerField(w) => w.write(fields, norms),
+            FieldsConsumerEnum::BloomFilter(w) => w.write(fields, norms),
+            FieldsConsumerEnum::FST(w) => w.write(fields, norms),
+            FieldsConsumerEnum::Completion(w) => w.write(fields, norms),
+            FieldsConsumerEnum::SimpleText(w) => w.write(fields, norms),
         }
     }
 
     fn merge<D1: Directory, C1: Codec>(
         &mut self,
         merge_state: &mut MergeState<D1, C1>,
+        norms: Option<&dyn NormsProducer>,
     ) -> Result<()> {
         match self {
-            FieldsConsumerEnum::Lucene50(w) => w.merge(merge_state),
-            FieldsConsumerEnum::PerField(w) => w.merge(merge_state),
+            FieldsConsumerEnum::Lucene50(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::PerField(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::BloomFilter(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::FST(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::Completion(w) => w.merge(merge_state, norms),
+            FieldsConsumerEnum::SimpleText(w) => w.merge(merge_state, norms),
         }
     }
 }
 
 ///  Abstract API that produces terms, doc, freq, prox, offset and payloads postings
-pub trait FieldsProducer: Fields {
+pub trait FieldsProducer: Fields + Accountable {
     /// Checks consistency of this reader.
     /// Note that this may be costly in terms of I/O, e.g.
     /// may involve computing a checksum value against large data files.
@@ -289,39 +394,79 @@ impl<T: FieldsProducer> Fields for Arc<T> {
 /// `FieldsProducer` impl for `PostingsFormatEnum`
 pub enum FieldsProducerEnum {
     Lucene50(BlockTreeTermsReader),
+    Direct(DirectFieldsReader),
+    FST(FSTTermsReader),
+    Completion(CompletionFieldsReader),
 }
 
 impl FieldsProducer for FieldsProducerEnum {
     fn check_integrity(&self) -> Result<()> {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.check_integrity(),
+            FieldsProducerEnum::Direct(f) => f.check_integrity(),
+            FieldsProducerEnum::FST(f) => f.check_integrity(),
+            FieldsProducerEnum::Completion(f) => f.check_integrity(),
+        }
+    }
+}
+
+impl Accountable for FieldsProducerEnum {
+    fn ram_bytes_used(&self) -> usize {
+        match self {
+            FieldsProducerEnum::Lucene50(f) => f.ram_bytes_used(),
+            FieldsProducerEnum::Direct(f) => f.ram_bytes_used(),
+            FieldsProducerEnum::FST(f) => f.ram_bytes_used(),
+            FieldsProducerEnum::Completion(f) => f.ram_bytes_used(),
+        }
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        match self {
+            FieldsProducerEnum::Lucene50(f) => f.child_resources(),
+            FieldsProducerEnum::Direct(f) => f.child_resources(),
+            FieldsProducerEnum::FST(f) => f.child_resources(),
+            FieldsProducerEnum::Completion(f) => f.child_resources(),
         }
     }
 }
 
 impl Fields for FieldsProducerEnum {
-    type Terms = FieldReaderRef;
+    type Terms = FieldTermsEnum;
     fn fields(&self) -> Vec<String> {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.fields(),
+            FieldsProducerEnum::Direct(f) => f.fields(),
+            FieldsProducerEnum::FST(f) => f.fields(),
+            FieldsProducerEnum::Completion(f) => f.fields(),
         }
     }
 
     fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
         match self {
-            FieldsProducerEnum::Lucene50(f) => f.terms(field),
+            FieldsProducerEnum::Lucene50(f) => Ok(f.terms(field)?.map(FieldTermsEnum::Lucene50)),
+            FieldsProducerEnum::Direct(f) => Ok(f.terms(field)?.map(FieldTermsEnum::Direct)),
+            FieldsProducerEnum::FST(f) => Ok(f.terms(field)?.map(FieldTermsEnum::FST)),
+            FieldsProducerEnum::Completion(f) => {
+                Ok(f.terms(field)?.map(FieldTermsEnum::Completion))
+            }
         }
     }
 
     fn size(&self) -> usize {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.size(),
+            FieldsProducerEnum::Direct(f) => f.size(),
+            FieldsProducerEnum::FST(f) => f.size(),
+            FieldsProducerEnum::Completion(f) => f.size(),
         }
     }
 
     fn terms_freq(&self, field: &str) -> usize {
         match self {
             FieldsProducerEnum::Lucene50(f) => f.terms_freq(field),
+            FieldsProducerEnum::Direct(f) => f.terms_freq(field),
+            FieldsProducerEnum::FST(f) => f.terms_freq(field),
+            FieldsProducerEnum::Completion(f) => f.terms_freq(field),
         }
     }
 }
@@ -346,7 +491,8 @@ pub trait PostingsWriterBase {
     /// method must set the bit in the provided {@link
     /// FixedBitSet} for every docID written.  If no docs
     /// were written, this method should return null, and the
-    /// terms dict will skip the term.
+    /// terms dict will skip the term. `norms` are the norms of
+    /// the field, if it has them.
     fn write_term(
         &mut self,
         term: &[u8],
@@ -354,6 +500,7 @@ pub trait PostingsWriterBase {
         docs_seen: &mut FixedBitSet,
         doc_freq_limit: i32,
         term_freq_limit: i32,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<Option<BlockTermState>>;
 
     /// Encode metadata as [i64] and [u8]. {@param absolute} controls whether
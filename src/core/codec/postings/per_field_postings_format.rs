@@ -11,21 +11,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::mem;
 use std::sync::Arc;
 
-use crate::core::codec::postings::blocktree::FieldReaderRef;
+use crate::core::codec::norms::NormsProducer;
 use crate::core::codec::postings::{
-    postings_format_for_name, FieldsConsumer, FieldsConsumerEnum, FieldsProducer,
-    FieldsProducerEnum, Lucene50PostingsFormat, PostingsFormat,
+    postings_format_for_name, FieldTermsEnum, FieldsConsumer, FieldsConsumerEnum, FieldsProducer,
+    FieldsProducerEnum, Lucene50PostingsFormat, PostingsFormat, PostingsFormatEnum,
 };
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
 use crate::core::codec::{Codec, Fields};
 use crate::core::doc::IndexOptions;
 use crate::core::store::directory::Directory;
+use crate::core::util::{Accountable, NamedAccountable};
 use crate::error::Error;
 use crate::Result;
 
@@ -55,12 +56,46 @@ fn get_suffix(format: &str, suffix: &str) -> String {
 /// filenames would look like <tt>_1_Lucene40_0.prx</tt>.
 /// @see ServiceLoader
 /// @lucene.experimental
-#[derive(Copy, Clone)]
-pub struct PerFieldPostingsFormat;
+///
+/// All fields use the default `Lucene50` format, or the one set with
+/// `with_default_format`, unless another format is set for them with
+/// `with_field_format`. The format of each field is recorded in its
+/// `FieldInfo`, so segments are read back whatever the configuration.
+#[derive(Clone)]
+pub struct PerFieldPostingsFormat {
+    field_formats: Arc<HashMap<String, PostingsFormatEnum>>,
+    default_format: PostingsFormatEnum,
+}
 
 impl Default for PerFieldPostingsFormat {
-    fn default() -> PerFieldPostingsFormat {
-        PerFieldPostingsFormat {}
+    fn default() -> Self {
+        PerFieldPostingsFormat {
+            field_formats: Arc::new(HashMap::new()),
+            default_format: PostingsFormatEnum::Lucene50(Lucene50PostingsFormat::default()),
+        }
+    }
+}
+
+impl PerFieldPostingsFormat {
+    /// Returns this format with the postings of fields without a format of their
+    /// own written by `format`.
+    pub fn with_default_format(mut self, format: PostingsFormatEnum) -> Self {
+        self.default_format = format;
+        self
+    }
+
+    /// Returns this format with the postings of `field` written by `format`.
+    pub fn with_field_format(mut self, field: &str, format: PostingsFormatEnum) -> Self {
+        Arc::make_mut(&mut self.field_formats).insert(field.to_string(), format);
+        self
+    }
+
+    /// The format the postings of `field` are written with.
+    pub fn postings_format_for_field(&self, field: &str) -> PostingsFormatEnum {
+        self.field_formats
+            .get(field)
+            .cloned()
+            .unwrap_or(self.default_format)
     }
 }
 
@@ -79,6 +114,7 @@ impl PostingsFormat for PerFieldPostingsFormat {
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
         Ok(FieldsConsumerEnum::PerField(PerFieldFieldsWriter::new(
             state,
+            self.clone(),
         )))
     }
 
@@ -95,6 +131,15 @@ pub struct PerFieldFieldsReader {
 impl PerFieldFieldsReader {
     fn new<D: Directory, DW: Directory, C: Codec>(
         state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<PerFieldFieldsReader> {
+        Self::with_format_resolver(state, postings_format_for_name)
+    }
+
+    /// Opens the postings of each field with the format `resolve` returns for the
+    /// format name recorded in its `FieldInfo`.
+    pub(crate) fn with_format_resolver<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+        resolve: impl Fn(&str) -> Result<PostingsFormatEnum>,
     ) -> Result<PerFieldFieldsReader> {
         let mut fields = BTreeMap::new();
         let mut formats = HashMap::new();
@@ -109,7 +154,7 @@ impl PerFieldFieldsReader {
                     let segment_suffix = get_suffix(&format, suffix);
 
                     if !formats.contains_key(&segment_suffix) {
-                        let postings_format = postings_format_for_name(format)?;
+                        let postings_format = resolve(format)?;
                         let state = SegmentReadState::with_suffix(state, &segment_suffix);
                         formats.insert(
                             segment_suffix.clone(),
@@ -132,7 +177,24 @@ impl PerFieldFieldsReader {
         Ok(PerFieldFieldsReader { fields, segment })
     }
 
-    fn terms_impl(&self, field: &str) -> Result<Option<FieldReaderRef>> {
+    /// Creates a reader exposing the fields of several readers, each field is
+    /// read from the first reader that has it.
+    pub fn from_readers(
+        segment: String,
+        readers: &[&PerFieldFieldsReader],
+    ) -> PerFieldFieldsReader {
+        let mut fields = BTreeMap::new();
+        for reader in readers {
+            for (name, producer) in &reader.fields {
+                fields
+                    .entry(name.clone())
+                    .or_insert_with(|| Arc::clone(producer));
+            }
+        }
+        PerFieldFieldsReader { fields, segment }
+    }
+
+    fn terms_impl(&self, field: &str) -> Result<Option<FieldTermsEnum>> {
         match self.fields.get(field) {
             Some(producer) => producer.terms(field),
             None => Ok(None),
@@ -142,6 +204,18 @@ impl PerFieldFieldsReader {
     fn size_impl(&self) -> usize {
         self.fields.len()
     }
+
+    /// The distinct producers of this reader, with the fields each one reads.
+    fn producers(&self) -> Vec<(&Arc<FieldsProducerEnum>, Vec<&str>)> {
+        let mut producers: Vec<(&Arc<FieldsProducerEnum>, Vec<&str>)> = Vec::new();
+        for (name, producer) in &self.fields {
+            match producers.iter_mut().find(|(p, _)| Arc::ptr_eq(p, producer)) {
+                Some((_, names)) => names.push(name),
+                None => producers.push((producer, vec![name])),
+            }
+        }
+        producers
+    }
 }
 
 impl fmt::Display for PerFieldFieldsReader {
@@ -159,8 +233,31 @@ impl FieldsProducer for PerFieldFieldsReader {
     }
 }
 
+impl Accountable for PerFieldFieldsReader {
+    fn ram_bytes_used(&self) -> usize {
+        let fields = self.fields.keys().map(|k| k.capacity()).sum::<usize>()
+            + self.fields.len() * mem::size_of::<Arc<FieldsProducerEnum>>();
+        mem::size_of::<Self>()
+            + fields
+            + self
+                .producers()
+                .iter()
+                .map(|(p, _)| p.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.producers()
+            .into_iter()
+            .map(|(p, names)| {
+                NamedAccountable::new(format!("fields {}", names.join(", ")), p.as_ref())
+            })
+            .collect()
+    }
+}
+
 impl Fields for PerFieldFieldsReader {
-    type Terms = FieldReaderRef;
+    type Terms = FieldTermsEnum;
     fn fields(&self) -> Vec<String> {
         self.fields.keys().cloned().collect()
     }
@@ -172,21 +269,16 @@ impl Fields for PerFieldFieldsReader {
     }
 }
 
-#[allow(dead_code)]
-struct FieldsGroup<D: Directory, DW: Directory, C: Codec> {
-    fields: BTreeSet<String>,
-    suffix: usize,
-    state: SegmentWriteState<D, DW, C>,
-}
-
 pub struct PerFieldFieldsWriter<D: Directory, DW: Directory, C: Codec> {
     write_state: SegmentWriteState<D, DW, C>,
+    format: PerFieldPostingsFormat,
 }
 
 impl<D: Directory, DW: Directory, C: Codec> PerFieldFieldsWriter<D, DW, C> {
-    pub fn new(write_state: &SegmentWriteState<D, DW, C>) -> Self {
+    pub fn new(write_state: &SegmentWriteState<D, DW, C>, format: PerFieldPostingsFormat) -> Self {
         PerFieldFieldsWriter {
             write_state: write_state.clone(),
+            format,
         }
     }
 
@@ -197,24 +289,91 @@ impl<D: Directory, DW: Directory, C: Codec> PerFieldFieldsWriter<D, DW, C> {
 }
 
 impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for PerFieldFieldsWriter<D, DW, C> {
-    // TODO this is only one postings format currently (lucene50).
-    // And we assume that we always use one format all the time.
-    // so we won't implement this like lucene
-    // when the format changes, it's easy to change the hard code then.
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
-        // this is only one format, so suffix is always "0"
-        let segment_suffix =
-            self.get_full_segment_suffix(&self.write_state.segment_suffix, "Lucene50_0".into());
-        // always use lucene50, so just hard code it.
-        let format = Lucene50PostingsFormat::default();
-
-        let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
+        // group the fields by format, the default format comes first so that
+        // it keeps the `Lucene50_0` suffix
+        let mut groups: Vec<(PostingsFormatEnum, Vec<String>)> = vec![(
+            PostingsFormatEnum::Lucene50(Lucene50PostingsFormat::default()),
+            vec![],
+        )];
+        for field in fields.fields() {
+            let format = self.format.postings_format_for_field(&field);
+            match groups.iter_mut().find(|(f, _)| *f == format) {
+                Some((_, group)) => group.push(field),
+                None => groups.push((format, vec![field])),
+            }
+        }
 
-        let mut consumer = format.fields_consumer(&self.write_state)?;
-        consumer.write(fields)?;
+        let mut suffixes: HashMap<String, usize> = HashMap::new();
+        for (format, group) in groups {
+            if group.is_empty() {
+                continue;
+            }
+            let format_name = format.name().to_string();
+            let suffix = suffixes.get(&format_name).map_or(0, |s| *s + 1);
+            suffixes.insert(format_name.clone(), suffix);
+            let suffix = suffix.to_string();
 
-        self.write_state.segment_suffix = old_suffix;
+            for field in &group {
+                if let Some(info) = self.write_state.field_infos.field_info_by_name(field) {
+                    info.put_attribute(
+                        PER_FIELD_POSTING_FORMAT_KEY.to_string(),
+                        format_name.clone(),
+                    );
+                    info.put_attribute(PER_FIELD_POSTING_SUFFIX_KEY.to_string(), suffix.clone());
+                }
+            }
 
+            let segment_suffix = self.get_full_segment_suffix(
+                &self.write_state.segment_suffix,
+                get_suffix(&format_name, &suffix),
+            );
+            let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
+            let subset = FieldsSubset {
+                fields,
+                names: group,
+            };
+            match format.fields_consumer(&self.write_state)? {
+                FieldsConsumerEnum::Lucene50(mut consumer) => consumer.write(&subset, norms)?,
+                FieldsConsumerEnum::BloomFilter(mut consumer) => consumer.write(&subset, norms)?,
+                FieldsConsumerEnum::FST(mut consumer) => consumer.write(&subset, norms)?,
+                FieldsConsumerEnum::Completion(mut consumer) => consumer.write(&subset, norms)?,
+                FieldsConsumerEnum::SimpleText(mut consumer) => consumer.write(&subset, norms)?,
+                FieldsConsumerEnum::PerField(_) => {
+                    return Err(Error::IllegalState(format!(
+                        "per field postings format '{}' can't be nested",
+                        format_name
+                    )));
+                }
+            }
+            self.write_state.segment_suffix = old_suffix;
+        }
         Ok(())
     }
 }
+
+/// The fields of one group of `PerFieldFieldsWriter`
+struct FieldsSubset<'a, F: Fields> {
+    fields: &'a F,
+    names: Vec<String>,
+}
+
+impl<'a, F: Fields> Fields for FieldsSubset<'a, F> {
+    type Terms = F::Terms;
+
+    fn fields(&self) -> Vec<String> {
+        self.names.clone()
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<Self::Terms>> {
+        if self.names.iter().any(|n| n == field) {
+            self.fields.terms(field)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.names.len()
+    }
+}
@@ -14,20 +14,25 @@
 use crate::core::codec::postings::blocktree::{BlockTreeTermsReader, BlockTreeTermsWriter};
 use crate::core::codec::postings::{
     FieldsConsumerEnum, Lucene50PostingsReader, Lucene50PostingsWriter, PostingsFormat,
+    VERSION_CURRENT, VERSION_START,
 };
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
 use crate::core::codec::Codec;
 use crate::core::store::directory::Directory;
+use crate::core::util::fst::{FST_VERSION_CURRENT, FST_VERSION_JAVA_LUCENE6};
 
+use crate::error::Error::UnsupportedOperation;
 use crate::Result;
 
 use std::fmt;
 
-#[derive(Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Lucene50PostingsFormat {
     name: &'static str,
     min_term_block_size: usize,
     max_term_block_size: usize,
+    java_format: bool,
+    version: i32,
 }
 
 /// Fixed packed block size, number of integers encoded in
@@ -51,6 +56,10 @@ impl Default for Lucene50PostingsFormat {
 }
 
 impl Lucene50PostingsFormat {
+    /// Creates a format writing between `min_term_block_size` and
+    /// `max_term_block_size` terms per block of the terms dictionary. Smaller
+    /// blocks speed up exact term lookups, e.g. of id fields, at the cost of a
+    /// larger terms index.
     pub fn with_block_size(
         min_term_block_size: usize,
         max_term_block_size: usize,
@@ -59,8 +68,49 @@ impl Lucene50PostingsFormat {
             name: "Lucene50",
             min_term_block_size,
             max_term_block_size,
+            java_format: false,
+            version: VERSION_CURRENT,
         }
     }
+
+    /// Creates a format writing postings and terms dictionaries that Java Lucene 6.5
+    /// and later 6.x releases can read: blocks aren't packed with SIMD instructions
+    /// and the terms index FSTs are saved in the version Java Lucene 6 knows.
+    pub fn java_lucene66() -> Lucene50PostingsFormat {
+        Lucene50PostingsFormat {
+            version: VERSION_START,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a format reading the postings of Java Lucene 8.0 to 8.3 indexes, whose
+    /// skip data holds the impacts of the skipped blocks. Writing isn't supported.
+    pub fn java_lucene80() -> Lucene50PostingsFormat {
+        Lucene50PostingsFormat {
+            java_format: true,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn terms_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<BlockTreeTermsWriter<Lucene50PostingsWriter<DW::IndexOutput>, DW::IndexOutput>>
+    {
+        let postings_writer = Lucene50PostingsWriter::new(state, self.version)?;
+        let fst_version = if self.version == VERSION_START {
+            FST_VERSION_JAVA_LUCENE6
+        } else {
+            FST_VERSION_CURRENT
+        };
+        Ok(BlockTreeTermsWriter::new(
+            state,
+            postings_writer,
+            self.min_term_block_size,
+            self.max_term_block_size,
+        )?
+        .with_fst_version(fst_version))
+    }
 }
 
 impl PostingsFormat for Lucene50PostingsFormat {
@@ -69,7 +119,7 @@ impl PostingsFormat for Lucene50PostingsFormat {
         &self,
         state: &SegmentReadState<'a, D, DW, C>,
     ) -> Result<Self::FieldsProducer> {
-        let reader = Lucene50PostingsReader::open(&state)?;
+        let reader = Lucene50PostingsReader::open(state, self.java_format)?;
         BlockTreeTermsReader::new(reader, state)
     }
 
@@ -77,13 +127,12 @@ impl PostingsFormat for Lucene50PostingsFormat {
         &self,
         state: &SegmentWriteState<D, DW, C>,
     ) -> Result<FieldsConsumerEnum<D, DW, C>> {
-        let postings_writer = Lucene50PostingsWriter::new(state)?;
-        Ok(FieldsConsumerEnum::Lucene50(BlockTreeTermsWriter::new(
-            state,
-            postings_writer,
-            self.min_term_block_size,
-            self.max_term_block_size,
-        )?))
+        if self.java_format {
+            return Err(UnsupportedOperation(
+                "the Java Lucene postings format is read only".into(),
+            ));
+        }
+        Ok(FieldsConsumerEnum::Lucene50(self.terms_writer(state)?))
     }
 
     fn name(&self) -> &str {
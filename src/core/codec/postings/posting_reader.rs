@@ -14,6 +14,7 @@
 use crate::core::codec::field_infos::FieldInfo;
 use crate::core::codec::postings::blocktree::BlockTermState;
 use crate::core::codec::postings::for_util::*;
+use crate::core::codec::postings::impacts::Impacts;
 use crate::core::codec::postings::posting_format::BLOCK_SIZE;
 use crate::core::codec::postings::skip_reader::*;
 use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState};
@@ -56,7 +57,9 @@ pub const PAY_CODEC: &str = "Lucene50PostingsWriterPay";
 
 // Increment version to change it
 pub const VERSION_START: i32 = 0;
-pub const VERSION_CURRENT: i32 = VERSION_START + 1;
+pub const VERSION_SIMD: i32 = VERSION_START + 1;
+pub const VERSION_IMPACTS: i32 = VERSION_SIMD + 1;
+pub const VERSION_CURRENT: i32 = VERSION_IMPACTS;
 
 fn clone_option_index_input(input: &Option<Box<dyn IndexInput>>) -> Result<Box<dyn IndexInput>> {
     debug_assert!(input.is_some());
@@ -74,6 +77,10 @@ pub struct Lucene50PostingsReader {
     pub version: i32,
     pub for_util: ForUtil,
     use_simd: bool,
+    skip_has_impacts: bool,
+    /// Whether the skip data hold impacts `Impacts` can decode, the ones of Java
+    /// Lucene are only skipped over.
+    has_impacts: bool,
 }
 
 impl Lucene50PostingsReader {
@@ -83,8 +90,12 @@ impl Lucene50PostingsReader {
     fn clone_pay_in(&self) -> Result<Box<dyn IndexInput>> {
         clone_option_index_input(&self.pay_in)
     }
+    /// Opens the postings of a segment, `java_format` tells that they were written by
+    /// Java Lucene, whose version 1 files hold impacts in the skip data rather than
+    /// SIMD encoded blocks, ours only hold them since `VERSION_IMPACTS`.
     pub fn open<D: Directory, DW: Directory, C: Codec>(
         state: &SegmentReadState<'_, D, DW, C>,
+        java_format: bool,
     ) -> Result<Lucene50PostingsReader> {
         let doc_name = segment_file_name(
             &state.segment_info.name,
@@ -101,11 +112,17 @@ impl Lucene50PostingsReader {
             &state.segment_suffix,
         )?;
 
-        let use_simd = if version > VERSION_START && SIMD128Packer::is_support() {
+        let use_simd = if !java_format && version > VERSION_START && SIMD128Packer::is_support() {
             true
         } else {
             false
         };
+        let skip_has_impacts = if java_format {
+            version > VERSION_START
+        } else {
+            version >= VERSION_IMPACTS
+        };
+        let has_impacts = !java_format && skip_has_impacts;
 
         let for_util = ForUtil::with_input(doc_in.as_mut())?;
         codec_util::retrieve_checksum(doc_in.as_mut())?;
@@ -155,6 +172,8 @@ impl Lucene50PostingsReader {
             version,
             for_util,
             use_simd,
+            skip_has_impacts,
+            has_impacts,
         })
     }
 
@@ -209,6 +228,7 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    self.skip_has_impacts,
                 )?,
             )))
         } else if (!index_has_offsets
@@ -225,6 +245,7 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    self.skip_has_impacts,
                 )?,
             )))
         } else {
@@ -240,11 +261,39 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    self.skip_has_impacts,
                 )?),
             ))
         }
     }
 
+    /// Returns the impacts of the postings of a term, they have no levels when the
+    /// postings are too short to have skip data or were written without impacts.
+    pub fn impacts(&self, field_info: &FieldInfo, state: &BlockTermState) -> Result<Impacts> {
+        if !self.has_impacts || state.doc_freq <= BLOCK_SIZE {
+            return Ok(Impacts::new(None));
+        }
+        debug_assert_ne!(state.skip_offset, -1);
+        let options = &field_info.index_options;
+        let mut skipper = Lucene50SkipReader::new(
+            self.doc_in.clone()?,
+            MAX_SKIP_LEVELS,
+            options.has_positions(),
+            options.has_offsets(),
+            field_info.has_store_payloads,
+            true,
+        );
+        skipper.keep_impacts();
+        skipper.init(
+            state.doc_start_fp + state.skip_offset,
+            state.doc_start_fp,
+            state.pos_start_fp,
+            state.pay_start_fp,
+            state.doc_freq,
+        )?;
+        Ok(Impacts::new(Some(skipper)))
+    }
+
     pub fn check_integrity(&self) -> Result<()> {
         //        codec_util::checksum_entire_file(self.doc_in.as_ref())?;
         //
@@ -405,6 +454,7 @@ struct BlockDocIterator {
     #[allow(dead_code)]
     partial_freqs: PartialBlockDecoder,
     use_simd: bool,
+    skip_has_impacts: bool,
 }
 
 impl BlockDocIterator {
@@ -415,6 +465,7 @@ impl BlockDocIterator {
         flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        skip_has_impacts: bool,
     ) -> Result<BlockDocIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockDocIterator {
@@ -453,6 +504,7 @@ impl BlockDocIterator {
             partial_doc_deltas: PartialBlockDecoder::new(),
             partial_freqs: PartialBlockDecoder::new(),
             use_simd,
+            skip_has_impacts,
         };
         iterator.reset(term_state, flags)?;
         Ok(iterator)
@@ -664,6 +716,7 @@ impl DocIterator for BlockDocIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.skip_has_impacts,
                 ));
             }
 
@@ -817,6 +870,7 @@ impl SIMDBlockDocIterator {
                 flags,
                 for_util,
                 false,
+                false,
             )?,
             simd_doc_deltas: SIMDBlockDecoder::new(),
             total_base: 0,
@@ -921,6 +975,7 @@ impl DocIterator for SIMDBlockDocIterator {
                     self.doc_iter.index_has_pos,
                     self.doc_iter.index_has_offsets,
                     self.doc_iter.index_has_payloads,
+                    self.doc_iter.skip_has_impacts,
                 ));
             }
 
@@ -1120,9 +1175,11 @@ struct BlockPostingIterator {
     bits_min_doc: DocId,
     bits_index: i32,
     use_simd: bool,
+    skip_has_impacts: bool,
 }
 
 impl BlockPostingIterator {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_doc_in: Box<dyn IndexInput>,
         pos_in: Box<dyn IndexInput>,
@@ -1131,6 +1188,7 @@ impl BlockPostingIterator {
         _flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        skip_has_impacts: bool,
     ) -> Result<BlockPostingIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockPostingIterator {
@@ -1173,6 +1231,7 @@ impl BlockPostingIterator {
             bits_min_doc: 0,
             bits_index: 0,
             use_simd,
+            skip_has_impacts,
         };
         iterator.reset(term_state)?;
         Ok(iterator)
@@ -1453,6 +1512,7 @@ impl DocIterator for BlockPostingIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.skip_has_impacts,
                 ));
             }
 
@@ -1692,6 +1752,7 @@ struct EverythingIterator {
     bits_min_doc: DocId,
     bits_index: i32,
     use_simd: bool,
+    skip_has_impacts: bool,
 }
 
 impl<'a> EverythingIterator {
@@ -1705,6 +1766,7 @@ impl<'a> EverythingIterator {
         flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        skip_has_impacts: bool,
     ) -> Result<EverythingIterator> {
         let encoded = [0u8; MAX_ENCODED_SIZE];
         let index_has_offsets = field_info.index_options.has_offsets();
@@ -1776,6 +1838,7 @@ impl<'a> EverythingIterator {
             bits_min_doc: 0,
             bits_index: 0,
             use_simd,
+            skip_has_impacts,
         };
 
         iterator.reset(term_state, flags)?;
@@ -2199,6 +2262,7 @@ impl DocIterator for EverythingIterator {
                     true,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.skip_has_impacts,
                 ));
             }
 
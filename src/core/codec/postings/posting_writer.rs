@@ -11,9 +11,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::codec::field_infos::FieldInfo;
 use crate::core::codec::postings::blocktree::BlockTermState;
 use crate::core::codec::postings::for_util::*;
+use crate::core::codec::postings::impacts::CompetitiveImpactAccumulator;
 use crate::core::codec::postings::posting_format::BLOCK_SIZE;
 use crate::core::codec::postings::posting_reader::*;
 use crate::core::codec::postings::skip_writer::Lucene50SkipWriter;
@@ -97,6 +99,8 @@ pub struct Lucene50PostingsWriter<O: IndexOutput> {
     encoded: Vec<u8>,
     for_util: ForUtil,
     skip_writer: Lucene50SkipWriter,
+    /// the competitive freq and norm pairs of the docs of the current block
+    competitive_freq_norms: CompetitiveImpactAccumulator,
 
     // fields from PushPostingsWriterBase
     // Reused in writeTerm
@@ -110,12 +114,23 @@ pub struct Lucene50PostingsWriter<O: IndexOutput> {
     write_offsets: bool,
     ef_writer_meta: EfWriterMeta,
     use_simd: bool,
+    version: i32,
 }
 
 impl<O: IndexOutput> Lucene50PostingsWriter<O> {
+    /// Creates a writer of postings in `version`; blocks are only packed with SIMD
+    /// instructions after `VERSION_START`, which is the version Java Lucene writes,
+    /// and impacts are only written since `VERSION_IMPACTS`.
     pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
         state: &SegmentWriteState<D, DW, C>,
+        version: i32,
     ) -> Result<Self> {
+        if !(VERSION_START..=VERSION_CURRENT).contains(&version) {
+            return Err(Error::IllegalArgument(format!(
+                "unsupported postings version: {}",
+                version
+            )));
+        }
         let acceptable_overhead_ratio = COMPACT;
 
         let doc_file_name = segment_file_name(
@@ -136,7 +151,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         write_index_header(
             &mut doc_out,
             DOC_CODEC,
-            VERSION_CURRENT,
+            version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -156,7 +171,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             write_index_header(
                 pos_out.as_mut().unwrap(),
                 POS_CODEC,
-                VERSION_CURRENT,
+                version,
                 state.segment_info.get_id(),
                 &state.segment_suffix,
             )?;
@@ -183,7 +198,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
                 write_index_header(
                     pay_out.as_mut().unwrap(),
                     PAY_CODEC,
-                    VERSION_CURRENT,
+                    version,
                     state.segment_info.get_id(),
                     &state.segment_suffix,
                 )?;
@@ -196,9 +211,10 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             state.segment_info.max_doc() as u32,
             pos_out.is_some(),
             pay_out.is_some(),
+            version >= VERSION_IMPACTS,
         );
 
-        let use_simd = if VERSION_CURRENT > VERSION_START && SIMD128Packer::is_support() {
+        let use_simd = if version > VERSION_START && SIMD128Packer::is_support() {
             true
         } else {
             false
@@ -240,6 +256,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             encoded: vec![0u8; MAX_ENCODED_SIZE],
             for_util,
             skip_writer,
+            competitive_freq_norms: CompetitiveImpactAccumulator::default(),
             enum_flags: 0,
             write_freqs: false,
             write_positions: false,
@@ -247,6 +264,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             write_offsets: false,
             ef_writer_meta: EfWriterMeta::new(),
             use_simd,
+            version,
         })
     }
 
@@ -297,17 +315,19 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         }
         self.last_doc_id = 0;
         self.last_block_doc_id = -1;
+        self.competitive_freq_norms.clear();
         self.skip_writer
             .reset_skip(self.doc_start_fp, self.pos_start_fp, self.pay_start_fp);
     }
 
-    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32) -> Result<()> {
+    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32, norm: i64) -> Result<()> {
         // Have collected a block of docs, and get a new doc.
         // Should write skip data as well as postings list for
         // current block.
         if self.last_block_doc_id != -1 && self.doc_buffer_upto == 0 {
             self.skip_writer.buffer_skip(
                 self.last_block_doc_id,
+                &self.competitive_freq_norms,
                 self.doc_count as u32,
                 self.last_block_pos_fp,
                 self.last_block_pay_fp,
@@ -315,6 +335,7 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
                 self.last_block_payload_byte_upto,
                 self.doc_out.file_pointer(),
             )?;
+            self.competitive_freq_norms.clear();
         }
 
         let doc_delta = doc_id - self.last_doc_id;
@@ -330,6 +351,8 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
 
         self.doc_buffer_upto += 1;
         self.doc_count += 1;
+        let freq = if self.write_freqs { term_doc_freq } else { 1 };
+        self.competitive_freq_norms.add(freq, norm);
 
         if self.doc_buffer_upto == BLOCK_SIZE as usize {
             self.ef_writer_meta.ef_upper_doc = doc_id;
@@ -600,7 +623,7 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
         write_index_header(
             terms_out,
             TERMS_CODEC,
-            VERSION_CURRENT,
+            self.version,
             state.segment_info.get_id(),
             &state.segment_suffix,
         )?;
@@ -625,6 +648,7 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
         docs_seen: &mut FixedBitSet,
         doc_freq_limit: i32,
         term_freq_limit: i32,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<Option<BlockTermState>> {
         self.start_term();
         let mut postings_enum = terms.postings_with_flags(self.enum_flags)?;
@@ -644,7 +668,11 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
             } else {
                 -1
             };
-            self.start_doc(doc_id, freq)?;
+            let norm = match norms {
+                Some(norms) => norms.get(doc_id)?,
+                None => 0,
+            };
+            self.start_doc(doc_id, freq, norm)?;
 
             if self.write_positions {
                 for _ in 0..freq {
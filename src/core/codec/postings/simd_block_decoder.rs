@@ -79,6 +79,7 @@ impl SIMDBlockDecoder {
         num: usize,
         bits_num: usize,
     ) -> Result<()> {
+        input.prefill(num)?;
         let encoded = unsafe { input.get_and_advance(num) };
         self.next_index = 0;
         self.packer.delta_unpack(
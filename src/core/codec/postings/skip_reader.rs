@@ -175,6 +175,12 @@ pub struct Lucene50SkipReader {
     last_payload_byte_upto: i32,
     last_doc_pointer: i64,
     last_pos_buffer_upto: i32,
+
+    /// Whether skip entries end with the impacts of the skipped block.
+    has_impacts: bool,
+    /// The impacts of the current skip entry of each level, only kept to read
+    /// `Impacts`, they are skipped over otherwise.
+    impact_data: Option<Vec<Vec<u8>>>,
 }
 
 impl Lucene50SkipReader {
@@ -216,6 +222,8 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: self.last_payload_byte_upto,
             last_doc_pointer: self.last_doc_pointer,
             last_pos_buffer_upto: self.last_pos_buffer_upto,
+            has_impacts: self.has_impacts,
+            impact_data: self.impact_data.clone(),
         })
     }
 
@@ -225,6 +233,7 @@ impl Lucene50SkipReader {
         has_pos: bool,
         has_offsets: bool,
         has_payloads: bool,
+        has_impacts: bool,
     ) -> Lucene50SkipReader {
         // fields for MultiLevelSkipReader part
         let max_number_of_skip_levels = max_skip_levels;
@@ -295,9 +304,17 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: 0,
             last_doc_pointer: 0,
             last_pos_buffer_upto: 0,
+            has_impacts,
+            impact_data: None,
         }
     }
 
+    /// Keeps the impacts of the skip entries, see `impact_data`.
+    pub(crate) fn keep_impacts(&mut self) {
+        debug_assert!(self.has_impacts);
+        self.impact_data = Some(vec![vec![]; self.max_number_of_skip_levels as usize]);
+    }
+
     /// Trim original docFreq to tell skipReader read proper number of skip points.
     ///
     /// Since our definition in Lucene50Skip* is a little different from MultiLevelSkip*
@@ -381,6 +398,22 @@ impl Lucene50SkipReader {
         self.skip_doc[0]
     }
 
+    /// The doc of the current skip entry of `level`.
+    pub(crate) fn skip_doc(&self, level: usize) -> DocId {
+        self.skip_doc[level]
+    }
+
+    /// The number of levels with a current skip entry, zero once the skip list is
+    /// exhausted.
+    pub(crate) fn impact_levels(&self) -> usize {
+        self.number_of_skip_levels.max(0) as usize
+    }
+
+    /// The encoded impacts of the current skip entry of `level`.
+    pub(crate) fn impact_data(&self, level: usize) -> &[u8] {
+        self.impact_data.as_ref().map_or(&[], |data| &data[level])
+    }
+
     /// Seeks the skip entry on the given level
     pub fn seek_child(&mut self, level: i32) -> Result<()> {
         let ulevel = level as usize;
@@ -449,6 +482,16 @@ impl Lucene50SkipReader {
                 self.pay_pointer.as_mut().unwrap()[level] += pointer;
             }
         }
+        if self.has_impacts {
+            let stream = self.skip_stream[level].as_mut().unwrap();
+            let length = stream.read_vint()? as usize;
+            if let Some(data) = &mut self.impact_data {
+                data[level].resize(length, 0);
+                stream.read_exact(&mut data[level])?;
+            } else {
+                stream.skip_bytes(length)?;
+            }
+        }
         Ok(delta)
     }
 
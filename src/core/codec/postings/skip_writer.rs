@@ -13,6 +13,7 @@
 
 use std::cmp::min;
 
+use crate::core::codec::postings::impacts::{write_impacts, CompetitiveImpactAccumulator};
 use crate::core::store::io::{DataOutput, IndexOutput, RAMOutputStream};
 use crate::core::util::{fill_slice, log, DocId};
 
@@ -37,6 +38,7 @@ const SKIP_MULTIPLIER: u32 = 8;
 /// 2. its related file points(position, payload),
 /// 3. related numbers or uptos(position, payload).
 /// 4. start offset.
+/// 5. the competitive impacts of the skipped docs, when written.
 pub struct Lucene50SkipWriter {
     last_skip_doc: Vec<i32>,
     last_skip_doc_pointer: Vec<i64>,
@@ -55,6 +57,11 @@ pub struct Lucene50SkipWriter {
     field_has_positions: bool,
     field_has_offsets: bool,
     field_has_payloads: bool,
+    /// the competitive freq and norm pairs of the docs skipped by the current
+    /// entry of each level
+    cur_competitive_freq_norms: Vec<CompetitiveImpactAccumulator>,
+    write_impacts: bool,
+    impacts_out: RAMOutputStream,
 
     // fields from MultiLevelSkipListWriter
     /// number of levels in this skip list
@@ -84,6 +91,7 @@ impl Lucene50SkipWriter {
         doc_count: u32,
         write_pos: bool,
         write_pay: bool,
+        write_impacts: bool,
     ) -> Self {
         debug_assert!(block_size > 1);
         let number_of_skip_levels = if doc_count <= block_size {
@@ -115,6 +123,12 @@ impl Lucene50SkipWriter {
             field_has_positions: false,
             field_has_offsets: false,
             field_has_payloads: false,
+            cur_competitive_freq_norms: vec![
+                CompetitiveImpactAccumulator::default();
+                max_skip_levels
+            ],
+            write_impacts,
+            impacts_out: RAMOutputStream::new(false),
             skip_interval: block_size,
             skip_multiplier: SKIP_MULTIPLIER,
             number_of_skip_levels: min(max_skip_levels, number_of_skip_levels),
@@ -159,6 +173,9 @@ impl Lucene50SkipWriter {
                     fill_slice(&mut self.last_skip_pay_pointer, self.last_pay_fp);
                 }
             }
+            for acc in &mut self.cur_competitive_freq_norms {
+                acc.clear();
+            }
             self.initialized = true;
         }
     }
@@ -187,6 +204,7 @@ impl Lucene50SkipWriter {
     pub fn buffer_skip(
         &mut self,
         doc: DocId,
+        competitive_freq_norms: &CompetitiveImpactAccumulator,
         num_docs: u32,
         pos_fp: i64,
         pay_fp: i64,
@@ -201,6 +219,7 @@ impl Lucene50SkipWriter {
         self.cur_pay_pointer = pay_fp;
         self.cur_pos_buffer_upto = pos_buffer_upto;
         self.cur_payload_byte_upto = payload_byte_upto;
+        self.cur_competitive_freq_norms[0].add_all(competitive_freq_norms);
         self.buffer_skip_levels(num_docs)
     }
 
@@ -285,6 +304,21 @@ impl Lucene50SkipWriter {
             }
         }
 
+        if self.write_impacts {
+            // the entry of the upper level skips the docs of this one
+            if level + 1 < self.number_of_skip_levels {
+                let (lower, upper) = self.cur_competitive_freq_norms.split_at_mut(level + 1);
+                upper[0].add_all(&lower[level]);
+            }
+            let impacts = self.cur_competitive_freq_norms[level].competitive_impacts();
+            debug_assert!(!impacts.is_empty());
+            write_impacts(&impacts, &mut self.impacts_out)?;
+            self.skip_buffer[level].write_vint(self.impacts_out.file_pointer() as i32)?;
+            self.impacts_out.write_to(&mut self.skip_buffer[level])?;
+            self.impacts_out.reset();
+            self.cur_competitive_freq_norms[level].clear();
+        }
+
         Ok(())
     }
 }
@@ -91,6 +91,10 @@ impl TermsHashBase {
     pub fn need_flush(&self) -> bool {
         self.int_pool.need_flush
     }
+
+    pub fn bytes_used(&self) -> usize {
+        self.int_pool.bytes_used() + self.byte_pool.bytes_used()
+    }
 }
 
 pub trait TermsHash<D: Directory, C: Codec> {
@@ -289,6 +293,7 @@ where
                 .codec()
                 .postings_format()
                 .fields_consumer(state)?;
+            let norms = norms.map(|n| n as &dyn NormsProducer);
 
             if let Some(sort_map) = sort_map {
                 let fields = SortingFields::new(
@@ -296,9 +301,9 @@ where
                     Arc::new(state.field_infos.clone()),
                     Arc::clone(sort_map),
                 );
-                consumer.write(&fields)
+                consumer.write(&fields, norms)
             } else {
-                consumer.write(&fields)
+                consumer.write(&fields, norms)
             }
         } else {
             Ok(())
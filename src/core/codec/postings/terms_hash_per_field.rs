@@ -30,7 +30,7 @@ use crate::core::util::{INT_BLOCK_MASK, INT_BLOCK_SHIFT, INT_BLOCK_SIZE};
 use std::cmp::{max, Ordering};
 
 use crate::Result;
-use std::mem::MaybeUninit;
+use std::mem::{self, MaybeUninit};
 use std::ptr;
 
 const HASH_INIT_SIZE: usize = 4;
@@ -109,6 +109,20 @@ impl<T: PostingsArray + 'static> TermsHashPerFieldBase<T> {
         }
     }
 
+    /// The RAM used by the postings array and the terms hash of this field,
+    /// the postings themselves are accounted in the shared pools.
+    pub fn bytes_used(&self) -> usize {
+        let postings =
+            self.postings_array.parallel_array().size * self.postings_array.bytes_per_posting();
+        if self.inited {
+            postings
+                + unsafe { self.bytes_hash.assume_init_ref().ids.capacity() }
+                    * mem::size_of::<i32>()
+        } else {
+            postings
+        }
+    }
+
     pub fn int_pool(&self) -> &IntBlockPool {
         unsafe { &*self.int_pool }
     }
@@ -295,7 +309,12 @@ pub trait TermsHashPerField: Ord + PartialOrd + Eq + PartialEq {
         // term text into text_start address
         let bytes_ref = BytesRef::new(&token_stream.token().term);
 
-        let term_id = unsafe { self.base_mut().bytes_hash.assume_init_mut().add(&bytes_ref) };
+        let term_id = unsafe {
+            self.base_mut()
+                .bytes_hash
+                .assume_init_mut()
+                .add(&bytes_ref)?
+        };
         if term_id >= 0 {
             unsafe {
                 self.base_mut()
@@ -13,14 +13,18 @@
 
 mod segment_infos;
 
+pub use self::segment_infos::SegmentInfos;
+
 pub(crate) use self::segment_infos::{
-    generation_from_segments_file_name, get_last_commit_segments_filename, get_segment_file_name,
-    run_with_find_segment_file, SegmentInfos,
+    generation_from_segments_file_name, get_last_commit_generation,
+    get_last_commit_segments_filename, run_with_find_segment_file,
 };
 
 mod segment_infos_format;
 
-pub use self::segment_infos_format::{Lucene62SegmentInfoFormat, SegmentInfoFormat};
+pub use self::segment_infos_format::{
+    Lucene62SegmentInfoFormat, Lucene70SegmentInfoFormat, SegmentInfoFormat,
+};
 
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
@@ -34,7 +38,8 @@ use std::sync::Arc;
 use regex::Regex;
 
 use crate::core::codec::field_infos::FieldInfos;
-use crate::core::codec::{Codec, LiveDocsFormat};
+use crate::core::codec::simple_text::{check_checksum, is_simple_text_file};
+use crate::core::codec::{checksum_entire_file, Codec, LiveDocsFormat};
 use crate::core::index::writer::BufferedUpdates;
 use crate::core::search::sort_field::Sort;
 use crate::core::store::directory::Directory;
@@ -45,7 +50,7 @@ use crate::core::util::Version;
 use crate::core::util::ID_LENGTH;
 
 use crate::error::{
-    Error::{IllegalArgument, IllegalState},
+    Error::{CorruptIndex, IllegalArgument, IllegalState},
     Result,
 };
 
@@ -583,6 +588,30 @@ impl<D: Directory, C: Codec> SegmentCommitInfo<D, C> {
         size
     }
 
+    /// Verifies the checksum footer of every file of this segment, including
+    /// live docs and doc values update files.
+    ///
+    /// Returns the number of files checked, or `CorruptIndex` naming the first
+    /// file whose checksum doesn't match.
+    pub fn check_integrity(&self) -> Result<usize> {
+        let files = self.files();
+        let simple_text = self.info.codec().name() == "SimpleText";
+        for file in &files {
+            if simple_text && is_simple_text_file(file) {
+                check_checksum(self.info.directory.as_ref(), file)
+                    .map_err(|e| CorruptIndex(format!("file {}: {}", file, e)))?;
+                continue;
+            }
+            let input = self
+                .info
+                .directory
+                .open_input(file, &IOContext::READ_ONCE)?;
+            checksum_entire_file(input.as_ref())
+                .map_err(|e| CorruptIndex(format!("file {}: {}", file, e)))?;
+        }
+        Ok(files.len())
+    }
+
     pub fn buffered_deletes_gen(&self) -> i64 {
         self.buffered_deletes_gen.load(AtomicOrdering::Acquire)
     }
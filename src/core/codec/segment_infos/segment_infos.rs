@@ -45,6 +45,14 @@ const SEGMENT_VERSION_51: i32 = 5;
 /// Adds the {@link Version} that committed this segments_N file, as well as the {@link Version} of
 /// the oldest segment, since 5.3+
 const SEGMENT_VERSION_53: i32 = 6;
+/// Records the major version the index was created with, and drops the hasID byte of
+/// segments, written by Java Lucene 7.0+ and only read
+const SEGMENT_VERSION_70: i32 = 7;
+/// The segment name counter is a vlong, written by Java Lucene 7.2+ and only read
+const SEGMENT_VERSION_72: i32 = 8;
+/// Records the number of soft deleted documents of segments, written by Java Lucene 7.4+
+/// and only read
+const SEGMENT_VERSION_74: i32 = 9;
 
 const SEGMENT_VERSION_CURRENT: i32 = SEGMENT_VERSION_53;
 
@@ -76,6 +84,9 @@ pub struct SegmentInfos<D: Directory, C: Codec> {
     pub lucene_version: Option<Version>,
     /// Version of the oldest segment in the index, or null if there are no segments.
     pub min_seg_version: Option<Version>,
+    /// Opaque map of strings that the user stored with the commit, see
+    /// `IndexWriter::set_live_commit_data`.
+    pub user_data: HashMap<String, String>,
     // Only true after prepareCommit has been called and
     // before finishCommit is called
     pending_commit: bool,
@@ -92,6 +103,7 @@ impl<D: Directory, C: Codec> Default for SegmentInfos<D, C> {
             id: [0u8; ID_LENGTH],
             lucene_version: None,
             min_seg_version: None,
+            user_data: HashMap::new(),
             pending_commit: false,
         }
     }
@@ -119,6 +131,7 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
             id,
             lucene_version,
             min_seg_version,
+            user_data: HashMap::new(),
             pending_commit: false,
         }
     }
@@ -301,7 +314,7 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
                 output.write_set_of_strings(files)?;
             }
         }
-        output.write_map_of_strings(&HashMap::with_capacity(0))?;
+        output.write_map_of_strings(&self.user_data)?;
         write_footer(output)
     }
 
@@ -466,12 +479,8 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
             return Err(Error::RuntimeError("invalid magic number".into()));
         }
 
-        let format = check_header_no_magic(
-            input,
-            "segments",
-            SEGMENT_VERSION_50,
-            SEGMENT_VERSION_CURRENT,
-        )?;
+        let format =
+            check_header_no_magic(input, "segments", SEGMENT_VERSION_50, SEGMENT_VERSION_74)?;
 
         let mut id = [0; ID_LENGTH];
         input.read_exact(&mut id)?;
@@ -485,8 +494,16 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
         } else {
             None
         };
+        if format >= SEGMENT_VERSION_70 {
+            // index created version major, rucene doesn't keep track of it
+            input.read_vint()?;
+        }
         let version = input.read_long()?;
-        let counter = input.read_int()?;
+        let counter = if format > SEGMENT_VERSION_70 {
+            input.read_vlong()?
+        } else {
+            i64::from(input.read_int()?)
+        };
         let num_segs = input.read_int()?;
         if num_segs < 0 {
             return Err(Error::RuntimeError(
@@ -507,11 +524,13 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
         let mut segments = Vec::new();
         for _sge in 0..num_segs {
             let seg_name = input.read_string()?;
-            let has_id = input.read_byte()?;
-            if has_id != 1u8 {
-                return Err(Error::RuntimeError(
-                    format!("invalid hasID byte, got: {}", has_id).into(),
-                ));
+            if format < SEGMENT_VERSION_70 {
+                let has_id = input.read_byte()?;
+                if has_id != 1u8 {
+                    return Err(Error::RuntimeError(
+                        format!("invalid hasID byte, got: {}", has_id).into(),
+                    ));
+                }
             }
             let mut segment_id = [0; ID_LENGTH];
             input.read_exact(&mut segment_id)?;
@@ -538,6 +557,10 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
             }
             let field_infos_gen = input.read_long()?;
             let dv_gen = input.read_long()?;
+            if format > SEGMENT_VERSION_72 {
+                // soft deletes are not supported, soft deleted documents stay live
+                input.read_int()?;
+            }
             let field_infos_files = input.read_set_of_strings()?;
             let num_dv_fields = input.read_int()?;
             let dv_update_files = if num_dv_fields == 0 {
@@ -564,9 +587,9 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
                 // TODO check version
             }
         }
-        let _user_data = input.read_map_of_strings();
+        let user_data = input.read_map_of_strings()?;
 
-        Ok(SegmentInfos::new(
+        let mut infos = SegmentInfos::new(
             counter as i32,
             version,
             generation,
@@ -575,7 +598,9 @@ impl<D: Directory, C: Codec> SegmentInfos<D, C> {
             id,
             lucene_version,
             min_seg_ver,
-        ))
+        );
+        infos.user_data = user_data;
+        Ok(infos)
     }
 
     pub fn read_latest_commit(directory: &Arc<D>) -> Result<Self> {
@@ -603,6 +628,7 @@ impl<D: Directory, C: Codec> Clone for SegmentInfos<D, C> {
             id,
             lucene_version: self.lucene_version,
             min_seg_version: self.min_seg_version,
+            user_data: self.user_data.clone(),
             pending_commit: self.pending_commit,
         }
     }
@@ -659,47 +685,6 @@ fn read_codec<T: Codec>(input: &mut dyn IndexInput, _unsupported_allowed: bool)
     T::try_from(name)
 }
 
-/// Utility function for executing code that needs to do
-/// something with the current segments file.  This is
-/// necessary with lock-less commits because from the time
-/// you locate the current segments file name, until you
-/// actually open it, read its contents, or check modified
-/// time, etc., it could have been deleted due to a writer
-/// commit finishing.
-///
-/// NOTE: this function is copy from lucene class `FindSegmentsFile` but
-/// only return the segment file name ,thus the abstract logic should be done
-/// by the app the call this.
-///
-/// there won't be a trait like `FindSegmentsFile` because of useless for rust
-pub fn get_segment_file_name<D: Directory>(directory: &D) -> Result<String> {
-    // TODO currently not support IndexCommit
-
-    let mut last_gen;
-    let mut gen = -1;
-    loop {
-        last_gen = gen;
-        let files = directory.list_all()?;
-        let files2 = directory.list_all()?;
-        if !&files.eq(&files2) {
-            continue;
-        }
-
-        gen = get_last_commit_generation(&files)?;
-        if gen == -1 {
-            return Err(Error::RuntimeError(
-                format!("no segments* file found in directory: files: {:?}", files).into(),
-            ));
-        } else if gen > last_gen {
-            return Ok(file_name_from_generation(
-                &INDEX_FILE_SEGMENTS,
-                "",
-                gen as u64,
-            ));
-        }
-    }
-}
-
 /// Utility function for executing code that needs to do
 /// something with the current segments file.  This is
 /// necessary with lock-less commits because from the time
@@ -15,7 +15,7 @@ use crate::core::codec::segment_infos::{
     parse_segment_name, segment_file_name, SegmentInfo, SEGMENT_USE_COMPOUND_NO,
     SEGMENT_USE_COMPOUND_YES,
 };
-use crate::core::codec::{codec_util, Codec};
+use crate::core::codec::{codec_util, Codec, Sorter};
 use crate::core::search::sort_field::{
     SimpleSortField, Sort, SortField, SortFieldType, SortedNumericSelectorType,
     SortedNumericSortField, SortedSetSelectorType,
@@ -27,7 +27,7 @@ use crate::core::store::io::{
 use crate::core::store::IOContext;
 use crate::core::util::ID_LENGTH;
 use crate::core::util::{VariantValue, Version};
-use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState};
+use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState, UnsupportedOperation};
 use crate::Result;
 use std::sync::Arc;
 
@@ -65,6 +65,10 @@ const VERSION_START: i32 = 0;
 const VERSION_MULTI_VALUED_SORT: i32 = 1;
 const VERSION_CURRENT: i32 = VERSION_MULTI_VALUED_SORT;
 
+const LUCENE70_CODEC_NAME: &str = "Lucene70SegmentInfo";
+const LUCENE70_VERSION_START: i32 = 0;
+const LUCENE70_VERSION_CURRENT: i32 = LUCENE70_VERSION_START;
+
 fn read_segment_info_from_index<D: Directory, C: Codec>(
     input: &mut dyn IndexInput,
     dir: &Arc<D>,
@@ -76,6 +80,50 @@ fn read_segment_info_from_index<D: Directory, C: Codec>(
     let minor = input.read_int()?;
     let bugfix = input.read_int()?;
     let version = Version::new(major, minor, bugfix)?;
+    read_segment_info_fields(input, dir, segment, id, version)
+}
+
+fn read_lucene70_segment_info_from_index<D: Directory, C: Codec>(
+    input: &mut dyn IndexInput,
+    dir: &Arc<D>,
+    segment: &str,
+    id: [u8; ID_LENGTH],
+) -> Result<SegmentInfo<D, C>> {
+    codec_util::check_index_header(
+        input,
+        LUCENE70_CODEC_NAME,
+        LUCENE70_VERSION_START,
+        LUCENE70_VERSION_CURRENT,
+        &id,
+        "",
+    )?;
+    let major = input.read_int()?;
+    let minor = input.read_int()?;
+    let bugfix = input.read_int()?;
+    let version = Version::new(major, minor, bugfix)?;
+    // the min version of the segments merged into this one, not used by rucene
+    match input.read_byte()? {
+        0 => {}
+        1 => {
+            for _ in 0..3 {
+                input.read_int()?;
+            }
+        }
+        b => {
+            return Err(CorruptIndex(format!("invalid hasMinVersion byte: {}", b)));
+        }
+    }
+    read_segment_info_fields(input, dir, segment, id, version)
+}
+
+// The fields following the version, which are shared by all formats
+fn read_segment_info_fields<D: Directory, C: Codec>(
+    input: &mut dyn IndexInput,
+    dir: &Arc<D>,
+    segment: &str,
+    id: [u8; ID_LENGTH],
+    version: Version,
+) -> Result<SegmentInfo<D, C>> {
     let doc_count = input.read_int()?;
     if doc_count < 0 {
         return Err(CorruptIndex(format!("invalid docCount: {}", doc_count)));
@@ -398,22 +446,34 @@ impl SegmentInfoFormat for Lucene62SegmentInfoFormat {
                 if let Some(missing_value) = sort_field.missing_value() {
                     match missing_value {
                         VariantValue::Long(l) => {
-                            debug_assert_eq!(sort_field.field_type(), SortFieldType::Long);
+                            debug_assert_eq!(
+                                Sorter::sort_field_type(sort_field),
+                                SortFieldType::Long
+                            );
                             output.write_byte(1)?;
                             output.write_long(*l)?;
                         }
                         VariantValue::Int(i) => {
-                            debug_assert_eq!(sort_field.field_type(), SortFieldType::Int);
+                            debug_assert_eq!(
+                                Sorter::sort_field_type(sort_field),
+                                SortFieldType::Int
+                            );
                             output.write_byte(1)?;
                             output.write_int(*i)?;
                         }
                         VariantValue::Double(d) => {
-                            debug_assert_eq!(sort_field.field_type(), SortFieldType::Double);
+                            debug_assert_eq!(
+                                Sorter::sort_field_type(sort_field),
+                                SortFieldType::Double
+                            );
                             output.write_byte(1)?;
                             output.write_long((*d).to_bits() as i64)?;
                         }
                         VariantValue::Float(f) => {
-                            debug_assert_eq!(sort_field.field_type(), SortFieldType::Float);
+                            debug_assert_eq!(
+                                Sorter::sort_field_type(sort_field),
+                                SortFieldType::Float
+                            );
                             output.write_byte(1)?;
                             output.write_int((*f).to_bits() as i32)?;
                         }
@@ -435,3 +495,45 @@ impl SegmentInfoFormat for Lucene62SegmentInfoFormat {
         codec_util::write_footer(&mut output)
     }
 }
+
+/// The segment info format of Java Lucene 7.0 to 8.5 indexes, which also records the
+/// min version of the segments a segment was merged from.
+///
+/// It is only used to read indexes written by Java Lucene, see `Lucene80Codec`.
+#[derive(Copy, Clone, Default)]
+pub struct Lucene70SegmentInfoFormat;
+
+impl SegmentInfoFormat for Lucene70SegmentInfoFormat {
+    fn read<D: Directory, C: Codec>(
+        &self,
+        directory: &Arc<D>,
+        segment_name: &str,
+        segment_id: [u8; ID_LENGTH],
+        context: &IOContext,
+    ) -> Result<SegmentInfo<D, C>> {
+        let file_name = segment_file_name(segment_name, "", SI_EXTENSION);
+        let original_input = directory.open_input(&file_name, context)?;
+        let mut checksum = BufferedChecksumIndexInput::new(original_input);
+        let segment_info = read_lucene70_segment_info_from_index(
+            &mut checksum,
+            directory,
+            segment_name,
+            segment_id,
+        )?;
+        codec_util::validate_footer(&mut checksum)?;
+        let digest = checksum.checksum();
+        codec_util::check_checksum(&mut checksum, digest)?;
+        Ok(segment_info)
+    }
+
+    fn write<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        _dir: &Arc<DW>,
+        _info: &mut SegmentInfo<D, C>,
+        _io_context: &IOContext,
+    ) -> Result<()> {
+        Err(UnsupportedOperation(
+            "Lucene70SegmentInfoFormat is read only".into(),
+        ))
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A codec writing all its data as human-readable text.
+//!
+//! It is extremely slow and uses a lot of memory, it is only meant for
+//! debugging and for looking at what gets written to an index in tests.
+
+mod simple_text_util;
+
+mod simple_text_postings_format;
+
+pub use self::simple_text_postings_format::{
+    SimpleTextFieldsWriter, SimpleTextPostingsFormat, SIMPLE_TEXT_POSTINGS_EXTENSION,
+};
+
+mod simple_text_doc_values_format;
+
+pub use self::simple_text_doc_values_format::{
+    SimpleTextBinaryDocValues, SimpleTextDocValuesFormat, SimpleTextDocValuesReader,
+    SimpleTextDocValuesWriter, SimpleTextNumericDocValues, SimpleTextSortedDocValues,
+    SimpleTextSortedNumericDocValues, SimpleTextSortedSetDocValues,
+    SIMPLE_TEXT_DOC_VALUES_EXTENSION,
+};
+
+mod simple_text_points_format;
+
+pub use self::simple_text_points_format::{
+    SimpleTextPointsFormat, SimpleTextPointsReader, SimpleTextPointsWriter,
+    SIMPLE_TEXT_POINTS_EXTENSION,
+};
+
+mod simple_text_stored_fields_format;
+
+pub use self::simple_text_stored_fields_format::{
+    SimpleTextStoredFieldsFormat, SimpleTextStoredFieldsReader, SimpleTextStoredFieldsWriter,
+    SIMPLE_TEXT_STORED_FIELDS_EXTENSION,
+};
+
+pub(crate) use self::simple_text_util::check_checksum;
+
+use crate::core::codec::field_infos::Lucene60FieldInfosFormat;
+use crate::core::codec::norms::Lucene53NormsFormat;
+use crate::core::codec::postings::DirectFieldsReader;
+use crate::core::codec::segment_infos::Lucene62SegmentInfoFormat;
+use crate::core::codec::term_vectors::{term_vectors_format, CompressingTermVectorsFormat};
+use crate::core::codec::vectors::HnswVectorsFormat;
+use crate::core::codec::{Codec, Lucene50CompoundFormat, Lucene50LiveDocsFormat};
+use crate::error::Error::CorruptIndex;
+use crate::error::{Error, Result};
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Whether `file` of a segment written by `SimpleTextCodec` is one of its
+/// plain text files, which end with a checksum line instead of a codec footer.
+pub(crate) fn is_simple_text_file(file: &str) -> bool {
+    [
+        SIMPLE_TEXT_POSTINGS_EXTENSION,
+        SIMPLE_TEXT_DOC_VALUES_EXTENSION,
+        SIMPLE_TEXT_POINTS_EXTENSION,
+        SIMPLE_TEXT_STORED_FIELDS_EXTENSION,
+    ]
+    .iter()
+    .any(|ext| file.ends_with(&format!(".{}", ext)))
+}
+
+/// A codec writing postings, doc values, points and stored fields as plain
+/// text, the other parts of a segment use the `Lucene62Codec` formats.
+///
+/// Pass it to `IndexWriterConfig::new` and disable compound files to look
+/// at the `.pst`, `.dat`, `.dim` and `.fld` files of each segment.
+#[derive(Clone)]
+pub struct SimpleTextCodec {
+    term_vector_format: CompressingTermVectorsFormat,
+}
+
+impl Default for SimpleTextCodec {
+    fn default() -> SimpleTextCodec {
+        SimpleTextCodec {
+            term_vector_format: term_vectors_format(),
+        }
+    }
+}
+
+impl Codec for SimpleTextCodec {
+    type FieldsProducer = Arc<DirectFieldsReader>;
+    type PostingFmt = SimpleTextPostingsFormat;
+    type DVFmt = SimpleTextDocValuesFormat;
+    type StoredFmt = SimpleTextStoredFieldsFormat;
+    type TVFmt = CompressingTermVectorsFormat;
+    type FieldFmt = Lucene60FieldInfosFormat;
+    type SegmentFmt = Lucene62SegmentInfoFormat;
+    type NormFmt = Lucene53NormsFormat;
+    type LiveDocFmt = Lucene50LiveDocsFormat;
+    type CompoundFmt = Lucene50CompoundFormat;
+    type PointFmt = SimpleTextPointsFormat;
+    type VectorsFmt = HnswVectorsFormat;
+
+    fn name(&self) -> &str {
+        "SimpleText"
+    }
+
+    fn postings_format(&self) -> Self::PostingFmt {
+        SimpleTextPostingsFormat
+    }
+
+    fn doc_values_format(&self) -> Self::DVFmt {
+        SimpleTextDocValuesFormat
+    }
+
+    fn stored_fields_format(&self) -> Self::StoredFmt {
+        SimpleTextStoredFieldsFormat
+    }
+
+    fn term_vectors_format(&self) -> Self::TVFmt {
+        self.term_vector_format.clone()
+    }
+
+    fn field_infos_format(&self) -> Self::FieldFmt {
+        Lucene60FieldInfosFormat::default()
+    }
+
+    fn segment_info_format(&self) -> Self::SegmentFmt {
+        Lucene62SegmentInfoFormat
+    }
+
+    fn norms_format(&self) -> Self::NormFmt {
+        Lucene53NormsFormat::default()
+    }
+
+    fn live_docs_format(&self) -> Self::LiveDocFmt {
+        Lucene50LiveDocsFormat {}
+    }
+
+    fn compound_format(&self) -> Self::CompoundFmt {
+        Lucene50CompoundFormat {}
+    }
+
+    fn points_format(&self) -> Self::PointFmt {
+        SimpleTextPointsFormat
+    }
+
+    fn vectors_format(&self) -> Self::VectorsFmt {
+        HnswVectorsFormat::default()
+    }
+}
+
+impl TryFrom<String> for SimpleTextCodec {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        if value.as_str() == "SimpleText" {
+            Ok(Self::default())
+        } else {
+            Err(CorruptIndex(format!(
+                "unknown codec name, expected 'SimpleText' got {:?}",
+                value
+            )))
+        }
+    }
+}
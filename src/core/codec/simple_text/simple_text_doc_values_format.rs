@@ -0,0 +1,651 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::lucene54::{
+    DocValuesTermIterator, SortedDocValuesTermIterator, SortedSetDocValuesTermIterator,
+};
+use crate::core::codec::doc_values::{
+    BinaryDocValues, BinaryDocValuesProvider, DocValuesConsumer, DocValuesConsumerEnum,
+    DocValuesFormat, DocValuesProducer, NumericDocValues, NumericDocValuesProvider,
+    SortedDocValues, SortedDocValuesProvider, SortedNumericDocValues,
+    SortedNumericDocValuesProvider, SortedSetDocValues, SortedSetDocValuesProvider, NO_MORE_ORDS,
+};
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::simple_text_util::{
+    read_file, write_checksum, write_line, LineReader,
+};
+use crate::core::codec::Codec;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexOutput;
+use crate::core::util::{
+    Accountable, BitsMut, BytesRef, DocId, NamedAccountable, Numeric, ReusableIterator,
+};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+
+/// Extension of the doc values file of `SimpleTextDocValuesFormat`
+pub const SIMPLE_TEXT_DOC_VALUES_EXTENSION: &str = "dat";
+
+const END: &[u8] = b"END";
+const FIELD: &[u8] = b"field ";
+const TYPE: &[u8] = b"  type ";
+const NUM_TERMS: &[u8] = b"  terms ";
+const TERM: &[u8] = b"    term ";
+const VALUE: &[u8] = b"    value ";
+const MISSING: &[u8] = b"    missing";
+const ORD: &[u8] = b"    ord ";
+const COUNT: &[u8] = b"    count ";
+const SUB_VALUE: &[u8] = b"      value ";
+const SUB_ORD: &[u8] = b"      ord ";
+
+const TYPE_NUMERIC: &[u8] = b"numeric";
+const TYPE_BINARY: &[u8] = b"binary";
+const TYPE_SORTED: &[u8] = b"sorted";
+const TYPE_SORTED_NUMERIC: &[u8] = b"sorted_numeric";
+const TYPE_SORTED_SET: &[u8] = b"sorted_set";
+
+/// A `DocValuesFormat` writing the values of each doc as plain text, only
+/// meant for debugging.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SimpleTextDocValuesFormat;
+
+impl DocValuesFormat for SimpleTextDocValuesFormat {
+    fn name(&self) -> &str {
+        "SimpleText"
+    }
+
+    fn fields_producer<'a, D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'a, D, DW, C>,
+    ) -> Result<Box<dyn DocValuesProducer>> {
+        Ok(Box::new(SimpleTextDocValuesReader::new(state)?))
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<DocValuesConsumerEnum<D, DW, C>> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_DOC_VALUES_EXTENSION,
+        );
+        let out = state.directory.create_output(&name, &state.context)?;
+        Ok(DocValuesConsumerEnum::SimpleText(
+            SimpleTextDocValuesWriter { out },
+        ))
+    }
+}
+
+/// `DocValuesConsumer` for `SimpleTextDocValuesFormat`
+pub struct SimpleTextDocValuesWriter<O: IndexOutput> {
+    out: O,
+}
+
+impl<O: IndexOutput> SimpleTextDocValuesWriter<O> {
+    fn write_field_header(&mut self, field_info: &FieldInfo, t: &[u8]) -> Result<()> {
+        write_line(&mut self.out, FIELD, field_info.name.as_bytes())?;
+        write_line(&mut self.out, TYPE, t)
+    }
+
+    fn write_terms(&mut self, values: &mut impl Iterator<Item = Result<BytesRef>>) -> Result<()> {
+        let mut terms = vec![];
+        for value in values {
+            terms.push(value?.bytes().to_vec());
+        }
+        write_line(&mut self.out, NUM_TERMS, terms.len().to_string().as_bytes())?;
+        for term in &terms {
+            write_line(&mut self.out, TERM, term)?;
+        }
+        Ok(())
+    }
+}
+
+impl<O: IndexOutput> DocValuesConsumer for SimpleTextDocValuesWriter<O> {
+    fn add_numeric_field(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<Numeric>>,
+    ) -> Result<()> {
+        self.write_field_header(field_info, TYPE_NUMERIC)?;
+        for value in values {
+            let value = value?;
+            if value.is_null() {
+                write_line(&mut self.out, MISSING, &[])?;
+            } else {
+                write_line(
+                    &mut self.out,
+                    VALUE,
+                    value.long_value().to_string().as_bytes(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_binary_field(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
+    ) -> Result<()> {
+        self.write_field_header(field_info, TYPE_BINARY)?;
+        for value in values {
+            let value = value?;
+            // like other formats, empty values are missing
+            if value.is_empty() {
+                write_line(&mut self.out, MISSING, &[])?;
+            } else {
+                write_line(&mut self.out, VALUE, value.bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_sorted_field(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
+        doc_to_ord: &mut impl ReusableIterator<Item = Result<Numeric>>,
+    ) -> Result<()> {
+        self.write_field_header(field_info, TYPE_SORTED)?;
+        self.write_terms(values)?;
+        for ord in doc_to_ord {
+            let ord = ord?;
+            let ord = if ord.is_null() { -1 } else { ord.long_value() };
+            write_line(&mut self.out, ORD, ord.to_string().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn add_sorted_numeric_field(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<Numeric>>,
+        doc_to_value_count: &mut impl ReusableIterator<Item = Result<u32>>,
+    ) -> Result<()> {
+        self.write_field_header(field_info, TYPE_SORTED_NUMERIC)?;
+        for count in doc_to_value_count {
+            let count = count?;
+            write_line(&mut self.out, COUNT, count.to_string().as_bytes())?;
+            for _ in 0..count {
+                let value = match values.next() {
+                    Some(value) => value?.long_value(),
+                    None => return Err(IllegalArgument("missing sorted numeric value".into())),
+                };
+                write_line(&mut self.out, SUB_VALUE, value.to_string().as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_sorted_set_field(
+        &mut self,
+        field_info: &FieldInfo,
+        values: &mut impl ReusableIterator<Item = Result<BytesRef>>,
+        doc_to_ord_count: &mut impl ReusableIterator<Item = Result<u32>>,
+        ords: &mut impl ReusableIterator<Item = Result<Numeric>>,
+    ) -> Result<()> {
+        self.write_field_header(field_info, TYPE_SORTED_SET)?;
+        self.write_terms(values)?;
+        for count in doc_to_ord_count {
+            let count = count?;
+            write_line(&mut self.out, COUNT, count.to_string().as_bytes())?;
+            for _ in 0..count {
+                let ord = match ords.next() {
+                    Some(ord) => ord?.long_value(),
+                    None => return Err(IllegalArgument("missing sorted set ord".into())),
+                };
+                write_line(&mut self.out, SUB_ORD, ord.to_string().as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<O: IndexOutput> Drop for SimpleTextDocValuesWriter<O> {
+    fn drop(&mut self) {
+        let _ = write_line(&mut self.out, END, &[]);
+        let _ = write_checksum(&mut self.out);
+    }
+}
+
+enum DocValuesEntry {
+    Numeric(SimpleTextNumericDocValues),
+    Binary(SimpleTextBinaryDocValues),
+    Sorted(SimpleTextSortedDocValues),
+    SortedNumeric(SimpleTextSortedNumericDocValues),
+    SortedSet(SimpleTextSortedSetDocValues),
+}
+
+impl Accountable for DocValuesEntry {
+    fn ram_bytes_used(&self) -> usize {
+        fn vec_bytes<T>(values: &[T]) -> usize {
+            mem::size_of_val(values)
+        }
+        fn terms_bytes(terms: &[Vec<u8>]) -> usize {
+            vec_bytes(terms) + terms.iter().map(Vec::len).sum::<usize>()
+        }
+        mem::size_of::<Self>()
+            + match self {
+                DocValuesEntry::Numeric(dv) => vec_bytes(&dv.values),
+                DocValuesEntry::Binary(dv) => terms_bytes(&dv.values),
+                DocValuesEntry::Sorted(dv) => terms_bytes(&dv.terms) + vec_bytes(&dv.ords),
+                DocValuesEntry::SortedNumeric(dv) => vec_bytes(&dv.offsets) + vec_bytes(&dv.values),
+                DocValuesEntry::SortedSet(dv) => {
+                    terms_bytes(&dv.terms) + vec_bytes(&dv.offsets) + vec_bytes(&dv.ords)
+                }
+            }
+    }
+}
+
+/// `DocValuesProducer` for `SimpleTextDocValuesFormat`, it holds the values
+/// of all fields in memory.
+#[derive(Clone)]
+pub struct SimpleTextDocValuesReader {
+    fields: Arc<HashMap<String, DocValuesEntry>>,
+}
+
+impl SimpleTextDocValuesReader {
+    fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<SimpleTextDocValuesReader> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_DOC_VALUES_EXTENSION,
+        );
+        let content = read_file(state.directory.as_ref(), &name, state.context)?;
+        let mut lines = LineReader::new(&content);
+        let max_doc = state.segment_info.max_doc() as usize;
+        let mut fields = HashMap::new();
+        while !lines.starts_with(END) {
+            let field = lines.read_string(FIELD)?;
+            let entry = match lines.read_value(TYPE)?.as_slice() {
+                TYPE_NUMERIC => {
+                    let mut values = Vec::with_capacity(max_doc);
+                    for _ in 0..max_doc {
+                        values.push(if lines.starts_with(MISSING) {
+                            lines.read_value(MISSING)?;
+                            None
+                        } else {
+                            Some(lines.read_parsed(VALUE)?)
+                        });
+                    }
+                    DocValuesEntry::Numeric(SimpleTextNumericDocValues {
+                        values: Arc::new(values),
+                    })
+                }
+                TYPE_BINARY => {
+                    let mut values = Vec::with_capacity(max_doc);
+                    for _ in 0..max_doc {
+                        values.push(if lines.starts_with(MISSING) {
+                            lines.read_value(MISSING)?;
+                            vec![]
+                        } else {
+                            lines.read_value(VALUE)?
+                        });
+                    }
+                    DocValuesEntry::Binary(SimpleTextBinaryDocValues {
+                        values: Arc::new(values),
+                    })
+                }
+                TYPE_SORTED => {
+                    let terms = read_terms(&mut lines)?;
+                    let mut ords = Vec::with_capacity(max_doc);
+                    for _ in 0..max_doc {
+                        let ord: i32 = lines.read_parsed(ORD)?;
+                        if ord < -1 || ord >= terms.len() as i32 {
+                            return Err(CorruptIndex(format!("{}: invalid ord {}", name, ord)));
+                        }
+                        ords.push(ord);
+                    }
+                    DocValuesEntry::Sorted(SimpleTextSortedDocValues {
+                        terms: Arc::new(terms),
+                        ords: Arc::new(ords),
+                    })
+                }
+                TYPE_SORTED_NUMERIC => {
+                    let mut offsets = Vec::with_capacity(max_doc + 1);
+                    let mut values = vec![];
+                    offsets.push(0);
+                    for _ in 0..max_doc {
+                        let count: usize = lines.read_parsed(COUNT)?;
+                        for _ in 0..count {
+                            values.push(lines.read_parsed(SUB_VALUE)?);
+                        }
+                        offsets.push(values.len());
+                    }
+                    DocValuesEntry::SortedNumeric(SimpleTextSortedNumericDocValues {
+                        offsets: Arc::new(offsets),
+                        values: Arc::new(values),
+                        start: 0,
+                        end: 0,
+                    })
+                }
+                TYPE_SORTED_SET => {
+                    let terms = read_terms(&mut lines)?;
+                    let mut offsets = Vec::with_capacity(max_doc + 1);
+                    let mut ords = vec![];
+                    offsets.push(0);
+                    for _ in 0..max_doc {
+                        let count: usize = lines.read_parsed(COUNT)?;
+                        for _ in 0..count {
+                            let ord: i64 = lines.read_parsed(SUB_ORD)?;
+                            if ord < 0 || ord >= terms.len() as i64 {
+                                return Err(CorruptIndex(format!("{}: invalid ord {}", name, ord)));
+                            }
+                            ords.push(ord);
+                        }
+                        offsets.push(ords.len());
+                    }
+                    DocValuesEntry::SortedSet(SimpleTextSortedSetDocValues {
+                        terms: Arc::new(terms),
+                        offsets: Arc::new(offsets),
+                        ords: Arc::new(ords),
+                        upto: 0,
+                        end: 0,
+                    })
+                }
+                t => {
+                    return Err(CorruptIndex(format!(
+                        "{}: unknown doc values type {}",
+                        name,
+                        String::from_utf8_lossy(t)
+                    )));
+                }
+            };
+            fields.insert(field, entry);
+        }
+        lines.read_value(END)?;
+        Ok(SimpleTextDocValuesReader {
+            fields: Arc::new(fields),
+        })
+    }
+
+    fn entry(&self, field_info: &FieldInfo) -> Result<&DocValuesEntry> {
+        self.fields.get(&field_info.name).ok_or_else(|| {
+            IllegalArgument(format!("no doc values for field '{}'", field_info.name))
+        })
+    }
+
+    fn wrong_type(field_info: &FieldInfo) -> crate::error::Error {
+        IllegalArgument(format!(
+            "field '{}' has doc values of another type",
+            field_info.name
+        ))
+    }
+}
+
+fn read_terms(lines: &mut LineReader<'_>) -> Result<Vec<Vec<u8>>> {
+    let num_terms: usize = lines.read_parsed(NUM_TERMS)?;
+    let mut terms = Vec::with_capacity(num_terms);
+    for _ in 0..num_terms {
+        terms.push(lines.read_value(TERM)?);
+    }
+    Ok(terms)
+}
+
+impl Accountable for SimpleTextDocValuesReader {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self
+                .fields
+                .iter()
+                .map(|(k, v)| k.capacity() + v.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources: Vec<NamedAccountable> = self
+            .fields
+            .iter()
+            .map(|(k, v)| NamedAccountable::new(format!("field '{}'", k), v))
+            .collect();
+        resources.sort_by(|a, b| a.name.cmp(&b.name));
+        resources
+    }
+}
+
+impl DocValuesProducer for SimpleTextDocValuesReader {
+    fn get_numeric(&self, field_info: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
+        match self.entry(field_info)? {
+            DocValuesEntry::Numeric(dv) => Ok(Arc::new(dv.clone())),
+            _ => Err(Self::wrong_type(field_info)),
+        }
+    }
+
+    fn get_binary(&self, field_info: &FieldInfo) -> Result<Arc<dyn BinaryDocValuesProvider>> {
+        match self.entry(field_info)? {
+            DocValuesEntry::Binary(dv) => Ok(Arc::new(dv.clone())),
+            _ => Err(Self::wrong_type(field_info)),
+        }
+    }
+
+    fn get_sorted(&self, field: &FieldInfo) -> Result<Arc<dyn SortedDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::Sorted(dv) => Ok(Arc::new(dv.clone())),
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_sorted_numeric(
+        &self,
+        field: &FieldInfo,
+    ) -> Result<Arc<dyn SortedNumericDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::SortedNumeric(dv) => Ok(Arc::new(dv.clone())),
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_sorted_set(&self, field: &FieldInfo) -> Result<Arc<dyn SortedSetDocValuesProvider>> {
+        match self.entry(field)? {
+            DocValuesEntry::SortedSet(dv) => Ok(Arc::new(dv.clone())),
+            _ => Err(Self::wrong_type(field)),
+        }
+    }
+
+    fn get_docs_with_field(&self, field: &FieldInfo) -> Result<Box<dyn BitsMut>> {
+        let docs: Vec<bool> = match self.entry(field)? {
+            DocValuesEntry::Numeric(dv) => dv.values.iter().map(Option::is_some).collect(),
+            DocValuesEntry::Binary(dv) => dv.values.iter().map(|v| !v.is_empty()).collect(),
+            DocValuesEntry::Sorted(dv) => dv.ords.iter().map(|o| *o >= 0).collect(),
+            DocValuesEntry::SortedNumeric(dv) => {
+                dv.offsets.windows(2).map(|w| w[0] < w[1]).collect()
+            }
+            DocValuesEntry::SortedSet(dv) => dv.offsets.windows(2).map(|w| w[0] < w[1]).collect(),
+        };
+        Ok(Box::new(SimpleTextDocsWithField { docs }))
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        // the checksum was verified on open
+        Ok(())
+    }
+
+    fn get_merge_instance(&self) -> Result<Box<dyn DocValuesProducer>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+struct SimpleTextDocsWithField {
+    docs: Vec<bool>,
+}
+
+impl BitsMut for SimpleTextDocsWithField {
+    fn get(&mut self, index: usize) -> bool {
+        self.docs[index]
+    }
+
+    fn len(&self) -> usize {
+        self.docs.len()
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleTextNumericDocValues {
+    values: Arc<Vec<Option<i64>>>,
+}
+
+impl NumericDocValues for SimpleTextNumericDocValues {
+    fn get(&self, doc_id: DocId) -> Result<i64> {
+        Ok(self.values[doc_id as usize].unwrap_or(0))
+    }
+}
+
+impl NumericDocValuesProvider for SimpleTextNumericDocValues {
+    fn get(&self) -> Result<Box<dyn NumericDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleTextBinaryDocValues {
+    values: Arc<Vec<Vec<u8>>>,
+}
+
+impl BinaryDocValues for SimpleTextBinaryDocValues {
+    fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+        Ok(self.values[doc_id as usize].clone())
+    }
+}
+
+impl BinaryDocValuesProvider for SimpleTextBinaryDocValues {
+    fn get(&self) -> Result<Box<dyn BinaryDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleTextSortedDocValues {
+    terms: Arc<Vec<Vec<u8>>>,
+    ords: Arc<Vec<i32>>,
+}
+
+impl BinaryDocValues for SimpleTextSortedDocValues {
+    fn get(&mut self, doc_id: DocId) -> Result<Vec<u8>> {
+        let ord = self.ords[doc_id as usize];
+        if ord < 0 {
+            Ok(vec![])
+        } else {
+            Ok(self.terms[ord as usize].clone())
+        }
+    }
+}
+
+impl SortedDocValues for SimpleTextSortedDocValues {
+    fn get_ord(&mut self, doc_id: DocId) -> Result<i32> {
+        Ok(self.ords[doc_id as usize])
+    }
+
+    fn lookup_ord(&mut self, ord: i32) -> Result<Vec<u8>> {
+        Ok(self.terms[ord as usize].clone())
+    }
+
+    fn value_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+        Ok(DocValuesTermIterator::simple_text_sorted(
+            SortedDocValuesTermIterator::new(self),
+        ))
+    }
+}
+
+impl SortedDocValuesProvider for SimpleTextSortedDocValues {
+    fn get(&self) -> Result<Box<dyn SortedDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleTextSortedNumericDocValues {
+    offsets: Arc<Vec<usize>>,
+    values: Arc<Vec<i64>>,
+    start: usize,
+    end: usize,
+}
+
+impl SortedNumericDocValues for SimpleTextSortedNumericDocValues {
+    fn set_document(&mut self, doc: DocId) -> Result<()> {
+        self.start = self.offsets[doc as usize];
+        self.end = self.offsets[doc as usize + 1];
+        Ok(())
+    }
+
+    fn value_at(&mut self, index: usize) -> Result<i64> {
+        Ok(self.values[self.start + index])
+    }
+
+    fn count(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl SortedNumericDocValuesProvider for SimpleTextSortedNumericDocValues {
+    fn get(&self) -> Result<Box<dyn SortedNumericDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+pub struct SimpleTextSortedSetDocValues {
+    terms: Arc<Vec<Vec<u8>>>,
+    offsets: Arc<Vec<usize>>,
+    ords: Arc<Vec<i64>>,
+    upto: usize,
+    end: usize,
+}
+
+impl SortedSetDocValues for SimpleTextSortedSetDocValues {
+    fn set_document(&mut self, doc: DocId) -> Result<()> {
+        self.upto = self.offsets[doc as usize];
+        self.end = self.offsets[doc as usize + 1];
+        Ok(())
+    }
+
+    fn next_ord(&mut self) -> Result<i64> {
+        if self.upto < self.end {
+            self.upto += 1;
+            Ok(self.ords[self.upto - 1])
+        } else {
+            Ok(NO_MORE_ORDS)
+        }
+    }
+
+    fn lookup_ord(&mut self, ord: i64) -> Result<Vec<u8>> {
+        Ok(self.terms[ord as usize].clone())
+    }
+
+    fn get_value_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    fn term_iterator(&self) -> Result<DocValuesTermIterator> {
+        Ok(DocValuesTermIterator::simple_text_sorted_set(
+            SortedSetDocValuesTermIterator::new(self),
+        ))
+    }
+}
+
+impl SortedSetDocValuesProvider for SimpleTextSortedSetDocValues {
+    fn get(&self) -> Result<Box<dyn SortedSetDocValues>> {
+        Ok(Box::new(self.clone()))
+    }
+}
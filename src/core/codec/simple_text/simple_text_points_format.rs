@@ -0,0 +1,336 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::points::{
+    merge_point_values, IntersectVisitor, MutablePointsReader, PointValues, PointsFormat,
+    PointsReader, PointsReaderEnum, PointsWriter, PointsWriterEnum, Relation,
+};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::simple_text_util::{
+    read_file, write_checksum, write_line, LineReader,
+};
+use crate::core::codec::Codec;
+use crate::core::index::merge::MergeState;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexOutput;
+use crate::core::util::DocId;
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Extension of the points file of `SimpleTextPointsFormat`
+pub const SIMPLE_TEXT_POINTS_EXTENSION: &str = "dim";
+
+const END: &[u8] = b"END";
+const FIELD: &[u8] = b"field ";
+const NUM_DIMS: &[u8] = b"  num dims ";
+const INDEX_DIMS: &[u8] = b"  index dims ";
+const BYTES_PER_DIM: &[u8] = b"  bytes per dim ";
+const NUM_POINTS: &[u8] = b"  points ";
+const DOC: &[u8] = b"    doc ";
+const VALUE: &[u8] = b"    value ";
+
+/// A `PointsFormat` writing the points of each field as a plain text list,
+/// only meant for debugging.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SimpleTextPointsFormat;
+
+impl PointsFormat for SimpleTextPointsFormat {
+    type Reader = SimpleTextPointsReader;
+
+    fn fields_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<PointsWriterEnum<D, DW, C>> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_POINTS_EXTENSION,
+        );
+        let out = state.directory.create_output(&name, &state.context)?;
+        Ok(PointsWriterEnum::SimpleText(SimpleTextPointsWriter { out }))
+    }
+
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::Reader> {
+        SimpleTextPointsReader::new(state)
+    }
+}
+
+/// `PointsWriter` for `SimpleTextPointsFormat`
+pub struct SimpleTextPointsWriter<O: IndexOutput> {
+    out: O,
+}
+
+impl<O: IndexOutput> PointsWriter for SimpleTextPointsWriter<O> {
+    fn write_field<P, MP>(
+        &mut self,
+        field_info: &FieldInfo,
+        values: PointsReaderEnum<P, MP>,
+    ) -> Result<()>
+    where
+        P: PointsReader,
+        MP: MutablePointsReader,
+    {
+        let mut collector = PointsCollector { points: vec![] };
+        values.intersect(&field_info.name, &mut collector)?;
+        // we could have 0 points on merge since all docs with points may be deleted
+        if collector.points.is_empty() {
+            return Ok(());
+        }
+        collector.points.sort_by_key(|(doc, _)| *doc);
+
+        write_line(&mut self.out, FIELD, field_info.name.as_bytes())?;
+        let num_dims = field_info.point_dimension_count.to_string();
+        write_line(&mut self.out, NUM_DIMS, num_dims.as_bytes())?;
+        let index_dims = field_info.point_index_dimension_count.to_string();
+        write_line(&mut self.out, INDEX_DIMS, index_dims.as_bytes())?;
+        let bytes_per_dim = field_info.point_num_bytes.to_string();
+        write_line(&mut self.out, BYTES_PER_DIM, bytes_per_dim.as_bytes())?;
+        let num_points = collector.points.len().to_string();
+        write_line(&mut self.out, NUM_POINTS, num_points.as_bytes())?;
+        for (doc, value) in &collector.points {
+            write_line(&mut self.out, DOC, doc.to_string().as_bytes())?;
+            write_line(&mut self.out, VALUE, to_hex(value).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &MergeState<D, C>) -> Result<()> {
+        merge_point_values(self, merge_state)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        write_line(&mut self.out, END, &[])?;
+        write_checksum(&mut self.out)
+    }
+}
+
+/// Collects all the points of a field.
+struct PointsCollector {
+    points: Vec<(DocId, Vec<u8>)>,
+}
+
+impl IntersectVisitor for PointsCollector {
+    fn visit(&mut self, _doc_id: DocId) -> Result<()> {
+        unreachable!()
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        self.points.push((doc_id, packed_value.to_vec()));
+        Ok(())
+    }
+
+    fn compare(&self, _min_packed_value: &[u8], _max_packed_value: &[u8]) -> Relation {
+        Relation::CellCrossesQuery
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &[u8]) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(CorruptIndex(format!(
+            "invalid packed value: {}",
+            String::from_utf8_lossy(hex)
+        )));
+    }
+    hex.chunks(2)
+        .map(|c| {
+            std::str::from_utf8(c)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    CorruptIndex(format!(
+                        "invalid packed value: {}",
+                        String::from_utf8_lossy(hex)
+                    ))
+                })
+        })
+        .collect()
+}
+
+struct PointsField {
+    num_dims: usize,
+    index_dims: usize,
+    bytes_per_dim: usize,
+    docs: Vec<DocId>,
+    values: Vec<Vec<u8>>,
+    min_packed_value: Vec<u8>,
+    max_packed_value: Vec<u8>,
+    doc_count: i32,
+}
+
+/// `PointsReader` for `SimpleTextPointsFormat`, it holds the points of all
+/// fields in memory and visits all of them on each intersection.
+pub struct SimpleTextPointsReader {
+    fields: HashMap<String, PointsField>,
+}
+
+impl SimpleTextPointsReader {
+    fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<SimpleTextPointsReader> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_POINTS_EXTENSION,
+        );
+        let content = read_file(state.directory.as_ref(), &name, state.context)?;
+        let mut lines = LineReader::new(&content);
+        let mut fields = HashMap::new();
+        while !lines.starts_with(END) {
+            let field = lines.read_string(FIELD)?;
+            let num_dims: usize = lines.read_parsed(NUM_DIMS)?;
+            let index_dims: usize = lines.read_parsed(INDEX_DIMS)?;
+            let bytes_per_dim: usize = lines.read_parsed(BYTES_PER_DIM)?;
+            let num_points: usize = lines.read_parsed(NUM_POINTS)?;
+            let packed_len = num_dims * bytes_per_dim;
+
+            let mut docs = Vec::with_capacity(num_points);
+            let mut values = Vec::with_capacity(num_points);
+            let mut min_packed_value = vec![0xffu8; index_dims * bytes_per_dim];
+            let mut max_packed_value = vec![0u8; index_dims * bytes_per_dim];
+            for _ in 0..num_points {
+                docs.push(lines.read_parsed(DOC)?);
+                let value = from_hex(&lines.read_value(VALUE)?)?;
+                if value.len() != packed_len {
+                    return Err(CorruptIndex(format!(
+                        "{}: field '{}' has a packed value of {} bytes, expected {}",
+                        name,
+                        field,
+                        value.len(),
+                        packed_len
+                    )));
+                }
+                for dim in 0..index_dims {
+                    let range = dim * bytes_per_dim..(dim + 1) * bytes_per_dim;
+                    if value[range.clone()] < min_packed_value[range.clone()] {
+                        min_packed_value[range.clone()].copy_from_slice(&value[range.clone()]);
+                    }
+                    if value[range.clone()] > max_packed_value[range.clone()] {
+                        max_packed_value[range.clone()].copy_from_slice(&value[range]);
+                    }
+                }
+                values.push(value);
+            }
+            // the points are sorted by doc
+            let mut doc_count = 0;
+            for (i, doc) in docs.iter().enumerate() {
+                if i == 0 || docs[i - 1] != *doc {
+                    doc_count += 1;
+                }
+            }
+            fields.insert(
+                field,
+                PointsField {
+                    num_dims,
+                    index_dims,
+                    bytes_per_dim,
+                    docs,
+                    values,
+                    min_packed_value,
+                    max_packed_value,
+                    doc_count,
+                },
+            );
+        }
+        lines.read_value(END)?;
+        Ok(SimpleTextPointsReader { fields })
+    }
+
+    fn field(&self, field_name: &str) -> Result<&PointsField> {
+        self.fields
+            .get(field_name)
+            .ok_or_else(|| IllegalArgument(format!("field '{}' has no points", field_name)))
+    }
+}
+
+impl PointValues for SimpleTextPointsReader {
+    fn intersect(&self, field_name: &str, visitor: &mut impl IntersectVisitor) -> Result<()> {
+        // a field without points in this segment has no values
+        let field = match self.fields.get(field_name) {
+            Some(field) => field,
+            None => return Ok(()),
+        };
+        match visitor.compare(&field.min_packed_value, &field.max_packed_value) {
+            Relation::CellOutsideQuery => {}
+            Relation::CellInsideQuery => {
+                visitor.grow(field.docs.len());
+                for doc in &field.docs {
+                    visitor.visit(*doc)?;
+                }
+            }
+            Relation::CellCrossesQuery => {
+                visitor.grow(field.docs.len());
+                for (doc, value) in field.docs.iter().zip(&field.values) {
+                    visitor.visit_by_packed_value(*doc, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn min_packed_value(&self, field_name: &str) -> Result<Vec<u8>> {
+        Ok(self.field(field_name)?.min_packed_value.clone())
+    }
+
+    fn max_packed_value(&self, field_name: &str) -> Result<Vec<u8>> {
+        Ok(self.field(field_name)?.max_packed_value.clone())
+    }
+
+    fn num_dimensions(&self, field_name: &str) -> Result<usize> {
+        Ok(self.field(field_name)?.num_dims)
+    }
+
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        Ok(self.field(field_name)?.index_dims)
+    }
+
+    fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
+        Ok(self.field(field_name)?.bytes_per_dim)
+    }
+
+    fn size(&self, field_name: &str) -> Result<i64> {
+        Ok(self
+            .fields
+            .get(field_name)
+            .map_or(0, |f| f.docs.len() as i64))
+    }
+
+    fn doc_count(&self, field_name: &str) -> Result<i32> {
+        Ok(self.fields.get(field_name).map_or(0, |f| f.doc_count))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PointsReader for SimpleTextPointsReader {
+    fn check_integrity(&self) -> Result<()> {
+        // the checksum was verified on open
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
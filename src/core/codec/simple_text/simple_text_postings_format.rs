@@ -0,0 +1,279 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::norms::NormsProducer;
+use crate::core::codec::postings::{
+    DirectFieldsReader, DirectTerms, FieldsConsumer, FieldsConsumerEnum, PostingsFormat,
+};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::simple_text::simple_text_util::{
+    read_file, write_checksum, write_line, LineReader,
+};
+use crate::core::codec::{
+    Codec, Fields, PostingIterator, PostingIteratorFlags, TermIterator, Terms,
+};
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexOutput;
+use crate::core::util::{BitSet, DocId, FixedBitSet, ImmutableBitSet};
+use crate::error::Error::{CorruptIndex, IllegalState};
+use crate::Result;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Extension of the postings file of `SimpleTextPostingsFormat`
+pub const SIMPLE_TEXT_POSTINGS_EXTENSION: &str = "pst";
+
+const END: &[u8] = b"END";
+const FIELD: &[u8] = b"field ";
+const TERM: &[u8] = b"  term ";
+const DOC: &[u8] = b"    doc ";
+const FREQ: &[u8] = b"      freq ";
+const POS: &[u8] = b"      pos ";
+const START_OFFSET: &[u8] = b"      startOffset ";
+const END_OFFSET: &[u8] = b"      endOffset ";
+const PAYLOAD: &[u8] = b"        payload ";
+
+/// A `PostingsFormat` writing the postings as plain text, one line per term,
+/// doc and position.
+///
+/// This is only meant to inspect the postings of an index, e.g. when
+/// debugging a codec, the postings are read back in memory when the segment
+/// is opened.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Default)]
+pub struct SimpleTextPostingsFormat;
+
+impl SimpleTextPostingsFormat {
+    pub(crate) fn open_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<DirectFieldsReader> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_POSTINGS_EXTENSION,
+        );
+        let content = read_file(state.directory.as_ref(), &name, state.context)?;
+        let mut lines = LineReader::new(&content);
+        let max_doc = state.segment_info.max_doc();
+        let mut fields = BTreeMap::new();
+        while !lines.starts_with(END) {
+            let field = lines.read_string(FIELD)?;
+            let field_info = match state.field_infos.field_info_by_name(&field) {
+                Some(info) => info,
+                None => return Err(CorruptIndex(format!("{}: unknown field {}", name, field))),
+            };
+            let terms = read_terms(&mut lines, field_info, max_doc)?;
+            fields.insert(field, Arc::new(terms));
+        }
+        lines.read_value(END)?;
+        if !lines.is_eof() {
+            return Err(CorruptIndex(format!("{}: content after END", name)));
+        }
+        Ok(DirectFieldsReader::with_terms(fields))
+    }
+}
+
+fn read_terms(
+    lines: &mut LineReader<'_>,
+    field_info: &FieldInfo,
+    max_doc: DocId,
+) -> Result<DirectTerms> {
+    let has_freqs = field_info.index_options.has_freqs();
+    let has_positions = field_info.index_options.has_positions();
+    let has_offsets = field_info.index_options.has_offsets();
+    let has_payloads = field_info.has_store_payloads;
+    let mut terms = DirectTerms::new(has_freqs, has_positions, has_offsets, has_payloads);
+
+    let mut docs_seen = FixedBitSet::new(max_doc as usize);
+    let mut sum_total_term_freq = 0;
+    let mut sum_doc_freq = 0;
+    while lines.starts_with(TERM) {
+        terms.start_term(&lines.read_value(TERM)?);
+        let mut total_term_freq = 0;
+        while lines.starts_with(DOC) {
+            let doc: DocId = lines.read_parsed(DOC)?;
+            if doc < 0 || doc >= max_doc {
+                return Err(CorruptIndex(format!("invalid doc: {}", doc)));
+            }
+            let freq = if has_freqs {
+                lines.read_parsed(FREQ)?
+            } else {
+                1
+            };
+            terms.start_doc(doc, freq);
+            if has_positions {
+                for _ in 0..freq {
+                    let position = lines.read_parsed(POS)?;
+                    let (start_offset, end_offset) = if has_offsets {
+                        (
+                            lines.read_parsed(START_OFFSET)?,
+                            lines.read_parsed(END_OFFSET)?,
+                        )
+                    } else {
+                        (-1, -1)
+                    };
+                    let payload = if lines.starts_with(PAYLOAD) {
+                        lines.read_value(PAYLOAD)?
+                    } else {
+                        vec![]
+                    };
+                    terms.add_position(position, start_offset, end_offset, &payload);
+                }
+            }
+            terms.finish_doc();
+            docs_seen.set(doc as usize);
+            total_term_freq += i64::from(freq);
+            sum_doc_freq += 1;
+        }
+        if has_freqs {
+            sum_total_term_freq += total_term_freq;
+            terms.finish_term(total_term_freq);
+        } else {
+            terms.finish_term(-1);
+        }
+    }
+    terms.finish(
+        if has_freqs { sum_total_term_freq } else { -1 },
+        sum_doc_freq,
+        docs_seen.cardinality() as i32,
+    );
+    Ok(terms)
+}
+
+impl PostingsFormat for SimpleTextPostingsFormat {
+    type FieldsProducer = Arc<DirectFieldsReader>;
+
+    fn fields_producer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::FieldsProducer> {
+        Ok(Arc::new(self.open_reader(state)?))
+    }
+
+    fn fields_consumer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<FieldsConsumerEnum<D, DW, C>> {
+        let name = segment_file_name(
+            &state.segment_info.name,
+            &state.segment_suffix,
+            SIMPLE_TEXT_POSTINGS_EXTENSION,
+        );
+        let out = state.directory.create_output(&name, &state.context)?;
+        Ok(FieldsConsumerEnum::SimpleText(SimpleTextFieldsWriter {
+            out,
+            field_infos: state.field_infos.clone(),
+        }))
+    }
+
+    fn name(&self) -> &str {
+        "SimpleText"
+    }
+}
+
+/// `FieldsConsumer` for `SimpleTextPostingsFormat`
+pub struct SimpleTextFieldsWriter<O: IndexOutput> {
+    out: O,
+    field_infos: FieldInfos,
+}
+
+impl<O: IndexOutput> SimpleTextFieldsWriter<O> {
+    fn write_field(&mut self, field_info: &FieldInfo, terms: &impl Terms) -> Result<()> {
+        let has_freqs = field_info.index_options.has_freqs();
+        let has_positions = field_info.index_options.has_positions();
+        let has_offsets = field_info.index_options.has_offsets();
+        let has_payloads = field_info.has_store_payloads;
+        let flags = if has_positions {
+            let mut flags = PostingIteratorFlags::POSITIONS;
+            if has_offsets {
+                flags |= PostingIteratorFlags::OFFSETS;
+            }
+            if has_payloads {
+                flags |= PostingIteratorFlags::PAYLOADS;
+            }
+            flags
+        } else if has_freqs {
+            PostingIteratorFlags::FREQS
+        } else {
+            PostingIteratorFlags::NONE
+        };
+
+        let mut wrote_field = false;
+        let mut terms_iter = terms.iterator()?;
+        while let Some(term) = terms_iter.next()? {
+            let mut postings = terms_iter.postings_with_flags(flags)?;
+            let mut wrote_term = false;
+            loop {
+                let doc = postings.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                // fields and terms without docs, e.g. all deleted on merge,
+                // are skipped
+                if !wrote_field {
+                    write_line(&mut self.out, FIELD, field_info.name.as_bytes())?;
+                    wrote_field = true;
+                }
+                if !wrote_term {
+                    write_line(&mut self.out, TERM, &term)?;
+                    wrote_term = true;
+                }
+                write_line(&mut self.out, DOC, doc.to_string().as_bytes())?;
+                if !has_freqs {
+                    continue;
+                }
+                let freq = postings.freq()?;
+                write_line(&mut self.out, FREQ, freq.to_string().as_bytes())?;
+                if !has_positions {
+                    continue;
+                }
+                for _ in 0..freq {
+                    let position = postings.next_position()?;
+                    write_line(&mut self.out, POS, position.to_string().as_bytes())?;
+                    if has_offsets {
+                        let start_offset = postings.start_offset()?.to_string();
+                        let end_offset = postings.end_offset()?.to_string();
+                        write_line(&mut self.out, START_OFFSET, start_offset.as_bytes())?;
+                        write_line(&mut self.out, END_OFFSET, end_offset.as_bytes())?;
+                    }
+                    if has_payloads {
+                        let payload = postings.payload()?;
+                        if !payload.is_empty() {
+                            write_line(&mut self.out, PAYLOAD, &payload)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<O: IndexOutput> FieldsConsumer for SimpleTextFieldsWriter<O> {
+    fn write(&mut self, fields: &impl Fields, _norms: Option<&dyn NormsProducer>) -> Result<()> {
+        for field in fields.fields() {
+            let field_info = match self.field_infos.field_info_by_name(&field) {
+                Some(info) => info.clone(),
+                None => return Err(IllegalState(format!("unknown field: {}", field))),
+            };
+            if let Some(terms) = fields.terms(&field)? {
+                self.write_field(&field_info, &terms)?;
+            }
+        }
+        write_line(&mut self.out, END, &[])?;
+        write_checksum(&mut self.out)
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentInfo};
+use crate::core::codec::simple_text::simple_text_util::{
+    read_file, write_checksum, write_line, LineReader,
+};
+use crate::core::codec::stored_fields::{
+    merge_store_fields, StoredFieldsFormat, StoredFieldsReader, StoredFieldsWriter,
+    StoredFieldsWriterEnum,
+};
+use crate::core::codec::Codec;
+use crate::core::doc::{Fieldable, Status, StoredFieldVisitor};
+use crate::core::index::merge::MergeState;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexOutput;
+use crate::core::store::IOContext;
+use crate::core::util::{DocId, Numeric};
+use crate::error::Error::{CorruptIndex, IllegalArgument, IllegalState};
+use crate::Result;
+
+use std::any::Any;
+use std::sync::Arc;
+
+/// Extension of the stored fields file of `SimpleTextStoredFieldsFormat`
+pub const SIMPLE_TEXT_STORED_FIELDS_EXTENSION: &str = "fld";
+
+const END: &[u8] = b"END";
+const DOC: &[u8] = b"doc ";
+const FIELD: &[u8] = b"  field ";
+const NAME: &[u8] = b"    name ";
+const TYPE: &[u8] = b"    type ";
+const VALUE: &[u8] = b"    value ";
+
+const TYPE_STRING: &[u8] = b"string";
+const TYPE_BINARY: &[u8] = b"binary";
+const TYPE_INT: &[u8] = b"int";
+const TYPE_LONG: &[u8] = b"long";
+const TYPE_FLOAT: &[u8] = b"float";
+const TYPE_DOUBLE: &[u8] = b"double";
+
+/// A `StoredFieldsFormat` writing the stored fields of each doc as plain
+/// text, only meant for debugging.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SimpleTextStoredFieldsFormat;
+
+impl StoredFieldsFormat for SimpleTextStoredFieldsFormat {
+    type Reader = SimpleTextStoredFieldsReader;
+
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        directory: &DW,
+        si: &SegmentInfo<D, C>,
+        field_info: Arc<FieldInfos>,
+        ioctx: &IOContext,
+    ) -> Result<Self::Reader> {
+        let name = segment_file_name(&si.name, "", SIMPLE_TEXT_STORED_FIELDS_EXTENSION);
+        let content = read_file(directory, &name, ioctx)?;
+        let mut lines = LineReader::new(&content);
+        let mut docs = Vec::with_capacity(si.max_doc() as usize);
+        while !lines.starts_with(END) {
+            let doc: DocId = lines.read_parsed(DOC)?;
+            if doc as usize != docs.len() {
+                return Err(CorruptIndex(format!("{}: unexpected doc {}", name, doc)));
+            }
+            let mut fields = vec![];
+            while lines.starts_with(FIELD) {
+                let number: u32 = lines.read_parsed(FIELD)?;
+                lines.read_value(NAME)?;
+                let value = match lines.read_value(TYPE)?.as_slice() {
+                    TYPE_STRING => StoredValue::String(lines.read_value(VALUE)?),
+                    TYPE_BINARY => StoredValue::Binary(lines.read_value(VALUE)?),
+                    TYPE_INT => StoredValue::Int(lines.read_parsed(VALUE)?),
+                    TYPE_LONG => StoredValue::Long(lines.read_parsed(VALUE)?),
+                    TYPE_FLOAT => StoredValue::Float(lines.read_parsed(VALUE)?),
+                    TYPE_DOUBLE => StoredValue::Double(lines.read_parsed(VALUE)?),
+                    t => {
+                        return Err(CorruptIndex(format!(
+                            "{}: unknown field type {}",
+                            name,
+                            String::from_utf8_lossy(t)
+                        )));
+                    }
+                };
+                match field_info.by_number.get(&number) {
+                    Some(info) => fields.push((Arc::clone(info), value)),
+                    None => {
+                        return Err(CorruptIndex(format!(
+                            "{}: unknown field number {}",
+                            name, number
+                        )));
+                    }
+                }
+            }
+            docs.push(fields);
+        }
+        lines.read_value(END)?;
+        if docs.len() != si.max_doc() as usize {
+            return Err(CorruptIndex(format!(
+                "{}: found {} docs, expected {}",
+                name,
+                docs.len(),
+                si.max_doc()
+            )));
+        }
+        Ok(SimpleTextStoredFieldsReader {
+            docs: Arc::new(docs),
+        })
+    }
+
+    fn fields_writer<D, DW, C>(
+        &self,
+        directory: Arc<DW>,
+        si: &mut SegmentInfo<D, C>,
+        ioctx: &IOContext,
+    ) -> Result<StoredFieldsWriterEnum<DW::IndexOutput>>
+    where
+        D: Directory,
+        DW: Directory,
+        DW::IndexOutput: 'static,
+        C: Codec,
+    {
+        let name = segment_file_name(&si.name, "", SIMPLE_TEXT_STORED_FIELDS_EXTENSION);
+        let out = directory.create_output(&name, ioctx)?;
+        Ok(StoredFieldsWriterEnum::SimpleText(
+            SimpleTextStoredFieldsWriter { out, num_docs: 0 },
+        ))
+    }
+}
+
+enum StoredValue {
+    String(Vec<u8>),
+    Binary(Vec<u8>),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+}
+
+/// `StoredFieldsReader` for `SimpleTextStoredFieldsFormat`, it holds the
+/// stored fields of all docs in memory.
+pub struct SimpleTextStoredFieldsReader {
+    docs: Arc<Vec<StoredDoc>>,
+}
+
+type StoredDoc = Vec<(Arc<FieldInfo>, StoredValue)>;
+
+impl StoredFieldsReader for SimpleTextStoredFieldsReader {
+    fn visit_document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()> {
+        let fields = match self.docs.get(doc_id as usize) {
+            Some(fields) => fields,
+            None => return Err(IllegalArgument(format!("invalid doc: {}", doc_id))),
+        };
+        for (field_info, value) in fields {
+            match visitor.needs_field(field_info) {
+                Status::Yes => match value {
+                    StoredValue::String(v) => visitor.add_string_field(field_info, v.clone())?,
+                    StoredValue::Binary(v) => visitor.add_binary_field(field_info, v.clone())?,
+                    StoredValue::Int(v) => visitor.add_int_field(field_info, *v)?,
+                    StoredValue::Long(v) => visitor.add_long_field(field_info, *v)?,
+                    StoredValue::Float(v) => visitor.add_float_field(field_info, *v)?,
+                    StoredValue::Double(v) => visitor.add_double_field(field_info, *v)?,
+                },
+                Status::No => {}
+                Status::Stop => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_document_mut(
+        &mut self,
+        doc_id: DocId,
+        visitor: &mut dyn StoredFieldVisitor,
+    ) -> Result<()> {
+        self.visit_document(doc_id, visitor)
+    }
+
+    fn get_merge_instance(&self) -> Result<Self> {
+        Ok(SimpleTextStoredFieldsReader {
+            docs: Arc::clone(&self.docs),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// `StoredFieldsWriter` for `SimpleTextStoredFieldsFormat`
+pub struct SimpleTextStoredFieldsWriter<O: IndexOutput> {
+    out: O,
+    num_docs: usize,
+}
+
+impl<O: IndexOutput + 'static> StoredFieldsWriter for SimpleTextStoredFieldsWriter<O> {
+    fn start_document(&mut self) -> Result<()> {
+        write_line(&mut self.out, DOC, self.num_docs.to_string().as_bytes())?;
+        self.num_docs += 1;
+        Ok(())
+    }
+
+    fn finish_document(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_field(&mut self, field_info: &FieldInfo, field: &impl Fieldable) -> Result<()> {
+        let (field_type, value) = if let Some(v) = field.numeric_value() {
+            match v {
+                Numeric::Byte(v) => (TYPE_INT, v.to_string()),
+                Numeric::Short(v) => (TYPE_INT, v.to_string()),
+                Numeric::Int(v) => (TYPE_INT, v.to_string()),
+                Numeric::Long(v) => (TYPE_LONG, v.to_string()),
+                Numeric::Float(v) => (TYPE_FLOAT, v.to_string()),
+                Numeric::Double(v) => (TYPE_DOUBLE, v.to_string()),
+                Numeric::Null => unreachable!(),
+            }
+        } else if let Some(bytes) = field.binary_value() {
+            write_field_header(&mut self.out, field_info, TYPE_BINARY)?;
+            return write_line(&mut self.out, VALUE, bytes);
+        } else if let Some(string) = field.string_value() {
+            (TYPE_STRING, string.to_string())
+        } else {
+            return Err(IllegalArgument(format!(
+                "field '{}' is stored but its value is not a string, bytes or a number: {:?}",
+                field_info.name,
+                field.field_data()
+            )));
+        };
+        write_field_header(&mut self.out, field_info, field_type)?;
+        write_line(&mut self.out, VALUE, value.as_bytes())
+    }
+
+    fn finish(&mut self, _field_infos: &FieldInfos, num_docs: usize) -> Result<()> {
+        if self.num_docs != num_docs {
+            return Err(IllegalState(format!(
+                "wrote {} docs, finish called with num_docs={}",
+                self.num_docs, num_docs
+            )));
+        }
+        write_line(&mut self.out, END, &[])?;
+        write_checksum(&mut self.out)
+    }
+
+    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &mut MergeState<D, C>) -> Result<i32> {
+        merge_store_fields(self, merge_state)
+    }
+}
+
+fn write_field_header(out: &mut impl IndexOutput, field_info: &FieldInfo, t: &[u8]) -> Result<()> {
+    write_line(out, FIELD, field_info.number.to_string().as_bytes())?;
+    write_line(out, NAME, field_info.name.as_bytes())?;
+    write_line(out, TYPE, t)
+}
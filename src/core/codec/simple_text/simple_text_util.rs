@@ -0,0 +1,154 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{ChecksumIndexInput, DataInput, DataOutput, IndexInput, IndexOutput};
+use crate::core::store::IOContext;
+use crate::error::Error::CorruptIndex;
+use crate::Result;
+
+use std::str::FromStr;
+
+const NEWLINE: u8 = b'\n';
+const ESCAPE: u8 = b'\\';
+const CHECKSUM: &[u8] = b"checksum ";
+// "checksum " followed by 20 digits and a newline
+const CHECKSUM_LINE_LENGTH: usize = 30;
+
+/// Writes `key` followed by `value` on a line, escaping the new lines of
+/// `value`.
+pub(crate) fn write_line(out: &mut impl DataOutput, key: &[u8], value: &[u8]) -> Result<()> {
+    out.write_bytes(key, 0, key.len())?;
+    for b in value {
+        if *b == NEWLINE || *b == ESCAPE {
+            out.write_byte(ESCAPE)?;
+        }
+        out.write_byte(*b)?;
+    }
+    out.write_byte(NEWLINE)
+}
+
+/// Writes the checksum of everything written to `out` as its last line.
+pub(crate) fn write_checksum(out: &mut impl IndexOutput) -> Result<()> {
+    let checksum = out.checksum()?;
+    write_line(out, CHECKSUM, format!("{:020}", checksum).as_bytes())
+}
+
+/// Reads the content of a file written with `write_checksum`, without the
+/// checksum line, after verifying the checksum.
+pub(crate) fn read_file<D: Directory + ?Sized>(
+    directory: &D,
+    name: &str,
+    context: &IOContext,
+) -> Result<Vec<u8>> {
+    let mut input = directory.open_checksum_input(name, context)?;
+    let length = input.len() as usize;
+    if length < CHECKSUM_LINE_LENGTH {
+        return Err(CorruptIndex(format!("{}: missing checksum", name)));
+    }
+    let mut content = vec![0u8; length - CHECKSUM_LINE_LENGTH];
+    let content_length = content.len();
+    input.read_bytes(&mut content, 0, content_length)?;
+    let actual = input.checksum();
+
+    let mut line = [0u8; CHECKSUM_LINE_LENGTH];
+    input.read_bytes(&mut line, 0, CHECKSUM_LINE_LENGTH)?;
+    let mut lines = LineReader::new(&line);
+    let expected: i64 = lines.read_parsed(CHECKSUM)?;
+    if expected != actual {
+        return Err(CorruptIndex(format!(
+            "{}: checksum failed (hardware problems?): expected={}, actual={}",
+            name, expected, actual
+        )));
+    }
+    Ok(content)
+}
+
+/// Verifies the checksum of a file written with `write_checksum`.
+pub(crate) fn check_checksum<D: Directory + ?Sized>(directory: &D, name: &str) -> Result<()> {
+    read_file(directory, name, &IOContext::READ_ONCE).map(|_| ())
+}
+
+/// Reads the lines written with `write_line`.
+pub(crate) struct LineReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LineReader<'a> {
+    pub fn new(data: &'a [u8]) -> LineReader<'a> {
+        LineReader { data, pos: 0 }
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// Whether the next line starts with `key`.
+    pub fn starts_with(&self, key: &[u8]) -> bool {
+        self.data[self.pos..].starts_with(key)
+    }
+
+    /// Reads the next line, unescaped.
+    pub fn read_line(&mut self) -> Result<Vec<u8>> {
+        let mut line = vec![];
+        loop {
+            if self.pos >= self.data.len() {
+                return Err(CorruptIndex("unexpected end of simple text file".into()));
+            }
+            let b = self.data[self.pos];
+            self.pos += 1;
+            match b {
+                NEWLINE => return Ok(line),
+                ESCAPE if self.pos < self.data.len() => {
+                    line.push(self.data[self.pos]);
+                    self.pos += 1;
+                }
+                _ => line.push(b),
+            }
+        }
+    }
+
+    /// Reads the value of the next line, which must start with `key`.
+    pub fn read_value(&mut self, key: &[u8]) -> Result<Vec<u8>> {
+        if !self.starts_with(key) {
+            let end = self.data[self.pos..]
+                .iter()
+                .position(|b| *b == NEWLINE)
+                .map_or(self.data.len(), |i| self.pos + i);
+            return Err(CorruptIndex(format!(
+                "expected '{}' got '{}'",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(&self.data[self.pos..end])
+            )));
+        }
+        self.pos += key.len();
+        self.read_line()
+    }
+
+    pub fn read_string(&mut self, key: &[u8]) -> Result<String> {
+        String::from_utf8(self.read_value(key)?)
+            .map_err(|e| CorruptIndex(format!("invalid utf-8 value: {}", e)))
+    }
+
+    pub fn read_parsed<T: FromStr>(&mut self, key: &[u8]) -> Result<T> {
+        let value = self.read_string(key)?;
+        value.parse().map_err(|_| {
+            CorruptIndex(format!(
+                "invalid value for '{}': {}",
+                String::from_utf8_lossy(key),
+                value
+            ))
+        })
+    }
+}
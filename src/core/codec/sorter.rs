@@ -47,6 +47,7 @@ impl Sorter {
         match sort {
             SortField::Simple(s) => s.field_type(),
             SortField::SortedNumeric(s) => s.numeric_type(),
+            SortField::Distance(_) => SortFieldType::Custom,
         }
     }
 
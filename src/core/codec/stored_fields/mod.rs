@@ -26,7 +26,10 @@ pub(crate) use stored_fields_writer::{
 
 mod stored_fields;
 
-pub(crate) use self::stored_fields::{Lucene50StoredFieldsFormat, StoredFieldCompressMode};
+pub(crate) use self::stored_fields::Lucene50StoredFieldsFormat;
+pub use self::stored_fields::StoredFieldCompressMode;
+#[cfg(feature = "zstd")]
+pub use self::stored_fields::ZSTD_DEFAULT_LEVEL;
 
 mod stored_fields_consumer;
 
@@ -35,6 +38,7 @@ pub(crate) use self::stored_fields_consumer::StoredFieldsConsumer;
 use crate::core::analysis::TokenStream;
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
 use crate::core::codec::segment_infos::SegmentInfo;
+use crate::core::codec::simple_text::SimpleTextStoredFieldsWriter;
 use crate::core::codec::Codec;
 use crate::core::doc::{FieldType, Fieldable, STORE_FIELD_TYPE};
 use crate::core::doc::{Status, StoredFieldVisitor};
@@ -170,36 +174,42 @@ pub trait StoredFieldsWriter {
 
 pub enum StoredFieldsWriterEnum<O: IndexOutput + 'static> {
     Compressing(CompressingStoredFieldsWriter<O>),
+    SimpleText(SimpleTextStoredFieldsWriter<O>),
 }
 
 impl<O: IndexOutput + 'static> StoredFieldsWriter for StoredFieldsWriterEnum<O> {
     fn start_document(&mut self) -> Result<()> {
         match self {
             StoredFieldsWriterEnum::Compressing(w) => w.start_document(),
+            StoredFieldsWriterEnum::SimpleText(w) => w.start_document(),
         }
     }
 
     fn finish_document(&mut self) -> Result<()> {
         match self {
             StoredFieldsWriterEnum::Compressing(w) => w.finish_document(),
+            StoredFieldsWriterEnum::SimpleText(w) => w.finish_document(),
         }
     }
 
     fn write_field(&mut self, field_info: &FieldInfo, field: &impl Fieldable) -> Result<()> {
         match self {
             StoredFieldsWriterEnum::Compressing(w) => w.write_field(field_info, field),
+            StoredFieldsWriterEnum::SimpleText(w) => w.write_field(field_info, field),
         }
     }
 
     fn finish(&mut self, field_infos: &FieldInfos, num_docs: usize) -> Result<()> {
         match self {
             StoredFieldsWriterEnum::Compressing(w) => w.finish(field_infos, num_docs),
+            StoredFieldsWriterEnum::SimpleText(w) => w.finish(field_infos, num_docs),
         }
     }
 
     fn merge<D: Directory, C: Codec>(&mut self, merge_state: &mut MergeState<D, C>) -> Result<i32> {
         match self {
             StoredFieldsWriterEnum::Compressing(w) => w.merge(merge_state),
+            StoredFieldsWriterEnum::SimpleText(w) => w.merge(merge_state),
         }
     }
 }
@@ -25,21 +25,39 @@ use crate::core::store::directory::Directory;
 use crate::core::store::IOContext;
 use crate::core::util::CompressionMode;
 use crate::error::Error;
-use crate::error::{Error::IllegalState, Result};
+use crate::error::{
+    Error::{CorruptIndex, IllegalState},
+    Result,
+};
 
 const MODE_KEY: &str = "Lucene50StoredFieldsFormat.mode";
 
-#[derive(Debug, Copy, Clone)]
+/// How the stored fields of a segment are compressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum StoredFieldCompressMode {
+    /// LZ4 compression of small blocks, the default.
     BestSpeed,
+    /// DEFLATE compression of larger blocks, for a better compression ratio
+    /// at the cost of slower document loading.
     BestCompression,
+    /// Zstandard compression at the given level of large chunks, each with a
+    /// preset dictionary taken from its head, see `ZSTD_DEFAULT_LEVEL`.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
 }
 
+/// The zstd level used when no level is given, a good trade-off of speed and
+/// compression ratio.
+#[cfg(feature = "zstd")]
+pub const ZSTD_DEFAULT_LEVEL: i32 = 3;
+
 impl StoredFieldCompressMode {
     fn name(&self) -> &'static str {
         match self {
             StoredFieldCompressMode::BestSpeed => "BEST_SPEED",
             StoredFieldCompressMode::BestCompression => "BEST_COMPRESSION",
+            #[cfg(feature = "zstd")]
+            StoredFieldCompressMode::Zstd(_) => "ZSTD",
         }
     }
 }
@@ -47,19 +65,27 @@ impl StoredFieldCompressMode {
 impl FromStr for StoredFieldCompressMode {
     type Err = Error;
     fn from_str(v: &str) -> Result<Self> {
-        let r = if v == "BEST_SPEED" {
-            StoredFieldCompressMode::BestSpeed
-        } else {
-            StoredFieldCompressMode::BestCompression
-        };
-        Ok(r)
+        match v {
+            "BEST_SPEED" => Ok(StoredFieldCompressMode::BestSpeed),
+            "BEST_COMPRESSION" => Ok(StoredFieldCompressMode::BestCompression),
+            // the level only matters for writing
+            #[cfg(feature = "zstd")]
+            "ZSTD" => Ok(StoredFieldCompressMode::Zstd(ZSTD_DEFAULT_LEVEL)),
+            #[cfg(not(feature = "zstd"))]
+            "ZSTD" => Err(CorruptIndex(
+                "stored fields are zstd compressed, but the zstd feature is disabled".into(),
+            )),
+            _ => Err(CorruptIndex(format!(
+                "unknown stored fields compression mode: {}",
+                v
+            ))),
+        }
     }
 }
 
 /// Lucene 5.0 stored fields format.
 #[derive(Copy, Clone)]
 pub struct Lucene50StoredFieldsFormat {
-    #[allow(dead_code)]
     mode: StoredFieldCompressMode,
 }
 
@@ -74,6 +100,11 @@ impl Lucene50StoredFieldsFormat {
         }
     }
 
+    /// The compression mode of the segments written by this format.
+    pub fn mode(&self) -> StoredFieldCompressMode {
+        self.mode
+    }
+
     pub fn format(self, mode: StoredFieldCompressMode) -> CompressingStoredFieldsFormat {
         match mode {
             StoredFieldCompressMode::BestSpeed => CompressingStoredFieldsFormat::new(
@@ -92,6 +123,15 @@ impl Lucene50StoredFieldsFormat {
                 512,
                 1024,
             ),
+            #[cfg(feature = "zstd")]
+            StoredFieldCompressMode::Zstd(level) => CompressingStoredFieldsFormat::new(
+                "Lucene50StoredFieldsZstd",
+                "",
+                CompressionMode::Zstd(level),
+                480 * 1024,
+                4096,
+                1024,
+            ),
         }
     }
 }
@@ -594,6 +594,9 @@ impl CompressingStoredFieldsReader {
         if b == 0xff {
             // negative value
             Ok(f64::from_bits(input.read_long()? as u64))
+        } else if b == 0xfe {
+            // value with an exact float representation
+            Ok(f64::from(f32::from_bits(input.read_int()? as u32)))
         } else if (b & 0x80) != 0 {
             // small integer [-1..125]
             Ok(f64::from((b & 0x7f) - 1))
@@ -1039,6 +1042,15 @@ impl DataInput for CompressingStoredFieldsInput {
 impl Read for CompressingStoredFieldsInput {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let reader = self.reader();
+        if reader.bytes_position.1 == 0 {
+            // sliced documents are decompressed one slice at a time
+            if reader.current_doc.decompressed == reader.current_doc.length {
+                return Ok(0);
+            }
+            reader
+                .fill_buffer()
+                .map_err(|e| io::Error::other(e.to_string()))?;
+        }
         let size = min(buf.len(), reader.bytes_position.1);
         buf[0..size].copy_from_slice(
             &reader.bytes[reader.bytes_position.0..reader.bytes_position.0 + size],
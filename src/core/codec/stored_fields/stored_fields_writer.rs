@@ -447,7 +447,7 @@ impl<O: IndexOutput + 'static> CompressingStoredFieldsWriter<O> {
         } else {
             // other negative float: 5 bytes
             out.write_byte(0xFF as u8)?;
-            out.write_vint(float_bits)?;
+            out.write_int(float_bits)?;
         }
 
         Ok(())
@@ -480,7 +480,7 @@ impl<O: IndexOutput + 'static> CompressingStoredFieldsWriter<O> {
         {
             // small integer value [-1..124]: single byte
             out.write_byte((0x80 | (int_val + 1)) as u8)?;
-        } else if (d - (d as f32) as f64).abs() < ::std::f64::EPSILON {
+        } else if f64::from(d as f32).to_bits() == d.to_bits() {
             // d has an accurate float representation: 5 bytes
             out.write_byte(0xFE as u8)?;
             out.write_int((d as f32).to_bits() as i32)?;
@@ -592,7 +592,6 @@ impl<O: IndexOutput + 'static> StoredFieldsWriter for CompressingStoredFieldsWri
     }
 
     fn write_field(&mut self, field_info: &FieldInfo, field: &impl Fieldable) -> Result<()> {
-        self.num_stored_fields_in_doc += 1;
         let bits = if let Some(v) = field.numeric_value() {
             match v {
                 Numeric::Byte(_) | Numeric::Short(_) | Numeric::Int(_) => NUMERIC_INT,
@@ -603,9 +602,16 @@ impl<O: IndexOutput + 'static> StoredFieldsWriter for CompressingStoredFieldsWri
             }
         } else if field.binary_value().is_some() {
             BYTE_ARR
-        } else {
+        } else if field.string_value().is_some() {
             STRING
+        } else {
+            return Err(IllegalArgument(format!(
+                "field '{}' is stored but its value is not a string, bytes or a number: {:?}",
+                field_info.name,
+                field.field_data()
+            )));
         };
+        self.num_stored_fields_in_doc += 1;
 
         let info_and_bits = ((field_info.number as i64) << TYPE_BITS) | bits as i64;
         self.buffered_docs.write_vlong(info_and_bits)?;
@@ -73,6 +73,18 @@ pub fn term_vectors_format() -> CompressingTermVectorsFormat {
     CompressingTermVectorsFormat::default()
 }
 
+/// term vectors compressed with zstd at `level` in larger chunks
+#[cfg(feature = "zstd")]
+pub fn zstd_term_vectors_format(level: i32) -> CompressingTermVectorsFormat {
+    CompressingTermVectorsFormat::new(
+        String::from("Lucene50TermVectorsZstd"),
+        String::new(),
+        crate::core::util::CompressionMode::Zstd(level),
+        1 << 16,
+        1024,
+    )
+}
+
 pub trait TermVectorsReader {
     type Fields: Fields;
     fn get(&self, doc: DocId) -> Result<Option<Self::Fields>>;
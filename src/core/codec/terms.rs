@@ -11,6 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::codec::postings::Impacts;
 use crate::core::codec::{EmptyPostingIterator, PostingIterator, PostingIteratorFlags};
 
 use crate::error::Error::{IllegalArgument, UnsupportedOperation};
@@ -375,6 +376,13 @@ pub trait TermIterator: 'static {
     }
     fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings>;
 
+    /// Returns the impacts of the postings of the current term, which bound the
+    /// scores of its docs block by block, or `None` if the codec doesn't index
+    /// them. Do not call this when the enum is unpositioned.
+    fn impacts(&mut self) -> Result<Option<Impacts>> {
+        Ok(None)
+    }
+
     /// Expert: Returns the TermsEnums internal state to position the TermsEnum
     /// without re-seeking the term dictionary.
     /// <p>
@@ -0,0 +1,171 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A navigable graph of vectors: each vector is linked to some of its most
+//! similar vectors, so that a greedy walk from the entry point reaches the
+//! neighborhood of any target without comparing it with all the vectors.
+//!
+//! This is the bottom layer of a HNSW graph, all searches start at the
+//! first vector.
+
+use crate::core::codec::vectors::VectorSimilarity;
+use crate::Result;
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// A node of the graph with its similarity to the searched vector.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Neighbor {
+    pub ord: usize,
+    pub score: f32,
+}
+
+impl Ord for Neighbor {
+    // the most similar node is the greatest, ties are broken by lower ordinal
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then(other.ord.cmp(&self.ord))
+    }
+}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+/// Walks the graph of `num_nodes` nodes from `entry_point` and returns the at
+/// most `beam_width` most similar accepted nodes found, best first.
+///
+/// `score` gives the similarity of a node to the searched vector and
+/// `neighbors` the nodes it is linked to. Rejected nodes are still walked
+/// through.
+pub(crate) fn search_graph<S, N, A>(
+    entry_point: usize,
+    num_nodes: usize,
+    beam_width: usize,
+    mut score: S,
+    mut neighbors: N,
+    mut accept: A,
+) -> Result<Vec<Neighbor>>
+where
+    S: FnMut(usize) -> Result<f32>,
+    N: FnMut(usize) -> Result<Vec<usize>>,
+    A: FnMut(usize) -> Result<bool>,
+{
+    let mut visited = vec![false; num_nodes];
+    // the nodes to expand, best first
+    let mut candidates = BinaryHeap::new();
+    // the best nodes found, worst first
+    let mut results: BinaryHeap<Reverse<Neighbor>> = BinaryHeap::with_capacity(beam_width + 1);
+
+    let entry = Neighbor {
+        ord: entry_point,
+        score: score(entry_point)?,
+    };
+    visited[entry_point] = true;
+    candidates.push(entry);
+    if accept(entry_point)? {
+        results.push(Reverse(entry));
+    }
+
+    while let Some(candidate) = candidates.pop() {
+        if results.len() >= beam_width && candidate.score < results.peek().unwrap().0.score {
+            // no remaining candidate can improve the results
+            break;
+        }
+        for ord in neighbors(candidate.ord)? {
+            if visited[ord] {
+                continue;
+            }
+            visited[ord] = true;
+            let neighbor = Neighbor {
+                ord,
+                score: score(ord)?,
+            };
+            if results.len() < beam_width || neighbor.score > results.peek().unwrap().0.score {
+                candidates.push(neighbor);
+                if accept(ord)? {
+                    results.push(Reverse(neighbor));
+                    if results.len() > beam_width {
+                        results.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    let mut results: Vec<Neighbor> = results.into_iter().map(|r| r.0).collect();
+    results.sort_by(|n1, n2| n2.cmp(n1));
+    Ok(results)
+}
+
+/// Builds the graph of the `vectors`, laid one after the other. Each node is
+/// linked to at most `max_conn` nodes, found by searching the graph of the
+/// previous nodes with a beam of `beam_width` nodes.
+///
+/// Returns the neighbors of each node.
+pub(crate) fn build_graph(
+    vectors: &[f32],
+    dimension: usize,
+    similarity: VectorSimilarity,
+    max_conn: usize,
+    beam_width: usize,
+) -> Result<Vec<Vec<usize>>> {
+    let num_nodes = vectors.len() / dimension;
+    let vector = |ord: usize| &vectors[ord * dimension..(ord + 1) * dimension];
+    let mut graph: Vec<Vec<Neighbor>> = Vec::with_capacity(num_nodes);
+    for ord in 0..num_nodes {
+        let mut selected = if ord == 0 {
+            vec![]
+        } else {
+            search_graph(
+                0,
+                ord,
+                beam_width,
+                |other| Ok(similarity.compare(vector(ord), vector(other))),
+                |other| Ok(graph[other].iter().map(|n| n.ord).collect()),
+                |_| Ok(true),
+            )?
+        };
+        selected.truncate(max_conn);
+        // link back, keeping only the most similar nodes of the neighbors
+        for neighbor in &selected {
+            let links = &mut graph[neighbor.ord];
+            links.push(Neighbor {
+                ord,
+                score: neighbor.score,
+            });
+            if links.len() > max_conn {
+                links.sort_by(|n1, n2| n2.cmp(n1));
+                links.truncate(max_conn);
+            }
+        }
+        graph.push(selected);
+    }
+    Ok(graph
+        .into_iter()
+        .map(|links| links.into_iter().map(|n| n.ord).collect())
+        .collect())
+}
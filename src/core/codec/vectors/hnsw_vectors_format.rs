@@ -0,0 +1,479 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::segment_infos::{segment_file_name, SegmentReadState, SegmentWriteState};
+use crate::core::codec::vectors::hnsw_graph::{build_graph, search_graph};
+use crate::core::codec::vectors::{
+    check_dimension, top_hits, VectorSimilarity, VectorValues, VectorsFormat, VectorsReader,
+    VectorsWriter, VectorsWriterEnum,
+};
+use crate::core::codec::{codec_util, Codec};
+use crate::core::search::sort_field::ScoreDoc;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{IndexInput, IndexOutput, RandomAccessInput};
+use crate::core::util::{Bits, DocId};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use std::cmp::max;
+use std::collections::HashMap;
+
+const META_CODEC: &str = "HnswVectorsMeta";
+const DATA_CODEC: &str = "HnswVectorsData";
+const INDEX_CODEC: &str = "HnswVectorsIndex";
+const META_EXTENSION: &str = "vem";
+const DATA_EXTENSION: &str = "vec";
+const INDEX_EXTENSION: &str = "vex";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// Default maximum number of neighbors of a node of the graph
+pub const DEFAULT_MAX_CONN: usize = 16;
+/// Default number of candidates tracked while searching the graph
+pub const DEFAULT_BEAM_WIDTH: usize = 100;
+
+/// Stores the vectors of a segment and a navigable graph of them.
+///
+/// The `.vec` file holds the documents having a vector, as sorted ints,
+/// followed by the raw vectors, as the bits of their floats, in the same order, so
+/// consumers that only need the raw vectors never read the graph. The `.vex`
+/// file holds the neighbors of each vector, the `.vem` file the offsets of
+/// each field in both files.
+///
+/// `max_conn` and `beam_width` trade indexing time and index size against
+/// search recall, they are recorded per field so that segments written with
+/// any settings are searched the same way.
+#[derive(Copy, Clone, Debug)]
+pub struct HnswVectorsFormat {
+    max_conn: usize,
+    beam_width: usize,
+}
+
+impl Default for HnswVectorsFormat {
+    fn default() -> Self {
+        HnswVectorsFormat {
+            max_conn: DEFAULT_MAX_CONN,
+            beam_width: DEFAULT_BEAM_WIDTH,
+        }
+    }
+}
+
+impl HnswVectorsFormat {
+    /// Creates a format linking each vector to at most `max_conn` similar
+    /// vectors, found by keeping the `beam_width` best candidates while
+    /// searching the graph.
+    pub fn new(max_conn: usize, beam_width: usize) -> Result<Self> {
+        if max_conn == 0 {
+            return Err(IllegalArgument("max_conn must be > 0".into()));
+        }
+        if beam_width == 0 {
+            return Err(IllegalArgument("beam_width must be > 0".into()));
+        }
+        Ok(HnswVectorsFormat {
+            max_conn,
+            beam_width,
+        })
+    }
+}
+
+impl VectorsFormat for HnswVectorsFormat {
+    type Reader = HnswVectorsReader;
+
+    fn fields_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<VectorsWriterEnum<DW::IndexOutput>> {
+        Ok(VectorsWriterEnum::Hnsw(HnswVectorsWriter::new(
+            state,
+            self.max_conn,
+            self.beam_width,
+        )?))
+    }
+
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::Reader> {
+        HnswVectorsReader::new(state)
+    }
+}
+
+/// Writer for `HnswVectorsFormat`
+pub struct HnswVectorsWriter<O: IndexOutput> {
+    meta: O,
+    data: O,
+    index: O,
+    max_conn: usize,
+    beam_width: usize,
+}
+
+impl<O: IndexOutput> HnswVectorsWriter<O> {
+    pub fn new<D: Directory, DW: Directory<IndexOutput = O>, C: Codec>(
+        state: &SegmentWriteState<D, DW, C>,
+        max_conn: usize,
+        beam_width: usize,
+    ) -> Result<Self> {
+        let create = |codec: &str, extension: &str| -> Result<O> {
+            let name =
+                segment_file_name(&state.segment_info.name, &state.segment_suffix, extension);
+            let mut output = state.directory.create_output(&name, &state.context)?;
+            codec_util::write_index_header(
+                &mut output,
+                codec,
+                VERSION_CURRENT,
+                state.segment_info.get_id(),
+                &state.segment_suffix,
+            )?;
+            Ok(output)
+        };
+        Ok(HnswVectorsWriter {
+            meta: create(META_CODEC, META_EXTENSION)?,
+            data: create(DATA_CODEC, DATA_EXTENSION)?,
+            index: create(INDEX_CODEC, INDEX_EXTENSION)?,
+            max_conn,
+            beam_width,
+        })
+    }
+}
+
+impl<O: IndexOutput> VectorsWriter for HnswVectorsWriter<O> {
+    fn write_field(&mut self, field_info: &FieldInfo, values: &dyn VectorValues) -> Result<()> {
+        let dimension = field_info.vector_dimension as usize;
+        if dimension == 0 || values.dimension() != dimension {
+            return Err(IllegalArgument(format!(
+                "field '{}' has {} vector dimensions, got vectors of {} dimensions",
+                field_info.name,
+                dimension,
+                values.dimension()
+            )));
+        }
+        let size = values.size();
+        let mut vectors = Vec::with_capacity(size * dimension);
+
+        let docs_offset = self.data.file_pointer();
+        let mut last_doc = -1;
+        for ord in 0..size {
+            let doc = values.doc_id(ord)?;
+            if doc <= last_doc {
+                return Err(IllegalArgument(format!(
+                    "vectors of field '{}' are not ordered by doc: {} after {}",
+                    field_info.name, doc, last_doc
+                )));
+            }
+            self.data.write_int(doc)?;
+            last_doc = doc;
+        }
+        let vectors_offset = self.data.file_pointer();
+        for ord in 0..size {
+            let vector = values.vector(ord)?;
+            for v in &vector {
+                self.data.write_int(v.to_bits() as i32)?;
+            }
+            vectors.extend(vector);
+        }
+
+        let graph = build_graph(
+            &vectors,
+            dimension,
+            field_info.vector_similarity,
+            self.max_conn,
+            self.beam_width,
+        )?;
+        let graph_offset = self.index.file_pointer();
+        let mut node_offsets = Vec::with_capacity(size);
+        for neighbors in &graph {
+            node_offsets.push(self.index.file_pointer() - graph_offset);
+            self.index.write_int(neighbors.len() as i32)?;
+            for &neighbor in neighbors {
+                self.index.write_int(neighbor as i32)?;
+            }
+        }
+        let node_offsets_offset = self.index.file_pointer();
+        for offset in node_offsets {
+            self.index.write_long(offset)?;
+        }
+
+        self.meta.write_vint(field_info.number as i32)?;
+        self.meta.write_vint(dimension as i32)?;
+        self.meta
+            .write_byte(field_info.vector_similarity.as_byte())?;
+        self.meta.write_int(size as i32)?;
+        self.meta.write_long(docs_offset)?;
+        self.meta.write_long(vectors_offset)?;
+        self.meta.write_long(graph_offset)?;
+        self.meta.write_long(node_offsets_offset)?;
+        self.meta.write_vint(self.beam_width as i32)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        // write EOF marker
+        self.meta.write_vint(-1)?;
+        codec_util::write_footer(&mut self.meta)?;
+        codec_util::write_footer(&mut self.data)?;
+        codec_util::write_footer(&mut self.index)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct FieldEntry {
+    dimension: usize,
+    similarity: VectorSimilarity,
+    size: usize,
+    docs_offset: i64,
+    vectors_offset: i64,
+    graph_offset: i64,
+    node_offsets_offset: i64,
+    beam_width: usize,
+}
+
+/// Reader for `HnswVectorsFormat`
+pub struct HnswVectorsReader {
+    fields: HashMap<String, FieldEntry>,
+    data: Box<dyn IndexInput>,
+    index: Box<dyn IndexInput>,
+}
+
+impl HnswVectorsReader {
+    pub fn new<D: Directory, DW: Directory, C: Codec>(
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<HnswVectorsReader> {
+        let file_name = |extension: &str| {
+            segment_file_name(&state.segment_info.name, &state.segment_suffix, extension)
+        };
+
+        let mut meta = state
+            .directory
+            .open_checksum_input(&file_name(META_EXTENSION), state.context)?;
+        let version = codec_util::check_index_header(
+            &mut meta,
+            META_CODEC,
+            VERSION_START,
+            VERSION_CURRENT,
+            state.segment_info.get_id(),
+            &state.segment_suffix,
+        )?;
+        let fields = Self::read_fields(&mut meta, &state.field_infos)?;
+        codec_util::check_footer(&mut meta)?;
+
+        let open = |codec: &str, extension: &str| -> Result<Box<dyn IndexInput>> {
+            let mut input = state
+                .directory
+                .open_input(&file_name(extension), state.context)?;
+            let input_version = codec_util::check_index_header(
+                input.as_mut(),
+                codec,
+                VERSION_START,
+                VERSION_CURRENT,
+                state.segment_info.get_id(),
+                &state.segment_suffix,
+            )?;
+            if input_version != version {
+                return Err(CorruptIndex(format!(
+                    "Format versions mismatch: meta={}, {}={}",
+                    version, extension, input_version
+                )));
+            }
+            codec_util::retrieve_checksum(input.as_mut())?;
+            Ok(input)
+        };
+
+        Ok(HnswVectorsReader {
+            fields,
+            data: open(DATA_CODEC, DATA_EXTENSION)?,
+            index: open(INDEX_CODEC, INDEX_EXTENSION)?,
+        })
+    }
+
+    fn read_fields<T: IndexInput + ?Sized>(
+        input: &mut T,
+        infos: &FieldInfos,
+    ) -> Result<HashMap<String, FieldEntry>> {
+        let mut fields = HashMap::new();
+        loop {
+            let field_num = input.read_vint()?;
+            if field_num == -1 {
+                break;
+            }
+            let field_info = infos
+                .field_info_by_number(field_num as u32)
+                .ok_or_else(|| CorruptIndex(format!("Invalid field number: {}", field_num)))?;
+            let dimension = input.read_vint()? as usize;
+            let similarity = VectorSimilarity::from_byte(input.read_byte()?)?;
+            if dimension != field_info.vector_dimension as usize
+                || similarity != field_info.vector_similarity
+            {
+                return Err(CorruptIndex(format!(
+                    "vectors of field '{}' don't match its field info",
+                    field_info.name
+                )));
+            }
+            let size = input.read_int()?;
+            if size <= 0 {
+                return Err(CorruptIndex(format!(
+                    "Invalid number of vectors for field '{}': {}",
+                    field_info.name, size
+                )));
+            }
+            let entry = FieldEntry {
+                dimension,
+                similarity,
+                size: size as usize,
+                docs_offset: input.read_long()?,
+                vectors_offset: input.read_long()?,
+                graph_offset: input.read_long()?,
+                node_offsets_offset: input.read_long()?,
+                beam_width: input.read_vint()? as usize,
+            };
+            fields.insert(field_info.name.clone(), entry);
+        }
+        Ok(fields)
+    }
+}
+
+impl VectorsReader for HnswVectorsReader {
+    fn check_integrity(&self) -> Result<()> {
+        codec_util::checksum_entire_file(self.data.as_ref())?;
+        codec_util::checksum_entire_file(self.index.as_ref())?;
+        Ok(())
+    }
+
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues>>> {
+        let entry = match self.fields.get(field) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let size = entry.size as i64;
+        let values = HnswVectorValues {
+            docs: self.data.random_access_slice(entry.docs_offset, size * 4)?,
+            vectors: self
+                .data
+                .random_access_slice(entry.vectors_offset, size * entry.dimension as i64 * 4)?,
+            graph: self.index.random_access_slice(
+                entry.graph_offset,
+                entry.node_offsets_offset - entry.graph_offset,
+            )?,
+            node_offsets: self
+                .index
+                .random_access_slice(entry.node_offsets_offset, size * 8)?,
+            entry: entry.clone(),
+        };
+        Ok(Some(Box::new(values)))
+    }
+}
+
+/// The vectors and graph of a field of `HnswVectorsReader`
+struct HnswVectorValues {
+    entry: FieldEntry,
+    docs: Box<dyn RandomAccessInput>,
+    vectors: Box<dyn RandomAccessInput>,
+    graph: Box<dyn RandomAccessInput>,
+    node_offsets: Box<dyn RandomAccessInput>,
+}
+
+impl HnswVectorValues {
+    fn neighbors(&self, ord: usize) -> Result<Vec<usize>> {
+        let offset = self.node_offsets.read_long(ord as u64 * 8)? as u64;
+        let count = self.graph.read_int(offset)?;
+        let mut neighbors = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count as u64 {
+            let neighbor = self.graph.read_int(offset + 4 + i * 4)? as usize;
+            if neighbor >= self.entry.size {
+                return Err(CorruptIndex(format!(
+                    "invalid neighbor {} of vector {}",
+                    neighbor, ord
+                )));
+            }
+            neighbors.push(neighbor);
+        }
+        Ok(neighbors)
+    }
+
+    fn score(&self, target: &[f32], ord: usize) -> Result<f32> {
+        Ok(self.entry.similarity.compare(target, &self.vector(ord)?))
+    }
+}
+
+impl VectorValues for HnswVectorValues {
+    fn dimension(&self) -> usize {
+        self.entry.dimension
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.entry.similarity
+    }
+
+    fn size(&self) -> usize {
+        self.entry.size
+    }
+
+    fn doc_id(&self, ord: usize) -> Result<DocId> {
+        self.docs.read_int(ord as u64 * 4)
+    }
+
+    fn vector(&self, ord: usize) -> Result<Vec<f32>> {
+        let start = (ord * self.entry.dimension) as u64 * 4;
+        let mut vector = Vec::with_capacity(self.entry.dimension);
+        for i in 0..self.entry.dimension as u64 {
+            vector.push(f32::from_bits(self.vectors.read_int(start + i * 4)? as u32));
+        }
+        Ok(vector)
+    }
+
+    fn get(&self, doc_id: DocId) -> Result<Option<Vec<f32>>> {
+        let (mut from, mut to) = (0, self.entry.size);
+        while from < to {
+            let mid = from + (to - from) / 2;
+            let doc = self.doc_id(mid)?;
+            if doc < doc_id {
+                from = mid + 1;
+            } else if doc > doc_id {
+                to = mid;
+            } else {
+                return self.vector(mid).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Searches the graph of the vectors, the hits are approximate: some
+    /// similar vectors may be missed, more likely when `accept_docs` rejects
+    /// most of the documents.
+    fn search(
+        &self,
+        target: &[f32],
+        k: usize,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoreDoc>> {
+        check_dimension(self.entry.dimension, target)?;
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        let neighbors = search_graph(
+            0,
+            self.entry.size,
+            max(k, self.entry.beam_width),
+            |ord| self.score(target, ord),
+            |ord| self.neighbors(ord),
+            |ord| match accept_docs {
+                Some(bits) => Ok(bits.get(self.doc_id(ord)? as usize)),
+                None => Ok(true),
+            },
+        )?;
+        let mut hits = Vec::with_capacity(neighbors.len());
+        for neighbor in neighbors {
+            hits.push(ScoreDoc::new(self.doc_id(neighbor.ord)?, neighbor.score));
+        }
+        Ok(top_hits(hits, k))
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod hnsw_graph;
+
+mod hnsw_vectors_format;
+
+pub use self::hnsw_vectors_format::{HnswVectorsFormat, HnswVectorsReader, HnswVectorsWriter};
+
+mod vector_values_writer;
+
+pub use self::vector_values_writer::{BufferedVectorValues, VectorValuesWriter};
+
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
+use crate::core::codec::Codec;
+use crate::core::index::merge::{DocMap, MergeState};
+use crate::core::search::sort_field::ScoreDoc;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexOutput;
+use crate::core::util::{Bits, DocId};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// Maximum number of dimensions of an indexed vector
+pub const MAX_VECTOR_DIMENSION: u32 = 1024;
+
+/// How the vectors of a field are compared, all of them give a higher score
+/// to more similar vectors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum VectorSimilarity {
+    /// `1 / (1 + d)` where `d` is the squared euclidean distance
+    Euclidean,
+    /// `(1 + dot) / 2`, the vectors must be normalized to unit length
+    DotProduct,
+    /// `(1 + cos) / 2` of the angle between the vectors
+    Cosine,
+}
+
+impl VectorSimilarity {
+    /// Returns the similarity score of two vectors of the same dimension.
+    pub fn compare(self, v1: &[f32], v2: &[f32]) -> f32 {
+        debug_assert_eq!(v1.len(), v2.len());
+        match self {
+            VectorSimilarity::Euclidean => {
+                let distance: f32 = v1.iter().zip(v2).map(|(a, b)| (a - b) * (a - b)).sum();
+                1.0 / (1.0 + distance)
+            }
+            VectorSimilarity::DotProduct => (1.0 + dot_product(v1, v2)) / 2.0,
+            VectorSimilarity::Cosine => {
+                let norms = (dot_product(v1, v1) * dot_product(v2, v2)).sqrt();
+                if norms == 0.0 {
+                    0.5
+                } else {
+                    (1.0 + dot_product(v1, v2) / norms) / 2.0
+                }
+            }
+        }
+    }
+
+    pub(crate) fn as_byte(self) -> u8 {
+        match self {
+            VectorSimilarity::Euclidean => 0,
+            VectorSimilarity::DotProduct => 1,
+            VectorSimilarity::Cosine => 2,
+        }
+    }
+
+    pub(crate) fn from_byte(b: u8) -> Result<VectorSimilarity> {
+        match b {
+            0 => Ok(VectorSimilarity::Euclidean),
+            1 => Ok(VectorSimilarity::DotProduct),
+            2 => Ok(VectorSimilarity::Cosine),
+            _ => Err(CorruptIndex(format!("invalid vector similarity: {}", b))),
+        }
+    }
+}
+
+fn dot_product(v1: &[f32], v2: &[f32]) -> f32 {
+    v1.iter().zip(v2).map(|(a, b)| a * b).sum()
+}
+
+/// Random access to the vectors of one field of a segment.
+///
+/// Each document has at most one vector, vectors are numbered by ordinals in
+/// `0..size()`.
+pub trait VectorValues: Send + Sync {
+    /// Number of dimensions of the vectors
+    fn dimension(&self) -> usize;
+
+    fn similarity(&self) -> VectorSimilarity;
+
+    /// Number of documents having a vector
+    fn size(&self) -> usize;
+
+    /// Returns the document of the vector at `ord`.
+    fn doc_id(&self, ord: usize) -> Result<DocId>;
+
+    /// Returns the vector at `ord`.
+    fn vector(&self, ord: usize) -> Result<Vec<f32>>;
+
+    /// Returns the vector of the document, if it has one.
+    fn get(&self, doc_id: DocId) -> Result<Option<Vec<f32>>>;
+
+    /// Returns the `k` documents whose vectors are the most similar to
+    /// `target`, best first, skipping the documents not set in
+    /// `accept_docs`.
+    ///
+    /// This compares `target` with all the vectors, formats indexing a graph
+    /// of the vectors override it with an approximate search.
+    fn search(
+        &self,
+        target: &[f32],
+        k: usize,
+        accept_docs: Option<&dyn Bits>,
+    ) -> Result<Vec<ScoreDoc>> {
+        check_dimension(self.dimension(), target)?;
+        let similarity = self.similarity();
+        let mut hits = Vec::with_capacity(self.size());
+        for ord in 0..self.size() {
+            let doc = self.doc_id(ord)?;
+            if accept_docs.is_none_or(|bits| bits.get(doc as usize)) {
+                hits.push(ScoreDoc::new(
+                    doc,
+                    similarity.compare(target, &self.vector(ord)?),
+                ));
+            }
+        }
+        Ok(top_hits(hits, k))
+    }
+}
+
+fn check_dimension(dimension: usize, target: &[f32]) -> Result<()> {
+    if target.len() != dimension {
+        return Err(IllegalArgument(format!(
+            "vector dimension mismatch: the field has {} dimensions, the target has {}",
+            dimension,
+            target.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Sorts the hits by decreasing score, then by doc, and keeps the first `k`.
+fn top_hits(mut hits: Vec<ScoreDoc>, k: usize) -> Vec<ScoreDoc> {
+    hits.sort_by(|h1, h2| {
+        h2.score
+            .partial_cmp(&h1.score)
+            .unwrap_or(Ordering::Equal)
+            .then(h1.doc.cmp(&h2.doc))
+    });
+    hits.truncate(k);
+    hits
+}
+
+/// Reads the vectors of a segment.
+pub trait VectorsReader: Send + Sync {
+    /// Checks consistency of this reader by verifying the checksums of its
+    /// files.
+    fn check_integrity(&self) -> Result<()>;
+
+    /// Returns the vectors of `field`, or `None` if the field has no vectors
+    /// in this segment.
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues>>>;
+}
+
+/// Encodes/decodes per-document vectors, for kNN search and raw access.
+pub trait VectorsFormat {
+    type Reader: VectorsReader + 'static;
+
+    /// Returns a writer of the vectors of a new segment.
+    fn fields_writer<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<VectorsWriterEnum<DW::IndexOutput>>;
+
+    /// Returns a reader of the vectors of a segment, it holds open any file
+    /// it needs.
+    fn fields_reader<D: Directory, DW: Directory, C: Codec>(
+        &self,
+        state: &SegmentReadState<'_, D, DW, C>,
+    ) -> Result<Self::Reader>;
+}
+
+/// Writes the vectors of a segment, one field at a time.
+pub trait VectorsWriter {
+    /// Writes all the vectors of a field, ordinals must be ordered by doc.
+    fn write_field(&mut self, field_info: &FieldInfo, values: &dyn VectorValues) -> Result<()>;
+
+    /// Merges the vectors of one field of the merged segments, dropping the
+    /// deleted documents.
+    fn merge_one_field<D: Directory, C: Codec>(
+        &mut self,
+        merge_state: &MergeState<D, C>,
+        field_info: &FieldInfo,
+    ) -> Result<()> {
+        let mut values = BufferedVectorValues::new(
+            field_info.vector_dimension as usize,
+            field_info.vector_similarity,
+        );
+        for (i, reader) in merge_state.vectors_readers.iter().enumerate() {
+            let reader_values = match reader {
+                Some(reader) => reader.vector_values(&field_info.name)?,
+                None => None,
+            };
+            if let Some(reader_values) = reader_values {
+                let doc_map = &merge_state.doc_maps[i];
+                for ord in 0..reader_values.size() {
+                    let doc = doc_map.get(reader_values.doc_id(ord)?)?;
+                    if doc != -1 {
+                        values.add(doc, &reader_values.vector(ord)?)?;
+                    }
+                }
+            }
+        }
+        values.sort();
+        if values.size() > 0 {
+            self.write_field(field_info, &values)?;
+        }
+        Ok(())
+    }
+
+    /// Merges the vectors of all the fields of the merged segments.
+    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &MergeState<D, C>) -> Result<()> {
+        for field_info in merge_state
+            .merge_field_infos
+            .as_ref()
+            .unwrap()
+            .by_number
+            .values()
+        {
+            if field_info.vector_dimension > 0 {
+                self.merge_one_field(merge_state, field_info)?;
+            }
+        }
+        self.finish()
+    }
+
+    /// Called once at the end before close
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Enum dispatch of the `VectorsWriter` of each `VectorsFormat`
+pub enum VectorsWriterEnum<O: IndexOutput> {
+    Hnsw(HnswVectorsWriter<O>),
+}
+
+impl<O: IndexOutput> VectorsWriter for VectorsWriterEnum<O> {
+    fn write_field(&mut self, field_info: &FieldInfo, values: &dyn VectorValues) -> Result<()> {
+        match self {
+            VectorsWriterEnum::Hnsw(w) => w.write_field(field_info, values),
+        }
+    }
+
+    fn merge<D: Directory, C: Codec>(&mut self, merge_state: &MergeState<D, C>) -> Result<()> {
+        match self {
+            VectorsWriterEnum::Hnsw(w) => w.merge(merge_state),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self {
+            VectorsWriterEnum::Hnsw(w) => w.finish(),
+        }
+    }
+}
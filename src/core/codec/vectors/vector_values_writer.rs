@@ -0,0 +1,151 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::segment_infos::SegmentWriteState;
+use crate::core::codec::vectors::{VectorSimilarity, VectorValues, VectorsWriter};
+use crate::core::codec::{Codec, SorterDocMap};
+use crate::core::store::directory::Directory;
+use crate::core::util::DocId;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Vectors held in memory, e.g. the vectors buffered for a flush or gathered
+/// from the segments of a merge.
+pub struct BufferedVectorValues {
+    dimension: usize,
+    similarity: VectorSimilarity,
+    docs: Vec<DocId>,
+    // the vectors, one after the other
+    vectors: Vec<f32>,
+}
+
+impl BufferedVectorValues {
+    pub fn new(dimension: usize, similarity: VectorSimilarity) -> Self {
+        BufferedVectorValues {
+            dimension,
+            similarity,
+            docs: vec![],
+            vectors: vec![],
+        }
+    }
+
+    pub fn add(&mut self, doc_id: DocId, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.dimension {
+            return Err(IllegalArgument(format!(
+                "vector of doc {} has {} dimensions, expected {}",
+                doc_id,
+                vector.len(),
+                self.dimension
+            )));
+        }
+        self.docs.push(doc_id);
+        self.vectors.extend_from_slice(vector);
+        Ok(())
+    }
+
+    /// Orders the vectors by doc.
+    pub fn sort(&mut self) {
+        if self.docs.windows(2).all(|w| w[0] < w[1]) {
+            return;
+        }
+        let mut ords: Vec<usize> = (0..self.docs.len()).collect();
+        ords.sort_by_key(|&ord| self.docs[ord]);
+        let mut docs = Vec::with_capacity(self.docs.len());
+        let mut vectors = Vec::with_capacity(self.vectors.len());
+        for ord in ords {
+            docs.push(self.docs[ord]);
+            vectors.extend_from_slice(self.slice(ord));
+        }
+        self.docs = docs;
+        self.vectors = vectors;
+    }
+
+    fn slice(&self, ord: usize) -> &[f32] {
+        &self.vectors[ord * self.dimension..(ord + 1) * self.dimension]
+    }
+}
+
+impl VectorValues for BufferedVectorValues {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.similarity
+    }
+
+    fn size(&self) -> usize {
+        self.docs.len()
+    }
+
+    fn doc_id(&self, ord: usize) -> Result<DocId> {
+        Ok(self.docs[ord])
+    }
+
+    fn vector(&self, ord: usize) -> Result<Vec<f32>> {
+        Ok(self.slice(ord).to_vec())
+    }
+
+    fn get(&self, doc_id: DocId) -> Result<Option<Vec<f32>>> {
+        Ok(self
+            .docs
+            .binary_search(&doc_id)
+            .ok()
+            .map(|ord| self.slice(ord).to_vec()))
+    }
+}
+
+/// Buffers the vectors of a field until the segment is flushed.
+pub struct VectorValuesWriter {
+    field_info: FieldInfo,
+    values: BufferedVectorValues,
+}
+
+impl VectorValuesWriter {
+    pub fn new(field_info: &FieldInfo) -> VectorValuesWriter {
+        VectorValuesWriter {
+            field_info: field_info.clone(),
+            values: BufferedVectorValues::new(
+                field_info.vector_dimension as usize,
+                field_info.vector_similarity,
+            ),
+        }
+    }
+
+    pub fn add_value(&mut self, doc_id: DocId, vector: &[f32]) -> Result<()> {
+        if self.values.docs.last().is_some_and(|&last| last >= doc_id) {
+            return Err(IllegalArgument(format!(
+                "vector field '{}' appears more than once in this document (only one value \
+                 is allowed per field)",
+                self.field_info.name
+            )));
+        }
+        self.values.add(doc_id, vector)
+    }
+
+    pub fn flush<D: Directory, DW: Directory, C: Codec, W: VectorsWriter>(
+        &mut self,
+        _state: &SegmentWriteState<D, DW, C>,
+        sort_map: Option<&impl SorterDocMap>,
+        writer: &mut W,
+    ) -> Result<()> {
+        if let Some(sort_map) = sort_map {
+            for doc in &mut self.values.docs {
+                *doc = sort_map.old_to_new(*doc);
+            }
+            self.values.sort();
+        }
+        writer.write_field(&self.field_info, &self.values)
+    }
+}
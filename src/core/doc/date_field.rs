@@ -0,0 +1,167 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::SystemTime;
+
+use crate::core::analysis::TokenStream;
+use crate::core::codec::vectors::VectorSimilarity;
+use crate::core::codec::Codec;
+use crate::core::doc::{
+    DateTools, Field, FieldType, Fieldable, Resolution, NUMERIC_DOC_VALUES_FIELD_TYPE,
+};
+use crate::core::search::query::{LongPoint, Query};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::Result;
+
+/// An indexed date for fast range filters, the date is indexed as a 1D
+/// `LongPoint` of milliseconds since the unix epoch, truncated to a `Resolution`.
+///
+/// If you also need to sort on or store the value, add a separate
+/// `DateDocValuesField` or `StoredField` instance.
+pub struct DatePoint {
+    field: Field,
+}
+
+impl DatePoint {
+    pub fn new(name: &str, time: SystemTime, resolution: Resolution) -> DatePoint {
+        let millis = DateTools::round(DateTools::to_millis(time), resolution);
+        let field_type = FieldType {
+            tokenized: false,
+            dimension_count: 1,
+            index_dimension_count: 1,
+            dimension_num_bytes: 8,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
+            ..Default::default()
+        };
+        DatePoint {
+            field: Field::new_bytes(name.to_string(), LongPoint::pack(&[millis]), field_type),
+        }
+    }
+
+    /// The indexed milliseconds since the unix epoch.
+    pub fn millis(&self) -> i64 {
+        LongPoint::decode_dimension(self.field.binary_value().unwrap())
+    }
+
+    /// Create a query for matching an exact date.
+    pub fn new_exact_query<C: Codec>(field: String, time: SystemTime) -> Result<Box<dyn Query<C>>> {
+        DatePoint::new_range_query(field, time, time)
+    }
+
+    /// Create a range query for dates, both ends are inclusive.
+    pub fn new_range_query<C: Codec>(
+        field: String,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Box<dyn Query<C>>> {
+        LongPoint::new_range_query(
+            field,
+            DateTools::to_millis(start),
+            DateTools::to_millis(end),
+        )
+    }
+}
+
+impl Fieldable for DatePoint {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+/// Per-document date doc values for sorting, the date is stored as a numeric
+/// doc value of milliseconds since the unix epoch, truncated to a `Resolution`.
+pub struct DateDocValuesField {
+    field: Field,
+}
+
+impl DateDocValuesField {
+    pub fn new(name: &str, time: SystemTime, resolution: Resolution) -> DateDocValuesField {
+        let millis = DateTools::round(DateTools::to_millis(time), resolution);
+        DateDocValuesField {
+            field: Field::new(
+                name.to_string(),
+                NUMERIC_DOC_VALUES_FIELD_TYPE,
+                Some(VariantValue::Long(millis)),
+                None,
+            ),
+        }
+    }
+
+    pub fn time(&self) -> SystemTime {
+        DateTools::from_millis(self.field.field_data().unwrap().get_long().unwrap())
+    }
+}
+
+impl Fieldable for DateDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field.numeric_value()
+    }
+}
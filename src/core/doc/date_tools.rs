@@ -0,0 +1,353 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+const MILLIS_PER_SECOND: i64 = 1000;
+const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+/// The precision of a date, see `DateTools`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Resolution {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Millisecond,
+}
+
+impl Resolution {
+    /// Length of the `DateTools` string of a date with this resolution.
+    fn format_len(self) -> usize {
+        match self {
+            Resolution::Year => 4,
+            Resolution::Month => 6,
+            Resolution::Day => 8,
+            Resolution::Hour => 10,
+            Resolution::Minute => 12,
+            Resolution::Second => 14,
+            Resolution::Millisecond => 17,
+        }
+    }
+
+    fn from_format_len(len: usize) -> Option<Resolution> {
+        match len {
+            4 => Some(Resolution::Year),
+            6 => Some(Resolution::Month),
+            8 => Some(Resolution::Day),
+            10 => Some(Resolution::Hour),
+            12 => Some(Resolution::Minute),
+            14 => Some(Resolution::Second),
+            17 => Some(Resolution::Millisecond),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Provides support for converting dates to strings and vice-versa, and for
+/// truncating dates to a given `Resolution`.
+///
+/// Dates are handled as milliseconds since the unix epoch, in UTC. The string
+/// format is `yyyyMMddHHmmssSSS` cut after the resolution of the date, e.g.
+/// `20200115` at day resolution, so that strings sort like the dates they
+/// represent and can be indexed as terms.
+pub struct DateTools;
+
+impl DateTools {
+    /// Converts a time to milliseconds since the unix epoch.
+    pub fn to_millis(time: SystemTime) -> i64 {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        }
+    }
+
+    /// Converts milliseconds since the unix epoch back to a time.
+    pub fn from_millis(millis: i64) -> SystemTime {
+        if millis >= 0 {
+            UNIX_EPOCH + Duration::from_millis(millis as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_millis(millis.unsigned_abs())
+        }
+    }
+
+    /// Truncates a date to the given resolution, e.g. rounding 2020-01-15T10:30 to
+    /// `Resolution::Month` gives 2020-01-01T00:00.
+    pub fn round(millis: i64, resolution: Resolution) -> i64 {
+        let unit = match resolution {
+            Resolution::Year | Resolution::Month => {
+                let (year, month, _) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+                let month = if resolution == Resolution::Year {
+                    1
+                } else {
+                    month
+                };
+                return days_from_civil(year, month, 1) * MILLIS_PER_DAY;
+            }
+            Resolution::Day => MILLIS_PER_DAY,
+            Resolution::Hour => MILLIS_PER_HOUR,
+            Resolution::Minute => MILLIS_PER_MINUTE,
+            Resolution::Second => MILLIS_PER_SECOND,
+            Resolution::Millisecond => 1,
+        };
+        millis - millis.rem_euclid(unit)
+    }
+
+    /// Returns the last millisecond of the period of the given resolution that
+    /// the date falls in, e.g. 2020-01-31T23:59:59.999 for any date of January 2020
+    /// at `Resolution::Month`.
+    pub fn end_of(millis: i64, resolution: Resolution) -> i64 {
+        let start = DateTools::round(millis, resolution);
+        let next = match resolution {
+            Resolution::Year | Resolution::Month => {
+                let (year, month, _) = civil_from_days(start.div_euclid(MILLIS_PER_DAY));
+                let (year, month) = match resolution {
+                    Resolution::Year => (year + 1, 1),
+                    _ if month == 12 => (year + 1, 1),
+                    _ => (year, month + 1),
+                };
+                days_from_civil(year, month, 1) * MILLIS_PER_DAY
+            }
+            Resolution::Day => start + MILLIS_PER_DAY,
+            Resolution::Hour => start + MILLIS_PER_HOUR,
+            Resolution::Minute => start + MILLIS_PER_MINUTE,
+            Resolution::Second => start + MILLIS_PER_SECOND,
+            Resolution::Millisecond => start + 1,
+        };
+        next - 1
+    }
+
+    /// Converts a date to a string suitable for indexing, see `DateTools`.
+    pub fn time_to_string(millis: i64, resolution: Resolution) -> String {
+        let days = millis.div_euclid(MILLIS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let ms = millis.rem_euclid(MILLIS_PER_DAY);
+        let s = format!(
+            "{:04}{:02}{:02}{:02}{:02}{:02}{:03}",
+            year,
+            month,
+            day,
+            ms / MILLIS_PER_HOUR,
+            ms % MILLIS_PER_HOUR / MILLIS_PER_MINUTE,
+            ms % MILLIS_PER_MINUTE / MILLIS_PER_SECOND,
+            ms % MILLIS_PER_SECOND
+        );
+        s[..resolution.format_len()].to_string()
+    }
+
+    /// Converts a string produced by `time_to_string` back to a date, the
+    /// resolution is implied by the length of the string.
+    pub fn string_to_time(s: &str) -> Result<i64> {
+        DateTools::parse_compact(s).map(|(millis, _)| millis)
+    }
+
+    /// Parses either a `DateTools` string or an ISO 8601 date such as `2020`,
+    /// `2020-01-15`, `2020-01-15T10:30:00` or `2020-01-15T10:30:00.250Z`, in UTC.
+    ///
+    /// Returns the first millisecond of the date along with its resolution, so
+    /// `2020-01` is the whole month of January 2020.
+    pub fn parse(s: &str) -> Result<(i64, Resolution)> {
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            return DateTools::parse_compact(s);
+        }
+
+        const ISO_PATTERN: &[u8] = b"####-##-##T##:##:##.###";
+        let iso = s.strip_suffix('Z').unwrap_or(s).as_bytes();
+        let valid = matches!(iso.len(), 4 | 7 | 10 | 13 | 16 | 19 | 23)
+            && iso.iter().zip(ISO_PATTERN).all(|(&c, &p)| {
+                if p == b'#' {
+                    c.is_ascii_digit()
+                } else {
+                    c == p
+                }
+            });
+        if !valid {
+            return Err(IllegalArgument(format!("invalid date '{}'", s)));
+        }
+        let digits: String = iso
+            .iter()
+            .filter(|c| c.is_ascii_digit())
+            .map(|&c| c as char)
+            .collect();
+        DateTools::parse_compact(&digits)
+    }
+
+    fn parse_compact(s: &str) -> Result<(i64, Resolution)> {
+        let invalid = || IllegalArgument(format!("invalid date '{}'", s));
+        let resolution = Resolution::from_format_len(s.len()).ok_or_else(invalid)?;
+        if !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let part = |start: usize, end: usize, default: i64| -> i64 {
+            if s.len() >= end {
+                s[start..end].parse().unwrap()
+            } else {
+                default
+            }
+        };
+        let year = part(0, 4, 0);
+        let month = part(4, 6, 1);
+        let day = part(6, 8, 1);
+        let hour = part(8, 10, 0);
+        let minute = part(10, 12, 0);
+        let second = part(12, 14, 0);
+        let millis = part(14, 17, 0);
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(invalid());
+        }
+        let millis = days_from_civil(year, month, day) * MILLIS_PER_DAY
+            + hour * MILLIS_PER_HOUR
+            + minute * MILLIS_PER_MINUTE
+            + second * MILLIS_PER_SECOND
+            + millis;
+        Ok((millis, resolution))
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the unix epoch of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`, returns `(year, month, day)`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_round_trip() {
+        // 2004-09-01T13:45:12.345Z
+        let millis = 1_094_046_312_345;
+        assert_eq!(
+            DateTools::time_to_string(millis, Resolution::Millisecond),
+            "20040901134512345"
+        );
+        assert_eq!(
+            DateTools::time_to_string(millis, Resolution::Day),
+            "20040901"
+        );
+        assert_eq!(
+            DateTools::time_to_string(0, Resolution::Second),
+            "19700101000000"
+        );
+        assert_eq!(
+            DateTools::time_to_string(-1, Resolution::Millisecond),
+            "19691231235959999"
+        );
+        assert_eq!(
+            DateTools::string_to_time("20040901134512345").unwrap(),
+            millis
+        );
+        assert_eq!(
+            DateTools::string_to_time("200409").unwrap(),
+            DateTools::round(millis, Resolution::Month)
+        );
+        assert!(DateTools::string_to_time("20041301").is_err());
+        assert!(DateTools::string_to_time("20040230").is_err());
+        assert!(DateTools::string_to_time("2004090").is_err());
+    }
+
+    #[test]
+    fn test_round() {
+        let millis = DateTools::string_to_time("20040901134512345").unwrap();
+        let round =
+            |r| DateTools::time_to_string(DateTools::round(millis, r), Resolution::Millisecond);
+        assert_eq!(round(Resolution::Year), "20040101000000000");
+        assert_eq!(round(Resolution::Month), "20040901000000000");
+        assert_eq!(round(Resolution::Day), "20040901000000000");
+        assert_eq!(round(Resolution::Hour), "20040901130000000");
+        assert_eq!(round(Resolution::Minute), "20040901134500000");
+        assert_eq!(round(Resolution::Second), "20040901134512000");
+        assert_eq!(DateTools::round(-1, Resolution::Day), -MILLIS_PER_DAY);
+
+        let end = |s, r| {
+            let millis = DateTools::string_to_time(s).unwrap();
+            DateTools::time_to_string(DateTools::end_of(millis, r), Resolution::Millisecond)
+        };
+        assert_eq!(end("20040215", Resolution::Month), "20040229235959999");
+        assert_eq!(end("20041231", Resolution::Month), "20041231235959999");
+        assert_eq!(end("2004", Resolution::Year), "20041231235959999");
+        assert_eq!(end("2004090113", Resolution::Hour), "20040901135959999");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            DateTools::parse("2004-09-01T13:45:12.345Z").unwrap(),
+            (1_094_046_312_345, Resolution::Millisecond)
+        );
+        assert_eq!(
+            DateTools::parse("2004-09").unwrap(),
+            (
+                DateTools::string_to_time("200409").unwrap(),
+                Resolution::Month
+            )
+        );
+        assert_eq!(
+            DateTools::parse("2004-09-01T13").unwrap().1,
+            Resolution::Hour
+        );
+        assert_eq!(DateTools::parse("20040901").unwrap().1, Resolution::Day);
+        assert!(DateTools::parse("2004-9-1").is_err());
+        assert!(DateTools::parse("2004-09-01 13:45").is_err());
+        assert!(DateTools::parse("yesterday").is_err());
+    }
+}
@@ -16,7 +16,8 @@ use serde::Serialize;
 use crate::core::analysis::{BinaryTokenStream, TokenStream};
 use crate::core::doc::{
     Field, FieldType, Fieldable, BINARY_DOC_VALUES_FIELD_TYPE, NUMERIC_DOC_VALUES_FIELD_TYPE,
-    SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE, SORTED_SET_DOC_VALUES_FIELD_TYPE,
+    SORTED_DOC_VALUES_FIELD_TYPE, SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE,
+    SORTED_SET_DOC_VALUES_FIELD_TYPE,
 };
 use crate::core::util::{BytesRef, Numeric, VariantValue};
 
@@ -76,19 +77,26 @@ impl Default for DocValuesType {
     }
 }
 
+/// Field that stores a per-document opaque `[u8]` value.
+///
+/// Unlike the sorted variants the value is not limited to the max term length,
+/// it is read back as is through `LeafReader::get_binary_doc_values`.
 pub struct BinaryDocValuesField {
     field: Field,
 }
 
 impl BinaryDocValuesField {
     pub fn new(name: &str, value: &[u8]) -> BinaryDocValuesField {
-        BinaryDocValuesField {
-            field: Field::new(
-                String::from(name),
-                BINARY_DOC_VALUES_FIELD_TYPE,
-                Some(VariantValue::from(value)),
-                None,
-            ),
+        // `Field::new` would truncate the value to the max term length
+        let mut field = Field::new(String::from(name), BINARY_DOC_VALUES_FIELD_TYPE, None, None);
+        field.set_field_data(Some(VariantValue::from(value)));
+        BinaryDocValuesField { field }
+    }
+
+    pub fn binary_value(&self) -> &[u8] {
+        match self.field.field_data().unwrap() {
+            VariantValue::Binary(ref v) => v,
+            _ => unreachable!(),
         }
     }
 }
@@ -287,6 +295,70 @@ impl Fieldable for SortedNumericDocValuesField {
     }
 }
 
+/// Field that stores a per-document `[u8]` value, indexed for sorting.
+///
+/// Only distinct values are stored, each document points to the ordinal of its value.
+pub struct SortedDocValuesField {
+    field: Field,
+}
+
+impl SortedDocValuesField {
+    pub fn new(name: &str, value: &[u8]) -> SortedDocValuesField {
+        SortedDocValuesField {
+            field: Field::new(
+                String::from(name),
+                SORTED_DOC_VALUES_FIELD_TYPE,
+                Some(VariantValue::from(value)),
+                None,
+            ),
+        }
+    }
+
+    pub fn binary_value(&self) -> &[u8] {
+        match self.field.field_data().unwrap() {
+            VariantValue::Binary(ref v) => v,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Fieldable for SortedDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+/// Field that stores a set of per-document `[u8]` values, indexed for
+/// faceting, grouping and joining. Add one instance per value, duplicate
+/// values of a document are only stored once.
 pub struct SortedSetDocValuesField {
     field: Field,
 }
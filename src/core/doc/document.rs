@@ -11,9 +11,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::analysis::TokenStream;
 use crate::core::codec::field_infos::FieldInfo;
-use crate::core::doc::{DocValuesType, Field, FieldType, Fieldable, IndexOptions};
-use crate::core::util::VariantValue;
+use crate::core::doc::{
+    DocValuesType, Field, FieldType, Fieldable, IndexOptions, STORE_FIELD_TYPE,
+};
+use crate::core::util::{Numeric, VariantValue};
 
 use crate::Result;
 
@@ -78,6 +81,47 @@ impl StoredField {
             }
         }
     }
+
+    /// Creates a stored-only field, the value is not indexed.
+    pub fn with_value<V: Into<VariantValue>>(name: &str, value: V) -> StoredField {
+        StoredField {
+            field: Field::new(name.to_string(), STORE_FIELD_TYPE, Some(value.into()), None),
+        }
+    }
+}
+
+impl Fieldable for StoredField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field.numeric_value()
+    }
 }
 
 #[derive(Debug)]
@@ -98,6 +142,53 @@ impl Document {
     pub fn remove_field(&mut self, name: &str) {
         self.fields.retain(|ref v| v.field.name() != name);
     }
+
+    /// Returns the first field with the given name, if any.
+    pub fn get_field(&self, name: &str) -> Option<&StoredField> {
+        self.fields.iter().find(|f| f.field.name() == name)
+    }
+
+    /// Returns the value of the first field with the given name, if any.
+    pub fn get_value(&self, name: &str) -> Option<&VariantValue> {
+        self.get_field(name).and_then(|f| f.field.field_data())
+    }
+
+    /// Returns the values of all the fields with the given name, in the order
+    /// they were added to the document.
+    pub fn get_values(&self, name: &str) -> Vec<&VariantValue> {
+        self.fields
+            .iter()
+            .filter(|f| f.field.name() == name)
+            .filter_map(|f| f.field.field_data())
+            .collect()
+    }
+
+    /// Returns the first string value of the field, `None` if the field is missing
+    /// or wasn't stored as a string.
+    pub fn get_string(&self, name: &str) -> Option<&str> {
+        self.get_value(name).and_then(|v| v.get_string())
+    }
+
+    pub fn get_binary(&self, name: &str) -> Option<&[u8]> {
+        self.get_value(name).and_then(|v| v.get_binary())
+    }
+
+    /// Returns the first int value of the field, `i16` values are stored as ints.
+    pub fn get_int(&self, name: &str) -> Option<i32> {
+        self.get_value(name).and_then(|v| v.get_int())
+    }
+
+    pub fn get_long(&self, name: &str) -> Option<i64> {
+        self.get_value(name).and_then(|v| v.get_long())
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f32> {
+        self.get_value(name).and_then(|v| v.get_float())
+    }
+
+    pub fn get_double(&self, name: &str) -> Option<f64> {
+        self.get_value(name).and_then(|v| v.get_double())
+    }
 }
 
 pub struct DocumentStoredFieldVisitor {
@@ -143,18 +234,11 @@ impl StoredFieldVisitor for DocumentStoredFieldVisitor {
             0,
         );
 
-        match String::from_utf8(value) {
-            Ok(s) => {
-                self.fields.push(StoredField::new(
-                    &field_info.name,
-                    Some(field_type),
-                    VariantValue::VString(s),
-                ));
-            }
-            Err(e) => {
-                panic!("string_field failed: {:?}", e);
-            }
-        }
+        self.fields.push(StoredField::new(
+            &field_info.name,
+            Some(field_type),
+            VariantValue::VString(String::from_utf8(value)?),
+        ));
         Ok(())
     }
 
@@ -0,0 +1,95 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::doc::{
+    BinaryDocValuesField, Fieldable, NumericDocValuesField, SortedDocValuesField,
+    SortedNumericDocValuesField, SortedSetDocValuesField, StoredField, StringField, TextField,
+};
+use crate::core::util::VariantValue;
+
+/// Starts building a new document, see `DocumentBuilder`.
+pub fn doc() -> DocumentBuilder {
+    DocumentBuilder::new()
+}
+
+/// Collects the fields of a document to pass to `IndexWriter::add_document`.
+///
+/// ```ignore
+/// let fields = doc()
+///     .string("id", "1")
+///     .text("title", title)
+///     .stored_text("title.raw", title)
+///     .numeric_dv("weight", 1)
+///     .build();
+/// writer.add_document(fields)?;
+/// ```
+#[derive(Default)]
+pub struct DocumentBuilder {
+    fields: Vec<Box<dyn Fieldable>>,
+}
+
+impl DocumentBuilder {
+    pub fn new() -> DocumentBuilder {
+        DocumentBuilder { fields: vec![] }
+    }
+
+    /// Adds an arbitrary field.
+    pub fn field<F: Fieldable + 'static>(mut self, field: F) -> Self {
+        self.fields.push(Box::new(field));
+        self
+    }
+
+    /// Adds an un-stored, tokenized `TextField`.
+    pub fn text(self, name: &str, value: &str) -> Self {
+        self.field(TextField::new(name, value, false))
+    }
+
+    /// Adds an un-stored, un-tokenized `StringField`.
+    pub fn string(self, name: &str, value: &str) -> Self {
+        self.field(StringField::new(name, value, false))
+    }
+
+    /// Adds a stored-only text value.
+    pub fn stored_text(self, name: &str, value: &str) -> Self {
+        self.stored(name, value)
+    }
+
+    /// Adds a stored-only value of any type the stored fields format supports.
+    pub fn stored<V: Into<VariantValue>>(self, name: &str, value: V) -> Self {
+        self.field(StoredField::with_value(name, value))
+    }
+
+    pub fn numeric_dv(self, name: &str, value: i64) -> Self {
+        self.field(NumericDocValuesField::new(name, value))
+    }
+
+    pub fn sorted_numeric_dv(self, name: &str, value: i64) -> Self {
+        self.field(SortedNumericDocValuesField::new(name, value))
+    }
+
+    pub fn binary_dv(self, name: &str, value: &[u8]) -> Self {
+        self.field(BinaryDocValuesField::new(name, value))
+    }
+
+    pub fn sorted_dv(self, name: &str, value: &[u8]) -> Self {
+        self.field(SortedDocValuesField::new(name, value))
+    }
+
+    pub fn sorted_set_dv(self, name: &str, value: &[u8]) -> Self {
+        self.field(SortedSetDocValuesField::new(name, value))
+    }
+
+    pub fn build(self) -> Vec<Box<dyn Fieldable>> {
+        self.fields
+    }
+}
@@ -13,8 +13,12 @@
 
 use serde::Serialize;
 
-use crate::core::analysis::{BinaryTokenStream, StringTokenStream, TokenStream};
+use crate::core::analysis::{
+    BinaryTokenStream, PreTokenizedTokenStream, StringTokenStream, Token, TokenStream,
+};
+use crate::core::codec::vectors::VectorSimilarity;
 use crate::core::doc::{DocValuesType, IndexOptions};
+use crate::core::facet::FacetLabel;
 use crate::core::util::{ByteBlockPool, BytesRef, Numeric, VariantValue};
 
 use crate::error::Error::IllegalArgument;
@@ -28,6 +32,7 @@ pub struct Field {
     field_data: Option<VariantValue>,
     boost: f32,
     token_stream: Option<Box<dyn TokenStream>>,
+    norm: Option<i64>,
 }
 
 impl Field {
@@ -63,7 +68,31 @@ impl Field {
             field_data,
             boost: 1.0_f32,
             token_stream,
+            norm: None,
+        }
+    }
+
+    /// Creates an indexed field from tokens produced outside of rucene, see
+    /// `PreTokenizedTokenStream`. Which of the positions, offsets and payloads
+    /// of the tokens are indexed depends on the `IndexOptions` of the field.
+    pub fn new_pre_tokenized(
+        field_name: String,
+        field_type: FieldType,
+        tokens: Vec<Token>,
+    ) -> Result<Field> {
+        if field_type.index_options == IndexOptions::Null {
+            return Err(IllegalArgument(format!(
+                "pre-tokenized field '{}' must be indexed",
+                field_name
+            )));
         }
+        let token_stream = PreTokenizedTokenStream::new(tokens)?;
+        Ok(Field::new(
+            field_name,
+            field_type,
+            None,
+            Some(Box::new(token_stream)),
+        ))
     }
 
     pub fn new_bytes(field_name: String, bytes: Vec<u8>, field_type: FieldType) -> Self {
@@ -74,6 +103,7 @@ impl Field {
             field_type,
             token_stream: None,
             boost: 1.0,
+            norm: None,
         }
     }
 
@@ -84,6 +114,12 @@ impl Field {
     pub fn set_field_data(&mut self, data: Option<VariantValue>) {
         self.field_data = data;
     }
+
+    /// Indexes `norm` as the norm of this field for the document instead of
+    /// the one computed from the field length, see `Fieldable::norm`.
+    pub fn set_norm(&mut self, norm: i64) {
+        self.norm = Some(norm);
+    }
 }
 
 impl Fieldable for Field {
@@ -145,6 +181,10 @@ impl Fieldable for Field {
     fn numeric_value(&self) -> Option<Numeric> {
         self.field_data.as_ref().and_then(|f| f.get_numeric())
     }
+
+    fn norm(&self) -> Option<i64> {
+        self.norm
+    }
 }
 
 impl Clone for Field {
@@ -156,6 +196,7 @@ impl Clone for Field {
             field_data: self.field_data.clone(),
             boost: self.boost,
             token_stream: None,
+            norm: self.norm,
             // TODO, no used
         }
     }
@@ -173,6 +214,29 @@ pub trait Fieldable {
     fn binary_value(&self) -> Option<&[u8]>;
     fn string_value(&self) -> Option<&str>;
     fn numeric_value(&self) -> Option<Numeric>;
+
+    /// The label of a facet field, such fields must be turned into indexable
+    /// fields by `FacetsConfig::build` before the document is indexed.
+    fn facet_label(&self) -> Option<&FacetLabel> {
+        None
+    }
+
+    /// A custom norm of this indexed field for the document, replacing the
+    /// one computed by the similarity from the field length, e.g. to index an
+    /// application-specific quality signal. The field must not omit norms.
+    ///
+    /// `BM25Similarity` reads the lowest byte of the norm as an encoded
+    /// length, see `BM25Similarity::encode_norm_value`. If several values of
+    /// the field set a norm, the last one wins.
+    fn norm(&self) -> Option<i64> {
+        None
+    }
+
+    /// The vector of this field for the document, indexed when the field type
+    /// has a `vector_dimension`.
+    fn vector_value(&self) -> Option<&[f32]> {
+        None
+    }
 }
 
 impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
@@ -200,6 +264,15 @@ impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
     fn numeric_value(&self) -> Option<Numeric> {
         (**self).numeric_value()
     }
+    fn facet_label(&self) -> Option<&FacetLabel> {
+        (**self).facet_label()
+    }
+    fn norm(&self) -> Option<i64> {
+        (**self).norm()
+    }
+    fn vector_value(&self) -> Option<&[f32]> {
+        (**self).vector_value()
+    }
 }
 
 #[derive(Clone, PartialEq, Hash, Serialize, Debug)]
@@ -214,7 +287,15 @@ pub struct FieldType {
     pub index_options: IndexOptions,
     pub doc_values_type: DocValuesType,
     pub dimension_count: u32,
+    /// How many of the leading `dimension_count` dimensions are indexed, the
+    /// others are only stored alongside them in the points index
+    pub index_dimension_count: u32,
     pub dimension_num_bytes: u32,
+    /// Number of dimensions of the vector indexed for kNN search, 0 if the
+    /// field has no vector
+    pub vector_dimension: u32,
+    /// How two vectors of the field are compared
+    pub vector_similarity: VectorSimilarity,
 }
 
 impl Default for FieldType {
@@ -230,7 +311,10 @@ impl Default for FieldType {
             index_options: IndexOptions::Null,
             doc_values_type: DocValuesType::Null,
             dimension_count: 0,
+            index_dimension_count: 0,
             dimension_num_bytes: 0,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         }
     }
 }
@@ -266,7 +350,10 @@ impl FieldType {
             index_options,
             doc_values_type,
             dimension_count,
+            index_dimension_count: dimension_count,
             dimension_num_bytes,
+            vector_dimension: 0,
+            vector_similarity: VectorSimilarity::Euclidean,
         }
     }
 
@@ -336,6 +423,17 @@ impl FieldType {
     }
 
     pub fn set_dimensions(&mut self, dimension_count: u32, dimension_num_bytes: u32) -> Result<()> {
+        self.set_dimensions_with_index_count(dimension_count, dimension_count, dimension_num_bytes)
+    }
+
+    /// Sets the point dimensions of this field, only the first `index_dimension_count`
+    /// of them are indexed, the others are just stored in the leaves of the points index.
+    pub fn set_dimensions_with_index_count(
+        &mut self,
+        dimension_count: u32,
+        index_dimension_count: u32,
+        dimension_num_bytes: u32,
+    ) -> Result<()> {
         if dimension_count > POINT_MAX_DIMENSIONS {
             return Err(IllegalArgument(format!(
                 "dimension_count must be <={}",
@@ -362,7 +460,17 @@ impl FieldType {
             )));
         }
 
+        if index_dimension_count > dimension_count
+            || (index_dimension_count == 0) != (dimension_count == 0)
+        {
+            return Err(IllegalArgument(format!(
+                "index_dimension_count must be in 1..={}, got {}",
+                dimension_count, index_dimension_count
+            )));
+        }
+
         self.dimension_count = dimension_count;
+        self.index_dimension_count = index_dimension_count;
         self.dimension_num_bytes = dimension_num_bytes;
 
         Ok(())
@@ -390,7 +498,10 @@ pub const NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::Numeric,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -404,7 +515,10 @@ pub const SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::SortedNumeric,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const BINARY_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -418,7 +532,10 @@ pub const BINARY_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::Binary,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -432,7 +549,10 @@ pub const SORTED_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::Sorted,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const SORTED_SET_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
@@ -446,7 +566,10 @@ pub const SORTED_SET_DOC_VALUES_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::SortedSet,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
 
 pub const STORE_FIELD_TYPE: FieldType = FieldType {
@@ -460,5 +583,8 @@ pub const STORE_FIELD_TYPE: FieldType = FieldType {
     index_options: IndexOptions::Null,
     doc_values_type: DocValuesType::Null,
     dimension_count: 0,
+    index_dimension_count: 0,
     dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
 };
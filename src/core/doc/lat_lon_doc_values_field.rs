@@ -0,0 +1,105 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::TokenStream;
+use crate::core::doc::{Field, FieldType, Fieldable, SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE};
+use crate::core::search::sort_field::{LatLonDistanceSortField, SortField};
+use crate::core::util::{
+    check_latitude, check_longitude, decode_latitude, decode_longitude, encode_latitude,
+    encode_longitude, Numeric, VariantValue,
+};
+
+use crate::Result;
+
+/// Per-document geo-point doc values for sorting by distance.
+///
+/// The latitude and longitude are quantized to 32 bits each and packed into a
+/// single sorted numeric doc value, the latitude in the upper bits. A document
+/// may have several points, it then sorts by the closest one.
+pub struct LatLonDocValuesField {
+    field: Field,
+}
+
+impl LatLonDocValuesField {
+    pub fn new(name: &str, latitude: f64, longitude: f64) -> Result<LatLonDocValuesField> {
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        let encoded = (i64::from(encode_latitude(latitude)) << 32)
+            | i64::from(encode_longitude(longitude) as u32);
+        Ok(LatLonDocValuesField {
+            field: Field::new(
+                name.to_string(),
+                SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE,
+                Some(VariantValue::Long(encoded)),
+                None,
+            ),
+        })
+    }
+
+    fn encoded(&self) -> i64 {
+        self.field.field_data().unwrap().get_long().unwrap()
+    }
+
+    /// The indexed latitude, after quantization.
+    pub fn latitude(&self) -> f64 {
+        decode_latitude((self.encoded() >> 32) as i32)
+    }
+
+    /// The indexed longitude, after quantization.
+    pub fn longitude(&self) -> f64 {
+        decode_longitude(self.encoded() as i32)
+    }
+
+    /// Creates a `SortField` that sorts documents by their distance in meters
+    /// from the given point, closest first. The distances are returned as the
+    /// `Double` sort values of the hits.
+    pub fn new_distance_sort(field: &str, latitude: f64, longitude: f64) -> Result<SortField> {
+        Ok(SortField::Distance(LatLonDistanceSortField::new(
+            field, latitude, longitude,
+        )?))
+    }
+}
+
+impl Fieldable for LatLonDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field.numeric_value()
+    }
+}
@@ -0,0 +1,534 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::result;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, DeserializeSeed, Error as DeError, IntoDeserializer, MapAccess};
+use serde::de::{Deserializer, Visitor};
+use serde::{forward_to_deserialize_any, Serialize};
+use serde_json::Value;
+
+use crate::core::doc::{
+    Document, Fieldable, NumericDocValuesField, SortedDocValuesField, SortedNumericDocValuesField,
+    SortedSetDocValuesField, StoredField, StringField, TextField,
+};
+use crate::core::util::VariantValue;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// How the values of a mapped field are indexed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indexing {
+    /// The values are not indexed.
+    None,
+    /// Each value is indexed as a single token, see `StringField`.
+    Keyword,
+    /// The values are tokenized, see `TextField`.
+    Text,
+}
+
+/// Controls which fields a struct member is turned into by `DocumentMapping`.
+///
+/// The default mapping only stores the value.
+#[derive(Clone, Copy, Debug)]
+pub struct FieldMapping {
+    pub indexing: Indexing,
+    pub stored: bool,
+    pub doc_values: bool,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        FieldMapping {
+            indexing: Indexing::None,
+            stored: true,
+            doc_values: false,
+        }
+    }
+}
+
+impl FieldMapping {
+    /// Stored only, this is the mapping of fields that aren't explicitly mapped.
+    pub fn stored() -> FieldMapping {
+        FieldMapping::default()
+    }
+
+    /// Indexed as a single token and stored.
+    pub fn keyword() -> FieldMapping {
+        FieldMapping {
+            indexing: Indexing::Keyword,
+            ..Default::default()
+        }
+    }
+
+    /// Tokenized and stored.
+    pub fn text() -> FieldMapping {
+        FieldMapping {
+            indexing: Indexing::Text,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_stored(mut self, stored: bool) -> FieldMapping {
+        self.stored = stored;
+        self
+    }
+
+    /// Also writes the values as doc values: numbers (and booleans, as 0 or 1) as
+    /// numeric doc values, strings as sorted doc values, or their multi-valued
+    /// variants when the member is a sequence. Doubles are encoded with
+    /// `f64::to_bits`, like `SortFieldType::Double` expects.
+    pub fn with_doc_values(mut self, doc_values: bool) -> FieldMapping {
+        self.doc_values = doc_values;
+        self
+    }
+}
+
+/// Maps any `Serialize` struct (or map) to the fields of a document, and back
+/// from the stored fields with `from_document`.
+///
+/// Every member of the struct becomes one field, named after the member, that is
+/// indexed, stored and written as doc values according to its `FieldMapping`.
+/// Sequences of scalars become multi-valued fields, null values and empty
+/// sequences are skipped, and nested structs, maps or sequences of those are
+/// stored as JSON strings.
+///
+/// ```ignore
+/// let mapping = DocumentMapping::new()
+///     .field("id", FieldMapping::keyword())
+///     .field("title", FieldMapping::text())
+///     .field("price", FieldMapping::stored().with_doc_values(true));
+/// writer.add_document(mapping.to_fields(&book)?)?;
+/// let book: Book = from_document(&reader.document(doc_id, &[])?)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DocumentMapping {
+    fields: HashMap<String, FieldMapping>,
+}
+
+impl DocumentMapping {
+    pub fn new() -> DocumentMapping {
+        DocumentMapping::default()
+    }
+
+    pub fn field(mut self, name: &str, mapping: FieldMapping) -> DocumentMapping {
+        self.fields.insert(name.to_string(), mapping);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> FieldMapping {
+        self.fields.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn to_fields<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<Box<dyn Fieldable>>> {
+        let object = match serde_json::to_value(value)? {
+            Value::Object(object) => object,
+            v => {
+                return Err(IllegalArgument(format!(
+                    "only structs and maps can be mapped to a document, got: {}",
+                    v
+                )));
+            }
+        };
+
+        let mut fields = vec![];
+        for (name, value) in &object {
+            let mapping = self.get(name);
+            match value {
+                Value::Null => {}
+                Value::Array(values) if values.iter().all(is_scalar) => {
+                    for v in values {
+                        Self::add_value(name, v, mapping, true, &mut fields)?;
+                    }
+                }
+                Value::Array(_) | Value::Object(_) => {
+                    let json = Value::String(value.to_string());
+                    Self::add_value(name, &json, mapping, false, &mut fields)?;
+                }
+                _ => Self::add_value(name, value, mapping, false, &mut fields)?,
+            }
+        }
+        Ok(fields)
+    }
+
+    fn add_value(
+        name: &str,
+        value: &Value,
+        mapping: FieldMapping,
+        multi_valued: bool,
+        fields: &mut Vec<Box<dyn Fieldable>>,
+    ) -> Result<()> {
+        let (text, stored, numeric) = match value {
+            Value::String(s) => (s.clone(), VariantValue::from(s.as_str()), None),
+            Value::Bool(b) => (b.to_string(), VariantValue::Int(*b as i32), Some(*b as i64)),
+            Value::Number(n) => {
+                if let Some(v) = n.as_i64() {
+                    (v.to_string(), VariantValue::Long(v), Some(v))
+                } else if let Some(v) = n.as_u64() {
+                    return Err(IllegalArgument(format!(
+                        "value {} of field '{}' is out of the range of i64",
+                        v, name
+                    )));
+                } else {
+                    let v = n.as_f64().unwrap();
+                    (
+                        v.to_string(),
+                        VariantValue::Double(v),
+                        Some(v.to_bits() as i64),
+                    )
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        match mapping.indexing {
+            Indexing::None => {}
+            Indexing::Keyword => fields.push(Box::new(StringField::new(name, &text, false))),
+            Indexing::Text => fields.push(Box::new(TextField::new(name, &text, false))),
+        }
+        if mapping.doc_values {
+            let field: Box<dyn Fieldable> = match (numeric, multi_valued) {
+                (Some(v), false) => Box::new(NumericDocValuesField::new(name, v)),
+                (Some(v), true) => Box::new(SortedNumericDocValuesField::new(name, v)),
+                (None, false) => Box::new(SortedDocValuesField::new(name, text.as_bytes())),
+                (None, true) => Box::new(SortedSetDocValuesField::new(name, text.as_bytes())),
+            };
+            fields.push(field);
+        }
+        if mapping.stored {
+            fields.push(Box::new(StoredField::with_value(name, stored)));
+        }
+        Ok(())
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::Bool(_) | Value::Number(_) | Value::String(_))
+}
+
+/// Deserializes a value from the stored fields of a document, typically one
+/// written with `DocumentMapping::to_fields`.
+///
+/// Each distinct field name is a member of the deserialized map, fields with
+/// several values are sequences. Members that weren't stored are missing, so
+/// they must be `Option`s or have a `#[serde(default)]`.
+pub fn from_document<T: DeserializeOwned>(doc: &Document) -> Result<T> {
+    let mut fields: Vec<(&str, Vec<&VariantValue>)> = vec![];
+    for field in &doc.fields {
+        if let Some(value) = field.field.field_data() {
+            let name = field.field.name();
+            match fields.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, values)) => values.push(value),
+                None => fields.push((name, vec![value])),
+            }
+        }
+    }
+    Ok(T::deserialize(DocumentDeserializer { fields })?)
+}
+
+type DeResult<T> = result::Result<T, serde_json::Error>;
+
+fn parse_json(s: &str) -> DeResult<Value> {
+    serde_json::from_str(s)
+}
+
+struct DocumentDeserializer<'a> {
+    fields: Vec<(&'a str, Vec<&'a VariantValue>)>,
+}
+
+impl<'de, 'a> Deserializer<'de> for DocumentDeserializer<'a> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        visitor.visit_map(FieldsAccess {
+            fields: self.fields.into_iter(),
+            values: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct FieldsAccess<'a> {
+    fields: std::vec::IntoIter<(&'a str, Vec<&'a VariantValue>)>,
+    values: Option<Vec<&'a VariantValue>>,
+}
+
+impl<'de, 'a> MapAccess<'de> for FieldsAccess<'a> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> DeResult<Option<K::Value>> {
+        match self.fields.next() {
+            Some((name, values)) => {
+                self.values = Some(values);
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> DeResult<S::Value> {
+        let values = self.values.take().unwrap();
+        seed.deserialize(ValuesDeserializer(values))
+    }
+}
+
+/// All the values of one field.
+struct ValuesDeserializer<'a>(Vec<&'a VariantValue>);
+
+impl<'a> ValuesDeserializer<'a> {
+    fn single(self) -> DeResult<ValueDeserializer<'a>> {
+        if self.0.len() == 1 {
+            Ok(ValueDeserializer(self.0[0]))
+        } else {
+            Err(serde_json::Error::invalid_length(
+                self.0.len(),
+                &"a single value",
+            ))
+        }
+    }
+}
+
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+            self.single()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de, 'a> Deserializer<'de> for ValuesDeserializer<'a> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.0.len() {
+            0 => visitor.visit_unit(),
+            1 => ValueDeserializer(self.0[0]).deserialize_any(visitor),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        // sequences of non scalar values are stored as a single JSON array
+        if let [VariantValue::VString(s)] = self.0.as_slice() {
+            if let Ok(v @ Value::Array(_)) = parse_json(s) {
+                return v.deserialize_seq(visitor);
+            }
+        }
+        let mut seq = SeqDeserializer::new(self.0.into_iter().map(ValueDeserializer));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> DeResult<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_unit deserialize_map deserialize_identifier
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+/// A single stored value.
+struct ValueDeserializer<'a>(&'a VariantValue);
+
+impl<'de, 'a> IntoDeserializer<'de, serde_json::Error> for ValueDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'a> ValueDeserializer<'a> {
+    /// Nested structs, maps and enums with data are stored as JSON strings.
+    fn json(&self) -> Option<DeResult<Value>> {
+        match self.0 {
+            VariantValue::VString(s) => Some(parse_json(s)),
+            _ => None,
+        }
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.0 {
+            VariantValue::Bool(v) => visitor.visit_bool(*v),
+            VariantValue::Char(v) => visitor.visit_char(*v),
+            VariantValue::Short(v) => visitor.visit_i16(*v),
+            VariantValue::Int(v) => visitor.visit_i32(*v),
+            VariantValue::Long(v) => visitor.visit_i64(*v),
+            VariantValue::Float(v) => visitor.visit_f32(*v),
+            VariantValue::Double(v) => visitor.visit_f64(*v),
+            VariantValue::VString(v) => visitor.visit_str(v),
+            VariantValue::Binary(v) => visitor.visit_bytes(v),
+            VariantValue::Date(v) => visitor.visit_i64(*v),
+            VariantValue::Vec(v) => {
+                let mut seq = SeqDeserializer::new(v.iter().map(ValueDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            VariantValue::Map(v) => {
+                let mut map =
+                    MapDeserializer::new(v.iter().map(|(k, v)| (k.as_str(), ValueDeserializer(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        // booleans are stored as ints
+        match self.0 {
+            VariantValue::Int(v) => visitor.visit_bool(*v != 0),
+            VariantValue::Long(v) => visitor.visit_bool(*v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.0 {
+            VariantValue::Binary(v) => {
+                let mut seq = SeqDeserializer::new(v.iter().cloned());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            _ => match self.json() {
+                Some(json) => json?.deserialize_seq(visitor),
+                None => self.deserialize_any(visitor),
+            },
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> DeResult<V::Value> {
+        match self.json() {
+            Some(json) => json?.deserialize_map(visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        match self.json() {
+            Some(json) => json?.deserialize_struct(name, fields, visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        match self.0 {
+            // unit variants are stored as plain strings
+            VariantValue::VString(s) => match parse_json(s) {
+                Ok(json @ Value::Object(_)) => json.deserialize_enum(name, variants, visitor),
+                _ => Value::String(s.clone()).deserialize_enum(name, variants, visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DeResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
@@ -15,7 +15,8 @@ mod field;
 
 pub use self::field::{
     Field, FieldType, Fieldable, BINARY_DOC_VALUES_FIELD_TYPE, NUMERIC_DOC_VALUES_FIELD_TYPE,
-    SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE, SORTED_SET_DOC_VALUES_FIELD_TYPE, STORE_FIELD_TYPE,
+    SORTED_DOC_VALUES_FIELD_TYPE, SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE,
+    SORTED_SET_DOC_VALUES_FIELD_TYPE, STORE_FIELD_TYPE,
 };
 
 mod document;
@@ -24,10 +25,49 @@ pub use self::document::{
     Document, DocumentStoredFieldVisitor, Status, StoredField, StoredFieldVisitor,
 };
 
+mod document_builder;
+
+pub use self::document_builder::{doc, DocumentBuilder};
+
+mod mapping;
+
+pub use self::mapping::{from_document, DocumentMapping, FieldMapping, Indexing};
+
 mod index_options;
 
 pub use self::index_options::IndexOptions;
 
+mod text_field;
+
+pub use self::text_field::{StringField, TextField, STRING_FIELD_TYPE, TEXT_FIELD_TYPE};
+
+mod date_tools;
+
+pub use self::date_tools::{DateTools, Resolution};
+
+mod date_field;
+
+pub use self::date_field::{DateDocValuesField, DatePoint};
+
+mod lat_lon_doc_values_field;
+
+pub use self::lat_lon_doc_values_field::LatLonDocValuesField;
+
+mod range_field;
+
+pub use self::range_field::{DateRange, DoubleRange, LongRange, RANGE_MAX_DIMENSIONS};
+
+mod vector_field;
+
+pub use self::vector_field::VectorField;
+
+mod suggest_field;
+
+pub use self::suggest_field::{SuggestField, SUGGEST_FIELD_TYPE};
+
 mod doc_values;
 
-pub use self::doc_values::{DocValuesType, NumericDocValuesField};
+pub use self::doc_values::{
+    BinaryDocValuesField, DocValuesType, NumericDocValuesField, SortedDocValuesField,
+    SortedNumericDocValuesField, SortedSetDocValuesField,
+};
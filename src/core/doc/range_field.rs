@@ -0,0 +1,358 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::time::SystemTime;
+
+use crate::core::analysis::TokenStream;
+use crate::core::codec::vectors::VectorSimilarity;
+use crate::core::codec::Codec;
+use crate::core::doc::{DateTools, Field, FieldType, Fieldable};
+use crate::core::search::query::{
+    DoublePoint, LongPoint, PointValueType, Query, RangeFieldQuery, RangeRelation,
+};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Maximum number of dimensions of a range field, each dimension is indexed as
+/// two point dimensions.
+pub const RANGE_MAX_DIMENSIONS: usize = 4;
+
+fn range_field_type(num_dims: usize, bytes_per_dim: u32) -> FieldType {
+    FieldType {
+        tokenized: false,
+        dimension_count: 2 * num_dims as u32,
+        index_dimension_count: 2 * num_dims as u32,
+        dimension_num_bytes: bytes_per_dim,
+        vector_dimension: 0,
+        vector_similarity: VectorSimilarity::Euclidean,
+        ..Default::default()
+    }
+}
+
+/// Packs all the mins followed by all the maxs, checking each `min <= max`.
+fn pack_range<T: PartialOrd + Copy>(
+    min: &[T],
+    max: &[T],
+    bytes_per_dim: usize,
+    encode: fn(T, &mut [u8]),
+) -> Result<Vec<u8>> {
+    if min.is_empty() || min.len() > RANGE_MAX_DIMENSIONS {
+        return Err(IllegalArgument(format!(
+            "range fields must have 1 to {} dimensions, got {}",
+            RANGE_MAX_DIMENSIONS,
+            min.len()
+        )));
+    }
+    if min.len() != max.len() {
+        return Err(IllegalArgument(format!(
+            "min has {} dimensions but max has {}",
+            min.len(),
+            max.len()
+        )));
+    }
+    let num_dims = min.len();
+    let mut packed = vec![0u8; 2 * num_dims * bytes_per_dim];
+    for dim in 0..num_dims {
+        // also rejects NaN bounds
+        if !matches!(
+            min[dim].partial_cmp(&max[dim]),
+            Some(Ordering::Less) | Some(Ordering::Equal)
+        ) {
+            return Err(IllegalArgument(format!(
+                "min value of dimension {} is greater than its max value",
+                dim
+            )));
+        }
+        let offset = dim * bytes_per_dim;
+        encode(min[dim], &mut packed[offset..offset + bytes_per_dim]);
+        let offset = (num_dims + dim) * bytes_per_dim;
+        encode(max[dim], &mut packed[offset..offset + bytes_per_dim]);
+    }
+    Ok(packed)
+}
+
+/// An indexed `i64` range of up to 4 dimensions, e.g. `[min, max]` for a 1D range
+/// or a box for the 2D case.
+///
+/// Multiple values for the same field in one document are allowed, use the query
+/// factory methods to find the documents whose ranges intersect, contain or are
+/// within a query range.
+pub struct LongRange {
+    field: Field,
+}
+
+impl LongRange {
+    pub fn new(name: &str, min: &[i64], max: &[i64]) -> Result<LongRange> {
+        let packed = pack_range(min, max, 8, LongPoint::encode_dimension)?;
+        Ok(LongRange {
+            field: Field::new_bytes(name.to_string(), packed, range_field_type(min.len(), 8)),
+        })
+    }
+
+    /// The min value of the given dimension.
+    pub fn min(&self, dim: usize) -> i64 {
+        LongPoint::decode_dimension(&self.packed()[dim * 8..])
+    }
+
+    /// The max value of the given dimension.
+    pub fn max(&self, dim: usize) -> i64 {
+        let num_dims = self.packed().len() / 16;
+        LongPoint::decode_dimension(&self.packed()[(num_dims + dim) * 8..])
+    }
+
+    fn packed(&self) -> &[u8] {
+        self.field.binary_value().unwrap()
+    }
+
+    /// Create a query matching the documents whose range intersects `[min, max]`.
+    pub fn new_intersects_query<C: Codec>(
+        field: String,
+        min: &[i64],
+        max: &[i64],
+    ) -> Result<Box<dyn Query<C>>> {
+        LongRange::new_relation_query(field, min, max, RangeRelation::Intersects)
+    }
+
+    /// Create a query matching the documents whose range contains `[min, max]`.
+    pub fn new_contains_query<C: Codec>(
+        field: String,
+        min: &[i64],
+        max: &[i64],
+    ) -> Result<Box<dyn Query<C>>> {
+        LongRange::new_relation_query(field, min, max, RangeRelation::Contains)
+    }
+
+    /// Create a query matching the documents whose range is within `[min, max]`.
+    pub fn new_within_query<C: Codec>(
+        field: String,
+        min: &[i64],
+        max: &[i64],
+    ) -> Result<Box<dyn Query<C>>> {
+        LongRange::new_relation_query(field, min, max, RangeRelation::Within)
+    }
+
+    fn new_relation_query<C: Codec>(
+        field: String,
+        min: &[i64],
+        max: &[i64],
+        relation: RangeRelation,
+    ) -> Result<Box<dyn Query<C>>> {
+        let packed = pack_range(min, max, 8, LongPoint::encode_dimension)?;
+        Ok(Box::new(RangeFieldQuery::new(
+            field,
+            packed,
+            min.len(),
+            relation,
+            PointValueType::Long,
+        )?))
+    }
+}
+
+impl Fieldable for LongRange {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+/// An indexed `f64` range of up to 4 dimensions.
+///
+/// See `LongRange`, bounds must not be NaN.
+pub struct DoubleRange {
+    field: Field,
+}
+
+impl DoubleRange {
+    pub fn new(name: &str, min: &[f64], max: &[f64]) -> Result<DoubleRange> {
+        let packed = pack_range(min, max, 8, DoublePoint::encode_dimension)?;
+        Ok(DoubleRange {
+            field: Field::new_bytes(name.to_string(), packed, range_field_type(min.len(), 8)),
+        })
+    }
+
+    /// The min value of the given dimension.
+    pub fn min(&self, dim: usize) -> f64 {
+        DoublePoint::decode_dimension(&self.packed()[dim * 8..])
+    }
+
+    /// The max value of the given dimension.
+    pub fn max(&self, dim: usize) -> f64 {
+        let num_dims = self.packed().len() / 16;
+        DoublePoint::decode_dimension(&self.packed()[(num_dims + dim) * 8..])
+    }
+
+    fn packed(&self) -> &[u8] {
+        self.field.binary_value().unwrap()
+    }
+
+    /// Create a query matching the documents whose range intersects `[min, max]`.
+    pub fn new_intersects_query<C: Codec>(
+        field: String,
+        min: &[f64],
+        max: &[f64],
+    ) -> Result<Box<dyn Query<C>>> {
+        DoubleRange::new_relation_query(field, min, max, RangeRelation::Intersects)
+    }
+
+    /// Create a query matching the documents whose range contains `[min, max]`.
+    pub fn new_contains_query<C: Codec>(
+        field: String,
+        min: &[f64],
+        max: &[f64],
+    ) -> Result<Box<dyn Query<C>>> {
+        DoubleRange::new_relation_query(field, min, max, RangeRelation::Contains)
+    }
+
+    /// Create a query matching the documents whose range is within `[min, max]`.
+    pub fn new_within_query<C: Codec>(
+        field: String,
+        min: &[f64],
+        max: &[f64],
+    ) -> Result<Box<dyn Query<C>>> {
+        DoubleRange::new_relation_query(field, min, max, RangeRelation::Within)
+    }
+
+    fn new_relation_query<C: Codec>(
+        field: String,
+        min: &[f64],
+        max: &[f64],
+        relation: RangeRelation,
+    ) -> Result<Box<dyn Query<C>>> {
+        let packed = pack_range(min, max, 8, DoublePoint::encode_dimension)?;
+        Ok(Box::new(RangeFieldQuery::new(
+            field,
+            packed,
+            min.len(),
+            relation,
+            PointValueType::Double,
+        )?))
+    }
+}
+
+impl Fieldable for DoubleRange {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+/// A time window `[start, end]` indexed as a 1D `LongRange` of milliseconds
+/// since the unix epoch, e.g. the availability window of a booking.
+pub struct DateRange;
+
+impl DateRange {
+    pub fn new_field(name: &str, start: SystemTime, end: SystemTime) -> Result<LongRange> {
+        LongRange::new(
+            name,
+            &[DateRange::to_millis(start)],
+            &[DateRange::to_millis(end)],
+        )
+    }
+
+    /// Converts a time to the milliseconds since the unix epoch it is indexed as.
+    pub fn to_millis(time: SystemTime) -> i64 {
+        DateTools::to_millis(time)
+    }
+
+    /// Converts milliseconds since the unix epoch back to a time.
+    pub fn from_millis(millis: i64) -> SystemTime {
+        DateTools::from_millis(millis)
+    }
+
+    /// Create a query matching the documents whose window overlaps `[start, end]`.
+    pub fn new_intersects_query<C: Codec>(
+        field: String,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Box<dyn Query<C>>> {
+        let (start, end) = (DateRange::to_millis(start), DateRange::to_millis(end));
+        LongRange::new_intersects_query(field, &[start], &[end])
+    }
+
+    /// Create a query matching the documents whose window covers all of `[start, end]`.
+    pub fn new_contains_query<C: Codec>(
+        field: String,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Box<dyn Query<C>>> {
+        let (start, end) = (DateRange::to_millis(start), DateRange::to_millis(end));
+        LongRange::new_contains_query(field, &[start], &[end])
+    }
+
+    /// Create a query matching the documents whose window is within `[start, end]`.
+    pub fn new_within_query<C: Codec>(
+        field: String,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Box<dyn Query<C>>> {
+        let (start, end) = (DateRange::to_millis(start), DateRange::to_millis(end));
+        LongRange::new_within_query(field, &[start], &[end])
+    }
+}
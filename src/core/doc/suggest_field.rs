@@ -0,0 +1,126 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Token, TokenStream};
+use crate::core::codec::postings::{completion_payload, completion_term};
+use crate::core::doc::{Field, FieldType, Fieldable, TEXT_FIELD_TYPE};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Indexed with positions and payloads, omits norms.
+pub const SUGGEST_FIELD_TYPE: FieldType = FieldType {
+    omit_norms: true,
+    ..TEXT_FIELD_TYPE
+};
+
+/// A suggestion of a document, looked up with `SuggestIndexSearcher`.
+///
+/// The field must use the `CompletionPostingsFormat`, which keeps the
+/// suggestions of each segment in an FST, so that they follow the documents
+/// through commits, deletes and merges. The suggestion is indexed once per
+/// context, or without context when it has none.
+pub struct SuggestField {
+    field: Field,
+}
+
+impl SuggestField {
+    /// Creates a suggestion of `input` without context.
+    pub fn new(name: &str, input: &str, weight: u32) -> Result<SuggestField> {
+        Self::with_contexts(name, input, &[], weight)
+    }
+
+    /// Creates a suggestion of `input` in each of `contexts`.
+    pub fn with_contexts(
+        name: &str,
+        input: &str,
+        contexts: &[&str],
+        weight: u32,
+    ) -> Result<SuggestField> {
+        if input.is_empty() {
+            return Err(IllegalArgument(format!(
+                "suggest field '{}' has an empty input",
+                name
+            )));
+        }
+        let contexts = if contexts.is_empty() { &[""] } else { contexts };
+        let mut tokens = Vec::with_capacity(contexts.len());
+        for context in contexts {
+            let term = completion_term(context.as_bytes(), input.as_bytes())?;
+            let mut token = Token::with_term(&term, 1, 0, 0);
+            token.payload = completion_payload(weight);
+            tokens.push(token);
+        }
+        Ok(SuggestField {
+            field: Field::new_pre_tokenized(name.to_string(), SUGGEST_FIELD_TYPE, tokens)?,
+        })
+    }
+}
+
+impl Fieldable for SuggestField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_field_tokens() {
+        let mut field = SuggestField::with_contexts("suggest", "rust", &["a", "b"], 7).unwrap();
+        assert!(field.field_type().omit_norms);
+        let mut stream = field.token_stream().unwrap();
+        stream.reset().unwrap();
+        let mut terms = vec![];
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            assert_eq!(token.payload, completion_payload(7));
+            terms.push(token.term.clone());
+        }
+        assert_eq!(terms, vec![b"a\x1drust".to_vec(), b"b\x1drust".to_vec()]);
+
+        assert!(SuggestField::new("suggest", "", 1).is_err());
+        assert!(SuggestField::with_contexts("suggest", "rust", &["a\x1d"], 1).is_err());
+    }
+}
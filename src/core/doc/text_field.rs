@@ -0,0 +1,203 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Cursor;
+
+use crate::core::analysis::{Token, TokenStream, WhitespaceTokenizer};
+use crate::core::codec::vectors::VectorSimilarity;
+use crate::core::doc::{DocValuesType, Field, FieldType, Fieldable, IndexOptions};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::Result;
+
+/// Indexed, not tokenized, omits norms, indexes docs only.
+pub const STRING_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Docs,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    index_dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// Indexed, tokenized, with frequencies and positions.
+pub const TEXT_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: true,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: false,
+    index_options: IndexOptions::DocsAndFreqsAndPositions,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    index_dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+/// A field that is indexed but not tokenized: the entire value is indexed as
+/// a single token. For example this might be used for a 'country' field or
+/// an 'id' field.
+pub struct StringField {
+    field: Field,
+}
+
+impl StringField {
+    pub fn new(name: &str, value: &str, stored: bool) -> StringField {
+        let mut field_type = STRING_FIELD_TYPE;
+        field_type.stored = stored;
+        StringField {
+            field: Field::new(name.to_string(), field_type, Some(value.into()), None),
+        }
+    }
+
+    /// Creates a field whose single indexed token is the given bytes.
+    pub fn new_bytes(name: &str, value: Vec<u8>, stored: bool) -> StringField {
+        let mut field_type = STRING_FIELD_TYPE;
+        field_type.stored = stored;
+        StringField {
+            field: Field::new_bytes(name.to_string(), value, field_type),
+        }
+    }
+}
+
+impl Fieldable for StringField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        self.field.binary_value()
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+
+    fn norm(&self) -> Option<i64> {
+        self.field.norm()
+    }
+}
+
+/// A field that is indexed and tokenized, without term vectors. For example
+/// this would be used on a 'body' field, that contains the bulk of a
+/// document's text.
+///
+/// String values are split on whitespace, use `with_token_stream` to index
+/// the output of another tokenizer.
+pub struct TextField {
+    field: Field,
+}
+
+impl TextField {
+    pub fn new(name: &str, value: &str, stored: bool) -> TextField {
+        let mut field_type = TEXT_FIELD_TYPE;
+        field_type.stored = stored;
+        TextField {
+            field: Field::new(name.to_string(), field_type, Some(value.into()), None),
+        }
+    }
+
+    /// Creates an un-stored field whose tokens come from `token_stream`.
+    pub fn with_token_stream(name: &str, token_stream: Box<dyn TokenStream>) -> TextField {
+        TextField {
+            field: Field::new(name.to_string(), TEXT_FIELD_TYPE, None, Some(token_stream)),
+        }
+    }
+
+    /// Creates an un-stored field from already analyzed tokens, e.g. the
+    /// output of an external NLP pipeline.
+    pub fn from_tokens(name: &str, tokens: Vec<Token>) -> Result<TextField> {
+        Ok(TextField {
+            field: Field::new_pre_tokenized(name.to_string(), TEXT_FIELD_TYPE, tokens)?,
+        })
+    }
+
+    /// Indexes a custom norm for this field, see `Fieldable::norm`.
+    pub fn set_norm(&mut self, norm: i64) {
+        self.field.set_norm(norm);
+    }
+}
+
+impl Fieldable for TextField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        if let Some(text) = self.field.string_value() {
+            let reader = Cursor::new(text.to_string());
+            return Ok(Box::new(WhitespaceTokenizer::new(Box::new(reader))));
+        }
+        self.field.token_stream()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        self.field.string_value()
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+
+    fn norm(&self) -> Option<i64> {
+        self.field.norm()
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::TokenStream;
+use crate::core::codec::vectors::{VectorSimilarity, MAX_VECTOR_DIMENSION};
+use crate::core::doc::{FieldType, Fieldable};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// A per-document vector of floats, indexed for kNN search by the codec's
+/// `VectorsFormat`.
+///
+/// All the vectors of a field have the same dimension and similarity, and a
+/// document has at most one vector per field.
+pub struct VectorField {
+    name: String,
+    field_type: FieldType,
+    vector: Vec<f32>,
+}
+
+impl VectorField {
+    pub fn new(name: &str, vector: Vec<f32>, similarity: VectorSimilarity) -> Result<VectorField> {
+        if vector.is_empty() || vector.len() > MAX_VECTOR_DIMENSION as usize {
+            return Err(IllegalArgument(format!(
+                "vector dimension must be in 1..={}, got {}",
+                MAX_VECTOR_DIMENSION,
+                vector.len()
+            )));
+        }
+        let field_type = FieldType {
+            tokenized: false,
+            vector_dimension: vector.len() as u32,
+            vector_similarity: similarity,
+            ..FieldType::default()
+        };
+        Ok(VectorField {
+            name: name.to_string(),
+            field_type,
+            vector,
+        })
+    }
+}
+
+impl Fieldable for VectorField {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn field_type(&self) -> &FieldType {
+        &self.field_type
+    }
+
+    fn boost(&self) -> f32 {
+        1.0
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        None
+    }
+
+    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        None
+    }
+
+    fn vector_value(&self) -> Option<&[f32]> {
+        Some(&self.vector)
+    }
+}
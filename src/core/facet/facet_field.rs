@@ -0,0 +1,173 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::TokenStream;
+use crate::core::codec::vectors::VectorSimilarity;
+use crate::core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions};
+use crate::core::util::{Numeric, VariantValue};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// The dimension and path of a facet field, e.g. dimension `Author` with path
+/// `["Lisa"]`, or dimension `Publish Date` with path `["2010", "10", "15"]`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FacetLabel {
+    pub dim: String,
+    pub path: Vec<String>,
+    /// Set for `SortedSetDocValuesFacetField`, whose dimensions can't be
+    /// hierarchical.
+    pub flat: bool,
+}
+
+impl FacetLabel {
+    fn new(dim: &str, path: &[&str], flat: bool) -> Result<FacetLabel> {
+        if dim.is_empty() {
+            return Err(IllegalArgument("dim must be non-empty".into()));
+        }
+        if path.is_empty() {
+            return Err(IllegalArgument(
+                "path must have at least one element".into(),
+            ));
+        }
+        if path.iter().any(|p| p.is_empty()) {
+            return Err(IllegalArgument(
+                "each path component must have length > 0 (got: \"\")".into(),
+            ));
+        }
+        Ok(FacetLabel {
+            dim: dim.to_string(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+            flat,
+        })
+    }
+}
+
+// facet fields aren't indexed as is, they're only placeholders for
+// `FacetsConfig::build`
+const FACET_FIELD_TYPE: FieldType = FieldType {
+    stored: false,
+    tokenized: false,
+    store_term_vectors: false,
+    store_term_vector_offsets: false,
+    store_term_vector_positions: false,
+    store_term_vector_payloads: false,
+    omit_norms: true,
+    index_options: IndexOptions::Null,
+    doc_values_type: DocValuesType::Null,
+    dimension_count: 0,
+    index_dimension_count: 0,
+    dimension_num_bytes: 0,
+    vector_dimension: 0,
+    vector_similarity: VectorSimilarity::Euclidean,
+};
+
+fn facet_token_stream() -> Result<Box<dyn TokenStream>> {
+    Err(IllegalArgument(
+        "facet fields must be turned into indexable fields by FacetsConfig::build".into(),
+    ))
+}
+
+/// Add an instance of this to your document for every facet label.
+///
+/// NOTE: you must call `FacetsConfig::build` before you add the document to
+/// `IndexWriter`.
+pub struct FacetField {
+    label: FacetLabel,
+}
+
+impl FacetField {
+    pub fn new(dim: &str, path: &[&str]) -> Result<FacetField> {
+        Ok(FacetField {
+            label: FacetLabel::new(dim, path, false)?,
+        })
+    }
+
+    pub fn dim(&self) -> &str {
+        &self.label.dim
+    }
+
+    pub fn path(&self) -> &[String] {
+        &self.label.path
+    }
+}
+
+/// Add an instance of this to your document for every facet label to be
+/// indexed via `SortedSetDocValues`, dimensions are always flat.
+///
+/// NOTE: you must call `FacetsConfig::build` before you add the document to
+/// `IndexWriter`.
+pub struct SortedSetDocValuesFacetField {
+    label: FacetLabel,
+}
+
+impl SortedSetDocValuesFacetField {
+    pub fn new(dim: &str, label: &str) -> Result<SortedSetDocValuesFacetField> {
+        Ok(SortedSetDocValuesFacetField {
+            label: FacetLabel::new(dim, &[label], true)?,
+        })
+    }
+
+    pub fn dim(&self) -> &str {
+        &self.label.dim
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label.path[0]
+    }
+}
+
+macro_rules! impl_facet_fieldable {
+    ($t:ty) => {
+        impl Fieldable for $t {
+            fn name(&self) -> &str {
+                &self.label.dim
+            }
+
+            fn field_type(&self) -> &FieldType {
+                &FACET_FIELD_TYPE
+            }
+
+            fn boost(&self) -> f32 {
+                1.0
+            }
+
+            fn field_data(&self) -> Option<&VariantValue> {
+                None
+            }
+
+            fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+                facet_token_stream()
+            }
+
+            fn binary_value(&self) -> Option<&[u8]> {
+                None
+            }
+
+            fn string_value(&self) -> Option<&str> {
+                None
+            }
+
+            fn numeric_value(&self) -> Option<Numeric> {
+                None
+            }
+
+            fn facet_label(&self) -> Option<&FacetLabel> {
+                Some(&self.label)
+            }
+        }
+    };
+}
+
+impl_facet_fieldable!(FacetField);
+impl_facet_fieldable!(SortedSetDocValuesFacetField);
@@ -0,0 +1,216 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::core::doc::{Fieldable, SortedSetDocValuesField, StringField};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Default index field name for the facet dimensions.
+pub const DEFAULT_INDEX_FIELD_NAME: &str = "$facets";
+
+/// Delimiter between the components of an indexed facet path.
+pub const DELIM_CHAR: char = '\u{1F}';
+
+/// Escapes `DELIM_CHAR` and itself in the components of an indexed facet path.
+pub const ESCAPE_CHAR: char = '\u{1E}';
+
+/// Holds the configuration for one dimension.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DimConfig {
+    /// True if this dimension is hierarchical.
+    pub hierarchical: bool,
+    /// True if this dimension is multi-valued.
+    pub multi_valued: bool,
+    /// True if the count/aggregate for the entire dimension is required, which
+    /// is unusual (default is false).
+    pub require_dim_count: bool,
+    /// Actual field name the dimension is indexed into.
+    pub index_field_name: String,
+}
+
+impl Default for DimConfig {
+    fn default() -> Self {
+        DimConfig {
+            hierarchical: false,
+            multi_valued: false,
+            require_dim_count: false,
+            index_field_name: DEFAULT_INDEX_FIELD_NAME.to_string(),
+        }
+    }
+}
+
+/// Records per-dimension configuration, and translates the `FacetField` and
+/// `SortedSetDocValuesFacetField` of a document into the fields that are
+/// actually indexed.
+///
+/// Each facet label is indexed into its dimension's index field as:
+/// - sorted set doc values of the full path `dim/path...`, to count facets. For
+///   `FacetField`, the ancestor paths of hierarchical dimensions and, when
+///   `require_dim_count` is set, the dimension alone are added as well.
+/// - drill-down terms for the dimension and every prefix of the path, so that
+///   results can be filtered on a facet value with a `TermQuery`, see
+///   `FacetsConfig::path_to_string`.
+#[derive(Clone, Debug, Default)]
+pub struct FacetsConfig {
+    field_types: HashMap<String, DimConfig>,
+    default_dim_config: DimConfig,
+}
+
+impl FacetsConfig {
+    pub fn new() -> FacetsConfig {
+        FacetsConfig::default()
+    }
+
+    /// Get the current configuration for a dimension.
+    pub fn get_dim_config(&self, dim: &str) -> &DimConfig {
+        self.field_types
+            .get(dim)
+            .unwrap_or(&self.default_dim_config)
+    }
+
+    /// Pass true if this dimension is hierarchical (has depth > 1 paths).
+    pub fn set_hierarchical(&mut self, dim: &str, hierarchical: bool) {
+        self.dim_config_mut(dim).hierarchical = hierarchical;
+    }
+
+    /// Pass true if this dimension may have more than one value per document.
+    pub fn set_multi_valued(&mut self, dim: &str, multi_valued: bool) {
+        self.dim_config_mut(dim).multi_valued = multi_valued;
+    }
+
+    /// Pass true if at search time you require accurate counts of the dimension,
+    /// i.e. how many hits have at least one value for this dimension.
+    pub fn set_require_dim_count(&mut self, dim: &str, require_dim_count: bool) {
+        self.dim_config_mut(dim).require_dim_count = require_dim_count;
+    }
+
+    /// Specify which index field name should hold the values for this dimension.
+    pub fn set_index_field_name(&mut self, dim: &str, index_field_name: &str) {
+        self.dim_config_mut(dim).index_field_name = index_field_name.to_string();
+    }
+
+    fn dim_config_mut(&mut self, dim: &str) -> &mut DimConfig {
+        let default = &self.default_dim_config;
+        self.field_types
+            .entry(dim.to_string())
+            .or_insert_with(|| default.clone())
+    }
+
+    /// Translates the facet fields of a document into the fields that are
+    /// indexed, other fields are returned unchanged.
+    pub fn build(&self, doc: Vec<Box<dyn Fieldable>>) -> Result<Vec<Box<dyn Fieldable>>> {
+        let mut seen_dims = HashSet::new();
+        let mut fields: Vec<Box<dyn Fieldable>> = Vec::with_capacity(doc.len());
+        for field in doc {
+            let label = match field.facet_label() {
+                Some(label) => label.clone(),
+                None => {
+                    fields.push(field);
+                    continue;
+                }
+            };
+
+            let dim_config = self.get_dim_config(&label.dim);
+            if !dim_config.multi_valued && !seen_dims.insert(label.dim.clone()) {
+                return Err(IllegalArgument(format!(
+                    "dimension \"{}\" is not multiValued, but it appears more than once in this \
+                     document",
+                    label.dim
+                )));
+            }
+            if label.flat && dim_config.hierarchical {
+                return Err(IllegalArgument(format!(
+                    "dimension \"{}\" is hierarchical but SortedSetDocValuesFacetField only \
+                     supports flat dimensions",
+                    label.dim
+                )));
+            }
+            if !dim_config.hierarchical && label.path.len() > 1 {
+                return Err(IllegalArgument(format!(
+                    "dimension \"{}\" is not hierarchical but has {} components",
+                    label.dim,
+                    label.path.len()
+                )));
+            }
+
+            let index_field_name = dim_config.index_field_name.as_str();
+            let mut components = Vec::with_capacity(label.path.len() + 1);
+            components.push(label.dim.as_str());
+            components.extend(label.path.iter().map(|p| p.as_str()));
+
+            // for counting
+            let full_path = FacetsConfig::path_to_string(&components);
+            fields.push(Box::new(SortedSetDocValuesField::new(
+                index_field_name,
+                full_path.as_bytes(),
+            )));
+            if !label.flat {
+                let min_len = if dim_config.require_dim_count { 1 } else { 2 };
+                for len in min_len..components.len() {
+                    let path = FacetsConfig::path_to_string(&components[..len]);
+                    fields.push(Box::new(SortedSetDocValuesField::new(
+                        index_field_name,
+                        path.as_bytes(),
+                    )));
+                }
+            }
+
+            // for drill-down
+            for len in 1..=components.len() {
+                let path = FacetsConfig::path_to_string(&components[..len]);
+                fields.push(Box::new(StringField::new(index_field_name, &path, false)));
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Turns a dimension followed by a path into the encoded string it is
+    /// indexed as.
+    pub fn path_to_string(components: &[&str]) -> String {
+        let mut s = String::new();
+        for (i, component) in components.iter().enumerate() {
+            if i > 0 {
+                s.push(DELIM_CHAR);
+            }
+            for c in component.chars() {
+                if c == DELIM_CHAR || c == ESCAPE_CHAR {
+                    s.push(ESCAPE_CHAR);
+                }
+                s.push(c);
+            }
+        }
+        s
+    }
+
+    /// Turns an encoded string back into its components, the inverse of
+    /// `path_to_string`.
+    pub fn string_to_path(s: &str) -> Vec<String> {
+        let mut components = vec![];
+        let mut current = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                ESCAPE_CHAR => current.extend(chars.next()),
+                DELIM_CHAR => components.push(std::mem::take(&mut current)),
+                _ => current.push(c),
+            }
+        }
+        if !s.is_empty() {
+            components.push(current);
+        }
+        components
+    }
+}
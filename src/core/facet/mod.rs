@@ -0,0 +1,22 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod facet_field;
+
+pub use self::facet_field::{FacetField, FacetLabel, SortedSetDocValuesFacetField};
+
+mod facets_config;
+
+pub use self::facets_config::{
+    DimConfig, FacetsConfig, DEFAULT_INDEX_FIELD_NAME, DELIM_CHAR, ESCAPE_CHAR,
+};
@@ -0,0 +1,184 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::highlight::BoundaryScanner;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The text units a `BreakIteratorBoundaryScanner` snaps fragments to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BreakType {
+    /// Grapheme clusters, so no user-perceived character is split.
+    Character,
+    /// Words, as defined by Unicode word boundaries.
+    Word,
+    /// Sentences, as defined by Unicode sentence boundaries.
+    Sentence,
+}
+
+// Abbreviations, lower cased and without the trailing period, that don't end
+// a sentence in the language.
+fn abbreviations(language: &str) -> &'static [&'static str] {
+    match language {
+        "en" => &[
+            "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "inc",
+            "ltd", "co", "no", "fig",
+        ],
+        "de" => &[
+            "dr", "prof", "hr", "fr", "nr", "str", "bzw", "ca", "usw", "z.b", "d.h", "vgl", "evtl",
+            "ggf",
+        ],
+        "fr" => &[
+            "m", "mme", "mlle", "dr", "pr", "etc", "cf", "p.ex", "av", "bd",
+        ],
+        "es" => &["sr", "sra", "srta", "dr", "dra", "ud", "uds", "etc", "p.ej"],
+        _ => &[],
+    }
+}
+
+/// A `BoundaryScanner` that snaps the fragments to Unicode text boundaries of
+/// the given `BreakType`, like Java's `BreakIterator`, instead of scanning a
+/// limited number of chars for separators.
+///
+/// The boundaries follow the default rules of Unicode text segmentation, a
+/// locale tailors them, e.g. English sentences don't end after "Dr.".
+#[derive(Debug, Clone)]
+pub struct BreakIteratorBoundaryScanner {
+    break_type: BreakType,
+    abbreviations: &'static [&'static str],
+}
+
+impl BreakIteratorBoundaryScanner {
+    pub fn new(break_type: BreakType) -> BreakIteratorBoundaryScanner {
+        BreakIteratorBoundaryScanner {
+            break_type,
+            abbreviations: &[],
+        }
+    }
+
+    /// Applies the rules of `locale`, a language tag like "en" or "de-CH",
+    /// unknown languages keep the default rules.
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        let language = locale
+            .split(['-', '_'])
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        self.abbreviations = abbreviations(&language);
+        self
+    }
+
+    pub fn break_type(&self) -> BreakType {
+        self.break_type
+    }
+
+    /// The boundaries of `text` in chars, ascending, including 0 and the length
+    /// of the text.
+    pub fn boundaries(&self, text: &str) -> Vec<i32> {
+        let segments: Vec<&str> = match self.break_type {
+            BreakType::Character => text.graphemes(true).collect(),
+            BreakType::Word => text.split_word_bounds().collect(),
+            BreakType::Sentence => text.split_sentence_bounds().collect(),
+        };
+        let mut boundaries = Vec::with_capacity(segments.len() + 1);
+        boundaries.push(0);
+        let mut offset = 0;
+        for (i, segment) in segments.iter().enumerate() {
+            offset += segment.chars().count() as i32;
+            if i + 1 < segments.len() && self.ends_with_abbreviation(segment) {
+                continue;
+            }
+            boundaries.push(offset);
+        }
+        boundaries
+    }
+
+    fn ends_with_abbreviation(&self, sentence: &str) -> bool {
+        if self.break_type != BreakType::Sentence || self.abbreviations.is_empty() {
+            return false;
+        }
+        let last_word = sentence.split_whitespace().last().unwrap_or("");
+        match last_word.strip_suffix('.') {
+            Some(word) => {
+                let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+                self.abbreviations.contains(&word.to_lowercase().as_str())
+            }
+            None => false,
+        }
+    }
+}
+
+impl BoundaryScanner for BreakIteratorBoundaryScanner {
+    fn find_start_offset(&self, buffer: &str, start: i32) -> i32 {
+        let boundaries = self.boundaries(buffer);
+        if start > *boundaries.last().unwrap() || start < 1 {
+            return start;
+        }
+        // the last boundary at or before start
+        let i = boundaries.partition_point(|&b| b <= start);
+        boundaries[i - 1]
+    }
+
+    fn find_end_offset(&self, buffer: &str, start: i32) -> i32 {
+        let boundaries = self.boundaries(buffer);
+        if start > *boundaries.last().unwrap() || start < 0 {
+            return start;
+        }
+        // the first boundary at or after start
+        let i = boundaries.partition_point(|&b| b < start);
+        boundaries[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentence_boundaries() {
+        let text = "He met Dr. Smith. Then he left! Did he?";
+        let scanner = BreakIteratorBoundaryScanner::new(BreakType::Sentence);
+        assert_eq!(scanner.boundaries(text), vec![0, 11, 18, 32, 39]);
+
+        let scanner = scanner.with_locale("en-US");
+        assert_eq!(scanner.boundaries(text), vec![0, 18, 32, 39]);
+        // "Smith" is in the first sentence, "left" in the second
+        assert_eq!(scanner.find_start_offset(text, 11), 0);
+        assert_eq!(scanner.find_end_offset(text, 16), 18);
+        assert_eq!(scanner.find_start_offset(text, 26), 18);
+        assert_eq!(scanner.find_end_offset(text, 30), 32);
+        assert_eq!(scanner.find_start_offset(text, 18), 18);
+        assert_eq!(scanner.find_end_offset(text, 39), 39);
+        assert_eq!(scanner.find_start_offset(text, 40), 40);
+
+        let text = "你好。世界很大。";
+        let scanner = BreakIteratorBoundaryScanner::new(BreakType::Sentence).with_locale("zh");
+        assert_eq!(scanner.boundaries(text), vec![0, 3, 8]);
+    }
+
+    #[test]
+    fn test_word_and_character_boundaries() {
+        let scanner = BreakIteratorBoundaryScanner::new(BreakType::Word);
+        let text = "quick brown";
+        assert_eq!(scanner.boundaries(text), vec![0, 5, 6, 11]);
+        assert_eq!(scanner.find_start_offset(text, 8), 6);
+        assert_eq!(scanner.find_end_offset(text, 8), 11);
+
+        // "e" followed by a combining acute accent is a single character
+        let scanner = BreakIteratorBoundaryScanner::new(BreakType::Character);
+        let text = "ne\u{301}e";
+        assert_eq!(scanner.boundaries(text), vec![0, 1, 3, 4]);
+        assert_eq!(scanner.find_end_offset(text, 2), 3);
+        assert_eq!(scanner.find_start_offset(text, 2), 1);
+    }
+}
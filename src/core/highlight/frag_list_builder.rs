@@ -146,7 +146,7 @@ impl BaseFragListBuilder {
             for i in 1..phrase_len {
                 score_map[i][i] = field_phrase_list.phrase_list[i].boost;
 
-                for j in (0..i - 1).rev() {
+                for j in (0..i).rev() {
                     let phrase_i = &field_phrase_list.phrase_list[i];
                     let phrase_j = &field_phrase_list.phrase_list[j];
                     score_map[i][j] = phrase_i.boost + score_map[i - 1][j];
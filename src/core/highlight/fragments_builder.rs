@@ -18,8 +18,8 @@ use std::collections::HashMap;
 use crate::core::codec::Codec;
 use crate::core::doc::{Fieldable, StoredField};
 use crate::core::highlight::{
-    BoundaryScanner, DefaultEncoder, Encoder, FieldFragList, FragmentsBuilder,
-    SimpleBoundaryScanner, SubInfo, Toffs, WeightedFragInfo,
+    BoundaryScanner, Encoder, FieldFragList, FragmentsBuilder, SimpleBoundaryScanner,
+    SimpleHtmlEncoder, SubInfo, Toffs, WeightedFragInfo,
 };
 use crate::core::index::reader::IndexReader;
 use crate::core::util::DocId;
@@ -30,6 +30,8 @@ use crate::Result;
 pub struct BaseFragmentsBuilder {
     pre_tags: Vec<String>,
     post_tags: Vec<String>,
+    field_tags: HashMap<String, (Vec<String>, Vec<String>)>,
+    encoder: Box<dyn Encoder>,
     multi_valued_separator: char,
     boundary_scanner: Box<dyn BoundaryScanner>,
     pub discrete_multi_value_highlighting: bool,
@@ -44,6 +46,8 @@ impl BaseFragmentsBuilder {
         BaseFragmentsBuilder {
             pre_tags: pre_tags.map_or(vec!["<b>".to_owned()], |x| x.to_vec()),
             post_tags: post_tags.map_or(vec!["</b>".to_owned()], |x| x.to_vec()),
+            field_tags: HashMap::new(),
+            encoder: Box::new(SimpleHtmlEncoder),
             multi_valued_separator: ' ',
             boundary_scanner: boundary_scanner
                 .unwrap_or_else(|| Box::new(SimpleBoundaryScanner::new(None, None))),
@@ -51,6 +55,22 @@ impl BaseFragmentsBuilder {
         }
     }
 
+    /// Sets the tags of the fragments of the stored `field`, the tags passed to
+    /// `create_fragments` still take precedence.
+    pub fn with_field_tags(mut self, field: &str, pre_tags: &[&str], post_tags: &[&str]) -> Self {
+        let to_vec = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect();
+        self.field_tags
+            .insert(field.to_string(), (to_vec(pre_tags), to_vec(post_tags)));
+        self
+    }
+
+    /// Sets the encoder of the text around the highlighted terms, the default
+    /// escapes HTML.
+    pub fn with_encoder(mut self, encoder: Box<dyn Encoder>) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
     fn fields<C: Codec>(
         &self,
         reader: &dyn IndexReader<Codec = C>,
@@ -305,11 +325,9 @@ impl BaseFragmentsBuilder {
             buffer.push(self.multi_valued_separator);
         }
 
-        let mut buffer_len = buffer.chars().count() as i32;
-        // we added the multi value char to the last buffer, ignore it
-        if values[index[0] as usize - 1].field.field_type().tokenized() {
-            buffer_len -= 1;
-        }
+        // we added the multi value char to the last buffer, ignore it, the stored
+        // values read back don't know whether they were tokenized
+        let buffer_len = buffer.chars().count() as i32 - 1;
 
         let mut start_offset = start_offset;
         let eo = if buffer_len < end_offset {
@@ -366,23 +384,17 @@ impl FragmentsBuilder for BaseFragmentsBuilder {
         encoder: Option<&dyn Encoder>,
         score_ordered: Option<bool>,
     ) -> Result<Vec<String>> {
-        let pre_tags = match pre_tags {
-            Some(x) => x,
-            None => &self.pre_tags,
-        };
-        let post_tags = match post_tags {
-            Some(x) => x,
-            None => &self.post_tags,
+        let (field_pre_tags, field_post_tags) = match self.field_tags.get(field_name) {
+            Some((pre_tags, post_tags)) => (pre_tags, post_tags),
+            None => (&self.pre_tags, &self.post_tags),
         };
+        let pre_tags = pre_tags.unwrap_or(field_pre_tags);
+        let post_tags = post_tags.unwrap_or(field_post_tags);
         let max_num_fragments = match max_num_fragments {
             Some(x) => x,
             None => 1,
         };
-        let null_decoder = DefaultEncoder::default();
-        let encoder = match encoder {
-            Some(x) => x,
-            None => &null_decoder,
-        };
+        let encoder = encoder.unwrap_or_else(|| self.encoder.as_ref());
         let score_ordered = match score_ordered {
             Some(x) => x,
             None => false,
@@ -434,3 +446,74 @@ impl FragmentsBuilder for BaseFragmentsBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+    use crate::core::doc::{Field, FieldType, IndexOptions};
+    use crate::core::highlight::{DefaultEncoder, FastVectorHighlighter, FieldQuery};
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::index::Term;
+    use crate::core::search::query::TermQuery;
+
+    const TEXT: &str = "fish & chips <with> salt";
+
+    fn highlight(fragments_builder: BaseFragmentsBuilder) -> Vec<String> {
+        let writer = in_memory_writer();
+        let field_type = FieldType {
+            index_options: IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+            ..FieldType::default()
+        };
+        let token_stream = WhitespaceAnalyzer.token_stream("body", TEXT).unwrap();
+        let doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(Field::new(
+                "body".into(),
+                field_type,
+                None,
+                Some(token_stream),
+            )),
+            Box::new(StoredField::new("body.raw", None, TEXT.into())),
+        ];
+        writer.add_document(doc).unwrap();
+        let reader = writer.get_reader(true, false).unwrap();
+
+        let query = TermQuery::new(Term::new("body".into(), b"chips".to_vec()), 1.0, None);
+        let mut field_query = FieldQuery::new(&query, Some(&reader), true, true).unwrap();
+        let mut highlighter =
+            FastVectorHighlighter::default().with_fragments_builder(fragments_builder);
+        highlighter
+            .get_best_fragments(
+                &mut field_query,
+                &reader.leaf_reader_for_doc(0),
+                0,
+                "body",
+                "body.raw",
+                100,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_encoder_escapes_html() {
+        let fragments = highlight(BaseFragmentsBuilder::new(None, None, None));
+        assert_eq!(fragments, vec!["fish &amp; <b>chips</b> &lt;with&gt; salt"]);
+    }
+
+    #[test]
+    fn test_with_encoder() {
+        let fragments_builder =
+            BaseFragmentsBuilder::new(None, None, None).with_encoder(Box::new(DefaultEncoder));
+        assert_eq!(
+            highlight(fragments_builder),
+            vec!["fish & <b>chips</b> <with> salt"]
+        );
+    }
+}
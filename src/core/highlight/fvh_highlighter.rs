@@ -24,18 +24,23 @@ use crate::core::search::query::Query;
 use crate::core::util::DocId;
 use crate::Result;
 
+use std::collections::HashMap;
 use std::i32;
 
 const DEFAULT_PHRASE_HIGHLIGHT: bool = true;
 const DEFAULT_FIELD_MATCH: bool = true;
 const DEFAULT_PHRASE_LIMIT: i32 = 256;
 
-pub struct FastVectorHighlighter {
+/// Highlights from the term offsets in the term vectors, or the postings, of
+/// the documents, see `FieldTermStack`. The fragments are built by a pluggable
+/// `FragmentsBuilder`.
+pub struct FastVectorHighlighter<F: FragmentsBuilder = BaseFragmentsBuilder> {
     pub phrase_highlight: bool,
     pub field_match: bool,
     frag_list_builder: Box<dyn FragListBuilder>,
-    fragments_builder: BaseFragmentsBuilder,
+    fragments_builder: F,
     pub phrase_limit: i32,
+    matched_fields: HashMap<String, Vec<String>>,
 }
 
 impl Default for FastVectorHighlighter {
@@ -73,9 +78,36 @@ impl FastVectorHighlighter {
                 Some(x) => x,
                 None => DEFAULT_PHRASE_LIMIT,
             },
+            matched_fields: HashMap::new(),
+        }
+    }
+}
+
+impl<F: FragmentsBuilder> FastVectorHighlighter<F> {
+    /// Replaces the builder of the fragments, e.g. for custom markup.
+    pub fn with_fragments_builder<B: FragmentsBuilder>(
+        self,
+        fragments_builder: B,
+    ) -> FastVectorHighlighter<B> {
+        FastVectorHighlighter {
+            phrase_highlight: self.phrase_highlight,
+            field_match: self.field_match,
+            frag_list_builder: self.frag_list_builder,
+            fragments_builder,
+            phrase_limit: self.phrase_limit,
+            matched_fields: self.matched_fields,
         }
     }
 
+    /// Combines the matches of `matched_fields`, e.g. differently analyzed
+    /// variants of the same text like `title.stemmed`, with the ones of `field`
+    /// when highlighting it. Their offsets must point into the same stored text.
+    pub fn with_matched_fields(mut self, field: &str, matched_fields: &[&str]) -> Self {
+        let fields = matched_fields.iter().map(|f| f.to_string()).collect();
+        self.matched_fields.insert(field.to_string(), fields);
+        self
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn get_best_fragments<C: Codec>(
         &mut self,
@@ -87,7 +119,7 @@ impl FastVectorHighlighter {
         frag_char_size: i32,
         max_num_fragments: Option<i32>,
         frag_list_builder: Option<&dyn FragListBuilder>,
-        fragments_builder: Option<&BaseFragmentsBuilder>,
+        fragments_builder: Option<&F>,
         pre_tags: Option<&[String]>,
         post_tags: Option<&[String]>,
         encoder: Option<&dyn Encoder>,
@@ -98,14 +130,29 @@ impl FastVectorHighlighter {
             None => self.frag_list_builder.as_ref(),
         };
 
-        let mut field_frag_list = self.get_field_frag_list(
-            frag_list_builder,
-            field_query,
-            reader,
-            doc_id,
-            field_name,
-            frag_char_size,
-        )?;
+        let mut field_frag_list = match self.matched_fields.get(field_name) {
+            Some(matched_fields) => {
+                let mut fields = Vec::with_capacity(matched_fields.len() + 1);
+                fields.push(field_name.to_string());
+                fields.extend(matched_fields.iter().cloned());
+                self.get_fields_frag_list(
+                    frag_list_builder,
+                    field_query,
+                    reader,
+                    doc_id,
+                    &fields,
+                    frag_char_size,
+                )?
+            }
+            None => self.get_field_frag_list(
+                frag_list_builder,
+                field_query,
+                reader,
+                doc_id,
+                field_name,
+                frag_char_size,
+            )?,
+        };
 
         let fragments_builder = match fragments_builder {
             Some(builder) => builder,
@@ -125,6 +172,8 @@ impl FastVectorHighlighter {
         )
     }
 
+    /// Highlights `stored_field` with the merged matches of all the
+    /// `matched_fields`, which should include the field of the stored text.
     #[allow(clippy::too_many_arguments)]
     pub fn get_best_fragments_with_tags_fields<C: Codec>(
         &mut self,
@@ -135,13 +184,16 @@ impl FastVectorHighlighter {
         matched_fields: &[String],
         frag_char_size: i32,
         max_num_fragments: Option<i32>,
-        frag_list_builder: &dyn FragListBuilder,
-        fragments_builder: &BaseFragmentsBuilder,
+        frag_list_builder: Option<&dyn FragListBuilder>,
+        fragments_builder: Option<&F>,
         pre_tags: Option<&[String]>,
         post_tags: Option<&[String]>,
         encoder: Option<&dyn Encoder>,
         score_ordered: Option<bool>,
     ) -> Result<Vec<String>> {
+        let frag_list_builder =
+            frag_list_builder.unwrap_or_else(|| self.frag_list_builder.as_ref());
+        let fragments_builder = fragments_builder.unwrap_or(&self.fragments_builder);
         let mut field_frag_list = self.get_fields_frag_list(
             frag_list_builder,
             field_query,
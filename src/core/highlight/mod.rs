@@ -11,18 +11,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod break_iterator_boundary_scanner;
 mod frag_list_builder;
 mod fragments_builder;
 mod fvh_highlighter;
+mod plain_highlighter;
+mod unified_highlighter;
 use crate::core::codec::{
     Codec, Fields, PostingIterator, PostingIteratorFlags, TermIterator, Terms,
 };
+use crate::core::doc::{Fieldable, IndexOptions};
 use crate::core::index::reader::{IndexReader, LeafReaderContext};
 use crate::core::index::Term;
-use crate::core::search::query::{Query, TermQuery};
+use crate::core::search::query::{PhraseQuery, Query, TermQuery};
 use crate::core::search::DocIterator;
 use crate::core::util::DocId;
+pub use break_iterator_boundary_scanner::{BreakIteratorBoundaryScanner, BreakType};
+pub use fragments_builder::BaseFragmentsBuilder;
 pub use fvh_highlighter::FastVectorHighlighter;
+pub use plain_highlighter::{Highlighter, DEFAULT_FRAGMENT_SIZE, DEFAULT_MAX_CHARS_TO_ANALYZE};
+pub use unified_highlighter::{
+    FieldHighlightConfig, HighlightedField, OffsetSource, Passage, PassageFormatter, PassageMatch,
+    PassageScorer, UnifiedHighlighter, DEFAULT_MAX_LENGTH, DEFAULT_MAX_PASSAGES,
+};
 
 use crate::Result;
 
@@ -30,6 +41,23 @@ use std::borrow::Cow;
 use std::cmp::{self, Ordering};
 use std::collections::HashMap;
 use std::f32::EPSILON;
+use std::iter;
+
+/// Tags with a different background color per query term, to be used as the
+/// pre tags of a highlighter with `COLORED_POST_TAGS`.
+pub const COLORED_PRE_TAGS: [&str; 10] = [
+    "<b style=\"background:yellow\">",
+    "<b style=\"background:lawngreen\">",
+    "<b style=\"background:aquamarine\">",
+    "<b style=\"background:magenta\">",
+    "<b style=\"background:palegreen\">",
+    "<b style=\"background:coral\">",
+    "<b style=\"background:wheat\">",
+    "<b style=\"background:khaki\">",
+    "<b style=\"background:lime\">",
+    "<b style=\"background:deepskyblue\">",
+];
+pub const COLORED_POST_TAGS: [&str; 1] = ["</b>"];
 
 ///
 // Encodes original text. The Encoder works with the {@link Formatter} to generate output.
@@ -77,6 +105,39 @@ impl Encoder for SimpleHtmlEncoder {
     }
 }
 
+/// The distinct terms of `query` on `field`.
+pub(crate) fn query_terms<C: Codec>(field: &str, query: &dyn Query<C>) -> Vec<Vec<u8>> {
+    let mut terms: Vec<Vec<u8>> = vec![];
+    for term_query in query.extract_terms() {
+        let term = term_query.term();
+        if term.field() == field && !terms.iter().any(|t| t.as_slice() == term.bytes()) {
+            terms.push(term.bytes().to_vec());
+        }
+    }
+    terms
+}
+
+/// The text of the stored `field` of a document, the values of a multi-valued
+/// field are joined with a space, `None` if the document has no text value.
+pub(crate) fn load_stored_text<C: Codec>(
+    reader: &dyn IndexReader<Codec = C>,
+    doc_id: DocId,
+    field: &str,
+) -> Result<Option<String>> {
+    let document = reader.document(doc_id, &[field.to_string()])?;
+    let values: Vec<&str> = document
+        .fields
+        .iter()
+        .filter(|f| f.field.name() == field)
+        .filter_map(|f| f.field.string_value())
+        .collect();
+    if values.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(values.join(" ")))
+    }
+}
+
 ///
 // Term offsets (start + end)
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -421,6 +482,17 @@ impl QueryPhraseMap {
         Ok(())
     }
 
+    /// Adds the terms of the phrase as a chain of sub maps, only the map of the
+    /// last term is terminal.
+    pub fn add_phrase(&mut self, query: &PhraseQuery, term_or_phrase_number: i32) {
+        let boost = 1f32;
+        let mut map = self;
+        for term in query.terms() {
+            map = map.sub_map.entry(term.text()).or_default();
+        }
+        map.mark_terminal(Some(query.slop()), boost, term_or_phrase_number);
+    }
+
     pub fn is_valid_term_or_phrase(&self, phrase_candidate: &[TermInfo]) -> bool {
         if !self.terminal {
             return false;
@@ -469,6 +541,9 @@ pub const MAX_MTQ_TERMS: i32 = 1024;
 #[derive(Debug)]
 pub struct FieldQuery {
     field_match: bool,
+    // phraseHighlight==true, the terms of a phrase are only highlighted where the
+    // whole phrase matches
+    phrase_highlight: bool,
     // fieldMatch==true,  Map<fieldName,QueryPhraseMap>
     // fieldMatch==false, Map<null,QueryPhraseMap>
     root_maps: HashMap<String, QueryPhraseMap>,
@@ -483,19 +558,21 @@ impl FieldQuery {
     pub fn new<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
         query: &dyn Query<C>,
         reader: Option<&IR>,
-        _phrase_highlight: bool,
+        phrase_highlight: bool,
         field_match: bool,
     ) -> Result<FieldQuery> {
         let mut flat_queries: Vec<TermQuery> = vec![];
+        let mut flat_phrases: Vec<PhraseQuery> = vec![];
         let mut field_query = FieldQuery {
             field_match,
+            phrase_highlight,
             root_maps: HashMap::new(),
             term_set_map: HashMap::new(),
             term_or_phrase_number: 0,
         };
 
-        field_query.flatten(query, reader, &mut flat_queries, 1f32)?;
-        field_query.save_terms(reader, &flat_queries)?;
+        field_query.flatten(query, reader, &mut flat_queries, &mut flat_phrases, 1f32)?;
+        field_query.save_terms(reader, &flat_queries, &flat_phrases)?;
 
         let expand_queries = field_query.expand(flat_queries);
 
@@ -504,21 +581,42 @@ impl FieldQuery {
             field_query.add_root_map_by_query(flat_query, reader, term_or_phrase_number)?;
         }
 
+        for flat_phrase in &flat_phrases {
+            let term_or_phrase_number = field_query.next_term_or_phrase_number();
+            field_query.add_root_map_by_phrase(flat_phrase, term_or_phrase_number);
+        }
+
         Ok(field_query)
     }
 
+    // Splits the query into its terms and its phrases, the terms of a phrase only
+    // stand on their own when phraseHighlight==false or when the query has them
+    // outside of the phrase as well.
     fn flatten<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
         &self,
         source_query: &dyn Query<C>,
         _reader: Option<&IR>,
         flat_queries: &mut Vec<TermQuery>,
+        flat_phrases: &mut Vec<PhraseQuery>,
         boost: f32,
     ) -> Result<()> {
         if (boost - 1f32).abs() > EPSILON {
             unimplemented!()
         }
 
-        for term_query in source_query.extract_terms() {
+        let mut term_queries = source_query.extract_terms();
+        for phrase in source_query.extract_phrases() {
+            if self.phrase_highlight {
+                for term in phrase.terms() {
+                    if let Some(i) = term_queries.iter().position(|q| q.term() == term) {
+                        term_queries.remove(i);
+                    }
+                }
+            }
+            flat_phrases.push(phrase);
+        }
+
+        for term_query in term_queries {
             if !flat_queries.contains(&term_query) {
                 flat_queries.push(term_query);
             }
@@ -544,9 +642,16 @@ impl FieldQuery {
         &mut self,
         _reader: Option<&IR>,
         flat_queries: &[TermQuery],
+        flat_phrases: &[PhraseQuery],
     ) -> Result<()> {
         for query in flat_queries {
-            self.add_term_set_by_query(query, query.term().text());
+            self.add_term_set_by_field(query.term().field(), query.term().text());
+        }
+
+        for phrase in flat_phrases {
+            for term in phrase.terms() {
+                self.add_term_set_by_field(phrase.field(), term.text());
+            }
         }
 
         Ok(())
@@ -555,28 +660,27 @@ impl FieldQuery {
     // Return 'key' string. 'key' is the field name of the Query.
     // If not fieldMatch, 'key' will be null.
     //
-    fn get_key(&self, query: &TermQuery) -> String {
+    fn get_key(&self, field: &str) -> String {
         if !self.field_match {
             return String::from("");
         }
 
-        String::from(query.term().field())
+        String::from(field)
     }
 
-    fn add_term_set_by_query(&mut self, query: &TermQuery, value: String) {
-        let key = self.get_key(query);
+    fn add_term_set_by_field(&mut self, field: &str, value: String) {
+        let key = self.get_key(field);
 
-        if !self.term_set_map.contains_key(&key) {
-            let set: Vec<String> = vec![];
-            self.term_set_map.insert(key.clone(), set);
+        let set = self.term_set_map.entry(key).or_default();
+        if !set.contains(&value) {
+            set.push(value)
         }
-
-        debug_assert!(self.term_set_map.contains_key(&key));
-        self.term_set_map.get_mut(&key).unwrap().push(value)
     }
 
     pub fn get_term_set_by_field(&self, field: &str) -> Option<&Vec<String>> {
-        self.term_set_map.get(field)
+        let key = if self.field_match { field } else { "" };
+
+        self.term_set_map.get(key)
     }
 
     fn expand(&self, flat_queries: Vec<TermQuery>) -> Vec<TermQuery> {
@@ -585,7 +689,7 @@ impl FieldQuery {
 
     pub fn next_term_or_phrase_number(&mut self) -> i32 {
         self.term_or_phrase_number += 1;
-        self.term_or_phrase_number
+        self.term_or_phrase_number - 1
     }
 
     fn add_root_map_by_query<C: Codec, IR: IndexReader<Codec = C> + ?Sized>(
@@ -594,7 +698,7 @@ impl FieldQuery {
         reader: Option<&IR>,
         term_or_phrase_number: i32,
     ) -> Result<()> {
-        let key = self.get_key(query);
+        let key = self.get_key(query.term().field());
 
         if !self.root_maps.contains_key(&key) {
             let map = QueryPhraseMap::default();
@@ -608,6 +712,14 @@ impl FieldQuery {
             .add(query, reader, term_or_phrase_number)
     }
 
+    fn add_root_map_by_phrase(&mut self, query: &PhraseQuery, term_or_phrase_number: i32) {
+        let key = self.get_key(query.field());
+        self.root_maps
+            .entry(key)
+            .or_default()
+            .add_phrase(query, term_or_phrase_number);
+    }
+
     pub fn get_root_map_by_field(&self, field_name: &str) -> Option<&QueryPhraseMap> {
         let key = if self.field_match { field_name } else { "" };
 
@@ -643,6 +755,10 @@ pub struct FieldTermStack {
 }
 
 impl FieldTermStack {
+    /// Collects the occurrences of the query terms in the field of the document,
+    /// their offsets are read from the term vector of the document or, if it has
+    /// none for the field, from the postings if the field was indexed with
+    /// offsets. The stack is empty if neither has offsets.
     pub fn new<C: Codec>(
         ctx: &LeafReaderContext<'_, C>,
         doc_id: DocId,
@@ -660,16 +776,32 @@ impl FieldTermStack {
             }
         };
 
+        let term_list = match Self::term_vector_terms(ctx, doc_id, field_name, term_set)? {
+            Some(term_list) => term_list,
+            None => Self::postings_terms(ctx, doc_id, field_name, term_set)?.unwrap_or_default(),
+        };
+
+        Ok(FieldTermStack {
+            field_name: field_name.to_string(),
+            term_list: Self::link_terms(term_list),
+        })
+    }
+
+    // Returns `None` if the doc has no term vector for the field, an empty list
+    // if it has one without positions or offsets.
+    fn term_vector_terms<C: Codec>(
+        ctx: &LeafReaderContext<'_, C>,
+        doc_id: DocId,
+        field_name: &str,
+        term_set: &[String],
+    ) -> Result<Option<Vec<TermInfo>>> {
         let reader = ctx.reader;
 
         if let Some(vectors) = reader.term_vector(doc_id - ctx.doc_base)? {
             if let Some(vector) = vectors.terms(field_name)? {
                 // true null snippet
                 if vectors.fields().is_empty() || !vector.has_positions()? {
-                    return Ok(FieldTermStack {
-                        field_name: field_name.to_string(),
-                        term_list: vec![],
-                    });
+                    return Ok(Some(vec![]));
                 }
 
                 let mut terms_iter = vector.iterator()?;
@@ -686,10 +818,7 @@ impl FieldTermStack {
                         terms_iter.postings_with_flags(PostingIteratorFlags::POSITIONS)?;
                     postings.next()?;
 
-                    // For weight look here: http://lucene.apache.org/core/3_6_0/api/core/org/apache/lucene/search/DefaultSimilarity.html
-                    let weight = (f64::from(max_docs) / (terms_iter.total_term_freq()? + 1) as f64
-                        + 1.0)
-                        .log(10.0f64) as f32;
+                    let weight = Self::weight(max_docs, terms_iter.total_term_freq()?);
                     let freq = postings.freq()?;
 
                     for _ in 0..freq {
@@ -697,10 +826,7 @@ impl FieldTermStack {
 
                         if postings.start_offset()? < 0 {
                             // no offsets, null snippet
-                            return Ok(FieldTermStack {
-                                field_name: field_name.to_string(),
-                                term_list: vec![],
-                            });
+                            return Ok(Some(vec![]));
                         }
 
                         term_list.push(TermInfo::new(
@@ -713,64 +839,117 @@ impl FieldTermStack {
                     }
                 }
 
-                // now look for dups at the same position, linking them together
-                term_list.sort_by(|o1, o2| {
-                    if o1.end_offset != o2.end_offset {
-                        o1.end_offset.cmp(&o2.end_offset).reverse()
-                    } else {
-                        o1.start_offset.cmp(&o2.start_offset)
-                    }
-                });
+                return Ok(Some(term_list));
+            }
+        }
+        Ok(None)
+    }
 
-                let mut start_offset = -1;
-                let mut end_offset = -1;
-                let mut terms_count = HashMap::new();
-                let mut total_count = 0;
-                for term_info in term_list.iter_mut() {
-                    if !(term_info.start_offset >= start_offset
-                        && term_info.end_offset <= end_offset)
-                    {
-                        if !terms_count.contains_key(&term_info.text) {
-                            terms_count.insert(term_info.text.clone(), 1);
-                        }
-                        *(terms_count.get_mut(&term_info.text).unwrap()) += 1;
+    // Returns `None` if the field wasn't indexed with offsets.
+    fn postings_terms<C: Codec>(
+        ctx: &LeafReaderContext<'_, C>,
+        doc_id: DocId,
+        field_name: &str,
+        term_set: &[String],
+    ) -> Result<Option<Vec<TermInfo>>> {
+        let reader = ctx.reader;
+        match reader.field_info(field_name) {
+            Some(info)
+                if info.index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets => {}
+            _ => return Ok(None),
+        }
 
-                        total_count += 1;
-                    } else {
-                        term_info.position = -1;
-                        continue;
-                    }
+        let leaf_doc = doc_id - ctx.doc_base;
+        let max_docs = reader.max_doc();
+        let mut term_list: Vec<TermInfo> = vec![];
+        for (i, term) in term_set.iter().enumerate() {
+            if term_set[..i].contains(term) {
+                continue;
+            }
 
-                    start_offset = term_info.start_offset;
-                    end_offset = term_info.end_offset;
+            let index_term = Term::new(field_name.to_string(), term.as_bytes().to_vec());
+            let flags = i32::from(PostingIteratorFlags::OFFSETS);
+            let mut postings = match reader.postings(&index_term, flags)? {
+                Some(postings) => postings,
+                None => continue,
+            };
+            if postings.advance(leaf_doc)? != leaf_doc {
+                continue;
+            }
+
+            let freq = postings.freq()?;
+            let weight = Self::weight(max_docs, i64::from(freq));
+            for _ in 0..freq {
+                let pos = postings.next_position()?;
+                if postings.start_offset()? < 0 {
+                    return Ok(None);
                 }
 
-                let mut i = term_list.len();
-                while i > 0 {
-                    if term_list[i - 1].position == -1 {
-                        term_list.remove(i - 1);
-                    }
+                term_list.push(TermInfo::new(
+                    term.clone(),
+                    postings.start_offset()?,
+                    postings.end_offset()?,
+                    pos,
+                    weight,
+                ));
+            }
+        }
+        Ok(Some(term_list))
+    }
 
-                    i -= 1;
-                }
+    // For weight look here: http://lucene.apache.org/core/3_6_0/api/core/org/apache/lucene/search/DefaultSimilarity.html
+    fn weight(max_docs: i32, total_term_freq: i64) -> f32 {
+        (f64::from(max_docs) / (total_term_freq + 1) as f64 + 1.0).log(10.0f64) as f32
+    }
 
-                let avg_count = (total_count as f32) / (terms_count.len() as f32 + 1.0);
-                for term_info in term_list.iter_mut() {
-                    if let Some(count) = terms_count.get(&term_info.text) {
-                        term_info.weight *= 1.0 + avg_count / (*count as f32 + 1.0);
-                    }
+    // now look for dups at the same position, linking them together
+    fn link_terms(mut term_list: Vec<TermInfo>) -> Vec<TermInfo> {
+        term_list.sort_by(|o1, o2| {
+            if o1.end_offset != o2.end_offset {
+                o1.end_offset.cmp(&o2.end_offset).reverse()
+            } else {
+                o1.start_offset.cmp(&o2.start_offset)
+            }
+        });
+
+        let mut start_offset = -1;
+        let mut end_offset = -1;
+        let mut terms_count = HashMap::new();
+        let mut total_count = 0;
+        for term_info in term_list.iter_mut() {
+            if !(term_info.start_offset >= start_offset && term_info.end_offset <= end_offset) {
+                if !terms_count.contains_key(&term_info.text) {
+                    terms_count.insert(term_info.text.clone(), 1);
                 }
+                *(terms_count.get_mut(&term_info.text).unwrap()) += 1;
 
-                return Ok(FieldTermStack {
-                    field_name: field_name.to_string(),
-                    term_list,
-                });
+                total_count += 1;
+            } else {
+                term_info.position = -1;
+                continue;
             }
+
+            start_offset = term_info.start_offset;
+            end_offset = term_info.end_offset;
         }
-        Ok(FieldTermStack {
-            field_name: field_name.to_string(),
-            term_list: vec![],
-        })
+
+        let mut i = term_list.len();
+        while i > 0 {
+            if term_list[i - 1].position == -1 {
+                term_list.remove(i - 1);
+            }
+
+            i -= 1;
+        }
+
+        let avg_count = (total_count as f32) / (terms_count.len() as f32 + 1.0);
+        for term_info in term_list.iter_mut() {
+            if let Some(count) = terms_count.get(&term_info.text) {
+                term_info.weight *= 1.0 + avg_count / (*count as f32 + 1.0);
+            }
+        }
+
+        term_list
     }
 
     pub fn pop(&mut self) -> Option<TermInfo> {
@@ -801,93 +980,70 @@ impl FieldPhraseList {
             phrase_list: vec![],
         };
 
-        let mut curr_map: Option<&QueryPhraseMap> = None;
-        let mut next_map: Option<&QueryPhraseMap>;
-
         while !field_term_stack.is_empty()
             && field_phrase_list.phrase_list.len() < phrase_limit as usize
         {
             let mut phrase_candidate: Vec<TermInfo> = vec![];
 
+            // the first term, or one at the same position, must start a query term or phrase
             let first = field_term_stack.pop().unwrap();
-            match field_query.get_field_term_map(&field, &first.text) {
-                Some(map) => {
-                    curr_map = Some(map);
-                    phrase_candidate.push(first.clone());
-                }
-                None => {
-                    for i in 0..first.next.len() {
-                        curr_map = field_query.get_field_term_map(&field, &first.next[i].text);
-                        if curr_map.is_some() {
-                            phrase_candidate.push(first.next[i].clone());
-                            break;
-                        }
-                    }
+            let mut curr_map = None;
+            for ti in iter::once(&first).chain(first.next.iter()) {
+                curr_map = field_query.get_field_term_map(&field, &ti.text);
+                if curr_map.is_some() {
+                    phrase_candidate.push(ti.clone());
+                    break;
                 }
             }
 
             // if not found, discard top TermInfo from stack, then try next element
-            if curr_map.is_none() {
-                continue;
-            }
+            let mut curr_map = match curr_map {
+                Some(map) => map,
+                None => continue,
+            };
 
             loop {
-                next_map = None;
-                let curr_map_unwrap = curr_map.unwrap();
-                let first = field_term_stack.pop();
-
-                if first.is_some() {
-                    let first_unwrap = first.unwrap();
-
-                    next_map = curr_map_unwrap.sub_map.get(&first_unwrap.text);
-                    let mut i = 0;
-                    while next_map.is_none() && i < first_unwrap.next.len() {
-                        next_map = curr_map_unwrap.sub_map.get(&first_unwrap.next[i].text);
-                        i += 1;
-                    }
-
-                    match next_map {
-                        Some(_) => {
-                            if i > 0 {
-                                phrase_candidate.push(first_unwrap.next[i - 1].clone());
-                            } else {
-                                phrase_candidate.push(first_unwrap);
-                                curr_map = next_map;
-                            }
+                let mut next_map = None;
+                if let Some(first) = field_term_stack.pop() {
+                    for ti in iter::once(&first).chain(first.next.iter()) {
+                        if let Some(map) = curr_map.sub_map.get(&ti.text) {
+                            next_map = Some((map, ti.clone()));
+                            break;
                         }
-                        None => field_term_stack.push(first_unwrap),
+                    }
+                    if next_map.is_none() {
+                        field_term_stack.push(first);
                     }
                 }
 
-                if next_map.is_none() {
-                    if curr_map_unwrap.is_valid_term_or_phrase(&phrase_candidate) {
-                        field_phrase_list.add_if_no_overlap(WeightedPhraseInfo::new(
-                            phrase_candidate,
-                            curr_map_unwrap.boost,
-                            Some(curr_map_unwrap.term_or_phrase_number),
-                        ));
-                        break;
-                    } else {
-                        let mut i = phrase_candidate.len() - 1;
-                        while i > 0 {
-                            field_term_stack.push(phrase_candidate[i].clone());
-                            curr_map = field_query.search_phrase(&field, &phrase_candidate);
-                            if curr_map.is_some() {
-                                let curr_map_unwrap = curr_map.unwrap();
-                                field_phrase_list.add_if_no_overlap(WeightedPhraseInfo::new(
-                                    phrase_candidate,
-                                    curr_map_unwrap.boost,
-                                    Some(curr_map_unwrap.term_or_phrase_number),
-                                ));
-                                break;
-                            }
-
-                            i -= 1;
+                if let Some((map, ti)) = next_map {
+                    phrase_candidate.push(ti);
+                    curr_map = map;
+                    continue;
+                }
+
+                if curr_map.is_valid_term_or_phrase(&phrase_candidate) {
+                    field_phrase_list.add_if_no_overlap(WeightedPhraseInfo::new(
+                        phrase_candidate,
+                        curr_map.boost,
+                        Some(curr_map.term_or_phrase_number),
+                    ));
+                } else {
+                    // shrink the candidate until what is left is a query term or phrase,
+                    // the dropped terms go back to the stack
+                    while phrase_candidate.len() > 1 {
+                        field_term_stack.push(phrase_candidate.pop().unwrap());
+                        if let Some(map) = field_query.search_phrase(&field, &phrase_candidate) {
+                            field_phrase_list.add_if_no_overlap(WeightedPhraseInfo::new(
+                                phrase_candidate,
+                                map.boost,
+                                Some(map.term_or_phrase_number),
+                            ));
+                            break;
                         }
                     }
-
-                    break;
                 }
+                break;
             }
         }
 
@@ -0,0 +1,237 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::codec::Codec;
+use crate::core::highlight::{
+    load_stored_text, query_terms, Passage, PassageFormatter, PassageMatch,
+};
+use crate::core::index::reader::IndexReader;
+use crate::core::search::query::Query;
+use crate::core::util::DocId;
+use crate::Result;
+
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_FRAGMENT_SIZE: usize = 100;
+pub const DEFAULT_MAX_CHARS_TO_ANALYZE: usize = 50 * 1024;
+
+/// A highlighter that finds the query terms by analyzing the stored text
+/// again, so it works for fields indexed without term vectors or offsets.
+///
+/// The text is split into fragments of about `fragment_size` chars at token
+/// boundaries, a fragment scores the number of distinct query terms in it.
+pub struct Highlighter {
+    fragment_size: usize,
+    max_chars_to_analyze: usize,
+    formatter: PassageFormatter,
+    field_formatters: HashMap<String, PassageFormatter>,
+    analyzer: Box<dyn Analyzer>,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Highlighter::new(WhitespaceAnalyzer)
+    }
+}
+
+impl Highlighter {
+    /// `analyzer` must produce the same terms as the one the fields were
+    /// indexed with.
+    pub fn new<A: Analyzer + 'static>(analyzer: A) -> Highlighter {
+        Highlighter {
+            fragment_size: DEFAULT_FRAGMENT_SIZE,
+            max_chars_to_analyze: DEFAULT_MAX_CHARS_TO_ANALYZE,
+            formatter: PassageFormatter::default(),
+            field_formatters: HashMap::new(),
+            analyzer: Box::new(analyzer),
+        }
+    }
+
+    pub fn with_fragment_size(mut self, fragment_size: usize) -> Self {
+        self.fragment_size = fragment_size.max(1);
+        self
+    }
+
+    /// Only the first `max_chars_to_analyze` chars of a text are highlighted.
+    pub fn with_max_chars_to_analyze(mut self, max_chars_to_analyze: usize) -> Self {
+        self.max_chars_to_analyze = max_chars_to_analyze;
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: PassageFormatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Formats the fragments of `field` with `formatter` instead of the
+    /// highlighter's one, e.g. with its own tags.
+    pub fn with_field_formatter(mut self, field: &str, formatter: PassageFormatter) -> Self {
+        self.field_formatters.insert(field.to_string(), formatter);
+        self
+    }
+
+    /// The best fragment of `text`, `None` if no query term occurs in it.
+    pub fn get_best_fragment<C: Codec>(
+        &self,
+        query: &dyn Query<C>,
+        field: &str,
+        text: &str,
+    ) -> Result<Option<String>> {
+        Ok(self.get_best_fragments(query, field, text, 1)?.pop())
+    }
+
+    /// The best `max_num_fragments` fragments of `text` for the terms of
+    /// `query` on `field`, the best scoring first.
+    pub fn get_best_fragments<C: Codec>(
+        &self,
+        query: &dyn Query<C>,
+        field: &str,
+        text: &str,
+        max_num_fragments: usize,
+    ) -> Result<Vec<String>> {
+        let terms = query_terms(field, query);
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+        let content: Vec<char> = text.chars().take(self.max_chars_to_analyze).collect();
+        let fragments = self.best_fragments(field, &terms, &content, max_num_fragments)?;
+        let formatter = self.field_formatters.get(field).unwrap_or(&self.formatter);
+        Ok(fragments
+            .iter()
+            .map(|f| formatter.format(std::slice::from_ref(f), &content))
+            .collect())
+    }
+
+    /// Highlights the text of `stored_field` of a document, for the terms of
+    /// `query` on `field`, see `get_best_fragments`.
+    pub fn get_best_fragments_for_doc<C: Codec>(
+        &self,
+        query: &dyn Query<C>,
+        reader: &dyn IndexReader<Codec = C>,
+        doc_id: DocId,
+        field: &str,
+        stored_field: &str,
+        max_num_fragments: usize,
+    ) -> Result<Vec<String>> {
+        match load_stored_text(reader, doc_id, stored_field)? {
+            Some(text) => self.get_best_fragments(query, field, &text, max_num_fragments),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn best_fragments(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        content: &[char],
+        max_num_fragments: usize,
+    ) -> Result<Vec<Passage>> {
+        let text: String = content.iter().collect();
+        let mut token_stream = self.analyzer.token_stream(field, &text)?;
+        token_stream.reset()?;
+        let mut fragments: Vec<Passage> = vec![];
+        while token_stream.next_token()? {
+            let token = token_stream.token();
+            if token.end_offset > content.len() {
+                break;
+            }
+            let new_fragment = match fragments.last() {
+                Some(_) => token.end_offset >= self.fragment_size * fragments.len(),
+                None => true,
+            };
+            if new_fragment {
+                fragments.push(Passage {
+                    start_offset: token.start_offset,
+                    end_offset: token.end_offset,
+                    score: 0.0,
+                    matches: vec![],
+                });
+            }
+            let fragment = fragments.last_mut().unwrap();
+            fragment.end_offset = fragment.end_offset.max(token.end_offset);
+            if let Some(term_index) = terms.iter().position(|t| *t == token.term) {
+                fragment.matches.push(PassageMatch {
+                    start_offset: token.start_offset,
+                    end_offset: token.end_offset,
+                    term: token.term.clone(),
+                    term_index,
+                });
+            }
+        }
+        token_stream.end()?;
+
+        fragments.retain(|f| !f.matches.is_empty());
+        for fragment in &mut fragments {
+            let distinct: HashSet<&[u8]> =
+                fragment.matches.iter().map(|m| m.term.as_slice()).collect();
+            fragment.score = distinct.len() as f32;
+        }
+        // stable, so fragments of the same score stay in text order
+        fragments.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        fragments.truncate(max_num_fragments);
+        Ok(fragments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::tests::TestCodec;
+    use crate::core::highlight::{COLORED_POST_TAGS, COLORED_PRE_TAGS};
+    use crate::core::index::Term;
+    use crate::core::search::query::{BooleanQuery, TermQuery};
+
+    #[test]
+    fn test_best_fragments() -> Result<()> {
+        let text = "the quick brown fox jumps over the lazy dog and the lazy cat \
+                    sleeps while the quick fox runs away";
+        let term_query = |text: &str| -> Box<dyn Query<TestCodec>> {
+            let term = Term::new("body".into(), text.as_bytes().to_vec());
+            Box::new(TermQuery::new(term, 1.0, None))
+        };
+        let query = BooleanQuery::build(
+            vec![],
+            vec![term_query("lazy"), term_query("fox")],
+            vec![],
+            vec![],
+            0,
+        )?;
+        let highlighter = Highlighter::default().with_fragment_size(40);
+
+        let fragments = highlighter.get_best_fragments(query.as_ref(), "body", text, 2)?;
+        assert_eq!(
+            fragments,
+            vec![
+                "the quick brown <b>fox</b> jumps over the <b>lazy</b>".to_string(),
+                "dog and the <b>lazy</b> cat sleeps while the".to_string(),
+            ]
+        );
+        let formatter =
+            PassageFormatter::default().with_multi_tags(&COLORED_PRE_TAGS, &COLORED_POST_TAGS)?;
+        let highlighter = highlighter.with_field_formatter("body", formatter);
+        let fragment = highlighter.get_best_fragment(query.as_ref(), "body", "a lazy fox <3")?;
+        assert_eq!(
+            fragment,
+            Some(
+                "a <b style=\"background:yellow\">lazy</b> \
+                 <b style=\"background:lawngreen\">fox</b> &lt;3"
+                    .to_string()
+            )
+        );
+        assert!(highlighter
+            .get_best_fragment(query.as_ref(), "title", text)?
+            .is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,805 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::codec::{Codec, PostingIterator, PostingIteratorFlags};
+use crate::core::doc::IndexOptions;
+use crate::core::highlight::{load_stored_text, query_terms, Encoder, SimpleHtmlEncoder};
+use crate::core::index::reader::{IndexReader, LeafReaderContext};
+use crate::core::index::Term;
+use crate::core::search::query::Query;
+use crate::core::search::DocIterator;
+use crate::core::util::DocId;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub const DEFAULT_MAX_LENGTH: usize = 10_000;
+pub const DEFAULT_MAX_PASSAGES: usize = 1;
+
+/// Where the `UnifiedHighlighter` reads the offsets of the query terms in a
+/// field from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OffsetSource {
+    /// The offsets indexed in the postings, the field must be indexed with
+    /// `IndexOptions::DocsAndFreqsAndPositionsAndOffsets`.
+    Postings,
+    /// The offsets stored in the term vectors of the document.
+    TermVectors,
+    /// Re-analysis of the stored text with the analyzer of the field.
+    Analysis,
+}
+
+impl OffsetSource {
+    /// The cheapest source that can provide offsets for the field: indexed
+    /// offsets, then term vectors, then re-analysis.
+    pub fn for_field(field_info: Option<&FieldInfo>) -> OffsetSource {
+        match field_info {
+            Some(info)
+                if info.index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets =>
+            {
+                OffsetSource::Postings
+            }
+            Some(info) if info.has_store_term_vector => OffsetSource::TermVectors,
+            _ => OffsetSource::Analysis,
+        }
+    }
+}
+
+/// A match of a query term in a `Passage`, offsets are in chars of the
+/// stored text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassageMatch {
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub term: Vec<u8>,
+    /// The index of the term among the distinct query terms of the field, it
+    /// selects the tags of the match.
+    pub term_index: usize,
+}
+
+/// A sentence of the stored text that contains matches of the query terms.
+#[derive(Debug, Clone, Default)]
+pub struct Passage {
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub score: f32,
+    /// The matches in the passage, ordered by start offset.
+    pub matches: Vec<PassageMatch>,
+}
+
+/// The passages of the highlighted field of a document, for callers that
+/// render their own markup instead of the snippet of a `PassageFormatter`.
+#[derive(Debug, Clone)]
+pub struct HighlightedField {
+    /// The stored text, up to the max length of the highlighter, the offsets
+    /// of the passages and their matches are char offsets into it.
+    pub content: String,
+    /// The best passages ordered by start offset, without matches if the
+    /// document had none and `highlight_no_match` is set.
+    pub passages: Vec<Passage>,
+}
+
+impl HighlightedField {
+    /// The text of `passage`.
+    pub fn passage_text(&self, passage: &Passage) -> String {
+        self.content
+            .chars()
+            .skip(passage.start_offset)
+            .take(passage.end_offset - passage.start_offset)
+            .collect()
+    }
+}
+
+/// Scores passages with a BM25 like formula, each passage is treated as a
+/// document of a collection formed by the passages of the field.
+#[derive(Debug, Copy, Clone)]
+pub struct PassageScorer {
+    /// term frequency saturation.
+    pub k1: f32,
+    /// length normalization.
+    pub b: f32,
+    /// average passage length, in chars.
+    pub pivot: f32,
+}
+
+impl Default for PassageScorer {
+    fn default() -> Self {
+        PassageScorer::new(1.2, 0.75, 87.0)
+    }
+}
+
+impl PassageScorer {
+    pub fn new(k1: f32, b: f32, pivot: f32) -> PassageScorer {
+        PassageScorer { k1, b, pivot }
+    }
+
+    /// The weight of a term occurring `total_term_freq` times in a text of
+    /// `content_length` chars.
+    pub fn weight(&self, content_length: usize, total_term_freq: usize) -> f32 {
+        let num_docs = 1.0 + (content_length as f32 / self.pivot).floor();
+        let doc_freq = num_docs.min(total_term_freq as f32);
+        (self.k1 + 1.0) * (1.0 + (num_docs + 0.5) / (doc_freq + 0.5)).ln()
+    }
+
+    /// The score contribution of a term occurring `freq` times in a passage of
+    /// `passage_length` chars.
+    pub fn tf(&self, freq: usize, passage_length: usize) -> f32 {
+        let norm = self.k1 * ((1.0 - self.b) + self.b * (passage_length as f32 / self.pivot));
+        freq as f32 / (freq as f32 + norm)
+    }
+
+    /// Boosts passages near the start of the text.
+    pub fn norm(&self, passage_start: usize) -> f32 {
+        1.0 + 1.0 / (self.pivot + passage_start as f32).ln()
+    }
+}
+
+/// Formats passages as a snippet, the matches are wrapped in tags and the text
+/// is escaped by the encoder, HTML by default.
+pub struct PassageFormatter {
+    pre_tags: Vec<String>,
+    post_tags: Vec<String>,
+    ellipsis: String,
+    encoder: Box<dyn Encoder>,
+}
+
+impl Default for PassageFormatter {
+    fn default() -> Self {
+        PassageFormatter {
+            pre_tags: vec!["<b>".to_string()],
+            post_tags: vec!["</b>".to_string()],
+            ellipsis: "... ".to_string(),
+            encoder: Box::new(SimpleHtmlEncoder),
+        }
+    }
+}
+
+impl PassageFormatter {
+    pub fn with_tags(mut self, pre_tag: &str, post_tag: &str) -> Self {
+        self.pre_tags = vec![pre_tag.to_string()];
+        self.post_tags = vec![post_tag.to_string()];
+        self
+    }
+
+    /// Sets a tag set per query term, the matches of the i-th term of a field
+    /// are wrapped in the i-th tags modulo the number of tags, e.g. to give
+    /// each term its own color with `COLORED_PRE_TAGS`.
+    pub fn with_multi_tags(mut self, pre_tags: &[&str], post_tags: &[&str]) -> Result<Self> {
+        if pre_tags.is_empty() || post_tags.is_empty() {
+            return Err(IllegalArgument(
+                "pre and post tags must not be empty".into(),
+            ));
+        }
+        self.pre_tags = pre_tags.iter().map(|t| t.to_string()).collect();
+        self.post_tags = post_tags.iter().map(|t| t.to_string()).collect();
+        Ok(self)
+    }
+
+    /// Sets the separator of non-adjacent passages.
+    pub fn with_ellipsis(mut self, ellipsis: &str) -> Self {
+        self.ellipsis = ellipsis.to_string();
+        self
+    }
+
+    pub fn with_encoder(mut self, encoder: Box<dyn Encoder>) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    /// Formats `passages`, ordered by start offset, of `content`.
+    pub fn format(&self, passages: &[Passage], content: &[char]) -> String {
+        let mut result = String::new();
+        let mut pos = 0;
+        for passage in passages {
+            if pos > 0 {
+                result.push_str(&self.ellipsis);
+            }
+            pos = passage.start_offset;
+            for m in &passage.matches {
+                // overlapping matches are highlighted as one
+                if m.start_offset < pos {
+                    if m.end_offset > pos {
+                        self.append(&mut result, &content[pos..m.end_offset]);
+                        pos = m.end_offset;
+                    }
+                    continue;
+                }
+                self.append(&mut result, &content[pos..m.start_offset]);
+                result.push_str(&self.pre_tags[m.term_index % self.pre_tags.len()]);
+                self.append(&mut result, &content[m.start_offset..m.end_offset]);
+                result.push_str(&self.post_tags[m.term_index % self.post_tags.len()]);
+                pos = m.end_offset;
+            }
+            self.append(&mut result, &content[pos..passage.end_offset.max(pos)]);
+            pos = passage.end_offset.max(pos);
+        }
+        result
+    }
+
+    fn append(&self, result: &mut String, chars: &[char]) {
+        let text: String = chars.iter().collect();
+        result.push_str(&self.encoder.encode_text(&text));
+    }
+}
+
+/// Per field settings of the `UnifiedHighlighter`, unset values fall back to
+/// the highlighter's.
+#[derive(Default)]
+pub struct FieldHighlightConfig {
+    offset_source: Option<OffsetSource>,
+    stored_field: Option<String>,
+    max_passages: Option<usize>,
+    analyzer: Option<Box<dyn Analyzer>>,
+    formatter: Option<PassageFormatter>,
+}
+
+impl FieldHighlightConfig {
+    /// Forces the offset source instead of choosing it from the field infos.
+    pub fn with_offset_source(mut self, offset_source: OffsetSource) -> Self {
+        self.offset_source = Some(offset_source);
+        self
+    }
+
+    /// Reads the text to highlight from another stored field, e.g. when the
+    /// indexed field isn't stored.
+    pub fn with_stored_field(mut self, stored_field: &str) -> Self {
+        self.stored_field = Some(stored_field.to_string());
+        self
+    }
+
+    pub fn with_max_passages(mut self, max_passages: usize) -> Self {
+        self.max_passages = Some(max_passages);
+        self
+    }
+
+    /// The analyzer used by `OffsetSource::Analysis`, it must produce the same
+    /// offsets as the one the field was indexed with.
+    pub fn with_analyzer<A: Analyzer + 'static>(mut self, analyzer: A) -> Self {
+        self.analyzer = Some(Box::new(analyzer));
+        self
+    }
+
+    /// Formats the snippets of the field, e.g. with its own tags.
+    pub fn with_formatter(mut self, formatter: PassageFormatter) -> Self {
+        self.formatter = Some(formatter);
+        self
+    }
+}
+
+/// A highlighter that builds snippets out of the best scoring passages
+/// (sentences) of a stored field.
+///
+/// The offsets of the query terms come, per field, from the postings if they
+/// were indexed with offsets, else from the term vectors, else from
+/// re-analyzing the stored text, see `OffsetSource`. Compared to
+/// `FastVectorHighlighter` no term vectors are required and the highlighter
+/// is configured once, with per field overrides, instead of per call.
+///
+/// ```rust,ignore
+/// let highlighter = UnifiedHighlighter::default()
+///     .with_max_passages(2)
+///     .with_field_config("title", FieldHighlightConfig::default().with_stored_field("title.raw"));
+/// let snippets = highlighter.highlight("title", query.as_ref(), searcher.reader(), &doc_ids)?;
+/// ```
+pub struct UnifiedHighlighter {
+    max_length: usize,
+    max_passages: usize,
+    highlight_no_match: bool,
+    scorer: PassageScorer,
+    formatter: PassageFormatter,
+    analyzer: Box<dyn Analyzer>,
+    field_configs: HashMap<String, FieldHighlightConfig>,
+}
+
+impl Default for UnifiedHighlighter {
+    fn default() -> Self {
+        UnifiedHighlighter {
+            max_length: DEFAULT_MAX_LENGTH,
+            max_passages: DEFAULT_MAX_PASSAGES,
+            highlight_no_match: true,
+            scorer: PassageScorer::default(),
+            formatter: PassageFormatter::default(),
+            analyzer: Box::new(WhitespaceAnalyzer),
+            field_configs: HashMap::new(),
+        }
+    }
+}
+
+impl UnifiedHighlighter {
+    /// Only the first `max_length` chars of a stored text are highlighted.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn with_max_passages(mut self, max_passages: usize) -> Self {
+        self.max_passages = max_passages;
+        self
+    }
+
+    /// Whether the leading passages are returned for documents without
+    /// matches, defaults to true.
+    pub fn with_highlight_no_match(mut self, highlight_no_match: bool) -> Self {
+        self.highlight_no_match = highlight_no_match;
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: PassageScorer) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: PassageFormatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// The analyzer of the fields without one in their config.
+    pub fn with_analyzer<A: Analyzer + 'static>(mut self, analyzer: A) -> Self {
+        self.analyzer = Box::new(analyzer);
+        self
+    }
+
+    pub fn with_field_config(mut self, field: &str, config: FieldHighlightConfig) -> Self {
+        self.field_configs.insert(field.to_string(), config);
+        self
+    }
+
+    /// The offset source used for `field` in the segment of `leaf`.
+    pub fn offset_source<C: Codec>(
+        &self,
+        leaf: &LeafReaderContext<'_, C>,
+        field: &str,
+    ) -> OffsetSource {
+        self.field_configs
+            .get(field)
+            .and_then(|c| c.offset_source)
+            .unwrap_or_else(|| OffsetSource::for_field(leaf.reader.field_info(field)))
+    }
+
+    /// Highlights `field` of each of `doc_ids`, the snippet of a document is
+    /// `None` if it has no stored text or, unless `highlight_no_match`, no
+    /// matches.
+    pub fn highlight<C: Codec>(
+        &self,
+        field: &str,
+        query: &dyn Query<C>,
+        reader: &dyn IndexReader<Codec = C>,
+        doc_ids: &[DocId],
+    ) -> Result<Vec<Option<String>>> {
+        let terms = query_terms(field, query);
+        let mut snippets = Vec::with_capacity(doc_ids.len());
+        for &doc_id in doc_ids {
+            snippets.push(self.highlight_doc(field, &terms, reader, doc_id)?);
+        }
+        Ok(snippets)
+    }
+
+    /// Like `highlight` but returns the passages and their matches instead of
+    /// formatted snippets.
+    pub fn highlight_passages<C: Codec>(
+        &self,
+        field: &str,
+        query: &dyn Query<C>,
+        reader: &dyn IndexReader<Codec = C>,
+        doc_ids: &[DocId],
+    ) -> Result<Vec<Option<HighlightedField>>> {
+        let terms = query_terms(field, query);
+        let mut highlights = Vec::with_capacity(doc_ids.len());
+        for &doc_id in doc_ids {
+            let highlight =
+                self.doc_passages(field, &terms, reader, doc_id)?
+                    .map(|(content, passages)| HighlightedField {
+                        content: content.into_iter().collect(),
+                        passages,
+                    });
+            highlights.push(highlight);
+        }
+        Ok(highlights)
+    }
+
+    /// Highlights each of `fields`, see `highlight`.
+    pub fn highlight_fields<C: Codec>(
+        &self,
+        fields: &[&str],
+        query: &dyn Query<C>,
+        reader: &dyn IndexReader<Codec = C>,
+        doc_ids: &[DocId],
+    ) -> Result<HashMap<String, Vec<Option<String>>>> {
+        let mut result = HashMap::with_capacity(fields.len());
+        for field in fields {
+            let snippets = self.highlight(field, query, reader, doc_ids)?;
+            result.insert(field.to_string(), snippets);
+        }
+        Ok(result)
+    }
+
+    fn highlight_doc<C: Codec>(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        reader: &dyn IndexReader<Codec = C>,
+        doc_id: DocId,
+    ) -> Result<Option<String>> {
+        let (content, passages) = match self.doc_passages(field, terms, reader, doc_id)? {
+            Some(passages) => passages,
+            None => return Ok(None),
+        };
+        let formatter = self
+            .field_configs
+            .get(field)
+            .and_then(|c| c.formatter.as_ref())
+            .unwrap_or(&self.formatter);
+        Ok(Some(formatter.format(&passages, &content)))
+    }
+
+    // Returns the chars of the stored text and the passages to highlight.
+    fn doc_passages<C: Codec>(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        reader: &dyn IndexReader<Codec = C>,
+        doc_id: DocId,
+    ) -> Result<Option<(Vec<char>, Vec<Passage>)>> {
+        let config = self.field_configs.get(field);
+        let stored_field = config
+            .and_then(|c| c.stored_field.as_deref())
+            .unwrap_or(field);
+        let content = match load_stored_text(reader, doc_id, stored_field)? {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let content: Vec<char> = content.chars().take(self.max_length).collect();
+        let max_passages = config
+            .and_then(|c| c.max_passages)
+            .unwrap_or(self.max_passages);
+
+        let mut matches = if terms.is_empty() {
+            vec![]
+        } else {
+            let leaf = reader.leaf_reader_for_doc(doc_id);
+            let indexed = match self.offset_source(&leaf, field) {
+                OffsetSource::Postings => Self::postings_matches(&leaf, field, terms, doc_id)?,
+                OffsetSource::TermVectors => {
+                    Self::term_vector_matches(reader, field, terms, doc_id)?
+                }
+                OffsetSource::Analysis => None,
+            };
+            match indexed {
+                Some(matches) => matches,
+                // offsets weren't indexed after all, fall back to re-analysis
+                None => self.analysis_matches(field, terms, &content)?,
+            }
+        };
+        matches.retain(|m| m.end_offset <= content.len() && m.start_offset < m.end_offset);
+        matches.sort_by(|a, b| {
+            a.start_offset
+                .cmp(&b.start_offset)
+                .then(b.end_offset.cmp(&a.end_offset))
+        });
+
+        let mut passages = self.best_passages(&content, matches, max_passages);
+        if passages.is_empty() {
+            if !self.highlight_no_match {
+                return Ok(None);
+            }
+            passages = leading_passages(&content, max_passages);
+        }
+        Ok(Some((content, passages)))
+    }
+
+    // Returns `None` if the postings have no offsets.
+    fn postings_matches<C: Codec>(
+        leaf: &LeafReaderContext<'_, C>,
+        field: &str,
+        terms: &[Vec<u8>],
+        doc_id: DocId,
+    ) -> Result<Option<Vec<PassageMatch>>> {
+        let leaf_doc = doc_id - leaf.doc_base;
+        let mut matches = vec![];
+        for (term_index, term) in terms.iter().enumerate() {
+            let term = Term::new(field.to_string(), term.clone());
+            let mut postings = match leaf
+                .reader
+                .postings(&term, i32::from(PostingIteratorFlags::OFFSETS))?
+            {
+                Some(postings) => postings,
+                None => continue,
+            };
+            if postings.advance(leaf_doc)? != leaf_doc {
+                continue;
+            }
+            for _ in 0..postings.freq()? {
+                postings.next_position()?;
+                let (start, end) = (postings.start_offset()?, postings.end_offset()?);
+                if start < 0 {
+                    return Ok(None);
+                }
+                matches.push(PassageMatch {
+                    start_offset: start as usize,
+                    end_offset: end as usize,
+                    term: term.bytes.clone(),
+                    term_index,
+                });
+            }
+        }
+        Ok(Some(matches))
+    }
+
+    // Returns `None` if the document has no term vector with offsets.
+    fn term_vector_matches<C: Codec>(
+        reader: &dyn IndexReader<Codec = C>,
+        field: &str,
+        terms: &[Vec<u8>],
+        doc_id: DocId,
+    ) -> Result<Option<Vec<PassageMatch>>> {
+        let term_vector = match reader.get_term_vector(doc_id, field)? {
+            Some(term_vector) if term_vector.has_offsets => term_vector,
+            _ => return Ok(None),
+        };
+        let mut matches = vec![];
+        for tv_term in &term_vector.terms {
+            let term_index = match terms.iter().position(|t| *t == tv_term.term) {
+                Some(term_index) => term_index,
+                None => continue,
+            };
+            for occurrence in &tv_term.occurrences {
+                if let (Some(start), Some(end)) = (occurrence.start_offset, occurrence.end_offset) {
+                    matches.push(PassageMatch {
+                        start_offset: start as usize,
+                        end_offset: end as usize,
+                        term: tv_term.term.clone(),
+                        term_index,
+                    });
+                }
+            }
+        }
+        Ok(Some(matches))
+    }
+
+    fn analysis_matches(
+        &self,
+        field: &str,
+        terms: &[Vec<u8>],
+        content: &[char],
+    ) -> Result<Vec<PassageMatch>> {
+        let analyzer = self
+            .field_configs
+            .get(field)
+            .and_then(|c| c.analyzer.as_deref())
+            .unwrap_or_else(|| self.analyzer.as_ref());
+        let text: String = content.iter().collect();
+        let mut token_stream = analyzer.token_stream(field, &text)?;
+        token_stream.reset()?;
+        let mut matches = vec![];
+        while token_stream.next_token()? {
+            let token = token_stream.token();
+            if let Some(term_index) = terms.iter().position(|t| *t == token.term) {
+                matches.push(PassageMatch {
+                    start_offset: token.start_offset,
+                    end_offset: token.end_offset,
+                    term: token.term.clone(),
+                    term_index,
+                });
+            }
+        }
+        token_stream.end()?;
+        Ok(matches)
+    }
+
+    // `matches` must be sorted by start offset, returns the top `max_passages`
+    // passages ordered by start offset.
+    fn best_passages(
+        &self,
+        content: &[char],
+        matches: Vec<PassageMatch>,
+        max_passages: usize,
+    ) -> Vec<Passage> {
+        let mut term_freqs: HashMap<&[u8], usize> = HashMap::new();
+        for m in &matches {
+            *term_freqs.entry(m.term.as_slice()).or_insert(0) += 1;
+        }
+        let weights: HashMap<&[u8], f32> = term_freqs
+            .iter()
+            .map(|(term, freq)| (*term, self.scorer.weight(content.len(), *freq)))
+            .collect();
+
+        let mut passages: Vec<Passage> = vec![];
+        for m in &matches {
+            if let Some(passage) = passages.last_mut() {
+                if m.start_offset < passage.end_offset {
+                    passage.end_offset = passage.end_offset.max(m.end_offset);
+                    passage.matches.push(m.clone());
+                    continue;
+                }
+            }
+            passages.push(Passage {
+                start_offset: sentence_start(content, m.start_offset),
+                end_offset: sentence_end(content, m.end_offset),
+                score: 0.0,
+                matches: vec![m.clone()],
+            });
+        }
+
+        for passage in &mut passages {
+            let mut freqs: HashMap<&[u8], usize> = HashMap::new();
+            for m in &passage.matches {
+                *freqs.entry(m.term.as_slice()).or_insert(0) += 1;
+            }
+            let length = passage.end_offset - passage.start_offset;
+            let score: f32 = freqs
+                .iter()
+                .map(|(term, freq)| weights[term] * self.scorer.tf(*freq, length))
+                .sum();
+            passage.score = score * self.scorer.norm(passage.start_offset);
+        }
+
+        passages.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then(a.start_offset.cmp(&b.start_offset))
+        });
+        passages.truncate(max_passages);
+        passages.sort_by_key(|p| p.start_offset);
+        passages
+    }
+}
+
+// The first `max_passages` sentences, used as the snippet of a document
+// without matches.
+fn leading_passages(content: &[char], max_passages: usize) -> Vec<Passage> {
+    let mut passages = Vec::with_capacity(max_passages);
+    let mut pos = 0;
+    while passages.len() < max_passages {
+        while pos < content.len() && content[pos].is_whitespace() {
+            pos += 1;
+        }
+        if pos >= content.len() {
+            break;
+        }
+        let end = sentence_end(content, pos + 1);
+        passages.push(Passage {
+            start_offset: pos,
+            end_offset: end,
+            score: 0.0,
+            matches: vec![],
+        });
+        pos = end;
+    }
+    passages
+}
+
+// Whether a sentence ends right before `content[i]`.
+fn is_sentence_boundary(content: &[char], i: usize) -> bool {
+    i == 0
+        || i >= content.len()
+        || content[i - 1] == '\n'
+        || (content[i].is_whitespace() && matches!(content[i - 1], '.' | '!' | '?'))
+}
+
+fn sentence_start(content: &[char], offset: usize) -> usize {
+    let mut start = offset.min(content.len());
+    while !is_sentence_boundary(content, start) {
+        start -= 1;
+    }
+    while start < offset && content[start].is_whitespace() {
+        start += 1;
+    }
+    start
+}
+
+fn sentence_end(content: &[char], offset: usize) -> usize {
+    let mut end = offset.min(content.len());
+    while !is_sentence_boundary(content, end) {
+        end += 1;
+    }
+    while end > offset && content[end - 1].is_whitespace() {
+        end -= 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::highlight::DefaultEncoder;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn new_match(
+        start_offset: usize,
+        end_offset: usize,
+        term: &str,
+        term_index: usize,
+    ) -> PassageMatch {
+        PassageMatch {
+            start_offset,
+            end_offset,
+            term: term.as_bytes().to_vec(),
+            term_index,
+        }
+    }
+
+    #[test]
+    fn test_sentences() {
+        let content = chars("One two. Three four!  Five\nsix");
+        assert_eq!(sentence_start(&content, 4), 0);
+        assert_eq!(sentence_end(&content, 4), 8);
+        assert_eq!(sentence_start(&content, 15), 9);
+        assert_eq!(sentence_end(&content, 15), 20);
+        assert_eq!(sentence_start(&content, 28), 27);
+        assert_eq!(sentence_end(&content, 28), 30);
+
+        let passages = leading_passages(&content, 2);
+        assert_eq!(passages.len(), 2);
+        assert_eq!((passages[0].start_offset, passages[0].end_offset), (0, 8));
+        assert_eq!((passages[1].start_offset, passages[1].end_offset), (9, 20));
+    }
+
+    #[test]
+    fn test_best_passages() {
+        let text = "A dog. The cat & the cat. No pets here.";
+        let content = chars(text);
+        let matches = vec![
+            new_match(2, 5, "dog", 0),
+            new_match(11, 14, "cat", 1),
+            new_match(21, 24, "cat", 1),
+        ];
+        let highlighter = UnifiedHighlighter::default();
+
+        let passages = highlighter.best_passages(&content, matches.clone(), 1);
+        assert_eq!(passages.len(), 1);
+        assert_eq!((passages[0].start_offset, passages[0].end_offset), (7, 25));
+        assert_eq!(passages[0].matches.len(), 2);
+        assert_eq!(
+            highlighter.formatter.format(&passages, &content),
+            "The <b>cat</b> &amp; the <b>cat</b>."
+        );
+
+        let passages = highlighter.best_passages(&content, matches, 3);
+        assert_eq!(passages.len(), 2);
+        assert!(passages[0].start_offset < passages[1].start_offset);
+        let formatter = PassageFormatter::default()
+            .with_multi_tags(&["<em>", "<i>"], &["</em>", "</i>"])
+            .unwrap()
+            .with_ellipsis(" … ")
+            .with_encoder(Box::new(DefaultEncoder));
+        assert_eq!(
+            formatter.format(&passages, &content),
+            "A <em>dog</em>. … The <i>cat</i> & the <i>cat</i>."
+        );
+        assert!(PassageFormatter::default()
+            .with_multi_tags(&[], &["</em>"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_analysis_matches() -> Result<()> {
+        let highlighter = UnifiedHighlighter::default();
+        let content = chars("héllo wörld, hello world");
+        let terms = vec![b"world".to_vec(), "héllo".as_bytes().to_vec()];
+        let matches = highlighter.analysis_matches("body", &terms, &content)?;
+        assert_eq!(
+            matches,
+            vec![new_match(0, 5, "héllo", 1), new_match(19, 24, "world", 0)]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,959 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::NO_MORE_ORDS;
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::points::{IntersectVisitor, PointValues, Relation};
+use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
+use crate::core::codec::{checksum_entire_file, Codec, Fields, PostingIteratorFlags};
+use crate::core::codec::{PostingIterator, TermIterator, Terms};
+use crate::core::doc::{DocValuesType, IndexOptions, Status as VisitStatus, StoredFieldVisitor};
+use crate::core::index::reader::{LeafReader, SegmentReader};
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::store::IOContext;
+use crate::core::util::DocId;
+
+use crate::error::Error::{CorruptIndex, IllegalState};
+use crate::error::Result;
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// The result of `CheckIndex::check_index`.
+#[derive(Debug, Default)]
+pub struct Status {
+    /// True if no problems were found with the index.
+    pub clean: bool,
+    /// True if the segments_N file couldn't be read, nothing else was checked.
+    pub missing_segments: bool,
+    /// The segments_N file that was checked.
+    pub segments_file_name: Option<String>,
+    /// Number of segments in the index.
+    pub num_segments: usize,
+    /// Status of each segment, in the order of the commit.
+    pub segment_infos: Vec<SegmentStatus>,
+    /// Number of bad segments.
+    pub num_bad_segments: usize,
+    /// Number of documents that would be lost if `exorcise_index` is called.
+    pub tot_lose_doc_count: i32,
+    /// The user data stored with the commit.
+    pub user_data: HashMap<String, String>,
+}
+
+/// The check result of a single segment.
+#[derive(Debug, Default)]
+pub struct SegmentStatus {
+    pub name: String,
+    pub codec: String,
+    pub max_doc: i32,
+    pub compound: bool,
+    pub num_files: usize,
+    pub size_in_bytes: i64,
+    pub has_deletions: bool,
+    /// Number of deleted docs according to the commit.
+    pub del_count: i32,
+    pub diagnostics: HashMap<String, String>,
+    /// None if the check of the part failed or, except for the checksums,
+    /// was skipped because only the checksums were checked.
+    pub checksums: Option<ChecksumStatus>,
+    pub live_docs: Option<LiveDocStatus>,
+    pub field_infos: Option<FieldInfoStatus>,
+    pub field_norms: Option<FieldNormStatus>,
+    pub postings: Option<TermIndexStatus>,
+    pub stored_fields: Option<StoredFieldStatus>,
+    pub term_vectors: Option<TermVectorStatus>,
+    pub doc_values: Option<DocValuesStatus>,
+    pub points: Option<PointsStatus>,
+    /// The failures found in this segment, one per failed part.
+    pub errors: Vec<String>,
+}
+
+impl SegmentStatus {
+    /// True if no problems were found with this segment.
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChecksumStatus {
+    pub num_files: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct LiveDocStatus {
+    pub num_deleted: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct FieldInfoStatus {
+    pub tot_fields: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct FieldNormStatus {
+    pub tot_fields: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct TermIndexStatus {
+    pub term_count: i64,
+    pub del_term_count: i64,
+    pub tot_freq: i64,
+    pub tot_pos: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct StoredFieldStatus {
+    pub doc_count: i32,
+    pub tot_fields: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct TermVectorStatus {
+    pub doc_count: i32,
+    pub tot_vector_fields: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct DocValuesStatus {
+    pub tot_numeric_fields: usize,
+    pub tot_binary_fields: usize,
+    pub tot_sorted_fields: usize,
+    pub tot_sorted_numeric_fields: usize,
+    pub tot_sorted_set_fields: usize,
+    pub tot_value_fields: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct PointsStatus {
+    pub tot_value_fields: usize,
+    pub tot_value_points: i64,
+}
+
+/// Basic tool and API to check the health of an index and to remove the
+/// broken segments of a corrupted one.
+///
+/// Every segment of the latest commit is opened and each of its parts,
+/// the checksums of all the files, the live docs, the field infos, the
+/// norms, the postings, the stored fields, the term vectors, the doc values
+/// and the points, is fully read and cross checked. The problems are
+/// reported per segment in the returned `Status`.
+///
+/// `exorcise_index` writes a new commit without the broken segments, so all
+/// the documents of those segments are lost. It must not be called while an
+/// `IndexWriter` is open on the directory, and it's a good idea to back up
+/// the index first.
+pub struct CheckIndex<D: Directory, C: Codec> {
+    directory: Arc<D>,
+    checksums_only: bool,
+    _codec: PhantomData<C>,
+}
+
+impl<D: Directory + Send + Sync + 'static, C: Codec> CheckIndex<D, C> {
+    pub fn new(directory: Arc<D>) -> Self {
+        CheckIndex {
+            directory,
+            checksums_only: false,
+            _codec: PhantomData,
+        }
+    }
+
+    /// If true, only the checksums of the segment files are verified, which
+    /// is much faster but doesn't detect bugs that wrote invalid data.
+    pub fn set_checksums_only(&mut self, checksums_only: bool) {
+        self.checksums_only = checksums_only;
+    }
+
+    pub fn checksums_only(&self) -> bool {
+        self.checksums_only
+    }
+
+    /// Verifies the checksum footers of the latest segments_N file and of
+    /// all the files of the segments it references, without decoding them.
+    ///
+    /// Unlike `check_index`, the first corruption found is returned as a
+    /// `CorruptIndex` error. Returns the number of files verified.
+    pub fn verify_checksums(&self) -> Result<usize> {
+        let infos = SegmentInfos::<D, C>::read_latest_commit(&self.directory)?;
+        let mut num_files = 0;
+        if let Some(file) = infos.segment_file_name() {
+            let input = self.directory.open_input(&file, &IOContext::READ_ONCE)?;
+            checksum_entire_file(input.as_ref())
+                .map_err(|e| CorruptIndex(format!("file {}: {}", file, e)))?;
+            num_files += 1;
+        }
+        for info in &infos.segments {
+            num_files += info.check_integrity()?;
+        }
+        Ok(num_files)
+    }
+
+    /// Checks all the segments of the latest commit.
+    ///
+    /// The corruptions found are reported in the returned `Status`, an error
+    /// is only returned if the index couldn't be checked at all.
+    pub fn check_index(&self) -> Result<Status> {
+        let mut result = Status::default();
+        let infos = match SegmentInfos::<D, C>::read_latest_commit(&self.directory) {
+            Ok(infos) => infos,
+            Err(e) => {
+                log::error!("could not read any segments file in directory: {:?}", e);
+                result.missing_segments = true;
+                return Ok(result);
+            }
+        };
+
+        result.segments_file_name = infos.segment_file_name();
+        result.num_segments = infos.len();
+        result.user_data = infos.user_data.clone();
+        for info in &infos.segments {
+            let status = self.check_segment(info);
+            if !status.is_clean() {
+                log::warn!(
+                    "segment {} is broken, {} documents would be lost: {:?}",
+                    status.name,
+                    status.max_doc - status.del_count,
+                    status.errors
+                );
+                result.num_bad_segments += 1;
+                result.tot_lose_doc_count += status.max_doc - status.del_count;
+            }
+            result.segment_infos.push(status);
+        }
+        result.clean = result.num_bad_segments == 0;
+        Ok(result)
+    }
+
+    fn check_segment(&self, info: &Arc<SegmentCommitInfo<D, C>>) -> SegmentStatus {
+        let mut status = SegmentStatus {
+            name: info.info.name.clone(),
+            codec: info.info.codec().name().to_string(),
+            max_doc: info.info.max_doc(),
+            compound: info.info.is_compound_file(),
+            num_files: info.files().len(),
+            size_in_bytes: info.size_in_bytes(),
+            has_deletions: info.has_deletions(),
+            del_count: info.del_count(),
+            diagnostics: info.info.diagnostics.clone(),
+            ..Default::default()
+        };
+        log::info!(
+            "checking segment {}: max_doc={}, del_count={}, files={}",
+            status.name,
+            status.max_doc,
+            status.del_count,
+            status.num_files
+        );
+
+        match self.check_checksums(info) {
+            Ok(s) => status.checksums = Some(s),
+            Err(e) => status.errors.push(format!("checksums: {}", e)),
+        }
+        if self.checksums_only {
+            return status;
+        }
+
+        let reader = match SegmentReader::open(info, &IOContext::READ) {
+            Ok(reader) => reader,
+            Err(e) => {
+                status.errors.push(format!("open reader: {}", e));
+                return status;
+            }
+        };
+
+        fn record<T>(part: &str, res: Result<T>, slot: &mut Option<T>, errors: &mut Vec<String>) {
+            match res {
+                Ok(s) => *slot = Some(s),
+                Err(e) => errors.push(format!("{}: {}", part, e)),
+            }
+        }
+        let errors = &mut status.errors;
+        record(
+            "live docs",
+            check_live_docs(&reader, info),
+            &mut status.live_docs,
+            errors,
+        );
+        record(
+            "field infos",
+            check_field_infos(reader.field_infos()),
+            &mut status.field_infos,
+            errors,
+        );
+        record(
+            "field norms",
+            check_norms(&reader),
+            &mut status.field_norms,
+            errors,
+        );
+        record(
+            "postings",
+            check_postings(&reader),
+            &mut status.postings,
+            errors,
+        );
+        record(
+            "stored fields",
+            check_stored_fields(&reader),
+            &mut status.stored_fields,
+            errors,
+        );
+        record(
+            "term vectors",
+            check_term_vectors(&reader),
+            &mut status.term_vectors,
+            errors,
+        );
+        record(
+            "doc values",
+            check_doc_values(&reader),
+            &mut status.doc_values,
+            errors,
+        );
+        record("points", check_points(&reader), &mut status.points, errors);
+        status
+    }
+
+    fn check_checksums(&self, info: &SegmentCommitInfo<D, C>) -> Result<ChecksumStatus> {
+        let num_files = info.check_integrity()?;
+        Ok(ChecksumStatus { num_files })
+    }
+
+    /// Writes a new commit that drops the broken segments of `result`,
+    /// losing all their documents.
+    ///
+    /// `result` must be the status of the latest commit, returns an error if
+    /// the index changed since it was checked.
+    pub fn exorcise_index(&self, result: &Status) -> Result<()> {
+        if result.missing_segments {
+            return Err(IllegalState(
+                "can't exorcise an index whose segments file couldn't be read".into(),
+            ));
+        }
+        let mut infos = SegmentInfos::<D, C>::read_latest_commit(&self.directory)?;
+        if infos.segment_file_name() != result.segments_file_name {
+            return Err(IllegalState(format!(
+                "the index changed since it was checked: latest commit is {:?}, checked {:?}",
+                infos.segment_file_name(),
+                result.segments_file_name
+            )));
+        }
+        if result.clean {
+            return Ok(());
+        }
+
+        infos.segments.retain(|si| {
+            result
+                .segment_infos
+                .iter()
+                .all(|s| s.name != si.info.name || s.is_clean())
+        });
+        log::warn!(
+            "writing new segments file, removing {} bad segments and {} documents",
+            result.num_bad_segments,
+            result.tot_lose_doc_count
+        );
+        infos.changed();
+        infos.prepare_commit(self.directory.as_ref())?;
+        infos.finish_commit(self.directory.as_ref())?;
+        Ok(())
+    }
+}
+
+fn check_live_docs<D, C>(
+    reader: &SegmentReader<D, C>,
+    info: &SegmentCommitInfo<D, C>,
+) -> Result<LiveDocStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let max_doc = reader.max_doc();
+    let live_docs = reader.live_docs();
+    if live_docs.len() != max_doc as usize {
+        return Err(CorruptIndex(format!(
+            "live docs length {} doesn't match max_doc {}",
+            live_docs.len(),
+            max_doc
+        )));
+    }
+    let num_deleted = (0..max_doc as usize).filter(|&i| !live_docs.get(i)).count() as i32;
+    if num_deleted != info.del_count() {
+        return Err(CorruptIndex(format!(
+            "live docs count mismatch: {} deleted docs but del_count is {}",
+            num_deleted,
+            info.del_count()
+        )));
+    }
+    if reader.num_docs() != max_doc - num_deleted {
+        return Err(CorruptIndex(format!(
+            "num_docs {} doesn't match max_doc {} - deleted {}",
+            reader.num_docs(),
+            max_doc,
+            num_deleted
+        )));
+    }
+    Ok(LiveDocStatus { num_deleted })
+}
+
+fn check_field_infos(field_infos: &FieldInfos) -> Result<FieldInfoStatus> {
+    for fi in field_infos.by_number.values() {
+        fi.check_consistency()?;
+    }
+    Ok(FieldInfoStatus {
+        tot_fields: field_infos.len(),
+    })
+}
+
+fn check_norms<D, C>(reader: &SegmentReader<D, C>) -> Result<FieldNormStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = FieldNormStatus::default();
+    for fi in reader.field_infos().by_number.values() {
+        if !fi.has_norms() {
+            continue;
+        }
+        let norms = reader
+            .norm_values(&fi.name)?
+            .ok_or_else(|| CorruptIndex(format!("field {} should have norms", fi.name)))?;
+        for doc in 0..reader.max_doc() {
+            norms.get(doc)?;
+        }
+        status.tot_fields += 1;
+    }
+    Ok(status)
+}
+
+fn check_postings<D, C>(reader: &SegmentReader<D, C>) -> Result<TermIndexStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = TermIndexStatus::default();
+    let fields = reader.fields()?;
+    let live_docs = reader.live_docs();
+    for field in fields.fields() {
+        let fi = reader.field_info(&field).ok_or_else(|| {
+            CorruptIndex(format!("field {} has postings but no field info", field))
+        })?;
+        if fi.index_options == IndexOptions::Null {
+            return Err(CorruptIndex(format!(
+                "field {} has postings but isn't indexed",
+                field
+            )));
+        }
+        if let Some(terms) = fields.terms(&field)? {
+            check_terms(
+                &field,
+                &terms,
+                Some(fi),
+                reader.max_doc(),
+                &|doc| live_docs.get(doc as usize),
+                &mut status,
+            )?;
+        }
+    }
+    Ok(status)
+}
+
+/// Fully reads the terms of `field` and their postings and cross checks
+/// them with the statistics of `terms`.
+fn check_terms<T: Terms>(
+    field: &str,
+    terms: &T,
+    fi: Option<&FieldInfo>,
+    max_doc: DocId,
+    is_live: &dyn Fn(DocId) -> bool,
+    status: &mut TermIndexStatus,
+) -> Result<()> {
+    let has_freqs = terms.has_freqs()?;
+    let has_positions = terms.has_positions()?;
+    let has_offsets = terms.has_offsets()?;
+    if let Some(fi) = fi {
+        if has_freqs != fi.index_options.has_freqs()
+            || has_positions != fi.index_options.has_positions()
+            || has_offsets != fi.index_options.has_offsets()
+        {
+            return Err(CorruptIndex(format!(
+                "field {}: terms options don't match index options {:?}",
+                field, fi.index_options
+            )));
+        }
+    }
+    let flags = if has_offsets {
+        PostingIteratorFlags::OFFSETS
+    } else if has_positions {
+        PostingIteratorFlags::POSITIONS
+    } else if has_freqs {
+        PostingIteratorFlags::FREQS
+    } else {
+        PostingIteratorFlags::NONE
+    };
+
+    let mut term_count = 0i64;
+    let mut sum_doc_freq = 0i64;
+    let mut sum_total_term_freq = 0i64;
+    let mut last_term: Option<Vec<u8>> = None;
+    let mut iter = terms.iterator()?;
+    while let Some(term) = iter.next()? {
+        if let Some(ref last) = last_term {
+            if *last >= term {
+                return Err(CorruptIndex(format!(
+                    "field {}: terms out of order: {:?} >= {:?}",
+                    field, last, term
+                )));
+            }
+        }
+        term_count += 1;
+
+        let doc_freq = iter.doc_freq()?;
+        if doc_freq <= 0 {
+            return Err(CorruptIndex(format!(
+                "field {}, term {:?}: doc_freq {} is out of bounds",
+                field, term, doc_freq
+            )));
+        }
+        let mut postings = iter.postings_with_flags(flags)?;
+        let mut doc_count = 0;
+        let mut live_doc_count = 0;
+        let mut total_term_freq = 0i64;
+        let mut last_doc = -1;
+        loop {
+            let doc = postings.next()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            if doc <= last_doc || doc >= max_doc {
+                return Err(CorruptIndex(format!(
+                    "field {}, term {:?}: doc {} is out of order or bounds (last doc {}, max_doc \
+                     {})",
+                    field, term, doc, last_doc, max_doc
+                )));
+            }
+            last_doc = doc;
+            doc_count += 1;
+            if is_live(doc) {
+                live_doc_count += 1;
+            }
+            if !has_freqs {
+                continue;
+            }
+
+            let freq = postings.freq()?;
+            if freq <= 0 {
+                return Err(CorruptIndex(format!(
+                    "field {}, term {:?}, doc {}: freq {} is out of bounds",
+                    field, term, doc, freq
+                )));
+            }
+            total_term_freq += i64::from(freq);
+            if !has_positions {
+                continue;
+            }
+            let mut last_pos = -1;
+            let mut last_offset = 0;
+            for _ in 0..freq {
+                let pos = postings.next_position()?;
+                if pos < 0 || pos < last_pos {
+                    return Err(CorruptIndex(format!(
+                        "field {}, term {:?}, doc {}: position {} is out of order or bounds (last \
+                         position {})",
+                        field, term, doc, pos, last_pos
+                    )));
+                }
+                last_pos = pos;
+                status.tot_pos += 1;
+                if has_offsets {
+                    let start = postings.start_offset()?;
+                    let end = postings.end_offset()?;
+                    if start < last_offset || end < start {
+                        return Err(CorruptIndex(format!(
+                            "field {}, term {:?}, doc {}: offsets [{}, {}) are out of order (last \
+                             start offset {})",
+                            field, term, doc, start, end, last_offset
+                        )));
+                    }
+                    last_offset = start;
+                }
+            }
+        }
+
+        if doc_count != doc_freq {
+            return Err(CorruptIndex(format!(
+                "field {}, term {:?}: doc_freq {} != number of docs {}",
+                field, term, doc_freq, doc_count
+            )));
+        }
+        if has_freqs {
+            let expected = iter.total_term_freq()?;
+            if expected != total_term_freq {
+                return Err(CorruptIndex(format!(
+                    "field {}, term {:?}: total_term_freq {} != sum of freqs {}",
+                    field, term, expected, total_term_freq
+                )));
+            }
+        }
+        if live_doc_count == 0 {
+            status.del_term_count += 1;
+        }
+        sum_doc_freq += i64::from(doc_freq);
+        sum_total_term_freq += total_term_freq;
+        status.tot_freq += i64::from(doc_freq);
+        last_term = Some(term);
+    }
+
+    let size = terms.size()?;
+    if size != -1 && size != term_count {
+        return Err(CorruptIndex(format!(
+            "field {}: size {} != number of terms {}",
+            field, size, term_count
+        )));
+    }
+    let expected = terms.sum_doc_freq()?;
+    if expected != -1 && expected != sum_doc_freq {
+        return Err(CorruptIndex(format!(
+            "field {}: sum_doc_freq {} != sum of doc freqs {}",
+            field, expected, sum_doc_freq
+        )));
+    }
+    if has_freqs {
+        let expected = terms.sum_total_term_freq()?;
+        if expected != -1 && expected != sum_total_term_freq {
+            return Err(CorruptIndex(format!(
+                "field {}: sum_total_term_freq {} != sum of term freqs {}",
+                field, expected, sum_total_term_freq
+            )));
+        }
+    }
+    status.term_count += term_count;
+    Ok(())
+}
+
+struct FieldCountVisitor {
+    count: i64,
+}
+
+impl StoredFieldVisitor for FieldCountVisitor {
+    fn add_binary_field(&mut self, _field_info: &FieldInfo, _value: Vec<u8>) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_string_field(&mut self, _field_info: &FieldInfo, value: Vec<u8>) -> Result<()> {
+        String::from_utf8(value)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_int_field(&mut self, _field_info: &FieldInfo, _value: i32) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_long_field(&mut self, _field_info: &FieldInfo, _value: i64) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_float_field(&mut self, _field_info: &FieldInfo, _value: f32) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn add_double_field(&mut self, _field_info: &FieldInfo, _value: f64) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn needs_field(&self, _field_info: &FieldInfo) -> VisitStatus {
+        VisitStatus::Yes
+    }
+}
+
+fn check_stored_fields<D, C>(reader: &SegmentReader<D, C>) -> Result<StoredFieldStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = StoredFieldStatus::default();
+    let live_docs = reader.live_docs();
+    for doc in 0..reader.max_doc() {
+        // deleted docs are read too, merges still visit them
+        let mut visitor = FieldCountVisitor { count: 0 };
+        reader.document(doc, &mut visitor)?;
+        if live_docs.get(doc as usize) {
+            status.doc_count += 1;
+            status.tot_fields += visitor.count;
+        }
+    }
+    if status.doc_count != reader.num_docs() {
+        return Err(CorruptIndex(format!(
+            "doc count {} != num_docs {}",
+            status.doc_count,
+            reader.num_docs()
+        )));
+    }
+    Ok(status)
+}
+
+fn check_term_vectors<D, C>(reader: &SegmentReader<D, C>) -> Result<TermVectorStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = TermVectorStatus::default();
+    if !reader.field_infos().has_vectors {
+        return Ok(status);
+    }
+    let live_docs = reader.live_docs();
+    let mut term_status = TermIndexStatus::default();
+    for doc in 0..reader.max_doc() {
+        let vectors = match reader.term_vector(doc)? {
+            Some(vectors) => vectors,
+            None => continue,
+        };
+        for field in vectors.fields() {
+            let fi = reader.field_info(&field);
+            if !matches!(fi, Some(fi) if fi.has_store_term_vector) {
+                return Err(CorruptIndex(format!(
+                    "doc {}: field {} has term vectors but they aren't enabled",
+                    doc, field
+                )));
+            }
+            if let Some(terms) = vectors.terms(&field)? {
+                // the vectors of a doc are a single doc index
+                check_terms(&field, &terms, None, 1, &|_| true, &mut term_status)?;
+            }
+            if live_docs.get(doc as usize) {
+                status.tot_vector_fields += 1;
+            }
+        }
+        if live_docs.get(doc as usize) {
+            status.doc_count += 1;
+        }
+    }
+    Ok(status)
+}
+
+fn check_doc_values<D, C>(reader: &SegmentReader<D, C>) -> Result<DocValuesStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = DocValuesStatus::default();
+    let max_doc = reader.max_doc();
+    for fi in reader.field_infos().by_number.values() {
+        let field = fi.name.as_str();
+        match fi.doc_values_type {
+            DocValuesType::Null => continue,
+            DocValuesType::Numeric => {
+                let dv = reader.get_numeric_doc_values(field)?;
+                for doc in 0..max_doc {
+                    dv.get(doc)?;
+                }
+                status.tot_numeric_fields += 1;
+            }
+            DocValuesType::Binary => {
+                let mut dv = reader.get_binary_doc_values(field)?;
+                for doc in 0..max_doc {
+                    dv.get(doc)?;
+                }
+                status.tot_binary_fields += 1;
+            }
+            DocValuesType::Sorted => {
+                let mut dv = reader.get_sorted_doc_values(field)?;
+                let value_count = dv.value_count() as i32;
+                let mut seen = vec![false; value_count as usize];
+                for doc in 0..max_doc {
+                    let ord = dv.get_ord(doc)?;
+                    if ord < -1 || ord >= value_count {
+                        return Err(CorruptIndex(format!(
+                            "field {}, doc {}: ord {} is out of bounds (value count {})",
+                            field, doc, ord, value_count
+                        )));
+                    }
+                    if ord >= 0 {
+                        seen[ord as usize] = true;
+                    }
+                }
+                check_ords_seen(field, &seen)?;
+                check_sorted_values(field, value_count as i64, &mut |ord| {
+                    dv.lookup_ord(ord as i32)
+                })?;
+                status.tot_sorted_fields += 1;
+            }
+            DocValuesType::SortedNumeric => {
+                let mut dv = reader.get_sorted_numeric_doc_values(field)?;
+                for doc in 0..max_doc {
+                    dv.set_document(doc)?;
+                    let mut last = i64::MIN;
+                    for i in 0..dv.count() {
+                        let value = dv.value_at(i)?;
+                        if value < last {
+                            return Err(CorruptIndex(format!(
+                                "field {}, doc {}: values out of order: {} < {}",
+                                field, doc, value, last
+                            )));
+                        }
+                        last = value;
+                    }
+                }
+                status.tot_sorted_numeric_fields += 1;
+            }
+            DocValuesType::SortedSet => {
+                let mut dv = reader.get_sorted_set_doc_values(field)?;
+                let value_count = dv.get_value_count() as i64;
+                let mut seen = vec![false; value_count as usize];
+                for doc in 0..max_doc {
+                    dv.set_document(doc)?;
+                    let mut last = -1;
+                    loop {
+                        let ord = dv.next_ord()?;
+                        if ord == NO_MORE_ORDS {
+                            break;
+                        }
+                        if ord <= last || ord >= value_count {
+                            return Err(CorruptIndex(format!(
+                                "field {}, doc {}: ord {} is out of order or bounds (last ord {}, \
+                                 value count {})",
+                                field, doc, ord, last, value_count
+                            )));
+                        }
+                        seen[ord as usize] = true;
+                        last = ord;
+                    }
+                }
+                check_ords_seen(field, &seen)?;
+                check_sorted_values(field, value_count, &mut |ord| dv.lookup_ord(ord))?;
+                status.tot_sorted_set_fields += 1;
+            }
+        }
+        status.tot_value_fields += 1;
+    }
+    Ok(status)
+}
+
+fn check_ords_seen(field: &str, seen: &[bool]) -> Result<()> {
+    if let Some(ord) = seen.iter().position(|s| !s) {
+        return Err(CorruptIndex(format!(
+            "field {}: ord {} is never used by a document",
+            field, ord
+        )));
+    }
+    Ok(())
+}
+
+fn check_sorted_values(
+    field: &str,
+    value_count: i64,
+    lookup_ord: &mut dyn FnMut(i64) -> Result<Vec<u8>>,
+) -> Result<()> {
+    let mut last: Option<Vec<u8>> = None;
+    for ord in 0..value_count {
+        let value = lookup_ord(ord)?;
+        if let Some(ref last) = last {
+            if *last >= value {
+                return Err(CorruptIndex(format!(
+                    "field {}: value of ord {} is out of order",
+                    field, ord
+                )));
+            }
+        }
+        last = Some(value);
+    }
+    Ok(())
+}
+
+struct PointsCheckVisitor<'a> {
+    field: &'a str,
+    max_doc: DocId,
+    packed_len: usize,
+    count: i64,
+    error: Option<String>,
+}
+
+impl<'a> IntersectVisitor for PointsCheckVisitor<'a> {
+    fn visit(&mut self, _doc_id: DocId) -> Result<()> {
+        unreachable!()
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        if self.error.is_none() {
+            if doc_id < 0 || doc_id >= self.max_doc {
+                self.error = Some(format!(
+                    "field {}: doc {} is out of bounds (max_doc {})",
+                    self.field, doc_id, self.max_doc
+                ));
+            } else if packed_value.len() != self.packed_len {
+                self.error = Some(format!(
+                    "field {}: packed value length {} != {}",
+                    self.field,
+                    packed_value.len(),
+                    self.packed_len
+                ));
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn compare(&self, _min_packed_value: &[u8], _max_packed_value: &[u8]) -> Relation {
+        // visit every point with its value
+        Relation::CellCrossesQuery
+    }
+}
+
+fn check_points<D, C>(reader: &SegmentReader<D, C>) -> Result<PointsStatus>
+where
+    D: Directory + 'static,
+    C: Codec,
+{
+    let mut status = PointsStatus::default();
+    if !reader.field_infos().has_point_values {
+        return Ok(status);
+    }
+    let points = reader
+        .point_values()
+        .ok_or_else(|| CorruptIndex("field infos have points but there is no reader".into()))?;
+    for fi in reader.field_infos().by_number.values() {
+        if fi.point_dimension_count == 0 {
+            continue;
+        }
+        let mut visitor = PointsCheckVisitor {
+            field: &fi.name,
+            max_doc: reader.max_doc(),
+            packed_len: (fi.point_dimension_count * fi.point_num_bytes) as usize,
+            count: 0,
+            error: None,
+        };
+        points.intersect(&fi.name, &mut visitor)?;
+        if let Some(error) = visitor.error {
+            return Err(CorruptIndex(error));
+        }
+        let size = points.size(&fi.name)?;
+        if visitor.count != size {
+            return Err(CorruptIndex(format!(
+                "field {}: visited {} points but size is {}",
+                fi.name, visitor.count, size
+            )));
+        }
+        status.tot_value_fields += 1;
+        status.tot_value_points += size;
+    }
+    Ok(status)
+}
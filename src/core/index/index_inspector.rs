@@ -0,0 +1,364 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{Codec, PostingIterator, PostingIteratorFlags, TermIterator, Terms};
+use crate::core::doc::{DocValuesType, IndexOptions};
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::reader::{IndexReader, StandardDirectoryReader};
+use crate::core::index::Term;
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::store::directory::Directory;
+use crate::core::util::{DocId, PriorityQueue, Version};
+use crate::Result;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// A segment of the inspected index.
+#[derive(Clone, Debug, Serialize)]
+pub struct SegmentSummary {
+    pub name: String,
+    pub max_doc: i32,
+    pub num_docs: i32,
+    pub del_count: i32,
+    pub size_in_bytes: i64,
+    pub num_files: usize,
+    pub compound: bool,
+    pub version: Version,
+    pub diagnostics: HashMap<String, String>,
+}
+
+/// How a field of the inspected index is indexed, as declared by the first
+/// segment having it.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldSummary {
+    pub name: String,
+    pub number: u32,
+    pub index_options: IndexOptions,
+    pub doc_values_type: DocValuesType,
+    pub has_norms: bool,
+    pub has_term_vectors: bool,
+    pub has_payloads: bool,
+    pub point_dimension_count: u32,
+    pub point_num_bytes: u32,
+    pub vector_dimension: u32,
+    /// The number of documents having a term in the field, `-1` if unknown.
+    pub doc_count: i32,
+}
+
+/// A term of a field, with its statistics over the whole index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TermSummary {
+    pub term: Vec<u8>,
+    pub doc_freq: i32,
+    /// `-1` if the frequencies are not indexed.
+    pub total_term_freq: i64,
+}
+
+/// An occurrence of a term in a document.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PositionEntry {
+    pub position: i32,
+    /// `-1` if the offsets are not indexed.
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub payload: Vec<u8>,
+}
+
+/// A document of the postings of a term.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PostingEntry {
+    pub doc: DocId,
+    /// Whether the document isn't deleted.
+    pub live: bool,
+    pub freq: i32,
+    /// Empty if the positions are not indexed.
+    pub positions: Vec<PositionEntry>,
+}
+
+/// A read-only view of the internals of an index, to debug it: its segments,
+/// the index options of its fields, the most frequent terms of a field and
+/// the raw postings of a term, deleted documents included.
+///
+/// The summaries are `Serialize`, to be displayed by a debugging UI.
+///
+/// ```rust,ignore
+/// let reader = StandardDirectoryReader::open(directory)?;
+/// let inspector = IndexInspector::new(&reader);
+/// for segment in inspector.segments() {
+///     println!("{}: {} docs, {} deleted", segment.name, segment.max_doc, segment.del_count);
+/// }
+/// let top_terms = inspector.top_terms("body", 10)?;
+/// ```
+pub struct IndexInspector<D, C, MS, MP, IR>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+    IR: Deref<Target = StandardDirectoryReader<D, C, MS, MP>>,
+{
+    reader: IR,
+}
+
+impl<D, C, MS, MP, IR> IndexInspector<D, C, MS, MP, IR>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+    IR: Deref<Target = StandardDirectoryReader<D, C, MS, MP>>,
+{
+    pub fn new(reader: IR) -> Self {
+        IndexInspector { reader }
+    }
+
+    /// The segments of the index, in order.
+    pub fn segments(&self) -> Vec<SegmentSummary> {
+        self.reader
+            .segment_readers()
+            .iter()
+            .map(|r| SegmentSummary {
+                name: r.si.info.name.clone(),
+                max_doc: r.max_docs(),
+                num_docs: r.num_docs(),
+                del_count: r.num_deleted_docs(),
+                size_in_bytes: r.si.size_in_bytes(),
+                num_files: r.si.files().len(),
+                compound: r.si.info.is_compound_file(),
+                version: r.si.info.version,
+                diagnostics: r.si.info.diagnostics.clone(),
+            })
+            .collect()
+    }
+
+    /// The fields of the index, by field number.
+    pub fn fields(&self) -> Result<Vec<FieldSummary>> {
+        let mut fields: HashMap<&str, FieldSummary> = HashMap::new();
+        for r in self.reader.segment_readers() {
+            for info in r.field_infos.by_number.values() {
+                fields
+                    .entry(info.name.as_str())
+                    .or_insert_with(|| FieldSummary {
+                        name: info.name.clone(),
+                        number: info.number,
+                        index_options: info.index_options,
+                        doc_values_type: info.doc_values_type,
+                        has_norms: info.index_options != IndexOptions::Null && !info.omit_norms,
+                        has_term_vectors: info.has_store_term_vector,
+                        has_payloads: info.has_store_payloads,
+                        point_dimension_count: info.point_dimension_count,
+                        point_num_bytes: info.point_num_bytes,
+                        vector_dimension: info.vector_dimension,
+                        doc_count: -1,
+                    });
+            }
+        }
+        let mut fields: Vec<FieldSummary> = fields.into_values().collect();
+        fields.sort_by(|a, b| a.number.cmp(&b.number).then_with(|| a.name.cmp(&b.name)));
+        for field in &mut fields {
+            if field.index_options != IndexOptions::Null {
+                field.doc_count = self.reader.doc_count(&field.name)?;
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The `num` terms of `field` found in the most documents, the most
+    /// frequent first.
+    pub fn top_terms(&self, field: &str, num: usize) -> Result<Vec<TermSummary>> {
+        if num == 0 {
+            return Ok(vec![]);
+        }
+        let mut terms: HashMap<Vec<u8>, (i32, i64)> = HashMap::new();
+        for leaf in self.reader.leaves() {
+            if let Some(leaf_terms) = leaf.reader.terms(field)? {
+                let mut terms_iter = leaf_terms.iterator()?;
+                while let Some(term) = terms_iter.next()? {
+                    let doc_freq = terms_iter.doc_freq()?;
+                    let total_term_freq = terms_iter.total_term_freq()?;
+                    let stats = terms.entry(term).or_insert((0, 0));
+                    stats.0 += doc_freq;
+                    stats.1 = if stats.1 < 0 || total_term_freq < 0 {
+                        -1
+                    } else {
+                        stats.1 + total_term_freq
+                    };
+                }
+            }
+        }
+
+        // the least frequent term on top, the greater term first on ties
+        let mut queue = PriorityQueue::new(num, |a: &TermSummary, b: &TermSummary| {
+            a.doc_freq < b.doc_freq || (a.doc_freq == b.doc_freq && a.term > b.term)
+        });
+        for (term, (doc_freq, total_term_freq)) in terms {
+            queue.insert_with_overflow(TermSummary {
+                term,
+                doc_freq,
+                total_term_freq,
+            });
+        }
+        let mut top_terms: Vec<TermSummary> = queue.drain_sorted().collect();
+        top_terms.reverse();
+        Ok(top_terms)
+    }
+
+    /// The postings of `term` over the whole index, with the positions,
+    /// offsets and payloads its field indexes.
+    pub fn postings(&self, term: &Term) -> Result<Vec<PostingEntry>> {
+        let mut entries = vec![];
+        for leaf in self.reader.leaves() {
+            let flags = match leaf.reader.field_info(&term.field) {
+                Some(info) => {
+                    let mut flags = PostingIteratorFlags::FREQS;
+                    if info.index_options.has_positions() {
+                        flags = PostingIteratorFlags::POSITIONS;
+                        if info.index_options.has_offsets() {
+                            flags |= PostingIteratorFlags::OFFSETS;
+                        }
+                        if info.has_store_payloads {
+                            flags |= PostingIteratorFlags::PAYLOADS;
+                        }
+                    }
+                    flags
+                }
+                None => continue,
+            };
+            let has_positions =
+                PostingIteratorFlags::feature_requested(flags, PostingIteratorFlags::POSITIONS);
+            let leaf_terms = match leaf.reader.terms(&term.field)? {
+                Some(leaf_terms) => leaf_terms,
+                None => continue,
+            };
+            let mut terms_iter = leaf_terms.iterator()?;
+            if !terms_iter.seek_exact(term.bytes())? {
+                continue;
+            }
+
+            let live_docs = leaf.reader.live_docs();
+            let mut postings = terms_iter.postings_with_flags(flags)?;
+            loop {
+                let doc = postings.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                let freq = postings.freq()?;
+                let mut positions = vec![];
+                if has_positions {
+                    for _ in 0..freq {
+                        positions.push(PositionEntry {
+                            position: postings.next_position()?,
+                            start_offset: postings.start_offset()?,
+                            end_offset: postings.end_offset()?,
+                            payload: postings.payload()?,
+                        });
+                    }
+                }
+                entries.push(PostingEntry {
+                    doc: leaf.doc_base() + doc,
+                    live: live_docs.get(doc as usize),
+                    freq,
+                    positions,
+                });
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+    use crate::core::doc::{Field, Fieldable, StringField, TEXT_FIELD_TYPE};
+    use crate::core::index::tests::in_memory_writer;
+
+    #[test]
+    fn test_inspect_index() {
+        let writer = in_memory_writer();
+        for (id, text) in &[("1", "a b a"), ("2", "b c")] {
+            let token_stream = WhitespaceAnalyzer.token_stream("body", text).unwrap();
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(StringField::new("id", id, true)),
+                Box::new(Field::new(
+                    "body".into(),
+                    TEXT_FIELD_TYPE,
+                    Some((*text).into()),
+                    Some(token_stream),
+                )),
+            ];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        writer
+            .delete_documents_by_terms(vec![Term::new("id".into(), b"2".to_vec())])
+            .unwrap();
+        let reader = writer.get_reader(true, false).unwrap();
+        let inspector = IndexInspector::new(&reader);
+
+        let segments = inspector.segments();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].max_doc, 2);
+        assert_eq!(segments[0].del_count, 1);
+        assert!(segments[0].size_in_bytes > 0);
+
+        let fields = inspector.fields().unwrap();
+        let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "body"]);
+        assert_eq!(fields[0].index_options, IndexOptions::Docs);
+        assert_eq!(
+            fields[1].index_options,
+            IndexOptions::DocsAndFreqsAndPositions
+        );
+        assert_eq!(fields[1].doc_values_type, DocValuesType::Null);
+        assert_eq!(fields[1].doc_count, 2);
+
+        let top_terms = inspector.top_terms("body", 2).unwrap();
+        assert_eq!(
+            top_terms,
+            vec![
+                TermSummary {
+                    term: b"b".to_vec(),
+                    doc_freq: 2,
+                    total_term_freq: 2,
+                },
+                TermSummary {
+                    term: b"a".to_vec(),
+                    doc_freq: 1,
+                    total_term_freq: 2,
+                },
+            ]
+        );
+
+        // the deleted documents are walked too
+        let postings = inspector
+            .postings(&Term::new("body".into(), b"a".to_vec()))
+            .unwrap();
+        assert_eq!(postings.len(), 1);
+        assert_eq!(postings[0].freq, 2);
+        let positions: Vec<i32> = postings[0].positions.iter().map(|p| p.position).collect();
+        assert_eq!(positions, vec![0, 2]);
+        let postings = inspector
+            .postings(&Term::new("body".into(), b"c".to_vec()))
+            .unwrap();
+        assert_eq!(postings.len(), 1);
+        assert_eq!(postings[0].doc, 1);
+        assert!(!postings[0].live);
+        assert!(inspector
+            .postings(&Term::new("none".into(), b"c".to_vec()))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -258,19 +258,21 @@ impl<T: DocIdMergerSub> DocIdMerger for SortedDocIdMerger<T> {
             return Ok(None);
         }
 
+        // pop and re-push the top rather than updating it in place through
+        // `peek_mut`, which doesn't re-sift the heap unless mutably deref'ed
+        let top = self.queue.pop().unwrap();
         loop {
-            let doc_id = self.queue.peek_mut().unwrap().sub().next_doc()?;
+            let doc_id = top.sub().next_doc()?;
             if doc_id == NO_MORE_DOCS {
-                self.queue.pop();
                 break;
             }
-            let top = self.queue.peek_mut().unwrap();
             let mapped_doc_id = top.sub().base().doc_map.get(doc_id)?;
             if mapped_doc_id == -1 {
                 // doc was deleted
                 continue;
             } else {
                 top.sub().base_mut().mapped_doc_id = mapped_doc_id;
+                self.queue.push(top);
                 break;
             }
         }
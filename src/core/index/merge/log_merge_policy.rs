@@ -0,0 +1,757 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
+use crate::core::codec::Codec;
+use crate::core::index::merge::{
+    MergePolicy, MergeScheduler, MergeSpecification, MergerTrigger, OneMerge,
+    DEFAULT_MAX_CFS_SEGMENT_SIZE, DEFAULT_NO_CFS_RATIO,
+};
+use crate::core::index::writer::IndexWriter;
+use crate::core::store::directory::Directory;
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Default merge factor, which is how many segments are merged at a time.
+pub const DEFAULT_MERGE_FACTOR: u32 = 10;
+
+/// Default maximum segment size. A segment of this size or larger will
+/// never be merged.
+pub const DEFAULT_MAX_MERGE_DOCS: u32 = i32::MAX as u32;
+
+/// Default minimum segment size of `LogByteSizeMergePolicy`.
+pub const DEFAULT_MIN_MERGE_MB: f64 = 1.6;
+
+/// Default maximum segment size of `LogByteSizeMergePolicy`, a segment of
+/// this size or larger will never be merged during normal merging.
+pub const DEFAULT_MAX_MERGE_MB: f64 = 2048.0;
+
+/// Default minimum segment size of `LogDocMergePolicy`.
+pub const DEFAULT_MIN_MERGE_DOCS: u32 = 1000;
+
+/// How many levels below the biggest segment of a run of segments the
+/// smaller segments are still considered to be on the same level.
+const LEVEL_LOG_SPAN: f64 = 0.75;
+
+/// How `LogMergePolicy` measures the size of a segment.
+pub trait LogMergeSize: 'static {
+    fn size<D, C, MS, MP>(
+        info: &SegmentCommitInfo<D, C>,
+        calibrate_size_by_deletes: bool,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy;
+}
+
+/// Measures the segments by the total byte size of their files.
+pub struct ByteSize;
+
+impl LogMergeSize for ByteSize {
+    fn size<D, C, MS, MP>(
+        info: &SegmentCommitInfo<D, C>,
+        calibrate_size_by_deletes: bool,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let byte_size = info.size_in_bytes();
+        if calibrate_size_by_deletes && info.info.max_doc > 0 {
+            let del_ratio = writer.num_deleted_docs(info) as f64 / info.info.max_doc as f64;
+            (byte_size as f64 * (1.0 - del_ratio)) as i64
+        } else {
+            byte_size
+        }
+    }
+}
+
+/// Measures the segments by their number of documents.
+pub struct DocCount;
+
+impl LogMergeSize for DocCount {
+    fn size<D, C, MS, MP>(
+        info: &SegmentCommitInfo<D, C>,
+        calibrate_size_by_deletes: bool,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        size_docs(info, calibrate_size_by_deletes, writer)
+    }
+}
+
+fn size_docs<D, C, MS, MP>(
+    info: &SegmentCommitInfo<D, C>,
+    calibrate_size_by_deletes: bool,
+    writer: &IndexWriter<D, C, MS, MP>,
+) -> i64
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    if calibrate_size_by_deletes {
+        info.info.max_doc as i64 - writer.num_deleted_docs(info) as i64
+    } else {
+        info.info.max_doc as i64
+    }
+}
+
+fn mb_to_bytes(mb: f64) -> i64 {
+    let bytes = mb * 1024.0 * 1024.0;
+    if bytes > i64::MAX as f64 {
+        i64::MAX
+    } else {
+        bytes as i64
+    }
+}
+
+/// A `MergePolicy` that tries to merge segments into levels of exponentially
+/// increasing size, where each level has fewer segments than the value of
+/// the merge factor. Whenever extra segments (beyond the merge factor upper
+/// bound) are encountered, all segments within the level are merged.
+///
+/// Unlike `TieredMergePolicy` only adjacent segments are merged, so the docs
+/// keep their insertion order, which suits append-only or time partitioned
+/// indexes. The size of the segments is measured by `S`, see
+/// `LogByteSizeMergePolicy` and `LogDocMergePolicy`.
+pub struct LogMergePolicy<S: LogMergeSize> {
+    merge_factor: u32,
+    // segments smaller than this are all considered on the same level
+    min_merge_size: i64,
+    // segments of this size or larger are never merged by normal merging
+    max_merge_size: i64,
+    // segments larger than this are never merged by a forced merge
+    max_merge_size_for_forced_merge: i64,
+    max_merge_docs: u32,
+    calibrate_size_by_deletes: bool,
+    no_cfs_ratio: f64,
+    max_cfs_segment_size: u64,
+    _size: PhantomData<S>,
+}
+
+/// A `LogMergePolicy` that measures the size of a segment as the total byte
+/// size of the segment's files.
+pub type LogByteSizeMergePolicy = LogMergePolicy<ByteSize>;
+
+/// A `LogMergePolicy` that measures the size of a segment as the number of
+/// documents (not taking deletions into account).
+pub type LogDocMergePolicy = LogMergePolicy<DocCount>;
+
+impl<S: LogMergeSize> LogMergePolicy<S> {
+    fn new(min_merge_size: i64, max_merge_size: i64) -> Self {
+        LogMergePolicy {
+            merge_factor: DEFAULT_MERGE_FACTOR,
+            min_merge_size,
+            max_merge_size,
+            max_merge_size_for_forced_merge: i64::MAX,
+            max_merge_docs: DEFAULT_MAX_MERGE_DOCS,
+            calibrate_size_by_deletes: true,
+            no_cfs_ratio: DEFAULT_NO_CFS_RATIO,
+            max_cfs_segment_size: DEFAULT_MAX_CFS_SEGMENT_SIZE,
+            _size: PhantomData,
+        }
+    }
+
+    /// Determines how often segment indices are merged by `add_document`.
+    /// With smaller values, less RAM is used while indexing, and searches
+    /// are faster, but indexing speed is slower. With larger values, more
+    /// RAM is used during indexing, and while searches is slower, indexing
+    /// is faster. Default is 10.
+    pub fn set_merge_factor(&mut self, merge_factor: u32) -> Result<()> {
+        if merge_factor < 2 {
+            return Err(IllegalArgument(format!(
+                "merge_factor cannot be less than 2, got {}",
+                merge_factor
+            )));
+        }
+        self.merge_factor = merge_factor;
+        Ok(())
+    }
+
+    pub fn merge_factor(&self) -> u32 {
+        self.merge_factor
+    }
+
+    /// Determines the largest segment (measured by document count) that may
+    /// be merged with other segments. Default is unlimited.
+    pub fn set_max_merge_docs(&mut self, max_merge_docs: u32) {
+        self.max_merge_docs = max_merge_docs;
+    }
+
+    pub fn max_merge_docs(&self) -> u32 {
+        self.max_merge_docs
+    }
+
+    /// Sets whether the segment size should be calibrated by the number of
+    /// deletes when choosing segments for merge. Default is true.
+    pub fn set_calibrate_size_by_deletes(&mut self, calibrate_size_by_deletes: bool) {
+        self.calibrate_size_by_deletes = calibrate_size_by_deletes;
+    }
+
+    pub fn calibrate_size_by_deletes(&self) -> bool {
+        self.calibrate_size_by_deletes
+    }
+
+    /// A merged segment uses the compound file format if its size is at most
+    /// this ratio of the total index size, `0.0` disables the compound file
+    /// format for merged segments and `1.0` always uses it.
+    pub fn set_no_cfs_ratio(&mut self, no_cfs_ratio: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&no_cfs_ratio) {
+            return Err(IllegalArgument(format!(
+                "no_cfs_ratio must be 0.0 to 1.0 inclusive, got {}",
+                no_cfs_ratio
+            )));
+        }
+        self.no_cfs_ratio = no_cfs_ratio;
+        Ok(())
+    }
+
+    /// Sets the max size of the merged segments that may use the compound
+    /// file format.
+    pub fn set_max_cfs_segment_size_mb(&mut self, mb: f64) -> Result<()> {
+        if mb < 0.0 {
+            return Err(IllegalArgument(format!(
+                "max_cfs_segment_size_mb must be >= 0.0, got {}",
+                mb
+            )));
+        }
+        self.max_cfs_segment_size = mb_to_bytes(mb) as u64;
+        Ok(())
+    }
+
+    fn size_docs<D, C, MS, MP>(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        size_docs(info, self.calibrate_size_by_deletes, writer)
+    }
+
+    fn is_too_large_for_forced_merge<D, C, MS, MP>(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        self.size(info, writer) > self.max_merge_size_for_forced_merge
+            || self.size_docs(info, writer) > self.max_merge_docs as i64
+    }
+
+    /// Returns true if the number of segments eligible for merging is less
+    /// than or equal to the specified `max_num_segments`. The segments to
+    /// merge are keyed by name, telling whether they are original segments.
+    fn is_merged_to<D, C, MS, MP>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        max_num_segments: u32,
+        segments_to_merge: &HashMap<&str, bool>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let mut num_segments = 0;
+        let mut merge_info = None;
+        let mut segment_is_original = false;
+        for info in &infos.segments {
+            if let Some(is_original) = segments_to_merge.get(info.info.name.as_str()) {
+                segment_is_original = *is_original;
+                num_segments += 1;
+                merge_info = Some(info);
+            }
+        }
+
+        num_segments <= max_num_segments
+            && (num_segments != 1
+                || !segment_is_original
+                || self.is_merged(infos, merge_info.unwrap().as_ref(), writer))
+    }
+
+    fn new_merge<D, C, MS, MP>(
+        segments: &[Arc<SegmentCommitInfo<D, C>>],
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<OneMerge<D, C>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        OneMerge::new(segments.to_vec(), writer.next_merge_id())
+    }
+
+    /// Returns the merges necessary to merge the index, taking the max merge
+    /// size or max merge docs into consideration. This method attempts to
+    /// respect the `max_num_segments` parameter, however it might be, due to
+    /// size constraints, that more than that number of segments will remain
+    /// in the index.
+    fn find_forced_merges_size_limit<D, C, MS, MP>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        mut last: usize,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let segments = &infos.segments;
+        let mut spec = MergeSpecification::default();
+
+        let mut start = last as i64 - 1;
+        while start >= 0 {
+            let idx = start as usize;
+            if self.is_too_large_for_forced_merge(segments[idx].as_ref(), writer) {
+                // need to skip that segment + add a merge for the 'right'
+                // segments, unless there is only 1 which is merged.
+                if last - idx - 1 > 1
+                    || (idx != last - 1
+                        && !self.is_merged(infos, segments[idx + 1].as_ref(), writer))
+                {
+                    spec.add(Self::new_merge(&segments[idx + 1..last], writer)?);
+                }
+                last = idx;
+            } else if last - idx == self.merge_factor as usize {
+                // mergeFactor eligible segments were found, add them as a merge.
+                spec.add(Self::new_merge(&segments[idx..last], writer)?);
+                last = idx;
+            }
+            start -= 1;
+        }
+
+        // Add any left-over segments, unless there is just 1 already fully
+        // merged
+        if last > 0 && (1 < last || !self.is_merged(infos, segments[0].as_ref(), writer)) {
+            spec.add(Self::new_merge(&segments[..last], writer)?);
+        }
+
+        if spec.merges.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(spec))
+        }
+    }
+
+    /// Returns the merges necessary to force merge the index down to
+    /// `max_num_segments`, the segments are all under the size limits.
+    fn find_forced_merges_max_num_segments<D, C, MS, MP>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        max_num_segments: u32,
+        mut last: usize,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let segments = &infos.segments;
+        let merge_factor = self.merge_factor as usize;
+        let max_num_segments = max_num_segments as usize;
+        let mut spec = MergeSpecification::default();
+
+        // First, enroll all "full" merges (size merge_factor) to potentially
+        // be run concurrently:
+        while last + 1 >= merge_factor + max_num_segments {
+            spec.add(Self::new_merge(
+                &segments[last - merge_factor..last],
+                writer,
+            )?);
+            last -= merge_factor;
+        }
+
+        // Only if there are no full merges pending do we add a final partial
+        // (< merge_factor segments) merge:
+        if spec.merges.is_empty() {
+            if max_num_segments == 1 {
+                // Since we must merge down to 1 segment, the choice is simple:
+                if last > 1 || !self.is_merged(infos, segments[0].as_ref(), writer) {
+                    spec.add(Self::new_merge(&segments[..last], writer)?);
+                }
+            } else if last > max_num_segments {
+                // Take care to pick a partial merge that is least cost, but
+                // does not make the index too lopsided. If we always just
+                // picked the partial tail then we could produce a highly
+                // lopsided index over time:
+
+                // We must merge this many segments to leave max_num_segments
+                // in the index (from when force_merge was first kicked off):
+                let final_merge_size = last - max_num_segments + 1;
+
+                // Consider all possible starting points:
+                let mut best_size = 0;
+                let mut best_start = 0;
+                for i in 0..last - final_merge_size + 1 {
+                    let sum_size: i64 = segments[i..i + final_merge_size]
+                        .iter()
+                        .map(|info| self.size(info.as_ref(), writer))
+                        .sum();
+                    if i == 0
+                        || (sum_size < 2 * self.size(segments[i - 1].as_ref(), writer)
+                            && sum_size < best_size)
+                    {
+                        best_start = i;
+                        best_size = sum_size;
+                    }
+                }
+
+                spec.add(Self::new_merge(
+                    &segments[best_start..best_start + final_merge_size],
+                    writer,
+                )?);
+            }
+        }
+
+        if spec.merges.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(spec))
+        }
+    }
+}
+
+impl Default for LogMergePolicy<ByteSize> {
+    fn default() -> Self {
+        Self::new(
+            mb_to_bytes(DEFAULT_MIN_MERGE_MB),
+            mb_to_bytes(DEFAULT_MAX_MERGE_MB),
+        )
+    }
+}
+
+impl LogMergePolicy<ByteSize> {
+    /// Sets the minimum size for the lowest level segments. Any segments
+    /// below this size are considered to be on the same level (even if they
+    /// vary drastically in size) and will be merged whenever there are
+    /// merge factor of them. This effectively truncates the "long tail" of
+    /// small segments that would otherwise be created into a single level.
+    /// Default is 1.6 MB.
+    pub fn set_min_merge_mb(&mut self, mb: f64) {
+        self.min_merge_size = mb_to_bytes(mb);
+    }
+
+    pub fn min_merge_mb(&self) -> f64 {
+        self.min_merge_size as f64 / 1024.0 / 1024.0
+    }
+
+    /// Determines the largest segment (measured by total byte size of the
+    /// segment's files, in MB) that may be merged with other segments during
+    /// normal merging. Default is 2048 MB.
+    pub fn set_max_merge_mb(&mut self, mb: f64) {
+        self.max_merge_size = mb_to_bytes(mb);
+    }
+
+    pub fn max_merge_mb(&self) -> f64 {
+        self.max_merge_size as f64 / 1024.0 / 1024.0
+    }
+
+    /// Determines the largest segment (measured by total byte size of the
+    /// segment's files, in MB) that may be merged with other segments during
+    /// a forced merge. Default is unlimited.
+    pub fn set_max_merge_mb_for_forced_merge(&mut self, mb: f64) {
+        self.max_merge_size_for_forced_merge = mb_to_bytes(mb);
+    }
+
+    pub fn max_merge_mb_for_forced_merge(&self) -> f64 {
+        self.max_merge_size_for_forced_merge as f64 / 1024.0 / 1024.0
+    }
+}
+
+impl Default for LogMergePolicy<DocCount> {
+    fn default() -> Self {
+        // maxMergeSize(ForForcedMerge) are never used by LogDocMergePolicy;
+        // set it to i64::MAX to disable it
+        Self::new(DEFAULT_MIN_MERGE_DOCS as i64, i64::MAX)
+    }
+}
+
+impl LogMergePolicy<DocCount> {
+    /// Sets the minimum size for the lowest level segments. Any segments
+    /// below this size are considered to be on the same level (even if they
+    /// vary drastically in size) and will be merged whenever there are
+    /// merge factor of them. Default is 1000 docs.
+    pub fn set_min_merge_docs(&mut self, min_merge_docs: u32) {
+        self.min_merge_size = min_merge_docs as i64;
+    }
+
+    pub fn min_merge_docs(&self) -> u32 {
+        self.min_merge_size as u32
+    }
+}
+
+impl<S: LogMergeSize> MergePolicy for LogMergePolicy<S> {
+    /// Checks if any merges are now necessary and returns them. The segments
+    /// are grouped in levels by the log of their size, and merge factor
+    /// adjacent segments of a same level are merged once available.
+    fn find_merges<D, C, MS, MP>(
+        &self,
+        _merge_trigger: MergerTrigger,
+        segment_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let segments = &segment_infos.segments;
+        let merge_factor = self.merge_factor as usize;
+
+        // Compute levels, which is just log (base merge_factor) of the size
+        // of each segment
+        let norm = (self.merge_factor as f64).ln();
+        let levels: Vec<f64> = segments
+            .iter()
+            .map(|info| (self.size(info.as_ref(), writer).max(1) as f64).ln() / norm)
+            .collect();
+
+        let level_floor = if self.min_merge_size <= 0 {
+            0.0
+        } else {
+            (self.min_merge_size as f64).ln() / norm
+        };
+
+        // Now, we quantize the log values into levels. The first level is
+        // any segment whose log size is within LEVEL_LOG_SPAN of the max
+        // size, or, who has such as segment "to the right". Then, we find
+        // the max of all other segments and use that to define the next
+        // level segment, etc.
+        let merging = writer.merging_segments();
+        let mut spec = MergeSpecification::default();
+        let num_mergeable_segments = segments.len();
+
+        let mut start = 0;
+        while start < num_mergeable_segments {
+            // Find max level of all segments not already quantized.
+            let max_level = levels[start..]
+                .iter()
+                .cloned()
+                .fold(f64::NEG_INFINITY, f64::max);
+
+            // Now search backwards for the rightmost segment that falls into
+            // this level:
+            let level_bottom = if max_level <= level_floor {
+                // All remaining segments fall into the min level
+                -1.0
+            } else {
+                let bottom = max_level - LEVEL_LOG_SPAN;
+                // Force a boundary at the level floor
+                if bottom < level_floor && max_level >= level_floor {
+                    level_floor
+                } else {
+                    bottom
+                }
+            };
+
+            let mut upto = num_mergeable_segments - 1;
+            while upto >= start {
+                if levels[upto] >= level_bottom {
+                    break;
+                }
+                upto -= 1;
+            }
+
+            // Finally, record all merges that are viable at this level:
+            let mut end = start + merge_factor;
+            while end <= 1 + upto {
+                let mut any_too_large = false;
+                let mut any_merging = false;
+                for info in &segments[start..end] {
+                    any_too_large |= self.size(info.as_ref(), writer) >= self.max_merge_size
+                        || self.size_docs(info.as_ref(), writer) >= self.max_merge_docs as i64;
+                    if merging.contains(&info.info.name) {
+                        any_merging = true;
+                        break;
+                    }
+                }
+
+                if !any_merging && !any_too_large {
+                    spec.add(Self::new_merge(&segments[start..end], writer)?);
+                }
+
+                start = end;
+                end = start + merge_factor;
+            }
+
+            start = 1 + upto;
+        }
+
+        if spec.merges.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(spec))
+        }
+    }
+
+    /// Returns the merges necessary to merge the index down to a specified
+    /// number of segments. This respects the max merge size and max merge
+    /// docs limits, so more segments may remain.
+    fn find_forced_merges<D, C, MS, MP>(
+        &self,
+        segment_infos: &SegmentInfos<D, C>,
+        max_segment_count: u32,
+        segments_to_merge: &HashMap<Arc<SegmentCommitInfo<D, C>>, bool>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        debug_assert!(max_segment_count > 0);
+        let segments_to_merge: HashMap<&str, bool> = segments_to_merge
+            .iter()
+            .map(|(info, is_original)| (info.info.name.as_str(), *is_original))
+            .collect();
+        if self.is_merged_to(segment_infos, max_segment_count, &segments_to_merge, writer) {
+            return Ok(None);
+        }
+
+        let segments = &segment_infos.segments;
+        // Find the newest (rightmost) segment that needs to be merged (other
+        // segments may have been flushed since merging started):
+        let last = match segments
+            .iter()
+            .rposition(|info| segments_to_merge.contains_key(info.info.name.as_str()))
+        {
+            Some(idx) => idx + 1,
+            None => return Ok(None),
+        };
+
+        // There is only one segment already, and it is merged
+        if max_segment_count == 1
+            && last == 1
+            && self.is_merged(segment_infos, segments[0].as_ref(), writer)
+        {
+            return Ok(None);
+        }
+
+        // Check if there are any segments above the threshold
+        let any_too_large = segments[..last]
+            .iter()
+            .any(|info| self.is_too_large_for_forced_merge(info.as_ref(), writer));
+
+        if any_too_large {
+            self.find_forced_merges_size_limit(segment_infos, last, writer)
+        } else {
+            self.find_forced_merges_max_num_segments(segment_infos, max_segment_count, last, writer)
+        }
+    }
+
+    /// Finds merges necessary to force-merge all deletes from the index. The
+    /// adjacent segments with deletions are merged, at most merge factor at
+    /// a time.
+    fn find_forced_deletes_mergers<D, C, MS, MP>(
+        &self,
+        segments_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        let segments = &segments_infos.segments;
+        let mut spec = MergeSpecification::default();
+        let mut first_segment_with_deletions = None;
+        for (i, info) in segments.iter().enumerate() {
+            if writer.num_deleted_docs(info.as_ref()) > 0 {
+                match first_segment_with_deletions {
+                    None => first_segment_with_deletions = Some(i),
+                    Some(first) if i - first == self.merge_factor as usize => {
+                        // We've seen merge_factor segments in a row with
+                        // deletions, so force a merge now:
+                        spec.add(Self::new_merge(&segments[first..i], writer)?);
+                        first_segment_with_deletions = Some(i);
+                    }
+                    _ => {}
+                }
+            } else if let Some(first) = first_segment_with_deletions.take() {
+                // End of a sequence of segments with deletions, so, merge
+                // those past segments even if it's fewer than merge_factor
+                // segments
+                spec.add(Self::new_merge(&segments[first..i], writer)?);
+            }
+        }
+
+        if let Some(first) = first_segment_with_deletions {
+            spec.add(Self::new_merge(&segments[first..], writer)?);
+        }
+
+        if spec.merges.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(spec))
+        }
+    }
+
+    fn max_cfs_segment_size(&self) -> u64 {
+        self.max_cfs_segment_size
+    }
+
+    fn no_cfs_ratio(&self) -> f64 {
+        self.no_cfs_ratio
+    }
+
+    fn size<D, C, MS, MP>(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        S::size(info, self.calibrate_size_by_deletes, writer)
+    }
+}
@@ -21,6 +21,7 @@ use crate::core::index::writer::IndexWriter;
 use crate::core::store::directory::Directory;
 use crate::core::store::MergeInfo;
 use crate::core::util::external::Volatile;
+use crate::core::util::FixedBitSet;
 
 use crate::error::{
     Error::{IllegalArgument, RuntimeError},
@@ -225,6 +226,21 @@ pub trait MergePolicy: 'static {
             && ptr::eq(info.info.directory.as_ref(), writer.directory().as_ref())
             && self.use_compound_file(infos, info, writer) == info.info.is_compound_file()
     }
+
+    /// Returns the docs among the soft deleted docs `soft_deleted` of the
+    /// segment read by `reader` that a merge may drop, the other ones are
+    /// carried over to the merged segment. All of them are dropped by default.
+    fn reclaimable_soft_deletes<D, C>(
+        &self,
+        _reader: &SegmentReader<D, C>,
+        soft_deleted: FixedBitSet,
+    ) -> Result<FixedBitSet>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+    {
+        Ok(soft_deleted)
+    }
 }
 
 ///
@@ -243,7 +259,7 @@ impl<D: Directory + Send + Sync + 'static, C: Codec> Default for MergeSpecificat
 }
 
 impl<D: Directory + Send + Sync + 'static, C: Codec> MergeSpecification<D, C> {
-    fn add(&mut self, merge: OneMerge<D, C>) {
+    pub fn add(&mut self, merge: OneMerge<D, C>) {
         self.merges.push(merge);
     }
 }
@@ -417,7 +433,7 @@ pub struct TieredMergePolicy {
     max_merge_at_once: u32,
     max_merged_segment_bytes: u64,
     max_merge_at_once_explicit: u32,
-    floor_segment_bytes: u32,
+    floor_segment_bytes: u64,
     segs_per_tier: f64,
     force_merge_deletes_pct_allowed: f64,
     reclaim_deletes_weight: f64,
@@ -440,6 +456,8 @@ impl Default for TieredMergePolicy {
 }
 
 impl TieredMergePolicy {
+    /// Maximum number of segments to be merged at a time during "normal"
+    /// merging. Default is 10.
     pub fn set_max_merge_at_once(&mut self, v: u32) -> Result<()> {
         if v < 2 {
             return Err(IllegalArgument(format!(
@@ -451,6 +469,8 @@ impl TieredMergePolicy {
         Ok(())
     }
 
+    /// Maximum number of segments to be merged at a time, during
+    /// `force_merge` or `force_merge_deletes`. Default is 30.
     pub fn set_max_merge_at_once_explicit(&mut self, v: u32) -> Result<()> {
         if v < 2 {
             return Err(IllegalArgument(format!(
@@ -462,6 +482,8 @@ impl TieredMergePolicy {
         Ok(())
     }
 
+    /// Sets the allowed number of segments per tier. Smaller values mean more
+    /// merging but fewer segments. Default is 5.
     pub fn set_segs_per_tier(&mut self, v: f64) -> Result<()> {
         if v < 0.0 {
             return Err(IllegalArgument(format!(
@@ -473,6 +495,8 @@ impl TieredMergePolicy {
         Ok(())
     }
 
+    /// Maximum sized segment to produce during normal merging. Default is
+    /// 5 GB.
     pub fn set_max_merged_segment_mb(&mut self, mut v: f64) -> Result<()> {
         if v < 0.0 {
             return Err(IllegalArgument(format!(
@@ -490,6 +514,115 @@ impl TieredMergePolicy {
         Ok(())
     }
 
+    /// Segments smaller than this are "rounded up" to this size, i.e. treated
+    /// as equal (floor) size for merge selection. This prevents frequent
+    /// flushing of tiny segments from allowing a long tail in the index.
+    /// Default is 2 MB.
+    pub fn set_floor_segment_mb(&mut self, mut v: f64) -> Result<()> {
+        if v <= 0.0 {
+            return Err(IllegalArgument(format!(
+                "floor_segment_mb must be > 0.0, got {}",
+                v
+            )));
+        }
+        v *= 1024.0 * 1024.0;
+        self.floor_segment_bytes = if v > i64::MAX as f64 {
+            i64::MAX as u64
+        } else {
+            v as u64
+        };
+        Ok(())
+    }
+
+    /// When `force_merge_deletes` is called, only merge away the segments
+    /// whose percentage of deleted docs exceeds this threshold. Default is
+    /// 10%.
+    pub fn set_force_merge_deletes_pct_allowed(&mut self, v: f64) -> Result<()> {
+        if !(0.0..=100.0).contains(&v) {
+            return Err(IllegalArgument(format!(
+                "force_merge_deletes_pct_allowed must be between 0.0 and 100.0 inclusive, got {}",
+                v
+            )));
+        }
+        self.force_merge_deletes_pct_allowed = v;
+        Ok(())
+    }
+
+    /// Controls how aggressively merges that reclaim more deletions are
+    /// favored. Higher values favor selecting merges that reclaim deletions,
+    /// a value of 0.0 means deletions don't impact merge selection. Default
+    /// is 2.0.
+    pub fn set_reclaim_deletes_weight(&mut self, v: f64) -> Result<()> {
+        if v < 0.0 {
+            return Err(IllegalArgument(format!(
+                "reclaim_deletes_weight must be >= 0.0, got {}",
+                v
+            )));
+        }
+        self.reclaim_deletes_weight = v;
+        Ok(())
+    }
+
+    /// Sets the max size of the merged segments that may use the compound
+    /// file format, see also `set_no_cfs_ratio`.
+    pub fn set_max_cfs_segment_size_mb(&mut self, mut v: f64) -> Result<()> {
+        if v < 0.0 {
+            return Err(IllegalArgument(format!(
+                "max_cfs_segment_size_mb must be >= 0.0, got {}",
+                v
+            )));
+        }
+        v *= 1024.0 * 1024.0;
+        self.max_cfs_segment_size = if v > i64::MAX as f64 {
+            i64::MAX as u64
+        } else {
+            v as u64
+        };
+        Ok(())
+    }
+
+    /// A merged segment uses the compound file format if its size is at most
+    /// this ratio of the total index size, `0.0` disables the compound file
+    /// format for merged segments and `1.0` always uses it.
+    pub fn set_no_cfs_ratio(&mut self, v: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&v) {
+            return Err(IllegalArgument(format!(
+                "no_cfs_ratio must be 0.0 to 1.0 inclusive, got {}",
+                v
+            )));
+        }
+        self.no_cfs_ratio = v;
+        Ok(())
+    }
+
+    pub fn max_merge_at_once(&self) -> u32 {
+        self.max_merge_at_once
+    }
+
+    pub fn max_merge_at_once_explicit(&self) -> u32 {
+        self.max_merge_at_once_explicit
+    }
+
+    pub fn segs_per_tier(&self) -> f64 {
+        self.segs_per_tier
+    }
+
+    pub fn max_merged_segment_mb(&self) -> f64 {
+        self.max_merged_segment_bytes as f64 / 1024.0 / 1024.0
+    }
+
+    pub fn floor_segment_mb(&self) -> f64 {
+        self.floor_segment_bytes as f64 / 1024.0 / 1024.0
+    }
+
+    pub fn force_merge_deletes_pct_allowed(&self) -> f64 {
+        self.force_merge_deletes_pct_allowed
+    }
+
+    pub fn reclaim_deletes_weight(&self) -> f64 {
+        self.reclaim_deletes_weight
+    }
+
     fn floor_size(&self, bytes: i64) -> i64 {
         bytes.max(self.floor_segment_bytes as i64)
     }
@@ -1116,3 +1249,30 @@ impl MergeScore {
         format!("skew {} non_del_ratio: {}", self.skew, self.non_del_ratio)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiered_merge_policy_settings() {
+        let mut policy = TieredMergePolicy::default();
+        assert!(policy.set_floor_segment_mb(0.0).is_err());
+        assert!(policy.set_force_merge_deletes_pct_allowed(100.5).is_err());
+        assert!(policy.set_reclaim_deletes_weight(-1.0).is_err());
+        assert!(policy.set_no_cfs_ratio(1.5).is_err());
+
+        policy.set_segs_per_tier(8.0).unwrap();
+        policy.set_max_merged_segment_mb(1024.0).unwrap();
+        policy.set_floor_segment_mb(0.5).unwrap();
+        policy.set_force_merge_deletes_pct_allowed(20.0).unwrap();
+        policy.set_reclaim_deletes_weight(0.0).unwrap();
+        policy.set_no_cfs_ratio(0.1).unwrap();
+        assert_eq!(policy.segs_per_tier(), 8.0);
+        assert_eq!(policy.max_merged_segment_mb(), 1024.0);
+        assert_eq!(policy.floor_segment_mb(), 0.5);
+        assert_eq!(policy.force_merge_deletes_pct_allowed(), 20.0);
+        assert_eq!(policy.reclaim_deletes_weight(), 0.0);
+        assert_eq!(policy.no_cfs_ratio(), 0.1);
+    }
+}
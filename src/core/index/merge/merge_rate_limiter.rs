@@ -18,7 +18,7 @@ use crate::error::{Error, Result};
 
 use std::f64;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Condvar, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::{Duration, SystemTime};
 
 use crate::core::util::external::Volatile;
@@ -126,7 +126,7 @@ impl Default for MergeRateLimiter {
             lock: Mutex::new(()),
             cond: Condvar::new(),
         };
-        limiter.set_mb_per_sec(f64::INFINITY);
+        limiter.set_mb_per_sec(f64::INFINITY).unwrap();
         limiter
     }
 }
@@ -134,11 +134,14 @@ impl Default for MergeRateLimiter {
 const MIN_PAUSE_CHECK_MSEC: i32 = 25;
 
 impl RateLimiter for MergeRateLimiter {
-    fn set_mb_per_sec(&self, mb_per_sec: f64) {
-        let _g = self.lock.lock().unwrap();
+    fn set_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        let _g = self.lock.lock()?;
         // 0.0 is allowed: it means the merge is paused
-        if mb_per_sec < 0.0 {
-            panic!("mb_per_sec must be position; got: {}", mb_per_sec);
+        if mb_per_sec.is_nan() || mb_per_sec < 0.0 {
+            return Err(Error::IllegalArgument(format!(
+                "mb_per_sec must be >= 0; got: {}",
+                mb_per_sec
+            )));
         }
 
         self.mb_per_sec.write(mb_per_sec);
@@ -154,6 +157,7 @@ impl RateLimiter for MergeRateLimiter {
         self.min_pause_check_bytes
             .write(::std::cmp::min(64 * 1024 * 1024, check_bytes));
         self.cond.notify_one();
+        Ok(())
     }
 
     fn mb_per_sec(&self) -> f64 {
@@ -199,3 +203,41 @@ impl RateLimiter for MergeRateLimiter {
         self.min_pause_check_bytes.read()
     }
 }
+
+/// The `RateLimiter` of the files written by a merge.
+///
+/// Applies both the merge's own `MergeRateLimiter`, which the `MergeScheduler`
+/// controls and which aborts the merge, and the `IndexWriter` wide merge rate
+/// limiter, which is shared by all the running merges.
+pub struct MergeOutputRateLimiter {
+    merge: Arc<MergeRateLimiter>,
+    writer: Arc<dyn RateLimiter>,
+}
+
+impl MergeOutputRateLimiter {
+    pub fn new(merge: Arc<MergeRateLimiter>, writer: Arc<dyn RateLimiter>) -> Self {
+        MergeOutputRateLimiter { merge, writer }
+    }
+}
+
+impl RateLimiter for MergeOutputRateLimiter {
+    /// Only changes the rate of this merge, not the writer wide one.
+    fn set_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        self.merge.set_mb_per_sec(mb_per_sec)
+    }
+
+    fn mb_per_sec(&self) -> f64 {
+        self.merge.mb_per_sec().min(self.writer.mb_per_sec())
+    }
+
+    fn pause(&self, bytes: u64) -> Result<Duration> {
+        let paused = self.merge.pause(bytes)?;
+        Ok(paused + self.writer.pause(bytes)?)
+    }
+
+    fn min_pause_check_bytes(&self) -> u64 {
+        self.merge
+            .min_pause_check_bytes()
+            .min(self.writer.min_pause_check_bytes())
+    }
+}
@@ -18,7 +18,7 @@ use crate::core::index::writer::IndexWriter;
 use crate::core::store::directory::Directory;
 use crate::core::store::RateLimiter;
 
-use crate::error::Error;
+use crate::error::{Error, Error::IllegalArgument};
 use crate::Result;
 
 use std::cmp::Ordering;
@@ -130,13 +130,17 @@ impl Ord for MergeTaskInfo {
 /// A `MergeScheduler` that runs each merge using a separate thread.
 ///
 /// Specify the max number of threads that may run at once, and the maximum number
-/// of simultaneous merges with *max_merge_thread*
+/// of simultaneous merges with `set_max_merges_and_threads`.
 ///
 /// If the number of merges exceeds the max number of threads then the largest merges
 /// are paused until one of the smaller merges completes.
 ///
 /// If more than *max_merge_count* merges are requested then this class will forcefully
 /// throttle the incoming threads by pausing until one more more merges complete.
+///
+/// The write rate of the large merges is automatically throttled unless
+/// `disable_auto_io_throttle` is called: it's raised when merges fall behind
+/// and lowered when they keep up.
 #[derive(Clone)]
 pub struct ConcurrentMergeScheduler {
     inner: Arc<ConcurrentMergeSchedulerInner>,
@@ -147,7 +151,7 @@ impl Default for ConcurrentMergeScheduler {
         let available_parallelism = std::thread::available_parallelism()
             .map(NonZero::get)
             .unwrap_or(1);
-        let max_thread_count = 3.min(available_parallelism / 2);
+        let max_thread_count = 3.min(available_parallelism / 2).max(1);
         Self::new(max_thread_count)
     }
 }
@@ -161,6 +165,98 @@ impl ConcurrentMergeScheduler {
             inner: Arc::new(ConcurrentMergeSchedulerInner::new(max_thread_count)),
         }
     }
+
+    /// Expert: sets the maximum number of simultaneous merges
+    /// `max_merge_count` and of threads `max_thread_count` running them.
+    ///
+    /// If the number of merges exceeds `max_thread_count`, the largest ones
+    /// are paused until a smaller one completes, and once more than
+    /// `max_merge_count` merges are pending the threads adding documents are
+    /// stalled until the merges catch up.
+    pub fn set_max_merges_and_threads(
+        &self,
+        max_merge_count: usize,
+        max_thread_count: usize,
+    ) -> Result<()> {
+        if max_thread_count == 0 {
+            return Err(IllegalArgument(
+                "max_thread_count should be at least 1".into(),
+            ));
+        }
+        if max_merge_count < max_thread_count {
+            return Err(IllegalArgument(format!(
+                "max_merge_count should be at least max_thread_count (= {}), got {}",
+                max_thread_count, max_merge_count
+            )));
+        }
+        let guard = self.inner.lock.lock()?;
+        let scheduler = unsafe { self.inner.scheduler_mut(&guard) };
+        scheduler.max_merge_count = max_merge_count;
+        scheduler.max_thread_count = max_thread_count;
+        scheduler.update_merge_threads()?;
+        self.inner.cond.notify_all();
+        Ok(())
+    }
+
+    pub fn max_merge_count(&self) -> usize {
+        self.inner.max_merge_count
+    }
+
+    pub fn max_thread_count(&self) -> usize {
+        self.inner.max_thread_count
+    }
+
+    /// Turns on dynamic IO throttling, which is the default: the write rate
+    /// of the merges is raised when merges fall behind and lowered when
+    /// they keep up.
+    pub fn enable_auto_io_throttle(&self) -> Result<()> {
+        let guard = self.inner.lock.lock()?;
+        let scheduler = unsafe { self.inner.scheduler_mut(&guard) };
+        scheduler.do_auto_io_throttle = true;
+        scheduler.target_mb_per_sec = START_MB_PER_SEC;
+        scheduler.update_merge_threads()
+    }
+
+    /// Turns off dynamic IO throttling, the merges then run at full speed.
+    pub fn disable_auto_io_throttle(&self) -> Result<()> {
+        let guard = self.inner.lock.lock()?;
+        let scheduler = unsafe { self.inner.scheduler_mut(&guard) };
+        scheduler.do_auto_io_throttle = false;
+        scheduler.update_merge_threads()
+    }
+
+    pub fn auto_io_throttle(&self) -> bool {
+        self.inner.do_auto_io_throttle
+    }
+
+    /// The current write rate limit of the large merges in MB/sec,
+    /// `f64::INFINITY` if auto IO throttling is disabled.
+    pub fn io_throttle_mb_per_sec(&self) -> f64 {
+        if self.inner.do_auto_io_throttle {
+            self.inner.target_mb_per_sec
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Sets the write rate limit of the merges run by `force_merge`, in
+    /// MB/sec. Default is unlimited.
+    pub fn set_force_merge_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        if mb_per_sec.is_nan() || mb_per_sec <= 0.0 {
+            return Err(IllegalArgument(format!(
+                "force_merge_mb_per_sec must be > 0.0, got {}",
+                mb_per_sec
+            )));
+        }
+        let guard = self.inner.lock.lock()?;
+        let scheduler = unsafe { self.inner.scheduler_mut(&guard) };
+        scheduler.force_merge_mb_per_sec = mb_per_sec;
+        scheduler.update_merge_threads()
+    }
+
+    pub fn force_merge_mb_per_sec(&self) -> f64 {
+        self.inner.force_merge_mb_per_sec
+    }
 }
 
 struct ConcurrentMergeSchedulerInner {
@@ -182,7 +278,6 @@ const MIN_MERGE_MB_PER_SEC: f64 = 5.0;
 const MAX_MERGE_MB_PER_SEC: f64 = 10240.0;
 
 // Initial value for IO write rate limit when do_auto_io_throttle is true
-#[allow(dead_code)]
 const START_MB_PER_SEC: f64 = 20.0;
 
 // Merges below this size are not counted in the maxThreadCount, i.e. they can freely run in
@@ -252,7 +347,7 @@ impl ConcurrentMergeSchedulerInner {
         (true, guard)
     }
 
-    fn update_merge_threads(&mut self) {
+    fn update_merge_threads(&mut self) -> Result<()> {
         let mut active_tasks: Vec<_> = self.merge_tasks.iter().collect();
         active_tasks.sort();
 
@@ -288,8 +383,9 @@ impl ConcurrentMergeSchedulerInner {
                 self.target_mb_per_sec
             };
 
-            task.merge.rate_limiter.set_mb_per_sec(new_mb_per_sec);
+            task.merge.rate_limiter.set_mb_per_sec(new_mb_per_sec)?;
         }
+        Ok(())
     }
 
     fn merge_thread_count(&self) -> usize {
@@ -305,16 +401,16 @@ impl ConcurrentMergeSchedulerInner {
     fn update_io_throttle<D: Directory + Send + Sync + 'static, C: Codec>(
         &mut self,
         new_merge: &OneMerge<D, C>,
-    ) {
+    ) -> Result<()> {
         if !self.do_auto_io_throttle {
-            return;
+            return Ok(());
         }
 
         let merge_mb = bytes_to_mb(new_merge.estimated_merge_bytes.read());
         if merge_mb < MIN_BIG_MERGE_MB {
             // Only watch non-trivial merges for throttling; this is safe because the MP must
             // eventually have to do larger merges:
-            return;
+            return Ok(());
         }
 
         let now = SystemTime::now();
@@ -357,7 +453,7 @@ impl ConcurrentMergeSchedulerInner {
         } else {
             self.target_mb_per_sec
         };
-        new_merge.rate_limiter.set_mb_per_sec(rate);
+        new_merge.rate_limiter.set_mb_per_sec(rate)
     }
 
     fn is_back_log(&self, now: SystemTime, merge_id: u32, estimated_merge_bytes: u64) -> bool {
@@ -402,7 +498,7 @@ impl MergeScheduler for ConcurrentMergeScheduler {
         if trigger == MergerTrigger::Closing {
             // Disable throttling on close:
             scheduler.target_mb_per_sec = MAX_MERGE_MB_PER_SEC;
-            scheduler.update_merge_threads();
+            scheduler.update_merge_threads()?;
         }
 
         // First, quickly run through the newly proposed merges
@@ -420,7 +516,7 @@ impl MergeScheduler for ConcurrentMergeScheduler {
             }
 
             if let Some(merge) = writer.next_merge() {
-                scheduler.update_io_throttle(&merge);
+                scheduler.update_io_throttle(&merge)?;
 
                 let sentinel = Arc::new(ThreadSentinel);
                 let live_sentinel = Arc::downgrade(&sentinel);
@@ -447,7 +543,7 @@ impl MergeScheduler for ConcurrentMergeScheduler {
                     live_sentinel,
                 };
                 scheduler.merge_tasks.push(merge_task);
-                scheduler.update_merge_threads();
+                scheduler.update_merge_threads()?;
             } else {
                 return Ok(());
             }
@@ -498,7 +594,9 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MP: MergePolicy> MergeThrea
         scheduler_mut
             .merge_tasks
             .retain(|t| t.merge.id != one_merge.id);
-        scheduler_mut.update_merge_threads();
+        if let Err(e) = scheduler_mut.update_merge_threads() {
+            log::error!("failed to update the merge rates: {:?}", e);
+        }
         // In case we had stalled indexing, we can now wake up
         // and possibly unstall:
         scheduler_mut.cond.notify_all();
@@ -518,3 +616,30 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MP: MergePolicy> MergeThrea
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_merge_scheduler_settings() {
+        let scheduler = ConcurrentMergeScheduler::new(2);
+        assert!(scheduler.set_max_merges_and_threads(4, 0).is_err());
+        assert!(scheduler.set_max_merges_and_threads(1, 2).is_err());
+        scheduler.set_max_merges_and_threads(6, 3).unwrap();
+        // the clones share the settings
+        let cloned = scheduler.clone();
+        assert_eq!(cloned.max_merge_count(), 6);
+        assert_eq!(cloned.max_thread_count(), 3);
+
+        assert!(scheduler.auto_io_throttle());
+        assert_eq!(scheduler.io_throttle_mb_per_sec(), START_MB_PER_SEC);
+        scheduler.disable_auto_io_throttle().unwrap();
+        assert!(!cloned.auto_io_throttle());
+        assert!(scheduler.io_throttle_mb_per_sec().is_infinite());
+
+        assert!(scheduler.set_force_merge_mb_per_sec(0.0).is_err());
+        scheduler.set_force_merge_mb_per_sec(100.0).unwrap();
+        assert_eq!(cloned.force_merge_mb_per_sec(), 100.0);
+    }
+}
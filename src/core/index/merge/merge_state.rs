@@ -22,6 +22,7 @@ use crate::core::codec::postings::FieldsProducer;
 use crate::core::codec::segment_infos::SegmentInfo;
 use crate::core::codec::stored_fields::StoredFieldsReader;
 use crate::core::codec::term_vectors::TermVectorsReader;
+use crate::core::codec::vectors::VectorsReader;
 use crate::core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
     CodecTVFields, CodecTVReader,
@@ -36,7 +37,7 @@ use crate::core::util::packed::COMPACT;
 use crate::core::util::packed::{
     PackedLongValues, PackedLongValuesBuilder, PackedLongValuesBuilderType, DEFAULT_PAGE_SIZE,
 };
-use crate::core::util::{Bits, BitsMut, BitsRef, DocId};
+use crate::core::util::{Accountable, Bits, BitsMut, BitsRef, DocId, NamedAccountable};
 
 use crate::error::Error::IllegalArgument;
 use crate::Result;
@@ -79,6 +80,7 @@ pub struct MergeState<D: Directory + 'static, C: Codec> {
     pub live_docs: Vec<BitsRef>,
     pub fields_producers: Vec<MergeFieldsProducer<CodecFieldsProducer<C>>>,
     pub points_readers: Vec<Option<MergePointValuesEnum<Arc<CodecPointsReader<C>>>>>,
+    pub vectors_readers: Vec<Option<Arc<dyn VectorsReader>>>,
     pub max_docs: Vec<i32>,
     /// Indicates if the index needs to be sorted
     pub needs_index_sort: bool,
@@ -114,6 +116,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
         let mut term_vectors_readers = Vec::with_capacity(num_readers);
         let mut doc_values_producers = Vec::with_capacity(num_readers);
         let mut points_readers = Vec::with_capacity(num_readers);
+        let mut vectors_readers = Vec::with_capacity(num_readers);
         let mut fields_infos = Vec::with_capacity(num_readers);
         let mut live_docs = Vec::with_capacity(num_readers);
 
@@ -134,6 +137,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
             term_vectors_readers.push(reader.term_vectors_reader()?);
             fields_producers.push(reader.postings_reader()?);
             points_readers.push(reader.point_values());
+            vectors_readers.push(reader.vectors_reader()?);
             num_docs += reader.num_docs();
         }
         // TODO: hack logic
@@ -154,6 +158,7 @@ impl<D: Directory + 'static, C: Codec> MergeState<D, C> {
             live_docs,
             fields_producers,
             points_readers,
+            vectors_readers,
             max_docs,
             needs_index_sort,
         })
@@ -461,6 +466,20 @@ impl<D: Directory + 'static, C: Codec> LeafReader for ReaderWrapperEnum<D, C> {
         }
     }
 
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        match self {
+            ReaderWrapperEnum::Segment(s) => s.vectors_reader(),
+            ReaderWrapperEnum::SortedSegment(s) => s.vectors_reader(),
+        }
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        match self {
+            ReaderWrapperEnum::Segment(s) => s.check_integrity(),
+            ReaderWrapperEnum::SortedSegment(s) => s.check_integrity(),
+        }
+    }
+
     fn is_codec_reader(&self) -> bool {
         match self {
             ReaderWrapperEnum::Segment(s) => s.is_codec_reader(),
@@ -687,6 +706,13 @@ impl<P: PointValues + Clone + 'static> PointValues for MergePointValuesEnum<P> {
         }
     }
 
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        match self {
+            MergePointValuesEnum::Raw(p) => p.num_index_dimensions(field_name),
+            MergePointValuesEnum::Sorting(p) => p.num_index_dimensions(field_name),
+        }
+    }
+
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
         match self {
             MergePointValuesEnum::Raw(p) => p.bytes_per_dimension(field_name),
@@ -719,6 +745,22 @@ impl<P: PointValues + Clone + 'static> PointValues for MergePointValuesEnum<P> {
 #[derive(Clone)]
 pub struct MergeFieldsProducer<T: FieldsProducer>(MergeFieldsProducerEnum<T>);
 
+impl<T: FieldsProducer> Accountable for MergeFieldsProducer<T> {
+    fn ram_bytes_used(&self) -> usize {
+        match &self.0 {
+            MergeFieldsProducerEnum::Raw(f) => f.ram_bytes_used(),
+            MergeFieldsProducerEnum::Sort(f) => f.ram_bytes_used(),
+        }
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        match &self.0 {
+            MergeFieldsProducerEnum::Raw(f) => f.child_resources(),
+            MergeFieldsProducerEnum::Sort(f) => f.child_resources(),
+        }
+    }
+}
+
 impl<T: FieldsProducer> FieldsProducer for MergeFieldsProducer<T> {
     fn check_integrity(&self) -> Result<()> {
         match &self.0 {
@@ -15,6 +15,10 @@ mod doc_id_merger;
 
 pub use self::doc_id_merger::*;
 
+mod log_merge_policy;
+
+pub use self::log_merge_policy::*;
+
 mod merge_policy;
 
 pub use self::merge_policy::*;
@@ -31,6 +35,18 @@ mod merge_state;
 
 pub use self::merge_state::*;
 
+mod no_merge_policy;
+
+pub use self::no_merge_policy::*;
+
 mod segment_merger;
 
 pub use self::segment_merger::*;
+
+mod soft_deletes_policy;
+
+pub use self::soft_deletes_policy::*;
+
+mod upgrade_index_merge_policy;
+
+pub use self::upgrade_index_merge_policy::*;
@@ -0,0 +1,102 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
+use crate::core::codec::Codec;
+use crate::core::index::merge::{
+    MergePolicy, MergeScheduler, MergeSpecification, MergerTrigger, DEFAULT_MAX_CFS_SEGMENT_SIZE,
+};
+use crate::core::index::writer::IndexWriter;
+use crate::core::store::directory::Directory;
+
+use crate::error::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A `MergePolicy` which never returns merges to execute, neither for the
+/// normal merges nor for `force_merge` and `force_merge_deletes`. Use it if
+/// you want to prevent segment merges.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoMergePolicy;
+
+impl MergePolicy for NoMergePolicy {
+    fn find_merges<D, C, MS, MP>(
+        &self,
+        _merge_trigger: MergerTrigger,
+        _segment_infos: &SegmentInfos<D, C>,
+        _writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        Ok(None)
+    }
+
+    fn find_forced_merges<D, C, MS, MP>(
+        &self,
+        _segment_infos: &SegmentInfos<D, C>,
+        _max_segment_count: u32,
+        _segments_to_merge: &HashMap<Arc<SegmentCommitInfo<D, C>>, bool>,
+        _writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        Ok(None)
+    }
+
+    fn find_forced_deletes_mergers<D, C, MS, MP>(
+        &self,
+        _segments_infos: &SegmentInfos<D, C>,
+        _writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        Ok(None)
+    }
+
+    fn max_cfs_segment_size(&self) -> u64 {
+        DEFAULT_MAX_CFS_SEGMENT_SIZE
+    }
+
+    fn no_cfs_ratio(&self) -> f64 {
+        1.0
+    }
+
+    /// Keeps the compound file setting the new segment was flushed with.
+    fn use_compound_file<D, C, MS, MP>(
+        &self,
+        _infos: &SegmentInfos<D, C>,
+        merged_info: &SegmentCommitInfo<D, C>,
+        _writer: &IndexWriter<D, C, MS, MP>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        merged_info.info.is_compound_file()
+    }
+}
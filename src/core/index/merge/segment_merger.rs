@@ -13,12 +13,13 @@
 
 use crate::core::codec::doc_values::{DocValuesConsumer, DocValuesFormat};
 use crate::core::codec::field_infos::{FieldInfosBuilder, FieldInfosFormat, FieldNumbersRef};
-use crate::core::codec::norms::{NormsConsumer, NormsFormat};
+use crate::core::codec::norms::{NormsConsumer, NormsFormat, NormsProducer};
 use crate::core::codec::points::{PointsFormat, PointsWriter};
 use crate::core::codec::postings::{FieldsConsumer, PostingsFormat};
-use crate::core::codec::segment_infos::{SegmentInfo, SegmentWriteState};
+use crate::core::codec::segment_infos::{SegmentInfo, SegmentReadState, SegmentWriteState};
 use crate::core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsWriter};
 use crate::core::codec::term_vectors::{TermVectorsFormat, TermVectorsWriter};
+use crate::core::codec::vectors::{VectorsFormat, VectorsWriter};
 use crate::core::codec::Codec;
 use crate::core::index::merge::MergeState;
 use crate::core::index::reader::SegmentReader;
@@ -100,7 +101,17 @@ where
             self.context,
             "".into(),
         );
-        self.merge_terms(&segment_write_state)?;
+        // norms are merged first for the postings to compute their impacts
+        let has_norms = self
+            .merge_state
+            .merge_field_infos
+            .as_ref()
+            .unwrap()
+            .has_norms;
+        if has_norms {
+            self.merge_norms(&segment_write_state)?;
+        }
+        self.merge_terms(&segment_write_state, has_norms)?;
 
         if self
             .merge_state
@@ -120,16 +131,16 @@ where
         {
             self.merge_points(&segment_write_state)?;
         }
-
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_norms
+            .has_vector_values
         {
-            self.merge_norms(&segment_write_state)?;
+            self.merge_vector_values(&segment_write_state)?;
         }
+
         if self
             .merge_state
             .merge_field_infos
@@ -175,6 +186,17 @@ where
         writer.merge(&self.merge_state)
     }
 
+    fn merge_vector_values(
+        &mut self,
+        segment_write_state: &SegmentWriteState<D, DW, C>,
+    ) -> Result<()> {
+        let mut writer = self
+            .codec
+            .vectors_format()
+            .fields_writer(segment_write_state)?;
+        writer.merge(&self.merge_state)
+    }
+
     fn merge_norms(&mut self, segment_write_state: &SegmentWriteState<D, DW, C>) -> Result<()> {
         let mut consumer = self
             .codec
@@ -224,11 +246,30 @@ where
         term_vectors_writer.merge(&mut self.merge_state)
     }
 
-    fn merge_terms(&mut self, segment_write_state: &SegmentWriteState<D, DW, C>) -> Result<()> {
+    fn merge_terms(
+        &mut self,
+        segment_write_state: &SegmentWriteState<D, DW, C>,
+        has_norms: bool,
+    ) -> Result<()> {
+        let norms = if has_norms {
+            let read_state = SegmentReadState::new(
+                Arc::clone(&self.directory),
+                &segment_write_state.segment_info,
+                Arc::new(segment_write_state.field_infos.clone()),
+                &IOContext::READ,
+                segment_write_state.segment_suffix.clone(),
+            );
+            Some(self.codec.norms_format().norms_producer(&read_state)?)
+        } else {
+            None
+        };
         let mut consumer = self
             .codec
             .postings_format()
             .fields_consumer(segment_write_state)?;
-        consumer.merge(&mut self.merge_state)
+        consumer.merge(
+            &mut self.merge_state,
+            norms.as_ref().map(|n| n as &dyn NormsProducer),
+        )
     }
 }
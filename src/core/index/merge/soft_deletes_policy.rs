@@ -0,0 +1,196 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
+use crate::core::codec::Codec;
+use crate::core::doc::DocValuesType;
+use crate::core::index::merge::{MergePolicy, MergeScheduler, MergeSpecification, MergerTrigger};
+use crate::core::index::reader::{LeafReader, SegmentReader};
+use crate::core::index::writer::IndexWriter;
+use crate::core::store::directory::Directory;
+use crate::core::util::{BitSet, Bits, FixedBitSet};
+
+use crate::error::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A `MergePolicy` that keeps recently soft deleted documents around, e.g. so
+/// that they can still be replicated or rolled back.
+///
+/// Merges only reclaim the soft deleted docs whose numeric doc values field
+/// `retention_field` is missing or has a value lower than the one returned by
+/// `min_retained` at merge time, typically the sequence number below which
+/// all the operations are known to be replicated. The retained docs are
+/// carried over to the merged segment, still soft deleted. The choice of the
+/// segments to merge is delegated to the wrapped policy.
+///
+/// This only has an effect if `IndexWriterConfig::soft_deletes_field` is set.
+pub struct SoftDeletesRetentionMergePolicy<MP: MergePolicy> {
+    retention_field: String,
+    min_retained: Arc<dyn Fn() -> i64 + Send + Sync>,
+    policy: MP,
+}
+
+impl<MP: MergePolicy> SoftDeletesRetentionMergePolicy<MP> {
+    pub fn new<F>(retention_field: &str, min_retained: F, policy: MP) -> Self
+    where
+        F: Fn() -> i64 + Send + Sync + 'static,
+    {
+        SoftDeletesRetentionMergePolicy {
+            retention_field: retention_field.to_string(),
+            min_retained: Arc::new(min_retained),
+            policy,
+        }
+    }
+
+    pub fn retention_field(&self) -> &str {
+        &self.retention_field
+    }
+
+    pub fn policy(&self) -> &MP {
+        &self.policy
+    }
+}
+
+impl<MP: MergePolicy> MergePolicy for SoftDeletesRetentionMergePolicy<MP> {
+    fn find_merges<D, C, MS, MP1>(
+        &self,
+        merge_trigger: MergerTrigger,
+        segment_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy
+            .find_merges(merge_trigger, segment_infos, writer)
+    }
+
+    fn find_forced_merges<D, C, MS, MP1>(
+        &self,
+        segment_infos: &SegmentInfos<D, C>,
+        max_segment_count: u32,
+        segments_to_merge: &HashMap<Arc<SegmentCommitInfo<D, C>>, bool>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy
+            .find_forced_merges(segment_infos, max_segment_count, segments_to_merge, writer)
+    }
+
+    fn find_forced_deletes_mergers<D, C, MS, MP1>(
+        &self,
+        segments_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy
+            .find_forced_deletes_mergers(segments_infos, writer)
+    }
+
+    fn max_cfs_segment_size(&self) -> u64 {
+        self.policy.max_cfs_segment_size()
+    }
+
+    fn no_cfs_ratio(&self) -> f64 {
+        self.policy.no_cfs_ratio()
+    }
+
+    fn use_compound_file<D, C, MS, MP1>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        merged_info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.use_compound_file(infos, merged_info, writer)
+    }
+
+    fn size<D, C, MS, MP1>(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.size(info, writer)
+    }
+
+    fn is_merged<D, C, MS, MP1>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.is_merged(infos, info, writer)
+    }
+
+    fn reclaimable_soft_deletes<D, C>(
+        &self,
+        reader: &SegmentReader<D, C>,
+        soft_deleted: FixedBitSet,
+    ) -> Result<FixedBitSet>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+    {
+        let soft_deleted = self.policy.reclaimable_soft_deletes(reader, soft_deleted)?;
+        match reader.field_info(&self.retention_field) {
+            Some(fi) if fi.doc_values_type == DocValuesType::Numeric => {}
+            _ => return Ok(soft_deleted),
+        }
+
+        let min_retained = (self.min_retained)();
+        let values = reader.get_numeric_doc_values(&self.retention_field)?;
+        let mut docs_with_field = reader.get_docs_with_field(&self.retention_field)?;
+        let mut reclaimable = soft_deleted;
+        for doc in 0..reclaimable.len() {
+            if reclaimable.get(doc)
+                && docs_with_field.get(doc)
+                && values.get(doc as i32)? >= min_retained
+            {
+                reclaimable.clear(doc);
+            }
+        }
+        Ok(reclaimable)
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
+use crate::core::codec::Codec;
+use crate::core::index::merge::{
+    MergePolicy, MergeScheduler, MergeSpecification, MergerTrigger, OneMerge,
+};
+use crate::core::index::reader::SegmentReader;
+use crate::core::index::writer::IndexWriter;
+use crate::core::store::directory::Directory;
+use crate::core::util::{FixedBitSet, VERSION_LATEST};
+
+use crate::error::Result;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A `MergePolicy` that on `force_merge` only rewrites the segments written
+/// by an older rucene version or with another codec than the writer's, so
+/// that they are upgraded to the current index format. Used by
+/// `IndexUpgrader`.
+///
+/// The old segments are first offered to the wrapped policy, the ones it
+/// doesn't merge are then merged together into a single new segment. All
+/// the other merges are delegated to the wrapped policy.
+pub struct UpgradeIndexMergePolicy<MP: MergePolicy> {
+    policy: MP,
+}
+
+impl<MP: MergePolicy> UpgradeIndexMergePolicy<MP> {
+    pub fn new(policy: MP) -> Self {
+        UpgradeIndexMergePolicy { policy }
+    }
+
+    pub fn policy(&self) -> &MP {
+        &self.policy
+    }
+
+    /// Returns true if the segment `si` must be rewritten by `writer` to be
+    /// in the current index format.
+    pub fn should_upgrade_segment<D, C, MS, MP1>(
+        &self,
+        si: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        si.info.version != VERSION_LATEST
+            || si.info.codec().name() != writer.config().codec().name()
+    }
+}
+
+impl<MP: MergePolicy> MergePolicy for UpgradeIndexMergePolicy<MP> {
+    fn find_merges<D, C, MS, MP1>(
+        &self,
+        merge_trigger: MergerTrigger,
+        segment_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy
+            .find_merges(merge_trigger, segment_infos, writer)
+    }
+
+    fn find_forced_merges<D, C, MS, MP1>(
+        &self,
+        segment_infos: &SegmentInfos<D, C>,
+        max_segment_count: u32,
+        segments_to_merge: &HashMap<Arc<SegmentCommitInfo<D, C>>, bool>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        // only the old segments that are part of the forced merge
+        #[allow(clippy::mutable_key_type)]
+        let mut old_segments = HashMap::new();
+        for si in &segment_infos.segments {
+            if let Some(v) = segments_to_merge.get(si) {
+                if self.should_upgrade_segment(si, writer) {
+                    old_segments.insert(Arc::clone(si), *v);
+                }
+            }
+        }
+        log::debug!(
+            "find_forced_merges: {} segments to upgrade",
+            old_segments.len()
+        );
+        if old_segments.is_empty() {
+            return Ok(None);
+        }
+
+        let mut spec = self.policy.find_forced_merges(
+            segment_infos,
+            max_segment_count,
+            &old_segments,
+            writer,
+        )?;
+        if let Some(ref spec) = spec {
+            // remove all the segments that are merged by the wrapped policy,
+            // the remaining ones are merged below
+            for merge in &spec.merges {
+                for si in &merge.segments {
+                    old_segments.remove(si);
+                }
+            }
+        }
+
+        if !old_segments.is_empty() {
+            let remaining: Vec<_> = segment_infos
+                .segments
+                .iter()
+                .filter(|si| old_segments.contains_key(*si))
+                .cloned()
+                .collect();
+            log::debug!(
+                "find_forced_merges: the wrapped policy didn't upgrade all segments, merging the \
+                 {} remaining ones",
+                remaining.len()
+            );
+            spec.get_or_insert_with(MergeSpecification::default)
+                .add(OneMerge::new(remaining, writer.next_merge_id())?);
+        }
+        Ok(spec)
+    }
+
+    fn find_forced_deletes_mergers<D, C, MS, MP1>(
+        &self,
+        segments_infos: &SegmentInfos<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> Result<Option<MergeSpecification<D, C>>>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy
+            .find_forced_deletes_mergers(segments_infos, writer)
+    }
+
+    fn max_cfs_segment_size(&self) -> u64 {
+        self.policy.max_cfs_segment_size()
+    }
+
+    fn no_cfs_ratio(&self) -> f64 {
+        self.policy.no_cfs_ratio()
+    }
+
+    fn use_compound_file<D, C, MS, MP1>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        merged_info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.use_compound_file(infos, merged_info, writer)
+    }
+
+    fn size<D, C, MS, MP1>(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> i64
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.size(info, writer)
+    }
+
+    fn is_merged<D, C, MS, MP1>(
+        &self,
+        infos: &SegmentInfos<D, C>,
+        info: &SegmentCommitInfo<D, C>,
+        writer: &IndexWriter<D, C, MS, MP1>,
+    ) -> bool
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP1: MergePolicy,
+    {
+        self.policy.is_merged(infos, info, writer)
+    }
+
+    fn reclaimable_soft_deletes<D, C>(
+        &self,
+        reader: &SegmentReader<D, C>,
+        soft_deleted: FixedBitSet,
+    ) -> Result<FixedBitSet>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+    {
+        self.policy.reclaimable_soft_deletes(reader, soft_deleted)
+    }
+}
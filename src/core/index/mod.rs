@@ -13,11 +13,15 @@
 
 use thiserror::Error;
 
+mod check_index;
+mod index_inspector;
 pub mod merge;
 pub mod reader;
 mod term;
 pub mod writer;
 
+pub use check_index::*;
+pub use index_inspector::*;
 pub use term::Term;
 
 #[derive(Debug, Clone, Error)]
@@ -37,10 +41,17 @@ pub mod tests {
     use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
     use crate::core::codec::tests::TestCodec;
     use crate::core::codec::*;
-    use crate::core::doc::{DocValuesType, Document, IndexOptions, StoredFieldVisitor};
+    use crate::core::doc::{
+        DocValuesType, Document, Fieldable, IndexOptions, StoredFieldVisitor, StringField,
+    };
+    use crate::core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
     use crate::core::index::reader::*;
+    use crate::core::index::writer::{
+        IndexWriter, IndexWriterConfig, KeepOnlyLastCommitDeletionPolicy, SnapshotDeletionPolicy,
+    };
     use crate::core::search::similarity::BM25Similarity;
     use crate::core::search::sort_field::Sort;
+    use crate::core::store::directory::ByteBuffersDirectory;
     use crate::core::util::external::Deferred;
     use crate::core::util::*;
     use crate::Result;
@@ -113,6 +124,7 @@ pub mod tests {
                 HashMap::new(),
                 1,
                 1,
+                1,
             )
             .unwrap();
             let field_info_two = FieldInfo::new(
@@ -127,6 +139,7 @@ pub mod tests {
                 HashMap::new(),
                 2,
                 2,
+                2,
             )
             .unwrap();
             infos.push(field_info_one);
@@ -155,7 +168,9 @@ pub mod tests {
         }
 
         fn fields(&self) -> Result<Self::FieldsProducer> {
-            todo!()
+            Err(crate::Error::UnsupportedOperation(
+                "MockLeafReader has no postings".into(),
+            ))
         }
 
         fn name(&self) -> &str {
@@ -236,7 +251,7 @@ pub mod tests {
         // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
         // &quot;identical&quot;.
         fn core_cache_key(&self) -> &str {
-            unimplemented!()
+            "test"
         }
 
         /// Returns null if this leaf is unsorted, or the `Sort` that it was sorted by
@@ -245,9 +260,7 @@ pub mod tests {
         }
 
         /// Expert: adds a CoreClosedListener to this reader's shared core
-        fn add_core_drop_listener(&self, _listener: Deferred) {
-            unimplemented!()
-        }
+        fn add_core_drop_listener(&self, _listener: Deferred) {}
 
         // TODO, currently we don't provide remove listener method
 
@@ -340,4 +353,47 @@ pub mod tests {
             1
         }
     }
+
+    pub type InMemoryIndexWriter =
+        IndexWriter<ByteBuffersDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+    /// An `IndexWriter` with the default config over a new in-memory index.
+    pub fn in_memory_writer() -> InMemoryIndexWriter {
+        IndexWriter::new(
+            Arc::new(ByteBuffersDirectory::new()),
+            Arc::new(IndexWriterConfig::default()),
+        )
+        .unwrap()
+    }
+
+    pub type InMemoryReader = StandardDirectoryReader<
+        ByteBuffersDirectory,
+        CodecEnum,
+        SerialMergeScheduler,
+        TieredMergePolicy,
+    >;
+
+    /// An in-memory `IndexWriter` whose commits can be snapshotted with the returned
+    /// policy.
+    pub fn snapshot_writer() -> (
+        InMemoryIndexWriter,
+        Arc<SnapshotDeletionPolicy<KeepOnlyLastCommitDeletionPolicy>>,
+    ) {
+        let policy = Arc::new(SnapshotDeletionPolicy::new(
+            KeepOnlyLastCommitDeletionPolicy,
+        ));
+        let config = IndexWriterConfig {
+            index_deletion_policy: policy.clone(),
+            ..IndexWriterConfig::default()
+        };
+        let writer =
+            IndexWriter::new(Arc::new(ByteBuffersDirectory::new()), Arc::new(config)).unwrap();
+        (writer, policy)
+    }
+
+    /// Adds a doc with the stored and indexed string field `id`.
+    pub fn add_id_doc(writer: &InMemoryIndexWriter, id: &str) {
+        let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(StringField::new("id", id, true))];
+        writer.add_document(doc).unwrap();
+    }
 }
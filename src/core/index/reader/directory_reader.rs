@@ -11,28 +11,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::codec::segment_infos::INDEX_FILE_SEGMENTS;
 use crate::core::codec::segment_infos::{
-    get_segment_file_name, run_with_find_segment_file, SegmentInfos,
+    generation_from_segments_file_name, run_with_find_segment_file, SegmentInfos,
+    INDEX_FILE_OLD_SEGMENT_GEN, INDEX_FILE_SEGMENTS,
 };
 use crate::core::codec::{Codec, CodecTVFields};
 use crate::core::doc::{Document, DocumentStoredFieldVisitor};
 use crate::core::index::merge::MergePolicy;
 use crate::core::index::merge::MergeScheduler;
 use crate::core::index::reader::{
-    IndexReader, LeafReader, LeafReaderContext, SearchLeafReader, SegmentReader,
+    apply_soft_deletes, IndexReader, LeafReader, LeafReaderContext, SearchLeafReader, SegmentReader,
 };
 use crate::core::index::writer::{CommitPoint, IndexWriter};
 use crate::core::store::directory::Directory;
 use crate::core::store::IOContext;
-use crate::core::util::DocId;
+use crate::core::util::{Accountable, DocId, NamedAccountable};
 
 use crate::error::{
-    Error::{IllegalArgument, IllegalState},
+    Error::{self, IllegalArgument, IllegalState},
     Result,
 };
 
-use std::{collections::HashMap, fmt, sync::Arc};
+use std::{collections::HashMap, fmt, io, sync::Arc};
 
 ///
 // Returns <code>true</code> if an index likely exists at
@@ -61,6 +61,40 @@ pub fn index_exist<D: Directory>(directory: &D) -> Result<bool> {
         .any(|f| f.starts_with(&prefix)))
 }
 
+/// Returns all the commit points of the index in `directory`, sorted from
+/// the oldest to the latest one.
+///
+/// There is only more than one commit if the `IndexDeletionPolicy` of the
+/// writer keeps the old ones. Any of them can be opened with
+/// `StandardDirectoryReader::open_commit`.
+pub fn list_commits<D: Directory, C: Codec>(directory: &Arc<D>) -> Result<Vec<CommitPoint>> {
+    let files = directory.list_all()?;
+    let mut commits = Vec::new();
+    for file in &files {
+        if !file.starts_with(INDEX_FILE_SEGMENTS) || file == INDEX_FILE_OLD_SEGMENT_GEN {
+            continue;
+        }
+        // skip the files that don't look like a commit, e.g. segments_N.tmp
+        if generation_from_segments_file_name(file).is_err() {
+            continue;
+        }
+        match SegmentInfos::<D, C>::read_commit(directory, file) {
+            Ok(sis) => commits.push(CommitPoint::new(&sis)),
+            // the commit was deleted by a writer since we listed the files
+            Err(Error::IOError(ref e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if commits.is_empty() {
+        return Err(Error::RuntimeError(format!(
+            "IndexNotFound: no segments* file found in {}: files: {:?}",
+            directory, files
+        )));
+    }
+    commits.sort();
+    Ok(commits)
+}
+
 pub struct StandardDirectoryReader<
     D: Directory + Send + Sync + 'static,
     C: Codec,
@@ -76,6 +110,7 @@ pub struct StandardDirectoryReader<
     apply_all_deletes: bool,
     write_all_deletes: bool,
     writer: Option<IndexWriter<D, C, MS, MP>>,
+    soft_deletes_field: Option<String>,
 }
 
 impl<D, C, MS, MP> StandardDirectoryReader<D, C, MS, MP>
@@ -85,13 +120,56 @@ where
     MS: MergeScheduler,
     MP: MergePolicy,
 {
+    /// Opens the latest commit of `directory`.
+    ///
+    /// This doesn't need the write lock, so the index may be written
+    /// concurrently, e.g. by an `IndexWriter` in another process. If the
+    /// writer deletes the files of the commit while it's being opened, the
+    /// open is retried on the newer commit; an error is only returned if no
+    /// newer commit shows up. As the files of a commit are never modified,
+    /// the reader only sees the changes of the commit it opened, until it's
+    /// reopened with `open_if_changed`.
     pub fn open(directory: Arc<D>) -> Result<Self> {
-        let segment_file_name = get_segment_file_name(directory.as_ref())?;
-        let segment_infos = SegmentInfos::read_commit(&directory, &segment_file_name)?;
+        Self::open_latest(directory, None)
+    }
+
+    /// Opens the latest commit of `directory`, hiding the docs that are soft
+    /// deleted through the numeric doc values field `soft_deletes_field`.
+    ///
+    /// The readers reopened from the returned reader hide them as well.
+    pub fn open_with_soft_deletes(directory: Arc<D>, soft_deletes_field: &str) -> Result<Self> {
+        Self::open_latest(directory, Some(soft_deletes_field.to_string()))
+    }
+
+    /// Opens the past commit point `commit` of `directory`, as returned by
+    /// `list_commits`, rather than the latest one.
+    ///
+    /// Returns an error if the commit was deleted since it was listed.
+    pub fn open_commit(directory: Arc<D>, commit: &CommitPoint) -> Result<Self> {
+        Self::open_segments_file(directory, commit.segments_file_name(), None)
+    }
+
+    // a writer may commit and delete the files of the commit being opened in
+    // the meantime, the open is then retried on the newer commit
+    fn open_latest(directory: Arc<D>, soft_deletes_field: Option<String>) -> Result<Self> {
+        run_with_find_segment_file(&directory, None, |(dir, file_name)| {
+            Self::open_segments_file(Arc::clone(dir), file_name, soft_deletes_field.clone())
+        })
+    }
+
+    fn open_segments_file(
+        directory: Arc<D>,
+        segment_file_name: &str,
+        soft_deletes_field: Option<String>,
+    ) -> Result<Self> {
+        let segment_infos = SegmentInfos::read_commit(&directory, segment_file_name)?;
         let mut readers = Vec::with_capacity(segment_infos.segments.len());
         for seg_info in &segment_infos.segments {
-            let s = SegmentReader::open(seg_info, &IOContext::READ)?;
-            readers.push(Arc::new(s));
+            let mut s = Arc::new(SegmentReader::open(seg_info, &IOContext::READ)?);
+            if let Some(ref field) = soft_deletes_field {
+                s = apply_soft_deletes(s, field)?;
+            }
+            readers.push(s);
         }
         Ok(Self::new(
             directory,
@@ -100,16 +178,26 @@ where
             None,
             false,
             false,
+            soft_deletes_field,
         ))
     }
 
     /// Used by near real-time searcher
+    ///
+    /// The readers of `old_readers`, previously opened by the same writer,
+    /// are shared by the new reader for the segments that didn't change since.
     pub fn open_by_writer(
         writer: IndexWriter<D, C, MS, MP>,
         infos: &SegmentInfos<D, C>,
         apply_all_deletes: bool,
         write_all_deletes: bool,
+        old_readers: &[Arc<SegmentReader<D, C>>],
     ) -> Result<Self> {
+        let mut reader_indexes: HashMap<&str, usize> = HashMap::with_capacity(old_readers.len());
+        for (i, r) in old_readers.iter().enumerate() {
+            reader_indexes.insert(r.name(), i);
+        }
+
         // IndexWriter synchronizes externally before calling
         // us, which ensures infos will not change; so there's
         // no need to process segments in reverse order
@@ -124,10 +212,20 @@ where
             // actual instance of SegmentInfoPerCommit in
             // IndexWriter's segmentInfos:
             let rld = writer.reader_pool().get_or_create(&infos.segments[i])?;
-            let reader = rld.get_readonly_clone(&IOContext::READ)?;
+            let old_reader = reader_indexes
+                .get(infos.segments[i].info.name.as_str())
+                .map(|idx| &old_readers[*idx])
+                .filter(|r| rld.is_current_readonly_clone(r));
+            let reader = if let Some(old_reader) = old_reader {
+                // No change; this reader will be shared between the old and new one
+                Arc::clone(old_reader)
+            } else {
+                let reader = Arc::new(rld.get_readonly_clone(&IOContext::READ)?);
+                Self::maybe_apply_soft_deletes(reader, writer.config().soft_deletes_field())?
+            };
             if reader.num_docs() > 0 {
                 // Steal the ref:
-                readers.push(Arc::new(reader));
+                readers.push(reader);
                 infos_upto += 1;
             } else {
                 segment_infos.segments.remove(infos_upto);
@@ -136,6 +234,7 @@ where
         }
         writer.inc_ref_deleter(&segment_infos)?;
         let dir = Arc::clone(writer.directory());
+        let soft_deletes_field = writer.config().soft_deletes_field.clone();
         Ok(StandardDirectoryReader::new(
             dir,
             readers,
@@ -143,6 +242,7 @@ where
             Some(writer),
             apply_all_deletes,
             write_all_deletes,
+            soft_deletes_field,
         ))
     }
 
@@ -150,6 +250,7 @@ where
         directory: Arc<D>,
         infos: SegmentInfos<D, C>,
         old_readers: &[Arc<SegmentReader<D, C>>],
+        soft_deletes_field: Option<&str>,
     ) -> Result<Self> {
         let mut reader_indexes: HashMap<&str, usize> = HashMap::with_capacity(old_readers.len());
         for (i, r) in old_readers.iter().enumerate() {
@@ -177,7 +278,9 @@ where
                         // No change; this reader will be shard between the old and new one
                         new_readers.push(Arc::clone(&reader));
                     } else {
-                        let new_reader = if reader.si.del_gen() == commit_info.del_gen() {
+                        let new_reader = if reader.si.del_gen() == commit_info.del_gen()
+                            && soft_deletes_field.is_none()
+                        {
                             // only DV updates
                             SegmentReader::build_from(
                                 Arc::clone(commit_info),
@@ -187,19 +290,26 @@ where
                                 true,
                             )?
                         } else {
-                            // both DV and liveDocs have changed
+                            // both DV and liveDocs have changed, or the soft
+                            // deletes must be re-applied on the hard live docs
                             SegmentReader::build_from_reader(
                                 Arc::clone(commit_info),
                                 reader.as_ref(),
                             )?
                         };
-                        new_readers.push(Arc::new(new_reader));
+                        new_readers.push(Self::maybe_apply_soft_deletes(
+                            Arc::new(new_reader),
+                            soft_deletes_field,
+                        )?);
                     }
                     continue;
                 }
             }
             let new_reader = SegmentReader::open(commit_info, &IOContext::READ)?;
-            new_readers.push(Arc::new(new_reader));
+            new_readers.push(Self::maybe_apply_soft_deletes(
+                Arc::new(new_reader),
+                soft_deletes_field,
+            )?);
         }
         Ok(StandardDirectoryReader::new(
             directory,
@@ -208,9 +318,20 @@ where
             None,
             false,
             false,
+            soft_deletes_field.map(str::to_string),
         ))
     }
 
+    fn maybe_apply_soft_deletes(
+        reader: Arc<SegmentReader<D, C>>,
+        soft_deletes_field: Option<&str>,
+    ) -> Result<Arc<SegmentReader<D, C>>> {
+        match soft_deletes_field {
+            Some(field) => apply_soft_deletes(reader, field),
+            None => Ok(reader),
+        }
+    }
+
     fn new(
         directory: Arc<D>,
         mut readers: Vec<Arc<SegmentReader<D, C>>>,
@@ -218,6 +339,7 @@ where
         writer: Option<IndexWriter<D, C, MS, MP>>,
         apply_all_deletes: bool,
         write_all_deletes: bool,
+        soft_deletes_field: Option<String>,
     ) -> Self {
         let mut starts = Vec::with_capacity(readers.len() + 1);
         let mut max_doc = 0;
@@ -240,6 +362,7 @@ where
             writer,
             apply_all_deletes,
             write_all_deletes,
+            soft_deletes_field,
         }
     }
 
@@ -269,6 +392,10 @@ where
         self.segment_infos.version
     }
 
+    pub fn segment_readers(&self) -> &[Arc<SegmentReader<D, C>>] {
+        &self.readers
+    }
+
     pub fn open_if_changed(&self, commit: Option<&CommitPoint>) -> Result<Option<Self>> {
         // If we were obtained by writer.getReader(), re-ask the
         // writer to get a new reader.
@@ -288,7 +415,11 @@ where
                 return Ok(None);
             }
 
-            let mut reader = writer.get_reader(self.apply_all_deletes, self.write_all_deletes)?;
+            let mut reader = writer.reopen_reader(
+                self.apply_all_deletes,
+                self.write_all_deletes,
+                &self.readers,
+            )?;
             reader.writer = self.writer.clone();
             if reader.version() == self.segment_infos.version {
                 return Ok(None);
@@ -300,7 +431,12 @@ where
     fn open_from_commit(&self, commit: Option<&CommitPoint>) -> Result<Self> {
         run_with_find_segment_file(&self.directory, commit, |(dir, file_name)| {
             let infos = SegmentInfos::read_commit(dir, file_name)?;
-            Self::open_by_readers(Arc::clone(dir), infos, &self.readers)
+            Self::open_by_readers(
+                Arc::clone(dir),
+                infos,
+                &self.readers,
+                self.soft_deletes_field.as_deref(),
+            )
         })
     }
 
@@ -402,6 +538,25 @@ where
     }
 }
 
+impl<D, C, MS, MP> Accountable for StandardDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn ram_bytes_used(&self) -> usize {
+        self.readers.iter().map(|r| r.ram_bytes_used()).sum()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.readers
+            .iter()
+            .map(|r| NamedAccountable::new(format!("segment {}", r.si.info.name), r))
+            .collect()
+    }
+}
+
 impl<D, C, MS, MP> fmt::Debug for StandardDirectoryReader<D, C, MS, MP>
 where
     D: Directory + Send + Sync + 'static,
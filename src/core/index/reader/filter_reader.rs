@@ -0,0 +1,492 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::{
+    BinaryDocValues, DocValuesProducer, NumericDocValues, SortedDocValues, SortedNumericDocValues,
+    SortedSetDocValues,
+};
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::vectors::VectorsReader;
+use crate::core::codec::{
+    Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
+    CodecTVFields, CodecTVReader, CodecTerms,
+};
+use crate::core::doc::{Document, DocumentStoredFieldVisitor, StoredFieldVisitor};
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::reader::{
+    IndexReader, LeafReader, LeafReaderContext, SearchLeafReader, StandardDirectoryReader,
+};
+use crate::core::search::sort_field::Sort;
+use crate::core::store::directory::Directory;
+use crate::core::util::external::Deferred;
+use crate::core::util::{BitsMut, BitsRef, DocId};
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// A `FilterLeafReader` contains another `LeafReader`, which it uses as its
+/// basic source of data, possibly transforming the data along the way or
+/// providing additional functionality.
+///
+/// Every method delegates to the wrapped reader by default, so implementors
+/// only override the ones they need, e.g. `field_infos` and `terms` to hide
+/// fields, or `live_docs` and `num_docs` to apply extra deletes. Every
+/// `FilterLeafReader` is a `LeafReader` and an `IndexReader`, and thus is
+/// searchable.
+pub trait FilterLeafReader {
+    type Codec: Codec;
+
+    /// Returns the wrapped reader.
+    fn delegate(&self) -> &SearchLeafReader<Self::Codec>;
+
+    fn fields(&self) -> Result<CodecFieldsProducer<Self::Codec>> {
+        self.delegate().fields()
+    }
+
+    fn name(&self) -> &str {
+        self.delegate().name()
+    }
+
+    /// Returns the terms of `field`, this is also used to look up the
+    /// postings, so returning `None` hides the field from the queries.
+    fn terms(&self, field: &str) -> Result<Option<CodecTerms<Self::Codec>>> {
+        self.delegate().terms(field)
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<Self::Codec>>> {
+        self.delegate().term_vector(doc_id)
+    }
+
+    fn document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()> {
+        self.delegate().document(doc_id, visitor)
+    }
+
+    fn live_docs(&self) -> BitsRef {
+        self.delegate().live_docs()
+    }
+
+    fn field_info(&self, field: &str) -> Option<&FieldInfo> {
+        self.field_infos().field_info_by_name(field)
+    }
+
+    fn field_infos(&self) -> &FieldInfos {
+        self.delegate().field_infos()
+    }
+
+    fn clone_field_infos(&self) -> Arc<FieldInfos> {
+        self.delegate().clone_field_infos()
+    }
+
+    fn max_doc(&self) -> DocId {
+        self.delegate().max_doc()
+    }
+
+    fn num_docs(&self) -> i32 {
+        self.delegate().num_docs()
+    }
+
+    fn get_numeric_doc_values(&self, field: &str) -> Result<Box<dyn NumericDocValues>> {
+        self.delegate().get_numeric_doc_values(field)
+    }
+
+    fn get_binary_doc_values(&self, field: &str) -> Result<Box<dyn BinaryDocValues>> {
+        self.delegate().get_binary_doc_values(field)
+    }
+
+    fn get_sorted_doc_values(&self, field: &str) -> Result<Box<dyn SortedDocValues>> {
+        self.delegate().get_sorted_doc_values(field)
+    }
+
+    fn get_sorted_numeric_doc_values(
+        &self,
+        field: &str,
+    ) -> Result<Box<dyn SortedNumericDocValues>> {
+        self.delegate().get_sorted_numeric_doc_values(field)
+    }
+
+    fn get_sorted_set_doc_values(&self, field: &str) -> Result<Box<dyn SortedSetDocValues>> {
+        self.delegate().get_sorted_set_doc_values(field)
+    }
+
+    fn norm_values(&self, field: &str) -> Result<Option<Box<dyn NumericDocValues>>> {
+        self.delegate().norm_values(field)
+    }
+
+    fn get_docs_with_field(&self, field: &str) -> Result<Box<dyn BitsMut>> {
+        self.delegate().get_docs_with_field(field)
+    }
+
+    fn point_values(&self) -> Option<Arc<CodecPointsReader<Self::Codec>>> {
+        self.delegate().point_values()
+    }
+
+    fn core_cache_key(&self) -> &str {
+        self.delegate().core_cache_key()
+    }
+
+    fn index_sort(&self) -> Option<&Sort> {
+        self.delegate().index_sort()
+    }
+
+    fn add_core_drop_listener(&self, listener: Deferred) {
+        self.delegate().add_core_drop_listener(listener)
+    }
+
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        self.delegate().vectors_reader()
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        self.delegate().check_integrity()
+    }
+}
+
+impl<T: FilterLeafReader> LeafReader for T {
+    type Codec = T::Codec;
+    type FieldsProducer = CodecFieldsProducer<T::Codec>;
+    type TVFields = CodecTVFields<T::Codec>;
+    type TVReader = Arc<CodecTVReader<T::Codec>>;
+    type StoredReader = Arc<CodecStoredFieldsReader<T::Codec>>;
+    type NormsReader = Arc<CodecNormsProducer<T::Codec>>;
+    type PointsReader = Arc<CodecPointsReader<T::Codec>>;
+
+    fn codec(&self) -> &Self::Codec {
+        self.delegate().codec()
+    }
+
+    fn fields(&self) -> Result<Self::FieldsProducer> {
+        FilterLeafReader::fields(self)
+    }
+
+    fn name(&self) -> &str {
+        FilterLeafReader::name(self)
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<CodecTerms<T::Codec>>> {
+        FilterLeafReader::terms(self, field)
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<Self::TVFields>> {
+        FilterLeafReader::term_vector(self, doc_id)
+    }
+
+    fn document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()> {
+        FilterLeafReader::document(self, doc_id, visitor)
+    }
+
+    fn live_docs(&self) -> BitsRef {
+        FilterLeafReader::live_docs(self)
+    }
+
+    fn field_info(&self, field: &str) -> Option<&FieldInfo> {
+        FilterLeafReader::field_info(self, field)
+    }
+
+    fn field_infos(&self) -> &FieldInfos {
+        FilterLeafReader::field_infos(self)
+    }
+
+    fn clone_field_infos(&self) -> Arc<FieldInfos> {
+        FilterLeafReader::clone_field_infos(self)
+    }
+
+    fn max_doc(&self) -> DocId {
+        FilterLeafReader::max_doc(self)
+    }
+
+    fn num_docs(&self) -> i32 {
+        FilterLeafReader::num_docs(self)
+    }
+
+    fn get_numeric_doc_values(&self, field: &str) -> Result<Box<dyn NumericDocValues>> {
+        FilterLeafReader::get_numeric_doc_values(self, field)
+    }
+
+    fn get_binary_doc_values(&self, field: &str) -> Result<Box<dyn BinaryDocValues>> {
+        FilterLeafReader::get_binary_doc_values(self, field)
+    }
+
+    fn get_sorted_doc_values(&self, field: &str) -> Result<Box<dyn SortedDocValues>> {
+        FilterLeafReader::get_sorted_doc_values(self, field)
+    }
+
+    fn get_sorted_numeric_doc_values(
+        &self,
+        field: &str,
+    ) -> Result<Box<dyn SortedNumericDocValues>> {
+        FilterLeafReader::get_sorted_numeric_doc_values(self, field)
+    }
+
+    fn get_sorted_set_doc_values(&self, field: &str) -> Result<Box<dyn SortedSetDocValues>> {
+        FilterLeafReader::get_sorted_set_doc_values(self, field)
+    }
+
+    fn norm_values(&self, field: &str) -> Result<Option<Box<dyn NumericDocValues>>> {
+        FilterLeafReader::norm_values(self, field)
+    }
+
+    fn get_docs_with_field(&self, field: &str) -> Result<Box<dyn BitsMut>> {
+        FilterLeafReader::get_docs_with_field(self, field)
+    }
+
+    fn point_values(&self) -> Option<Self::PointsReader> {
+        FilterLeafReader::point_values(self)
+    }
+
+    fn core_cache_key(&self) -> &str {
+        FilterLeafReader::core_cache_key(self)
+    }
+
+    fn index_sort(&self) -> Option<&Sort> {
+        FilterLeafReader::index_sort(self)
+    }
+
+    fn add_core_drop_listener(&self, listener: Deferred) {
+        FilterLeafReader::add_core_drop_listener(self, listener)
+    }
+
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        FilterLeafReader::vectors_reader(self)
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        FilterLeafReader::check_integrity(self)
+    }
+
+    fn is_codec_reader(&self) -> bool {
+        false
+    }
+
+    // following methods are from `CodecReader`
+    fn store_fields_reader(&self) -> Result<Self::StoredReader> {
+        unreachable!()
+    }
+
+    fn term_vectors_reader(&self) -> Result<Option<Self::TVReader>> {
+        unreachable!()
+    }
+
+    fn norms_reader(&self) -> Result<Option<Self::NormsReader>> {
+        unreachable!()
+    }
+
+    fn doc_values_reader(&self) -> Result<Option<Arc<dyn DocValuesProducer>>> {
+        unreachable!()
+    }
+
+    fn postings_reader(&self) -> Result<Self::FieldsProducer> {
+        unreachable!()
+    }
+}
+
+impl<T: FilterLeafReader + 'static> IndexReader for T {
+    type Codec = T::Codec;
+
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, T::Codec>> {
+        vec![LeafReaderContext::new(self, self, 0, 0)]
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<T::Codec>>> {
+        FilterLeafReader::term_vector(self, doc_id)
+    }
+
+    fn document(&self, doc_id: DocId, fields: &[String]) -> Result<Document> {
+        let mut visitor = DocumentStoredFieldVisitor::new(fields);
+        FilterLeafReader::document(self, doc_id, &mut visitor)?;
+        Ok(visitor.document())
+    }
+
+    fn max_doc(&self) -> i32 {
+        FilterLeafReader::max_doc(self)
+    }
+
+    fn num_docs(&self) -> i32 {
+        FilterLeafReader::num_docs(self)
+    }
+}
+
+type SubReaderWrapper<C> =
+    dyn Fn(Arc<SearchLeafReader<C>>) -> Result<Arc<SearchLeafReader<C>>> + Send + Sync;
+
+/// A `StandardDirectoryReader` whose leaves are wrapped by `wrapper`,
+/// typically into `FilterLeafReader`s. The leaves of the readers reopened
+/// from this one are wrapped the same way.
+pub struct FilterDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    reader: StandardDirectoryReader<D, C, MS, MP>,
+    wrapper: Arc<SubReaderWrapper<C>>,
+    leaves: Vec<Arc<SearchLeafReader<C>>>,
+    // doc base of each leaf, followed by max_doc
+    starts: Vec<DocId>,
+    num_docs: i32,
+}
+
+impl<D, C, MS, MP> FilterDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    pub fn new<F>(reader: StandardDirectoryReader<D, C, MS, MP>, wrapper: F) -> Result<Self>
+    where
+        F: Fn(Arc<SearchLeafReader<C>>) -> Result<Arc<SearchLeafReader<C>>> + Send + Sync + 'static,
+    {
+        Self::wrap(reader, Arc::new(wrapper))
+    }
+
+    fn wrap(
+        reader: StandardDirectoryReader<D, C, MS, MP>,
+        wrapper: Arc<SubReaderWrapper<C>>,
+    ) -> Result<Self> {
+        let mut leaves = Vec::with_capacity(reader.segment_readers().len());
+        let mut starts = Vec::with_capacity(leaves.capacity() + 1);
+        let mut max_doc = 0;
+        let mut num_docs = 0;
+        for segment_reader in reader.segment_readers() {
+            let segment_reader = Arc::clone(segment_reader) as Arc<SearchLeafReader<C>>;
+            let leaf = wrapper(Arc::clone(&segment_reader))?;
+            if leaf.max_doc() != segment_reader.max_doc() {
+                return Err(IllegalArgument(format!(
+                    "the wrapped reader of {} must keep its max_doc {}, got {}",
+                    segment_reader.name(),
+                    segment_reader.max_doc(),
+                    leaf.max_doc()
+                )));
+            }
+            starts.push(max_doc);
+            max_doc += leaf.max_doc();
+            num_docs += leaf.num_docs();
+            leaves.push(leaf);
+        }
+        starts.push(max_doc);
+
+        Ok(FilterDirectoryReader {
+            reader,
+            wrapper,
+            leaves,
+            starts,
+            num_docs,
+        })
+    }
+
+    /// Returns the wrapped reader.
+    pub fn delegate(&self) -> &StandardDirectoryReader<D, C, MS, MP> {
+        &self.reader
+    }
+
+    /// Reopens the wrapped reader, see `StandardDirectoryReader::open_if_changed`,
+    /// and wraps the leaves of the new reader.
+    pub fn open_if_changed(&self) -> Result<Option<Self>> {
+        match self.reader.open_if_changed(None)? {
+            Some(reader) => Ok(Some(Self::wrap(reader, Arc::clone(&self.wrapper))?)),
+            None => Ok(None),
+        }
+    }
+
+    fn leaf_index(&self, doc_id: DocId) -> Result<usize> {
+        if doc_id < 0 || doc_id >= self.max_doc() {
+            return Err(IllegalArgument(format!(
+                "doc_id {} invalid: [max_doc={}]",
+                doc_id,
+                self.max_doc()
+            )));
+        }
+        // skip the empty leaves sharing the doc base of the next one
+        Ok(match self.starts.binary_search(&doc_id) {
+            Ok(mut i) => {
+                while self.starts[i + 1] == doc_id {
+                    i += 1;
+                }
+                i
+            }
+            Err(i) => i - 1,
+        })
+    }
+}
+
+impl<D, C, MS, MP> IndexReader for FilterDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    type Codec = C;
+
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        self.leaves
+            .iter()
+            .enumerate()
+            .map(|(i, leaf)| LeafReaderContext::new(self, leaf.as_ref(), i, self.starts[i]))
+            .collect()
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<C>>> {
+        let i = self.leaf_index(doc_id)?;
+        self.leaves[i].term_vector(doc_id - self.starts[i])
+    }
+
+    fn document(&self, doc_id: DocId, fields_load: &[String]) -> Result<Document> {
+        let i = self.leaf_index(doc_id)?;
+        let mut visitor = DocumentStoredFieldVisitor::new(fields_load);
+        self.leaves[i].document(doc_id - self.starts[i], &mut visitor)?;
+        Ok(visitor.document())
+    }
+
+    fn max_doc(&self) -> i32 {
+        self.starts[self.leaves.len()]
+    }
+
+    fn num_docs(&self) -> i32 {
+        self.num_docs
+    }
+
+    fn refresh(&self) -> Result<Option<Box<dyn IndexReader<Codec = C>>>> {
+        match self.open_if_changed()? {
+            Some(reader) => Ok(Some(Box::new(reader))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<D, C, MS, MP> fmt::Debug for FilterDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FilterDirectoryReader({:?})", self.reader)
+    }
+}
+
+impl<D, C, MS, MP> AsRef<dyn IndexReader<Codec = C>> for FilterDirectoryReader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn as_ref(&self) -> &(dyn IndexReader<Codec = C> + 'static) {
+        self
+    }
+}
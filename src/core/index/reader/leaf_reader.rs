@@ -21,6 +21,7 @@ use crate::core::codec::points::PointValues;
 use crate::core::codec::postings::FieldsProducer;
 use crate::core::codec::stored_fields::StoredFieldsReader;
 use crate::core::codec::term_vectors::TermVectorsReader;
+use crate::core::codec::vectors::{VectorValues, VectorsReader};
 use crate::core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
     CodecTVFields, CodecTVReader,
@@ -152,6 +153,21 @@ pub trait LeafReader {
     /// spatial searches, or None if there are no point fields.
     fn point_values(&self) -> Option<Self::PointsReader>;
 
+    /// Returns the reader of the per-document vectors of this leaf, or None if
+    /// it has no vector fields.
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        Ok(None)
+    }
+
+    /// Returns the vectors of `field`, or None if the field has no vectors in
+    /// this leaf.
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues>>> {
+        match self.vectors_reader()? {
+            Some(reader) => reader.vector_values(field),
+            None => Ok(None),
+        }
+    }
+
     /// Expert: Returns a key for this IndexReader, so CachingWrapperFilter can find
     // it again.
     // This key must not have equals()/hashCode() methods, so &quot;equals&quot; means
@@ -164,6 +180,14 @@ pub trait LeafReader {
     /// Expert: adds a CoreClosedListener to this reader's shared core
     fn add_core_drop_listener(&self, listener: Deferred);
 
+    /// Checks consistency of this reader by verifying the checksums of the
+    /// index files it reads from, returns `CorruptIndex` on a mismatch.
+    ///
+    /// Note that this may be costly, it reads the entire files.
+    fn check_integrity(&self) -> Result<()> {
+        Ok(())
+    }
+
     // TODO, currently we don't provide remove listener method
 
     // following methods are from `CodecReader`
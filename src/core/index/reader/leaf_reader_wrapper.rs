@@ -19,6 +19,7 @@ use crate::core::codec::points::{IntersectVisitor, PointValues, Relation};
 use crate::core::codec::postings::FieldsProducer;
 use crate::core::codec::stored_fields::StoredFieldsReader;
 use crate::core::codec::term_vectors::TermVectorsReader;
+use crate::core::codec::vectors::{VectorSimilarity, VectorValues, VectorsReader};
 use crate::core::codec::*;
 use crate::core::codec::{Fields, SeekStatus, TermIterator, Terms};
 use crate::core::codec::{PackedLongDocMap, SorterDocMap};
@@ -31,7 +32,7 @@ use crate::core::store::directory::Directory;
 use crate::core::store::io::{DataInput, IndexInput, IndexOutput, RAMOutputStream};
 use crate::core::util::external::Deferred;
 use crate::core::util::fst::{BytesStore, StoreBytesReader};
-use crate::core::util::{Bits, BitsMut, BitsRef, DocId};
+use crate::core::util::{Accountable, Bits, BitsMut, BitsRef, DocId, NamedAccountable};
 
 use crate::error::{Error::IllegalArgument, Result};
 
@@ -244,6 +245,14 @@ impl<D: Directory + 'static, C: Codec> LeafReader for MergeReaderWrapper<D, C> {
         self.reader.add_core_drop_listener(listener)
     }
 
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        self.reader.vectors_reader()
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        self.reader.check_integrity()
+    }
+
     fn is_codec_reader(&self) -> bool {
         false
     }
@@ -426,6 +435,19 @@ impl<T: LeafReader + 'static> LeafReader for SortingLeafReader<T> {
         self.reader.add_core_drop_listener(listener)
     }
 
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        Ok(self.reader.vectors_reader()?.map(|reader| {
+            Arc::new(SortingVectorsReader {
+                reader,
+                doc_map: Arc::clone(&self.doc_map),
+            }) as Arc<dyn VectorsReader>
+        }))
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        self.reader.check_integrity()
+    }
+
     fn is_codec_reader(&self) -> bool {
         false
     }
@@ -480,6 +502,60 @@ impl CachedNumericDVs {
     }
 }
 
+/// Maps the docs of the vectors of a leaf to their sorted order.
+struct SortingVectorsReader {
+    reader: Arc<dyn VectorsReader>,
+    doc_map: Arc<PackedLongDocMap>,
+}
+
+impl VectorsReader for SortingVectorsReader {
+    fn check_integrity(&self) -> Result<()> {
+        self.reader.check_integrity()
+    }
+
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues>>> {
+        Ok(self.reader.vector_values(field)?.map(|values| {
+            Box::new(SortingVectorValues {
+                values,
+                doc_map: Arc::clone(&self.doc_map),
+            }) as Box<dyn VectorValues>
+        }))
+    }
+}
+
+/// Vectors whose ordinals keep the unsorted doc order, so they are not
+/// ordered by doc.
+struct SortingVectorValues {
+    values: Box<dyn VectorValues>,
+    doc_map: Arc<PackedLongDocMap>,
+}
+
+impl VectorValues for SortingVectorValues {
+    fn dimension(&self) -> usize {
+        self.values.dimension()
+    }
+
+    fn similarity(&self) -> VectorSimilarity {
+        self.values.similarity()
+    }
+
+    fn size(&self) -> usize {
+        self.values.size()
+    }
+
+    fn doc_id(&self, ord: usize) -> Result<DocId> {
+        Ok(self.doc_map.old_to_new(self.values.doc_id(ord)?))
+    }
+
+    fn vector(&self, ord: usize) -> Result<Vec<f32>> {
+        self.values.vector(ord)
+    }
+
+    fn get(&self, doc_id: DocId) -> Result<Option<Vec<f32>>> {
+        self.values.get(self.doc_map.new_to_old(doc_id))
+    }
+}
+
 pub struct SortingFields<T: Fields> {
     fields: T,
     doc_map: Arc<PackedLongDocMap>,
@@ -518,6 +594,16 @@ impl<T: Fields> Fields for SortingFields<T> {
     }
 }
 
+impl<T: Fields + Accountable> Accountable for SortingFields<T> {
+    fn ram_bytes_used(&self) -> usize {
+        self.fields.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.fields.child_resources()
+    }
+}
+
 impl<T: FieldsProducer> FieldsProducer for SortingFields<T> {
     fn check_integrity(&self) -> Result<()> {
         Ok(())
@@ -1243,6 +1329,10 @@ impl<P: PointValues + 'static> PointValues for SortingPointValues<P> {
         self.point_values.num_dimensions(field_name)
     }
 
+    fn num_index_dimensions(&self, field_name: &str) -> Result<usize> {
+        self.point_values.num_index_dimensions(field_name)
+    }
+
     fn bytes_per_dimension(&self, field_name: &str) -> Result<usize> {
         self.point_values.bytes_per_dimension(field_name)
     }
@@ -1478,6 +1568,14 @@ impl<T: LeafReader + 'static> LeafReader for SlowCodecReaderWrapper<T> {
         self.reader.add_core_drop_listener(listener)
     }
 
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        self.reader.vectors_reader()
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        self.reader.check_integrity()
+    }
+
     fn is_codec_reader(&self) -> bool {
         true
     }
@@ -1653,6 +1751,13 @@ impl<T: LeafReader> LeafReaderAsDocValuesProducer<T> {
     }
 }
 
+impl<T: LeafReader> Accountable for LeafReaderAsDocValuesProducer<T> {
+    fn ram_bytes_used(&self) -> usize {
+        // the values are owned and accounted by the wrapped reader
+        mem::size_of::<Self>()
+    }
+}
+
 impl<T: LeafReader + 'static> DocValuesProducer for LeafReaderAsDocValuesProducer<T> {
     fn get_numeric(&self, field_info: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
         Ok(Arc::new(LeafReaderAsDVFieldProvider {
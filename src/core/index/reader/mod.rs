@@ -31,9 +31,32 @@ mod index_lookup;
 
 pub use self::index_lookup::*;
 
+mod soft_deletes;
+
+pub use self::soft_deletes::*;
+
+mod multi_reader;
+
+pub use self::multi_reader::*;
+
+mod parallel_leaf_reader;
+
+pub use self::parallel_leaf_reader::*;
+
+mod filter_reader;
+
+pub use self::filter_reader::*;
+
+mod term_vector;
+
+pub use self::term_vector::*;
+
 use crate::core::codec::Codec;
 use crate::core::codec::CodecTVFields;
+use crate::core::codec::{TermIterator, Terms};
 use crate::core::doc::Document;
+use crate::core::index::Term;
+use crate::core::search::{CollectionStatistics, TermStatistics};
 use crate::core::util::DocId;
 
 use crate::Result;
@@ -68,6 +91,16 @@ pub trait IndexReader {
     type Codec: Codec;
     fn leaves(&self) -> Vec<LeafReaderContext<'_, Self::Codec>>;
     fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<Self::Codec>>>;
+
+    /// Returns the term vector of `field` for the doc `doc_id`, with the
+    /// freq, positions, offsets and payloads of each term as far as they were
+    /// stored, or `None` if the doc has no term vector for the field.
+    fn get_term_vector(&self, doc_id: DocId, field: &str) -> Result<Option<TermVector>> {
+        match self.term_vector(doc_id)? {
+            Some(fields) => TermVector::read(&fields, field),
+            None => Ok(None),
+        }
+    }
     fn document(&self, doc_id: DocId, fields: &[String]) -> Result<Document>;
     fn max_doc(&self) -> i32;
     fn num_docs(&self) -> i32;
@@ -103,6 +136,107 @@ pub trait IndexReader {
     fn refresh(&self) -> Result<Option<Box<dyn IndexReader<Codec = Self::Codec>>>> {
         Ok(None)
     }
+
+    /// Returns the number of documents containing `term`, summed over all the
+    /// leaves.
+    ///
+    /// Like all the statistics below, this doesn't take deletions into
+    /// account: the deleted documents are counted until they are merged away.
+    fn doc_freq(&self, term: &Term) -> Result<i32> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            total += leaf.reader.doc_freq(term)?;
+        }
+        Ok(total)
+    }
+
+    /// Returns the total number of occurrences of `term` across all the
+    /// documents, or `-1` if the frequencies are not indexed for its field.
+    fn total_term_freq(&self, term: &Term) -> Result<i64> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader.terms(&term.field)? {
+                let mut terms_iter = terms.iterator()?;
+                if terms_iter.seek_exact(term.bytes())? {
+                    let freq = terms_iter.total_term_freq()?;
+                    if freq == -1 {
+                        return Ok(-1);
+                    }
+                    total += freq;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Returns the `TermStatistics` of `term` over the whole index.
+    fn term_statistics(&self, term: &Term) -> Result<TermStatistics> {
+        Ok(TermStatistics::new(
+            term.bytes().to_vec(),
+            i64::from(self.doc_freq(term)?),
+            self.total_term_freq(term)?,
+        ))
+    }
+
+    /// Returns the number of documents that have at least one term for
+    /// `field`, or `-1` if a leaf doesn't store this statistic.
+    fn doc_count(&self, field: &str) -> Result<i32> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader.terms(field)? {
+                let count = terms.doc_count()?;
+                if count == -1 {
+                    return Ok(-1);
+                }
+                total += count;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Returns the sum of the `doc_freq` of all the terms of `field`, or `-1`
+    /// if a leaf doesn't store this statistic.
+    fn sum_doc_freq(&self, field: &str) -> Result<i64> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader.terms(field)? {
+                let freq = terms.sum_doc_freq()?;
+                if freq == -1 {
+                    return Ok(-1);
+                }
+                total += freq;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Returns the sum of the `total_term_freq` of all the terms of `field`,
+    /// or `-1` if the frequencies are not indexed for this field.
+    fn sum_total_term_freq(&self, field: &str) -> Result<i64> {
+        let mut total = 0;
+        for leaf in self.leaves() {
+            if let Some(terms) = leaf.reader.terms(field)? {
+                let freq = terms.sum_total_term_freq()?;
+                if freq == -1 {
+                    return Ok(-1);
+                }
+                total += freq;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Returns the `CollectionStatistics` of `field` over the whole index.
+    fn collection_statistics(&self, field: &str) -> Result<CollectionStatistics> {
+        Ok(CollectionStatistics::new(
+            field.to_string(),
+            0,
+            i64::from(self.max_doc()),
+            i64::from(self.doc_count(field)?),
+            self.sum_total_term_freq(field)?,
+            self.sum_doc_freq(field)?,
+        ))
+    }
 }
 
 #[derive(Copy, Clone)]
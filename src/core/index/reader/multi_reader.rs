@@ -0,0 +1,163 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{Codec, CodecTVFields};
+use crate::core::doc::Document;
+use crate::core::index::reader::{IndexReader, LeafReaderContext};
+use crate::core::util::DocId;
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// A composite `IndexReader` presenting several independent indexes as a
+/// single one, e.g. to search the daily partitions of a log index at once.
+///
+/// The doc ids of the sub-readers are re-based one after the other: the
+/// documents of the i-th sub-reader start at the sum of the `max_doc` of
+/// the previous ones.
+pub struct MultiReader<C: Codec> {
+    sub_readers: Vec<Arc<dyn IndexReader<Codec = C>>>,
+    // doc base of each sub-reader, followed by max_doc
+    starts: Vec<DocId>,
+    num_docs: i32,
+}
+
+impl<C: Codec> MultiReader<C> {
+    pub fn new(sub_readers: Vec<Arc<dyn IndexReader<Codec = C>>>) -> Result<Self> {
+        let mut starts = Vec::with_capacity(sub_readers.len() + 1);
+        let mut max_doc: i64 = 0;
+        let mut num_docs = 0;
+        for reader in &sub_readers {
+            starts.push(max_doc as DocId);
+            max_doc += i64::from(reader.max_doc());
+            if max_doc > i64::from(i32::MAX) {
+                return Err(IllegalArgument(format!(
+                    "too many documents: the sub-readers hold more than {} documents",
+                    i32::MAX
+                )));
+            }
+            num_docs += reader.num_docs();
+        }
+        starts.push(max_doc as DocId);
+
+        Ok(MultiReader {
+            sub_readers,
+            starts,
+            num_docs,
+        })
+    }
+
+    pub fn sub_readers(&self) -> &[Arc<dyn IndexReader<Codec = C>>] {
+        &self.sub_readers
+    }
+
+    /// Returns the index of the sub-reader holding `doc_id`.
+    fn sub_index(&self, doc_id: DocId) -> Result<usize> {
+        if doc_id < 0 || doc_id >= self.max_doc() {
+            return Err(IllegalArgument(format!(
+                "doc_id {} invalid: [max_doc={}]",
+                doc_id,
+                self.max_doc()
+            )));
+        }
+        // skip the empty sub-readers sharing the doc base of the next one
+        Ok(match self.starts.binary_search(&doc_id) {
+            Ok(mut i) => {
+                while self.starts[i + 1] == doc_id {
+                    i += 1;
+                }
+                i
+            }
+            Err(i) => i - 1,
+        })
+    }
+}
+
+impl<C: Codec> IndexReader for MultiReader<C> {
+    type Codec = C;
+
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        let mut leaves = Vec::new();
+        for (reader, start) in self.sub_readers.iter().zip(&self.starts) {
+            for leaf in reader.leaves() {
+                let ord = leaves.len();
+                leaves.push(LeafReaderContext::new(
+                    self,
+                    leaf.reader,
+                    ord,
+                    start + leaf.doc_base,
+                ));
+            }
+        }
+        leaves
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<C>>> {
+        let i = self.sub_index(doc_id)?;
+        self.sub_readers[i].term_vector(doc_id - self.starts[i])
+    }
+
+    fn document(&self, doc_id: DocId, fields_load: &[String]) -> Result<Document> {
+        let i = self.sub_index(doc_id)?;
+        self.sub_readers[i].document(doc_id - self.starts[i], fields_load)
+    }
+
+    fn max_doc(&self) -> i32 {
+        self.starts[self.sub_readers.len()]
+    }
+
+    fn num_docs(&self) -> i32 {
+        self.num_docs
+    }
+
+    /// Refreshes every sub-reader, the unchanged ones are shared with the new
+    /// `MultiReader`.
+    fn refresh(&self) -> Result<Option<Box<dyn IndexReader<Codec = C>>>> {
+        let mut changed = false;
+        let mut sub_readers = Vec::with_capacity(self.sub_readers.len());
+        for reader in &self.sub_readers {
+            match reader.refresh()? {
+                Some(new_reader) => {
+                    changed = true;
+                    sub_readers.push(Arc::from(new_reader));
+                }
+                None => sub_readers.push(Arc::clone(reader)),
+            }
+        }
+        if changed {
+            Ok(Some(Box::new(MultiReader::new(sub_readers)?)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: Codec> fmt::Debug for MultiReader<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let leaf_readers: Vec<&str> = self.leaves().iter().map(|l| l.reader.name()).collect();
+        write!(
+            f,
+            "MultiReader(sub_readers: {}, leaves: {:?})",
+            self.sub_readers.len(),
+            leaf_readers
+        )
+    }
+}
+
+impl<C: Codec> AsRef<dyn IndexReader<Codec = C>> for MultiReader<C> {
+    fn as_ref(&self) -> &(dyn IndexReader<Codec = C> + 'static) {
+        self
+    }
+}
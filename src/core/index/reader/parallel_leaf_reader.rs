@@ -0,0 +1,359 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::{
+    BinaryDocValues, DocValuesProducer, NumericDocValues, SortedDocValues, SortedNumericDocValues,
+    SortedSetDocValues,
+};
+use crate::core::codec::field_infos::{FieldInfo, FieldInfos};
+use crate::core::codec::postings::PerFieldFieldsReader;
+use crate::core::codec::vectors::VectorValues;
+use crate::core::codec::{
+    Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
+    CodecTVFields, CodecTVReader,
+};
+use crate::core::doc::{Document, DocumentStoredFieldVisitor, StoredFieldVisitor};
+use crate::core::index::reader::{IndexReader, LeafReader, LeafReaderContext, SearchLeafReader};
+use crate::core::search::sort_field::Sort;
+use crate::core::util::external::Deferred;
+use crate::core::util::{id2str, random_id, BitsMut, BitsRef, DocId};
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// A `LeafReader` which reads multiple parallel leaves, e.g. the segments of
+/// several indexes holding different fields of the same documents. This is
+/// useful to add fields to an existing index without reindexing it: the new
+/// fields are indexed in another index, in the same order, and both indexes
+/// are read side by side.
+///
+/// All the leaves must have the same `max_doc` and the same index sort. Each
+/// field is read from the first leaf that has it, except for the stored
+/// fields which are visited in all the leaves. The deleted documents are the
+/// ones of the first leaf. At most one leaf may store term vectors and at
+/// most one may index points.
+///
+/// Use a `MultiReader` over several `ParallelLeafReader` to read parallel
+/// indexes made of more than one segment.
+pub struct ParallelLeafReader<C: Codec> {
+    readers: Vec<Arc<SearchLeafReader<C>>>,
+    field_infos: Arc<FieldInfos>,
+    // the index of the reader each field is read from
+    field_to_reader: HashMap<String, usize>,
+    fields: CodecFieldsProducer<C>,
+    tv_reader: Option<usize>,
+    points_reader: Option<usize>,
+    name: String,
+    core_cache_key: String,
+    core_dropped_listeners: Mutex<Vec<Deferred>>,
+}
+
+impl<C> ParallelLeafReader<C>
+where
+    C: Codec<FieldsProducer = Arc<PerFieldFieldsReader>>,
+{
+    pub fn new(readers: Vec<Arc<SearchLeafReader<C>>>) -> Result<Self> {
+        if readers.is_empty() {
+            return Err(IllegalArgument(
+                "ParallelLeafReader needs at least one reader".into(),
+            ));
+        }
+        let max_doc = readers[0].max_doc();
+        for reader in &readers[1..] {
+            if reader.max_doc() != max_doc {
+                return Err(IllegalArgument(format!(
+                    "all readers must have the same max_doc: {} has {} while {} has {}",
+                    readers[0].name(),
+                    max_doc,
+                    reader.name(),
+                    reader.max_doc()
+                )));
+            }
+            if reader.index_sort() != readers[0].index_sort() {
+                return Err(IllegalArgument(format!(
+                    "all readers must have the same index sort: {} has {:?} while {} has {:?}",
+                    readers[0].name(),
+                    readers[0].index_sort(),
+                    reader.name(),
+                    reader.index_sort()
+                )));
+            }
+        }
+
+        let mut infos = Vec::new();
+        let mut field_to_reader = HashMap::new();
+        let mut tv_reader = None;
+        let mut points_reader = None;
+        for (i, reader) in readers.iter().enumerate() {
+            let reader_infos = reader.field_infos();
+            for info in reader_infos.by_number.values() {
+                if !field_to_reader.contains_key(&info.name) {
+                    field_to_reader.insert(info.name.clone(), i);
+                    let mut info = FieldInfo::clone(info);
+                    info.number = infos.len() as u32;
+                    infos.push(info);
+                }
+            }
+            if reader_infos.has_vectors {
+                if tv_reader.is_some() {
+                    return Err(IllegalArgument(
+                        "only one of the parallel readers can store term vectors".into(),
+                    ));
+                }
+                tv_reader = Some(i);
+            }
+            if reader_infos.has_point_values {
+                if points_reader.is_some() {
+                    return Err(IllegalArgument(
+                        "only one of the parallel readers can index points".into(),
+                    ));
+                }
+                points_reader = Some(i);
+            }
+        }
+
+        let name = format!(
+            "parallel({})",
+            readers
+                .iter()
+                .map(|r| r.name())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let sub_fields = readers
+            .iter()
+            .map(|r| r.fields())
+            .collect::<Result<Vec<_>>>()?;
+        let sub_fields: Vec<&PerFieldFieldsReader> = sub_fields.iter().map(Arc::as_ref).collect();
+        let fields = Arc::new(PerFieldFieldsReader::from_readers(
+            name.clone(),
+            &sub_fields,
+        ));
+        let core_cache_key = format!("{}@{}", name, id2str(&random_id()));
+
+        Ok(ParallelLeafReader {
+            readers,
+            field_infos: Arc::new(FieldInfos::new(infos)?),
+            field_to_reader,
+            fields,
+            tv_reader,
+            points_reader,
+            name,
+            core_cache_key,
+            core_dropped_listeners: Mutex::new(vec![]),
+        })
+    }
+}
+
+impl<C: Codec> ParallelLeafReader<C> {
+    pub fn parallel_readers(&self) -> &[Arc<SearchLeafReader<C>>] {
+        &self.readers
+    }
+
+    // the reader holding `field`, the first one if none has it so that the
+    // missing fields are handled the same as by a single reader
+    fn reader_for_field(&self, field: &str) -> &SearchLeafReader<C> {
+        let idx = self.field_to_reader.get(field).copied().unwrap_or(0);
+        self.readers[idx].as_ref()
+    }
+}
+
+impl<C: Codec> LeafReader for ParallelLeafReader<C> {
+    type Codec = C;
+    type FieldsProducer = CodecFieldsProducer<C>;
+    type TVFields = CodecTVFields<C>;
+    type TVReader = Arc<CodecTVReader<C>>;
+    type StoredReader = Arc<CodecStoredFieldsReader<C>>;
+    type NormsReader = Arc<CodecNormsProducer<C>>;
+    type PointsReader = Arc<CodecPointsReader<C>>;
+
+    fn codec(&self) -> &Self::Codec {
+        self.readers[0].codec()
+    }
+
+    fn fields(&self) -> Result<Self::FieldsProducer> {
+        Ok(self.fields.clone())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<Self::TVFields>> {
+        match self.tv_reader {
+            Some(idx) => self.readers[idx].term_vector(doc_id),
+            None => Ok(None),
+        }
+    }
+
+    fn document(&self, doc_id: DocId, visitor: &mut dyn StoredFieldVisitor) -> Result<()> {
+        for reader in &self.readers {
+            reader.document(doc_id, visitor)?;
+        }
+        Ok(())
+    }
+
+    fn live_docs(&self) -> BitsRef {
+        self.readers[0].live_docs()
+    }
+
+    fn field_info(&self, field: &str) -> Option<&FieldInfo> {
+        self.field_infos.field_info_by_name(field)
+    }
+
+    fn field_infos(&self) -> &FieldInfos {
+        &self.field_infos
+    }
+
+    fn clone_field_infos(&self) -> Arc<FieldInfos> {
+        Arc::clone(&self.field_infos)
+    }
+
+    fn max_doc(&self) -> DocId {
+        self.readers[0].max_doc()
+    }
+
+    fn num_docs(&self) -> i32 {
+        self.readers[0].num_docs()
+    }
+
+    fn get_numeric_doc_values(&self, field: &str) -> Result<Box<dyn NumericDocValues>> {
+        self.reader_for_field(field).get_numeric_doc_values(field)
+    }
+
+    fn get_binary_doc_values(&self, field: &str) -> Result<Box<dyn BinaryDocValues>> {
+        self.reader_for_field(field).get_binary_doc_values(field)
+    }
+
+    fn get_sorted_doc_values(&self, field: &str) -> Result<Box<dyn SortedDocValues>> {
+        self.reader_for_field(field).get_sorted_doc_values(field)
+    }
+
+    fn get_sorted_numeric_doc_values(
+        &self,
+        field: &str,
+    ) -> Result<Box<dyn SortedNumericDocValues>> {
+        self.reader_for_field(field)
+            .get_sorted_numeric_doc_values(field)
+    }
+
+    fn get_sorted_set_doc_values(&self, field: &str) -> Result<Box<dyn SortedSetDocValues>> {
+        self.reader_for_field(field)
+            .get_sorted_set_doc_values(field)
+    }
+
+    fn norm_values(&self, field: &str) -> Result<Option<Box<dyn NumericDocValues>>> {
+        self.reader_for_field(field).norm_values(field)
+    }
+
+    fn get_docs_with_field(&self, field: &str) -> Result<Box<dyn BitsMut>> {
+        self.reader_for_field(field).get_docs_with_field(field)
+    }
+
+    fn point_values(&self) -> Option<Self::PointsReader> {
+        self.points_reader
+            .and_then(|idx| self.readers[idx].point_values())
+    }
+
+    fn vector_values(&self, field: &str) -> Result<Option<Box<dyn VectorValues>>> {
+        self.reader_for_field(field).vector_values(field)
+    }
+
+    fn core_cache_key(&self) -> &str {
+        &self.core_cache_key
+    }
+
+    fn index_sort(&self) -> Option<&Sort> {
+        self.readers[0].index_sort()
+    }
+
+    fn add_core_drop_listener(&self, listener: Deferred) {
+        self.core_dropped_listeners.lock().unwrap().push(listener);
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        for reader in &self.readers {
+            reader.check_integrity()?;
+        }
+        Ok(())
+    }
+
+    fn is_codec_reader(&self) -> bool {
+        false
+    }
+
+    // following methods are from `CodecReader`
+    fn store_fields_reader(&self) -> Result<Self::StoredReader> {
+        unreachable!()
+    }
+
+    fn term_vectors_reader(&self) -> Result<Option<Self::TVReader>> {
+        unreachable!()
+    }
+
+    fn norms_reader(&self) -> Result<Option<Self::NormsReader>> {
+        unreachable!()
+    }
+
+    fn doc_values_reader(&self) -> Result<Option<Arc<dyn DocValuesProducer>>> {
+        unreachable!()
+    }
+
+    fn postings_reader(&self) -> Result<Self::FieldsProducer> {
+        unreachable!()
+    }
+}
+
+impl<C: Codec> IndexReader for ParallelLeafReader<C> {
+    type Codec = C;
+
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        vec![LeafReaderContext::new(self, self, 0, 0)]
+    }
+
+    fn term_vector(&self, doc_id: DocId) -> Result<Option<CodecTVFields<C>>> {
+        LeafReader::term_vector(self, doc_id)
+    }
+
+    fn document(&self, doc_id: DocId, fields: &[String]) -> Result<Document> {
+        let mut visitor = DocumentStoredFieldVisitor::new(fields);
+        LeafReader::document(self, doc_id, &mut visitor)?;
+        Ok(visitor.document())
+    }
+
+    fn max_doc(&self) -> i32 {
+        LeafReader::max_doc(self)
+    }
+
+    fn num_docs(&self) -> i32 {
+        LeafReader::num_docs(self)
+    }
+}
+
+impl<C: Codec> AsRef<dyn IndexReader<Codec = C>> for ParallelLeafReader<C> {
+    fn as_ref(&self) -> &(dyn IndexReader<Codec = C> + 'static) {
+        self
+    }
+}
+
+impl<C: Codec> Drop for ParallelLeafReader<C> {
+    fn drop(&mut self) {
+        let listeners = mem::take(&mut *self.core_dropped_listeners.lock().unwrap());
+        for listener in listeners {
+            listener.call();
+        }
+    }
+}
@@ -17,16 +17,16 @@ use std::fmt;
 use std::mem;
 use std::num::NonZero;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
 
 use crate::error::{Error::IllegalArgument, Error::IllegalState, Result};
 use thread_local::{CachedThreadLocal, ThreadLocal};
 
 use crate::core::codec::doc_values::{
-    BinaryDocValues, BinaryDocValuesProvider, DocValuesFormat, DocValuesProducer,
-    DocValuesProviderEnum, NumericDocValues, NumericDocValuesProvider, SortedDocValues,
-    SortedDocValuesProvider, SortedNumericDocValues, SortedNumericDocValuesProvider,
-    SortedSetDocValues, SortedSetDocValuesProvider,
+    distinct_producers, BinaryDocValues, BinaryDocValuesProvider, DocValuesFormat,
+    DocValuesProducer, DocValuesProviderEnum, NumericDocValues, NumericDocValuesProvider,
+    SortedDocValues, SortedDocValuesProvider, SortedNumericDocValues,
+    SortedNumericDocValuesProvider, SortedSetDocValues, SortedSetDocValuesProvider,
 };
 
 use crate::core::codec::field_infos::{FieldInfo, FieldInfos, FieldInfosFormat};
@@ -36,18 +36,24 @@ use crate::core::codec::postings::PostingsFormat;
 use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfo, SegmentReadState};
 use crate::core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsReader};
 use crate::core::codec::term_vectors::{TermVectorsFormat, TermVectorsReader};
+use crate::core::codec::vectors::{VectorsFormat, VectorsReader};
 use crate::core::codec::{
     Codec, CodecFieldsProducer, CodecNormsProducer, CodecPointsReader, CodecStoredFieldsReader,
-    CodecTVFields, CodecTVReader, CompoundFormat, LiveDocsFormat, Lucene50CompoundReader,
+    CodecTVFields, CodecTVReader, CodecVectorsReader, CompoundFormat, LiveDocsFormat,
+    Lucene50CompoundReader,
 };
 use crate::core::doc::{DocValuesType, Document, DocumentStoredFieldVisitor, StoredFieldVisitor};
 use crate::core::index::reader::{IndexReader, LeafReader, LeafReaderContext};
 use crate::core::search::sort_field::Sort;
 use crate::core::store::directory::Directory;
 use crate::core::store::io::{BufferedChecksumIndexInput, IndexInput};
+use crate::core::store::lock::Lock;
 use crate::core::store::IOContext;
 use crate::core::util::external::Deferred;
-use crate::core::util::{id2str, random_id, to_base36, BitsMut, BitsRef, DocId, MatchAllBits};
+use crate::core::util::{
+    id2str, random_id, to_base36, Accountable, BitsMut, BitsRef, DocId, MatchAllBits,
+    NamedAccountable,
+};
 
 /// Holds core readers that are shared (unchanged) when
 /// SegmentReader is cloned or reopened
@@ -64,6 +70,7 @@ pub struct SegmentCoreReaders<D: Directory, C: Codec> {
     /// in the case of DV updates, SR may hold a newer version.
     pub core_field_infos: Arc<FieldInfos>,
     pub points_reader: Option<Arc<CodecPointsReader<C>>>,
+    pub vectors_reader: Option<Arc<CodecVectorsReader<C>>>,
     pub core_dropped_listeners: Mutex<Vec<Deferred>>,
     pub core_cache_key: String,
 }
@@ -140,6 +147,13 @@ impl<D: Directory, C: Codec> SegmentCoreReaders<D, C> {
         } else {
             None
         };
+        let vectors_reader = if core_field_infos.has_vector_values {
+            Some(Arc::new(
+                codec.vectors_format().fields_reader(&segment_read_state)?,
+            ))
+        } else {
+            None
+        };
         // TODO process norms_producers/store_fields_reader/term vectors
 
         Ok(SegmentCoreReaders {
@@ -152,6 +166,7 @@ impl<D: Directory, C: Codec> SegmentCoreReaders<D, C> {
             cfs_reader,
             core_field_infos,
             points_reader,
+            vectors_reader,
             core_dropped_listeners: Mutex::new(vec![]),
             core_cache_key: format!("{}@{}", si.name, id2str(&random_id())),
         })
@@ -281,6 +296,13 @@ impl<D: Directory> Directory for CfsDirectory<D> {
         }
     }
 
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        match self {
+            CfsDirectory::Raw(d) => d.obtain_lock(name),
+            CfsDirectory::Cfs(d) => d.obtain_lock(name),
+        }
+    }
+
     fn copy_from<D1: Directory>(
         &self,
         from: &D1,
@@ -474,6 +496,28 @@ impl SegmentDocValues {
     }
 }
 
+impl Accountable for SegmentDocValues {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + distinct_producers(&self.dv_producers_by_field)
+                .iter()
+                .map(|(p, _)| p.ram_bytes_used())
+                .sum::<usize>()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources: Vec<NamedAccountable> = distinct_producers(&self.dv_producers_by_field)
+            .into_iter()
+            .map(|(p, mut fields)| {
+                fields.sort_unstable();
+                NamedAccountable::new(format!("fields {}", fields.join(", ")), p.as_ref())
+            })
+            .collect();
+        resources.sort_by(|a, b| a.name.cmp(&b.name));
+        resources
+    }
+}
+
 impl DocValuesProducer for SegmentDocValues {
     fn get_numeric(&self, field_info: &FieldInfo) -> Result<Arc<dyn NumericDocValuesProvider>> {
         let dv_producer = self.dv_producers_by_field.get(&field_info.name).unwrap();
@@ -656,6 +700,33 @@ impl<D: Directory + 'static, C: Codec> SegmentReader<D, C> {
         ))
     }
 
+    /// Returns a new reader over the same segment with different live docs.
+    ///
+    /// The clone shares the core readers, the field infos and the pool of
+    /// doc values producers with `self`, so nothing is reopened. Used for NRT
+    /// refreshes where only the deletes of a segment changed.
+    pub fn with_live_docs(
+        &self,
+        si: Arc<SegmentCommitInfo<D, C>>,
+        live_docs: BitsRef,
+        num_docs: i32,
+        is_nrt: bool,
+    ) -> SegmentReader<D, C> {
+        debug_assert_eq!(si.info.name, self.si.info.name);
+        SegmentReader {
+            si,
+            live_docs,
+            num_docs,
+            core: Arc::clone(&self.core),
+            is_nrt,
+            field_infos: Arc::clone(&self.field_infos),
+            dv_producers_preload: Arc::clone(&self.dv_producers_preload),
+            dv_producer_local: ThreadLocal::new(),
+            dv_providers_preload: Arc::clone(&self.dv_providers_preload),
+            dv_provider_local: CachedThreadLocal::new(),
+        }
+    }
+
     pub fn max_docs(&self) -> i32 {
         self.si.info.max_doc()
     }
@@ -817,6 +888,63 @@ impl<D: Directory + 'static, C: Codec> SegmentReader<D, C> {
     }
 }
 
+impl<D: Directory, C: Codec> Drop for SegmentReader<D, C> {
+    fn drop(&mut self) {
+        // hand the per-thread doc values back to the pool when other readers
+        // of this segment are still alive, so they don't have to reopen them
+        if Arc::strong_count(&self.dv_producers_preload) > 1 {
+            if let Ok(mut pool) = self.dv_producers_preload.write() {
+                let locals = mem::replace(&mut self.dv_producer_local, ThreadLocal::new());
+                pool.extend(locals.into_iter().map(|p| *p));
+            }
+        }
+        if Arc::strong_count(&self.dv_providers_preload) > 1 {
+            if let Ok(mut pool) = self.dv_providers_preload.write() {
+                let locals = mem::replace(&mut self.dv_provider_local, CachedThreadLocal::new());
+                pool.extend(locals.into_iter().map(|p| *p));
+            }
+        }
+    }
+}
+
+impl<D: Directory + 'static, C: Codec> SegmentReader<D, C> {
+    /// The doc values producers held by this reader: the one of the current
+    /// thread and the preloaded ones not yet handed out to a thread.
+    fn doc_values_ram_bytes_used(&self) -> usize {
+        let local = self
+            .dv_producer_local
+            .get()
+            .map_or(0, |p| p.ram_bytes_used());
+        let preloaded = self
+            .dv_producers_preload
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|p| p.ram_bytes_used())
+            .sum::<usize>();
+        local + preloaded
+    }
+}
+
+impl<D: Directory + 'static, C: Codec> Accountable for SegmentReader<D, C> {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.core.fields.ram_bytes_used()
+            + self.doc_values_ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let mut resources = vec![NamedAccountable::new("postings", &self.core.fields)];
+        if self.field_infos.has_doc_values {
+            resources.push(NamedAccountable::with_bytes(
+                "doc values",
+                self.doc_values_ram_bytes_used(),
+            ));
+        }
+        resources
+    }
+}
+
 impl<D: Directory + 'static, C: Codec> IndexReader for SegmentReader<D, C> {
     type Codec = C;
     fn leaves(&self) -> Vec<LeafReaderContext<C>> {
@@ -1125,6 +1253,18 @@ where
         self.core.add_core_drop_listener(listener)
     }
 
+    fn vectors_reader(&self) -> Result<Option<Arc<dyn VectorsReader>>> {
+        Ok(self
+            .core
+            .vectors_reader
+            .clone()
+            .map(|reader| reader as Arc<dyn VectorsReader>))
+    }
+
+    fn check_integrity(&self) -> Result<()> {
+        self.si.check_integrity().map(|_| ())
+    }
+
     fn is_codec_reader(&self) -> bool {
         true
     }
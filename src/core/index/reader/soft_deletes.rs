@@ -0,0 +1,89 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::doc::DocValuesType;
+use crate::core::index::reader::{LeafReader, SegmentReader};
+use crate::core::store::directory::Directory;
+use crate::core::util::{BitSet, Bits, BitsRef, FixedBitSet};
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::sync::Arc;
+
+/// Returns the live docs of `reader` that are soft deleted, i.e. that have a
+/// non-zero value in the numeric doc values field `field`, or `None` if there
+/// are no such docs.
+///
+/// Docs without a value for the field read as `0` and are live, this is also
+/// what a doc values update writes for the docs it doesn't touch.
+pub fn soft_deleted_docs<R: LeafReader + ?Sized>(
+    reader: &R,
+    field: &str,
+) -> Result<Option<FixedBitSet>> {
+    match reader.field_info(field) {
+        None => return Ok(None),
+        Some(fi) if fi.doc_values_type == DocValuesType::Numeric => {}
+        Some(fi) => {
+            return Err(IllegalArgument(format!(
+                "soft deletes field [{}] must be a numeric doc values field, got {:?}",
+                field, fi.doc_values_type
+            )));
+        }
+    }
+
+    let live_docs = reader.live_docs();
+    let values = reader.get_numeric_doc_values(field)?;
+    let max_doc = reader.max_doc();
+    let mut soft_deleted: Option<FixedBitSet> = None;
+    for doc in 0..max_doc {
+        if live_docs.get(doc as usize) && values.get(doc)? != 0 {
+            soft_deleted
+                .get_or_insert_with(|| FixedBitSet::new(max_doc as usize))
+                .set(doc as usize);
+        }
+    }
+    Ok(soft_deleted)
+}
+
+/// Returns a reader over the same segment as `reader` that additionally
+/// hides the docs soft deleted through the field `field`, or `reader`
+/// itself if it has no soft deleted docs.
+pub fn apply_soft_deletes<D: Directory + 'static, C: Codec>(
+    reader: Arc<SegmentReader<D, C>>,
+    field: &str,
+) -> Result<Arc<SegmentReader<D, C>>> {
+    let soft_deleted = match soft_deleted_docs(reader.as_ref(), field)? {
+        Some(bits) => bits,
+        None => return Ok(reader),
+    };
+
+    let hard_live_docs = reader.live_docs();
+    let mut live_docs = FixedBitSet::new(reader.max_docs() as usize);
+    let mut num_docs = 0;
+    for doc in 0..live_docs.len() {
+        if hard_live_docs.get(doc) && !soft_deleted.get(doc) {
+            live_docs.set(doc);
+            num_docs += 1;
+        }
+    }
+
+    let live_docs: BitsRef = Arc::new(live_docs);
+    Ok(Arc::new(SegmentReader::build_from(
+        Arc::clone(&reader.si),
+        reader.as_ref(),
+        live_docs,
+        num_docs,
+        reader.is_nrt,
+    )?))
+}
@@ -0,0 +1,127 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{Fields, PostingIterator, PostingIteratorFlags, TermIterator, Terms};
+use crate::core::search::DocIterator;
+use crate::Result;
+
+use std::str;
+
+/// The term vector of a field of a document, see `IndexReader::get_term_vector`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermVector {
+    pub field: String,
+    /// True if the positions of the terms were stored.
+    pub has_positions: bool,
+    /// True if the start and end offsets of the terms were stored.
+    pub has_offsets: bool,
+    /// True if the payloads of the terms were stored.
+    pub has_payloads: bool,
+    /// The distinct terms of the field, sorted by their bytes.
+    pub terms: Vec<TermVectorTerm>,
+}
+
+/// A term of a `TermVector` with its occurrences in the field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermVectorTerm {
+    pub term: Vec<u8>,
+    /// Number of occurrences of the term in the field.
+    pub freq: i32,
+    /// One entry per occurrence, in order, empty if neither the positions
+    /// nor the offsets were stored.
+    pub occurrences: Vec<TermVectorOccurrence>,
+}
+
+/// An occurrence of a `TermVectorTerm`, the values that weren't stored are
+/// `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermVectorOccurrence {
+    pub position: Option<i32>,
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+    pub payload: Option<Vec<u8>>,
+}
+
+impl TermVector {
+    /// Reads the term vector of `field` from the term vectors `fields` of a
+    /// single document, returns `None` if the field has none.
+    pub fn read<F: Fields>(fields: &F, field: &str) -> Result<Option<TermVector>> {
+        let terms = match fields.terms(field)? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+        let has_positions = terms.has_positions()?;
+        let has_offsets = terms.has_offsets()?;
+        let has_payloads = terms.has_payloads()?;
+        let flags = if has_positions || has_offsets {
+            PostingIteratorFlags::ALL
+        } else {
+            PostingIteratorFlags::FREQS
+        };
+
+        let mut vector_terms = vec![];
+        let mut iter = terms.iterator()?;
+        while let Some(term) = iter.next()? {
+            let mut postings = iter.postings_with_flags(flags)?;
+            // the vectors of a doc are a single doc index
+            postings.next()?;
+            let freq = postings.freq()?;
+            let mut occurrences = vec![];
+            if has_positions || has_offsets {
+                occurrences.reserve(freq as usize);
+                for _ in 0..freq {
+                    let position = postings.next_position()?;
+                    let payload = if has_payloads {
+                        Some(postings.payload()?).filter(|p| !p.is_empty())
+                    } else {
+                        None
+                    };
+                    occurrences.push(TermVectorOccurrence {
+                        position: has_positions.then_some(position),
+                        start_offset: has_offsets.then_some(postings.start_offset()?),
+                        end_offset: has_offsets.then_some(postings.end_offset()?),
+                        payload,
+                    });
+                }
+            }
+            vector_terms.push(TermVectorTerm {
+                term,
+                freq,
+                occurrences,
+            });
+        }
+
+        Ok(Some(TermVector {
+            field: field.to_string(),
+            has_positions,
+            has_offsets,
+            has_payloads,
+            terms: vector_terms,
+        }))
+    }
+
+    /// Returns the term `term`, if it occurs in the field.
+    pub fn term(&self, term: &[u8]) -> Option<&TermVectorTerm> {
+        self.terms
+            .binary_search_by(|t| t.term.as_slice().cmp(term))
+            .ok()
+            .map(|i| &self.terms[i])
+    }
+}
+
+impl TermVectorTerm {
+    /// Returns the term as a string, or an error if it isn't valid UTF-8.
+    pub fn text(&self) -> Result<&str> {
+        Ok(str::from_utf8(&self.term)?)
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+/// The condition that made an `IndexWriter` commit on its own, see
+/// `AutoCommitPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoCommitTrigger {
+    /// `AutoCommitPolicy::max_commit_interval` elapsed since the last commit.
+    Interval,
+    /// The uncommitted changes reached `AutoCommitPolicy::max_uncommitted_bytes`.
+    UncommittedBytes,
+    /// The deletes since the last commit reached
+    /// `AutoCommitPolicy::max_uncommitted_deletes`.
+    UncommittedDeletes,
+}
+
+/// Makes the `IndexWriter` commit by itself once too much time elapsed or too
+/// much data was indexed since the last commit. All the limits are disabled
+/// by default.
+///
+/// The limits are checked after each document update or delete, and by
+/// `IndexWriter::maybe_auto_commit`: the writer has no timer of its own, so an
+/// idle writer with pending changes only commits once it is used again. The
+/// commit runs in the thread of the operation that triggered it, and
+/// `IndexWriterEventListener::on_auto_commit` is notified once it's done.
+///
+/// This is independent of the flush triggers, e.g.
+/// `IndexWriterConfig::ram_buffer_size_mb`, which only write new segments
+/// without making them durable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutoCommitPolicy {
+    /// Commits when this much time elapsed since the last commit, or since
+    /// the writer was opened, and there are uncommitted changes.
+    pub max_commit_interval: Option<Duration>,
+    /// Commits once the segments flushed since the last commit and the
+    /// indexing buffer use this many bytes.
+    pub max_uncommitted_bytes: Option<u64>,
+    /// Commits once this many delete terms and queries were buffered since
+    /// the last commit.
+    pub max_uncommitted_deletes: Option<u64>,
+}
+
+impl AutoCommitPolicy {
+    /// Returns true if any of the limits is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_commit_interval.is_some()
+            || self.max_uncommitted_bytes.is_some()
+            || self.max_uncommitted_deletes.is_some()
+    }
+
+    /// Returns the first limit reached by the uncommitted changes, if any.
+    pub fn trigger(
+        &self,
+        since_commit: Duration,
+        uncommitted_bytes: u64,
+        uncommitted_deletes: u64,
+    ) -> Option<AutoCommitTrigger> {
+        if self
+            .max_uncommitted_bytes
+            .is_some_and(|max| uncommitted_bytes >= max)
+        {
+            Some(AutoCommitTrigger::UncommittedBytes)
+        } else if self
+            .max_uncommitted_deletes
+            .is_some_and(|max| uncommitted_deletes >= max)
+        {
+            Some(AutoCommitTrigger::UncommittedDeletes)
+        } else if self
+            .max_commit_interval
+            .is_some_and(|max| since_commit >= max)
+        {
+            Some(AutoCommitTrigger::Interval)
+        } else {
+            None
+        }
+    }
+}
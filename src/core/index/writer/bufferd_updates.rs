@@ -14,7 +14,7 @@
 use crate::core::codec::segment_infos::{SegmentCommitInfo, SegmentInfos};
 use crate::core::codec::PostingIteratorFlags;
 use crate::core::codec::{Codec, CodecPostingIterator, CodecTermIterator};
-use crate::core::codec::{Fields, SeekStatus, TermIterator, Terms};
+use crate::core::codec::{Fields, TermIterator, Terms};
 use crate::core::doc::DocValuesType;
 use crate::core::index::merge::MergePolicy;
 use crate::core::index::reader::{IndexReader, LeafReader};
@@ -25,14 +25,14 @@ use crate::core::index::writer::{
 use crate::core::index::writer::{ReaderPool, ReadersAndUpdates};
 use crate::core::index::Term;
 use crate::core::search::cache::{NoCacheQueryCache, QueryCache};
-use crate::core::search::{query::Query, DocIterator, NO_MORE_DOCS};
+use crate::core::search::{query::Query, DocIterator, ScoreMode, NO_MORE_DOCS};
 use crate::core::search::{DefaultIndexSearcher, IndexSearcher, SearchPlanBuilder};
 use crate::core::store::directory::Directory;
 use crate::core::store::IOContext;
 use crate::core::util::DocId;
 
-use std::cmp::{min, Ordering as CmpOrdering};
-use std::collections::{BinaryHeap, HashMap};
+use std::cmp::min;
+use std::collections::HashMap;
 use std::fmt;
 use std::mem;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -301,6 +301,8 @@ pub struct BufferedUpdatesStream<C: Codec> {
     num_terms: AtomicUsize,
     num_queries: AtomicUsize,
     num_updates: AtomicUsize,
+    // docs deleted by the applied packets, only used for metrics
+    num_applied_deletes: AtomicU64,
 }
 
 impl<C: Codec> Default for BufferedUpdatesStream<C> {
@@ -313,6 +315,7 @@ impl<C: Codec> Default for BufferedUpdatesStream<C> {
             num_terms: AtomicUsize::new(0),
             num_queries: AtomicUsize::new(0),
             num_updates: AtomicUsize::new(0),
+            num_applied_deletes: AtomicU64::new(0),
         }
     }
 }
@@ -363,6 +366,11 @@ impl<C: Codec> BufferedUpdatesStream<C> {
         self.num_terms.load(Ordering::Acquire)
     }
 
+    /// Returns the number of docs deleted by applying the buffered deletes.
+    pub fn num_applied_deletes(&self) -> u64 {
+        self.num_applied_deletes.load(Ordering::Acquire)
+    }
+
     pub fn apply_deletes_and_updates<D, MS, MP>(
         &self,
         pool: &ReaderPool<D, C, MS, MP>,
@@ -582,7 +590,8 @@ impl<C: Codec> BufferedUpdatesStream<C> {
         searcher.set_query_cache(query_cache);
         let reader = searcher.reader().leaves().remove(0);
         for (query, limit) in queries {
-            let weight = searcher.create_normalized_weight(query.as_ref(), false)?;
+            let weight =
+                searcher.create_normalized_weight(query.as_ref(), ScoreMode::CompleteNoScores)?;
             if let Some(mut scorer) = weight.create_scorer(&reader)? {
                 let live_docs = reader.reader.live_docs();
                 loop {
@@ -607,7 +616,11 @@ impl<C: Codec> BufferedUpdatesStream<C> {
         Ok(del_count)
     }
 
-    /// Merge sorts the deleted terms and all segments to resolve terms to doc_ids for deletion.
+    /// Resolves the deleted terms to doc_ids with `seek_exact` in each segment.
+    ///
+    /// The deleted terms are sorted, so consecutive seeks share the prefix of
+    /// the previous one; and postings formats with bloom filters answer most
+    /// lookups of unique ids without touching their terms dictionary.
     fn apply_term_deletes<D, MS, MP>(
         &mut self,
         updates: &CoalescedUpdates<C>,
@@ -623,7 +636,6 @@ impl<C: Codec> BufferedUpdatesStream<C> {
 
         let mut del_term_visited_count: u64 = 0;
         let mut seg_term_visited_count = 0;
-        let mut queue = BinaryHeap::new();
 
         let mut iter = updates.term_iterator()?;
         let mut field = String::with_capacity(0);
@@ -631,21 +643,15 @@ impl<C: Codec> BufferedUpdatesStream<C> {
         while let Some(term) = iter.next()? {
             if iter.field() != field {
                 field = iter.field().to_string();
-                queue = BinaryHeap::with_capacity(num_readers);
-                for i in 0..num_readers {
+                for seg_state in seg_states.iter_mut() {
                     let terms_option = {
-                        let guard = seg_states[i].rld.inner.lock()?;
+                        let guard = seg_state.rld.inner.lock()?;
                         guard.reader().fields()?.terms(&field)?
                     };
-
-                    if let Some(terms) = terms_option {
-                        let mut terms_iterator = terms.iterator()?;
-                        seg_states[i].term = terms_iterator.next()?;
-                        seg_states[i].terms_iterator = Some(terms_iterator);
-                        if seg_states[i].term.is_some() {
-                            queue.push(SegmentStateRef::new(seg_states, i));
-                        }
-                    }
+                    seg_state.terms_iterator = match terms_option {
+                        Some(terms) => Some(terms.iterator()?),
+                        None => None,
+                    };
                 }
 
                 self.last_delete_term.clear();
@@ -657,101 +663,43 @@ impl<C: Codec> BufferedUpdatesStream<C> {
             del_term_visited_count += 1;
             let del_gen = iter.del_gen();
 
-            while !queue.is_empty() {
-                // Get next term merged across all segments
-                let mut should_pop = false;
-                {
-                    let top = queue.peek_mut().unwrap();
-                    let i = top.index;
-
-                    seg_term_visited_count += 1;
-                    match term.bytes().cmp(seg_states[i].term.as_ref().unwrap()) {
-                        CmpOrdering::Less => {
-                            break;
-                        }
-                        CmpOrdering::Equal => {
-                            // fall through
-                        }
-                        CmpOrdering::Greater => {
-                            debug_assert!(seg_states[i].terms_iterator.is_some());
-                            match seg_states[i]
-                                .terms_iterator
-                                .as_mut()
-                                .unwrap()
-                                .seek_ceil(term.bytes())?
-                            {
-                                SeekStatus::Found => {
-                                    // fall through
-                                }
-                                SeekStatus::NotFound => {
-                                    seg_states[i].term = Some(
-                                        seg_states[i]
-                                            .terms_iterator
-                                            .as_ref()
-                                            .unwrap()
-                                            .term()?
-                                            .to_vec(),
-                                    );
-                                    continue;
-                                }
-                                SeekStatus::End => {
-                                    should_pop = true;
-                                    // pop current
-                                }
-                            }
-                        }
-                    }
+            for seg_state in seg_states.iter_mut() {
+                debug_assert_ne!(seg_state.del_gen, del_gen);
+                if seg_state.del_gen > del_gen {
+                    continue;
                 }
-
-                if should_pop {
-                    queue.pop();
+                let terms_iterator = match seg_state.terms_iterator.as_mut() {
+                    Some(terms_iterator) => terms_iterator,
+                    None => continue,
+                };
+                seg_term_visited_count += 1;
+                if !terms_iterator.seek_exact(term.bytes())? {
                     continue;
                 }
 
-                let idx: usize;
-                {
-                    let top = queue.peek_mut().unwrap();
-                    idx = top.index;
-                    debug_assert_ne!(seg_states[idx].del_gen, del_gen);
-                    debug_assert!(seg_states[idx].terms_iterator.is_some());
-
-                    if seg_states[idx].del_gen < del_gen {
-                        // we don't need term frequencies for this
-                        let mut postings = seg_states[idx]
-                            .terms_iterator
-                            .as_mut()
-                            .unwrap()
-                            .postings_with_flags(PostingIteratorFlags::NONE)?;
-
-                        loop {
-                            let doc_id = postings.next()?;
-                            if doc_id == NO_MORE_DOCS {
-                                break;
-                            }
-                            if !seg_states[idx].rld.test_doc_id(doc_id as usize)? {
-                                continue;
-                            }
-                            if !seg_states[idx].any {
-                                seg_states[idx].rld.init_writable_live_docs()?;
-                                seg_states[idx].any = true;
-                            }
-                            // NOTE: there is no limit check on the docID
-                            // when deleting by Term (unlike by Query)
-                            // because on flush we apply all Term deletes to
-                            // each segment.  So all Term deleting here is
-                            // against prior segments:
-                            seg_states[idx].rld.delete(doc_id)?;
-                        }
-                        seg_states[idx].postings = Some(postings);
+                // we don't need term frequencies for this
+                let mut postings =
+                    terms_iterator.postings_with_flags(PostingIteratorFlags::NONE)?;
+                loop {
+                    let doc_id = postings.next()?;
+                    if doc_id == NO_MORE_DOCS {
+                        break;
                     }
-
-                    seg_states[idx].term =
-                        seg_states[idx].terms_iterator.as_mut().unwrap().next()?;
-                }
-
-                if seg_states[idx].term.is_none() {
-                    queue.pop();
+                    if !seg_state.rld.test_doc_id(doc_id as usize)? {
+                        continue;
+                    }
+                    if !seg_state.any {
+                        seg_state.rld.init_writable_live_docs()?;
+                        seg_state.any = true;
+                    }
+                    // NOTE: there is no limit check on the docID
+                    // when deleting by Term (unlike by Query)
+                    // because on flush we apply all Term deletes to
+                    // each segment.  So all Term deleting here is
+                    // against prior segments:
+                    seg_state.rld.delete(doc_id)?;
                 }
+                seg_state.postings = Some(postings);
             }
         }
 
@@ -878,6 +826,8 @@ impl<C: Codec> BufferedUpdatesStream<C> {
             "BD - apply_deletes: {} new delete documents.",
             total_del_count
         );
+        self.num_applied_deletes
+            .fetch_add(u64::from(total_del_count), Ordering::AcqRel);
 
         Ok(ApplyDeletesResult::new(
             total_del_count > 0,
@@ -984,7 +934,6 @@ struct SegmentState<
     start_del_count: usize,
     terms_iterator: Option<CodecTermIterator<C>>,
     postings: Option<CodecPostingIterator<C>>,
-    term: Option<Vec<u8>>,
     any: bool,
 }
 
@@ -1007,7 +956,6 @@ where
             start_del_count,
             terms_iterator: None,
             postings: None,
-            term: None,
             any: false,
         })
     }
@@ -1017,89 +965,6 @@ where
     }
 }
 
-struct SegmentStateRef<
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-> {
-    states: *const [SegmentState<D, C, MS, MP>],
-    index: usize,
-}
-
-impl<D, C, MS, MP> SegmentStateRef<D, C, MS, MP>
-where
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-{
-    fn new(states: &[SegmentState<D, C, MS, MP>], index: usize) -> Self {
-        Self { states, index }
-    }
-}
-
-/// use for iter terms on binary heap
-impl<D, C, MS, MP> Ord for SegmentStateRef<D, C, MS, MP>
-where
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-{
-    fn cmp(&self, other: &Self) -> CmpOrdering {
-        // reversed order
-        unsafe {
-            if let Some(ref s) = (*self.states)[self.index].term {
-                if let Some(ref o) = (*other.states)[other.index].term {
-                    o.cmp(s)
-                } else {
-                    CmpOrdering::Less
-                }
-            } else {
-                CmpOrdering::Greater
-            }
-        }
-    }
-}
-
-impl<D, C, MS, MP> PartialOrd for SegmentStateRef<D, C, MS, MP>
-where
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-{
-    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl<D, C, MS, MP> Eq for SegmentStateRef<D, C, MS, MP>
-where
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-{
-}
-
-impl<D, C, MS, MP> PartialEq for SegmentStateRef<D, C, MS, MP>
-where
-    D: Directory + Send + Sync + 'static,
-    C: Codec,
-    MS: MergeScheduler,
-    MP: MergePolicy,
-{
-    fn eq(&self, other: &Self) -> bool {
-        unsafe {
-            (*self.states)[self.index]
-                .term
-                .eq(&(*other.states)[self.index].term)
-        }
-    }
-}
-
 struct CoalescedUpdates<C: Codec> {
     queries: HashMap<String, (Arc<dyn Query<C>>, DocId)>,
     terms: Vec<Arc<PrefixCodedTerms>>,
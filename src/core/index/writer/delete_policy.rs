@@ -12,7 +12,7 @@
 // limitations under the License.
 
 use crate::core::index::writer::index_file_deleter::CommitPoint;
-use crate::Result;
+use crate::error::{Error::IllegalArgument, Result};
 
 /// Expert: policy for deletion of stale `IndexCommit index commits`.
 ///
@@ -43,7 +43,7 @@ use crate::Result;
 /// Implementers of sub-classes should make sure that `#clone()`
 /// returns an independent instance able to work with any other `IndexWriter`
 /// or `Directory` instance.
-pub trait IndexDeletionPolicy {
+pub trait IndexDeletionPolicy: Send + Sync {
     /// This is called once when a writer is first
     /// instantiated to give the policy a chance to remove old
     /// commit points.
@@ -88,15 +88,19 @@ pub trait IndexDeletionPolicy {
     fn on_commit(&self, commits: Vec<&mut CommitPoint>) -> Result<()>;
 }
 
+/// This `IndexDeletionPolicy` implementation that
+/// keeps only the most recent commit and immediately removes
+/// all prior commits after a new commit is done.  This is
+/// the default deletion policy.
 #[derive(Default)]
 pub struct KeepOnlyLastCommitDeletionPolicy;
 
-impl KeepOnlyLastCommitDeletionPolicy {
-    pub fn on_init(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
+impl IndexDeletionPolicy for KeepOnlyLastCommitDeletionPolicy {
+    fn on_init(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
         self.on_commit(commits)
     }
 
-    pub fn on_commit(&self, mut commits: Vec<&mut CommitPoint>) -> Result<()> {
+    fn on_commit(&self, mut commits: Vec<&mut CommitPoint>) -> Result<()> {
         commits.pop();
         for commit in commits {
             commit.delete()?;
@@ -104,3 +108,40 @@ impl KeepOnlyLastCommitDeletionPolicy {
         Ok(())
     }
 }
+
+/// This `IndexDeletionPolicy` implementation that keeps the `num_commits`
+/// most recent commits and removes the older ones after a new commit is done,
+/// so that point-in-time readers and backups can rely on the previous commits
+/// surviving a few more commits.
+pub struct KeepLastNCommitsDeletionPolicy {
+    num_commits: usize,
+}
+
+impl KeepLastNCommitsDeletionPolicy {
+    pub fn new(num_commits: usize) -> Result<Self> {
+        if num_commits == 0 {
+            return Err(IllegalArgument(
+                "at least the last commit must be kept".into(),
+            ));
+        }
+        Ok(KeepLastNCommitsDeletionPolicy { num_commits })
+    }
+
+    pub fn num_commits(&self) -> usize {
+        self.num_commits
+    }
+}
+
+impl IndexDeletionPolicy for KeepLastNCommitsDeletionPolicy {
+    fn on_init(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
+        self.on_commit(commits)
+    }
+
+    fn on_commit(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
+        let num_to_delete = commits.len().saturating_sub(self.num_commits);
+        for commit in commits.into_iter().take(num_to_delete) {
+            commit.delete()?;
+        }
+        Ok(())
+    }
+}
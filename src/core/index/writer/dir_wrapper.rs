@@ -9,7 +9,6 @@ use crate::core::store::io::{IndexInput, IndexOutput, RateLimitIndexOutput};
 use crate::core::store::{IOContext, RateLimiter};
 
 use crate::error::{Error::IllegalState, Result};
-use thread_local::ThreadLocal;
 
 pub type TrackingTmpDirectory<D> = TrackingTmpOutputDirectoryWrapper<
     TrackingDirectoryWrapper<
@@ -96,8 +95,8 @@ impl<D: Directory> fmt::Display for TrackingTmpOutputDirectoryWrapper<D> {
 
 pub struct RateLimitFilterDirectory<D: Directory, RL: RateLimiter + ?Sized> {
     dir: Arc<D>,
-    // reference to IndexWriter.rate_limiter
-    rate_limiter: Arc<ThreadLocal<Arc<RL>>>,
+    // the rate limiter of the merge writing to this directory
+    rate_limiter: Arc<RL>,
 }
 
 impl<D, RL> RateLimitFilterDirectory<D, RL>
@@ -105,7 +104,7 @@ where
     D: Directory,
     RL: RateLimiter + ?Sized,
 {
-    pub fn new(dir: Arc<D>, rate_limiter: Arc<ThreadLocal<Arc<RL>>>) -> Self {
+    pub fn new(dir: Arc<D>, rate_limiter: Arc<RL>) -> Self {
         RateLimitFilterDirectory { dir, rate_limiter }
     }
 }
@@ -133,7 +132,7 @@ where
 
     fn create_output(&self, name: &str, context: &IOContext) -> Result<Self::IndexOutput> {
         debug_assert!(context.is_merge());
-        let rate_limiter = Arc::clone(&self.rate_limiter.get().unwrap());
+        let rate_limiter = Arc::clone(&self.rate_limiter);
         let index_output = self.dir.create_output(name, context)?;
 
         Ok(RateLimitIndexOutput::new(rate_limiter, index_output))
@@ -26,8 +26,9 @@ use crate::core::codec::postings::{
 use crate::core::codec::segment_infos::{SegmentReadState, SegmentWriteState};
 use crate::core::codec::stored_fields::StoredFieldsConsumer;
 use crate::core::codec::term_vectors::TermVectorsConsumer;
+use crate::core::codec::vectors::{VectorValuesWriter, VectorsFormat, VectorsWriter};
 use crate::core::codec::Codec;
-use crate::core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions};
+use crate::core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions, NumericDocValuesField};
 use crate::core::index::merge::MergePolicy;
 use crate::core::index::writer::{index_writer, DocState, DocumentsWriterPerThread};
 use crate::core::store::directory::Directory;
@@ -35,6 +36,7 @@ use crate::core::store::IOContext;
 use crate::core::util::{BytesRef, DocId, VariantValue};
 
 use crate::core::search::similarity::BM25Similarity;
+use crate::core::search::sort_field::{Sort, SortField};
 
 use crate::error::{
     Error::{IllegalArgument, UnsupportedOperation},
@@ -49,6 +51,9 @@ use std::sync::Arc;
 
 const MAX_FIELD_COUNT: usize = 65536;
 
+/// Offset gap between the values of a multi-valued tokenized field.
+const TOKENIZED_OFFSET_GAP: usize = 1;
+
 pub struct DocConsumer<
     D: Directory + Send + Sync + 'static,
     C: Codec,
@@ -167,6 +172,34 @@ where
         Ok(())
     }
 
+    /// Writes all buffered vectors.
+    fn write_vectors<DW: Directory>(
+        &mut self,
+        state: &SegmentWriteState<D, DW, C>,
+        sort_map: Option<&PackedLongDocMap>,
+    ) -> Result<()> {
+        let mut vectors_writer = None;
+        for per_field in &mut self.field_hash {
+            if let Some(mut values_writer) = per_field.vector_values_writer.take() {
+                if vectors_writer.is_none() {
+                    // lazy init
+                    vectors_writer = Some(
+                        state
+                            .segment_info
+                            .codec()
+                            .vectors_format()
+                            .fields_writer(state)?,
+                    );
+                }
+                values_writer.flush(state, sort_map, vectors_writer.as_mut().unwrap())?;
+            }
+        }
+        if let Some(ref mut writer) = vectors_writer {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
     /// Writes all buffered doc values (called from {@link #flush}).
     fn write_doc_values<DW: Directory>(
         &mut self,
@@ -315,6 +348,13 @@ where
             self.index_point(per_field.unwrap(), field, doc_state)?;
         }
 
+        if field.field_type().vector_dimension > 0 {
+            if per_field.is_none() {
+                per_field = Some(self.get_or_add_field(field.name(), field.field_type(), false)?);
+            }
+            self.index_vector(per_field.unwrap(), field, doc_state)?;
+        }
+
         Ok(field_count)
     }
 
@@ -399,6 +439,11 @@ where
                 pf.field_info_mut()
                     .set_index_options(field_type.index_options);
                 pf.set_invert_state(&mut self.terms_hash);
+                // a field first seen as doc values or stored only, that is now
+                // also indexed, needs the same init as a brand new field
+                if let Some(ref mut term_hash_per_field) = pf.term_hash_per_field {
+                    term_hash_per_field.init();
+                }
             }
         }
 
@@ -415,6 +460,12 @@ where
         field: &impl Fieldable,
         doc_state: &DocState,
     ) -> Result<()> {
+        if self.field_hash[field_idx].field_info().doc_values_type == DocValuesType::Null {
+            if let Some(sort) = self.doc_writer().segment_info.index_sort() {
+                validate_index_sort_dv_type(sort, &self.field_hash[field_idx].name, dv_type)?;
+            }
+        }
+
         let per_field = &mut self.field_hash[field_idx];
 
         if per_field.field_info().doc_values_type == DocValuesType::Null {
@@ -514,6 +565,7 @@ where
         let doc_writer = unsafe { &mut (*self.parent) };
         let per_field = &mut self.field_hash[field_idx];
         let point_dimension_count = field.field_type().dimension_count;
+        let index_dimension_count = field.field_type().index_dimension_count;
         let dimension_num_bytes = field.field_type().dimension_num_bytes;
 
         // Record dimensions for this field; this setter will throw IllegalArgExc if
@@ -530,7 +582,11 @@ where
                 )?;
             self.field_infos
                 .get_or_add(&per_field.field_info().name)?
-                .set_dimensions(point_dimension_count, dimension_num_bytes)?;
+                .set_dimensions(
+                    point_dimension_count,
+                    index_dimension_count,
+                    dimension_num_bytes,
+                )?;
         }
 
         if per_field.point_values_writer.is_none() {
@@ -548,6 +604,54 @@ where
             )
     }
 
+    /// Called from process_document to index one field's vector
+    fn index_vector(
+        &mut self,
+        field_idx: usize,
+        field: &impl Fieldable,
+        doc_state: &DocState,
+    ) -> Result<()> {
+        let per_field = &mut self.field_hash[field_idx];
+        let vector_dimension = field.field_type().vector_dimension;
+        let vector_similarity = field.field_type().vector_similarity;
+        let vector = field.vector_value().ok_or_else(|| {
+            IllegalArgument(format!("vector field '{}' has no vector", field.name()))
+        })?;
+
+        // Record the vectors of this field; this setter will throw IllegalArgExc if
+        // they were already set to something different:
+        if per_field.field_info().vector_dimension == 0 {
+            self.field_infos
+                .global_field_numbers
+                .as_ref()
+                .set_vector_dimension(
+                    per_field.field_info().number,
+                    &per_field.field_info().name,
+                    vector_dimension,
+                    vector_similarity,
+                )?;
+            self.field_infos
+                .get_or_add(&per_field.field_info().name)?
+                .set_vector_dimension(vector_dimension, vector_similarity)?;
+        } else if per_field.field_info().vector_dimension != vector_dimension
+            || per_field.field_info().vector_similarity != vector_similarity
+        {
+            return Err(IllegalArgument(format!(
+                "cannot change field '{}' vector dimension or similarity",
+                field.name()
+            )));
+        }
+
+        if per_field.vector_values_writer.is_none() {
+            per_field.vector_values_writer = Some(VectorValuesWriter::new(per_field.field_info()));
+        }
+        per_field
+            .vector_values_writer
+            .as_mut()
+            .unwrap()
+            .add_value(doc_state.doc_id, vector)
+    }
+
     fn get_per_field_index(&mut self, name: &str) -> Option<usize> {
         for (idx, pf) in self.field_hash.iter().enumerate() {
             if pf.field_info().name.as_str() == name {
@@ -624,9 +728,19 @@ where
         self.terms_hash.start_document()?;
 
         self.start_stored_fields(doc_state.doc_id)?;
-        for field in doc {
+        for field in doc.iter_mut() {
             field_count = unsafe { self.process_field(field, doc_state, field_gen, field_count)? };
         }
+        // Every doc carries the soft deletes field, so that it can be soft
+        // deleted later on by a doc values update:
+        let config = Arc::clone(&self.doc_writer().index_writer_config);
+        if let Some(soft_deletes_field) = config.soft_deletes_field() {
+            if doc.iter().all(|f| f.name() != soft_deletes_field) {
+                let mut field = NumericDocValuesField::new(soft_deletes_field, 0);
+                field_count =
+                    unsafe { self.process_field(&mut field, doc_state, field_gen, field_count)? };
+            }
+        }
         // Finish each indexed field name seen in the document:
         for i in 0..field_count {
             let idx = self.fields[i];
@@ -672,6 +786,7 @@ where
 
         self.write_doc_values(state, sort_map.as_ref().map(|m| m.as_ref()))?;
         self.write_points(state, sort_map.as_ref().map(|m| m.as_ref()))?;
+        self.write_vectors(state, sort_map.as_ref().map(|m| m.as_ref()))?;
 
         // it's possible all docs hit non-aborting exceptions...
         self.stored_fields_consumer.finish(max_doc)?;
@@ -736,6 +851,39 @@ where
     pub fn need_flush(&self) -> bool {
         self.terms_hash.need_flush()
     }
+
+    /// Estimates the RAM used by the buffered postings.
+    pub fn bytes_used(&self) -> usize {
+        let per_field: usize = self
+            .field_hash
+            .iter()
+            .filter_map(|f| f.term_hash_per_field.as_ref())
+            .map(|f| f.base().bytes_used())
+            .sum();
+        self.terms_hash.base.bytes_used() + per_field
+    }
+}
+
+/// Checks that the doc values type of a field is the one its index sort
+/// field reads.
+fn validate_index_sort_dv_type(sort: &Sort, field: &str, dv_type: DocValuesType) -> Result<()> {
+    for sort_field in sort.get_sort() {
+        if sort_field.field() == field {
+            let expected = match sort_field {
+                SortField::SortedNumeric(_) | SortField::Distance(_) => {
+                    DocValuesType::SortedNumeric
+                }
+                SortField::Simple(_) => DocValuesType::Numeric,
+            };
+            if dv_type != expected {
+                return Err(IllegalArgument(format!(
+                    "invalid doc value type: {:?} for sort field: {:?}, expected {:?}",
+                    dv_type, sort_field, expected
+                )));
+            }
+        }
+    }
+    Ok(())
 }
 
 pub struct PerField<T: TermsHashPerField> {
@@ -748,10 +896,14 @@ pub struct PerField<T: TermsHashPerField> {
     doc_values_writer: Option<DocValuesWriterEnum>,
     // Non-null if this field ever had points in this segment:
     point_values_writer: Option<PointValuesWriter>,
+    // Non-null if this field ever had vectors in this segment:
+    vector_values_writer: Option<VectorValuesWriter>,
     /// We use this to know when a PerField is seen for the
     /// first time in the current document
     field_gen: i64,
     norms: Option<NormValuesWriter>,
+    // the norm set by a field of the current document, if any
+    custom_norm: Option<i64>,
     invert: bool,
 }
 
@@ -770,8 +922,10 @@ impl<T: TermsHashPerField> PerField<T> {
             term_hash_per_field,
             doc_values_writer: None,
             point_values_writer: None,
+            vector_values_writer: None,
             field_gen: -1,
             norms: None,
+            custom_norm: None,
             invert,
         };
 
@@ -810,13 +964,17 @@ impl<T: TermsHashPerField> PerField<T> {
     }
 
     fn finish(&mut self, doc_state: &DocState) -> Result<()> {
-        if !self.field_info().omit_norms && self.invert_state.length != 0 {
+        if !self.field_info().omit_norms {
             debug_assert!(self.norms.is_some());
             let doc_id = doc_state.doc_id;
-            self.norms
-                .as_mut()
-                .unwrap()
-                .add_value(doc_id, BM25Similarity::compute_norm(&self.invert_state));
+            if let Some(norm) = self.custom_norm {
+                self.norms.as_mut().unwrap().add_value(doc_id, norm);
+            } else if self.invert_state.length != 0 {
+                self.norms
+                    .as_mut()
+                    .unwrap()
+                    .add_value(doc_id, BM25Similarity::compute_norm(&self.invert_state));
+            }
         }
 
         self.term_hash_per_field
@@ -854,6 +1012,10 @@ impl<T: TermsHashPerField> PerField<T> {
             // First time we're seeing this field (indexed) in
             // this document:
             self.invert_state.reset();
+            self.custom_norm = None;
+        }
+        if let Some(norm) = field.norm() {
+            self.custom_norm = Some(norm);
         }
 
         let index_options = field.field_type().index_options;
@@ -964,10 +1126,11 @@ impl<T: TermsHashPerField> PerField<T> {
         self.invert_state.position += token_stream.token().position as i32;
         self.invert_state.offset += token_stream.token().end_offset;
 
-        //        if analyzed {
-        //            self.invert_state.position += doc_state.analyzer.get_position_increment_gap();
-        //            self.invert_state.offset += doc_state.analyzer.get_offset_gap();
-        //        }
+        // values of a multi-valued tokenized field are one char apart, the way
+        // highlighters join them back with a separator
+        if field.field_type().tokenized() {
+            self.invert_state.offset += TOKENIZED_OFFSET_GAP;
+        }
 
         self.invert_state.boost *= field.boost();
 
@@ -11,6 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
 use crate::core::codec::{Codec, PackedLongDocMap, PostingIteratorFlags};
 use crate::core::codec::{Fields, SorterDocMap, TermIterator, Terms};
 use crate::core::doc::DocValuesType;
@@ -19,6 +20,7 @@ use crate::core::index::Term;
 use crate::core::search::DocIterator;
 use crate::core::search::NO_MORE_DOCS;
 use crate::core::store::directory::Directory;
+use crate::core::util::DocId;
 use crate::Result;
 use std::cmp::Ordering;
 use std::collections::binary_heap::BinaryHeap;
@@ -334,6 +336,39 @@ enum DVUpdates {
     Iterator(MergedDocValuesUpdatesIterator),
 }
 
+// The values of the updated field before the updates, the field may be a
+// numeric or a sorted numeric doc values field
+enum OldNumericValues {
+    Numeric(Box<dyn NumericDocValues>),
+    SortedNumeric(Box<dyn SortedNumericDocValues>),
+}
+
+impl OldNumericValues {
+    fn new<D: Directory + 'static, C: Codec>(
+        reader: &SegmentReader<D, C>,
+        field: &str,
+    ) -> Result<Self> {
+        match reader.field_info(field) {
+            Some(fi) if fi.doc_values_type == DocValuesType::Numeric => Ok(
+                OldNumericValues::Numeric(reader.get_numeric_doc_values(field)?),
+            ),
+            _ => Ok(OldNumericValues::SortedNumeric(
+                reader.get_sorted_numeric_doc_values(field)?,
+            )),
+        }
+    }
+
+    fn get(&mut self, doc_id: DocId) -> Result<i64> {
+        match self {
+            OldNumericValues::Numeric(dv) => dv.get_mut(doc_id),
+            OldNumericValues::SortedNumeric(dv) => {
+                dv.set_document(doc_id)?;
+                Ok(dv.value_at(0).unwrap_or(0))
+            }
+        }
+    }
+}
+
 // Iterator for merge old & new doc values
 pub struct NewDocValuesIterator<D, C>
 where
@@ -396,12 +431,8 @@ where
                     iterator.next_dv_update();
                     return (doc_id, value);
                 }
-                let mut dvs = self
-                    .reader
-                    .get_sorted_numeric_doc_values(&iterator.field)
-                    .unwrap();
-                dvs.set_document(self.doc_id).unwrap_or(());
-                let value = dvs.value_at(0).unwrap_or(0);
+                let mut dvs = OldNumericValues::new(self.reader.as_ref(), &iterator.field).unwrap();
+                let value = dvs.get(self.doc_id).unwrap_or(0);
                 (self.doc_id, value)
             }
         }
@@ -485,7 +516,7 @@ where
                 if !updates.is_empty() {
                     // merge old & new doc values
                     if let Some(field) = field {
-                        let mut old_ndv = reader.get_sorted_numeric_doc_values(&field)?;
+                        let mut old_ndv = OldNumericValues::new(reader.as_ref(), &field)?;
                         let mut i = 0;
                         let mut it = updates.iter();
                         loop {
@@ -495,9 +526,7 @@ where
                                 if i < doc_id {
                                     // old values
                                     for id in i..doc_id {
-                                        // let old_value = old_ndv.get(id).unwrap();
-                                        old_ndv.set_document(id)?;
-                                        let old_value = old_ndv.value_at(0).unwrap_or(0);
+                                        let old_value = old_ndv.get(id)?;
                                         new_ndv.push((id, old_value));
                                         i += 1;
                                     }
@@ -508,9 +537,7 @@ where
                             } else if i > 0 {
                                 // old values
                                 while i < reader.max_docs() {
-                                    old_ndv.set_document(i)?;
-                                    let old_value = old_ndv.value_at(0).unwrap_or(0);
-                                    // let old_value = old_ndv.get(i).unwrap();
+                                    let old_value = old_ndv.get(i)?;
                                     new_ndv.push((i, old_value));
                                     i += 1;
                                 }
@@ -13,7 +13,7 @@
 
 use crate::core::codec::segment_infos::SegmentInfo;
 use crate::core::codec::Codec;
-use crate::core::doc::Fieldable;
+use crate::core::doc::{Fieldable, IndexOptions};
 use crate::core::index::writer::{
     DocValuesUpdate, DocumentsWriterDeleteQueue, DocumentsWriterFlushControl,
     DocumentsWriterFlushQueue, DocumentsWriterPerThread, DocumentsWriterPerThreadPool,
@@ -23,6 +23,7 @@ use crate::core::index::Term;
 use crate::core::search::query::Query;
 use crate::core::store::directory::{Directory, LockValidatingDirectoryWrapper};
 use crate::core::util::external::Volatile;
+use crate::core::util::{Accountable, NamedAccountable};
 use crate::error::{Error::AlreadyClosed, Error::IllegalState, Result};
 use crate::Error;
 
@@ -144,7 +145,7 @@ where
         directory: Arc<LockValidatingDirectoryWrapper<D>>,
     ) -> Self {
         let flush_policy = Arc::new(FlushByCountsPolicy::new(Arc::clone(&config)));
-        let flush_control = DocumentsWriterFlushControl::new(Arc::clone(&flush_policy));
+        let flush_control = DocumentsWriterFlushControl::new(Arc::clone(&flush_policy), &config);
         DocumentsWriter {
             lock: Arc::new(Mutex::new(())),
             directory_orig,
@@ -201,6 +202,7 @@ where
         docs: Vec<Vec<F>>,
         del_term: Option<Term>,
     ) -> Result<(u64, bool)> {
+        self.check_fields(docs.iter().flatten())?;
         let mut has_event = self.pre_update()?;
 
         let per_thread: Arc<ThreadState<D, C, MS, MP>> = self.obtain_and_lock()?;
@@ -291,6 +293,7 @@ where
         doc: Vec<F>,
         del_term: Option<Term>,
     ) -> Result<(u64, bool)> {
+        self.check_fields(doc.iter())?;
         let mut has_event = self.pre_update()?;
 
         let per_thread: Arc<ThreadState<D, C, MS, MP>> = self.obtain_and_lock()?;
@@ -365,11 +368,56 @@ where
         Ok(seq_no)
     }
 
+    /// Rejects documents that can't be indexed before they reach the indexing
+    /// chain, where any error aborts the pending segment.
+    fn check_fields<'a, F: Fieldable + 'a>(
+        &self,
+        fields: impl Iterator<Item = &'a F> + Clone,
+    ) -> Result<()> {
+        if let Some(label) = fields.clone().find_map(|f| f.facet_label()) {
+            return Err(Error::IllegalArgument(format!(
+                "facet field of dimension \"{}\" must be turned into indexable fields by \
+                 FacetsConfig::build",
+                label.dim
+            )));
+        }
+        if let Some(field) = fields.clone().find(|f| {
+            f.norm().is_some()
+                && (f.field_type().omit_norms || f.field_type().index_options == IndexOptions::Null)
+        }) {
+            return Err(Error::IllegalArgument(format!(
+                "cannot set a custom norm on field '{}': it is not indexed or omits norms",
+                field.name()
+            )));
+        }
+        if let Some(schema) = self.config.schema() {
+            schema.check(fields)?;
+        }
+        Ok(())
+    }
+
     fn pre_update(&self) -> Result<bool> {
         debug_assert!(self.inited);
         self.ensure_open()?;
 
-        Ok(false)
+        let mut has_events = false;
+        while self.flush_control.is_stalled() {
+            // the flushes can't keep up with indexing: help out flushing,
+            // then wait for the flushes of the other threads
+            has_events |= self.help_flush()?;
+            self.flush_control.wait_if_stalled()?;
+            self.ensure_open()?;
+        }
+        Ok(has_events)
+    }
+
+    // flushes the pending DWPTs until there are none left
+    fn help_flush(&self) -> Result<bool> {
+        let mut has_events = false;
+        while let Some(flushing_dwpt) = self.flush_control.next_pending_flush() {
+            has_events |= self.do_flush(flushing_dwpt)?;
+        }
+        Ok(has_events)
     }
 
     fn post_update(&self, mut has_event: bool) -> Result<bool> {
@@ -461,6 +509,18 @@ where
         self.num_docs_in_ram.load(Ordering::Acquire)
     }
 
+    /// Returns the highest sequence number of the operations that completed.
+    pub fn max_completed_sequence_number(&self) -> u64 {
+        let mut value = self.last_seq_no;
+        for i in 0..self.per_thread_pool.active_thread_state_count() {
+            value = max(
+                value,
+                self.per_thread_pool.get_thread_state(i).last_seq_no(),
+            );
+        }
+        value
+    }
+
     pub fn num_global_term_deletes(&self) -> usize {
         self.delete_queue.num_global_term_deletes()
             + self
@@ -695,7 +755,15 @@ where
             self.put_event(WriterEvent::DeleteNewFiles(files_to_delete));
             *has_events = true;
         }
-        if res.is_err() {
+        if let Err(ref e) = res {
+            // the buffered docs of the DWPT are lost, the writer can't go on
+            let tragedy = Error::RuntimeError(format!(
+                "flush of segment '{}' failed: {:?}",
+                dwpt.segment_info.name, e
+            ));
+            if let Some(writer) = self.index_writer.upgrade() {
+                let _ = writer.on_tragic_event(tragedy, "flush");
+            }
             self.put_event(WriterEvent::FlushFailed(dwpt.segment_info.clone()));
             *has_events = true;
         }
@@ -733,11 +801,32 @@ where
             self.flush_control.mark_for_full_flush()
         };
 
-        let mut anything_flushed = false;
-        while let Some(flushing_dwpt) = self.flush_control.next_pending_flush() {
-            // Help out with flushing:
-            anything_flushed |= self.do_flush(flushing_dwpt)?;
-        }
+        // Help out with flushing, the DWPTs are flushed in parallel so that
+        // the flush takes about as long as the largest one
+        let helpers = (self.config.max_concurrent_flushes() as usize)
+            .min(self.flush_control.num_queued_flushes())
+            .saturating_sub(1);
+        let anything_flushed = if helpers > 0 {
+            let index_writer = self.index_writer();
+            thread::scope(|s| {
+                let handles: Vec<_> = (0..helpers)
+                    .map(|_| s.spawn(|| index_writer.doc_writer.help_flush()))
+                    .collect();
+                let mut res = self.help_flush();
+                for handle in handles {
+                    let helper_res = handle.join().unwrap_or_else(|_| {
+                        Err(Error::RuntimeError("flush thread panicked".into()))
+                    });
+                    res = match (res, helper_res) {
+                        (Ok(a), Ok(b)) => Ok(a | b),
+                        (Err(e), _) | (_, Err(e)) => Err(e),
+                    };
+                }
+                res
+            })?
+        } else {
+            self.help_flush()?
+        };
         // If a concurrent flush is still in flight wait for it
         self.flush_control.wait_for_flush()?;
         if !anything_flushed && flushing_queue.any_changes() {
@@ -776,6 +865,27 @@ where
     }
 }
 
+/// Accounts the RAM used by the DWPTs that are indexing, pending for flush or
+/// flushing.
+impl<D, C, MS, MP> Accountable for DocumentsWriter<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn ram_bytes_used(&self) -> usize {
+        (self.flush_control.active_bytes() + self.flush_control.flush_bytes()) as usize
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        vec![
+            NamedAccountable::with_bytes("active", self.flush_control.active_bytes() as usize),
+            NamedAccountable::with_bytes("flushing", self.flush_control.flush_bytes() as usize),
+        ]
+    }
+}
+
 impl<D, C, MS, MP> Drop for DocumentsWriter<D, C, MS, MP>
 where
     D: Directory + Send + Sync + 'static,
@@ -181,6 +181,15 @@ where
         self.index_writer_config.codec()
     }
 
+    /// Estimates the RAM used by the docs buffered in this writer.
+    pub fn bytes_used(&self) -> u64 {
+        if self.inited {
+            unsafe { self.consumer.assume_init_ref().bytes_used() as u64 }
+        } else {
+            0
+        }
+    }
+
     // Anything that will add N docs to the index should reserve first to make sure it's allowed
     fn reserve_one_doc(&mut self) -> Result<()> {
         self.pending_num_docs.fetch_add(1, Ordering::AcqRel);
@@ -383,7 +392,10 @@ where
         debug_assert!(self.delete_slice.is_empty());
 
         self.segment_info.max_doc = self.num_docs_in_ram as i32;
-        let ctx = IOContext::Flush(FlushInfo::new(self.num_docs_in_ram));
+        let ctx = IOContext::Flush(FlushInfo::with_estimated_segment_size(
+            self.num_docs_in_ram,
+            self.bytes_used(),
+        ));
 
         let mut flush_state = SegmentWriteState::new(
             Arc::clone(&self.directory),
@@ -498,6 +510,24 @@ where
         Ok(sorted_live_docs)
     }
 
+    /// Flushed segments use the compound file format if it's enabled on the
+    /// config, unless they are larger than the max compound file segment size
+    /// of the merge policy.
+    fn use_compound_file(&self, info: &SegmentInfo<D, C>) -> Result<bool> {
+        if !self.index_writer_config.use_compound_file {
+            return Ok(false);
+        }
+        let max_size = self.index_writer_config.merge_policy.max_cfs_segment_size();
+        let mut size = 0u64;
+        for file in info.files() {
+            size += self.directory.file_length(file)? as u64;
+            if size > max_size {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     fn seal_flushed_segment(
         &mut self,
         flushed_segment: &mut FlushedSegment<D, C>,
@@ -505,10 +535,20 @@ where
     ) -> Result<()> {
         // set_diagnostics(&mut flushed_segment.segment_info.info, index_writer::SOURCE_FLUSH);
 
-        let flush_info = FlushInfo::new(flushed_segment.segment_info.info.max_doc() as u32);
+        let segment_size: i64 = flushed_segment
+            .segment_info
+            .info
+            .files()
+            .iter()
+            .map(|f| self.directory.file_length(f).unwrap_or(0))
+            .sum();
+        let flush_info = FlushInfo::with_estimated_segment_size(
+            flushed_segment.segment_info.info.max_doc() as u32,
+            segment_size as u64,
+        );
         let ctx = &IOContext::Flush(flush_info);
 
-        if self.index_writer_config.use_compound_file {
+        if self.use_compound_file(&flushed_segment.segment_info.info)? {
             let original_files = flushed_segment.segment_info.info.files().clone();
             // TODO: like addIndexes, we are relying on createCompoundFile to successfully
             // cleanup...
@@ -794,6 +834,9 @@ pub struct ThreadState<
     // write access guarded by DocumentsWriterFlushControl
     // set by DocumentsWriter after each indexing op finishes
     last_seq_no: AtomicU64,
+    // TODO this should really be part of DocumentsWriterFlushControl
+    // write access guarded by DocumentsWriterFlushControl
+    pub bytes_used: u64,
     // index in DocumentsWriterPerThreadPool
     index: usize,
 }
@@ -811,6 +854,7 @@ where
             dwpt,
             flush_pending: AtomicBool::new(false),
             last_seq_no: AtomicU64::new(0),
+            bytes_used: 0,
             index,
         }
     }
@@ -841,6 +885,7 @@ where
     fn reset(&mut self) -> Option<DocumentsWriterPerThread<D, C, MS, MP>> {
         let dwpt = self.dwpt.take();
         self.flush_pending.store(false, Ordering::Release);
+        self.bytes_used = 0;
         dwpt
     }
 
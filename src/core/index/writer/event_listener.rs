@@ -0,0 +1,83 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::SegmentCommitInfo;
+use crate::core::codec::Codec;
+use crate::core::index::writer::AutoCommitTrigger;
+use crate::core::store::directory::Directory;
+
+/// The name, doc count and size of a segment reported to an
+/// `IndexWriterEventListener`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentEventInfo {
+    pub name: String,
+    pub max_doc: i32,
+    pub del_count: i32,
+    /// Total size of the segment files, `-1` if it couldn't be computed.
+    pub size_in_bytes: i64,
+}
+
+impl SegmentEventInfo {
+    pub(crate) fn new<D: Directory, C: Codec>(info: &SegmentCommitInfo<D, C>) -> Self {
+        SegmentEventInfo {
+            name: info.info.name.clone(),
+            max_doc: info.info.max_doc(),
+            del_count: info.del_count(),
+            size_in_bytes: info.size_in_bytes(),
+        }
+    }
+}
+
+/// Callbacks invoked by the `IndexWriter` on flushes, merges and commits,
+/// e.g. to emit metrics or to warm caches for new segments.
+///
+/// All methods default to a no-op. They are called from the thread doing
+/// the work, without holding the writer's lock, so a slow listener slows
+/// down indexing. They must not flush, commit or open a NRT reader from the
+/// writer themselves, as this could deadlock with an in-progress flush.
+pub trait IndexWriterEventListener: Send + Sync {
+    /// Called before a full flush of the buffered docs and deletes, i.e. on
+    /// `flush`, `commit` and when opening a NRT reader.
+    fn on_flush_start(&self) {}
+
+    /// Called after a full flush completed, the segments it flushed were
+    /// reported to `on_segment_published` before. Not called if the flush
+    /// failed.
+    fn on_flush_end(&self) {}
+
+    /// Called after a newly flushed segment was published to the writer, it
+    /// is part of the next NRT reader and commit from now on.
+    fn on_segment_published(&self, _segment: &SegmentEventInfo) {}
+
+    /// Called when the merge `merge_id` of `segments` starts.
+    fn on_merge_start(&self, _merge_id: u32, _segments: &[SegmentEventInfo]) {}
+
+    /// Called when the merge `merge_id` is done, `merged` is the new segment
+    /// or `None` if the merge failed, was aborted or all its docs were
+    /// deleted.
+    fn on_merge_end(&self, _merge_id: u32, _merged: Option<&SegmentEventInfo>) {}
+
+    /// Called after the commit point `segments_file_name` of generation
+    /// `generation` was written and synced.
+    fn on_commit(&self, _segments_file_name: &str, _generation: i64) {}
+
+    /// Called after the writer committed on its own because of `trigger`,
+    /// `seq_no` is the sequence number returned by the commit. `on_commit`
+    /// was called before if anything was committed.
+    fn on_auto_commit(&self, _trigger: AutoCommitTrigger, _seq_no: i64) {}
+}
+
+/// The default `IndexWriterEventListener`, ignores all events.
+pub struct NoOpIndexWriterEventListener;
+
+impl IndexWriterEventListener for NoOpIndexWriterEventListener {}
@@ -15,7 +15,7 @@ use crate::core::codec::Codec;
 use crate::core::index::merge::{MergePolicy, MergeScheduler};
 use crate::core::index::writer::{
     DocumentsWriter, DocumentsWriterDeleteQueue, DocumentsWriterPerThread,
-    DocumentsWriterPerThreadPool, FlushByCountsPolicy, FlushPolicy, ThreadState,
+    DocumentsWriterPerThreadPool, FlushByCountsPolicy, FlushPolicy, IndexWriterConfig, ThreadState,
 };
 use crate::core::util::external::Volatile;
 use crate::Result;
@@ -26,6 +26,7 @@ use std::mem;
 use std::ptr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
 
 /// This class controls `DocumentsWriterPerThread` flushing during
 /// indexing. It tracks the memory consumption per
@@ -37,6 +38,10 @@ use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 /// `DocumentsWriterPerThread` exceeds the
 /// `IndexWriterConfig#getRAMPerThreadHardLimitMB()` to prevent address
 /// space exhaustion.
+///
+/// It also stalls the indexing threads while the pending and flushing
+/// `DocumentsWriterPerThread`s use more than twice the RAM buffer, so that
+/// indexing can't outrun the flushes.
 pub struct DocumentsWriterFlushControl<
     D: Directory + Send + Sync + 'static,
     C: Codec,
@@ -45,6 +50,14 @@ pub struct DocumentsWriterFlushControl<
 > {
     lock: Arc<Mutex<FlushControlLock>>,
     cond: Condvar,
+    hard_max_bytes_per_dwpt: u64,
+    // RAM used by the DWPTs that are not pending for flush
+    active_bytes: u64,
+    // RAM used by the pending and flushing DWPTs
+    flush_bytes: u64,
+    // indexing threads are stalled above this RAM, u64::MAX if disabled
+    stall_limit_bytes: u64,
+    stalled: AtomicBool,
     num_pending: Volatile<usize>,
     // only with assert
     flush_deletes: AtomicBool,
@@ -64,10 +77,22 @@ pub struct FlushControlLock;
 impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: MergePolicy>
     DocumentsWriterFlushControl<D, C, MS, MP>
 {
-    pub fn new(flush_policy: Arc<FlushByCountsPolicy<C, MS, MP>>) -> Self {
+    pub fn new(
+        flush_policy: Arc<FlushByCountsPolicy<C, MS, MP>>,
+        config: &IndexWriterConfig<C, MS, MP>,
+    ) -> Self {
         DocumentsWriterFlushControl {
             lock: Arc::new(Mutex::new(FlushControlLock)),
             cond: Condvar::new(),
+            hard_max_bytes_per_dwpt: config.ram_per_thread_hard_limit_mb() as u64 * 1024 * 1024,
+            active_bytes: 0,
+            flush_bytes: 0,
+            stall_limit_bytes: if config.flush_on_ram() {
+                (2.0 * config.ram_buffer_size_mb() * 1024.0 * 1024.0) as u64
+            } else {
+                u64::MAX
+            },
+            stalled: AtomicBool::new(false),
             num_pending: Volatile::new(0),
             flush_deletes: AtomicBool::new(false),
             full_flush: AtomicBool::new(false),
@@ -101,6 +126,16 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         unsafe { &*self.per_thread_pool }
     }
 
+    /// RAM used by the DWPTs that are still indexing.
+    pub fn active_bytes(&self) -> u64 {
+        self.active_bytes
+    }
+
+    /// RAM used by the DWPTs that are pending for flush or flushing.
+    pub fn flush_bytes(&self) -> u64 {
+        self.flush_bytes
+    }
+
     #[allow(clippy::mut_from_ref)]
     unsafe fn flush_control_mut(
         &self,
@@ -129,6 +164,7 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         is_update: bool,
         lg: &MutexGuard<FlushControlLock>,
     ) {
+        self.commit_per_thread_bytes(per_thread);
         if !per_thread.flush_pending() {
             unsafe {
                 let writer = self as *mut DocumentsWriterFlushControl<D, C, MS, MP>;
@@ -138,7 +174,58 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
                     self.flush_policy.on_insert(&mut *writer, lg, per_thread);
                 }
             }
+            if !per_thread.flush_pending() && per_thread.bytes_used > self.hard_max_bytes_per_dwpt {
+                // Safety check to prevent a single DWPT exceeding its RAM limit. This
+                // is super important since we can not address more than 2048 MB per DWPT
+                self.set_flush_pending(per_thread, lg);
+            }
+        }
+        if per_thread.flush_pending() && !self.is_full_flush() {
+            // take it out of rotation right away so that no more docs are added to
+            // it, it is then flushed by the flush threads
+            if let Some(dwpt) = self.internal_try_checkout_for_flush_no_lock(per_thread) {
+                self.flush_queue.push_back(dwpt);
+            }
         }
+        self.update_stall_state(lg);
+    }
+
+    fn update_stall_state(&self, _lg: &MutexGuard<FlushControlLock>) {
+        // don't stall if the active DWPTs alone are over the limit, only
+        // the flushes could release them
+        let stall = self.active_bytes + self.flush_bytes > self.stall_limit_bytes
+            && self.active_bytes < self.stall_limit_bytes
+            && !self.closed;
+        if self.stalled.swap(stall, Ordering::AcqRel) && !stall {
+            self.cond.notify_all();
+        }
+    }
+
+    /// Returns true if the indexing threads must wait for the flushes to
+    /// catch up, see `wait_if_stalled`.
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(Ordering::Acquire)
+    }
+
+    /// Blocks while the indexing threads are stalled, for at most a second
+    /// so that the caller can help out flushing again.
+    pub fn wait_if_stalled(&self) -> Result<()> {
+        let l = self.lock.lock()?;
+        if self.is_stalled() {
+            let _ = self.cond.wait_timeout(l, Duration::from_secs(1))?;
+        }
+        Ok(())
+    }
+
+    fn commit_per_thread_bytes(&mut self, per_thread: &mut ThreadState<D, C, MS, MP>) {
+        let bytes_used = per_thread.dwpt().bytes_used();
+        if per_thread.flush_pending() {
+            // marked pending by another thread, its RAM is accounted as flushing
+            self.flush_bytes = self.flush_bytes + bytes_used - per_thread.bytes_used;
+        } else {
+            self.active_bytes = self.active_bytes + bytes_used - per_thread.bytes_used;
+        }
+        per_thread.bytes_used = bytes_used;
     }
 
     pub fn do_on_delete(&mut self) {
@@ -153,9 +240,16 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         &self,
         state: &mut ThreadState<D, C, MS, MP>,
     ) -> Option<DocumentsWriterPerThread<D, C, MS, MP>> {
-        let _l = self.lock.lock().unwrap();
+        let l = self.lock.lock().unwrap();
+        let control_mut = unsafe { self.flush_control_mut(&l) };
+        if state.flush_pending() {
+            control_mut.flush_bytes -= state.bytes_used;
+        } else {
+            control_mut.active_bytes -= state.bytes_used;
+        }
         // Take it out of the loop this DWPT is stale
         let dwpt = self.per_thread_pool().reset(state);
+        self.update_stall_state(&l);
 
         dwpt
     }
@@ -198,10 +292,14 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         debug_assert!(flush_control_mut
             .flushing_writers
             .contains_key(&dwpt.segment_info.name));
-        flush_control_mut
+        if let Some(bytes) = flush_control_mut
             .flushing_writers
-            .remove(&dwpt.segment_info.name);
+            .remove(&dwpt.segment_info.name)
+        {
+            flush_control_mut.flush_bytes -= bytes;
+        }
         self.per_thread_pool().recycle(dwpt);
+        self.update_stall_state(lg);
 
         self.cond.notify_all();
     }
@@ -265,6 +363,8 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         if per_thread.dwpt().num_docs_in_ram > 0 {
             // write access synced
             per_thread.flush_pending.store(true, Ordering::Release);
+            self.flush_bytes += per_thread.bytes_used;
+            self.active_bytes -= per_thread.bytes_used;
             self.num_pending.update(|v| *v += 1);
         }
         // don't assert on numDocs since we could hit an abort excp.
@@ -296,11 +396,12 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
         debug_assert!(per_thread.flush_pending());
         // We are pending so all memory is already moved to flushBytes
         if per_thread.inited() {
+            let bytes_used = per_thread.bytes_used;
             let dwpt = self.per_thread_pool().reset(per_thread).unwrap();
             debug_assert!(!self.flushing_writers.contains_key(&dwpt.segment_info.name));
 
             self.flushing_writers
-                .insert(dwpt.segment_info.name.clone(), 0);
+                .insert(dwpt.segment_info.name.clone(), bytes_used);
             self.num_pending.update(|v| *v -= 1);
             Some(dwpt)
         } else {
@@ -310,8 +411,9 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
 
     pub fn set_closed(&mut self) {
         // set by DW to signal that we should not release new DWPT after close
-        let _l = self.lock.lock().unwrap();
+        let l = self.lock.lock().unwrap();
         self.closed = true;
+        self.update_stall_state(&l);
     }
 
     pub fn is_full_flush(&self) -> bool {
@@ -386,6 +488,9 @@ impl<D: Directory + Send + Sync + 'static, C: Codec, MS: MergeScheduler, MP: Mer
                 .flush_queue
                 .push_back(flushing_dwpt.unwrap());
         } else {
+            let l = self.lock.lock().unwrap();
+            let flush_control_mut = unsafe { self.flush_control_mut(&l) };
+            flush_control_mut.active_bytes -= per_therad.bytes_used;
             // make this state inactive
             self.per_thread_pool().reset(per_therad);
         }
@@ -18,6 +18,7 @@ use crate::core::index::writer::{
 };
 use crate::core::store::directory::Directory;
 
+use std::ptr;
 use std::sync::{Arc, MutexGuard};
 
 /// `FlushPolicy` controls when segments are flushed from a RAM resident
@@ -25,9 +26,9 @@ use std::sync::{Arc, MutexGuard};
 ///
 /// Segments are traditionally flushed by:
 /// - RAM consumption - configured via
-/// - `IndexWriterConfig#setRAMBufferSizeMB(double)`
+/// - `IndexWriterConfig::set_ram_buffer_size_mb`
 /// - Number of RAM resident documents - configured via
-/// - `IndexWriterConfig#setMaxBufferedDocs(int)`
+/// - `IndexWriterConfig::set_max_buffered_docs`
 ///
 /// The policy also applies pending delete operations (by term and/or query),
 /// given the threshold set in
@@ -133,7 +134,7 @@ pub trait FlushPolicy {
 /// `DocumentsWriterPerThread` as flush pending during indexing with
 /// respect to their live updates.
 ///
-/// If `IndexWriterConfig#setRAMBufferSizeMB(double)` is enabled, the
+/// If `IndexWriterConfig::set_ram_buffer_size_mb` is enabled, the
 /// largest ram consuming `DocumentsWriterPerThread` will be marked as
 /// pending iff the global active RAM consumption is {@code >=} the configured max RAM
 /// buffer.
@@ -187,6 +188,52 @@ impl<C1: Codec, MS1: MergeScheduler, MP1: MergePolicy> FlushPolicy
         {
             // Flush this state by num docs
             control.set_flush_pending(state, lg);
+        } else if self.index_write_config.flush_on_ram() {
+            // Flush by the RAM used by all the active DWPTs
+            let limit = (self.index_write_config.ram_buffer_size_mb() * 1024.0 * 1024.0) as u64;
+            if control.active_bytes() >= limit {
+                self.mark_largest_writer_pending(control, lg, state);
+            }
+        }
+    }
+}
+
+impl<C1: Codec, MS1: MergeScheduler, MP1: MergePolicy> FlushByCountsPolicy<C1, MS1, MP1> {
+    /// Marks the most RAM consuming active `DocumentsWriterPerThread` flush
+    /// pending.
+    fn mark_largest_writer_pending<D, C, MS, MP>(
+        &self,
+        control: &mut DocumentsWriterFlushControl<D, C, MS, MP>,
+        lg: &MutexGuard<FlushControlLock>,
+        state: &ThreadState<D, C, MS, MP>,
+    ) where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+    {
+        // the bytes used of a state are only updated while holding the flush
+        // control lock, and are zero for the states without a DWPT
+        let mut largest: Option<Arc<ThreadState<D, C, MS, MP>>> = None;
+        let mut max_bytes = state.bytes_used;
+        let pool = control.per_thread_pool();
+        for i in 0..pool.active_thread_state_count() {
+            let next = pool.get_thread_state(i);
+            if ptr::eq(next.as_ref(), state) || next.flush_pending() {
+                continue;
+            }
+            if next.bytes_used > max_bytes {
+                max_bytes = next.bytes_used;
+                largest = Some(next);
+            }
+        }
+
+        match largest {
+            Some(next) => control.set_flush_pending(next.as_ref(), lg),
+            None => {
+                debug_assert!(!state.flush_pending());
+                control.set_flush_pending(state, lg);
+            }
         }
     }
 }
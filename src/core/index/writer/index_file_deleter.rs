@@ -17,7 +17,7 @@ use crate::core::codec::segment_infos::{
     INDEX_FILE_OLD_SEGMENT_GEN, INDEX_FILE_PENDING_SEGMENTS, INDEX_FILE_SEGMENTS,
 };
 use crate::core::codec::Codec;
-use crate::core::index::writer::KeepOnlyLastCommitDeletionPolicy;
+use crate::core::index::writer::IndexDeletionPolicy;
 use crate::core::store::directory::{Directory, LockValidatingDirectoryWrapper};
 
 use regex::Regex;
@@ -76,7 +76,7 @@ pub struct IndexFileDeleter<D: Directory> {
     commits: Vec<CommitPoint>,
     /// Holds files we had inc_ref'd from the previous non-commit checkpoint:
     last_files: HashSet<String>,
-    policy: KeepOnlyLastCommitDeletionPolicy,
+    policy: Arc<dyn IndexDeletionPolicy>,
 
     delayed_dv_update_files: Arc<Mutex<Vec<(u64, Vec<String>)>>>,
     dv_pattern: Regex,
@@ -87,12 +87,15 @@ pub struct IndexFileDeleter<D: Directory> {
 }
 
 impl<D: Directory> IndexFileDeleter<D> {
-    pub fn new(directory: Arc<LockValidatingDirectoryWrapper<D>>) -> Self {
+    pub fn new(
+        directory: Arc<LockValidatingDirectoryWrapper<D>>,
+        policy: Arc<dyn IndexDeletionPolicy>,
+    ) -> Self {
         IndexFileDeleter {
             ref_counts: Arc::new(RwLock::new(HashMap::new())),
             commits: vec![],
             last_files: HashSet::new(),
-            policy: KeepOnlyLastCommitDeletionPolicy {},
+            policy,
             delayed_dv_update_files: Arc::new(Mutex::new(Vec::new())),
             dv_pattern: Regex::new(CODEC_UPDATE_DV_PATTERN).unwrap(),
             fnm_pattern: Regex::new(CODEC_UPDATE_FNM_PATTERN).unwrap(),
@@ -133,12 +136,7 @@ impl<D: Directory> IndexFileDeleter<D> {
                         // incref all files it refers to:
                         let sis: SegmentInfos<D, C> =
                             SegmentInfos::read_commit(&directory_orig, filename)?;
-                        let commit_point = CommitPoint::new(
-                            sis.generation,
-                            sis.segment_file_name().unwrap_or("".to_string()),
-                            sis.files(true),
-                            sis.has_dv_updates(),
-                        );
+                        let commit_point = CommitPoint::new(&sis);
                         self.commits.push(commit_point);
                         if sis.generation == segment_infos.generation {
                             current_commit_point_idx = Some(self.commits.len() - 1);
@@ -158,12 +156,7 @@ impl<D: Directory> IndexFileDeleter<D> {
                 // try now to explicitly open this commit point:
                 let sis: SegmentInfos<D, C> =
                     SegmentInfos::read_commit(&directory_orig, current_segments_file)?;
-                let commit_point = CommitPoint::new(
-                    sis.generation,
-                    sis.segment_file_name().unwrap_or("".to_string()),
-                    sis.files(true),
-                    sis.has_dv_updates(),
-                );
+                let commit_point = CommitPoint::new(&sis);
                 self.commits.push(commit_point);
                 current_commit_point_idx = Some(self.commits.len() - 1);
                 self.inc_ref_files(&sis.files(true));
@@ -326,12 +319,7 @@ impl<D: Directory> IndexFileDeleter<D> {
 
         if is_commit {
             // Append to our commits list:
-            let p = CommitPoint::new(
-                segment_infos.generation,
-                segment_infos.segment_file_name().unwrap_or("".to_string()),
-                segment_infos.files(true),
-                segment_infos.has_dv_updates(),
-            );
+            let p = CommitPoint::new(segment_infos);
             self.commits.push(p);
 
             // Tell policy so it can remove commits:
@@ -355,6 +343,22 @@ impl<D: Directory> IndexFileDeleter<D> {
         }
     }
 
+    /// Gives the policy a chance to remove commits outside of a commit, e.g.
+    /// after a snapshot of one of them was released.
+    pub fn revisit_policy(&mut self) -> Result<()> {
+        if !self.commits.is_empty() {
+            {
+                let mut commits: Vec<&mut CommitPoint> = Vec::with_capacity(self.commits.len());
+                for i in &mut self.commits {
+                    commits.push(i);
+                }
+                self.policy.on_commit(commits)?;
+            }
+            self.delete_commits()?;
+        }
+        Ok(())
+    }
+
     pub fn exists(&self, filename: &str) -> bool {
         if !self.ref_counts.read().unwrap().contains_key(filename) {
             false
@@ -633,26 +637,24 @@ impl fmt::Debug for RefCount {
 /// Holds details for each commit point. This class is also passed to
 /// the deletion policy. Note: this class has a natural ordering that
 /// is inconsistent with equals.
+#[derive(Clone, Debug)]
 pub struct CommitPoint {
     generation: i64,
     segment_file_name: String,
     files: HashSet<String>,
     has_dv_updates: bool,
+    user_data: HashMap<String, String>,
     deleted: bool,
 }
 
 impl CommitPoint {
-    fn new(
-        generation: i64,
-        segment_file_name: String,
-        files: HashSet<String>,
-        has_dv_updates: bool,
-    ) -> Self {
+    pub(crate) fn new<D: Directory, C: Codec>(infos: &SegmentInfos<D, C>) -> Self {
         CommitPoint {
-            generation,
-            segment_file_name,
-            files,
-            has_dv_updates,
+            generation: infos.generation,
+            segment_file_name: infos.segment_file_name().unwrap_or_default(),
+            files: infos.files(true),
+            has_dv_updates: infos.has_dv_updates(),
+            user_data: infos.user_data.clone(),
             deleted: false,
         }
     }
@@ -662,6 +664,27 @@ impl CommitPoint {
         &self.segment_file_name
     }
 
+    /// Returns the generation (the _N in segments_N) for this commit point
+    pub fn generation(&self) -> i64 {
+        self.generation
+    }
+
+    /// Returns all index files referenced by this commit point, including
+    /// the segments file.
+    pub fn file_names(&self) -> &HashSet<String> {
+        &self.files
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Revert a `delete` call done on this commit point by the wrapped
+    /// policy of a policy that needs to protect the commit.
+    pub(crate) fn undelete(&mut self) {
+        self.deleted = false;
+    }
+
     /// Delete this commit point.  This only applies when using
     /// the commit point in the context of IndexWriter's
     /// IndexDeletionPolicy.
@@ -681,6 +704,12 @@ impl CommitPoint {
     pub fn has_dv_updates(&self) -> bool {
         self.has_dv_updates
     }
+
+    /// Returns the user data stored with this commit, see
+    /// `IndexWriter::set_live_commit_data`.
+    pub fn user_data(&self) -> &HashMap<String, String> {
+        &self.user_data
+    }
 }
 
 impl Ord for CommitPoint {
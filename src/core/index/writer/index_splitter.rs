@@ -0,0 +1,218 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::reader::{IndexReader, LeafReader, SegmentReader, StandardDirectoryReader};
+use crate::core::index::writer::{IndexWriter, IndexWriterConfig, OpenMode};
+use crate::core::search::cache::{NoCacheQueryCache, QueryCache};
+use crate::core::search::query::Query;
+use crate::core::search::{
+    DefaultIndexSearcher, DocIterator, IndexSearcher, ScoreMode, SearchPlanBuilder, NO_MORE_DOCS,
+};
+use crate::core::store::directory::Directory;
+use crate::core::util::{BitSet, BitsRef, FixedBitSet};
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::sync::Arc;
+
+/// How `IndexSplitter` assigns the documents to the parts.
+pub enum SplitMode<C: Codec> {
+    /// The i-th live document goes to the part `i % num_parts`.
+    RoundRobin,
+    /// A document goes to the part of the first query it matches, the
+    /// documents matching none of them go to the last part. There must be
+    /// one query less than parts.
+    Queries(Vec<Arc<dyn Query<C>>>),
+}
+
+/// Splits an index into several indexes, e.g. to reshard an index that grew
+/// too large.
+///
+/// Each part is written by merging the source segments, with the documents
+/// of the other parts hidden, into a new index: the documents are rewritten
+/// as by a merge and are not re-analyzed. The deleted documents of the
+/// source index are dropped.
+///
+/// The source index is left untouched, it must not be written to while it
+/// is split.
+pub struct IndexSplitter<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    directory: Arc<D>,
+    config: Arc<IndexWriterConfig<C, MS, MP>>,
+}
+
+impl<D, C, MS, MP> IndexSplitter<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    /// Creates a splitter for the index in `directory`, the parts are written
+    /// with the codec and the other settings of `config`. Its `open_mode` is
+    /// ignored, the parts are always created from scratch.
+    pub fn new(directory: Arc<D>, mut config: IndexWriterConfig<C, MS, MP>) -> Self {
+        config.open_mode = OpenMode::Create;
+        IndexSplitter {
+            directory,
+            config: Arc::new(config),
+        }
+    }
+
+    /// Splits the latest commit of the index into `outputs.len()` parts, the
+    /// i-th part being committed to `outputs[i]`.
+    pub fn split(&self, outputs: &[Arc<D>], mode: &SplitMode<C>) -> Result<()> {
+        if outputs.len() < 2 {
+            return Err(IllegalArgument(format!(
+                "an index must be split into at least 2 parts, got {}",
+                outputs.len()
+            )));
+        }
+        if let SplitMode::Queries(queries) = mode {
+            if queries.len() + 1 != outputs.len() {
+                return Err(IllegalArgument(format!(
+                    "splitting into {} parts needs {} queries, got {}",
+                    outputs.len(),
+                    outputs.len() - 1,
+                    queries.len()
+                )));
+            }
+        }
+
+        let reader: StandardDirectoryReader<D, C, MS, MP> =
+            StandardDirectoryReader::open(Arc::clone(&self.directory))?;
+        let parts = match mode {
+            SplitMode::RoundRobin => Self::round_robin_parts(&reader, outputs.len()),
+            SplitMode::Queries(queries) => Self::query_parts(&reader, queries)?,
+        };
+
+        for (i, output) in outputs.iter().enumerate() {
+            let mut readers = Vec::with_capacity(parts.len());
+            for (segment_reader, doc_parts) in reader.segment_readers().iter().zip(&parts) {
+                readers.push(Arc::new(Self::part_reader(segment_reader, doc_parts, i)?));
+            }
+            log::info!(
+                "writing part {} of index directory '{}' to '{}'",
+                i,
+                self.directory,
+                output
+            );
+            let writer = IndexWriter::new(Arc::clone(output), Arc::clone(&self.config))?;
+            let res = writer.add_readers(&readers).and_then(|_| writer.commit());
+            if let Err(e) = res {
+                writer.rollback()?;
+                return Err(e);
+            }
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    // the part of each doc of each segment, `None` for the deleted docs
+    fn round_robin_parts(
+        reader: &StandardDirectoryReader<D, C, MS, MP>,
+        num_parts: usize,
+    ) -> Vec<Vec<Option<usize>>> {
+        let mut next = 0;
+        reader
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| {
+                let live_docs = segment_reader.live_docs();
+                (0..segment_reader.max_docs() as usize)
+                    .map(|doc| {
+                        if !live_docs.get(doc) {
+                            return None;
+                        }
+                        let part = next;
+                        next = (next + 1) % num_parts;
+                        Some(part)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn query_parts(
+        reader: &StandardDirectoryReader<D, C, MS, MP>,
+        queries: &[Arc<dyn Query<C>>],
+    ) -> Result<Vec<Vec<Option<usize>>>> {
+        let mut searcher = DefaultIndexSearcher::new(reader, None);
+        let query_cache: Arc<dyn QueryCache<C>> = Arc::new(NoCacheQueryCache::new());
+        searcher.set_query_cache(query_cache);
+
+        let mut parts = Vec::with_capacity(reader.segment_readers().len());
+        for leaf in searcher.reader().leaves() {
+            let live_docs = leaf.reader.live_docs();
+            let mut doc_parts: Vec<Option<usize>> = (0..leaf.reader.max_doc() as usize)
+                .map(|doc| {
+                    if live_docs.get(doc) {
+                        Some(queries.len())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            // the queries are applied in reverse order so that the first
+            // matching one wins
+            for (i, query) in queries.iter().enumerate().rev() {
+                let weight = searcher
+                    .create_normalized_weight(query.as_ref(), ScoreMode::CompleteNoScores)?;
+                if let Some(mut scorer) = weight.create_scorer(&leaf)? {
+                    loop {
+                        let doc = scorer.next()?;
+                        if doc == NO_MORE_DOCS {
+                            break;
+                        }
+                        if let Some(part) = doc_parts[doc as usize].as_mut() {
+                            *part = i;
+                        }
+                    }
+                }
+            }
+            parts.push(doc_parts);
+        }
+        Ok(parts)
+    }
+
+    // a reader over `segment_reader` hiding the docs of the other parts
+    fn part_reader(
+        segment_reader: &Arc<SegmentReader<D, C>>,
+        doc_parts: &[Option<usize>],
+        part: usize,
+    ) -> Result<SegmentReader<D, C>> {
+        let mut live_docs = FixedBitSet::new(doc_parts.len());
+        let mut num_docs = 0;
+        for (doc, doc_part) in doc_parts.iter().enumerate() {
+            if *doc_part == Some(part) {
+                live_docs.set(doc);
+                num_docs += 1;
+            }
+        }
+        let live_docs: BitsRef = Arc::new(live_docs);
+        SegmentReader::build_from(
+            Arc::clone(&segment_reader.si),
+            segment_reader.as_ref(),
+            live_docs,
+            num_docs,
+            false,
+        )
+    }
+}
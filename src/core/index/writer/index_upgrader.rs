@@ -0,0 +1,90 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::index::merge::{MergePolicy, MergeScheduler, UpgradeIndexMergePolicy};
+use crate::core::index::reader::index_exist;
+use crate::core::index::writer::{IndexWriter, IndexWriterConfig, OpenMode};
+use crate::core::store::directory::Directory;
+use crate::core::util::VERSION_LATEST;
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::sync::Arc;
+
+/// Upgrades an index written by an older rucene version to the current
+/// index format, without reindexing the documents.
+///
+/// All the segments written by an older version or with another codec than
+/// the one of the config are rewritten by a forced merge with an
+/// `UpgradeIndexMergePolicy` wrapping the config's merge policy. The
+/// segments already in the current format are left alone. The old commits
+/// are removed or kept by the config's `index_deletion_policy`, as usual.
+///
+/// No `IndexWriter` may be open on the directory during the upgrade.
+pub struct IndexUpgrader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    directory: Arc<D>,
+    config: Arc<IndexWriterConfig<C, MS, UpgradeIndexMergePolicy<MP>>>,
+}
+
+impl<D, C, MS, MP> IndexUpgrader<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    /// Creates an upgrader for the index in `directory`, the segments are
+    /// rewritten with the codec and the other settings of `config`. Its
+    /// `open_mode` is ignored, the index must exist.
+    pub fn new(directory: Arc<D>, config: IndexWriterConfig<C, MS, MP>) -> Self {
+        let mut config = config.with_merge_policy(UpgradeIndexMergePolicy::new);
+        config.open_mode = OpenMode::Append;
+        IndexUpgrader {
+            directory,
+            config: Arc::new(config),
+        }
+    }
+
+    /// Rewrites all the old segments and commits the upgraded index.
+    pub fn upgrade(&self) -> Result<()> {
+        if !index_exist(self.directory.as_ref())? {
+            return Err(IllegalArgument(format!(
+                "{} is not a valid index",
+                self.directory
+            )));
+        }
+
+        let writer = IndexWriter::new(Arc::clone(&self.directory), Arc::clone(&self.config))?;
+        log::info!(
+            "upgrading all segments of index directory '{}' older than {:?} or not written with \
+             codec {}",
+            self.directory,
+            VERSION_LATEST,
+            self.config.codec().name()
+        );
+        let res = writer.force_merge(1, true).and_then(|_| writer.commit());
+        if let Err(e) = res {
+            writer.rollback()?;
+            return Err(e);
+        }
+        log::info!("all segments upgraded to version {:?}", VERSION_LATEST);
+        writer.close()
+    }
+}
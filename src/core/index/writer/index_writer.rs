@@ -16,33 +16,42 @@ use crate::core::codec::field_infos::{
     FieldInfo, FieldInfos, FieldInfosBuilder, FieldInfosFormat, FieldNumbers, FieldNumbersRef,
 };
 use crate::core::codec::segment_infos::{
-    file_name_from_generation, get_last_commit_segments_filename, SegmentCommitInfo, SegmentInfo,
-    SegmentInfoFormat, SegmentInfos, SegmentWriteState, INDEX_FILE_PENDING_SEGMENTS,
+    file_name_from_generation, get_last_commit_segments_filename, strip_segment_name,
+    SegmentCommitInfo, SegmentInfo, SegmentInfoFormat, SegmentInfos, SegmentWriteState,
+    INDEX_FILE_PENDING_SEGMENTS,
 };
 use crate::core::codec::{Codec, CompoundFormat, LiveDocsFormat, PackedLongDocMap};
 use crate::core::doc::{DocValuesType, Fieldable};
-use crate::core::index::merge::MergeRateLimiter;
+use crate::core::index::merge::MergeOutputRateLimiter;
 use crate::core::index::merge::MergeScheduler;
 use crate::core::index::merge::SegmentMerger;
 use crate::core::index::merge::{DocMap, MergeState};
 use crate::core::index::merge::{MergePolicy, MergeSpecification, MergerTrigger};
 use crate::core::index::merge::{OneMerge, OneMergeRunningInfo};
 use crate::core::index::reader::index_exist;
-use crate::core::index::reader::{LeafReader, SegmentReader, StandardDirectoryReader};
+use crate::core::index::reader::{
+    soft_deleted_docs, LeafReader, SearchLeafReader, SegmentReader, StandardDirectoryReader,
+};
 use crate::core::index::writer::{
-    BufferedUpdatesStream, DocumentsWriter, Event, FlushedSegment, FrozenBufferedUpdates,
-    IndexFileDeleter, IndexWriterConfig, MergedDocValuesUpdatesIterator, NewDocValuesIterator,
-    NumericDocValuesUpdate, OpenMode,
+    AutoCommitTrigger, BufferedUpdatesStream, CommitDurability, DocumentsWriter, Event,
+    FlushedSegment, FrozenBufferedUpdates, IndexFileDeleter, IndexWriterConfig, IndexWriterMetrics,
+    MergedDocValuesUpdatesIterator, NewDocValuesIterator, NumericDocValuesUpdate, OpenMode,
+    SegmentEventInfo,
 };
 use crate::core::index::Term;
 use crate::core::search::query::{MatchAllDocsQuery, Query};
 use crate::core::store::directory::{
-    Directory, LockValidatingDirectoryWrapper, TrackingDirectoryWrapper,
+    obtain_lock_with_timeout, Directory, LockValidatingDirectoryWrapper, NoSyncDirectoryWrapper,
+    TrackingDirectoryWrapper, DEFAULT_POLL_INTERVAL,
 };
-use crate::core::store::{FlushInfo, IOContext};
+use crate::core::store::lock::{Lock, WRITE_LOCK_NAME};
+use crate::core::store::{FlushInfo, IOContext, MergeInfo, RateLimiter, SimpleRateLimiter};
 use crate::core::util::random_id;
 use crate::core::util::to_base36;
-use crate::core::util::{BitsRef, DerefWrapper, DocId, VERSION_LATEST};
+use crate::core::util::{
+    Accountable, Bits, BitsRef, DerefWrapper, DocId, ImmutableBitSet, NamedAccountable,
+    VERSION_LATEST,
+};
 
 use crate::core::index::Error::MergeAborted;
 use crate::error::Error::{AlreadyClosed, IllegalArgument, IllegalState, IndexError, RuntimeError};
@@ -54,14 +63,13 @@ use std::ops::Deref;
 use std::ptr;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::core::codec::doc_values::{
     DocValuesWriter, NumericDocValuesWriter, SortedNumericDocValuesWriter,
 };
 use crate::core::index::writer::dir_wrapper::RateLimitFilterDirectory;
 use crate::core::search::NO_MORE_DOCS;
-use thread_local::ThreadLocal;
 
 /// Hard limit on maximum number of documents that may be added to the index
 /// If you try to add more than this you'll hit `IllegalArgument` Error
@@ -213,6 +221,26 @@ where
     }
 }
 
+/// Accounts the RAM used by the indexing buffer of this writer.
+impl<D, C, MS, MP> Accountable for IndexWriter<D, C, MS, MP>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+{
+    fn ram_bytes_used(&self) -> usize {
+        self.writer.doc_writer.ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        vec![NamedAccountable::new(
+            "indexing buffer",
+            &self.writer.doc_writer,
+        )]
+    }
+}
+
 impl<D, C, MS, MP> IndexWriter<D, C, MS, MP>
 where
     D: Directory + Send + Sync + 'static,
@@ -291,7 +319,19 @@ where
         apply_all_deletes: bool,
         write_all_deletes: bool,
     ) -> Result<StandardDirectoryReader<D, C, MS, MP>> {
-        IndexWriterInner::get_reader(self, apply_all_deletes, write_all_deletes)
+        IndexWriterInner::get_reader(self, apply_all_deletes, write_all_deletes, &[])
+    }
+
+    /// Same as `get_reader`, but shares the readers of `old_readers` whose
+    /// segment didn't change since they were obtained from this writer, so
+    /// that only the new and modified segments are opened.
+    pub(crate) fn reopen_reader(
+        &self,
+        apply_all_deletes: bool,
+        write_all_deletes: bool,
+        old_readers: &[Arc<SegmentReader<D, C>>],
+    ) -> Result<StandardDirectoryReader<D, C, MS, MP>> {
+        IndexWriterInner::get_reader(self, apply_all_deletes, write_all_deletes, old_readers)
     }
 
     #[inline]
@@ -315,6 +355,57 @@ where
         count
     }
 
+    /// Returns a snapshot of the flush, merge and delete activity of this
+    /// writer, e.g. to export it to a monitoring system.
+    pub fn metrics(&self) -> IndexWriterMetrics {
+        let writer = &self.writer;
+        let (running_merges, pending_merges, segment_count) = {
+            let _l = writer.lock.lock().unwrap();
+            (
+                writer.running_merges.len(),
+                writer.pending_merges.len(),
+                writer.segment_infos.len(),
+            )
+        };
+        IndexWriterMetrics {
+            flush_count: writer.flushed_segment_count.load(Ordering::Acquire),
+            flushed_docs: writer.flushed_docs.load(Ordering::Acquire),
+            flushed_bytes: writer.flushed_bytes.load(Ordering::Acquire),
+            running_merges,
+            pending_merges,
+            merge_count: writer.merge_count.load(Ordering::Acquire),
+            merged_bytes: writer.merged_bytes.load(Ordering::Acquire),
+            buffered_delete_terms: writer.doc_writer.num_global_term_deletes(),
+            applied_deletes: writer.buffered_updates_stream.num_applied_deletes(),
+            ram_bytes_used: writer.doc_writer.ram_bytes_used() as u64,
+            segment_count,
+        }
+    }
+
+    /// Returns the sequence number of the last operation that changed the
+    /// index, all the operations up to it are included in a reader opened
+    /// after this call.
+    pub fn last_sequence_number(&self) -> u64 {
+        self.writer.doc_writer.delete_queue.last_sequence_number()
+    }
+
+    /// Returns the highest sequence number of the completed operations, the
+    /// operations still running in other threads are not counted until they
+    /// finish.
+    ///
+    /// A reader opened after this call sees all the operations up to it.
+    pub fn max_completed_sequence_number(&self) -> u64 {
+        self.writer.doc_writer.max_completed_sequence_number()
+    }
+
+    /// Returns the sequence number of the last commit done by this writer,
+    /// as returned by `#commit`, or -1 if it didn't commit anything yet.
+    ///
+    /// All the operations with a sequence number <= this value are durable.
+    pub fn last_committed_sequence_number(&self) -> i64 {
+        self.writer.last_committed_seq_no.load(Ordering::Acquire)
+    }
+
     #[inline]
     /// Returns the Directory used by this index.
     pub fn directory(&self) -> &Arc<D> {
@@ -397,36 +488,64 @@ where
         IndexWriterInner::update_document(self, doc, term)
     }
 
+    /// Updates a document by first soft deleting the document(s) containing
+    /// `term` and then adding the new document.
+    ///
+    /// Instead of being deleted, the old documents get the value `1` for the
+    /// `IndexWriterConfig::soft_deletes_field`: readers opened from this writer
+    /// hide them, while merges only reclaim them once the merge policy allows
+    /// it, see `SoftDeletesRetentionMergePolicy`.
+    ///
+    /// The new document gets a later sequence number than the soft delete,
+    /// so it is never soft deleted itself, but unlike `update_document` a
+    /// reader opened in between may see neither the old nor the new document.
+    /// Documents indexed before the soft deletes field was configured can't be
+    /// soft deleted.
+    ///
+    /// Returns the sequence number of the add.
+    pub fn soft_update_document<F: Fieldable>(&self, doc: Vec<F>, term: Term) -> Result<u64> {
+        let field = match self.writer.config.soft_deletes_field() {
+            Some(field) => field,
+            None => {
+                return Err(IllegalState("soft deletes field is not configured".into()));
+            }
+        };
+        // the field is only known once a doc was indexed with it, until then
+        // there is nothing to soft delete
+        if self
+            .writer
+            .global_field_numbers
+            .get_doc_values_type(field)?
+            .is_some()
+        {
+            IndexWriterInner::update_numeric_doc_value(self, term, field, 1)?;
+        }
+        self.add_document(doc)
+    }
+
     /// Atomically adds a block of documents with sequentially
     /// assigned document IDs, such that an external reader
     /// will see all or none of the documents.
     ///
+    /// The block is written contiguously, in the given order, to a single
+    /// segment and merges never split it, as block-join queries require,
+    /// e.g. with the parent document last. If indexing one of the documents
+    /// fails, all the documents of the block are deleted. Blocks can't be
+    /// added to an index with an `IndexWriterConfig::index_sort`, as the
+    /// sort would split them.
+    ///
     /// <b>WARNING</b>: the index does not currently record
-    /// which documents were added as a block.  Today this is
-    /// fine, because merging will preserve a block. The order of
-    /// documents within a segment will be preserved, even when child
-    /// documents within a block are deleted. Most search features
+    /// which documents were added as a block. Most search features
     /// (like result grouping and block joining) require you to
     /// mark documents; when these documents are deleted these
     /// search features will not work as expected. Obviously adding
     /// documents to an existing block will require you the reindex
     /// the entire block.
     ///
-    /// However it's possible that in the future Lucene may
-    /// merge more aggressively re-order documents (for example,
-    /// perhaps to obtain better index compression), in which case
-    /// you may need to fully re-index your documents at that time.
-    ///
-    /// See `#addDocument(Iterable)` for details on
-    /// index and IndexWriter state after an Exception, and
-    /// flushing/merging temporary free space requirements.
-    ///
     /// <b>NOTE</b>: tools that do offline splitting of an index
-    /// (for example, IndexSplitter in contrib) or
-    /// re-sorting of documents (for example, IndexSorter in
-    /// contrib) are not aware of these atomically added documents
-    /// and will likely break them up.  Use such tools at your
-    /// own risk!
+    /// (for example, IndexSplitter in contrib) are not aware of these
+    /// atomically added documents and will likely break them up. Use such
+    /// tools at your own risk!
     ///
     /// @return The <a href="#sequence_number">sequence number</a>
     /// for this operation
@@ -515,6 +634,47 @@ where
         IndexWriterInner::delete_all(self)
     }
 
+    /// Adds all segments from an array of indexes into this index.
+    ///
+    /// This may be used to parallelize batch indexing. A large document
+    /// collection can be broken into sub-collections. Each sub-collection can be
+    /// indexed in parallel, on a different thread, process or machine. The
+    /// complete index can then be created by merging sub-collection indexes
+    /// with this method.
+    ///
+    /// The latest commit of each directory is copied as is, segment by segment,
+    /// including its deletions, and the segments are renamed to new segment
+    /// names of this index. The directories must not be written to while this
+    /// runs. Then the merge policy is asked whether some merges are needed.
+    ///
+    /// An `IllegalArgument` error is returned if a directory is this writer's
+    /// directory or appears twice, if a segment was written by an incompatible
+    /// version, if its index sort doesn't match the configured index sort, or
+    /// if the added documents would exceed `INDEX_MAX_DOCS`.
+    ///
+    /// @return The <a href="#sequence_number">sequence number</a>
+    /// for this operation
+    pub fn add_indexes(&self, dirs: &[Arc<D>]) -> Result<u64> {
+        IndexWriterInner::add_indexes(self, dirs)
+    }
+
+    /// Merges the live documents of `readers`, typically opened on other
+    /// indexes, into a single new segment of this index, e.g. to add only
+    /// part of another index by passing readers whose live docs hide the
+    /// other documents (see `SegmentReader::build_from`). Unlike
+    /// `add_indexes`, the documents are rewritten, so the readers may be
+    /// of another version or index sort.
+    ///
+    /// An `IllegalArgument` error is returned if the added documents would
+    /// exceed `INDEX_MAX_DOCS`. Nothing is added if the readers have no live
+    /// documents.
+    ///
+    /// @return The <a href="#sequence_number">sequence number</a>
+    /// for this operation
+    pub fn add_readers(&self, readers: &[Arc<SegmentReader<D, C>>]) -> Result<u64> {
+        IndexWriterInner::add_readers(self, readers)
+    }
+
     pub fn nrt_is_current(&self, infos: &SegmentInfos<D, C>) -> bool {
         self.writer.nrt_is_current(infos)
     }
@@ -583,6 +743,20 @@ where
         IndexWriterInner::force_merge(self, max_num_segments, do_wait)
     }
 
+    /// Forces merging of all segments that have deleted documents. The actual
+    /// merges to be executed are determined by the `MergePolicy`, e.g. the
+    /// `TieredMergePolicy` only merges the segments whose percentage of
+    /// deleted docs is over its `force_merge_deletes_pct_allowed`.
+    ///
+    /// This is often a horribly costly operation; rarely is it warranted.
+    ///
+    /// If `do_wait` is true, this call blocks until the merges complete, else
+    /// it returns immediately while the merges run in the background (when
+    /// the merge scheduler supports it).
+    pub fn force_merge_deletes(&self, do_wait: bool) -> Result<()> {
+        IndexWriterInner::force_merge_deletes(self, do_wait)
+    }
+
     /// Returns true if there may be changes that have not been
     /// committed.  There are cases where this may return true
     /// when there are no actual "real" changes to the index,
@@ -633,12 +807,78 @@ where
         IndexWriterInner::commit(self)
     }
 
+    /// Commits if one of the limits of `IndexWriterConfig::auto_commit_policy`
+    /// is reached, and returns the limit that triggered the commit.
+    ///
+    /// The writer already checks the policy after each update and delete,
+    /// call this periodically to also commit the pending changes of an idle
+    /// writer once `AutoCommitPolicy::max_commit_interval` elapsed.
+    pub fn maybe_auto_commit(&self) -> Result<Option<AutoCommitTrigger>> {
+        IndexWriterInner::maybe_auto_commit(self)
+    }
+
+    /// Expert: prepare for commit. This does the
+    /// first phase of 2-phase commit. This method does all
+    /// steps necessary to commit changes since this writer
+    /// was opened: flushes pending added and deleted docs,
+    /// syncs the index files, writes most of next segments_N
+    /// file. After calling this you must call either
+    /// `#commit` to finish the commit, or `#rollback` to revert
+    /// the commit and undo all changes done since the writer
+    /// was opened.
+    ///
+    /// You can also just call `#commit` directly
+    /// without prepareCommit first in which case that method
+    /// will internally call prepareCommit.
+    ///
+    /// @return The <a href="#sequence_number">sequence number</a>
+    /// of the last operation in the commit.  All sequence numbers <= this value
+    /// will be reflected in the commit, and all others will not.
+    pub fn prepare_commit(&self) -> Result<i64> {
+        IndexWriterInner::prepare_commit(self)
+    }
+
+    /// Sets the commit user data map, replacing any previous one.
+    /// The map is stored in the segments file by the next commit,
+    /// so it can be used to record e.g. the checkpoint of an
+    /// external system up to which the index is in sync, and read
+    /// back with `#live_commit_data` or from the `SegmentInfos` of
+    /// the commit.
+    ///
+    /// This counts as a change to the index: the next `#commit`
+    /// writes a new segments file even if no document was changed.
+    /// A call done after `#prepare_commit` is only recorded by the
+    /// following commit.
+    pub fn set_live_commit_data(&self, commit_user_data: HashMap<String, String>) -> Result<()> {
+        self.writer.set_live_commit_data(commit_user_data)
+    }
+
+    /// Returns the commit user data map that was last committed,
+    /// or the one set with `#set_live_commit_data`.
+    pub fn live_commit_data(&self) -> Result<HashMap<String, String>> {
+        let _l = self.writer.lock.lock()?;
+        Ok(self.writer.segment_infos.user_data.clone())
+    }
+
     /// Moves all in-memory segments to the `Directory`, but does not commit
     /// (fsync) them (call {@link #commit} for that).
     pub fn flush(&self) -> Result<()> {
         IndexWriterInner::flush(self, true, true)
     }
 
+    /// Expert: remove any index files that are no longer
+    /// used.
+    ///
+    /// IndexWriter normally deletes unused files itself,
+    /// during indexing.  However, the `IndexDeletionPolicy` is
+    /// only asked which commits to remove on commit, so call
+    /// this e.g. after releasing a snapshot of a commit with
+    /// `SnapshotDeletionPolicy::release` to remove the files
+    /// of the released commit right away.
+    pub fn delete_unused_files(&self) -> Result<()> {
+        self.writer.delete_unused_files()
+    }
+
     pub fn is_open(&self) -> bool {
         self.writer.is_open()
     }
@@ -650,6 +890,19 @@ where
     pub fn explicit_merge(&self) -> Result<()> {
         IndexWriterInner::maybe_merge(self, MergerTrigger::Explicit, None)
     }
+
+    /// Sets the maximum MB/sec all the merges of this writer may write
+    /// together, `f64::INFINITY` (the default) for no limit. This applies
+    /// to the running merges too, on top of the per-merge rate set by the
+    /// `MergeScheduler`.
+    pub fn set_merge_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        self.writer.merge_rate_limiter.set_mb_per_sec(mb_per_sec)
+    }
+
+    /// The maximum MB/sec all the merges of this writer may write together.
+    pub fn merge_mb_per_sec(&self) -> f64 {
+        self.writer.merge_rate_limiter.mb_per_sec()
+    }
 }
 
 impl<D, C, MS, MP> IndexWriter<D, C, MS, MP>
@@ -798,6 +1051,8 @@ pub struct IndexWriterInner<
     directory_orig: Arc<D>,
     // wrapped with additional checks
     directory: Arc<LockValidatingDirectoryWrapper<D>>,
+    // held until the writer is closed or rolled back
+    write_lock: Arc<dyn Lock>,
 
     lock: Arc<Mutex<()>>,
     closed: AtomicBool,
@@ -827,10 +1082,20 @@ pub struct IndexWriterInner<
     pending_commit: Option<SegmentInfos<D, C>>,
     pending_seq_no: AtomicI64,
     pending_commit_change_count: AtomicU64,
+    // sequence number of the last commit done by this writer, -1 if none
+    last_committed_seq_no: AtomicI64,
+    // uncommitted changes checked against the `AutoCommitPolicy`, all reset
+    // by each commit
+    opened_at: Instant,
+    last_commit_millis: AtomicU64,
+    committed_flushed_bytes: AtomicU64,
+    uncommitted_deletes: AtomicU64,
+    auto_committing: AtomicBool,
     files_to_commit: HashSet<String>,
 
-    rate_limiters: Arc<ThreadLocal<Arc<MergeRateLimiter>>>,
-    merge_directory: RateLimitFilterDirectory<LockValidatingDirectoryWrapper<D>, MergeRateLimiter>,
+    // the IO rate limit shared by all the merges, on top of the per-merge
+    // rate limiters controlled by the merge scheduler
+    merge_rate_limiter: Arc<SimpleRateLimiter>,
 
     segments_to_merge: HashMap<Arc<SegmentCommitInfo<D, C>>, bool>,
     merge_max_num_segments: u32,
@@ -850,6 +1115,13 @@ pub struct IndexWriterInner<
     flush_count: AtomicU32,
     flush_deletes_count: AtomicU32,
 
+    // cumulative counters only used for `IndexWriter::metrics`
+    flushed_segment_count: AtomicU64,
+    flushed_docs: AtomicU64,
+    flushed_bytes: AtomicU64,
+    merge_count: AtomicU64,
+    merged_bytes: AtomicU64,
+
     reader_pool: ReaderPool<D, C, MS, MP>,
     updates_stream_lock: Mutex<()>,
     buffered_updates_stream: BufferedUpdatesStream<C>,
@@ -908,14 +1180,16 @@ where
     ///           <code>OpenMode.APPEND</code> or if there is any other low-level
     ///           IO error
     fn new(d: Arc<D>, conf: Arc<IndexWriterConfig<C, MS, MP>>) -> Result<Self> {
-        let directory = Arc::new(LockValidatingDirectoryWrapper::new(Arc::clone(&d)));
-
-        let rate_limiters = Arc::new(ThreadLocal::default());
-
-        // Directory we use for merging, so we can abort running merges, and so
-        // merge schedulers can optionally rate-limit per-merge IO:
-        let merge_directory =
-            RateLimitFilterDirectory::new(Arc::clone(&directory), Arc::clone(&rate_limiters));
+        let write_lock: Arc<dyn Lock> = Arc::from(obtain_lock_with_timeout(
+            d.as_ref(),
+            WRITE_LOCK_NAME,
+            conf.write_lock_timeout,
+            DEFAULT_POLL_INTERVAL,
+        )?);
+        let directory = Arc::new(LockValidatingDirectoryWrapper::new(
+            Arc::clone(&d),
+            Arc::clone(&write_lock),
+        ));
 
         let buffered_updates_stream = BufferedUpdatesStream::default();
         let pool_readers = conf.reader_pooling;
@@ -973,6 +1247,11 @@ where
             // Do not use SegmentInfos.read(Directory) since the spooky
             // retrying it does is not necessary here (we hold the write lock):
             segment_infos = SegmentInfos::read_commit(&d, &last_segments_file)?;
+            if conf.verify_checksums_on_open {
+                for info in &segment_infos.segments {
+                    info.check_integrity()?;
+                }
+            }
             rollback_segments = segment_infos.create_backup_segment_infos();
         }
 
@@ -984,6 +1263,9 @@ where
         for info in &segment_infos.segments {
             let fis = read_field_infos(info.as_ref())?;
             for fi in fis.by_number.values() {
+                if let Some(schema) = conf.schema() {
+                    schema.record_field_info(fi)?;
+                }
                 global_field_numbers.add_or_get(
                     &fi.name,
                     fi.number,
@@ -991,6 +1273,14 @@ where
                     fi.point_dimension_count,
                     fi.point_num_bytes,
                 )?;
+                if fi.vector_dimension > 0 {
+                    global_field_numbers.set_vector_dimension(
+                        fi.number,
+                        &fi.name,
+                        fi.vector_dimension,
+                        fi.vector_similarity,
+                    )?;
+                }
             }
         }
 
@@ -1002,7 +1292,7 @@ where
         // Default deleter (for backwards compatibility) is
         // KeepOnlyLastCommitDeleter:
 
-        let mut deleter = IndexFileDeleter::new(directory.clone());
+        let mut deleter = IndexFileDeleter::new(directory.clone(), conf.index_deletion_policy());
         let starting_commit_deleted =
             deleter.init(d.clone(), &files, &mut segment_infos, initial_index_exists)?;
 
@@ -1022,13 +1312,19 @@ where
             cond: Condvar::new(),
             directory_orig: d,
             directory,
-            merge_directory,
+            merge_rate_limiter: Arc::new(SimpleRateLimiter::default()),
             change_count,
             last_commit_change_count: AtomicU64::new(0),
             rollback_segments,
             pending_commit: None,
             pending_seq_no: AtomicI64::new(0),
             pending_commit_change_count: AtomicU64::new(0),
+            last_committed_seq_no: AtomicI64::new(-1),
+            opened_at: Instant::now(),
+            last_commit_millis: AtomicU64::new(0),
+            committed_flushed_bytes: AtomicU64::new(0),
+            uncommitted_deletes: AtomicU64::new(0),
+            auto_committing: AtomicBool::new(false),
             files_to_commit: HashSet::new(),
             segment_infos,
             segment_infos_lock: Mutex::new(()),
@@ -1037,6 +1333,7 @@ where
             deleter,
             segments_to_merge: HashMap::new(),
             merge_max_num_segments: 0,
+            write_lock,
             closed: AtomicBool::new(false),
             closing: AtomicBool::new(false),
             merging_segments: HashSet::new(),
@@ -1049,6 +1346,11 @@ where
             stop_merges: false,
             flush_count: AtomicU32::new(0),
             flush_deletes_count: AtomicU32::new(0),
+            flushed_segment_count: AtomicU64::new(0),
+            flushed_docs: AtomicU64::new(0),
+            flushed_bytes: AtomicU64::new(0),
+            merge_count: AtomicU64::new(0),
+            merged_bytes: AtomicU64::new(0),
             reader_pool: ReaderPool::new(),
             updates_stream_lock: Mutex::new(()),
             buffered_updates_stream,
@@ -1057,7 +1359,6 @@ where
             pending_num_docs: Arc::new(pending_num_docs),
             full_flush_lock: Arc::new(Mutex::new(())),
             commit_lock: Mutex::new(()),
-            rate_limiters,
             tragedy: None,
         })
     }
@@ -1073,6 +1374,7 @@ where
         index_writer: &IndexWriter<D, C, MS, MP>,
         apply_all_deletes: bool,
         write_all_deletes: bool,
+        old_readers: &[Arc<SegmentReader<D, C>>],
     ) -> Result<StandardDirectoryReader<D, C, MS, MP>> {
         index_writer.writer.ensure_open(true)?;
 
@@ -1103,8 +1405,10 @@ where
             index_writer,
             apply_all_deletes,
             write_all_deletes,
+            old_readers,
             &mut any_changes,
         )?;
+        index_writer.writer.config.event_listener().on_flush_end();
 
         if any_changes {
             let _ = Self::maybe_merge(index_writer, MergerTrigger::Explicit, None);
@@ -1124,6 +1428,7 @@ where
         index_writer: &IndexWriter<D, C, MS, MP>,
         apply_all_deletes: bool,
         write_all_deletes: bool,
+        old_readers: &[Arc<SegmentReader<D, C>>],
         any_changes: &mut bool,
     ) -> Result<StandardDirectoryReader<D, C, MS, MP>> {
         let _l = index_writer.writer.full_flush_lock.lock()?;
@@ -1132,6 +1437,7 @@ where
             index_writer,
             apply_all_deletes,
             write_all_deletes,
+            old_readers,
             any_changes,
         );
         // Done: finish the full flush!
@@ -1153,6 +1459,7 @@ where
         index_writer: &IndexWriter<D, C, MS, MP>,
         apply_all_deletes: bool,
         write_all_deletes: bool,
+        old_readers: &[Arc<SegmentReader<D, C>>],
         any_changes: &mut bool,
     ) -> Result<StandardDirectoryReader<D, C, MS, MP>> {
         let (changes, _) = index_writer.writer.doc_writer.flush_all_threads()?;
@@ -1187,6 +1494,7 @@ where
                 &index_writer.writer.segment_infos,
                 apply_all_deletes,
                 write_all_deletes,
+                old_readers,
             )?;
             log::debug!(
                 "IW - return reader version: {}, reader: {:?} ",
@@ -1320,9 +1628,15 @@ where
         new_segment: FlushedSegment<D, C>,
         global_packet: Option<FrozenBufferedUpdates<C>>,
     ) -> Result<()> {
+        let segment_info = Arc::clone(&new_segment.segment_info);
         let res = self.do_publish_flushed_segment(new_segment, global_packet);
         self.flush_count.fetch_add(1, Ordering::AcqRel);
         self.do_after_flush();
+        if res.is_ok() {
+            self.config
+                .event_listener()
+                .on_segment_published(&SegmentEventInfo::new(segment_info.as_ref()));
+        }
         res
     }
 
@@ -1367,6 +1681,15 @@ where
         new_segment
             .segment_info
             .set_buffered_deletes_gen(next_gen as i64);
+        self.flushed_segment_count.fetch_add(1, Ordering::AcqRel);
+        self.flushed_docs.fetch_add(
+            new_segment.segment_info.info.max_doc() as u64,
+            Ordering::AcqRel,
+        );
+        self.flushed_bytes.fetch_add(
+            new_segment.segment_info.size_in_bytes() as u64,
+            Ordering::AcqRel,
+        );
         let writer = unsafe { self.writer_mut(&l) };
         writer
             .segment_infos
@@ -1448,6 +1771,9 @@ where
             }
             self.closed.store(true, Ordering::Release);
             self.closing.store(false, Ordering::Release);
+            if let Err(e) = self.write_lock.close() {
+                log::warn!("IW - failed to release the write lock: {:?}", e);
+            }
 
             // so any "concurrently closing" threads wake up and see that the close has now
             // completed:
@@ -1462,7 +1788,6 @@ where
             let l = lock.lock()?;
             let _l = self.abort_merges(l)?;
         }
-        self.rate_limiters = Arc::new(ThreadLocal::new());
         log::debug!("IW - rollback: done finish merges");
 
         // Must pre-close in case it increments changeCount so that we can then
@@ -1605,7 +1930,9 @@ where
         self.change_count.load(Ordering::Acquire)
     }
 
-    fn do_before_flush(&self) {}
+    fn do_before_flush(&self) {
+        self.config.event_listener().on_flush_start();
+    }
 
     ///
     // A hook for extending classes to execute operations after pending added and
@@ -1622,6 +1949,15 @@ where
         {
             return Err(AlreadyClosed("this IndexWriter is closed".into()));
         }
+        // a writer that hit a tragedy only accepts to be closed or rolled back
+        if fail_if_closing {
+            if let Some(ref tragedy) = self.tragedy {
+                return Err(AlreadyClosed(format!(
+                    "this IndexWriter hit an unrecoverable error: {:?}",
+                    tragedy
+                )));
+            }
+        }
         Ok(())
     }
 
@@ -1719,11 +2055,113 @@ where
             };
 
             writer.finish_commit(&l)?;
+            if seq_no >= 0 {
+                index_writer
+                    .writer
+                    .last_committed_seq_no
+                    .fetch_max(seq_no, Ordering::AcqRel);
+            }
+            index_writer.writer.reset_uncommitted_counters();
+        }
+
+        Ok(seq_no)
+    }
+
+    fn reset_uncommitted_counters(&self) {
+        self.last_commit_millis.store(
+            self.opened_at.elapsed().as_millis() as u64,
+            Ordering::Release,
+        );
+        self.committed_flushed_bytes.store(
+            self.flushed_bytes.load(Ordering::Acquire),
+            Ordering::Release,
+        );
+        self.uncommitted_deletes.store(0, Ordering::Release);
+    }
+
+    fn maybe_auto_commit(
+        index_writer: &IndexWriter<D, C, MS, MP>,
+    ) -> Result<Option<AutoCommitTrigger>> {
+        let writer = &index_writer.writer;
+        let policy = &writer.config.auto_commit_policy;
+        // never finish a commit prepared by the user
+        if !policy.is_enabled()
+            || writer.closing.load(Ordering::Acquire)
+            || writer.tragedy.is_some()
+            || writer.pending_commit.is_some()
+        {
+            return Ok(None);
+        }
+
+        let since_commit = writer.opened_at.elapsed()
+            - Duration::from_millis(writer.last_commit_millis.load(Ordering::Acquire));
+        let uncommitted_bytes = writer
+            .flushed_bytes
+            .load(Ordering::Acquire)
+            .saturating_sub(writer.committed_flushed_bytes.load(Ordering::Acquire))
+            + writer.doc_writer.ram_bytes_used() as u64;
+        let uncommitted_deletes = writer.uncommitted_deletes.load(Ordering::Acquire);
+        let trigger = match policy.trigger(since_commit, uncommitted_bytes, uncommitted_deletes) {
+            Some(trigger) if writer.has_uncommitted_changes() => trigger,
+            _ => return Ok(None),
+        };
+
+        // a single thread commits, the others keep on indexing
+        if writer
+            .auto_committing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Ok(None);
+        }
+        log::debug!("IW - auto commit triggered by {:?}", trigger);
+        let res = Self::commit(index_writer);
+        writer.auto_committing.store(false, Ordering::Release);
+
+        writer.config.event_listener.on_auto_commit(trigger, res?);
+        Ok(Some(trigger))
+    }
+
+    fn prepare_commit(index_writer: &IndexWriter<D, C, MS, MP>) -> Result<i64> {
+        index_writer.writer.ensure_open(true)?;
+
+        let mut do_maybe_merge = false;
+        let seq_no: i64;
+        {
+            let l = index_writer.writer.commit_lock.lock()?;
+            let writer = unsafe { index_writer.writer.writer_mut(&l) };
+
+            log::debug!("IW - prepare_commit: enter lock");
+
+            seq_no = writer.prepare_commit_internal(&mut do_maybe_merge, index_writer, &l)?;
+            index_writer
+                .writer
+                .pending_seq_no
+                .store(seq_no, Ordering::Release);
         }
 
+        // we must do this outside of the commit_lock else we can deadlock:
+        if do_maybe_merge {
+            Self::maybe_merge(index_writer, MergerTrigger::FullFlush, None)?;
+        }
         Ok(seq_no)
     }
 
+    fn delete_unused_files(&self) -> Result<()> {
+        self.ensure_open(false)?;
+        let l = self.lock.lock()?;
+        let writer = unsafe { self.writer_mut(&l) };
+        writer.deleter.revisit_policy()
+    }
+
+    fn set_live_commit_data(&self, commit_user_data: HashMap<String, String>) -> Result<()> {
+        let l = self.lock.lock()?;
+        let writer = unsafe { self.writer_mut(&l) };
+        writer.segment_infos.user_data = commit_user_data;
+        writer.changed(&l);
+        Ok(())
+    }
+
     fn prepare_commit_internal(
         &mut self,
         do_maybe_merge: &mut bool,
@@ -1782,6 +2220,8 @@ where
             }
         }
 
+        self.config.event_listener().on_flush_end();
+
         if any_segments_flushed {
             *do_maybe_merge = true;
         }
@@ -1837,9 +2277,6 @@ where
             self.segment_infos.changed();
         }
 
-        // TODO currently not support SegmentInfos#user_data
-        // if self.commit_user_data.is_some() {}
-
         // Must clone the segmentInfos while we still
         // hold fullFlushLock and while sync'd so that
         // no partial changes (eg a delete w/o
@@ -1939,7 +2376,11 @@ where
             // Exception here means nothing is prepared
             // (this method unwinds everything it did on
             // an exception)
-            to_sync.prepare_commit(self.directory.as_ref())?;
+            if self.config.commit_durability == CommitDurability::Fsync {
+                to_sync.prepare_commit(self.directory.as_ref())?;
+            } else {
+                to_sync.prepare_commit(&NoSyncDirectoryWrapper::new(self.directory.as_ref()))?;
+            }
 
             log::debug!(
                 "IW - start_commit: wrote pending segment file '{}' ",
@@ -1954,6 +2395,9 @@ where
             self.pending_commit = Some(to_sync);
         }
 
+        if self.config.commit_durability == CommitDurability::NoSync {
+            return Ok(());
+        }
         let files_to_sync: HashSet<String> = self.pending_commit.as_ref().unwrap().files(false);
         if let Err(e) = self.directory.sync(&files_to_sync) {
             *pending_commit_set = false;
@@ -1974,6 +2418,11 @@ where
 
         let res = self.try_finish_commit(&mut commit_completed);
 
+        if let Ok(Some((ref segments_file, generation))) = res {
+            self.config
+                .event_listener()
+                .on_commit(segments_file, generation);
+        }
         if let Err(e) = res {
             if commit_completed {
                 {
@@ -2006,7 +2455,9 @@ where
         Ok(())
     }
 
-    fn try_finish_commit(&mut self, commit_completed: &mut bool) -> Result<()> {
+    /// Returns the name and generation of the new commit point, `None` if
+    /// there was no pending commit.
+    fn try_finish_commit(&mut self, commit_completed: &mut bool) -> Result<Option<(String, i64)>> {
         let lock = Arc::clone(&self.lock);
         let _l = lock.lock()?;
         self.ensure_open(false)?;
@@ -2019,34 +2470,38 @@ where
         }
 
         if self.pending_commit.is_some() {
-            let mut res = self.do_finish_commit(commit_completed);
+            let res = self.do_finish_commit(commit_completed);
             self.cond.notify_all();
-            if res.is_ok() {
+            let res = match res {
                 // all is good
-                res = self.deleter.dec_ref_files(&self.files_to_commit);
-            } else {
-                // exc happened in finishCommit: not a tragedy
-                self.deleter.dec_ref_files_no_error(&self.files_to_commit);
-            }
+                Ok(commit) => self
+                    .deleter
+                    .dec_ref_files(&self.files_to_commit)
+                    .map(|_| commit),
+                Err(e) => {
+                    // exc happened in finishCommit: not a tragedy
+                    self.deleter.dec_ref_files_no_error(&self.files_to_commit);
+                    Err(e)
+                }
+            };
             self.pending_commit = None;
             self.files_to_commit.clear();
-            if res.is_err() {
-                return res;
-            }
+            res.map(Some)
         } else {
             log::debug!("IW - commit: pendingCommit is None; skip");
+            Ok(None)
         }
-        Ok(())
     }
 
-    fn do_finish_commit(&mut self, commit_completed: &mut bool) -> Result<()> {
+    fn do_finish_commit(&mut self, commit_completed: &mut bool) -> Result<(String, i64)> {
         log::debug!("IW - commit: pending_commit is not none");
 
-        let committed_segments_file = self
-            .pending_commit
-            .as_mut()
-            .unwrap()
-            .finish_commit(self.directory.as_ref())?;
+        let pending_commit = self.pending_commit.as_mut().unwrap();
+        let committed_segments_file = if self.config.commit_durability == CommitDurability::Fsync {
+            pending_commit.finish_commit(self.directory.as_ref())?
+        } else {
+            pending_commit.finish_commit(&NoSyncDirectoryWrapper::new(self.directory.as_ref()))?
+        };
 
         // we committed, if anything goes wrong after this, we are
         // screwed and it's a tragedy:
@@ -2076,7 +2531,7 @@ where
             .as_ref()
             .unwrap()
             .create_backup_segment_infos();
-        Ok(())
+        Ok((committed_segments_file, gen))
     }
 
     // called only from assert
@@ -2159,6 +2614,7 @@ where
             any_changes |= index_writer.writer.maybe_apply_deletes(apply_deletes, &l)?;
             index_writer.writer.do_after_flush();
         }
+        index_writer.writer.config.event_listener().on_flush_end();
         Ok(any_changes)
     }
 
@@ -2230,7 +2686,17 @@ where
         term: Option<Term>,
     ) -> Result<u64> {
         index_writer.writer.ensure_open(true)?;
+        if docs.len() > 1 {
+            if let Some(index_sort) = index_writer.writer.config.index_sort() {
+                return Err(IllegalArgument(format!(
+                    "can't add a block of documents to an index sorted by {:?}, the sort would \
+                     split the block",
+                    index_sort
+                )));
+            }
+        }
 
+        let is_update = term.is_some();
         let (seq_no, changed) = index_writer
             .writer
             .doc_writer
@@ -2238,6 +2704,13 @@ where
         if changed {
             Self::process_events(index_writer, true, false)?;
         }
+        if is_update {
+            index_writer
+                .writer
+                .uncommitted_deletes
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        Self::maybe_auto_commit(index_writer)?;
 
         Ok(seq_no)
     }
@@ -2248,10 +2721,16 @@ where
     ) -> Result<u64> {
         index_writer.writer.ensure_open(true)?;
 
+        let num_terms = terms.len() as u64;
         let (seq_no, changed) = index_writer.writer.doc_writer.delete_terms(terms);
         if changed {
             Self::process_events(index_writer, true, false)?;
         }
+        index_writer
+            .writer
+            .uncommitted_deletes
+            .fetch_add(num_terms, Ordering::AcqRel);
+        Self::maybe_auto_commit(index_writer)?;
         Ok(seq_no)
     }
 
@@ -2278,10 +2757,16 @@ where
             }
         }
 
+        let num_queries = queries.len() as u64;
         let (seq_no, changed) = index_writer.writer.doc_writer.delete_queries(queries);
         if changed {
             Self::process_events(index_writer, true, false)?;
         }
+        index_writer
+            .writer
+            .uncommitted_deletes
+            .fetch_add(num_queries, Ordering::AcqRel);
+        Self::maybe_auto_commit(index_writer)?;
         Ok(seq_no)
     }
 
@@ -2291,10 +2776,18 @@ where
         term: Option<Term>,
     ) -> Result<u64> {
         index_writer.writer.ensure_open(true)?;
+        let is_update = term.is_some();
         let (seq_no, changed) = index_writer.writer.doc_writer.update_document(doc, term)?;
         if changed {
             Self::process_events(index_writer, true, false)?;
         }
+        if is_update {
+            index_writer
+                .writer
+                .uncommitted_deletes
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        Self::maybe_auto_commit(index_writer)?;
 
         Ok(seq_no)
     }
@@ -2355,6 +2848,7 @@ where
         if changed {
             Self::process_events(index_writer, true, false)?;
         }
+        Self::maybe_auto_commit(index_writer)?;
         Ok(seq)
     }
 
@@ -2439,6 +2933,368 @@ where
     /// Forces merge policy to merge segments until there are
     /// max_num_segments. The actual merges to be
     /// executed are determined by the `MergePolicy`.
+    fn force_merge_deletes(index_writer: &IndexWriter<D, C, MS, MP>, do_wait: bool) -> Result<()> {
+        index_writer.writer.ensure_open(true)?;
+
+        Self::flush(index_writer, true, true)?;
+
+        let merge_ids: Vec<u32> = {
+            let l = index_writer.writer.lock.lock()?;
+            let writer_mut = unsafe { index_writer.writer.writer_mut(&l) };
+            let spec = index_writer
+                .writer
+                .config
+                .merge_policy()
+                .find_forced_deletes_mergers(&index_writer.writer.segment_infos, index_writer)?;
+            let mut merge_ids = vec![];
+            if let Some(spec) = spec {
+                for merge in spec.merges {
+                    merge_ids.push(merge.id);
+                    writer_mut.register_merge(merge, &l)?;
+                }
+            }
+            merge_ids
+        };
+
+        index_writer.writer.merge_scheduler.merge(
+            index_writer,
+            MergerTrigger::Explicit,
+            !merge_ids.is_empty(),
+        )?;
+
+        if do_wait && !merge_ids.is_empty() {
+            let mut l = index_writer.writer.lock.lock()?;
+            loop {
+                if let Some(ref tragedy) = index_writer.writer.tragedy {
+                    return Err(IllegalState(format!(
+                        "this writer hit an unrecoverable error; cannot complete \
+                         force_merge_deletes: {:?}",
+                        tragedy
+                    )));
+                }
+
+                // Check each merge that the merge policy asked us to do, to
+                // see if any of them are still pending or running:
+                let running = merge_ids.iter().any(|id| {
+                    index_writer.writer.running_merges.contains_key(id)
+                        || index_writer
+                            .writer
+                            .pending_merges
+                            .iter()
+                            .any(|m| m.id == *id)
+                });
+                if running {
+                    let (guard, _) = index_writer
+                        .writer
+                        .cond
+                        .wait_timeout(l, Duration::from_millis(1000))?;
+                    l = guard;
+                } else {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_indexes(index_writer: &IndexWriter<D, C, MS, MP>, dirs: &[Arc<D>]) -> Result<u64> {
+        index_writer.writer.ensure_open(true)?;
+        for (i, dir) in dirs.iter().enumerate() {
+            if ptr::eq(dir.as_ref(), index_writer.writer.directory_orig.as_ref()) {
+                return Err(IllegalArgument("cannot add directory to itself".into()));
+            }
+            if dirs[..i].iter().any(|d| Arc::ptr_eq(d, dir)) {
+                return Err(IllegalArgument(format!(
+                    "directory {} appears more than once",
+                    dir
+                )));
+            }
+        }
+
+        Self::flush(index_writer, false, true)?;
+
+        let mut commits = Vec::with_capacity(dirs.len());
+        let mut total_max_doc = 0i64;
+        for dir in dirs {
+            let sis = SegmentInfos::<D, C>::read_latest_commit(dir)?;
+            for info in &sis.segments {
+                index_writer.writer.validate_added_segment(info.as_ref())?;
+            }
+            total_max_doc += sis.total_max_doc() as i64;
+            commits.push(sis);
+        }
+        index_writer.writer.test_reserve_docs(total_max_doc)?;
+
+        let mut infos = Vec::new();
+        let mut copied_files = HashSet::new();
+        let res: Result<()> = (|| {
+            for sis in &commits {
+                for info in &sis.segments {
+                    let fis = read_field_infos(info.as_ref())?;
+                    for fi in fis.by_number.values() {
+                        index_writer.writer.global_field_numbers.add_or_get(
+                            &fi.name,
+                            fi.number,
+                            fi.doc_values_type,
+                            fi.point_dimension_count,
+                            fi.point_num_bytes,
+                        )?;
+                        if fi.vector_dimension > 0 {
+                            index_writer
+                                .writer
+                                .global_field_numbers
+                                .set_vector_dimension(
+                                    fi.number,
+                                    &fi.name,
+                                    fi.vector_dimension,
+                                    fi.vector_similarity,
+                                )?;
+                        }
+                    }
+                    let seg_name = index_writer.writer.new_segment_name();
+                    let context = IOContext::Flush(FlushInfo::new(info.info.max_doc as u32));
+                    infos.push(index_writer.writer.copy_segment_as_is(
+                        info.as_ref(),
+                        &seg_name,
+                        &context,
+                        &mut copied_files,
+                    )?);
+                }
+            }
+            Ok(())
+        })();
+        if let Err(e) = res {
+            index_writer.delete_new_files(&copied_files)?;
+            return Err(e);
+        }
+
+        let seq_no = {
+            let l = index_writer.writer.lock.lock()?;
+            if let Err(e) = index_writer.writer.ensure_open(true) {
+                index_writer.writer.delete_new_files(&copied_files)?;
+                return Err(e);
+            }
+            index_writer
+                .writer
+                .pending_num_docs
+                .fetch_add(total_max_doc, Ordering::AcqRel);
+            let seq_no = index_writer
+                .writer
+                .doc_writer
+                .delete_queue
+                .next_sequence_number();
+            let writer = unsafe { index_writer.writer.writer_mut(&l) };
+            for info in infos {
+                writer.segment_infos.add(Arc::new(info));
+            }
+            writer.check_point(&l)?;
+            seq_no
+        };
+
+        Self::maybe_merge(index_writer, MergerTrigger::Explicit, None)?;
+        Ok(seq_no)
+    }
+
+    fn add_readers(
+        index_writer: &IndexWriter<D, C, MS, MP>,
+        readers: &[Arc<SegmentReader<D, C>>],
+    ) -> Result<u64> {
+        index_writer.writer.ensure_open(true)?;
+
+        Self::flush(index_writer, false, true)?;
+
+        let num_docs: i64 = readers.iter().map(|r| r.num_docs() as i64).sum();
+        index_writer.writer.test_reserve_docs(num_docs)?;
+
+        let seg_name = index_writer.writer.new_segment_name();
+        let mut si = SegmentInfo::new(
+            VERSION_LATEST,
+            &seg_name,
+            -1,
+            Arc::clone(&index_writer.writer.directory_orig),
+            false,
+            Some(Arc::clone(&index_writer.writer.config.codec)),
+            HashMap::new(),
+            random_id(),
+            HashMap::new(),
+            index_writer.writer.config.index_sort().cloned(),
+        )?;
+        let mut diagnostics = HashMap::new();
+        diagnostics.insert("source".into(), "add_readers".into());
+        si.set_diagnostics(diagnostics);
+
+        let context = IOContext::Merge(MergeInfo::new(num_docs as u32, 0, false, None));
+        let dir_wrapper = Arc::new(TrackingDirectoryWrapper::new(Arc::clone(
+            &index_writer.writer.directory,
+        )));
+        {
+            let mut merger = SegmentMerger::new(
+                readers.to_vec(),
+                &si,
+                Arc::clone(&dir_wrapper),
+                FieldNumbersRef::new(Arc::clone(&index_writer.writer.global_field_numbers)),
+                context,
+            )?;
+            if !merger.should_merge() {
+                return Ok(index_writer
+                    .writer
+                    .doc_writer
+                    .delete_queue
+                    .next_sequence_number());
+            }
+            if let Err(e) = merger.merge() {
+                index_writer
+                    .writer
+                    .delete_new_files(&dir_wrapper.create_files())?;
+                return Err(e);
+            }
+        }
+        si.set_files(&dir_wrapper.create_files())?;
+        let mut info = SegmentCommitInfo::new(si, 0, -1, -1, -1, HashMap::new(), HashSet::new());
+
+        let use_compound_file = {
+            let _l = index_writer.writer.lock.lock()?;
+            index_writer.writer.config.merge_policy().use_compound_file(
+                &index_writer.writer.segment_infos,
+                &info,
+                index_writer,
+            )
+        };
+        if use_compound_file {
+            let files_to_remove = info.files();
+            let tracking_cfs_dir =
+                TrackingDirectoryWrapper::new(index_writer.writer.directory.as_ref());
+            if let Err(e) = index_writer.writer.create_compound_file(
+                &tracking_cfs_dir,
+                &mut info.info,
+                &context,
+            ) {
+                index_writer.writer.delete_new_files(&files_to_remove)?;
+                return Err(e);
+            }
+            // delete new non cfs files directly: they were never
+            // registered with IFD
+            index_writer.writer.delete_new_files(&files_to_remove)?;
+            info.info.set_use_compound_file();
+        }
+
+        // Have codec write SegmentInfo.  Must do this after
+        // creating CFS so that 1) .si isn't slurped into CFS,
+        // and 2) .si reflects useCompoundFile=true change
+        // above:
+        if let Err(e) = index_writer
+            .writer
+            .config
+            .codec()
+            .segment_info_format()
+            .write(&index_writer.writer.directory, &mut info.info, &context)
+        {
+            index_writer.writer.delete_new_files(&info.files())?;
+            return Err(e);
+        }
+
+        let l = index_writer.writer.lock.lock()?;
+        if let Err(e) = index_writer.writer.ensure_open(true) {
+            index_writer.writer.delete_new_files(&info.files())?;
+            return Err(e);
+        }
+        index_writer
+            .writer
+            .pending_num_docs
+            .fetch_add(num_docs, Ordering::AcqRel);
+        let seq_no = index_writer
+            .writer
+            .doc_writer
+            .delete_queue
+            .next_sequence_number();
+        let writer = unsafe { index_writer.writer.writer_mut(&l) };
+        writer.segment_infos.add(Arc::new(info));
+        writer.check_point(&l)?;
+        Ok(seq_no)
+    }
+
+    fn validate_added_segment(&self, info: &SegmentCommitInfo<D, C>) -> Result<()> {
+        if info.info.version.major != VERSION_LATEST.major {
+            return Err(IllegalArgument(format!(
+                "cannot add segment {} written by version {:?}, expected major version {}",
+                info.info.name, info.info.version, VERSION_LATEST.major
+            )));
+        }
+        if let Some(index_sort) = self.config.index_sort() {
+            if info.info.index_sort() != Some(index_sort) {
+                return Err(IllegalArgument(format!(
+                    "cannot change index sort from {:?} to {:?}",
+                    info.info.index_sort(),
+                    index_sort
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn test_reserve_docs(&self, added_num_docs: i64) -> Result<()> {
+        if self.pending_num_docs.load(Ordering::Acquire) + added_num_docs > INDEX_MAX_DOCS as i64 {
+            return Err(IllegalArgument(format!(
+                "number of documents in the index cannot exceed {}",
+                INDEX_MAX_DOCS
+            )));
+        }
+        Ok(())
+    }
+
+    // Copies the files of a segment of another index to this index under the
+    // new segment name `seg_name`, keeping its deletions and doc values updates.
+    fn copy_segment_as_is(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        seg_name: &str,
+        context: &IOContext,
+        copied_files: &mut HashSet<String>,
+    ) -> Result<SegmentCommitInfo<D, C>> {
+        let rename = |file: &str| format!("{}{}", seg_name, strip_segment_name(file));
+
+        let mut new_info = SegmentInfo::new(
+            info.info.version,
+            seg_name,
+            info.info.max_doc,
+            Arc::clone(&self.directory_orig),
+            info.info.is_compound_file(),
+            info.info.codec.clone(),
+            info.info.diagnostics.clone(),
+            info.info.id,
+            info.info.attributes.clone(),
+            info.info.index_sort.clone(),
+        )?;
+        new_info.set_files(info.info.files())?;
+        let dv_updates_files = info
+            .get_doc_values_updates_files()
+            .iter()
+            .map(|(field, files)| (*field, files.iter().map(|f| rename(f)).collect()))
+            .collect();
+        let field_infos_files = info.field_infos_files.iter().map(|f| rename(f)).collect();
+        let new_info = SegmentCommitInfo::new(
+            new_info,
+            info.del_count(),
+            info.del_gen(),
+            info.field_infos_gen(),
+            info.doc_values_gen(),
+            dv_updates_files,
+            field_infos_files,
+        );
+
+        for file in info.files() {
+            let new_file_name = rename(&file);
+            self.directory.copy_from(
+                info.info.directory.as_ref(),
+                &file,
+                &new_file_name,
+                context,
+            )?;
+            copied_files.insert(new_file_name);
+        }
+        Ok(new_info)
+    }
+
     fn force_merge(
         index_writer: &IndexWriter<D, C, MS, MP>,
         max_num_segments: u32,
@@ -2772,24 +3628,47 @@ where
         index_writer: &IndexWriter<D, C, MS, MP>,
         merge: &mut OneMerge<D, C>,
     ) -> Result<()> {
-        index_writer
-            .writer
-            .rate_limiters
-            .get_or(|| Box::new(Arc::clone(&merge.rate_limiter)));
-
         // let t0 = SystemTime::now();
 
         index_writer.writer.merge_init(merge)?;
 
+        let listener = index_writer.writer.config.event_listener();
+        let segments: Vec<_> = merge
+            .segments
+            .iter()
+            .map(|s| SegmentEventInfo::new(s.as_ref()))
+            .collect();
+        listener.on_merge_start(merge.id, &segments);
+
         log::trace!("IW - now merge");
 
-        Self::merge_middle(index_writer, merge)?;
+        let res = Self::merge_middle(index_writer, merge);
+        let merged = match (&res, &merge.info) {
+            (Ok(_), Some(info)) if info.info.max_doc() > 0 => {
+                Some(SegmentEventInfo::new(info.as_ref()))
+            }
+            _ => None,
+        };
+        listener.on_merge_end(merge.id, merged.as_ref());
+        res?;
         // self.merge_success();
         Ok(())
     }
 
     /// Does initial setup for a merge, which is fast but holds
     /// the synchronized lock on IndexWriter instance
+    /// Directory the merge writes to, so we can abort the merge, and so the
+    /// merge scheduler and `set_merge_mb_per_sec` can rate-limit its IO.
+    fn merge_directory(
+        &self,
+        merge: &OneMerge<D, C>,
+    ) -> RateLimitFilterDirectory<LockValidatingDirectoryWrapper<D>, MergeOutputRateLimiter> {
+        let merge_rate_limiter: Arc<dyn RateLimiter> = self.merge_rate_limiter.clone();
+        let rate_limiter =
+            MergeOutputRateLimiter::new(Arc::clone(&merge.rate_limiter), merge_rate_limiter);
+        RateLimitFilterDirectory::new(Arc::clone(&self.directory), Arc::new(rate_limiter))
+    }
+
     fn merge_init(&self, merge: &mut OneMerge<D, C>) -> Result<()> {
         let lock = Arc::clone(&self.lock);
         let l = lock.lock().unwrap();
@@ -2888,6 +3767,32 @@ where
         Ok(())
     }
 
+    /// Marks the soft deleted docs of `reader` the merge policy doesn't want to
+    /// keep as deleted, so that the merge drops them.
+    fn reclaim_soft_deletes(
+        index_writer: &IndexWriter<D, C, MS, MP>,
+        rld: &ReadersAndUpdates<D, C, MS, MP>,
+        reader: &SegmentReader<D, C>,
+        soft_deletes_field: &str,
+    ) -> Result<()> {
+        if let Some(soft_deleted) = soft_deleted_docs(reader, soft_deletes_field)? {
+            let reclaimable = index_writer
+                .writer
+                .config
+                .merge_policy()
+                .reclaimable_soft_deletes(reader, soft_deleted)?;
+            if reclaimable.cardinality() > 0 {
+                rld.init_writable_live_docs()?;
+                for doc in 0..reclaimable.len() {
+                    if reclaimable.get(doc) {
+                        rld.delete(doc as DocId)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Does the actual (time-consuming) work of the merge, but without holding
     /// synchronized lock on IndexWriter instance.
     fn merge_middle(
@@ -2913,8 +3818,9 @@ where
 
         let context = IOContext::Merge(merge.store_merge_info());
 
+        let merge_directory = index_writer.writer.merge_directory(merge);
         let dir_wrapper = Arc::new(TrackingDirectoryWrapper::new(DerefWrapper(
-            index_writer.writer.merge_directory.clone(),
+            merge_directory.clone(),
         )));
         merge.readers = Vec::with_capacity(merge.segments.len());
 
@@ -2943,6 +3849,12 @@ where
                     }
                 }
 
+                if let Some(field) = index_writer.writer.config.soft_deletes_field() {
+                    // Hard delete the soft deleted docs the merge policy
+                    // doesn't retain, so that the merge drops them:
+                    Self::reclaim_soft_deletes(index_writer, &rld, reader.as_ref(), field)?;
+                }
+
                 live_docs = rld.readonly_live_docs();
                 del_count =
                     rld.pending_delete_count() as i32 + merge.segments[seg_upto].del_count();
@@ -3024,8 +3936,7 @@ where
             )
         };
         if use_compound_file {
-            let tracking_cfs_dir =
-                TrackingDirectoryWrapper::new(&index_writer.writer.merge_directory);
+            let tracking_cfs_dir = TrackingDirectoryWrapper::new(&merge_directory);
 
             let info = merge.info.as_mut().unwrap();
             let segment_info = Arc::get_mut(info).unwrap();
@@ -3095,6 +4006,26 @@ where
             }
         }
 
+        if let Some(warmer) = index_writer.writer.config.merged_segment_warmer() {
+            if index_writer.writer.pool_readers.load(Ordering::Acquire) {
+                let rld = index_writer
+                    .writer
+                    .reader_pool
+                    .get_or_create(merge.info.as_ref().unwrap())?;
+                let res = rld.get_reader(&IOContext::READ).and_then(|reader| {
+                    let reader: Arc<SearchLeafReader<C>> = reader;
+                    warmer(reader.as_ref())
+                });
+                index_writer.writer.reader_pool.release(&rld)?;
+                if let Err(e) = res {
+                    let info = merge.info.as_ref().unwrap();
+                    index_writer.writer.reader_pool.drop(info.as_ref())?;
+                    index_writer.writer.delete_new_files(&info.files())?;
+                    return Err(e);
+                }
+            }
+        }
+
         if !index_writer
             .writer
             .commit_merge(merge, &merger.merge_state)?
@@ -3556,6 +4487,13 @@ where
         writer_mut
             .segment_infos
             .apply_merge_changes(merge, drop_segment);
+        self.merge_count.fetch_add(1, Ordering::AcqRel);
+        if !drop_segment {
+            self.merged_bytes.fetch_add(
+                merge.info.as_ref().unwrap().size_in_bytes() as u64,
+                Ordering::AcqRel,
+            );
+        }
 
         // Now deduct the deleted docs that we just reclaimed from this merge:
         let del_doc_count = merge.total_max_doc as i32 - merge.info.as_ref().unwrap().info.max_doc;
@@ -3641,25 +4579,32 @@ where
         Ok(())
     }
 
+    /// Records an unrecoverable error hit in `location`, e.g. a failed flush
+    /// which lost the buffered documents. Gives `tragedy` back if a tragedy
+    /// was already recorded.
+    ///
+    /// From then on all the mutations fail, the writer may only be closed or
+    /// rolled back, which leaves the index as of its last commit.
+    pub fn on_tragic_event(&self, tragedy: Error, location: &str) -> Result<()> {
+        log::error!("IW - hit tragic '{:?}' inside {}", tragedy, location);
+
+        let l = self.lock.lock()?;
+        // It's possible you could have a really bad day
+        if self.tragedy.is_some() {
+            return Err(tragedy);
+        }
+        let writer = unsafe { self.writer_mut(&l) };
+        writer.tragedy = Some(tragedy);
+        Ok(())
+    }
+
     fn tragic_event(
         &self,
         tragedy: Error,
         location: &str,
         commit_lock: Option<&MutexGuard<()>>,
     ) -> Result<()> {
-        log::trace!("IW - hit tragic '{:?}' inside {}", &tragedy, location);
-
-        {
-            let l = self.lock.lock()?;
-
-            // It's possible you could have a really bad day
-            if self.tragedy.is_some() {
-                return Err(tragedy);
-            }
-
-            let writer = unsafe { self.writer_mut(&l) };
-            writer.tragedy = Some(tragedy);
-        }
+        self.on_tragic_event(tragedy, location)?;
 
         // if we are already closed (e.g. called by rollback), this will be a no-op.
         if self.should_close(false) {
@@ -4067,6 +5012,11 @@ where
         guard.get_readonly_clone(&self.info, context)
     }
 
+    pub fn is_current_readonly_clone(&self, reader: &SegmentReader<D, C>) -> bool {
+        let guard = self.inner.lock().unwrap();
+        guard.is_current_readonly_clone(&self.info, reader)
+    }
+
     pub fn test_doc_id(&self, doc_id: usize) -> Result<bool> {
         let guard = self.inner.lock().unwrap();
         debug_assert!(guard.live_docs.is_some());
@@ -4105,6 +5055,13 @@ where
 
     /// Returns a reader for merge. this method applies filed update if there are
     /// any and marks that this segment is currently merging.
+    /// Returns the pooled reader of the segment, opening it if needed.
+    pub fn get_reader(&self, context: &IOContext) -> Result<Arc<SegmentReader<D, C>>> {
+        self.create_reader_if_not_exist(context)?;
+        let guard = self.inner.lock()?;
+        Ok(Arc::clone(guard.reader()))
+    }
+
     pub fn reader_for_merge(&self, context: &IOContext) -> Result<Arc<SegmentReader<D, C>>> {
         // must execute these two statements as atomic operation, otherwise we
         // could lose updates if e.g. another thread calls writeFieldUpdates in
@@ -4268,6 +5225,7 @@ where
             Arc::get_mut(live_docs)
                 .unwrap()
                 .as_bit_set_mut()
+                .ok_or_else(|| IllegalState("live docs must be a FixedBitSet".into()))?
                 .clear(doc_id as usize);
             self.pending_delete_count += 1;
         }
@@ -4280,6 +5238,15 @@ where
         &mut self,
         info: &Arc<SegmentCommitInfo<D, C>>,
         context: &IOContext,
+    ) -> Result<SegmentReader<D, C>> {
+        self.readonly_clone(info, context, true)
+    }
+
+    fn readonly_clone(
+        &mut self,
+        info: &Arc<SegmentCommitInfo<D, C>>,
+        context: &IOContext,
+        share_field_infos: bool,
     ) -> Result<SegmentReader<D, C>> {
         if self.reader.is_none() {
             self.create_reader_if_not_exist(info, context)?;
@@ -4298,12 +5265,44 @@ where
             Arc::clone(live_docs)
         };
         let reader = self.reader.as_ref().unwrap();
-        SegmentReader::build(
-            Arc::clone(info),
-            live_docs,
-            info.info.max_doc - info.del_count() - self.pending_delete_count as i32,
-            Arc::clone(&reader.core),
-        )
+        let num_docs = info.info.max_doc - info.del_count() - self.pending_delete_count as i32;
+        if share_field_infos {
+            // the pooled reader is reopened whenever the field infos change,
+            // so the clone can share its doc values with it as well
+            Ok(reader.with_live_docs(Arc::clone(info), live_docs, num_docs, true))
+        } else {
+            SegmentReader::build(
+                Arc::clone(info),
+                live_docs,
+                num_docs,
+                Arc::clone(&reader.core),
+            )
+        }
+    }
+
+    /// Returns true if `reader`, a clone previously returned by
+    /// `get_readonly_clone`, still reflects all the deletes and doc values
+    /// updates of this segment, so it doesn't need to be cloned again.
+    ///
+    /// The live docs and the pooled reader are never modified once shared:
+    /// any new delete or field update replaces them, so comparing their
+    /// identity is enough.
+    pub fn is_current_readonly_clone(
+        &self,
+        info: &SegmentCommitInfo<D, C>,
+        reader: &SegmentReader<D, C>,
+    ) -> bool {
+        let pooled = match self.reader {
+            Some(ref pooled) => pooled,
+            None => return false,
+        };
+        let live_docs = self.live_docs.as_ref().unwrap_or(&pooled.live_docs);
+        reader.is_nrt
+            && Arc::ptr_eq(&reader.core, &pooled.core)
+            && Arc::ptr_eq(&reader.field_infos, &pooled.field_infos)
+            && Arc::ptr_eq(&reader.live_docs, live_docs)
+            && reader.num_docs()
+                == info.info.max_doc - info.del_count() - self.pending_delete_count as i32
     }
 
     pub fn init_writable_live_docs(&mut self, info: &Arc<SegmentCommitInfo<D, C>>) -> Result<()> {
@@ -4509,8 +5508,13 @@ where
                 };
             }
 
-            // reopen segment reader for updates
-            me.reader = Some(Arc::new(me.get_readonly_clone(info, &IOContext::Default)?));
+            // reopen segment reader for updates, the field infos must be
+            // re-read for the new generation
+            me.reader = Some(Arc::new(me.readonly_clone(
+                info,
+                &IOContext::Default,
+                false,
+            )?));
         }
 
         me.pending_dv_updates.clear();
@@ -11,14 +11,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::codec::{Codec, CodecEnum, Lucene62Codec};
+use crate::core::codec::stored_fields::StoredFieldCompressMode;
+use crate::core::codec::{Codec, CodecEnum, Lucene62Codec, Sorter};
 use crate::core::index::merge::MergeScheduler;
 use crate::core::index::merge::SerialMergeScheduler;
 use crate::core::index::merge::{MergePolicy, TieredMergePolicy};
-use crate::core::index::writer::KeepOnlyLastCommitDeletionPolicy;
-use crate::core::search::sort_field::Sort;
+use crate::core::index::reader::SearchLeafReader;
+use crate::core::index::writer::{
+    AutoCommitPolicy, IndexDeletionPolicy, IndexWriterEventListener,
+    KeepOnlyLastCommitDeletionPolicy, NoOpIndexWriterEventListener, Schema,
+};
+use crate::core::search::sort_field::{Sort, SortFieldType};
+use crate::core::util::VariantValue;
+
+use crate::error::{Error::IllegalArgument, Result};
 
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Denotes a flush trigger is disabled.
 pub const DISABLE_AUTO_FLUSH: i32 = -1;
@@ -29,6 +38,14 @@ pub const DEFAULT_MAX_BUFFERED_DELETE_TERMS: i32 = DISABLE_AUTO_FLUSH;
 /// Disabled by default (because IndexWriter flushes by RAM usage by default).
 pub const DEFAULT_MAX_BUFFERED_DOCS: i32 = DISABLE_AUTO_FLUSH;
 
+/// Default value of the per-thread hard limit of the RAM buffer in MB: a
+/// `DocumentsWriterPerThread` can't address more than 2048 MB.
+pub const DEFAULT_RAM_PER_THREAD_HARD_LIMIT_MB: u32 = 1945;
+
+/// Default number of threads flushing the in-memory segments in parallel on a
+/// full flush, e.g. on `IndexWriter::commit`.
+pub const DEFAULT_MAX_CONCURRENT_FLUSHES: u32 = 4;
+
 /// Default setting for `seg_reader_pooling`
 pub const DEFAULT_READER_POOLING: bool = false;
 
@@ -37,6 +54,10 @@ pub const DEFAULT_READER_POOLING: bool = false;
 /// ram buffers use <code>false</code>
 pub const DEFAULT_USE_COMPOUND_FILE_SYSTEM: bool = true;
 
+/// Warms the reader of a newly merged segment, see
+/// `IndexWriterConfig::set_merged_segment_warmer`.
+pub type MergedSegmentWarmer<C> = Arc<dyn Fn(&SearchLeafReader<C>) -> Result<()> + Send + Sync>;
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum OpenMode {
     Create,
@@ -44,6 +65,17 @@ pub enum OpenMode {
     CreateOrAppend,
 }
 
+/// Whether the commits of an `IndexWriter` are fsynced.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CommitDurability {
+    /// Commits are fsynced, so they survive a crash of the OS.
+    Fsync,
+    /// Commits only reach the OS page cache, e.g. for bulk loads which
+    /// start over after a crash. The files of the last commit can be synced
+    /// later with `Directory::sync` and `Directory::sync_metadata`.
+    NoSync,
+}
+
 /// Holds all the configuration that is used to create an {@link IndexWriter}.
 /// Once {@link IndexWriter} has been created with this object, changes to this
 /// object will not affect the {@link IndexWriter} instance. For that, use
@@ -59,9 +91,23 @@ pub enum OpenMode {
 ///
 /// @see IndexWriter#getConfig()
 pub struct IndexWriterConfig<C: Codec, MS: MergeScheduler, MP: MergePolicy> {
+    /// Packs the files of each flushed segment into a compound file, which
+    /// needs fewer file descriptors at the cost of copying the files once.
+    /// Flushed segments larger than the `max_cfs_segment_size` of the merge
+    /// policy never use it. Whether merged segments use it is decided by the
+    /// merge policy, see `set_no_cfs_ratio`. Disabled by default.
     pub use_compound_file: bool,
     pub max_buffered_delete_terms: Option<u32>,
     pub max_buffered_docs: Option<u32>,
+    /// Flushes the largest in-memory segment once the buffered docs of all
+    /// the indexing threads use this much RAM, disabled by default.
+    pub ram_buffer_size_mb: Option<f64>,
+    /// Flushes an in-memory segment once it uses this much RAM, whatever the
+    /// other flush triggers.
+    pub ram_per_thread_hard_limit_mb: u32,
+    /// Maximum number of in-memory segments flushed in parallel by a full
+    /// flush, the calling thread included.
+    pub max_concurrent_flushes: u32,
     pub merge_policy: MP,
     pub merge_scheduler: MS,
     pub index_sort: Option<Sort>,
@@ -70,6 +116,38 @@ pub struct IndexWriterConfig<C: Codec, MS: MergeScheduler, MP: MergePolicy> {
     pub open_mode: OpenMode,
     pub codec: Arc<C>,
     pub commit_on_close: bool,
+    /// The numeric doc values field used to mark documents as soft deleted,
+    /// docs with a non-zero value for this field are hidden by the readers
+    /// opened from the writer. See `IndexWriter::soft_update_document`.
+    pub soft_deletes_field: Option<String>,
+    /// Decides when the old commits are deleted, defaults to
+    /// `KeepOnlyLastCommitDeletionPolicy`. Use e.g.
+    /// `KeepLastNCommitsDeletionPolicy` to keep older commits around.
+    pub index_deletion_policy: Arc<dyn IndexDeletionPolicy>,
+    /// Notified of the flushes, merges and commits of the writer, defaults to
+    /// `NoOpIndexWriterEventListener`.
+    pub event_listener: Arc<dyn IndexWriterEventListener>,
+    /// Called with a reader of each newly merged segment before it becomes
+    /// visible, see `set_merged_segment_warmer`.
+    pub merged_segment_warmer: Option<MergedSegmentWarmer<C>>,
+    /// Makes the writer commit by itself based on the time or the amount of
+    /// changes since the last commit, disabled by default.
+    pub auto_commit_policy: AutoCommitPolicy,
+    /// Validates the fields of the added documents against a `Schema`
+    /// shared by the writer, disabled by default. The schema is seeded with
+    /// the fields of the existing segments when the writer is opened.
+    pub schema: Option<Arc<Schema>>,
+    /// Verifies the checksums of all the files of the commit the writer is
+    /// opened on, e.g. to detect files torn by a crash before appending to
+    /// the index. Opening fails with `CorruptIndex` on a mismatch. Disabled
+    /// by default as it reads the whole index.
+    pub verify_checksums_on_open: bool,
+    /// How long opening the writer waits for the write lock of the directory
+    /// if it is held elsewhere, retrying with a backoff. Zero, the default,
+    /// fails right away with `LockObtainFailed`.
+    pub write_lock_timeout: Duration,
+    /// Defaults to `CommitDurability::Fsync`.
+    pub commit_durability: CommitDurability,
 }
 
 impl Default for IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy> {
@@ -88,6 +166,9 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
             use_compound_file: false,
             max_buffered_delete_terms: None,
             max_buffered_docs: None,
+            ram_buffer_size_mb: None,
+            ram_per_thread_hard_limit_mb: DEFAULT_RAM_PER_THREAD_HARD_LIMIT_MB,
+            max_concurrent_flushes: DEFAULT_MAX_CONCURRENT_FLUSHES,
             merge_policy,
             merge_scheduler,
             index_sort: None,
@@ -95,7 +176,142 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
             open_mode: OpenMode::CreateOrAppend,
             codec,
             commit_on_close: true,
+            soft_deletes_field: None,
+            index_deletion_policy: Arc::new(KeepOnlyLastCommitDeletionPolicy),
+            event_listener: Arc::new(NoOpIndexWriterEventListener),
+            merged_segment_warmer: None,
+            auto_commit_policy: AutoCommitPolicy::default(),
+            schema: None,
+            verify_checksums_on_open: false,
+            write_lock_timeout: Duration::ZERO,
+            commit_durability: CommitDurability::Fsync,
+        }
+    }
+
+    /// Sets the RAM that may be used for buffering added documents and
+    /// deletions before they are flushed, `None` disables flushing by RAM.
+    ///
+    /// A larger buffer means fewer, larger flushes and so usually faster
+    /// indexing. If both this and `max_buffered_docs` are set, a flush
+    /// happens on whichever triggers first.
+    ///
+    /// If the flushes can't keep up, the indexing threads are stalled, and
+    /// help flushing, while the buffered docs use more than twice this RAM.
+    pub fn set_ram_buffer_size_mb(&mut self, ram_buffer_size_mb: Option<f64>) -> Result<&mut Self> {
+        if let Some(mb) = ram_buffer_size_mb {
+            if mb.is_nan() || mb <= 0.0 {
+                return Err(IllegalArgument(format!(
+                    "ram_buffer_size_mb should be > 0.0 MB when enabled, got {}",
+                    mb
+                )));
+            }
+        }
+        self.ram_buffer_size_mb = ram_buffer_size_mb;
+        Ok(self)
+    }
+
+    /// Sets the number of buffered added documents of an indexing thread
+    /// that triggers a flush, `None` disables flushing by doc count.
+    pub fn set_max_buffered_docs(&mut self, max_buffered_docs: Option<u32>) -> Result<&mut Self> {
+        if let Some(max_docs) = max_buffered_docs {
+            if max_docs < 2 {
+                return Err(IllegalArgument(format!(
+                    "max_buffered_docs must at least be 2 when enabled, got {}",
+                    max_docs
+                )));
+            }
         }
+        self.max_buffered_docs = max_buffered_docs;
+        Ok(self)
+    }
+
+    /// Sets the RAM an indexing thread may use before its buffered docs are
+    /// flushed, whatever the other flush triggers. Must be in `1..2048`.
+    pub fn set_ram_per_thread_hard_limit_mb(
+        &mut self,
+        per_thread_hard_limit_mb: u32,
+    ) -> Result<&mut Self> {
+        if per_thread_hard_limit_mb == 0 || per_thread_hard_limit_mb >= 2048 {
+            return Err(IllegalArgument(format!(
+                "ram_per_thread_hard_limit_mb must be greater than 0 and less than 2048 MB, got {}",
+                per_thread_hard_limit_mb
+            )));
+        }
+        self.ram_per_thread_hard_limit_mb = per_thread_hard_limit_mb;
+        Ok(self)
+    }
+
+    /// Sets how many in-memory segments a full flush, e.g. on commit or when
+    /// opening a NRT reader, flushes in parallel. With one, the calling
+    /// thread flushes them one after the other.
+    pub fn set_max_concurrent_flushes(&mut self, max_concurrent_flushes: u32) -> Result<&mut Self> {
+        if max_concurrent_flushes == 0 {
+            return Err(IllegalArgument(
+                "max_concurrent_flushes must be at least 1".into(),
+            ));
+        }
+        self.max_concurrent_flushes = max_concurrent_flushes;
+        Ok(self)
+    }
+
+    /// Sets the order of the documents in the segments, by one or more
+    /// numeric doc values fields, `None` to keep them in the order they were
+    /// added.
+    ///
+    /// Flushed and merged segments are sorted and record the sort in their
+    /// `SegmentInfo`, so that searches sorted the same way can early terminate
+    /// on each segment. Only `Long`, `Int`, `Double` and `Float` sort fields
+    /// are supported, the fields must be indexed as `Numeric` doc values, or
+    /// `SortedNumeric` ones for a `SortField::SortedNumeric`. The index sort
+    /// of an existing index can't be changed.
+    pub fn set_index_sort(&mut self, index_sort: Option<Sort>) -> Result<&mut Self> {
+        if let Some(ref sort) = index_sort {
+            if sort.get_sort().is_empty() {
+                return Err(IllegalArgument(
+                    "index sort must have at least one sort field".into(),
+                ));
+            }
+            for sort_field in sort.get_sort() {
+                let field_type = Sorter::sort_field_type(sort_field);
+                let valid = matches!(
+                    (field_type, sort_field.missing_value()),
+                    (SortFieldType::Long, None | Some(VariantValue::Long(_)))
+                        | (SortFieldType::Int, None | Some(VariantValue::Int(_)))
+                        | (SortFieldType::Double, None | Some(VariantValue::Double(_)))
+                        | (SortFieldType::Float, None | Some(VariantValue::Float(_)))
+                );
+                if !valid {
+                    return Err(IllegalArgument(format!(
+                        "invalid index sort field {:?}: the type must be one of Long, Int, Double \
+                         or Float, with a missing value of the same type",
+                        sort_field
+                    )));
+                }
+            }
+        }
+        self.index_sort = index_sort;
+        Ok(self)
+    }
+
+    /// Sets a callback invoked with a reader of each newly merged segment
+    /// before the merge is committed, so that the caches of the segment can
+    /// be loaded before searches hit it, avoiding latency spikes after large
+    /// merges.
+    ///
+    /// The reader shares its core with the readers later opened from the
+    /// writer. The warmer runs in the merge thread and delays the commit of
+    /// the merge, an error fails the merge. It is only called when
+    /// `reader_pooling` is enabled.
+    pub fn set_merged_segment_warmer<F>(&mut self, warmer: F) -> &mut Self
+    where
+        F: Fn(&SearchLeafReader<C>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.merged_segment_warmer = Some(Arc::new(warmer));
+        self
+    }
+
+    pub fn merged_segment_warmer(&self) -> Option<&MergedSegmentWarmer<C>> {
+        self.merged_segment_warmer.as_ref()
     }
 
     pub fn max_buffered_delete_terms(&self) -> u32 {
@@ -114,6 +330,22 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
         self.max_buffered_docs.is_some()
     }
 
+    pub fn ram_buffer_size_mb(&self) -> f64 {
+        self.ram_buffer_size_mb.unwrap_or(0.0)
+    }
+
+    pub fn flush_on_ram(&self) -> bool {
+        self.ram_buffer_size_mb.is_some()
+    }
+
+    pub fn ram_per_thread_hard_limit_mb(&self) -> u32 {
+        self.ram_per_thread_hard_limit_mb
+    }
+
+    pub fn max_concurrent_flushes(&self) -> u32 {
+        self.max_concurrent_flushes.max(1)
+    }
+
     pub fn merge_policy(&self) -> &MP {
         &self.merge_policy
     }
@@ -122,8 +354,12 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
         self.index_sort.as_ref()
     }
 
-    pub fn index_deletion_policy(&self) -> KeepOnlyLastCommitDeletionPolicy {
-        KeepOnlyLastCommitDeletionPolicy::default()
+    pub fn index_deletion_policy(&self) -> Arc<dyn IndexDeletionPolicy> {
+        Arc::clone(&self.index_deletion_policy)
+    }
+
+    pub fn event_listener(&self) -> &dyn IndexWriterEventListener {
+        self.event_listener.as_ref()
     }
 
     pub fn merge_scheduler(&self) -> MS {
@@ -133,4 +369,61 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
     pub fn codec(&self) -> &C {
         self.codec.as_ref()
     }
+
+    pub fn soft_deletes_field(&self) -> Option<&str> {
+        self.soft_deletes_field.as_deref()
+    }
+
+    pub fn schema(&self) -> Option<&Arc<Schema>> {
+        self.schema.as_ref()
+    }
+
+    /// Returns this config with the merge policy replaced by the result of
+    /// `f`, e.g. to wrap it into another policy. All the other settings are
+    /// kept.
+    pub fn with_merge_policy<MP1: MergePolicy>(
+        self,
+        f: impl FnOnce(MP) -> MP1,
+    ) -> IndexWriterConfig<C, MS, MP1> {
+        IndexWriterConfig {
+            use_compound_file: self.use_compound_file,
+            max_buffered_delete_terms: self.max_buffered_delete_terms,
+            max_buffered_docs: self.max_buffered_docs,
+            ram_buffer_size_mb: self.ram_buffer_size_mb,
+            ram_per_thread_hard_limit_mb: self.ram_per_thread_hard_limit_mb,
+            max_concurrent_flushes: self.max_concurrent_flushes,
+            merge_policy: f(self.merge_policy),
+            merge_scheduler: self.merge_scheduler,
+            index_sort: self.index_sort,
+            reader_pooling: self.reader_pooling,
+            open_mode: self.open_mode,
+            codec: self.codec,
+            commit_on_close: self.commit_on_close,
+            soft_deletes_field: self.soft_deletes_field,
+            index_deletion_policy: self.index_deletion_policy,
+            event_listener: self.event_listener,
+            merged_segment_warmer: self.merged_segment_warmer,
+            auto_commit_policy: self.auto_commit_policy,
+            schema: self.schema,
+            verify_checksums_on_open: self.verify_checksums_on_open,
+            write_lock_timeout: self.write_lock_timeout,
+            commit_durability: self.commit_durability,
+        }
+    }
+}
+
+impl<MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<CodecEnum, MS, MP> {
+    /// Selects how the stored fields of the segments written by the writer
+    /// are compressed, `StoredFieldCompressMode::BestSpeed` by default.
+    ///
+    /// The mode is recorded per segment, so segments written in different
+    /// modes can be read and merged together.
+    pub fn set_stored_fields_mode(&mut self, mode: StoredFieldCompressMode) -> &mut Self {
+        self.codec = Arc::new(self.codec.with_stored_fields_mode(mode));
+        self
+    }
+
+    pub fn stored_fields_mode(&self) -> StoredFieldCompressMode {
+        self.codec.stored_fields_format().mode()
+    }
 }
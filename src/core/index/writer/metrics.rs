@@ -0,0 +1,43 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// A point-in-time snapshot of the activity of an `IndexWriter`, see
+/// `IndexWriter::metrics`.
+///
+/// The counters are cumulative since the writer was opened, while the other
+/// fields describe its current state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IndexWriterMetrics {
+    /// Number of segments flushed.
+    pub flush_count: u64,
+    /// Number of documents in the flushed segments.
+    pub flushed_docs: u64,
+    /// Size in bytes of the flushed segments.
+    pub flushed_bytes: u64,
+    /// Number of merges currently running.
+    pub running_merges: usize,
+    /// Number of merges registered but not started yet.
+    pub pending_merges: usize,
+    /// Number of merges committed to the index.
+    pub merge_count: u64,
+    /// Size in bytes of the segments written by the committed merges.
+    pub merged_bytes: u64,
+    /// Number of delete terms buffered and not applied to the segments yet.
+    pub buffered_delete_terms: usize,
+    /// Number of documents deleted by applying the buffered deletes.
+    pub applied_deletes: u64,
+    /// RAM used by the in-memory segments being indexed or flushed.
+    pub ram_bytes_used: u64,
+    /// Number of segments of the index, the in-memory ones excluded.
+    pub segment_count: usize,
+}
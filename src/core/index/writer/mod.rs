@@ -59,6 +59,18 @@ mod index_writer_config;
 
 pub use self::index_writer_config::*;
 
+mod schema;
+
+pub use self::schema::{FieldSchema, Schema};
+
+mod index_upgrader;
+
+pub use self::index_upgrader::*;
+
+mod index_splitter;
+
+pub use self::index_splitter::*;
+
 mod doc_writer_per_thread;
 
 pub use self::doc_writer_per_thread::*;
@@ -67,6 +79,22 @@ mod prefix_code_terms;
 
 pub use self::prefix_code_terms::*;
 
+mod event_listener;
+
+pub use self::event_listener::*;
+
+mod auto_commit;
+
+pub use self::auto_commit::*;
+
+mod metrics;
+
+pub use self::metrics::*;
+
+mod snapshot_deletion_policy;
+
+pub use self::snapshot_deletion_policy::*;
+
 pub mod doc_values_update;
 
 pub use self::doc_values_update::*;
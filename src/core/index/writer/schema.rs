@@ -0,0 +1,162 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::core::codec::field_infos::FieldInfo;
+use crate::core::doc::{DocValuesType, FieldType, Fieldable, IndexOptions};
+use crate::error::{Error::IllegalArgument, Result};
+
+/// How a field is indexed: its postings, doc values and points.
+///
+/// A field name may be shared by several `Fieldable`s of a document, e.g. a
+/// `StringField` and a `SortedDocValuesField`, each of them setting only a
+/// part of the schema. An unset part is `IndexOptions::Null`,
+/// `DocValuesType::Null` or a dimension count of 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub index_options: IndexOptions,
+    pub doc_values_type: DocValuesType,
+    pub dimension_count: u32,
+    pub index_dimension_count: u32,
+    pub dimension_num_bytes: u32,
+}
+
+impl FieldSchema {
+    pub fn from_field_type(field_type: &FieldType) -> FieldSchema {
+        FieldSchema {
+            index_options: field_type.index_options,
+            doc_values_type: field_type.doc_values_type,
+            dimension_count: field_type.dimension_count,
+            index_dimension_count: field_type.index_dimension_count,
+            dimension_num_bytes: field_type.dimension_num_bytes,
+        }
+    }
+
+    fn from_field_info(fi: &FieldInfo) -> FieldSchema {
+        FieldSchema {
+            index_options: fi.index_options,
+            doc_values_type: fi.doc_values_type,
+            dimension_count: fi.point_dimension_count,
+            index_dimension_count: fi.point_index_dimension_count,
+            dimension_num_bytes: fi.point_num_bytes,
+        }
+    }
+
+    /// Fills the unset parts of this schema from `other`, fails if both set
+    /// a part differently.
+    fn merge(&mut self, name: &str, other: &FieldSchema) -> Result<()> {
+        if other.index_options != IndexOptions::Null {
+            if self.index_options == IndexOptions::Null {
+                self.index_options = other.index_options;
+            } else if self.index_options != other.index_options {
+                return Err(IllegalArgument(format!(
+                    "cannot change index options from {:?} to {:?} for field '{}'",
+                    self.index_options, other.index_options, name
+                )));
+            }
+        }
+        if other.doc_values_type != DocValuesType::Null {
+            if self.doc_values_type == DocValuesType::Null {
+                self.doc_values_type = other.doc_values_type;
+            } else if self.doc_values_type != other.doc_values_type {
+                return Err(IllegalArgument(format!(
+                    "cannot change doc values type from {:?} to {:?} for field '{}'",
+                    self.doc_values_type, other.doc_values_type, name
+                )));
+            }
+        }
+        if other.dimension_count != 0 {
+            if self.dimension_count == 0 {
+                self.dimension_count = other.dimension_count;
+                self.index_dimension_count = other.index_dimension_count;
+                self.dimension_num_bytes = other.dimension_num_bytes;
+            } else if self.dimension_count != other.dimension_count
+                || self.index_dimension_count != other.index_dimension_count
+                || self.dimension_num_bytes != other.dimension_num_bytes
+            {
+                return Err(IllegalArgument(format!(
+                    "cannot change point dimensions from {}x{} bytes ({} indexed) to {}x{} bytes \
+                     ({} indexed) for field '{}'",
+                    self.dimension_count,
+                    self.dimension_num_bytes,
+                    self.index_dimension_count,
+                    other.dimension_count,
+                    other.dimension_num_bytes,
+                    other.index_dimension_count,
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An opt-in registry of the `FieldSchema` of each field of an index, see
+/// `IndexWriterConfig::schema`.
+///
+/// The schema of a field is recorded the first time it is used, or declared
+/// upfront with `define`. Documents using a field inconsistently with its
+/// schema are rejected by the writer before they reach the indexing chain,
+/// instead of failing the flush of their segment or silently changing the
+/// index options of the field.
+#[derive(Default)]
+pub struct Schema {
+    fields: Mutex<HashMap<String, FieldSchema>>,
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Declares the schema of a field, fails if it conflicts with the one
+    /// already recorded.
+    pub fn define(&self, name: &str, schema: FieldSchema) -> Result<()> {
+        let mut fields = self.fields.lock()?;
+        let mut merged = fields.get(name).copied().unwrap_or_default();
+        merged.merge(name, &schema)?;
+        fields.insert(name.to_string(), merged);
+        Ok(())
+    }
+
+    /// Returns the recorded schema of a field.
+    pub fn get(&self, name: &str) -> Option<FieldSchema> {
+        self.fields.lock().ok()?.get(name).copied()
+    }
+
+    /// Checks that the fields of a document, or of a block of documents, are
+    /// consistent with the schema and with each other, then records the
+    /// schema of the new fields. Rejected documents don't change the schema.
+    pub fn check<'a, F: Fieldable + 'a>(&self, doc: impl IntoIterator<Item = &'a F>) -> Result<()> {
+        let mut fields = self.fields.lock()?;
+        let mut updated: HashMap<&str, FieldSchema> = HashMap::new();
+        for field in doc {
+            let name = field.name();
+            let schema = updated
+                .entry(name)
+                .or_insert_with(|| fields.get(name).copied().unwrap_or_default());
+            schema.merge(name, &FieldSchema::from_field_type(field.field_type()))?;
+        }
+        for (name, schema) in updated {
+            fields.insert(name.to_string(), schema);
+        }
+        Ok(())
+    }
+
+    /// Records the schema of a field of an existing segment.
+    pub(crate) fn record_field_info(&self, fi: &FieldInfo) -> Result<()> {
+        self.define(&fi.name, FieldSchema::from_field_info(fi))
+    }
+}
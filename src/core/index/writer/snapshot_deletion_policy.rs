@@ -0,0 +1,440 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{check_footer, check_header, write_footer, write_header};
+use crate::core::index::writer::{CommitPoint, IndexDeletionPolicy, OpenMode};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::store::IOContext;
+
+use crate::error::Error::{IllegalArgument, IllegalState};
+use crate::error::Result;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Snapshots {
+    // number of times each commit generation was snapshotted
+    ref_counts: HashMap<i64, u32>,
+    // the snapshotted commits, by generation
+    index_commits: HashMap<i64, CommitPoint>,
+    // the most recent commit
+    last_commit: Option<CommitPoint>,
+    // whether the writer called `on_init`
+    initialized: bool,
+}
+
+impl Snapshots {
+    fn inc_ref(&mut self, commit: CommitPoint) {
+        let gen = commit.generation();
+        *self.ref_counts.entry(gen).or_insert(0) += 1;
+        self.index_commits.insert(gen, commit);
+    }
+
+    fn release_gen(&mut self, gen: i64) -> Result<()> {
+        let ref_count = match self.ref_counts.get_mut(&gen) {
+            Some(count) => count,
+            None => {
+                return Err(IllegalArgument(format!(
+                    "commit gen={} is not currently snapshotted",
+                    gen
+                )));
+            }
+        };
+        *ref_count -= 1;
+        if *ref_count == 0 {
+            self.ref_counts.remove(&gen);
+            self.index_commits.remove(&gen);
+        }
+        Ok(())
+    }
+
+    // keeps the snapshotted commits the wrapped policy just deleted
+    fn protect(&mut self, commits: Vec<&mut CommitPoint>) {
+        for commit in commits {
+            if self.ref_counts.contains_key(&commit.generation()) {
+                commit.undelete();
+                self.index_commits
+                    .insert(commit.generation(), commit.clone());
+            }
+            self.last_commit = Some(commit.clone());
+        }
+    }
+}
+
+/// An `IndexDeletionPolicy` that wraps any other `IndexDeletionPolicy` and
+/// adds the ability to hold and later release snapshots of an index. While a
+/// snapshot is held, the `IndexWriter` will not remove any files associated
+/// with it even if the index is otherwise being actively, arbitrarily
+/// changed. Because we wrap another arbitrary `IndexDeletionPolicy`, this
+/// gives you the freedom to continue using whatever `IndexDeletionPolicy`
+/// you would normally want to use with your index.
+///
+/// This class maintains all snapshots in-memory, and so the information is
+/// not persisted and not protected against system failures. If persistence
+/// is important, you can use `PersistentSnapshotDeletionPolicy`.
+///
+/// The same instance must be set as `IndexWriterConfig::index_deletion_policy`
+/// and used to take the snapshots, it can't be shared by several writers.
+pub struct SnapshotDeletionPolicy<P: IndexDeletionPolicy> {
+    primary: P,
+    snapshots: Mutex<Snapshots>,
+}
+
+impl<P: IndexDeletionPolicy> SnapshotDeletionPolicy<P> {
+    pub fn new(primary: P) -> Self {
+        SnapshotDeletionPolicy {
+            primary,
+            snapshots: Mutex::new(Snapshots::default()),
+        }
+    }
+
+    /// Snapshots the last commit and returns it. Once a commit is
+    /// 'snapshotted', it is protected from deletion (as long as this
+    /// `IndexDeletionPolicy` is used). The snapshot can be removed by
+    /// calling `release` followed by a call to
+    /// `IndexWriter::delete_unused_files`.
+    ///
+    /// NOTE: while the snapshot is held, the files it references will not
+    /// be deleted, which will consume additional disk space in your index.
+    /// If you take a snapshot at a particularly bad time (say just before
+    /// you call force_merge) then in the worst case this could consume an
+    /// extra 1X of your total index size, until you release the snapshot.
+    pub fn snapshot(&self) -> Result<CommitPoint> {
+        let mut snapshots = self.snapshots.lock()?;
+        if !snapshots.initialized {
+            return Err(IllegalState(
+                "this instance is not being used by IndexWriter; be sure to use the instance set \
+                 as IndexWriterConfig::index_deletion_policy"
+                    .into(),
+            ));
+        }
+        let commit = match snapshots.last_commit {
+            Some(ref commit) => commit.clone(),
+            // No commit yet, eg this is a new IndexWriter:
+            None => return Err(IllegalState("No index commit to snapshot".into())),
+        };
+        snapshots.inc_ref(commit.clone());
+        Ok(commit)
+    }
+
+    /// Release a snapshotted commit.
+    pub fn release(&self, commit: &CommitPoint) -> Result<()> {
+        self.release_gen(commit.generation())
+    }
+
+    /// Release a snapshot by generation.
+    pub fn release_gen(&self, gen: i64) -> Result<()> {
+        self.snapshots.lock()?.release_gen(gen)
+    }
+
+    /// Returns all the commits that are currently snapshotted.
+    pub fn snapshots(&self) -> Result<Vec<CommitPoint>> {
+        let snapshots = self.snapshots.lock()?;
+        let mut commits: Vec<CommitPoint> = snapshots.index_commits.values().cloned().collect();
+        commits.sort();
+        Ok(commits)
+    }
+
+    /// Returns the total number of snapshots currently held.
+    pub fn snapshot_count(&self) -> Result<usize> {
+        let snapshots = self.snapshots.lock()?;
+        Ok(snapshots.ref_counts.values().map(|c| *c as usize).sum())
+    }
+
+    /// Retrieve an `CommitPoint` from its generation, or `None` if this
+    /// commit is not currently snapshotted.
+    pub fn index_commit(&self, gen: i64) -> Result<Option<CommitPoint>> {
+        Ok(self.snapshots.lock()?.index_commits.get(&gen).cloned())
+    }
+
+    fn ref_counts(&self) -> Result<HashMap<i64, u32>> {
+        Ok(self.snapshots.lock()?.ref_counts.clone())
+    }
+}
+
+impl<P: IndexDeletionPolicy> IndexDeletionPolicy for SnapshotDeletionPolicy<P> {
+    fn on_init(&self, mut commits: Vec<&mut CommitPoint>) -> Result<()> {
+        let mut snapshots = self.snapshots.lock()?;
+        snapshots.initialized = true;
+        self.primary
+            .on_init(commits.iter_mut().map(|c| &mut **c).collect())?;
+        snapshots.protect(commits);
+        Ok(())
+    }
+
+    fn on_commit(&self, mut commits: Vec<&mut CommitPoint>) -> Result<()> {
+        let mut snapshots = self.snapshots.lock()?;
+        self.primary
+            .on_commit(commits.iter_mut().map(|c| &mut **c).collect())?;
+        snapshots.protect(commits);
+        Ok(())
+    }
+}
+
+/// File name prefix for the snapshots files.
+pub const SNAPSHOTS_PREFIX: &str = "snapshots_";
+
+const CODEC_NAME: &str = "snapshots";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// A `SnapshotDeletionPolicy` which adds a persistence layer so that
+/// snapshots can be maintained across the life of an application. The
+/// snapshots are persisted in a `Directory` and are committed as soon as
+/// `snapshot` or `release` is called.
+///
+/// The snapshots are stored in `snapshots_N` files, which are ignored by the
+/// `IndexWriter`, so the index directory itself can be used. Only the commit
+/// generations are persisted: once the application restarts, the
+/// `IndexWriter` must be opened with this policy to protect the snapshotted
+/// commits again, this is also when `index_commit` and `snapshots` get to
+/// know them.
+pub struct PersistentSnapshotDeletionPolicy<D: Directory, P: IndexDeletionPolicy> {
+    policy: SnapshotDeletionPolicy<P>,
+    directory: Arc<D>,
+    // generation of the next snapshots file, also serializes the writes
+    next_write_gen: Mutex<u64>,
+}
+
+impl<D, P> PersistentSnapshotDeletionPolicy<D, P>
+where
+    D: Directory + Send + Sync,
+    P: IndexDeletionPolicy,
+{
+    /// Loads the snapshots stored in `directory`, if any.
+    ///
+    /// With `OpenMode::Create` the existing snapshots are discarded, with
+    /// `OpenMode::Append` there must be a snapshots file in `directory`.
+    pub fn new(primary: P, directory: Arc<D>, mode: OpenMode) -> Result<Self> {
+        let policy = PersistentSnapshotDeletionPolicy {
+            policy: SnapshotDeletionPolicy::new(primary),
+            directory,
+            next_write_gen: Mutex::new(0),
+        };
+
+        if mode == OpenMode::Create {
+            policy.clear_prior_snapshots()?;
+        }
+        policy.load_prior_snapshots()?;
+
+        if mode == OpenMode::Append && *policy.next_write_gen.lock()? == 0 {
+            return Err(IllegalState("no snapshots stored in this directory".into()));
+        }
+        Ok(policy)
+    }
+
+    /// Snapshots the last commit, and persists the snapshots. Once this
+    /// method returns, the snapshot information is persisted in the
+    /// directory.
+    ///
+    /// See `SnapshotDeletionPolicy::snapshot`.
+    pub fn snapshot(&self) -> Result<CommitPoint> {
+        let commit = self.policy.snapshot()?;
+        if let Err(e) = self.persist() {
+            if let Err(err) = self.policy.release(&commit) {
+                log::warn!("release snapshot after persist failure failed: {:?}", err);
+            }
+            return Err(e);
+        }
+        Ok(commit)
+    }
+
+    /// Deletes a snapshotted commit. Once this method returns, the snapshot
+    /// information is persisted in the directory.
+    ///
+    /// See `SnapshotDeletionPolicy::release`.
+    pub fn release(&self, commit: &CommitPoint) -> Result<()> {
+        self.release_gen(commit.generation())
+    }
+
+    /// Deletes a snapshotted commit by generation. Once this method
+    /// returns, the snapshot information is persisted in the directory.
+    pub fn release_gen(&self, gen: i64) -> Result<()> {
+        let commit = self.policy.index_commit(gen)?;
+        self.policy.release_gen(gen)?;
+        if let Err(e) = self.persist() {
+            let mut snapshots = self.policy.snapshots.lock()?;
+            match commit {
+                Some(commit) => snapshots.inc_ref(commit),
+                None => *snapshots.ref_counts.entry(gen).or_insert(0) += 1,
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// See `SnapshotDeletionPolicy::snapshots`.
+    pub fn snapshots(&self) -> Result<Vec<CommitPoint>> {
+        self.policy.snapshots()
+    }
+
+    /// See `SnapshotDeletionPolicy::snapshot_count`.
+    pub fn snapshot_count(&self) -> Result<usize> {
+        self.policy.snapshot_count()
+    }
+
+    /// See `SnapshotDeletionPolicy::index_commit`.
+    pub fn index_commit(&self, gen: i64) -> Result<Option<CommitPoint>> {
+        self.policy.index_commit(gen)
+    }
+
+    /// Returns the file name the snapshots are currently saved to, or `None`
+    /// if no snapshots have been saved.
+    pub fn last_save_file(&self) -> Result<Option<String>> {
+        let next_write_gen = *self.next_write_gen.lock()?;
+        if next_write_gen == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(format!("{}{}", SNAPSHOTS_PREFIX, next_write_gen - 1)))
+        }
+    }
+
+    fn persist(&self) -> Result<()> {
+        let mut next_write_gen = self.next_write_gen.lock()?;
+        let ref_counts = self.policy.ref_counts()?;
+
+        let file_name = format!("{}{}", SNAPSHOTS_PREFIX, *next_write_gen);
+        let res = self.write_snapshots(&file_name, &ref_counts);
+        if res.is_err() {
+            if let Err(e) = self.directory.delete_file(&file_name) {
+                log::warn!("delete file '{}' failed by: '{:?}'", file_name, e);
+            }
+            return res;
+        }
+
+        if *next_write_gen > 0 {
+            // error OK: likely it didn't exist
+            let last_save_file = format!("{}{}", SNAPSHOTS_PREFIX, *next_write_gen - 1);
+            if let Err(e) = self.directory.delete_file(&last_save_file) {
+                log::debug!("delete file '{}' failed by: '{:?}'", last_save_file, e);
+            }
+        }
+        *next_write_gen += 1;
+        Ok(())
+    }
+
+    fn write_snapshots(&self, file_name: &str, ref_counts: &HashMap<i64, u32>) -> Result<()> {
+        {
+            let mut output = self
+                .directory
+                .create_output(file_name, &IOContext::Default)?;
+            write_header(&mut output, CODEC_NAME, VERSION_CURRENT)?;
+            output.write_vint(ref_counts.len() as i32)?;
+            for (gen, ref_count) in ref_counts {
+                output.write_vlong(*gen)?;
+                output.write_vint(*ref_count as i32)?;
+            }
+            write_footer(&mut output)?;
+        }
+
+        let mut sync_files = HashSet::with_capacity(1);
+        sync_files.insert(file_name.to_string());
+        self.directory.sync(&sync_files)
+    }
+
+    fn read_snapshots(&self, file_name: &str) -> Result<HashMap<i64, u32>> {
+        let mut input = self
+            .directory
+            .open_checksum_input(file_name, &IOContext::READ)?;
+        check_header(&mut input, CODEC_NAME, VERSION_START, VERSION_CURRENT)?;
+        let count = input.read_vint()?;
+        let mut ref_counts = HashMap::with_capacity(count.max(0) as usize);
+        for _ in 0..count {
+            let gen = input.read_vlong()?;
+            let ref_count = input.read_vint()?;
+            ref_counts.insert(gen, ref_count as u32);
+        }
+        check_footer(&mut input)?;
+        Ok(ref_counts)
+    }
+
+    // Returns the generations of the snapshots files in the directory,
+    // the most recent first.
+    fn snapshot_gens(&self) -> Result<Vec<u64>> {
+        let mut gens = vec![];
+        for file in self.directory.list_all()? {
+            if let Some(gen) = file.strip_prefix(SNAPSHOTS_PREFIX) {
+                if let Ok(gen) = gen.parse::<u64>() {
+                    gens.push(gen);
+                }
+            }
+        }
+        gens.sort_by(|a, b| b.cmp(a));
+        Ok(gens)
+    }
+
+    fn clear_prior_snapshots(&self) -> Result<()> {
+        for gen in self.snapshot_gens()? {
+            self.directory
+                .delete_file(&format!("{}{}", SNAPSHOTS_PREFIX, gen))?;
+        }
+        Ok(())
+    }
+
+    // Reads the snapshots from the most recent readable snapshots file, and
+    // removes the other ones.
+    fn load_prior_snapshots(&self) -> Result<()> {
+        let gens = self.snapshot_gens()?;
+        let mut gen_loaded = None;
+        let mut error = None;
+        for gen in &gens {
+            match self.read_snapshots(&format!("{}{}", SNAPSHOTS_PREFIX, gen)) {
+                Ok(ref_counts) => {
+                    self.policy.snapshots.lock()?.ref_counts = ref_counts;
+                    gen_loaded = Some(*gen);
+                    break;
+                }
+                Err(e) => {
+                    log::warn!("failed to read snapshots file of gen {}: {:?}", gen, e);
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        match gen_loaded {
+            Some(gen_loaded) => {
+                // Remove any broken / old snapshot files:
+                for gen in gens {
+                    if gen != gen_loaded {
+                        let file_name = format!("{}{}", SNAPSHOTS_PREFIX, gen);
+                        if let Err(e) = self.directory.delete_file(&file_name) {
+                            log::warn!("delete file '{}' failed by: '{:?}'", file_name, e);
+                        }
+                    }
+                }
+                *self.next_write_gen.lock()? = gen_loaded + 1;
+                Ok(())
+            }
+            None => match error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl<D, P> IndexDeletionPolicy for PersistentSnapshotDeletionPolicy<D, P>
+where
+    D: Directory + Send + Sync,
+    P: IndexDeletionPolicy,
+{
+    fn on_init(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
+        self.policy.on_init(commits)
+    }
+
+    fn on_commit(&self, commits: Vec<&mut CommitPoint>) -> Result<()> {
+        self.policy.on_commit(commits)
+    }
+}
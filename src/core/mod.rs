@@ -12,10 +12,14 @@
 // limitations under the License.
 
 pub mod analysis;
+pub mod backup;
+pub mod classification;
 pub mod codec;
 pub mod doc;
+pub mod facet;
 pub mod highlight;
 pub mod index;
+pub mod replicator;
 pub mod search;
 pub mod store;
 pub mod util;
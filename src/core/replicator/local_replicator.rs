@@ -0,0 +1,217 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::replicator::{Revision, RevisionFile};
+use crate::error::Error::{IllegalArgument, IllegalState};
+use crate::Result;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A replication session returned by `Replicator::check_for_update`: the
+/// files of the revision can be obtained with the session id until the
+/// session is released.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SessionToken {
+    pub id: String,
+    pub version: i64,
+    pub files: Vec<RevisionFile>,
+}
+
+/// The primary side of the replication, as seen by the replicas.
+///
+/// Implement this to carry the replication over a network, e.g. serving the
+/// calls of a `LocalReplicator` on the primary.
+pub trait Replicator: Send + Sync {
+    /// Returns a session to copy the latest revision if it's newer than
+    /// `current_version`, the version of the replica if it has one.
+    fn check_for_update(&self, current_version: Option<i64>) -> Result<Option<SessionToken>>;
+
+    /// Reads at most `length` bytes of the file `file_name` of the session
+    /// revision, from `offset`; fewer bytes are only returned at the end of
+    /// the file.
+    fn obtain_file(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        offset: u64,
+        length: usize,
+    ) -> Result<Vec<u8>>;
+
+    /// Ends the session, the revision may then be released.
+    fn release(&self, session_id: &str) -> Result<()>;
+}
+
+struct PublishedRevision {
+    revision: Box<dyn Revision>,
+    // the sessions copying the revision, plus one while it's the latest
+    ref_count: usize,
+}
+
+#[derive(Default)]
+struct Revisions {
+    // the published revisions still in use, by version
+    published: HashMap<i64, PublishedRevision>,
+    latest: Option<i64>,
+    // the version copied by each session
+    sessions: HashMap<String, i64>,
+    next_session_id: u64,
+}
+
+impl Revisions {
+    fn dec_ref(&mut self, version: i64) -> Result<()> {
+        let published = self.published.get_mut(&version).ok_or_else(|| {
+            IllegalState(format!("released revision {} isn't published", version))
+        })?;
+        published.ref_count -= 1;
+        if published.ref_count == 0 {
+            let published = self.published.remove(&version).unwrap();
+            published.revision.release()?;
+        }
+        Ok(())
+    }
+
+    fn session_revision(&self, session_id: &str) -> Result<&dyn Revision> {
+        match self.sessions.get(session_id) {
+            Some(version) => Ok(self.published[version].revision.as_ref()),
+            None => Err(IllegalState(format!(
+                "replication session '{}' doesn't exist or was released",
+                session_id
+            ))),
+        }
+    }
+}
+
+/// A `Replicator` serving the revisions published in-process, by the
+/// primary index.
+///
+/// A revision is released once a newer one is published and the sessions
+/// copying it are released.
+///
+/// ```rust,ignore
+/// let policy = Arc::new(SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy));
+/// config.index_deletion_policy = policy.clone();
+/// ...
+/// writer.commit()?;
+/// replicator.publish(Box::new(IndexRevision::new(writer.clone(), Arc::clone(&policy))?))?;
+/// ```
+#[derive(Default)]
+pub struct LocalReplicator {
+    revisions: Mutex<Revisions>,
+}
+
+impl LocalReplicator {
+    pub fn new() -> LocalReplicator {
+        LocalReplicator::default()
+    }
+
+    /// Makes `revision` the latest revision, it must be newer than the
+    /// current one. Publishing the current version again is a no-op.
+    pub fn publish(&self, revision: Box<dyn Revision>) -> Result<()> {
+        let mut revisions = self.revisions.lock()?;
+        let version = revision.version();
+        if let Some(latest) = revisions.latest {
+            if version <= latest {
+                revision.release()?;
+                if version == latest {
+                    return Ok(());
+                }
+                return Err(IllegalArgument(format!(
+                    "can't publish revision {} older than the latest revision {}",
+                    version, latest
+                )));
+            }
+        }
+        revisions.published.insert(
+            version,
+            PublishedRevision {
+                revision,
+                ref_count: 1,
+            },
+        );
+        if let Some(latest) = revisions.latest.replace(version) {
+            revisions.dec_ref(latest)?;
+        }
+        Ok(())
+    }
+
+    /// The version of the latest published revision.
+    pub fn latest_version(&self) -> Result<Option<i64>> {
+        Ok(self.revisions.lock()?.latest)
+    }
+
+    /// The number of sessions not yet released.
+    pub fn session_count(&self) -> Result<usize> {
+        Ok(self.revisions.lock()?.sessions.len())
+    }
+}
+
+impl Replicator for LocalReplicator {
+    fn check_for_update(&self, current_version: Option<i64>) -> Result<Option<SessionToken>> {
+        let mut revisions = self.revisions.lock()?;
+        let latest = match revisions.latest {
+            Some(latest) if current_version.is_none_or(|v| v < latest) => latest,
+            _ => return Ok(None),
+        };
+        let published = revisions.published.get_mut(&latest).unwrap();
+        published.ref_count += 1;
+        let files = published.revision.files().to_vec();
+
+        let id = revisions.next_session_id.to_string();
+        revisions.next_session_id += 1;
+        revisions.sessions.insert(id.clone(), latest);
+        Ok(Some(SessionToken {
+            id,
+            version: latest,
+            files,
+        }))
+    }
+
+    fn obtain_file(
+        &self,
+        session_id: &str,
+        file_name: &str,
+        offset: u64,
+        length: usize,
+    ) -> Result<Vec<u8>> {
+        let revisions = self.revisions.lock()?;
+        let revision = revisions.session_revision(session_id)?;
+        if !revision.files().iter().any(|f| f.name == file_name) {
+            return Err(IllegalArgument(format!(
+                "file '{}' is not part of revision {}",
+                file_name,
+                revision.version()
+            )));
+        }
+        let mut input = revision.open(file_name)?;
+        let length = input.len().saturating_sub(offset).min(length as u64) as usize;
+        let mut bytes = vec![0u8; length];
+        if length > 0 {
+            input.seek(offset as i64)?;
+            input.read_bytes(&mut bytes, 0, length)?;
+        }
+        Ok(bytes)
+    }
+
+    fn release(&self, session_id: &str) -> Result<()> {
+        let mut revisions = self.revisions.lock()?;
+        match revisions.sessions.remove(session_id) {
+            Some(version) => revisions.dec_ref(version),
+            None => Err(IllegalState(format!(
+                "replication session '{}' doesn't exist or was released",
+                session_id
+            ))),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replication of an index from a primary to replicas, at the granularity of
+//! segment files.
+//!
+//! The primary publishes its commits as `Revision`s to a `Replicator`, and
+//! each replica runs a `ReplicationClient` pulling the files of the new
+//! revisions it doesn't have yet. The `Replicator` trait is the transport,
+//! `LocalReplicator` serves the revisions in-process and can be wrapped by a
+//! remote implementation.
+
+mod revision;
+
+pub use self::revision::*;
+
+mod local_replicator;
+
+pub use self::local_replicator::*;
+
+mod replication_client;
+
+pub use self::replication_client::*;
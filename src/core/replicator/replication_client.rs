@@ -0,0 +1,277 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::{
+    get_last_commit_generation, CODEC_FILE_PATTERN, INDEX_FILE_PENDING_SEGMENTS,
+    INDEX_FILE_SEGMENTS,
+};
+use crate::core::replicator::{Replicator, SessionToken};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::DataOutput;
+use crate::core::store::IOContext;
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The replica side of the replication: copies the new revisions of a
+/// `Replicator` into the replica index directory.
+///
+/// Only the files the replica doesn't have yet are copied, as segment files
+/// are never modified once written, a revision sharing most of its segments
+/// with the previous one only copies the new segments. The `segments_N`
+/// file is copied last and renamed into place once the other files are
+/// synced, so a reader opening the replica directory never sees a partly
+/// copied commit. The files of the previous commits are then deleted.
+///
+/// ```rust,ignore
+/// let mut client = ReplicationClient::new(replicator, Arc::clone(&replica_directory))
+///     .with_refresh(Box::new(move |_version| searcher_manager.maybe_refresh().map(|_| ())));
+/// client.update()?;
+/// ```
+pub struct ReplicationClient<R: Replicator, D: Directory> {
+    replicator: Arc<R>,
+    directory: Arc<D>,
+    chunk_size: usize,
+    refresh: Option<Box<dyn FnMut(i64) -> Result<()> + Send>>,
+}
+
+impl<R: Replicator, D: Directory> ReplicationClient<R, D> {
+    pub fn new(replicator: Arc<R>, directory: Arc<D>) -> Self {
+        ReplicationClient {
+            replicator,
+            directory,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            refresh: None,
+        }
+    }
+
+    /// The number of bytes obtained from the replicator at once.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Result<Self> {
+        if chunk_size == 0 {
+            return Err(IllegalArgument("chunk_size must be > 0".into()));
+        }
+        self.chunk_size = chunk_size;
+        Ok(self)
+    }
+
+    /// Called with the version of each revision copied by `update`, e.g. to
+    /// reopen the readers of the replica.
+    pub fn with_refresh(mut self, refresh: Box<dyn FnMut(i64) -> Result<()> + Send>) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// The version of the last revision copied into the replica directory,
+    /// the generation of its latest commit.
+    pub fn current_version(&self) -> Result<Option<i64>> {
+        let generation = get_last_commit_generation(&self.directory.list_all()?)?;
+        Ok(if generation < 0 {
+            None
+        } else {
+            Some(generation)
+        })
+    }
+
+    /// Copies the latest revision of the replicator if it's newer than the
+    /// replica, returns whether there was one.
+    pub fn update(&mut self) -> Result<bool> {
+        let session = match self.replicator.check_for_update(self.current_version()?)? {
+            Some(session) => session,
+            None => return Ok(false),
+        };
+        let res = self.copy_revision(&session);
+        self.replicator.release(&session.id)?;
+        res?;
+
+        if let Some(refresh) = self.refresh.as_mut() {
+            refresh(session.version)?;
+        }
+        self.delete_old_files(&session)?;
+        Ok(true)
+    }
+
+    fn copy_revision(&self, session: &SessionToken) -> Result<()> {
+        let existing: HashSet<String> = self.directory.list_all()?.into_iter().collect();
+        let mut copied = HashSet::new();
+        let mut segments_file = None;
+        for file in &session.files {
+            if file.name.starts_with(INDEX_FILE_SEGMENTS) {
+                segments_file = Some(file);
+                continue;
+            }
+            if existing.contains(&file.name) {
+                if self.directory.file_length(&file.name)? as u64 == file.length {
+                    continue;
+                }
+                // left over by an interrupted copy
+                self.directory.delete_file(&file.name)?;
+            }
+            self.copy_file(session, &file.name, &file.name, file.length)?;
+            copied.insert(file.name.clone());
+        }
+        self.directory.sync(&copied)?;
+
+        if let Some(file) = segments_file {
+            let pending = format!(
+                "{}{}",
+                INDEX_FILE_PENDING_SEGMENTS,
+                &file.name[INDEX_FILE_SEGMENTS.len()..]
+            );
+            if existing.contains(&pending) {
+                self.directory.delete_file(&pending)?;
+            }
+            self.copy_file(session, &file.name, &pending, file.length)?;
+            let mut pending_files = HashSet::new();
+            pending_files.insert(pending.clone());
+            self.directory.sync(&pending_files)?;
+            self.directory.rename(&pending, &file.name)?;
+            self.directory.sync_metadata()?;
+        }
+        Ok(())
+    }
+
+    fn copy_file(
+        &self,
+        session: &SessionToken,
+        file_name: &str,
+        dest: &str,
+        length: u64,
+    ) -> Result<()> {
+        let mut output = self.directory.create_output(dest, &IOContext::Default)?;
+        let mut offset = 0u64;
+        while offset < length {
+            let chunk_size = (length - offset).min(self.chunk_size as u64) as usize;
+            let bytes = self
+                .replicator
+                .obtain_file(&session.id, file_name, offset, chunk_size)?;
+            if bytes.is_empty() {
+                return Err(CorruptIndex(format!(
+                    "file '{}' of revision {} ended at {} bytes, expected {}",
+                    file_name, session.version, offset, length
+                )));
+            }
+            output.write_bytes(&bytes, 0, bytes.len())?;
+            offset += bytes.len() as u64;
+        }
+        Ok(())
+    }
+
+    // deletes the index files not referenced by the copied revision, the
+    // readers of the previous revisions must have been refreshed
+    fn delete_old_files(&self, session: &SessionToken) -> Result<()> {
+        let referenced: HashSet<&str> = session.files.iter().map(|f| f.name.as_str()).collect();
+        let pattern = Regex::new(CODEC_FILE_PATTERN).unwrap();
+        for name in self.directory.list_all()? {
+            let index_file = name.starts_with(INDEX_FILE_SEGMENTS)
+                || name.starts_with(INDEX_FILE_PENDING_SEGMENTS)
+                || pattern.is_match(&name);
+            if index_file && !referenced.contains(name.as_str()) {
+                self.directory.delete_file(&name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::index::reader::IndexReader;
+    use crate::core::index::tests::{add_id_doc, snapshot_writer, InMemoryReader};
+    use crate::core::replicator::{IndexRevision, LocalReplicator};
+    use crate::core::store::directory::ByteBuffersDirectory;
+
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    #[test]
+    fn test_replicate() {
+        let (writer, policy) = snapshot_writer();
+        let replicator = Arc::new(LocalReplicator::new());
+
+        let replica = Arc::new(ByteBuffersDirectory::new());
+        let refreshed = Arc::new(AtomicI64::new(-1));
+        let refreshed_version = Arc::clone(&refreshed);
+        let mut client = ReplicationClient::new(Arc::clone(&replicator), Arc::clone(&replica))
+            .with_chunk_size(7)
+            .unwrap()
+            .with_refresh(Box::new(move |version| {
+                refreshed_version.store(version, Ordering::Release);
+                Ok(())
+            }));
+        assert!(!client.update().unwrap());
+
+        add_id_doc(&writer, "1");
+        add_id_doc(&writer, "2");
+        writer.commit().unwrap();
+        let revision = IndexRevision::new(writer.clone(), Arc::clone(&policy)).unwrap();
+        let version = revision.commit().generation();
+        replicator.publish(Box::new(revision)).unwrap();
+        assert_eq!(policy.snapshot_count().unwrap(), 1);
+
+        assert!(client.update().unwrap());
+        assert_eq!(client.current_version().unwrap(), Some(version));
+        assert_eq!(refreshed.load(Ordering::Acquire), version);
+        assert_eq!(replicator.session_count().unwrap(), 0);
+        assert_eq!(
+            InMemoryReader::open(Arc::clone(&replica))
+                .unwrap()
+                .num_docs(),
+            2
+        );
+        assert!(!client.update().unwrap());
+
+        add_id_doc(&writer, "3");
+        writer.commit().unwrap();
+        let revision = IndexRevision::new(writer.clone(), Arc::clone(&policy)).unwrap();
+        let new_version = revision.commit().generation();
+        assert!(new_version > version);
+        replicator.publish(Box::new(revision)).unwrap();
+        // the previous revision isn't copied by any session, so it's released
+        assert_eq!(policy.snapshot_count().unwrap(), 1);
+
+        assert!(client.update().unwrap());
+        assert_eq!(client.current_version().unwrap(), Some(new_version));
+        assert_eq!(
+            InMemoryReader::open(Arc::clone(&replica))
+                .unwrap()
+                .num_docs(),
+            3
+        );
+        // the files of the previous commit are gone
+        let files = replica.list_all().unwrap();
+        assert_eq!(
+            files.iter().filter(|f| f.starts_with("segments_")).count(),
+            1
+        );
+
+        // publishing the latest version again is a no-op
+        let revision = IndexRevision::new(writer.clone(), Arc::clone(&policy)).unwrap();
+        replicator.publish(Box::new(revision)).unwrap();
+        assert_eq!(policy.snapshot_count().unwrap(), 1);
+        assert!(replicator.release("unknown").is_err());
+    }
+
+    #[test]
+    fn test_invalid_chunk_size() {
+        let client = ReplicationClient::new(
+            Arc::new(LocalReplicator::new()),
+            Arc::new(ByteBuffersDirectory::new()),
+        );
+        assert!(client.with_chunk_size(0).is_err());
+    }
+}
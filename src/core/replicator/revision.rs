@@ -0,0 +1,151 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::segment_infos::INDEX_FILE_SEGMENTS;
+use crate::core::codec::Codec;
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::writer::{
+    CommitPoint, IndexDeletionPolicy, IndexWriter, SnapshotDeletionPolicy,
+};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::IndexInput;
+use crate::core::store::IOContext;
+use crate::Result;
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A file of a `Revision`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RevisionFile {
+    pub name: String,
+    pub length: u64,
+}
+
+/// A point-in-time state of an index published by a primary, whose files
+/// are kept until it's released.
+pub trait Revision: Send + Sync {
+    /// The version of the revision, the newer revisions have greater
+    /// versions.
+    fn version(&self) -> i64;
+
+    /// The files of the revision, in the order they must be copied.
+    fn files(&self) -> &[RevisionFile];
+
+    /// Opens one of `files` for reading.
+    fn open(&self, file_name: &str) -> Result<Box<dyn IndexInput>>;
+
+    /// Called once the revision isn't published nor copied anymore, to
+    /// free its files.
+    fn release(&self) -> Result<()>;
+}
+
+/// A `Revision` of an index commit, snapshotted by the
+/// `SnapshotDeletionPolicy` of the writer so that its files aren't deleted
+/// while replicas copy them.
+///
+/// The version is the commit generation, and the `segments_N` file comes
+/// last, so that a replica only sees the commit once all the segment files
+/// are there.
+pub struct IndexRevision<D, C, MS, MP, P>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+    P: IndexDeletionPolicy,
+{
+    writer: IndexWriter<D, C, MS, MP>,
+    policy: Arc<SnapshotDeletionPolicy<P>>,
+    commit: CommitPoint,
+    files: Vec<RevisionFile>,
+}
+
+impl<D, C, MS, MP, P> IndexRevision<D, C, MS, MP, P>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+    P: IndexDeletionPolicy,
+{
+    /// Snapshots the last commit of `writer`, whose deletion policy must be
+    /// `policy`.
+    pub fn new(
+        writer: IndexWriter<D, C, MS, MP>,
+        policy: Arc<SnapshotDeletionPolicy<P>>,
+    ) -> Result<Self> {
+        let commit = policy.snapshot()?;
+        match Self::commit_files(writer.directory().as_ref(), &commit) {
+            Ok(files) => Ok(IndexRevision {
+                writer,
+                policy,
+                commit,
+                files,
+            }),
+            Err(e) => {
+                policy.release(&commit)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn commit_files(directory: &D, commit: &CommitPoint) -> Result<Vec<RevisionFile>> {
+        let mut names: Vec<&String> = commit.file_names().iter().collect();
+        names.sort_by_key(|name| (name.starts_with(INDEX_FILE_SEGMENTS), name.as_str()));
+        let mut files = Vec::with_capacity(names.len());
+        for name in names {
+            files.push(RevisionFile {
+                name: name.clone(),
+                length: directory.file_length(name)? as u64,
+            });
+        }
+        Ok(files)
+    }
+
+    /// The snapshotted commit.
+    pub fn commit(&self) -> &CommitPoint {
+        &self.commit
+    }
+}
+
+impl<D, C, MS, MP, P> Revision for IndexRevision<D, C, MS, MP, P>
+where
+    D: Directory + Send + Sync + 'static,
+    C: Codec,
+    MS: MergeScheduler,
+    MP: MergePolicy,
+    P: IndexDeletionPolicy,
+{
+    fn version(&self) -> i64 {
+        self.commit.generation()
+    }
+
+    fn files(&self) -> &[RevisionFile] {
+        &self.files
+    }
+
+    fn open(&self, file_name: &str) -> Result<Box<dyn IndexInput>> {
+        self.writer
+            .directory()
+            .open_input(file_name, &IOContext::READ_ONCE)
+    }
+
+    fn release(&self) -> Result<()> {
+        self.policy.release(&self.commit)?;
+        if self.writer.is_open() {
+            self.writer.delete_unused_files()?;
+        }
+        Ok(())
+    }
+}
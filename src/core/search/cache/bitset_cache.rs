@@ -0,0 +1,258 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::query::{Query, Weight};
+use crate::core::search::{DocIterator, ScoreMode, SearchPlanBuilder, NO_MORE_DOCS};
+use crate::core::util::external::Deferred;
+use crate::core::util::{bits2words, Accountable, BitSet, FixedBitSet, NamedAccountable};
+use crate::Result;
+
+/// Produces the `FixedBitSet` of the docs of a segment that match a filter,
+/// e.g. the parent docs of a block join.
+pub trait BitSetProducer<C: Codec> {
+    /// Returns the matching docs of the segment, `None` if no doc matches.
+    fn bit_set(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Arc<FixedBitSet>>>;
+}
+
+#[derive(Default)]
+struct BitSetCacheData {
+    // core cache key -> filter key -> matching docs
+    segments: HashMap<String, HashMap<String, Option<Arc<FixedBitSet>>>>,
+    ram_bytes_used: usize,
+}
+
+impl BitSetCacheData {
+    fn ram_bytes(bits: &Option<Arc<FixedBitSet>>) -> usize {
+        bits.as_ref()
+            .map_or(0, |b| bits2words(b.num_bits) * mem::size_of::<i64>())
+    }
+
+    fn clear_core_cache_key(&mut self, core_key: &str) {
+        if let Some(filters) = self.segments.remove(core_key) {
+            let freed: usize = filters.values().map(Self::ram_bytes).sum();
+            self.ram_bytes_used -= freed;
+        }
+    }
+}
+
+/// A cache of materialized filters, keyed by segment core and filter.
+///
+/// Unlike the `LRUQueryCache`, the cached bit sets are never evicted as long
+/// as the segment is alive: this is intended for a small number of filters
+/// that are needed on every request, such as block join parent filters or
+/// heavy terms filters. Entries are dropped when the core of the segment is
+/// dropped, e.g. once it was merged away and no reader uses it anymore.
+///
+/// Deleted docs are not removed from the cached bit sets.
+#[derive(Default)]
+pub struct BitSetFilterCache {
+    data: Arc<RwLock<BitSetCacheData>>,
+    hit_count: AtomicU64,
+    miss_count: AtomicU64,
+}
+
+impl BitSetFilterCache {
+    pub fn new() -> BitSetFilterCache {
+        Self::default()
+    }
+
+    /// Returns a `BitSetProducer` for `query` backed by this cache, the
+    /// filter is identified by the `Display` of the query.
+    pub fn bit_set_producer<C: Codec>(
+        self: &Arc<Self>,
+        searcher: &dyn SearchPlanBuilder<C>,
+        query: &dyn Query<C>,
+    ) -> Result<QueryBitSetProducer<C>> {
+        Ok(QueryBitSetProducer {
+            key: query.to_string(),
+            weight: searcher.create_normalized_weight(query, ScoreMode::CompleteNoScores)?,
+            cache: Arc::clone(self),
+        })
+    }
+
+    /// Returns the cached docs of the segment matching the filter identified
+    /// by `key`, or computes and caches them with `weight`, which must not
+    /// need scores.
+    pub fn get_or_build<C: Codec>(
+        &self,
+        key: &str,
+        weight: &dyn Weight<C>,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Arc<FixedBitSet>>> {
+        let core_key = reader.reader.core_cache_key();
+        if let Some(bits) = self
+            .data
+            .read()?
+            .segments
+            .get(core_key)
+            .and_then(|filters| filters.get(key))
+        {
+            self.hit_count.fetch_add(1, Ordering::Relaxed);
+            return Ok(bits.clone());
+        }
+
+        self.miss_count.fetch_add(1, Ordering::Relaxed);
+        let bits = Self::build(weight, reader)?;
+
+        let mut data = self.data.write()?;
+        let new_segment = !data.segments.contains_key(core_key);
+        let filters = data.segments.entry(core_key.to_string()).or_default();
+        if !filters.contains_key(key) {
+            filters.insert(key.to_string(), bits.clone());
+            data.ram_bytes_used += BitSetCacheData::ram_bytes(&bits);
+        }
+        if new_segment {
+            let core_key = core_key.to_string();
+            let cache_data = Arc::clone(&self.data);
+            reader.reader.add_core_drop_listener(Deferred::new(move || {
+                cache_data.write().unwrap().clear_core_cache_key(&core_key);
+            }));
+        }
+        Ok(bits)
+    }
+
+    fn build<C: Codec>(
+        weight: &dyn Weight<C>,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Arc<FixedBitSet>>> {
+        let mut scorer = match weight.create_scorer(reader)? {
+            Some(scorer) => scorer,
+            None => return Ok(None),
+        };
+        let mut bits = FixedBitSet::new(reader.reader.max_doc() as usize);
+        loop {
+            let doc = scorer.next()?;
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            bits.set(doc as usize);
+        }
+        Ok(Some(Arc::new(bits)))
+    }
+
+    /// Drop all the cached bit sets.
+    pub fn clear(&self) -> Result<()> {
+        let mut data = self.data.write()?;
+        data.segments.clear();
+        data.ram_bytes_used = 0;
+        Ok(())
+    }
+
+    /// The number of cached (segment, filter) entries.
+    pub fn size(&self) -> Result<usize> {
+        Ok(self.data.read()?.segments.values().map(|f| f.len()).sum())
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.hit_count.load(Ordering::Relaxed)
+    }
+
+    pub fn miss_count(&self) -> u64 {
+        self.miss_count.load(Ordering::Relaxed)
+    }
+}
+
+impl Accountable for BitSetFilterCache {
+    fn ram_bytes_used(&self) -> usize {
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        data.ram_bytes_used
+    }
+
+    /// The cached filters of each segment core.
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        let mut segments: Vec<_> = data
+            .segments
+            .iter()
+            .map(|(core_key, filters)| {
+                let mut children: Vec<_> = filters
+                    .iter()
+                    .map(|(key, bits)| {
+                        NamedAccountable::with_bytes(
+                            format!("filter {}", key),
+                            BitSetCacheData::ram_bytes(bits),
+                        )
+                    })
+                    .collect();
+                children.sort_by(|a, b| a.name.cmp(&b.name));
+                NamedAccountable {
+                    name: format!("segment {}", core_key),
+                    ram_bytes_used: children.iter().map(|c| c.ram_bytes_used).sum(),
+                    children,
+                }
+            })
+            .collect();
+        segments.sort_by(|a, b| a.name.cmp(&b.name));
+        segments
+    }
+}
+
+/// A `BitSetProducer` that caches the docs matching a query in a
+/// `BitSetFilterCache`, created with `BitSetFilterCache::bit_set_producer`.
+pub struct QueryBitSetProducer<C: Codec> {
+    key: String,
+    weight: Box<dyn Weight<C>>,
+    cache: Arc<BitSetFilterCache>,
+}
+
+impl<C: Codec> BitSetProducer<C> for QueryBitSetProducer<C> {
+    fn bit_set(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Arc<FixedBitSet>>> {
+        self.cache
+            .get_or_build(&self.key, self.weight.as_ref(), reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::index::reader::IndexReader;
+    use crate::core::index::tests::{MockIndexReader, MockLeafReader};
+    use crate::core::search::tests::create_mock_weight;
+    use crate::core::util::{Bits, ImmutableBitSet};
+
+    #[test]
+    fn test_bit_set_filter_cache() {
+        let index_reader = MockIndexReader::new(vec![MockLeafReader::new(10)]);
+        let leaves = index_reader.leaves();
+        let weight = create_mock_weight(vec![1, 3, 5]);
+        let cache = BitSetFilterCache::new();
+
+        let bits = cache.get_or_build("filter", &weight, &leaves[0]).unwrap();
+        let bits = bits.unwrap();
+        assert_eq!(bits.cardinality(), 3);
+        assert!(bits.get(3));
+        assert!(!bits.get(4));
+        assert_eq!(cache.miss_count(), 1);
+
+        let cached = cache.get_or_build("filter", &weight, &leaves[0]).unwrap();
+        assert!(Arc::ptr_eq(&bits, &cached.unwrap()));
+        assert_eq!(cache.hit_count(), 1);
+        assert_eq!(cache.size().unwrap(), 1);
+        assert_eq!(cache.ram_bytes_used(), 8);
+        let segments = cache.child_resources();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].children[0].name, "filter filter");
+        assert_eq!(segments[0].children[0].ram_bytes_used, 8);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.size().unwrap(), 0);
+        assert_eq!(cache.ram_bytes_used(), 0);
+    }
+}
@@ -22,3 +22,7 @@ pub use self::lru_cache::*;
 mod query_cache;
 
 pub use self::query_cache::*;
+
+mod bitset_cache;
+
+pub use self::bitset_cache::*;
@@ -15,25 +15,23 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::cache::{LRUCache, QueryCachingPolicy};
 use crate::core::search::collector::Collector;
 use crate::core::search::scorer::{BulkScorer, ConstantScoreScorer};
 use crate::core::search::Explanation;
+use crate::core::search::Matches;
 use crate::core::search::{
     query::Weight, scorer::two_phase_next, scorer::Scorer, DocIdSet, DocIterator, NO_MORE_DOCS,
 };
 use crate::core::util::external::Deferred;
-use crate::core::util::UnsignedShift;
-use crate::core::util::{
-    BitDocIdSet, BitSetDocIterator, DocIdSetDocIterEnum, DocIdSetEnum, NotDocIdSet,
-    ShortArrayDocIdSet,
-};
-use crate::core::util::{BitSet, FixedBitSet, ImmutableBitSet};
-use crate::core::util::{Bits, DocId};
+use crate::core::util::DocId;
+use crate::core::util::{Accountable, FixedBitSet, NamedAccountable};
+use crate::core::util::{AdaptiveDocIdSetBuilder, DocIdSetDocIterEnum, DocIdSetEnum};
 
 use crate::core::codec::Codec;
 use crate::error::Error;
@@ -74,9 +72,16 @@ impl<C: Codec> QueryCache<C> for NoCacheQueryCache {
     }
 }
 
+/// rough per entry overhead of the hash maps of the cache
+const HASHTABLE_RAM_BYTES_PER_ENTRY: usize = 2 * mem::size_of::<usize>() + 2 * 24;
+
+/// default memory budget of `LRUQueryCache`, 32MB.
+pub const DEFAULT_QUERY_CACHE_MAX_RAM_BYTES: usize = 32 * 1024 * 1024;
+
 struct LeafCache {
     _key: String,
-    leaf_cache: HashMap<String, CacheDocIdSetEnum>,
+    leaf_cache: HashMap<String, DocIdSetEnum>,
+    ram_bytes_used: usize,
 }
 
 impl LeafCache {
@@ -84,24 +89,39 @@ impl LeafCache {
         LeafCache {
             _key,
             leaf_cache: HashMap::new(),
+            ram_bytes_used: 0,
         }
     }
 
-    pub fn get(&self, query_key: &str) -> Result<Option<CachedDocIdSetIterEnum>> {
+    pub fn get(&self, query_key: &str) -> Result<Option<DocIdSetDocIterEnum>> {
         match self.leaf_cache.get(query_key) {
             Some(set) => set.iterator(),
             None => Ok(None),
         }
     }
 
-    pub fn put_if_absent(&mut self, query_key: &str, set: CacheDocIdSetEnum) {
+    /// returns the number of bytes added to the cache
+    pub fn put_if_absent(&mut self, query_key: &str, set: DocIdSetEnum) -> usize {
         if !self.leaf_cache.contains_key(query_key) {
+            let ram_bytes = set.ram_bytes_used() + HASHTABLE_RAM_BYTES_PER_ENTRY;
             self.leaf_cache.insert(query_key.to_string(), set);
+            self.ram_bytes_used += ram_bytes;
+            ram_bytes
+        } else {
+            0
         }
     }
 
-    pub fn remove(&mut self, query_key: &str) {
-        self.leaf_cache.remove(query_key);
+    /// returns the number of bytes released from the cache
+    pub fn remove(&mut self, query_key: &str) -> usize {
+        match self.leaf_cache.remove(query_key) {
+            Some(set) => {
+                let ram_bytes = set.ram_bytes_used() + HASHTABLE_RAM_BYTES_PER_ENTRY;
+                self.ram_bytes_used -= ram_bytes;
+                ram_bytes
+            }
+            None => 0,
+        }
     }
 }
 
@@ -116,6 +136,8 @@ struct CacheData {
     pub cache: HashMap<String, LeafCache>,
 
     max_size: usize,
+    max_ram_bytes_used: usize,
+    ram_bytes_used: usize,
     min_size: i32,
     min_size_ratio: f32,
 }
@@ -134,14 +156,15 @@ impl CacheData {
 
     /// Whether evictions are required.
     fn requires_eviction(&self) -> Result<bool> {
-        Ok(self.unique_queries.len() >= self.max_size)
+        Ok(self.unique_queries.len() >= self.max_size
+            || (!self.unique_queries.is_empty() && self.ram_bytes_used >= self.max_ram_bytes_used))
     }
 
     fn get<C: Codec>(
         &mut self,
         query_key: &str,
         leaf_reader: &LeafReaderContext<'_, C>,
-    ) -> Result<Option<CachedDocIdSetIterEnum>> {
+    ) -> Result<Option<DocIdSetDocIterEnum>> {
         if let Some(leaf_cache) = self.cache.get(leaf_reader.reader.core_cache_key()) {
             if let Some(singleton) = self.unique_queries.get(&query_key.to_string()) {
                 // this get call moves the query to the most-recently-used position
@@ -156,7 +179,7 @@ impl CacheData {
         &mut self,
         query_key: &str,
         leaf_reader: &LeafReaderContext<'_, C>,
-        set: CacheDocIdSetEnum,
+        set: DocIdSetEnum,
     ) -> Result<bool> {
         self.evict_if_necessary()?;
 
@@ -182,7 +205,7 @@ impl CacheData {
 
         {
             let leaf_cache = self.cache.get_mut(key).unwrap();
-            leaf_cache.put_if_absent(&query_key, set);
+            self.ram_bytes_used += leaf_cache.put_if_absent(&query_key, set);
         }
 
         Ok(new_entry)
@@ -212,7 +235,14 @@ impl CacheData {
 
     fn on_eviction(&mut self, query_key: &str) {
         for leaf_cache in self.cache.values_mut() {
-            leaf_cache.remove(query_key);
+            self.ram_bytes_used -= leaf_cache.remove(query_key);
+        }
+    }
+
+    /// called when the core reader of a segment is dropped.
+    fn clear_core_cache_key(&mut self, core_key: &str) {
+        if let Some(leaf_cache) = self.cache.remove(core_key) {
+            self.ram_bytes_used -= leaf_cache.ram_bytes_used;
         }
     }
 }
@@ -229,23 +259,114 @@ impl CacheData {
 /// segments, and it is advised to not share this cache across too many indices.
 pub struct LRUQueryCache {
     cache_data: Arc<RwLock<CacheData>>,
+    stats: Arc<QueryCacheStats>,
 }
 
 impl LRUQueryCache {
+    /// Create a cache that caches at most `max_size` queries and at most
+    /// `DEFAULT_QUERY_CACHE_MAX_RAM_BYTES` bytes of doc id sets.
     pub fn new(max_size: usize) -> LRUQueryCache {
-        // let max_size = 10;
+        Self::with_max_ram_bytes(max_size, DEFAULT_QUERY_CACHE_MAX_RAM_BYTES)
+    }
+
+    /// Create a cache that caches at most `max_size` queries and at most
+    /// `max_ram_bytes_used` bytes of doc id sets, the least recently used
+    /// queries are evicted once any of the limits is exceeded.
+    pub fn with_max_ram_bytes(max_size: usize, max_ram_bytes_used: usize) -> LRUQueryCache {
         let cache_data = CacheData {
             unique_queries: LRUCache::with_capacity(max_size),
             cache: HashMap::new(),
             max_size,
+            max_ram_bytes_used,
+            ram_bytes_used: 0,
             min_size: 10000,
             min_size_ratio: 0.03f32,
         };
 
         LRUQueryCache {
             cache_data: Arc::new(RwLock::new(cache_data)),
+            stats: Arc::new(QueryCacheStats::default()),
         }
     }
+
+    /// Only segments that have at least `min_size` docs and contain at least
+    /// `min_size_ratio` of the docs of the index are cached. Defaults are
+    /// 10000 and 0.03.
+    pub fn set_min_segment_size(&self, min_size: i32, min_size_ratio: f32) -> Result<()> {
+        let mut cache_data = self.cache_data.write()?;
+        cache_data.min_size = min_size;
+        cache_data.min_size_ratio = min_size_ratio;
+        Ok(())
+    }
+
+    /// Returns the number of queries that are currently cached.
+    pub fn cache_size(&self) -> Result<usize> {
+        Ok(self.cache_data.read()?.unique_queries.len())
+    }
+
+    pub fn stats(&self) -> &QueryCacheStats {
+        &self.stats
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        let mut cache_data = self.cache_data.write()?;
+        while cache_data.unique_queries.remove_last().is_some() {}
+        cache_data.cache.clear();
+        cache_data.ram_bytes_used = 0;
+        Ok(())
+    }
+}
+
+impl Accountable for LRUQueryCache {
+    /// Returns the estimated number of bytes used by the cached doc id sets.
+    fn ram_bytes_used(&self) -> usize {
+        let cache_data = self
+            .cache_data
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        cache_data.ram_bytes_used
+    }
+
+    /// The cached doc id sets of each segment core.
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        let cache_data = self
+            .cache_data
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut children: Vec<_> = cache_data
+            .cache
+            .iter()
+            .map(|(key, leaf_cache)| {
+                NamedAccountable::with_bytes(format!("segment {}", key), leaf_cache.ram_bytes_used)
+            })
+            .collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        children
+    }
+}
+
+/// Hit and miss counts of a `LRUQueryCache`, a miss is only counted for
+/// segments that are eligible for caching.
+#[derive(Default)]
+pub struct QueryCacheStats {
+    hit_count: AtomicU64,
+    miss_count: AtomicU64,
+    cache_count: AtomicU64,
+}
+
+impl QueryCacheStats {
+    pub fn hit_count(&self) -> u64 {
+        self.hit_count.load(Ordering::Relaxed)
+    }
+
+    pub fn miss_count(&self) -> u64 {
+        self.miss_count.load(Ordering::Relaxed)
+    }
+
+    /// number of doc id sets that have been added to the cache
+    pub fn cache_count(&self) -> u64 {
+        self.cache_count.load(Ordering::Relaxed)
+    }
 }
 
 impl<C: Codec> QueryCache<C> for LRUQueryCache {
@@ -259,6 +380,7 @@ impl<C: Codec> QueryCache<C> for LRUQueryCache {
         } else {
             Box::new(CachingWrapperWeight::new(
                 Arc::clone(&self.cache_data),
+                Arc::clone(&self.stats),
                 weight,
                 policy,
             ))
@@ -268,6 +390,7 @@ impl<C: Codec> QueryCache<C> for LRUQueryCache {
 
 struct CachingWrapperWeight<C: Codec> {
     cache_data: Arc<RwLock<CacheData>>,
+    stats: Arc<QueryCacheStats>,
     weight: Box<dyn Weight<C>>,
     policy: Arc<dyn QueryCachingPolicy<C>>,
     used: AtomicBool,
@@ -278,6 +401,7 @@ struct CachingWrapperWeight<C: Codec> {
 impl<C: Codec> CachingWrapperWeight<C> {
     fn new(
         cache_data: Arc<RwLock<CacheData>>,
+        stats: Arc<QueryCacheStats>,
         weight: Box<dyn Weight<C>>,
         policy: Arc<dyn QueryCachingPolicy<C>>,
     ) -> CachingWrapperWeight<C> {
@@ -286,6 +410,7 @@ impl<C: Codec> CachingWrapperWeight<C> {
         query_key.hash(&mut hasher);
         CachingWrapperWeight {
             cache_data,
+            stats,
             weight,
             policy,
             used: AtomicBool::new(false),
@@ -294,70 +419,19 @@ impl<C: Codec> CachingWrapperWeight<C> {
         }
     }
 
-    ///
-    // Default cache implementation: uses {@link RoaringDocIdSet} for sets that
-    // have a density &lt; 1% and a {@link BitDocIdSet} over a {@link FixedBitSet}
-    // otherwise.
-    //
+    // Caches the docs of `scorer` in the most compact of a sorted array, a
+    // `RoaringDocIdSet` and a `FixedBitSet`, see `AdaptiveDocIdSetBuilder`.
     fn cache_impl<'a, S>(
         &self,
         scorer: &mut BulkScorer<'a, S>,
         max_doc: i32,
-    ) -> Result<CacheDocIdSetEnum>
-    where
-        S: Scorer + ?Sized + 'a,
-    {
-        // FixedBitSet is faster for dense sets and will enable the random-access
-        // optimization in ConjunctionDISI
-        if scorer.scorer().cost() * 100 > max_doc as usize {
-            Ok(CacheDocIdSetEnum::Bit(
-                self.cache_into_bitset(scorer, max_doc)?,
-            ))
-        } else {
-            Ok(CacheDocIdSetEnum::Roaring(
-                self.cache_into_roaring_docid_set(scorer, max_doc)?,
-            ))
-        }
-    }
-
-    fn cache_into_bitset<'a, S>(
-        &self,
-        scorer: &mut BulkScorer<'a, S>,
-        max_doc: i32,
-    ) -> Result<BitDocIdSet<FixedBitSet>>
-    where
-        S: Scorer + ?Sized + 'a,
-    {
-        let mut leaf_collector = BitSetLeafCollector {
-            bit_set: FixedBitSet::new(max_doc as usize),
-            cost: 0,
-        };
-
-        let bits: Option<FixedBitSet> = None;
-        scorer.score(
-            &mut leaf_collector,
-            bits.as_ref(),
-            0,
-            NO_MORE_DOCS,
-            NO_MORE_DOCS as usize,
-        )?;
-
-        Ok(BitDocIdSet::new(
-            Arc::new(leaf_collector.bit_set),
-            leaf_collector.cost as usize,
-        ))
-    }
-
-    fn cache_into_roaring_docid_set<'a, S>(
-        &self,
-        scorer: &mut BulkScorer<S>,
-        max_doc: i32,
-    ) -> Result<RoaringDocIdSet>
+    ) -> Result<DocIdSetEnum>
     where
         S: Scorer + ?Sized + 'a,
     {
+        let cost = scorer.scorer().cost();
         let mut leaf_collector = DocIdSetLeafCollector {
-            doc_id_set: RoaringDocIdSetBuilder::new(max_doc),
+            doc_id_set: AdaptiveDocIdSetBuilder::with_cost(max_doc, cost),
         };
 
         let bits: Option<FixedBitSet> = None;
@@ -380,7 +454,7 @@ impl<C: Codec> CachingWrapperWeight<C> {
         &self,
         leaf_reader: &LeafReaderContext<'_, C>,
         query_key: &str,
-    ) -> Result<Option<CachedDocIdSetIterEnum>> {
+    ) -> Result<Option<DocIdSetDocIterEnum>> {
         match self.weight.create_scorer(leaf_reader)? {
             Some(mut scorer) => {
                 let mut bulk_scorer = BulkScorer::new(scorer.as_mut());
@@ -389,6 +463,7 @@ impl<C: Codec> CachingWrapperWeight<C> {
                 let doc_id_set = self.cache_impl(&mut bulk_scorer, max_doc)?;
 
                 let iter = doc_id_set.iterator()?;
+                self.stats.cache_count.fetch_add(1, Ordering::Relaxed);
                 if self
                     .cache_data
                     .write()?
@@ -400,7 +475,7 @@ impl<C: Codec> CachingWrapperWeight<C> {
                         .reader
                         .add_core_drop_listener(Deferred::new(move || {
                             let core_key = key;
-                            cache_data.write().unwrap().cache.remove(&core_key);
+                            cache_data.write().unwrap().clear_core_cache_key(&core_key);
                         }))
                 }
 
@@ -433,9 +508,11 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
             match self.cache_data.try_write() {
                 Ok(mut cache_data) => {
                     if let Some(disi) = cache_data.get(&self.query_key, leaf_reader)? {
+                        self.stats.hit_count.fetch_add(1, Ordering::Relaxed);
                         let cost = disi.cost();
                         return Ok(Some(Box::new(ConstantScoreScorer::new(0.0f32, disi, cost))));
                     }
+                    self.stats.miss_count.fetch_add(1, Ordering::Relaxed);
                 }
                 _ => {
                     return self.weight.create_scorer(leaf_reader);
@@ -510,6 +587,10 @@ impl<C: Codec> Weight<C> for CachingWrapperWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for CachingWrapperWeight<C> {
@@ -518,26 +599,8 @@ impl<C: Codec> fmt::Display for CachingWrapperWeight<C> {
     }
 }
 
-struct BitSetLeafCollector {
-    bit_set: FixedBitSet,
-    cost: i64,
-}
-
-impl Collector for BitSetLeafCollector {
-    fn needs_scores(&self) -> bool {
-        false
-    }
-
-    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
-        self.cost += 1;
-        self.bit_set.set(doc as usize);
-
-        Ok(())
-    }
-}
-
 struct DocIdSetLeafCollector {
-    doc_id_set: RoaringDocIdSetBuilder,
+    doc_id_set: AdaptiveDocIdSetBuilder,
 }
 
 impl Collector for DocIdSetLeafCollector {
@@ -550,397 +613,123 @@ impl Collector for DocIdSetLeafCollector {
     }
 }
 
-// Number of documents in a block
-static BLOCK_SIZE: usize = 1 << 16;
-// The maximum length for an array, beyond that point we switch to a bitset
-static MAX_ARRAY_LENGTH: usize = 1 << 12;
-
-///
-// {@link DocIdSet} implementation inspired from http://roaringbitmap.org/
-//
-// The space is divided into blocks of 2^16 bits and each block is encoded
-// independently. In each block, if less than 2^12 bits are set, then
-// documents are simply stored in a short[]. If more than 2^16-2^12 bits are
-// set, then the inverse of the set is encoded in a simple short[]. Otherwise
-// a {@link FixedBitSet} is used.
-//
-// @lucene.internal
-//
-struct RoaringDocIdSet {
-    doc_id_sets: Arc<[Option<DocIdSetEnum>]>,
-    cardinality: usize,
-}
-
-impl RoaringDocIdSet {
-    fn new(doc_id_sets: Vec<Option<DocIdSetEnum>>, cardinality: usize) -> RoaringDocIdSet {
-        RoaringDocIdSet {
-            doc_id_sets: Arc::from(doc_id_sets.into_boxed_slice()),
-            cardinality,
-        }
-    }
-}
-
-struct RoaringDocIdSetBuilder {
-    doc_id_sets: Vec<Option<DocIdSetEnum>>,
-    cardinality: usize,
-
-    max_doc: i32,
-    last_doc_id: DocId,
-    current_block: i32,
-    current_block_cardinality: usize,
-
-    // We start by filling the buffer and when it's full we copy the content of
-    // the buffer to the FixedBitSet and put further documents in that bitset
-    buffer: Vec<u16>,
-    dense_buffer: Option<Box<FixedBitSet>>,
-}
-
-impl RoaringDocIdSetBuilder {
-    fn new(max_doc: i32) -> RoaringDocIdSetBuilder {
-        let length = (max_doc + (1 << 16) - 1).unsigned_shift(16);
-        let mut doc_id_sets = Vec::with_capacity(length as usize);
-        for _ in 0..length {
-            doc_id_sets.push(None);
-        }
-
-        RoaringDocIdSetBuilder {
-            doc_id_sets,
-            cardinality: 0,
-            max_doc,
-            last_doc_id: -1,
-            current_block: -1,
-            current_block_cardinality: 0,
-            buffer: vec![0u16; MAX_ARRAY_LENGTH as usize],
-            dense_buffer: None,
-        }
-    }
-
-    fn flush(&mut self) {
-        assert!(self.current_block_cardinality <= BLOCK_SIZE);
-
-        let current_block = self.current_block;
-        let current_block_cardinality = self.current_block_cardinality;
-
-        if current_block_cardinality <= MAX_ARRAY_LENGTH {
-            // Use sparse encoding
-            assert!(self.dense_buffer.is_none());
-            if current_block_cardinality > 0 {
-                let mut docs: Vec<u16> = vec![0u16; current_block_cardinality];
-                docs.copy_from_slice(&self.buffer[0..current_block_cardinality]);
-
-                self.doc_id_sets[current_block as usize] = Some(DocIdSetEnum::ShortArray(
-                    ShortArrayDocIdSet::new(docs, current_block_cardinality),
-                ));
-            }
-        } else {
-            assert!(self.dense_buffer.is_some());
-            assert_eq!(
-                self.dense_buffer.as_mut().unwrap().cardinality(),
-                self.current_block_cardinality
-            );
-
-            if self.dense_buffer.as_mut().unwrap().len() == BLOCK_SIZE as usize
-                && BLOCK_SIZE - self.current_block_cardinality < MAX_ARRAY_LENGTH
-            {
-                let dense_buffer = self.dense_buffer.as_mut().unwrap();
-                // Doc ids are very dense, inverse the encoding
-                let mut exclude_docs =
-                    vec![0u16; (BLOCK_SIZE - self.current_block_cardinality) as usize];
-                let num_bits = dense_buffer.num_bits;
-                dense_buffer.flip(0, num_bits);
-
-                let mut exclude_doc = -1;
-                unsafe {
-                    let ptr = exclude_docs.as_mut_ptr();
-                    for i in 0..exclude_docs.len() {
-                        exclude_doc = dense_buffer.next_set_bit((exclude_doc + 1) as usize);
-                        debug_assert_ne!(exclude_doc, NO_MORE_DOCS);
-                        *ptr.add(i) = exclude_doc as u16;
-                    }
-                }
-
-                assert!(
-                    exclude_doc as usize + 1 == dense_buffer.len()
-                        || dense_buffer.next_set_bit((exclude_doc + 1) as usize) == NO_MORE_DOCS
-                );
-
-                let length = exclude_docs.len();
-                self.doc_id_sets[self.current_block as usize] =
-                    Some(DocIdSetEnum::NotDocId(NotDocIdSet::new(
-                        ShortArrayDocIdSet::new(exclude_docs, length),
-                        BLOCK_SIZE as i32,
-                    )));
-            } else {
-                // Neither sparse nor super dense, use a fixed bit set
-                let dense_buf = self.dense_buffer.take().unwrap();
-                self.doc_id_sets[self.current_block as usize] =
-                    Some(DocIdSetEnum::BitDocId(BitDocIdSet::new(
-                        Arc::from(dense_buf),
-                        self.current_block_cardinality as usize,
-                    )));
-            }
-        }
-
-        self.cardinality += self.current_block_cardinality;
-        self.dense_buffer = None;
-        self.current_block_cardinality = 0;
-    }
-
-    ///
-    // Add a new doc-id to this builder.
-    // NOTE: doc ids must be added in order.
-    //
-    pub fn add_doc(&mut self, doc_id: i32) -> Result<()> {
-        if doc_id < self.last_doc_id {
-            return Err(Error::RuntimeError(format!(
-                "Doc ids must be added in-order, got {} which is <= lastDocID={}",
-                doc_id, self.last_doc_id
-            )));
-        }
-
-        let block = doc_id.unsigned_shift(16);
-        if block != self.current_block {
-            // we went to a different block, let's flush what we buffered and start from fresh
-            self.flush();
-            self.current_block = block;
-        }
-
-        if self.current_block_cardinality < MAX_ARRAY_LENGTH {
-            unsafe {
-                *self.buffer.as_mut_ptr().add(self.current_block_cardinality) = doc_id as u16
-            };
-        } else {
-            if self.dense_buffer.is_none() {
-                // the buffer is full, let's move to a fixed bit set
-                let num_bits = (1i32 << 16).min(self.max_doc - (block << 16));
-                let mut fixed_bit_set = Box::new(FixedBitSet::new(num_bits as usize));
-                for doc in &self.buffer {
-                    fixed_bit_set.set(*doc as usize);
-                }
-
-                self.dense_buffer = Some(fixed_bit_set);
-            }
-
-            self.dense_buffer
-                .as_mut()
-                .unwrap()
-                .set((doc_id & 0xFFFF) as usize);
-        }
-
-        self.last_doc_id = doc_id;
-        self.current_block_cardinality += 1;
-
-        Ok(())
-    }
-
-    pub fn build(mut self) -> RoaringDocIdSet {
-        self.flush();
-        RoaringDocIdSet::new(self.doc_id_sets, self.cardinality)
-    }
-}
-
-impl DocIdSet for RoaringDocIdSet {
-    type Iter = RoaringDocIterator;
-    fn iterator(&self) -> Result<Option<Self::Iter>> {
-        if self.cardinality == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(RoaringDocIterator::new(
-                self.doc_id_sets.clone(),
-                self.cardinality,
-            )))
-        }
-    }
-}
-
-struct RoaringDocIterator {
-    doc_id_sets: Arc<[Option<DocIdSetEnum>]>,
-    doc: DocId,
-    block: i32,
-    cardinality: usize,
-    sub: Option<DocIdSetDocIterEnum>,
-}
-
-impl RoaringDocIterator {
-    fn new(doc_id_sets: Arc<[Option<DocIdSetEnum>]>, cardinality: usize) -> Self {
-        RoaringDocIterator {
-            doc_id_sets,
-            doc: -1,
-            block: -1,
-            cardinality,
-            // init as stub
-            sub: Some(DocIdSetDocIterEnum::default()),
-        }
-    }
-
-    fn first_doc_from_next_block(&mut self) -> Result<DocId> {
-        loop {
-            self.block += 1;
-            if self.block as usize >= self.doc_id_sets.len() {
-                self.sub = None;
-                self.doc = NO_MORE_DOCS;
-
-                return Ok(self.doc);
-            } else if self.doc_id_sets[self.block as usize].is_some() {
-                self.sub = self.doc_id_sets[self.block as usize]
-                    .as_ref()
-                    .unwrap()
-                    .iterator()?;
-                let sub_next = self.sub.as_mut().unwrap().next()?;
-                debug_assert_ne!(sub_next, NO_MORE_DOCS);
-
-                self.doc = (self.block << 16) | sub_next;
-                return Ok(self.doc);
-            }
-        }
-    }
-}
-
-impl DocIterator for RoaringDocIterator {
-    fn doc_id(&self) -> DocId {
-        self.doc
-    }
-
-    fn next(&mut self) -> Result<DocId> {
-        let sub_next = self.sub.as_mut().unwrap().next()?;
-        if sub_next == NO_MORE_DOCS {
-            return self.first_doc_from_next_block();
-        }
-
-        self.doc = (self.block << 16) | sub_next;
-        Ok(self.doc)
-    }
-
-    fn advance(&mut self, target: DocId) -> Result<DocId> {
-        let target_block = target.unsigned_shift(16);
-
-        if target_block != self.block {
-            self.block = target_block;
-            if self.block as usize > self.doc_id_sets.len() {
-                self.sub = None;
-                self.doc = NO_MORE_DOCS;
-
-                return Ok(self.doc);
-            }
-
-            if self.doc_id_sets[self.block as usize].is_none() {
-                return self.first_doc_from_next_block();
-            }
-
-            self.sub = self.doc_id_sets[self.block as usize]
-                .as_ref()
-                .unwrap()
-                .iterator()?;
-        }
-
-        let sub_next = self.sub.as_mut().unwrap().advance(target & 0xFFFF)?;
-        if sub_next == NO_MORE_DOCS {
-            return self.first_doc_from_next_block();
-        }
-
-        self.doc = (self.block << 16) | sub_next;
-        Ok(self.doc)
-    }
-
-    fn cost(&self) -> usize {
-        self.cardinality as usize
-    }
-}
-
-enum CacheDocIdSetEnum {
-    Bit(BitDocIdSet<FixedBitSet>),
-    Roaring(RoaringDocIdSet),
-}
-
-impl DocIdSet for CacheDocIdSetEnum {
-    type Iter = CachedDocIdSetIterEnum;
-
-    fn iterator(&self) -> Result<Option<Self::Iter>> {
-        match self {
-            CacheDocIdSetEnum::Bit(i) => {
-                if let Some(iter) = i.iterator()? {
-                    Ok(Some(CachedDocIdSetIterEnum::Bit(iter)))
-                } else {
-                    Ok(None)
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::CodecEnum;
+    use crate::core::doc::StringField;
+    use crate::core::index::reader::IndexReader;
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::index::Term;
+    use crate::core::search::cache::AlwaysCacheQueryCachingPolicy;
+    use crate::core::search::query::TermQuery;
+    use crate::core::search::{DefaultIndexSearcher, IndexSearcher, ScoreMode};
+
+    // one segment of 30 docs, tagged "even" or "odd", and "three" for the multiples of 3
+    fn searcher_with_cache(cache: &Arc<LRUQueryCache>) -> impl IndexSearcher<CodecEnum> {
+        let writer = in_memory_writer();
+        for i in 0..30 {
+            let mut doc = vec![StringField::new(
+                "tag",
+                if i % 2 == 0 { "even" } else { "odd" },
+                false,
+            )];
+            if i % 3 == 0 {
+                doc.push(StringField::new("tag", "three", false));
             }
-            CacheDocIdSetEnum::Roaring(i) => {
-                if let Some(iter) = i.iterator()? {
-                    Ok(Some(CachedDocIdSetIterEnum::Roaring(iter)))
-                } else {
-                    Ok(None)
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        let mut searcher =
+            DefaultIndexSearcher::new(Arc::new(writer.get_reader(true, false).unwrap()), None);
+        searcher.set_query_cache(Arc::clone(cache) as Arc<dyn QueryCache<CodecEnum>>);
+        searcher.set_query_cache_policy(Arc::new(AlwaysCacheQueryCachingPolicy::default()));
+        searcher
+    }
+
+    // counts the docs of the non-scoring, thus cacheable, weight of a term query
+    fn count<S: IndexSearcher<CodecEnum>>(searcher: &S, tag: &str) -> i32 {
+        let query = TermQuery::new(Term::new("tag".into(), tag.as_bytes().to_vec()), 1.0, None);
+        let weight = searcher
+            .create_weight(&query, ScoreMode::CompleteNoScores)
+            .unwrap();
+        let mut count = 0;
+        for leaf in searcher.reader().leaves() {
+            if let Some(mut scorer) = weight.create_scorer(&leaf).unwrap() {
+                while scorer.next().unwrap() != NO_MORE_DOCS {
+                    count += 1;
                 }
             }
         }
-    }
-}
-
-enum CachedDocIdSetIterEnum {
-    Bit(BitSetDocIterator<FixedBitSet>),
-    Roaring(RoaringDocIterator),
-}
-
-impl DocIterator for CachedDocIdSetIterEnum {
-    fn doc_id(&self) -> DocId {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.doc_id(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.doc_id(),
-        }
-    }
-
-    fn next(&mut self) -> Result<DocId> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.next(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.next(),
-        }
-    }
-
-    fn advance(&mut self, target: i32) -> Result<DocId> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.advance(target),
-            CachedDocIdSetIterEnum::Roaring(i) => i.advance(target),
-        }
-    }
-
-    fn slow_advance(&mut self, target: i32) -> Result<DocId> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.slow_advance(target),
-            CachedDocIdSetIterEnum::Roaring(i) => i.slow_advance(target),
-        }
-    }
-
-    fn cost(&self) -> usize {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.cost(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.cost(),
-        }
-    }
-
-    fn matches(&mut self) -> Result<bool> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.matches(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.matches(),
-        }
-    }
-
-    fn match_cost(&self) -> f32 {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.match_cost(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.match_cost(),
-        }
-    }
-
-    fn approximate_next(&mut self) -> Result<DocId> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.approximate_next(),
-            CachedDocIdSetIterEnum::Roaring(i) => i.approximate_next(),
-        }
-    }
-
-    fn approximate_advance(&mut self, target: i32) -> Result<DocId> {
-        match self {
-            CachedDocIdSetIterEnum::Bit(i) => i.approximate_advance(target),
-            CachedDocIdSetIterEnum::Roaring(i) => i.approximate_advance(target),
-        }
+        count
+    }
+
+    #[test]
+    fn test_min_segment_size() {
+        let cache = Arc::new(LRUQueryCache::new(10));
+        let searcher = searcher_with_cache(&cache);
+        // the segment is below the default min size of 10000 docs
+        assert_eq!(count(&searcher, "even"), 15);
+        assert_eq!(cache.cache_size().unwrap(), 0);
+        assert_eq!(cache.stats().miss_count(), 0);
+
+        cache.set_min_segment_size(30, 0.5).unwrap();
+        assert_eq!(count(&searcher, "even"), 15);
+        assert_eq!(cache.cache_size().unwrap(), 1);
+
+        cache.clear().unwrap();
+        cache.set_min_segment_size(31, 0.0).unwrap();
+        assert_eq!(count(&searcher, "even"), 15);
+        assert_eq!(cache.cache_size().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_cache_stats_and_clear() {
+        let cache = Arc::new(LRUQueryCache::new(10));
+        cache.set_min_segment_size(0, 0.0).unwrap();
+        let searcher = searcher_with_cache(&cache);
+
+        assert_eq!(count(&searcher, "odd"), 15);
+        assert_eq!(cache.stats().miss_count(), 1);
+        assert_eq!(cache.stats().hit_count(), 0);
+        assert_eq!(cache.stats().cache_count(), 1);
+        assert_eq!(count(&searcher, "odd"), 15);
+        assert_eq!(count(&searcher, "three"), 10);
+        assert_eq!(cache.stats().miss_count(), 2);
+        assert_eq!(cache.stats().hit_count(), 1);
+        assert_eq!(cache.cache_size().unwrap(), 2);
+        assert!(cache.ram_bytes_used() > 0);
+        assert_eq!(cache.child_resources().len(), 1);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.cache_size().unwrap(), 0);
+        assert_eq!(cache.ram_bytes_used(), 0);
+        assert_eq!(count(&searcher, "odd"), 15);
+        assert_eq!(cache.stats().miss_count(), 3);
+        assert_eq!(cache.stats().hit_count(), 1);
+    }
+
+    #[test]
+    fn test_ram_bounded_eviction() {
+        let cache = Arc::new(LRUQueryCache::new(10));
+        cache.set_min_segment_size(0, 0.0).unwrap();
+        let searcher = searcher_with_cache(&cache);
+        assert_eq!(count(&searcher, "even"), 15);
+        let entry_bytes = cache.ram_bytes_used();
+        assert!(entry_bytes > 0);
+
+        // room for a single query, the least recently used one is evicted
+        let cache = Arc::new(LRUQueryCache::with_max_ram_bytes(10, entry_bytes));
+        cache.set_min_segment_size(0, 0.0).unwrap();
+        let searcher = searcher_with_cache(&cache);
+        assert_eq!(count(&searcher, "even"), 15);
+        assert_eq!(count(&searcher, "odd"), 15);
+        assert_eq!(cache.cache_size().unwrap(), 1);
+        assert!(cache.ram_bytes_used() <= entry_bytes);
+        assert_eq!(count(&searcher, "odd"), 15);
+        assert_eq!(cache.stats().hit_count(), 1);
+        assert_eq!(count(&searcher, "even"), 15);
+        assert_eq!(cache.stats().hit_count(), 1);
+        assert_eq!(cache.stats().miss_count(), 3);
     }
 }
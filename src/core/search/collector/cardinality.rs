@@ -0,0 +1,274 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::core::codec::doc_values::{
+    BinaryDocValues, SortedDocValues, SortedNumericDocValues, SortedSetDocValues, NO_MORE_ORDS,
+};
+use crate::core::codec::Codec;
+use crate::core::doc::DocValuesType;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::collector::{
+    numeric_leaf_values, Collector, NumericLeafValues, ParallelLeafCollector, SearchCollector,
+};
+use crate::core::search::scorer::Scorer;
+use crate::core::util::{
+    mix64, murmur3_hash64, BitSet, BitSetIterator, BitsMut, DocId, FixedBitSet,
+    HyperLogLogPlusPlus, LongBitSet,
+};
+use crate::error::{Error::IllegalState, Result};
+
+/// the doc values of a segment, sorted and sorted set values are collected
+/// as ords, and only the distinct ords are hashed once the leaf is finished.
+/// Sorted set ords are `i64`s, so they are tracked by a `LongBitSet`.
+enum CardinalityLeafValues {
+    Numeric(NumericLeafValues),
+    SortedNumeric(Box<dyn SortedNumericDocValues>),
+    Binary(Box<dyn BinaryDocValues>, Box<dyn BitsMut>),
+    Sorted(Box<dyn SortedDocValues>, FixedBitSet),
+    SortedSet(Box<dyn SortedSetDocValues>, LongBitSet),
+}
+
+impl CardinalityLeafValues {
+    fn new<C: Codec>(reader: &LeafReaderContext<'_, C>, field: &str) -> Result<Option<Self>> {
+        let dv_type = match reader.reader.field_info(field) {
+            Some(fi) => fi.doc_values_type,
+            None => return Ok(None),
+        };
+        let values = match dv_type {
+            DocValuesType::Numeric => {
+                numeric_leaf_values(reader, field)?.map(CardinalityLeafValues::Numeric)
+            }
+            DocValuesType::SortedNumeric => Some(CardinalityLeafValues::SortedNumeric(
+                reader.reader.get_sorted_numeric_doc_values(field)?,
+            )),
+            DocValuesType::Binary => Some(CardinalityLeafValues::Binary(
+                reader.reader.get_binary_doc_values(field)?,
+                reader.reader.get_docs_with_field(field)?,
+            )),
+            DocValuesType::Sorted => {
+                let values = reader.reader.get_sorted_doc_values(field)?;
+                let ords = FixedBitSet::new(values.value_count());
+                Some(CardinalityLeafValues::Sorted(values, ords))
+            }
+            DocValuesType::SortedSet => {
+                let values = reader.reader.get_sorted_set_doc_values(field)?;
+                let ords = LongBitSet::new(values.get_value_count() as i64);
+                Some(CardinalityLeafValues::SortedSet(values, ords))
+            }
+            _ => None,
+        };
+        Ok(values)
+    }
+
+    fn collect(&mut self, doc: DocId, hll: &mut HyperLogLogPlusPlus) -> Result<()> {
+        match self {
+            CardinalityLeafValues::Numeric(values) => {
+                if let Some(v) = values.get(doc)? {
+                    hll.add_hash(mix64(v as u64));
+                }
+            }
+            CardinalityLeafValues::SortedNumeric(values) => {
+                values.set_document(doc)?;
+                for i in 0..values.count() {
+                    hll.add_hash(mix64(values.value_at(i)? as u64));
+                }
+            }
+            CardinalityLeafValues::Binary(values, docs_with_field) => {
+                if docs_with_field.get(doc as usize) {
+                    hll.add_hash(murmur3_hash64(&values.get(doc)?, 0));
+                }
+            }
+            CardinalityLeafValues::Sorted(values, ords) => {
+                let ord = values.get_ord(doc)?;
+                if ord >= 0 {
+                    ords.set(ord as usize);
+                }
+            }
+            CardinalityLeafValues::SortedSet(values, ords) => {
+                values.set_document(doc)?;
+                loop {
+                    let ord = values.next_ord()?;
+                    if ord == NO_MORE_ORDS {
+                        break;
+                    }
+                    ords.set(ord);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// hash the values of the collected ords.
+    fn finish(&mut self, hll: &mut HyperLogLogPlusPlus) -> Result<()> {
+        match self {
+            CardinalityLeafValues::Sorted(values, ords) => {
+                for ord in BitSetIterator::new(&*ords) {
+                    hll.add_hash(murmur3_hash64(&values.lookup_ord(ord)?, 0));
+                }
+                ords.clear_all();
+            }
+            CardinalityLeafValues::SortedSet(values, ords) => {
+                let mut ord = -1;
+                while ord + 1 < ords.len() {
+                    ord = ords.next_set_bit(ord + 1);
+                    if ord < 0 {
+                        break;
+                    }
+                    hll.add_hash(murmur3_hash64(&values.lookup_ord(ord)?, 0));
+                }
+                ords.clear_all();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A `Collector` that approximately counts the distinct values of a doc values
+/// field over the matching docs, using the HyperLogLog++ algorithm.
+///
+/// Counts are exact as long as the number of distinct values is small compared
+/// to the precision, higher precisions give a better accuracy at the cost of
+/// `2^precision` bytes of memory. Numeric, sorted numeric, binary, sorted and
+/// sorted set doc values are supported.
+pub struct CardinalityCollector {
+    field: String,
+    hll: HyperLogLogPlusPlus,
+    leaf_values: Option<CardinalityLeafValues>,
+    channel: Option<(Sender<HyperLogLogPlusPlus>, Receiver<HyperLogLogPlusPlus>)>,
+}
+
+impl CardinalityCollector {
+    pub fn new(field: &str) -> CardinalityCollector {
+        CardinalityCollector {
+            field: field.to_string(),
+            hll: HyperLogLogPlusPlus::default(),
+            leaf_values: None,
+            channel: None,
+        }
+    }
+
+    /// create a collector with the given precision, which must be in
+    /// `[HLL_MIN_PRECISION, HLL_MAX_PRECISION]`.
+    pub fn with_precision(field: &str, precision: u8) -> Result<CardinalityCollector> {
+        Ok(CardinalityCollector {
+            field: field.to_string(),
+            hll: HyperLogLogPlusPlus::new(precision)?,
+            leaf_values: None,
+            channel: None,
+        })
+    }
+
+    /// Returns the estimated number of distinct values, this will
+    /// finish the collection of the current leaf.
+    pub fn cardinality(&mut self) -> Result<u64> {
+        self.finish_current_leaf()?;
+        Ok(self.hll.cardinality())
+    }
+
+    fn finish_current_leaf(&mut self) -> Result<()> {
+        if let Some(mut values) = self.leaf_values.take() {
+            values.finish(&mut self.hll)?;
+        }
+        Ok(())
+    }
+}
+
+impl SearchCollector for CardinalityCollector {
+    type LC = CardinalityLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.finish_current_leaf()?;
+        self.leaf_values = CardinalityLeafValues::new(reader, &self.field)?;
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<CardinalityLeafCollector> {
+        Ok(CardinalityLeafCollector {
+            hll: HyperLogLogPlusPlus::new(self.hll.precision())?,
+            leaf_values: CardinalityLeafValues::new(reader, &self.field)?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(hll) = receiver.recv() {
+                self.hll.merge(hll)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for CardinalityCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        if let Some(values) = self.leaf_values.as_mut() {
+            values.collect(doc, &mut self.hll)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct CardinalityLeafCollector {
+    hll: HyperLogLogPlusPlus,
+    leaf_values: Option<CardinalityLeafValues>,
+    channel: Sender<HyperLogLogPlusPlus>,
+}
+
+impl ParallelLeafCollector for CardinalityLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        if let Some(mut values) = self.leaf_values.take() {
+            values.finish(&mut self.hll)?;
+        }
+        let precision = self.hll.precision();
+        let hll = mem::replace(&mut self.hll, HyperLogLogPlusPlus::new(precision)?);
+        self.channel
+            .send(hll)
+            .map_err(|_e| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for CardinalityLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        if let Some(values) = self.leaf_values.as_mut() {
+            values.collect(doc, &mut self.hll)?;
+        }
+        Ok(())
+    }
+}
@@ -14,8 +14,9 @@
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
-use crate::core::search::scorer::Scorer;
-use crate::core::util::DocId;
+use crate::core::search::scorer::{FeatureResult, Scorer};
+use crate::core::search::{DocIterator, ScoreMode};
+use crate::core::util::{DocId, IndexedContext};
 use crate::Result;
 
 /// ChainCollector makes it possible to collect on more than one collector in sequence.
@@ -78,9 +79,21 @@ where
         self.first.needs_scores() || self.second.needs_scores()
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        self.first.score_mode().merge(self.second.score_mode())
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
-        self.first.collect(doc, scorer)?;
-        self.second.collect(doc, scorer)
+        if self.score_mode().is_exhaustive() {
+            // one of the collectors needs all the hits, the other one must
+            // not let the scorer skip any
+            let mut scorer = NoPruningScorer { scorer };
+            self.first.collect(doc, &mut scorer)?;
+            self.second.collect(doc, &mut scorer)
+        } else {
+            self.first.collect(doc, scorer)?;
+            self.second.collect(doc, scorer)
+        }
     }
 }
 
@@ -95,3 +108,48 @@ where
         self.first.finish_leaf()
     }
 }
+
+// Ignores the minimum competitive scores set by the collectors.
+struct NoPruningScorer<'a, S: Scorer + ?Sized> {
+    scorer: &'a mut S,
+}
+
+impl<'a, S: Scorer + ?Sized> Scorer for NoPruningScorer<'a, S> {
+    fn score(&mut self) -> Result<f32> {
+        self.scorer.score()
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        self.scorer.score_feature()
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.scorer.max_score(up_to)
+    }
+}
+
+impl<'a, S: Scorer + ?Sized> DocIterator for NoPruningScorer<'a, S> {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        unreachable!("collectors must not move the scorer")
+    }
+
+    fn advance(&mut self, _target: DocId) -> Result<DocId> {
+        unreachable!("collectors must not move the scorer")
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+}
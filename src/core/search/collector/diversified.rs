@@ -0,0 +1,589 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::core::codec::doc_values::SortedDocValues;
+use crate::core::codec::Codec;
+use crate::core::doc::DocValuesType;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::collector::{
+    numeric_leaf_values, Collector, NumericLeafValues, ParallelLeafCollector, SearchCollector,
+};
+use crate::core::search::scorer::Scorer;
+use crate::core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs};
+use crate::core::util::{mix64, DocId};
+use crate::error::{
+    Error::{IllegalArgument, IllegalState},
+    Result,
+};
+
+/// The value used to diversify the hits, e.g. the author of a document.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiversityKey {
+    Long(i64),
+    Bytes(Vec<u8>),
+}
+
+/// the diversity keys of a segment, read from numeric or sorted doc values.
+enum LeafKeys {
+    Numeric(NumericLeafValues),
+    Sorted(Box<dyn SortedDocValues>),
+}
+
+impl LeafKeys {
+    fn new<C: Codec>(reader: &LeafReaderContext<'_, C>, field: &str) -> Result<Option<Self>> {
+        match reader.reader.field_info(field).map(|fi| fi.doc_values_type) {
+            Some(DocValuesType::Numeric) => {
+                Ok(numeric_leaf_values(reader, field)?.map(LeafKeys::Numeric))
+            }
+            Some(DocValuesType::Sorted) => Ok(Some(LeafKeys::Sorted(
+                reader.reader.get_sorted_doc_values(field)?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    fn key(&mut self, doc: DocId) -> Result<Option<DiversityKey>> {
+        match self {
+            LeafKeys::Numeric(values) => Ok(values.get(doc)?.map(DiversityKey::Long)),
+            LeafKeys::Sorted(values) => {
+                let ord = values.get_ord(doc)?;
+                if ord < 0 {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiversityKey::Bytes(values.lookup_ord(ord)?)))
+                }
+            }
+        }
+    }
+}
+
+/// A hit ordered from the least to the most competitive, ties on the score
+/// are won by the smaller doc id.
+#[derive(Clone, Copy, Debug)]
+struct Hit {
+    doc: DocId,
+    score: f32,
+}
+
+impl Ord for Hit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.doc.cmp(&self.doc))
+    }
+}
+
+impl PartialOrd for Hit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Hit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hit {}
+
+// hits without a diversity key are not limited
+const NO_KEY: usize = usize::MAX;
+
+/// The top `size` hits with at most `max_hits_per_key` hits per key.
+struct DiversifiedHits {
+    size: usize,
+    max_hits_per_key: usize,
+    hits: BTreeSet<(Hit, usize)>,
+    key_slots: HashMap<DiversityKey, usize>,
+    keys: Vec<DiversityKey>,
+    hits_per_key: Vec<BTreeSet<Hit>>,
+}
+
+impl DiversifiedHits {
+    fn new(size: usize, max_hits_per_key: usize) -> Self {
+        DiversifiedHits {
+            size,
+            max_hits_per_key,
+            hits: BTreeSet::new(),
+            key_slots: HashMap::new(),
+            keys: vec![],
+            hits_per_key: vec![],
+        }
+    }
+
+    fn is_competitive(&self, hit: &Hit) -> bool {
+        self.hits.len() < self.size || self.hits.iter().next().is_none_or(|(min, _)| hit > min)
+    }
+
+    fn add(&mut self, hit: Hit, key: Option<DiversityKey>) {
+        if !self.is_competitive(&hit) {
+            return;
+        }
+
+        let slot = match key {
+            Some(key) => {
+                let slot = match self.key_slots.get(&key) {
+                    Some(slot) => *slot,
+                    None => {
+                        let slot = self.keys.len();
+                        self.key_slots.insert(key.clone(), slot);
+                        self.keys.push(key);
+                        self.hits_per_key.push(BTreeSet::new());
+                        slot
+                    }
+                };
+                let key_hits = &mut self.hits_per_key[slot];
+                if key_hits.len() >= self.max_hits_per_key {
+                    // replace the least competitive hit of the same key
+                    let min = *key_hits.iter().next().unwrap();
+                    if hit <= min {
+                        return;
+                    }
+                    key_hits.remove(&min);
+                    self.hits.remove(&(min, slot));
+                }
+                key_hits.insert(hit);
+                slot
+            }
+            None => NO_KEY,
+        };
+
+        self.hits.insert((hit, slot));
+        if self.hits.len() > self.size {
+            let min = *self.hits.iter().next().unwrap();
+            self.hits.remove(&min);
+            if min.1 != NO_KEY {
+                self.hits_per_key[min.1].remove(&min.0);
+            }
+        }
+    }
+
+    fn into_entries(self) -> Vec<(Hit, Option<DiversityKey>)> {
+        let keys = self.keys;
+        self.hits
+            .into_iter()
+            .map(|(hit, slot)| {
+                let key = if slot == NO_KEY {
+                    None
+                } else {
+                    Some(keys[slot].clone())
+                };
+                (hit, key)
+            })
+            .collect()
+    }
+
+    /// hits sorted from the most to the least competitive
+    fn sorted_hits(&self) -> Vec<Hit> {
+        self.hits.iter().rev().map(|(hit, _)| *hit).collect()
+    }
+}
+
+struct LeafDiversifiedHits {
+    hits: Vec<(Hit, Option<DiversityKey>)>,
+    total_hits: usize,
+}
+
+/// A `Collector` that collects the top scoring docs while limiting the
+/// number of hits that share the same diversity key, e.g. at most 2 docs
+/// per author, so that a few keys can't crowd out the others.
+///
+/// Keys are read from a numeric or sorted doc values field, docs without a
+/// value are not limited.
+pub struct DiversifiedTopDocsCollector {
+    field: String,
+    hits: DiversifiedHits,
+    total_hits: usize,
+    cur_doc_base: DocId,
+    leaf_keys: Option<LeafKeys>,
+    channel: Option<(Sender<LeafDiversifiedHits>, Receiver<LeafDiversifiedHits>)>,
+}
+
+impl DiversifiedTopDocsCollector {
+    pub fn new(
+        size: usize,
+        field: &str,
+        max_hits_per_key: usize,
+    ) -> Result<DiversifiedTopDocsCollector> {
+        if max_hits_per_key == 0 {
+            return Err(IllegalArgument("max_hits_per_key must be > 0".into()));
+        }
+        Ok(DiversifiedTopDocsCollector {
+            field: field.to_string(),
+            hits: DiversifiedHits::new(size, max_hits_per_key),
+            total_hits: 0,
+            cur_doc_base: 0,
+            leaf_keys: None,
+            channel: None,
+        })
+    }
+
+    /// Returns the diversified top docs that were collected by this collector.
+    pub fn top_docs(&self) -> TopDocs {
+        let score_docs = self
+            .hits
+            .sorted_hits()
+            .into_iter()
+            .map(|h| ScoreDocHit::Score(ScoreDoc::new(h.doc, h.score)))
+            .collect();
+        TopDocs::Score(TopScoreDocs::new(self.total_hits, score_docs))
+    }
+}
+
+impl SearchCollector for DiversifiedTopDocsCollector {
+    type LC = DiversifiedTopDocsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        self.leaf_keys = LeafKeys::new(reader, &self.field)?;
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<DiversifiedTopDocsLeafCollector> {
+        Ok(DiversifiedTopDocsLeafCollector {
+            hits: DiversifiedHits::new(self.hits.size, self.hits.max_hits_per_key),
+            total_hits: 0,
+            doc_base: reader.doc_base,
+            leaf_keys: LeafKeys::new(reader, &self.field)?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            // a hit of the global top docs is always in the top docs of its
+            // segment, so merging the per segment results is exact.
+            while let Ok(leaf) = receiver.recv() {
+                self.total_hits += leaf.total_hits;
+                for (hit, key) in leaf.hits {
+                    self.hits.add(hit, key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for DiversifiedTopDocsCollector {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let hit = Hit {
+            doc: doc + self.cur_doc_base,
+            score: scorer.score()?,
+        };
+        if self.hits.is_competitive(&hit) {
+            let key = match self.leaf_keys.as_mut() {
+                Some(keys) => keys.key(doc)?,
+                None => None,
+            };
+            self.hits.add(hit, key);
+        }
+        Ok(())
+    }
+}
+
+pub struct DiversifiedTopDocsLeafCollector {
+    hits: DiversifiedHits,
+    total_hits: usize,
+    doc_base: DocId,
+    leaf_keys: Option<LeafKeys>,
+    channel: Sender<LeafDiversifiedHits>,
+}
+
+impl ParallelLeafCollector for DiversifiedTopDocsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let empty = DiversifiedHits::new(self.hits.size, self.hits.max_hits_per_key);
+        let hits = mem::replace(&mut self.hits, empty);
+        let leaf = LeafDiversifiedHits {
+            hits: hits.into_entries(),
+            total_hits: self.total_hits,
+        };
+        self.channel
+            .send(leaf)
+            .map_err(|_e| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for DiversifiedTopDocsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let hit = Hit {
+            doc: doc + self.doc_base,
+            score: scorer.score()?,
+        };
+        if self.hits.is_competitive(&hit) {
+            let key = match self.leaf_keys.as_mut() {
+                Some(keys) => keys.key(doc)?,
+                None => None,
+            };
+            self.hits.add(hit, key);
+        }
+        Ok(())
+    }
+}
+
+/// The `sample_size` docs with the smallest priorities, the priority of
+/// each doc is a hash of its id and the seed.
+struct Sample {
+    sample_size: usize,
+    seed: u64,
+    docs: BinaryHeap<(u64, DocId)>,
+}
+
+impl Sample {
+    fn new(sample_size: usize, seed: u64) -> Self {
+        Sample {
+            sample_size,
+            seed,
+            docs: BinaryHeap::with_capacity(sample_size),
+        }
+    }
+
+    fn add(&mut self, doc: DocId) {
+        self.add_with_priority(mix64(self.seed ^ doc as u64), doc);
+    }
+
+    fn add_with_priority(&mut self, priority: u64, doc: DocId) {
+        if self.docs.len() < self.sample_size {
+            self.docs.push((priority, doc));
+        } else if let Some(mut max) = self.docs.peek_mut() {
+            if priority < max.0 {
+                *max = (priority, doc);
+            }
+        }
+    }
+}
+
+struct LeafSample {
+    docs: Vec<(u64, DocId)>,
+    total_hits: usize,
+}
+
+/// A `Collector` that takes a uniform random sample of at most
+/// `sample_size` matching docs, e.g. for analytics or relevance judgements
+/// over result sets that are too large to be collected entirely.
+///
+/// The sample only depends on the seed and the matching docs, so it is the
+/// same for sequential and parallel searches.
+pub struct RandomSamplingCollector {
+    sample: Sample,
+    total_hits: usize,
+    cur_doc_base: DocId,
+    channel: Option<(Sender<LeafSample>, Receiver<LeafSample>)>,
+}
+
+impl RandomSamplingCollector {
+    pub fn new(sample_size: usize, seed: u64) -> RandomSamplingCollector {
+        RandomSamplingCollector {
+            sample: Sample::new(sample_size, seed),
+            total_hits: 0,
+            cur_doc_base: 0,
+            channel: None,
+        }
+    }
+
+    /// The number of docs the sample was taken from.
+    pub fn total_hits(&self) -> usize {
+        self.total_hits
+    }
+
+    /// Returns the sampled docs, sorted by doc id.
+    pub fn sample(&self) -> Vec<DocId> {
+        let mut docs: Vec<DocId> = self.sample.docs.iter().map(|(_, doc)| *doc).collect();
+        docs.sort_unstable();
+        docs
+    }
+}
+
+impl SearchCollector for RandomSamplingCollector {
+    type LC = RandomSamplingLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<RandomSamplingLeafCollector> {
+        Ok(RandomSamplingLeafCollector {
+            sample: Sample::new(self.sample.sample_size, self.sample.seed),
+            total_hits: 0,
+            doc_base: reader.doc_base,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(leaf) = receiver.recv() {
+                self.total_hits += leaf.total_hits;
+                for (priority, doc) in leaf.docs {
+                    self.sample.add_with_priority(priority, doc);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for RandomSamplingCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        self.sample.add(doc + self.cur_doc_base);
+        Ok(())
+    }
+}
+
+pub struct RandomSamplingLeafCollector {
+    sample: Sample,
+    total_hits: usize,
+    doc_base: DocId,
+    channel: Sender<LeafSample>,
+}
+
+impl ParallelLeafCollector for RandomSamplingLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let docs = mem::take(&mut self.sample.docs);
+        let leaf = LeafSample {
+            docs: docs.into_vec(),
+            total_hits: self.total_hits,
+        };
+        self.channel
+            .send(leaf)
+            .map_err(|_e| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for RandomSamplingLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        self.sample.add(doc + self.doc_base);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(doc: DocId, score: f32) -> Hit {
+        Hit { doc, score }
+    }
+
+    #[test]
+    fn test_diversified_hits() {
+        let mut hits = DiversifiedHits::new(3, 2);
+        let author = |a: i64| Some(DiversityKey::Long(a));
+        hits.add(hit(0, 10.0), author(1));
+        hits.add(hit(1, 9.0), author(1));
+        // third hit of author 1 only replaces a less competitive one
+        hits.add(hit(2, 8.0), author(1));
+        hits.add(hit(3, 9.5), author(1));
+        hits.add(hit(4, 1.0), author(2));
+        hits.add(hit(5, 2.0), None);
+
+        let docs: Vec<DocId> = hits.sorted_hits().iter().map(|h| h.doc).collect();
+        assert_eq!(docs, vec![0, 3, 5]);
+
+        // merging the per segment entries gives the same result
+        let mut merged = DiversifiedHits::new(3, 2);
+        for (hit, key) in hits.into_entries() {
+            merged.add(hit, key);
+        }
+        merged.add(hit(6, 9.8), author(1));
+        let docs: Vec<DocId> = merged.sorted_hits().iter().map(|h| h.doc).collect();
+        assert_eq!(docs, vec![0, 6, 5]);
+    }
+
+    #[test]
+    fn test_random_sample() {
+        let mut sample1 = Sample::new(10, 42);
+        let mut sample2 = Sample::new(10, 42);
+        for doc in 0..1000 {
+            sample1.add(doc);
+        }
+        for doc in (0..1000).rev() {
+            sample2.add(doc);
+        }
+        let mut docs1: Vec<_> = sample1.docs.into_vec();
+        let mut docs2: Vec<_> = sample2.docs.into_vec();
+        docs1.sort();
+        docs2.sort();
+        assert_eq!(docs1.len(), 10);
+        assert_eq!(docs1, docs2);
+
+        let mut other_seed = Sample::new(10, 7);
+        for doc in 0..1000 {
+            other_seed.add(doc);
+        }
+        let mut docs3: Vec<_> = other_seed.docs.into_vec();
+        docs3.sort();
+        assert_ne!(docs1, docs3);
+    }
+
+    #[test]
+    fn test_invalid_max_hits_per_key() {
+        assert!(DiversifiedTopDocsCollector::new(10, "author", 0).is_err());
+        assert!(DiversifiedTopDocsCollector::new(10, "author", 1).is_ok());
+    }
+}
@@ -16,6 +16,7 @@ use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::collector;
 use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
 use crate::core::search::scorer::Scorer;
+use crate::core::search::ScoreMode;
 use crate::core::util::external::Volatile;
 use crate::core::util::DocId;
 use crate::error::{Error, Result};
@@ -76,6 +77,10 @@ impl Collector for EarlyTerminatingSortingCollector {
         false
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::TopDocs
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
         self.num_docs_collected_per_reader += 1;
 
@@ -137,6 +142,10 @@ impl Collector for EarlyTerminatingLeafCollector {
         false
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::TopDocs
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: i32, _scorer: &mut S) -> Result<()> {
         self.num_docs_collected += 1;
 
@@ -0,0 +1,616 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::collector::{
+    numeric_leaf_values, Collector, NumericLeafValues, ParallelLeafCollector, SearchCollector,
+};
+use crate::core::search::scorer::Scorer;
+use crate::core::util::DocId;
+use crate::error::{
+    Error::{IllegalArgument, IllegalState},
+    Result,
+};
+
+const MILLIS_PER_MINUTE: i64 = 60 * 1000;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+/// The maximum number of empty buckets `HistogramCollector::buckets` fills in
+/// when `min_doc_count` is 0.
+pub const MAX_EMPTY_BUCKETS: usize = 65_536;
+
+/// Calendar aware intervals for date fields stored as epoch millis.
+///
+/// All the bucket boundaries are computed in UTC, weeks start on monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarInterval {
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl CalendarInterval {
+    /// Returns the start of the calendar unit that contains the given epoch millis.
+    pub fn round_down(&self, millis: i64) -> i64 {
+        match self {
+            CalendarInterval::Minute => floor_to(millis, MILLIS_PER_MINUTE),
+            CalendarInterval::Hour => floor_to(millis, MILLIS_PER_HOUR),
+            CalendarInterval::Day => floor_to(millis, MILLIS_PER_DAY),
+            CalendarInterval::Week => {
+                let days = millis.div_euclid(MILLIS_PER_DAY);
+                // 1970-01-01 is a thursday
+                let days_since_monday = (days + 3).rem_euclid(7);
+                (days - days_since_monday) * MILLIS_PER_DAY
+            }
+            CalendarInterval::Month => {
+                let (y, m, _) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+                days_from_civil(y, m, 1) * MILLIS_PER_DAY
+            }
+            CalendarInterval::Quarter => {
+                let (y, m, _) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+                days_from_civil(y, (m - 1) / 3 * 3 + 1, 1) * MILLIS_PER_DAY
+            }
+            CalendarInterval::Year => {
+                let (y, _, _) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+                days_from_civil(y, 1, 1) * MILLIS_PER_DAY
+            }
+        }
+    }
+}
+
+#[inline]
+fn floor_to(value: i64, interval: i64) -> i64 {
+    value.div_euclid(interval) * interval
+}
+
+/// Days since 1970-01-01 for the given proleptic gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic gregorian (year, month, day) for the given days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// How the values of a field are grouped into buckets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramInterval {
+    /// buckets of fixed width, the bucket key is
+    /// `floor((value - offset) / interval) * interval + offset`
+    Fixed { interval: i64, offset: i64 },
+    /// calendar buckets over epoch millis
+    Calendar(CalendarInterval),
+}
+
+impl HistogramInterval {
+    pub fn fixed(interval: i64) -> Result<HistogramInterval> {
+        Self::fixed_with_offset(interval, 0)
+    }
+
+    pub fn fixed_with_offset(interval: i64, offset: i64) -> Result<HistogramInterval> {
+        if interval <= 0 {
+            return Err(IllegalArgument(format!(
+                "interval must be > 0, got {}",
+                interval
+            )));
+        }
+        Ok(HistogramInterval::Fixed { interval, offset })
+    }
+
+    /// Returns the key of the bucket the value falls into.
+    pub fn bucket_key(&self, value: i64) -> i64 {
+        match self {
+            HistogramInterval::Fixed { interval, offset } => {
+                // `value - offset` overflows i64 for far apart values, the keys
+                // below `i64::MIN` are clamped to it
+                let (value, interval, offset) = (
+                    i128::from(value),
+                    i128::from(*interval),
+                    i128::from(*offset),
+                );
+                let key = (value - offset).div_euclid(interval) * interval + offset;
+                key.max(i128::from(i64::MIN)) as i64
+            }
+            HistogramInterval::Calendar(calendar) => calendar.round_down(value),
+        }
+    }
+}
+
+/// Simple statistics over a numeric field for the docs of a bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketStats {
+    pub count: u64,
+    pub sum: f64,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Default for BucketStats {
+    fn default() -> Self {
+        BucketStats {
+            count: 0,
+            sum: 0.0,
+            min: i64::MAX,
+            max: i64::MIN,
+        }
+    }
+}
+
+impl BucketStats {
+    pub fn avg(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.sum / self.count as f64)
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, value: i64) {
+        self.count += 1;
+        self.sum += value as f64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn merge(&mut self, other: &BucketStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+    /// the lower bound of the bucket
+    pub key: i64,
+    pub doc_count: u64,
+    /// stats of the sub stats field, only present if the collector
+    /// is configured with `HistogramCollector::with_stats_field`
+    pub stats: Option<BucketStats>,
+}
+
+impl HistogramBucket {
+    fn new(key: i64, with_stats: bool) -> Self {
+        HistogramBucket {
+            key,
+            doc_count: 0,
+            stats: if with_stats {
+                Some(BucketStats::default())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn merge(&mut self, other: &HistogramBucket) {
+        self.doc_count += other.doc_count;
+        if let (Some(stats), Some(other_stats)) = (self.stats.as_mut(), other.stats.as_ref()) {
+            stats.merge(other_stats);
+        }
+    }
+}
+
+/// Buckets by key.
+type Buckets = BTreeMap<i64, HistogramBucket>;
+
+struct HistogramAggregator {
+    interval: HistogramInterval,
+    with_stats: bool,
+    buckets: Buckets,
+    values: Option<NumericLeafValues>,
+    stats_values: Option<NumericLeafValues>,
+}
+
+impl HistogramAggregator {
+    fn new(interval: HistogramInterval, with_stats: bool) -> Self {
+        HistogramAggregator {
+            interval,
+            with_stats,
+            buckets: BTreeMap::new(),
+            values: None,
+            stats_values: None,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+        field: &str,
+        stats_field: Option<&str>,
+    ) -> Result<()> {
+        self.values = numeric_leaf_values(reader, field)?;
+        self.stats_values = match stats_field {
+            Some(f) => numeric_leaf_values(reader, f)?,
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId) -> Result<()> {
+        let value = match self.values.as_mut() {
+            Some(values) => match values.get(doc)? {
+                Some(v) => v,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let key = self.interval.bucket_key(value);
+        let with_stats = self.with_stats;
+        let bucket = self
+            .buckets
+            .entry(key)
+            .or_insert_with(|| HistogramBucket::new(key, with_stats));
+        bucket.doc_count += 1;
+        if let Some(stats) = bucket.stats.as_mut() {
+            if let Some(values) = self.stats_values.as_mut() {
+                if let Some(v) = values.get(doc)? {
+                    stats.add(v);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, buckets: Buckets) {
+        for (key, bucket) in buckets {
+            match self.buckets.get_mut(&key) {
+                Some(b) => b.merge(&bucket),
+                None => {
+                    self.buckets.insert(key, bucket);
+                }
+            }
+        }
+    }
+}
+
+/// A `Collector` that buckets the matching docs by the numeric doc values of a field.
+///
+/// The buckets are either of fixed width or calendar based (for date fields
+/// stored as epoch millis), see `HistogramInterval`. Docs without a value for
+/// the field are ignored. Optionally a second numeric field can be configured
+/// to compute `BucketStats` for the docs of each bucket.
+pub struct HistogramCollector {
+    field: String,
+    stats_field: Option<String>,
+    min_doc_count: u64,
+    aggregator: HistogramAggregator,
+    channel: Option<(Sender<Buckets>, Receiver<Buckets>)>,
+}
+
+impl HistogramCollector {
+    pub fn new(field: &str, interval: HistogramInterval) -> HistogramCollector {
+        HistogramCollector {
+            field: field.to_string(),
+            stats_field: None,
+            min_doc_count: 1,
+            aggregator: HistogramAggregator::new(interval, false),
+            channel: None,
+        }
+    }
+
+    /// A histogram over a date field stored as epoch millis.
+    pub fn date_histogram(field: &str, interval: CalendarInterval) -> HistogramCollector {
+        Self::new(field, HistogramInterval::Calendar(interval))
+    }
+
+    /// compute `BucketStats` of the given numeric field for each bucket.
+    pub fn with_stats_field(mut self, field: &str) -> HistogramCollector {
+        self.stats_field = Some(field.to_string());
+        self.aggregator.with_stats = true;
+        self
+    }
+
+    /// only return buckets contains at least `min_doc_count` docs, default is 1.
+    ///
+    /// when set to 0, empty buckets between the first and the last non-empty
+    /// bucket are filled in for `HistogramInterval::Fixed` intervals, up to
+    /// `MAX_EMPTY_BUCKETS` of them.
+    pub fn with_min_doc_count(mut self, min_doc_count: u64) -> HistogramCollector {
+        self.min_doc_count = min_doc_count;
+        self
+    }
+
+    /// Returns the collected buckets sorted by key.
+    ///
+    /// Fails if more than `MAX_EMPTY_BUCKETS` empty buckets would be filled in.
+    pub fn buckets(&self) -> Result<Vec<HistogramBucket>> {
+        let buckets = &self.aggregator.buckets;
+        if self.min_doc_count > 0 {
+            return Ok(buckets
+                .values()
+                .filter(|b| b.doc_count >= self.min_doc_count)
+                .cloned()
+                .collect());
+        }
+        match (
+            self.aggregator.interval,
+            buckets.keys().next(),
+            buckets.keys().next_back(),
+        ) {
+            (HistogramInterval::Fixed { interval, .. }, Some(&first), Some(&last)) => {
+                let empty = last
+                    .checked_sub(first)
+                    .and_then(|span| usize::try_from(span / interval).ok())
+                    // a key clamped to `i64::MIN` isn't on the grid of the others
+                    .map(|n| (n + 1).saturating_sub(buckets.len()))
+                    .filter(|&empty| empty <= MAX_EMPTY_BUCKETS)
+                    .ok_or_else(|| {
+                        IllegalArgument(format!(
+                            "more than {} empty buckets between {} and {}",
+                            MAX_EMPTY_BUCKETS, first, last
+                        ))
+                    })?;
+                let mut res = Vec::with_capacity(buckets.len() + empty);
+                let mut key = Some(first);
+                while let Some(k) = key.filter(|&k| k <= last) {
+                    match buckets.get(&k) {
+                        Some(b) => res.push(b.clone()),
+                        None => res.push(HistogramBucket::new(k, self.aggregator.with_stats)),
+                    }
+                    key = k
+                        .checked_add(interval)
+                        .map(|next| self.aggregator.interval.bucket_key(next));
+                }
+                Ok(res)
+            }
+            _ => Ok(buckets.values().cloned().collect()),
+        }
+    }
+}
+
+impl SearchCollector for HistogramCollector {
+    type LC = HistogramLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.aggregator
+            .set_next_reader(reader, &self.field, self.stats_field.as_deref())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<HistogramLeafCollector> {
+        let mut aggregator =
+            HistogramAggregator::new(self.aggregator.interval, self.aggregator.with_stats);
+        aggregator.set_next_reader(reader, &self.field, self.stats_field.as_deref())?;
+        Ok(HistogramLeafCollector {
+            aggregator,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(buckets) = receiver.recv() {
+                self.aggregator.merge(buckets);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for HistogramCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.aggregator.collect(doc)
+    }
+}
+
+pub struct HistogramLeafCollector {
+    aggregator: HistogramAggregator,
+    channel: Sender<Buckets>,
+}
+
+impl ParallelLeafCollector for HistogramLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let buckets = mem::take(&mut self.aggregator.buckets);
+        self.channel
+            .send(buckets)
+            .map_err(|_e| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for HistogramLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.aggregator.collect(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::doc::NumericDocValuesField;
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::search::query::MatchAllDocsQuery;
+    use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+
+    fn collect(values: &[i64], collector: &mut HistogramCollector) {
+        let writer = in_memory_writer();
+        for &v in values {
+            writer
+                .add_document(vec![
+                    NumericDocValuesField::new("value", v),
+                    NumericDocValuesField::new("price", v.wrapping_mul(2)),
+                ])
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(&reader, None);
+        searcher.search(&MatchAllDocsQuery, collector).unwrap();
+    }
+
+    #[test]
+    fn test_fixed_bucket_key() {
+        let interval = HistogramInterval::fixed(10).unwrap();
+        assert_eq!(interval.bucket_key(0), 0);
+        assert_eq!(interval.bucket_key(9), 0);
+        assert_eq!(interval.bucket_key(10), 10);
+        assert_eq!(interval.bucket_key(-1), -10);
+
+        let interval = HistogramInterval::fixed_with_offset(10, 3).unwrap();
+        assert_eq!(interval.bucket_key(2), -7);
+        assert_eq!(interval.bucket_key(3), 3);
+        assert_eq!(interval.bucket_key(12), 3);
+        assert_eq!(interval.bucket_key(13), 13);
+
+        assert!(HistogramInterval::fixed(0).is_err());
+        assert!(HistogramInterval::fixed_with_offset(-5, 3).is_err());
+
+        let interval = HistogramInterval::fixed_with_offset(10, i64::MIN).unwrap();
+        assert_eq!(interval.bucket_key(i64::MAX), i64::MAX - 5);
+        let interval = HistogramInterval::fixed_with_offset(10, i64::MAX).unwrap();
+        assert_eq!(interval.bucket_key(-5), -13);
+        assert_eq!(interval.bucket_key(i64::MIN), i64::MIN);
+    }
+
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn test_calendar_round_down() {
+        // 2019-05-15T13:45:30.123Z
+        let millis = 1_557_927_930_123;
+        let day = 1_557_878_400_000; // 2019-05-15
+        assert_eq!(CalendarInterval::Day.round_down(millis), day);
+        assert_eq!(
+            CalendarInterval::Hour.round_down(millis),
+            day + 13 * MILLIS_PER_HOUR
+        );
+        // 2019-05-13 is a monday
+        assert_eq!(
+            CalendarInterval::Week.round_down(millis),
+            day - 2 * MILLIS_PER_DAY
+        );
+        assert_eq!(
+            CalendarInterval::Month.round_down(millis),
+            days_from_civil(2019, 5, 1) * MILLIS_PER_DAY
+        );
+        assert_eq!(
+            CalendarInterval::Quarter.round_down(millis),
+            days_from_civil(2019, 4, 1) * MILLIS_PER_DAY
+        );
+        assert_eq!(
+            CalendarInterval::Year.round_down(millis),
+            days_from_civil(2019, 1, 1) * MILLIS_PER_DAY
+        );
+    }
+
+    #[test]
+    fn test_histogram_collector() {
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(10).unwrap())
+            .with_stats_field("price");
+        collect(&[1, 5, 12, 31, 35, 39], &mut collector);
+        let buckets = collector.buckets().unwrap();
+        let counts: Vec<(i64, u64)> = buckets.iter().map(|b| (b.key, b.doc_count)).collect();
+        assert_eq!(counts, vec![(0, 2), (10, 1), (30, 3)]);
+        let stats = buckets[2].stats.unwrap();
+        assert_eq!((stats.min, stats.max), (62, 78));
+        assert_eq!(stats.avg(), Some(70.0));
+
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(10).unwrap())
+            .with_min_doc_count(0);
+        collect(&[1, 5, 12, 31, 35, 39], &mut collector);
+        let counts: Vec<(i64, u64)> = collector
+            .buckets()
+            .unwrap()
+            .iter()
+            .map(|b| (b.key, b.doc_count))
+            .collect();
+        assert_eq!(counts, vec![(0, 2), (10, 1), (20, 0), (30, 3)]);
+    }
+
+    #[test]
+    fn test_histogram_too_many_empty_buckets() {
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(1).unwrap())
+            .with_min_doc_count(0);
+        collect(&[0, MAX_EMPTY_BUCKETS as i64 + 1], &mut collector);
+        assert_eq!(collector.buckets().unwrap().len(), MAX_EMPTY_BUCKETS + 2);
+
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(1).unwrap())
+            .with_min_doc_count(0);
+        collect(&[0, MAX_EMPTY_BUCKETS as i64 + 2], &mut collector);
+        assert!(collector.buckets().is_err());
+
+        // the span between the first and the last key overflows
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(1).unwrap())
+            .with_min_doc_count(0);
+        collect(&[i64::MIN, i64::MAX], &mut collector);
+        assert!(collector.buckets().is_err());
+
+        // the first key is clamped to `i64::MIN`, off the grid of the others
+        let mut collector = HistogramCollector::new("value", HistogramInterval::fixed(10).unwrap())
+            .with_min_doc_count(0);
+        collect(&[i64::MIN, i64::MIN + 25], &mut collector);
+        let counts: Vec<(i64, u64)> = collector
+            .buckets()
+            .unwrap()
+            .iter()
+            .map(|b| (b.key, b.doc_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(i64::MIN, 1), (i64::MIN + 8, 0), (i64::MIN + 18, 1)]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use crate::core::search::scorer::{FeatureResult, Scorer};
+use crate::core::search::{DocIterator, ScoreMode};
+use crate::core::util::{DocId, IndexedContext};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// A collector wrapper that only forwards the hits whose score is at least
+/// `min_score` to the wrapped collector.
+///
+/// The threshold is also passed to the scorer with
+/// `Scorer::set_min_competitive_score`, so that scorers supporting dynamic
+/// pruning (e.g. `WANDScorer`) skip non-competitive docs without scoring
+/// them. The wrapped collector sees each score computed only once.
+pub struct MinScoreCollector<T> {
+    collector: T,
+    min_score: f32,
+    // whether the threshold was passed to the scorer of the current leaf
+    threshold_set: bool,
+}
+
+impl<T> MinScoreCollector<T> {
+    pub fn new(collector: T, min_score: f32) -> Result<MinScoreCollector<T>> {
+        if min_score.is_nan() {
+            return Err(IllegalArgument("min_score must not be NaN".into()));
+        }
+        Ok(MinScoreCollector {
+            collector,
+            min_score,
+            threshold_set: false,
+        })
+    }
+
+    pub fn min_score(&self) -> f32 {
+        self.min_score
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.collector
+    }
+
+    pub fn into_inner(self) -> T {
+        self.collector
+    }
+}
+
+impl<T: SearchCollector> SearchCollector for MinScoreCollector<T> {
+    type LC = MinScoreCollector<T::LC>;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.threshold_set = false;
+        self.collector.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        self.collector.support_parallel()
+    }
+
+    fn init_parallel(&mut self) {
+        self.collector.init_parallel()
+    }
+
+    fn leaf_collector<C: Codec>(&self, reader: &LeafReaderContext<'_, C>) -> Result<Self::LC> {
+        Ok(MinScoreCollector {
+            collector: self.collector.leaf_collector(reader)?,
+            min_score: self.min_score,
+            threshold_set: false,
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        self.collector.finish_parallel()
+    }
+}
+
+impl<T: Collector> Collector for MinScoreCollector<T> {
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn score_mode(&self) -> ScoreMode {
+        // hits below `min_score` are never collected, so they may be skipped
+        ScoreMode::TopScores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        if !self.threshold_set {
+            scorer.set_min_competitive_score(self.min_score)?;
+            self.threshold_set = true;
+        }
+        let score = scorer.score()?;
+        if score < self.min_score {
+            return Ok(());
+        }
+        let mut scorer = ScoreCachingScorer {
+            scorer,
+            score,
+            min_score: self.min_score,
+        };
+        self.collector.collect(doc, &mut scorer)
+    }
+}
+
+impl<T: ParallelLeafCollector> ParallelLeafCollector for MinScoreCollector<T> {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.collector.finish_leaf()
+    }
+}
+
+// Serves the already computed score of the current doc, and keeps the
+// competitive score of the scorer from dropping below `min_score`.
+struct ScoreCachingScorer<'a, S: Scorer + ?Sized> {
+    scorer: &'a mut S,
+    score: f32,
+    min_score: f32,
+}
+
+impl<'a, S: Scorer + ?Sized> Scorer for ScoreCachingScorer<'a, S> {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        self.scorer.score_feature()
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.scorer.max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        if min_score > self.min_score {
+            self.scorer.set_min_competitive_score(min_score)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a, S: Scorer + ?Sized> DocIterator for ScoreCachingScorer<'a, S> {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        unreachable!("collectors must not move the scorer")
+    }
+
+    fn advance(&mut self, _target: DocId) -> Result<DocId> {
+        unreachable!("collectors must not move the scorer")
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::tests::{MockDocIterator, MockSimpleScorer};
+    use crate::core::search::NO_MORE_DOCS;
+
+    struct DocsCollector {
+        docs: Vec<(DocId, f32)>,
+    }
+
+    impl Collector for DocsCollector {
+        fn needs_scores(&self) -> bool {
+            true
+        }
+
+        fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+            self.docs.push((doc, scorer.score()?));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_min_score_collector() {
+        // the mock scorer scores each doc with its doc id
+        let mut scorer = MockSimpleScorer::new(MockDocIterator::new(vec![1, 2, 5, 7, 9]));
+        let mut collector = MinScoreCollector::new(DocsCollector { docs: vec![] }, 5.0).unwrap();
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            collector.collect(doc, &mut scorer).unwrap();
+        }
+
+        assert!(collector.needs_scores());
+        assert_eq!(
+            collector.into_inner().docs,
+            vec![(5, 5.0), (7, 7.0), (9, 9.0)]
+        );
+
+        assert!(MinScoreCollector::new(DocsCollector { docs: vec![] }, f32::NAN).is_err());
+    }
+}
@@ -17,6 +17,10 @@ use thiserror::Error;
 
 pub use self::top_docs::*;
 
+mod top_field;
+
+pub use self::top_field::*;
+
 mod early_terminating;
 
 pub use self::early_terminating::*;
@@ -29,12 +33,31 @@ mod chain;
 
 pub use self::chain::*;
 
+mod histogram;
+
+pub use self::histogram::*;
+
+mod cardinality;
+
+pub use self::cardinality::*;
+
+mod min_score;
+
+pub use self::min_score::*;
+
+mod diversified;
+
+pub use self::diversified::*;
+
 use crate::Result;
 
+use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::codec::Codec;
+use crate::core::doc::DocValuesType;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::scorer::Scorer;
-use crate::core::util::DocId;
+use crate::core::search::ScoreMode;
+use crate::core::util::{BitsMut, DocId};
 
 #[derive(Debug, Clone, Error)]
 pub enum Error {
@@ -111,6 +134,16 @@ pub trait Collector {
     /// return `true` if scores are needed.
     fn needs_scores(&self) -> bool;
 
+    /// Indicates how the hits are consumed by this collector, which is
+    /// passed down to `Query::create_weight`.
+    ///
+    /// Collectors that call `Scorer::set_min_competitive_score` must return a
+    /// non exhaustive mode. The default is exhaustive, and computes scores
+    /// iff `needs_scores` returns `true`.
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::from_needs_scores(self.needs_scores())
+    }
+
     /// Called once for every document matching a query, with the unbased document
     /// number.
     /// Note: The collection of the current segment can be terminated by throwing
@@ -129,11 +162,34 @@ impl<'a, T: Collector + 'a> Collector for &'a mut T {
         (**self).needs_scores()
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        (**self).score_mode()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         (**self).collect(doc, scorer)
     }
 }
 
+/// A manager of collectors, that makes it possible to collect each slice of
+/// segments with its own `SearchCollector` in parallel.
+///
+/// `new_collector` is called once per slice, each collector then collects the
+/// segments of its slice sequentially in one thread, and once all slices are
+/// finished `reduce` merges the collectors into the final result.
+pub trait CollectorManager {
+    type Collector: SearchCollector + Send + 'static;
+    type Output;
+
+    /// Return a new `SearchCollector`. This must return a different instance on
+    /// each call.
+    fn new_collector(&self) -> Result<Self::Collector>;
+
+    /// Reduce the results of individual collectors into a meaningful result.
+    /// The collectors are given in the order of the slices.
+    fn reduce(&self, collectors: Vec<Self::Collector>) -> Result<Self::Output>;
+}
+
 /// `Collector` that collect parallel for a single segment.
 ///
 /// once finished, the `finish_leaf` method must be
@@ -141,3 +197,40 @@ impl<'a, T: Collector + 'a> Collector for &'a mut T {
 pub trait ParallelLeafCollector: Collector + Send + 'static {
     fn finish_leaf(&mut self) -> Result<()>;
 }
+
+/// Per segment numeric doc values of a field, together with the docs that
+/// actually have a value, used by the aggregating collectors.
+pub(crate) struct NumericLeafValues {
+    values: Box<dyn NumericDocValues>,
+    docs_with_field: Box<dyn BitsMut>,
+}
+
+impl NumericLeafValues {
+    /// Returns the value of the doc, or `None` if the doc has no value.
+    pub(crate) fn get(&mut self, doc: DocId) -> Result<Option<i64>> {
+        if self.docs_with_field.get(doc as usize) {
+            self.values.get_mut(doc).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Loads the numeric doc values of `field` for the given segment, returns `None`
+/// if the segment has no numeric doc values for the field.
+pub(crate) fn numeric_leaf_values<C: Codec>(
+    reader: &LeafReaderContext<'_, C>,
+    field: &str,
+) -> Result<Option<NumericLeafValues>> {
+    match reader.reader.field_info(field) {
+        Some(fi) if fi.doc_values_type == DocValuesType::Numeric => {
+            let values = reader.reader.get_numeric_doc_values(field)?;
+            let docs_with_field = reader.reader.get_docs_with_field(field)?;
+            Ok(Some(NumericLeafValues {
+                values,
+                docs_with_field,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
@@ -16,6 +16,7 @@ use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::collector;
 use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
 use crate::core::search::scorer::Scorer;
+use crate::core::search::ScoreMode;
 use crate::core::util::external::Volatile;
 use crate::core::util::DocId;
 use crate::error::{Error, Result};
@@ -83,6 +84,10 @@ impl Collector for TimeoutCollector {
         false
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::TopDocs
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
         let now = SystemTime::now();
         if self.start_time < now && now.duration_since(self.start_time)? >= self.timeout_duration {
@@ -118,6 +123,10 @@ impl Collector for TimeoutLeafCollector {
         false
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        ScoreMode::TopDocs
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, _doc: i32, _scorer: &mut S) -> Result<()> {
         let now = SystemTime::now();
         if self.start_time < now && now.duration_since(self.start_time)? >= self.timeout_duration {
@@ -19,9 +19,12 @@ use std::usize;
 
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
-use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use crate::core::search::collector::{
+    Collector, CollectorManager, ParallelLeafCollector, SearchCollector,
+};
 use crate::core::search::scorer::Scorer;
 use crate::core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs};
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 use crate::error::{Error::IllegalState, Result};
 
@@ -37,16 +40,20 @@ struct TopDocsBaseCollector {
     /// The total number of documents that the collector encountered.
     total_hits: usize,
 
+    /// Once `total_hits` exceeds this threshold, non-competitive docs may be skipped.
+    total_hits_threshold: usize,
+
     cur_doc_base: DocId,
 }
 
 impl TopDocsBaseCollector {
-    fn new(estimated_hits: usize) -> Self {
+    fn new(estimated_hits: usize, total_hits_threshold: usize) -> Self {
         let pq = BinaryHeap::with_capacity(estimated_hits);
         Self {
             pq,
             estimated_hits,
             total_hits: 0,
+            total_hits_threshold,
             cur_doc_base: 0,
         }
     }
@@ -81,6 +88,14 @@ impl Collector for TopDocsBaseCollector {
         true
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        if self.total_hits_threshold == usize::MAX {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::TopScores
+        }
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         let score = scorer.score()?;
         debug_assert!((score - f32::NEG_INFINITY).abs() >= f32::EPSILON);
@@ -90,6 +105,12 @@ impl Collector for TopDocsBaseCollector {
         self.add_doc(id, score);
         self.total_hits += 1;
 
+        if self.total_hits > self.total_hits_threshold && self.pq.len() >= self.estimated_hits {
+            if let Some(min) = self.pq.peek() {
+                scorer.set_min_competitive_score(min.score)?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -106,7 +127,15 @@ pub struct TopDocsCollector {
 
 impl TopDocsCollector {
     pub fn new(estimated_hits: usize) -> Self {
-        let base = TopDocsBaseCollector::new(estimated_hits);
+        Self::with_total_hits_threshold(estimated_hits, usize::MAX)
+    }
+
+    /// Create a collector that counts hits accurately up to `total_hits_threshold`,
+    /// after which the scorers are allowed to skip documents that can't make it
+    /// into the top `estimated_hits`. The reported total hits is then a lower bound
+    /// of the number of matching documents.
+    pub fn with_total_hits_threshold(estimated_hits: usize, total_hits_threshold: usize) -> Self {
+        let base = TopDocsBaseCollector::new(estimated_hits, total_hits_threshold);
         Self {
             base,
             channel: None,
@@ -146,7 +175,8 @@ impl SearchCollector for TopDocsCollector {
         &self,
         reader: &LeafReaderContext<'_, C>,
     ) -> Result<TopDocsLeafCollector> {
-        let mut collector = TopDocsBaseCollector::new(self.base.estimated_hits);
+        let mut collector =
+            TopDocsBaseCollector::new(self.base.estimated_hits, self.base.total_hits_threshold);
         collector.cur_doc_base = reader.doc_base;
         Ok(TopDocsLeafCollector::new(
             collector,
@@ -177,11 +207,47 @@ impl Collector for TopDocsCollector {
         true
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        self.base.score_mode()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
         self.base.collect(doc, scorer)
     }
 }
 
+/// A `CollectorManager` that merges the per slice `TopDocsCollector`s
+/// into the global top `estimated_hits` score docs.
+pub struct TopDocsCollectorManager {
+    estimated_hits: usize,
+}
+
+impl TopDocsCollectorManager {
+    pub fn new(estimated_hits: usize) -> Self {
+        Self { estimated_hits }
+    }
+}
+
+impl CollectorManager for TopDocsCollectorManager {
+    type Collector = TopDocsCollector;
+    type Output = TopDocs;
+
+    fn new_collector(&self) -> Result<TopDocsCollector> {
+        Ok(TopDocsCollector::new(self.estimated_hits))
+    }
+
+    fn reduce(&self, collectors: Vec<TopDocsCollector>) -> Result<TopDocs> {
+        let mut merged = TopDocsBaseCollector::new(self.estimated_hits, usize::MAX);
+        for collector in collectors {
+            merged.total_hits += collector.base.total_hits;
+            for doc in collector.base.pq.into_vec() {
+                merged.add_doc(doc.doc, doc.score);
+            }
+        }
+        Ok(merged.top_docs())
+    }
+}
+
 struct LeafTopDocs {
     docs: Vec<ScoreDoc>,
     total_hits: usize,
@@ -218,6 +284,10 @@ impl Collector for TopDocsLeafCollector {
         true
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        self.collector.score_mode()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         self.collector.collect(doc, scorer)
     }
@@ -0,0 +1,367 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use crate::core::search::scorer::Scorer;
+use crate::core::search::sort_field::{
+    ComparatorValue, FieldComparator, FieldComparatorEnum, FieldDoc, ScoreDocHit, Sort,
+    SortFieldType, TopDocs, TopFieldDocs,
+};
+use crate::core::util::{DocId, VariantValue};
+use crate::error::{Error::IllegalState, Result};
+
+/// A sort value of a hit, ordered as its sort field sorts.
+#[derive(Clone, Debug)]
+struct SortValue {
+    value: VariantValue,
+    reverse: bool,
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ord = self.value.cmp(&other.value);
+        if self.reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+/// A hit ordered from the most to the least competitive, ties on the sort
+/// values are won by the smaller doc id.
+#[derive(Clone, Debug)]
+struct FieldHit {
+    values: Vec<SortValue>,
+    doc: DocId,
+    score: f32,
+}
+
+impl Ord for FieldHit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.values
+            .cmp(&other.values)
+            .then_with(|| self.doc.cmp(&other.doc))
+    }
+}
+
+impl PartialOrd for FieldHit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FieldHit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FieldHit {}
+
+/// The `size` most competitive hits, the least competitive one on top.
+struct FieldHits {
+    size: usize,
+    hits: BinaryHeap<FieldHit>,
+}
+
+impl FieldHits {
+    fn new(size: usize) -> Self {
+        FieldHits {
+            size,
+            hits: BinaryHeap::with_capacity(size),
+        }
+    }
+
+    fn add(&mut self, hit: FieldHit) {
+        if self.hits.len() < self.size {
+            self.hits.push(hit);
+        } else if let Some(mut least) = self.hits.peek_mut() {
+            if hit < *least {
+                *least = hit;
+            }
+        }
+    }
+
+    /// hits sorted from the most to the least competitive
+    fn sorted_hits(&self) -> Vec<FieldHit> {
+        self.hits.clone().into_sorted_vec()
+    }
+}
+
+/// The comparators of the sort fields for a segment, each with a single slot
+/// used to read the sort values of the collected docs.
+struct LeafSortValues {
+    comparators: Vec<FieldComparatorEnum>,
+    reverses: Vec<bool>,
+}
+
+impl LeafSortValues {
+    fn new<C: Codec>(sort: &Sort, reader: &LeafReaderContext<'_, C>) -> Result<Self> {
+        let mut comparators = Vec::with_capacity(sort.get_sort().len());
+        let mut reverses = Vec::with_capacity(sort.get_sort().len());
+        for sort_field in sort.get_sort() {
+            let mut comparator = sort_field.get_comparator(1, sort_field.missing_value());
+            comparator.get_information_from_reader(reader)?;
+            comparators.push(comparator);
+            // scores sort in descending order by default
+            reverses
+                .push(sort_field.is_reverse() ^ (sort_field.field_type() == SortFieldType::Score));
+        }
+        Ok(LeafSortValues {
+            comparators,
+            reverses,
+        })
+    }
+
+    fn hit(&mut self, doc: DocId, doc_base: DocId, score: f32) -> Result<FieldHit> {
+        let mut values = Vec::with_capacity(self.comparators.len());
+        for (comparator, reverse) in self.comparators.iter_mut().zip(&self.reverses) {
+            let value = if comparator.get_type() == SortFieldType::Score {
+                ComparatorValue::Score(score)
+            } else {
+                ComparatorValue::Doc(doc)
+            };
+            comparator.copy(0, value)?;
+            values.push(SortValue {
+                value: comparator.value(0),
+                reverse: *reverse,
+            });
+        }
+        Ok(FieldHit {
+            values,
+            doc: doc + doc_base,
+            score,
+        })
+    }
+}
+
+struct LeafFieldHits {
+    hits: Vec<FieldHit>,
+    total_hits: usize,
+}
+
+/// A `Collector` that collects the top docs ordered by a `Sort`, e.g. by a
+/// doc values field or by the distance from a point.
+///
+/// The hits are returned as `FieldDoc`s holding the sort values of each
+/// doc, scores are only computed if one of the sort fields sorts by score.
+pub struct TopFieldCollector {
+    sort: Sort,
+    hits: FieldHits,
+    total_hits: usize,
+    cur_doc_base: DocId,
+    leaf_values: Option<LeafSortValues>,
+    channel: Option<(Sender<LeafFieldHits>, Receiver<LeafFieldHits>)>,
+}
+
+impl TopFieldCollector {
+    pub fn new(sort: Sort, size: usize) -> TopFieldCollector {
+        TopFieldCollector {
+            sort,
+            hits: FieldHits::new(size),
+            total_hits: 0,
+            cur_doc_base: 0,
+            leaf_values: None,
+            channel: None,
+        }
+    }
+
+    /// Returns the top docs that were collected by this collector.
+    pub fn top_docs(&self) -> TopDocs {
+        let hits = self.hits.sorted_hits();
+        let max_score = hits
+            .iter()
+            .map(|h| h.score)
+            .filter(|s| !s.is_nan())
+            .fold(f32::NAN, f32::max);
+        let score_docs = hits
+            .into_iter()
+            .map(|h| {
+                let fields = h.values.into_iter().map(|v| v.value).collect();
+                ScoreDocHit::Field(FieldDoc::new(h.doc, h.score, fields))
+            })
+            .collect();
+        TopDocs::Field(TopFieldDocs {
+            total_hits: self.total_hits,
+            score_docs,
+            max_score,
+            fields: self.sort.get_sort().to_vec(),
+        })
+    }
+}
+
+impl SearchCollector for TopFieldCollector {
+    type LC = TopFieldLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        self.leaf_values = Some(LeafSortValues::new(&self.sort, reader)?);
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TopFieldLeafCollector> {
+        Ok(TopFieldLeafCollector {
+            hits: FieldHits::new(self.hits.size),
+            total_hits: 0,
+            doc_base: reader.doc_base,
+            needs_scores: self.sort.needs_scores(),
+            leaf_values: LeafSortValues::new(&self.sort, reader)?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        if let Some((sender, receiver)) = self.channel.take() {
+            drop(sender);
+            while let Ok(leaf) = receiver.recv() {
+                self.total_hits += leaf.total_hits;
+                for hit in leaf.hits {
+                    self.hits.add(hit);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn needs_scores(&self) -> bool {
+        self.sort.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let score = if self.sort.needs_scores() {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        let leaf_values = self
+            .leaf_values
+            .as_mut()
+            .ok_or_else(|| IllegalState("collect called before set_next_reader".into()))?;
+        let hit = leaf_values.hit(doc, self.cur_doc_base, score)?;
+        self.hits.add(hit);
+        Ok(())
+    }
+}
+
+pub struct TopFieldLeafCollector {
+    hits: FieldHits,
+    total_hits: usize,
+    doc_base: DocId,
+    needs_scores: bool,
+    leaf_values: LeafSortValues,
+    channel: Sender<LeafFieldHits>,
+}
+
+impl ParallelLeafCollector for TopFieldLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let empty = FieldHits::new(self.hits.size);
+        let hits = mem::replace(&mut self.hits, empty);
+        let leaf = LeafFieldHits {
+            hits: hits.hits.into_vec(),
+            total_hits: self.total_hits,
+        };
+        self.channel
+            .send(leaf)
+            .map_err(|_e| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for TopFieldLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        let hit = self.leaf_values.hit(doc, self.doc_base, score)?;
+        self.hits.add(hit);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(price: i64, doc: DocId, reverse: bool) -> FieldHit {
+        FieldHit {
+            values: vec![SortValue {
+                value: VariantValue::Long(price),
+                reverse,
+            }],
+            doc,
+            score: f32::NAN,
+        }
+    }
+
+    #[test]
+    fn test_field_hits() {
+        let mut hits = FieldHits::new(3);
+        for (doc, price) in [5, 3, 9, 3, 1].iter().enumerate() {
+            hits.add(hit(*price, doc as DocId, false));
+        }
+        let docs: Vec<DocId> = hits.sorted_hits().iter().map(|h| h.doc).collect();
+        // ties are won by the smaller doc id
+        assert_eq!(docs, vec![4, 1, 3]);
+
+        let mut hits = FieldHits::new(2);
+        for (doc, price) in [5, 3, 9, 3, 1].iter().enumerate() {
+            hits.add(hit(*price, doc as DocId, true));
+        }
+        let docs: Vec<DocId> = hits.sorted_hits().iter().map(|h| h.doc).collect();
+        assert_eq!(docs, vec![2, 0]);
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::core::codec::Codec;
+use crate::core::index::Term;
+use crate::core::search::query::Query;
+use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+use crate::core::search::SearchPlanBuilder;
+use crate::Result;
+
+/// Term and field statistics of a query, used to score consistently across
+/// the shards of a distributed index.
+///
+/// Each shard gathers the statistics of the query with `collect`, the
+/// coordinator `merge`s them and sends the result back to the shards, which
+/// install it with `DefaultIndexSearcher::set_distributed_statistics` before
+/// running the query. Hits of the shards can then be combined with
+/// `TopScoreDocs::merge` or `TopFieldDocs::merge`.
+#[derive(Clone, Debug, Default)]
+pub struct DistributedStatistics {
+    collection_statistics: HashMap<String, CollectionStatistics>,
+    term_statistics: HashMap<Term, TermStatistics>,
+}
+
+impl DistributedStatistics {
+    /// Gathers the local statistics of the terms of `query` and their fields.
+    pub fn collect<C: Codec>(
+        searcher: &dyn SearchPlanBuilder<C>,
+        query: &dyn Query<C>,
+    ) -> Result<DistributedStatistics> {
+        let mut stats = DistributedStatistics::default();
+        for term_query in query.extract_terms() {
+            let term = term_query.term;
+            if stats.term_statistics.contains_key(&term) {
+                continue;
+            }
+            if !stats.collection_statistics.contains_key(&term.field) {
+                if let Some(field_stats) = searcher.collections_statistics(&term.field) {
+                    stats.add_collection_statistics(field_stats.clone());
+                }
+            }
+            let term_stats = searcher.term_statistics(&term)?;
+            stats.add_term_statistics(term, term_stats);
+        }
+        Ok(stats)
+    }
+
+    pub fn add_collection_statistics(&mut self, stats: CollectionStatistics) {
+        if let Some(s) = self.collection_statistics.get_mut(&stats.field) {
+            s.merge(&stats);
+        } else {
+            self.collection_statistics
+                .insert(stats.field.clone(), stats);
+        }
+    }
+
+    pub fn add_term_statistics(&mut self, term: Term, stats: TermStatistics) {
+        if let Some(s) = self.term_statistics.get_mut(&term) {
+            s.merge(&stats);
+        } else {
+            self.term_statistics.insert(term, stats);
+        }
+    }
+
+    /// Adds the statistics gathered on another shard.
+    pub fn merge(&mut self, other: &DistributedStatistics) {
+        for stats in other.collection_statistics.values() {
+            self.add_collection_statistics(stats.clone());
+        }
+        for (term, stats) in &other.term_statistics {
+            self.add_term_statistics(term.clone(), stats.clone());
+        }
+    }
+
+    pub fn collection_statistics(&self, field: &str) -> Option<&CollectionStatistics> {
+        self.collection_statistics.get(field)
+    }
+
+    pub fn term_statistics(&self, term: &Term) -> Option<&TermStatistics> {
+        self.term_statistics.get(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_shard_statistics() {
+        let term = Term::new("body".into(), b"rust".to_vec());
+        let mut shard1 = DistributedStatistics::default();
+        shard1.add_collection_statistics(CollectionStatistics::new(
+            "body".into(),
+            0,
+            100,
+            90,
+            1000,
+            500,
+        ));
+        shard1.add_term_statistics(term.clone(), TermStatistics::new(b"rust".to_vec(), 10, 12));
+
+        let mut shard2 = DistributedStatistics::default();
+        shard2.add_collection_statistics(CollectionStatistics::new(
+            "body".into(),
+            0,
+            50,
+            50,
+            600,
+            300,
+        ));
+        shard2.add_term_statistics(term.clone(), TermStatistics::new(b"rust".to_vec(), 1, 1));
+
+        shard1.merge(&shard2);
+        let field_stats = shard1.collection_statistics("body").unwrap();
+        assert_eq!(field_stats.max_doc, 150);
+        assert_eq!(field_stats.doc_count, 140);
+        assert_eq!(field_stats.sum_total_term_freq, 1600);
+        let term_stats = shard1.term_statistics(&term).unwrap();
+        assert_eq!(term_stats.doc_freq, 11);
+        assert_eq!(term_stats.total_term_freq, 13);
+        assert!(shard1.collection_statistics("title").is_none());
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use crate::core::codec::PostingIterator;
+use crate::Result;
+
+/// A single match of a query in a field: a range of positions, and the
+/// matching character offsets if they were indexed (`-1` otherwise).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MatchPosition {
+    pub start_position: i32,
+    pub end_position: i32,
+    pub start_offset: i32,
+    pub end_offset: i32,
+}
+
+impl MatchPosition {
+    pub fn new(
+        start_position: i32,
+        end_position: i32,
+        start_offset: i32,
+        end_offset: i32,
+    ) -> MatchPosition {
+        MatchPosition {
+            start_position,
+            end_position,
+            start_offset,
+            end_offset,
+        }
+    }
+
+    /// Read the current position of `postings` as a single term match.
+    pub(crate) fn from_postings<T: PostingIterator + ?Sized>(
+        postings: &mut T,
+    ) -> Result<MatchPosition> {
+        let position = postings.next_position()?;
+        Ok(MatchPosition::new(
+            position,
+            position,
+            postings.start_offset()?,
+            postings.end_offset()?,
+        ))
+    }
+}
+
+/// Reports the positions and optionally offsets of all matching terms in a
+/// query for a single document, returned by `Weight::matches`.
+///
+/// A `Matches` without any field means the document matches, but the query
+/// doesn't have any position information, e.g. `MatchAllDocsQuery` or a
+/// `PointRangeQuery`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Matches {
+    fields: BTreeMap<String, Vec<MatchPosition>>,
+}
+
+impl Matches {
+    /// Matches of a single field, `positions` needn't be sorted.
+    pub fn for_field(field: impl Into<String>, mut positions: Vec<MatchPosition>) -> Matches {
+        positions.sort();
+        positions.dedup();
+        let mut fields = BTreeMap::new();
+        fields.insert(field.into(), positions);
+        Matches { fields }
+    }
+
+    /// Merge the matches of several sub queries into one.
+    pub fn merge(matches: Vec<Matches>) -> Matches {
+        let mut fields: BTreeMap<String, Vec<MatchPosition>> = BTreeMap::new();
+        for m in matches {
+            for (field, positions) in m.fields {
+                fields.entry(field).or_default().extend(positions);
+            }
+        }
+        for positions in fields.values_mut() {
+            positions.sort();
+            positions.dedup();
+        }
+        Matches { fields }
+    }
+
+    /// Returns the fields with matches for this document, in sorted order.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(String::as_str)
+    }
+
+    /// Returns a `MatchesIterator` over the matches of `field`, or `None`
+    /// if the field doesn't have any match.
+    pub fn get_matches(&self, field: &str) -> Option<MatchesIterator<'_>> {
+        self.fields.get(field).map(|positions| MatchesIterator {
+            positions,
+            current: None,
+        })
+    }
+
+    /// Returns true if none of the fields has position information.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// An iterator over match positions (and optionally offsets) for a single
+/// document and field, ordered by start position.
+///
+/// `next` must be called before any of the accessors.
+pub struct MatchesIterator<'a> {
+    positions: &'a [MatchPosition],
+    current: Option<usize>,
+}
+
+impl<'a> MatchesIterator<'a> {
+    /// Advance the iterator to the next match position, returns false if
+    /// there are no more matches.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> bool {
+        let next = self.current.map_or(0, |c| c + 1);
+        if next < self.positions.len() {
+            self.current = Some(next);
+            true
+        } else {
+            self.current = Some(self.positions.len());
+            false
+        }
+    }
+
+    fn current(&self) -> &MatchPosition {
+        &self.positions[self.current.expect("next() must be called first")]
+    }
+
+    /// The start position of the current match.
+    pub fn start_position(&self) -> i32 {
+        self.current().start_position
+    }
+
+    /// The end position of the current match.
+    pub fn end_position(&self) -> i32 {
+        self.current().end_position
+    }
+
+    /// The start offset of the current match, or `-1` if offsets were not indexed.
+    pub fn start_offset(&self) -> i32 {
+        self.current().start_offset
+    }
+
+    /// The end offset of the current match, or `-1` if offsets were not indexed.
+    pub fn end_offset(&self) -> i32 {
+        self.current().end_offset
+    }
+
+    /// All the matches of this field.
+    pub fn positions(&self) -> &'a [MatchPosition] {
+        self.positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_matches() {
+        let m1 = Matches::for_field(
+            "body",
+            vec![
+                MatchPosition::new(5, 5, 20, 25),
+                MatchPosition::new(1, 1, 3, 7),
+            ],
+        );
+        let m2 = Matches::for_field("body", vec![MatchPosition::new(1, 1, 3, 7)]);
+        let m3 = Matches::for_field("title", vec![MatchPosition::new(0, 1, 0, 9)]);
+
+        let merged = Matches::merge(vec![m1, m2, m3, Matches::default()]);
+        assert_eq!(merged.fields().collect::<Vec<_>>(), vec!["body", "title"]);
+
+        let mut it = merged.get_matches("body").unwrap();
+        assert!(it.next());
+        assert_eq!((it.start_position(), it.end_position()), (1, 1));
+        assert_eq!((it.start_offset(), it.end_offset()), (3, 7));
+        assert!(it.next());
+        assert_eq!(it.start_position(), 5);
+        assert!(!it.next());
+
+        assert!(merged.get_matches("tags").is_none());
+    }
+}
@@ -13,18 +13,41 @@
 
 pub mod cache;
 pub mod collector;
+pub mod percolator;
 pub mod query;
 pub mod scorer;
 pub mod similarity;
 pub mod sort_field;
+pub mod spell;
+pub mod suggest;
 
 mod searcher;
-pub use searcher::{DefaultIndexSearcher, IndexSearcher, SearchPlanBuilder};
+pub use searcher::{
+    DefaultIndexSearcher, IndexSearcher, SearchExecutor, SearchPlanBuilder, SlicePolicy,
+};
 use thiserror::Error;
 mod explanation;
 pub use explanation::Explanation;
+mod matches;
+pub use matches::{MatchPosition, Matches, MatchesIterator};
+mod profile;
+pub use profile::{
+    ProfileResult, ProfileScorer, ProfileWeight, ProfilingPlanBuilder, QueryProfileBreakdown,
+    QueryProfiler, QueryTimingType,
+};
+mod score_mode;
+pub use score_mode::ScoreMode;
 mod search_manager;
+pub use search_manager::{
+    DefaultSearcherFactory, ReferenceManager, ReferenceManagerBase, RefreshListener,
+    SearcherFactory, SearcherManager,
+};
+mod reopen_thread;
+pub use reopen_thread::ControlledRealTimeReopenThread;
 mod statistics;
+pub use statistics::{CollectionStatistics, TermStatistics};
+mod distributed;
+pub use distributed::DistributedStatistics;
 
 use std::i32;
 
@@ -0,0 +1,78 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::Analyzer;
+use crate::core::codec::CodecEnum;
+use crate::core::doc::{Field, Fieldable, TEXT_FIELD_TYPE};
+use crate::core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
+use crate::core::index::reader::StandardDirectoryReader;
+use crate::core::index::writer::{IndexWriter, IndexWriterConfig};
+use crate::core::search::query::Query;
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::core::store::directory::ByteBuffersDirectory;
+use crate::Result;
+
+use std::sync::Arc;
+
+type MemoryWriter =
+    IndexWriter<ByteBuffersDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+type MemoryReader = StandardDirectoryReader<
+    ByteBuffersDirectory,
+    CodecEnum,
+    SerialMergeScheduler,
+    TieredMergePolicy,
+>;
+
+/// A searchable index of a single document held in memory, to tell whether
+/// queries match the document without indexing it for good.
+///
+/// ```rust,ignore
+/// let index = MemoryIndex::new(&WhitespaceAnalyzer, &[("body", "the quick fox")])?;
+/// assert!(index.matches(&TermQuery::new(Term::new("body".into(), b"fox".to_vec()), 1.0, None))?);
+/// ```
+pub struct MemoryIndex {
+    reader: MemoryReader,
+    _writer: MemoryWriter,
+}
+
+impl MemoryIndex {
+    /// Indexes a document of text fields, the `(name, text)` pairs, analyzed
+    /// by `analyzer`.
+    pub fn new(analyzer: &dyn Analyzer, fields: &[(&str, &str)]) -> Result<MemoryIndex> {
+        let directory = Arc::new(ByteBuffersDirectory::new());
+        let writer: MemoryWriter =
+            IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+        let mut doc: Vec<Box<dyn Fieldable>> = Vec::with_capacity(fields.len());
+        for (name, text) in fields {
+            let token_stream = analyzer.token_stream(name, text)?;
+            doc.push(Box::new(Field::new(
+                name.to_string(),
+                TEXT_FIELD_TYPE,
+                Some((*text).into()),
+                Some(token_stream),
+            )));
+        }
+        writer.add_document(doc)?;
+        let reader = writer.get_reader(true, false)?;
+        Ok(MemoryIndex {
+            reader,
+            _writer: writer,
+        })
+    }
+
+    /// Whether `query` matches the document.
+    pub fn matches(&self, query: &dyn Query<CodecEnum>) -> Result<bool> {
+        let searcher = DefaultIndexSearcher::new(&self.reader, None);
+        Ok(searcher.count(query)? > 0)
+    }
+}
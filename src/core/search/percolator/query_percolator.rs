@@ -0,0 +1,323 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::codec::CodecEnum;
+use crate::core::doc::{Field, Fieldable, StringField, STORE_FIELD_TYPE};
+use crate::core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
+use crate::core::index::reader::{IndexReader, StandardDirectoryReader};
+use crate::core::index::writer::{IndexWriter, IndexWriterConfig};
+use crate::core::index::Term;
+use crate::core::search::collector::TopDocsCollector;
+use crate::core::search::percolator::MemoryIndex;
+use crate::core::search::query::{BooleanQuery, Query, QueryDsl, TermQuery};
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::core::store::directory::Directory;
+use crate::core::util::ByteBlockPool;
+use crate::Result;
+
+use std::sync::Arc;
+
+const ID_FIELD_NAME: &str = "id";
+const QUERY_FIELD_NAME: &str = "query";
+const EXTRACTED_TERMS_FIELD_NAME: &str = "extracted_terms";
+const EXTRACTION_FAILED_FIELD_NAME: &str = "extraction_failed";
+const EXTRACTION_FAILED_VALUE: &str = "T";
+// the longest stored value or indexed term
+const MAX_VALUE_LENGTH: usize = ByteBlockPool::BYTE_BLOCK_SIZE - 2;
+
+type PercolatorWriter<D> = IndexWriter<D, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+type PercolatorReader<D> =
+    StandardDirectoryReader<D, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+/// Finds the registered queries matching a document, the reverse of a search.
+///
+/// The queries are documents of an auxiliary index in `directory`, with their
+/// JSON form stored and the terms a document must contain to match them
+/// indexed, see `QueryDsl::extract_terms`. Percolating a document first
+/// selects the queries with one of its terms, or whose terms couldn't be
+/// extracted, then runs each of them against the document in a
+/// `MemoryIndex`.
+///
+/// The query values are terms as indexed, so they should match the output of
+/// the analyzer of the percolated documents. Registered queries become
+/// visible after `refresh`.
+///
+/// ```rust,ignore
+/// let mut percolator = Percolator::new(Arc::new(directory))?;
+/// percolator.register_json("rust", r#"{"term": {"field": "body", "value": "rust"}}"#)?;
+/// percolator.refresh()?;
+/// let ids = percolator.percolate(&[("body", "rust is fast")])?;
+/// ```
+pub struct Percolator<D: Directory + Send + Sync + 'static> {
+    writer: PercolatorWriter<D>,
+    reader: Arc<PercolatorReader<D>>,
+    analyzer: Box<dyn Analyzer>,
+}
+
+impl<D: Directory + Send + Sync + 'static> Percolator<D> {
+    /// Opens the percolator of the index in `directory`, which is created if
+    /// it doesn't exist yet.
+    pub fn new(directory: Arc<D>) -> Result<Percolator<D>> {
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+        let reader = Arc::new(writer.get_reader(true, false)?);
+        Ok(Percolator {
+            writer,
+            reader,
+            analyzer: Box::new(WhitespaceAnalyzer),
+        })
+    }
+
+    /// The analyzer of the percolated documents.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// The number of visible queries.
+    pub fn count(&self) -> usize {
+        self.reader.num_docs() as usize
+    }
+
+    /// Registers `query` under `id`, replacing the query registered under the
+    /// same id if any, visible after the next `refresh`.
+    pub fn register(&mut self, id: &str, query: &QueryDsl) -> Result<()> {
+        // reject the queries that can't be built before storing them
+        query.build::<CodecEnum>()?;
+        let json = query.to_json()?;
+
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+        doc.push(Box::new(StringField::new(ID_FIELD_NAME, id, true)));
+        // `Field::new` truncates values to `MAX_VALUE_LENGTH` bytes, so large
+        // queries are stored as several values, concatenated back by `percolate`
+        for chunk in Self::split_json(&json) {
+            doc.push(Box::new(Field::new(
+                QUERY_FIELD_NAME.into(),
+                STORE_FIELD_TYPE,
+                Some(chunk.to_string().into()),
+                None,
+            )));
+        }
+        // a term longer than the indexed ones can't be extracted, the query is
+        // then a candidate for every document
+        let extracted_terms = query.extract_terms().filter(|terms| {
+            terms
+                .iter()
+                .all(|t| t.field.len() + 1 + t.bytes.len() <= MAX_VALUE_LENGTH)
+        });
+        match extracted_terms {
+            Some(terms) => {
+                for term in &terms {
+                    doc.push(Box::new(StringField::new_bytes(
+                        EXTRACTED_TERMS_FIELD_NAME,
+                        Self::encode_term(&term.field, &term.bytes),
+                        false,
+                    )));
+                }
+            }
+            None => {
+                doc.push(Box::new(StringField::new(
+                    EXTRACTION_FAILED_FIELD_NAME,
+                    EXTRACTION_FAILED_VALUE,
+                    false,
+                )));
+            }
+        }
+        let id_term = Term::new(ID_FIELD_NAME.into(), id.as_bytes().to_vec());
+        self.writer.update_document(doc, Some(id_term))?;
+        Ok(())
+    }
+
+    /// Registers the query of the JSON form of a `QueryDsl`.
+    pub fn register_json(&mut self, id: &str, json: &str) -> Result<()> {
+        self.register(id, &QueryDsl::from_json(json)?)
+    }
+
+    /// Removes the query registered under `id`, after the next `refresh`.
+    pub fn unregister(&mut self, id: &str) -> Result<()> {
+        let id_term = Term::new(ID_FIELD_NAME.into(), id.as_bytes().to_vec());
+        self.writer.delete_documents_by_terms(vec![id_term])?;
+        Ok(())
+    }
+
+    /// Commits the registered queries and makes them visible to `percolate`.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.writer.commit()?;
+        self.reader = Arc::new(self.writer.get_reader(true, false)?);
+        Ok(())
+    }
+
+    /// Returns the sorted ids of the queries matching the document of text
+    /// fields, the `(name, text)` pairs.
+    pub fn percolate(&self, fields: &[(&str, &str)]) -> Result<Vec<String>> {
+        let num_docs = self.reader.num_docs() as usize;
+        if num_docs == 0 {
+            return Ok(vec![]);
+        }
+
+        let term_query = |field: &str, term: Vec<u8>| -> Box<dyn Query<CodecEnum>> {
+            Box::new(TermQuery::new(Term::new(field.into(), term), 1.0, None))
+        };
+        let mut candidate_terms = vec![];
+        for (name, text) in fields {
+            let mut token_stream = self.analyzer.token_stream(name, text)?;
+            token_stream.reset()?;
+            while token_stream.next_token()? {
+                candidate_terms.push(Self::encode_term(name, &token_stream.token().term));
+            }
+            token_stream.end()?;
+        }
+        candidate_terms.sort();
+        candidate_terms.dedup();
+        let mut clauses: Vec<Box<dyn Query<CodecEnum>>> = candidate_terms
+            .into_iter()
+            .map(|t| term_query(EXTRACTED_TERMS_FIELD_NAME, t))
+            .collect();
+        clauses.push(term_query(
+            EXTRACTION_FAILED_FIELD_NAME,
+            EXTRACTION_FAILED_VALUE.as_bytes().to_vec(),
+        ));
+        let candidates = BooleanQuery::build(vec![], clauses, vec![], vec![], 1)?;
+
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&self.reader), None);
+        let mut collector = TopDocsCollector::new(num_docs);
+        searcher.search(candidates.as_ref(), &mut collector)?;
+
+        let memory_index = MemoryIndex::new(self.analyzer.as_ref(), fields)?;
+        let stored_fields = [ID_FIELD_NAME.to_string(), QUERY_FIELD_NAME.to_string()];
+        let mut ids = vec![];
+        for hit in collector.top_docs().score_docs() {
+            let doc = self.reader.document(hit.doc_id(), &stored_fields)?;
+            let json: String = doc
+                .get_values(QUERY_FIELD_NAME)
+                .iter()
+                .filter_map(|v| v.get_string())
+                .collect();
+            let query = QueryDsl::from_json(&json)?.build::<CodecEnum>()?;
+            if memory_index.matches(query.as_ref())? {
+                let id = doc.get_string(ID_FIELD_NAME).unwrap_or("");
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    // splits `json` in pieces of at most `MAX_VALUE_LENGTH` bytes, at char
+    // boundaries
+    fn split_json(json: &str) -> Vec<&str> {
+        let mut chunks = vec![];
+        let mut rest = json;
+        while !rest.is_empty() {
+            let mut end = rest.len().min(MAX_VALUE_LENGTH);
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (chunk, tail) = rest.split_at(end);
+            chunks.push(chunk);
+            rest = tail;
+        }
+        chunks
+    }
+
+    // the field and the term separated by a 0 byte, which field names don't
+    // contain
+    fn encode_term(field: &str, term: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(field.len() + 1 + term.len());
+        encoded.extend_from_slice(field.as_bytes());
+        encoded.push(0);
+        encoded.extend_from_slice(term);
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+
+    #[test]
+    fn test_percolate() {
+        let directory = Arc::new(ByteBuffersDirectory::new());
+        let mut percolator = Percolator::new(directory).unwrap();
+        percolator
+            .register_json("rust", r#"{"term": {"field": "body", "value": "rust"}}"#)
+            .unwrap();
+        percolator
+            .register_json(
+                "fast_rust",
+                r#"{"phrase": {"field": "body", "terms": ["fast", "rust"]}}"#,
+            )
+            .unwrap();
+        percolator
+            .register_json(
+                "not_java",
+                r#"{"bool": {"must_not": [{"term": {"field": "body", "value": "java"}}]}}"#,
+            )
+            .unwrap();
+        percolator
+            .register_json("title", r#"{"term": {"field": "title", "value": "rust"}}"#)
+            .unwrap();
+        assert!(percolator.register_json("bad", r#"{"bad": {}}"#).is_err());
+        percolator.refresh().unwrap();
+        assert_eq!(percolator.count(), 4);
+
+        let ids = percolator
+            .percolate(&[("body", "rust is fast rust")])
+            .unwrap();
+        assert_eq!(ids, vec!["fast_rust", "not_java", "rust"]);
+        let ids = percolator.percolate(&[("body", "java")]).unwrap();
+        assert!(ids.is_empty());
+        let ids = percolator
+            .percolate(&[("title", "rust"), ("body", "go")])
+            .unwrap();
+        assert_eq!(ids, vec!["not_java", "title"]);
+
+        // registering under the same id replaces the query
+        percolator
+            .register_json("rust", r#"{"term": {"field": "body", "value": "go"}}"#)
+            .unwrap();
+        percolator.unregister("not_java").unwrap();
+        percolator.refresh().unwrap();
+        assert_eq!(percolator.count(), 3);
+        let ids = percolator
+            .percolate(&[("title", "rust"), ("body", "go")])
+            .unwrap();
+        assert_eq!(ids, vec!["rust", "title"]);
+    }
+
+    #[test]
+    fn test_percolate_large_queries() {
+        let directory = Arc::new(ByteBuffersDirectory::new());
+        let mut percolator = Percolator::new(directory).unwrap();
+        let values: Vec<String> = (0..10_000).map(|i| format!("v{}", i)).collect();
+        let many_values = QueryDsl::Terms {
+            field: "body".into(),
+            values,
+        };
+        assert!(many_values.to_json().unwrap().len() > MAX_VALUE_LENGTH);
+        percolator.register("many_values", &many_values).unwrap();
+        let long_value = QueryDsl::Term {
+            field: "body".into(),
+            value: "é".repeat(MAX_VALUE_LENGTH),
+        };
+        percolator.register("long_value", &long_value).unwrap();
+        percolator.refresh().unwrap();
+        assert_eq!(percolator.count(), 2);
+
+        let ids = percolator.percolate(&[("body", "v9999")]).unwrap();
+        assert_eq!(ids, vec!["many_values"]);
+        let ids = percolator.percolate(&[("body", "rust")]).unwrap();
+        assert!(ids.is_empty());
+    }
+}
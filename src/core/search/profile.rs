@@ -0,0 +1,498 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opt-in query profiling, see `DefaultIndexSearcher::profile`.
+//!
+//! The profiler wraps every `Weight` created while building the search plan
+//! and the `Scorer`s they produce, and records how long each node of the
+//! query tree spent creating its weight, building scorers, iterating and
+//! scoring documents.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::index::Term;
+use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{Query, Weight};
+use crate::core::search::scorer::{BatchScorer, FeatureResult, Scorer};
+use crate::core::search::similarity::Similarity;
+use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+use crate::core::search::{DocIterator, ScoreMode, SearchPlanBuilder};
+use crate::core::util::{DocId, IndexedContext};
+use crate::Result;
+
+/// The timed operations of a query node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryTimingType {
+    CreateWeight,
+    BuildScorer,
+    NextDoc,
+    Advance,
+    Match,
+    Score,
+}
+
+impl QueryTimingType {
+    pub const ALL: [QueryTimingType; 6] = [
+        QueryTimingType::CreateWeight,
+        QueryTimingType::BuildScorer,
+        QueryTimingType::NextDoc,
+        QueryTimingType::Advance,
+        QueryTimingType::Match,
+        QueryTimingType::Score,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QueryTimingType::CreateWeight => "create_weight",
+            QueryTimingType::BuildScorer => "build_scorer",
+            QueryTimingType::NextDoc => "next_doc",
+            QueryTimingType::Advance => "advance",
+            QueryTimingType::Match => "match",
+            QueryTimingType::Score => "score",
+        }
+    }
+}
+
+impl fmt::Display for QueryTimingType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Accumulated timings and invocation counts of a single query node, shared
+/// by the weight and all the scorers it created.
+#[derive(Default)]
+pub struct QueryProfileBreakdown {
+    timings: [AtomicU64; 6],
+    counts: [AtomicU64; 6],
+}
+
+impl QueryProfileBreakdown {
+    fn record(&self, timing: QueryTimingType, start: Instant) {
+        let elapsed = start.elapsed().as_nanos() as u64;
+        self.timings[timing as usize].fetch_add(elapsed, Ordering::Relaxed);
+        self.counts[timing as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Total time in nanoseconds spent in `timing`.
+    pub fn time_nanos(&self, timing: QueryTimingType) -> u64 {
+        self.timings[timing as usize].load(Ordering::Relaxed)
+    }
+
+    /// Number of invocations of `timing`.
+    pub fn count(&self, timing: QueryTimingType) -> u64 {
+        self.counts[timing as usize].load(Ordering::Relaxed)
+    }
+
+    fn to_map(&self) -> BTreeMap<String, u64> {
+        let mut map = BTreeMap::new();
+        for timing in &QueryTimingType::ALL {
+            map.insert(timing.name().to_string(), self.time_nanos(*timing));
+            map.insert(format!("{}_count", timing.name()), self.count(*timing));
+        }
+        map
+    }
+}
+
+/// The profile of one node of the query tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileResult {
+    /// The type of the weight, e.g. `TermQuery`.
+    pub query_type: String,
+    /// The query description as returned by its `Display` impl.
+    pub description: String,
+    /// Total time of this node in nanoseconds, including its children.
+    pub time_nanos: u64,
+    /// Time in nanoseconds and count of each `QueryTimingType`, the counts
+    /// are keyed by the timing name suffixed with `_count`.
+    pub breakdown: BTreeMap<String, u64>,
+    pub children: Vec<ProfileResult>,
+}
+
+impl ProfileResult {
+    /// Time in nanoseconds of `timing` for this node only.
+    pub fn time_of(&self, timing: QueryTimingType) -> u64 {
+        self.breakdown.get(timing.name()).cloned().unwrap_or(0)
+    }
+
+    /// Invocation count of `timing` for this node only.
+    pub fn count_of(&self, timing: QueryTimingType) -> u64 {
+        self.breakdown
+            .get(&format!("{}_count", timing.name()))
+            .cloned()
+            .unwrap_or(0)
+    }
+}
+
+struct ProfileNode {
+    query_type: &'static str,
+    description: String,
+    breakdown: Arc<QueryProfileBreakdown>,
+    children: Vec<usize>,
+}
+
+#[derive(Default)]
+struct ProfileTree {
+    nodes: Vec<ProfileNode>,
+    roots: Vec<usize>,
+    // nodes whose weight is being created, the last one is the parent of
+    // the weights created next.
+    stack: Vec<usize>,
+}
+
+/// Builds the tree of profiled query nodes, nodes are added as weights are
+/// created so the tree mirrors the way queries create their sub weights.
+#[derive(Default)]
+pub struct QueryProfiler {
+    tree: Mutex<ProfileTree>,
+}
+
+impl QueryProfiler {
+    fn start_node(&self, description: String) -> (usize, Arc<QueryProfileBreakdown>) {
+        let mut tree = self.tree.lock().unwrap();
+        let idx = tree.nodes.len();
+        let breakdown = Arc::new(QueryProfileBreakdown::default());
+        tree.nodes.push(ProfileNode {
+            query_type: "",
+            description,
+            breakdown: Arc::clone(&breakdown),
+            children: vec![],
+        });
+        match tree.stack.last().cloned() {
+            Some(parent) => tree.nodes[parent].children.push(idx),
+            None => tree.roots.push(idx),
+        }
+        tree.stack.push(idx);
+        (idx, breakdown)
+    }
+
+    fn stop_node(&self, idx: usize, query_type: &'static str) {
+        let mut tree = self.tree.lock().unwrap();
+        debug_assert_eq!(tree.stack.last(), Some(&idx));
+        tree.stack.pop();
+        tree.nodes[idx].query_type = query_type;
+    }
+
+    /// The profile trees of all the top level queries profiled so far.
+    pub fn results(&self) -> Vec<ProfileResult> {
+        let tree = self.tree.lock().unwrap();
+        tree.roots.iter().map(|r| Self::result(&tree, *r)).collect()
+    }
+
+    fn result(tree: &ProfileTree, idx: usize) -> ProfileResult {
+        let node = &tree.nodes[idx];
+        let children: Vec<ProfileResult> = node
+            .children
+            .iter()
+            .map(|c| Self::result(tree, *c))
+            .collect();
+        let time_nanos = QueryTimingType::ALL
+            .iter()
+            .map(|t| node.breakdown.time_nanos(*t))
+            .sum();
+        ProfileResult {
+            query_type: node.query_type.to_string(),
+            description: node.description.clone(),
+            time_nanos,
+            breakdown: node.breakdown.to_map(),
+            children,
+        }
+    }
+}
+
+/// A `SearchPlanBuilder` that profiles every weight created through it,
+/// all the other methods are delegated to the wrapped builder.
+///
+/// Weights are created without the query cache, so that the profile
+/// reflects the cost of the queries themselves.
+pub struct ProfilingPlanBuilder<'a, C: Codec> {
+    inner: &'a dyn SearchPlanBuilder<C>,
+    profiler: &'a QueryProfiler,
+}
+
+impl<'a, C: Codec> ProfilingPlanBuilder<'a, C> {
+    pub fn new(
+        inner: &'a dyn SearchPlanBuilder<C>,
+        profiler: &'a QueryProfiler,
+    ) -> ProfilingPlanBuilder<'a, C> {
+        ProfilingPlanBuilder { inner, profiler }
+    }
+}
+
+impl<'a, C: Codec> SearchPlanBuilder<C> for ProfilingPlanBuilder<'a, C> {
+    fn num_docs(&self) -> i32 {
+        self.inner.num_docs()
+    }
+
+    fn max_doc(&self) -> i32 {
+        self.inner.max_doc()
+    }
+
+    fn create_weight(
+        &self,
+        query: &dyn Query<C>,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let (idx, breakdown) = self.profiler.start_node(query.to_string());
+        let start = Instant::now();
+        let res = query.create_weight(self, score_mode);
+        breakdown.record(QueryTimingType::CreateWeight, start);
+        let weight = match res {
+            Ok(w) => w,
+            Err(e) => {
+                self.profiler.stop_node(idx, "");
+                return Err(e);
+            }
+        };
+        self.profiler.stop_node(idx, weight.actual_query_type());
+        Ok(Box::new(ProfileWeight { weight, breakdown }))
+    }
+
+    fn create_normalized_weight(
+        &self,
+        query: &dyn Query<C>,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let mut weight = self.create_weight(query, score_mode)?;
+        let v = weight.value_for_normalization();
+        let norm: f32 = self
+            .similarity("", score_mode.needs_scores())
+            .query_norm(v, None);
+        if norm.is_finite() && (norm - 1.0f32).abs() > f32::EPSILON {
+            weight.normalize(norm, 1.0f32);
+        }
+        Ok(weight)
+    }
+
+    fn similarity(&self, field: &str, needs_scores: bool) -> Box<dyn Similarity<C>> {
+        self.inner.similarity(field, needs_scores)
+    }
+
+    fn term_statistics(&self, term: &Term) -> Result<TermStatistics> {
+        self.inner.term_statistics(term)
+    }
+
+    fn collections_statistics(&self, field: &str) -> Option<&CollectionStatistics> {
+        self.inner.collections_statistics(field)
+    }
+}
+
+/// Weight wrapper that times `create_scorer` and wraps the created scorers
+/// with `ProfileScorer`.
+///
+/// Batch scorers are delegated as is and are not timed.
+pub struct ProfileWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    breakdown: Arc<QueryProfileBreakdown>,
+}
+
+impl<C: Codec> Weight<C> for ProfileWeight<C> {
+    fn create_scorer(&self, reader: &LeafReaderContext<'_, C>) -> Result<Option<Box<dyn Scorer>>> {
+        let start = Instant::now();
+        let scorer = self.weight.create_scorer(reader);
+        self.breakdown.record(QueryTimingType::BuildScorer, start);
+        Ok(scorer?.map(|scorer| -> Box<dyn Scorer> {
+            Box::new(ProfileScorer {
+                scorer,
+                breakdown: Arc::clone(&self.breakdown),
+            })
+        }))
+    }
+
+    fn hash_code(&self) -> u32 {
+        self.weight.hash_code()
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.weight.query_type()
+    }
+
+    fn actual_query_type(&self) -> &'static str {
+        self.weight.actual_query_type()
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.weight.needs_scores()
+    }
+
+    fn create_batch_scorer(&self) -> Option<Box<dyn BatchScorer>> {
+        self.weight.create_batch_scorer()
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        self.weight.explain(reader, doc)
+    }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
+}
+
+impl<C: Codec> fmt::Display for ProfileWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ProfileWeight({})", self.weight)
+    }
+}
+
+/// Scorer wrapper that records the time spent iterating and scoring.
+pub struct ProfileScorer {
+    scorer: Box<dyn Scorer>,
+    breakdown: Arc<QueryProfileBreakdown>,
+}
+
+impl Scorer for ProfileScorer {
+    fn score(&mut self) -> Result<f32> {
+        let start = Instant::now();
+        let score = self.scorer.score();
+        self.breakdown.record(QueryTimingType::Score, start);
+        score
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+
+    fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
+        self.scorer.score_feature()
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.scorer.max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        self.scorer.set_min_competitive_score(min_score)
+    }
+}
+
+impl DocIterator for ProfileScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.next();
+        self.breakdown.record(QueryTimingType::NextDoc, start);
+        doc
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.advance(target);
+        self.breakdown.record(QueryTimingType::Advance, start);
+        doc
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let start = Instant::now();
+        let matches = self.scorer.matches();
+        self.breakdown.record(QueryTimingType::Match, start);
+        matches
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.approximate_next();
+        self.breakdown.record(QueryTimingType::NextDoc, start);
+        doc
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        let start = Instant::now();
+        let doc = self.scorer.approximate_advance(target);
+        self.breakdown.record(QueryTimingType::Advance, start);
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::tests::{MockDocIterator, MockSimpleScorer};
+
+    #[test]
+    fn test_profile_tree() {
+        let profiler = QueryProfiler::default();
+        let (root, root_breakdown) = profiler.start_node("+a +b".to_string());
+        let (a, a_breakdown) = profiler.start_node("a".to_string());
+        profiler.stop_node(a, "TermQuery");
+        let (b, _) = profiler.start_node("b".to_string());
+        profiler.stop_node(b, "TermQuery");
+        profiler.stop_node(root, "BooleanQuery");
+
+        root_breakdown.record(QueryTimingType::CreateWeight, Instant::now());
+        let mut scorer = ProfileScorer {
+            scorer: Box::new(MockSimpleScorer::new(MockDocIterator::new(vec![1, 2, 3]))),
+            breakdown: Arc::clone(&a_breakdown),
+        };
+        scorer.next().unwrap();
+        scorer.score().unwrap();
+        scorer.advance(3).unwrap();
+        scorer.next().unwrap();
+
+        let results = profiler.results();
+        assert_eq!(results.len(), 1);
+        let root = &results[0];
+        assert_eq!(root.query_type, "BooleanQuery");
+        assert_eq!(root.description, "+a +b");
+        assert_eq!(root.count_of(QueryTimingType::CreateWeight), 1);
+        assert_eq!(root.children.len(), 2);
+
+        let a = &root.children[0];
+        assert_eq!(a.query_type, "TermQuery");
+        assert_eq!(a.count_of(QueryTimingType::NextDoc), 2);
+        assert_eq!(a.count_of(QueryTimingType::Advance), 1);
+        assert_eq!(a.count_of(QueryTimingType::Score), 1);
+        assert_eq!(a.count_of(QueryTimingType::BuildScorer), 0);
+        assert_eq!(
+            a.time_nanos,
+            QueryTimingType::ALL
+                .iter()
+                .map(|t| a.time_of(*t))
+                .sum::<u64>()
+        );
+        assert_eq!(root.children[1].description, "b");
+    }
+}
@@ -17,11 +17,15 @@ use std::fmt;
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{
+    ConstantScoreQuery, MatchAllDocsQuery, PhraseQuery, Query, TermQuery, Weight,
+};
 use crate::core::search::scorer::{
-    ConjunctionScorer, DisjunctionSumScorer, ReqNotScorer, ReqOptScorer, Scorer,
+    ConjunctionScorer, DisjunctionSumScorer, ReqNotScorer, ReqOptScorer, Scorer, WANDScorer,
 };
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 use crate::error::{Error::IllegalArgument, Result};
 
@@ -96,30 +100,30 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         let mut must_weights =
             Vec::with_capacity(self.must_queries.len() + self.filter_queries.len());
         for q in &self.must_queries {
-            must_weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
+            must_weights.push(searcher.create_weight(q.as_ref(), score_mode)?);
         }
         for q in &self.filter_queries {
-            must_weights.push(searcher.create_weight(q.as_ref(), false)?);
+            must_weights.push(searcher.create_weight(q.as_ref(), ScoreMode::CompleteNoScores)?);
         }
         let mut should_weights = Vec::with_capacity(self.should_queries.len());
         for q in &self.should_queries {
-            should_weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
+            should_weights.push(searcher.create_weight(q.as_ref(), score_mode)?);
         }
         let mut must_not_weights = Vec::with_capacity(self.must_not_queries.len());
         for q in &self.must_not_queries {
-            must_not_weights.push(searcher.create_weight(q.as_ref(), false)?);
+            must_not_weights.push(searcher.create_weight(q.as_ref(), ScoreMode::CompleteNoScores)?);
         }
 
         Ok(Box::new(BooleanWeight::new(
             must_weights,
             should_weights,
             must_not_weights,
-            needs_scores,
+            score_mode,
             self.min_should_match,
         )))
     }
@@ -142,6 +146,14 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
         term_query_list
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        self.must_queries
+            .iter()
+            .chain(self.should_queries.iter())
+            .flat_map(|query| query.extract_phrases())
+            .collect()
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -166,7 +178,7 @@ struct BooleanWeight<C: Codec> {
     should_weights: Vec<Box<dyn Weight<C>>>,
     must_not_weights: Vec<Box<dyn Weight<C>>>,
     min_should_match: i32,
-    needs_scores: bool,
+    score_mode: ScoreMode,
 }
 
 impl<C: Codec> BooleanWeight<C> {
@@ -174,7 +186,7 @@ impl<C: Codec> BooleanWeight<C> {
         musts: Vec<Box<dyn Weight<C>>>,
         shoulds: Vec<Box<dyn Weight<C>>>,
         must_nots: Vec<Box<dyn Weight<C>>>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
         min_should_match: i32,
     ) -> BooleanWeight<C> {
         BooleanWeight {
@@ -182,7 +194,7 @@ impl<C: Codec> BooleanWeight<C> {
             should_weights: shoulds,
             must_not_weights: must_nots,
             min_should_match,
-            needs_scores,
+            score_mode,
         }
     }
 
@@ -225,9 +237,16 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
                 0 => None,
                 // min_should_match
                 // 1 => Some(scorers.remove(0)),
+                // pure disjunctions may skip non-competitive docs
+                _ if self.must_weights.is_empty()
+                    && self.score_mode == ScoreMode::TopScores
+                    && self.min_should_match <= 1 =>
+                {
+                    Some(Box::new(WANDScorer::new(scorers)))
+                }
                 _ => Some(Box::new(DisjunctionSumScorer::new(
                     scorers,
-                    self.needs_scores,
+                    self.score_mode.needs_scores(),
                     self.min_should_match,
                 ))),
             }
@@ -305,7 +324,7 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
     }
 
     fn needs_scores(&self) -> bool {
-        self.needs_scores
+        self.score_mode.needs_scores()
     }
 
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
@@ -399,6 +418,25 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
             }
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let matched = match self.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+        if !matched {
+            return Ok(None);
+        }
+
+        // prohibited clauses don't contribute any match
+        let mut matches = vec![];
+        for weight in self.must_weights.iter().chain(self.should_weights.iter()) {
+            if let Some(m) = weight.matches(reader, doc)? {
+                matches.push(m);
+            }
+        }
+        Ok(Some(Matches::merge(matches)))
+    }
 }
 
 impl<C: Codec> fmt::Display for BooleanWeight<C> {
@@ -410,7 +448,11 @@ impl<C: Codec> fmt::Display for BooleanWeight<C> {
             f,
             "BooleanWeight(must: [{}], should: [{}], must_not: [{}], min match: {}, needs score: \
              {})",
-            must_str, should_str, must_not_str, self.min_should_match, self.needs_scores
+            must_str,
+            should_str,
+            must_not_str,
+            self.min_should_match,
+            self.score_mode.needs_scores()
         )
     }
 }
@@ -18,9 +18,11 @@ use std::fmt;
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::query::{Query, TermQuery, Weight};
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{PhraseQuery, Query, TermQuery, Weight};
 use crate::core::search::scorer::Scorer;
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 
 use crate::Result;
@@ -51,9 +53,9 @@ impl<C: Codec> Query<C> for BoostQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        let mut weight = self.query.create_weight(searcher, needs_scores)?;
+        let mut weight = self.query.create_weight(searcher, score_mode)?;
         Weight::<C>::normalize(weight.as_mut(), 1.0f32, self.boost);
         // weight.normalize(1.0f32, self.boost);
         Ok(Box::new(BoostWeight::new(weight, self.boost)))
@@ -63,6 +65,10 @@ impl<C: Codec> Query<C> for BoostQuery<C> {
         self.query.extract_terms()
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        self.query.extract_phrases()
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -122,6 +128,10 @@ impl<C: Codec> Weight<C> for BoostWeight<C> {
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
         self.weight.explain(reader, doc)
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for BoostWeight<C> {
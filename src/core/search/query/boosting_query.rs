@@ -17,15 +17,24 @@ use std::fmt;
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::query::{Query, TermQuery, Weight};
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{PhraseQuery, Query, TermQuery, Weight};
 use crate::core::search::scorer::BoostingScorer;
 use crate::core::search::scorer::Scorer;
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
+use crate::error::Error::IllegalArgument;
 use crate::Result;
 
 const BOOSTING_QUERY: &str = "boosting";
 
+/// A query that demotes, rather than excludes, the documents matching the
+/// `negative` query: documents matching `positive` are returned with their
+/// score multiplied by `negative_boost` if they also match `negative`.
+///
+/// This is useful to downrank e.g. stale or low quality content while
+/// still returning it. The `negative` query doesn't contribute to scores.
 pub struct BoostingQuery<C: Codec> {
     positive: Box<dyn Query<C>>,
     negative: Box<dyn Query<C>>,
@@ -37,12 +46,18 @@ impl<C: Codec> BoostingQuery<C> {
         positive: Box<dyn Query<C>>,
         negative: Box<dyn Query<C>>,
         negative_boost: f32,
-    ) -> Box<dyn Query<C>> {
-        Box::new(BoostingQuery {
+    ) -> Result<Box<dyn Query<C>>> {
+        if !(0.0..=1.0).contains(&negative_boost) {
+            return Err(IllegalArgument(format!(
+                "negative_boost must be in [0, 1], got {}",
+                negative_boost
+            )));
+        }
+        Ok(Box::new(BoostingQuery {
             positive,
             negative,
             negative_boost,
-        })
+        }))
     }
 }
 
@@ -50,11 +65,17 @@ impl<C: Codec> Query<C> for BoostingQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
+        let positive = self.positive.create_weight(searcher, score_mode)?;
+        if !score_mode.needs_scores() {
+            // the negative query only affects scores
+            return Ok(positive);
+        }
         Ok(Box::new(BoostingWeight::new(
-            self.positive.create_weight(searcher, needs_scores)?,
-            self.negative.create_weight(searcher, false)?,
+            positive,
+            self.negative
+                .create_weight(searcher, ScoreMode::CompleteNoScores)?,
             self.negative_boost,
         )))
     }
@@ -63,6 +84,10 @@ impl<C: Codec> Query<C> for BoostingQuery<C> {
         self.positive.extract_terms()
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        self.positive.extract_phrases()
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -103,17 +128,18 @@ impl<C: Codec> Weight<C> for BoostingWeight<C> {
         &self,
         leaf_reader: &LeafReaderContext<'_, C>,
     ) -> Result<Option<Box<dyn Scorer>>> {
-        if let (Some(positive_scorer), Some(negative_scorer)) = (
-            self.positive_weight.create_scorer(leaf_reader)?,
-            self.negative_weight.create_scorer(leaf_reader)?,
-        ) {
-            Ok(Some(Box::new(BoostingScorer::new(
+        let positive_scorer = match self.positive_weight.create_scorer(leaf_reader)? {
+            Some(scorer) => scorer,
+            None => return Ok(None),
+        };
+        match self.negative_weight.create_scorer(leaf_reader)? {
+            Some(negative_scorer) => Ok(Some(Box::new(BoostingScorer::new(
                 positive_scorer,
                 negative_scorer,
                 self.negative_boost,
-            ))))
-        } else {
-            Ok(None)
+            )))),
+            // no doc of this segment is demoted
+            None => Ok(Some(positive_scorer)),
         }
     }
 
@@ -138,7 +164,36 @@ impl<C: Codec> Weight<C> for BoostingWeight<C> {
     }
 
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
-        self.positive_weight.explain(reader, doc)
+        let positive = self.positive_weight.explain(reader, doc)?;
+        if !positive.is_match() {
+            return Ok(positive);
+        }
+        let demoted = match self.negative_weight.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+        if !demoted {
+            return Ok(positive);
+        }
+        Ok(Explanation::new(
+            true,
+            positive.value() * self.negative_boost,
+            "product of:".to_string(),
+            vec![
+                positive,
+                Explanation::new(
+                    true,
+                    self.negative_boost,
+                    "negative_boost, matched the negative query".to_string(),
+                    vec![],
+                ),
+            ],
+        ))
+    }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        // the negative query only affects the score
+        self.positive_weight.matches(reader, doc)
     }
 }
 
@@ -151,3 +206,25 @@ impl<C: Codec> fmt::Display for BoostingWeight<C> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::CodecEnum;
+    use crate::core::index::Term;
+
+    fn term_query(text: &str) -> Box<dyn Query<CodecEnum>> {
+        Box::new(TermQuery::new(
+            Term::new("body".into(), text.as_bytes().to_vec()),
+            1.0,
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_build() {
+        assert!(BoostingQuery::build(term_query("a"), term_query("b"), 0.5).is_ok());
+        assert!(BoostingQuery::build(term_query("a"), term_query("b"), 1.5).is_err());
+        assert!(BoostingQuery::build(term_query("a"), term_query("b"), f32::NAN).is_err());
+    }
+}
@@ -14,9 +14,11 @@
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::query::{Query, TermQuery, Weight};
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{PhraseQuery, Query, TermQuery, Weight};
 use crate::core::search::scorer::{DisjunctionMaxScorer, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 
 use crate::error::Error::IllegalArgument;
@@ -74,17 +76,17 @@ impl<C: Codec> Query<C> for DisjunctionMaxQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         let mut weights = Vec::with_capacity(self.disjuncts.len());
         for q in &self.disjuncts {
-            weights.push(q.create_weight(searcher, needs_scores)?);
+            weights.push(q.create_weight(searcher, score_mode)?);
         }
 
         Ok(Box::new(DisjunctionMaxWeight::new(
             weights,
             self.tie_breaker_multiplier,
-            needs_scores,
+            score_mode.needs_scores(),
         )))
     }
 
@@ -96,6 +98,13 @@ impl<C: Codec> Query<C> for DisjunctionMaxQuery<C> {
         queries
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        self.disjuncts
+            .iter()
+            .flat_map(|q| q.extract_phrases())
+            .collect()
+    }
+
     fn as_any(&self) -> &dyn (::std::any::Any) {
         self
     }
@@ -224,6 +233,20 @@ impl<C: Codec> Weight<C> for DisjunctionMaxWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let mut matches = vec![];
+        for w in &self.weights {
+            if let Some(m) = w.matches(reader, doc)? {
+                matches.push(m);
+            }
+        }
+        if matches.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Matches::merge(matches)))
+        }
+    }
 }
 
 impl<C: Codec> fmt::Display for DisjunctionMaxWeight<C> {
@@ -22,6 +22,7 @@ use crate::core::search::query::{Query, TermQuery, Weight};
 use crate::core::search::scorer::ConstantScoreScorer;
 use crate::core::search::scorer::Scorer;
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 use crate::Result;
 
@@ -41,7 +42,7 @@ impl<C: Codec> Query<C> for ExistsQuery {
     fn create_weight(
         &self,
         _searcher: &dyn SearchPlanBuilder<C>,
-        _needs_scores: bool,
+        _score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         Ok(Box::new(ExistsWeight::new(self.field.clone())))
     }
@@ -14,10 +14,11 @@
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::query::{Query, TermQuery, Weight};
+use crate::core::search::matches::Matches;
+use crate::core::search::query::{PhraseQuery, Query, TermQuery, Weight};
 use crate::core::search::scorer::{FeatureResult, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
-use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::search::{DocIterator, ScoreMode, NO_MORE_DOCS};
 use crate::core::util::DocId;
 use crate::core::util::IndexedContext;
 use crate::Result;
@@ -55,14 +56,14 @@ impl<C: Codec> Query<C> for FilterQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         let mut filters = Vec::with_capacity(self.filters.len());
         for f in &self.filters {
             filters.push(Arc::clone(f));
         }
         Ok(Box::new(FilterWeight {
-            weight: self.query.create_weight(searcher, needs_scores)?,
+            weight: self.query.create_weight(searcher, score_mode)?,
             filters,
         }))
     }
@@ -71,6 +72,10 @@ impl<C: Codec> Query<C> for FilterQuery<C> {
         self.query.extract_terms()
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        self.query.extract_phrases()
+    }
+
     fn as_any(&self) -> &dyn (::std::any::Any) {
         self
     }
@@ -133,6 +138,19 @@ impl<C: Codec> Weight<C> for FilterWeight<C> {
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
         self.weight.explain(reader, doc)
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        // the filters don't contribute any match
+        let matched = match self.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+        if matched {
+            self.weight.matches(reader, doc)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 impl<C: Codec> fmt::Display for FilterWeight<C> {
@@ -14,10 +14,11 @@
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::Matches;
 use crate::core::search::query::{Query, TermQuery, Weight};
 use crate::core::search::scorer::{two_phase_next, ConstantScoreScorer, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
-use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::search::{DocIterator, ScoreMode, NO_MORE_DOCS};
 use crate::core::util::DocId;
 use crate::Result;
 use std::fmt;
@@ -31,7 +32,7 @@ impl<C: Codec> Query<C> for MatchAllDocsQuery {
     fn create_weight(
         &self,
         _searcher: &dyn SearchPlanBuilder<C>,
-        _needs_scores: bool,
+        _score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         Ok(Box::new(MatchAllDocsWeight::default()))
     }
@@ -170,12 +171,14 @@ pub struct ConstantScoreQuery<C: Codec> {
 
 impl<C: Codec> From<Box<dyn Query<C>>> for ConstantScoreQuery<C> {
     fn from(value: Box<dyn Query<C>>) -> Self {
-        Self { query: value, boost: 0_f32 }
+        Self {
+            query: value,
+            boost: 0_f32,
+        }
     }
 }
 
 impl<C: Codec> ConstantScoreQuery<C> {
-
     pub fn with_boost(query: Box<dyn Query<C>>, boost: f32) -> ConstantScoreQuery<C> {
         ConstantScoreQuery { query, boost }
     }
@@ -199,10 +202,10 @@ impl<C: Codec> Query<C> for ConstantScoreQuery<C> {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        let weight = searcher.create_weight(self.query.as_ref(), false)?;
-        if needs_scores {
+        let weight = searcher.create_weight(self.query.as_ref(), ScoreMode::CompleteNoScores)?;
+        if score_mode.needs_scores() {
             Ok(Box::new(ConstantScoreWeight::new(weight, self.boost)))
         } else {
             Ok(weight)
@@ -295,6 +298,10 @@ impl<C: Codec> Weight<C> for ConstantScoreWeight<C> {
             ))
         }
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        self.sub_weight.matches(reader, doc)
+    }
 }
 
 impl<C: Codec> fmt::Display for ConstantScoreWeight<C> {
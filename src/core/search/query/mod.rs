@@ -38,10 +38,18 @@ mod point_range_query;
 
 pub use self::point_range_query::*;
 
+mod range_field_query;
+
+pub use self::range_field_query::*;
+
 mod query_string;
 
 pub use self::query_string::*;
 
+mod query_dsl;
+
+pub use self::query_dsl::*;
+
 mod term_query;
 
 pub use self::term_query::*;
@@ -61,8 +69,10 @@ pub use self::exists_query::*;
 use crate::core::codec::Codec;
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::Matches;
 use crate::core::search::scorer::{BatchScorer, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::{DocIterator, ScoreMode};
 use crate::core::util::DocId;
 
 use crate::Result;
@@ -101,16 +111,23 @@ use std::hash::{Hash, Hasher};
 ///
 /// See also the family of [`Span Queries`](spans/index.html)
 pub trait Query<C: Codec>: Display {
-    /// Create new `Scorer` based on query.
+    /// Create new `Weight` based on query, `score_mode` tells whether scores
+    /// are needed and whether the scorers may skip non-competitive docs.
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>>;
 
     /// For highlight use.
     fn extract_terms(&self) -> Vec<TermQuery>;
 
+    /// For highlight use, the phrases that must match as a whole, their
+    /// terms are part of `extract_terms` too.
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        vec![]
+    }
+
     fn as_any(&self) -> &dyn Any;
 }
 
@@ -165,6 +182,21 @@ pub trait Weight<C: Codec>: Display {
         None
     }
 
-    /// An explanation of the score computation for the named document.
+    /// An explanation of the score computation for the named document,
+    /// `doc` is relative to the given leaf `reader`.
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation>;
+
+    /// Returns the `Matches` of the query for the leaf-relative document `doc`,
+    /// or `None` if the document doesn't match.
+    ///
+    /// The default implementation only checks whether the document matches and
+    /// returns `Matches` without position information.
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        if let Some(mut scorer) = self.create_scorer(reader)? {
+            if scorer.advance(doc)? == doc {
+                return Ok(Some(Matches::default()));
+            }
+        }
+        Ok(None)
+    }
 }
@@ -15,17 +15,18 @@ use std::boxed::Box;
 use std::fmt;
 
 use crate::core::codec::Codec;
-use crate::core::codec::PostingIteratorFlags;
+use crate::core::codec::{PostingIterator, PostingIteratorFlags};
 use crate::core::codec::{TermIterator, Terms};
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::index::Term;
 use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::{MatchPosition, Matches};
 use crate::core::search::query::{Query, TermQuery, Weight};
 use crate::core::search::scorer::{ExactPhraseScorer, PostingsAndFreq, Scorer, SloppyPhraseScorer};
 use crate::core::search::searcher::SearchPlanBuilder;
 use crate::core::search::similarity::{SimWeight, Similarity};
 use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
-use crate::core::search::DocIterator;
+use crate::core::search::{DocIterator, ScoreMode};
 use crate::core::util::{DocId, KeyedContext};
 use crate::error::{Error, Result};
 
@@ -130,14 +131,31 @@ impl PhraseQuery {
     fn increment_positions(length: usize) -> Vec<i32> {
         (0..length as i32).collect()
     }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn positions(&self) -> &[i32] {
+        &self.positions
+    }
+
+    pub fn slop(&self) -> i32 {
+        self.slop
+    }
 }
 
 impl<C: Codec> Query<C> for PhraseQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
+        let needs_scores = score_mode.needs_scores();
         debug_assert!(
             self.positions.len() >= 2,
             "PhraseWeight does not support less than 2 terms, call rewrite first"
@@ -203,6 +221,10 @@ impl<C: Codec> Query<C> for PhraseQuery {
         term_query_list
     }
 
+    fn extract_phrases(&self) -> Vec<PhraseQuery> {
+        vec![self.clone()]
+    }
+
     fn as_any(&self) -> &dyn (::std::any::Any) {
         self
     }
@@ -266,6 +288,32 @@ impl<C: Codec> PhraseWeight<C> {
         Ok(TERM_POSNS_SEEK_OPS_PER_DOC as f32
             + exp_occurrences_in_matching_doc * TERM_OPS_PER_POS as f32)
     }
+
+    /// Read the positions and offsets of each of the terms in `doc`.
+    fn term_positions(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+        doc: DocId,
+    ) -> Result<Vec<Vec<MatchPosition>>> {
+        let mut term_positions = Vec::with_capacity(self.terms.len());
+        if let Some(field_terms) = reader.reader.terms(&self.field)? {
+            let mut term_iter = field_terms.iterator()?;
+            for term in &self.terms {
+                let mut positions = vec![];
+                if term_iter.seek_exact(term.bytes())? {
+                    let mut postings =
+                        term_iter.postings_with_flags(PostingIteratorFlags::OFFSETS)?;
+                    if postings.advance(doc)? == doc {
+                        for _ in 0..postings.freq()? {
+                            positions.push(MatchPosition::from_postings(&mut postings)?);
+                        }
+                    }
+                }
+                term_positions.push(positions);
+            }
+        }
+        Ok(term_positions)
+    }
 }
 
 impl<C: Codec> Weight<C> for PhraseWeight<C> {
@@ -431,6 +479,55 @@ impl<C: Codec> Weight<C> for PhraseWeight<C> {
             vec![],
         ))
     }
+
+    /// Exact phrases report one match per phrase occurrence, from its first to
+    /// its last term. Sloppy phrases report the positions of the individual terms.
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let matched = match self.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+        if !matched {
+            return Ok(None);
+        }
+
+        let term_positions = self.term_positions(reader, doc)?;
+        if self.slop != 0 {
+            let positions = term_positions.into_iter().flatten().collect();
+            return Ok(Some(Matches::for_field(self.field.clone(), positions)));
+        }
+
+        let mut positions = vec![];
+        'outer: for first in &term_positions[0] {
+            let phrase_start = first.start_position - self.positions[0];
+            let mut start = *first;
+            let mut end = *first;
+            for i in 1..self.terms.len() {
+                let expected = phrase_start + self.positions[i];
+                match term_positions[i]
+                    .iter()
+                    .find(|p| p.start_position == expected)
+                {
+                    Some(p) => {
+                        if p.start_position < start.start_position {
+                            start = *p;
+                        }
+                        if p.end_position > end.end_position {
+                            end = *p;
+                        }
+                    }
+                    None => continue 'outer,
+                }
+            }
+            positions.push(MatchPosition::new(
+                start.start_position,
+                end.end_position,
+                start.start_offset,
+                end.end_offset,
+            ));
+        }
+        Ok(Some(Matches::for_field(self.field.clone(), positions)))
+    }
 }
 
 impl<C: Codec> fmt::Display for PhraseWeight<C> {
@@ -21,7 +21,7 @@ use crate::core::search::explanation::Explanation;
 use crate::core::search::query::{AllDocsIterator, Query, TermQuery, Weight};
 use crate::core::search::scorer::{ConstantScoreScorer, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
-use crate::core::search::{DocIdSet, DocIterator, EmptyDocIterator};
+use crate::core::search::{DocIdSet, DocIterator, EmptyDocIterator, ScoreMode};
 use crate::core::util::*;
 
 use num_traits::float::Float;
@@ -413,7 +413,7 @@ impl<C: Codec> Query<C> for PointRangeQuery {
     fn create_weight(
         &self,
         _searcher: &dyn SearchPlanBuilder<C>,
-        _needs_scores: bool,
+        _score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         Ok(Box::new(PointRangeWeight::new(
             self.field.clone(),
@@ -460,6 +460,7 @@ struct PointRangeWeight {
     upper_point: Vec<u8>,
     value_type: PointValueType,
     weight: f32,
+    boost: f32,
     norm: f32,
 }
 
@@ -479,7 +480,10 @@ impl PointRangeWeight {
             lower_point,
             upper_point,
             value_type,
-            weight: 0f32,
+            // searchers only normalize weights for similarities with a query
+            // norm, so start out as normalized by `(1.0, 1.0)`
+            weight: 1f32,
+            boost: 1f32,
             norm: 1f32,
         }
     }
@@ -507,10 +511,10 @@ impl<C: Codec> Weight<C> for PointRangeWeight {
         let leaf_reader = leaf_reader_ctx.reader;
         if let Some(ref values) = leaf_reader.point_values() {
             if let Some(field_info) = leaf_reader.field_info(&self.field) {
-                if field_info.point_dimension_count != self.num_dims as u32 {
+                if field_info.point_index_dimension_count != self.num_dims as u32 {
                     return Err(Error::IllegalArgument(format!(
                         "field '{}' was indexed with num_dims={} but this query has num_dims={}",
-                        &self.field, field_info.point_dimension_count, self.num_dims
+                        &self.field, field_info.point_index_dimension_count, self.num_dims
                     )));
                 }
                 if self.bytes_per_dim as u32 != field_info.point_num_bytes {
@@ -566,6 +570,7 @@ impl<C: Codec> Weight<C> for PointRangeWeight {
 
     fn normalize(&mut self, norm: f32, boost: f32) {
         self.weight = norm * boost;
+        self.boost = boost;
         self.norm = norm;
     }
 
@@ -577,8 +582,30 @@ impl<C: Codec> Weight<C> for PointRangeWeight {
         false
     }
 
-    fn explain(&self, _reader: &LeafReaderContext<'_, C>, _doc: DocId) -> Result<Explanation> {
-        unimplemented!()
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let matched = match self.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+
+        if matched {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.boost, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
     }
 }
 
@@ -668,7 +695,7 @@ impl<'a> IntersectVisitor for PointRangeIntersectVisitor<'a> {
     }
 }
 
-enum PointDocIterEnum {
+pub(crate) enum PointDocIterEnum {
     DocSet(DocIdSetDocIterEnum),
     All(AllDocsIterator),
     None(EmptyDocIterator),
@@ -747,3 +774,56 @@ impl DocIterator for PointDocIterEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::CodecEnum;
+    use crate::core::doc::{Field, FieldType};
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_point_range_explain() {
+        let writer = in_memory_writer();
+        let field_type = FieldType {
+            tokenized: false,
+            dimension_count: 1,
+            index_dimension_count: 1,
+            dimension_num_bytes: 8,
+            ..Default::default()
+        };
+        for i in 0..10 {
+            writer
+                .add_document(vec![Field::new_bytes(
+                    "num".into(),
+                    LongPoint::pack(&[i]),
+                    field_type.clone(),
+                )])
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher =
+            DefaultIndexSearcher::new(Arc::new(writer.get_reader(true, false).unwrap()), None);
+        let query: Box<dyn Query<CodecEnum>> =
+            LongPoint::new_range_query("num".into(), 3, 5).unwrap();
+
+        let explanation = searcher.explain(query.as_ref(), 4).unwrap();
+        assert!(explanation.is_match());
+        assert!(explanation.value() > 0.0);
+        assert!(explanation.description().ends_with("product of:"));
+        let details = explanation.details();
+        assert_eq!(details.len(), 2);
+        assert_eq!(details[0].value() * details[1].value(), explanation.value());
+
+        let explanation = searcher.explain(query.as_ref(), 7).unwrap();
+        assert!(!explanation.is_match());
+        assert_eq!(explanation.value(), 0.0);
+        assert!(explanation.description().ends_with("doesn't match id 7"));
+
+        assert!(searcher.explain(query.as_ref(), 10).is_err());
+        assert!(searcher.explain(query.as_ref(), -1).is_err());
+    }
+}
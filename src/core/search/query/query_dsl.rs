@@ -0,0 +1,265 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::index::Term;
+use crate::core::search::query::{
+    BooleanQuery, BoostQuery, MatchAllDocsQuery, PhraseQuery, Query, TermQuery,
+};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use serde::{Deserialize, Serialize};
+
+/// The JSON form of a query, to store queries or receive them from clients.
+///
+/// The values are terms as indexed, i.e. already analyzed:
+///
+/// ```json
+/// {"bool": {
+///     "must": [{"term": {"field": "title", "value": "rust"}}],
+///     "should": [{"phrase": {"field": "body", "terms": ["search", "engine"]}}],
+///     "must_not": [{"terms": {"field": "tag", "values": ["draft", "spam"]}}]
+/// }}
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryDsl {
+    MatchAll {},
+    Term {
+        field: String,
+        value: String,
+    },
+    /// Matches the documents having any of the values.
+    Terms {
+        field: String,
+        values: Vec<String>,
+    },
+    Phrase {
+        field: String,
+        terms: Vec<String>,
+        #[serde(default)]
+        slop: i32,
+    },
+    Bool {
+        #[serde(default)]
+        must: Vec<QueryDsl>,
+        #[serde(default)]
+        should: Vec<QueryDsl>,
+        #[serde(default)]
+        filter: Vec<QueryDsl>,
+        #[serde(default)]
+        must_not: Vec<QueryDsl>,
+        #[serde(default)]
+        minimum_should_match: i32,
+    },
+    Boost {
+        query: Box<QueryDsl>,
+        boost: f32,
+    },
+}
+
+impl QueryDsl {
+    pub fn from_json(json: &str) -> Result<QueryDsl> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Builds the query described by this DSL.
+    pub fn build<C: Codec>(&self) -> Result<Box<dyn Query<C>>> {
+        match self {
+            QueryDsl::MatchAll {} => Ok(Box::new(MatchAllDocsQuery)),
+            QueryDsl::Term { field, value } => Ok(Self::term_query(field, value)),
+            QueryDsl::Terms { field, values } => {
+                if values.is_empty() {
+                    return Err(IllegalArgument(format!(
+                        "terms query on '{}' has no values",
+                        field
+                    )));
+                }
+                let shoulds = values.iter().map(|v| Self::term_query(field, v)).collect();
+                BooleanQuery::build(vec![], shoulds, vec![], vec![], 1)
+            }
+            QueryDsl::Phrase { field, terms, slop } => match terms.len() {
+                0 => Err(IllegalArgument(format!(
+                    "phrase query on '{}' has no terms",
+                    field
+                ))),
+                1 => Ok(Self::term_query(field, &terms[0])),
+                _ => {
+                    let terms = terms
+                        .iter()
+                        .map(|t| Term::new(field.clone(), t.as_bytes().to_vec()))
+                        .collect();
+                    Ok(Box::new(PhraseQuery::build(terms, *slop, None, None)?))
+                }
+            },
+            QueryDsl::Bool {
+                must,
+                should,
+                filter,
+                must_not,
+                minimum_should_match,
+            } => {
+                let build_all = |queries: &[QueryDsl]| -> Result<Vec<Box<dyn Query<C>>>> {
+                    queries.iter().map(QueryDsl::build).collect()
+                };
+                BooleanQuery::build(
+                    build_all(must)?,
+                    build_all(should)?,
+                    build_all(filter)?,
+                    build_all(must_not)?,
+                    *minimum_should_match,
+                )
+            }
+            QueryDsl::Boost { query, boost } => Ok(BoostQuery::build(query.build()?, *boost)),
+        }
+    }
+
+    fn term_query<C: Codec>(field: &str, value: &str) -> Box<dyn Query<C>> {
+        Box::new(TermQuery::new(
+            Term::new(field.to_string(), value.as_bytes().to_vec()),
+            1.0,
+            None,
+        ))
+    }
+
+    /// Returns terms such that a document can only match this query if it
+    /// contains one of them, or `None` if there is no such set, e.g. for a
+    /// `match_all` or a query having only `must_not` clauses.
+    ///
+    /// A required clause is enough for a boolean query, the one with the
+    /// fewest and then the longest terms is picked as longer terms tend to be
+    /// rarer.
+    pub fn extract_terms(&self) -> Option<Vec<Term>> {
+        match self {
+            QueryDsl::MatchAll {} => None,
+            QueryDsl::Term { field, value } => {
+                Some(vec![Term::new(field.clone(), value.as_bytes().to_vec())])
+            }
+            QueryDsl::Terms { field, values } => Some(
+                values
+                    .iter()
+                    .map(|v| Term::new(field.clone(), v.as_bytes().to_vec()))
+                    .collect(),
+            ),
+            // all the terms are required, the longest one is enough
+            QueryDsl::Phrase { field, terms, .. } => terms
+                .iter()
+                .max_by_key(|t| t.len())
+                .map(|t| vec![Term::new(field.clone(), t.as_bytes().to_vec())]),
+            QueryDsl::Bool {
+                must,
+                should,
+                filter,
+                ..
+            } => {
+                let required = must
+                    .iter()
+                    .chain(filter)
+                    .filter_map(QueryDsl::extract_terms)
+                    .min_by_key(|terms| {
+                        let shortest = terms.iter().map(|t| t.bytes.len()).min().unwrap_or(0);
+                        (terms.len(), usize::MAX - shortest)
+                    });
+                if required.is_some() {
+                    return required;
+                }
+                if !must.is_empty() || !filter.is_empty() || should.is_empty() {
+                    return None;
+                }
+                // at least one should clause must match
+                let mut terms = vec![];
+                for clause in should {
+                    terms.extend(clause.extract_terms()?);
+                }
+                Some(terms)
+            }
+            QueryDsl::Boost { query, .. } => query.extract_terms(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: &str, value: &str) -> Term {
+        Term::new(field.into(), value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_query_dsl_json() -> Result<()> {
+        let json = r#"{"bool": {
+            "must": [{"term": {"field": "title", "value": "rust"}}],
+            "should": [{"phrase": {"field": "body", "terms": ["search", "engine"]}}],
+            "must_not": [{"terms": {"field": "tag", "values": ["draft"]}}]
+        }}"#;
+        let dsl = QueryDsl::from_json(json)?;
+        assert_eq!(QueryDsl::from_json(&dsl.to_json()?)?, dsl);
+        match &dsl {
+            QueryDsl::Bool {
+                must,
+                minimum_should_match,
+                ..
+            } => {
+                assert_eq!(must.len(), 1);
+                assert_eq!(*minimum_should_match, 0);
+            }
+            _ => panic!("expected a bool query, got {:?}", dsl),
+        }
+        assert!(QueryDsl::from_json(r#"{"unknown": {}}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_terms() -> Result<()> {
+        let dsl = QueryDsl::from_json(
+            r#"{"bool": {
+                "must": [
+                    {"terms": {"field": "tag", "values": ["a", "b"]}},
+                    {"phrase": {"field": "body", "terms": ["quick", "fox"]}}
+                ],
+                "must_not": [{"term": {"field": "tag", "value": "c"}}]
+            }}"#,
+        )?;
+        assert_eq!(dsl.extract_terms(), Some(vec![term("body", "quick")]));
+
+        let dsl = QueryDsl::from_json(
+            r#"{"bool": {"should": [
+                {"term": {"field": "tag", "value": "a"}},
+                {"boost": {"query": {"term": {"field": "tag", "value": "b"}}, "boost": 2.0}}
+            ]}}"#,
+        )?;
+        assert_eq!(
+            dsl.extract_terms(),
+            Some(vec![term("tag", "a"), term("tag", "b")])
+        );
+
+        let dsl = QueryDsl::from_json(
+            r#"{"bool": {"should": [
+                {"term": {"field": "tag", "value": "a"}},
+                {"match_all": {}}
+            ]}}"#,
+        )?;
+        assert_eq!(dsl.extract_terms(), None);
+        let dsl = QueryDsl::from_json(
+            r#"{"bool": {"must_not": [{"term": {"field": "tag", "value": "a"}}]}}"#,
+        )?;
+        assert_eq!(dsl.extract_terms(), None);
+        Ok(())
+    }
+}
@@ -12,14 +12,18 @@
 // limitations under the License.
 
 use crate::error::{Error::IllegalArgument, Result};
+use std::collections::HashMap;
 use std::option::Option::{None, Some};
 use std::result::Result::Ok;
 use std::str::Chars;
 use std::vec::Vec;
 
 use crate::core::codec::Codec;
+use crate::core::doc::{DateTools, Resolution};
 use crate::core::index::Term;
-use crate::core::search::query::{BooleanQuery, BoostQuery, PhraseQuery, Query, TermQuery};
+use crate::core::search::query::{
+    BooleanQuery, BoostQuery, LongPoint, PhraseQuery, Query, TermQuery,
+};
 
 /// A query that parses a query string and runs it.
 ///
@@ -32,6 +36,7 @@ pub struct QueryStringQueryBuilder {
     min_should_match: i32,
     #[allow(dead_code)]
     boost: f32,
+    date_fields: HashMap<String, Resolution>,
 }
 
 impl QueryStringQueryBuilder {
@@ -46,9 +51,21 @@ impl QueryStringQueryBuilder {
             fields,
             min_should_match,
             boost,
+            date_fields: HashMap::new(),
         }
     }
 
+    /// Declares a `DatePoint` field indexed with the given resolution, so that
+    /// ranges like `published:[2020-01 TO 2020-06-15}` can be searched on it.
+    ///
+    /// `[` and `]` make a bound inclusive, `{` and `}` exclusive, and `*` leaves it
+    /// open. Bounds are parsed with `DateTools::parse` and cover their whole
+    /// period, e.g. an inclusive upper bound of `2020-06` matches all of June.
+    pub fn with_date_field(mut self, field: &str, resolution: Resolution) -> Self {
+        self.date_fields.insert(field.to_string(), resolution);
+        self
+    }
+
     pub fn build<C: Codec>(&self) -> Result<Box<dyn Query<C>>> {
         match self.parse_query(&mut self.query_string.chars(), None) {
             Ok(Some(q)) => Ok(q),
@@ -128,7 +145,17 @@ impl QueryStringQueryBuilder {
                     let mut term_chars = Vec::new();
                     term_chars.push(ch);
                     let mut should_return = false;
+                    let mut in_range = false;
                     while let Some(c) = chars.next() {
+                        if in_range {
+                            // ranges contain spaces, read up to the closing bracket
+                            term_chars.push(c);
+                            in_range = c != ']' && c != '}';
+                            continue;
+                        }
+                        if (c == '[' || c == '{') && term_chars.last() == Some(&':') {
+                            in_range = true;
+                        }
                         if c == ' ' {
                             break;
                         }
@@ -181,6 +208,9 @@ impl QueryStringQueryBuilder {
     }
 
     fn build_field_query<C: Codec>(&self, term_boost: String) -> Result<Box<dyn Query<C>>> {
+        if let Some(query) = self.range_query(&term_boost)? {
+            return Ok(query);
+        }
         let mut queries = if term_boost.find('~').is_some() {
             self.field_phrase_query(&term_boost)?
         } else {
@@ -216,6 +246,60 @@ impl QueryStringQueryBuilder {
         Ok(queries)
     }
 
+    fn range_query<C: Codec>(&self, query: &str) -> Result<Option<Box<dyn Query<C>>>> {
+        let idx = match query.find(":[").or_else(|| query.find(":{")) {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        let (field, range) = (&query[..idx], &query[idx + 1..]);
+        let resolution = match self.date_fields.get(field) {
+            Some(resolution) => *resolution,
+            None => {
+                return Err(IllegalArgument(format!(
+                    "range queries are only supported on date fields, '{}' isn't one",
+                    field
+                )));
+            }
+        };
+        let upper_inclusive = match range.chars().last() {
+            Some(']') if range.len() > 1 => true,
+            Some('}') if range.len() > 1 => false,
+            _ => {
+                return Err(IllegalArgument(format!("invalid range query '{}'", query)));
+            }
+        };
+        let lower_inclusive = range.starts_with('[');
+        let bounds: Vec<&str> = range[1..range.len() - 1].split(" TO ").collect();
+        if bounds.len() != 2 {
+            return Err(IllegalArgument(format!("invalid range query '{}'", query)));
+        }
+
+        // indexed dates are rounded to the field's resolution
+        let lower = match bounds[0].trim() {
+            "*" => i64::MIN,
+            s => {
+                let (start, precision) = DateTools::parse(s)?;
+                if lower_inclusive {
+                    DateTools::round(start, resolution)
+                } else {
+                    DateTools::round(DateTools::end_of(start, precision), resolution) + 1
+                }
+            }
+        };
+        let upper = match bounds[1].trim() {
+            "*" => i64::MAX,
+            s => {
+                let (start, precision) = DateTools::parse(s)?;
+                if upper_inclusive {
+                    DateTools::round(DateTools::end_of(start, precision), resolution)
+                } else {
+                    DateTools::round(start, resolution) - 1
+                }
+            }
+        };
+        LongPoint::new_range_query(field.to_string(), lower, upper).map(Some)
+    }
+
     fn field_phrase_query<C: Codec>(&self, query: &str) -> Result<Vec<Box<dyn Query<C>>>> {
         if let Some(idx) = query.find('~') {
             let (t, s) = query.split_at(idx);
@@ -410,4 +494,39 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_date_range_query_string() {
+        let build = |query_string: &str| -> Result<Box<dyn Query<TestCodec>>> {
+            QueryStringQueryBuilder::new(query_string.into(), vec![("title".into(), 1.0)], 0, 1.0)
+                .with_date_field("published", Resolution::Day)
+                .build()
+        };
+
+        let q = build("test +published:[2020-01 TO 2020-01-15}").unwrap();
+        assert_eq!(
+            q.to_string(),
+            String::from(
+                "BooleanQuery(must: [PointRangeQuery(field: published, type:long, num_dims: 1, \
+                 bytes_per_dim: 8, lower: [1577836800000], upper: [1579046399999])], should: \
+                 [TermQuery(field: title, term: test, boost: 1)], filters: [], must_not: [], \
+                 match: 0)",
+            )
+        );
+
+        // an exclusive lower bound starts after its whole period
+        let q = build("(published:{20200115 TO *])").unwrap();
+        assert_eq!(
+            q.to_string(),
+            format!(
+                "PointRangeQuery(field: published, type:long, num_dims: 1, bytes_per_dim: 8, \
+                 lower: [1579046400001], upper: [{}])",
+                i64::MAX
+            )
+        );
+
+        assert!(build("title:[2020 TO 2021]").is_err());
+        assert!(build("published:[2020 TO]").is_err());
+        assert!(build("published:[2020 TO tomorrow]").is_err());
+    }
 }
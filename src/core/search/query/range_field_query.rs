@@ -0,0 +1,390 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::{Error, Result};
+use std::fmt;
+
+use crate::core::codec::points::{IntersectVisitor, PointValues, Relation};
+use crate::core::codec::Codec;
+use crate::core::index::reader::LeafReaderContext;
+use crate::core::search::explanation::Explanation;
+use crate::core::search::query::{
+    AllDocsIterator, PointDocIterEnum, PointValueType, Query, TermQuery, Weight,
+};
+use crate::core::search::scorer::{ConstantScoreScorer, Scorer};
+use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::{DocIdSet, DocIterator, EmptyDocIterator, ScoreMode};
+use crate::core::util::{DocId, DocIdSetBuilder};
+
+/// How the range indexed for a document must relate to the query range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RangeRelation {
+    /// The document range shares at least one value with the query range.
+    Intersects,
+    /// The document range fully contains the query range.
+    Contains,
+    /// The document range is fully within the query range.
+    Within,
+}
+
+impl RangeRelation {
+    /// Relation of the cell holding all documents ranges with mins in
+    /// `[min_mins, max_mins]` and maxs in `[min_maxs, max_maxs]` to the query range
+    /// `[q_min, q_max]`, for a single dimension.
+    fn compare(
+        self,
+        min_mins: &[u8],
+        max_mins: &[u8],
+        min_maxs: &[u8],
+        max_maxs: &[u8],
+        q_min: &[u8],
+        q_max: &[u8],
+    ) -> Relation {
+        let (outside, inside) = match self {
+            RangeRelation::Intersects => (
+                min_mins > q_max || max_maxs < q_min,
+                max_mins <= q_max && min_maxs >= q_min,
+            ),
+            RangeRelation::Contains => (
+                min_mins > q_min || max_maxs < q_max,
+                max_mins <= q_min && min_maxs >= q_max,
+            ),
+            RangeRelation::Within => (
+                max_mins < q_min || min_maxs > q_max,
+                min_mins >= q_min && max_maxs <= q_max,
+            ),
+        };
+        if outside {
+            Relation::CellOutsideQuery
+        } else if inside {
+            Relation::CellInsideQuery
+        } else {
+            Relation::CellCrossesQuery
+        }
+    }
+
+    fn matches(self, min: &[u8], max: &[u8], q_min: &[u8], q_max: &[u8]) -> bool {
+        match self {
+            RangeRelation::Intersects => min <= q_max && max >= q_min,
+            RangeRelation::Contains => min <= q_min && max >= q_max,
+            RangeRelation::Within => min >= q_min && max <= q_max,
+        }
+    }
+}
+
+impl fmt::Display for RangeRelation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match *self {
+            RangeRelation::Intersects => "intersects",
+            RangeRelation::Contains => "contains",
+            RangeRelation::Within => "within",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// Query against range fields such as `LongRange`, matching the documents whose
+/// indexed range relates to the query range as the given `RangeRelation`.
+///
+/// The ranges are packed as all the min values of each dimension followed by all
+/// the max values, so that a range of `num_dims` dimensions is indexed as a point
+/// of `2 * num_dims` dimensions.
+pub struct RangeFieldQuery {
+    field: String,
+    num_dims: usize,
+    bytes_per_dim: usize,
+    ranges: Vec<u8>,
+    relation: RangeRelation,
+    value_type: PointValueType,
+}
+
+impl RangeFieldQuery {
+    pub fn new(
+        field: String,
+        ranges: Vec<u8>,
+        num_dims: usize,
+        relation: RangeRelation,
+        value_type: PointValueType,
+    ) -> Result<RangeFieldQuery> {
+        if field.is_empty() {
+            return Err(Error::IllegalArgument("field must not be empty".into()));
+        }
+        if num_dims == 0 || ranges.is_empty() || !ranges.len().is_multiple_of(2 * num_dims) {
+            return Err(Error::IllegalArgument(format!(
+                "ranges of length {} can't hold the min and max values of {} dimensions",
+                ranges.len(),
+                num_dims
+            )));
+        }
+        let bytes_per_dim = ranges.len() / (2 * num_dims);
+        Ok(RangeFieldQuery {
+            field,
+            num_dims,
+            bytes_per_dim,
+            ranges,
+            relation,
+            value_type,
+        })
+    }
+}
+
+pub const RANGE_FIELD: &str = "range_field";
+
+impl<C: Codec> Query<C> for RangeFieldQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        Ok(Box::new(RangeFieldWeight {
+            field: self.field.clone(),
+            num_dims: self.num_dims,
+            bytes_per_dim: self.bytes_per_dim,
+            ranges: self.ranges.clone(),
+            relation: self.relation,
+            value_type: self.value_type,
+            weight: 0f32,
+            norm: 1f32,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn ::std::any::Any {
+        self
+    }
+}
+
+impl fmt::Display for RangeFieldQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, max) = self.ranges.split_at(self.num_dims * self.bytes_per_dim);
+        write!(
+            f,
+            "RangeFieldQuery(field: {}, type: {}, relation: {}, min: {}, max: {})",
+            self.field,
+            self.value_type,
+            self.relation,
+            self.value_type.format_bytes(min, self.bytes_per_dim),
+            self.value_type.format_bytes(max, self.bytes_per_dim),
+        )
+    }
+}
+
+struct RangeFieldWeight {
+    field: String,
+    num_dims: usize,
+    bytes_per_dim: usize,
+    ranges: Vec<u8>,
+    relation: RangeRelation,
+    value_type: PointValueType,
+    weight: f32,
+    norm: f32,
+}
+
+impl RangeFieldWeight {
+    /// Returns the `[offset, end)` of the given dimension's min value, the
+    /// max value is at `offset + max_offset`.
+    fn dim_bounds(&self, dim: usize) -> (usize, usize, usize) {
+        let offset = dim * self.bytes_per_dim;
+        (
+            offset,
+            offset + self.bytes_per_dim,
+            self.num_dims * self.bytes_per_dim,
+        )
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        let mut crosses = false;
+        for dim in 0..self.num_dims {
+            let (offset, end, max_offset) = self.dim_bounds(dim);
+            let maxs = offset + max_offset..end + max_offset;
+            match self.relation.compare(
+                &min_packed_value[offset..end],
+                &max_packed_value[offset..end],
+                &min_packed_value[maxs.clone()],
+                &max_packed_value[maxs.clone()],
+                &self.ranges[offset..end],
+                &self.ranges[maxs],
+            ) {
+                Relation::CellOutsideQuery => return Relation::CellOutsideQuery,
+                Relation::CellCrossesQuery => crosses = true,
+                Relation::CellInsideQuery => {}
+            }
+        }
+        if crosses {
+            Relation::CellCrossesQuery
+        } else {
+            Relation::CellInsideQuery
+        }
+    }
+
+    fn matches(&self, packed_value: &[u8]) -> bool {
+        (0..self.num_dims).all(|dim| {
+            let (offset, end, max_offset) = self.dim_bounds(dim);
+            let maxs = offset + max_offset..end + max_offset;
+            self.relation.matches(
+                &packed_value[offset..end],
+                &packed_value[maxs.clone()],
+                &self.ranges[offset..end],
+                &self.ranges[maxs],
+            )
+        })
+    }
+}
+
+impl<C: Codec> Weight<C> for RangeFieldWeight {
+    fn create_scorer(
+        &self,
+        leaf_reader_ctx: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let leaf_reader = leaf_reader_ctx.reader;
+        let values = match leaf_reader.point_values() {
+            Some(values) => values,
+            None => return Ok(None),
+        };
+        let field_info = match leaf_reader.field_info(&self.field) {
+            Some(field_info) => field_info,
+            None => return Ok(None),
+        };
+        if field_info.point_index_dimension_count != 2 * self.num_dims as u32 {
+            return Err(Error::IllegalArgument(format!(
+                "field '{}' was indexed with num_dims={} but this query has num_dims={}",
+                self.field,
+                field_info.point_index_dimension_count / 2,
+                self.num_dims
+            )));
+        }
+        if field_info.point_num_bytes != self.bytes_per_dim as u32 {
+            return Err(Error::IllegalArgument(format!(
+                "field '{}' was indexed with bytes_per_dim={} but this query has bytes_per_dim={}",
+                self.field, field_info.point_num_bytes, self.bytes_per_dim
+            )));
+        }
+
+        let all_docs_match = values.doc_count(&self.field)? == leaf_reader.max_doc() && {
+            let min_packed_value = values.min_packed_value(&self.field)?;
+            let max_packed_value = values.max_packed_value(&self.field)?;
+            self.compare(&min_packed_value, &max_packed_value) == Relation::CellInsideQuery
+        };
+        let iterator = if all_docs_match {
+            PointDocIterEnum::All(AllDocsIterator::new(leaf_reader.max_doc()))
+        } else {
+            let mut result =
+                DocIdSetBuilder::from_values(leaf_reader.max_doc(), &values, &self.field)?;
+            {
+                let mut visitor = RangeFieldIntersectVisitor {
+                    doc_id_set_builder: &mut result,
+                    weight: self,
+                };
+                values.intersect(&self.field, &mut visitor)?;
+            }
+            match result.build().iterator()? {
+                Some(iter) => PointDocIterEnum::DocSet(iter),
+                None => PointDocIterEnum::None(EmptyDocIterator::default()),
+            }
+        };
+        let cost = iterator.cost();
+        Ok(Some(Box::new(ConstantScoreScorer::new(
+            self.weight,
+            iterator,
+            cost,
+        ))))
+    }
+
+    fn query_type(&self) -> &'static str {
+        RANGE_FIELD
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight = norm * boost;
+        self.norm = norm;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let matched = match self.create_scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc)? == doc,
+            None => false,
+        };
+
+        if matched {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.weight, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl fmt::Display for RangeFieldWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (min, max) = self.ranges.split_at(self.num_dims * self.bytes_per_dim);
+        write!(
+            f,
+            "RangeFieldWeight(field: {}, type: {}, relation: {}, min: {}, max: {})",
+            self.field,
+            self.value_type,
+            self.relation,
+            self.value_type.format_bytes(min, self.bytes_per_dim),
+            self.value_type.format_bytes(max, self.bytes_per_dim),
+        )
+    }
+}
+
+struct RangeFieldIntersectVisitor<'a> {
+    doc_id_set_builder: &'a mut DocIdSetBuilder,
+    weight: &'a RangeFieldWeight,
+}
+
+impl<'a> IntersectVisitor for RangeFieldIntersectVisitor<'a> {
+    fn visit(&mut self, doc_id: DocId) -> Result<()> {
+        self.doc_id_set_builder.add_doc(doc_id);
+        Ok(())
+    }
+
+    fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        if self.weight.matches(packed_value) {
+            self.doc_id_set_builder.add_doc(doc_id);
+        }
+        Ok(())
+    }
+
+    fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+        self.weight.compare(min_packed_value, max_packed_value)
+    }
+
+    fn grow(&mut self, count: usize) {
+        self.doc_id_set_builder.grow(count)
+    }
+}
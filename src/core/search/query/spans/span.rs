@@ -27,6 +27,7 @@ use crate::core::search::query::{Query, TermQuery, Weight};
 use crate::core::search::scorer::{ConjunctionScorer, Scorer};
 use crate::core::search::searcher::SearchPlanBuilder;
 use crate::core::search::similarity::{SimScorer, SimWeight};
+use crate::core::search::ScoreMode;
 use crate::core::search::{DocIterator, NO_MORE_DOCS};
 use crate::core::util::{DocId, KeyedContext};
 
@@ -112,14 +113,14 @@ impl<C: Codec> Query<C> for SpanQueryEnum {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         match self {
-            SpanQueryEnum::Term(q) => q.create_weight(searcher, needs_scores),
-            SpanQueryEnum::Gap(q) => q.create_weight(searcher, needs_scores),
-            SpanQueryEnum::Or(q) => q.create_weight(searcher, needs_scores),
-            SpanQueryEnum::Near(q) => q.create_weight(searcher, needs_scores),
-            SpanQueryEnum::Boost(q) => q.create_weight(searcher, needs_scores),
+            SpanQueryEnum::Term(q) => q.create_weight(searcher, score_mode),
+            SpanQueryEnum::Gap(q) => q.create_weight(searcher, score_mode),
+            SpanQueryEnum::Or(q) => q.create_weight(searcher, score_mode),
+            SpanQueryEnum::Near(q) => q.create_weight(searcher, score_mode),
+            SpanQueryEnum::Boost(q) => q.create_weight(searcher, score_mode),
         }
     }
 
@@ -21,6 +21,7 @@ use crate::core::search::query::spans::{
     SpanWeightEnum, SpansEnum,
 };
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::search::{
     query::Query, query::TermQuery, query::Weight, scorer::Scorer, similarity::SimScorer,
     similarity::SimWeight,
@@ -89,10 +90,10 @@ impl<C: Codec> Query<C> for SpanBoostQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        if !needs_scores {
-            self.query.create_weight(searcher, needs_scores)
+        if !score_mode.needs_scores() {
+            self.query.create_weight(searcher, score_mode)
         } else {
             let weight = self.span_boost_weight(searcher)?;
             Ok(Box::new(weight))
@@ -184,13 +185,13 @@ impl<C: Codec> Query<C> for SpanBoostQueryEnum {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         match self {
-            SpanBoostQueryEnum::Term(q) => q.create_weight(searcher, needs_scores),
-            SpanBoostQueryEnum::Gap(q) => q.create_weight(searcher, needs_scores),
-            SpanBoostQueryEnum::Or(q) => q.create_weight(searcher, needs_scores),
-            SpanBoostQueryEnum::Near(q) => q.create_weight(searcher, needs_scores),
+            SpanBoostQueryEnum::Term(q) => q.create_weight(searcher, score_mode),
+            SpanBoostQueryEnum::Gap(q) => q.create_weight(searcher, score_mode),
+            SpanBoostQueryEnum::Or(q) => q.create_weight(searcher, score_mode),
+            SpanBoostQueryEnum::Near(q) => q.create_weight(searcher, score_mode),
         }
     }
 
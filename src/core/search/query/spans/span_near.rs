@@ -21,6 +21,7 @@ use crate::core::search::query::spans::{
 use crate::core::search::query::spans::{ConjunctionSpanBase, ConjunctionSpans};
 use crate::core::search::query::spans::{SpanCollector, SpanQuery, SpanWeight, Spans};
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::search::{
     query::Query, query::TermQuery, query::Weight, scorer::Scorer, similarity::SimWeight,
     DocIterator, NO_MORE_DOCS,
@@ -180,9 +181,9 @@ impl<C: Codec> Query<C> for SpanNearQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        let weight = self.span_near_weight(searcher, needs_scores)?;
+        let weight = self.span_near_weight(searcher, score_mode.needs_scores())?;
         Ok(Box::new(weight))
     }
 
@@ -882,7 +883,7 @@ impl<C: Codec> Query<C> for SpanGapQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        _needs_scores: bool,
+        _score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         let weight = SpanGapWeight::new(self, searcher, self.width)?;
         Ok(Box::new(weight))
@@ -20,6 +20,7 @@ use crate::core::search::query::spans::{
 };
 use crate::core::search::query::spans::{SpanCollector, SpanQuery, SpanWeight, Spans};
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::search::{
     query::Query, query::TermQuery, query::Weight, scorer::Scorer, similarity::SimWeight,
     DocIterator,
@@ -100,9 +101,11 @@ impl<C: Codec> Query<C> for SpanOrQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        Ok(Box::new(self.span_or_weight(searcher, needs_scores)?))
+        Ok(Box::new(
+            self.span_or_weight(searcher, score_mode.needs_scores())?,
+        ))
     }
 
     fn extract_terms(&self) -> Vec<TermQuery> {
@@ -287,7 +290,8 @@ impl<P: PostingIterator> SpanOrSpans<P> {
     }
 
     fn two_phase_current_doc_matches(&mut self) -> Result<bool> {
-        let mut list_at_current_doc = self.by_doc_queue.top_list();
+        let mut idx = self.by_doc_queue.top_list();
+        let mut list_at_current_doc = self.by_doc_queue.get_mut(idx);
         // remove the head of the list as long as it does not match
         let current_doc = list_at_current_doc.doc;
         while list_at_current_doc.inner().support_two_phase() {
@@ -298,12 +302,11 @@ impl<P: PostingIterator> SpanOrSpans<P> {
             }
             // do not use this spans for positions at current doc:
             list_at_current_doc.last_approx_non_match_doc = current_doc;
-            if list_at_current_doc.next.is_null() {
-                return Ok(false);
-            }
-            unsafe {
-                list_at_current_doc = &mut (*list_at_current_doc.next);
+            match list_at_current_doc.next {
+                Some(next) => idx = next,
+                None => return Ok(false),
             }
+            list_at_current_doc = self.by_doc_queue.get_mut(idx);
         }
         self.last_doc_two_phase_matched = current_doc;
         self.top_position_spans_valid = false;
@@ -314,8 +317,9 @@ impl<P: PostingIterator> SpanOrSpans<P> {
         // cualled at first next_start_position
         assert_eq!(self.by_position_queue.len(), 0);
         // add all matching Spans at current doc to by_position_queue
-        let mut list_at_current_doc = self.by_doc_queue.top_list();
-        loop {
+        let mut next = Some(self.by_doc_queue.top_list());
+        while let Some(idx) = next {
+            let list_at_current_doc = self.by_doc_queue.get_mut(idx);
             let spans_at_doc_match = self.last_doc_two_phase_matched != list_at_current_doc.doc
                 || !list_at_current_doc.inner().support_two_phase()
                 || (list_at_current_doc.last_approx_non_match_doc != list_at_current_doc.doc
@@ -337,12 +341,7 @@ impl<P: PostingIterator> SpanOrSpans<P> {
                     list_at_current_doc.inner_mut() as *mut SpansEnum<P>
                 ));
             }
-            if list_at_current_doc.next.is_null() {
-                break;
-            }
-            unsafe {
-                list_at_current_doc = &mut *list_at_current_doc.next;
-            }
+            next = list_at_current_doc.next;
         }
         assert!(!self.by_position_queue.is_empty());
         Ok(())
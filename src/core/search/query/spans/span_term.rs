@@ -22,6 +22,7 @@ use crate::core::search::query::spans::{
 };
 use crate::core::search::query::spans::{SpanCollector, SpanQuery, SpanWeight, Spans};
 use crate::core::search::searcher::SearchPlanBuilder;
+use crate::core::search::ScoreMode;
 use crate::core::search::{
     query::Query, query::TermQuery, query::Weight, scorer::Scorer, similarity::SimWeight,
     DocIterator, NO_MORE_DOCS,
@@ -52,13 +53,13 @@ impl<C: Codec> Query<C> for SpanTermQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
         Ok(Box::new(SpanTermWeight::new(
             self,
             searcher,
             self.ctx.clone(),
-            needs_scores,
+            score_mode.needs_scores(),
         )?))
     }
 
@@ -16,16 +16,17 @@ use crate::Result;
 use std::fmt;
 
 use crate::core::codec::Codec;
-use crate::core::codec::{PostingIterator, PostingIteratorFlags};
+use crate::core::codec::{PostingIterator, PostingIteratorFlags, TermIterator, Terms};
 use crate::core::index::reader::LeafReaderContext;
 use crate::core::index::Term;
 use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::{MatchPosition, Matches};
 use crate::core::search::query::{Query, Weight};
 use crate::core::search::scorer::{Scorer, TermScorer};
 use crate::core::search::searcher::SearchPlanBuilder;
 use crate::core::search::similarity::{SimWeight, Similarity};
 use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
-use crate::core::search::DocIterator;
+use crate::core::search::{DocIterator, ScoreMode};
 
 use crate::core::util::{DocId, KeyedContext};
 
@@ -58,8 +59,9 @@ impl<C: Codec> Query<C> for TermQuery {
     fn create_weight(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
+        let needs_scores = score_mode.needs_scores();
         let max_doc = searcher.max_doc() as i64;
 
         let term_stats = if needs_scores {
@@ -90,7 +92,7 @@ impl<C: Codec> Query<C> for TermQuery {
             self.boost,
             similarity,
             sim_weight,
-            needs_scores,
+            score_mode,
         )))
     }
 
@@ -120,7 +122,7 @@ struct TermWeight<C: Codec> {
     boost: f32,
     similarity: Box<dyn Similarity<C>>,
     sim_weight: Box<dyn SimWeight<C>>,
-    needs_scores: bool,
+    score_mode: ScoreMode,
 }
 
 impl<C: Codec> TermWeight<C> {
@@ -129,14 +131,14 @@ impl<C: Codec> TermWeight<C> {
         boost: f32,
         similarity: Box<dyn Similarity<C>>,
         sim_weight: Box<dyn SimWeight<C>>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> TermWeight<C> {
         TermWeight {
             term,
             boost,
             similarity,
             sim_weight,
-            needs_scores,
+            score_mode,
         }
     }
 }
@@ -146,20 +148,32 @@ impl<C: Codec> Weight<C> for TermWeight<C> {
         let _norms = reader.reader.norm_values(&self.term.field);
         let sim_scorer = self.sim_weight.sim_scorer(reader.reader)?;
 
-        let flags = if self.needs_scores {
+        let flags = if self.score_mode.needs_scores() {
             PostingIteratorFlags::FREQS
         } else {
             PostingIteratorFlags::NONE
         };
 
-        if let Some(postings_iterator) = reader.reader.postings(&self.term, flags as i32)? {
-            Ok(Some(Box::new(TermScorer::new(
-                sim_scorer,
-                postings_iterator,
-            ))))
-        } else {
-            Ok(None)
+        if let Some(terms) = reader.reader.terms(&self.term.field)? {
+            let mut terms_iter = terms.iterator()?;
+            if terms_iter.seek_exact(self.term.bytes())? {
+                // the impacts are only worth reading to skip non-competitive docs
+                let impacts = if self.score_mode == ScoreMode::TopScores {
+                    terms_iter.impacts()?
+                } else {
+                    None
+                };
+                let postings_iterator = terms_iter.postings_with_flags(flags)?;
+                let scorer = match impacts {
+                    Some(impacts) => {
+                        TermScorer::with_impacts(sim_scorer, postings_iterator, impacts)
+                    }
+                    None => TermScorer::new(sim_scorer, postings_iterator),
+                };
+                return Ok(Some(Box::new(scorer)));
+            }
         }
+        Ok(None)
     }
 
     fn query_type(&self) -> &'static str {
@@ -175,11 +189,11 @@ impl<C: Codec> Weight<C> for TermWeight<C> {
     }
 
     fn needs_scores(&self) -> bool {
-        self.needs_scores
+        self.score_mode.needs_scores()
     }
 
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
-        let flags = if self.needs_scores {
+        let flags = if self.score_mode.needs_scores() {
             PostingIteratorFlags::FREQS
         } else {
             PostingIteratorFlags::NONE
@@ -211,6 +225,21 @@ impl<C: Codec> Weight<C> for TermWeight<C> {
             vec![],
         ))
     }
+
+    fn matches(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Option<Matches>> {
+        let flags = PostingIteratorFlags::OFFSETS as i32;
+        if let Some(mut postings_iterator) = reader.reader.postings(&self.term, flags)? {
+            if postings_iterator.advance(doc)? == doc {
+                let freq = postings_iterator.freq()?;
+                let mut positions = Vec::with_capacity(freq as usize);
+                for _ in 0..freq {
+                    positions.push(MatchPosition::from_postings(&mut postings_iterator)?);
+                }
+                return Ok(Some(Matches::for_field(self.term.field(), positions)));
+            }
+        }
+        Ok(None)
+    }
 }
 
 impl<C: Codec> fmt::Display for TermWeight<C> {
@@ -222,7 +251,7 @@ impl<C: Codec> fmt::Display for TermWeight<C> {
             &self.term.text(),
             self.boost,
             &self.similarity,
-            self.needs_scores
+            self.score_mode.needs_scores()
         )
     }
 }
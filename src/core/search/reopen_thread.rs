@@ -0,0 +1,226 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::Codec;
+use crate::core::index::merge::{MergePolicy, MergeScheduler};
+use crate::core::index::writer::IndexWriter;
+use crate::core::search::{ReferenceManager, RefreshListener};
+use crate::core::store::directory::Directory;
+
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+struct ReopenState {
+    searching_gen: u64,
+    waiting_gen: u64,
+    finish: bool,
+}
+
+struct ReopenShared {
+    state: Mutex<ReopenState>,
+    // wakes up the reopen thread when a newer generation is waited for
+    reopen_cond: Condvar,
+    // wakes up the waiting callers once a reopen completed
+    searching_cond: Condvar,
+}
+
+/// Runs a background thread that periodically reopens a `ReferenceManager`,
+/// e.g. a `SearcherManager`, and lets callers wait until a given index change
+/// is visible to the searchers.
+///
+/// The generations are the sequence numbers returned by the `IndexWriter`
+/// operations such as `add_document` or `delete_documents_by_terms`: once
+/// `wait_for_generation(gen)` returned true, the references acquired from the
+/// manager see the change of `gen`. The manager is reopened at least every
+/// `target_max_stale`, and at most `target_min_stale` after a caller starts
+/// waiting for a generation that isn't visible yet.
+pub struct ControlledRealTimeReopenThread {
+    shared: Arc<ReopenShared>,
+    last_sequence_number: Arc<dyn Fn() -> u64 + Send + Sync>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ControlledRealTimeReopenThread {
+    pub fn new<D, C, MS, MP, M, T, RL>(
+        writer: &IndexWriter<D, C, MS, MP>,
+        manager: Arc<M>,
+        target_max_stale: Duration,
+        target_min_stale: Duration,
+    ) -> Result<Self>
+    where
+        D: Directory + Send + Sync + 'static,
+        C: Codec,
+        MS: MergeScheduler,
+        MP: MergePolicy,
+        M: ReferenceManager<T, RL> + Send + Sync + 'static,
+        T: ?Sized,
+        RL: RefreshListener + ?Sized,
+    {
+        if target_max_stale < target_min_stale {
+            return Err(IllegalArgument(format!(
+                "target_max_stale (= {:?}) < target_min_stale (= {:?})",
+                target_max_stale, target_min_stale
+            )));
+        }
+
+        let writer = writer.clone();
+        let last_sequence_number: Arc<dyn Fn() -> u64 + Send + Sync> =
+            Arc::new(move || writer.last_sequence_number());
+        let shared = Arc::new(ReopenShared {
+            state: Mutex::new(ReopenState {
+                searching_gen: 0,
+                waiting_gen: 0,
+                finish: false,
+            }),
+            reopen_cond: Condvar::new(),
+            searching_cond: Condvar::new(),
+        });
+
+        let thread_shared = Arc::clone(&shared);
+        let thread_last_sequence_number = Arc::clone(&last_sequence_number);
+        let handle = thread::Builder::new()
+            .name("Rucene NRT Reopen Thread".into())
+            .spawn(move || {
+                Self::run(
+                    &thread_shared,
+                    || manager.maybe_refresh_blocking(),
+                    thread_last_sequence_number.as_ref(),
+                    target_max_stale,
+                    target_min_stale,
+                )
+            })?;
+
+        Ok(ControlledRealTimeReopenThread {
+            shared,
+            last_sequence_number,
+            handle: Some(handle),
+        })
+    }
+
+    fn run(
+        shared: &ReopenShared,
+        refresh: impl Fn() -> Result<()>,
+        last_sequence_number: &dyn Fn() -> u64,
+        target_max_stale: Duration,
+        target_min_stale: Duration,
+    ) {
+        let mut last_reopen_start = Instant::now();
+        let mut state = shared.state.lock().unwrap();
+        while !state.finish {
+            let has_waiting = state.waiting_gen > state.searching_gen;
+            let next_reopen_start = last_reopen_start
+                + if has_waiting {
+                    target_min_stale
+                } else {
+                    target_max_stale
+                };
+            let now = Instant::now();
+            if next_reopen_start > now {
+                state = shared
+                    .reopen_cond
+                    .wait_timeout(state, next_reopen_start - now)
+                    .unwrap()
+                    .0;
+                continue;
+            }
+            drop(state);
+
+            last_reopen_start = Instant::now();
+            // all the changes up to this generation are visible once the
+            // refresh completes
+            let refresh_start_gen = last_sequence_number();
+            let res = refresh();
+
+            state = shared.state.lock().unwrap();
+            match res {
+                Ok(()) => {
+                    state.searching_gen = state.searching_gen.max(refresh_start_gen);
+                    shared.searching_cond.notify_all();
+                }
+                Err(e) => {
+                    log::error!("NRT reopen failed: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Waits for the change of generation `target_gen` to be visible, or for
+    /// `max_wait` if set. Returns false if the wait timed out.
+    ///
+    /// Returns an error if `target_gen` wasn't returned by the writer yet.
+    pub fn wait_for_generation(&self, target_gen: u64, max_wait: Option<Duration>) -> Result<bool> {
+        let last_gen = (self.last_sequence_number)();
+        if target_gen > last_gen {
+            return Err(IllegalArgument(format!(
+                "target_gen={} was never returned by the IndexWriter instance (current gen={})",
+                target_gen, last_gen
+            )));
+        }
+
+        let deadline = max_wait.map(|d| Instant::now() + d);
+        let mut state = self.shared.state.lock()?;
+        if target_gen > state.searching_gen && target_gen > state.waiting_gen {
+            state.waiting_gen = target_gen;
+            self.shared.reopen_cond.notify_all();
+        }
+        while target_gen > state.searching_gen {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(false);
+                    }
+                    state = self
+                        .shared
+                        .searching_cond
+                        .wait_timeout(state, deadline - now)?
+                        .0;
+                }
+                None => {
+                    state = self.shared.searching_cond.wait(state)?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// The generation all the changes up to are visible to the searchers.
+    pub fn searching_generation(&self) -> u64 {
+        self.shared.state.lock().unwrap().searching_gen
+    }
+
+    /// Stops the reopen thread, the pending and future `wait_for_generation`
+    /// calls return immediately.
+    pub fn close(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.shared.state.lock().unwrap().finish = true;
+            self.shared.reopen_cond.notify_all();
+            if handle.join().is_err() {
+                log::error!("NRT reopen thread panicked");
+            }
+
+            // so that any waiting threads stop waiting
+            self.shared.state.lock().unwrap().searching_gen = u64::MAX;
+            self.shared.searching_cond.notify_all();
+        }
+    }
+}
+
+impl Drop for ControlledRealTimeReopenThread {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+/// Different modes of search, as requested by the `Collector` and passed
+/// down to `Query::create_weight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScoreMode {
+    /// Produced scorers will allow visiting all matches and get their score.
+    Complete,
+    /// Produced scorers will allow visiting all matches but scores won't be
+    /// available.
+    CompleteNoScores,
+    /// Produced scorers will optionally allow skipping over non-competitive
+    /// hits using the `Scorer::set_min_competitive_score` API.
+    TopScores,
+    /// Only the top docs by a criterion other than the score (e.g. an index
+    /// sort) are needed, scores won't be available and the scorers may skip
+    /// the remaining matches.
+    TopDocs,
+}
+
+impl ScoreMode {
+    /// Whether this mode requires scores to be computed.
+    pub fn needs_scores(self) -> bool {
+        match self {
+            ScoreMode::Complete | ScoreMode::TopScores => true,
+            ScoreMode::CompleteNoScores | ScoreMode::TopDocs => false,
+        }
+    }
+
+    /// Whether all the matches need to be visited, if not, the scorers are
+    /// allowed to skip non-competitive docs.
+    pub fn is_exhaustive(self) -> bool {
+        match self {
+            ScoreMode::Complete | ScoreMode::CompleteNoScores => true,
+            ScoreMode::TopScores | ScoreMode::TopDocs => false,
+        }
+    }
+
+    /// The exhaustive mode that computes scores iff `needs_scores`.
+    pub fn from_needs_scores(needs_scores: bool) -> ScoreMode {
+        if needs_scores {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        }
+    }
+
+    /// Combines the modes of two collectors fed with the same hits: scores
+    /// are needed if any of them needs scores, and hits may only be skipped
+    /// if none of them is exhaustive.
+    pub fn merge(self, other: ScoreMode) -> ScoreMode {
+        let needs_scores = self.needs_scores() || other.needs_scores();
+        if self.is_exhaustive() || other.is_exhaustive() {
+            ScoreMode::from_needs_scores(needs_scores)
+        } else if needs_scores {
+            ScoreMode::TopScores
+        } else {
+            ScoreMode::TopDocs
+        }
+    }
+}
+
+impl fmt::Display for ScoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScoreMode::Complete => write!(f, "COMPLETE"),
+            ScoreMode::CompleteNoScores => write!(f, "COMPLETE_NO_SCORES"),
+            ScoreMode::TopScores => write!(f, "TOP_SCORES"),
+            ScoreMode::TopDocs => write!(f, "TOP_DOCS"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_mode_merge() {
+        assert_eq!(
+            ScoreMode::TopScores.merge(ScoreMode::CompleteNoScores),
+            ScoreMode::Complete
+        );
+        assert_eq!(
+            ScoreMode::TopScores.merge(ScoreMode::TopDocs),
+            ScoreMode::TopScores
+        );
+        assert_eq!(
+            ScoreMode::TopDocs.merge(ScoreMode::TopDocs),
+            ScoreMode::TopDocs
+        );
+        assert_eq!(
+            ScoreMode::CompleteNoScores.merge(ScoreMode::TopDocs),
+            ScoreMode::CompleteNoScores
+        );
+    }
+}
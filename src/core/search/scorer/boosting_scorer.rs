@@ -16,6 +16,9 @@ use crate::core::search::DocIterator;
 use crate::core::util::DocId;
 use crate::Result;
 
+/// Scores the docs of the `positive` scorer, demoting the ones that are also
+/// matched by the `negative` scorer by multiplying their score with
+/// `negative_boost`.
 pub struct BoostingScorer {
     positive: Box<dyn Scorer>,
     negative: Box<dyn Scorer>,
@@ -28,13 +31,21 @@ impl BoostingScorer {
         negative: Box<dyn Scorer>,
         negative_boost: f32,
     ) -> BoostingScorer {
-        debug_assert!(negative_boost > 0.0 && negative_boost < 1.0);
+        debug_assert!((0.0..=1.0).contains(&negative_boost));
         BoostingScorer {
             positive,
             negative,
             negative_boost,
         }
     }
+
+    fn negative_matches(&mut self, doc: DocId) -> Result<bool> {
+        let mut negative_doc = self.negative.doc_id();
+        if negative_doc < doc {
+            negative_doc = self.negative.advance(doc)?;
+        }
+        Ok(negative_doc == doc)
+    }
 }
 
 impl Scorer for BoostingScorer {
@@ -42,12 +53,21 @@ impl Scorer for BoostingScorer {
         let current_doc = self.positive.doc_id();
         let mut score = self.positive.score()?;
 
-        if current_doc == self.negative.advance(current_doc)? {
+        if self.negative_matches(current_doc)? {
             score *= self.negative_boost;
         }
 
         Ok(score)
     }
+
+    // demoting can only lower the scores of the positive scorer
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.positive.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.positive.max_score(up_to)
+    }
 }
 
 impl DocIterator for BoostingScorer {
@@ -71,6 +91,14 @@ impl DocIterator for BoostingScorer {
         self.positive.matches()
     }
 
+    fn match_cost(&self) -> f32 {
+        self.positive.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.positive.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         self.positive.approximate_next()
     }
@@ -79,3 +107,32 @@ impl DocIterator for BoostingScorer {
         self.positive.approximate_advance(target)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::tests::create_mock_scorer;
+    use crate::core::search::NO_MORE_DOCS;
+
+    #[test]
+    fn test_boosting_scorer() {
+        let positive = create_mock_scorer(vec![1, 2, 3, 5, 8]);
+        let negative = create_mock_scorer(vec![2, 5, 6]);
+        let mut scorer = BoostingScorer::new(Box::new(positive), Box::new(negative), 0.5);
+
+        let mut scores = vec![];
+        loop {
+            let doc = scorer.next().unwrap();
+            if doc == NO_MORE_DOCS {
+                break;
+            }
+            // scoring twice must not move the negative scorer backwards
+            scorer.score().unwrap();
+            scores.push((doc, scorer.score().unwrap()));
+        }
+        assert_eq!(
+            scores,
+            vec![(1, 1.0), (2, 1.0), (3, 3.0), (5, 2.5), (8, 8.0)]
+        );
+    }
+}
@@ -21,6 +21,11 @@ pub struct ConjunctionScorer<T: Scorer> {
     lead1: T,
     lead2: T,
     others: Vec<T>,
+    // docs whose score is less than this may be skipped
+    min_competitive_score: f32,
+    // last doc of the current block of docs and max score of the block
+    up_to: DocId,
+    block_max_score: f32,
 }
 
 impl<T: Scorer> ConjunctionScorer<T> {
@@ -38,6 +43,47 @@ impl<T: Scorer> ConjunctionScorer<T> {
             lead1,
             lead2,
             others,
+            min_competitive_score: 0.0,
+            up_to: -1,
+            block_max_score: 0.0,
+        }
+    }
+
+    // Returns the first doc >= `target` that is in a block whose max score
+    // is competitive.
+    fn competitive_target(&mut self, mut target: DocId) -> Result<DocId> {
+        loop {
+            if target == NO_MORE_DOCS {
+                return Ok(target);
+            }
+            if target > self.up_to {
+                self.up_to = self.advance_shallow(target)?;
+                self.block_max_score = self.max_score(self.up_to)?;
+            }
+            if self.block_max_score >= self.min_competitive_score {
+                return Ok(target);
+            }
+            if self.up_to == NO_MORE_DOCS {
+                return Ok(NO_MORE_DOCS);
+            }
+            target = self.up_to + 1;
+        }
+    }
+
+    fn advance_competitive(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = self.competitive_target(target)?;
+        loop {
+            let doc = self.lead1.approximate_advance(target)?;
+            let doc = self.skip_to_approx(doc)?;
+            if doc == NO_MORE_DOCS {
+                return Ok(doc);
+            }
+            // the match may be in a following block that is not competitive
+            let next_target = self.competitive_target(doc)?;
+            if next_target == doc {
+                return Ok(doc);
+            }
+            target = next_target;
         }
     }
 
@@ -93,6 +139,35 @@ impl<T: Scorer> Scorer for ConjunctionScorer<T> {
 
         Ok(score)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let mut up_to = self
+            .lead1
+            .advance_shallow(target.max(self.lead1.doc_id()))?;
+        up_to = up_to.min(
+            self.lead2
+                .advance_shallow(target.max(self.lead2.doc_id()))?,
+        );
+        for scorer in &mut self.others {
+            up_to = up_to.min(scorer.advance_shallow(target.max(scorer.doc_id()))?);
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        // summed in the same order as `score` so that it is a valid bound
+        let mut max_score = self.lead1.max_score(up_to)?;
+        max_score += self.lead2.max_score(up_to)?;
+        for scorer in &mut self.others {
+            max_score += scorer.max_score(up_to)?;
+        }
+        Ok(max_score)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        self.min_competitive_score = min_score;
+        Ok(())
+    }
 }
 
 impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
@@ -117,11 +192,18 @@ impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
     }
 
     fn approximate_next(&mut self) -> Result<DocId> {
+        if self.min_competitive_score > 0.0 {
+            let target = self.doc_id() + 1;
+            return self.advance_competitive(target);
+        }
         let doc = self.lead1.approximate_next()?;
         self.skip_to_approx(doc)
     }
 
     fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        if self.min_competitive_score > 0.0 {
+            return self.advance_competitive(target);
+        }
         let doc = self.lead1.approximate_advance(target)?;
         self.skip_to_approx(doc)
     }
@@ -213,6 +295,62 @@ mod tests {
         assert_eq!(scorer.doc_id(), NO_MORE_DOCS);
     }
 
+    // scores each doc with its id, in blocks of 4 docs
+    struct BlockScorer {
+        scorer: MockSimpleScorer<MockDocIterator>,
+    }
+
+    impl Scorer for BlockScorer {
+        fn score(&mut self) -> Result<f32> {
+            self.scorer.score()
+        }
+
+        fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+            Ok(target | 3)
+        }
+
+        fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+            Ok(up_to as f32)
+        }
+    }
+
+    impl DocIterator for BlockScorer {
+        fn doc_id(&self) -> DocId {
+            self.scorer.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.scorer.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.scorer.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.scorer.cost()
+        }
+    }
+
+    #[test]
+    fn test_conjunction_skips_non_competitive_blocks() {
+        let s1 = BlockScorer {
+            scorer: create_mock_scorer(vec![1, 2, 5, 6, 9, 10]),
+        };
+        let s2 = BlockScorer {
+            scorer: create_mock_scorer(vec![2, 5, 6, 10]),
+        };
+        let mut scorer = ConjunctionScorer::new(vec![s1, s2]);
+
+        assert_eq!(scorer.next().unwrap(), 2);
+        assert_eq!(scorer.max_score(7).unwrap(), 14.0);
+        // the block of docs [4, 7] can't score more than 14
+        scorer.set_min_competitive_score(15.0).unwrap();
+        assert_eq!(scorer.next().unwrap(), 10);
+        assert!((scorer.score().unwrap() - 20.0).abs() < ::std::f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+
     fn create_conjunction_scorer() -> ConjunctionScorer<MockSimpleScorer<MockDocIterator>> {
         let s1 = create_mock_scorer(vec![1, 2, 3, 4, 5]);
         let s2 = create_mock_scorer(vec![2, 5]);
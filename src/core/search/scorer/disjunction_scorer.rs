@@ -13,19 +13,27 @@
 
 use crate::core::search::scorer::Scorer;
 use crate::core::search::{DocIterator, NO_MORE_DOCS};
-use crate::core::util::{DisiPriorityQueue, DocId};
+use crate::core::util::{DisiPriorityQueue, DisiWrapper, DocId};
 
 use crate::Result;
+use std::cmp::Ordering;
 use std::f32;
 
 pub const DEFAULT_MIN_SHOULD_MATCH: i32 = 1;
 
 /// A Scorer for OR like queries, counterpart of `ConjunctionScorer`.
+///
+/// If any of the sub scorers supports two phase iteration, so does this
+/// scorer: its approximation is the disjunction of the sub approximations,
+/// and the sub scorers on the current doc are only verified when needed,
+/// cheapest first.
 pub struct DisjunctionSumScorer<T: Scorer> {
     sub_scorers: SubScorers<T>,
     needs_scores: bool,
     cost: usize,
     min_should_match: i32,
+    two_phase: bool,
+    match_cost: f32,
 }
 
 impl<T: Scorer> DisjunctionSumScorer<T> {
@@ -37,6 +45,8 @@ impl<T: Scorer> DisjunctionSumScorer<T> {
         debug_assert!(children.len() > 0);
 
         let cost = children.iter().map(|w| w.cost()).sum();
+        let two_phase = children.iter().any(|s| s.support_two_phase());
+        let match_cost = disjunction_match_cost(&children);
 
         let sub_scorers = if children.len() < 10 || min_should_match > DEFAULT_MIN_SHOULD_MATCH {
             SubScorers::SQ(SimpleQueue::new(children))
@@ -49,8 +59,22 @@ impl<T: Scorer> DisjunctionSumScorer<T> {
             needs_scores,
             cost,
             min_should_match,
+            two_phase,
+            match_cost,
         }
     }
+
+    // the approximation only guarantees `min_should_match` for `next`
+    fn needs_verification(&self) -> bool {
+        self.two_phase || self.min_should_match > DEFAULT_MIN_SHOULD_MATCH
+    }
+
+    fn next_match(&mut self, mut doc: DocId) -> Result<DocId> {
+        while doc != NO_MORE_DOCS && !self.matches()? {
+            doc = self.approximate_next()?;
+        }
+        Ok(doc)
+    }
 }
 
 impl<T: Scorer> Scorer for DisjunctionSumScorer<T> {
@@ -69,11 +93,13 @@ impl<T: Scorer> DocIterator for DisjunctionSumScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        self.next_match(doc)
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        self.next_match(doc)
     }
 
     fn cost(&self) -> usize {
@@ -81,11 +107,18 @@ impl<T: Scorer> DocIterator for DisjunctionSumScorer<T> {
     }
 
     fn matches(&mut self) -> Result<bool> {
-        Ok(true)
+        if !self.needs_verification() {
+            return Ok(true);
+        }
+        self.sub_scorers.matches(self.min_should_match)
     }
 
     fn match_cost(&self) -> f32 {
-        0f32
+        self.match_cost
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.two_phase
     }
 
     fn approximate_next(&mut self) -> Result<DocId> {
@@ -113,6 +146,8 @@ pub struct DisjunctionMaxScorer<T: Scorer> {
     needs_scores: bool,
     cost: usize,
     tie_breaker_multiplier: f32,
+    two_phase: bool,
+    match_cost: f32,
 }
 
 impl<T: Scorer> DisjunctionMaxScorer<T> {
@@ -124,6 +159,8 @@ impl<T: Scorer> DisjunctionMaxScorer<T> {
         debug_assert!(children.len() > 0);
 
         let cost = children.iter().map(|w| w.cost()).sum();
+        let two_phase = children.iter().any(|s| s.support_two_phase());
+        let match_cost = disjunction_match_cost(&children);
 
         let sub_scorers = if children.len() < 10 {
             SubScorers::SQ(SimpleQueue::new(children))
@@ -136,7 +173,16 @@ impl<T: Scorer> DisjunctionMaxScorer<T> {
             needs_scores,
             cost,
             tie_breaker_multiplier,
+            two_phase,
+            match_cost,
+        }
+    }
+
+    fn next_match(&mut self, mut doc: DocId) -> Result<DocId> {
+        while doc != NO_MORE_DOCS && !self.matches()? {
+            doc = self.approximate_next()?;
         }
+        Ok(doc)
     }
 }
 
@@ -156,11 +202,13 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        self.next_match(doc)
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        self.next_match(doc)
     }
 
     fn cost(&self) -> usize {
@@ -168,11 +216,18 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
     }
 
     fn matches(&mut self) -> Result<bool> {
-        Ok(true)
+        if !self.two_phase {
+            return Ok(true);
+        }
+        self.sub_scorers.matches(DEFAULT_MIN_SHOULD_MATCH)
     }
 
     fn match_cost(&self) -> f32 {
-        0f32
+        self.match_cost
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.two_phase
     }
 
     fn approximate_next(&mut self) -> Result<DocId> {
@@ -184,9 +239,32 @@ impl<T: Scorer> DocIterator for DisjunctionMaxScorer<T> {
     }
 }
 
+/// The cost to verify a doc of a disjunction: the match costs of the two
+/// phase sub scorers, weighted by the number of docs of their approximations.
+fn disjunction_match_cost<T: Scorer>(children: &[T]) -> f32 {
+    let mut sum_match_cost = 0f32;
+    let mut sum_approx_cost = 0f32;
+    for s in children {
+        let cost = s.cost().max(1) as f32;
+        sum_approx_cost += cost;
+        if s.support_two_phase() {
+            sum_match_cost += s.match_cost() * cost;
+        }
+    }
+    if sum_approx_cost > 0f32 {
+        sum_match_cost / sum_approx_cost
+    } else {
+        0f32
+    }
+}
+
 pub struct SimpleQueue<T: Scorer> {
     scorers: Vec<T>,
     curr_doc: DocId,
+    // verification results of the two phase sub scorers on `curr_doc`
+    matches: Vec<Option<bool>>,
+    // the two phase sub scorers, cheapest to verify first
+    two_phase_by_cost: Vec<usize>,
 }
 
 impl<T: Scorer> SimpleQueue<T> {
@@ -195,11 +273,45 @@ impl<T: Scorer> SimpleQueue<T> {
         for s in children.iter() {
             curr_doc = curr_doc.min(s.doc_id());
         }
+        let mut two_phase_by_cost: Vec<usize> = (0..children.len())
+            .filter(|&i| children[i].support_two_phase())
+            .collect();
+        two_phase_by_cost.sort_by(|&a, &b| {
+            children[a]
+                .match_cost()
+                .partial_cmp(&children[b].match_cost())
+                .unwrap_or(Ordering::Equal)
+        });
         SimpleQueue {
+            matches: vec![None; children.len()],
             scorers: children,
             curr_doc,
+            two_phase_by_cost,
+        }
+    }
+
+    fn set_curr_doc(&mut self, doc: DocId) {
+        self.curr_doc = doc;
+        for m in &mut self.matches {
+            *m = None;
         }
     }
+
+    // whether the sub scorer at `idx` matches the current doc, two phase sub
+    // scorers are verified at most once per doc
+    fn sub_matches(&mut self, idx: usize) -> Result<bool> {
+        let scorer = &mut self.scorers[idx];
+        if scorer.doc_id() != self.curr_doc {
+            return Ok(false);
+        }
+        if !scorer.support_two_phase() {
+            return Ok(true);
+        }
+        if self.matches[idx].is_none() {
+            self.matches[idx] = Some(scorer.matches()?);
+        }
+        Ok(self.matches[idx] == Some(true))
+    }
 }
 
 pub enum SubScorers<T: Scorer> {
@@ -213,10 +325,9 @@ impl<T: Scorer> SubScorers<T> {
             SubScorers::SQ(sq) => {
                 let mut score: f32 = 0.0f32;
 
-                let doc_id = sq.curr_doc;
-                for s in sq.scorers.iter_mut() {
-                    if s.doc_id() == doc_id {
-                        let sub_score = s.score()?;
+                for i in 0..sq.scorers.len() {
+                    if sq.sub_matches(i)? {
+                        let sub_score = sq.scorers[i].score()?;
                         score += sub_score;
                     }
                 }
@@ -225,17 +336,15 @@ impl<T: Scorer> SubScorers<T> {
             }
             SubScorers::DPQ(dpq) => {
                 let mut score: f32 = 0.0f32;
-                let mut disi = dpq.top_list();
-
-                loop {
-                    let sub_score = disi.inner_mut().score()?;
-                    score += sub_score;
+                let mut next = Some(dpq.top_list());
 
-                    if disi.next.is_null() {
-                        break;
-                    } else {
-                        unsafe { disi = &mut *disi.next };
+                while let Some(idx) = next {
+                    let disi = dpq.get_mut(idx);
+                    if Self::disi_matches(disi)? {
+                        let sub_score = disi.inner_mut().score()?;
+                        score += sub_score;
                     }
+                    next = disi.next;
                 }
 
                 Ok(score)
@@ -249,10 +358,9 @@ impl<T: Scorer> SubScorers<T> {
                 let mut score_sum = 0.0f32;
                 let mut score_max = f32::NEG_INFINITY;
 
-                let doc_id = sq.curr_doc;
-                for s in sq.scorers.iter_mut() {
-                    if s.doc_id() == doc_id {
-                        let sub_score = s.score()?;
+                for i in 0..sq.scorers.len() {
+                    if sq.sub_matches(i)? {
+                        let sub_score = sq.scorers[i].score()?;
 
                         score_sum += sub_score;
                         score_max = score_max.max(sub_score);
@@ -264,23 +372,80 @@ impl<T: Scorer> SubScorers<T> {
             SubScorers::DPQ(dbq) => {
                 let mut score_sum = 0.0f32;
                 let mut score_max = f32::NEG_INFINITY;
-                let mut disi = dbq.top_list();
+                let mut next = Some(dbq.top_list());
 
-                loop {
-                    let sub_score = disi.inner_mut().score()?;
-                    score_sum += sub_score;
-                    if sub_score > score_max {
-                        score_max = sub_score;
+                while let Some(idx) = next {
+                    let disi = dbq.get_mut(idx);
+                    if Self::disi_matches(disi)? {
+                        let sub_score = disi.inner_mut().score()?;
+                        score_sum += sub_score;
+                        if sub_score > score_max {
+                            score_max = sub_score;
+                        }
                     }
+                    next = disi.next;
+                }
+
+                Ok(score_max + (score_sum - score_max) * tie_breaker_multiplier)
+            }
+        }
+    }
 
-                    if disi.next.is_null() {
+    fn disi_matches(disi: &mut DisiWrapper<T>) -> Result<bool> {
+        if disi.inner().support_two_phase() {
+            disi.matches()
+        } else {
+            Ok(true)
+        }
+    }
+
+    /// Whether at least `min_should_match` sub scorers match the current doc,
+    /// two phase sub scorers are only verified until enough of them match.
+    fn matches(&mut self, min_should_match: i32) -> Result<bool> {
+        let min_should_match = min_should_match.max(DEFAULT_MIN_SHOULD_MATCH);
+        match self {
+            SubScorers::SQ(sq) => {
+                let doc = sq.curr_doc;
+                let mut count = sq
+                    .scorers
+                    .iter()
+                    .filter(|s| s.doc_id() == doc && !s.support_two_phase())
+                    .count() as i32;
+                for i in 0..sq.two_phase_by_cost.len() {
+                    if count >= min_should_match {
                         break;
-                    } else {
-                        unsafe { disi = &mut *disi.next };
+                    }
+                    if sq.sub_matches(sq.two_phase_by_cost[i])? {
+                        count += 1;
                     }
                 }
-
-                Ok(score_max + (score_sum - score_max) * tie_breaker_multiplier)
+                Ok(count >= min_should_match)
+            }
+            SubScorers::DPQ(dpq) => {
+                // only used when a single match is required
+                debug_assert_eq!(min_should_match, DEFAULT_MIN_SHOULD_MATCH);
+                let mut two_phase: Vec<usize> = vec![];
+                let mut next = Some(dpq.top_list());
+                while let Some(idx) = next {
+                    let wrapper = dpq.get(idx);
+                    if !wrapper.inner().support_two_phase() {
+                        return Ok(true);
+                    }
+                    two_phase.push(idx);
+                    next = wrapper.next;
+                }
+                two_phase.sort_by(|a, b| {
+                    dpq.get(*a)
+                        .match_cost()
+                        .partial_cmp(&dpq.get(*b).match_cost())
+                        .unwrap_or(Ordering::Equal)
+                });
+                for idx in two_phase {
+                    if dpq.get_mut(idx).matches()? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
             }
         }
     }
@@ -312,7 +477,7 @@ impl<T: Scorer> SubScorers<T> {
 
                         min_doc = min_doc.min(s.doc_id());
                     }
-                    sq.curr_doc = min_doc;
+                    sq.set_curr_doc(min_doc);
 
                     if min_should_match > DEFAULT_MIN_SHOULD_MATCH {
                         let mut should_count = 0;
@@ -359,7 +524,7 @@ impl<T: Scorer> SubScorers<T> {
                     min_doc = min_doc.min(s.doc_id());
                 }
 
-                sq.curr_doc = min_doc;
+                sq.set_curr_doc(min_doc);
                 Ok(sq.curr_doc)
             }
             SubScorers::DPQ(dbq) => {
@@ -375,3 +540,151 @@ impl<T: Scorer> SubScorers<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::tests::*;
+
+    // a two phase scorer whose approximation is `docs`, only the docs in
+    // `matching` match
+    struct MockTwoPhaseScorer {
+        approximation: MockDocIterator,
+        matching: Vec<DocId>,
+    }
+
+    impl MockTwoPhaseScorer {
+        fn new(docs: Vec<DocId>, matching: Vec<DocId>) -> MockTwoPhaseScorer {
+            MockTwoPhaseScorer {
+                approximation: MockDocIterator::new(docs),
+                matching,
+            }
+        }
+    }
+
+    impl Scorer for MockTwoPhaseScorer {
+        fn score(&mut self) -> Result<f32> {
+            Ok(self.doc_id() as f32)
+        }
+    }
+
+    impl DocIterator for MockTwoPhaseScorer {
+        fn doc_id(&self) -> DocId {
+            self.approximation.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            let mut doc = self.approximation.next()?;
+            while doc != NO_MORE_DOCS && !self.matches()? {
+                doc = self.approximation.next()?;
+            }
+            Ok(doc)
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            let mut doc = self.approximation.advance(target)?;
+            while doc != NO_MORE_DOCS && !self.matches()? {
+                doc = self.approximation.next()?;
+            }
+            Ok(doc)
+        }
+
+        fn cost(&self) -> usize {
+            self.approximation.cost()
+        }
+
+        fn matches(&mut self) -> Result<bool> {
+            Ok(self.matching.contains(&self.doc_id()))
+        }
+
+        fn match_cost(&self) -> f32 {
+            10f32
+        }
+
+        fn support_two_phase(&self) -> bool {
+            true
+        }
+
+        fn approximate_next(&mut self) -> Result<DocId> {
+            self.approximation.next()
+        }
+
+        fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+            self.approximation.advance(target)
+        }
+    }
+
+    fn collect_docs<S: Scorer>(scorer: &mut S) -> Vec<(DocId, f32)> {
+        let mut docs = vec![];
+        while scorer.next().unwrap() != NO_MORE_DOCS {
+            docs.push((scorer.doc_id(), scorer.score().unwrap()));
+        }
+        docs
+    }
+
+    #[test]
+    fn test_disjunction_sum_two_phase() {
+        let s1: Box<dyn Scorer> = Box::new(create_mock_scorer(vec![1, 4]));
+        let s2: Box<dyn Scorer> = Box::new(MockTwoPhaseScorer::new(vec![1, 2, 3], vec![1, 3]));
+        let mut scorer = DisjunctionSumScorer::new(vec![s1, s2], true, 1);
+
+        assert!(scorer.support_two_phase());
+        assert!(scorer.match_cost() > 0f32);
+        // doc 2 is only an approximate match of the phrase like sub scorer
+        assert_eq!(
+            collect_docs(&mut scorer),
+            vec![(1, 2.0), (3, 3.0), (4, 4.0)]
+        );
+
+        let mut scorer = DisjunctionSumScorer::new(
+            vec![
+                MockTwoPhaseScorer::new(vec![1, 2, 5], vec![1, 5]),
+                MockTwoPhaseScorer::new(vec![1, 2, 3], vec![2]),
+            ],
+            true,
+            2,
+        );
+        assert_eq!(scorer.approximate_next().unwrap(), 1);
+        assert!(!scorer.matches().unwrap());
+        assert_eq!(scorer.approximate_next().unwrap(), 2);
+        assert!(!scorer.matches().unwrap());
+        assert_eq!(scorer.approximate_next().unwrap(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_disjunction_max_two_phase() {
+        let mut scorer = DisjunctionMaxScorer::new(
+            vec![
+                MockTwoPhaseScorer::new(vec![1, 2, 4], vec![2, 4]),
+                MockTwoPhaseScorer::new(vec![2, 3, 4], vec![3, 4]),
+            ],
+            0.5,
+            true,
+        );
+
+        assert!(scorer.support_two_phase());
+        assert_eq!(
+            collect_docs(&mut scorer),
+            vec![(2, 2.0), (3, 3.0), (4, 6.0)]
+        );
+    }
+
+    #[test]
+    fn test_disjunction_without_two_phase() {
+        let mut scorer = DisjunctionSumScorer::new(
+            vec![
+                create_mock_scorer(vec![1, 3]),
+                create_mock_scorer(vec![2, 3]),
+            ],
+            true,
+            1,
+        );
+
+        assert!(!scorer.support_two_phase());
+        assert_eq!(scorer.match_cost(), 0f32);
+        assert_eq!(
+            collect_docs(&mut scorer),
+            vec![(1, 1.0), (2, 2.0), (3, 6.0)]
+        );
+    }
+}
@@ -51,6 +51,10 @@ mod phrase_scorer;
 
 pub use self::phrase_scorer::*;
 
+mod wand_scorer;
+
+pub use self::wand_scorer::*;
+
 use std::collections::HashMap;
 use std::i32;
 
@@ -97,6 +101,33 @@ pub trait Scorer: DocIterator {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         Ok(vec![])
     }
+
+    /// Advance to the block of documents that contains `target` in order to get
+    /// scoring information about this block, and returns the last doc of the
+    /// block. This is used by dynamic pruning scorers such as `WANDScorer`
+    /// to skip non-competitive blocks, `target` must be >= the current doc.
+    ///
+    /// The default implementation treats all the remaining docs as a single
+    /// block and returns `NO_MORE_DOCS`.
+    fn advance_shallow(&mut self, _target: DocId) -> Result<DocId> {
+        Ok(NO_MORE_DOCS)
+    }
+
+    /// Returns an upper bound of the score of all the docs between the current
+    /// doc and `up_to` included, `f32::MAX` if no bound is known.
+    fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+        Ok(f32::MAX)
+    }
+
+    /// Optional method: Tell the scorer that its iterator may safely ignore all
+    /// documents whose score is less than the given `min_score`. This is a no-op
+    /// by default.
+    ///
+    /// This method may only be called from collectors that don't need the exact
+    /// count of matching documents.
+    fn set_min_competitive_score(&mut self, _min_score: f32) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl Scorer for Box<dyn Scorer> {
@@ -111,6 +142,18 @@ impl Scorer for Box<dyn Scorer> {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         (**self).score_feature()
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        (**self).advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        (**self).max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        (**self).set_min_competitive_score(min_score)
+    }
 }
 
 impl DocIterator for Box<dyn Scorer> {
@@ -36,6 +36,19 @@ impl Scorer for ReqNotScorer {
     fn score(&mut self) -> Result<f32> {
         self.req_scorer.score()
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.req_scorer.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        self.req_scorer.max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        // excluded docs don't contribute to the score
+        self.req_scorer.set_min_competitive_score(min_score)
+    }
 }
 
 impl DocIterator for ReqNotScorer {
@@ -26,6 +26,7 @@ use crate::core::search::searcher::IndexSearcher;
 use crate::core::search::sort_field::ScoreDocHit;
 use crate::core::search::sort_field::SortFieldType;
 use crate::core::search::sort_field::TopDocs;
+use crate::core::search::ScoreMode;
 use crate::core::util::DocId;
 use crate::core::util::{IndexedContext, VariantValue};
 
@@ -74,6 +75,12 @@ pub struct RescoreRequest<C: Codec> {
 }
 
 impl<C: Codec> RescoreRequest<C> {
+    /// Creates a request that rescores the top `window_size` hits with `query`,
+    /// adding the rescore score to the first pass score.
+    pub fn with_query(query: Box<dyn Query<C>>, window_size: usize) -> RescoreRequest<C> {
+        Self::new(query, 1.0, 1.0, RescoreMode::Total, window_size, false)
+    }
+
     pub fn new(
         query: Box<dyn Query<C>>,
         query_weight: f32,
@@ -91,6 +98,36 @@ impl<C: Codec> RescoreRequest<C> {
             rescore_movedout,
         }
     }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    /// weight applied to the first pass score before combination
+    pub fn query_weight(&self) -> f32 {
+        self.query_weight
+    }
+
+    pub fn set_query_weight(&mut self, query_weight: f32) {
+        self.query_weight = query_weight;
+    }
+
+    /// weight applied to the rescore query score before combination
+    pub fn rescore_weight(&self) -> f32 {
+        self.rescore_weight
+    }
+
+    pub fn set_rescore_weight(&mut self, rescore_weight: f32) {
+        self.rescore_weight = rescore_weight;
+    }
+
+    pub fn rescore_mode(&self) -> &RescoreMode {
+        &self.rescore_mode
+    }
+
+    pub fn set_rescore_mode(&mut self, rescore_mode: RescoreMode) {
+        self.rescore_mode = rescore_mode;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -331,7 +368,7 @@ impl QueryRescorer {
             _ => {}
         }
 
-        let weight = req.query.create_weight(searcher, true)?;
+        let weight = req.query.create_weight(searcher, ScoreMode::Complete)?;
 
         if let Some(batch_scorer) = weight.create_batch_scorer() {
             self.batch_rescore(
@@ -426,7 +463,7 @@ impl QueryRescorer {
         let rescore = searcher.explain(req.query.as_ref(), doc)?;
         let rescore_value = rescore.value();
         let first_value = first.value();
-        let primary_weight = 1.0f32;
+        let primary_weight = req.query_weight;
 
         let prim = if first.is_match() {
             Explanation::new(
@@ -451,7 +488,7 @@ impl QueryRescorer {
         // description with which ScoreMode was used.  Maybe we should add
         // QueryRescorer.explainCombine to Lucene?
         if rescore.is_match() {
-            let secondary_weight = 1.0f32;
+            let secondary_weight = req.rescore_weight;
             let sec = Explanation::new(
                 true,
                 rescore_value * secondary_weight,
@@ -469,8 +506,8 @@ impl QueryRescorer {
 
             Ok(Explanation::new(
                 true,
-                self.combine_score(req, prim.value(), true, sec.value()),
-                "sum of:".to_string(),
+                req.rescore_mode.combine(prim.value(), sec.value()),
+                format!("{} of:", req.rescore_mode),
                 vec![prim, sec],
             ))
         } else {
@@ -486,7 +523,7 @@ impl QueryRescorer {
     ) -> Result<(Vec<Option<Vec<FeatureResult>>>, Vec<f32>)> {
         let hits = top_docs.score_docs();
         let readers = searcher.reader().leaves();
-        let weight = req.query.create_weight(searcher, true)?;
+        let weight = req.query.create_weight(searcher, ScoreMode::Complete)?;
 
         let mut hit_upto = 0usize;
         let mut end_doc = 0;
@@ -605,3 +642,141 @@ impl Rescorer for QueryRescorer {
         self.explain_inner(searcher, req, first, doc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::CodecEnum;
+    use crate::core::doc::StringField;
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::index::Term;
+    use crate::core::search::collector::TopDocsCollector;
+    use crate::core::search::query::TermQuery;
+    use crate::core::search::DefaultIndexSearcher;
+
+    use std::sync::Arc;
+
+    const MODES: [RescoreMode; 5] = [
+        RescoreMode::Avg,
+        RescoreMode::Max,
+        RescoreMode::Min,
+        RescoreMode::Total,
+        RescoreMode::Multiply,
+    ];
+
+    // all the docs are tagged "a", docs 0, 3 and 6 are tagged "b" too
+    fn searcher() -> impl IndexSearcher<CodecEnum> {
+        let writer = in_memory_writer();
+        for i in 0..8 {
+            let mut doc = vec![StringField::new("tag", "a", false)];
+            if i % 3 == 0 {
+                doc.push(StringField::new("tag", "b", false));
+            }
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        DefaultIndexSearcher::new(Arc::new(writer.get_reader(true, false).unwrap()), None)
+    }
+
+    fn term_query(tag: &str) -> Box<dyn Query<CodecEnum>> {
+        Box::new(TermQuery::new(
+            Term::new("tag".into(), tag.as_bytes().to_vec()),
+            1.0,
+            None,
+        ))
+    }
+
+    fn top_docs<IS: IndexSearcher<CodecEnum>>(searcher: &IS, tag: &str) -> TopDocs {
+        let mut collector = TopDocsCollector::new(10);
+        searcher
+            .search(term_query(tag).as_ref(), &mut collector)
+            .unwrap();
+        collector.top_docs()
+    }
+
+    fn scores(top_docs: &TopDocs) -> HashMap<DocId, f32> {
+        top_docs
+            .score_docs()
+            .iter()
+            .map(|hit| (hit.doc_id(), hit.score()))
+            .collect()
+    }
+
+    fn request(mode: &RescoreMode) -> RescoreRequest<CodecEnum> {
+        let mut req = RescoreRequest::with_query(term_query("b"), 10);
+        req.set_query_weight(2.0);
+        req.set_rescore_weight(0.5);
+        req.set_rescore_mode(mode.clone());
+        req
+    }
+
+    #[test]
+    fn test_rescore_modes() {
+        let searcher = searcher();
+        let first = scores(&top_docs(&searcher, "a"));
+        let second = scores(&top_docs(&searcher, "b"));
+        assert_eq!(first.len(), 8);
+        assert_eq!(second.len(), 3);
+
+        for mode in &MODES {
+            let mut docs = top_docs(&searcher, "a");
+            QueryRescorer
+                .rescore(&searcher, &request(mode), &mut docs)
+                .unwrap();
+            let hits = docs.score_docs();
+            assert_eq!(hits.len(), 8);
+            for hit in hits {
+                let doc = hit.doc_id();
+                let expected = match second.get(&doc) {
+                    Some(s) => mode.combine(first[&doc] * 2.0, s * 0.5),
+                    None => first[&doc] * 2.0,
+                };
+                assert!(
+                    (hit.score() - expected).abs() < 1e-5,
+                    "{}: doc {} scored {} instead of {}",
+                    mode,
+                    doc,
+                    hit.score(),
+                    expected
+                );
+            }
+            // the rescored hits are sorted by their new score
+            assert!(hits.windows(2).all(|w| w[0].score() >= w[1].score()));
+        }
+    }
+
+    #[test]
+    fn test_rescore_explain() {
+        let searcher = searcher();
+        let first = scores(&top_docs(&searcher, "a"));
+        let second = scores(&top_docs(&searcher, "b"));
+
+        for mode in &MODES {
+            let req = request(mode);
+            let a = term_query("a");
+
+            // doc 3 matches the rescore query
+            let explanation = QueryRescorer
+                .explain(&searcher, &req, searcher.explain(a.as_ref(), 3).unwrap(), 3)
+                .unwrap();
+            assert!(explanation.is_match());
+            assert_eq!(explanation.description(), format!("{} of:", mode));
+            let expected = mode.combine(first[&3] * 2.0, second[&3] * 0.5);
+            assert!((explanation.value() - expected).abs() < 1e-5);
+            let details = explanation.details();
+            assert_eq!(details.len(), 2);
+            assert!((details[0].value() - first[&3] * 2.0).abs() < 1e-5);
+            assert_eq!(details[0].details()[1].description(), "primaryWeight");
+            assert!((details[1].value() - second[&3] * 0.5).abs() < 1e-5);
+            assert_eq!(details[1].details()[1].description(), "secondaryWeight");
+
+            // doc 1 only matches the first pass query
+            let explanation = QueryRescorer
+                .explain(&searcher, &req, searcher.explain(a.as_ref(), 1).unwrap(), 1)
+                .unwrap();
+            assert!(explanation.is_match());
+            assert_eq!(explanation.description(), "product of:");
+            assert!((explanation.value() - first[&1] * 2.0).abs() < 1e-5);
+        }
+    }
+}
@@ -11,16 +11,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::codec::postings::Impacts;
 use crate::core::codec::PostingIterator;
 use crate::core::search::scorer::Scorer;
 use crate::core::search::similarity::SimScorer;
-use crate::core::search::DocIterator;
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
 use crate::core::util::DocId;
 use crate::Result;
 
 pub struct TermScorer<T: PostingIterator> {
     sim_scorer: Box<dyn SimScorer>,
     postings_iterator: T,
+    // the impacts of the postings, which bound the score of their blocks
+    impacts: Option<Impacts>,
+    // the last doc of the block the impacts were last advanced to, -1 if they
+    // weren't advanced yet
+    shallow_up_to: DocId,
+    min_competitive_score: f32,
+    // set once the minimum competitive score exceeds the max score of the
+    // term, the remaining docs are then skipped
+    non_competitive: bool,
 }
 
 impl<T: PostingIterator> TermScorer<T> {
@@ -28,9 +38,25 @@ impl<T: PostingIterator> TermScorer<T> {
         TermScorer {
             sim_scorer,
             postings_iterator,
+            impacts: None,
+            shallow_up_to: -1,
+            min_competitive_score: 0f32,
+            non_competitive: false,
         }
     }
 
+    /// Creates a scorer that skips the blocks of postings whose impacts show
+    /// they can't hold a competitive doc.
+    pub fn with_impacts(
+        sim_scorer: Box<dyn SimScorer>,
+        postings_iterator: T,
+        impacts: Impacts,
+    ) -> Self {
+        let mut scorer = Self::new(sim_scorer, postings_iterator);
+        scorer.impacts = Some(impacts);
+        scorer
+    }
+
     fn freq(&self) -> i32 {
         if let Ok(f) = self.postings_iterator.freq() {
             f
@@ -38,6 +64,50 @@ impl<T: PostingIterator> TermScorer<T> {
             1
         }
     }
+
+    // the max score of the docs up to `up_to`, from the impacts of the
+    // smallest block holding them
+    fn impacts_max_score(&self, up_to: DocId) -> Result<Option<f32>> {
+        if let Some(impacts) = &self.impacts {
+            for level in 0..impacts.num_levels() {
+                if impacts.doc_id_up_to(level) >= up_to {
+                    let level_impacts = impacts.impacts(level)?;
+                    if level_impacts.is_empty() {
+                        // the docs after the last full block can score anything
+                        return Ok(None);
+                    }
+                    let max_score = level_impacts
+                        .iter()
+                        .map(|i| self.sim_scorer.max_score_for_impact(i.freq as f32, i.norm))
+                        .fold(0f32, f32::max);
+                    return Ok(Some(max_score));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // the first doc from `target` in a block that may hold a competitive doc
+    fn competitive_target(&mut self, mut target: DocId) -> Result<DocId> {
+        if self.impacts.is_none() || self.min_competitive_score <= 0f32 {
+            return Ok(target);
+        }
+        while target != NO_MORE_DOCS {
+            let up_to = self.advance_shallow(target)?;
+            debug_assert!(up_to >= target);
+            match self.impacts_max_score(up_to)? {
+                Some(max_score) if max_score < self.min_competitive_score && up_to >= target => {
+                    target = if up_to == NO_MORE_DOCS {
+                        NO_MORE_DOCS
+                    } else {
+                        up_to + 1
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(target)
+    }
 }
 
 impl<T: PostingIterator> Scorer for TermScorer<T> {
@@ -46,6 +116,38 @@ impl<T: PostingIterator> Scorer for TermScorer<T> {
         let freq = self.freq();
         Ok(self.sim_scorer.score(doc_id, freq as f32)?)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        if let Some(impacts) = &mut self.impacts {
+            if target > self.shallow_up_to {
+                impacts.advance_shallow(target)?;
+                self.shallow_up_to = impacts.doc_id_up_to(0);
+            }
+            return Ok(self.shallow_up_to);
+        }
+        Ok(NO_MORE_DOCS)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        if self.impacts.is_some() {
+            // the levels must hold the current doc for their bounds to apply
+            let doc_id = self.doc_id().max(0);
+            self.advance_shallow(doc_id)?;
+            if let Some(max_score) = self.impacts_max_score(up_to)? {
+                return Ok(max_score);
+            }
+        }
+        Ok(self.sim_scorer.max_score(f32::INFINITY))
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        self.min_competitive_score = min_score;
+        // without impacts the only bound is the max score over all freqs
+        if min_score > self.sim_scorer.max_score(f32::INFINITY) {
+            self.non_competitive = true;
+        }
+        Ok(())
+    }
 }
 
 impl<T: PostingIterator> DocIterator for TermScorer<T> {
@@ -54,10 +156,24 @@ impl<T: PostingIterator> DocIterator for TermScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
+        if self.non_competitive {
+            return self.postings_iterator.advance(NO_MORE_DOCS);
+        }
+        if self.impacts.is_some() && self.min_competitive_score > 0f32 {
+            let doc_id = self.doc_id();
+            if doc_id == NO_MORE_DOCS {
+                return Ok(NO_MORE_DOCS);
+            }
+            return self.advance(doc_id + 1);
+        }
         self.postings_iterator.next()
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
+        if self.non_competitive {
+            return self.postings_iterator.advance(NO_MORE_DOCS);
+        }
+        let target = self.competitive_target(target)?;
         self.postings_iterator.advance(target)
     }
 
@@ -65,3 +181,68 @@ impl<T: PostingIterator> DocIterator for TermScorer<T> {
         self.postings_iterator.cost()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+    use crate::core::doc::{Field, FieldType, Fieldable, IndexOptions};
+    use crate::core::index::reader::IndexReader;
+    use crate::core::index::tests::in_memory_writer;
+    use crate::core::index::Term;
+    use crate::core::search::query::TermQuery;
+    use crate::core::search::searcher::SearchPlanBuilder;
+    use crate::core::search::{DefaultIndexSearcher, IndexSearcher, ScoreMode};
+
+    #[test]
+    fn test_term_scorer_skips_non_competitive_blocks() {
+        // "a" is once in each of 1000 docs, but 4 times in the block of docs 512..640
+        let writer = in_memory_writer();
+        let field_type = FieldType {
+            index_options: IndexOptions::DocsAndFreqs,
+            omit_norms: true,
+            ..FieldType::default()
+        };
+        for i in 0..1000 {
+            let text = if (512..640).contains(&i) {
+                "a a a a"
+            } else {
+                "a"
+            };
+            let token_stream = WhitespaceAnalyzer.token_stream("body", text).unwrap();
+            let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "body".into(),
+                field_type.clone(),
+                None,
+                Some(token_stream),
+            ))];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(&reader, None);
+
+        let query = TermQuery::new(Term::new("body".into(), b"a".to_vec()), 1.0, None);
+        let weight = searcher
+            .create_weight(&query, ScoreMode::TopScores)
+            .unwrap();
+        let leaves = searcher.reader().leaves();
+        assert_eq!(leaves.len(), 1);
+        let mut scorer = weight.create_scorer(&leaves[0]).unwrap().unwrap();
+
+        assert_eq!(scorer.next().unwrap(), 0);
+        let low_score = scorer.score().unwrap();
+        assert_eq!(scorer.advance_shallow(0).unwrap(), 127);
+        assert!((scorer.max_score(127).unwrap() - low_score).abs() < f32::EPSILON);
+
+        // the blocks of docs scoring `low_score` are skipped, up to the docs after
+        // the last full block, whose scores are unknown
+        scorer.set_min_competitive_score(low_score * 1.1).unwrap();
+        assert_eq!(scorer.next().unwrap(), 512);
+        assert!(scorer.score().unwrap() > low_score * 1.1);
+        for doc in 513..640 {
+            assert_eq!(scorer.next().unwrap(), doc);
+        }
+        assert_eq!(scorer.next().unwrap(), 896);
+    }
+}
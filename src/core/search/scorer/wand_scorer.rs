@@ -0,0 +1,313 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::search::scorer::Scorer;
+use crate::core::search::{DocIterator, NO_MORE_DOCS};
+use crate::core::util::DocId;
+
+use crate::Result;
+
+/// A disjunction scorer that skips documents which can't be competitive.
+///
+/// This is an implementation of block-max MaxScore: the doc id space is split
+/// into windows whose bounds are given by the `advance_shallow` of the
+/// sub-scorers. Term scorers created for `ScoreMode::TopScores` bound their
+/// windows with the blocks of the postings impacts, other scorers treat all
+/// the remaining docs as a single window. Within a window, sub-scorers are
+/// sorted by their maximum score and the ones whose cumulated maximum score is
+/// below the minimum competitive score are "non-essential": they can't produce
+/// a competitive hit on their own and are only advanced to check candidates
+/// produced by the essential scorers.
+///
+/// As long as `set_min_competitive_score` is never called, this scorer behaves
+/// like `DisjunctionSumScorer` with `min_should_match == 1`.
+pub struct WANDScorer<T: Scorer> {
+    scorers: Vec<T>,
+    // upper bound of each scorer's score in the current window
+    max_scores: Vec<f32>,
+    // indexes of `scorers`, sorted by ascending max score
+    order: Vec<usize>,
+    // sum of the max scores of order[..i], of length `scorers.len() + 1`
+    max_score_sums: Vec<f64>,
+    // order[..num_non_essential] are the non-essential scorers
+    num_non_essential: usize,
+    // last doc of the current window, -1 if the window must be recomputed
+    up_to: DocId,
+    min_competitive_score: f32,
+    doc: DocId,
+    score: f32,
+    cost: usize,
+}
+
+impl<T: Scorer> WANDScorer<T> {
+    pub fn new(scorers: Vec<T>) -> WANDScorer<T> {
+        debug_assert!(!scorers.is_empty());
+
+        let cost = scorers.iter().map(|s| s.cost()).sum();
+        let num_scorers = scorers.len();
+        WANDScorer {
+            scorers,
+            max_scores: vec![f32::MAX; num_scorers],
+            order: (0..num_scorers).collect(),
+            max_score_sums: vec![0f64; num_scorers + 1],
+            num_non_essential: 0,
+            up_to: -1,
+            min_competitive_score: 0f32,
+            doc: -1,
+            score: 0f32,
+            cost,
+        }
+    }
+
+    /// Compute the window containing `target` and re-partition the sub-scorers
+    /// into essential and non-essential ones.
+    fn update_max_scores(&mut self, target: DocId) -> Result<()> {
+        let mut up_to = NO_MORE_DOCS;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() != NO_MORE_DOCS {
+                let t = target.max(scorer.doc_id());
+                up_to = up_to.min(scorer.advance_shallow(t)?);
+            }
+        }
+        debug_assert!(up_to >= target);
+        self.up_to = up_to;
+
+        for (i, scorer) in self.scorers.iter_mut().enumerate() {
+            self.max_scores[i] = if scorer.doc_id() == NO_MORE_DOCS {
+                0f32
+            } else {
+                scorer.max_score(up_to)?
+            };
+        }
+        let max_scores = &self.max_scores;
+        self.order
+            .sort_by(|a, b| max_scores[*a].total_cmp(&max_scores[*b]));
+
+        self.num_non_essential = 0;
+        for i in 0..self.order.len() {
+            let sum = self.max_score_sums[i] + f64::from(self.max_scores[self.order[i]]);
+            self.max_score_sums[i + 1] = sum;
+            if sum < f64::from(self.min_competitive_score) {
+                self.num_non_essential = i + 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn do_advance(&mut self, mut target: DocId) -> Result<DocId> {
+        loop {
+            if target == NO_MORE_DOCS {
+                self.doc = NO_MORE_DOCS;
+                return Ok(NO_MORE_DOCS);
+            }
+            if target > self.up_to {
+                self.update_max_scores(target)?;
+            }
+            if self.num_non_essential == self.scorers.len() {
+                // no doc in this window can be competitive
+                if self.up_to == NO_MORE_DOCS {
+                    self.doc = NO_MORE_DOCS;
+                    return Ok(NO_MORE_DOCS);
+                }
+                target = self.up_to + 1;
+                continue;
+            }
+
+            // find the next candidate among the essential scorers
+            let mut candidate = NO_MORE_DOCS;
+            for &idx in &self.order[self.num_non_essential..] {
+                let scorer = &mut self.scorers[idx];
+                let doc = if scorer.doc_id() < target {
+                    scorer.advance(target)?
+                } else {
+                    scorer.doc_id()
+                };
+                candidate = candidate.min(doc);
+            }
+            if candidate == NO_MORE_DOCS {
+                self.doc = NO_MORE_DOCS;
+                return Ok(NO_MORE_DOCS);
+            }
+            if candidate > self.up_to {
+                // move to the window of the candidate
+                target = candidate;
+                continue;
+            }
+
+            let mut score = 0f64;
+            for &idx in &self.order[self.num_non_essential..] {
+                let scorer = &mut self.scorers[idx];
+                if scorer.doc_id() == candidate {
+                    score += f64::from(scorer.score()?);
+                }
+            }
+
+            let min_competitive_score = f64::from(self.min_competitive_score);
+            let mut competitive = true;
+            for i in (0..self.num_non_essential).rev() {
+                if score + self.max_score_sums[i + 1] < min_competitive_score {
+                    competitive = false;
+                    break;
+                }
+                let scorer = &mut self.scorers[self.order[i]];
+                if scorer.doc_id() < candidate {
+                    scorer.advance(candidate)?;
+                }
+                if scorer.doc_id() == candidate {
+                    score += f64::from(scorer.score()?);
+                }
+            }
+
+            if competitive && score >= min_competitive_score {
+                self.doc = candidate;
+                self.score = score as f32;
+                return Ok(candidate);
+            }
+            target = candidate + 1;
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for WANDScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        Ok(self.score)
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let mut up_to = NO_MORE_DOCS;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() != NO_MORE_DOCS {
+                let t = target.max(scorer.doc_id());
+                up_to = up_to.min(scorer.advance_shallow(t)?);
+            }
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        let mut sum = 0f64;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() <= up_to {
+                sum += f64::from(scorer.max_score(up_to)?);
+            }
+        }
+        Ok(sum.min(f64::from(f32::MAX)) as f32)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        debug_assert!(min_score >= self.min_competitive_score);
+        if min_score > self.min_competitive_score {
+            self.min_competitive_score = min_score;
+            // force the partition of the sub-scorers to be recomputed
+            self.up_to = -1;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Scorer> DocIterator for WANDScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.do_advance(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.do_advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::tests::*;
+
+    struct BoundedScorer {
+        scorer: MockSimpleScorer<MockDocIterator>,
+        max_score: f32,
+    }
+
+    impl Scorer for BoundedScorer {
+        fn score(&mut self) -> Result<f32> {
+            Ok(self.max_score)
+        }
+
+        fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+            Ok(self.max_score)
+        }
+    }
+
+    impl DocIterator for BoundedScorer {
+        fn doc_id(&self) -> DocId {
+            self.scorer.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.scorer.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.scorer.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.scorer.cost()
+        }
+    }
+
+    fn bounded(docs: Vec<DocId>, max_score: f32) -> BoundedScorer {
+        BoundedScorer {
+            scorer: create_mock_scorer(docs),
+            max_score,
+        }
+    }
+
+    #[test]
+    fn test_wand_scorer_without_min_score() {
+        let s1 = bounded(vec![1, 3, 5, 7], 1.0);
+        let s2 = bounded(vec![2, 3, 6, 7], 2.0);
+        let mut scorer = WANDScorer::new(vec![s1, s2]);
+
+        let mut docs = vec![];
+        let mut scores = vec![];
+        while scorer.next().unwrap() != NO_MORE_DOCS {
+            docs.push(scorer.doc_id());
+            scores.push(scorer.score().unwrap());
+        }
+        assert_eq!(docs, vec![1, 2, 3, 5, 6, 7]);
+        assert_eq!(scores, vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_wand_scorer_skips_non_competitive_docs() {
+        let s1 = bounded(vec![1, 3, 5, 7, 9], 1.0);
+        let s2 = bounded(vec![2, 3, 6, 7, 8], 2.0);
+        let mut scorer = WANDScorer::new(vec![s1, s2]);
+
+        assert_eq!(scorer.next().unwrap(), 1);
+        scorer.set_min_competitive_score(2.5).unwrap();
+        assert_eq!(scorer.next().unwrap(), 3);
+        assert!((scorer.score().unwrap() - 3.0).abs() < f32::EPSILON);
+        assert_eq!(scorer.next().unwrap(), 7);
+
+        scorer.set_min_competitive_score(3.5).unwrap();
+        assert_eq!(scorer.next().unwrap(), NO_MORE_DOCS);
+    }
+}
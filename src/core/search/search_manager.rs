@@ -16,25 +16,25 @@ use crate::core::{
     index::merge::{MergePolicy, MergeScheduler},
     index::reader::{IndexReader, StandardDirectoryReader},
     index::writer::IndexWriter,
-    search::searcher::IndexSearcher,
+    search::searcher::{DefaultIndexSearcher, DefaultSimilarityProducer, IndexSearcher},
     store::directory::Directory,
 };
 
 use std::ops::Deref;
 use std::{
     mem,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
 };
 
-use crate::error::{
-    Error::{AlreadyClosed, IllegalState},
-    Result,
-};
+use crate::error::{Error::AlreadyClosed, Result};
 
 /// Utility class to safely share `IndexSearcher` instances across multiple
 /// threads, while periodically reopening. This class ensures each searcher is
 /// closed only once all threads have finished using it.
 ///
+/// Use `acquire` to obtain the current searcher, and `release` to release it
+/// once you are done with it: the searcher, and its reader, are dropped when
+/// they are released by the last user after being replaced by a refresh.
 ///
 /// In addition you should periodically call {@link #maybeRefresh}. While it's
 /// possible to call this just before running each query, this is discouraged
@@ -47,6 +47,13 @@ pub struct SearcherManager<C: Codec, T, SF: SearcherFactory<C>> {
     refresh_listener: Option<T>,
 }
 
+// the searchers are only used for searching, which is safe from any thread,
+// this allows to refresh the manager from a background thread, see
+// `ControlledRealTimeReopenThread`
+unsafe impl<C: Codec, T: Send, SF: SearcherFactory<C> + Send> Send for SearcherManager<C, T, SF> {}
+
+unsafe impl<C: Codec, T: Sync, SF: SearcherFactory<C> + Sync> Sync for SearcherManager<C, T, SF> {}
+
 impl<C: Codec, T, SF: SearcherFactory<C>> SearcherManager<C, T, SF> {
     pub fn from_writer<D, MS, MP>(
         writer: &IndexWriter<D, C, MS, MP>,
@@ -89,7 +96,7 @@ where
     C: Codec,
     T: Deref<Target = RL>,
     SF: SearcherFactory<C>,
-    RL: RefreshListener,
+    RL: RefreshListener + ?Sized,
 {
     fn base(&self) -> &ReferenceManagerBase<SF::Searcher> {
         &self.manager_base
@@ -100,7 +107,7 @@ where
     }
 
     fn dec_ref(&self, _reference: &SF::Searcher) -> Result<()> {
-        // reference.reader().dec_ref()
+        // the searcher and its reader are dropped with the last `Arc`
         Ok(())
     }
 
@@ -118,27 +125,56 @@ where
     }
 
     fn try_inc_ref(&self, _reference: &Arc<SF::Searcher>) -> Result<bool> {
-        // reference.reader().inc_ref()
+        // cloning the `Arc` is the reference
         Ok(true)
     }
 
-    fn ref_count(&self, _reference: &SF::Searcher) -> u32 {
-        // TODO ?
-        1
+    fn ref_count(&self, reference: &Arc<SF::Searcher>) -> u32 {
+        Arc::strong_count(reference) as u32
     }
 }
 
 /// Factory used by `SearcherManager` to create new `IndexSearcher` impls.
+///
+/// Implement this to configure the new searchers, e.g. with a query cache or
+/// a thread pool, or to warm them with a few queries before they are
+/// published to the searching threads.
 pub trait SearcherFactory<C: Codec> {
     type Searcher: IndexSearcher<C>;
     /// Returns a new IndexSearcher over the given reader.
     fn new_searcher(&self, reader: Arc<dyn IndexReader<Codec = C>>) -> Result<Self::Searcher>;
 }
 
+/// A `SearcherFactory` creating `DefaultIndexSearcher`s with the default
+/// similarity.
+#[derive(Default)]
+pub struct DefaultSearcherFactory {
+    next_limit: Option<usize>,
+}
+
+impl DefaultSearcherFactory {
+    pub fn new(next_limit: Option<usize>) -> Self {
+        DefaultSearcherFactory { next_limit }
+    }
+}
+
+impl<C: Codec> SearcherFactory<C> for DefaultSearcherFactory {
+    type Searcher = DefaultIndexSearcher<
+        C,
+        dyn IndexReader<Codec = C>,
+        Arc<dyn IndexReader<Codec = C>>,
+        DefaultSimilarityProducer,
+    >;
+
+    fn new_searcher(&self, reader: Arc<dyn IndexReader<Codec = C>>) -> Result<Self::Searcher> {
+        Ok(DefaultIndexSearcher::new(reader, self.next_limit))
+    }
+}
+
 pub struct ReferenceManagerBase<T: ?Sized> {
     lock: Mutex<()>,
     refresh_lock: Mutex<()>,
-    current: Option<Arc<T>>,
+    current: RwLock<Option<Arc<T>>>,
 }
 
 impl<T: ?Sized> ReferenceManagerBase<T> {
@@ -146,11 +182,11 @@ impl<T: ?Sized> ReferenceManagerBase<T> {
         Self {
             lock: Mutex::new(()),
             refresh_lock: Mutex::new(()),
-            current: Some(current),
+            current: RwLock::new(Some(current)),
         }
     }
     fn ensure_open(&self) -> Result<()> {
-        if self.current.is_none() {
+        if self.current.read()?.is_none() {
             return Err(AlreadyClosed("this ReferenceManager is closed".into()));
         }
         Ok(())
@@ -162,20 +198,15 @@ impl<T: ?Sized> ReferenceManagerBase<T> {
 /// reference is closed only once all threads have finished using it. It is
 /// recommended to consult the documentation of {@link ReferenceManager}
 /// implementations for their {@link #maybeRefresh()} semantics.
-pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
+pub trait ReferenceManager<T: ?Sized, RL: RefreshListener + ?Sized> {
     fn base(&self) -> &ReferenceManagerBase<T>;
 
     fn refresh_listener(&self) -> Option<&RL>;
 
     fn _swap_reference(&self, new_reference: Option<Arc<T>>, _l: &MutexGuard<()>) -> Result<()> {
-        // let _l = base.lock.lock().unwrap();
-        // we use base.lock to make sure this operation is safe
-        let base_ptr =
-            self.base() as *const ReferenceManagerBase<T> as *mut ReferenceManagerBase<T>;
-        unsafe {
-            if let Some(old_ref) = mem::replace(&mut (*base_ptr).current, new_reference) {
-                self.release(old_ref.as_ref())?;
-            }
+        let old_ref = mem::replace(&mut *self.base().current.write()?, new_reference);
+        if let Some(old_ref) = old_ref {
+            self.release(old_ref)?;
         }
         Ok(())
     }
@@ -196,17 +227,12 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
     /// reference to None to prevent accidental usage after it has been released.
     fn acquire(&self) -> Result<Arc<T>> {
         loop {
-            if let Some(ref cur) = self.base().current {
+            if let Some(ref cur) = *self.base().current.read()? {
                 if self.try_inc_ref(cur)? {
                     return Ok(Arc::clone(cur));
                 }
-            // TODO double check for ref count
             } else {
-                return Err(IllegalState(
-                    "this ReferenceManager is closed - this is likely a bug when the reference \
-                     count is modified outside of the ReferenceManager"
-                        .into(),
-                ));
+                return Err(AlreadyClosed("this ReferenceManager is closed".into()));
             }
         }
     }
@@ -226,7 +252,7 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
     /// {@link AlreadyClosedException}.
     fn close(&self) -> Result<()> {
         let _l = self.base().lock.lock().unwrap();
-        if self.base().current.is_some() {
+        if self.base().current.read()?.is_some() {
             // make sure we can call this more than once
             // closeable javadoc says:
             // if this is already closed then invoking this method has no effect.
@@ -236,8 +262,9 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
         Ok(())
     }
 
-    /// Returns the current reference count of the given reference
-    fn ref_count(&self, reference: &T) -> u32;
+    /// Returns the current reference count of the given reference, including
+    /// the one held by this manager if it is the current reference.
+    fn ref_count(&self, reference: &Arc<T>) -> u32;
 
     /// Called after close(), so subclass can free any resources.
     fn after_close(&self) -> Result<()> {
@@ -251,7 +278,7 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
         let reference = self.acquire()?;
         self.notify_refresh_listeners_before()?;
         let res = self._exec_maybe_refresh(&reference, &mut refreshed, l);
-        if let Err(e) = self.release(reference.as_ref()) {
+        if let Err(e) = self.release(reference) {
             log::error!("release old reference failed by: {:?}", e);
         }
         if let Err(e) = self.notify_refresh_listeners_refreshed(refreshed) {
@@ -283,7 +310,7 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
     fn _do_swap(&self, reference: Arc<T>, l: &MutexGuard<()>) -> Result<()> {
         let res = self._swap_reference(Some(Arc::clone(&reference)), l);
         if res.is_err() {
-            let _ = self.release(reference.as_ref());
+            let _ = self.release(reference);
         }
         res
     }
@@ -321,8 +348,10 @@ pub trait ReferenceManager<T: ?Sized, RL: RefreshListener> {
         Ok(())
     }
 
-    fn release(&self, reference: &T) -> Result<()> {
-        self.dec_ref(reference)
+    /// Release the reference previously obtained via `acquire`, it must not
+    /// be used after being released.
+    fn release(&self, reference: Arc<T>) -> Result<()> {
+        self.dec_ref(reference.as_ref())
     }
 
     fn notify_refresh_listeners_before(&self) -> Result<()> {
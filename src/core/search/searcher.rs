@@ -14,7 +14,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crossbeam::channel::{unbounded, Receiver, Sender};
 
@@ -27,17 +27,23 @@ use crate::core::index::reader::{
 };
 use crate::core::index::Term;
 use crate::core::search::cache::{
-    LRUQueryCache, QueryCache, QueryCachingPolicy, UsageTrackingQueryCachingPolicy,
+    LRUQueryCache, NoCacheQueryCache, QueryCache, QueryCachingPolicy,
+    UsageTrackingQueryCachingPolicy,
 };
-use crate::core::search::collector::{self, Collector, ParallelLeafCollector, SearchCollector};
+use crate::core::search::collector::{
+    self, Collector, CollectorManager, ParallelLeafCollector, SearchCollector,
+};
+use crate::core::search::distributed::DistributedStatistics;
 use crate::core::search::explanation::Explanation;
+use crate::core::search::matches::Matches;
+use crate::core::search::profile::{ProfileResult, ProfilingPlanBuilder, QueryProfiler};
 use crate::core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
 use crate::core::search::scorer::{BulkScorer, Scorer};
 use crate::core::search::similarity::{
     BM25Similarity, SimScorer, SimWeight, Similarity, SimilarityProducer,
 };
 use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
-use crate::core::search::NO_MORE_DOCS;
+use crate::core::search::{ScoreMode, NO_MORE_DOCS};
 use crate::core::util::external::{DefaultContext, ThreadPool, ThreadPoolBuilder};
 use crate::core::util::{Bits, DocId, KeyedContext};
 
@@ -48,6 +54,44 @@ const MAX_DOCS_PER_SLICE: i32 = 250_000;
 const MAX_SEGMENTS_PER_SLICE: i32 = 20;
 const MIN_PARALLEL_SLICES: i32 = 3;
 
+/// Controls how the segments of the reader are grouped into slices for
+/// concurrent search, each slice is searched sequentially by one job of
+/// the `SearchExecutor`.
+#[derive(Debug, Clone, Copy)]
+pub struct SlicePolicy {
+    /// segments with more docs than this get their own slice, smaller
+    /// segments are grouped until a slice holds this many docs.
+    pub max_docs_per_slice: i32,
+    /// the max number of segments grouped into a slice.
+    pub max_segments_per_slice: i32,
+    /// indexes with at most this many segments use one slice per segment.
+    pub min_parallel_slices: i32,
+}
+
+impl Default for SlicePolicy {
+    fn default() -> Self {
+        SlicePolicy {
+            max_docs_per_slice: MAX_DOCS_PER_SLICE,
+            max_segments_per_slice: MAX_SEGMENTS_PER_SLICE,
+            min_parallel_slices: MIN_PARALLEL_SLICES,
+        }
+    }
+}
+
+/// The executor that runs the per slice jobs of a concurrent search.
+///
+/// Implemented for the crate's `ThreadPool`, other pools (eg. rayon) can be
+/// plugged in by implementing this trait.
+pub trait SearchExecutor: Send + Sync {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+impl SearchExecutor for ThreadPool<DefaultContext> {
+    fn execute(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        ThreadPool::execute(self, move |_| job())
+    }
+}
+
 const DEFAULT_DISMATCH_NEXT_LIMIT: usize = 500_000;
 
 pub struct TermContext<S: TermState> {
@@ -215,8 +259,11 @@ pub trait SearchPlanBuilder<C: Codec> {
 
     /// Creates a `Weight` for the given query, potentially adding caching
     /// if possible and configured.
-    fn create_weight(&self, query: &dyn Query<C>, needs_scores: bool)
-        -> Result<Box<dyn Weight<C>>>;
+    fn create_weight(
+        &self,
+        query: &dyn Query<C>,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>>;
 
     /// Creates a normalized weight for a top-level `Query`.
     /// The query is rewritten by this method and `Query#createWeight` called,
@@ -225,7 +272,7 @@ pub trait SearchPlanBuilder<C: Codec> {
     fn create_normalized_weight(
         &self,
         query: &dyn Query<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>>;
 
     fn similarity(&self, field: &str, needs_scores: bool) -> Box<dyn Similarity<C>>;
@@ -247,9 +294,29 @@ pub trait IndexSearcher<C: Codec>: SearchPlanBuilder<C> {
     where
         S: SearchCollector;
 
+    /// Search with a `CollectorManager`: each slice of segments is collected by
+    /// its own collector, in parallel if the searcher has a thread pool, and the
+    /// collectors are then merged with `CollectorManager::reduce`.
+    fn search_with_manager<M>(&self, query: &dyn Query<C>, manager: &M) -> Result<M::Output>
+    where
+        M: CollectorManager;
+
     fn count(&self, query: &dyn Query<C>) -> Result<i32>;
 
+    /// Returns an `Explanation` that describes how `doc` scored against
+    /// `query`, `doc` is the top level doc id, eg. the one in the
+    /// `ScoreDocHit` returned by a search.
+    ///
+    /// This is intended to be used in developing `Similarity` implementations,
+    /// and, for good performance, should not be displayed with every hit.
+    /// Computing an explanation is as expensive as executing the query over the
+    /// entire index.
     fn explain(&self, query: &dyn Query<C>, doc: DocId) -> Result<Explanation>;
+
+    /// Returns the `Matches` of `query` for the top level `doc`, describing
+    /// which fields and positions matched, or `None` if it doesn't match or
+    /// is deleted.
+    fn matches(&self, query: &dyn Query<C>, doc: DocId) -> Result<Option<Matches>>;
 }
 
 ///  Implements search over a single IndexReader.
@@ -274,7 +341,8 @@ pub struct DefaultIndexSearcher<
     SP: SimilarityProducer<C>,
 > {
     reader: IR,
-    thread_pool: Option<Arc<ThreadPool<DefaultContext>>>,
+    thread_pool: Option<Arc<dyn SearchExecutor>>,
+    slice_policy: SlicePolicy,
     // used for concurrent search - each slice holds a set of LeafReader's ord that
     // executed within one thread.
     leaf_ord_slices: Vec<Vec<usize>>,
@@ -284,6 +352,9 @@ pub struct DefaultIndexSearcher<
 
     sim_producer: SP,
     collection_statistics: HashMap<String, CollectionStatistics>,
+    // statistics of all the shards of a distributed index, overriding the
+    // local ones.
+    distributed_statistics: Option<Arc<DistributedStatistics>>,
 
     // dismatch next limit to break.
     next_limit: usize,
@@ -357,10 +428,12 @@ where
         DefaultIndexSearcher {
             reader,
             sim_producer,
-            query_cache: Arc::new(LRUQueryCache::new(1000)),
+            query_cache: Arc::new(NoCacheQueryCache::new()),
             cache_policy: Arc::new(UsageTrackingQueryCachingPolicy::default()),
             collection_statistics,
+            distributed_statistics: None,
             thread_pool: None,
+            slice_policy: SlicePolicy::default(),
             leaf_ord_slices: vec![],
             next_limit: next_limit.unwrap_or(DEFAULT_DISMATCH_NEXT_LIMIT),
         }
@@ -377,15 +450,68 @@ where
     }
 
     pub fn set_thread_pool(&mut self, pool: Arc<ThreadPool<DefaultContext>>) {
-        self.thread_pool = Some(pool);
+        self.set_executor(pool);
+    }
+
+    /// Search the slices of segments concurrently with the given executor,
+    /// see `SlicePolicy` for how the slices are built.
+    pub fn set_executor(&mut self, executor: Arc<dyn SearchExecutor>) {
+        self.thread_pool = Some(executor);
+        self.update_slices();
+    }
+
+    pub fn set_slice_policy(&mut self, slice_policy: SlicePolicy) -> Result<()> {
+        if slice_policy.max_docs_per_slice <= 0
+            || slice_policy.max_segments_per_slice <= 0
+            || slice_policy.min_parallel_slices <= 0
+        {
+            return Err(Error::IllegalArgument(format!(
+                "max_docs_per_slice, max_segments_per_slice and min_parallel_slices must be > \
+                 0, got {:?}",
+                slice_policy
+            )));
+        }
+        self.slice_policy = slice_policy;
+        if self.thread_pool.is_some() {
+            self.update_slices();
+        }
+        Ok(())
+    }
+
+    pub fn slice_policy(&self) -> &SlicePolicy {
+        &self.slice_policy
+    }
+
+    /// the ords of the leaf readers in each slice, empty if the search is
+    /// not concurrent.
+    pub fn leaf_slices(&self) -> &[Vec<usize>] {
+        &self.leaf_ord_slices
+    }
+
+    fn update_slices(&mut self) {
         self.leaf_ord_slices = Self::slice(
             self.reader.leaves(),
-            MAX_DOCS_PER_SLICE,
-            MAX_SEGMENTS_PER_SLICE,
-            MIN_PARALLEL_SLICES,
+            self.slice_policy.max_docs_per_slice,
+            self.slice_policy.max_segments_per_slice,
+            self.slice_policy.min_parallel_slices,
         );
     }
 
+    /// Enable caching of the matching docs of non-scoring clauses with a
+    /// `LRUQueryCache` that holds at most `max_size` queries and
+    /// `max_ram_bytes_used` bytes, only clauses that are frequently reused are
+    /// cached as decided by `UsageTrackingQueryCachingPolicy`.
+    ///
+    /// The query cache is disabled by default, use `set_query_cache` to share
+    /// a cache across searchers.
+    pub fn enable_query_cache(&mut self, max_size: usize, max_ram_bytes_used: usize) {
+        self.query_cache = Arc::new(LRUQueryCache::with_max_ram_bytes(
+            max_size,
+            max_ram_bytes_used,
+        ));
+        self.cache_policy = Arc::new(UsageTrackingQueryCachingPolicy::default());
+    }
+
     pub fn set_query_cache(&mut self, cache: Arc<dyn QueryCache<C>>) {
         self.query_cache = cache;
     }
@@ -394,6 +520,40 @@ where
         self.cache_policy = cache_policy;
     }
 
+    /// Score with the statistics of all the shards of a distributed index
+    /// instead of the local ones, so that the scores of the hits of different
+    /// shards are comparable. Terms and fields missing from `stats` still use
+    /// the local statistics.
+    pub fn set_distributed_statistics(&mut self, stats: Option<Arc<DistributedStatistics>>) {
+        self.distributed_statistics = stats;
+    }
+
+    /// Search the leaves sequentially like `search`, recording the time spent
+    /// in each node of the query tree, and returns the profile of `query`
+    /// along with the hits gathered by `collector`.
+    ///
+    /// Profiling adds overhead to every doc iterated and bypasses the query
+    /// cache, it should only be used to diagnose slow queries.
+    pub fn profile<S: SearchCollector>(
+        &self,
+        query: &dyn Query<C>,
+        collector: &mut S,
+    ) -> Result<ProfileResult> {
+        let profiler = QueryProfiler::default();
+        let weight = ProfilingPlanBuilder::new(self, &profiler)
+            .create_normalized_weight(query, collector.score_mode())?;
+
+        for reader in self.reader.leaves() {
+            match Self::search_leaf(weight.as_ref(), &reader, collector, self.next_limit) {
+                Ok(()) => {}
+                Err(Error::CollectorError(collector::Error::CollectionTimeout)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(profiler.results().remove(0))
+    }
+
     fn do_search<S: Scorer + ?Sized, T: Collector, B: Bits + ?Sized>(
         scorer: &mut S,
         collector: &mut T,
@@ -418,6 +578,22 @@ where
         }
     }
 
+    /// collect the matches of a single segment, collection termination errors
+    /// are swallowed but timeout is reported to the caller.
+    fn search_leaf<T: SearchCollector>(
+        weight: &dyn Weight<C>,
+        leaf_ctx: &LeafReaderContext<'_, C>,
+        collector: &mut T,
+        next_limit: usize,
+    ) -> Result<()> {
+        if let Some(mut scorer) = weight.create_scorer(leaf_ctx)? {
+            collector.set_next_reader(leaf_ctx)?;
+            let live_docs = leaf_ctx.reader.live_docs();
+            Self::do_search(scorer.as_mut(), collector, live_docs.as_ref(), next_limit)?;
+        }
+        Ok(())
+    }
+
     // segregate leaf readers amongst multiple slices
     fn slice(
         mut leaves: Vec<LeafReaderContext<'_, C>>,
@@ -491,7 +667,7 @@ where
     where
         S: SearchCollector,
     {
-        let weight = self.create_weight(query, collector.needs_scores())?;
+        let weight = self.create_weight(query, collector.score_mode())?;
 
         for reader in self.reader.leaves() {
             if let Some(mut scorer) = weight.create_scorer(&reader)? {
@@ -531,8 +707,10 @@ where
         if collector.support_parallel() && self.leaf_ord_slices.len() > 1 {
             debug_assert!(self.thread_pool.is_some());
             let thread_pool = self.thread_pool.as_ref().unwrap();
-            let weight = self.create_weight(query, collector.needs_scores())?;
+            let weight = self.create_weight(query, collector.score_mode())?;
             let leaf_readers = self.reader.leaves();
+            // the first error of any slice, reported once all slices are finished
+            let slice_error: Arc<Mutex<Option<Error>>> = Arc::new(Mutex::new(None));
 
             collector.init_parallel();
 
@@ -566,8 +744,9 @@ where
 
                 if !scorer_and_collectors.is_empty() {
                     let next_limit = self.next_limit;
+                    let slice_error = Arc::clone(&slice_error);
 
-                    thread_pool.execute(move |_| {
+                    thread_pool.execute(Box::new(move || {
                         for (w, leaf_ctx_ptr, mut collector) in scorer_and_collectors {
                             let weight = unsafe { &*(w as *const Box<dyn Weight<C>>) };
                             let reader = unsafe { &(*leaf_ctx_ptr.reader) };
@@ -598,11 +777,11 @@ where
                                         true
                                     }
                                     Err(e) => {
-                                        log::error!(
-                                            "do search parallel failed by '{:?}', may return \
-                                             partial result",
-                                            e
-                                        );
+                                        log::error!("do search parallel failed by '{:?}'", e);
+                                        let mut slice_error = slice_error.lock().unwrap();
+                                        if slice_error.is_none() {
+                                            *slice_error = Some(e);
+                                        }
                                         true
                                     }
                                 };
@@ -618,14 +797,112 @@ where
                                 }
                             }
                         }
-                    });
+                    }));
                 }
             }
-            return collector.finish_parallel();
+            collector.finish_parallel()?;
+            if let Some(e) = slice_error.lock()?.take() {
+                return Err(e);
+            }
+            return Ok(());
         }
         self.search(query, collector)
     }
 
+    fn search_with_manager<M>(&self, query: &dyn Query<C>, manager: &M) -> Result<M::Output>
+    where
+        M: CollectorManager,
+    {
+        if self.leaf_ord_slices.len() <= 1 {
+            let mut collector = manager.new_collector()?;
+            self.search(query, &mut collector)?;
+            return manager.reduce(vec![collector]);
+        }
+
+        debug_assert!(self.thread_pool.is_some());
+        let thread_pool = self.thread_pool.as_ref().unwrap();
+        let mut collectors = Vec::with_capacity(self.leaf_ord_slices.len());
+        for _ in 0..self.leaf_ord_slices.len() {
+            collectors.push(manager.new_collector()?);
+        }
+        let score_mode = collectors
+            .iter()
+            .map(|c| c.score_mode())
+            .fold(ScoreMode::TopDocs, ScoreMode::merge);
+        let weight = self.create_weight(query, score_mode)?;
+        let leaf_readers = self.reader.leaves();
+        let (sender, receiver) = unbounded();
+
+        for (slice_idx, (leaf_slice, mut collector)) in
+            self.leaf_ord_slices.iter().zip(collectors).enumerate()
+        {
+            let mut leaf_ctx_ptrs = Vec::with_capacity(leaf_slice.len());
+            for ord in leaf_slice {
+                let leaf_ctx = &leaf_readers[*ord];
+                let reader = unsafe { ::std::mem::transmute(leaf_ctx.reader) };
+                let parent = unsafe { ::std::mem::transmute(leaf_ctx.parent) };
+                leaf_ctx_ptrs.push(LeafReaderContextPtr::new(
+                    leaf_ctx.ord,
+                    leaf_ctx.doc_base,
+                    reader,
+                    parent,
+                ));
+            }
+            let w = &weight as *const Box<dyn Weight<C>> as u64;
+            let next_limit = self.next_limit;
+            let sender: Sender<(usize, Result<M::Collector>)> = sender.clone();
+
+            thread_pool.execute(Box::new(move || {
+                let weight = unsafe { &*(w as *const Box<dyn Weight<C>>) };
+                let mut result = Ok(());
+                for leaf_ctx_ptr in leaf_ctx_ptrs {
+                    let reader = unsafe { &(*leaf_ctx_ptr.reader) };
+                    let parent = unsafe { &(*leaf_ctx_ptr.parent) };
+                    let leaf_ctx = LeafReaderContext::new(
+                        parent,
+                        reader,
+                        leaf_ctx_ptr.ord,
+                        leaf_ctx_ptr.doc_base,
+                    );
+                    result =
+                        Self::search_leaf(weight.as_ref(), &leaf_ctx, &mut collector, next_limit);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                let res = match result {
+                    Ok(()) | Err(Error::CollectorError(collector::Error::CollectionTimeout)) => {
+                        Ok(collector)
+                    }
+                    Err(e) => Err(e),
+                };
+                // the receiver is alive until all the slices are finished
+                let _ = sender.send((slice_idx, res));
+            }));
+        }
+        drop(sender);
+
+        // wait for all the slices to finish even on failure, the weight
+        // is shared by all the running slices.
+        let mut slice_results = Vec::with_capacity(self.leaf_ord_slices.len());
+        while let Ok(res) = receiver.recv() {
+            slice_results.push(res);
+        }
+        if slice_results.len() != self.leaf_ord_slices.len() {
+            return Err(Error::IllegalState(format!(
+                "only {} of {} search slices finished",
+                slice_results.len(),
+                self.leaf_ord_slices.len()
+            )));
+        }
+        slice_results.sort_by_key(|(idx, _)| *idx);
+        let mut collectors = Vec::with_capacity(slice_results.len());
+        for (_, res) in slice_results {
+            collectors.push(res?);
+        }
+        manager.reduce(collectors)
+    }
+
     fn count(&self, query: &dyn Query<C>) -> Result<i32> {
         let mut query = query;
         while let Some(constant_query) = query.as_any().downcast_ref::<ConstantScoreQuery<C>>() {
@@ -651,20 +928,47 @@ where
     }
 
     fn explain(&self, query: &dyn Query<C>, doc: DocId) -> Result<Explanation> {
+        if doc < 0 || doc >= self.reader.max_doc() {
+            return Err(Error::IllegalArgument(format!(
+                "doc {} is out of bounds [0, {})",
+                doc,
+                self.reader.max_doc()
+            )));
+        }
         let reader = self.reader.leaf_reader_for_doc(doc);
         let live_docs = reader.reader.live_docs();
         if !live_docs.get((doc - reader.doc_base()) as usize) {
             Ok(Explanation::new(
                 false,
                 0.0f32,
-                format!("Document {} if deleted", doc),
+                format!("Document {} is deleted", doc),
                 vec![],
             ))
         } else {
-            self.create_normalized_weight(query, true)?
+            self.create_normalized_weight(query, ScoreMode::Complete)?
                 .explain(&reader, doc - reader.doc_base())
         }
     }
+
+    fn matches(&self, query: &dyn Query<C>, doc: DocId) -> Result<Option<Matches>> {
+        if doc < 0 || doc >= self.reader.max_doc() {
+            return Err(Error::IllegalArgument(format!(
+                "doc {} is out of bounds [0, {})",
+                doc,
+                self.reader.max_doc()
+            )));
+        }
+        let reader = self.reader.leaf_reader_for_doc(doc);
+        if !reader
+            .reader
+            .live_docs()
+            .get((doc - reader.doc_base()) as usize)
+        {
+            return Ok(None);
+        }
+        self.create_normalized_weight(query, ScoreMode::CompleteNoScores)?
+            .matches(&reader, doc - reader.doc_base())
+    }
 }
 
 impl<C, R, IR, SP> SearchPlanBuilder<C> for DefaultIndexSearcher<C, R, IR, SP>
@@ -687,11 +991,11 @@ where
     fn create_weight(
         &self,
         query: &dyn Query<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        let mut weight = query.create_weight(self, needs_scores)?;
-        // currently not to use query_cache.
-        if false && !needs_scores {
+        let mut weight = query.create_weight(self, score_mode)?;
+        // cached doc id sets don't carry scores
+        if !score_mode.needs_scores() {
             weight = self
                 .query_cache
                 .do_cache(weight, Arc::clone(&self.cache_policy));
@@ -706,15 +1010,18 @@ where
     fn create_normalized_weight(
         &self,
         query: &dyn Query<C>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
     ) -> Result<Box<dyn Weight<C>>> {
-        let weight = self.create_weight(query, needs_scores)?;
-        //        let v = weight.value_for_normalization();
-        //        let mut norm: f32 = self.similarity("", needs_scores).query_norm(v, None);
-        //        if norm.is_finite() || norm.is_nan() {
-        //            norm = 1.0f32;
-        //        }
-        //        weight.normalize(norm, 1.0f32);
+        let mut weight = self.create_weight(query, score_mode)?;
+        let v = weight.value_for_normalization();
+        let norm: f32 = self
+            .similarity("", score_mode.needs_scores())
+            .query_norm(v, None);
+        // only similarities that take part in query normalization (e.g. `ClassicSimilarity`)
+        // return a norm other than 1, other weights keep the boost they were created with
+        if norm.is_finite() && (norm - 1.0f32).abs() > f32::EPSILON {
+            weight.normalize(norm, 1.0f32);
+        }
         Ok(weight)
     }
 
@@ -727,6 +1034,14 @@ where
     }
 
     fn term_statistics(&self, term: &Term) -> Result<TermStatistics> {
+        if let Some(stats) = self
+            .distributed_statistics
+            .as_ref()
+            .and_then(|s| s.term_statistics(term))
+        {
+            return Ok(stats.clone());
+        }
+
         let doc_base = if let Some(field_stat) = self.collection_statistics.get(&term.field) {
             field_stat.doc_base
         } else {
@@ -764,7 +1079,10 @@ where
     }
 
     fn collections_statistics(&self, field: &str) -> Option<&CollectionStatistics> {
-        self.collection_statistics.get(field)
+        self.distributed_statistics
+            .as_ref()
+            .and_then(|s| s.collection_statistics(field))
+            .or_else(|| self.collection_statistics.get(field))
     }
 }
 
@@ -870,11 +1188,15 @@ impl ParallelLeafCollector for TotalHitsCountLeafCollector {
 mod tests {
     use super::*;
     use crate::core::codec::tests::TestCodec;
+    use crate::core::codec::CodecEnum;
+    use crate::core::doc::StringField;
     use crate::core::index::tests::*;
     use crate::core::search::collector::*;
     use crate::core::search::query::TermQuery;
+    use crate::core::search::sort_field::TopDocs;
     use crate::core::search::tests::*;
     use crate::core::util::DocId;
+    use crate::error::Error;
 
     struct MockQuery {
         docs: Vec<DocId>,
@@ -890,7 +1212,7 @@ mod tests {
         fn create_weight(
             &self,
             _searcher: &dyn SearchPlanBuilder<C>,
-            _needs_scores: bool,
+            _score_mode: ScoreMode,
         ) -> Result<Box<dyn Weight<C>>> {
             Ok(Box::new(create_mock_weight(self.docs.clone())))
         }
@@ -947,4 +1269,205 @@ mod tests {
         assert!((score_docs[1].score() - 5f32) < ::std::f32::EPSILON);
         assert!((score_docs[2].score() - 5f32) < ::std::f32::EPSILON);
     }
+
+    #[test]
+    fn test_search_with_manager() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![
+                MockLeafReader::new(0),
+                MockLeafReader::new(10),
+            ]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None);
+        let query = MockQuery::new(vec![1, 5, 3, 4, 2]);
+
+        let top_docs = searcher
+            .search_with_manager(&query, &TopDocsCollectorManager::new(3))
+            .unwrap();
+        assert_eq!(top_docs.total_hits(), 10);
+
+        let score_docs = top_docs.score_docs();
+        assert_eq!(score_docs.len(), 3);
+        assert!((score_docs[0].score() - 5f32) < ::std::f32::EPSILON);
+    }
+
+    // 4 segments of 10 docs, tagged "even" or "odd"
+    fn multi_segment_reader() -> Arc<impl IndexReader<Codec = CodecEnum>> {
+        let writer = in_memory_writer();
+        for i in 0..40 {
+            let tag = if i % 2 == 0 { "even" } else { "odd" };
+            writer
+                .add_document(vec![StringField::new("tag", tag, false)])
+                .unwrap();
+            if i % 10 == 9 {
+                writer.commit().unwrap();
+            }
+        }
+        Arc::new(writer.get_reader(true, false).unwrap())
+    }
+
+    fn one_segment_per_slice() -> SlicePolicy {
+        SlicePolicy {
+            max_docs_per_slice: 10,
+            max_segments_per_slice: 1,
+            min_parallel_slices: 1,
+        }
+    }
+
+    #[test]
+    fn test_set_slice_policy() {
+        let mut searcher = DefaultIndexSearcher::new(multi_segment_reader(), None);
+        for policy in &[
+            SlicePolicy {
+                max_docs_per_slice: 0,
+                ..SlicePolicy::default()
+            },
+            SlicePolicy {
+                max_segments_per_slice: -1,
+                ..SlicePolicy::default()
+            },
+            SlicePolicy {
+                min_parallel_slices: 0,
+                ..SlicePolicy::default()
+            },
+        ] {
+            assert!(searcher.set_slice_policy(*policy).is_err());
+        }
+        assert_eq!(
+            searcher.slice_policy().max_docs_per_slice,
+            MAX_DOCS_PER_SLICE
+        );
+
+        searcher.set_slice_policy(one_segment_per_slice()).unwrap();
+        searcher.with_thread_pool(2);
+        assert_eq!(searcher.leaf_slices().len(), 4);
+    }
+
+    #[test]
+    fn test_sliced_search() {
+        let reader = multi_segment_reader();
+        let query = TermQuery::new(Term::new("tag".into(), b"odd".to_vec()), 1.0, None);
+
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&reader), None);
+        let mut collector = TopDocsCollector::new(30);
+        searcher.search(&query, &mut collector).unwrap();
+        let sequential = collector.top_docs();
+
+        let mut searcher = DefaultIndexSearcher::new(reader, None);
+        searcher.set_slice_policy(one_segment_per_slice()).unwrap();
+        searcher.with_thread_pool(4);
+        let mut collector = TopDocsCollector::new(30);
+        searcher.search_parallel(&query, &mut collector).unwrap();
+        let sliced = collector.top_docs();
+
+        assert_eq!(sliced.total_hits(), 20);
+        assert_eq!(sliced.total_hits(), sequential.total_hits());
+        let hits = |top_docs: &TopDocs| -> Vec<(DocId, f32)> {
+            let mut hits: Vec<_> = top_docs
+                .score_docs()
+                .iter()
+                .map(|d| (d.doc_id(), d.score()))
+                .collect();
+            hits.sort_by_key(|h| h.0);
+            hits
+        };
+        assert_eq!(hits(&sliced), hits(&sequential));
+    }
+
+    // fails collecting the docs of the leaf with the given ord
+    struct FailingCollector {
+        fail_ord: usize,
+        channel: Option<(Sender<()>, Receiver<()>)>,
+    }
+
+    struct FailingLeafCollector {
+        fail: bool,
+        _sender: Sender<()>,
+    }
+
+    impl SearchCollector for FailingCollector {
+        type LC = FailingLeafCollector;
+
+        fn set_next_reader<C: Codec>(&mut self, _reader: &LeafReaderContext<'_, C>) -> Result<()> {
+            Ok(())
+        }
+
+        fn support_parallel(&self) -> bool {
+            true
+        }
+
+        fn init_parallel(&mut self) {
+            self.channel = Some(unbounded());
+        }
+
+        fn leaf_collector<C: Codec>(
+            &self,
+            reader: &LeafReaderContext<'_, C>,
+        ) -> Result<FailingLeafCollector> {
+            Ok(FailingLeafCollector {
+                fail: reader.ord == self.fail_ord,
+                _sender: self.channel.as_ref().unwrap().0.clone(),
+            })
+        }
+
+        fn finish_parallel(&mut self) -> Result<()> {
+            // waits for the leaf collectors of all the slices to be dropped
+            if let Some((sender, receiver)) = self.channel.take() {
+                drop(sender);
+                while receiver.recv().is_ok() {}
+            }
+            Ok(())
+        }
+    }
+
+    impl Collector for FailingCollector {
+        fn needs_scores(&self) -> bool {
+            false
+        }
+
+        fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Collector for FailingLeafCollector {
+        fn needs_scores(&self) -> bool {
+            false
+        }
+
+        fn collect<S: Scorer + ?Sized>(&mut self, _doc: DocId, _scorer: &mut S) -> Result<()> {
+            if self.fail {
+                return Err(Error::IllegalState("slice failed".into()));
+            }
+            Ok(())
+        }
+    }
+
+    impl ParallelLeafCollector for FailingLeafCollector {
+        fn finish_leaf(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sliced_search_error() {
+        let mut searcher = DefaultIndexSearcher::new(multi_segment_reader(), None);
+        searcher.set_slice_policy(one_segment_per_slice()).unwrap();
+        searcher.with_thread_pool(4);
+        let query = TermQuery::new(Term::new("tag".into(), b"even".to_vec()), 1.0, None);
+
+        let mut collector = FailingCollector {
+            fail_ord: 2,
+            channel: None,
+        };
+        match searcher.search_parallel(&query, &mut collector) {
+            Err(Error::IllegalState(msg)) => assert_eq!(msg, "slice failed"),
+            _ => panic!("the error of the failed slice should be returned"),
+        }
+
+        let mut collector = FailingCollector {
+            fail_ord: 4,
+            channel: None,
+        };
+        searcher.search_parallel(&query, &mut collector).unwrap();
+    }
 }
@@ -16,6 +16,7 @@
 //! In Proceedings of the Third *T*ext *RE*trieval *C*onference (TREC 1994).
 //! Gaithersburg, USA, November 1994.
 
+use crate::error::Error::IllegalArgument;
 use crate::Result;
 use std::fmt;
 use std::sync::Arc;
@@ -25,7 +26,7 @@ use crate::core::codec::field_infos::FieldInvertState;
 use crate::core::codec::Codec;
 use crate::core::index::reader::SearchLeafReader;
 use crate::core::search::explanation::Explanation;
-use crate::core::search::similarity::{SimScorer, SimWeight, Similarity};
+use crate::core::search::similarity::{SimScorer, SimWeight, Similarity, SimilarityProducer};
 use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
 use crate::core::util::SmallFloat;
 use crate::core::util::{DocId, KeyedContext};
@@ -53,13 +54,43 @@ pub struct BM25Similarity {
 
 impl Default for BM25Similarity {
     fn default() -> Self {
-        BM25Similarity::new(DEFAULT_BM25_K1, DEFAULT_BM25_B)
+        BM25Similarity {
+            k1: DEFAULT_BM25_K1,
+            b: DEFAULT_BM25_B,
+        }
     }
 }
 
 impl BM25Similarity {
-    pub fn new(k1: f32, b: f32) -> BM25Similarity {
-        BM25Similarity { k1, b }
+    /// BM25 with the supplied parameter values.
+    ///
+    /// `k1` controls non-linear term frequency normalization (saturation) and
+    /// must be finite and non-negative, `b` controls to what degree document
+    /// length normalizes tf values and must be within `[0, 1]`.
+    pub fn new(k1: f32, b: f32) -> Result<BM25Similarity> {
+        if !k1.is_finite() || k1 < 0.0 {
+            return Err(IllegalArgument(format!(
+                "illegal k1 value: {}, must be a non-negative finite value",
+                k1
+            )));
+        }
+        if b.is_nan() || !(0.0..=1.0).contains(&b) {
+            return Err(IllegalArgument(format!(
+                "illegal b value: {}, must be between 0 and 1",
+                b
+            )));
+        }
+        Ok(BM25Similarity { k1, b })
+    }
+
+    /// Returns the `k1` parameter
+    pub fn k1(&self) -> f32 {
+        self.k1
+    }
+
+    /// Returns the `b` parameter
+    pub fn b(&self) -> f32 {
+        self.b
     }
 
     fn sloppy_freq(distance: i32) -> f32 {
@@ -183,20 +214,62 @@ impl fmt::Display for BM25Similarity {
     }
 }
 
+/// `SimilarityProducer` using `BM25Similarity` with the same parameters for all the fields.
+#[derive(Clone, Copy)]
+pub struct BM25SimilarityProducer {
+    k1: f32,
+    b: f32,
+}
+
+impl BM25SimilarityProducer {
+    pub fn new(k1: f32, b: f32) -> Result<BM25SimilarityProducer> {
+        // validate the parameters eagerly
+        BM25Similarity::new(k1, b)?;
+        Ok(BM25SimilarityProducer { k1, b })
+    }
+}
+
+impl Default for BM25SimilarityProducer {
+    fn default() -> Self {
+        BM25SimilarityProducer {
+            k1: DEFAULT_BM25_K1,
+            b: DEFAULT_BM25_B,
+        }
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for BM25SimilarityProducer {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        // the parameters were validated by `new`
+        Box::new(BM25Similarity {
+            k1: self.k1,
+            b: self.b,
+        })
+    }
+}
+
 struct BM25SimScorer {
     k1: f32,
     weight: f32,
     cache: Arc<[f32; 256]>,
     norms: Option<Box<dyn NumericDocValues>>,
+    // the smallest length normalization, used to compute score upper bounds
+    min_norm: f32,
 }
 
 impl BM25SimScorer {
     fn new(weight: &BM25SimWeight, norms: Option<Box<dyn NumericDocValues>>) -> BM25SimScorer {
+        let min_norm = if norms.is_some() {
+            weight.cache.iter().cloned().fold(f32::INFINITY, f32::min)
+        } else {
+            weight.k1
+        };
         BM25SimScorer {
             k1: weight.k1,
             weight: weight.weight,
             cache: Arc::clone(&weight.cache),
             norms,
+            min_norm,
         }
     }
 
@@ -220,6 +293,24 @@ impl SimScorer for BM25SimScorer {
     fn compute_slop_factor(&self, distance: i32) -> f32 {
         BM25Similarity::sloppy_freq(distance)
     }
+
+    fn max_score(&self, max_freq: f32) -> f32 {
+        // the score increases with freq and decreases with norm
+        if max_freq.is_infinite() {
+            self.weight * (self.k1 + 1.0)
+        } else {
+            self.weight * (self.k1 + 1.0) * max_freq / (max_freq + self.min_norm)
+        }
+    }
+
+    fn max_score_for_impact(&self, freq: f32, norm: i64) -> f32 {
+        let norm = if self.norms.is_some() {
+            self.cache[(norm & 0xFF) as usize]
+        } else {
+            self.k1
+        };
+        self.weight * (self.k1 + 1.0) * freq / (freq + norm)
+    }
 }
 
 struct BM25SimWeight {
@@ -439,11 +530,25 @@ mod tests {
         assert!((BM25Similarity::avg_field_length(&collection_stats) - 3f32) < ::std::f32::EPSILON);
     }
 
+    #[test]
+    fn test_illegal_b() {
+        assert!(BM25Similarity::new(1.2, 2.0).is_err());
+        assert!(BM25Similarity::new(1.2, f32::NAN).is_err());
+        assert!(BM25SimilarityProducer::new(1.2, -0.5).is_err());
+    }
+
+    #[test]
+    fn test_illegal_k1() {
+        assert!(BM25Similarity::new(-1.0, 0.75).is_err());
+        assert!(BM25Similarity::new(f32::INFINITY, 0.75).is_err());
+        assert!(BM25SimilarityProducer::new(-1.0, 0.75).is_err());
+    }
+
     #[test]
     fn test_bm25_similarity() {
         let collection_stats = CollectionStatistics::new(String::from("world"), 0, 32, 32, 120, -1);
         let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
-        let bm25_sim = BM25Similarity::new(1.2, 0.75);
+        let bm25_sim = BM25Similarity::new(1.2, 0.75).unwrap();
         let sim_weight = bm25_sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
 
         assert!((sim_weight.get_value_for_normalization() - 9.554_543_5f32) < ::std::f32::EPSILON);
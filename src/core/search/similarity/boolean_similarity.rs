@@ -0,0 +1,134 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Result;
+use std::fmt;
+
+use crate::core::codec::Codec;
+use crate::core::index::reader::SearchLeafReader;
+use crate::core::search::explanation::Explanation;
+use crate::core::search::similarity::{SimScorer, SimWeight, Similarity, SimilarityProducer};
+use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+use crate::core::util::{DocId, KeyedContext};
+
+/// Simple similarity that gives terms a score that is equal to their query
+/// boost. This similarity is typically used with disabled norms since neither
+/// document statistics nor index statistics are used for scoring. That said,
+/// if norms are enabled, they will be computed the same way as
+/// `BM25Similarity` so that this similarity can be switched at search time.
+#[derive(Clone, Copy, Default)]
+pub struct BooleanSimilarity;
+
+impl<C: Codec> Similarity<C> for BooleanSimilarity {
+    fn compute_weight(
+        &self,
+        _collection_stats: &CollectionStatistics,
+        _term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        Box::new(BooleanSimWeight { boost })
+    }
+}
+
+impl fmt::Display for BooleanSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BooleanSimilarity")
+    }
+}
+
+/// `SimilarityProducer` using `BooleanSimilarity` for all the fields.
+pub struct BooleanSimilarityProducer;
+
+impl<C: Codec> SimilarityProducer<C> for BooleanSimilarityProducer {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(BooleanSimilarity)
+    }
+}
+
+struct BooleanSimWeight {
+    boost: f32,
+}
+
+impl<C: Codec> SimWeight<C> for BooleanSimWeight {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.boost * self.boost
+    }
+
+    fn normalize(&mut self, _query_norm: f32, boost: f32) {
+        self.boost = boost;
+    }
+
+    fn sim_scorer(&self, _reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        // norms are not needed
+        Ok(Box::new(BooleanSimScorer { boost: self.boost }))
+    }
+
+    fn explain(
+        &self,
+        _reader: &SearchLeafReader<C>,
+        _doc: DocId,
+        _freq: Explanation,
+    ) -> Result<Explanation> {
+        Ok(Explanation::new(
+            true,
+            self.boost,
+            "score(BooleanSimilarity), computed from:".to_string(),
+            vec![Explanation::new(
+                true,
+                self.boost,
+                "boost".to_string(),
+                vec![],
+            )],
+        ))
+    }
+}
+
+struct BooleanSimScorer {
+    boost: f32,
+}
+
+impl SimScorer for BooleanSimScorer {
+    fn score(&mut self, _doc: DocId, _freq: f32) -> Result<f32> {
+        Ok(self.boost)
+    }
+
+    fn compute_slop_factor(&self, _distance: i32) -> f32 {
+        1.0
+    }
+
+    fn max_score(&self, _max_freq: f32) -> f32 {
+        self.boost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::tests::TestCodec;
+    use crate::core::index::tests::MockLeafReader;
+
+    #[test]
+    fn test_boolean_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 0, 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let sim_weight: Box<dyn SimWeight<TestCodec>> =
+            BooleanSimilarity.compute_weight(&collection_stats, &term_stats, None, 3.0f32);
+
+        let leaf_reader = MockLeafReader::new(1);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        assert!((sim_scorer.score(1, 100.0).unwrap() - 3.0).abs() < ::std::f32::EPSILON);
+        assert!((sim_scorer.score(2, 1.0).unwrap() - 3.0).abs() < ::std::f32::EPSILON);
+        assert!((sim_scorer.max_score(f32::INFINITY) - 3.0).abs() < ::std::f32::EPSILON);
+    }
+}
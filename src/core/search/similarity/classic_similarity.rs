@@ -0,0 +1,348 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Classic TF-IDF similarity, the vector space model used as the default
+//! similarity of Lucene before BM25.
+
+use crate::Result;
+use std::fmt;
+
+use crate::core::codec::doc_values::NumericDocValues;
+use crate::core::codec::Codec;
+use crate::core::index::reader::SearchLeafReader;
+use crate::core::search::explanation::Explanation;
+use crate::core::search::similarity::{SimScorer, SimWeight, Similarity, SimilarityProducer};
+use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+use crate::core::util::SmallFloat;
+use crate::core::util::{DocId, KeyedContext};
+
+lazy_static::lazy_static! {
+    static ref NORM_TABLE: [f32; 256] = {
+        let mut norm_table: [f32; 256] = [0f32; 256];
+        for (i, norm) in norm_table.iter_mut().enumerate() {
+            *norm = SmallFloat::byte315_to_float(i as u8);
+        }
+        norm_table
+    };
+}
+
+/// Expert: Default scoring implementation of Lucene before 6.0, which encodes
+/// norm values as a single byte before being stored.
+///
+/// The score of a document is `queryNorm * sum(tf * idf^2 * boost * fieldNorm)`
+/// over the query terms, where:
+/// - `tf` is `sqrt(freq)`
+/// - `idf` is `1 + log((docCount + 1) / (docFreq + 1))`
+/// - `fieldNorm` is `1 / sqrt(numTerms)`, using the same norm encoding as `BM25Similarity`, so that
+///   indices don't need to be rebuilt to switch
+/// - `queryNorm` is `1 / sqrt(sumOfSquaredWeights)`
+#[derive(Default)]
+pub struct ClassicSimilarity;
+
+impl ClassicSimilarity {
+    /// Implemented as `sqrt(freq)`.
+    pub fn tf(freq: f32) -> f32 {
+        freq.sqrt()
+    }
+
+    /// Implemented as `1 / (distance + 1)`.
+    pub fn sloppy_freq(distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+
+    /// Implemented as `log((docCount + 1) / (docFreq + 1)) + 1`.
+    pub fn idf(doc_freq: i64, doc_count: i64) -> f32 {
+        (((doc_count + 1) as f64 / (doc_freq + 1) as f64).ln() + 1.0) as f32
+    }
+
+    #[inline]
+    fn decode_norm_value(b: usize) -> f32 {
+        NORM_TABLE[b]
+    }
+
+    fn idf_explain(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Explanation {
+        let doc_count = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+
+        let mut idf_total = 0f32;
+        let mut details: Vec<Explanation> = Vec::with_capacity(term_stats.len());
+        for stat in term_stats {
+            let idf = ClassicSimilarity::idf(stat.doc_freq, doc_count);
+            idf_total += idf;
+            details.push(Explanation::new(
+                true,
+                idf,
+                "idf, computed as log((docCount+1)/(docFreq+1)) + 1 from:".to_string(),
+                vec![
+                    Explanation::new(true, stat.doc_freq as f32, "docFreq".to_string(), vec![]),
+                    Explanation::new(true, doc_count as f32, "docCount".to_string(), vec![]),
+                ],
+            ));
+        }
+
+        if details.len() == 1 {
+            details.remove(0)
+        } else {
+            Explanation::new(true, idf_total, "idf(), sum of:".to_string(), details)
+        }
+    }
+}
+
+impl<C: Codec> Similarity<C> for ClassicSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let idf = self.idf_explain(collection_stats, term_stats);
+        Box::new(TFIDFSimWeight::new(
+            collection_stats.field.clone(),
+            idf,
+            boost,
+        ))
+    }
+
+    fn query_norm(&self, value_for_normalization: f32, _context: Option<&KeyedContext>) -> f32 {
+        if value_for_normalization <= 0.0 {
+            // fall back to no normalization
+            1.0
+        } else {
+            1.0 / value_for_normalization.sqrt()
+        }
+    }
+}
+
+impl fmt::Display for ClassicSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClassicSimilarity")
+    }
+}
+
+/// `SimilarityProducer` using `ClassicSimilarity` for all the fields.
+pub struct ClassicSimilarityProducer;
+
+impl<C: Codec> SimilarityProducer<C> for ClassicSimilarityProducer {
+    fn create(&self, _field: &str) -> Box<dyn Similarity<C>> {
+        Box::new(ClassicSimilarity)
+    }
+}
+
+struct TFIDFSimWeight {
+    field: String,
+    idf: Explanation,
+    boost: f32,
+    query_norm: f32,
+    query_weight: f32,
+    // idf * query_weight, the weight applied to each matching doc
+    value: f32,
+}
+
+impl TFIDFSimWeight {
+    fn new(field: String, idf: Explanation, boost: f32) -> TFIDFSimWeight {
+        let mut weight = TFIDFSimWeight {
+            field,
+            idf,
+            boost: 1.0,
+            query_norm: 1.0,
+            query_weight: 0.0,
+            value: 0.0,
+        };
+        weight.do_normalize(1.0, boost);
+        weight
+    }
+
+    fn do_normalize(&mut self, query_norm: f32, boost: f32) {
+        let idf = self.idf.value();
+        self.boost = boost;
+        self.query_norm = query_norm;
+        self.query_weight = query_norm * boost * idf;
+        self.value = self.query_weight * idf;
+    }
+
+    fn explain_field(
+        &self,
+        doc: DocId,
+        freq: Explanation,
+        norms: Option<Box<dyn NumericDocValues>>,
+    ) -> Result<Explanation> {
+        let tf = Explanation::new(
+            true,
+            ClassicSimilarity::tf(freq.value()),
+            format!("tf(freq={}), with freq of:", freq.value()),
+            vec![freq],
+        );
+        let field_norm = match norms {
+            Some(n) => ClassicSimilarity::decode_norm_value((n.get(doc)? & 0xFF) as usize),
+            None => 1.0,
+        };
+        let field_norm_expl =
+            Explanation::new(true, field_norm, format!("fieldNorm(doc={})", doc), vec![]);
+
+        Ok(Explanation::new(
+            true,
+            tf.value() * self.idf.value() * field_norm,
+            format!("fieldWeight in {}, product of:", doc),
+            vec![tf, self.idf.clone(), field_norm_expl],
+        ))
+    }
+}
+
+impl<C: Codec> SimWeight<C> for TFIDFSimWeight {
+    fn get_value_for_normalization(&self) -> f32 {
+        // the square of the query weight
+        self.query_weight * self.query_weight
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        self.do_normalize(query_norm, boost)
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        let norms = reader.norm_values(&self.field)?;
+        Ok(Box::new(TFIDFSimScorer {
+            weight_value: self.value,
+            norms,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let freq_value = freq.value();
+        let field_expl = self.explain_field(doc, freq, norms)?;
+
+        let mut query_details = Vec::with_capacity(3);
+        if (self.boost - 1.0).abs() > ::std::f32::EPSILON {
+            query_details.push(Explanation::new(
+                true,
+                self.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        query_details.push(self.idf.clone());
+        query_details.push(Explanation::new(
+            true,
+            self.query_norm,
+            "queryNorm".to_string(),
+            vec![],
+        ));
+        let query_expl = Explanation::new(
+            true,
+            self.query_weight,
+            "queryWeight, product of:".to_string(),
+            query_details,
+        );
+
+        if (query_expl.value() - 1.0).abs() < ::std::f32::EPSILON {
+            return Ok(field_expl);
+        }
+        Ok(Explanation::new(
+            true,
+            query_expl.value() * field_expl.value(),
+            format!("score(doc={},freq={}), product of:", doc, freq_value),
+            vec![query_expl, field_expl],
+        ))
+    }
+}
+
+struct TFIDFSimScorer {
+    weight_value: f32,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl SimScorer for TFIDFSimScorer {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let raw = ClassicSimilarity::tf(freq) * self.weight_value;
+        if let Some(ref norms) = self.norms {
+            let norm = ClassicSimilarity::decode_norm_value((norms.get(doc)? & 0xFF) as usize);
+            Ok(raw * norm)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        ClassicSimilarity::sloppy_freq(distance)
+    }
+
+    fn max_score(&self, max_freq: f32) -> f32 {
+        if max_freq.is_infinite() {
+            return f32::MAX;
+        }
+        // norms may include index-time boosts, so bound by the largest encodable one
+        let max_norm = if self.norms.is_some() {
+            NORM_TABLE[255]
+        } else {
+            1.0
+        };
+        ClassicSimilarity::tf(max_freq) * self.weight_value * max_norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::tests::TestCodec;
+    use crate::core::index::tests::MockLeafReader;
+
+    #[test]
+    fn test_idf_and_tf() {
+        assert!((ClassicSimilarity::idf(0, 0) - 1.0).abs() < ::std::f32::EPSILON);
+        assert!((ClassicSimilarity::idf(1, 3) - (2f32.ln() + 1.0)).abs() < ::std::f32::EPSILON);
+        assert!((ClassicSimilarity::tf(4.0) - 2.0).abs() < ::std::f32::EPSILON);
+    }
+
+    #[test]
+    fn test_classic_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 0, 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let sim = ClassicSimilarity;
+        let mut sim_weight: Box<dyn SimWeight<TestCodec>> =
+            sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+
+        let norm = Similarity::<TestCodec>::query_norm(
+            &sim,
+            sim_weight.get_value_for_normalization(),
+            None,
+        );
+        sim_weight.normalize(norm, 1.0);
+        assert!((sim_weight.get_value_for_normalization() - 1.0).abs() < 1e-6);
+
+        let leaf_reader = MockLeafReader::new(1);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+
+        // same field length
+        let score1 = sim_scorer.score(1, 100.0).unwrap();
+        let score2 = sim_scorer.score(1, 20.0).unwrap();
+        assert!(score1 > score2);
+
+        // same term_freq, shorter field
+        let score1 = sim_scorer.score(1, 10.0).unwrap();
+        let score2 = sim_scorer.score(2, 10.0).unwrap();
+        assert!(score1 > score2);
+    }
+}
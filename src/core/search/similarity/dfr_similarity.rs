@@ -0,0 +1,620 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Divergence from randomness (DFR) framework, see Gianni Amati and Cornelis
+//! Joost Van Rijsbergen. 2002. Probabilistic models of information retrieval
+//! based on measuring the divergence from randomness. ACM Trans. Inf. Syst.
+//! 20, 4 (October 2002), 357-389.
+//!
+//! The DFR scoring formula is composed of three separate components: the
+//! *basic model*, the *aftereffect* and an additional *normalization*
+//! component, each of them is a trait so that new models can be plugged in.
+
+use std::f64::consts::{E, LOG2_E, PI};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::core::search::explanation::Explanation;
+use crate::core::search::similarity::{BasicStats, SimilarityBase};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+#[inline]
+fn log2(x: f64) -> f64 {
+    x.log2()
+}
+
+/// The basic model of information content, which computes the informative
+/// content of a term given its normalized frequency `tfn`.
+pub trait BasicModel: fmt::Display + Send + Sync {
+    /// Returns the informative content score.
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32;
+
+    /// Returns an explanation for the score.
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("{}, computed from:", self),
+            vec![
+                Explanation::new(true, tfn, "tfn".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.number_of_documents as f32,
+                    "numberOfDocuments".to_string(),
+                    vec![],
+                ),
+                Explanation::new(
+                    true,
+                    stats.total_term_freq as f32,
+                    "totalTermFreq".to_string(),
+                    vec![],
+                ),
+            ],
+        )
+    }
+}
+
+/// Limiting form of the Bose-Einstein model.
+pub struct BasicModelBE;
+
+impl BasicModelBE {
+    // The f helper function defined for Be.
+    fn f(n: f64, m: f64) -> f64 {
+        (m + 0.5) * log2(n / m) + (n - m) * log2(n)
+    }
+}
+
+impl BasicModel for BasicModelBE {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let f = stats.total_term_freq as f64 + 1.0 + tfn;
+        // approximation only holds true when F << N, so we use N += F
+        let n = f + stats.number_of_documents as f64;
+        (-log2((n - 1.0) * E) + Self::f(n + f - 1.0, n + f - tfn - 2.0) - Self::f(f, f - tfn))
+            as f32
+    }
+}
+
+impl fmt::Display for BasicModelBE {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Be")
+    }
+}
+
+/// Implements the approximation of the binomial model with the divergence
+/// for DFR.
+pub struct BasicModelD;
+
+impl BasicModel for BasicModelD {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        // we have to ensure phi is always < 1 for tiny TTF values, otherwise nphi
+        // can go negative
+        let f = stats.total_term_freq as f64 + 1.0 + tfn;
+        let phi = tfn / f;
+        let nphi = 1.0 - phi;
+        let p = 1.0 / (stats.number_of_documents as f64 + 1.0);
+        let d = phi * log2(phi / p) + nphi * log2(nphi / (1.0 - p));
+        (d * f + 0.5 * log2(1.0 + 2.0 * PI * tfn * nphi)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelD {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "D")
+    }
+}
+
+/// Geometric as limiting form of the Bose-Einstein model.
+pub struct BasicModelG;
+
+impl BasicModel for BasicModelG {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        // just like in BE, approximation only holds true when F << N,
+        // so we use lambda = F / (N + F)
+        let f = stats.total_term_freq as f64 + 1.0;
+        let n = stats.number_of_documents as f64;
+        let lambda = f / (n + f);
+        // -log(1 / (lambda + 1)) -> log(lambda + 1)
+        (log2(lambda + 1.0) + f64::from(tfn) * log2((1.0 + lambda) / lambda)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelG {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "G")
+    }
+}
+
+/// An approximation of the *I(n<sub>e</sub>)* model.
+pub struct BasicModelIF;
+
+impl BasicModel for BasicModelIF {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let f = stats.total_term_freq as f64;
+        (f64::from(tfn) * log2(1.0 + (n + 1.0) / (f + 0.5))) as f32
+    }
+}
+
+impl fmt::Display for BasicModelIF {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(F)")
+    }
+}
+
+/// The basic tf-idf model of randomness.
+pub struct BasicModelIn;
+
+impl BasicModel for BasicModelIn {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let df = stats.doc_freq as f64;
+        (f64::from(tfn) * log2((n + 1.0) / (df + 0.5))) as f32
+    }
+}
+
+impl fmt::Display for BasicModelIn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(n)")
+    }
+}
+
+/// Tf-idf model of randomness, based on a mixture of Poisson and inverse
+/// document frequency.
+pub struct BasicModelIne;
+
+impl BasicModel for BasicModelIne {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let n = stats.number_of_documents as f64;
+        let f = stats.total_term_freq as f64;
+        let ne = n * (1.0 - ((n - 1.0) / n).powf(f));
+        (f64::from(tfn) * log2((n + 1.0) / (ne + 0.5))) as f32
+    }
+}
+
+impl fmt::Display for BasicModelIne {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "I(ne)")
+    }
+}
+
+/// Implements the Poisson approximation for the binomial model for DFR.
+pub struct BasicModelP;
+
+impl BasicModel for BasicModelP {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let lambda =
+            (stats.total_term_freq as f64 + 1.0) / (stats.number_of_documents as f64 + 1.0);
+        (tfn * log2(tfn / lambda)
+            + (lambda + 1.0 / (12.0 * tfn) - tfn) * LOG2_E
+            + 0.5 * log2(2.0 * PI * tfn)) as f32
+    }
+}
+
+impl fmt::Display for BasicModelP {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "P")
+    }
+}
+
+/// This trait acts as the base for the first normalization of information
+/// gain, also called *after effect* and denoted by *B* and *L*.
+pub trait AfterEffect: fmt::Display + Send + Sync {
+    /// Returns the aftereffect score.
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32;
+
+    /// Returns an explanation for the score.
+    fn explain(&self, stats: &BasicStats, tfn: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("after effect {}, computed from:", self),
+            vec![Explanation::new(true, tfn, "tfn".to_string(), vec![])],
+        )
+    }
+}
+
+/// Model of the information gain based on the ratio of two Bernoulli processes.
+pub struct AfterEffectB;
+
+impl AfterEffect for AfterEffectB {
+    fn score(&self, stats: &BasicStats, tfn: f32) -> f32 {
+        let f = stats.total_term_freq as f32 + 1.0;
+        let n = stats.doc_freq as f32 + 1.0;
+        (f + 1.0) / (n * (tfn + 1.0))
+    }
+}
+
+impl fmt::Display for AfterEffectB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")
+    }
+}
+
+/// Model of the information gain based on Laplace's law of succession.
+pub struct AfterEffectL;
+
+impl AfterEffect for AfterEffectL {
+    fn score(&self, _stats: &BasicStats, tfn: f32) -> f32 {
+        1.0 / (tfn + 1.0)
+    }
+}
+
+impl fmt::Display for AfterEffectL {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "L")
+    }
+}
+
+/// Implementation used when there is no aftereffect.
+pub struct NoAfterEffect;
+
+impl AfterEffect for NoAfterEffect {
+    fn score(&self, _stats: &BasicStats, _tfn: f32) -> f32 {
+        1.0
+    }
+}
+
+impl fmt::Display for NoAfterEffect {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// This trait acts as the base for the second (length) normalization,
+/// which computes the normalized term frequency.
+pub trait Normalization: fmt::Display + Send + Sync {
+    /// Returns the normalized term frequency.
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32;
+
+    /// Returns an explanation for the normalized term frequency.
+    fn explain(&self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        Explanation::new(
+            true,
+            self.tfn(stats, tf, len),
+            format!("{}, computed from:", self),
+            vec![
+                Explanation::new(true, tf, "tf".to_string(), vec![]),
+                Explanation::new(
+                    true,
+                    stats.avg_field_length,
+                    "avgFieldLength".to_string(),
+                    vec![],
+                ),
+                Explanation::new(true, len, "len".to_string(), vec![]),
+            ],
+        )
+    }
+}
+
+/// Normalization model that assumes a uniform distribution of the term frequency.
+pub struct NormalizationH1 {
+    c: f32,
+}
+
+impl NormalizationH1 {
+    pub fn new(c: f32) -> Result<NormalizationH1> {
+        if !(c.is_finite() && c > 0.0) {
+            return Err(IllegalArgument(format!(
+                "illegal c value: {}, must be a positive finite value",
+                c
+            )));
+        }
+        Ok(NormalizationH1 { c })
+    }
+}
+
+impl Default for NormalizationH1 {
+    fn default() -> Self {
+        NormalizationH1 { c: 1.0 }
+    }
+}
+
+impl Normalization for NormalizationH1 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        tf * self.c * (stats.avg_field_length / len)
+    }
+}
+
+impl fmt::Display for NormalizationH1 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "1")
+    }
+}
+
+/// Normalization model in which the term frequency is inversely related to
+/// the length.
+pub struct NormalizationH2 {
+    c: f32,
+}
+
+impl NormalizationH2 {
+    pub fn new(c: f32) -> Result<NormalizationH2> {
+        if !(c.is_finite() && c > 0.0) {
+            return Err(IllegalArgument(format!(
+                "illegal c value: {}, must be a positive finite value",
+                c
+            )));
+        }
+        Ok(NormalizationH2 { c })
+    }
+}
+
+impl Default for NormalizationH2 {
+    fn default() -> Self {
+        NormalizationH2 { c: 1.0 }
+    }
+}
+
+impl Normalization for NormalizationH2 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        (f64::from(tf) * log2(1.0 + f64::from(self.c * stats.avg_field_length / len))) as f32
+    }
+}
+
+impl fmt::Display for NormalizationH2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "2")
+    }
+}
+
+/// Dirichlet Priors normalization.
+pub struct NormalizationH3 {
+    mu: f32,
+}
+
+impl NormalizationH3 {
+    pub fn new(mu: f32) -> Result<NormalizationH3> {
+        if !(mu.is_finite() && mu >= 0.0) {
+            return Err(IllegalArgument(format!(
+                "illegal mu value: {}, must be a non-negative finite value",
+                mu
+            )));
+        }
+        Ok(NormalizationH3 { mu })
+    }
+}
+
+impl Default for NormalizationH3 {
+    fn default() -> Self {
+        NormalizationH3 { mu: 800.0 }
+    }
+}
+
+impl Normalization for NormalizationH3 {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        let p = (stats.total_term_freq as f32 + 1.0) / (stats.number_of_field_tokens as f32 + 1.0);
+        (tf + self.mu * p) / (len + self.mu) * self.mu
+    }
+}
+
+impl fmt::Display for NormalizationH3 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "3({})", self.mu)
+    }
+}
+
+/// Pareto-Zipf Normalization.
+pub struct NormalizationZ {
+    z: f32,
+}
+
+impl NormalizationZ {
+    /// `z` represents `A / (A + 1)` where `A` measures the specificity of the
+    /// language, it must be within `(0, 0.5)`.
+    pub fn new(z: f32) -> Result<NormalizationZ> {
+        if !(z > 0.0 && z < 0.5) {
+            return Err(IllegalArgument(format!(
+                "illegal z value: {}, must be in the range (0 .. 0.5)",
+                z
+            )));
+        }
+        Ok(NormalizationZ { z })
+    }
+}
+
+impl Default for NormalizationZ {
+    fn default() -> Self {
+        NormalizationZ { z: 0.3 }
+    }
+}
+
+impl Normalization for NormalizationZ {
+    fn tfn(&self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        tf * (stats.avg_field_length / len).powf(self.z)
+    }
+}
+
+impl fmt::Display for NormalizationZ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Z({})", self.z)
+    }
+}
+
+/// Implementation used when there is no normalization.
+pub struct NoNormalization;
+
+impl Normalization for NoNormalization {
+    fn tfn(&self, _stats: &BasicStats, tf: f32, _len: f32) -> f32 {
+        tf
+    }
+
+    fn explain(&self, _stats: &BasicStats, tf: f32, _len: f32) -> Explanation {
+        Explanation::new(true, tf, "no normalization".to_string(), vec![])
+    }
+}
+
+impl fmt::Display for NoNormalization {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+/// Implements the *divergence from randomness (DFR)* framework.
+///
+/// The score of a document is `boost * basicModel(tfn) * afterEffect(tfn)`
+/// where `tfn` is the term frequency normalized by the `Normalization`, e.g.
+/// `DFRSimilarity::new(BasicModelIn, AfterEffectL, NormalizationH2::default())`
+/// is the *InL2* model.
+#[derive(Clone)]
+pub struct DFRSimilarity {
+    basic_model: Arc<dyn BasicModel>,
+    after_effect: Arc<dyn AfterEffect>,
+    normalization: Arc<dyn Normalization>,
+}
+
+impl DFRSimilarity {
+    pub fn new<B, A, N>(basic_model: B, after_effect: A, normalization: N) -> DFRSimilarity
+    where
+        B: BasicModel + 'static,
+        A: AfterEffect + 'static,
+        N: Normalization + 'static,
+    {
+        DFRSimilarity {
+            basic_model: Arc::new(basic_model),
+            after_effect: Arc::new(after_effect),
+            normalization: Arc::new(normalization),
+        }
+    }
+
+    pub fn basic_model(&self) -> &dyn BasicModel {
+        self.basic_model.as_ref()
+    }
+
+    pub fn after_effect(&self) -> &dyn AfterEffect {
+        self.after_effect.as_ref()
+    }
+
+    pub fn normalization(&self) -> &dyn Normalization {
+        self.normalization.as_ref()
+    }
+}
+
+impl SimilarityBase for DFRSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let tfn = self.normalization.tfn(stats, freq, doc_len);
+        stats.boost * self.basic_model.score(stats, tfn) * self.after_effect.score(stats, tfn)
+    }
+
+    fn explain(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> Vec<Explanation> {
+        let mut subs = Vec::with_capacity(4);
+        if (stats.boost - 1.0).abs() > f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        let normalized = self.normalization.explain(stats, freq, doc_len);
+        let tfn = normalized.value();
+        subs.push(normalized);
+        subs.push(self.basic_model.explain(stats, tfn));
+        subs.push(self.after_effect.explain(stats, tfn));
+        subs
+    }
+}
+
+impl fmt::Display for DFRSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DFR {}{}{}",
+            self.basic_model, self.after_effect, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+
+    fn stats() -> BasicStats {
+        let collection_stats =
+            CollectionStatistics::new(String::from("body"), 0, 100, 100, 10000, 5000);
+        let term_stats = TermStatistics::new(Vec::new(), 10, 20);
+        BasicStats::new(&collection_stats, &term_stats, 1.0)
+    }
+
+    fn check_model(sim: DFRSimilarity) {
+        let stats = stats();
+        for &(freq, len) in &[(1.0, 100.0), (5.0, 100.0), (10.0, 50.0), (3.0, 1000.0)] {
+            let score = sim.score(&stats, freq, len);
+            assert!(score.is_finite(), "{} scored {}", sim, score);
+            assert!(score >= 0.0, "{} scored {}", sim, score);
+        }
+        // longer docs don't score higher
+        assert!(sim.score(&stats, 5.0, 50.0) >= sim.score(&stats, 5.0, 500.0));
+    }
+
+    #[test]
+    fn test_dfr_models() {
+        check_model(DFRSimilarity::new(
+            BasicModelIn,
+            AfterEffectL,
+            NormalizationH2::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelIF,
+            AfterEffectB,
+            NormalizationH1::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelIne,
+            AfterEffectB,
+            NormalizationH3::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelG,
+            AfterEffectL,
+            NormalizationZ::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelBE,
+            AfterEffectL,
+            NormalizationH2::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelP,
+            AfterEffectL,
+            NormalizationH2::default(),
+        ));
+        check_model(DFRSimilarity::new(
+            BasicModelD,
+            AfterEffectL,
+            NormalizationH2::default(),
+        ));
+    }
+
+    #[test]
+    fn test_illegal_normalizations() {
+        assert!(NormalizationH1::new(0.0).is_err());
+        assert!(NormalizationH2::new(f32::INFINITY).is_err());
+        assert!(NormalizationH3::new(-1.0).is_err());
+        assert!(NormalizationH3::new(0.0).is_ok());
+        assert!(NormalizationZ::new(0.5).is_err());
+        assert!(NormalizationZ::new(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_dfr_to_string() {
+        let sim = DFRSimilarity::new(BasicModelIn, AfterEffectL, NormalizationH2::default());
+        assert_eq!(sim.to_string(), "DFR I(n)L2");
+        let sim = DFRSimilarity::new(BasicModelG, NoAfterEffect, NoNormalization);
+        assert_eq!(sim.to_string(), "DFR G");
+    }
+}
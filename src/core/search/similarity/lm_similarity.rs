@@ -0,0 +1,271 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Language model based similarities, see Chengxiang Zhai and John Lafferty.
+//! 2001. A study of smoothing methods for language models applied to Ad Hoc
+//! information retrieval. In Proceedings of the 24th annual international
+//! ACM SIGIR conference on Research and development in information retrieval
+//! (SIGIR '01). ACM, New York, NY, USA, 334-342.
+
+use std::fmt;
+
+use crate::core::search::explanation::Explanation;
+use crate::core::search::similarity::{BasicStats, SimilarityBase};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+pub const DEFAULT_LM_DIRICHLET_MU: f32 = 2000.0;
+pub const DEFAULT_LM_JELINEK_MERCER_LAMBDA: f32 = 0.7;
+
+/// Computes the probability of a term in the collection, as
+/// `(totalTermFreq + 1) / (numberOfFieldTokens + 1)`.
+pub fn collection_probability(stats: &BasicStats) -> f32 {
+    (stats.total_term_freq as f32 + 1.0) / (stats.number_of_field_tokens as f32 + 1.0)
+}
+
+fn collection_probability_explain(stats: &BasicStats) -> Explanation {
+    Explanation::new(
+        true,
+        collection_probability(stats),
+        "collection probability, computed as (totalTermFreq + 1) / (numberOfFieldTokens + 1) from:"
+            .to_string(),
+        vec![
+            Explanation::new(
+                true,
+                stats.total_term_freq as f32,
+                "totalTermFreq".to_string(),
+                vec![],
+            ),
+            Explanation::new(
+                true,
+                stats.number_of_field_tokens as f32,
+                "numberOfFieldTokens".to_string(),
+                vec![],
+            ),
+        ],
+    )
+}
+
+/// Bayesian smoothing using Dirichlet priors.
+///
+/// The formula as defined the paper assigns a negative score to documents that
+/// contain the term, but with fewer occurrences than predicted by the collection
+/// language model. The Lucene implementation returns `0` for such documents.
+#[derive(Clone, Copy, Debug)]
+pub struct LMDirichletSimilarity {
+    mu: f32,
+}
+
+impl LMDirichletSimilarity {
+    /// Instantiates the similarity with the provided `mu` parameter, which
+    /// must be finite and non-negative.
+    pub fn new(mu: f32) -> Result<LMDirichletSimilarity> {
+        if !mu.is_finite() || mu < 0.0 {
+            return Err(IllegalArgument(format!(
+                "illegal mu value: {}, must be a non-negative finite value",
+                mu
+            )));
+        }
+        Ok(LMDirichletSimilarity { mu })
+    }
+
+    /// Returns the `mu` parameter.
+    pub fn mu(&self) -> f32 {
+        self.mu
+    }
+}
+
+impl Default for LMDirichletSimilarity {
+    fn default() -> Self {
+        LMDirichletSimilarity {
+            mu: DEFAULT_LM_DIRICHLET_MU,
+        }
+    }
+}
+
+impl SimilarityBase for LMDirichletSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let p = collection_probability(stats);
+        let score = stats.boost
+            * ((1.0 + f64::from(freq) / (f64::from(self.mu) * f64::from(p))).ln()
+                + (f64::from(self.mu) / (f64::from(doc_len) + f64::from(self.mu))).ln())
+                as f32;
+        if score > 0.0 {
+            score
+        } else {
+            0.0
+        }
+    }
+
+    fn explain(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> Vec<Explanation> {
+        let mut subs = Vec::with_capacity(5);
+        if (stats.boost - 1.0).abs() > f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(true, self.mu, "mu".to_string(), vec![]));
+        let p = collection_probability_explain(stats);
+        let weight = (1.0 + freq / (self.mu * p.value())).ln();
+        subs.push(Explanation::new(
+            true,
+            weight,
+            "term weight, computed as log(1 + freq / (mu * collectionProbability))".to_string(),
+            vec![p],
+        ));
+        subs.push(Explanation::new(
+            true,
+            (self.mu / (doc_len + self.mu)).ln(),
+            "document norm, computed as log(mu / (docLen + mu))".to_string(),
+            vec![Explanation::new(
+                true,
+                doc_len,
+                "docLen".to_string(),
+                vec![],
+            )],
+        ));
+        subs
+    }
+}
+
+impl fmt::Display for LMDirichletSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dirichlet({:.2})", self.mu)
+    }
+}
+
+/// Language model based on the Jelinek-Mercer smoothing method.
+///
+/// The model has a single parameter, `lambda`. According to the paper, the
+/// optimal value depends on both the collection and the query. The optimal
+/// value is around `0.1` for title queries and `0.7` for long queries.
+#[derive(Clone, Copy, Debug)]
+pub struct LMJelinekMercerSimilarity {
+    lambda: f32,
+}
+
+impl LMJelinekMercerSimilarity {
+    /// Instantiates the similarity with the provided `lambda` parameter, which
+    /// must be within `(0, 1]`.
+    pub fn new(lambda: f32) -> Result<LMJelinekMercerSimilarity> {
+        if !(lambda > 0.0 && lambda <= 1.0) {
+            return Err(IllegalArgument(format!(
+                "illegal lambda value: {}, must be in range (0 .. 1]",
+                lambda
+            )));
+        }
+        Ok(LMJelinekMercerSimilarity { lambda })
+    }
+
+    /// Returns the `lambda` parameter.
+    pub fn lambda(&self) -> f32 {
+        self.lambda
+    }
+}
+
+impl Default for LMJelinekMercerSimilarity {
+    fn default() -> Self {
+        LMJelinekMercerSimilarity {
+            lambda: DEFAULT_LM_JELINEK_MERCER_LAMBDA,
+        }
+    }
+}
+
+impl SimilarityBase for LMJelinekMercerSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let p = collection_probability(stats);
+        stats.boost
+            * (1.0 + f64::from((1.0 - self.lambda) * freq / doc_len) / f64::from(self.lambda * p))
+                .ln() as f32
+    }
+
+    fn explain(&self, stats: &BasicStats, _freq: f32, doc_len: f32) -> Vec<Explanation> {
+        let mut subs = Vec::with_capacity(4);
+        if (stats.boost - 1.0).abs() > f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(
+            true,
+            self.lambda,
+            "lambda".to_string(),
+            vec![],
+        ));
+        subs.push(collection_probability_explain(stats));
+        subs.push(Explanation::new(
+            true,
+            doc_len,
+            "docLen".to_string(),
+            vec![],
+        ));
+        subs
+    }
+}
+
+impl fmt::Display for LMJelinekMercerSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Jelinek-Mercer({:.2})", self.lambda)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+
+    fn stats() -> BasicStats {
+        let collection_stats =
+            CollectionStatistics::new(String::from("body"), 0, 100, 100, 10000, 5000);
+        let term_stats = TermStatistics::new(Vec::new(), 10, 20);
+        BasicStats::new(&collection_stats, &term_stats, 1.0)
+    }
+
+    #[test]
+    fn test_dirichlet() {
+        let sim = LMDirichletSimilarity::default();
+        let stats = stats();
+        assert!((stats.avg_field_length - 100.0).abs() < f32::EPSILON);
+
+        // more occurrences score higher, longer docs score lower
+        assert!(sim.score(&stats, 10.0, 100.0) > sim.score(&stats, 5.0, 100.0));
+        assert!(sim.score(&stats, 10.0, 50.0) > sim.score(&stats, 10.0, 500.0));
+        // fewer occurrences than predicted by the collection model
+        assert_eq!(sim.score(&stats, 1.0, 100_000.0), 0.0);
+    }
+
+    #[test]
+    fn test_jelinek_mercer() {
+        let sim = LMJelinekMercerSimilarity::default();
+        let stats = stats();
+
+        assert!(sim.score(&stats, 10.0, 100.0) > sim.score(&stats, 5.0, 100.0));
+        assert!(sim.score(&stats, 10.0, 50.0) > sim.score(&stats, 10.0, 500.0));
+        assert!(sim.score(&stats, 1.0, 100.0) > 0.0);
+    }
+
+    #[test]
+    fn test_illegal_parameters() {
+        assert!(LMDirichletSimilarity::new(-1.0).is_err());
+        assert!(LMDirichletSimilarity::new(f32::NAN).is_err());
+        assert!(LMJelinekMercerSimilarity::new(0.0).is_err());
+        assert!(LMJelinekMercerSimilarity::new(f32::NAN).is_err());
+        assert!(LMJelinekMercerSimilarity::new(1.0).is_ok());
+    }
+}
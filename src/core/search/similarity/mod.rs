@@ -13,7 +13,33 @@
 
 mod bm25_similarity;
 
-pub use self::bm25_similarity::BM25Similarity;
+pub use self::bm25_similarity::{
+    BM25Similarity, BM25SimilarityProducer, DEFAULT_BM25_B, DEFAULT_BM25_K1,
+};
+
+mod classic_similarity;
+
+pub use self::classic_similarity::{ClassicSimilarity, ClassicSimilarityProducer};
+
+mod per_field_similarity;
+
+pub use self::per_field_similarity::PerFieldSimilarityWrapper;
+
+mod similarity_base;
+
+pub use self::similarity_base::{BasicStats, SimilarityBase};
+
+mod lm_similarity;
+
+pub use self::lm_similarity::*;
+
+mod dfr_similarity;
+
+pub use self::dfr_similarity::*;
+
+mod boolean_similarity;
+
+pub use self::boolean_similarity::{BooleanSimilarity, BooleanSimilarityProducer};
 
 use crate::core::util::{DocId, KeyedContext};
 
@@ -134,6 +160,20 @@ pub trait SimScorer: Send {
     /// Computes the amount of a sloppy phrase match, based on an edit distance.
     fn compute_slop_factor(&self, distance: i32) -> f32;
 
+    /// Returns an upper bound of the score of any document whose frequency
+    /// is at most `max_freq`, `max_freq` may be `f32::INFINITY` if unknown.
+    /// Returns `f32::MAX` if no bound can be computed.
+    fn max_score(&self, _max_freq: f32) -> f32 {
+        f32::MAX
+    }
+
+    /// Returns an upper bound of the score of any document whose frequency
+    /// is at most `freq` and whose norm is at most as competitive as `norm`,
+    /// as given by the postings `Impact`s.
+    fn max_score_for_impact(&self, freq: f32, _norm: i64) -> f32 {
+        self.max_score(freq)
+    }
+
     // Calculate a scoring factor based on the data in the payload.
     // fn compute_payload_factor(&self, doc: DocId, start: i32, end: i32, payload: &Payload);
 }
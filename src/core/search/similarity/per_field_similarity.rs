@@ -0,0 +1,77 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::core::codec::Codec;
+use crate::core::search::similarity::{Similarity, SimilarityProducer};
+
+/// Provides the ability to use a different `Similarity` for different fields.
+///
+/// Fields that were not registered with `with_field` use the default producer,
+/// e.g. `title` may use `BM25Similarity` with a small `b` while `tags` use
+/// `ClassicSimilarity`.
+pub struct PerFieldSimilarityWrapper<C: Codec> {
+    default: Box<dyn SimilarityProducer<C> + Send + Sync>,
+    fields: HashMap<String, Box<dyn SimilarityProducer<C> + Send + Sync>>,
+}
+
+impl<C: Codec> PerFieldSimilarityWrapper<C> {
+    pub fn new<P>(default: P) -> PerFieldSimilarityWrapper<C>
+    where
+        P: SimilarityProducer<C> + Send + Sync + 'static,
+    {
+        PerFieldSimilarityWrapper {
+            default: Box::new(default),
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Use `producer` to create the similarity of `field`.
+    pub fn with_field<P>(mut self, field: impl Into<String>, producer: P) -> Self
+    where
+        P: SimilarityProducer<C> + Send + Sync + 'static,
+    {
+        self.fields.insert(field.into(), Box::new(producer));
+        self
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for PerFieldSimilarityWrapper<C> {
+    fn create(&self, field: &str) -> Box<dyn Similarity<C>> {
+        match self.fields.get(field) {
+            Some(producer) => producer.create(field),
+            None => self.default.create(field),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::tests::TestCodec;
+    use crate::core::search::similarity::{BM25SimilarityProducer, ClassicSimilarityProducer};
+
+    #[test]
+    fn test_per_field_similarity() {
+        let producer: PerFieldSimilarityWrapper<TestCodec> =
+            PerFieldSimilarityWrapper::new(BM25SimilarityProducer::new(1.5, 0.3).unwrap())
+                .with_field("title", ClassicSimilarityProducer);
+
+        assert_eq!(
+            producer.create("body").to_string(),
+            "BM25Similarity(k1: 1.5, b: 0.3)"
+        );
+        assert_eq!(producer.create("title").to_string(), "ClassicSimilarity");
+    }
+}
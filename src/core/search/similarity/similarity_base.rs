@@ -0,0 +1,254 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Result;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::core::codec::doc_values::NumericDocValues;
+use crate::core::codec::Codec;
+use crate::core::index::reader::SearchLeafReader;
+use crate::core::search::explanation::Explanation;
+use crate::core::search::similarity::{SimScorer, SimWeight, Similarity};
+use crate::core::search::statistics::{CollectionStatistics, TermStatistics};
+use crate::core::util::SmallFloat;
+use crate::core::util::{DocId, KeyedContext};
+
+lazy_static::lazy_static! {
+    static ref LENGTH_TABLE: [f32; 256] = {
+        let mut length_table: [f32; 256] = [0f32; 256];
+        for (i, len) in length_table.iter_mut().enumerate().skip(1) {
+            let f = SmallFloat::byte315_to_float(i as u8);
+            *len = 1f32 / (f * f);
+        }
+        length_table[0] = 1f32 / length_table[255];
+        length_table
+    };
+}
+
+/// Stores all statistics commonly used by ranking methods.
+#[derive(Clone, Debug)]
+pub struct BasicStats {
+    pub field: String,
+    /// The number of documents.
+    pub number_of_documents: i64,
+    /// The total number of tokens in the field.
+    pub number_of_field_tokens: i64,
+    /// The average field length.
+    pub avg_field_length: f32,
+    /// The document frequency.
+    pub doc_freq: i64,
+    /// The total number of occurrences of this term across all documents.
+    pub total_term_freq: i64,
+    /// Query boost, including the query norm.
+    pub boost: f32,
+}
+
+impl BasicStats {
+    /// Fills all member fields defined in `BasicStats` from the index statistics.
+    pub fn new(
+        collection_stats: &CollectionStatistics,
+        term_stats: &TermStatistics,
+        boost: f32,
+    ) -> BasicStats {
+        let number_of_documents = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        // codecs that don't record term frequencies fall back to doc frequencies
+        let number_of_field_tokens = if collection_stats.sum_total_term_freq == -1 {
+            collection_stats.sum_doc_freq
+        } else {
+            collection_stats.sum_total_term_freq
+        };
+        let avg_field_length = if number_of_documents > 0 {
+            number_of_field_tokens as f32 / number_of_documents as f32
+        } else {
+            1f32
+        };
+        let total_term_freq = if term_stats.total_term_freq == -1 {
+            term_stats.doc_freq
+        } else {
+            term_stats.total_term_freq
+        };
+
+        BasicStats {
+            field: collection_stats.field.clone(),
+            number_of_documents,
+            number_of_field_tokens,
+            avg_field_length,
+            doc_freq: term_stats.doc_freq,
+            total_term_freq,
+            boost,
+        }
+    }
+}
+
+/// A subclass of `Similarity` that provides a simplified API for its
+/// descendants. Implementations are only required to implement the `score`
+/// method, the weights, norm decoding and explanations are provided.
+///
+/// Every type implementing this trait is a `Similarity`. Document length
+/// is decoded from the same norm encoding as `BM25Similarity`.
+pub trait SimilarityBase: fmt::Display + Clone + Send + Sync + 'static {
+    /// Scores the document `doc`.
+    ///
+    /// `freq` is the term frequency and `doc_len` the document length.
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32;
+
+    /// Subclasses should implement this method to explain the score, the
+    /// returned explanations are appended to the details of the score.
+    fn explain(&self, stats: &BasicStats, _freq: f32, doc_len: f32) -> Vec<Explanation> {
+        let mut subs = Vec::with_capacity(2);
+        if (stats.boost - 1.0).abs() > f32::EPSILON {
+            subs.push(Explanation::new(
+                true,
+                stats.boost,
+                "boost".to_string(),
+                vec![],
+            ));
+        }
+        subs.push(Explanation::new(
+            true,
+            doc_len,
+            "docLen".to_string(),
+            vec![],
+        ));
+        subs
+    }
+
+    /// Compute the document length from an encoded norm value.
+    fn decode_norm_value(&self, norm: u8) -> f32 {
+        LENGTH_TABLE[norm as usize]
+    }
+}
+
+impl<C: Codec, T: SimilarityBase> Similarity<C> for T {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let stats = term_stats
+            .iter()
+            .map(|ts| BasicStats::new(collection_stats, ts, boost))
+            .collect();
+        Box::new(BaseSimWeight {
+            similarity: Arc::new(self.clone()),
+            field: collection_stats.field.clone(),
+            stats,
+            boost,
+        })
+    }
+}
+
+/// One `BasicStats` per term, the score of a multi-term (e.g. phrase)
+/// query is the sum of the per term scores.
+struct BaseSimWeight<T: SimilarityBase> {
+    similarity: Arc<T>,
+    field: String,
+    stats: Vec<BasicStats>,
+    boost: f32,
+}
+
+impl<T: SimilarityBase> BaseSimWeight<T> {
+    fn doc_len(&self, norms: &Option<Box<dyn NumericDocValues>>, doc: DocId) -> Result<f32> {
+        match norms {
+            Some(n) => Ok(self.similarity.decode_norm_value(n.get(doc)? as u8)),
+            None => Ok(1f32),
+        }
+    }
+}
+
+impl<C: Codec, T: SimilarityBase> SimWeight<C> for BaseSimWeight<T> {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.boost * self.boost
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        self.boost = boost;
+        for stats in &mut self.stats {
+            stats.boost = query_norm * boost;
+        }
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        let norms = reader.norm_values(&self.field)?;
+        Ok(Box::new(BaseSimScorer {
+            similarity: Arc::clone(&self.similarity),
+            stats: self.stats.clone(),
+            norms,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let doc_len = self.doc_len(&norms, doc)?;
+        let freq_value = freq.value();
+
+        let mut details = Vec::with_capacity(self.stats.len());
+        for stats in &self.stats {
+            let score = self.similarity.score(stats, freq_value, doc_len);
+            let mut subs = vec![freq.clone()];
+            subs.extend(self.similarity.explain(stats, freq_value, doc_len));
+            details.push(Explanation::new(
+                true,
+                score,
+                format!(
+                    "score({}, doc={}, freq={}), computed from:",
+                    self.similarity, doc, freq_value
+                ),
+                subs,
+            ));
+        }
+
+        if details.len() == 1 {
+            Ok(details.remove(0))
+        } else {
+            let sum = details.iter().map(|e| e.value()).sum();
+            Ok(Explanation::new(true, sum, "sum of:".to_string(), details))
+        }
+    }
+}
+
+struct BaseSimScorer<T: SimilarityBase> {
+    similarity: Arc<T>,
+    stats: Vec<BasicStats>,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl<T: SimilarityBase> SimScorer for BaseSimScorer<T> {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let doc_len = match self.norms {
+            Some(ref n) => self.similarity.decode_norm_value(n.get(doc)? as u8),
+            None => 1f32,
+        };
+        Ok(self
+            .stats
+            .iter()
+            .map(|stats| self.similarity.score(stats, freq, doc_len))
+            .sum())
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+}
@@ -11,9 +11,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::search::sort_field::SortField;
+use crate::core::search::sort_field::{Sort, SortField, SortFieldType};
 use crate::core::util::DocId;
 use crate::core::util::VariantValue;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
 use std::cmp::{Ord, Ordering};
 use std::f32;
 
@@ -22,11 +24,17 @@ use std::f32;
 pub struct ScoreDoc {
     pub doc: DocId,
     pub score: f32,
+    /// Index of the shard the hit came from, only set by `TopScoreDocs::merge`.
+    pub shard_index: usize,
 }
 
 impl ScoreDoc {
     pub fn new(doc: DocId, score: f32) -> ScoreDoc {
-        ScoreDoc { doc, score }
+        ScoreDoc {
+            doc,
+            score,
+            shard_index: 0,
+        }
     }
 
     pub fn reset(&mut self, doc: DocId, score: f32) {
@@ -102,7 +110,6 @@ impl FieldDoc {
             doc,
             score,
             shard_index: 0,
-
             fields,
         }
     }
@@ -158,6 +165,20 @@ impl ScoreDocHit {
         }
     }
 
+    pub fn shard_index(&self) -> usize {
+        match *self {
+            ScoreDocHit::Score(ref s) => s.shard_index,
+            ScoreDocHit::Field(ref f) => f.shard_index,
+        }
+    }
+
+    pub fn set_shard_index(&mut self, shard_index: usize) {
+        match *self {
+            ScoreDocHit::Score(ref mut s) => s.shard_index = shard_index,
+            ScoreDocHit::Field(ref mut f) => f.shard_index = shard_index,
+        }
+    }
+
     pub fn order_by_doc(d1: &ScoreDocHit, d2: &ScoreDocHit) -> Ordering {
         if d1.doc_id() < d2.doc_id() {
             Ordering::Less
@@ -226,6 +247,92 @@ impl TopScoreDocs {
     pub fn score_docs(&self) -> &[ScoreDocHit] {
         &self.score_docs
     }
+
+    pub fn max_score(&self) -> f32 {
+        self.max_score
+    }
+
+    /// Returns the merged top hits of several shards sorted by score,
+    /// skipping the first `start` hits. Hits with the same score are ordered
+    /// by shard index, then by their rank in the shard, and each merged hit
+    /// has its `shard_index` set to the position of its shard in `shard_hits`.
+    pub fn merge(start: usize, top_n: usize, shard_hits: &[TopScoreDocs]) -> TopScoreDocs {
+        let total_hits = shard_hits.iter().map(|s| s.total_hits).sum();
+        let mut max_score = f32::NAN;
+        for shard in shard_hits {
+            let shard_max = if shard.max_score.is_nan() {
+                shard.score_docs.first().map_or(f32::NAN, |h| h.score())
+            } else {
+                shard.max_score
+            };
+            if max_score.is_nan() || shard_max > max_score {
+                max_score = shard_max;
+            }
+        }
+
+        let score_docs = merge_hits(
+            start,
+            top_n,
+            shard_hits.iter().map(|s| s.score_docs.as_slice()),
+            |h1, h2| {
+                h2.score()
+                    .partial_cmp(&h1.score())
+                    .unwrap_or(Ordering::Equal)
+            },
+        );
+        TopScoreDocs {
+            total_hits,
+            score_docs,
+            max_score,
+        }
+    }
+}
+
+// k-way merge of hits already sorted by `cmp` in each shard, ties are
+// broken by shard index then by the rank of the hit in its shard.
+fn merge_hits<'a, I, F>(start: usize, top_n: usize, shards: I, cmp: F) -> Vec<ScoreDocHit>
+where
+    I: Iterator<Item = &'a [ScoreDocHit]>,
+    F: Fn(&ScoreDocHit, &ScoreDocHit) -> Ordering,
+{
+    let mut hits: Vec<(usize, usize, &ScoreDocHit)> = vec![];
+    for (shard_index, shard) in shards.enumerate() {
+        for (rank, hit) in shard.iter().take(start + top_n).enumerate() {
+            hits.push((shard_index, rank, hit));
+        }
+    }
+    hits.sort_by(|(s1, r1, h1), (s2, r2, h2)| cmp(h1, h2).then(s1.cmp(s2)).then(r1.cmp(r2)));
+    hits.into_iter()
+        .skip(start)
+        .take(top_n)
+        .map(|(shard_index, _, hit)| {
+            let mut hit = hit.clone();
+            hit.set_shard_index(shard_index);
+            hit
+        })
+        .collect()
+}
+
+// compare the sort values of two hits of a `TopFieldDocs` with `sort`
+fn compare_field_docs(sort: &[SortField], h1: &ScoreDocHit, h2: &ScoreDocHit) -> Ordering {
+    let (f1, f2) = match (h1, h2) {
+        (ScoreDocHit::Field(f1), ScoreDocHit::Field(f2)) => (f1, f2),
+        _ => unreachable!(),
+    };
+    for (i, sort_field) in sort.iter().enumerate() {
+        let mut ord = f1.fields[i].cmp(&f2.fields[i]);
+        // scores sort in descending order by default
+        if sort_field.field_type() == SortFieldType::Score {
+            ord = ord.reverse();
+        }
+        if sort_field.is_reverse() {
+            ord = ord.reverse();
+        }
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
 }
 
 #[derive(Clone)]
@@ -236,6 +343,60 @@ pub struct TopFieldDocs {
     pub fields: Vec<SortField>,
 }
 
+impl TopFieldDocs {
+    /// Returns the merged top hits of several shards that were sorted by
+    /// `sort`, skipping the first `start` hits. Ties are broken by shard
+    /// index, then by the rank of the hit in its shard.
+    ///
+    /// All the hits must be `FieldDoc`s holding one sort value per sort field.
+    pub fn merge(
+        sort: &Sort,
+        start: usize,
+        top_n: usize,
+        shard_hits: &[TopFieldDocs],
+    ) -> Result<TopFieldDocs> {
+        let sort_fields = sort.get_sort();
+        for shard in shard_hits {
+            for hit in &shard.score_docs {
+                match hit {
+                    ScoreDocHit::Field(f) if f.fields.len() == sort_fields.len() => {}
+                    ScoreDocHit::Field(f) => {
+                        return Err(IllegalArgument(format!(
+                            "hit has {} sort values but sort has {} fields",
+                            f.fields.len(),
+                            sort_fields.len()
+                        )));
+                    }
+                    ScoreDocHit::Score(_) => {
+                        return Err(IllegalArgument(
+                            "shard hits must be FieldDoc to be merged by sort".into(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let total_hits = shard_hits.iter().map(|s| s.total_hits).sum();
+        let max_score = shard_hits
+            .iter()
+            .map(|s| s.max_score)
+            .filter(|s| !s.is_nan())
+            .fold(f32::NAN, f32::max);
+        let score_docs = merge_hits(
+            start,
+            top_n,
+            shard_hits.iter().map(|s| s.score_docs.as_slice()),
+            |h1, h2| compare_field_docs(sort_fields, h1, h2),
+        );
+        Ok(TopFieldDocs {
+            total_hits,
+            score_docs,
+            max_score,
+            fields: sort_fields.to_vec(),
+        })
+    }
+}
+
 pub struct CollapseTopFieldDocs {
     /// The total number of hits for the query.
     pub total_hits: usize,
@@ -324,3 +485,76 @@ impl TopDocs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::sort_field::SimpleSortField;
+
+    fn score_hit(doc: DocId, score: f32) -> ScoreDocHit {
+        ScoreDocHit::Score(ScoreDoc::new(doc, score))
+    }
+
+    #[test]
+    fn test_merge_score_docs() {
+        let shard0 = TopScoreDocs::new(10, vec![score_hit(3, 5.0), score_hit(1, 2.0)]);
+        let shard1 = TopScoreDocs::new(
+            4,
+            vec![score_hit(7, 6.0), score_hit(0, 2.0), score_hit(2, 1.0)],
+        );
+
+        let merged = TopScoreDocs::merge(0, 4, &[shard0.clone(), shard1.clone()]);
+        assert_eq!(merged.total_hits, 14);
+        assert!((merged.max_score() - 6.0).abs() < f32::EPSILON);
+        let hits: Vec<_> = merged
+            .score_docs()
+            .iter()
+            .map(|h| (h.shard_index(), h.doc_id()))
+            .collect();
+        // equal scores are ordered by shard index
+        assert_eq!(hits, vec![(1, 7), (0, 3), (0, 1), (1, 0)]);
+
+        let page = TopScoreDocs::merge(3, 10, &[shard0, shard1]);
+        let hits: Vec<_> = page.score_docs().iter().map(|h| h.doc_id()).collect();
+        assert_eq!(hits, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_merge_field_docs() {
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Int,
+            true,
+        ))]);
+        let field_hit = |doc: DocId, price: i32| {
+            ScoreDocHit::Field(FieldDoc::new(doc, f32::NAN, vec![VariantValue::Int(price)]))
+        };
+        let shard = |hits: Vec<ScoreDocHit>| TopFieldDocs {
+            total_hits: hits.len(),
+            score_docs: hits,
+            max_score: f32::NAN,
+            fields: sort.get_sort().to_vec(),
+        };
+
+        let shards = vec![
+            shard(vec![field_hit(0, 30), field_hit(1, 10)]),
+            shard(vec![field_hit(5, 20), field_hit(4, 10)]),
+        ];
+        let merged = TopFieldDocs::merge(&sort, 0, 3, &shards).unwrap();
+        assert_eq!(merged.total_hits, 4);
+        let hits: Vec<_> = merged
+            .score_docs
+            .iter()
+            .map(|h| (h.shard_index(), h.doc_id()))
+            .collect();
+        assert_eq!(hits, vec![(0, 0), (1, 5), (0, 1)]);
+
+        let invalid = vec![TopFieldDocs {
+            total_hits: 1,
+            score_docs: vec![score_hit(0, 1.0)],
+            max_score: 1.0,
+            fields: vec![],
+        }];
+        assert!(TopFieldDocs::merge(&sort, 0, 3, &invalid).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::doc_values::{
+    EmptySortedNumericDocValues, NumericDocValues, SortedNumericDocValues,
+};
+use crate::core::codec::Codec;
+use crate::core::index::reader::SearchLeafReader;
+use crate::core::search::sort_field::DocValuesSource;
+use crate::core::util::{decode_latitude, decode_longitude, haversin_meters, BitsMut, DocId};
+use crate::Result;
+
+/// Per-segment double values, as produced by a `DoubleValuesSource`.
+pub trait DoubleValues: Send + Sync {
+    /// Returns the value of the given document, `None` if it has no value.
+    fn get(&mut self, doc_id: DocId) -> Result<Option<f64>>;
+}
+
+/// A source of per-document double values, computed per segment, e.g. the
+/// distance of the documents from a given point.
+pub trait DoubleValuesSource {
+    fn values<C: Codec>(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn DoubleValues>>;
+}
+
+/// The distance in meters between the points of a `LatLonDocValuesField`
+/// and an origin point, documents with several points take the closest one.
+#[derive(Clone, Debug)]
+pub struct LatLonDistanceValuesSource {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl LatLonDistanceValuesSource {
+    pub fn new(field: &str, latitude: f64, longitude: f64) -> LatLonDistanceValuesSource {
+        LatLonDistanceValuesSource {
+            field: field.to_string(),
+            latitude,
+            longitude,
+        }
+    }
+}
+
+impl DoubleValuesSource for LatLonDistanceValuesSource {
+    fn values<C: Codec>(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn DoubleValues>> {
+        let doc_values: Box<dyn SortedNumericDocValues> =
+            if reader.field_info(&self.field).is_some() {
+                reader.get_sorted_numeric_doc_values(&self.field)?
+            } else {
+                Box::new(EmptySortedNumericDocValues)
+            };
+        Ok(Box::new(LatLonDistanceValues {
+            doc_values,
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }))
+    }
+}
+
+struct LatLonDistanceValues {
+    doc_values: Box<dyn SortedNumericDocValues>,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl DoubleValues for LatLonDistanceValues {
+    fn get(&mut self, doc_id: DocId) -> Result<Option<f64>> {
+        self.doc_values.set_document(doc_id)?;
+        let mut min_distance: Option<f64> = None;
+        for i in 0..self.doc_values.count() {
+            let encoded = self.doc_values.value_at(i)?;
+            let distance = haversin_meters(
+                self.latitude,
+                self.longitude,
+                decode_latitude((encoded >> 32) as i32),
+                decode_longitude(encoded as i32),
+            );
+            min_distance = Some(min_distance.map_or(distance, |d| d.min(distance)));
+        }
+        Ok(min_distance)
+    }
+}
+
+/// Exposes the values of a `DoubleValuesSource` as `Double` sort values,
+/// documents without a value get `missing_value`.
+pub struct DoubleValuesDocValuesSource<S: DoubleValuesSource> {
+    source: S,
+    missing_value: f64,
+}
+
+impl<S: DoubleValuesSource> DoubleValuesDocValuesSource<S> {
+    pub fn new(source: S, missing_value: f64) -> Self {
+        DoubleValuesDocValuesSource {
+            source,
+            missing_value,
+        }
+    }
+}
+
+impl<S: DoubleValuesSource> DocValuesSource for DoubleValuesDocValuesSource<S> {
+    fn numeric_doc_values<C: Codec>(
+        &self,
+        reader: &SearchLeafReader<C>,
+        _field: &str,
+    ) -> Result<Box<dyn NumericDocValues>> {
+        Ok(Box::new(DoubleValuesAsNumDocValues {
+            values: self.source.values(reader)?,
+            missing_value: self.missing_value,
+        }))
+    }
+
+    fn docs_with_fields<C: Codec>(
+        &self,
+        reader: &SearchLeafReader<C>,
+        field: &str,
+    ) -> Result<Box<dyn BitsMut>> {
+        reader.get_docs_with_field(field)
+    }
+}
+
+struct DoubleValuesAsNumDocValues {
+    values: Box<dyn DoubleValues>,
+    missing_value: f64,
+}
+
+impl NumericDocValues for DoubleValuesAsNumDocValues {
+    fn get(&self, _doc_id: DocId) -> Result<i64> {
+        unreachable!()
+    }
+
+    fn get_mut(&mut self, doc_id: DocId) -> Result<i64> {
+        let value = self.values.get(doc_id)?.unwrap_or(self.missing_value);
+        Ok(value.to_bits() as i64)
+    }
+}
@@ -13,7 +13,10 @@
 
 use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::index::reader::{LeafReaderContext, SearchLeafReader};
-use crate::core::search::sort_field::{SortFieldType, SortedWrapperDocValuesSource};
+use crate::core::search::sort_field::{
+    DoubleValuesDocValuesSource, LatLonDistanceValuesSource, SortFieldType,
+    SortedWrapperDocValuesSource,
+};
 use crate::core::util::{BitsMut, DocId, VariantValue};
 use crate::Result;
 
@@ -145,6 +148,7 @@ pub enum FieldComparatorEnum {
     Doc(DocComparator),
     NumericDV(NumericDocValuesComparator<DefaultDocValuesSource>),
     SortedNumericDV(NumericDocValuesComparator<SortedWrapperDocValuesSource>),
+    Distance(NumericDocValuesComparator<DoubleValuesDocValuesSource<LatLonDistanceValuesSource>>),
 }
 
 impl FieldComparator for FieldComparatorEnum {
@@ -154,6 +158,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::NumericDV(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::Distance(c) => c.compare(slot1, slot2),
         }
     }
 
@@ -163,6 +168,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.value(slot),
             FieldComparatorEnum::NumericDV(c) => c.value(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.value(slot),
+            FieldComparatorEnum::Distance(c) => c.value(slot),
         }
     }
 
@@ -172,6 +178,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.set_bottom(slot),
             FieldComparatorEnum::NumericDV(c) => c.set_bottom(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.set_bottom(slot),
+            FieldComparatorEnum::Distance(c) => c.set_bottom(slot),
         }
     }
 
@@ -181,6 +188,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare_bottom(value),
             FieldComparatorEnum::NumericDV(c) => c.compare_bottom(value),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare_bottom(value),
+            FieldComparatorEnum::Distance(c) => c.compare_bottom(value),
         }
     }
 
@@ -190,6 +198,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.copy(slot, value),
             FieldComparatorEnum::NumericDV(c) => c.copy(slot, value),
             FieldComparatorEnum::SortedNumericDV(c) => c.copy(slot, value),
+            FieldComparatorEnum::Distance(c) => c.copy(slot, value),
         }
     }
 
@@ -202,6 +211,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::NumericDV(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::Distance(c) => c.get_information_from_reader(reader),
         }
     }
 
@@ -211,6 +221,7 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_type(),
             FieldComparatorEnum::NumericDV(c) => c.get_type(),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_type(),
+            FieldComparatorEnum::Distance(c) => c.get_type(),
         }
     }
 }
@@ -222,6 +233,7 @@ impl fmt::Display for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::NumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::SortedNumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::Distance(c) => write!(f, "FieldComparatorEnum({})", c),
         }
     }
 }
@@ -15,6 +15,10 @@ mod field_comparator;
 
 pub use self::field_comparator::*;
 
+mod double_values_source;
+
+pub use self::double_values_source::*;
+
 mod sort_field;
 
 pub use self::sort_field::*;
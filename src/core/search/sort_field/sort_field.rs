@@ -15,10 +15,13 @@ use crate::core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
 use crate::core::codec::Codec;
 use crate::core::index::reader::SearchLeafReader;
 use crate::core::search::sort_field::{
-    DefaultDocValuesSource, DocComparator, DocValuesSource, FieldComparatorEnum,
-    NumericDocValuesComparator, RelevanceComparator,
+    DefaultDocValuesSource, DocComparator, DocValuesSource, DoubleValuesDocValuesSource,
+    FieldComparatorEnum, LatLonDistanceValuesSource, NumericDocValuesComparator,
+    RelevanceComparator,
+};
+use crate::core::util::{
+    check_latitude, check_longitude, sortable_double_bits, sortable_float_bits,
 };
-use crate::core::util::{sortable_double_bits, sortable_float_bits};
 use crate::core::util::{BitsMut, DocId, VariantValue};
 
 use crate::error::Error::IllegalArgument;
@@ -62,6 +65,7 @@ pub enum SortFieldMissingValue {
 pub enum SortField {
     Simple(SimpleSortField),
     SortedNumeric(SortedNumericSortField),
+    Distance(LatLonDistanceSortField),
     // SortedSet(SortedSetSortField),
 }
 
@@ -74,6 +78,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => &s.field,
             SortField::SortedNumeric(s) => &s.raw_field.field,
+            SortField::Distance(s) => &s.field,
         }
     }
 
@@ -81,6 +86,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.field_type,
             SortField::SortedNumeric(s) => s.raw_field.field_type,
+            SortField::Distance(_) => SortFieldType::Custom,
         }
     }
 
@@ -88,6 +94,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.is_reverse,
             SortField::SortedNumeric(s) => s.raw_field.is_reverse,
+            SortField::Distance(_) => false,
         }
     }
 
@@ -95,6 +102,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.missing_value.as_ref(),
             SortField::SortedNumeric(s) => s.raw_field.missing_value.as_ref(),
+            SortField::Distance(_) => None,
         }
     }
 
@@ -102,6 +110,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.needs_scores(),
             SortField::SortedNumeric(s) => s.raw_field.needs_scores(),
+            SortField::Distance(_) => false,
         }
     }
 
@@ -113,6 +122,8 @@ impl SortField {
             SortField::SortedNumeric(s) => {
                 s.raw_field.missing_value = value;
             }
+            // docs without a point always sort last
+            SortField::Distance(_) => {}
         }
     }
 
@@ -124,6 +135,7 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.get_comparator(num_hits, missing_value),
             SortField::SortedNumeric(s) => s.get_comparator(num_hits, missing_value),
+            SortField::Distance(s) => s.get_comparator(num_hits),
         }
     }
 }
@@ -270,6 +282,59 @@ impl SortedNumericSortField {
     }
 }
 
+/// SortField for the distance of the points of a `LatLonDocValuesField` from an
+/// origin point, closest first.
+///
+/// The sort values are distances in meters, documents without a point sort last
+/// with a distance of `f64::INFINITY`.
+#[derive(Clone, Debug)]
+pub struct LatLonDistanceSortField {
+    field: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl LatLonDistanceSortField {
+    pub fn new(field: &str, latitude: f64, longitude: f64) -> Result<Self> {
+        check_latitude(latitude)?;
+        check_longitude(longitude)?;
+        Ok(LatLonDistanceSortField {
+            field: field.to_string(),
+            latitude,
+            longitude,
+        })
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn get_comparator(&self, num_hits: usize) -> FieldComparatorEnum {
+        let source = LatLonDistanceValuesSource::new(&self.field, self.latitude, self.longitude);
+        FieldComparatorEnum::Distance(NumericDocValuesComparator::new(
+            num_hits,
+            self.field.clone(),
+            SortFieldType::Double,
+            None,
+            DoubleValuesDocValuesSource::new(source, f64::INFINITY),
+        ))
+    }
+}
+
+impl PartialEq for LatLonDistanceSortField {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field
+            && self.latitude.to_bits() == other.latitude.to_bits()
+            && self.longitude.to_bits() == other.longitude.to_bits()
+    }
+}
+
+impl Eq for LatLonDistanceSortField {}
+
 pub struct SortedWrapperDocValuesSource {
     selector: SortedNumericSelectorType,
     field_type: SortFieldType,
@@ -0,0 +1,356 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{Codec, SeekStatus, TermIterator, Terms};
+use crate::core::index::reader::IndexReader;
+use crate::core::index::Term;
+use crate::core::search::spell::LevenshteinAutomaton;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// The edit distance of the furthest suggestions, `DirectSpellChecker` doesn't
+/// go beyond it.
+pub const MAX_SUPPORTED_EDITS: usize = 2;
+
+pub const DEFAULT_ACCURACY: f32 = 0.5;
+
+/// When a `DirectSpellChecker` suggests corrections of a term.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SuggestMode {
+    /// Only for terms that aren't in the index.
+    #[default]
+    WhenNotInIndex,
+    /// For any term, only suggesting terms found in more documents.
+    MorePopular,
+    /// For any term.
+    Always,
+}
+
+/// A suggested correction, with the number of documents it occurs in and its
+/// similarity to the misspelled term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuggestWord {
+    pub string: String,
+    pub freq: i32,
+    pub score: f32,
+}
+
+/// Suggests corrections of a term straight from the terms of its field,
+/// without a separate spelling index.
+///
+/// The candidates are the terms within `max_edits` of the term, found by
+/// running a `LevenshteinAutomaton` over the term dictionary. A candidate
+/// sharing `min_prefix` leading chars with the term and at an edit distance
+/// `ed` scores `1 - ed / min(len(term), len(candidate))`, those scoring under
+/// `accuracy` are dropped. Suggestions are ranked by score, then by document
+/// frequency.
+///
+/// ```rust,ignore
+/// let checker = DirectSpellChecker::default().with_accuracy(0.6);
+/// let term = Term::new("body".into(), b"lucenne".to_vec());
+/// let suggestions = checker.suggest_similar(&term, 5, &reader, SuggestMode::default())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirectSpellChecker {
+    max_edits: usize,
+    min_prefix: usize,
+    max_inspections: usize,
+    accuracy: f32,
+    threshold_frequency: f32,
+    min_query_length: usize,
+    max_query_frequency: f32,
+    lower_case_terms: bool,
+}
+
+impl Default for DirectSpellChecker {
+    fn default() -> Self {
+        DirectSpellChecker {
+            max_edits: MAX_SUPPORTED_EDITS,
+            min_prefix: 1,
+            max_inspections: 5,
+            accuracy: DEFAULT_ACCURACY,
+            threshold_frequency: 0.0,
+            min_query_length: 4,
+            max_query_frequency: 0.01,
+            lower_case_terms: true,
+        }
+    }
+}
+
+impl DirectSpellChecker {
+    /// The maximum edit distance of the suggestions, 1 or 2.
+    pub fn with_max_edits(mut self, max_edits: usize) -> Result<Self> {
+        if !(1..=MAX_SUPPORTED_EDITS).contains(&max_edits) {
+            return Err(IllegalArgument(format!(
+                "max_edits must be 1 or 2, got {}",
+                max_edits
+            )));
+        }
+        self.max_edits = max_edits;
+        Ok(self)
+    }
+
+    /// The number of leading chars the suggestions must share with the term.
+    pub fn with_min_prefix(mut self, min_prefix: usize) -> Self {
+        self.min_prefix = min_prefix;
+        self
+    }
+
+    /// Searching stops once `max_inspections` candidates per requested
+    /// suggestion are found within a single edit.
+    pub fn with_max_inspections(mut self, max_inspections: usize) -> Self {
+        self.max_inspections = max_inspections;
+        self
+    }
+
+    /// The minimum score of the suggestions, between 0 and 1.
+    pub fn with_accuracy(mut self, accuracy: f32) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    /// The minimum document frequency of the suggestions, as a number of
+    /// documents if at least 1, else as a fraction of the documents.
+    pub fn with_threshold_frequency(mut self, threshold_frequency: f32) -> Self {
+        self.threshold_frequency = threshold_frequency;
+        self
+    }
+
+    /// Terms shorter than `min_query_length` chars get no suggestions.
+    pub fn with_min_query_length(mut self, min_query_length: usize) -> Self {
+        self.min_query_length = min_query_length;
+        self
+    }
+
+    /// Terms found in more documents than `max_query_frequency`, a number of
+    /// documents if at least 1 else a fraction of the documents, are assumed
+    /// to be spelled correctly and get no suggestions.
+    pub fn with_max_query_frequency(mut self, max_query_frequency: f32) -> Self {
+        self.max_query_frequency = max_query_frequency;
+        self
+    }
+
+    /// Whether the term is lower cased first, for fields indexed lower cased.
+    pub fn with_lower_case_terms(mut self, lower_case_terms: bool) -> Self {
+        self.lower_case_terms = lower_case_terms;
+        self
+    }
+
+    /// Returns up to `num_sug` corrections of `term` from the terms of its
+    /// field in `reader`, best first.
+    pub fn suggest_similar<C: Codec>(
+        &self,
+        term: &Term,
+        num_sug: usize,
+        reader: &dyn IndexReader<Codec = C>,
+        suggest_mode: SuggestMode,
+    ) -> Result<Vec<SuggestWord>> {
+        let mut text = term.text();
+        if text.chars().count() < self.min_query_length {
+            return Ok(vec![]);
+        }
+        if self.lower_case_terms {
+            text = text.to_lowercase();
+        }
+
+        let mut doc_freq = reader.doc_freq(&Term::new(term.field.clone(), text.clone().into()))?;
+        if suggest_mode == SuggestMode::WhenNotInIndex && doc_freq > 0 {
+            return Ok(vec![]);
+        }
+        let max_doc = reader.max_doc();
+        if self.max_query_frequency >= 1.0 {
+            if doc_freq as f32 > self.max_query_frequency {
+                return Ok(vec![]);
+            }
+        } else if doc_freq > (self.max_query_frequency * max_doc as f32).ceil() as i32 {
+            return Ok(vec![]);
+        }
+
+        // the suggestions must be found in more documents than `doc_freq`
+        if suggest_mode != SuggestMode::MorePopular {
+            doc_freq = 0;
+        }
+        if self.threshold_frequency >= 1.0 {
+            doc_freq = doc_freq.max(self.threshold_frequency as i32);
+        } else if self.threshold_frequency > 0.0 {
+            doc_freq = doc_freq.max((self.threshold_frequency * max_doc as f32) as i32 - 1);
+        }
+
+        // try a single edit first, in case we get lucky
+        let inspections = num_sug * self.max_inspections;
+        let mut suggestions =
+            self.suggest_within(&term.field, &text, reader, doc_freq, 1, self.min_prefix)?;
+        if self.max_edits > 1 && suggestions.len() < inspections {
+            let min_prefix = self.min_prefix.max(self.max_edits - 1);
+            for suggestion in self.suggest_within(
+                &term.field,
+                &text,
+                reader,
+                doc_freq,
+                self.max_edits,
+                min_prefix,
+            )? {
+                if !suggestions.iter().any(|s| s.string == suggestion.string) {
+                    suggestions.push(suggestion);
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then(b.freq.cmp(&a.freq))
+                .then(a.string.cmp(&b.string))
+        });
+        suggestions.truncate(num_sug);
+        Ok(suggestions)
+    }
+
+    // The terms of `field` within `max_edits` of `text`, found in more than
+    // `doc_freq` documents and scoring at least `accuracy`.
+    fn suggest_within<C: Codec>(
+        &self,
+        field: &str,
+        text: &str,
+        reader: &dyn IndexReader<Codec = C>,
+        doc_freq: i32,
+        max_edits: usize,
+        min_prefix: usize,
+    ) -> Result<Vec<SuggestWord>> {
+        let automaton = LevenshteinAutomaton::new(text, max_edits, true);
+        let prefix: String = text.chars().take(min_prefix).collect();
+        let mut matches: HashMap<String, (usize, i32)> = HashMap::new();
+        for leaf in reader.leaves() {
+            if let Some(terms) = leaf.reader.terms(field)? {
+                let mut terms_iter = terms.iterator()?;
+                intersect(&mut terms_iter, &automaton, prefix.as_bytes(), &mut matches)?;
+            }
+        }
+
+        let length = text.chars().count();
+        let mut suggestions = Vec::with_capacity(matches.len());
+        for (string, (distance, freq)) in matches {
+            if distance == 0 || freq <= doc_freq {
+                continue;
+            }
+            let min_length = string.chars().count().min(length).max(1);
+            let score = 1.0 - distance as f32 / min_length as f32;
+            if score >= self.accuracy {
+                suggestions.push(SuggestWord {
+                    string,
+                    freq,
+                    score,
+                });
+            }
+        }
+        Ok(suggestions)
+    }
+}
+
+// Adds the terms starting with `prefix` that `automaton` accepts to `matches`
+// with their edit distance, summing their document frequencies.
+fn intersect<T: TermIterator>(
+    terms_iter: &mut T,
+    automaton: &LevenshteinAutomaton,
+    prefix: &[u8],
+    matches: &mut HashMap<String, (usize, i32)>,
+) -> Result<()> {
+    if terms_iter.seek_ceil(prefix)? == SeekStatus::End {
+        return Ok(());
+    }
+    let mut term = terms_iter.term()?.to_vec();
+    // `states[i]` is the state of the automaton after the first `i` chars,
+    // the chars shared with the previous term aren't run again
+    let mut chars: Vec<char> = vec![];
+    let mut states = vec![automaton.start()];
+    while term.starts_with(prefix) {
+        let term_chars: Vec<char> = String::from_utf8_lossy(&term).chars().collect();
+        let common = chars
+            .iter()
+            .zip(&term_chars)
+            .take_while(|(a, b)| a == b)
+            .count();
+        chars = term_chars;
+        states.truncate(common + 1);
+
+        let mut can_match = true;
+        while can_match && states.len() <= chars.len() {
+            let state = automaton.step(&states[states.len() - 1], chars[states.len() - 1]);
+            can_match = automaton.can_match(&state);
+            states.push(state);
+        }
+
+        let next = if can_match {
+            let state = &states[chars.len()];
+            if automaton.is_match(state) {
+                let freq = terms_iter.doc_freq()?;
+                let entry = matches
+                    .entry(chars.iter().collect())
+                    .or_insert((automaton.distance(state), 0));
+                entry.1 += freq;
+            }
+            terms_iter.next()?
+        } else {
+            // no term starting with the chars run so far can match, skip them all
+            chars.truncate(states.len() - 1);
+            let dead_prefix: String = chars.iter().collect();
+            match next_prefix(dead_prefix.as_bytes()) {
+                Some(target) if terms_iter.seek_ceil(&target)? != SeekStatus::End => {
+                    Some(terms_iter.term()?.to_vec())
+                }
+                _ => None,
+            }
+        };
+        match next {
+            Some(next) => term = next,
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+// The smallest byte string greater than all the ones starting with `prefix`.
+fn next_prefix(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut next = prefix.to_vec();
+    while let Some(last) = next.pop() {
+        if last < u8::MAX {
+            next.push(last + 1);
+            return Some(next);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_prefix() {
+        assert_eq!(next_prefix(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(next_prefix(&[b'a', u8::MAX]), Some(b"b".to_vec()));
+        assert_eq!(next_prefix(&[u8::MAX]), None);
+        assert_eq!(next_prefix(b""), None);
+    }
+
+    #[test]
+    fn test_invalid_max_edits() {
+        assert!(DirectSpellChecker::default().with_max_edits(0).is_err());
+        assert!(DirectSpellChecker::default().with_max_edits(3).is_err());
+        assert!(DirectSpellChecker::default().with_max_edits(2).is_ok());
+    }
+}
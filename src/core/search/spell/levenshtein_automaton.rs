@@ -0,0 +1,159 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Accepts the strings within `max_edits` insertions, deletions, substitutions
+/// and, optionally, transpositions of adjacent chars from a word.
+///
+/// The automaton is run a char at a time, a state is the last row of the edit
+/// distance matrix between the word and the chars consumed so far. Once a
+/// state can't match any more, no string starting with the consumed chars is
+/// accepted, which lets a sorted term dictionary skip them all.
+#[derive(Debug, Clone)]
+pub struct LevenshteinAutomaton {
+    word: Vec<char>,
+    max_edits: usize,
+    transpositions: bool,
+}
+
+/// A state of a `LevenshteinAutomaton`.
+#[derive(Debug, Clone)]
+pub struct LevenshteinState {
+    row: Vec<usize>,
+    // the row and the char before the last one, for transpositions
+    prev_row: Vec<usize>,
+    last_char: Option<char>,
+}
+
+impl LevenshteinAutomaton {
+    pub fn new(word: &str, max_edits: usize, transpositions: bool) -> LevenshteinAutomaton {
+        LevenshteinAutomaton {
+            word: word.chars().collect(),
+            max_edits,
+            transpositions,
+        }
+    }
+
+    pub fn max_edits(&self) -> usize {
+        self.max_edits
+    }
+
+    /// The state before any char is consumed.
+    pub fn start(&self) -> LevenshteinState {
+        LevenshteinState {
+            row: (0..=self.word.len()).map(|j| self.cap(j)).collect(),
+            prev_row: vec![],
+            last_char: None,
+        }
+    }
+
+    /// The state after consuming `c` in `state`.
+    pub fn step(&self, state: &LevenshteinState, c: char) -> LevenshteinState {
+        let row = &state.row;
+        let mut next = Vec::with_capacity(row.len());
+        next.push(self.cap(row[0] + 1));
+        for j in 1..row.len() {
+            let cost = usize::from(self.word[j - 1] != c);
+            let mut distance = (row[j] + 1).min(next[j - 1] + 1).min(row[j - 1] + cost);
+            if self.transpositions
+                && j > 1
+                && !state.prev_row.is_empty()
+                && self.word[j - 2] == c
+                && state.last_char == Some(self.word[j - 1])
+            {
+                distance = distance.min(state.prev_row[j - 2] + 1);
+            }
+            next.push(self.cap(distance));
+        }
+        LevenshteinState {
+            row: next,
+            prev_row: row.clone(),
+            last_char: Some(c),
+        }
+    }
+
+    /// Whether the chars consumed to reach `state` are accepted.
+    pub fn is_match(&self, state: &LevenshteinState) -> bool {
+        self.distance(state) <= self.max_edits
+    }
+
+    /// Whether some continuation of the chars consumed to reach `state` can be
+    /// accepted.
+    pub fn can_match(&self, state: &LevenshteinState) -> bool {
+        state.row.iter().any(|d| *d <= self.max_edits)
+    }
+
+    /// The edit distance between the word and the chars consumed to reach
+    /// `state`, any distance over `max_edits` is reported as `max_edits + 1`.
+    pub fn distance(&self, state: &LevenshteinState) -> usize {
+        state.row[self.word.len()]
+    }
+
+    /// The edit distance between the word and `text` if it is accepted.
+    pub fn run(&self, text: &str) -> Option<usize> {
+        let mut state = self.start();
+        for c in text.chars() {
+            state = self.step(&state, c);
+            if !self.can_match(&state) {
+                return None;
+            }
+        }
+        if self.is_match(&state) {
+            Some(self.distance(&state))
+        } else {
+            None
+        }
+    }
+
+    // distances over max_edits are all alike, capping them keeps states small
+    fn cap(&self, distance: usize) -> usize {
+        distance.min(self.max_edits + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let automaton = LevenshteinAutomaton::new("kitten", 2, true);
+        assert_eq!(automaton.run("kitten"), Some(0));
+        assert_eq!(automaton.run("sitten"), Some(1));
+        assert_eq!(automaton.run("kitte"), Some(1));
+        assert_eq!(automaton.run("kittens"), Some(1));
+        assert_eq!(automaton.run("sittin"), Some(2));
+        assert_eq!(automaton.run("sitting"), None);
+        assert_eq!(automaton.run(""), None);
+
+        // a swap of adjacent chars is a single edit with transpositions
+        assert_eq!(automaton.run("iktten"), Some(1));
+        let automaton = LevenshteinAutomaton::new("kitten", 2, false);
+        assert_eq!(automaton.run("iktten"), Some(2));
+
+        let automaton = LevenshteinAutomaton::new("über", 1, true);
+        assert_eq!(automaton.run("uber"), Some(1));
+    }
+
+    #[test]
+    fn test_can_match() {
+        let automaton = LevenshteinAutomaton::new("lucene", 1, true);
+        let mut state = automaton.start();
+        for c in "lx".chars() {
+            state = automaton.step(&state, c);
+        }
+        assert!(automaton.can_match(&state));
+        assert!(!automaton.is_match(&state));
+        state = automaton.step(&state, 'x');
+        assert!(!automaton.can_match(&state));
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod levenshtein_automaton;
+
+pub use self::levenshtein_automaton::{LevenshteinAutomaton, LevenshteinState};
+
+mod direct_spell_checker;
+
+pub use self::direct_spell_checker::{
+    DirectSpellChecker, SuggestMode, SuggestWord, DEFAULT_ACCURACY, MAX_SUPPORTED_EDITS,
+};
+
+mod phrase_suggester;
+
+pub use self::phrase_suggester::{
+    PhraseSuggester, PhraseSuggestion, DEFAULT_CANDIDATES_PER_TERM,
+    DEFAULT_REAL_WORD_ERROR_LIKELIHOOD,
+};
@@ -0,0 +1,420 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::codec::Codec;
+use crate::core::index::reader::IndexReader;
+use crate::core::index::Term;
+use crate::core::search::query::{BooleanQuery, PhraseQuery, Query, TermQuery};
+use crate::core::search::spell::{DirectSpellChecker, SuggestMode};
+use crate::core::search::suggest::BACKOFF_FACTOR;
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub const DEFAULT_CANDIDATES_PER_TERM: usize = 5;
+
+pub const DEFAULT_REAL_WORD_ERROR_LIKELIHOOD: f64 = 0.95;
+
+/// A corrected phrase, `collate_match` tells whether it matched the collate
+/// query when collation is enabled and pruning is on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhraseSuggestion {
+    pub text: String,
+    pub score: f64,
+    pub collate_match: Option<bool>,
+}
+
+/// Suggests corrections of a whole phrase, "did you mean" style.
+///
+/// Each term of the analyzed phrase gets candidate corrections from a
+/// `DirectSpellChecker`, and the phrases with at most `max_errors` corrected
+/// terms are ranked by a noisy channel model: the likelihood of the terms,
+/// see `with_real_word_error_likelihood`, times the probability of the
+/// phrase under a bigram language model with stupid backoff, where the
+/// unigram and bigram statistics are the number of documents of the field
+/// containing the term or the two adjacent terms.
+///
+/// Only the phrases at least `confidence` times as likely as the input are
+/// suggested. With collation, each suggestion is also run as a query
+/// requiring all its terms, and those without hits are dropped, or flagged
+/// when pruning.
+///
+/// ```rust,ignore
+/// let suggester = PhraseSuggester::new("body").with_collate(true);
+/// let suggestions = suggester.suggest("nobel prize winer", 3, &reader)?;
+/// ```
+pub struct PhraseSuggester {
+    field: String,
+    analyzer: Box<dyn Analyzer>,
+    spell_checker: DirectSpellChecker,
+    suggest_mode: SuggestMode,
+    candidates_per_term: usize,
+    max_errors: usize,
+    confidence: f64,
+    real_word_error_likelihood: f64,
+    collate: bool,
+    collate_prune: bool,
+}
+
+impl PhraseSuggester {
+    pub fn new(field: &str) -> PhraseSuggester {
+        PhraseSuggester {
+            field: field.to_string(),
+            analyzer: Box::new(WhitespaceAnalyzer),
+            spell_checker: DirectSpellChecker::default(),
+            suggest_mode: SuggestMode::default(),
+            candidates_per_term: DEFAULT_CANDIDATES_PER_TERM,
+            max_errors: 1,
+            confidence: 1.0,
+            real_word_error_likelihood: DEFAULT_REAL_WORD_ERROR_LIKELIHOOD,
+            collate: false,
+            collate_prune: false,
+        }
+    }
+
+    /// The analyzer splitting the input into the terms of the field.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// The spell checker generating the candidates of each term.
+    pub fn with_spell_checker(mut self, spell_checker: DirectSpellChecker) -> Self {
+        self.spell_checker = spell_checker;
+        self
+    }
+
+    /// Which terms get candidates, only the unknown ones by default, use
+    /// `SuggestMode::Always` to also correct real word errors.
+    pub fn with_suggest_mode(mut self, suggest_mode: SuggestMode) -> Self {
+        self.suggest_mode = suggest_mode;
+        self
+    }
+
+    /// The maximum number of candidates of each term.
+    pub fn with_candidates_per_term(mut self, candidates_per_term: usize) -> Self {
+        self.candidates_per_term = candidates_per_term;
+        self
+    }
+
+    /// The maximum number of corrected terms of a suggestion.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// How many times more likely than the input a suggestion must be, 0
+    /// suggests the best phrases whatever the input.
+    pub fn with_confidence(mut self, confidence: f64) -> Result<Self> {
+        if confidence.is_nan() || confidence < 0.0 {
+            return Err(IllegalArgument(format!(
+                "confidence must be >= 0, got {}",
+                confidence
+            )));
+        }
+        self.confidence = confidence;
+        Ok(self)
+    }
+
+    /// The likelihood that a term of the input is spelled correctly, the
+    /// likelihood of a correction being its score from the spell checker.
+    pub fn with_real_word_error_likelihood(mut self, likelihood: f64) -> Result<Self> {
+        if !(likelihood > 0.0 && likelihood < 1.0) {
+            return Err(IllegalArgument(format!(
+                "real_word_error_likelihood must be in (0, 1), got {}",
+                likelihood
+            )));
+        }
+        self.real_word_error_likelihood = likelihood;
+        Ok(self)
+    }
+
+    /// Whether the suggestions are verified to match documents.
+    pub fn with_collate(mut self, collate: bool) -> Self {
+        self.collate = collate;
+        self
+    }
+
+    /// Keeps the suggestions failing collation, with `collate_match` set.
+    pub fn with_collate_prune(mut self, collate_prune: bool) -> Self {
+        self.collate_prune = collate_prune;
+        self
+    }
+
+    /// Returns up to `num` corrections of `text` from the index of `reader`,
+    /// best first.
+    pub fn suggest<C: Codec>(
+        &self,
+        text: &str,
+        num: usize,
+        reader: &dyn IndexReader<Codec = C>,
+    ) -> Result<Vec<PhraseSuggestion>> {
+        let terms = self.analyze(text)?;
+        if terms.is_empty() || num == 0 {
+            return Ok(vec![]);
+        }
+
+        let searcher = DefaultIndexSearcher::new(reader, None);
+        let mut stats = Statistics {
+            field: &self.field,
+            searcher: &searcher,
+            unigrams: HashMap::new(),
+            bigrams: HashMap::new(),
+        };
+        let mut candidates = Vec::with_capacity(terms.len());
+        for term in &terms {
+            candidates.push(self.candidates(term, reader)?);
+        }
+
+        let mut phrases = vec![];
+        let mut path = Vec::with_capacity(terms.len());
+        self.collect(&candidates, &mut stats, &mut path, 0.0, &mut phrases)?;
+
+        // the input itself is the path without corrections
+        let original = phrases
+            .iter()
+            .find(|(p, _)| p.iter().all(|c| c.original))
+            .map(|(_, score)| *score)
+            .unwrap_or(f64::NEG_INFINITY);
+        let cutoff = original + self.confidence.ln();
+        let mut phrases: Vec<_> = phrases
+            .into_iter()
+            .filter(|(p, score)| !p.iter().all(|c| c.original) && *score >= cutoff)
+            .collect();
+        phrases.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        let mut suggestions = vec![];
+        for (path, score) in phrases {
+            if suggestions.len() == num {
+                break;
+            }
+            let words: Vec<&str> = path.iter().map(|c| c.term.as_str()).collect();
+            let collate_match = if self.collate {
+                let matches = searcher.count(self.collate_query(&words)?.as_ref())? > 0;
+                if !matches && !self.collate_prune {
+                    continue;
+                }
+                if self.collate_prune {
+                    Some(matches)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            suggestions.push(PhraseSuggestion {
+                text: words.join(" "),
+                score: score.exp(),
+                collate_match,
+            });
+        }
+        Ok(suggestions)
+    }
+
+    fn analyze(&self, text: &str) -> Result<Vec<String>> {
+        let mut token_stream = self.analyzer.token_stream(&self.field, text)?;
+        token_stream.reset()?;
+        let mut terms = vec![];
+        while token_stream.next_token()? {
+            terms.push(String::from_utf8(token_stream.token().term.clone())?);
+        }
+        token_stream.end()?;
+        Ok(terms)
+    }
+
+    // the term itself first, then its corrections
+    fn candidates<C: Codec>(
+        &self,
+        term: &str,
+        reader: &dyn IndexReader<Codec = C>,
+    ) -> Result<Vec<Candidate>> {
+        let mut candidates = vec![Candidate {
+            term: term.to_string(),
+            channel: self.real_word_error_likelihood.ln(),
+            original: true,
+        }];
+        let words = self.spell_checker.suggest_similar(
+            &Term::new(self.field.clone(), term.as_bytes().to_vec()),
+            self.candidates_per_term,
+            reader,
+            self.suggest_mode,
+        )?;
+        for word in words {
+            if word.string != term {
+                candidates.push(Candidate {
+                    term: word.string,
+                    channel: (word.score as f64).ln(),
+                    original: false,
+                });
+            }
+        }
+        Ok(candidates)
+    }
+
+    fn collect<'c, S: IndexSearcher<C>, C: Codec>(
+        &self,
+        candidates: &'c [Vec<Candidate>],
+        stats: &mut Statistics<S>,
+        path: &mut Vec<&'c Candidate>,
+        score: f64,
+        phrases: &mut Vec<(Vec<&'c Candidate>, f64)>,
+    ) -> Result<()> {
+        let pos = path.len();
+        if pos == candidates.len() {
+            phrases.push((path.clone(), score));
+            return Ok(());
+        }
+        let errors = path.iter().filter(|c| !c.original).count();
+        for candidate in &candidates[pos] {
+            if !candidate.original && errors >= self.max_errors {
+                continue;
+            }
+            let lm = match path.last() {
+                Some(prev) => stats.bigram_probability(&prev.term, &candidate.term)?,
+                None => stats.unigram_probability(&candidate.term)?,
+            };
+            path.push(candidate);
+            self.collect(
+                candidates,
+                stats,
+                path,
+                score + candidate.channel + lm.ln(),
+                phrases,
+            )?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn collate_query<C: Codec>(&self, words: &[&str]) -> Result<Box<dyn Query<C>>> {
+        if words.is_empty() {
+            return Err(IllegalArgument("can't collate an empty phrase".into()));
+        }
+        let musts = words
+            .iter()
+            .map(|w| {
+                let term = Term::new(self.field.clone(), w.as_bytes().to_vec());
+                Box::new(TermQuery::new(term, 1.0, None)) as Box<dyn Query<C>>
+            })
+            .collect();
+        BooleanQuery::build(musts, vec![], vec![], vec![], 0)
+    }
+}
+
+struct Candidate {
+    term: String,
+    // log likelihood of the edits turning this term into the input one
+    channel: f64,
+    original: bool,
+}
+
+// document frequencies of the terms and of the pairs of adjacent terms
+struct Statistics<'a, S> {
+    field: &'a str,
+    searcher: &'a S,
+    unigrams: HashMap<String, i32>,
+    bigrams: HashMap<(String, String), i32>,
+}
+
+impl<'a, S> Statistics<'a, S> {
+    fn unigram_freq<C: Codec>(&mut self, word: &str) -> Result<i32>
+    where
+        S: IndexSearcher<C>,
+    {
+        if let Some(freq) = self.unigrams.get(word) {
+            return Ok(*freq);
+        }
+        let term = Term::new(self.field.to_string(), word.as_bytes().to_vec());
+        let freq = self.searcher.reader().doc_freq(&term)?;
+        self.unigrams.insert(word.to_string(), freq);
+        Ok(freq)
+    }
+
+    // add-one smoothed, so that unknown terms don't rule a phrase out
+    fn unigram_probability<C: Codec>(&mut self, word: &str) -> Result<f64>
+    where
+        S: IndexSearcher<C>,
+    {
+        let freq = self.unigram_freq(word)?;
+        let max_doc = self.searcher.reader().max_doc();
+        Ok((freq as f64 + 1.0) / (max_doc as f64 + 1.0))
+    }
+
+    fn bigram_probability<C: Codec>(&mut self, prev: &str, word: &str) -> Result<f64>
+    where
+        S: IndexSearcher<C>,
+    {
+        let prev_freq = self.unigram_freq(prev)?;
+        if prev_freq > 0 && self.unigram_freq(word)? > 0 {
+            let key = (prev.to_string(), word.to_string());
+            let freq = match self.bigrams.get(&key) {
+                Some(freq) => *freq,
+                None => {
+                    let terms = vec![
+                        Term::new(self.field.to_string(), prev.as_bytes().to_vec()),
+                        Term::new(self.field.to_string(), word.as_bytes().to_vec()),
+                    ];
+                    let query = PhraseQuery::build(terms, 0, None, None)?;
+                    let freq = self.searcher.count(&query)?;
+                    self.bigrams.insert(key, freq);
+                    freq
+                }
+            };
+            if freq > 0 {
+                return Ok(freq as f64 / prev_freq as f64);
+            }
+        }
+        Ok(BACKOFF_FACTOR * self.unigram_probability(word)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collate_query_requires_all_terms() {
+        let suggester = PhraseSuggester::new("body");
+        let query = suggester
+            .collate_query::<crate::core::codec::CodecEnum>(&["nobel", "prize"])
+            .unwrap();
+        assert_eq!(
+            query.to_string(),
+            "BooleanQuery(must: [TermQuery(field: body, term: nobel, boost: 1), TermQuery(field: body, term: prize, boost: 1)], should: [], filters: [], must_not: [], match: 0)"
+        );
+        assert!(suggester
+            .collate_query::<crate::core::codec::CodecEnum>(&[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(PhraseSuggester::new("body").with_confidence(-1.0).is_err());
+        assert!(PhraseSuggester::new("body")
+            .with_confidence(f64::NAN)
+            .is_err());
+        assert!(PhraseSuggester::new("body").with_confidence(0.0).is_ok());
+        for likelihood in [0.0, 1.0, f64::NAN] {
+            assert!(PhraseSuggester::new("body")
+                .with_real_word_error_likelihood(likelihood)
+                .is_err());
+        }
+        assert!(PhraseSuggester::new("body")
+            .with_real_word_error_likelihood(0.95)
+            .is_ok());
+    }
+}
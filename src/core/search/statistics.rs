@@ -14,7 +14,7 @@
 use crate::core::util::DocId;
 
 /// Contains statistics for a collection (field)
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct CollectionStatistics {
     pub field: String,
     pub doc_base: DocId,
@@ -46,9 +46,31 @@ impl CollectionStatistics {
             sum_doc_freq,
         }
     }
+
+    /// Adds the statistics of the same field in another index, e.g. another
+    /// shard of a distributed index. Statistics that are unknown (`-1`) in
+    /// either index stay unknown.
+    pub fn merge(&mut self, other: &CollectionStatistics) {
+        debug_assert_eq!(self.field, other.field);
+        self.doc_base = 0;
+        self.max_doc += other.max_doc;
+        self.doc_count = sum_statistic(self.doc_count, other.doc_count);
+        self.sum_total_term_freq =
+            sum_statistic(self.sum_total_term_freq, other.sum_total_term_freq);
+        self.sum_doc_freq = sum_statistic(self.sum_doc_freq, other.sum_doc_freq);
+    }
+}
+
+fn sum_statistic(a: i64, b: i64) -> i64 {
+    if a == -1 || b == -1 {
+        -1
+    } else {
+        a + b
+    }
 }
 
 /// Contains statistics for a specific term
+#[derive(Clone, Debug)]
 pub struct TermStatistics {
     pub term: Vec<u8>,
     pub doc_freq: i64,
@@ -66,6 +88,13 @@ impl TermStatistics {
             total_term_freq,
         }
     }
+
+    /// Adds the statistics of the same term in another index.
+    pub fn merge(&mut self, other: &TermStatistics) {
+        debug_assert_eq!(self.term, other.term);
+        self.doc_freq += other.doc_freq;
+        self.total_term_freq = sum_statistic(self.total_term_freq, other.total_term_freq);
+    }
 }
 
 #[cfg(test)]
@@ -93,4 +122,27 @@ mod tests {
         assert_eq!(term_statistics.doc_freq, 1);
         assert_eq!(term_statistics.total_term_freq, 1);
     }
+
+    #[test]
+    fn test_merge_statistics() {
+        let mut stats = CollectionStatistics::new(String::from("body"), 10, 25, 10, 14, 13);
+        stats.merge(&CollectionStatistics::new(
+            String::from("body"),
+            0,
+            30,
+            20,
+            -1,
+            25,
+        ));
+        assert_eq!(stats.doc_base, 0);
+        assert_eq!(stats.max_doc, 55);
+        assert_eq!(stats.doc_count, 30);
+        assert_eq!(stats.sum_total_term_freq, -1);
+        assert_eq!(stats.sum_doc_freq, 38);
+
+        let mut term_stats = TermStatistics::new(vec![1], 3, 5);
+        term_stats.merge(&TermStatistics::new(vec![1], 2, 2));
+        assert_eq!(term_stats.doc_freq, 5);
+        assert_eq!(term_stats.total_term_freq, 7);
+    }
 }
@@ -0,0 +1,437 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, Token, WhitespaceAnalyzer};
+use crate::core::codec::CodecEnum;
+use crate::core::doc::{
+    Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField, StringField, TEXT_FIELD_TYPE,
+};
+use crate::core::index::merge::{SerialMergeScheduler, TieredMergePolicy};
+use crate::core::index::reader::{IndexReader, StandardDirectoryReader};
+use crate::core::index::writer::{IndexWriter, IndexWriterConfig};
+use crate::core::index::Term;
+use crate::core::search::collector::TopFieldCollector;
+use crate::core::search::query::{BooleanQuery, Query, TermQuery};
+use crate::core::search::sort_field::{
+    ScoreDocHit, SimpleSortField, Sort, SortField, SortFieldType,
+};
+use crate::core::search::{DefaultIndexSearcher, IndexSearcher};
+use crate::core::store::directory::Directory;
+use crate::Result;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+const TEXT_FIELD_NAME: &str = "text";
+const TEXT_GRAMS_FIELD_NAME: &str = "textgrams";
+const CONTEXTS_FIELD_NAME: &str = "contexts";
+const WEIGHT_FIELD_NAME: &str = "weight";
+
+type SuggestWriter<D> = IndexWriter<D, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+type SuggestReader<D> =
+    StandardDirectoryReader<D, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+/// A suggestion returned by `AnalyzingInfixSuggester::lookup`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InfixLookupResult {
+    pub key: String,
+    /// The key with the matched tokens wrapped in `<b>` and `</b>`, when
+    /// highlighting is enabled.
+    pub highlight_key: Option<String>,
+    pub value: i64,
+    pub contexts: Vec<String>,
+}
+
+/// Suggests the texts containing the tokens of a prefix anywhere, not only
+/// at their start, the heaviest first.
+///
+/// The suggestions are documents of an auxiliary index in `directory`, their
+/// text is indexed both as tokens and as the leading chars of each token, so
+/// that the last token of a prefix, which may be incomplete, matches any
+/// token starting with it. The other tokens of the prefix must match whole
+/// tokens. A suggestion may have contexts, a lookup with contexts only
+/// returns the suggestions having one of them.
+///
+/// Suggestions added with `add` become visible after `refresh`.
+///
+/// ```rust,ignore
+/// let mut suggester = AnalyzingInfixSuggester::new(Arc::new(directory))?;
+/// suggester.add("lend me your ear", &["shakespeare"], 8)?;
+/// suggester.add("a penny saved is a penny earned", &[], 10)?;
+/// suggester.refresh()?;
+/// let results = suggester.lookup("ear", &[], 5)?;
+/// ```
+pub struct AnalyzingInfixSuggester<D: Directory + Send + Sync + 'static> {
+    writer: SuggestWriter<D>,
+    reader: Arc<SuggestReader<D>>,
+    analyzer: Box<dyn Analyzer>,
+    all_terms_required: bool,
+    highlight: bool,
+}
+
+impl<D: Directory + Send + Sync + 'static> AnalyzingInfixSuggester<D> {
+    /// Opens the suggester of the index in `directory`, which is created if
+    /// it doesn't exist yet.
+    pub fn new(directory: Arc<D>) -> Result<AnalyzingInfixSuggester<D>> {
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+        let reader = Arc::new(writer.get_reader(true, false)?);
+        Ok(AnalyzingInfixSuggester {
+            writer,
+            reader,
+            analyzer: Box::new(WhitespaceAnalyzer),
+            all_terms_required: true,
+            highlight: true,
+        })
+    }
+
+    /// The analyzer of both the suggestions and the prefixes.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// Whether a suggestion must contain all the tokens of the prefix, or
+    /// only one of them.
+    pub fn with_all_terms_required(mut self, all_terms_required: bool) -> Self {
+        self.all_terms_required = all_terms_required;
+        self
+    }
+
+    /// Whether the lookup results have a `highlight_key`.
+    pub fn with_highlight(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    /// The number of visible suggestions.
+    pub fn count(&self) -> usize {
+        self.reader.num_docs() as usize
+    }
+
+    /// Replaces all the suggestions by the `(text, weight)` pairs, without
+    /// contexts.
+    pub fn build<S, I>(&mut self, entries: I) -> Result<()>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, i64)>,
+    {
+        self.writer.delete_all()?;
+        for (text, weight) in entries {
+            self.add(text.as_ref(), &[], weight)?;
+        }
+        self.refresh()
+    }
+
+    /// Adds a suggestion, visible after the next `refresh`.
+    pub fn add(&mut self, text: &str, contexts: &[&str], weight: i64) -> Result<()> {
+        let tokens = self.analyze(text)?;
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+
+        let text_field_type = FieldType {
+            stored: true,
+            ..TEXT_FIELD_TYPE
+        };
+        let token_stream = self.analyzer.token_stream(TEXT_FIELD_NAME, text)?;
+        doc.push(Box::new(Field::new(
+            TEXT_FIELD_NAME.into(),
+            text_field_type,
+            Some(text.into()),
+            Some(token_stream),
+        )));
+
+        // the leading chars of every token, for the incomplete last token of
+        // a prefix
+        let mut grams = vec![];
+        for token in &tokens {
+            let term = String::from_utf8_lossy(&token.term);
+            let ends = term.char_indices().map(|(i, c)| i + c.len_utf8());
+            for (i, end) in ends.enumerate() {
+                let position = if i == 0 { 1 } else { 0 };
+                grams.push(Token::with_term(
+                    term[..end].as_bytes(),
+                    position,
+                    token.start_offset,
+                    token.end_offset,
+                ));
+            }
+        }
+        if !grams.is_empty() {
+            let grams_field_type = FieldType {
+                index_options: IndexOptions::Docs,
+                omit_norms: true,
+                ..TEXT_FIELD_TYPE
+            };
+            doc.push(Box::new(Field::new_pre_tokenized(
+                TEXT_GRAMS_FIELD_NAME.into(),
+                grams_field_type,
+                grams,
+            )?));
+        }
+
+        for context in contexts {
+            doc.push(Box::new(StringField::new(
+                CONTEXTS_FIELD_NAME,
+                context,
+                true,
+            )));
+        }
+        doc.push(Box::new(NumericDocValuesField::new(
+            WEIGHT_FIELD_NAME,
+            weight,
+        )));
+        self.writer.add_document(doc)?;
+        Ok(())
+    }
+
+    /// Commits the added suggestions and makes them visible to `lookup`.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.writer.commit()?;
+        self.reader = Arc::new(self.writer.get_reader(true, false)?);
+        Ok(())
+    }
+
+    /// Returns the `num` heaviest suggestions containing the tokens of `key`,
+    /// the last one possibly as the start of a token, and one of `contexts`
+    /// if any.
+    pub fn lookup(
+        &self,
+        key: &str,
+        contexts: &[&str],
+        num: usize,
+    ) -> Result<Vec<InfixLookupResult>> {
+        let mut tokens = self.analyze(key)?;
+        let last = match tokens.pop() {
+            Some(last) if num > 0 => last,
+            _ => return Ok(vec![]),
+        };
+        // the last token is complete if the key goes on after it
+        let prefix_last = last.end_offset >= key.chars().count();
+
+        let term_query = |field: &str, term: &[u8]| -> Box<dyn Query<CodecEnum>> {
+            Box::new(TermQuery::new(
+                Term::new(field.into(), term.to_vec()),
+                1.0,
+                None,
+            ))
+        };
+        let mut clauses: Vec<Box<dyn Query<CodecEnum>>> = tokens
+            .iter()
+            .map(|t| term_query(TEXT_FIELD_NAME, &t.term))
+            .collect();
+        if prefix_last {
+            clauses.push(term_query(TEXT_GRAMS_FIELD_NAME, &last.term));
+        } else {
+            clauses.push(term_query(TEXT_FIELD_NAME, &last.term));
+        }
+        let mut filters = vec![];
+        if !contexts.is_empty() {
+            let contexts = contexts
+                .iter()
+                .map(|c| term_query(CONTEXTS_FIELD_NAME, c.as_bytes()))
+                .collect();
+            filters.push(BooleanQuery::build(vec![], contexts, vec![], vec![], 1)?);
+        }
+        let query = if self.all_terms_required {
+            BooleanQuery::build(clauses, vec![], filters, vec![], 0)?
+        } else {
+            BooleanQuery::build(vec![], clauses, filters, vec![], 1)?
+        };
+
+        let searcher = DefaultIndexSearcher::new(Arc::clone(&self.reader), None);
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            WEIGHT_FIELD_NAME.into(),
+            SortFieldType::Long,
+            true,
+        ))]);
+        let mut collector = TopFieldCollector::new(sort, num);
+        searcher.search(query.as_ref(), &mut collector)?;
+
+        let fields = [TEXT_FIELD_NAME.to_string(), CONTEXTS_FIELD_NAME.to_string()];
+        let mut results = vec![];
+        for hit in collector.top_docs().score_docs() {
+            let value = match hit {
+                ScoreDocHit::Field(f) => f.fields[0].get_long().unwrap_or(0),
+                ScoreDocHit::Score(_) => 0,
+            };
+            let doc = self.reader.document(hit.doc_id(), &fields)?;
+            let key = doc.get_string(TEXT_FIELD_NAME).unwrap_or("").to_string();
+            let highlight_key = if self.highlight {
+                Some(self.highlight_key(&key, &tokens, &last, prefix_last)?)
+            } else {
+                None
+            };
+            let contexts = doc
+                .get_values(CONTEXTS_FIELD_NAME)
+                .into_iter()
+                .filter_map(|v| v.get_string().map(str::to_string))
+                .collect();
+            results.push(InfixLookupResult {
+                key,
+                highlight_key,
+                value,
+                contexts,
+            });
+        }
+        Ok(results)
+    }
+
+    fn analyze(&self, text: &str) -> Result<Vec<Token>> {
+        let mut token_stream = self.analyzer.token_stream(TEXT_FIELD_NAME, text)?;
+        token_stream.reset()?;
+        let mut tokens = vec![];
+        while token_stream.next_token()? {
+            tokens.push(token_stream.token().clone());
+        }
+        token_stream.end()?;
+        Ok(tokens)
+    }
+
+    // wraps the tokens of `text` matching the prefix in `<b>` and `</b>`, only
+    // the matched start of a token matching the incomplete last token
+    fn highlight_key(
+        &self,
+        text: &str,
+        tokens: &[Token],
+        last: &Token,
+        prefix_last: bool,
+    ) -> Result<String> {
+        let mut terms: HashSet<&[u8]> = tokens.iter().map(|t| t.term.as_slice()).collect();
+        if !prefix_last {
+            terms.insert(&last.term);
+        }
+        let prefix_len = String::from_utf8_lossy(&last.term).chars().count();
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut highlighted = String::with_capacity(text.len() + 7);
+        let mut upto = 0;
+        for token in self.analyze(text)? {
+            let (start, end) = (token.start_offset, token.end_offset);
+            let matched_end = if terms.contains(token.term.as_slice()) {
+                end
+            } else if prefix_last && token.term.starts_with(&last.term) {
+                // the start of the token when it isn't changed by the analysis
+                if String::from_utf8_lossy(&token.term).chars().count() == end - start {
+                    start + prefix_len
+                } else {
+                    end
+                }
+            } else {
+                continue;
+            };
+            highlighted.extend(&chars[upto..start]);
+            highlighted.push_str("<b>");
+            highlighted.extend(&chars[start..matched_end]);
+            highlighted.push_str("</b>");
+            upto = matched_end;
+        }
+        highlighted.extend(&chars[upto..]);
+        Ok(highlighted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+
+    #[test]
+    fn test_infix_lookup() {
+        let directory = Arc::new(ByteBuffersDirectory::new());
+        let mut suggester = AnalyzingInfixSuggester::new(directory).unwrap();
+        suggester
+            .add("lend me your ear", &["shakespeare"], 8)
+            .unwrap();
+        suggester
+            .add("a penny saved is a penny earned", &["proverb"], 10)
+            .unwrap();
+        suggester.add("earnest ernest", &[], 3).unwrap();
+        suggester.refresh().unwrap();
+        assert_eq!(suggester.count(), 3);
+
+        let results = suggester.lookup("ear", &[], 5).unwrap();
+        let keys: Vec<_> = results
+            .iter()
+            .map(|r| (r.highlight_key.clone().unwrap(), r.value))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("a penny saved is a penny <b>ear</b>ned".to_string(), 10),
+                ("lend me your <b>ear</b>".to_string(), 8),
+                ("<b>ear</b>nest ernest".to_string(), 3),
+            ]
+        );
+        assert_eq!(results[0].contexts, vec!["proverb".to_string()]);
+
+        // complete tokens must match whole tokens
+        let results = suggester.lookup("ear ", &[], 5).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].key, "lend me your ear");
+        let results = suggester.lookup("penny ear", &[], 5).unwrap();
+        assert_eq!(
+            results[0].highlight_key.as_deref(),
+            Some("a <b>penny</b> saved is a <b>penny</b> <b>ear</b>ned")
+        );
+        assert_eq!(results.len(), 1);
+
+        // contexts filter the suggestions
+        let results = suggester.lookup("ear", &["shakespeare"], 5).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].key, "lend me your ear");
+        assert!(suggester.lookup("ear", &["none"], 5).unwrap().is_empty());
+
+        // build replaces all the suggestions
+        suggester.build(vec![("earth day", 1)]).unwrap();
+        assert_eq!(suggester.count(), 1);
+        assert_eq!(suggester.lookup("ea", &[], 5).unwrap()[0].key, "earth day");
+    }
+
+    #[test]
+    fn test_reopen() {
+        let directory = Arc::new(ByteBuffersDirectory::new());
+        {
+            let mut suggester = AnalyzingInfixSuggester::new(Arc::clone(&directory)).unwrap();
+            suggester
+                .add("the quick brown fox", &["animals"], 5)
+                .unwrap();
+            suggester.add("brownie recipes", &["food"], 9).unwrap();
+            suggester.add("quickstart guide", &[], 7).unwrap();
+            suggester.refresh().unwrap();
+        }
+
+        let suggester = AnalyzingInfixSuggester::new(directory)
+            .unwrap()
+            .with_highlight(false);
+        assert_eq!(suggester.count(), 3);
+        let results = suggester.lookup("brow", &[], 5).unwrap();
+        let keys: Vec<_> = results.iter().map(|r| (r.key.as_str(), r.value)).collect();
+        assert_eq!(
+            keys,
+            vec![("brownie recipes", 9), ("the quick brown fox", 5)]
+        );
+        assert!(results.iter().all(|r| r.highlight_key.is_none()));
+
+        let results = suggester.lookup("quick brow", &["animals"], 5).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].contexts, vec!["animals".to_string()]);
+
+        let suggester = suggester.with_all_terms_required(false);
+        let keys: Vec<_> = suggester
+            .lookup("quick brow", &[], 5)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.key)
+            .collect();
+        assert_eq!(keys, vec!["brownie recipes", "the quick brown fox"]);
+    }
+}
@@ -0,0 +1,634 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::codec::{check_footer, check_header, write_footer, write_header};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::store::IOContext;
+use crate::core::util::fst::{
+    Arc as FSTArc, FstBuilder, InputType, Output, OutputFactory, PositiveIntOutput,
+    PositiveIntOutputFactory, END_LABEL, FST,
+};
+use crate::core::util::{
+    to_ints_ref, ByteSequencesReader, IntsRefBuilder, OfflineSorter, DEFAULT_SORT_BUFFER_BYTES,
+};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// Separates the tokens of an analyzed form when `preserve_sep` is set.
+pub const SEP_LABEL: u8 = 0x1f;
+
+// ends the analyzed form in the inputs of the FST, the surface form follows
+pub(crate) const END_BYTE: u8 = 0x00;
+
+const CODEC_NAME: &str = "AnalyzingSuggester";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// A suggestion returned by `AnalyzingSuggester::lookup`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LookupResult {
+    pub key: String,
+    pub value: i64,
+}
+
+/// Suggests the texts whose analyzed form starts with the analyzed form of
+/// a prefix, the heaviest first.
+///
+/// The suggestions are built from `(text, weight)` pairs into an FST mapping
+/// the analyzed form followed by the text to `i32::MAX - weight`, so the
+/// output of an arc is the cost of the best suggestion below it and the top
+/// suggestions are found by a best first search from the end of the prefix.
+///
+/// ```rust,ignore
+/// let mut suggester = AnalyzingSuggester::default();
+/// suggester.build(vec![("ghost chrome", 10), ("ghost town", 5)])?;
+/// let results = suggester.lookup("ghost", 2)?;
+/// suggester.store(&directory, "suggest.fst")?;
+/// ```
+pub struct AnalyzingSuggester {
+    index_analyzer: Box<dyn Analyzer>,
+    query_analyzer: Box<dyn Analyzer>,
+    exact_first: bool,
+    preserve_sep: bool,
+    fst: Option<FST<PositiveIntOutputFactory>>,
+    count: usize,
+    sort_buffer_bytes: usize,
+}
+
+impl Default for AnalyzingSuggester {
+    fn default() -> Self {
+        AnalyzingSuggester {
+            index_analyzer: Box::new(WhitespaceAnalyzer),
+            query_analyzer: Box::new(WhitespaceAnalyzer),
+            exact_first: true,
+            preserve_sep: true,
+            fst: None,
+            count: 0,
+            sort_buffer_bytes: DEFAULT_SORT_BUFFER_BYTES,
+        }
+    }
+}
+
+impl AnalyzingSuggester {
+    /// The analyzer of both the suggestions and the prefixes.
+    pub fn with_analyzer<A: Analyzer + Clone + 'static>(mut self, analyzer: A) -> Self {
+        self.index_analyzer = Box::new(analyzer.clone());
+        self.query_analyzer = Box::new(analyzer);
+        self
+    }
+
+    /// The analyzer of the suggestions.
+    pub fn with_index_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.index_analyzer = analyzer;
+        self
+    }
+
+    /// The analyzer of the prefixes.
+    pub fn with_query_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.query_analyzer = analyzer;
+        self
+    }
+
+    /// Whether the suggestions whose analyzed form is exactly the one of the
+    /// prefix come first, whatever their weight.
+    pub fn with_exact_first(mut self, exact_first: bool) -> Self {
+        self.exact_first = exact_first;
+        self
+    }
+
+    /// Whether the boundaries between tokens must match, when unset
+    /// "ghost chrome" is suggested for "ghostc".
+    pub fn with_preserve_sep(mut self, preserve_sep: bool) -> Self {
+        self.preserve_sep = preserve_sep;
+        self
+    }
+
+    /// The RAM used to sort a partition of the suggestions by `build_offline`.
+    pub fn with_sort_buffer_bytes(mut self, sort_buffer_bytes: usize) -> Self {
+        self.sort_buffer_bytes = sort_buffer_bytes;
+        self
+    }
+
+    /// The number of suggestions built or loaded.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Builds the suggestions from `(text, weight)` pairs, replacing the
+    /// previous ones. A weight must be in `0..=i32::MAX`, a text appearing
+    /// several times keeps its highest weight.
+    pub fn build<S, I>(&mut self, entries: I) -> Result<()>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, i64)>,
+    {
+        let mut inputs: BTreeMap<Vec<u8>, i64> = BTreeMap::new();
+        for (text, weight) in entries {
+            if let Some(input) = self.fst_input(text.as_ref(), weight)? {
+                let max_weight = inputs.entry(input).or_insert(weight);
+                *max_weight = (*max_weight).max(weight);
+            }
+        }
+
+        self.count = inputs.len();
+        if inputs.is_empty() {
+            self.fst = None;
+            return Ok(());
+        }
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+        builder.init();
+        for (input, weight) in inputs {
+            builder.add(
+                to_ints_ref(&input, &mut scratch_ints_ref),
+                PositiveIntOutput::new(encode_weight(weight)),
+            )?;
+        }
+        self.fst = builder.finish()?;
+        Ok(())
+    }
+
+    /// Like `build`, but the suggestions are sorted by an `OfflineSorter` in
+    /// temp files of `directory`, so only about `sort_buffer_bytes` of them
+    /// are held in RAM at once.
+    pub fn build_offline<D, S, I>(&mut self, directory: &D, entries: I) -> Result<()>
+    where
+        D: Directory,
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, i64)>,
+    {
+        // the records are the FST input followed by the weight
+        let sorter = OfflineSorter::new(directory, "suggester", |a: &[u8], b: &[u8]| {
+            a[..a.len() - 4].cmp(&b[..b.len() - 4])
+        })
+        .with_buffer_bytes(self.sort_buffer_bytes);
+        let mut writer = sorter.input_writer()?;
+        let mut temp_files = vec![writer.name().to_string()];
+        let res = (|| {
+            for (text, weight) in entries {
+                if let Some(mut input) = self.fst_input(text.as_ref(), weight)? {
+                    input.extend_from_slice(&(weight as u32).to_be_bytes());
+                    writer.write(&input)?;
+                }
+            }
+            let sorted = sorter.sort(&writer.finish()?)?;
+            temp_files.push(sorted.clone());
+            self.build_sorted(&mut sorter.reader(&sorted)?)
+        })();
+        for name in &temp_files {
+            let deleted = directory.delete_file(name);
+            if res.is_ok() {
+                deleted?;
+            }
+        }
+        res
+    }
+
+    // builds the FST from the records of `build_offline`, sorted by input
+    fn build_sorted(&mut self, reader: &mut ByteSequencesReader) -> Result<()> {
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+        builder.init();
+        let mut count = 0;
+        let mut add = |input: &[u8], weight: i64| {
+            count += 1;
+            builder.add(
+                to_ints_ref(input, &mut scratch_ints_ref),
+                PositiveIntOutput::new(encode_weight(weight)),
+            )
+        };
+        let mut last: Option<(Vec<u8>, i64)> = None;
+        while reader.next()? {
+            let record = reader.value();
+            let (input, weight) = record.split_at(record.len() - 4);
+            let weight = i64::from(u32::from_be_bytes(weight.try_into().unwrap()));
+            if let Some((ref last_input, ref mut max_weight)) = last {
+                if last_input.as_slice() == input {
+                    *max_weight = (*max_weight).max(weight);
+                    continue;
+                }
+            }
+            if let Some((input, weight)) = last.replace((input.to_vec(), weight)) {
+                add(&input, weight)?;
+            }
+        }
+        if let Some((input, weight)) = last {
+            add(&input, weight)?;
+        }
+        self.count = count;
+        self.fst = if count > 0 { builder.finish()? } else { None };
+        Ok(())
+    }
+
+    // the input of `text` in the FST, its analyzed form and the text, or
+    // `None` if the analyzed form is empty
+    fn fst_input(&self, text: &str, weight: i64) -> Result<Option<Vec<u8>>> {
+        if weight < 0 || weight > i64::from(i32::MAX) {
+            return Err(IllegalArgument(format!(
+                "weight of '{}' must be in 0..={}, got {}",
+                text,
+                i32::MAX,
+                weight
+            )));
+        }
+        let mut input = self.analyze(self.index_analyzer.as_ref(), text)?;
+        if input.is_empty() {
+            return Ok(None);
+        }
+        input.push(END_BYTE);
+        input.extend_from_slice(text.as_bytes());
+        Ok(Some(input))
+    }
+
+    /// Returns the `num` heaviest suggestions whose analyzed form starts with
+    /// the one of `key`.
+    pub fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(vec![]),
+        };
+        if num == 0 {
+            return Ok(vec![]);
+        }
+        let prefix = self.analyze(self.query_analyzer.as_ref(), key)?;
+        let starts = prefix_path(fst, FSTPath::start(fst), &prefix)?
+            .into_iter()
+            .collect();
+        self.lookup_paths(fst, &prefix, starts, num)
+    }
+
+    // the `num` heaviest suggestions below `starts`, preceded by the ones
+    // whose analyzed form is `prefix` if `exact_first` is set
+    pub(crate) fn lookup_paths(
+        &self,
+        fst: &FST<PositiveIntOutputFactory>,
+        prefix: &[u8],
+        starts: Vec<FSTPath>,
+        num: usize,
+    ) -> Result<Vec<LookupResult>> {
+        let mut results = vec![];
+        if self.exact_first {
+            let mut exact = prefix.to_vec();
+            exact.push(END_BYTE);
+            if let Some(end) = prefix_path(fst, FSTPath::start(fst), &exact)? {
+                results = top_n(fst, vec![end], num, None)?;
+            }
+        }
+        let exact = results.len();
+        if exact < num && !starts.is_empty() {
+            for result in top_n(fst, starts, num + exact, None)? {
+                if results.len() == num {
+                    break;
+                }
+                if !results[..exact].contains(&result) {
+                    results.push(result);
+                }
+            }
+        }
+        results.into_iter().map(to_lookup_result).collect()
+    }
+
+    pub(crate) fn fst(&self) -> Option<&FST<PositiveIntOutputFactory>> {
+        self.fst.as_ref()
+    }
+
+    pub(crate) fn analyze_query(&self, key: &str) -> Result<Vec<u8>> {
+        self.analyze(self.query_analyzer.as_ref(), key)
+    }
+
+    /// Writes the suggestions to the file `name` of `directory`.
+    pub fn store<D: Directory>(&self, directory: &D, name: &str) -> Result<()> {
+        let mut output = directory.create_output(name, &IOContext::Default)?;
+        write_header(&mut output, CODEC_NAME, VERSION_CURRENT)?;
+        output.write_vlong(self.count as i64)?;
+        match self.fst {
+            Some(ref fst) => {
+                output.write_byte(1)?;
+                fst.save(&mut output)?;
+            }
+            None => output.write_byte(0)?,
+        }
+        write_footer(&mut output)
+    }
+
+    /// Reads the suggestions written by `store` to the file `name` of
+    /// `directory`, replacing the current ones. The analyzers and options
+    /// must be the ones of the suggester which stored them.
+    pub fn load<D: Directory>(&mut self, directory: &D, name: &str) -> Result<()> {
+        let mut input = directory.open_checksum_input(name, &IOContext::Default)?;
+        check_header(&mut input, CODEC_NAME, VERSION_START, VERSION_CURRENT)?;
+        let count = input.read_vlong()?;
+        let fst = match input.read_byte()? {
+            0 => None,
+            1 => Some(FST::from_input(
+                &mut input,
+                PositiveIntOutputFactory::new(),
+            )?),
+            b => {
+                return Err(CorruptIndex(format!(
+                    "invalid suggester file '{}': unexpected byte {}",
+                    name, b
+                )));
+            }
+        };
+        check_footer(&mut input)?;
+        self.count = count as usize;
+        self.fst = fst;
+        Ok(())
+    }
+
+    // the tokens of `text`, joined by `SEP_LABEL` when `preserve_sep` is set
+    fn analyze(&self, analyzer: &dyn Analyzer, text: &str) -> Result<Vec<u8>> {
+        let mut token_stream = analyzer.token_stream("", text)?;
+        token_stream.reset()?;
+        let mut analyzed = vec![];
+        let mut first = true;
+        while token_stream.next_token()? {
+            let term = &token_stream.token().term;
+            if term.contains(&SEP_LABEL) || term.contains(&END_BYTE) {
+                return Err(IllegalArgument(format!(
+                    "token {:?} of '{}' contains a reserved byte",
+                    term, text
+                )));
+            }
+            if !first && self.preserve_sep {
+                analyzed.push(SEP_LABEL);
+            }
+            analyzed.extend_from_slice(term);
+            first = false;
+        }
+        token_stream.end()?;
+        Ok(analyzed)
+    }
+}
+
+fn encode_weight(weight: i64) -> i64 {
+    i64::from(i32::MAX) - weight
+}
+
+fn to_lookup_result((input, cost): (Vec<u8>, i64)) -> Result<LookupResult> {
+    let end = match input.iter().position(|b| *b == END_BYTE) {
+        Some(end) => end,
+        None => return Err(CorruptIndex("invalid suggester FST".into())),
+    };
+    let key = match String::from_utf8(input[end + 1..].to_vec()) {
+        Ok(key) => key,
+        Err(e) => return Err(CorruptIndex(format!("invalid suggester FST: {}", e))),
+    };
+    Ok(LookupResult {
+        key,
+        value: encode_weight(cost),
+    })
+}
+
+// a path from the root of the FST, ordered by cost then input
+pub(crate) struct FSTPath {
+    pub(crate) cost: PositiveIntOutput,
+    pub(crate) input: Vec<u8>,
+    pub(crate) arc: FSTArc<PositiveIntOutput>,
+}
+
+impl FSTPath {
+    pub(crate) fn new(
+        cost: PositiveIntOutput,
+        input: Vec<u8>,
+        arc: FSTArc<PositiveIntOutput>,
+    ) -> FSTPath {
+        FSTPath { cost, input, arc }
+    }
+
+    pub(crate) fn start(fst: &FST<PositiveIntOutputFactory>) -> FSTPath {
+        FSTPath::new(fst.outputs().empty(), vec![], fst.root_arc())
+    }
+
+    // the path following `arc` from this one
+    pub(crate) fn follow(
+        &self,
+        fst: &FST<PositiveIntOutputFactory>,
+        arc: FSTArc<PositiveIntOutput>,
+    ) -> FSTPath {
+        let cost = match arc.output {
+            Some(ref output) => fst.outputs().add(&self.cost, output),
+            None => self.cost,
+        };
+        let mut input = self.input.clone();
+        if arc.label != END_LABEL {
+            input.push(arc.label as u8);
+        }
+        FSTPath::new(cost, input, arc)
+    }
+}
+
+impl PartialEq for FSTPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost && self.input == other.input
+    }
+}
+
+impl Eq for FSTPath {}
+
+impl PartialOrd for FSTPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FSTPath {
+    // reversed, the cheapest path is the greatest for the `BinaryHeap`
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .value()
+            .cmp(&self.cost.value())
+            .then_with(|| other.input.cmp(&self.input))
+    }
+}
+
+/// Follows `input` from `path`, returns `None` if the FST has no such path.
+pub(crate) fn prefix_path(
+    fst: &FST<PositiveIntOutputFactory>,
+    path: FSTPath,
+    input: &[u8],
+) -> Result<Option<FSTPath>> {
+    let mut reader = fst.bytes_reader();
+    let mut path = path;
+    for label in input {
+        path = match fst.find_target_arc(i32::from(*label), &path.arc, &mut reader)? {
+            Some(arc) => path.follow(fst, arc),
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(path))
+}
+
+/// Returns the `num` cheapest complete inputs below `starts`, with their
+/// costs, cheapest first. The arcs labeled `excluded` aren't followed.
+///
+/// The output of an arc being the least cost of the inputs through it, the
+/// cost of a path never decreases as it grows, so the complete inputs come
+/// out of a best first search in order.
+pub(crate) fn top_n(
+    fst: &FST<PositiveIntOutputFactory>,
+    starts: Vec<FSTPath>,
+    num: usize,
+    excluded: Option<u8>,
+) -> Result<Vec<(Vec<u8>, i64)>> {
+    let mut reader = fst.bytes_reader();
+    let mut queue: BinaryHeap<FSTPath> = starts.into_iter().collect();
+    let mut results = vec![];
+    while let Some(path) = queue.pop() {
+        if results.len() == num {
+            break;
+        }
+        if path.arc.label == END_LABEL {
+            results.push((path.input, path.cost.value()));
+            continue;
+        }
+        let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+        loop {
+            let last = arc.is_last();
+            if excluded.is_none_or(|label| arc.label != i32::from(label)) {
+                queue.push(path.follow(fst, arc.clone()));
+            }
+            if last {
+                break;
+            }
+            fst.read_next_arc(&mut arc, &mut reader)?;
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::suggest::FuzzySuggester;
+    use crate::core::store::directory::ByteBuffersDirectory;
+
+    #[test]
+    fn test_lookup() {
+        let mut suggester = AnalyzingSuggester::default();
+        suggester
+            .build(vec![
+                ("ghost chrome", 10),
+                ("ghost town", 50),
+                ("ghostbusters", 20),
+                ("ghost", 1),
+                ("chrome", 100),
+                ("ghost town", 5),
+            ])
+            .unwrap();
+        assert_eq!(suggester.count(), 5);
+
+        let keys = |results: Vec<LookupResult>| {
+            results
+                .into_iter()
+                .map(|r| (r.key, r.value))
+                .collect::<Vec<_>>()
+        };
+        // the exact match comes first, then by weight
+        assert_eq!(
+            keys(suggester.lookup("ghost", 3).unwrap()),
+            vec![
+                ("ghost".to_string(), 1),
+                ("ghost town".to_string(), 50),
+                ("ghostbusters".to_string(), 20),
+            ]
+        );
+        assert_eq!(
+            keys(suggester.lookup("ghost c", 5).unwrap()),
+            vec![("ghost chrome".to_string(), 10)]
+        );
+        assert!(suggester.lookup("ghostc", 5).unwrap().is_empty());
+        assert!(suggester.lookup("xyz", 5).unwrap().is_empty());
+
+        let mut suggester = AnalyzingSuggester::default()
+            .with_exact_first(false)
+            .with_preserve_sep(false);
+        suggester
+            .build(vec![
+                ("ghost chrome", 10),
+                ("ghost", 1),
+                ("ghostbusters", 20),
+            ])
+            .unwrap();
+        assert_eq!(
+            keys(suggester.lookup("ghostc", 5).unwrap()),
+            vec![("ghost chrome".to_string(), 10)]
+        );
+        assert_eq!(
+            keys(suggester.lookup("ghost", 2).unwrap()),
+            vec![
+                ("ghostbusters".to_string(), 20),
+                ("ghost chrome".to_string(), 10),
+            ]
+        );
+
+        assert!(suggester.build(vec![("negative", -1)]).is_err());
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let directory = ByteBuffersDirectory::new();
+        let mut suggester = AnalyzingSuggester::default();
+        suggester
+            .build(vec![
+                ("apache lucene", 30),
+                ("apache solr", 20),
+                ("apache kafka", 50),
+                ("lucene in action", 10),
+            ])
+            .unwrap();
+        suggester.store(&directory, "suggest.fst").unwrap();
+
+        let mut loaded = AnalyzingSuggester::default();
+        loaded.load(&directory, "suggest.fst").unwrap();
+        assert_eq!(loaded.count(), 4);
+        let results = loaded.lookup("apache", 2).unwrap();
+        assert_eq!(
+            results,
+            vec![
+                LookupResult {
+                    key: "apache kafka".into(),
+                    value: 50,
+                },
+                LookupResult {
+                    key: "apache lucene".into(),
+                    value: 30,
+                },
+            ]
+        );
+        assert_eq!(results, suggester.lookup("apache", 2).unwrap());
+        assert_eq!(
+            loaded.lookup("lucene i", 5).unwrap()[0].key,
+            "lucene in action"
+        );
+
+        let mut fuzzy = FuzzySuggester::default();
+        fuzzy.load(&directory, "suggest.fst").unwrap();
+        assert_eq!(fuzzy.lookup("apahce", 2).unwrap(), results);
+        assert!(loaded.lookup("apahce", 2).unwrap().is_empty());
+
+        let mut empty = AnalyzingSuggester::default();
+        empty.build(Vec::<(&str, i64)>::new()).unwrap();
+        empty.store(&directory, "empty.fst").unwrap();
+        loaded.load(&directory, "empty.fst").unwrap();
+        assert_eq!(loaded.count(), 0);
+        assert!(loaded.lookup("apache", 2).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::analysis::{Analyzer, WhitespaceAnalyzer};
+use crate::core::index::reader::IndexReader;
+use crate::core::search::suggest::analyzing_suggester::{prefix_path, top_n, FSTPath};
+use crate::core::store::directory::Directory;
+use crate::core::util::fst::{
+    FstBuilder, InputType, Output, PositiveIntOutput, PositiveIntOutputFactory, FST,
+};
+use crate::core::util::{
+    to_ints_ref, ByteSequencesReader, IntsRefBuilder, OfflineSorter, DEFAULT_SORT_BUFFER_BYTES,
+};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+/// The default order of the n-grams of a `FreeTextSuggester`, bigrams.
+pub const DEFAULT_GRAMS: usize = 2;
+
+/// The weight of the score of an n-gram of order `n - 1` relative to the one
+/// of order `n`, when backing off.
+pub const BACKOFF_FACTOR: f64 = 0.4;
+
+/// Separates the tokens of an n-gram in the FST.
+const SEP_BYTE: u8 = 0x1e;
+
+/// A prediction of `FreeTextSuggester::lookup`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FreeTextSuggestion {
+    /// The n-gram ending with the predicted token, its tokens separated by
+    /// spaces.
+    pub key: String,
+    pub score: f64,
+}
+
+/// Predicts the next token of a text from the n-grams of a corpus, e.g. as a
+/// fallback when no suggestion starts with the text.
+///
+/// Every n-gram of order `1..=grams` of the corpus is counted. The last token
+/// of a lookup key is completed, or the token after it is predicted if the
+/// key ends after it, from the `grams - 1` tokens before it. The score of a
+/// prediction is its count divided by the count of its context, backing off
+/// to shorter contexts ("stupid backoff") with a score multiplied by
+/// `BACKOFF_FACTOR` for each token dropped.
+///
+/// ```rust,ignore
+/// let mut suggester = FreeTextSuggester::default();
+/// suggester.build(vec!["foo bar baz", "foo bar", "foo bad"])?;
+/// let suggestions = suggester.lookup("foo b", 3)?;
+/// ```
+pub struct FreeTextSuggester {
+    analyzer: Box<dyn Analyzer>,
+    grams: usize,
+    fst: Option<FST<PositiveIntOutputFactory>>,
+    total_tokens: i64,
+    sort_buffer_bytes: usize,
+}
+
+impl Default for FreeTextSuggester {
+    fn default() -> Self {
+        FreeTextSuggester {
+            analyzer: Box::new(WhitespaceAnalyzer),
+            grams: DEFAULT_GRAMS,
+            fst: None,
+            total_tokens: 0,
+            sort_buffer_bytes: DEFAULT_SORT_BUFFER_BYTES,
+        }
+    }
+}
+
+impl FreeTextSuggester {
+    /// The analyzer of both the corpus and the lookup keys.
+    pub fn with_analyzer(mut self, analyzer: Box<dyn Analyzer>) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// The maximum order of the n-grams, at least 1.
+    pub fn with_grams(mut self, grams: usize) -> Result<Self> {
+        if grams == 0 {
+            return Err(IllegalArgument("grams must be at least 1".into()));
+        }
+        self.grams = grams;
+        Ok(self)
+    }
+
+    /// The RAM used to sort a partition of the n-grams by `build_offline`.
+    pub fn with_sort_buffer_bytes(mut self, sort_buffer_bytes: usize) -> Self {
+        self.sort_buffer_bytes = sort_buffer_bytes;
+        self
+    }
+
+    /// The number of tokens of the corpus.
+    pub fn total_tokens(&self) -> i64 {
+        self.total_tokens
+    }
+
+    /// Builds the model from the texts of a corpus, replacing the previous
+    /// one.
+    pub fn build<S, I>(&mut self, texts: I) -> Result<()>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+    {
+        let mut counts: BTreeMap<Vec<u8>, i64> = BTreeMap::new();
+        let mut total_tokens = 0;
+        for text in texts {
+            total_tokens += self.grams_of(text.as_ref(), |gram| {
+                *counts.entry(gram).or_insert(0) += 1;
+                Ok(())
+            })?;
+        }
+
+        self.total_tokens = total_tokens;
+        if counts.is_empty() {
+            self.fst = None;
+            return Ok(());
+        }
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+        builder.init();
+        for (gram, count) in counts {
+            builder.add(
+                to_ints_ref(&gram, &mut scratch_ints_ref),
+                PositiveIntOutput::new(encode_count(count)),
+            )?;
+        }
+        self.fst = builder.finish()?;
+        Ok(())
+    }
+
+    /// Like `build`, but the n-grams are sorted by an `OfflineSorter` in
+    /// temp files of `directory` to be counted, so only about
+    /// `sort_buffer_bytes` of them are held in RAM at once.
+    pub fn build_offline<D, S, I>(&mut self, directory: &D, texts: I) -> Result<()>
+    where
+        D: Directory,
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+    {
+        let sorter = OfflineSorter::new(directory, "free_text", |a: &[u8], b: &[u8]| a.cmp(b))
+            .with_buffer_bytes(self.sort_buffer_bytes);
+        let mut writer = sorter.input_writer()?;
+        let mut temp_files = vec![writer.name().to_string()];
+        let res = (|| {
+            let mut total_tokens = 0;
+            for text in texts {
+                total_tokens += self.grams_of(text.as_ref(), |gram| writer.write(&gram))?;
+            }
+            let sorted = sorter.sort(&writer.finish()?)?;
+            temp_files.push(sorted.clone());
+            self.build_sorted(&mut sorter.reader(&sorted)?)?;
+            self.total_tokens = total_tokens;
+            Ok(())
+        })();
+        for name in &temp_files {
+            let deleted = directory.delete_file(name);
+            if res.is_ok() {
+                deleted?;
+            }
+        }
+        res
+    }
+
+    // builds the FST from the n-grams of `build_offline`, sorted so that the
+    // occurrences of an n-gram are next to each other
+    fn build_sorted(&mut self, reader: &mut ByteSequencesReader) -> Result<()> {
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, PositiveIntOutputFactory::new());
+        builder.init();
+        let mut empty = true;
+        let mut add = |gram: &[u8], count: i64| {
+            empty = false;
+            builder.add(
+                to_ints_ref(gram, &mut scratch_ints_ref),
+                PositiveIntOutput::new(encode_count(count)),
+            )
+        };
+        let mut last: Option<(Vec<u8>, i64)> = None;
+        while reader.next()? {
+            let gram = reader.value();
+            if let Some((ref last_gram, ref mut count)) = last {
+                if last_gram.as_slice() == gram {
+                    *count += 1;
+                    continue;
+                }
+            }
+            if let Some((gram, count)) = last.replace((gram.to_vec(), 1)) {
+                add(&gram, count)?;
+            }
+        }
+        if let Some((gram, count)) = last {
+            add(&gram, count)?;
+        }
+        self.fst = if empty { None } else { builder.finish()? };
+        Ok(())
+    }
+
+    // passes the n-grams of `text` to `f`, returning its number of tokens
+    fn grams_of(&self, text: &str, mut f: impl FnMut(Vec<u8>) -> Result<()>) -> Result<i64> {
+        let tokens = self.analyze(text)?;
+        for start in 0..tokens.len() {
+            for end in start + 1..=tokens.len().min(start + self.grams) {
+                f(join(&tokens[start..end]))?;
+            }
+        }
+        Ok(tokens.len() as i64)
+    }
+
+    /// Builds the model from the stored values of `field` in the live docs
+    /// of `reader`.
+    pub fn build_from_reader<R: IndexReader + ?Sized>(
+        &mut self,
+        reader: &R,
+        field: &str,
+    ) -> Result<()> {
+        let fields = [field.to_string()];
+        let mut texts = vec![];
+        for leaf in reader.leaves() {
+            let live_docs = leaf.reader.live_docs();
+            for doc in 0..leaf.reader.max_doc() {
+                if !live_docs.get(doc as usize) {
+                    continue;
+                }
+                let document = reader.document(leaf.doc_base() + doc, &fields)?;
+                for value in document.get_values(field) {
+                    if let Some(text) = value.get_string() {
+                        texts.push(text.to_string());
+                    }
+                }
+            }
+        }
+        self.build(texts)
+    }
+
+    /// Returns the `num` most likely completions of the last token of `key`,
+    /// or predictions of the next token if `key` ends after its last token.
+    pub fn lookup(&self, key: &str, num: usize) -> Result<Vec<FreeTextSuggestion>> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(vec![]),
+        };
+        let mut tokens = self.analyze(key)?;
+        if num == 0 || tokens.is_empty() {
+            return Ok(vec![]);
+        }
+        let ends_with_token = key.chars().last().is_some_and(|c| !c.is_whitespace());
+        let last = if ends_with_token {
+            tokens.pop().unwrap()
+        } else {
+            vec![]
+        };
+
+        let mut suggestions = vec![];
+        // the tokens predicted by a longer context
+        let mut seen: HashSet<Vec<u8>> = HashSet::new();
+        let mut backoff = 1.0;
+        for order in (1..=self.grams).rev() {
+            if tokens.len() < order - 1 {
+                continue;
+            }
+            let context = &tokens[tokens.len() + 1 - order..];
+            let context_count = if context.is_empty() {
+                self.total_tokens
+            } else {
+                match fst.get(&join(context))? {
+                    Some(cost) => decode_count(cost.value()),
+                    None => {
+                        backoff *= BACKOFF_FACTOR;
+                        continue;
+                    }
+                }
+            };
+            let mut prefix = context.iter().fold(vec![], |mut prefix, token| {
+                prefix.extend_from_slice(token);
+                prefix.push(SEP_BYTE);
+                prefix
+            });
+            prefix.extend_from_slice(&last);
+
+            if let Some(start) = prefix_path(fst, FSTPath::start(fst), &prefix)? {
+                // the separator leads to the longer n-grams
+                for (gram, cost) in top_n(fst, vec![start], num + seen.len(), Some(SEP_BYTE))? {
+                    let token = gram[prefix.len() - last.len()..].to_vec();
+                    if token.is_empty() || !seen.insert(token) {
+                        continue;
+                    }
+                    let count = decode_count(cost);
+                    suggestions.push(FreeTextSuggestion {
+                        key: String::from_utf8_lossy(&gram).replace(SEP_BYTE as char, " "),
+                        score: backoff * count as f64 / context_count as f64,
+                    });
+                }
+            }
+            backoff *= BACKOFF_FACTOR;
+        }
+
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        suggestions.truncate(num);
+        Ok(suggestions)
+    }
+
+    fn analyze(&self, text: &str) -> Result<Vec<Vec<u8>>> {
+        let mut token_stream = self.analyzer.token_stream("", text)?;
+        token_stream.reset()?;
+        let mut tokens = vec![];
+        while token_stream.next_token()? {
+            let term = &token_stream.token().term;
+            if term.contains(&SEP_BYTE) {
+                return Err(IllegalArgument(format!(
+                    "token {:?} of '{}' contains the reserved byte {:#x}",
+                    term, text, SEP_BYTE
+                )));
+            }
+            tokens.push(term.clone());
+        }
+        token_stream.end()?;
+        Ok(tokens)
+    }
+}
+
+fn join(tokens: &[Vec<u8>]) -> Vec<u8> {
+    tokens.join(&SEP_BYTE)
+}
+
+fn encode_count(count: i64) -> i64 {
+    i64::MAX - count
+}
+
+fn decode_count(cost: i64) -> i64 {
+    i64::MAX - cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestions(suggester: &FreeTextSuggester, key: &str, num: usize) -> Vec<String> {
+        suggester
+            .lookup(key, num)
+            .unwrap()
+            .into_iter()
+            .map(|s| format!("{}/{:.2}", s.key, s.score))
+            .collect()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut suggester = FreeTextSuggester::default();
+        suggester
+            .build(vec!["foo bar baz blah", "boo foo bar foo bee"])
+            .unwrap();
+        assert_eq!(suggester.total_tokens(), 9);
+
+        // "foo" is followed by "bar" twice and "bee" once, then the unigrams
+        // starting with "b" back off
+        assert_eq!(
+            suggestions(&suggester, "foo b", 5),
+            vec![
+                "foo bar/0.67",
+                "foo bee/0.33",
+                "baz/0.04",
+                "blah/0.04",
+                "boo/0.04",
+            ]
+        );
+        // predicts the next token
+        assert_eq!(
+            suggestions(&suggester, "bar ", 2),
+            vec!["bar baz/0.50", "bar foo/0.50"]
+        );
+        // an unknown context backs off to the unigrams
+        assert_eq!(suggestions(&suggester, "xyz bl", 5), vec!["blah/0.04"]);
+        assert!(suggestions(&suggester, "xyz q", 5).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_grams() {
+        assert!(FreeTextSuggester::default().with_grams(0).is_err());
+        assert!(FreeTextSuggester::default().with_grams(1).is_ok());
+    }
+}
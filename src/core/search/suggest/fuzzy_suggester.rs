@@ -0,0 +1,289 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::search::spell::{LevenshteinAutomaton, LevenshteinState};
+use crate::core::search::suggest::analyzing_suggester::{prefix_path, FSTPath, END_BYTE};
+use crate::core::search::suggest::{AnalyzingSuggester, LookupResult};
+use crate::core::store::directory::Directory;
+use crate::core::util::fst::{PositiveIntOutputFactory, END_LABEL, FST};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// The default maximum number of edits of a `FuzzySuggester`.
+pub const DEFAULT_MAX_EDITS: usize = 1;
+
+/// The default number of leading chars of a prefix which must match exactly.
+pub const DEFAULT_NON_FUZZY_PREFIX: usize = 1;
+
+/// The default minimum length of a prefix to be matched fuzzily.
+pub const DEFAULT_MIN_FUZZY_LENGTH: usize = 3;
+
+/// An `AnalyzingSuggester` which also suggests the texts whose analyzed form
+/// starts with a string within `max_edits` edits of the analyzed prefix, so
+/// a prefix with typos still gets suggestions.
+///
+/// The edits are counted in chars of the analyzed forms, a transposition of
+/// two adjacent chars counts as a single edit when `transpositions` is set.
+/// The first `non_fuzzy_prefix` chars must match exactly and prefixes shorter
+/// than `min_fuzzy_length` chars aren't matched fuzzily at all. Suggestions
+/// are ranked by weight, whatever their number of edits.
+///
+/// ```rust,ignore
+/// let mut suggester = FuzzySuggester::default().with_max_edits(2)?;
+/// suggester.build(vec![("ghost chrome", 10), ("ghost town", 5)])?;
+/// let results = suggester.lookup("gohst", 2)?;
+/// ```
+pub struct FuzzySuggester {
+    suggester: AnalyzingSuggester,
+    max_edits: usize,
+    transpositions: bool,
+    non_fuzzy_prefix: usize,
+    min_fuzzy_length: usize,
+}
+
+impl Default for FuzzySuggester {
+    fn default() -> Self {
+        FuzzySuggester::new(AnalyzingSuggester::default())
+    }
+}
+
+impl FuzzySuggester {
+    /// A fuzzy suggester analyzing the texts and the prefixes like `suggester`.
+    pub fn new(suggester: AnalyzingSuggester) -> FuzzySuggester {
+        FuzzySuggester {
+            suggester,
+            max_edits: DEFAULT_MAX_EDITS,
+            transpositions: true,
+            non_fuzzy_prefix: DEFAULT_NON_FUZZY_PREFIX,
+            min_fuzzy_length: DEFAULT_MIN_FUZZY_LENGTH,
+        }
+    }
+
+    /// The maximum number of edits, at most 2.
+    pub fn with_max_edits(mut self, max_edits: usize) -> Result<Self> {
+        if max_edits > 2 {
+            return Err(IllegalArgument(format!(
+                "max_edits must be at most 2, got {}",
+                max_edits
+            )));
+        }
+        self.max_edits = max_edits;
+        Ok(self)
+    }
+
+    /// Whether a transposition of adjacent chars counts as a single edit,
+    /// rather than two substitutions.
+    pub fn with_transpositions(mut self, transpositions: bool) -> Self {
+        self.transpositions = transpositions;
+        self
+    }
+
+    /// The number of leading chars of a prefix which must match exactly.
+    pub fn with_non_fuzzy_prefix(mut self, non_fuzzy_prefix: usize) -> Self {
+        self.non_fuzzy_prefix = non_fuzzy_prefix;
+        self
+    }
+
+    /// The minimum length of a prefix to be matched fuzzily.
+    pub fn with_min_fuzzy_length(mut self, min_fuzzy_length: usize) -> Self {
+        self.min_fuzzy_length = min_fuzzy_length;
+        self
+    }
+
+    /// The number of suggestions built or loaded.
+    pub fn count(&self) -> usize {
+        self.suggester.count()
+    }
+
+    /// See `AnalyzingSuggester::build`.
+    pub fn build<S, I>(&mut self, entries: I) -> Result<()>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, i64)>,
+    {
+        self.suggester.build(entries)
+    }
+
+    /// See `AnalyzingSuggester::build_offline`.
+    pub fn build_offline<D, S, I>(&mut self, directory: &D, entries: I) -> Result<()>
+    where
+        D: Directory,
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, i64)>,
+    {
+        self.suggester.build_offline(directory, entries)
+    }
+
+    /// Returns the `num` heaviest suggestions whose analyzed form starts with
+    /// a string within `max_edits` edits of the analyzed form of `key`.
+    pub fn lookup(&self, key: &str, num: usize) -> Result<Vec<LookupResult>> {
+        let fst = match self.suggester.fst() {
+            Some(fst) => fst,
+            None => return Ok(vec![]),
+        };
+        if num == 0 {
+            return Ok(vec![]);
+        }
+        let prefix = self.suggester.analyze_query(key)?;
+        let starts = self.fuzzy_paths(fst, &prefix)?;
+        self.suggester.lookup_paths(fst, &prefix, starts, num)
+    }
+
+    /// See `AnalyzingSuggester::store`.
+    pub fn store<D: Directory>(&self, directory: &D, name: &str) -> Result<()> {
+        self.suggester.store(directory, name)
+    }
+
+    /// See `AnalyzingSuggester::load`.
+    pub fn load<D: Directory>(&mut self, directory: &D, name: &str) -> Result<()> {
+        self.suggester.load(directory, name)
+    }
+
+    // the shortest paths of the FST matching `prefix` within `max_edits`,
+    // none is a prefix of another so that no suggestion is found twice
+    fn fuzzy_paths(
+        &self,
+        fst: &FST<PositiveIntOutputFactory>,
+        prefix: &[u8],
+    ) -> Result<Vec<FSTPath>> {
+        let chars: Vec<char> = String::from_utf8_lossy(prefix).chars().collect();
+        if self.max_edits == 0 || chars.len() < self.min_fuzzy_length {
+            return Ok(prefix_path(fst, FSTPath::start(fst), prefix)?
+                .into_iter()
+                .collect());
+        }
+        let exact_len = self.non_fuzzy_prefix.min(chars.len());
+        let exact: String = chars[..exact_len].iter().collect();
+        let start = match prefix_path(fst, FSTPath::start(fst), exact.as_bytes())? {
+            Some(start) => start,
+            None => return Ok(vec![]),
+        };
+        let fuzzy: String = chars[exact_len..].iter().collect();
+        let automaton = LevenshteinAutomaton::new(&fuzzy, self.max_edits, self.transpositions);
+        let state = automaton.start();
+        if automaton.is_match(&state) {
+            return Ok(vec![start]);
+        }
+
+        let mut reader = fst.bytes_reader();
+        let mut paths = vec![];
+        // the bytes of the char being read are pending until it's complete
+        let mut stack: Vec<(FSTPath, LevenshteinState, Vec<u8>)> = vec![(start, state, vec![])];
+        while let Some((path, state, pending)) = stack.pop() {
+            let mut arc = fst.read_first_target_arc(&path.arc, &mut reader)?;
+            loop {
+                // the analyzed forms end at `END_BYTE`, the surface forms follow
+                if arc.label != END_LABEL && arc.label != i32::from(END_BYTE) {
+                    let mut bytes = pending.clone();
+                    bytes.push(arc.label as u8);
+                    let next = match next_char(&bytes) {
+                        Some(c) => {
+                            bytes.clear();
+                            automaton.step(&state, c)
+                        }
+                        None => state.clone(),
+                    };
+                    if automaton.can_match(&next) {
+                        let next_path = path.follow(fst, arc.clone());
+                        if bytes.is_empty() && automaton.is_match(&next) {
+                            paths.push(next_path);
+                        } else {
+                            stack.push((next_path, next, bytes));
+                        }
+                    }
+                }
+                if arc.is_last() {
+                    break;
+                }
+                fst.read_next_arc(&mut arc, &mut reader)?;
+            }
+        }
+        Ok(paths)
+    }
+}
+
+// decodes the char of the UTF-8 `bytes`, `None` if more bytes are needed,
+// an invalid sequence is read as the replacement char
+fn next_char(bytes: &[u8]) -> Option<char> {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.chars().next(),
+        Err(e) if e.error_len().is_none() => None,
+        Err(_) => Some(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(results: Vec<LookupResult>) -> Vec<String> {
+        results.into_iter().map(|r| r.key).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_lookup() {
+        let entries = vec![
+            ("ghost chrome", 10),
+            ("ghost town", 50),
+            ("goblin", 30),
+            ("hostel", 100),
+            ("ghostbusters", 20),
+            ("ghost", 1),
+        ];
+        let mut suggester = FuzzySuggester::default();
+        suggester.build(entries.clone()).unwrap();
+
+        // a substitution, a transposition and an insertion
+        assert_eq!(
+            keys(suggester.lookup("ghast", 3).unwrap()),
+            vec!["ghost town", "ghostbusters", "ghost chrome"]
+        );
+        assert_eq!(
+            keys(suggester.lookup("gohst", 3).unwrap()),
+            vec!["ghost town", "ghostbusters", "ghost chrome"]
+        );
+        assert_eq!(
+            keys(suggester.lookup("gghost t", 5).unwrap()),
+            vec!["ghost town"]
+        );
+        // the first char must match
+        assert!(suggester.lookup("xhost", 5).unwrap().is_empty());
+        // too short to be fuzzy
+        assert!(suggester.lookup("gx", 5).unwrap().is_empty());
+        // exact matches still come first
+        assert_eq!(
+            keys(suggester.lookup("ghost", 2).unwrap()),
+            vec!["ghost", "ghost town"]
+        );
+
+        let mut suggester = FuzzySuggester::default().with_transpositions(false);
+        suggester.build(entries.clone()).unwrap();
+        assert!(suggester.lookup("gohst", 5).unwrap().is_empty());
+
+        let mut suggester = FuzzySuggester::default()
+            .with_transpositions(false)
+            .with_max_edits(2)
+            .unwrap();
+        suggester.build(entries).unwrap();
+        assert_eq!(
+            keys(suggester.lookup("gohst", 2).unwrap()),
+            vec!["ghost town", "ghostbusters"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_max_edits() {
+        assert!(FuzzySuggester::default().with_max_edits(3).is_err());
+        assert!(FuzzySuggester::default().with_max_edits(0).is_ok());
+    }
+}
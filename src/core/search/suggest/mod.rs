@@ -0,0 +1,36 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod analyzing_suggester;
+
+pub use self::analyzing_suggester::{AnalyzingSuggester, LookupResult, SEP_LABEL};
+
+mod fuzzy_suggester;
+
+pub use self::fuzzy_suggester::{
+    FuzzySuggester, DEFAULT_MAX_EDITS, DEFAULT_MIN_FUZZY_LENGTH, DEFAULT_NON_FUZZY_PREFIX,
+};
+
+mod analyzing_infix_suggester;
+
+pub use self::analyzing_infix_suggester::{AnalyzingInfixSuggester, InfixLookupResult};
+
+mod free_text_suggester;
+
+pub use self::free_text_suggester::{
+    FreeTextSuggester, FreeTextSuggestion, BACKOFF_FACTOR, DEFAULT_GRAMS,
+};
+
+mod suggest_index_searcher;
+
+pub use self::suggest_index_searcher::{CompletionQuery, SuggestIndexSearcher, SuggestScoreDoc};
@@ -0,0 +1,124 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::postings::{FieldTermsEnum, PerFieldFieldsReader};
+use crate::core::codec::Codec;
+use crate::core::index::reader::IndexReader;
+use crate::core::util::DocId;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::sync::Arc;
+
+/// Looks up the suggestions of a `SuggestField` starting with `prefix`,
+/// optionally restricted to some contexts.
+#[derive(Clone, Debug)]
+pub struct CompletionQuery {
+    field: String,
+    prefix: String,
+    contexts: Vec<String>,
+}
+
+impl CompletionQuery {
+    pub fn new(field: &str, prefix: &str) -> CompletionQuery {
+        CompletionQuery {
+            field: field.to_string(),
+            prefix: prefix.to_string(),
+            contexts: vec![],
+        }
+    }
+
+    /// Only suggests the inputs indexed in one of `contexts`, use the empty
+    /// context for the suggestions without context. Any context matches
+    /// when `contexts` is empty, which is the default.
+    pub fn with_contexts(mut self, contexts: &[&str]) -> CompletionQuery {
+        self.contexts = contexts.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+/// A suggestion of `SuggestIndexSearcher`, with the doc it was indexed in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SuggestScoreDoc {
+    pub doc: DocId,
+    pub key: String,
+    pub context: String,
+    pub weight: u32,
+}
+
+/// Searches the suggestions of the `SuggestField`s of an index.
+///
+/// The suggestions come from the completion dictionaries of the segments,
+/// so they see the documents of the reader, without the deleted ones.
+pub struct SuggestIndexSearcher<'a, C: Codec> {
+    reader: &'a dyn IndexReader<Codec = C>,
+}
+
+impl<'a, C> SuggestIndexSearcher<'a, C>
+where
+    C: Codec<FieldsProducer = Arc<PerFieldFieldsReader>>,
+{
+    pub fn new(reader: &'a dyn IndexReader<Codec = C>) -> Self {
+        SuggestIndexSearcher { reader }
+    }
+
+    /// Returns the `num` suggestions of `query` with the highest weights, a
+    /// doc with several matching suggestions may be returned more than once.
+    pub fn suggest(&self, query: &CompletionQuery, num: usize) -> Result<Vec<SuggestScoreDoc>> {
+        let contexts: Vec<&[u8]> = query.contexts.iter().map(|c| c.as_bytes()).collect();
+        let mut suggestions = vec![];
+        for leaf in self.reader.leaves() {
+            let terms = match leaf.reader.terms(&query.field)? {
+                Some(FieldTermsEnum::Completion(terms)) => terms,
+                Some(_) => {
+                    return Err(IllegalArgument(format!(
+                        "field '{}' doesn't use the completion postings format",
+                        query.field
+                    )));
+                }
+                None => continue,
+            };
+            // the deleted docs of the segment can take at most that many slots
+            let deleted = (leaf.reader.max_doc() - leaf.reader.num_docs()) as usize;
+            let live_docs = leaf.reader.live_docs();
+            for completion in terms.lookup(query.prefix.as_bytes(), &contexts, num + deleted)? {
+                if !live_docs.get(completion.doc as usize) {
+                    continue;
+                }
+                suggestions.push(SuggestScoreDoc {
+                    doc: leaf.doc_base + completion.doc,
+                    key: String::from_utf8(completion.input)?,
+                    context: String::from_utf8(completion.context)?,
+                    weight: completion.weight,
+                });
+            }
+        }
+        suggestions.sort_by(|a, b| {
+            b.weight
+                .cmp(&a.weight)
+                .then_with(|| a.key.cmp(&b.key))
+                .then_with(|| a.context.cmp(&b.context))
+                .then_with(|| a.doc.cmp(&b.doc))
+        });
+        suggestions.truncate(num);
+        Ok(suggestions)
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::core::store::directory::ObjectStore;
+use crate::Result;
+
+/// A boxed, sendable future, as returned by `AsyncObjectStore`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Async flavor of `ObjectStore`, for network clients that can have many requests
+/// in flight.
+///
+/// The futures are polled by `block_on` on the calling thread, so they must not need
+/// a specific runtime to make progress: clients built on a runtime should spawn the
+/// request there and return a future waiting for its result (e.g. a join handle or
+/// oneshot channel).
+pub trait AsyncObjectStore: Send + Sync + 'static {
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<u64>>>;
+
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        offset: u64,
+        len: usize,
+    ) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>) -> BoxFuture<'a, Result<()>>;
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs the future to completion on the current thread, parking it while pending.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Future polling all the futures concurrently, resolving to their outputs in order.
+pub struct JoinAll<'a, T> {
+    futures: Vec<Option<BoxFuture<'a, T>>>,
+    outputs: Vec<Option<T>>,
+}
+
+pub fn join_all<T>(futures: Vec<BoxFuture<'_, T>>) -> JoinAll<'_, T> {
+    let outputs = futures.iter().map(|_| None).collect();
+    JoinAll {
+        futures: futures.into_iter().map(Some).collect(),
+        outputs,
+    }
+}
+
+// the futures are boxed and the outputs are never pinned
+impl<T> Unpin for JoinAll<'_, T> {}
+
+impl<T> Future for JoinAll<'_, T> {
+    type Output = Vec<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<T>> {
+        let this = self.get_mut();
+        let mut pending = false;
+        for (slot, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(future) = slot {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(v) => {
+                        *output = Some(v);
+                        *slot = None;
+                    }
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(this.outputs.iter_mut().map(|o| o.take().unwrap()).collect())
+        }
+    }
+}
+
+/// Adapts an `AsyncObjectStore` to the blocking `ObjectStore` used by
+/// `ObjectStoreDirectory`, issuing the reads of `get_ranges` concurrently.
+pub struct BlockingObjectStore<A: AsyncObjectStore> {
+    inner: A,
+}
+
+impl<A: AsyncObjectStore> BlockingObjectStore<A> {
+    pub fn new(inner: A) -> Self {
+        BlockingObjectStore { inner }
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A: AsyncObjectStore> ObjectStore for BlockingObjectStore<A> {
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        block_on(self.inner.list(prefix))
+    }
+
+    fn head(&self, key: &str) -> Result<Option<u64>> {
+        block_on(self.inner.head(key))
+    }
+
+    fn get_range(&self, key: &str, offset: u64, len: usize) -> Result<Vec<u8>> {
+        block_on(self.inner.get_range(key, offset, len))
+    }
+
+    fn get_ranges(&self, key: &str, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        let futures = ranges
+            .iter()
+            .map(|&(offset, len)| self.inner.get_range(key, offset, len))
+            .collect();
+        block_on(join_all(futures)).into_iter().collect()
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        block_on(self.inner.put(key, data))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        block_on(self.inner.delete(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::InMemoryObjectStore;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Completes each read from a background thread after a delay.
+    struct DelayedStore {
+        inner: Arc<InMemoryObjectStore>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    struct Delayed {
+        result: Arc<Mutex<Option<Result<Vec<u8>>>>>,
+        started: bool,
+        request: Option<(String, u64, usize)>,
+        store: Arc<InMemoryObjectStore>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Future for Delayed {
+        type Output = Result<Vec<u8>>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            if let Some(result) = this.result.lock().unwrap().take() {
+                return Poll::Ready(result);
+            }
+            if !this.started {
+                this.started = true;
+                let (key, offset, len) = this.request.take().unwrap();
+                let now = this.in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+                this.max_in_flight.fetch_max(now, Ordering::AcqRel);
+                let (store, result, in_flight) = (
+                    Arc::clone(&this.store),
+                    Arc::clone(&this.result),
+                    Arc::clone(&this.in_flight),
+                );
+                let waker = cx.waker().clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(50));
+                    in_flight.fetch_sub(1, Ordering::AcqRel);
+                    *result.lock().unwrap() = Some(store.get_range(&key, offset, len));
+                    waker.wake();
+                });
+            }
+            Poll::Pending
+        }
+    }
+
+    impl AsyncObjectStore for DelayedStore {
+        fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+            Box::pin(async move { self.inner.list(prefix) })
+        }
+
+        fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+            Box::pin(async move { self.inner.head(key) })
+        }
+
+        fn get_range<'a>(
+            &'a self,
+            key: &'a str,
+            offset: u64,
+            len: usize,
+        ) -> BoxFuture<'a, Result<Vec<u8>>> {
+            Box::pin(Delayed {
+                result: Arc::new(Mutex::new(None)),
+                started: false,
+                request: Some((key.to_string(), offset, len)),
+                store: Arc::clone(&self.inner),
+                in_flight: Arc::clone(&self.in_flight),
+                max_in_flight: Arc::clone(&self.max_in_flight),
+            })
+        }
+
+        fn put<'a>(&'a self, key: &'a str, data: Vec<u8>) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move { self.inner.put(key, data) })
+        }
+
+        fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move { self.inner.delete(key) })
+        }
+    }
+
+    #[test]
+    fn test_get_ranges_concurrently() -> Result<()> {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let store = BlockingObjectStore::new(DelayedStore {
+            inner: Arc::new(InMemoryObjectStore::new()),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            max_in_flight: Arc::clone(&max_in_flight),
+        });
+        store.put("a", (0..100u8).collect())?;
+        assert_eq!(store.head("a")?, Some(100));
+
+        let ranges: Vec<(u64, usize)> = (0..8).map(|i| (i * 10, 5)).collect();
+        let data = store.get_ranges("a", &ranges)?;
+        for (i, bytes) in data.iter().enumerate() {
+            let start = i as u8 * 10;
+            assert_eq!(bytes, &(start..start + 5).collect::<Vec<u8>>());
+        }
+        assert_eq!(max_in_flight.load(Ordering::Acquire), 8);
+        assert_eq!(store.inner().inner.get_requests(), 8);
+        assert!(store.get_ranges("a", &[(98, 5)]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use flate2::Crc;
+
+use crate::core::codec::segment_infos::segment_file_name;
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{ByteBuffersIndexInput, DataOutput, IndexInput, IndexOutput};
+use crate::core::store::lock::{Lock, LockFactory, SingleInstanceLockFactory};
+use crate::core::store::IOContext;
+use crate::core::util::to_base36;
+use crate::error::Error::IllegalState;
+use crate::Result;
+
+/// Files of a `ByteBuffersDirectory`, an entry is `None` while its output is still open.
+type FileMap = Arc<RwLock<BTreeMap<String, Option<Arc<Vec<u8>>>>>>;
+
+/// A `Directory` keeping all files on the heap.
+///
+/// Suitable for tests, ephemeral indexes and small short-lived indexes. A file becomes
+/// readable once its output is dropped, opened inputs share the written bytes so any
+/// number of readers can be opened concurrently without copying.
+pub struct ByteBuffersDirectory {
+    files: FileMap,
+    next_temp_file_counter: AtomicUsize,
+    lock_factory: Arc<dyn LockFactory>,
+}
+
+impl Default for ByteBuffersDirectory {
+    fn default() -> Self {
+        Self::with_lock_factory(Arc::new(SingleInstanceLockFactory::new()))
+    }
+}
+
+impl ByteBuffersDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_lock_factory(lock_factory: Arc<dyn LockFactory>) -> Self {
+        ByteBuffersDirectory {
+            files: Arc::new(RwLock::new(BTreeMap::new())),
+            next_temp_file_counter: AtomicUsize::new(0),
+            lock_factory,
+        }
+    }
+
+    /// Total bytes of all the finished files in this directory.
+    pub fn ram_bytes_used(&self) -> Result<usize> {
+        Ok(self
+            .files
+            .read()?
+            .values()
+            .map(|f| f.as_ref().map_or(0, |data| data.len()))
+            .sum())
+    }
+
+    /// Whether the file exists, including files whose output is still open.
+    pub fn file_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.files.read()?.contains_key(name))
+    }
+
+    fn new_output(&self, name: String) -> Result<ByteBuffersIndexOutput> {
+        let mut files = self.files.write()?;
+        if files.contains_key(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("file already exists: {}", name),
+            )
+            .into());
+        }
+        files.insert(name.clone(), None);
+        Ok(ByteBuffersIndexOutput::new(name, Arc::clone(&self.files)))
+    }
+
+    fn file_data(&self, name: &str) -> Result<Arc<Vec<u8>>> {
+        match self.files.read()?.get(name) {
+            Some(Some(data)) => Ok(Arc::clone(data)),
+            Some(None) => Err(IllegalState(format!(
+                "file {} is still open for writing",
+                name
+            ))),
+            None => Err(not_found(name)),
+        }
+    }
+}
+
+fn not_found(name: &str) -> crate::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("file not found: {}", name)).into()
+}
+
+impl Directory for ByteBuffersDirectory {
+    type IndexOutput = ByteBuffersIndexOutput;
+    type TempOutput = ByteBuffersIndexOutput;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        Ok(self.files.read()?.keys().cloned().collect())
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        Ok(self.file_data(name)?.len() as i64)
+    }
+
+    fn create_output(&self, name: &str, _context: &IOContext) -> Result<Self::IndexOutput> {
+        self.new_output(name.to_string())
+    }
+
+    fn open_input(&self, name: &str, _ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        let data = self.file_data(name)?;
+        Ok(Box::new(ByteBuffersIndexInput::new(data, name)))
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        _ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        loop {
+            let name = segment_file_name(
+                prefix,
+                &format!(
+                    "{}_{}",
+                    suffix,
+                    to_base36(self.next_temp_file_counter.fetch_add(1, Ordering::AcqRel) as u64)
+                ),
+                "tmp",
+            );
+            if self.files.read()?.contains_key(&name) {
+                continue;
+            }
+            return self.new_output(name);
+        }
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        match self.files.write()?.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(not_found(name)),
+        }
+    }
+
+    fn sync(&self, _name: &HashSet<String>) -> Result<()> {
+        Ok(())
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        let mut files = self.files.write()?;
+        match files.remove(source) {
+            Some(file) => {
+                files.insert(dest.to_string(), file);
+                Ok(())
+            }
+            None => Err(not_found(source)),
+        }
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.lock_factory.obtain_lock(Path::new(""), name)
+    }
+}
+
+impl fmt::Display for ByteBuffersDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ByteBuffersDirectory@{:p}", Arc::as_ptr(&self.files))
+    }
+}
+
+/// `IndexOutput` of a `ByteBuffersDirectory`, the content is published to the
+/// directory when the output is dropped.
+pub struct ByteBuffersIndexOutput {
+    name: String,
+    buffer: Vec<u8>,
+    crc: Crc,
+    files: FileMap,
+}
+
+impl ByteBuffersIndexOutput {
+    fn new(name: String, files: FileMap) -> Self {
+        ByteBuffersIndexOutput {
+            name,
+            buffer: Vec::new(),
+            crc: Crc::new(),
+            files,
+        }
+    }
+}
+
+impl Write for ByteBuffersIndexOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.crc.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl DataOutput for ByteBuffersIndexOutput {}
+
+impl IndexOutput for ByteBuffersIndexOutput {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.buffer.len() as i64
+    }
+
+    fn checksum(&self) -> Result<i64> {
+        Ok((self.crc.sum() as i64) & 0xffff_ffffi64)
+    }
+}
+
+impl Drop for ByteBuffersIndexOutput {
+    fn drop(&mut self) {
+        if let Ok(mut files) = self.files.write() {
+            // the file may have been deleted before the output was closed
+            if let Some(entry) = files.get_mut(&self.name) {
+                *entry = Some(Arc::new(std::mem::take(&mut self.buffer)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_then_read() -> Result<()> {
+        let dir = ByteBuffersDirectory::new();
+        {
+            let mut out = dir.create_output("a", &IOContext::Default)?;
+            out.write_int(42)?;
+            out.write_long(7)?;
+            out.write_string("hello")?;
+            assert!(dir.open_input("a", &IOContext::Default).is_err());
+        }
+        assert!(dir.create_output("a", &IOContext::Default).is_err());
+        assert_eq!(dir.file_length("a")?, 18);
+
+        let mut input = dir.open_input("a", &IOContext::Default)?;
+        let mut other = input.clone()?;
+        assert_eq!(input.read_int()?, 42);
+        assert_eq!(input.read_long()?, 7);
+        assert_eq!(input.read_string()?, "hello");
+        assert!(input.read_byte().is_err());
+        assert_eq!(other.read_int()?, 42);
+
+        let slice = input.random_access_slice(4, 8)?;
+        assert_eq!(slice.read_long(0)?, 7);
+        assert!(slice.read_int(6).is_err());
+
+        dir.rename("a", "b")?;
+        assert_eq!(dir.list_all()?, vec!["b".to_string()]);
+        dir.delete_file("b")?;
+        assert!(dir.list_all()?.is_empty());
+        // readers keep their bytes after the file is deleted
+        other.seek(0)?;
+        assert_eq!(other.read_int()?, 42);
+        Ok(())
+    }
+}
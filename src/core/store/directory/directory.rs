@@ -17,6 +17,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::core::store::io::{BufferedChecksumIndexInput, DataOutput, IndexInput, IndexOutput};
+use crate::core::store::lock::Lock;
 use crate::core::store::IOContext;
 use crate::Result;
 
@@ -76,6 +77,10 @@ pub trait Directory: fmt::Display {
 
     fn rename(&self, source: &str, dest: &str) -> Result<()>;
 
+    /// Obtains the lock `name` of this directory, e.g. `WRITE_LOCK_NAME`,
+    /// failing with `Error::LockObtainFailed` if it is already held.
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>>;
+
     fn copy_from<D: Directory>(
         &self,
         from: &D,
@@ -105,11 +110,12 @@ pub trait Directory: fmt::Display {
 /// `Lock` is valid before any destructive filesystem operation.
 pub struct LockValidatingDirectoryWrapper<D: Directory> {
     dir: Arc<D>,
+    write_lock: Arc<dyn Lock>,
 }
 
 impl<D: Directory> LockValidatingDirectoryWrapper<D> {
-    pub fn new(dir: Arc<D>) -> Self {
-        LockValidatingDirectoryWrapper { dir }
+    pub fn new(dir: Arc<D>, write_lock: Arc<dyn Lock>) -> Self {
+        LockValidatingDirectoryWrapper { dir, write_lock }
     }
 }
 
@@ -127,6 +133,7 @@ impl<D: Directory> Directory for LockValidatingDirectoryWrapper<D> {
     type TempOutput = D::TempOutput;
 
     fn create_output(&self, name: &str, context: &IOContext) -> Result<Self::IndexOutput> {
+        self.write_lock.ensure_valid()?;
         self.dir.create_output(name, context)
     }
 
@@ -144,21 +151,29 @@ impl<D: Directory> Directory for LockValidatingDirectoryWrapper<D> {
     }
 
     fn delete_file(&self, name: &str) -> Result<()> {
+        self.write_lock.ensure_valid()?;
         self.dir.delete_file(name)
     }
 
     fn sync(&self, name: &HashSet<String>) -> Result<()> {
+        self.write_lock.ensure_valid()?;
         self.dir.sync(name)
     }
 
     fn sync_metadata(&self) -> Result<()> {
+        self.write_lock.ensure_valid()?;
         self.dir.sync_metadata()
     }
 
     fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        self.write_lock.ensure_valid()?;
         self.dir.rename(source, dest)
     }
 
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.dir.obtain_lock(name)
+    }
+
     fn copy_from<D1: Directory>(
         &self,
         from: &D1,
@@ -166,6 +181,7 @@ impl<D: Directory> Directory for LockValidatingDirectoryWrapper<D> {
         dest: &str,
         ctx: &IOContext,
     ) -> Result<()> {
+        self.write_lock.ensure_valid()?;
         self.dir.copy_from(from, src, dest, ctx)
     }
 }
@@ -214,6 +230,10 @@ default impl<T: FilterDirectory> Directory for T {
         self.dir().rename(source, dest)
     }
 
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.dir().obtain_lock(name)
+    }
+
     fn copy_from<D: Directory>(
         &self,
         from: &D,
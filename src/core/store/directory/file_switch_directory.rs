@@ -0,0 +1,222 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::io;
+
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataOutput, IndexInput, IndexOutput};
+use crate::core::store::lock::Lock;
+use crate::core::store::IOContext;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// A `Directory` that routes files to one of two delegates based on their extension.
+///
+/// Files whose extension is in `primary_extensions` go to the primary directory and
+/// everything else (including `segments_N`, which has no extension) to the secondary
+/// one, e.g. to keep terms and doc values on fast storage and stored fields on slower
+/// disks. The two delegates should not share the same location.
+pub struct FileSwitchDirectory<P: Directory, S: Directory> {
+    primary_extensions: HashSet<String>,
+    primary: P,
+    secondary: S,
+}
+
+impl<P: Directory, S: Directory> FileSwitchDirectory<P, S> {
+    pub fn new(primary_extensions: HashSet<String>, primary: P, secondary: S) -> Self {
+        FileSwitchDirectory {
+            primary_extensions,
+            primary,
+            secondary,
+        }
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    /// Returns the extension of the file name, anything after the last '.'.
+    pub fn extension(name: &str) -> &str {
+        name.rfind('.').map_or("", |i| &name[i + 1..])
+    }
+
+    fn is_primary(&self, name: &str) -> bool {
+        self.primary_extensions.contains(Self::extension(name))
+    }
+}
+
+impl<P: Directory, S: Directory> Directory for FileSwitchDirectory<P, S> {
+    type IndexOutput = FileSwitchIndexOutput<P::IndexOutput, S::IndexOutput>;
+    type TempOutput = FileSwitchIndexOutput<P::TempOutput, S::TempOutput>;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        let mut files: BTreeSet<String> = self.primary.list_all()?.into_iter().collect();
+        files.extend(self.secondary.list_all()?);
+        Ok(files.into_iter().collect())
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        if self.is_primary(name) {
+            self.primary.file_length(name)
+        } else {
+            self.secondary.file_length(name)
+        }
+    }
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        if self.is_primary(name) {
+            Ok(FileSwitchIndexOutput::Primary(
+                self.primary.create_output(name, ctx)?,
+            ))
+        } else {
+            Ok(FileSwitchIndexOutput::Secondary(
+                self.secondary.create_output(name, ctx)?,
+            ))
+        }
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        if self.is_primary(name) {
+            self.primary.open_input(name, ctx)
+        } else {
+            self.secondary.open_input(name, ctx)
+        }
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        // temp files are all named *.tmp
+        if self.primary_extensions.contains("tmp") {
+            Ok(FileSwitchIndexOutput::Primary(
+                self.primary.create_temp_output(prefix, suffix, ctx)?,
+            ))
+        } else {
+            Ok(FileSwitchIndexOutput::Secondary(
+                self.secondary.create_temp_output(prefix, suffix, ctx)?,
+            ))
+        }
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        if self.is_primary(name) {
+            self.primary.delete_file(name)
+        } else {
+            self.secondary.delete_file(name)
+        }
+    }
+
+    fn sync(&self, names: &HashSet<String>) -> Result<()> {
+        let (primary_names, secondary_names): (HashSet<String>, HashSet<String>) = names
+            .iter()
+            .cloned()
+            .partition(|name| self.is_primary(name));
+        if !primary_names.is_empty() {
+            self.primary.sync(&primary_names)?;
+        }
+        if !secondary_names.is_empty() {
+            self.secondary.sync(&secondary_names)?;
+        }
+        Ok(())
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        self.primary.sync_metadata()?;
+        self.secondary.sync_metadata()
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        match (self.is_primary(source), self.is_primary(dest)) {
+            (true, true) => self.primary.rename(source, dest),
+            (false, false) => self.secondary.rename(source, dest),
+            _ => Err(IllegalArgument(format!(
+                "cannot rename '{}' to '{}' across directories",
+                source, dest
+            ))),
+        }
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        if self.is_primary(name) {
+            self.primary.obtain_lock(name)
+        } else {
+            self.secondary.obtain_lock(name)
+        }
+    }
+}
+
+impl<P: Directory, S: Directory> fmt::Display for FileSwitchDirectory<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FileSwitchDirectory({}, {})",
+            self.primary, self.secondary
+        )
+    }
+}
+
+/// `IndexOutput` of a `FileSwitchDirectory`, writing to one of its two delegates.
+pub enum FileSwitchIndexOutput<P: IndexOutput, S: IndexOutput> {
+    Primary(P),
+    Secondary(S),
+}
+
+impl<P: IndexOutput, S: IndexOutput> IndexOutput for FileSwitchIndexOutput<P, S> {
+    fn name(&self) -> &str {
+        match self {
+            FileSwitchIndexOutput::Primary(o) => o.name(),
+            FileSwitchIndexOutput::Secondary(o) => o.name(),
+        }
+    }
+
+    fn file_pointer(&self) -> i64 {
+        match self {
+            FileSwitchIndexOutput::Primary(o) => o.file_pointer(),
+            FileSwitchIndexOutput::Secondary(o) => o.file_pointer(),
+        }
+    }
+
+    fn checksum(&self) -> Result<i64> {
+        match self {
+            FileSwitchIndexOutput::Primary(o) => o.checksum(),
+            FileSwitchIndexOutput::Secondary(o) => o.checksum(),
+        }
+    }
+}
+
+impl<P: IndexOutput, S: IndexOutput> DataOutput for FileSwitchIndexOutput<P, S> {}
+
+impl<P: IndexOutput, S: IndexOutput> io::Write for FileSwitchIndexOutput<P, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            FileSwitchIndexOutput::Primary(o) => o.write(buf),
+            FileSwitchIndexOutput::Secondary(o) => o.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            FileSwitchIndexOutput::Primary(o) => o.flush(),
+            FileSwitchIndexOutput::Secondary(o) => o.flush(),
+        }
+    }
+}
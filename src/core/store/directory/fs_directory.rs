@@ -14,31 +14,60 @@
 use std::collections::{BTreeSet, HashSet};
 use std::fmt;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
 
 use crate::core::codec::segment_infos::segment_file_name;
-use crate::core::store::directory::Directory;
-use crate::core::store::io::{FSIndexOutput, IndexInput, MmapIndexInput};
-use crate::core::store::IOContext;
+use crate::core::store::directory::{Directory, SyncScheduler};
+use crate::core::store::io::{
+    FSIndexOutput, IndexInput, MmapIndexInput, DEFAULT_OUTPUT_BUFFER_SIZE,
+};
+use crate::core::store::lock::{Lock, LockFactory, NativeFSLockFactory};
+use crate::core::store::{IOContext, IOContextKind};
 use crate::core::util::to_base36;
 use crate::error::Error::IllegalState;
 use crate::{Error, Result};
 
+/// The default output buffer size of flushes and merges, which write large
+/// files sequentially.
+pub const DEFAULT_BULK_OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How many times a rename failing because a file is in use is retried.
+const RENAME_RETRIES: u32 = 5;
+
 /// a straightforward `Directory` implementations use std::fs::File.
 ///
 /// However, it has poor concurrent performance (multiple threads will bottleneck)
 /// as it synchronizes when multiple threads read from the same file.
+///
+/// On Windows files which are still open or mapped can't be deleted, so a
+/// failed delete is retried later by the following operations, the file is
+/// hidden from `list_all` meanwhile. Renames are retried for a short while
+/// when a file is held by another process, e.g. a virus scanner.
 pub struct FSDirectory {
     pub(crate) directory: PathBuf,
     pending_deletes: RwLock<BTreeSet<String>>,
     pub ops_since_last_delete: AtomicUsize,
     pub next_temp_file_counter: AtomicUsize,
+    lock_factory: Arc<dyn LockFactory>,
+    // indexed by `IOContextKind::index`
+    output_buffer_sizes: [usize; 4],
+    sync_scheduler: SyncScheduler,
 }
 
 impl FSDirectory {
     pub fn new<T: AsRef<Path> + ?Sized>(directory: &T) -> Result<FSDirectory> {
+        Self::with_lock_factory(directory, Arc::new(NativeFSLockFactory))
+    }
+
+    pub fn with_lock_factory<T: AsRef<Path> + ?Sized>(
+        directory: &T,
+        lock_factory: Arc<dyn LockFactory>,
+    ) -> Result<FSDirectory> {
         let directory = directory.as_ref();
         if !Path::exists(directory) {
             fs::create_dir_all(directory)?;
@@ -48,27 +77,61 @@ impl FSDirectory {
                 directory
             )));
         }
+        // the canonical form of a Windows path is a verbatim `\\?\` path, which
+        // isn't limited to `MAX_PATH` characters
+        #[cfg(windows)]
+        let canonical = fs::canonicalize(directory)?;
+        #[cfg(windows)]
+        let directory = canonical.as_path();
 
         Ok(FSDirectory {
             directory: directory.into(),
             pending_deletes: RwLock::new(BTreeSet::new()),
             ops_since_last_delete: AtomicUsize::new(0),
             next_temp_file_counter: AtomicUsize::new(0),
+            lock_factory,
+            output_buffer_sizes: [
+                DEFAULT_BULK_OUTPUT_BUFFER_SIZE,
+                DEFAULT_OUTPUT_BUFFER_SIZE,
+                DEFAULT_BULK_OUTPUT_BUFFER_SIZE,
+                DEFAULT_OUTPUT_BUFFER_SIZE,
+            ],
+            sync_scheduler: SyncScheduler::default(),
         })
     }
 
-    fn delete_pending_files(pending_deletes: &mut BTreeSet<String>, dir: &PathBuf) -> Result<()> {
-        let mut deleted_set = BTreeSet::new();
-        for name in pending_deletes.iter() {
+    /// Sets the number of threads the files of a commit are synced with.
+    pub fn with_sync_threads(mut self, threads: usize) -> Self {
+        self.sync_scheduler = SyncScheduler::new(threads);
+        self
+    }
+
+    /// Sets the size of the write buffer of the outputs created in the
+    /// contexts of `kind`.
+    pub fn with_output_buffer_size(mut self, kind: IOContextKind, size: usize) -> Self {
+        self.output_buffer_sizes[kind.index()] = size.max(1);
+        self
+    }
+
+    /// Returns the size of the write buffer of the outputs created in `ctx`.
+    pub fn output_buffer_size(&self, ctx: &IOContext) -> usize {
+        self.output_buffer_sizes[IOContextKind::from(ctx).index()]
+    }
+
+    // Deletes the files in `pending_deletes`, the ones which can't be deleted
+    // yet, e.g. because they are still open on Windows, are kept for a retry.
+    fn delete_pending_files(pending_deletes: &mut BTreeSet<String>, dir: &Path) -> Result<()> {
+        pending_deletes.retain(|name| {
             let path = dir.join(name);
-            if let Err(e) = fs::remove_file(path.clone()) {
-                log::info!("delete_pending_files {:?} failed. {}", path, e.to_string());
+            match fs::remove_file(&path) {
+                Ok(()) => false,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+                Err(e) => {
+                    log::info!("delete_pending_files {:?} failed, will retry. {}", path, e);
+                    true
+                }
             }
-            deleted_set.insert(name.clone());
-        }
-        for name in deleted_set {
-            pending_deletes.remove(&name);
-        }
+        });
         Ok(())
     }
 
@@ -94,19 +157,14 @@ impl FSDirectory {
         }
         Ok(())
     }
+}
 
-    fn fsync(&self, path: &Path, is_dir: bool) -> Result<()> {
-        // If the file is a directory we have to open read-only, for regular files we must
-        // open r/w for the fsync to have an effect.
-        // See http://blog.httrack.com/blog/2013/11/15/everything-you-always-wanted-to-know-about-fsync/
-        let file = if is_dir {
-            fs::File::open(path)?
-        } else {
-            fs::OpenOptions::new().append(true).open(path)?
-        };
-        file.sync_all()?;
-        Ok(())
-    }
+/// Returns whether `e` is the error of an operation on a file which is still
+/// open or mapped elsewhere, this only happens on Windows.
+fn is_file_in_use(e: &io::Error) -> bool {
+    // ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION and
+    // ERROR_USER_MAPPED_FILE
+    cfg!(windows) && matches!(e.raw_os_error(), Some(5 | 32 | 33 | 1224))
 }
 
 fn list_all<T: AsRef<Path>>(path: &T) -> Result<Vec<String>> {
@@ -125,7 +183,12 @@ impl Directory for FSDirectory {
     type TempOutput = FSIndexOutput;
 
     fn list_all(&self) -> Result<Vec<String>> {
-        list_all(&self.directory)
+        let mut names = list_all(&self.directory)?;
+        let pending_deletes = self.pending_deletes.read()?;
+        if !pending_deletes.is_empty() {
+            names.retain(|name| !pending_deletes.contains(name));
+        }
+        Ok(names)
     }
 
     fn file_length(&self, name: &str) -> Result<i64> {
@@ -145,26 +208,30 @@ impl Directory for FSDirectory {
         }
     }
 
-    fn create_output(&self, name: &str, _context: &IOContext) -> Result<Self::IndexOutput> {
+    fn create_output(&self, name: &str, context: &IOContext) -> Result<Self::IndexOutput> {
         // If this file was pending delete, we are now bringing it back to life:
         self.pending_deletes.write()?.remove(name);
         self.maybe_delete_pending_files()?;
         let path = self.resolve(name);
-        FSIndexOutput::new(name.to_string(), &path)
+        self.sync_scheduler.on_create(name);
+        FSIndexOutput::with_buffer_size(name.to_string(), &path, self.output_buffer_size(context))
     }
 
-    fn open_input(&self, name: &str, _ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
         self.ensure_can_read(name)?;
         let path = self.directory.as_path().join(name);
         // hack logic, we don'e implement FsIndexInput yes, so just us MmapIndexInput instead
-        Ok(Box::new(MmapIndexInput::new(path)?))
+        Ok(Box::new(MmapIndexInput::with_advice(
+            path,
+            ctx.read_advice(name),
+        )?))
     }
 
     fn create_temp_output(
         &self,
         prefix: &str,
         suffix: &str,
-        _ctx: &IOContext,
+        ctx: &IOContext,
     ) -> Result<Self::TempOutput> {
         self.maybe_delete_pending_files()?;
 
@@ -184,7 +251,8 @@ impl Directory for FSDirectory {
             }
 
             let path = self.resolve(&name);
-            return FSIndexOutput::new(name, &path);
+            self.sync_scheduler.on_create(&name);
+            return FSIndexOutput::with_buffer_size(name, &path, self.output_buffer_size(ctx));
         }
     }
 
@@ -199,36 +267,47 @@ impl Directory for FSDirectory {
         let mut deletes = BTreeSet::new();
         deletes.insert(name.to_string());
         Self::delete_pending_files(&mut deletes, &self.directory)?;
-        self.pending_deletes.write()?.remove(name);
+        self.pending_deletes.write()?.append(&mut deletes);
+        self.sync_scheduler.on_delete(name);
 
         self.maybe_delete_pending_files()
     }
 
     fn sync(&self, names: &HashSet<String>) -> Result<()> {
-        for name in names {
-            let path = self.resolve(name);
-            self.fsync(&path, false)?;
-        }
+        self.sync_scheduler.sync(&self.directory, names)?;
         self.maybe_delete_pending_files()
     }
 
     fn sync_metadata(&self) -> Result<()> {
-        self.fsync(&self.directory, true)
+        self.sync_scheduler.sync_metadata(&self.directory)
     }
 
     fn rename(&self, source: &str, dest: &str) -> Result<()> {
         if self.pending_deletes.read()?.contains(source) {
-            return Err(IllegalState(
-                "file '{}' is pending delete and cannot be moved".into(),
-            ));
+            return Err(IllegalState(format!(
+                "file '{}' is pending delete and cannot be moved",
+                source
+            )));
         }
         self.pending_deletes.write()?.remove(dest);
         let source_path = self.resolve(source);
         let dest_path = self.resolve(dest);
-        fs::rename(&source_path, &dest_path)?;
+        let mut retries = 0;
+        while let Err(e) = fs::rename(&source_path, &dest_path) {
+            if retries == RENAME_RETRIES || !is_file_in_use(&e) {
+                return Err(e.into());
+            }
+            thread::sleep(Duration::from_millis(10 << retries));
+            retries += 1;
+        }
+        self.sync_scheduler.on_rename(source, dest);
         self.maybe_delete_pending_files()
     }
 
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.lock_factory.obtain_lock(&self.directory, name)
+    }
+
     fn resolve(&self, name: &str) -> PathBuf {
         self.directory.join(name)
     }
@@ -239,3 +318,33 @@ impl fmt::Display for FSDirectory {
         write!(f, "FSDirectory({})", self.directory.display())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_pending_deletes() -> Result<()> {
+        let path = tempfile::tempdir()?;
+        let dir = FSDirectory::new(path.path())?;
+        dir.create_output("_0.si", &IOContext::Default)?;
+        // removing a directory with `remove_file` fails like removing a file
+        // still mapped on Windows does
+        fs::create_dir(path.path().join("_0.cfs"))?;
+
+        dir.delete_file("_0.cfs")?;
+        assert!(dir.pending_deletes.read()?.contains("_0.cfs"));
+        assert_eq!(dir.list_all()?, vec!["_0.si".to_string()]);
+        assert!(dir.file_length("_0.cfs").is_err());
+        assert!(dir.delete_file("_0.cfs").is_err());
+
+        fs::remove_dir(path.path().join("_0.cfs"))?;
+        fs::write(path.path().join("_0.cfs"), b"cfs")?;
+        for _ in 0..2 {
+            dir.maybe_delete_pending_files()?;
+        }
+        assert!(dir.pending_deletes.read()?.is_empty());
+        assert!(!path.path().join("_0.cfs").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::core::store::directory::{Directory, FilterDirectory};
+use crate::core::store::io::{DataInput, DataOutput, IndexInput, IndexOutput, RandomAccessInput};
+use crate::core::store::{IOContext, IOContextKind};
+use crate::core::util::Counter;
+use crate::Result;
+
+/// Upper bounds, in microseconds, of the read latency buckets of
+/// `IOContextStats::read_latency_histogram`, the last bucket holds the slower
+/// reads.
+pub const READ_LATENCY_BUCKETS_MICROS: [u64; 5] = [10, 100, 1_000, 10_000, 100_000];
+
+const NUM_LATENCY_BUCKETS: usize = READ_LATENCY_BUCKETS_MICROS.len() + 1;
+
+/// A snapshot of the IO done in one kind of `IOContext`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IOContextStats {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// Inputs opened so far, clones and slices excluded.
+    pub inputs_opened: u64,
+    pub outputs_opened: u64,
+    /// Inputs and outputs currently open.
+    pub open_inputs: u64,
+    pub open_outputs: u64,
+    /// Number of bulk reads, i.e. not the single byte ones, per latency bucket
+    /// of `READ_LATENCY_BUCKETS_MICROS`.
+    pub read_latency_histogram: [u64; NUM_LATENCY_BUCKETS],
+}
+
+impl IOContextStats {
+    fn merge(&mut self, other: &IOContextStats) {
+        self.bytes_read += other.bytes_read;
+        self.bytes_written += other.bytes_written;
+        self.inputs_opened += other.inputs_opened;
+        self.outputs_opened += other.outputs_opened;
+        self.open_inputs += other.open_inputs;
+        self.open_outputs += other.open_outputs;
+        for (count, other) in self
+            .read_latency_histogram
+            .iter_mut()
+            .zip(&other.read_latency_histogram)
+        {
+            *count += *other;
+        }
+    }
+}
+
+/// The IO done through a `MetricsDirectoryWrapper`, per kind of `IOContext`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryStats {
+    contexts: [IOContextStats; 4],
+}
+
+impl DirectoryStats {
+    pub fn get(&self, kind: IOContextKind) -> &IOContextStats {
+        &self.contexts[kind.index()]
+    }
+
+    /// The stats summed over all the contexts.
+    pub fn total(&self) -> IOContextStats {
+        let mut total = IOContextStats::default();
+        for stats in &self.contexts {
+            total.merge(stats);
+        }
+        total
+    }
+}
+
+#[derive(Default)]
+struct ContextCounters {
+    bytes_read: Counter,
+    bytes_written: Counter,
+    inputs_opened: Counter,
+    outputs_opened: Counter,
+    open_inputs: Counter,
+    open_outputs: Counter,
+    read_latency_histogram: [Counter; NUM_LATENCY_BUCKETS],
+}
+
+impl ContextCounters {
+    fn add_read_latency(&self, start: Instant) {
+        let micros = start.elapsed().as_micros() as u64;
+        let bucket = READ_LATENCY_BUCKETS_MICROS
+            .iter()
+            .position(|&bound| micros < bound)
+            .unwrap_or(READ_LATENCY_BUCKETS_MICROS.len());
+        self.read_latency_histogram[bucket].add_get(1);
+    }
+
+    fn snapshot(&self) -> IOContextStats {
+        let mut read_latency_histogram = [0; NUM_LATENCY_BUCKETS];
+        for (count, counter) in read_latency_histogram
+            .iter_mut()
+            .zip(&self.read_latency_histogram)
+        {
+            *count = counter.get() as u64;
+        }
+        IOContextStats {
+            bytes_read: self.bytes_read.get() as u64,
+            bytes_written: self.bytes_written.get() as u64,
+            inputs_opened: self.inputs_opened.get() as u64,
+            outputs_opened: self.outputs_opened.get() as u64,
+            open_inputs: self.open_inputs.get() as u64,
+            open_outputs: self.open_outputs.get() as u64,
+            read_latency_histogram,
+        }
+    }
+}
+
+/// A `Directory` wrapper accounting the bytes read and written, the opened
+/// files and the read latencies of the wrapped directory per kind of
+/// `IOContext`, e.g. to tell the IO of merges from the one of searches for
+/// capacity planning. See `stats`.
+///
+/// Reads through `get_and_advance` and random access slices are counted but
+/// not timed.
+pub struct MetricsDirectoryWrapper<D: Directory> {
+    directory: D,
+    counters: Arc<[ContextCounters; 4]>,
+}
+
+impl<D: Directory> MetricsDirectoryWrapper<D> {
+    pub fn new(directory: D) -> Self {
+        MetricsDirectoryWrapper {
+            directory,
+            counters: Arc::new(Default::default()),
+        }
+    }
+
+    pub fn directory(&self) -> &D {
+        &self.directory
+    }
+
+    /// Returns a snapshot of the IO done since this wrapper was created.
+    pub fn stats(&self) -> DirectoryStats {
+        let mut stats = DirectoryStats::default();
+        for kind in &IOContextKind::ALL {
+            stats.contexts[kind.index()] = self.counters[kind.index()].snapshot();
+        }
+        stats
+    }
+
+    fn wrap_output<O: IndexOutput>(&self, output: O, ctx: &IOContext) -> MetricsIndexOutput<O> {
+        let kind = IOContextKind::from(ctx);
+        let counters = &self.counters[kind.index()];
+        counters.outputs_opened.add_get(1);
+        counters.open_outputs.add_get(1);
+        MetricsIndexOutput {
+            delegate: output,
+            counters: Arc::clone(&self.counters),
+            kind,
+        }
+    }
+}
+
+impl<D: Directory> FilterDirectory for MetricsDirectoryWrapper<D> {
+    type Dir = D;
+
+    #[inline]
+    fn dir(&self) -> &Self::Dir {
+        &self.directory
+    }
+}
+
+impl<D: Directory> Directory for MetricsDirectoryWrapper<D> {
+    type IndexOutput = MetricsIndexOutput<D::IndexOutput>;
+    type TempOutput = MetricsIndexOutput<D::TempOutput>;
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        let output = self.directory.create_output(name, ctx)?;
+        Ok(self.wrap_output(output, ctx))
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        let input = self.directory.open_input(name, ctx)?;
+        let kind = IOContextKind::from(ctx);
+        let counters = &self.counters[kind.index()];
+        counters.inputs_opened.add_get(1);
+        counters.open_inputs.add_get(1);
+        Ok(Box::new(MetricsIndexInput {
+            delegate: input,
+            counters: Arc::clone(&self.counters),
+            kind,
+            owner: true,
+        }))
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        let output = self.directory.create_temp_output(prefix, suffix, ctx)?;
+        Ok(self.wrap_output(output, ctx))
+    }
+}
+
+impl<D: Directory> fmt::Display for MetricsDirectoryWrapper<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MetricsDirectoryWrapper({})", self.directory)
+    }
+}
+
+/// `IndexOutput` of a `MetricsDirectoryWrapper`.
+pub struct MetricsIndexOutput<O: IndexOutput> {
+    delegate: O,
+    counters: Arc<[ContextCounters; 4]>,
+    kind: IOContextKind,
+}
+
+impl<O: IndexOutput> MetricsIndexOutput<O> {
+    fn counters(&self) -> &ContextCounters {
+        &self.counters[self.kind.index()]
+    }
+}
+
+impl<O: IndexOutput> IndexOutput for MetricsIndexOutput<O> {
+    fn name(&self) -> &str {
+        self.delegate.name()
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.delegate.file_pointer()
+    }
+
+    fn checksum(&self) -> Result<i64> {
+        self.delegate.checksum()
+    }
+}
+
+impl<O: IndexOutput> DataOutput for MetricsIndexOutput<O> {}
+
+impl<O: IndexOutput> io::Write for MetricsIndexOutput<O> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.delegate.write(buf)?;
+        self.counters().bytes_written.add_get(written as i64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.delegate.flush()
+    }
+}
+
+impl<O: IndexOutput> Drop for MetricsIndexOutput<O> {
+    fn drop(&mut self) {
+        self.counters().open_outputs.add_get(-1);
+    }
+}
+
+struct MetricsIndexInput {
+    delegate: Box<dyn IndexInput>,
+    counters: Arc<[ContextCounters; 4]>,
+    kind: IOContextKind,
+    // clones and slices don't count as open files
+    owner: bool,
+}
+
+impl MetricsIndexInput {
+    fn counters(&self) -> &ContextCounters {
+        &self.counters[self.kind.index()]
+    }
+
+    fn derive(&self, delegate: Box<dyn IndexInput>) -> Box<dyn IndexInput> {
+        Box::new(MetricsIndexInput {
+            delegate,
+            counters: Arc::clone(&self.counters),
+            kind: self.kind,
+            owner: false,
+        })
+    }
+}
+
+impl IndexInput for MetricsIndexInput {
+    fn clone(&self) -> Result<Box<dyn IndexInput>> {
+        Ok(self.derive(self.delegate.clone()?))
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.delegate.file_pointer()
+    }
+
+    fn seek(&mut self, pos: i64) -> Result<()> {
+        self.delegate.seek(pos)
+    }
+
+    fn len(&self) -> u64 {
+        self.delegate.len()
+    }
+
+    fn name(&self) -> &str {
+        self.delegate.name()
+    }
+
+    fn random_access_slice(&self, offset: i64, length: i64) -> Result<Box<dyn RandomAccessInput>> {
+        Ok(Box::new(MetricsRandomAccessInput {
+            delegate: self.delegate.random_access_slice(offset, length)?,
+            counters: Arc::clone(&self.counters),
+            kind: self.kind,
+        }))
+    }
+
+    fn slice(&self, description: &str, offset: i64, length: i64) -> Result<Box<dyn IndexInput>> {
+        Ok(self.derive(self.delegate.slice(description, offset, length)?))
+    }
+
+    fn prefill(&mut self, length: usize) -> Result<()> {
+        self.delegate.prefill(length)
+    }
+
+    unsafe fn get_and_advance(&mut self, length: usize) -> *const u8 {
+        self.counters().bytes_read.add_get(length as i64);
+        self.delegate.get_and_advance(length)
+    }
+
+    fn is_buffered(&self) -> bool {
+        self.delegate.is_buffered()
+    }
+}
+
+impl DataInput for MetricsIndexInput {
+    fn read_byte(&mut self) -> Result<u8> {
+        let b = self.delegate.read_byte()?;
+        self.counters().bytes_read.add_get(1);
+        Ok(b)
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<()> {
+        self.delegate.skip_bytes(count)
+    }
+}
+
+impl Read for MetricsIndexInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        let read = self.delegate.read(buf)?;
+        let counters = &self.counters[self.kind.index()];
+        counters.add_read_latency(start);
+        counters.bytes_read.add_get(read as i64);
+        Ok(read)
+    }
+}
+
+impl Drop for MetricsIndexInput {
+    fn drop(&mut self) {
+        if self.owner {
+            self.counters().open_inputs.add_get(-1);
+        }
+    }
+}
+
+struct MetricsRandomAccessInput {
+    delegate: Box<dyn RandomAccessInput>,
+    counters: Arc<[ContextCounters; 4]>,
+    kind: IOContextKind,
+}
+
+impl MetricsRandomAccessInput {
+    fn count(&self, bytes: u64) {
+        self.counters[self.kind.index()]
+            .bytes_read
+            .add_get(bytes as i64);
+    }
+}
+
+impl RandomAccessInput for MetricsRandomAccessInput {
+    fn read_byte(&self, pos: u64) -> Result<u8> {
+        self.count(1);
+        self.delegate.read_byte(pos)
+    }
+
+    fn read_short(&self, pos: u64) -> Result<i16> {
+        self.count(2);
+        self.delegate.read_short(pos)
+    }
+
+    fn read_int(&self, pos: u64) -> Result<i32> {
+        self.count(4);
+        self.delegate.read_int(pos)
+    }
+
+    fn read_long(&self, pos: u64) -> Result<i64> {
+        self.count(8);
+        self.delegate.read_long(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::store::{FlushInfo, MergeInfo};
+
+    #[test]
+    fn test_stats_per_context() -> Result<()> {
+        let dir = MetricsDirectoryWrapper::new(ByteBuffersDirectory::new());
+        let flush = IOContext::Flush(FlushInfo::new(1));
+        {
+            let mut output = dir.create_output("a", &flush)?;
+            output.write_bytes(&[1u8; 100], 0, 100)?;
+            output.write_int(7)?;
+            assert_eq!(dir.stats().get(IOContextKind::Flush).open_outputs, 1);
+        }
+
+        let mut input = dir.open_input("a", &IOContext::READ)?;
+        let mut buf = [0u8; 100];
+        input.read_bytes(&mut buf, 0, 100)?;
+        assert_eq!(input.read_int()?, 7);
+        {
+            let mut clone = input.clone()?;
+            clone.seek(0)?;
+            clone.read_byte()?;
+            let slice = input.random_access_slice(0, 104)?;
+            slice.read_long(0)?;
+        }
+
+        let merge = IOContext::Merge(MergeInfo::new(1, 104, false, None));
+        let mut merge_input = dir.open_input("a", &merge)?;
+        merge_input.skip_bytes(50)?;
+        merge_input.read_bytes(&mut buf, 0, 54)?;
+
+        let stats = dir.stats();
+        let flush_stats = stats.get(IOContextKind::Flush);
+        assert_eq!(flush_stats.bytes_written, 104);
+        assert_eq!(flush_stats.outputs_opened, 1);
+        assert_eq!(flush_stats.open_outputs, 0);
+        assert_eq!(flush_stats.bytes_read, 0);
+
+        let search_stats = stats.get(IOContextKind::Search);
+        assert_eq!(search_stats.bytes_read, 104 + 1 + 8);
+        assert_eq!(search_stats.inputs_opened, 1);
+        assert_eq!(search_stats.open_inputs, 1);
+        assert!(search_stats.read_latency_histogram.iter().sum::<u64>() > 0);
+
+        let merge_stats = stats.get(IOContextKind::Merge);
+        assert_eq!(merge_stats.bytes_read, 54);
+        assert_eq!(merge_stats.open_inputs, 1);
+
+        drop(input);
+        drop(merge_input);
+        let total = dir.stats().total();
+        assert_eq!(total.open_inputs, 0);
+        assert_eq!(total.inputs_opened, 2);
+        assert_eq!(total.bytes_read, 104 + 1 + 8 + 54);
+        assert_eq!(total.bytes_written, 104);
+        Ok(())
+    }
+}
@@ -20,8 +20,11 @@ use std::sync::{Arc, Mutex, Weak};
 use memmap2::Mmap;
 
 use crate::core::store::directory::{Directory, FSDirectory, FilterDirectory};
-use crate::core::store::io::{FSIndexOutput, IndexInput, MmapIndexInput, ReadOnlySource};
-use crate::core::store::IOContext;
+use crate::core::store::io::{
+    ByteBuffersIndexInput, FSIndexOutput, IndexInput, MmapIndexInput, ReadOnlySource,
+};
+use crate::core::store::lock::LockFactory;
+use crate::core::store::{IOContext, IOContextKind, ReadAdvice};
 use crate::Result;
 
 #[derive(Default, Clone, Debug)]
@@ -67,7 +70,29 @@ impl MmapCache {
         }
     }
 
-    fn get_mmap(&mut self, full_path: &PathBuf) -> Result<Option<Arc<Mmap>>> {
+    // `advice` and `lock` only apply to new mappings, the cached ones keep theirs
+    fn prepare(mmap: &Mmap, advice: ReadAdvice, lock: bool, full_path: &Path) {
+        MmapIndexInput::advise(mmap, advice);
+        #[cfg(unix)]
+        {
+            if lock {
+                if let Err(e) = mmap.lock() {
+                    log::warn!(
+                        "failed to mlock {:?}, check RLIMIT_MEMLOCK: {:?}",
+                        full_path,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    fn get_mmap(
+        &mut self,
+        full_path: &PathBuf,
+        advice: ReadAdvice,
+        lock: bool,
+    ) -> Result<Option<Arc<Mmap>>> {
         // if we exceed this limit, then we go through the weak
         // and remove those that are obsolete.
         if self.cache.len() > self.purge_weak_limit {
@@ -83,6 +108,7 @@ impl MmapCache {
                     // The entry exists but the weak ref has been destroyed.
                     self.stat.miss_weak += 1;
                     if let Some(mmap) = MmapIndexInput::mmap(&full_path, 0, 0)? {
+                        Self::prepare(&mmap, advice, lock, full_path);
                         occupied.insert(Arc::downgrade(&mmap));
                         Ok(Some(mmap))
                     } else {
@@ -94,6 +120,7 @@ impl MmapCache {
             HashMapEntry::Vacant(vacant) => {
                 self.stat.miss_empty += 1;
                 if let Some(mmap) = MmapIndexInput::mmap(&full_path, 0, 0)? {
+                    Self::prepare(&mmap, advice, lock, full_path);
                     vacant.insert(Arc::downgrade(&mmap));
                     Ok(Some(mmap))
                 } else {
@@ -149,17 +176,84 @@ pub struct MmapDirectory {
     directory: FSDirectory,
     pub preload: bool,
     mmap_cache: Arc<Mutex<MmapCache>>,
+    preload_modes: HashMap<String, PreloadMode>,
+    // the files loaded by `PreloadMode::Read`, shared by their inputs
+    preloaded: Arc<Mutex<HashMap<PathBuf, Weak<Vec<u8>>>>>,
+}
+
+/// The extensions of the terms dictionaries and points index files, the
+/// files usually worth preloading with `MmapDirectory::with_preload`.
+pub const HOT_FILE_EXTENSIONS: [&str; 3] = ["tip", "tim", "dii"];
+
+/// How `MmapDirectory` loads a file opened for searching, so that the first
+/// queries don't wait for the page cache to warm up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadMode {
+    /// Reads the whole file into memory when it is first opened.
+    Read,
+    /// Maps the file and locks its pages in memory with `mlock`. Unix only,
+    /// locking more than `RLIMIT_MEMLOCK` fails with a warning.
+    Lock,
 }
 
 impl MmapDirectory {
     pub fn new<T: AsRef<Path>>(directory: &T) -> Result<MmapDirectory> {
-        let directory = FSDirectory::new(directory)?;
+        Self::from_fs_directory(FSDirectory::new(directory)?)
+    }
+
+    pub fn with_lock_factory<T: AsRef<Path>>(
+        directory: &T,
+        lock_factory: Arc<dyn LockFactory>,
+    ) -> Result<MmapDirectory> {
+        Self::from_fs_directory(FSDirectory::with_lock_factory(directory, lock_factory)?)
+    }
+
+    fn from_fs_directory(directory: FSDirectory) -> Result<MmapDirectory> {
         Ok(MmapDirectory {
             directory,
             preload: false,
             mmap_cache: Arc::new(Mutex::new(MmapCache::default())),
+            preload_modes: HashMap::new(),
+            preloaded: Arc::new(Mutex::new(HashMap::new())),
         })
     }
+
+    /// Preloads the files with the given extension, e.g. one of
+    /// `HOT_FILE_EXTENSIONS`, when they are opened for searching. Files
+    /// inside compound files are loaded along with the `cfs` file.
+    pub fn with_preload(mut self, extension: &str, mode: PreloadMode) -> Self {
+        self.preload_modes.insert(extension.to_string(), mode);
+        self
+    }
+
+    /// Sets the size of the write buffer of the outputs created in the
+    /// contexts of `kind`, see `FSDirectory::with_output_buffer_size`.
+    pub fn with_output_buffer_size(mut self, kind: IOContextKind, size: usize) -> Self {
+        self.directory = self.directory.with_output_buffer_size(kind, size);
+        self
+    }
+
+    /// Sets the number of threads the files of a commit are synced with.
+    pub fn with_sync_threads(mut self, threads: usize) -> Self {
+        self.directory = self.directory.with_sync_threads(threads);
+        self
+    }
+
+    pub fn preload_mode(&self, name: &str) -> Option<PreloadMode> {
+        let extension = name.rsplit('.').next()?;
+        self.preload_modes.get(extension).copied()
+    }
+
+    fn read_preloaded(&self, full_path: &Path) -> Result<Arc<Vec<u8>>> {
+        let mut preloaded = self.preloaded.lock()?;
+        if let Some(data) = preloaded.get(full_path).and_then(Weak::upgrade) {
+            return Ok(data);
+        }
+        preloaded.retain(|_, data| data.strong_count() > 0);
+        let data = Arc::new(std::fs::read(full_path)?);
+        preloaded.insert(full_path.to_path_buf(), Arc::downgrade(&data));
+        Ok(data)
+    }
 }
 
 impl FilterDirectory for MmapDirectory {
@@ -179,11 +273,22 @@ impl Directory for MmapDirectory {
         self.directory.create_output(name, context)
     }
 
-    fn open_input(&self, name: &str, _ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
         let full_path = self.directory.resolve(name);
+        let advice = ctx.read_advice(name);
+        if advice == ReadAdvice::Sequential {
+            // a mapping of its own, the sequential hint must not apply to the
+            // cached mapping shared with the searches
+            return Ok(Box::new(MmapIndexInput::with_advice(full_path, advice)?));
+        }
+        let preload_mode = self.preload_mode(name);
+        if preload_mode == Some(PreloadMode::Read) {
+            let data = self.read_preloaded(&full_path)?;
+            return Ok(Box::new(ByteBuffersIndexInput::new(data, name)));
+        }
         let mut mmap_cache = self.mmap_cache.lock()?;
         let boxed = mmap_cache
-            .get_mmap(&full_path)?
+            .get_mmap(&full_path, advice, preload_mode == Some(PreloadMode::Lock))?
             .map(ReadOnlySource::from)
             .map(MmapIndexInput::from)
             .unwrap();
@@ -205,3 +310,96 @@ impl fmt::Display for MmapDirectory {
         write!(f, "MmapDirectory({})", self.directory)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::DEFAULT_BULK_OUTPUT_BUFFER_SIZE;
+    use crate::core::store::io::DataOutput;
+    use crate::core::store::{FlushInfo, MergeInfo};
+
+    #[test]
+    fn test_merge_inputs_bypass_mmap_cache() -> Result<()> {
+        let path = std::env::temp_dir().join("test_rucene_mmap_read_advice");
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        let dir = MmapDirectory::new(&path)?;
+        {
+            let mut output = dir.create_output("_0_Lucene50_0.tim", &IOContext::Default)?;
+            output.write_long(42)?;
+        }
+
+        let merge = IOContext::Merge(MergeInfo::new(1, 8, false, None));
+        assert_eq!(
+            dir.open_input("_0_Lucene50_0.tim", &merge)?.read_long()?,
+            42
+        );
+        assert_eq!(dir.mmap_cache.lock()?.stat.miss_empty, 0);
+
+        let _input = dir.open_input("_0_Lucene50_0.tim", &IOContext::READ)?;
+        let mut input = dir.open_input("_0_Lucene50_0.tim", &IOContext::READ)?;
+        assert_eq!(input.read_long()?, 42);
+        let cache = dir.mmap_cache.lock()?;
+        assert_eq!((cache.stat.miss_empty, cache.stat.hit), (1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_preload() -> Result<()> {
+        let path = std::env::temp_dir().join("test_rucene_mmap_preload");
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        let dir = MmapDirectory::new(&path)?
+            .with_preload("tip", PreloadMode::Read)
+            .with_preload("tim", PreloadMode::Lock);
+        assert_eq!(
+            dir.preload_mode("_0_Lucene50_0.tip"),
+            Some(PreloadMode::Read)
+        );
+        assert_eq!(dir.preload_mode("_0.fdt"), None);
+        for name in &["_0_Lucene50_0.tip", "_0_Lucene50_0.tim"] {
+            let mut output = dir.create_output(name, &IOContext::Default)?;
+            output.write_long(42)?;
+        }
+
+        // merges read the files as usual
+        let merge = IOContext::Merge(MergeInfo::new(1, 8, false, None));
+        assert_eq!(
+            dir.open_input("_0_Lucene50_0.tip", &merge)?.read_long()?,
+            42
+        );
+        assert!(dir.preloaded.lock()?.is_empty());
+
+        let mut tip = dir.open_input("_0_Lucene50_0.tip", &IOContext::READ)?;
+        let mut tip2 = dir.open_input("_0_Lucene50_0.tip", &IOContext::READ)?;
+        assert_eq!((tip.read_long()?, tip2.read_long()?), (42, 42));
+        {
+            let preloaded = dir.preloaded.lock()?;
+            assert_eq!(preloaded.len(), 1);
+            let data = preloaded.values().next().unwrap();
+            assert_eq!(data.strong_count(), 2);
+        }
+
+        let mut tim = dir.open_input("_0_Lucene50_0.tim", &IOContext::READ)?;
+        assert_eq!(tim.read_long()?, 42);
+        assert_eq!(dir.mmap_cache.lock()?.stat.miss_empty, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_buffer_size() -> Result<()> {
+        let path = std::env::temp_dir().join("test_rucene_mmap_output_buffer_size");
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        let dir = MmapDirectory::new(&path)?.with_output_buffer_size(IOContextKind::Default, 1024);
+        let flush = IOContext::Flush(FlushInfo::new(8));
+        let output = dir.create_output("_0.fdt", &flush)?;
+        assert_eq!(output.buffer_size(), DEFAULT_BULK_OUTPUT_BUFFER_SIZE);
+        let output = dir.create_temp_output("_0", "fdt", &IOContext::Default)?;
+        assert_eq!(output.buffer_size(), 1024);
+        Ok(())
+    }
+}
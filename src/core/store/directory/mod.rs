@@ -17,12 +17,69 @@ pub use self::directory::{Directory, FilterDirectory, LockValidatingDirectoryWra
 
 mod fs_directory;
 
-pub use self::fs_directory::FSDirectory;
+pub use self::fs_directory::{FSDirectory, DEFAULT_BULK_OUTPUT_BUFFER_SIZE};
 
 mod mmap_directory;
 
-pub use self::mmap_directory::MmapDirectory;
+pub use self::mmap_directory::{MmapDirectory, PreloadMode, HOT_FILE_EXTENSIONS};
+
+mod byte_buffers_directory;
+
+pub use self::byte_buffers_directory::{ByteBuffersDirectory, ByteBuffersIndexOutput};
+
+mod nrt_caching_directory;
+
+pub use self::nrt_caching_directory::{NRTCachingDirectory, NRTCachingIndexOutput};
+
+mod file_switch_directory;
+
+pub use self::file_switch_directory::{FileSwitchDirectory, FileSwitchIndexOutput};
+
+mod object_store_directory;
+
+pub use self::object_store_directory::{
+    InMemoryObjectStore, ObjectStore, ObjectStoreDirectory, DEFAULT_BLOCK_CACHE_BYTES,
+    DEFAULT_BLOCK_SIZE,
+};
+
+mod async_object_store;
+
+pub use self::async_object_store::{
+    block_on, join_all, AsyncObjectStore, BlockingObjectStore, BoxFuture, JoinAll,
+};
+
+mod rate_limited_directory_wrapper;
+
+pub use self::rate_limited_directory_wrapper::RateLimitedDirectoryWrapper;
 
 mod tracking_directory_wrapper;
 
 pub use self::tracking_directory_wrapper::TrackingDirectoryWrapper;
+
+mod no_sync_directory_wrapper;
+
+pub use self::no_sync_directory_wrapper::NoSyncDirectoryWrapper;
+
+mod sleeping_lock_wrapper;
+
+pub use self::sleeping_lock_wrapper::{
+    obtain_lock_with_timeout, SleepingLockWrapper, DEFAULT_POLL_INTERVAL, LOCK_OBTAIN_WAIT_FOREVER,
+    MAX_POLL_INTERVAL,
+};
+
+mod metrics_directory_wrapper;
+
+pub use self::metrics_directory_wrapper::{
+    DirectoryStats, IOContextStats, MetricsDirectoryWrapper, MetricsIndexOutput,
+    READ_LATENCY_BUCKETS_MICROS,
+};
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring_directory;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use self::uring_directory::UringDirectory;
+
+mod sync_scheduler;
+
+pub use self::sync_scheduler::{SyncScheduler, DEFAULT_SYNC_THREADS};
@@ -0,0 +1,93 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::store::directory::{Directory, FilterDirectory};
+use crate::core::store::io::IndexInput;
+use crate::core::store::IOContext;
+
+use crate::Result;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+
+/// A delegating Directory whose `sync` and `sync_metadata` do nothing.
+///
+/// Used by `IndexWriter` to commit with `CommitDurability::NoSync`, the
+/// commits are only as durable as the OS page cache.
+pub struct NoSyncDirectoryWrapper<D: Directory, T: Deref<Target = D>> {
+    pub directory: T,
+}
+
+impl<D: Directory, T: Deref<Target = D>> NoSyncDirectoryWrapper<D, T> {
+    pub fn new(directory: T) -> NoSyncDirectoryWrapper<D, T> {
+        NoSyncDirectoryWrapper { directory }
+    }
+}
+
+impl<D, T> FilterDirectory for NoSyncDirectoryWrapper<D, T>
+where
+    D: Directory,
+    T: Deref<Target = D>,
+{
+    type Dir = D;
+
+    #[inline]
+    fn dir(&self) -> &Self::Dir {
+        &self.directory
+    }
+}
+
+impl<D, T> Directory for NoSyncDirectoryWrapper<D, T>
+where
+    D: Directory,
+    T: Deref<Target = D>,
+{
+    type IndexOutput = D::IndexOutput;
+    type TempOutput = D::TempOutput;
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        self.directory.create_output(name, ctx)
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        self.directory.open_input(name, ctx)
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.directory.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn sync(&self, _names: &HashSet<String>) -> Result<()> {
+        Ok(())
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<D, T> fmt::Display for NoSyncDirectoryWrapper<D, T>
+where
+    D: Directory,
+    T: Deref<Target = D>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NoSyncDirectoryWrapper({})", *self.directory)
+    }
+}
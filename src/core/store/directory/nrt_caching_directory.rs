@@ -0,0 +1,245 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+use crate::core::store::directory::{ByteBuffersDirectory, ByteBuffersIndexOutput, Directory};
+use crate::core::store::io::{DataOutput, IndexInput, IndexOutput};
+use crate::core::store::lock::Lock;
+use crate::core::store::IOContext;
+use crate::Result;
+
+/// Wraps a `Directory`, keeping small newly flushed or merged segment files in RAM.
+///
+/// Files are only written to the delegate once they are synced (on commit), or renamed,
+/// so near-real-time reopens on a high update rate avoid hitting the disk for the tiny
+/// segments they create. Writes are cached when the estimated size of the flushed or
+/// merged segment is at most `max_merge_size_mb` and the cache would stay under
+/// `max_cached_mb`; everything else goes straight to the delegate.
+///
+/// Cached files not synced when the directory is dropped are written to the delegate.
+pub struct NRTCachingDirectory<D: Directory> {
+    delegate: D,
+    cache: ByteBuffersDirectory,
+    max_merge_size_bytes: u64,
+    max_cached_bytes: u64,
+    // serializes moving files out of the cache with lookups against it
+    lock: Mutex<()>,
+}
+
+impl<D: Directory> NRTCachingDirectory<D> {
+    pub fn new(delegate: D, max_merge_size_mb: f64, max_cached_mb: f64) -> Self {
+        NRTCachingDirectory {
+            delegate,
+            cache: ByteBuffersDirectory::new(),
+            max_merge_size_bytes: (max_merge_size_mb * 1024.0 * 1024.0) as u64,
+            max_cached_bytes: (max_cached_mb * 1024.0 * 1024.0) as u64,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn delegate(&self) -> &D {
+        &self.delegate
+    }
+
+    /// Names of the files currently held in RAM.
+    pub fn list_cached_files(&self) -> Result<Vec<String>> {
+        self.cache.list_all()
+    }
+
+    /// Bytes of RAM used by the cached files.
+    pub fn ram_bytes_used(&self) -> Result<usize> {
+        self.cache.ram_bytes_used()
+    }
+
+    fn do_cache_write(&self, ctx: &IOContext) -> Result<bool> {
+        let bytes = match ctx {
+            IOContext::Merge(info) => info.estimated_merge_bytes(),
+            IOContext::Flush(info) => info.estimated_segment_size(),
+            _ => return Ok(false),
+        };
+        Ok(bytes <= self.max_merge_size_bytes
+            && bytes + self.cache.ram_bytes_used()? as u64 <= self.max_cached_bytes)
+    }
+
+    fn un_cache(&self, name: &str) -> Result<()> {
+        let _guard = self.lock.lock()?;
+        if self.cache.file_exists(name)? {
+            self.delegate
+                .copy_from(&self.cache, name, name, &IOContext::Default)?;
+            self.cache.delete_file(name)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Directory> Directory for NRTCachingDirectory<D> {
+    type IndexOutput = NRTCachingIndexOutput<D::IndexOutput>;
+    type TempOutput = D::TempOutput;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        let mut files: BTreeSet<String> = self.cache.list_all()?.into_iter().collect();
+        files.extend(self.delegate.list_all()?);
+        Ok(files.into_iter().collect())
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        let _guard = self.lock.lock()?;
+        if self.cache.file_exists(name)? {
+            self.cache.file_length(name)
+        } else {
+            self.delegate.file_length(name)
+        }
+    }
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        if self.do_cache_write(ctx)? {
+            let _guard = self.lock.lock()?;
+            Ok(NRTCachingIndexOutput::Cache(
+                self.cache.create_output(name, ctx)?,
+            ))
+        } else {
+            Ok(NRTCachingIndexOutput::Delegate(
+                self.delegate.create_output(name, ctx)?,
+            ))
+        }
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        let _guard = self.lock.lock()?;
+        if self.cache.file_exists(name)? {
+            self.cache.open_input(name, ctx)
+        } else {
+            self.delegate.open_input(name, ctx)
+        }
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.delegate.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        let _guard = self.lock.lock()?;
+        if self.cache.file_exists(name)? {
+            self.cache.delete_file(name)
+        } else {
+            self.delegate.delete_file(name)
+        }
+    }
+
+    fn sync(&self, names: &HashSet<String>) -> Result<()> {
+        for name in names {
+            self.un_cache(name)?;
+        }
+        self.delegate.sync(names)
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        self.delegate.sync_metadata()
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        self.un_cache(source)?;
+        self.delegate.rename(source, dest)
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.delegate.obtain_lock(name)
+    }
+}
+
+impl<D: Directory> Drop for NRTCachingDirectory<D> {
+    fn drop(&mut self) {
+        match self.cache.list_all() {
+            Ok(files) => {
+                for name in files {
+                    if let Err(e) = self.un_cache(&name) {
+                        log::warn!(
+                            "NRTCachingDirectory: failed to un-cache '{}': {:?}",
+                            name,
+                            e
+                        );
+                    }
+                }
+            }
+            Err(e) => log::warn!("NRTCachingDirectory: failed to list cached files: {:?}", e),
+        }
+    }
+}
+
+impl<D: Directory> fmt::Display for NRTCachingDirectory<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NRTCachingDirectory({}; max_cache_mb={} max_merge_size_mb={})",
+            self.delegate,
+            self.max_cached_bytes as f64 / 1024.0 / 1024.0,
+            self.max_merge_size_bytes as f64 / 1024.0 / 1024.0
+        )
+    }
+}
+
+/// `IndexOutput` of a `NRTCachingDirectory`, writing either to the cache or the delegate.
+pub enum NRTCachingIndexOutput<O: IndexOutput> {
+    Cache(ByteBuffersIndexOutput),
+    Delegate(O),
+}
+
+impl<O: IndexOutput> IndexOutput for NRTCachingIndexOutput<O> {
+    fn name(&self) -> &str {
+        match self {
+            NRTCachingIndexOutput::Cache(o) => o.name(),
+            NRTCachingIndexOutput::Delegate(o) => o.name(),
+        }
+    }
+
+    fn file_pointer(&self) -> i64 {
+        match self {
+            NRTCachingIndexOutput::Cache(o) => o.file_pointer(),
+            NRTCachingIndexOutput::Delegate(o) => o.file_pointer(),
+        }
+    }
+
+    fn checksum(&self) -> Result<i64> {
+        match self {
+            NRTCachingIndexOutput::Cache(o) => o.checksum(),
+            NRTCachingIndexOutput::Delegate(o) => o.checksum(),
+        }
+    }
+}
+
+impl<O: IndexOutput> DataOutput for NRTCachingIndexOutput<O> {}
+
+impl<O: IndexOutput> io::Write for NRTCachingIndexOutput<O> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            NRTCachingIndexOutput::Cache(o) => o.write(buf),
+            NRTCachingIndexOutput::Delegate(o) => o.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            NRTCachingIndexOutput::Cache(o) => o.flush(),
+            NRTCachingIndexOutput::Delegate(o) => o.flush(),
+        }
+    }
+}
@@ -0,0 +1,637 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::core::search::cache::LRUCache;
+use crate::core::store::directory::{Directory, FSDirectory};
+use crate::core::store::io::{DataInput, FSIndexOutput, IndexInput, RandomAccessInput};
+use crate::core::store::lock::Lock;
+use crate::core::store::IOContext;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Default size of the blocks fetched from the object store and cached, 1MB.
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+/// Default size of the block cache, 256MB.
+pub const DEFAULT_BLOCK_CACHE_BYTES: usize = 256 << 20;
+
+/// Minimal blob storage API needed by `ObjectStoreDirectory`.
+///
+/// Only `InMemoryObjectStore` ships with rucene, remote storage such as S3 needs an
+/// implementation over the user's own client. Keys are flat and objects are written
+/// whole and never modified in place.
+pub trait ObjectStore: Send + Sync + 'static {
+    /// Lists the keys starting with `prefix`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Returns the length of the object, or `None` if it doesn't exist.
+    fn head(&self, key: &str) -> Result<Option<u64>>;
+
+    /// Reads `len` bytes of the object starting at `offset`.
+    fn get_range(&self, key: &str, offset: u64, len: usize) -> Result<Vec<u8>>;
+
+    /// Reads several `(offset, len)` ranges of the object, stores able to issue
+    /// requests concurrently should override this.
+    fn get_ranges(&self, key: &str, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        ranges
+            .iter()
+            .map(|&(offset, len)| self.get_range(key, offset, len))
+            .collect()
+    }
+
+    /// Creates or replaces the object.
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()>;
+
+    /// Deletes the object, deleting a missing object is not an error.
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// An `ObjectStore` keeping objects on the heap, for tests.
+#[derive(Default)]
+pub struct InMemoryObjectStore {
+    objects: RwLock<BTreeMap<String, Arc<Vec<u8>>>>,
+    get_requests: AtomicUsize,
+}
+
+impl InMemoryObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of `get_range` calls served so far.
+    pub fn get_requests(&self) -> usize {
+        self.get_requests.load(Ordering::Acquire)
+    }
+}
+
+impl ObjectStore for InMemoryObjectStore {
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .objects
+            .read()?
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    fn head(&self, key: &str) -> Result<Option<u64>> {
+        Ok(self.objects.read()?.get(key).map(|o| o.len() as u64))
+    }
+
+    fn get_range(&self, key: &str, offset: u64, len: usize) -> Result<Vec<u8>> {
+        self.get_requests.fetch_add(1, Ordering::AcqRel);
+        let objects = self.objects.read()?;
+        let data = objects.get(key).ok_or_else(|| not_found(key))?;
+        let start = offset as usize;
+        if start + len > data.len() {
+            return Err(IllegalArgument(format!(
+                "range {}..{} out of bounds for object '{}' of length {}",
+                start,
+                start + len,
+                key,
+                data.len()
+            )));
+        }
+        Ok(data[start..start + len].to_vec())
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        self.objects
+            .write()?
+            .insert(key.to_string(), Arc::new(data));
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.objects.write()?.remove(key);
+        Ok(())
+    }
+}
+
+fn not_found(name: &str) -> crate::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("file not found: {}", name)).into()
+}
+
+/// Blocks keyed by object key and block index.
+type Blocks = LRUCache<(String, u64), Arc<Vec<u8>>>;
+
+/// LRU cache of fixed size blocks of objects, shared by all inputs of a directory.
+struct BlockCache {
+    blocks: Mutex<Blocks>,
+    block_size: usize,
+}
+
+impl BlockCache {
+    fn new(block_size: usize, cache_bytes: usize) -> Self {
+        BlockCache {
+            blocks: Mutex::new(LRUCache::with_capacity((cache_bytes / block_size).max(1))),
+            block_size,
+        }
+    }
+
+    fn block<O: ObjectStore>(
+        &self,
+        store: &O,
+        key: &str,
+        file_len: u64,
+        index: u64,
+    ) -> Result<Arc<Vec<u8>>> {
+        let cache_key = (key.to_string(), index);
+        if let Some(block) = self.blocks.lock()?.get(&cache_key) {
+            return Ok(Arc::clone(block));
+        }
+        // fetched outside of the lock, concurrent misses on the same block
+        // may both hit the store but the result is the same
+        let start = index * self.block_size as u64;
+        let len = (file_len - start).min(self.block_size as u64) as usize;
+        let block = Arc::new(store.get_range(key, start, len)?);
+        self.blocks.lock()?.insert(cache_key, Arc::clone(&block));
+        Ok(block)
+    }
+
+    /// Loads the given blocks not cached yet with a single `get_ranges` call.
+    fn load_blocks<O: ObjectStore>(
+        &self,
+        store: &O,
+        key: &str,
+        file_len: u64,
+        indices: &BTreeSet<u64>,
+    ) -> Result<()> {
+        let missing: Vec<u64> = {
+            let mut blocks = self.blocks.lock()?;
+            indices
+                .iter()
+                .filter(|&&i| !blocks.contains_key(&(key.to_string(), i)))
+                .cloned()
+                .collect()
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let block_size = self.block_size as u64;
+        let ranges: Vec<(u64, usize)> = missing
+            .iter()
+            .map(|&i| {
+                let start = i * block_size;
+                (start, (file_len - start).min(block_size) as usize)
+            })
+            .collect();
+        let fetched = store.get_ranges(key, &ranges)?;
+        let mut blocks = self.blocks.lock()?;
+        for (index, block) in missing.into_iter().zip(fetched) {
+            blocks.insert((key.to_string(), index), Arc::new(block));
+        }
+        Ok(())
+    }
+}
+
+/// A `Directory` storing the index in blob storage, through an `ObjectStore`.
+///
+/// New files are written to a local staging `FSDirectory` and uploaded when they are
+/// synced, which `IndexWriter` does on commit; the commit only becomes visible once
+/// `pending_segments_N` is renamed to `segments_N`. Files not present locally are read
+/// from the store in blocks, through a shared LRU block cache, so stateless search
+/// nodes can open a commit from the shared store with an empty staging directory.
+///
+/// Index files are write-once, cached blocks are never invalidated.
+pub struct ObjectStoreDirectory<O: ObjectStore> {
+    store: Arc<O>,
+    prefix: String,
+    staging: FSDirectory,
+    block_cache: Arc<BlockCache>,
+}
+
+impl<O: ObjectStore> ObjectStoreDirectory<O> {
+    /// Creates a directory over the objects with keys starting with `prefix`, staging
+    /// new files under `staging_path`.
+    pub fn new<T: AsRef<Path> + ?Sized>(
+        store: Arc<O>,
+        prefix: &str,
+        staging_path: &T,
+    ) -> Result<Self> {
+        Ok(ObjectStoreDirectory {
+            store,
+            prefix: prefix.to_string(),
+            staging: FSDirectory::new(staging_path)?,
+            block_cache: Arc::new(BlockCache::new(
+                DEFAULT_BLOCK_SIZE,
+                DEFAULT_BLOCK_CACHE_BYTES,
+            )),
+        })
+    }
+
+    pub fn with_block_cache(mut self, block_size: usize, cache_bytes: usize) -> Result<Self> {
+        if block_size == 0 {
+            return Err(IllegalArgument("block_size must be > 0".into()));
+        }
+        self.block_cache = Arc::new(BlockCache::new(block_size, cache_bytes));
+        Ok(self)
+    }
+
+    pub fn store(&self) -> &Arc<O> {
+        &self.store
+    }
+
+    /// Warms the block cache with the given `(offset, len)` ranges of a remote file,
+    /// fetching all the missing blocks with one `ObjectStore::get_ranges` call.
+    ///
+    /// Use it before reading scattered parts of a file, e.g. the stored fields of the
+    /// top hits, so a store issuing concurrent requests pays a single round trip.
+    pub fn prefetch(&self, name: &str, ranges: &[(u64, u64)]) -> Result<()> {
+        if self.is_local(name) {
+            return Ok(());
+        }
+        let key = self.key(name);
+        let file_len = self.store.head(&key)?.ok_or_else(|| not_found(name))?;
+        let block_size = self.block_cache.block_size as u64;
+        let indices: BTreeSet<u64> = ranges
+            .iter()
+            .flat_map(|&(offset, len)| {
+                let end = (offset + len).min(file_len);
+                offset / block_size..end.div_ceil(block_size)
+            })
+            .collect();
+        self.block_cache
+            .load_blocks(self.store.as_ref(), &key, file_len, &indices)
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.staging.resolve(name).exists()
+    }
+
+    fn upload(&self, name: &str, key: &str) -> Result<()> {
+        let data = fs::read(self.staging.resolve(name))?;
+        self.store.put(key, data)
+    }
+}
+
+impl<O: ObjectStore> Directory for ObjectStoreDirectory<O> {
+    type IndexOutput = FSIndexOutput;
+    type TempOutput = FSIndexOutput;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        let mut files: BTreeSet<String> = self.staging.list_all()?.into_iter().collect();
+        for key in self.store.list(&self.prefix)? {
+            files.insert(key[self.prefix.len()..].to_string());
+        }
+        Ok(files.into_iter().collect())
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        if self.is_local(name) {
+            return self.staging.file_length(name);
+        }
+        match self.store.head(&self.key(name))? {
+            Some(len) => Ok(len as i64),
+            None => Err(not_found(name)),
+        }
+    }
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        self.staging.create_output(name, ctx)
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        if self.is_local(name) {
+            return self.staging.open_input(name, ctx);
+        }
+        let key = self.key(name);
+        let len = self.store.head(&key)?.ok_or_else(|| not_found(name))?;
+        Ok(Box::new(ObjectStoreIndexInput {
+            store: Arc::clone(&self.store),
+            cache: Arc::clone(&self.block_cache),
+            key: Arc::new(key),
+            file_len: len,
+            name: name.to_string(),
+            offset: 0,
+            len,
+            position: 0,
+            current: None,
+            scratch: Vec::new(),
+            scratch_position: None,
+        }))
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.staging.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        if self.is_local(name) {
+            self.staging.delete_file(name)?;
+        }
+        self.store.delete(&self.key(name))
+    }
+
+    fn sync(&self, names: &HashSet<String>) -> Result<()> {
+        for name in names {
+            if self.is_local(name) {
+                self.upload(name, &self.key(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        let source_key = self.key(source);
+        let uploaded = self.store.head(&source_key)?;
+        if self.is_local(source) {
+            self.staging.rename(source, dest)?;
+            if uploaded.is_some() {
+                self.upload(dest, &self.key(dest))?;
+            }
+        } else if let Some(len) = uploaded {
+            let data = self.store.get_range(&source_key, 0, len as usize)?;
+            self.store.put(&self.key(dest), data)?;
+        } else {
+            return Err(not_found(source));
+        }
+        if uploaded.is_some() {
+            self.store.delete(&source_key)?;
+        }
+        Ok(())
+    }
+
+    /// Locks the local staging directory only, writers on other nodes sharing the
+    /// store are not excluded.
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.staging.obtain_lock(name)
+    }
+}
+
+impl<O: ObjectStore> fmt::Display for ObjectStoreDirectory<O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ObjectStoreDirectory(prefix={}, staging={})",
+            self.prefix, self.staging
+        )
+    }
+}
+
+/// `IndexInput` reading an object through the block cache.
+struct ObjectStoreIndexInput<O: ObjectStore> {
+    store: Arc<O>,
+    cache: Arc<BlockCache>,
+    key: Arc<String>,
+    file_len: u64,
+    name: String,
+    // start and length of this input (or slice) within the object
+    offset: u64,
+    len: u64,
+    position: u64,
+    current: Option<(u64, Arc<Vec<u8>>)>,
+    // holds reads of `get_and_advance` spanning two blocks, prefilled at
+    // `scratch_position`
+    scratch: Vec<u8>,
+    scratch_position: Option<u64>,
+}
+
+impl<O: ObjectStore> ObjectStoreIndexInput<O> {
+    fn slice_impl(&self, description: &str, offset: i64, length: i64) -> Result<Self> {
+        if offset < 0 || length < 0 || (offset + length) as u64 > self.len {
+            return Err(IllegalArgument(format!(
+                "Illegal (offset, length) slice: ({}, {}) for file of length: {}",
+                offset, length, self.len
+            )));
+        }
+        Ok(ObjectStoreIndexInput {
+            store: Arc::clone(&self.store),
+            cache: Arc::clone(&self.cache),
+            key: Arc::clone(&self.key),
+            file_len: self.file_len,
+            name: description.to_string(),
+            offset: self.offset + offset as u64,
+            len: length as u64,
+            position: 0,
+            current: self.current.clone(),
+            scratch: Vec::new(),
+            scratch_position: None,
+        })
+    }
+
+    fn block(&self, index: u64) -> Result<Arc<Vec<u8>>> {
+        self.cache
+            .block(self.store.as_ref(), &self.key, self.file_len, index)
+    }
+
+    /// Reads `buf.len()` bytes at `pos` of this input, without moving the file pointer.
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> Result<()> {
+        if pos + buf.len() as u64 > self.len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read past EOF").into());
+        }
+        let block_size = self.cache.block_size as u64;
+        let mut abs = self.offset + pos;
+        let mut done = 0;
+        while done < buf.len() {
+            let index = abs / block_size;
+            let block = match &self.current {
+                Some((i, b)) if *i == index => Arc::clone(b),
+                _ => self.block(index)?,
+            };
+            let start = (abs - index * block_size) as usize;
+            let count = (buf.len() - done).min(block.len() - start);
+            buf[done..done + count].copy_from_slice(&block[start..start + count]);
+            done += count;
+            abs += count as u64;
+        }
+        Ok(())
+    }
+
+    /// Makes the block holding the current position the current block.
+    fn load_current(&mut self) -> Result<()> {
+        let block_size = self.cache.block_size as u64;
+        let index = (self.offset + self.position) / block_size;
+        if !matches!(&self.current, Some((i, _)) if *i == index) {
+            let block = self.block(index)?;
+            self.current = Some((index, block));
+        }
+        Ok(())
+    }
+}
+
+impl<O: ObjectStore> IndexInput for ObjectStoreIndexInput<O> {
+    fn clone(&self) -> Result<Box<dyn IndexInput>> {
+        let mut input = self.slice_impl(&self.name, 0, self.len as i64)?;
+        input.position = self.position;
+        Ok(Box::new(input))
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.position as i64
+    }
+
+    fn seek(&mut self, pos: i64) -> Result<()> {
+        if pos < 0 || pos as u64 > self.len {
+            return Err(IllegalArgument(format!(
+                "seek past EOF: {} for file of length: {}",
+                pos, self.len
+            )));
+        }
+        self.position = pos as u64;
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn random_access_slice(&self, offset: i64, length: i64) -> Result<Box<dyn RandomAccessInput>> {
+        Ok(Box::new(self.slice_impl(
+            "RandomAccessSlice",
+            offset,
+            length,
+        )?))
+    }
+
+    fn slice(&self, description: &str, offset: i64, length: i64) -> Result<Box<dyn IndexInput>> {
+        Ok(Box::new(self.slice_impl(description, offset, length)?))
+    }
+
+    fn prefill(&mut self, length: usize) -> Result<()> {
+        let block_size = self.cache.block_size as u64;
+        let start = ((self.offset + self.position) % block_size) as usize;
+        if start + length <= block_size as usize {
+            return self.load_current();
+        }
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.resize(length, 0);
+        let res = self.read_at(self.position, &mut scratch);
+        self.scratch = scratch;
+        self.scratch_position = if res.is_ok() {
+            Some(self.position)
+        } else {
+            None
+        };
+        res
+    }
+
+    unsafe fn get_and_advance(&mut self, length: usize) -> *const u8 {
+        debug_assert!(self.position + length as u64 <= self.len);
+        let block_size = self.cache.block_size as u64;
+        let abs = self.offset + self.position;
+        let start = (abs % block_size) as usize;
+        let ptr = if start + length <= block_size as usize {
+            match &self.current {
+                Some((i, block)) if *i == abs / block_size => block.as_ptr().add(start),
+                _ => panic!("get_and_advance of {} bytes not prefilled", length),
+            }
+        } else {
+            assert!(
+                self.scratch_position == Some(self.position) && self.scratch.len() >= length,
+                "get_and_advance of {} bytes not prefilled",
+                length
+            );
+            self.scratch.as_ptr()
+        };
+        self.position += length as u64;
+        ptr
+    }
+}
+
+impl<O: ObjectStore> DataInput for ObjectStoreIndexInput<O> {
+    fn read_byte(&mut self) -> Result<u8> {
+        if self.position >= self.len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read past EOF").into());
+        }
+        self.load_current()?;
+        let block_size = self.cache.block_size as u64;
+        let start = ((self.offset + self.position) % block_size) as usize;
+        self.position += 1;
+        Ok(self.current.as_ref().unwrap().1[start])
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<()> {
+        if self.position + count as u64 > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            )
+            .into());
+        }
+        self.position += count as u64;
+        Ok(())
+    }
+}
+
+impl<O: ObjectStore> Read for ObjectStoreIndexInput<O> {
+    // fills `buf` as far as the end of the input, `DataInput::read_bytes` relies on it
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = (buf.len() as u64).min(self.len - self.position) as usize;
+        let mut done = 0;
+        while done < count {
+            self.load_current()
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            // read up to the end of the current block
+            let (_, block) = self.current.as_ref().unwrap();
+            let start = ((self.offset + self.position) % self.cache.block_size as u64) as usize;
+            let n = (count - done).min(block.len() - start);
+            buf[done..done + n].copy_from_slice(&block[start..start + n]);
+            self.position += n as u64;
+            done += n;
+        }
+        Ok(count)
+    }
+}
+
+impl<O: ObjectStore> RandomAccessInput for ObjectStoreIndexInput<O> {
+    fn read_byte(&self, pos: u64) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_at(pos, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_short(&self, pos: u64) -> Result<i16> {
+        let mut buf = [0u8; 2];
+        self.read_at(pos, &mut buf)?;
+        Ok(i16::from_be_bytes(buf))
+    }
+
+    fn read_int(&self, pos: u64) -> Result<i32> {
+        let mut buf = [0u8; 4];
+        self.read_at(pos, &mut buf)?;
+        Ok(i32::from_be_bytes(buf))
+    }
+
+    fn read_long(&self, pos: u64) -> Result<i64> {
+        let mut buf = [0u8; 8];
+        self.read_at(pos, &mut buf)?;
+        Ok(i64::from_be_bytes(buf))
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{BufferedChecksumIndexInput, IndexInput, RateLimitIndexOutput};
+use crate::core::store::lock::Lock;
+use crate::core::store::{IOContext, RateLimiter, SimpleRateLimiter};
+use crate::Result;
+
+/// A `Directory` wrapper rate limiting the writes of each `IOContext`.
+///
+/// Outputs created for merges, and optionally flushes, are throttled to the
+/// configured MB/sec so that background IO doesn't starve foreground searches;
+/// other outputs are never limited. Rates can be changed at any time and apply to
+/// the outputs already open.
+pub struct RateLimitedDirectoryWrapper<D: Directory> {
+    directory: D,
+    merge_rate_limiter: Arc<SimpleRateLimiter>,
+    flush_rate_limiter: Arc<SimpleRateLimiter>,
+    unlimited: Arc<SimpleRateLimiter>,
+}
+
+impl<D: Directory> RateLimitedDirectoryWrapper<D> {
+    /// Wraps the directory, with no limits set.
+    pub fn new(directory: D) -> Self {
+        RateLimitedDirectoryWrapper {
+            directory,
+            merge_rate_limiter: Arc::new(SimpleRateLimiter::default()),
+            flush_rate_limiter: Arc::new(SimpleRateLimiter::default()),
+            unlimited: Arc::new(SimpleRateLimiter::default()),
+        }
+    }
+
+    pub fn directory(&self) -> &D {
+        &self.directory
+    }
+
+    /// Sets the maximum MB/sec merges may write, `f64::INFINITY` for no limit.
+    pub fn set_merge_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        self.merge_rate_limiter.set_mb_per_sec(mb_per_sec)
+    }
+
+    pub fn merge_mb_per_sec(&self) -> f64 {
+        self.merge_rate_limiter.mb_per_sec()
+    }
+
+    /// Sets the maximum MB/sec flushes may write, `f64::INFINITY` for no limit.
+    pub fn set_flush_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        self.flush_rate_limiter.set_mb_per_sec(mb_per_sec)
+    }
+
+    pub fn flush_mb_per_sec(&self) -> f64 {
+        self.flush_rate_limiter.mb_per_sec()
+    }
+
+    fn rate_limiter(&self, ctx: &IOContext) -> &Arc<SimpleRateLimiter> {
+        match ctx {
+            IOContext::Merge(_) => &self.merge_rate_limiter,
+            IOContext::Flush(_) => &self.flush_rate_limiter,
+            _ => &self.unlimited,
+        }
+    }
+}
+
+impl<D: Directory> Directory for RateLimitedDirectoryWrapper<D> {
+    type IndexOutput = RateLimitIndexOutput<D::IndexOutput, SimpleRateLimiter>;
+    type TempOutput = D::TempOutput;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        self.directory.list_all()
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        self.directory.file_length(name)
+    }
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        let output = self.directory.create_output(name, ctx)?;
+        Ok(RateLimitIndexOutput::new(
+            Arc::clone(self.rate_limiter(ctx)),
+            output,
+        ))
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        self.directory.open_input(name, ctx)
+    }
+
+    fn open_checksum_input(
+        &self,
+        name: &str,
+        ctx: &IOContext,
+    ) -> Result<BufferedChecksumIndexInput> {
+        self.directory.open_checksum_input(name, ctx)
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.directory.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        self.directory.delete_file(name)
+    }
+
+    fn sync(&self, name: &HashSet<String>) -> Result<()> {
+        self.directory.sync(name)
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        self.directory.sync_metadata()
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        self.directory.rename(source, dest)
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.directory.obtain_lock(name)
+    }
+}
+
+impl<D: Directory> fmt::Display for RateLimitedDirectoryWrapper<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RateLimitedDirectoryWrapper({})", self.directory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::store::{FlushInfo, MergeInfo};
+
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_rate_limit_per_context() -> Result<()> {
+        let dir = RateLimitedDirectoryWrapper::new(ByteBuffersDirectory::new());
+        assert!(dir.set_merge_mb_per_sec(0.0).is_err());
+        let merge = IOContext::Merge(MergeInfo::new(10, 0, false, None));
+        let flush = IOContext::Flush(FlushInfo::new(10));
+        let chunk = vec![0u8; 64 * 1024];
+
+        // opened while unlimited, the limit set afterwards still applies
+        let mut merge_output = dir.create_output("merge", &merge)?;
+        dir.set_merge_mb_per_sec(10.0)?;
+        let start = Instant::now();
+        for _ in 0..48 {
+            merge_output.write_all(&chunk)?;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        // flushes are not limited
+        let mut flush_output = dir.create_output("flush", &flush)?;
+        let start = Instant::now();
+        for _ in 0..48 {
+            flush_output.write_all(&chunk)?;
+        }
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert!(dir.flush_mb_per_sec().is_infinite());
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::store::directory::{Directory, FilterDirectory};
+use crate::core::store::io::IndexInput;
+use crate::core::store::lock::Lock;
+use crate::core::store::IOContext;
+use crate::error::Error::LockObtainFailed;
+use crate::Result;
+
+/// Pass as the timeout to wait for the lock for as long as it takes.
+pub const LOCK_OBTAIN_WAIT_FOREVER: Duration = Duration::MAX;
+
+/// First sleep between two attempts to obtain the lock, doubled after each
+/// failed attempt up to `MAX_POLL_INTERVAL`.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Obtains the lock `name` of `directory`, retrying with an exponential backoff
+/// starting at `poll_interval` while it is held elsewhere, for at most `timeout`.
+///
+/// A zero `timeout` makes a single attempt. Fails with the last
+/// `Error::LockObtainFailed` once the timeout expired, other errors are
+/// returned right away.
+pub fn obtain_lock_with_timeout<D: Directory + ?Sized>(
+    directory: &D,
+    name: &str,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<Box<dyn Lock>> {
+    let start = Instant::now();
+    // `None` waits forever
+    let deadline = start.checked_add(timeout);
+    let mut sleep = poll_interval.max(Duration::from_millis(1));
+    loop {
+        match directory.obtain_lock(name) {
+            Err(LockObtainFailed(msg)) => {
+                let now = Instant::now();
+                let remaining = match deadline {
+                    Some(deadline) if now >= deadline => {
+                        return Err(LockObtainFailed(if timeout.is_zero() {
+                            msg
+                        } else {
+                            format!("Lock obtain timed out after {:?}: {}", now - start, msg)
+                        }));
+                    }
+                    Some(deadline) => deadline - now,
+                    None => MAX_POLL_INTERVAL,
+                };
+                thread::sleep(sleep.min(remaining));
+                sleep = (sleep * 2).min(MAX_POLL_INTERVAL);
+            }
+            res => return res,
+        }
+    }
+}
+
+/// A `Directory` wrapper retrying to obtain its locks, e.g. the write lock of
+/// an `IndexWriter`, for up to a timeout instead of failing right away when
+/// they are held elsewhere.
+///
+/// Useful when a writer is closed and another one opened, possibly in another
+/// process, and the lock of the first one may not be released yet.
+pub struct SleepingLockWrapper<D: Directory> {
+    directory: D,
+    lock_wait_timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl<D: Directory> SleepingLockWrapper<D> {
+    /// Wraps the directory, waiting up to `lock_wait_timeout` for the locks,
+    /// `LOCK_OBTAIN_WAIT_FOREVER` to never give up.
+    pub fn new(directory: D, lock_wait_timeout: Duration) -> Self {
+        SleepingLockWrapper {
+            directory,
+            lock_wait_timeout,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Sets the first sleep between two attempts, `DEFAULT_POLL_INTERVAL` by
+    /// default.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn lock_wait_timeout(&self) -> Duration {
+        self.lock_wait_timeout
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+}
+
+impl<D: Directory> FilterDirectory for SleepingLockWrapper<D> {
+    type Dir = D;
+
+    #[inline]
+    fn dir(&self) -> &Self::Dir {
+        &self.directory
+    }
+}
+
+impl<D: Directory> Directory for SleepingLockWrapper<D> {
+    type IndexOutput = D::IndexOutput;
+    type TempOutput = D::TempOutput;
+
+    fn create_output(&self, name: &str, ctx: &IOContext) -> Result<Self::IndexOutput> {
+        self.directory.create_output(name, ctx)
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        self.directory.open_input(name, ctx)
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.directory.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        obtain_lock_with_timeout(
+            &self.directory,
+            name,
+            self.lock_wait_timeout,
+            self.poll_interval,
+        )
+    }
+}
+
+impl<D: Directory> fmt::Display for SleepingLockWrapper<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SleepingLockWrapper({})", self.directory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::store::lock::WRITE_LOCK_NAME;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_obtain_lock_with_timeout() -> Result<()> {
+        let dir = Arc::new(SleepingLockWrapper::new(
+            ByteBuffersDirectory::new(),
+            Duration::from_secs(10),
+        ));
+        let lock = dir.obtain_lock(WRITE_LOCK_NAME)?;
+
+        // released while the other thread waits for it
+        let waiter = {
+            let dir = Arc::clone(&dir);
+            thread::spawn(move || dir.obtain_lock(WRITE_LOCK_NAME).map(|l| l.close()))
+        };
+        thread::sleep(Duration::from_millis(50));
+        lock.close()?;
+        waiter.join().unwrap()??;
+
+        // never released
+        let _lock = dir.obtain_lock(WRITE_LOCK_NAME)?;
+        let start = Instant::now();
+        let res = obtain_lock_with_timeout(
+            dir.dir(),
+            WRITE_LOCK_NAME,
+            Duration::from_millis(100),
+            DEFAULT_POLL_INTERVAL,
+        );
+        assert!(matches!(res, Err(LockObtainFailed(_))));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        Ok(())
+    }
+}
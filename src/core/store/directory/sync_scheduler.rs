@@ -0,0 +1,181 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::Result;
+
+/// The default number of threads `SyncScheduler` fsyncs the files with.
+pub const DEFAULT_SYNC_THREADS: usize = 4;
+
+/// Coalesces the fsyncs of a file system directory at commit time.
+///
+/// Index files are written once, so a file synced by an earlier commit is
+/// not synced again until it is recreated. The remaining files are synced
+/// in parallel, and the directory itself is only synced when files were
+/// created, renamed or deleted since the last time.
+pub struct SyncScheduler {
+    threads: usize,
+    synced: Mutex<HashSet<String>>,
+    metadata_dirty: AtomicBool,
+}
+
+impl SyncScheduler {
+    pub fn new(threads: usize) -> SyncScheduler {
+        SyncScheduler {
+            threads: threads.max(1),
+            synced: Mutex::new(HashSet::new()),
+            metadata_dirty: AtomicBool::new(true),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Returns whether `name` was synced and not recreated since.
+    pub fn is_synced(&self, name: &str) -> bool {
+        self.synced.lock().unwrap().contains(name)
+    }
+
+    /// Called when the file `name` is created or overwritten.
+    pub fn on_create(&self, name: &str) {
+        self.synced.lock().unwrap().remove(name);
+        self.metadata_dirty.store(true, Ordering::Release);
+    }
+
+    pub fn on_delete(&self, name: &str) {
+        self.synced.lock().unwrap().remove(name);
+        self.metadata_dirty.store(true, Ordering::Release);
+    }
+
+    /// Called when `source` is renamed to `dest`, the synced content moves
+    /// along with the name.
+    pub fn on_rename(&self, source: &str, dest: &str) {
+        let mut synced = self.synced.lock().unwrap();
+        if synced.remove(source) {
+            synced.insert(dest.to_string());
+        } else {
+            synced.remove(dest);
+        }
+        self.metadata_dirty.store(true, Ordering::Release);
+    }
+
+    /// Syncs the files of `directory` in `names` which aren't synced yet.
+    pub fn sync(&self, directory: &Path, names: &HashSet<String>) -> Result<()> {
+        let to_sync: Vec<&String> = {
+            let synced = self.synced.lock()?;
+            names.iter().filter(|n| !synced.contains(*n)).collect()
+        };
+        if to_sync.len() <= 1 || self.threads == 1 {
+            for name in to_sync {
+                self.sync_file(directory, name)?;
+            }
+            return Ok(());
+        }
+
+        let chunk_size = to_sync.len().div_ceil(self.threads);
+        thread::scope(|s| {
+            let handles: Vec<_> = to_sync
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || -> Result<()> {
+                        for name in chunk {
+                            self.sync_file(directory, name)?;
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            // wait for all the threads before reporting the first error
+            let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            results.into_iter().collect()
+        })
+    }
+
+    /// Syncs `directory`, if its entries changed since it was last synced.
+    pub fn sync_metadata(&self, directory: &Path) -> Result<()> {
+        if self.metadata_dirty.swap(false, Ordering::AcqRel) {
+            if let Err(e) = fsync(directory, true) {
+                self.metadata_dirty.store(true, Ordering::Release);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_file(&self, directory: &Path, name: &str) -> Result<()> {
+        fsync(&directory.join(name), false)?;
+        self.synced.lock()?.insert(name.to_string());
+        Ok(())
+    }
+}
+
+impl Default for SyncScheduler {
+    fn default() -> Self {
+        SyncScheduler::new(DEFAULT_SYNC_THREADS)
+    }
+}
+
+fn fsync(path: &Path, is_dir: bool) -> Result<()> {
+    // Windows can't open a directory to fsync it, NTFS journals its entries instead
+    if is_dir && cfg!(windows) {
+        return Ok(());
+    }
+    // If the file is a directory we have to open read-only, for regular files we must
+    // open r/w for the fsync to have an effect.
+    // See http://blog.httrack.com/blog/2013/11/15/everything-you-always-wanted-to-know-about-fsync/
+    let file = if is_dir {
+        fs::File::open(path)?
+    } else {
+        fs::OpenOptions::new().append(true).open(path)?
+    };
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_skips_synced_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let names: HashSet<String> = (0..10).map(|i| format!("_{}.si", i)).collect();
+        for name in &names {
+            fs::write(dir.path().join(name), name)?;
+        }
+
+        let scheduler = SyncScheduler::new(3);
+        scheduler.sync(dir.path(), &names)?;
+        assert!(names.iter().all(|n| scheduler.is_synced(n)));
+        scheduler.sync_metadata(dir.path())?;
+        assert!(!scheduler.metadata_dirty.load(Ordering::Acquire));
+
+        scheduler.on_rename("_0.si", "_10.si");
+        assert!(scheduler.is_synced("_10.si") && !scheduler.is_synced("_0.si"));
+        scheduler.on_create("_1.si");
+        assert!(!scheduler.is_synced("_1.si"));
+        assert!(scheduler.metadata_dirty.load(Ordering::Acquire));
+
+        let mut missing = HashSet::new();
+        missing.insert("_missing.si".to_string());
+        assert!(scheduler.sync(dir.path(), &missing).is_err());
+        Ok(())
+    }
+}
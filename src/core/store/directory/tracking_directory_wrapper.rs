@@ -23,6 +23,11 @@ use std::ops::Deref;
 use std::sync::Mutex;
 
 /// A delegating Directory that records which files were written to and deleted.
+///
+/// Files created through `create_output`, `create_temp_output`, `copy_from`
+/// or as the target of a `rename` are recorded, deleted ones are forgotten,
+/// so `create_files` lists exactly what an operation like a flush or a merge
+/// left in the directory.
 pub struct TrackingDirectoryWrapper<D: Directory, T: Deref<Target = D>> {
     create_file_names: Mutex<HashSet<String>>,
     pub directory: T,
@@ -39,6 +44,12 @@ impl<D: Directory, T: Deref<Target = D>> TrackingDirectoryWrapper<D, T> {
     pub fn get_create_files(&self) -> HashSet<String> {
         self.create_file_names.lock().unwrap().clone()
     }
+
+    /// Forgets the files created so far, e.g. to track the next operation
+    /// with the same wrapper.
+    pub fn clear_create_files(&self) {
+        self.create_file_names.lock().unwrap().clear();
+    }
 }
 
 impl<D, T> FilterDirectory for TrackingDirectoryWrapper<D, T>
@@ -99,6 +110,18 @@ where
         Ok(())
     }
 
+    fn copy_from<D1: Directory>(
+        &self,
+        from: &D1,
+        src: &str,
+        dest: &str,
+        ctx: &IOContext,
+    ) -> Result<()> {
+        self.directory.copy_from(from, src, dest, ctx)?;
+        self.create_file_names.lock()?.insert(dest.to_string());
+        Ok(())
+    }
+
     fn create_files(&self) -> HashSet<String> {
         self.create_file_names.lock().unwrap().clone()
     }
@@ -113,3 +136,39 @@ where
         write!(f, "TrackingDirectoryWrapper({})", &*self.directory)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::store::io::DataOutput;
+
+    fn names(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_track_created_files() -> Result<()> {
+        let other = ByteBuffersDirectory::new();
+        other
+            .create_output("src", &IOContext::Default)?
+            .write_int(1)?;
+        let dir = TrackingDirectoryWrapper::new(Box::new(ByteBuffersDirectory::new()));
+
+        dir.create_output("a", &IOContext::Default)?.write_int(1)?;
+        dir.create_output("b", &IOContext::Default)?.write_int(2)?;
+        dir.copy_from(&other, "src", "c", &IOContext::Default)?;
+        dir.rename("b", "d")?;
+        dir.delete_file("a")?;
+        assert_eq!(dir.create_files(), names(&["c", "d"]));
+
+        dir.clear_create_files();
+        assert!(dir.get_create_files().is_empty());
+        dir.create_output("e", &IOContext::Default)?;
+        assert_eq!(dir.create_files(), names(&["e"]));
+        let mut all = dir.list_all()?;
+        all.sort();
+        assert_eq!(all, vec!["c", "d", "e"]);
+        Ok(())
+    }
+}
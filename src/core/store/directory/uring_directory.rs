@@ -0,0 +1,154 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::core::store::directory::{Directory, FSDirectory, FilterDirectory};
+use crate::core::store::io::{
+    FSIndexOutput, IndexInput, UringIndexInput, UringReader, DEFAULT_QUEUE_DEPTH,
+};
+use crate::core::store::IOContext;
+use crate::Result;
+
+/// A `FSDirectory` reading its files through io_uring instead of mmap, Linux
+/// only, behind the `io-uring` cargo feature.
+///
+/// Inputs refill their buffer with a single io_uring read, and `read_ranges`
+/// submits the reads of many blocks at once, e.g. the stored fields or
+/// postings blocks of all the hits of a query, so that fast NVMe devices see
+/// them concurrently for the cost of one syscall. Writes go through
+/// `FSDirectory`.
+pub struct UringDirectory {
+    directory: FSDirectory,
+    reader: Arc<UringReader>,
+}
+
+impl UringDirectory {
+    /// Fails if io_uring isn't supported by the kernel, or not allowed.
+    pub fn new<T: AsRef<Path> + ?Sized>(directory: &T) -> Result<UringDirectory> {
+        Self::with_queue_depth(directory, DEFAULT_QUEUE_DEPTH)
+    }
+
+    /// Creates the directory with rings keeping up to `queue_depth` reads in flight.
+    pub fn with_queue_depth<T: AsRef<Path> + ?Sized>(
+        directory: &T,
+        queue_depth: u32,
+    ) -> Result<UringDirectory> {
+        Ok(UringDirectory {
+            directory: FSDirectory::new(directory)?,
+            reader: Arc::new(UringReader::new(queue_depth)?),
+        })
+    }
+
+    /// Reads the `(offset, len)` ranges of the file `name` with one batch of
+    /// io_uring reads.
+    pub fn read_ranges(&self, name: &str, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        self.open_uring_input(name)?.read_ranges(ranges)
+    }
+
+    fn open_uring_input(&self, name: &str) -> Result<UringIndexInput> {
+        let file = File::open(self.directory.resolve(name))?;
+        UringIndexInput::new(Arc::clone(&self.reader), file, name)
+    }
+}
+
+impl FilterDirectory for UringDirectory {
+    type Dir = FSDirectory;
+
+    #[inline]
+    fn dir(&self) -> &Self::Dir {
+        &self.directory
+    }
+}
+
+impl Directory for UringDirectory {
+    type IndexOutput = FSIndexOutput;
+    type TempOutput = FSIndexOutput;
+
+    fn create_output(&self, name: &str, context: &IOContext) -> Result<Self::IndexOutput> {
+        self.directory.create_output(name, context)
+    }
+
+    fn open_input(&self, name: &str, _ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        Ok(Box::new(self.open_uring_input(name)?))
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        self.directory.create_temp_output(prefix, suffix, ctx)
+    }
+}
+
+impl fmt::Display for UringDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UringDirectory({})", self.directory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::io::DataOutput;
+
+    #[test]
+    fn test_uring_reads() -> Result<()> {
+        let path = std::env::temp_dir().join("test_rucene_uring_directory");
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        let dir = UringDirectory::with_queue_depth(&path, 4)?;
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        {
+            let mut output = dir.create_output("data", &IOContext::Default)?;
+            output.write_bytes(&data, 0, data.len())?;
+            output.write_long(42)?;
+        }
+
+        let mut input = dir.open_input("data", &IOContext::READ)?;
+        assert_eq!(input.len(), data.len() as u64 + 8);
+        let mut buf = vec![0u8; data.len()];
+        input.read_bytes(&mut buf, 0, data.len())?;
+        assert_eq!(buf, data);
+        assert_eq!(input.read_long()?, 42);
+        assert!(input.read_byte().is_err());
+
+        input.seek(20_000)?;
+        input.prefill(300)?;
+        let encoded = unsafe { std::slice::from_raw_parts(input.get_and_advance(300), 300) };
+        assert_eq!(encoded, &data[20_000..20_300]);
+        let slice = input.slice("slice", 1000, 5000)?;
+        let mut clone = slice.clone()?;
+        clone.seek(10)?;
+        assert_eq!(clone.read_byte()?, data[1010]);
+        let random = input.random_access_slice(data.len() as i64, 8)?;
+        assert_eq!(random.read_long(0)?, 42);
+
+        // more ranges than the queue depth
+        let ranges: Vec<(u64, usize)> = (0..10).map(|i| (i * 9_000 + 7, 1_000)).collect();
+        let blocks = dir.read_ranges("data", &ranges)?;
+        for (&(offset, len), block) in ranges.iter().zip(&blocks) {
+            assert_eq!(&block[..], &data[offset as usize..offset as usize + len]);
+        }
+        assert!(dir
+            .read_ranges("data", &[(data.len() as u64, 100)])
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::store::io::{DataInput, IndexInput, RandomAccessInput};
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// `IndexInput` reading a file held in memory, cloning and slicing it share the bytes.
+#[derive(Clone)]
+pub struct ByteBuffersIndexInput {
+    data: Arc<Vec<u8>>,
+    offset: usize,
+    len: usize,
+    position: usize,
+    description: String,
+}
+
+impl ByteBuffersIndexInput {
+    pub fn new(data: Arc<Vec<u8>>, description: &str) -> ByteBuffersIndexInput {
+        let len = data.len();
+        ByteBuffersIndexInput {
+            data,
+            offset: 0,
+            len,
+            position: 0,
+            description: description.to_string(),
+        }
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        &self.data[self.offset..self.offset + self.len]
+    }
+
+    fn slice_impl(&self, description: &str, offset: i64, length: i64) -> Result<Self> {
+        if offset < 0 || length < 0 || (offset + length) as usize > self.len {
+            return Err(IllegalArgument(format!(
+                "Illegal (offset, length) slice: ({}, {}) for file of length: {}",
+                offset, length, self.len
+            )));
+        }
+        Ok(ByteBuffersIndexInput {
+            data: Arc::clone(&self.data),
+            offset: self.offset + offset as usize,
+            len: length as usize,
+            position: 0,
+            description: description.to_string(),
+        })
+    }
+
+    #[inline]
+    fn check_random_access(&self, from: u64, len: u64) -> Result<()> {
+        if from + len > self.len as u64 {
+            return Err(IllegalArgument(format!(
+                "invalid position, expecting 0 < pos < {}, got: {}",
+                self.len, from
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl IndexInput for ByteBuffersIndexInput {
+    fn clone(&self) -> Result<Box<dyn IndexInput>> {
+        Ok(Box::new(Clone::clone(self)))
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.position as i64
+    }
+
+    fn seek(&mut self, pos: i64) -> Result<()> {
+        if pos < 0 || pos as usize > self.len {
+            return Err(IllegalArgument(format!(
+                "seek past EOF: {} for file of length: {}",
+                pos, self.len
+            )));
+        }
+        self.position = pos as usize;
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.len as u64
+    }
+
+    fn name(&self) -> &str {
+        &self.description
+    }
+
+    fn random_access_slice(&self, offset: i64, length: i64) -> Result<Box<dyn RandomAccessInput>> {
+        Ok(Box::new(self.slice_impl(
+            "RandomAccessSlice",
+            offset,
+            length,
+        )?))
+    }
+
+    fn slice(&self, description: &str, offset: i64, length: i64) -> Result<Box<dyn IndexInput>> {
+        Ok(Box::new(self.slice_impl(description, offset, length)?))
+    }
+
+    unsafe fn get_and_advance(&mut self, length: usize) -> *const u8 {
+        debug_assert!(self.position + length <= self.len);
+        let ptr = self.as_slice().as_ptr().add(self.position);
+        self.position += length;
+        ptr
+    }
+}
+
+impl DataInput for ByteBuffersIndexInput {
+    fn read_byte(&mut self) -> Result<u8> {
+        if self.position >= self.len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read past EOF").into());
+        }
+        let b = self.data[self.offset + self.position];
+        self.position += 1;
+        Ok(b)
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<()> {
+        if self.position + count > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            )
+            .into());
+        }
+        self.position += count;
+        Ok(())
+    }
+}
+
+impl Read for ByteBuffersIndexInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.offset + self.position;
+        let count = buf.len().min(self.len - self.position);
+        buf[..count].copy_from_slice(&self.data[start..start + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+impl RandomAccessInput for ByteBuffersIndexInput {
+    fn read_byte(&self, pos: u64) -> Result<u8> {
+        self.check_random_access(pos, 1)?;
+        Ok(self.as_slice()[pos as usize])
+    }
+
+    fn read_short(&self, pos: u64) -> Result<i16> {
+        self.check_random_access(pos, 2)?;
+        (&self.as_slice()[pos as usize..]).read_short()
+    }
+
+    fn read_int(&self, pos: u64) -> Result<i32> {
+        self.check_random_access(pos, 4)?;
+        (&self.as_slice()[pos as usize..]).read_int()
+    }
+
+    fn read_long(&self, pos: u64) -> Result<i64> {
+        self.check_random_access(pos, 8)?;
+        (&self.as_slice()[pos as usize..]).read_long()
+    }
+}
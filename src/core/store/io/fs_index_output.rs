@@ -16,35 +16,74 @@ use crate::core::store::io::{DataOutput, IndexOutput};
 use crate::Result;
 
 use std::fs::{File, OpenOptions};
-use std::io::BufWriter;
 use std::io::Write;
 use std::path::Path;
 
-use flate2::CrcWriter;
+use crc32fast::Hasher;
 
-const CHUNK_SIZE: usize = 8192;
+/// The default size of the write buffer of a `FSIndexOutput`.
+pub const DEFAULT_OUTPUT_BUFFER_SIZE: usize = 8192;
 
 /// `IndexOutput` implement for `FsDirectory`
+///
+/// Small writes are buffered, writes larger than the buffer go straight to
+/// the file. The CRC32 of the footer is computed incrementally as the data
+/// is flushed, with the SIMD accelerated implementation of `crc32fast` when
+/// the CPU supports it. The checksum stays CRC32 rather than CRC32C, which
+/// would change the index format.
 pub struct FSIndexOutput {
     name: String,
-    writer: CrcWriter<BufWriter<File>>,
+    file: File,
+    buffer: Vec<u8>,
+    buffer_size: usize,
+    // the crc of the bytes already written to `file`
+    crc: Hasher,
     bytes_written: usize,
 }
 
 impl FSIndexOutput {
     pub fn new<P: AsRef<Path>>(name: String, path: P) -> Result<FSIndexOutput> {
-        let file = OpenOptions::new().write(true).create(true).open(path)?;
+        Self::with_buffer_size(name, path, DEFAULT_OUTPUT_BUFFER_SIZE)
+    }
+
+    pub fn with_buffer_size<P: AsRef<Path>>(
+        name: String,
+        path: P,
+        buffer_size: usize,
+    ) -> Result<FSIndexOutput> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let buffer_size = buffer_size.max(1);
         Ok(FSIndexOutput {
             name,
-            writer: CrcWriter::new(BufWriter::with_capacity(CHUNK_SIZE, file)),
+            file,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size,
+            crc: Hasher::new(),
             bytes_written: 0,
         })
     }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    fn flush_buffer(&mut self) -> ::std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.file.write_all(&self.buffer)?;
+            self.crc.update(&self.buffer);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
 }
 
 impl Drop for FSIndexOutput {
     fn drop(&mut self) {
-        if let Err(ref desc) = self.writer.flush() {
+        if let Err(ref desc) = self.flush() {
             log::error!("Oops, failed to flush {}, errmsg: {}", self.name, desc);
         }
         self.bytes_written = 0;
@@ -55,13 +94,22 @@ impl DataOutput for FSIndexOutput {}
 
 impl Write for FSIndexOutput {
     fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
-        let count = self.writer.write(buf)?;
-        self.bytes_written += count;
-        Ok(count)
+        if self.buffer.len() + buf.len() > self.buffer_size {
+            self.flush_buffer()?;
+        }
+        if buf.len() >= self.buffer_size {
+            self.file.write_all(buf)?;
+            self.crc.update(buf);
+        } else {
+            self.buffer.extend_from_slice(buf);
+        }
+        self.bytes_written += buf.len();
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> ::std::io::Result<()> {
-        self.writer.flush()
+        self.flush_buffer()?;
+        self.file.flush()
     }
 }
 
@@ -75,8 +123,9 @@ impl IndexOutput for FSIndexOutput {
     }
 
     fn checksum(&self) -> Result<i64> {
-        // self.writer.flush()?;
-        Ok((self.writer.crc().sum() as i64) & 0xffff_ffffi64)
+        let mut crc = self.crc.clone();
+        crc.update(&self.buffer);
+        Ok(i64::from(crc.finalize()))
     }
 }
 
@@ -94,4 +143,23 @@ mod tests {
         assert_eq!(fsout.file_pointer(), 1);
         ::std::fs::remove_file("hello.txt").unwrap();
     }
+
+    #[test]
+    fn test_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checksum.bin");
+        let mut data = Vec::new();
+        let mut fsout = FSIndexOutput::with_buffer_size("checksum.bin".into(), &path, 16).unwrap();
+        assert_eq!(fsout.buffer_size(), 16);
+        for i in 0..100usize {
+            // mix writes smaller and larger than the buffer
+            let chunk: Vec<u8> = (0..i % 40).map(|j| (i * 31 + j) as u8).collect();
+            fsout.write_bytes(&chunk, 0, chunk.len()).unwrap();
+            data.extend_from_slice(&chunk);
+            assert_eq!(fsout.checksum().unwrap(), i64::from(crc32fast::hash(&data)));
+        }
+        assert_eq!(fsout.file_pointer(), data.len() as i64);
+        drop(fsout);
+        assert_eq!(::std::fs::read(&path).unwrap(), data);
+    }
 }
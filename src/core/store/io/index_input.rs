@@ -33,6 +33,18 @@ pub trait IndexInput: DataInput + Send + Sync {
         unimplemented!();
     }
 
+    /// Makes the next `length` bytes available to `get_and_advance`, inputs loading
+    /// data lazily read it here so that IO errors are returned to the caller.
+    fn prefill(&mut self, _length: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns a pointer to the next `length` bytes and moves past them.
+    ///
+    /// # Safety
+    ///
+    /// `prefill(length)` must have succeeded at the current position, and the bytes
+    /// are only valid until the input is read or moved again.
     unsafe fn get_and_advance(&mut self, _length: usize) -> *const u8 {
         unimplemented!()
     }
@@ -93,6 +93,8 @@ impl IndexOutput for InvalidIndexOutput {
     }
 }
 
+const MAX_PAUSE_CHECK_BYTES: u64 = 1024 * 1024;
+
 /// a rate limiting `IndexOutput`
 pub struct RateLimitIndexOutput<O: IndexOutput, RL: RateLimiter + ?Sized> {
     delegate: O,
@@ -106,7 +108,7 @@ pub struct RateLimitIndexOutput<O: IndexOutput, RL: RateLimiter + ?Sized> {
 
 impl<O: IndexOutput, RL: RateLimiter + ?Sized> RateLimitIndexOutput<O, RL> {
     pub fn new(rate_limiter: Arc<RL>, delegate: O) -> Self {
-        let current_min_pause_check_bytes = rate_limiter.min_pause_check_bytes() as usize;
+        let current_min_pause_check_bytes = Self::pause_check_bytes(rate_limiter.as_ref());
         RateLimitIndexOutput {
             delegate,
             rate_limiter,
@@ -120,10 +122,19 @@ impl<O: IndexOutput, RL: RateLimiter + ?Sized> RateLimitIndexOutput<O, RL> {
             self.rate_limiter
                 .pause(self.bytes_since_last_pause as u64)?;
             self.bytes_since_last_pause = 0;
-            self.current_min_pause_check_bytes = self.rate_limiter.min_pause_check_bytes() as usize;
+            self.current_min_pause_check_bytes =
+                Self::pause_check_bytes(self.rate_limiter.as_ref());
         }
         Ok(())
     }
+
+    // checks at least every `MAX_PAUSE_CHECK_BYTES` so that a rate limit set
+    // while the limiter was unlimited applies to this output too
+    fn pause_check_bytes(rate_limiter: &RL) -> usize {
+        rate_limiter
+            .min_pause_check_bytes()
+            .min(MAX_PAUSE_CHECK_BYTES) as usize
+    }
 }
 
 impl<O: IndexOutput, RL: RateLimiter + ?Sized> IndexOutput for RateLimitIndexOutput<O, RL> {
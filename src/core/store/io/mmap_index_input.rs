@@ -13,8 +13,11 @@
 
 use crate::core::store::io::{DataInput, IndexInput, RandomAccessInput};
 
+use crate::core::store::ReadAdvice;
 use crate::error::Error::{IllegalArgument, IllegalState};
 use crate::Result;
+#[cfg(unix)]
+use memmap2::Advice;
 use memmap2::{Mmap, MmapOptions};
 use std::fmt::Debug;
 use std::fs::File;
@@ -157,6 +160,38 @@ impl MmapIndexInput {
             .ok_or_else(|| IllegalState(format!("Memmap empty file: {:?}", name)))?)
     }
 
+    /// Maps the whole file `name`, passing `advice` to the OS.
+    pub fn with_advice<P: AsRef<Path> + Debug>(
+        name: P,
+        advice: ReadAdvice,
+    ) -> Result<MmapIndexInput> {
+        let mmap = MmapIndexInput::mmap(name.as_ref(), 0, 0)?;
+        if let Some(ref mmap) = mmap {
+            MmapIndexInput::advise(mmap, advice);
+        }
+        mmap.map(ReadOnlySource::from)
+            .map(MmapIndexInput::from)
+            .ok_or_else(|| IllegalState(format!("Memmap empty file: {:?}", name)))
+    }
+
+    /// Tells the OS how `mmap` will be read. This is only a hint, failures are
+    /// logged and otherwise ignored.
+    #[cfg(unix)]
+    pub fn advise(mmap: &Mmap, advice: ReadAdvice) {
+        let advice = match advice {
+            ReadAdvice::Normal => return,
+            ReadAdvice::Sequential => Advice::Sequential,
+            ReadAdvice::Random => Advice::Random,
+            ReadAdvice::WillNeed => Advice::WillNeed,
+        };
+        if let Err(e) = mmap.advise(advice) {
+            log::warn!("madvise {:?} failed: {:?}", advice, e);
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn advise(_mmap: &Mmap, _advice: ReadAdvice) {}
+
     pub fn mmap(path: &Path, offset: usize, length: usize) -> Result<Option<Arc<Mmap>>> {
         let file = File::open(path)?;
         let meta_data = file.metadata()?;
@@ -35,6 +35,10 @@ mod mmap_index_input;
 
 pub use self::mmap_index_input::*;
 
+mod byte_buffers_index_input;
+
+pub use self::byte_buffers_index_input::*;
+
 mod data_output;
 
 pub use self::data_output::*;
@@ -58,3 +62,9 @@ pub use self::growable_byte_array_output::*;
 mod ram_output;
 
 pub use self::ram_output::*;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring_index_input;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use self::uring_index_input::*;
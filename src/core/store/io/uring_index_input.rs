@@ -0,0 +1,592 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::thread;
+
+use io_uring::{opcode, types, IoUring};
+use thread_local::ThreadLocal;
+
+use crate::core::store::io::{DataInput, IndexInput, RandomAccessInput};
+use crate::error::Error;
+use crate::error::Error::{IllegalArgument, IllegalState};
+use crate::Result;
+
+/// Default number of reads a ring keeps in flight.
+pub const DEFAULT_QUEUE_DEPTH: u32 = 64;
+
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Reads files through io_uring, one ring per thread so that searching threads
+/// never wait on each other's completions.
+pub struct UringReader {
+    rings: ThreadLocal<RefCell<ThreadRing>>,
+    queue_depth: u32,
+}
+
+impl UringReader {
+    /// Fails if io_uring isn't supported by the kernel, or not allowed.
+    pub fn new(queue_depth: u32) -> Result<Self> {
+        if queue_depth == 0 {
+            return Err(IllegalArgument("queue_depth must be > 0".into()));
+        }
+        let reader = UringReader {
+            rings: ThreadLocal::new(),
+            queue_depth,
+        };
+        reader.ring()?;
+        Ok(reader)
+    }
+
+    pub fn queue_depth(&self) -> u32 {
+        self.queue_depth
+    }
+
+    fn ring(&self) -> Result<&RefCell<ThreadRing>> {
+        self.rings.get_or_try(|| {
+            Ok(Box::new(RefCell::new(ThreadRing {
+                ring: Some(IoUring::new(self.queue_depth)?),
+                entries: self.queue_depth,
+            })))
+        })
+    }
+
+    /// Fills each buffer with the bytes of `fd` at its offset. All the reads are
+    /// submitted together, with up to `queue_depth` of them in flight, and short
+    /// reads are resubmitted for their remaining bytes.
+    pub fn read_at(&self, fd: RawFd, reads: &mut [(u64, &mut [u8])]) -> Result<()> {
+        let mut ring = self.ring()?.borrow_mut();
+        read_through(&mut *ring, self.queue_depth as usize, fd, reads)
+    }
+}
+
+/// The io_uring operations `UringReader::read_at` is made of.
+trait Ring {
+    /// Queues a read of `buf.len()` bytes of `fd` at `offset` into `buf`, which
+    /// must stay alive until the read completed.
+    unsafe fn push_read(
+        &mut self,
+        fd: RawFd,
+        buf: &mut [u8],
+        offset: u64,
+        user_data: u64,
+    ) -> Result<()>;
+
+    /// Submits the queued reads and waits for `want` completions. Nothing was
+    /// submitted if this fails.
+    fn submit_and_wait(&mut self, want: usize) -> Result<()>;
+
+    /// Takes the `(user_data, result)` of the completed reads, without waiting.
+    fn completions(&mut self) -> Vec<(u64, i32)>;
+
+    /// Drops the queued reads that weren't submitted, only called once no read
+    /// is in flight.
+    fn clear_submissions(&mut self);
+}
+
+/// The ring of a thread. It's dropped to clear its submission queue, and
+/// created again by the next read.
+struct ThreadRing {
+    ring: Option<IoUring>,
+    entries: u32,
+}
+
+impl ThreadRing {
+    fn get(&mut self) -> Result<&mut IoUring> {
+        if self.ring.is_none() {
+            self.ring = Some(IoUring::new(self.entries)?);
+        }
+        Ok(self.ring.as_mut().unwrap())
+    }
+}
+
+impl Ring for ThreadRing {
+    unsafe fn push_read(
+        &mut self,
+        fd: RawFd,
+        buf: &mut [u8],
+        offset: u64,
+        user_data: u64,
+    ) -> Result<()> {
+        let entry = opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf.len() as u32)
+            .offset(offset)
+            .build()
+            .user_data(user_data);
+        self.get()?
+            .submission()
+            .push(&entry)
+            .map_err(|e| IllegalState(format!("io_uring submission failed: {}", e)))
+    }
+
+    fn submit_and_wait(&mut self, want: usize) -> Result<()> {
+        self.get()?.submit_and_wait(want)?;
+        Ok(())
+    }
+
+    fn completions(&mut self) -> Vec<(u64, i32)> {
+        match self.ring.as_mut() {
+            Some(ring) => ring
+                .completion()
+                .map(|cqe| (cqe.user_data(), cqe.result()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn clear_submissions(&mut self) {
+        self.ring = None;
+    }
+}
+
+/// Runs `UringReader::read_at` on `ring`. The kernel writes into the buffers
+/// until all the reads in flight completed, so on errors this still waits for
+/// them, and drops the reads left in the submission queue, before returning.
+fn read_through<R: Ring>(
+    ring: &mut R,
+    queue_depth: usize,
+    fd: RawFd,
+    reads: &mut [(u64, &mut [u8])],
+) -> Result<()> {
+    let mut done = vec![0usize; reads.len()];
+    let mut pending: VecDeque<usize> = (0..reads.len())
+        .filter(|&i| !reads[i].1.is_empty())
+        .collect();
+    let mut in_flight = 0;
+    let mut error: Option<Error> = None;
+    // once a submit failed, completions are polled instead of waited for, as
+    // submitting again would send the reads left in the queue
+    let mut submit_failed = false;
+    let mut stale = false;
+    while in_flight > 0 || (error.is_none() && !pending.is_empty()) {
+        let mut queued = 0;
+        while error.is_none() && in_flight + queued < queue_depth {
+            let i = match pending.pop_front() {
+                Some(i) => i,
+                None => break,
+            };
+            let (offset, ref mut buf) = reads[i];
+            let remaining = &mut buf[done[i]..];
+            // the ring has `queue_depth` entries, so there is always room
+            match unsafe { ring.push_read(fd, remaining, offset + done[i] as u64, i as u64) } {
+                Ok(()) => queued += 1,
+                Err(e) => error = Some(e),
+            }
+        }
+        if submit_failed {
+            thread::yield_now();
+        } else {
+            match ring.submit_and_wait(1) {
+                Ok(()) => in_flight += queued,
+                Err(e) => {
+                    error = error.or(Some(e));
+                    submit_failed = true;
+                    stale = queued > 0;
+                }
+            }
+        }
+        for (i, res) in ring.completions() {
+            let i = i as usize;
+            in_flight -= 1;
+            if res < 0 {
+                let e = io::Error::from_raw_os_error(-res);
+                match e.kind() {
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => pending.push_back(i),
+                    _ => error = error.or_else(|| Some(e.into())),
+                }
+            } else if res == 0 {
+                error = error.or_else(|| {
+                    Some(io::Error::new(io::ErrorKind::UnexpectedEof, "read past EOF").into())
+                });
+            } else {
+                done[i] += res as usize;
+                if done[i] < reads[i].1.len() {
+                    pending.push_back(i);
+                }
+            }
+        }
+    }
+    if stale {
+        ring.clear_submissions();
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// `IndexInput` reading a file through a `UringReader`, buffering
+/// `BUFFER_SIZE` bytes at a time.
+pub struct UringIndexInput {
+    reader: Arc<UringReader>,
+    file: Arc<File>,
+    name: String,
+    // start and length of this input (or slice) within the file
+    offset: u64,
+    len: u64,
+    position: u64,
+    buffer: Vec<u8>,
+    // position of the first buffered byte
+    buffer_start: u64,
+}
+
+impl UringIndexInput {
+    pub fn new(reader: Arc<UringReader>, file: File, name: &str) -> Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(UringIndexInput {
+            reader,
+            file: Arc::new(file),
+            name: name.to_string(),
+            offset: 0,
+            len,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        })
+    }
+
+    /// Reads the `(offset, len)` ranges of this input at once, e.g. the blocks of
+    /// the stored fields of all the top hits, without moving the file pointer.
+    pub fn read_ranges(&self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        let mut buffers: Vec<Vec<u8>> = Vec::with_capacity(ranges.len());
+        for &(offset, len) in ranges {
+            self.check_range(offset, len)?;
+            buffers.push(vec![0u8; len]);
+        }
+        let mut reads: Vec<(u64, &mut [u8])> = ranges
+            .iter()
+            .zip(buffers.iter_mut())
+            .map(|(&(offset, _), buf)| (self.offset + offset, buf.as_mut_slice()))
+            .collect();
+        self.reader.read_at(self.file.as_raw_fd(), &mut reads)?;
+        Ok(buffers)
+    }
+
+    fn check_range(&self, pos: u64, len: usize) -> Result<()> {
+        if pos + len as u64 > self.len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read past EOF").into());
+        }
+        Ok(())
+    }
+
+    /// Reads `buf.len()` bytes at `pos` of this input, without moving the file pointer.
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> Result<()> {
+        self.check_range(pos, buf.len())?;
+        self.reader
+            .read_at(self.file.as_raw_fd(), &mut [(self.offset + pos, buf)])
+    }
+
+    fn buffered(&self) -> usize {
+        if self.position >= self.buffer_start
+            && self.position < self.buffer_start + self.buffer.len() as u64
+        {
+            (self.buffer_start + self.buffer.len() as u64 - self.position) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Makes the `length` bytes at the current position buffered.
+    fn fill_buffer(&mut self, length: usize) -> Result<()> {
+        if self.buffered() >= length {
+            return Ok(());
+        }
+        self.check_range(self.position, length)?;
+        let count = length
+            .max(BUFFER_SIZE)
+            .min((self.len - self.position) as usize);
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.resize(count, 0);
+        let res = self.read_at(self.position, &mut buffer);
+        self.buffer = buffer;
+        if res.is_err() {
+            self.buffer.clear();
+        }
+        self.buffer_start = self.position;
+        res
+    }
+
+    fn buffer_index(&self) -> usize {
+        (self.position - self.buffer_start) as usize
+    }
+
+    fn slice_impl(&self, description: &str, offset: i64, length: i64) -> Result<Self> {
+        if offset < 0 || length < 0 || (offset + length) as u64 > self.len {
+            return Err(IllegalArgument(format!(
+                "Illegal (offset, length) slice: ({}, {}) for file of length: {}",
+                offset, length, self.len
+            )));
+        }
+        Ok(UringIndexInput {
+            reader: Arc::clone(&self.reader),
+            file: Arc::clone(&self.file),
+            name: description.to_string(),
+            offset: self.offset + offset as u64,
+            len: length as u64,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        })
+    }
+}
+
+impl IndexInput for UringIndexInput {
+    fn clone(&self) -> Result<Box<dyn IndexInput>> {
+        let mut input = self.slice_impl(&self.name, 0, self.len as i64)?;
+        input.position = self.position;
+        Ok(Box::new(input))
+    }
+
+    fn file_pointer(&self) -> i64 {
+        self.position as i64
+    }
+
+    fn seek(&mut self, pos: i64) -> Result<()> {
+        if pos < 0 || pos as u64 > self.len {
+            return Err(IllegalArgument(format!(
+                "seek past EOF: {} for file of length: {}",
+                pos, self.len
+            )));
+        }
+        self.position = pos as u64;
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn random_access_slice(&self, offset: i64, length: i64) -> Result<Box<dyn RandomAccessInput>> {
+        Ok(Box::new(self.slice_impl(
+            "RandomAccessSlice",
+            offset,
+            length,
+        )?))
+    }
+
+    fn slice(&self, description: &str, offset: i64, length: i64) -> Result<Box<dyn IndexInput>> {
+        Ok(Box::new(self.slice_impl(description, offset, length)?))
+    }
+
+    fn prefill(&mut self, length: usize) -> Result<()> {
+        self.fill_buffer(length)
+    }
+
+    unsafe fn get_and_advance(&mut self, length: usize) -> *const u8 {
+        assert!(
+            self.buffered() >= length,
+            "get_and_advance of {} bytes not prefilled",
+            length
+        );
+        let ptr = self.buffer.as_ptr().add(self.buffer_index());
+        self.position += length as u64;
+        ptr
+    }
+}
+
+impl DataInput for UringIndexInput {
+    fn read_byte(&mut self) -> Result<u8> {
+        if self.buffered() == 0 {
+            self.fill_buffer(1)?;
+        }
+        let b = self.buffer[self.buffer_index()];
+        self.position += 1;
+        Ok(b)
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<()> {
+        self.check_range(self.position, count)?;
+        self.position += count as u64;
+        Ok(())
+    }
+}
+
+impl Read for UringIndexInput {
+    // fills `buf` as far as the end of the input, `DataInput::read_bytes` relies on it
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = (buf.len() as u64).min(self.len - self.position) as usize;
+        let mut done = 0;
+        while done < count {
+            let mut buffered = self.buffered();
+            if buffered == 0 && count - done >= BUFFER_SIZE {
+                // large reads skip the buffer
+                self.read_at(self.position, &mut buf[done..count])
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                self.position += (count - done) as u64;
+                return Ok(count);
+            }
+            if buffered == 0 {
+                self.fill_buffer(1)
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                buffered = self.buffered();
+            }
+            let start = self.buffer_index();
+            let n = (count - done).min(buffered);
+            buf[done..done + n].copy_from_slice(&self.buffer[start..start + n]);
+            self.position += n as u64;
+            done += n;
+        }
+        Ok(count)
+    }
+}
+
+impl RandomAccessInput for UringIndexInput {
+    fn read_byte(&self, pos: u64) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_at(pos, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_short(&self, pos: u64) -> Result<i16> {
+        let mut buf = [0u8; 2];
+        self.read_at(pos, &mut buf)?;
+        Ok(i16::from_be_bytes(buf))
+    }
+
+    fn read_int(&self, pos: u64) -> Result<i32> {
+        let mut buf = [0u8; 4];
+        self.read_at(pos, &mut buf)?;
+        Ok(i32::from_be_bytes(buf))
+    }
+
+    fn read_long(&self, pos: u64) -> Result<i64> {
+        let mut buf = [0u8; 8];
+        self.read_at(pos, &mut buf)?;
+        Ok(i64::from_be_bytes(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads `data` when the reads are submitted, completing one per wait or poll.
+    struct FakeRing {
+        data: Vec<u8>,
+        queued: Vec<(*mut u8, usize, u64, u64)>,
+        submitted: VecDeque<(*mut u8, usize, u64, u64)>,
+        completed: Vec<(u64, i32)>,
+        submits: usize,
+        // the submit that fails, counting from 1
+        fail_submit: usize,
+        cleared: bool,
+    }
+
+    impl FakeRing {
+        fn new(data: Vec<u8>, fail_submit: usize) -> Self {
+            FakeRing {
+                data,
+                queued: Vec::new(),
+                submitted: VecDeque::new(),
+                completed: Vec::new(),
+                submits: 0,
+                fail_submit,
+                cleared: false,
+            }
+        }
+
+        fn complete_one(&mut self) {
+            if let Some((ptr, len, offset, user_data)) = self.submitted.pop_front() {
+                let start = (offset as usize).min(self.data.len());
+                let n = len.min(self.data.len() - start);
+                unsafe { std::ptr::copy_nonoverlapping(self.data[start..].as_ptr(), ptr, n) };
+                self.completed.push((user_data, n as i32));
+            }
+        }
+    }
+
+    impl Ring for FakeRing {
+        unsafe fn push_read(
+            &mut self,
+            _fd: RawFd,
+            buf: &mut [u8],
+            offset: u64,
+            user_data: u64,
+        ) -> Result<()> {
+            self.queued
+                .push((buf.as_mut_ptr(), buf.len(), offset, user_data));
+            Ok(())
+        }
+
+        fn submit_and_wait(&mut self, want: usize) -> Result<()> {
+            self.submits += 1;
+            if self.submits == self.fail_submit {
+                return Err(io::Error::other("injected submit failure").into());
+            }
+            self.submitted.extend(self.queued.drain(..));
+            for _ in 0..want {
+                self.complete_one();
+            }
+            Ok(())
+        }
+
+        fn completions(&mut self) -> Vec<(u64, i32)> {
+            if self.completed.is_empty() {
+                self.complete_one();
+            }
+            std::mem::take(&mut self.completed)
+        }
+
+        fn clear_submissions(&mut self) {
+            self.queued.clear();
+            self.cleared = true;
+        }
+    }
+
+    #[test]
+    fn test_read_through() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut ring = FakeRing::new(data.clone(), 0);
+        let mut buffers = vec![vec![0u8; 10]; 6];
+        let mut reads: Vec<(u64, &mut [u8])> = buffers
+            .iter_mut()
+            .enumerate()
+            .map(|(i, buf)| (i as u64 * 15, buf.as_mut_slice()))
+            .collect();
+        read_through(&mut ring, 2, 0, &mut reads).unwrap();
+        for (i, buf) in buffers.iter().enumerate() {
+            assert_eq!(&buf[..], &data[i * 15..i * 15 + 10]);
+        }
+        assert!(!ring.cleared);
+    }
+
+    #[test]
+    fn test_read_through_failed_submit() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut ring = FakeRing::new(data.clone(), 2);
+        let mut buffers = vec![vec![0u8; 10]; 6];
+        let mut reads: Vec<(u64, &mut [u8])> = buffers
+            .iter_mut()
+            .enumerate()
+            .map(|(i, buf)| (i as u64 * 15, buf.as_mut_slice()))
+            .collect();
+        assert!(read_through(&mut ring, 2, 0, &mut reads).is_err());
+        // the read still in flight when the submit failed was waited for, and
+        // the one left in the queue was dropped
+        assert_eq!(ring.submits, 2);
+        assert!(ring.submitted.is_empty());
+        assert!(ring.queued.is_empty());
+        assert!(ring.cleared);
+        assert_eq!(&buffers[0][..], &data[..10]);
+        assert_eq!(&buffers[1][..], &data[15..25]);
+        assert_eq!(&buffers[2][..], &[0u8; 10][..]);
+    }
+}
@@ -1,4 +1,3 @@
-
 /// IOContext holds additional details on the merge/search context and
 /// specifies the context in which the Directory is being used for.
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -18,6 +17,70 @@ impl IOContext {
             _ => false,
         }
     }
+
+    /// Returns how the file `name` opened in this context is expected to be
+    /// read. Merges and read-once files are read sequentially, so they don't
+    /// evict the pages used by searches; at search time the terms dictionary
+    /// is read randomly and its small index is wanted right away.
+    pub fn read_advice(&self, name: &str) -> ReadAdvice {
+        match self {
+            IOContext::Merge(_) | IOContext::Read(true) => ReadAdvice::Sequential,
+            IOContext::Read(false) => match name.rsplit('.').next() {
+                // the terms index and dictionary of the block tree postings
+                Some("tip") => ReadAdvice::WillNeed,
+                Some("tim") => ReadAdvice::Random,
+                _ => ReadAdvice::Normal,
+            },
+            IOContext::Flush(_) | IOContext::Default => ReadAdvice::Normal,
+        }
+    }
+}
+
+/// The kinds of `IOContext`, e.g. to account or configure the IO of each kind
+/// separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IOContextKind {
+    Merge,
+    /// `IOContext::Read`, i.e. searches.
+    Search,
+    Flush,
+    Default,
+}
+
+impl IOContextKind {
+    pub const ALL: [IOContextKind; 4] = [
+        IOContextKind::Merge,
+        IOContextKind::Search,
+        IOContextKind::Flush,
+        IOContextKind::Default,
+    ];
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl<'a> From<&'a IOContext> for IOContextKind {
+    fn from(ctx: &'a IOContext) -> Self {
+        match ctx {
+            IOContext::Merge(_) => IOContextKind::Merge,
+            IOContext::Read(_) => IOContextKind::Search,
+            IOContext::Flush(_) => IOContextKind::Flush,
+            IOContext::Default => IOContextKind::Default,
+        }
+    }
+}
+
+/// A read-ahead hint for the OS, passed by the mmap based inputs with `madvise`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReadAdvice {
+    Normal,
+    /// Read ahead aggressively, the pages can be dropped soon after being read.
+    Sequential,
+    /// Don't read ahead.
+    Random,
+    /// Read the whole file ahead.
+    WillNeed,
 }
 
 /// A FlushInfo provides information required for a FLUSH context.
@@ -26,11 +89,23 @@ impl IOContext {
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub struct FlushInfo {
     num_docs: u32,
+    estimated_segment_size: u64,
 }
 
 impl FlushInfo {
     pub fn new(num_docs: u32) -> Self {
-        FlushInfo { num_docs }
+        Self::with_estimated_segment_size(num_docs, 0)
+    }
+
+    pub fn with_estimated_segment_size(num_docs: u32, estimated_segment_size: u64) -> Self {
+        FlushInfo {
+            num_docs,
+            estimated_segment_size,
+        }
+    }
+
+    pub fn estimated_segment_size(&self) -> u64 {
+        self.estimated_segment_size
     }
 }
 
@@ -59,4 +134,40 @@ impl MergeInfo {
             merge_max_num_segments,
         }
     }
+
+    pub fn estimated_merge_bytes(&self) -> u64 {
+        self.estimated_merge_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_advice() {
+        let merge = IOContext::Merge(MergeInfo::new(10, 1024, false, None));
+        assert_eq!(
+            merge.read_advice("_0_Lucene50_0.tim"),
+            ReadAdvice::Sequential
+        );
+        assert_eq!(merge.read_advice("_0.fdt"), ReadAdvice::Sequential);
+        assert_eq!(
+            IOContext::READ_ONCE.read_advice("segments_1"),
+            ReadAdvice::Sequential
+        );
+        assert_eq!(
+            IOContext::READ.read_advice("_0_Lucene50_0.tip"),
+            ReadAdvice::WillNeed
+        );
+        assert_eq!(
+            IOContext::READ.read_advice("_0_Lucene50_0.tim"),
+            ReadAdvice::Random
+        );
+        assert_eq!(IOContext::READ.read_advice("_0.fdt"), ReadAdvice::Normal);
+        assert_eq!(
+            IOContext::Flush(FlushInfo::new(1)).read_advice("_0.tim"),
+            ReadAdvice::Normal
+        );
+    }
 }
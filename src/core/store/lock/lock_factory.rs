@@ -0,0 +1,86 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::Result;
+
+/// Name of the lock an `IndexWriter` holds on its directory.
+pub const WRITE_LOCK_NAME: &str = "write.lock";
+
+/// An interprocess mutex lock, obtained from a `LockFactory`.
+///
+/// The lock is released when closed or dropped.
+pub trait Lock: Send + Sync {
+    /// Releases the lock, `ensure_valid` fails afterwards.
+    fn close(&self) -> Result<()>;
+
+    /// Best effort check that this lock is still valid, e.g. that its file
+    /// wasn't removed by an external force. Called before every destructive
+    /// operation of the `IndexWriter` holding it.
+    fn ensure_valid(&self) -> Result<()>;
+}
+
+/// Creates the locks of a `Directory`.
+///
+/// Obtaining a lock already held fails fast with `Error::LockObtainFailed`.
+pub trait LockFactory: Send + Sync {
+    /// Obtains the lock `lock_name` of the directory at `lock_dir`; directories
+    /// without a path pass an empty one.
+    fn obtain_lock(&self, lock_dir: &Path, lock_name: &str) -> Result<Box<dyn Lock>>;
+}
+
+/// A `LockFactory` whose locks never conflict, only use it if no other process
+/// or `IndexWriter` may write the index.
+#[derive(Default, Clone, Copy)]
+pub struct NoLockFactory;
+
+impl LockFactory for NoLockFactory {
+    fn obtain_lock(&self, _lock_dir: &Path, _lock_name: &str) -> Result<Box<dyn Lock>> {
+        Ok(Box::new(NoLock))
+    }
+}
+
+struct NoLock;
+
+impl Lock for NoLock {
+    fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn ensure_valid(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Identifies a lock file, to detect it was deleted or re-created by someone else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileIdentity {
+    created: Option<SystemTime>,
+    inode: u64,
+}
+
+impl FileIdentity {
+    pub(crate) fn new(meta: &Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(meta);
+        #[cfg(not(unix))]
+        let inode = 0;
+        FileIdentity {
+            created: meta.created().ok(),
+            inode,
+        }
+    }
+}
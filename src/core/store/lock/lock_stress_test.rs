@@ -0,0 +1,120 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::core::store::lock::{LockFactory, VerifyingLockFactory};
+use crate::error::Error::LockObtainFailed;
+use crate::Result;
+
+/// Name of the lock the stress test clients compete for.
+pub const STRESS_TEST_LOCK_NAME: &str = "test.lock";
+
+/// Repeatedly obtains and releases a lock of `lock_dir` through `lock_factory`
+/// as client `id` of the `LockVerifyServer` at `verifier`, holding and waiting
+/// `sleep` each time.
+///
+/// Run it from several processes (or hosts, for a shared file system) against
+/// one server to validate a locking setup; any overlap makes the server fail.
+/// Returns how many times this client got the lock.
+pub fn lock_stress_test<A: ToSocketAddrs>(
+    id: i32,
+    verifier: A,
+    lock_factory: Arc<dyn LockFactory>,
+    lock_dir: &Path,
+    sleep: Duration,
+    count: usize,
+) -> Result<usize> {
+    let verifying = VerifyingLockFactory::connect(lock_factory, verifier, id)?;
+    let mut obtained = 0;
+    for _ in 0..count {
+        match verifying.obtain_lock(lock_dir, STRESS_TEST_LOCK_NAME) {
+            Ok(lock) => {
+                obtained += 1;
+                lock.ensure_valid()?;
+                thread::sleep(sleep);
+                lock.close()?;
+            }
+            Err(LockObtainFailed(_)) => {}
+            Err(e) => return Err(e),
+        }
+        thread::sleep(sleep);
+    }
+    Ok(obtained)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::lock::{
+        LockVerifyServer, NativeFSLockFactory, NoLockFactory, SimpleFSLockFactory,
+    };
+
+    fn stress(lock_factory: Arc<dyn LockFactory>, dir: &str) -> Result<usize> {
+        let lock_dir = std::env::temp_dir().join(dir);
+        let server = LockVerifyServer::bind("127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        let clients: Vec<_> = (0..3)
+            .map(|id| {
+                let (lock_factory, lock_dir) = (Arc::clone(&lock_factory), lock_dir.clone());
+                thread::spawn(move || {
+                    lock_stress_test(
+                        id,
+                        addr,
+                        lock_factory,
+                        &lock_dir,
+                        Duration::from_millis(1),
+                        30,
+                    )
+                })
+            })
+            .collect();
+        server.run(3)?;
+        let mut obtained = 0;
+        for client in clients {
+            obtained += client.join().unwrap()?;
+        }
+        Ok(obtained)
+    }
+
+    #[test]
+    fn test_lock_stress() -> Result<()> {
+        assert!(stress(Arc::new(NativeFSLockFactory), "test_rucene_stress_native")? > 0);
+        assert!(stress(Arc::new(SimpleFSLockFactory), "test_rucene_stress_simple")? > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifier_detects_overlap() -> Result<()> {
+        let server = LockVerifyServer::bind("127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        let server = thread::spawn(move || server.run(2));
+        let connect = move |id| {
+            thread::spawn(move || VerifyingLockFactory::connect(Arc::new(NoLockFactory), addr, id))
+        };
+        let (a, b) = (connect(0), connect(1));
+        let (a, b) = (a.join().unwrap()?, b.join().unwrap()?);
+
+        let dir = Path::new("unused");
+        let _lock = a.obtain_lock(dir, "test.lock")?;
+        assert!(b.obtain_lock(dir, "test.lock").is_err());
+        drop(_lock);
+        drop((a, b));
+        assert!(server.join().unwrap().is_err());
+        Ok(())
+    }
+}
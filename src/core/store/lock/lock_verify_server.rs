@@ -0,0 +1,140 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use crate::core::store::lock::verifying_lock_factory::{VERIFY_OBTAIN, VERIFY_RELEASE};
+use crate::core::store::lock::VERIFY_START_MAGIC;
+use crate::error::Error::IllegalState;
+use crate::Result;
+
+/// The central verifier of `VerifyingLockFactory` clients, checking that no two
+/// clients ever hold the lock at the same time.
+pub struct LockVerifyServer {
+    listener: TcpListener,
+}
+
+struct VerifyState {
+    // id of the client holding the lock
+    locked_by: Option<i32>,
+    failure: Option<String>,
+}
+
+impl LockVerifyServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(LockVerifyServer {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accepts `max_clients` clients, starts them all at once and serves them
+    /// until they disconnect. Fails with the first violation seen, the offending
+    /// client gets disconnected.
+    pub fn run(&self, max_clients: usize) -> Result<()> {
+        let state = Arc::new(Mutex::new(VerifyState {
+            locked_by: None,
+            failure: None,
+        }));
+        let starting_gun = Arc::new(Barrier::new(max_clients));
+        let mut handles = Vec::with_capacity(max_clients);
+        for _ in 0..max_clients {
+            let (stream, _) = self.listener.accept()?;
+            let state = Arc::clone(&state);
+            let starting_gun = Arc::clone(&starting_gun);
+            handles.push(thread::spawn(move || {
+                if let Err(e) = Self::serve(stream, &state, &starting_gun) {
+                    let mut state = state.lock().unwrap();
+                    if state.failure.is_none() {
+                        state.failure = Some(format!("client failed: {:?}", e));
+                    }
+                }
+            }));
+        }
+        for handle in handles {
+            handle
+                .join()
+                .map_err(|_| IllegalState("lock verify thread panicked".into()))?;
+        }
+        let failure = state.lock()?.failure.take();
+        match failure {
+            Some(failure) => Err(IllegalState(failure)),
+            None => Ok(()),
+        }
+    }
+
+    fn serve(
+        mut stream: TcpStream,
+        state: &Mutex<VerifyState>,
+        starting_gun: &Barrier,
+    ) -> Result<()> {
+        stream.set_nodelay(true)?;
+        let id = stream.read_i32::<BigEndian>()?;
+        starting_gun.wait();
+        stream.write_all(&[VERIFY_START_MAGIC])?;
+        stream.flush()?;
+
+        let mut command = [0u8; 1];
+        loop {
+            match stream.read_exact(&mut command) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+            {
+                let mut state = state.lock()?;
+                let violation = match command[0] {
+                    VERIFY_OBTAIN => match state.locked_by {
+                        Some(holder) => Some(format!(
+                            "id {} got lock, but {} already holds the lock",
+                            id, holder
+                        )),
+                        None => {
+                            state.locked_by = Some(id);
+                            None
+                        }
+                    },
+                    VERIFY_RELEASE => {
+                        if state.locked_by == Some(id) {
+                            state.locked_by = None;
+                            None
+                        } else {
+                            Some(format!(
+                                "id {} released the lock, but {:?} is the one holding the lock",
+                                id, state.locked_by
+                            ))
+                        }
+                    }
+                    c => Some(format!("unrecognized command {} from id {}", c, id)),
+                };
+                if let Some(violation) = violation {
+                    if state.failure.is_none() {
+                        state.failure = Some(violation);
+                    }
+                    // dropping the stream disconnects the client
+                    return Ok(());
+                }
+            }
+            stream.write_all(&command)?;
+            stream.flush()?;
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod lock_factory;
+
+pub use self::lock_factory::{Lock, LockFactory, NoLockFactory, WRITE_LOCK_NAME};
+
+mod native_fs_lock_factory;
+
+pub use self::native_fs_lock_factory::NativeFSLockFactory;
+
+mod simple_fs_lock_factory;
+
+pub use self::simple_fs_lock_factory::SimpleFSLockFactory;
+
+mod single_instance_lock_factory;
+
+pub use self::single_instance_lock_factory::SingleInstanceLockFactory;
+
+mod verifying_lock_factory;
+
+pub use self::verifying_lock_factory::{VerifyingLockFactory, VERIFY_START_MAGIC};
+
+mod lock_verify_server;
+
+pub use self::lock_verify_server::LockVerifyServer;
+
+mod lock_stress_test;
+
+pub use self::lock_stress_test::{lock_stress_test, STRESS_TEST_LOCK_NAME};
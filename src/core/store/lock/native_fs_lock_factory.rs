@@ -0,0 +1,153 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::core::store::lock::lock_factory::FileIdentity;
+use crate::core::store::lock::{Lock, LockFactory};
+use crate::error::Error::{AlreadyClosed, LockObtainFailed};
+use crate::Result;
+
+lazy_static::lazy_static! {
+    // locks held by this process, as the OS locks may not conflict within a process
+    static ref LOCK_HELD: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// A `LockFactory` using the native OS file locks (`flock` / `LockFileEx`).
+///
+/// The OS releases the lock when the process exits, so unlike
+/// `SimpleFSLockFactory` a crash never leaves a stale lock behind. The lock
+/// files are never deleted. This is the default of `FSDirectory`.
+///
+/// Native locks may not work on shared file systems such as NFS.
+#[derive(Default, Clone, Copy)]
+pub struct NativeFSLockFactory;
+
+impl LockFactory for NativeFSLockFactory {
+    fn obtain_lock(&self, lock_dir: &Path, lock_name: &str) -> Result<Box<dyn Lock>> {
+        fs::create_dir_all(lock_dir)?;
+        let lock_file = lock_dir.join(lock_name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_file)?;
+        let path = lock_file.canonicalize()?;
+        let identity = FileIdentity::new(&file.metadata()?);
+
+        if !LOCK_HELD.lock()?.insert(path.clone()) {
+            return Err(LockObtainFailed(format!(
+                "Lock held by this process: {}",
+                path.display()
+            )));
+        }
+        match file.try_lock() {
+            Ok(()) => Ok(Box::new(NativeFSLock {
+                path,
+                file: Mutex::new(Some(file)),
+                identity,
+            })),
+            Err(e) => {
+                LOCK_HELD.lock()?.remove(&path);
+                match e {
+                    TryLockError::WouldBlock => Err(LockObtainFailed(format!(
+                        "Lock held by another program: {}",
+                        path.display()
+                    ))),
+                    TryLockError::Error(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+struct NativeFSLock {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+    identity: FileIdentity,
+}
+
+impl Lock for NativeFSLock {
+    fn close(&self) -> Result<()> {
+        if let Some(file) = self.file.lock()?.take() {
+            let res = file.unlock();
+            drop(file);
+            LOCK_HELD.lock()?.remove(&self.path);
+            res?;
+        }
+        Ok(())
+    }
+
+    fn ensure_valid(&self) -> Result<()> {
+        if self.file.lock()?.is_none() {
+            return Err(AlreadyClosed(format!(
+                "Lock instance already released: {}",
+                self.path.display()
+            )));
+        }
+        if !LOCK_HELD.lock()?.contains(&self.path) {
+            return Err(AlreadyClosed(format!(
+                "Lock path unexpectedly cleared from map: {}",
+                self.path.display()
+            )));
+        }
+        if FileIdentity::new(&fs::metadata(&self.path)?) != self.identity {
+            return Err(AlreadyClosed(format!(
+                "Underlying file changed by an external force: {}",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NativeFSLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::warn!("failed to release lock {}: {:?}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_fs_lock() -> Result<()> {
+        let dir = std::env::temp_dir().join("test_rucene_native_fs_lock");
+        let factory = NativeFSLockFactory;
+        let lock = factory.obtain_lock(&dir, "test.lock")?;
+        lock.ensure_valid()?;
+        assert!(matches!(
+            factory.obtain_lock(&dir, "test.lock"),
+            Err(LockObtainFailed(_))
+        ));
+        // the OS lock conflicts even without the in-process check
+        let file = OpenOptions::new().write(true).open(dir.join("test.lock"))?;
+        assert!(matches!(file.try_lock(), Err(TryLockError::WouldBlock)));
+
+        lock.close()?;
+        assert!(lock.ensure_valid().is_err());
+        let lock = factory.obtain_lock(&dir, "test.lock")?;
+
+        // deleting the file invalidates the lock
+        fs::remove_file(dir.join("test.lock"))?;
+        assert!(lock.ensure_valid().is_err());
+        Ok(())
+    }
+}
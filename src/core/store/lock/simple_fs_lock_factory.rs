@@ -0,0 +1,97 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::core::store::lock::lock_factory::FileIdentity;
+use crate::core::store::lock::{Lock, LockFactory};
+use crate::error::Error::{AlreadyClosed, LockObtainFailed};
+use crate::Result;
+
+/// A `LockFactory` holding a lock by creating its file, and releasing it by
+/// deleting the file.
+///
+/// Works on file systems without native locks, but a process crashing while
+/// holding a lock leaves the file behind: it must then be removed manually
+/// before the index can be written again.
+#[derive(Default, Clone, Copy)]
+pub struct SimpleFSLockFactory;
+
+impl LockFactory for SimpleFSLockFactory {
+    fn obtain_lock(&self, lock_dir: &Path, lock_name: &str) -> Result<Box<dyn Lock>> {
+        fs::create_dir_all(lock_dir)?;
+        let path = lock_dir.join(lock_name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => Ok(Box::new(SimpleFSLock {
+                identity: FileIdentity::new(&file.metadata()?),
+                path,
+                closed: AtomicBool::new(false),
+            })),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(LockObtainFailed(format!(
+                "Lock held by another program: {}",
+                path.display()
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+struct SimpleFSLock {
+    path: PathBuf,
+    identity: FileIdentity,
+    closed: AtomicBool,
+}
+
+impl Lock for SimpleFSLock {
+    fn close(&self) -> Result<()> {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        // don't delete a lock file someone else created in the meantime
+        if FileIdentity::new(&fs::metadata(&self.path)?) != self.identity {
+            return Err(AlreadyClosed(format!(
+                "Underlying file changed by an external force: {}",
+                self.path.display()
+            )));
+        }
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    fn ensure_valid(&self) -> Result<()> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(AlreadyClosed(format!(
+                "Lock instance already released: {}",
+                self.path.display()
+            )));
+        }
+        if FileIdentity::new(&fs::metadata(&self.path)?) != self.identity {
+            return Err(AlreadyClosed(format!(
+                "Underlying file changed by an external force: {}",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SimpleFSLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::warn!("failed to release lock {}: {:?}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::core::store::lock::{Lock, LockFactory};
+use crate::error::Error::{AlreadyClosed, LockObtainFailed};
+use crate::Result;
+
+/// A `LockFactory` keeping its locks in memory, the default of
+/// `ByteBuffersDirectory`.
+///
+/// Locks only conflict when obtained through the same factory instance, so it
+/// must not be used for directories other processes may write.
+#[derive(Default, Clone)]
+pub struct SingleInstanceLockFactory {
+    locks: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl SingleInstanceLockFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LockFactory for SingleInstanceLockFactory {
+    fn obtain_lock(&self, lock_dir: &Path, lock_name: &str) -> Result<Box<dyn Lock>> {
+        let path = lock_dir.join(lock_name);
+        if !self.locks.lock()?.insert(path.clone()) {
+            return Err(LockObtainFailed(format!(
+                "lock instance already obtained: {}",
+                path.display()
+            )));
+        }
+        Ok(Box::new(SingleInstanceLock {
+            locks: Arc::clone(&self.locks),
+            path,
+            closed: AtomicBool::new(false),
+        }))
+    }
+}
+
+struct SingleInstanceLock {
+    locks: Arc<Mutex<HashSet<PathBuf>>>,
+    path: PathBuf,
+    closed: AtomicBool,
+}
+
+impl Lock for SingleInstanceLock {
+    fn close(&self) -> Result<()> {
+        if !self.closed.swap(true, Ordering::AcqRel) {
+            self.locks.lock()?.remove(&self.path);
+        }
+        Ok(())
+    }
+
+    fn ensure_valid(&self) -> Result<()> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err(AlreadyClosed(format!(
+                "Lock instance already released: {}",
+                self.path.display()
+            )));
+        }
+        if !self.locks.lock()?.contains(&self.path) {
+            return Err(AlreadyClosed(format!(
+                "Lock instance was invalidated from map: {}",
+                self.path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SingleInstanceLock {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use crate::core::store::lock::{Lock, LockFactory};
+use crate::error::Error::IllegalState;
+use crate::Result;
+
+/// Byte the `LockVerifyServer` sends once all the clients are connected.
+pub const VERIFY_START_MAGIC: u8 = 43;
+
+pub(crate) const VERIFY_OBTAIN: u8 = 1;
+pub(crate) const VERIFY_RELEASE: u8 = 0;
+
+/// A `LockFactory` reporting every obtain and release of the locks of another
+/// factory to a `LockVerifyServer`, which fails if two clients ever hold the
+/// lock at the same time.
+///
+/// Used by `lock_stress_test` to check the locking really is exclusive on a
+/// given file system.
+pub struct VerifyingLockFactory {
+    lock_factory: Arc<dyn LockFactory>,
+    stream: Arc<Mutex<TcpStream>>,
+}
+
+impl VerifyingLockFactory {
+    /// Connects to the `LockVerifyServer` as client `id`, waiting until the
+    /// server has all its clients.
+    pub fn connect<A: ToSocketAddrs>(
+        lock_factory: Arc<dyn LockFactory>,
+        verifier: A,
+        id: i32,
+    ) -> Result<Self> {
+        let mut stream = TcpStream::connect(verifier)?;
+        stream.set_nodelay(true)?;
+        stream.write_i32::<BigEndian>(id)?;
+        stream.flush()?;
+        let mut magic = [0u8; 1];
+        stream.read_exact(&mut magic)?;
+        if magic[0] != VERIFY_START_MAGIC {
+            return Err(IllegalState(format!(
+                "Protocol violation, expected start magic {} but got {}",
+                VERIFY_START_MAGIC, magic[0]
+            )));
+        }
+        Ok(VerifyingLockFactory {
+            lock_factory,
+            stream: Arc::new(Mutex::new(stream)),
+        })
+    }
+}
+
+fn verify(stream: &Mutex<TcpStream>, message: u8) -> Result<()> {
+    let mut stream = stream.lock()?;
+    stream.write_all(&[message])?;
+    stream.flush()?;
+    let mut reply = [0u8; 1];
+    match stream.read_exact(&mut reply) {
+        Ok(()) if reply[0] == message => Ok(()),
+        Ok(()) => Err(IllegalState(format!(
+            "Protocol violation, sent {} but got {}",
+            message, reply[0]
+        ))),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(IllegalState("Lock server died or rejected the lock".into()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl LockFactory for VerifyingLockFactory {
+    fn obtain_lock(&self, lock_dir: &Path, lock_name: &str) -> Result<Box<dyn Lock>> {
+        let lock = self.lock_factory.obtain_lock(lock_dir, lock_name)?;
+        verify(&self.stream, VERIFY_OBTAIN)?;
+        Ok(Box::new(CheckedLock {
+            lock,
+            stream: Arc::clone(&self.stream),
+            closed: AtomicBool::new(false),
+        }))
+    }
+}
+
+struct CheckedLock {
+    lock: Box<dyn Lock>,
+    stream: Arc<Mutex<TcpStream>>,
+    closed: AtomicBool,
+}
+
+impl Lock for CheckedLock {
+    fn close(&self) -> Result<()> {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.lock.ensure_valid()?;
+        // report the release before releasing, another client may obtain it right after
+        verify(&self.stream, VERIFY_RELEASE)?;
+        self.lock.close()
+    }
+
+    fn ensure_valid(&self) -> Result<()> {
+        self.lock.ensure_valid()
+    }
+}
+
+impl Drop for CheckedLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            log::warn!("failed to release verified lock: {:?}", e);
+        }
+    }
+}
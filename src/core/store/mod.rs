@@ -13,7 +13,8 @@
 
 pub mod directory;
 pub mod io;
+pub mod lock;
 mod rate_limiter;
 mod io_context;
-pub use rate_limiter::RateLimiter;
-pub use io_context::{IOContext, FlushInfo, MergeInfo};
+pub use rate_limiter::{RateLimiter, SimpleRateLimiter};
+pub use io_context::{IOContext, IOContextKind, FlushInfo, MergeInfo, ReadAdvice};
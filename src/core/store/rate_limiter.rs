@@ -1,6 +1,8 @@
-use std::time::Duration;
+use crate::error::Error::IllegalArgument;
 use crate::Result;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Trait base class to rate limit IO.
 ///
@@ -11,7 +13,7 @@ use std::sync::Arc;
 
 pub trait RateLimiter: Sync + Send {
     /// Sets an updated MB per second rate limit.
-    fn set_mb_per_sec(&self, mb_per_sec: f64);
+    fn set_mb_per_sec(&self, mb_per_sec: f64) -> Result<()>;
 
     /// The current MB per second rate limit.
     fn mb_per_sec(&self) -> f64;
@@ -27,8 +29,8 @@ pub trait RateLimiter: Sync + Send {
 }
 
 impl RateLimiter for Arc<dyn RateLimiter> {
-    fn set_mb_per_sec(&self, mb_per_sec: f64) {
-        (**self).set_mb_per_sec(mb_per_sec);
+    fn set_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        (**self).set_mb_per_sec(mb_per_sec)
     }
 
     fn mb_per_sec(&self) -> f64 {
@@ -43,3 +45,127 @@ impl RateLimiter for Arc<dyn RateLimiter> {
         (**self).min_pause_check_bytes()
     }
 }
+
+const MIN_PAUSE_CHECK_MSEC: f64 = 5.0;
+
+struct SimpleRateLimiterState {
+    mb_per_sec: f64,
+    min_pause_check_bytes: u64,
+    last_time: Instant,
+}
+
+/// Simple class to rate limit IO.
+///
+/// The rate can be changed at any time with `set_mb_per_sec`, e.g. by another
+/// thread, and applies to the following `pause` calls.
+pub struct SimpleRateLimiter {
+    state: Mutex<SimpleRateLimiterState>,
+}
+
+impl SimpleRateLimiter {
+    /// `mb_per_sec` is the MB/sec max IO rate, `f64::INFINITY` for no limit.
+    pub fn new(mb_per_sec: f64) -> Result<Self> {
+        let limiter = SimpleRateLimiter::default();
+        limiter.set_mb_per_sec(mb_per_sec)?;
+        Ok(limiter)
+    }
+}
+
+impl Default for SimpleRateLimiter {
+    /// A limiter with no limit.
+    fn default() -> Self {
+        SimpleRateLimiter {
+            state: Mutex::new(SimpleRateLimiterState {
+                mb_per_sec: f64::INFINITY,
+                min_pause_check_bytes: u64::MAX,
+                last_time: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl RateLimiter for SimpleRateLimiter {
+    fn set_mb_per_sec(&self, mb_per_sec: f64) -> Result<()> {
+        if mb_per_sec.is_nan() || mb_per_sec <= 0.0 {
+            return Err(IllegalArgument(format!(
+                "mb_per_sec must be > 0; got: {}",
+                mb_per_sec
+            )));
+        }
+        let mut state = self.state.lock()?;
+        state.mb_per_sec = mb_per_sec;
+        let check_value = MIN_PAUSE_CHECK_MSEC / 1000.0 * mb_per_sec * 1024.0 * 1024.0;
+        state.min_pause_check_bytes = if check_value.is_infinite() {
+            u64::MAX
+        } else {
+            check_value as u64
+        };
+        Ok(())
+    }
+
+    fn mb_per_sec(&self) -> f64 {
+        self.state.lock().unwrap().mb_per_sec
+    }
+
+    fn pause(&self, bytes: u64) -> Result<Duration> {
+        let start = Instant::now();
+        let target = {
+            let mut state = self.state.lock()?;
+            let seconds_to_pause = bytes as f64 / 1024.0 / 1024.0 / state.mb_per_sec;
+            // Time we should sleep until; this is purely instantaneous
+            // rate (just adds seconds onto the last time we had paused to):
+            let target = state.last_time + Duration::from_secs_f64(seconds_to_pause);
+            if start >= target {
+                // Set to now, not target, to enforce the instant rate, not
+                // the "averaged over all history" rate:
+                state.last_time = start;
+                return Ok(Duration::default());
+            }
+            state.last_time = target;
+            target
+        };
+
+        // the lock is released while sleeping, so that concurrent callers
+        // queue up their own target times after ours
+        thread::sleep(target - start);
+        Ok(start.elapsed())
+    }
+
+    fn min_pause_check_bytes(&self) -> u64 {
+        self.state.lock().unwrap().min_pause_check_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_rate_limiter() {
+        let limiter = SimpleRateLimiter::default();
+        assert_eq!(limiter.min_pause_check_bytes(), u64::MAX);
+        assert_eq!(limiter.pause(1 << 30).unwrap(), Duration::default());
+
+        // 1 MB at 10 MB/sec takes 100 msec
+        limiter.set_mb_per_sec(10.0).unwrap();
+        assert_eq!(limiter.mb_per_sec(), 10.0);
+        assert_eq!(limiter.min_pause_check_bytes(), 52428);
+        let start = Instant::now();
+        limiter.pause(1024 * 1024).unwrap();
+        limiter.pause(1024 * 1024).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        assert!(SimpleRateLimiter::new(0.0).is_err());
+        assert!(SimpleRateLimiter::new(-1.0).is_err());
+        assert!(SimpleRateLimiter::new(f64::NAN).is_err());
+
+        let limiter = SimpleRateLimiter::new(10.0).unwrap();
+        assert!(limiter.set_mb_per_sec(0.0).is_err());
+        assert!(limiter.set_mb_per_sec(f64::NAN).is_err());
+        // the rate is left unchanged
+        assert_eq!(limiter.mb_per_sec(), 10.0);
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// An object whose RAM usage can be computed.
+///
+/// The usage is an estimate of the heap memory held by the object, which
+/// can be broken down into the `child_resources` it is made of, e.g. a
+/// segment reader reports its postings, doc values, stored fields... so
+/// `NamedAccountable::new` gives a tree of where the memory goes.
+///
+/// @lucene.internal
+pub trait Accountable {
+    /// Return the memory usage of this object in bytes.
+    fn ram_bytes_used(&self) -> usize;
+
+    /// Returns nested resources of this object. The result should be a
+    /// point-in-time snapshot (to avoid race conditions).
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        Vec::new()
+    }
+}
+
+impl<T: Accountable + ?Sized> Accountable for Arc<T> {
+    fn ram_bytes_used(&self) -> usize {
+        (**self).ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        (**self).child_resources()
+    }
+}
+
+impl<T: Accountable + ?Sized> Accountable for Box<T> {
+    fn ram_bytes_used(&self) -> usize {
+        (**self).ram_bytes_used()
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        (**self).child_resources()
+    }
+}
+
+impl<T: Accountable> Accountable for Option<T> {
+    fn ram_bytes_used(&self) -> usize {
+        self.as_ref().map_or(0, |a| a.ram_bytes_used())
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.as_ref().map_or_else(Vec::new, |a| a.child_resources())
+    }
+}
+
+/// A named point-in-time snapshot of the RAM usage of an `Accountable` and
+/// of its children, which displays as an indented tree:
+///
+/// ```text
+/// segment _0: 12.4 KB
+///     postings: 10.1 KB
+///         field 'body': 9.8 KB
+///     stored fields: 1.2 KB
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamedAccountable {
+    pub name: String,
+    pub ram_bytes_used: usize,
+    pub children: Vec<NamedAccountable>,
+}
+
+impl NamedAccountable {
+    /// Snapshots the RAM usage of `accountable` and of its children.
+    pub fn new<A: Accountable + ?Sized>(name: impl Into<String>, accountable: &A) -> Self {
+        NamedAccountable {
+            name: name.into(),
+            ram_bytes_used: accountable.ram_bytes_used(),
+            children: accountable.child_resources(),
+        }
+    }
+
+    /// A resource without children using `ram_bytes_used` bytes.
+    pub fn with_bytes(name: impl Into<String>, ram_bytes_used: usize) -> Self {
+        NamedAccountable {
+            name: name.into(),
+            ram_bytes_used,
+            children: Vec::new(),
+        }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{}: {}",
+            "",
+            self.name,
+            human_readable_units(self.ram_bytes_used),
+            indent = depth * 4
+        )?;
+        for child in &self.children {
+            child.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Accountable for NamedAccountable {
+    fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used
+    }
+
+    fn child_resources(&self) -> Vec<NamedAccountable> {
+        self.children.clone()
+    }
+}
+
+impl fmt::Display for NamedAccountable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+/// Formats a number of bytes with the largest unit keeping it >= 1, e.g.
+/// "1.5 MB".
+pub fn human_readable_units(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Resource(usize, Vec<NamedAccountable>);
+
+    impl Accountable for Resource {
+        fn ram_bytes_used(&self) -> usize {
+            self.0 + self.1.iter().map(|c| c.ram_bytes_used).sum::<usize>()
+        }
+
+        fn child_resources(&self) -> Vec<NamedAccountable> {
+            self.1.clone()
+        }
+    }
+
+    #[test]
+    fn test_named_accountable() {
+        let resource = Arc::new(Resource(
+            100,
+            vec![
+                NamedAccountable::with_bytes("terms", 3 * 1024 + 512),
+                NamedAccountable::new("norms", &Resource(2 << 20, vec![])),
+            ],
+        ));
+        let named = NamedAccountable::new("segment _0", &resource);
+        assert_eq!(named.ram_bytes_used, 100 + 3584 + (2 << 20));
+        assert_eq!(named.children.len(), 2);
+        assert_eq!(
+            named.to_string(),
+            "segment _0: 2.0 MB\n    terms: 3.5 KB\n    norms: 2.0 MB\n"
+        );
+        assert_eq!(human_readable_units(10), "10 bytes");
+        assert_eq!(None::<Resource>.ram_bytes_used(), 0);
+    }
+}
@@ -299,11 +299,11 @@ impl FixedBitSet {
         let start_word = start_index >> 6;
         let end_word = (end_index - 1) >> 6;
 
-        let start_mask = !((-1i64) << (start_index & 0x3fusize)) as i64;
-        let end_mask = !((-1i64).unsigned_shift((64usize - (end_index & 0x3fusize)) & 0x3fusize));
+        let start_mask = (-1i64) << (start_index & 0x3fusize);
+        let end_mask = (-1i64).unsigned_shift((64usize - (end_index & 0x3fusize)) & 0x3fusize);
 
         if start_word == end_word {
-            self.bits[start_word] ^= start_mask | end_mask;
+            self.bits[start_word] ^= start_mask & end_mask;
             return;
         }
 
@@ -464,16 +464,18 @@ impl Bits for FixedBitSet {
         self.num_bits
     }
 
-    fn as_bit_set(&self) -> &dyn BitSet {
-        self
+    fn as_bit_set(&self) -> Option<&dyn BitSet> {
+        Some(self)
     }
 
-    fn as_bit_set_mut(&mut self) -> &mut dyn BitSet {
-        self
+    fn as_bit_set_mut(&mut self) -> Option<&mut dyn BitSet> {
+        Some(self)
     }
 
-    fn clone_box(&self) -> BitsRef {
-        Arc::new(Self::copy_from(self.bits.clone(), self.num_bits).unwrap())
+    fn clone_box(&self) -> Option<BitsRef> {
+        Some(Arc::new(
+            Self::copy_from(self.bits.clone(), self.num_bits).unwrap(),
+        ))
     }
 }
 
@@ -34,16 +34,16 @@ pub trait Bits: Send + Sync {
         self.len() == 0
     }
 
-    // these two method are currently only implemented for FixedBitSet used
-    // in live docs
-    fn as_bit_set(&self) -> &dyn BitSet {
-        unreachable!()
+    // these methods are currently only implemented for FixedBitSet used
+    // in live docs, other implementations return `None`
+    fn as_bit_set(&self) -> Option<&dyn BitSet> {
+        None
     }
-    fn as_bit_set_mut(&mut self) -> &mut dyn BitSet {
-        unreachable!()
+    fn as_bit_set_mut(&mut self) -> Option<&mut dyn BitSet> {
+        None
     }
-    fn clone_box(&self) -> BitsRef {
-        unreachable!()
+    fn clone_box(&self) -> Option<BitsRef> {
+        None
     }
 }
 
@@ -171,24 +171,23 @@ pub struct FixedBits {
 
 impl FixedBits {
     pub fn new(bits: Arc<Vec<i64>>, len: usize) -> FixedBits {
-        FixedBits {
-            len,
-            bits,
-        }
+        FixedBits { len, bits }
     }
 
     pub fn cardinality(&self) -> usize {
         let mut n = 0_usize;
-        self.bits.iter().for_each(|x| {
-            n += x.count_ones() as usize
-        });
+        self.bits.iter().for_each(|x| n += x.count_ones() as usize);
         n
     }
 }
 
 impl Bits for FixedBits {
     fn get(&self, index: usize) -> bool {
-        debug_assert!(index < self.len, "index out of bounds (index: {index}, num_bits: {}", self.len);
+        debug_assert!(
+            index < self.len,
+            "index out of bounds (index: {index}, num_bits: {}",
+            self.len
+        );
         let i = index >> 6; // div 64
 
         let bit_mask = 1i64 << (index % 64) as i64;
@@ -203,6 +202,8 @@ impl Bits for FixedBits {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::codec::doc_values::NumericDocValues;
+    use crate::core::util::DocId;
 
     #[test]
     fn fixed_bits() {
@@ -215,9 +216,51 @@ mod tests {
         assert_eq!(fixed_bits.get(66), false);
         assert_eq!(fixed_bits.get(190), false);
     }
+
+    struct DocIds(Vec<i64>);
+
+    impl NumericDocValues for DocIds {
+        fn get(&self, doc_id: DocId) -> Result<i64> {
+            self.get64(i64::from(doc_id))
+        }
+    }
+
+    impl LongValues for DocIds {
+        fn get64(&self, index: i64) -> Result<i64> {
+            Ok(self.0[index as usize])
+        }
+    }
+
+    #[test]
+    fn sparse_bits_jump_table() -> Result<()> {
+        let max_doc = 1000;
+        let doc_ids: Vec<i64> = vec![3, 4, 60, 61, 62, 300, 520, 999];
+        // blocks of 16 docs
+        let mut starts = vec![];
+        for (i, doc) in doc_ids.iter().enumerate() {
+            while (starts.len() as i64) << 4 <= *doc {
+                starts.push(i as i64);
+            }
+        }
+        let bits = SparseBits::new(max_doc, doc_ids.len() as i64, DocIds(doc_ids.clone()))?
+            .with_jump_table(SparseJumpTable::new(4, starts));
+
+        let mut ctx = bits.context();
+        let targets = [998, 0, 61, 300, 62, 4, 999, 520, 519, 16, 3, 301, 60];
+        for target in targets.iter() {
+            assert_eq!(bits.get64(&mut ctx, *target)?, doc_ids.contains(target));
+            if doc_ids.contains(target) {
+                assert_eq!(doc_ids[ctx.index as usize], *target);
+            }
+        }
+        for target in 0..max_doc {
+            assert_eq!(bits.get64(&mut ctx, target)?, doc_ids.contains(&target));
+        }
+        Ok(())
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct SparseBitsContext {
     // index of doc_id in doc_ids
     pub index: i64,
@@ -245,12 +288,35 @@ impl SparseBitsContext {
     }
 }
 
+/// The index in the doc ids of a `SparseBits` of the first doc of each block
+/// of `1 << shift` docs, which lets it jump to far away targets instead of
+/// galloping through all the doc ids in between.
+#[derive(Clone)]
+pub struct SparseJumpTable {
+    shift: i32,
+    starts: Arc<Vec<i64>>,
+}
+
+impl SparseJumpTable {
+    pub fn new(shift: i32, starts: Vec<i64>) -> Self {
+        SparseJumpTable {
+            shift,
+            starts: Arc::new(starts),
+        }
+    }
+
+    fn block(&self, doc_id: i64) -> i64 {
+        doc_id >> self.shift
+    }
+}
+
 #[derive(Clone)]
 pub struct SparseBits<T: LongValues> {
     max_doc: i64,
     doc_ids_length: i64,
     first_doc_id: i64,
     doc_ids: T,
+    jump_table: Option<SparseJumpTable>,
     pub ctx: SparseBitsContext,
 }
 
@@ -271,10 +337,43 @@ impl<T: LongValues> SparseBits<T> {
             doc_ids_length,
             first_doc_id,
             doc_ids,
+            jump_table: None,
             ctx: SparseBitsContext::new(first_doc_id),
         })
     }
 
+    /// Returns these bits using `jump_table` to seek to targets in other blocks.
+    pub fn with_jump_table(mut self, jump_table: SparseJumpTable) -> Self {
+        self.jump_table = Some(jump_table);
+        self
+    }
+
+    /// Moves `ctx` right before the first doc id of the block of `doc_id`.
+    fn jump(
+        &self,
+        ctx: &mut SparseBitsContext,
+        jump_table: &SparseJumpTable,
+        doc_id: i64,
+    ) -> Result<()> {
+        let index = jump_table
+            .starts
+            .get(jump_table.block(doc_id) as usize)
+            .copied()
+            .unwrap_or(self.doc_ids_length);
+        ctx.index = index - 1;
+        ctx.doc_id = if index > 0 {
+            self.doc_ids.get64(index - 1)?
+        } else {
+            -1
+        };
+        ctx.next_doc_id = if index < self.doc_ids_length {
+            self.doc_ids.get64(index)?
+        } else {
+            self.max_doc
+        };
+        Ok(())
+    }
+
     /// Gallop forward and stop as soon as an index is found that is greater than
     ///  the given docId. *index* will store an index that stores a value
     /// that is <= *docId* while the return value will give an index
@@ -370,6 +469,12 @@ impl<T: LongValues> SparseBits<T> {
     }
 
     pub fn get64(&self, ctx: &mut SparseBitsContext, doc_id: i64) -> Result<bool> {
+        if let Some(ref jump_table) = self.jump_table {
+            // galloping is cheaper than jumping within a block
+            if doc_id < ctx.doc_id || jump_table.block(doc_id) > jump_table.block(ctx.next_doc_id) {
+                self.jump(ctx, jump_table, doc_id)?;
+            }
+        }
         if doc_id < ctx.doc_id {
             // reading doc ids backward, go back to the start
             ctx.reset(self.first_doc_id)
@@ -408,11 +513,12 @@ impl<T: LongValues> Bits for SparseBits<T> {
 
 impl<T: LongValues> BitsMut for SparseBits<T> {
     fn get(&mut self, index: usize) -> bool {
-        unsafe {
-            let ctx: *mut SparseBitsContext = &self.ctx as *const _ as *mut _;
-            let b = ctx.as_mut_unchecked();
-            self.get64(b, index as i64).unwrap()
-        }
+        // the context is a few words, copy it out so that `get64` can borrow
+        // `self` immutably while advancing it
+        let mut ctx = self.ctx;
+        let res = self.get64(&mut ctx, index as i64).unwrap();
+        self.ctx = ctx;
+        res
     }
 
     fn len(&self) -> usize {
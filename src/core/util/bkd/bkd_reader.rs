@@ -18,7 +18,8 @@ use crate::core::store::io::{ByteArrayDataInput, ByteArrayRef, DataInput, IndexI
 use crate::core::util::bkd::DocIdsWriter;
 use crate::core::util::bkd::{
     BKD_CODEC_NAME, BKD_VERSION_COMPRESSED_DOC_IDS, BKD_VERSION_COMPRESSED_VALUES,
-    BKD_VERSION_CURRENT, BKD_VERSION_IMPLICIT_SPLIT_DIM_1D, BKD_VERSION_PACKED_INDEX,
+    BKD_VERSION_IMPLICIT_SPLIT_DIM_1D, BKD_VERSION_LEAF_STORES_BOUNDS,
+    BKD_VERSION_LOW_CARDINALITY_LEAVES, BKD_VERSION_PACKED_INDEX, BKD_VERSION_SELECTIVE_INDEXING,
     BKD_VERSION_START,
 };
 use crate::core::util::math;
@@ -35,6 +36,8 @@ pub struct IntersectState<'a, IV: IntersectVisitor> {
     pub input: Box<dyn IndexInput>,
     scratch_doc_ids: Vec<i32>,
     pub scratch_packed_value: Vec<u8>,
+    scratch_min_index_packed_value: Vec<u8>,
+    scratch_max_index_packed_value: Vec<u8>,
     common_prefix_lengths: Vec<i32>,
     visitor: &'a mut IV,
     index_tree: Box<dyn IndexTree>,
@@ -51,11 +54,15 @@ impl<'a, IV: IntersectVisitor + 'a> IntersectState<'a, IV> {
     ) -> Self {
         let scratch_doc_ids = vec![0i32; max_points_in_leaf_node];
         let scratch_packed_value = vec![0u8; packed_bytes_length];
+        let scratch_min_index_packed_value = vec![0u8; packed_bytes_length];
+        let scratch_max_index_packed_value = vec![0u8; packed_bytes_length];
         let common_prefix_lengths = vec![0i32; num_dims];
         IntersectState {
             input,
             scratch_doc_ids,
             scratch_packed_value,
+            scratch_min_index_packed_value,
+            scratch_max_index_packed_value,
             common_prefix_lengths,
             visitor,
             index_tree,
@@ -69,7 +76,8 @@ impl<'a, IV: IntersectVisitor + 'a> IntersectState<'a, IV> {
 pub struct BKDReader {
     /// Packed array of bytes holding all split values in the full binary tree:
     pub leaf_node_offset: i32,
-    pub num_dims: usize,
+    pub num_data_dims: usize,
+    pub num_index_dims: usize,
     pub bytes_per_dim: usize,
     num_leaves: usize,
     pub input: Arc<dyn IndexInput>,
@@ -80,6 +88,7 @@ pub struct BKDReader {
     pub doc_count: i32,
     version: i32,
     pub packed_bytes_length: usize,
+    pub packed_index_bytes_length: usize,
     /// Used for 6.4.0+ index format
     packed_index: Arc<Vec<u8>>,
     /// Used for Legacy (pre-6.4.0) index format, to hold a compact form of the index:
@@ -96,30 +105,36 @@ impl BKDReader {
             reader.as_mut(),
             BKD_CODEC_NAME,
             BKD_VERSION_START,
-            BKD_VERSION_CURRENT,
+            BKD_VERSION_LOW_CARDINALITY_LEAVES,
         )?;
-        let num_dims = reader.read_vint()? as usize;
-        let max_points_in_leaf_node = reader.read_vint()? as usize;
-        let bytes_per_dim = reader.read_vint()? as usize;
-        let bytes_per_index_entry = if num_dims == 1 && version >= BKD_VERSION_IMPLICIT_SPLIT_DIM_1D
-        {
-            bytes_per_dim
+        let num_data_dims = reader.read_vint()? as usize;
+        let num_index_dims = if version >= BKD_VERSION_SELECTIVE_INDEXING {
+            reader.read_vint()? as usize
         } else {
-            bytes_per_dim + 1usize
+            num_data_dims
         };
-        let packed_bytes_length = num_dims * bytes_per_dim;
+        let max_points_in_leaf_node = reader.read_vint()? as usize;
+        let bytes_per_dim = reader.read_vint()? as usize;
+        let bytes_per_index_entry =
+            if num_index_dims == 1 && version >= BKD_VERSION_IMPLICIT_SPLIT_DIM_1D {
+                bytes_per_dim
+            } else {
+                bytes_per_dim + 1usize
+            };
+        let packed_bytes_length = num_data_dims * bytes_per_dim;
+        let packed_index_bytes_length = num_index_dims * bytes_per_dim;
 
         // read index
         let num_leaves = reader.read_vint()? as usize;
         debug_assert!(num_leaves as i32 > 0);
         let leaf_node_offset = num_leaves as i32;
 
-        let mut min_packed_value = vec![0u8; packed_bytes_length];
-        let mut max_packed_value = vec![0u8; packed_bytes_length];
+        let mut min_packed_value = vec![0u8; packed_index_bytes_length];
+        let mut max_packed_value = vec![0u8; packed_index_bytes_length];
         reader.read_exact(&mut min_packed_value)?;
         reader.read_exact(&mut max_packed_value)?;
 
-        for dim in 0..num_dims {
+        for dim in 0..num_index_dims {
             let start = dim * bytes_per_dim;
             let end = start + bytes_per_dim;
             if min_packed_value[start..end].cmp(&max_packed_value[start..end]) == Ordering::Greater
@@ -158,7 +173,7 @@ impl BKDReader {
             // Possibly rotate the leaf block FPs, if the index not fully balanced binary tree
             // (only happens if it was created by BKDWriter.merge or OneDimWriter).  In this case
             // the leaf nodes may straddle the two bottom levels of the binary tree:
-            if num_dims == 1 && num_leaves > 1 {
+            if num_index_dims == 1 && num_leaves > 1 {
                 let mut level_count = 2usize;
                 loop {
                     if num_leaves >= level_count && num_leaves <= 2 * level_count {
@@ -186,7 +201,8 @@ impl BKDReader {
 
         Ok(BKDReader {
             leaf_node_offset,
-            num_dims,
+            num_data_dims,
+            num_index_dims,
             bytes_per_dim,
             num_leaves,
             input,
@@ -197,6 +213,7 @@ impl BKDReader {
             doc_count,
             version,
             packed_bytes_length,
+            packed_index_bytes_length,
             packed_index: Arc::new(packed_index),
             split_packed_values,
             bytes_per_index_entry,
@@ -271,6 +288,8 @@ impl BKDReader {
                 self.visit_doc_values(
                     state.common_prefix_lengths.as_mut(),
                     state.scratch_packed_value.as_mut(),
+                    state.scratch_min_index_packed_value.as_mut(),
+                    state.scratch_max_index_packed_value.as_mut(),
                     state.input.as_mut(),
                     state.scratch_doc_ids.as_ref(),
                     count,
@@ -281,7 +300,7 @@ impl BKDReader {
             // Non-leaf node: recurse on the split left and right nodes
             let split_dim = state.index_tree.split_dim() as usize;
             debug_assert!(split_dim as i32 >= 0, "split_dim={}", split_dim);
-            debug_assert!(split_dim < self.num_dims);
+            debug_assert!(split_dim < self.num_index_dims);
 
             let split_packed_value_idx = state.index_tree.split_packed_value_index();
             let mut split_dim_value = state.index_tree.split_dim_value();
@@ -303,7 +322,7 @@ impl BKDReader {
             state.index_tree.set_split_packed_value(
                 split_packed_value_idx,
                 0,
-                &cell_max_packed[0..self.packed_bytes_length],
+                &cell_max_packed[0..self.packed_index_bytes_length],
             );
             state.index_tree.set_split_packed_value(
                 split_packed_value_idx,
@@ -323,8 +342,8 @@ impl BKDReader {
             );
 
             // Recurse on right sub-tree:
-            split_packed_value[0..self.packed_bytes_length]
-                .copy_from_slice(&cell_min_packed[0..self.packed_bytes_length]);
+            split_packed_value[0..self.packed_index_bytes_length]
+                .copy_from_slice(&cell_min_packed[0..self.packed_index_bytes_length]);
             split_packed_value[split_dim * self.bytes_per_dim
                 ..split_dim * self.bytes_per_dim + self.bytes_per_dim]
                 .copy_from_slice(&split_dim_value[0..self.bytes_per_dim]);
@@ -348,9 +367,9 @@ impl BKDReader {
         let index_tree: Box<dyn IndexTree> = if !self.packed_index.is_empty() {
             Box::new(PackedIndexTree::new(
                 self.bytes_per_dim,
-                self.num_dims,
+                self.num_index_dims,
                 self.tree_depth(),
-                self.packed_bytes_length,
+                self.packed_index_bytes_length,
                 self.leaf_node_offset,
                 Arc::clone(&self.packed_index),
             )?)
@@ -358,18 +377,18 @@ impl BKDReader {
             Box::new(LegacyIndexTree::new(
                 self.bytes_per_dim,
                 self.bytes_per_index_entry as i32,
-                self.num_dims as i32,
+                self.num_index_dims as i32,
                 Arc::clone(&self.leaf_block_fps),
                 self.version,
                 self.tree_depth(),
-                self.packed_bytes_length,
+                self.packed_index_bytes_length,
                 self.leaf_node_offset,
             ))
         };
 
         Ok(IntersectState::new(
             self.input.as_ref().clone()?,
-            self.num_dims,
+            self.num_data_dims,
             self.packed_bytes_length,
             self.max_points_in_leaf_node,
             visitor,
@@ -422,6 +441,8 @@ impl BKDReader {
         self.visit_doc_values(
             state.common_prefix_lengths.as_mut(),
             state.scratch_packed_value.as_mut(),
+            state.scratch_min_index_packed_value.as_mut(),
+            state.scratch_max_index_packed_value.as_mut(),
             state.input.as_mut(),
             &state.scratch_doc_ids,
             count,
@@ -470,23 +491,71 @@ impl BKDReader {
         Ok(count)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn visit_doc_values(
         &self,
         common_prefix_lengths: &mut [i32],
         scratch_packed_value: &mut [u8],
+        scratch_min_index_packed_value: &mut [u8],
+        scratch_max_index_packed_value: &mut [u8],
         input: &mut dyn IndexInput,
         doc_ids: &[DocId],
         count: usize,
         visitor: &mut impl IntersectVisitor,
     ) -> Result<()> {
-        visitor.grow(count);
-
         self.read_common_prefixes(common_prefix_lengths, scratch_packed_value, input)?;
 
+        // since `VERSION_LOW_CARDINALITY_LEAVES` the compressed dim comes before the leaf bounds
         let compressed_dim = if self.version < BKD_VERSION_COMPRESSED_VALUES {
-            -1
+            Some(-1)
+        } else if self.version >= BKD_VERSION_LOW_CARDINALITY_LEAVES {
+            let compressed_dim = self.read_compressed_dim(input)?;
+            if compressed_dim == -1 {
+                // all values of the leaf are the same
+                visitor.grow(count);
+                for doc in &doc_ids[..count] {
+                    visitor.visit_by_packed_value(*doc, scratch_packed_value)?;
+                }
+                return Ok(());
+            }
+            Some(compressed_dim)
         } else {
-            self.read_compressed_dim(input)?
+            None
+        };
+
+        if self.num_index_dims != 1 && self.version >= BKD_VERSION_LEAF_STORES_BOUNDS {
+            // the index only knows the cell bounds, the actual range of values of the leaf
+            // might be much narrower, so check the relation again which is cheap and might
+            // save decoding all values of the leaf.
+            let len = self.packed_index_bytes_length;
+            scratch_min_index_packed_value[..len].copy_from_slice(&scratch_packed_value[..len]);
+            scratch_max_index_packed_value[..len].copy_from_slice(&scratch_packed_value[..len]);
+            self.read_min_max(
+                common_prefix_lengths,
+                scratch_min_index_packed_value,
+                scratch_max_index_packed_value,
+                input,
+            )?;
+            match visitor.compare(
+                &scratch_min_index_packed_value[..len],
+                &scratch_max_index_packed_value[..len],
+            ) {
+                Relation::CellOutsideQuery => return Ok(()),
+                Relation::CellInsideQuery => {
+                    visitor.grow(count);
+                    for doc in &doc_ids[..count] {
+                        visitor.visit(*doc)?;
+                    }
+                    return Ok(());
+                }
+                Relation::CellCrossesQuery => {}
+            }
+        }
+        visitor.grow(count);
+
+        let compressed_dim = match compressed_dim {
+            Some(dim) => dim,
+            None => self.read_compressed_dim(input)?,
         };
 
         if compressed_dim == -1 {
@@ -498,6 +567,15 @@ impl BKDReader {
                 count,
                 visitor,
             )?;
+        } else if compressed_dim == -2 {
+            self.visit_sparse_raw_doc_values(
+                common_prefix_lengths,
+                scratch_packed_value,
+                input,
+                doc_ids,
+                count,
+                visitor,
+            )?;
         } else {
             self.visit_compressed_doc_values(
                 common_prefix_lengths,
@@ -513,6 +591,71 @@ impl BKDReader {
         Ok(())
     }
 
+    // Read the suffixes of the actual min and max values of the leaf for every index dimension
+    fn read_min_max(
+        &self,
+        common_prefix_lengths: &[i32],
+        min_packed_value: &mut [u8],
+        max_packed_value: &mut [u8],
+        input: &mut dyn IndexInput,
+    ) -> Result<()> {
+        for (dim, length) in common_prefix_lengths
+            .iter()
+            .enumerate()
+            .take(self.num_index_dims)
+        {
+            let prefix = *length as usize;
+            let offset = dim * self.bytes_per_dim + prefix;
+            input.read_bytes(min_packed_value, offset, self.bytes_per_dim - prefix)?;
+            input.read_bytes(max_packed_value, offset, self.bytes_per_dim - prefix)?;
+        }
+        Ok(())
+    }
+
+    // Low cardinality leaves store runs of equal values: a run length followed by the suffixes
+    fn visit_sparse_raw_doc_values(
+        &self,
+        common_prefix_lengths: &[i32],
+        scratch_packed_value: &mut [u8],
+        input: &mut dyn IndexInput,
+        doc_ids: &[DocId],
+        count: usize,
+        visitor: &mut impl IntersectVisitor,
+    ) -> Result<()> {
+        let mut i = 0usize;
+        while i < count {
+            let length = input.read_vint()? as usize;
+            for (dim, prefix_len) in common_prefix_lengths
+                .iter()
+                .enumerate()
+                .take(self.num_data_dims)
+            {
+                let prefix = *prefix_len as usize;
+                input.read_bytes(
+                    scratch_packed_value,
+                    dim * self.bytes_per_dim + prefix,
+                    self.bytes_per_dim - prefix,
+                )?;
+            }
+            if i + length > count {
+                break;
+            }
+            for doc in &doc_ids[i..i + length] {
+                visitor.visit_by_packed_value(*doc, scratch_packed_value)?;
+            }
+            i += length;
+        }
+
+        if i != count {
+            return Err(Error::CorruptIndex(format!(
+                "Sub blocks do not add up to the expected count: {} != {}",
+                count, i
+            )));
+        }
+
+        Ok(())
+    }
+
     // Just read suffixes for every dimension
     fn visit_raw_doc_values(
         &self,
@@ -524,7 +667,11 @@ impl BKDReader {
         visitor: &mut impl IntersectVisitor,
     ) -> Result<()> {
         for doc in doc_ids.iter().take(count) {
-            for (dim, length) in common_prefix_lengths.iter().enumerate().take(self.num_dims) {
+            for (dim, length) in common_prefix_lengths
+                .iter()
+                .enumerate()
+                .take(self.num_data_dims)
+            {
                 let prefix = *length as usize;
 
                 input.read_bytes(
@@ -563,7 +710,11 @@ impl BKDReader {
 
             let run_len = input.read_byte()? as usize;
             for j in 0..run_len {
-                for (dim, length) in common_prefix_lengths.iter().enumerate().take(self.num_dims) {
+                for (dim, length) in common_prefix_lengths
+                    .iter()
+                    .enumerate()
+                    .take(self.num_data_dims)
+                {
                     let prefix = *length as usize;
 
                     input.read_bytes(
@@ -592,7 +743,12 @@ impl BKDReader {
     fn read_compressed_dim(&self, input: &mut dyn IndexInput) -> Result<i32> {
         let compressed_dim = i32::from(input.read_byte()? as i8);
 
-        if compressed_dim < -1 || compressed_dim >= self.num_dims as i32 {
+        let min_dim = if self.version >= BKD_VERSION_LOW_CARDINALITY_LEAVES {
+            -2
+        } else {
+            -1
+        };
+        if compressed_dim < min_dim || compressed_dim >= self.num_data_dims as i32 {
             return Err(Error::CorruptIndex(format!(
                 "Got compressedDim={}",
                 compressed_dim
@@ -611,7 +767,7 @@ impl BKDReader {
         for (dim, length) in common_prefix_lengths
             .iter_mut()
             .enumerate()
-            .take(self.num_dims)
+            .take(self.num_data_dims)
         {
             let prefix = input.read_vint()?;
 
@@ -1160,7 +1316,7 @@ impl<'a, IV: IntersectVisitor + 'a> MergeReader<'a, IV> {
     ) -> Result<Self> {
         let mut state = IntersectState::new(
             reader.input.as_ref().clone()?,
-            reader.num_dims,
+            reader.num_data_dims,
             reader.packed_bytes_length,
             reader.max_points_in_leaf_node,
             visitor,
@@ -1202,6 +1358,8 @@ impl<'a, IV: IntersectVisitor + 'a> MergeReader<'a, IV> {
                 self.reader.visit_doc_values(
                     &mut self.state.common_prefix_lengths,
                     &mut self.state.scratch_packed_value,
+                    &mut self.state.scratch_min_index_packed_value,
+                    &mut self.state.scratch_max_index_packed_value,
                     self.state.input.as_mut(),
                     &self.state.scratch_doc_ids,
                     self.docs_in_block,
@@ -1297,7 +1455,8 @@ impl<'a, IV: IntersectVisitor + 'a> IntersectVisitor for MergeIntersectVisitor<'
     }
 
     fn compare(&self, _min_packed_value: &[u8], _max_packed_value: &[u8]) -> Relation {
-        unreachable!()
+        // merging must see every point, even if the leaf stores its bounds
+        Relation::CellCrossesQuery
     }
 }
 
@@ -24,12 +24,12 @@ use crate::core::util::bit_set::{BitSet, FixedBitSet, ImmutableBitSet};
 use crate::core::util::bit_util::UnsignedShift;
 use crate::core::util::bkd::{
     bkd_reader::{MergeReader, StubIntersectVisitor},
-    BKDReader, DocIdsWriter, HeapPointWriter, LongBitSet, MutablePointsReaderUtils,
-    OfflinePointWriter, PointReader, PointType, PointWriter, PointWriterEnum,
+    BKDReader, DocIdsWriter, HeapPointWriter, MutablePointsReaderUtils, OfflinePointWriter,
+    PointReader, PointType, PointWriter, PointWriterEnum,
 };
 use crate::core::util::sorter::{check_range, MSBRadixSorter, MSBSorter, Sorter};
 use crate::core::util::string_util::bytes_subtract;
-use crate::core::util::DocId;
+use crate::core::util::{DocId, LongBitSet, OfflineSorter};
 
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -44,7 +44,9 @@ pub const VERSION_COMPRESSED_DOC_IDS: i32 = 1;
 pub const VERSION_COMPRESSED_VALUES: i32 = 2;
 pub const VERSION_IMPLICIT_SPLIT_DIM_1D: i32 = 3;
 pub const VERSION_PACKED_INDEX: i32 = 4;
-pub const VERSION_CURRENT: i32 = VERSION_PACKED_INDEX;
+pub const VERSION_LEAF_STORES_BOUNDS: i32 = 5;
+pub const VERSION_SELECTIVE_INDEXING: i32 = 6;
+pub const VERSION_CURRENT: i32 = VERSION_SELECTIVE_INDEXING;
 pub const DEFAULT_MAX_POINTS_IN_LEAF_NODE: i32 = 1024;
 pub const DEFAULT_MAX_MB_SORT_IN_HEAP: f32 = 1024.0f32;
 pub const MAX_DIMS: i32 = 8;
@@ -110,10 +112,10 @@ impl<'a, D: Directory, O: IndexOutput> OneDimensionBKDWriter<'a, D, O> {
         out: &'a mut O,
         bkd_writer: &mut BKDWriter<D>,
     ) -> Result<OneDimensionBKDWriter<'a, D, O>> {
-        if bkd_writer.num_dims != 1 {
+        if bkd_writer.num_data_dims != 1 {
             return Err(UnsupportedOperation(format!(
-                "num_dims must be 1 but got {}",
-                bkd_writer.num_dims
+                "num_data_dims must be 1 but got {}",
+                bkd_writer.num_data_dims
             )));
         }
         if bkd_writer.point_count != 0 {
@@ -309,14 +311,33 @@ impl<'a, D: Directory, O: IndexOutput> OneDimensionBKDWriter<'a, D, O> {
     }
 }
 
+/// Recursively builds a block KD-tree to assign all incoming points in N-dim space to smaller
+/// and smaller N-dim rectangles (cells) until the number of points in a given rectangle is
+/// <= `max_points_in_leaf_node`.
+///
+/// Only the first `num_index_dims` dimensions of a point are used to split cells and are
+/// visible to `IntersectVisitor::compare`, the remaining data dimensions are only stored
+/// in the leaf blocks and handed to `IntersectVisitor::visit_by_packed_value`. This is
+/// how e.g. shapes store their encoding alongside the indexed bounding box.
+///
+/// Tuning:
+/// - `max_points_in_leaf_node` trades index size against search cost: larger leaves mean
+///   fewer inner nodes (a smaller index to load in heap) and better compression of the
+///   leaf blocks, but more points to scan for every leaf that crosses a query.
+/// - `max_mb_sort_in_heap` bounds the heap used to sort the points of a segment, it must
+///   be able to hold at least one leaf per indexed dimension. Sorting more points than fit
+///   in heap is not supported yet for multi-dimensional points, so this also caps the
+///   number of points of such a field in one segment.
 pub struct BKDWriter<D: Directory> {
-    _bytes_per_doc: usize,
-    num_dims: usize,
+    bytes_per_doc: usize,
+    num_data_dims: usize,
+    num_index_dims: usize,
     bytes_per_dim: usize,
     packed_bytes_length: usize,
+    packed_index_bytes_length: usize,
     temp_dir: TrackingDirectoryWrapper<D, Arc<D>>,
     temp_file_name_prefix: String,
-    _max_mb_sort_in_heap: f64,
+    max_mb_sort_in_heap: f64,
     scratch_diff: Vec<u8>,
     scratch1: Vec<u8>,
     scratch2: Vec<u8>,
@@ -344,34 +365,53 @@ pub struct BKDWriter<D: Directory> {
     min_packed_value: Vec<u8>,
     max_packed_value: Vec<u8>,
     scratch_out: GrowableByteArrayDataOutput,
+    version: i32,
 }
 
 impl<D: Directory> BKDWriter<D> {
+    /// Creates a writer of a BKD tree in `version`, which is `VERSION_CURRENT` unless
+    /// the tree must be read by an older Java Lucene, down to `VERSION_PACKED_INDEX`.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         max_doc: i32,
         temp_dir: Arc<D>,
         temp_file_name_prefix: &str,
-        num_dims: usize,
+        num_data_dims: usize,
+        num_index_dims: usize,
         bytes_per_dim: usize,
         max_points_in_leaf_node: i32,
         max_mb_sort_in_heap: f64,
         total_point_count: i64,
         single_value_per_doc: bool,
+        version: i32,
     ) -> Result<BKDWriter<D>> {
         Self::verify_params(
-            num_dims,
+            num_data_dims,
+            num_index_dims,
             max_points_in_leaf_node,
             max_mb_sort_in_heap,
             total_point_count,
         )?;
+        if !(VERSION_PACKED_INDEX..=VERSION_CURRENT).contains(&version) {
+            return Err(IllegalArgument(format!(
+                "can't write BKD version {}",
+                version
+            )));
+        }
+        if version < VERSION_SELECTIVE_INDEXING && num_index_dims != num_data_dims {
+            return Err(IllegalArgument(format!(
+                "BKD version {} can't index {} of {} dimensions",
+                version, num_index_dims, num_data_dims
+            )));
+        }
         // We use tracking dir to deal with removing files on exception, so each place that
         // creates temp files doesn't need crazy try/finally/sucess logic:
         // If we may have more than 1+Integer.MAX_VALUE values, then we must encode ords with long
         // (8 bytes), else we can use int (4 bytes).
         let long_ords = total_point_count > i32::max_value() as i64;
 
-        let packed_bytes_length = num_dims * bytes_per_dim;
+        let packed_bytes_length = num_data_dims * bytes_per_dim;
+        let packed_index_bytes_length = num_index_dims * bytes_per_dim;
         // dimensional values (numDims * bytesPerDim) + ord (int or long) + docID (int)
         let bytes_per_doc = if single_value_per_doc {
             // Lucene only supports up to 2.1 docs, so we better not need longOrds in this case:
@@ -390,9 +430,9 @@ impl<D: Directory> BKDWriter<D> {
         // all recursive halves (i.e. 16 + 8 + 4 + 2) so the memory usage is 2X
         // what that level would consume, so we multiply by 0.5 to convert from
         // bytes to points here.  Each dimension has its own sorted partition, so
-        // we must divide by numDims as wel.
+        // we must divide by numIndexDims as wel.
         let max_points_sort_in_heap = (0.5 * (max_mb_sort_in_heap * 1024f64 * 1024f64)
-            / (bytes_per_doc as f64 * num_dims as f64))
+            / (bytes_per_doc as f64 * num_index_dims as f64))
             as i32;
         // Finally, we must be able to hold at least the leaf node in heap during build:
         if max_points_sort_in_heap < max_points_in_leaf_node {
@@ -415,17 +455,19 @@ impl<D: Directory> BKDWriter<D> {
         );
 
         Ok(BKDWriter {
-            _bytes_per_doc: bytes_per_doc,
-            num_dims,
+            bytes_per_doc,
+            num_data_dims,
+            num_index_dims,
             bytes_per_dim,
             packed_bytes_length,
+            packed_index_bytes_length,
             temp_dir: TrackingDirectoryWrapper::new(temp_dir),
             temp_file_name_prefix: temp_file_name_prefix.to_string(),
-            _max_mb_sort_in_heap: max_mb_sort_in_heap,
+            max_mb_sort_in_heap,
             scratch_diff: vec![0u8; bytes_per_dim],
             scratch1: vec![0u8; packed_bytes_length],
             scratch2: vec![0u8; packed_bytes_length],
-            common_prefix_lengths: vec![0; num_dims],
+            common_prefix_lengths: vec![0; num_data_dims],
             docs_seen: FixedBitSet::new(max_doc as usize),
             offline_point_writer: None,
             temp_input: None,
@@ -437,9 +479,10 @@ impl<D: Directory> BKDWriter<D> {
             long_ords,
             single_value_per_doc,
             max_doc,
-            min_packed_value: vec![0u8; packed_bytes_length],
-            max_packed_value: vec![0u8; packed_bytes_length],
+            min_packed_value: vec![0u8; packed_index_bytes_length],
+            max_packed_value: vec![0u8; packed_index_bytes_length],
             scratch_out: GrowableByteArrayDataOutput::new(32 * 1024),
+            version,
         })
     }
 
@@ -451,7 +494,7 @@ impl<D: Directory> BKDWriter<D> {
             )));
         }
 
-        if self.point_count > self.max_points_sort_in_heap as i64 {
+        if self.point_count >= self.max_points_sort_in_heap as i64 {
             if self.offline_point_writer.is_none() {
                 self.spill_to_offline()?;
             }
@@ -472,12 +515,12 @@ impl<D: Directory> BKDWriter<D> {
 
         // TODO: we could specialize for the 1D case:
         if self.point_count == 0 {
-            self.min_packed_value[0..self.packed_bytes_length]
-                .copy_from_slice(&packed_value[0..self.packed_bytes_length]);
-            self.max_packed_value[0..self.packed_bytes_length]
-                .copy_from_slice(&packed_value[0..self.packed_bytes_length]);
+            self.min_packed_value[0..self.packed_index_bytes_length]
+                .copy_from_slice(&packed_value[0..self.packed_index_bytes_length]);
+            self.max_packed_value[0..self.packed_index_bytes_length]
+                .copy_from_slice(&packed_value[0..self.packed_index_bytes_length]);
         } else {
-            for dim in 0..self.num_dims {
+            for dim in 0..self.num_index_dims {
                 let offset = dim * self.bytes_per_dim;
                 let end_offset = (dim + 1) * self.bytes_per_dim;
                 if packed_value[offset..end_offset] < self.min_packed_value[offset..end_offset] {
@@ -510,27 +553,34 @@ impl<D: Directory> BKDWriter<D> {
         field_name: &str,
         reader: impl MutablePointsReader,
     ) -> Result<i64> {
-        if self.num_dims == 1 {
+        if self.num_data_dims == 1 {
             self.write_field_1_dim(out, field_name, reader)
         } else {
-            unimplemented!()
+            self.write_field_n_dims(out, field_name, reader)
         }
     }
 
     pub fn verify_params(
-        num_dims: usize,
+        num_data_dims: usize,
+        num_index_dims: usize,
         max_points_in_leaf_node: i32,
         max_mb_sort_in_heap: f64,
         total_point_count: i64,
     ) -> Result<()> {
         // We encode dim in a single byte in the splitPackedValues, but we only expose 4 bits for
         // it now, in case we want to use remaining 4 bits for another purpose later
-        if num_dims < 1 || num_dims as i32 > MAX_DIMS {
+        if num_data_dims < 1 || num_data_dims as i32 > MAX_DIMS {
             return Err(IllegalArgument(format!(
-                "num_dims must be 1 .. {}",
+                "num_data_dims must be 1 .. {}",
                 MAX_DIMS
             )));
         }
+        if num_index_dims < 1 || num_index_dims > num_data_dims {
+            return Err(IllegalArgument(format!(
+                "num_index_dims must be 1 .. {}, got {}",
+                num_data_dims, num_index_dims
+            )));
+        }
         if max_points_in_leaf_node <= 0 {
             return Err(IllegalArgument(
                 "max_points_in_leaf_node must be > 0".into(),
@@ -563,7 +613,7 @@ impl<D: Directory> BKDWriter<D> {
             return Err(IllegalState("must index at least one point".into()));
         }
 
-        let mut ord_bit_set = if self.num_dims > 1 {
+        let mut ord_bit_set = if self.num_index_dims > 1 {
             if self.single_value_per_doc {
                 Some(LongBitSet::new(self.max_doc as i64))
             } else {
@@ -601,7 +651,7 @@ impl<D: Directory> BKDWriter<D> {
         // This is only used on exception; on normal code paths we close all files we opened:
         let mut _success = false;
 
-        for dim in 0..self.num_dims {
+        for dim in 0..self.num_index_dims {
             sorted_point_writers.push(PathSlice::new(self.sort(dim as i32)?, 0, self.point_count));
         }
 
@@ -616,7 +666,7 @@ impl<D: Directory> BKDWriter<D> {
 
         let min_packed_value = (&mut self.min_packed_value) as *mut Vec<u8>;
         let max_packed_value = (&mut self.max_packed_value) as *mut Vec<u8>;
-        let mut parent_splits: Vec<i32> = vec![0i32; self.num_dims as usize];
+        let mut parent_splits: Vec<i32> = vec![0i32; self.num_index_dims];
         self.build(
             1,
             num_leaves,
@@ -778,6 +828,27 @@ impl<D: Directory> BKDWriter<D> {
         one_dim_writer.finish()
     }
 
+    // TODO: partition the mutable points in place like Lucene's `writeFieldNDims`
+    // instead of copying them to the heap writer
+    fn write_field_n_dims<O: IndexOutput>(
+        &mut self,
+        out: &mut O,
+        field_name: &str,
+        reader: impl MutablePointsReader,
+    ) -> Result<i64> {
+        let size = reader.size(field_name)? as i32;
+        let mut packed_value = vec![0u8; self.packed_bytes_length];
+        for i in 0..size {
+            reader.value(i, &mut packed_value);
+            self.add(&packed_value, reader.doc_id(i))?;
+        }
+        if self.point_count > 0 {
+            self.finish(out)
+        } else {
+            Ok(-1)
+        }
+    }
+
     fn rotate_to_tree(
         &self,
         node_id: i32,
@@ -852,7 +923,7 @@ impl<D: Directory> BKDWriter<D> {
         // Possibly rotate the leaf block FPs, if the index not fully balanced binary tree (only
         // happens if it was created by OneDimensionBKDWriter).  In this case the leaf
         // nodes may straddle the two bottom levels of the binary tree:
-        if self.num_dims == 1 && num_leaves > 1 {
+        if self.num_index_dims == 1 && num_leaves > 1 {
             let mut level_count = 2;
             loop {
                 if num_leaves >= level_count && num_leaves <= 2 * level_count {
@@ -883,8 +954,8 @@ impl<D: Directory> BKDWriter<D> {
 
         // This is the "file" we append the bytes to:
         let mut blocks = vec![];
-        let mut last_split_values = vec![0u8; self.bytes_per_dim * self.num_dims];
-        let mut negative_deltas = vec![false; self.num_dims];
+        let mut last_split_values = vec![0u8; self.bytes_per_dim * self.num_index_dims];
+        let mut negative_deltas = vec![false; self.num_index_dims];
 
         let total_size = self.recurse_pack_index(
             &mut write_buffer,
@@ -981,7 +1052,7 @@ impl<D: Directory> BKDWriter<D> {
 
             // pack the prefix, splitDim and delta first diff byte into a single vInt:
             let code = ((first_diff_byte_delta as usize * (1 + self.bytes_per_dim) + prefix)
-                * self.num_dims
+                * self.num_index_dims
                 + split_dim) as i32;
             write_buffer.write_vint(code)?;
 
@@ -1108,16 +1179,19 @@ impl<D: Directory> BKDWriter<D> {
         num_leaves: i32,
         packed_index: &[u8],
     ) -> Result<()> {
-        write_header(out, CODEC_NAME, VERSION_CURRENT)?;
+        write_header(out, CODEC_NAME, self.version)?;
 
-        out.write_vint(self.num_dims as i32)?;
+        out.write_vint(self.num_data_dims as i32)?;
+        if self.version >= VERSION_SELECTIVE_INDEXING {
+            out.write_vint(self.num_index_dims as i32)?;
+        }
         out.write_vint(self.max_points_in_leaf_node)?;
         out.write_vint(self.bytes_per_dim as i32)?;
 
         debug_assert!(num_leaves > 0);
         out.write_vint(num_leaves)?;
-        out.write_bytes(&self.min_packed_value, 0, self.packed_bytes_length)?;
-        out.write_bytes(&self.max_packed_value, 0, self.packed_bytes_length)?;
+        out.write_bytes(&self.min_packed_value, 0, self.packed_index_bytes_length)?;
+        out.write_bytes(&self.max_packed_value, 0, self.packed_index_bytes_length)?;
 
         out.write_vlong(self.point_count)?;
         out.write_vint(self.docs_seen.cardinality() as i32)?;
@@ -1154,8 +1228,25 @@ impl<D: Directory> BKDWriter<D> {
         if prefix_sum == self.packed_bytes_length {
             // all values in this block are equal
             out.write_byte(0xff)?;
+        } else if common_prefix_lengths[sorted_dim] == self.bytes_per_dim {
+            // values only differ in dimensions that are not indexed and so not sorted:
+            // write them without run-length compression
+            if self.num_index_dims != 1 && self.version >= VERSION_LEAF_STORES_BOUNDS {
+                self.write_actual_bounds(out, common_prefix_lengths, count, values)?;
+            }
+            out.write_byte(0xff)?;
+            self.write_leaf_block_packed_values_range(
+                out,
+                common_prefix_lengths,
+                0,
+                count,
+                values,
+            )?;
         } else {
             debug_assert!(common_prefix_lengths[sorted_dim] < self.bytes_per_dim);
+            if self.num_index_dims != 1 && self.version >= VERSION_LEAF_STORES_BOUNDS {
+                self.write_actual_bounds(out, common_prefix_lengths, count, values)?;
+            }
             out.write_byte(sorted_dim as u8)?;
 
             let compressed_byte_offset =
@@ -1187,6 +1278,39 @@ impl<D: Directory> BKDWriter<D> {
         Ok(())
     }
 
+    // Writes the suffixes of the actual min and max values of the leaf for every index
+    // dimension, so that readers can check them before decoding the values
+    fn write_actual_bounds(
+        &self,
+        out: &mut impl DataOutput,
+        common_prefix_lengths: &[usize],
+        count: usize,
+        values: &[&[u8]],
+    ) -> Result<()> {
+        let prefix_lengths = common_prefix_lengths.iter().take(self.num_index_dims);
+        for (dim, prefix_length) in prefix_lengths.enumerate() {
+            let offset = dim * self.bytes_per_dim + prefix_length;
+            let end = (dim + 1) * self.bytes_per_dim;
+            if offset == end {
+                continue;
+            }
+            let mut min = &values[0][offset..end];
+            let mut max = min;
+            for value in &values[1..count] {
+                let suffix = &value[offset..end];
+                if suffix < min {
+                    min = suffix;
+                } else if suffix > max {
+                    max = suffix;
+                }
+            }
+            out.write_bytes(min, 0, min.len())?;
+            out.write_bytes(max, 0, max.len())?;
+        }
+
+        Ok(())
+    }
+
     fn write_leaf_block_packed_values_range(
         &self,
         out: &mut impl DataOutput,
@@ -1198,8 +1322,11 @@ impl<D: Directory> BKDWriter<D> {
         for i in start..end {
             debug_assert_eq!(values[i].len(), self.packed_bytes_length);
 
-            for dim in 0..self.num_dims {
-                let prefix = common_prefix_lengths[dim];
+            for (dim, &prefix) in common_prefix_lengths
+                .iter()
+                .enumerate()
+                .take(self.num_data_dims)
+            {
                 out.write_bytes(
                     values[i],
                     dim * self.bytes_per_dim + prefix,
@@ -1231,13 +1358,13 @@ impl<D: Directory> BKDWriter<D> {
         common_prefix_lengths: &[usize],
         packed_value: &mut Vec<u8>,
     ) -> Result<()> {
-        for dim in 0..self.num_dims {
-            out.write_vint(common_prefix_lengths[dim] as i32)?;
-            out.write_bytes(
-                packed_value,
-                dim * self.bytes_per_dim,
-                common_prefix_lengths[dim],
-            )?;
+        for (dim, &prefix_length) in common_prefix_lengths
+            .iter()
+            .enumerate()
+            .take(self.num_data_dims)
+        {
+            out.write_vint(prefix_length as i32)?;
+            out.write_bytes(packed_value, dim * self.bytes_per_dim, prefix_length)?;
         }
 
         Ok(())
@@ -1326,7 +1453,7 @@ impl<D: Directory> BKDWriter<D> {
         min_packed_value: &[u8],
         max_packed_value: &[u8],
     ) -> bool {
-        for dim in 0..self.num_dims {
+        for dim in 0..self.num_index_dims {
             let dim_offset = self.bytes_per_dim * dim;
 
             if values[dim_offset..dim_offset + self.bytes_per_dim]
@@ -1362,7 +1489,7 @@ impl<D: Directory> BKDWriter<D> {
             debug_assert!(slice.count == slices[0].count);
         }
 
-        if self.num_dims == 1
+        if self.num_index_dims == 1
             && slices[0].writer.point_type() == PointType::Offline
             && slices[0].count <= self.max_points_sort_in_heap as i64
         {
@@ -1379,18 +1506,17 @@ impl<D: Directory> BKDWriter<D> {
             let mut sorted_dim = 0;
             let mut sorted_dim_cardinality = i32::max_value();
 
-            for dim in 0..self.num_dims {
-                if slices[dim].writer.point_type() != PointType::Heap {
+            for (dim, source) in slices.iter_mut().enumerate().take(self.num_index_dims) {
+                if source.writer.point_type() != PointType::Heap {
                     // Adversarial cases can cause this, e.g. very lopsided data, all equal points,
                     // such that we started offline, but then kept splitting
                     // only in one dimension, and so never had to rewrite into heap writer
-                    let p = self.switch_to_heap(&mut slices[dim])?;
-                    slices[dim] = p;
+                    let p = self.switch_to_heap(source)?;
+                    *source = p;
                 }
 
                 // Find common prefix by comparing first and last values, already sorted in this
                 // dimension:
-                let source = &mut slices[dim];
                 let heap_source = source.writer.try_as_heap_writer();
                 heap_source.read_packed_value(source.start as usize, &mut self.scratch1);
                 heap_source.read_packed_value(
@@ -1412,9 +1538,9 @@ impl<D: Directory> BKDWriter<D> {
                     let mut cardinality = 1;
                     let mut previous = self.scratch1[offset + prefix];
 
-                    for _i in 1..source.count {
+                    for i in 1..source.count {
                         heap_source
-                            .read_packed_value(source.start as usize + 1, &mut self.scratch2);
+                            .read_packed_value((source.start + i) as usize, &mut self.scratch2);
                         let b = self.scratch2[offset + prefix];
                         debug_assert!(previous <= b);
                         if b != previous {
@@ -1431,6 +1557,27 @@ impl<D: Directory> BKDWriter<D> {
                 }
             }
 
+            // The data dimensions that are not indexed have no sorted slice, so their common
+            // prefixes are found by comparing all values of the leaf:
+            if self.num_data_dims > self.num_index_dims {
+                let source = &mut slices[0];
+                let heap_source = source.writer.try_as_heap_writer();
+                heap_source.read_packed_value(source.start as usize, &mut self.scratch1);
+                for dim in self.num_index_dims..self.num_data_dims {
+                    self.common_prefix_lengths[dim] = self.bytes_per_dim;
+                }
+                for i in 1..source.count {
+                    heap_source.read_packed_value((source.start + i) as usize, &mut self.scratch2);
+                    for dim in self.num_index_dims..self.num_data_dims {
+                        let offset = dim * self.bytes_per_dim;
+                        let prefix = self.common_prefix_lengths[dim];
+                        self.common_prefix_lengths[dim] = (0..prefix)
+                            .find(|&j| self.scratch1[offset + j] != self.scratch2[offset + j])
+                            .unwrap_or(prefix);
+                    }
+                }
+            }
+
             let source = &mut slices[sorted_dim];
             // We ensured that maxPointsSortInHeap was >= maxPointsInLeafNode, so we better be in
             // heap at this point:
@@ -1489,14 +1636,8 @@ impl<D: Directory> BKDWriter<D> {
             )?;
         } else {
             // Inner node: partition/recurse
-            let split_dim = if self.num_dims > 1 {
-                let min_packed_value = (&self.min_packed_value) as *const Vec<u8>;
-                let max_packed_value = (&self.max_packed_value) as *const Vec<u8>;
-                self.split(
-                    unsafe { &(*min_packed_value) },
-                    unsafe { &(*max_packed_value) },
-                    parent_splits,
-                )
+            let split_dim = if self.num_index_dims > 1 {
+                self.split(min_packed_value, max_packed_value, parent_splits)
             } else {
                 0
             };
@@ -1513,29 +1654,31 @@ impl<D: Directory> BKDWriter<D> {
                 self.mark_right_tree(right_count, split_dim as i32, source, ord_bitset)?;
             let address = node_id as usize * (1 + self.bytes_per_dim);
             split_packed_values[address] = split_dim as u8;
+            split_packed_values[address + 1..address + 1 + self.bytes_per_dim]
+                .copy_from_slice(&split_value[0..self.bytes_per_dim]);
 
             // Partition all PathSlice that are not the split dim into sorted left and right sets,
             // so we can recurse:
             let mut left_slices = vec![];
             let mut right_slices = vec![];
 
-            let mut min_split_packed_value: Vec<u8> = vec![0u8; self.packed_bytes_length];
-            min_split_packed_value[0..self.packed_bytes_length]
-                .copy_from_slice(&min_packed_value[0..self.packed_bytes_length]);
+            let mut min_split_packed_value: Vec<u8> = vec![0u8; self.packed_index_bytes_length];
+            min_split_packed_value[0..self.packed_index_bytes_length]
+                .copy_from_slice(&min_packed_value[0..self.packed_index_bytes_length]);
 
-            let mut max_split_packed_value: Vec<u8> = vec![0u8; self.packed_bytes_length];
-            max_split_packed_value[0..self.packed_bytes_length]
-                .copy_from_slice(&max_packed_value[0..self.packed_bytes_length]);
+            let mut max_split_packed_value: Vec<u8> = vec![0u8; self.packed_index_bytes_length];
+            max_split_packed_value[0..self.packed_index_bytes_length]
+                .copy_from_slice(&max_packed_value[0..self.packed_index_bytes_length]);
 
             // When we are on this dim, below, we clear the ordBitSet:
-            let dim_to_clear = if self.num_dims - 1 == split_dim {
-                self.num_dims as i32 - 2
+            let dim_to_clear = if self.num_index_dims - 1 == split_dim {
+                self.num_index_dims as i32 - 2
             } else {
-                self.num_dims as i32 - 1
+                self.num_index_dims as i32 - 1
             };
 
-            for dim in 0..self.num_dims as usize {
-                if dim == split_dim as usize {
+            for (dim, slice) in slices.iter_mut().enumerate().take(self.num_index_dims) {
+                if dim == split_dim {
                     // No need to partition on this dim since it's a simple slice of the incoming
                     // already sorted slice, and we will re-use its shared
                     // reader when visiting it as we recurse:
@@ -1562,11 +1705,9 @@ impl<D: Directory> BKDWriter<D> {
                 // Not inside the try because we don't want to close this one now, so that after
                 // recursion is done, we will have done a singel full sweep of the
                 // file:
-                let dim_start = slices[dim].start as usize;
-                let dim_count = slices[dim].count as usize;
-                let reader = slices[dim]
-                    .writer
-                    .shared_point_reader(dim_start, dim_count)?;
+                let dim_start = slice.start as usize;
+                let dim_count = slice.count as usize;
+                let reader = slice.writer.shared_point_reader(dim_start, dim_count)?;
 
                 let mut left_point_writer = self.point_writer(left_count, &format!("left{}", dim));
                 let mut right_point_writer =
@@ -1578,6 +1719,8 @@ impl<D: Directory> BKDWriter<D> {
                     &mut right_point_writer,
                     dim as i32 == dim_to_clear,
                 )?;
+                left_point_writer.close()?;
+                right_point_writer.close()?;
 
                 if right_count != next_right_count {
                     return Err(IllegalState(format!(
@@ -1605,11 +1748,11 @@ impl<D: Directory> BKDWriter<D> {
                 leaf_block_fps,
             )?;
 
-            for dim in 0..self.num_dims as usize {
+            for (dim, slice) in left_slices.iter_mut().enumerate().take(self.num_index_dims) {
                 // Don't destroy the dim we split on because we just re-used what our caller above
                 // gave us for that dim:
-                if dim != split_dim as usize {
-                    left_slices[dim].writer.destory()?;
+                if dim != split_dim {
+                    slice.writer.destory()?;
                 }
             }
 
@@ -1628,11 +1771,15 @@ impl<D: Directory> BKDWriter<D> {
                 leaf_block_fps,
             )?;
 
-            for dim in 0..self.num_dims as usize {
+            for (dim, slice) in right_slices
+                .iter_mut()
+                .enumerate()
+                .take(self.num_index_dims)
+            {
                 // Don't destroy the dim we split on because we just re-used what our caller above
                 // gave us for that dim:
-                if dim != split_dim as usize {
-                    right_slices[dim].writer.destory()?;
+                if dim != split_dim {
+                    slice.writer.destory()?;
                 }
             }
 
@@ -1664,7 +1811,7 @@ impl<D: Directory> BKDWriter<D> {
         let offset = split_dim as usize * self.bytes_per_dim;
         scratch[0..self.bytes_per_dim]
             .copy_from_slice(&reader.packed_value()[offset..offset + self.bytes_per_dim]);
-        if self.num_dims > 1 {
+        if self.num_index_dims > 1 {
             debug_assert!(ord_bitset.is_some());
             let ord_bitset = ord_bitset.as_mut().unwrap();
             debug_assert!(!ord_bitset.get(reader.ord()));
@@ -1713,7 +1860,7 @@ impl<D: Directory> BKDWriter<D> {
             max_num_splits = max_num_splits.max(*num_splits);
         }
 
-        for dim in 0..self.num_dims {
+        for dim in 0..self.num_index_dims {
             let offset = (self.bytes_per_dim * dim) as usize;
             if parent_splits[dim as usize] < max_num_splits / 2
                 && min_packed_value[offset..offset + self.bytes_per_dim]
@@ -1725,7 +1872,7 @@ impl<D: Directory> BKDWriter<D> {
 
         // Find which dim has the largest span so we can split on it:
         let mut split_dim = None;
-        for dim in 0..self.num_dims {
+        for dim in 0..self.num_index_dims {
             bytes_subtract(
                 self.bytes_per_dim,
                 dim,
@@ -1788,7 +1935,7 @@ impl<D: Directory> BKDWriter<D> {
     }
 
     fn sort(&mut self, dim: i32) -> Result<PointWriterEnum<D>> {
-        debug_assert!(dim >= 0 && dim < self.num_dims as i32);
+        debug_assert!(dim >= 0 && dim < self.num_index_dims as i32);
 
         if self.heap_point_writer.is_some() {
             debug_assert!(self.temp_input.is_none());
@@ -1808,7 +1955,35 @@ impl<D: Directory> BKDWriter<D> {
             Ok(PointWriterEnum::Heap(sorted))
         } else {
             // Offline sort:
-            unimplemented!()
+            debug_assert!(self.temp_input.is_some());
+
+            // Tie-break by docID, like the heap sort, the spilled points are
+            // the packed value followed by the docID:
+            let offset = dim as usize * self.bytes_per_dim;
+            let end = offset + self.bytes_per_dim;
+            let doc_id_offset = self.packed_bytes_length;
+            let doc_id_end = doc_id_offset + INT_BYTES as usize;
+            let sorter = OfflineSorter::new(
+                self.temp_dir.directory.as_ref(),
+                &format!("{}_bkd{}", self.temp_file_name_prefix, dim),
+                |a: &[u8], b: &[u8]| {
+                    a[offset..end].cmp(&b[offset..end]).then_with(|| {
+                        a[doc_id_offset..doc_id_end].cmp(&b[doc_id_offset..doc_id_end])
+                    })
+                },
+            )
+            .with_value_length(self.bytes_per_doc)
+            .with_buffer_bytes((self.max_mb_sort_in_heap * 1024.0 * 1024.0) as usize);
+            let name = sorter.sort(self.temp_input.as_ref().unwrap())?;
+
+            Ok(PointWriterEnum::Offline(OfflinePointWriter::new(
+                Arc::clone(&self.temp_dir.directory),
+                name,
+                self.packed_bytes_length,
+                self.point_count,
+                self.long_ords,
+                self.single_value_per_doc,
+            )))
         }
     }
 }
@@ -1982,3 +2157,133 @@ impl<D: Directory> Sorter for BKDWriterMSBIntroSorter<D> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codec::points::Relation;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::store::IOContext;
+
+    struct BoxVisitor {
+        min: [i32; 2],
+        max: [i32; 2],
+        docs: Vec<DocId>,
+    }
+
+    impl BoxVisitor {
+        fn decode(packed_value: &[u8], dim: usize) -> i32 {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&packed_value[dim * 4..dim * 4 + 4]);
+            i32::from_be_bytes(bytes)
+        }
+    }
+
+    impl IntersectVisitor for BoxVisitor {
+        fn visit(&mut self, doc_id: DocId) -> Result<()> {
+            self.docs.push(doc_id);
+            Ok(())
+        }
+
+        fn visit_by_packed_value(&mut self, doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+            if (0..2).all(|d| {
+                let v = Self::decode(packed_value, d);
+                v >= self.min[d] && v <= self.max[d]
+            }) {
+                self.docs.push(doc_id);
+            }
+            Ok(())
+        }
+
+        fn compare(&self, min_packed_value: &[u8], max_packed_value: &[u8]) -> Relation {
+            let mut inside = true;
+            for d in 0..2 {
+                let (cell_min, cell_max) = (
+                    Self::decode(min_packed_value, d),
+                    Self::decode(max_packed_value, d),
+                );
+                if cell_max < self.min[d] || cell_min > self.max[d] {
+                    return Relation::CellOutsideQuery;
+                }
+                inside &= cell_min >= self.min[d] && cell_max <= self.max[d];
+            }
+            if inside {
+                Relation::CellInsideQuery
+            } else {
+                Relation::CellCrossesQuery
+            }
+        }
+    }
+
+    fn check_offline_sort(single_value_per_doc: bool) -> Result<()> {
+        let dir = Arc::new(ByteBuffersDirectory::new());
+        let num_points = 5000;
+        // multi valued docs have two points each
+        let max_doc = if single_value_per_doc {
+            num_points
+        } else {
+            num_points / 2
+        };
+        let doc_id = |i: i32| if single_value_per_doc { i } else { i / 2 };
+        // a heap of 0.01 MB holds a few hundred points, the rest is spilled
+        // and sorted offline
+        let mut writer = BKDWriter::new(
+            max_doc,
+            Arc::clone(&dir),
+            "_0",
+            2,
+            2,
+            4,
+            64,
+            0.01,
+            num_points as i64,
+            single_value_per_doc,
+            VERSION_CURRENT,
+        )?;
+        // values are kept non-negative so their big endian bytes sort like them
+        let point = |i: i32| [(i * 7919) % 1000, (i * 104_729) % 1000];
+        for i in 0..num_points {
+            let mut packed_value = Vec::with_capacity(8);
+            for v in &point(i) {
+                packed_value.extend_from_slice(&v.to_be_bytes());
+            }
+            writer.add(&packed_value, doc_id(i))?;
+        }
+        assert!(writer.temp_input.is_some());
+
+        let fp = {
+            let mut out = dir.create_output("_0.dim", &IOContext::Default)?;
+            writer.finish(&mut out)?
+        };
+        drop(writer);
+        // only the output is left, the temp files are deleted
+        assert_eq!(dir.list_all()?, vec!["_0.dim".to_string()]);
+
+        let mut input = dir.open_input("_0.dim", &IOContext::READ)?;
+        input.seek(fp)?;
+        let reader = BKDReader::new(Arc::from(input))?;
+        let mut visitor = BoxVisitor {
+            min: [100, 250],
+            max: [400, 900],
+            docs: vec![],
+        };
+        reader.intersect(&mut visitor)?;
+        visitor.docs.sort_unstable();
+        let expected: Vec<DocId> = (0..num_points)
+            .filter(|&i| {
+                let p = point(i);
+                (100..=400).contains(&p[0]) && (250..=900).contains(&p[1])
+            })
+            .map(doc_id)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(visitor.docs, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_offline_sort() -> Result<()> {
+        check_offline_sort(true)?;
+        check_offline_sort(false)
+    }
+}
@@ -34,12 +34,12 @@ use self::offline_point::*;
 use crate::error::Error::IllegalState;
 use crate::Result;
 
-use crate::core::util::DocId;
+use crate::core::util::{DocId, LongBitSet};
 
 use crate::core::codec::points::MutablePointsReader;
 use crate::core::store::directory::Directory;
 use crate::core::store::io::{DataOutput, IndexOutput, IndexOutputRef, InvalidIndexOutput};
-use crate::core::util::bit_util::{pop_array, BitsRequired, UnsignedShift};
+use crate::core::util::bit_util::{BitsRequired, UnsignedShift};
 use crate::core::util::math;
 use crate::core::util::selector::{DefaultIntroSelector, RadixSelector};
 use crate::core::util::sorter::{check_range, MSBRadixSorter, MSBSorter, Sorter};
@@ -52,8 +52,12 @@ pub const BKD_VERSION_COMPRESSED_DOC_IDS: i32 = 1;
 pub const BKD_VERSION_COMPRESSED_VALUES: i32 = 2;
 pub const BKD_VERSION_IMPLICIT_SPLIT_DIM_1D: i32 = 3;
 pub const BKD_VERSION_PACKED_INDEX: i32 = 4;
+pub const BKD_VERSION_LEAF_STORES_BOUNDS: i32 = 5;
+pub const BKD_VERSION_SELECTIVE_INDEXING: i32 = 6;
+/// Only read, leaves with few distinct values are stored as runs
+pub const BKD_VERSION_LOW_CARDINALITY_LEAVES: i32 = 7;
 pub const BKD_VERSION_START: i32 = 0;
-pub const BKD_VERSION_CURRENT: i32 = BKD_VERSION_PACKED_INDEX;
+pub const BKD_VERSION_CURRENT: i32 = BKD_VERSION_SELECTIVE_INDEXING;
 
 #[derive(PartialOrd, PartialEq)]
 pub enum PointType {
@@ -88,28 +92,44 @@ pub trait PointReader {
         left: &mut impl PointWriter,
         right: &mut impl PointWriter,
         do_clear_bits: bool,
-    ) -> Result<i64> {
-        // Partition this source according to how the splitDim split the values:
-        let mut right_count = 0i64;
-        for _ in 0..count {
-            let result = self.next()?;
-            debug_assert_eq!(result, true);
-            let packed_value = self.packed_value();
-            let ord = self.ord();
-            let doc_id = self.doc_id();
-            if right_tree.get(ord) {
-                right.append(packed_value, ord, doc_id)?;
-                right_count += 1;
-                if do_clear_bits {
-                    right_tree.clear(ord);
-                }
-            } else {
-                left.append(&packed_value, ord, doc_id)?;
+    ) -> Result<i64>
+    where
+        Self: Sized,
+    {
+        split_points(self, count, right_tree, left, right, do_clear_bits)
+    }
+}
+
+/// The default `PointReader::split`, appending the next `count` points of
+/// `reader` to `right` if their ord is set in `right_tree`, else to `left`.
+fn split_points(
+    reader: &mut impl PointReader,
+    count: i64,
+    right_tree: &mut LongBitSet,
+    left: &mut impl PointWriter,
+    right: &mut impl PointWriter,
+    do_clear_bits: bool,
+) -> Result<i64> {
+    // Partition this source according to how the splitDim split the values:
+    let mut right_count = 0i64;
+    for _ in 0..count {
+        let result = reader.next()?;
+        debug_assert!(result);
+        let packed_value = reader.packed_value();
+        let ord = reader.ord();
+        let doc_id = reader.doc_id();
+        if right_tree.get(ord) {
+            right.append(packed_value, ord, doc_id)?;
+            right_count += 1;
+            if do_clear_bits {
+                right_tree.clear(ord);
             }
+        } else {
+            left.append(packed_value, ord, doc_id)?;
         }
-
-        Ok(right_count)
     }
+
+    Ok(right_count)
 }
 
 pub enum PointReaderEnum {
@@ -306,70 +326,6 @@ impl<D: Directory> io::Write for PointWriterOutput<D> {
     }
 }
 
-/// BitSet of fixed length (num_bits), backed by accessible ({@link #getBits})
-/// Vec<i64>, accessed with a long index. Use it only if you intend to store more
-/// than 2.1B bits, otherwise you should use `FixedBitSet`.
-///
-/// @lucene.internal
-pub struct LongBitSet {
-    // Array of longs holding the bits
-    bits: Vec<i64>,
-    // The number of bits in use
-    num_bits: i64,
-    num_words: usize,
-}
-
-impl LongBitSet {
-    /// returns the number of 64 bit words it would take to hold numBits
-    pub fn bits2words(num_bits: i64) -> usize {
-        // I.e.: get the word-offset of the last bit and add one (make sure to use >> so 0 returns
-        // 0!)
-        (((num_bits - 1) >> 6) + 1) as usize
-    }
-
-    pub fn new(num_bits: i64) -> LongBitSet {
-        let num_words = LongBitSet::bits2words(num_bits);
-        LongBitSet {
-            bits: vec![0; num_words],
-            num_bits,
-            num_words,
-        }
-    }
-
-    /// Returns number of set bits.  NOTE: this visits every long in the
-    /// backing bits array, and the result is not internally cached.
-    pub fn cardinality(&self) -> usize {
-        pop_array(&self.bits, 0, self.num_words)
-    }
-
-    pub fn get(&self, index: i64) -> bool {
-        debug_assert!(index >= 0 && index < self.num_bits);
-        let word_num = (index >> 6) as usize;
-        // signed shift will keep a negative index and force an
-        // array-index-out-of-bounds-exception, removing the need for an explicit check.
-        let mask = 1i64 << (index & 0x3fi64);
-        (self.bits[word_num] & mask) != 0
-    }
-
-    pub fn set(&mut self, index: i64) {
-        debug_assert!(index >= 0 && index < self.num_bits);
-        let word_num = (index >> 6) as usize;
-        let mask = 1i64 << (index & 0x3fi64);
-        unsafe {
-            *self.bits.as_mut_ptr().add(word_num) |= mask;
-        }
-    }
-
-    pub fn clear(&mut self, index: i64) {
-        debug_assert!(index >= 0 && index < self.num_bits);
-        let word_num = (index >> 6) as usize;
-        let mask = 1i64 << (index & 0x3fi64);
-        unsafe {
-            *self.bits.as_mut_ptr().add(word_num) &= !mask;
-        }
-    }
-}
-
 pub struct UtilMSBIntroSorter<P: MutablePointsReader> {
     k: i32,
     packed_bytes_length: i32,
@@ -15,8 +15,8 @@ use crate::core::codec::{footer_length, write_footer, INT_BYTES, LONG_BYTES};
 use crate::core::store::directory::Directory;
 use crate::core::store::io::{DataOutput, IndexInput, IndexOutput, IndexOutputRef};
 use crate::core::store::IOContext;
-use crate::core::util::bkd::{LongBitSet, PointReader, PointReaderEnum, PointType, PointWriter};
-use crate::core::util::DocId;
+use crate::core::util::bkd::{split_points, PointReader, PointReaderEnum, PointType, PointWriter};
+use crate::core::util::{DocId, LongBitSet};
 
 use crate::{Error, Result};
 use std::io::Read;
@@ -166,7 +166,7 @@ impl PointReader for OfflinePointReader {
         }
 
         self.doc_id = self.input.read_int()?;
-        if self.single_value_per_doc {
+        if !self.single_value_per_doc {
             if self.long_ords {
                 self.ord = self.input.read_long()?;
             } else {
@@ -229,7 +229,7 @@ impl PointReader for OfflinePointReader {
         do_clear_bits: bool,
     ) -> Result<i64> {
         if left.point_type() != PointType::Offline || right.point_type() != PointType::Offline {
-            return PointReader::split(self, count, right_tree, left, right, do_clear_bits);
+            return split_points(self, count, right_tree, left, right, do_clear_bits);
         }
 
         // We specialize the offline -> offline split since the default impl
@@ -237,7 +237,7 @@ impl PointReader for OfflinePointReader {
         // need to)
         let packed_bytes_length = self.packed_value.capacity();
         let mut bytes_per_doc = packed_bytes_length + INT_BYTES as usize;
-        if self.single_value_per_doc {
+        if !self.single_value_per_doc {
             if self.long_ords {
                 bytes_per_doc += LONG_BYTES as usize;
             } else {
@@ -281,8 +281,8 @@ impl PointReader for OfflinePointReader {
             }
         }
 
-        right.set_count(right_count as i64);
-        left.set_count((count_start - right_count) as i64);
+        right.set_count(right_count);
+        left.set_count(count - right_count);
 
         Ok(right_count)
     }
@@ -300,12 +300,10 @@ pub struct OfflinePointWriter<D: Directory> {
     next_shared_read: i64,
     expected_count: i64,
     closed: bool,
-    temp_file_name_prefix: String,
-    desc: String,
 }
 
 impl<D: Directory> OfflinePointWriter<D> {
-    #[allow(dead_code)]
+    /// Opens the points of an existing temp file, e.g. sorted by `OfflineSorter`.
     pub fn new(
         temp_dir: Arc<D>,
         name: String,
@@ -325,9 +323,7 @@ impl<D: Directory> OfflinePointWriter<D> {
             shared_reader: None,
             next_shared_read: 0,
             expected_count: 0,
-            closed: false,
-            temp_file_name_prefix: "".to_string(),
-            desc: "".to_string(),
+            closed: true,
         }
     }
 
@@ -367,9 +363,7 @@ impl<D: Directory> OfflinePointWriter<D> {
             shared_reader: None,
             next_shared_read: 0,
             expected_count,
-            closed: true,
-            temp_file_name_prefix: temp_file_name_prefix.to_string(),
-            desc: desc.to_string(),
+            closed: false,
         }
     }
 }
@@ -429,16 +423,18 @@ impl<D: Directory> PointWriter for OfflinePointWriter<D> {
         length: usize,
     ) -> Result<&mut Self::PointReader> {
         if self.shared_reader.is_none() {
-            debug_assert!(start == 0 && length as i64 <= self.count);
+            debug_assert!((start + length) as i64 <= self.count);
+            // a clone reused for the right half of a split starts its sweep there
             let shared_reader = PointReaderEnum::Offline(OfflinePointReader::new(
                 self.temp_dir.as_ref(),
                 &self.name,
                 self.packed_bytes_length as i32,
-                0,
-                self.count as usize,
+                start,
+                self.count as usize - start,
                 self.long_ords,
                 self.single_value_per_doc,
             )?);
+            self.next_shared_read = start as i64;
 
             //_to_close.push(shared_reader);
             self.shared_reader = Some(shared_reader);
@@ -469,8 +465,8 @@ impl<D: Directory> PointWriter for OfflinePointWriter<D> {
     fn close(&mut self) -> Result<()> {
         if !self.closed {
             debug_assert!(self.shared_reader.is_none());
-            let output = self.output.as_mut().unwrap();
-            write_footer(output)?;
+            let mut output = self.output.take().unwrap();
+            write_footer(&mut output)?;
             self.closed = true;
         }
 
@@ -478,31 +474,15 @@ impl<D: Directory> PointWriter for OfflinePointWriter<D> {
     }
 
     fn clone(&self) -> Self {
-        let temp_dir = Arc::clone(&self.temp_dir);
-        let output = temp_dir
-            .create_temp_output(
-                &self.temp_file_name_prefix,
-                &format!("bkd_{}", self.desc),
-                &IOContext::Default,
-            )
-            .unwrap();
-
-        let name = output.name().to_string();
-
-        OfflinePointWriter {
-            temp_dir,
-            output: Some(output),
-            name,
-            packed_bytes_length: self.packed_bytes_length,
-            single_value_per_doc: self.single_value_per_doc,
-            count: self.count,
-            long_ords: self.long_ords,
-            shared_reader: None,
-            next_shared_read: self.next_shared_read,
-            expected_count: self.expected_count,
-            closed: self.closed,
-            temp_file_name_prefix: self.temp_file_name_prefix.clone(),
-            desc: self.desc.clone(),
-        }
+        // the clones read the same file, only the original destroys it
+        debug_assert!(self.closed);
+        OfflinePointWriter::new(
+            Arc::clone(&self.temp_dir),
+            self.name.clone(),
+            self.packed_bytes_length,
+            self.count,
+            self.long_ords,
+            self.single_value_per_doc,
+        )
     }
 }
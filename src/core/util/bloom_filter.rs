@@ -0,0 +1,180 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::util::{murmur3_hash64, Accountable};
+use crate::error::{Error::CorruptIndex, Result};
+
+use std::f64::consts::LN_2;
+use std::mem;
+
+const MIN_NUM_BITS: usize = 64;
+const MAX_NUM_HASHES: usize = 16;
+
+/// A bloom filter over byte sequences, answering whether a value was
+/// definitely not added or may have been added.
+///
+/// the number of bits is always a power of two, values are hashed once with
+/// `murmur3_hash64` and the `num_hashes` bit positions are derived from the two
+/// halves of that hash (Kirsch-Mitzenmacher).
+pub struct BloomFilter {
+    words: Vec<u64>,
+    num_hashes: usize,
+}
+
+impl Accountable for BloomFilter {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>() + self.words.len() * mem::size_of::<u64>()
+    }
+}
+
+impl BloomFilter {
+    /// Creates a filter sized for `expected_values` values with a false
+    /// positive probability close to `fpp`.
+    pub fn new(expected_values: usize, fpp: f64) -> BloomFilter {
+        debug_assert!(fpp > 0.0 && fpp < 1.0);
+        let n = expected_values.max(1) as f64;
+        let num_bits = (-n * fpp.ln() / (LN_2 * LN_2)).ceil() as usize;
+        let num_bits = num_bits.max(MIN_NUM_BITS).next_power_of_two();
+        let num_hashes = ((num_bits as f64 / n) * LN_2).round() as usize;
+        BloomFilter {
+            words: vec![0; num_bits / 64],
+            num_hashes: num_hashes.clamp(1, MAX_NUM_HASHES),
+        }
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    pub fn num_hashes(&self) -> usize {
+        self.num_hashes
+    }
+
+    /// Hash of `value` as expected by `add_hash` and `may_contain_hash`.
+    #[inline]
+    pub fn hash(value: &[u8]) -> u64 {
+        murmur3_hash64(value, 0)
+    }
+
+    pub fn add(&mut self, value: &[u8]) {
+        self.add_hash(Self::hash(value))
+    }
+
+    pub fn add_hash(&mut self, hash: u64) {
+        let mask = self.num_bits() - 1;
+        for bit in Self::bits(hash, self.num_hashes) {
+            let bit = bit as usize & mask;
+            self.words[bit >> 6] |= 1 << (bit & 63);
+        }
+    }
+
+    /// Returns `false` if `value` was definitely never added.
+    pub fn may_contain(&self, value: &[u8]) -> bool {
+        self.may_contain_hash(Self::hash(value))
+    }
+
+    pub fn may_contain_hash(&self, hash: u64) -> bool {
+        let mask = self.num_bits() - 1;
+        Self::bits(hash, self.num_hashes).all(|bit| {
+            let bit = bit as usize & mask;
+            self.words[bit >> 6] & (1 << (bit & 63)) != 0
+        })
+    }
+
+    #[inline]
+    fn bits(hash: u64, num_hashes: usize) -> impl Iterator<Item = u64> {
+        let h2 = hash.rotate_left(32) | 1;
+        (0..num_hashes as u64).map(move |i| hash.wrapping_add(i.wrapping_mul(h2)))
+    }
+
+    /// The fraction of bits set, a rough indication of the false positive rate.
+    pub fn saturation(&self) -> f64 {
+        let set: u32 = self.words.iter().map(|w| w.count_ones()).sum();
+        f64::from(set) / self.num_bits() as f64
+    }
+
+    pub fn write<T: DataOutput + ?Sized>(&self, out: &mut T) -> Result<()> {
+        out.write_vint(self.num_hashes as i32)?;
+        out.write_vint(self.words.len() as i32)?;
+        for word in &self.words {
+            out.write_long(*word as i64)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<T: DataInput + ?Sized>(input: &mut T) -> Result<BloomFilter> {
+        let num_hashes = input.read_vint()?;
+        if num_hashes < 1 || num_hashes as usize > MAX_NUM_HASHES {
+            return Err(CorruptIndex(format!(
+                "invalid bloom filter num_hashes: {}",
+                num_hashes
+            )));
+        }
+        let num_words = input.read_vint()?;
+        if num_words < 1 || !(num_words as u32).is_power_of_two() {
+            return Err(CorruptIndex(format!(
+                "invalid bloom filter num_words: {}",
+                num_words
+            )));
+        }
+        let mut words = Vec::with_capacity(num_words as usize);
+        for _ in 0..num_words {
+            words.push(input.read_long()? as u64);
+        }
+        Ok(BloomFilter {
+            words,
+            num_hashes: num_hashes as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        for i in 0..10_000 {
+            filter.add(format!("id{}", i).as_bytes());
+        }
+        for i in 0..10_000 {
+            assert!(filter.may_contain(format!("id{}", i).as_bytes()));
+        }
+        let false_positives = (10_000..20_000)
+            .filter(|i| filter.may_contain(format!("id{}", i).as_bytes()))
+            .count();
+        assert!(
+            false_positives < 200,
+            "false positives: {}",
+            false_positives
+        );
+    }
+
+    #[test]
+    fn test_serialization() -> Result<()> {
+        let mut filter = BloomFilter::new(100, 0.05);
+        for i in 0..100 {
+            filter.add(format!("id{}", i).as_bytes());
+        }
+        let mut bytes = Vec::new();
+        filter.write(&mut bytes)?;
+        let read = BloomFilter::read(&mut bytes.as_slice())?;
+        assert_eq!(read.num_bits(), filter.num_bits());
+        assert_eq!(read.num_hashes(), filter.num_hashes());
+        assert_eq!(read.words, filter.words);
+        assert!(BloomFilter::read(&mut [0u8, 1].as_ref()).is_err());
+        Ok(())
+    }
+}
@@ -133,6 +133,11 @@ impl ByteBlockPool {
         }
     }
 
+    /// The number of bytes of the buffers allocated so far.
+    pub fn bytes_used(&self) -> usize {
+        (self.buffer_upto + 1) as usize * Self::BYTE_BLOCK_SIZE
+    }
+
     pub fn current_buffer(&mut self) -> &mut [u8] {
         debug_assert!(self.buffer_upto >= 0);
         &mut self.buffers[self.buffer_upto as usize]
@@ -347,6 +352,12 @@ impl DirectTrackingAllocator {
     }
 }
 
+impl Default for DirectTrackingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ByteBlockAllocator for DirectTrackingAllocator {
     fn block_size(&self) -> usize {
         self.block_size
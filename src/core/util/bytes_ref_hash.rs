@@ -11,11 +11,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::core::util::byte_block_pool::ByteBlockPool;
+use crate::core::util::byte_block_pool::{ByteBlockPool, DirectTrackingAllocator};
 use crate::core::util::math;
 use crate::core::util::sorter::{MSBRadixSorter, MSBSorter, Sorter};
 use crate::core::util::BytesRef;
 use crate::core::util::{fill_slice, over_size};
+use crate::error::Error::IllegalState;
+use crate::Result;
 
 use std::cmp::Ordering;
 use std::hash::Hasher;
@@ -31,9 +33,12 @@ pub const DEFAULT_CAPACITY: usize = 16;
 ///
 /// Note: The maximum capacity {@link BytesRef} instance passed to
 /// {@link #add(BytesRef)} must not be longer than {@link ByteBlockPool#BYTE_BLOCK_SIZE}-2.
-/// The internal storage is limited to 2GB total byte storage.
+/// The byte pool grows by one block whenever the current one is full, the
+/// internal storage is limited to 4GB total byte storage.
 pub struct BytesRefHash {
     pub pool: *mut ByteBlockPool,
+    // set when the hash allocated its own pool, `pool` points into it
+    owned_pool: Option<Box<ByteBlockPool>>,
     // TODO BytesRef
     hash_size: usize,
     hash_half_size: usize,
@@ -45,6 +50,20 @@ pub struct BytesRefHash {
 }
 
 impl BytesRefHash {
+    /// Creates a hash of `capacity` initial slots storing the bytes in a pool
+    /// of its own.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut pool = Box::new(ByteBlockPool::new(Box::new(DirectTrackingAllocator::new())));
+        let mut hash = Self::new(
+            pool.as_mut(),
+            capacity,
+            Box::new(DirectByteStartArray::new(capacity)),
+        );
+        // moving the box doesn't move the pool, `hash.pool` stays valid
+        hash.owned_pool = Some(pool);
+        hash
+    }
+
     pub fn with_pool(pool: &mut ByteBlockPool) -> Self {
         Self::new(
             pool,
@@ -58,8 +77,10 @@ impl BytesRefHash {
         capacity: usize,
         bytes_start_array: Box<dyn BytesStartArray>,
     ) -> Self {
+        debug_assert!(capacity.is_power_of_two());
         BytesRefHash {
             pool,
+            owned_pool: None,
             hash_size: capacity,
             hash_half_size: capacity >> 1,
             hash_mask: capacity - 1,
@@ -96,7 +117,7 @@ impl BytesRefHash {
     /// and end at a limit of {@link #size()} - 1
     /// Note: This is a destructive operation. {@link #clear()} must be called in
     /// order to reuse this {@link BytesRefHash} instance.
-    fn compact(&mut self) -> &mut [i32] {
+    pub fn compact(&mut self) -> &mut [i32] {
         let mut upto = 0;
 
         for i in 0..self.hash_size {
@@ -110,7 +131,7 @@ impl BytesRefHash {
         }
         assert_eq!(upto, self.count);
         self.last_count = self.count as isize;
-        &mut self.ids
+        &mut self.ids[..upto]
     }
 
     pub fn sort(&mut self) {
@@ -121,6 +142,30 @@ impl BytesRefHash {
         msb_sorter.sort(0, count);
     }
 
+    /// Returns the ids sorted by their bytes, the id of the i-th smallest
+    /// bytes being at index i.
+    ///
+    /// Note: Like `sort` this is a destructive operation, `clear` must be
+    /// called in order to add more bytes.
+    pub fn sorted_ids(&mut self) -> &[i32] {
+        self.sort();
+        &self.ids[..self.count]
+    }
+
+    /// Iterates over the ids and bytes in the order of the bytes.
+    ///
+    /// Note: Like `sort` this is a destructive operation, `clear` must be
+    /// called in order to add more bytes.
+    pub fn sorted_iter(&mut self) -> SortedBytesRefIter<'_> {
+        self.sort();
+        SortedBytesRefIter { hash: self, ord: 0 }
+    }
+
+    /// Iterates over the bytes in the order of their ids.
+    pub fn iter(&self) -> impl Iterator<Item = BytesRef> + '_ {
+        (0..self.count).map(move |id| self.get(id))
+    }
+
     fn equals(&self, id: i32, b: &BytesRef) -> bool {
         let text_start = self.byte_start(id as usize);
         let bytes = self.byte_pool().set_bytes_ref(text_start);
@@ -168,7 +213,7 @@ impl BytesRefHash {
         self.ids = Vec::with_capacity(0);
     }
 
-    pub fn add(&mut self, bytes: &BytesRef) -> i32 {
+    pub fn add(&mut self, bytes: &BytesRef) -> Result<i32> {
         let length = bytes.len();
         // final position
         let hash_pos = self.find_hash(bytes);
@@ -186,6 +231,11 @@ impl BytesRefHash {
                 self.pool_mut().next_buffer();
             }
             let buffer_upto = self.byte_pool().byte_upto as isize;
+            if buffer_upto + self.byte_pool().byte_offset > u32::MAX as isize {
+                return Err(IllegalState(
+                    "BytesRefHash is full, the byte pool can hold at most 4GB".into(),
+                ));
+            }
             if self.count >= self.bytes_start_array.bytes().len() {
                 self.bytes_start_array.grow();
                 debug_assert!(self.count < self.bytes_start_array.bytes().len() + 1)
@@ -221,9 +271,9 @@ impl BytesRefHash {
                 let new_size = self.hash_size * 2;
                 self.rehash(new_size, true);
             }
-            e
+            Ok(e)
         } else {
-            -(e + 1)
+            Ok(-(e + 1))
         }
     }
 
@@ -366,6 +416,31 @@ impl BytesRefHash {
     }
 }
 
+/// Iterator returned by `BytesRefHash::sorted_iter`.
+pub struct SortedBytesRefIter<'a> {
+    hash: &'a BytesRefHash,
+    ord: usize,
+}
+
+impl<'a> Iterator for SortedBytesRefIter<'a> {
+    type Item = (i32, BytesRef);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ord < self.hash.count {
+            let id = self.hash.ids[self.ord];
+            self.ord += 1;
+            Some((id, self.hash.get(id as usize)))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.hash.count - self.ord;
+        (remaining, Some(remaining))
+    }
+}
+
 struct BytesRefStringMSBSorter<'a> {
     bytes_hash: &'a mut BytesRefHash,
     // compact: &[i32]  // bytes_hash.ids after compacted
@@ -509,3 +584,41 @@ impl BytesStartArray for DirectByteStartArray {
         self.bytes_start = Vec::with_capacity(0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_iter() {
+        let mut hash = BytesRefHash::with_capacity(DEFAULT_CAPACITY);
+        // enough distinct terms to fill several blocks of the pool
+        let terms: Vec<String> = (0..20_000)
+            .map(|i| format!("term_{}", i * 7919 % 20_000))
+            .collect();
+        for (i, term) in terms.iter().enumerate() {
+            assert_eq!(hash.add(&BytesRef::new(term.as_bytes())).unwrap(), i as i32);
+        }
+        assert_eq!(hash.add(&BytesRef::new(terms[42].as_bytes())).unwrap(), -43);
+        assert!(hash.byte_pool().buffer_upto > 0);
+
+        let in_order: Vec<Vec<u8>> = hash.iter().map(|b| b.bytes().to_vec()).collect();
+        let expected: Vec<Vec<u8>> = terms.iter().map(|t| t.as_bytes().to_vec()).collect();
+        assert_eq!(in_order, expected);
+
+        let mut sorted = expected.clone();
+        sorted.sort();
+        let mut count = 0;
+        for (i, (id, bytes)) in hash.sorted_iter().enumerate() {
+            assert_eq!(bytes.bytes(), sorted[i].as_slice());
+            assert_eq!(terms[id as usize].as_bytes(), bytes.bytes());
+            count += 1;
+        }
+        assert_eq!(count, terms.len());
+        assert_eq!(hash.sorted_ids().len(), terms.len());
+
+        hash.clear(true);
+        assert!(hash.is_empty());
+        assert_eq!(hash.add(&BytesRef::new(b"a")).unwrap(), 0);
+    }
+}
@@ -11,129 +11,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
 use std::sync::atomic::{AtomicI64, Ordering};
 
-/// Simple counter trait
-pub trait Count {
-    fn add_get(&mut self, delta: i64) -> i64;
-
-    fn get(&self) -> i64;
-}
-
-struct SerialCounter {
-    count: i64,
-}
-
-impl Count for SerialCounter {
-    fn add_get(&mut self, delta: i64) -> i64 {
-        self.count += delta;
-        self.count
-    }
-
-    fn get(&self) -> i64 {
-        self.count
-    }
-}
-
-struct AtomicCounter {
-    count: AtomicI64,
-}
-
-impl Count for AtomicCounter {
-    fn add_get(&mut self, delta: i64) -> i64 {
-        self.count.fetch_add(delta, Ordering::Release);
-        self.get()
-    }
-
-    fn get(&self) -> i64 {
-        self.count.load(Ordering::Acquire)
-    }
-}
-
-enum CounterEnum {
-    Serial(Box<SerialCounter>),
-    Atomic(Box<AtomicCounter>),
-    Borrowed(*mut dyn Count),
-    // TODO unsafe use for borrow a exist counter
-}
-
-impl Count for CounterEnum {
-    fn add_get(&mut self, delta: i64) -> i64 {
-        match *self {
-            CounterEnum::Serial(ref mut s) => s.add_get(delta),
-            CounterEnum::Atomic(ref mut s) => s.add_get(delta),
-            CounterEnum::Borrowed(b) => unsafe { (*b).add_get(delta) },
-        }
-    }
-
-    fn get(&self) -> i64 {
-        match *self {
-            CounterEnum::Serial(ref s) => s.get(),
-            CounterEnum::Atomic(ref s) => s.get(),
-            CounterEnum::Borrowed(b) => unsafe { (*b).get() },
-        }
-    }
-}
-
+/// A thread safe counter, e.g. of the bytes read from or written to a
+/// directory.
+///
+/// Wrap it in an `Arc` to share it between the components updating it.
+#[derive(Default)]
 pub struct Counter {
-    count: CounterEnum,
-}
-
-impl Default for Counter {
-    fn default() -> Self {
-        Self::new(false)
-    }
+    count: AtomicI64,
 }
 
 impl Counter {
-    pub fn new(thread_safe: bool) -> Self {
-        let count = if thread_safe {
-            CounterEnum::Atomic(Box::new(AtomicCounter {
-                count: AtomicI64::new(0),
-            }))
-        } else {
-            CounterEnum::Serial(Box::new(SerialCounter { count: 0 }))
-        };
-        Counter { count }
-    }
-
-    pub fn borrow(counter: &dyn Count) -> Self {
-        Counter {
-            count: CounterEnum::Borrowed(counter as *const dyn Count as *mut dyn Count),
-        }
-    }
-
-    fn borrow_raw(counter: *mut dyn Count) -> Self {
+    pub fn new(initial: i64) -> Self {
         Counter {
-            count: CounterEnum::Borrowed(counter),
+            count: AtomicI64::new(initial),
         }
     }
 
-    // TODO this copy while share the inner count of self,
-    // so it is not safe if self's lifetime is shorter than the copy one
-    pub unsafe fn shallow_copy(&self) -> Counter {
-        match self.count {
-            CounterEnum::Borrowed(b) => Counter::borrow_raw(b),
-            CounterEnum::Atomic(ref a) => Counter::borrow(a.as_ref() as &dyn Count),
-            CounterEnum::Serial(ref s) => Counter::borrow(s.as_ref() as &dyn Count),
-        }
+    /// Adds `delta`, which may be negative, and returns the new count.
+    pub fn add_get(&self, delta: i64) -> i64 {
+        self.count.fetch_add(delta, Ordering::AcqRel) + delta
     }
 
-    pub fn ptr(&self) -> *const dyn Count {
-        match self.count {
-            CounterEnum::Serial(ref s) => s.as_ref(),
-            CounterEnum::Atomic(ref s) => s.as_ref(),
-            CounterEnum::Borrowed(b) => b,
-        }
+    pub fn get(&self) -> i64 {
+        self.count.load(Ordering::Acquire)
     }
 }
 
-impl Count for Counter {
-    fn add_get(&mut self, delta: i64) -> i64 {
-        self.count.add_get(delta)
+impl fmt::Debug for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Counter").field(&self.get()).finish()
     }
+}
 
-    fn get(&self) -> i64 {
-        self.count.get()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_counter() {
+        let counter = Arc::new(Counter::default());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        counter.add_get(3);
+                        counter.add_get(-1);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(counter.get(), 8000);
+        assert_eq!(counter.add_get(-8000), 0);
     }
 }
@@ -17,32 +17,25 @@ use crate::Result;
 
 use std::cmp::{Ord, Ordering};
 use std::ops::{Deref, DerefMut};
-use std::ptr;
 
 pub struct DisiWrapper<T: DocIterator> {
     scorer: T,
     pub doc: DocId,
     matches: Option<bool>,
-    pub next: *mut DisiWrapper<T>,
+    /// Index in the owning `DisiPriorityQueue` of the next wrapper of the
+    /// list returned by `DisiPriorityQueue::top_list`.
+    pub next: Option<usize>,
     pub last_approx_match_doc: DocId,
     pub last_approx_non_match_doc: DocId,
 }
 
 impl<T: DocIterator> DisiWrapper<T> {
-    pub fn next_scorer(&self) -> Option<&mut DisiWrapper<T>> {
-        if self.next.is_null() {
-            None
-        } else {
-            unsafe { Some(&mut *self.next) }
-        }
-    }
-
     pub fn new(scorer: T) -> DisiWrapper<T> {
         DisiWrapper {
             scorer,
             doc: -1,
             matches: None,
-            next: ptr::null_mut(),
+            next: None,
             last_approx_match_doc: -1,
             last_approx_non_match_doc: -1,
         }
@@ -132,14 +125,15 @@ impl<T: DocIterator> PartialOrd for DisiWrapper<T> {
     }
 }
 
+/// A heap of `DisiWrapper`s ordered by doc id.
+///
+/// The queue owns its wrappers, the heap and the lists returned by `top_list`
+/// refer to them by their index, which stays valid for the queue's lifetime.
 pub struct DisiPriorityQueue<T: DocIterator> {
-    heap: Vec<*mut DisiWrapper<T>>,
-    size: usize,
+    heap: Vec<usize>,
     buffer: Vec<DisiWrapper<T>>,
 }
 
-unsafe impl<T: DocIterator> Send for DisiPriorityQueue<T> {}
-
 impl<T: DocIterator> DisiPriorityQueue<T> {
     #[inline]
     fn left_node(node: usize) -> usize {
@@ -161,107 +155,102 @@ impl<T: DocIterator> DisiPriorityQueue<T> {
     }
 
     pub fn new(children: Vec<T>) -> DisiPriorityQueue<T> {
-        let mut buffer: Vec<DisiWrapper<T>> = children.into_iter().map(DisiWrapper::new).collect();
-        let children: Vec<*mut DisiWrapper<T>> = buffer
-            .iter_mut()
-            .map(|x| x as *mut DisiWrapper<T>)
-            .collect();
+        let buffer: Vec<DisiWrapper<T>> = children.into_iter().map(DisiWrapper::new).collect();
         let mut queue = DisiPriorityQueue {
-            heap: vec![ptr::null_mut(); children.len()],
-            size: 0,
+            heap: Vec::with_capacity(buffer.len()),
             buffer,
         };
 
-        for disi in children {
-            unsafe {
-                queue.do_push(disi);
-            }
+        for i in 0..queue.buffer.len() {
+            queue.push(i);
         }
         queue
     }
 
     pub fn size(&self) -> usize {
-        self.size
+        self.heap.len()
     }
 
-    /// Get the list of scorers which are on the current doc.
-    pub fn top_list(&mut self) -> &mut DisiWrapper<T> {
-        unsafe { &mut *self.do_top_list() }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns the wrapper at `index` in this queue.
+    pub fn get(&self, index: usize) -> &DisiWrapper<T> {
+        &self.buffer[index]
+    }
+
+    /// Returns the wrapper at `index` in this queue.
+    pub fn get_mut(&mut self, index: usize) -> &mut DisiWrapper<T> {
+        &mut self.buffer[index]
+    }
+
+    #[inline]
+    fn doc_at(&self, node: usize) -> DocId {
+        self.buffer[self.heap[node]].doc()
     }
 
-    unsafe fn do_top_list(&self) -> *mut DisiWrapper<T> {
-        let heap = &self.heap;
-        let size = self.size;
-        let mut list = heap[0];
-        (*list).next = ptr::null_mut();
+    /// Get the list of scorers which are on the current doc.
+    ///
+    /// Returns the index of the head of the list, the following wrappers are
+    /// linked through `DisiWrapper::next`.
+    pub fn top_list(&mut self) -> usize {
+        let size = self.heap.len();
+        let top = self.heap[0];
+        self.buffer[top].next = None;
+        let mut list = top;
         if size >= 3 {
-            list = Self::top_list_to(list, heap, size, 1);
-            list = Self::top_list_to(list, heap, size, 2);
-        } else if size == 2 && (*heap[1]).doc() == (*list).doc() {
-            list = Self::prepend(heap[1], list);
+            list = self.top_list_to(list, 1);
+            list = self.top_list_to(list, 2);
+        } else if size == 2 && self.doc_at(1) == self.buffer[list].doc() {
+            list = self.prepend(self.heap[1], list);
         }
         list
     }
 
-    unsafe fn prepend(w1: *mut DisiWrapper<T>, w2: *mut DisiWrapper<T>) -> *mut DisiWrapper<T> {
-        (*w1).next = w2;
+    fn prepend(&mut self, w1: usize, w2: usize) -> usize {
+        self.buffer[w1].next = Some(w2);
         w1
     }
 
-    unsafe fn top_list_to(
-        mut list: *mut DisiWrapper<T>,
-        heap: &[*mut DisiWrapper<T>],
-        size: usize,
-        i: usize,
-    ) -> *mut DisiWrapper<T> {
-        let w = heap[i];
-        if (*w).doc() == (*list).doc() {
-            list = Self::prepend(w, list);
+    fn top_list_to(&mut self, mut list: usize, i: usize) -> usize {
+        let size = self.heap.len();
+        let w = self.heap[i];
+        if self.buffer[w].doc() == self.buffer[list].doc() {
+            list = self.prepend(w, list);
             let left = Self::left_node(i);
             let right = left + 1;
             if right < size {
-                list = Self::top_list_to(list, heap, size, left);
-                list = Self::top_list_to(list, heap, size, right);
-            } else if left < size && (*heap[left]).doc() == (*list).doc() {
-                list = Self::prepend(heap[left], list);
+                list = self.top_list_to(list, left);
+                list = self.top_list_to(list, right);
+            } else if left < size && self.doc_at(left) == self.buffer[list].doc() {
+                list = self.prepend(self.heap[left], list);
             }
         }
         list
     }
 
-    pub fn push(&mut self, entry: &mut DisiWrapper<T>) {
-        unsafe {
-            self.do_push(entry as *mut DisiWrapper<T>);
-        }
-    }
-
-    #[inline(always)]
-    unsafe fn do_push(&mut self, entry: *mut DisiWrapper<T>) -> *mut DisiWrapper<T> {
-        self.heap[self.size] = entry;
-        let size = self.size;
-        self.up_heap(size);
-        self.size += 1;
-        self.heap[0]
-    }
-
-    pub fn pop(&mut self) -> &'static mut DisiWrapper<T> {
-        unsafe { &mut *self.do_pop() }
+    /// Adds the wrapper at `index` back to the heap.
+    pub fn push(&mut self, index: usize) -> &mut DisiWrapper<T> {
+        debug_assert!(index < self.buffer.len() && !self.heap.contains(&index));
+        self.heap.push(index);
+        let size = self.heap.len();
+        self.up_heap(size - 1);
+        &mut self.buffer[self.heap[0]]
     }
 
-    #[inline(always)]
-    unsafe fn do_pop(&mut self) -> *mut DisiWrapper<T> {
-        debug_assert!(self.size > 0);
-        let result = self.heap[0];
-        self.size -= 1;
-        let size = self.size;
-        self.heap[0] = self.heap[size];
-        self.heap[size] = ptr::null_mut();
-        self.down_heap(size);
+    /// Removes the top of the heap and returns its index.
+    pub fn pop(&mut self) -> usize {
+        debug_assert!(!self.heap.is_empty());
+        let result = self.heap.swap_remove(0);
+        if !self.heap.is_empty() {
+            self.down_heap();
+        }
         result
     }
 
     pub fn peek(&self) -> &DisiWrapper<T> {
-        unsafe { &*self.heap[0] }
+        &self.buffer[self.heap[0]]
     }
 
     pub fn peek_mut(&mut self) -> PeekMut<T> {
@@ -273,32 +262,21 @@ impl<T: DocIterator> DisiPriorityQueue<T> {
     }
 
     pub fn update_top(&mut self) -> &mut DisiWrapper<T> {
-        unsafe { &mut *self.do_update_top() }
+        self.down_heap();
+        &mut self.buffer[self.heap[0]]
     }
 
-    unsafe fn do_update_top(&mut self) -> *mut DisiWrapper<T> {
-        let size = self.size;
-        self.down_heap(size);
-        self.heap[0]
-    }
-
-    pub fn update_top_with(&mut self, top_replacement: &mut DisiWrapper<T>) -> &mut DisiWrapper<T> {
-        unsafe { &mut *self.do_update_top_with(top_replacement as *mut DisiWrapper<T>) }
-    }
-
-    unsafe fn do_update_top_with(
-        &mut self,
-        top_replacement: *mut DisiWrapper<T>,
-    ) -> *mut DisiWrapper<T> {
-        self.heap[0] = top_replacement;
+    /// Replaces the top of the heap with the wrapper at `index`.
+    pub fn update_top_with(&mut self, index: usize) -> &mut DisiWrapper<T> {
+        self.heap[0] = index;
         self.update_top()
     }
 
-    unsafe fn up_heap(&mut self, mut i: usize) {
+    fn up_heap(&mut self, mut i: usize) {
         let node = self.heap[i];
-        let node_doc = (*node).doc();
+        let node_doc = self.buffer[node].doc();
         while let Some(j) = Self::parent_node(i) {
-            if node_doc >= (*self.heap[j]).doc() {
+            if node_doc >= self.doc_at(j) {
                 break;
             }
             self.heap[i] = self.heap[j];
@@ -308,25 +286,27 @@ impl<T: DocIterator> DisiPriorityQueue<T> {
         self.heap[i] = node;
     }
 
-    unsafe fn down_heap(&mut self, size: usize) {
+    fn down_heap(&mut self) {
+        let size = self.heap.len();
         let mut i = 0;
         let node = self.heap[0];
+        let node_doc = self.buffer[node].doc();
         let mut j = Self::left_node(i);
-        if j < self.size {
+        if j < size {
             let mut k = Self::right_node(j);
-            if k < size && (*self.heap[k]).doc() < (*self.heap[j]).doc() {
+            if k < size && self.doc_at(k) < self.doc_at(j) {
                 j = k;
             }
-            if (*self.heap[j]).doc() < (*node).doc() {
+            if self.doc_at(j) < node_doc {
                 loop {
                     self.heap[i] = self.heap[j];
                     i = j;
                     j = Self::left_node(i);
                     k = Self::right_node(j);
-                    if k < size && (*self.heap[k]).doc() < (*self.heap[j]).doc() {
+                    if k < size && self.doc_at(k) < self.doc_at(j) {
                         j = k;
                     }
-                    if j >= size || (*self.heap[j]).doc() >= (*node).doc() {
+                    if j >= size || self.doc_at(j) >= node_doc {
                         break;
                     }
                 }
@@ -334,10 +314,6 @@ impl<T: DocIterator> DisiPriorityQueue<T> {
             }
         }
     }
-
-    pub fn is_empty(&self) -> bool {
-        self.size == 0
-    }
 }
 
 impl<'a, T: DocIterator> IntoIterator for &'a DisiPriorityQueue<T> {
@@ -392,19 +368,20 @@ impl<'a, T: 'a + DocIterator> Drop for PeekMut<'a, T> {
 impl<'a, T: DocIterator> Deref for PeekMut<'a, T> {
     type Target = DisiWrapper<T>;
     fn deref(&self) -> &DisiWrapper<T> {
-        unsafe { &*self.heap.heap[0] }
+        self.heap.peek()
     }
 }
 
 impl<'a, T: DocIterator> DerefMut for PeekMut<'a, T> {
     fn deref_mut(&mut self) -> &mut DisiWrapper<T> {
-        unsafe { &mut *self.heap.heap[0] }
+        let top = self.heap.heap[0];
+        &mut self.heap.buffer[top]
     }
 }
 
 impl<'a, T: DocIterator> PeekMut<'a, T> {
-    /// Removes the peeked value from the heap and returns it.
-    pub fn pop(mut this: PeekMut<'a, T>) -> &'static mut DisiWrapper<T> {
+    /// Removes the peeked value from the heap and returns its index.
+    pub fn pop(mut this: PeekMut<'a, T>) -> usize {
         let value = this.heap.pop();
         this.sift = false;
         value
@@ -16,8 +16,10 @@ use crate::Result;
 use crate::core::search::{DocIdSet, DocIterator, NO_MORE_DOCS};
 use crate::core::util::bit_set::{FixedBitSet, ImmutableBitSet};
 use crate::core::util::packed::{EliasFanoDecoder, EliasFanoEncoder, NO_MORE_VALUES};
-use crate::core::util::DocId;
+use crate::core::util::roaring_doc_id_set::{RoaringDocIdSet, RoaringDocIterator};
+use crate::core::util::{bits2words, Accountable, DocId};
 use crate::error::Error::*;
+use std::mem;
 use std::sync::Arc;
 
 pub struct BitDocIdSet<T: ImmutableBitSet> {
@@ -181,6 +183,20 @@ pub enum DocIdSetEnum {
     IntArray(IntArrayDocIdSet),
     NotDocId(NotDocIdSet<ShortArrayDocIdSet>),
     BitDocId(BitDocIdSet<FixedBitSet>),
+    Roaring(RoaringDocIdSet),
+}
+
+impl Accountable for DocIdSetEnum {
+    /// Approximate memory usage of the docs of this set.
+    fn ram_bytes_used(&self) -> usize {
+        match self {
+            DocIdSetEnum::ShortArray(s) => s.docs.len() * mem::size_of::<u16>(),
+            DocIdSetEnum::IntArray(s) => s.docs.len() * mem::size_of::<i32>(),
+            DocIdSetEnum::NotDocId(s) => s.set.docs.len() * mem::size_of::<u16>(),
+            DocIdSetEnum::BitDocId(s) => bits2words(s.set.num_bits) * mem::size_of::<i64>(),
+            DocIdSetEnum::Roaring(s) => s.ram_bytes_used(),
+        }
+    }
 }
 
 impl DocIdSet for DocIdSetEnum {
@@ -191,6 +207,7 @@ impl DocIdSet for DocIdSetEnum {
             DocIdSetEnum::IntArray(s) => Ok(s.iterator()?.map(DocIdSetDocIterEnum::IntArray)),
             DocIdSetEnum::NotDocId(s) => Ok(s.iterator()?.map(DocIdSetDocIterEnum::NotDocId)),
             DocIdSetEnum::BitDocId(s) => Ok(s.iterator()?.map(DocIdSetDocIterEnum::BitDocId)),
+            DocIdSetEnum::Roaring(s) => Ok(s.iterator()?.map(DocIdSetDocIterEnum::Roaring)),
         }
     }
 }
@@ -463,6 +480,7 @@ pub enum DocIdSetDocIterEnum {
     IntArray(IntArrayDocIterator),
     NotDocId(NotDocIterator<ShortArrayDocIterator>),
     BitDocId(BitSetDocIterator<FixedBitSet>),
+    Roaring(RoaringDocIterator),
 }
 
 // used for empty stub
@@ -479,6 +497,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.doc_id(),
             DocIdSetDocIterEnum::NotDocId(i) => i.doc_id(),
             DocIdSetDocIterEnum::BitDocId(i) => i.doc_id(),
+            DocIdSetDocIterEnum::Roaring(i) => i.doc_id(),
         }
     }
 
@@ -488,6 +507,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.next(),
             DocIdSetDocIterEnum::NotDocId(i) => i.next(),
             DocIdSetDocIterEnum::BitDocId(i) => i.next(),
+            DocIdSetDocIterEnum::Roaring(i) => i.next(),
         }
     }
 
@@ -497,6 +517,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.advance(target),
             DocIdSetDocIterEnum::NotDocId(i) => i.advance(target),
             DocIdSetDocIterEnum::BitDocId(i) => i.advance(target),
+            DocIdSetDocIterEnum::Roaring(i) => i.advance(target),
         }
     }
 
@@ -506,6 +527,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.slow_advance(target),
             DocIdSetDocIterEnum::NotDocId(i) => i.slow_advance(target),
             DocIdSetDocIterEnum::BitDocId(i) => i.slow_advance(target),
+            DocIdSetDocIterEnum::Roaring(i) => i.slow_advance(target),
         }
     }
 
@@ -515,6 +537,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.cost(),
             DocIdSetDocIterEnum::NotDocId(i) => i.cost(),
             DocIdSetDocIterEnum::BitDocId(i) => i.cost(),
+            DocIdSetDocIterEnum::Roaring(i) => i.cost(),
         }
     }
 
@@ -524,6 +547,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.matches(),
             DocIdSetDocIterEnum::NotDocId(i) => i.matches(),
             DocIdSetDocIterEnum::BitDocId(i) => i.matches(),
+            DocIdSetDocIterEnum::Roaring(i) => i.matches(),
         }
     }
 
@@ -533,6 +557,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.match_cost(),
             DocIdSetDocIterEnum::NotDocId(i) => i.match_cost(),
             DocIdSetDocIterEnum::BitDocId(i) => i.match_cost(),
+            DocIdSetDocIterEnum::Roaring(i) => i.match_cost(),
         }
     }
 
@@ -542,6 +567,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.approximate_next(),
             DocIdSetDocIterEnum::NotDocId(i) => i.approximate_next(),
             DocIdSetDocIterEnum::BitDocId(i) => i.approximate_next(),
+            DocIdSetDocIterEnum::Roaring(i) => i.approximate_next(),
         }
     }
 
@@ -551,6 +577,7 @@ impl DocIterator for DocIdSetDocIterEnum {
             DocIdSetDocIterEnum::IntArray(i) => i.approximate_advance(target),
             DocIdSetDocIterEnum::NotDocId(i) => i.approximate_advance(target),
             DocIdSetDocIterEnum::BitDocId(i) => i.approximate_advance(target),
+            DocIdSetDocIterEnum::Roaring(i) => i.approximate_advance(target),
         }
     }
 }
@@ -14,12 +14,14 @@
 use crate::core::codec::points::PointValues;
 use crate::core::codec::Terms;
 use crate::core::search::{DocIterator, NO_MORE_DOCS};
-use crate::core::util::bit_set::{BitSet, FixedBitSet};
+use crate::core::util::bit_set::{BitSet, FixedBitSet, ImmutableBitSet};
 use crate::core::util::bit_util::{BitsRequired, UnsignedShift};
 use crate::core::util::doc_id_set::{BitDocIdSet, DocIdSetEnum, IntArrayDocIdSet};
+use crate::core::util::roaring_doc_id_set::{RoaringDocIdSet, RoaringDocIdSetBuilder};
 use crate::core::util::sorter::LSBRadixSorter;
-use crate::core::util::DocId;
+use crate::core::util::{bits2words, Accountable, DocId, SparseFixedBitSet};
 
+use crate::error::Error::IllegalArgument;
 use crate::Result;
 
 use std::cmp::{max, min};
@@ -27,7 +29,8 @@ use std::mem;
 use std::sync::Arc;
 
 /// A builder of {@link DocIdSet}s.  At first it uses a sparse structure to gather
-/// documents, and then upgrades to a non-sparse bit set once enough hits match.
+/// documents, and then upgrades to a `SparseFixedBitSet` once enough hits match,
+/// and to a non-sparse bit set once the sparse one is not much smaller.
 ///
 /// To add documents, you first need to call {@link #grow} in order to reserve
 /// space, and then call `BulkAdder#add(int)` on the returned
@@ -40,11 +43,15 @@ pub struct DocIdSetBuilder {
     num_values_per_doc: f64,
     buffers: Vec<Buffer>,
     total_allocated: usize,
+    sparse_bit_set: Option<SparseFixedBitSet>,
     bit_set: Option<FixedBitSet>,
     counter: i64,
     adder: BulkAddr,
 }
 
+// below that many docs, a `FixedBitSet` is small enough to be allocated right away
+const SPARSE_BIT_SET_MIN_MAX_DOC: DocId = 1 << 16;
+
 impl DocIdSetBuilder {
     pub fn with_max_doc(max_doc: DocId) -> DocIdSetBuilder {
         DocIdSetBuilder::new(max_doc, -1, -1)
@@ -87,6 +94,7 @@ impl DocIdSetBuilder {
             num_values_per_doc,
             buffers: Vec::new(),
             total_allocated: 0,
+            sparse_bit_set: None,
             bit_set: None,
             counter: -1,
             adder: BulkAddr::Buffers,
@@ -99,6 +107,9 @@ impl DocIdSetBuilder {
     pub fn add(&mut self, iter: &mut dyn DocIterator) -> Result<()> {
         if let Some(ref mut bit_set) = self.bit_set {
             bit_set.or(iter)?;
+        } else if let Some(ref mut bit_set) = self.sparse_bit_set {
+            bit_set.or(iter)?;
+            self.maybe_upgrade2fixed_bit_set();
         } else {
             let cost = min(i32::max_value() as usize, iter.cost());
             self.grow(cost);
@@ -133,13 +144,17 @@ impl DocIdSetBuilder {
                 assert!(!self.bit_set.is_none());
                 self.bit_set.as_mut().unwrap().set(doc as usize);
             }
+            BulkAddr::SparseFixedBitSet => {
+                assert!(!self.sparse_bit_set.is_none());
+                self.sparse_bit_set.as_mut().unwrap().set(doc as usize);
+            }
         }
     }
 
     /// Reserve space and return a `BulkAdder` object that can be used to
     /// add up to *numDocs* documents.
     pub fn grow(&mut self, num_docs: usize) {
-        if self.bit_set.is_none() {
+        if self.bit_set.is_none() && self.sparse_bit_set.is_none() {
             if self.total_allocated + num_docs <= self.threshold {
                 self.ensure_buffer_capacity(num_docs);
             } else {
@@ -147,6 +162,9 @@ impl DocIdSetBuilder {
                 self.counter += num_docs as i64;
             }
         } else {
+            if self.sparse_bit_set.is_some() {
+                self.maybe_upgrade2fixed_bit_set();
+            }
             self.counter += num_docs as i64;
         }
     }
@@ -206,7 +224,21 @@ impl DocIdSetBuilder {
     fn upgrade2bit_set(&mut self) {
         assert!(self.bit_set.is_none());
         assert!(self.max_doc > 0);
-        let mut bit_set = FixedBitSet::new(self.max_doc as usize);
+        if self.max_doc >= SPARSE_BIT_SET_MIN_MAX_DOC {
+            let mut bit_set = SparseFixedBitSet::new(self.max_doc as usize);
+            self.counter = self.copy_buffers(&mut bit_set);
+            self.sparse_bit_set = Some(bit_set);
+            self.adder = BulkAddr::SparseFixedBitSet;
+        } else {
+            let mut bit_set = FixedBitSet::new(self.max_doc as usize);
+            self.counter = self.copy_buffers(&mut bit_set);
+            self.bit_set = Some(bit_set);
+            self.adder = BulkAddr::FixedBitSet;
+        }
+        self.buffers = Vec::new();
+    }
+
+    fn copy_buffers(&self, bit_set: &mut impl BitSet) -> i64 {
         let mut counter = 0i64;
         for buffer in &self.buffers {
             let length = buffer.length;
@@ -215,21 +247,40 @@ impl DocIdSetBuilder {
                 bit_set.set(*i as usize);
             }
         }
-        self.bit_set = Some(bit_set);
-        self.counter = counter;
-        self.buffers = Vec::new();
-        self.adder = BulkAddr::FixedBitSet;
+        counter
+    }
+
+    // the sparse bit set stops saving memory once most of its longs are
+    // non-zero, switch to a `FixedBitSet` when it takes more than half its size
+    fn maybe_upgrade2fixed_bit_set(&mut self) {
+        let fixed_bytes = bits2words(self.max_doc as usize) * mem::size_of::<i64>();
+        let upgrade = match self.sparse_bit_set {
+            Some(ref bit_set) => bit_set.ram_bytes_used() > fixed_bytes >> 1,
+            None => false,
+        };
+        if upgrade {
+            let sparse_bit_set = self.sparse_bit_set.take().unwrap();
+            self.bit_set = Some(sparse_bit_set.to_fixed_bit_set());
+            self.adder = BulkAddr::FixedBitSet;
+        }
     }
 
     pub fn build(&mut self) -> DocIdSetEnum {
-        if self.bit_set.is_some() {
+        if let Some(bit_set) = self.bit_set.take() {
             assert!(self.counter >= 0);
-            let cost = (self.counter as f64 / self.num_values_per_doc).round() as usize;
             self.buffers = Vec::new();
-            DocIdSetEnum::BitDocId(BitDocIdSet::new(
-                Arc::new(self.bit_set.take().unwrap()),
-                cost,
-            ))
+            let cost = (self.counter as f64 / self.num_values_per_doc).round() as usize;
+            compact_bit_set(bit_set, cost)
+        } else if let Some(bit_set) = self.sparse_bit_set.take() {
+            assert!(self.counter >= 0);
+            let cost = (self.counter as f64 / self.num_values_per_doc).round() as usize;
+            match to_roaring(&bit_set, cost) {
+                Some(roaring) => DocIdSetEnum::Roaring(roaring),
+                None => DocIdSetEnum::BitDocId(BitDocIdSet::new(
+                    Arc::new(bit_set.to_fixed_bit_set()),
+                    cost,
+                )),
+            }
         } else {
             let mut concatenated = DocIdSetBuilder::concat(&mut self.buffers);
             let mut sorter = LSBRadixSorter::default();
@@ -315,6 +366,7 @@ impl DocIdSetBuilder {
 #[derive(Copy, Clone)]
 enum BulkAddr {
     FixedBitSet,
+    SparseFixedBitSet,
     Buffers,
 }
 
@@ -334,3 +386,236 @@ impl Buffer {
         Buffer { array, length }
     }
 }
+
+/// Builds the most compact `DocIdSetEnum` of docs added in increasing order,
+/// eg. the matches of a query: a sorted array for very sparse sets, a
+/// `RoaringDocIdSet` for medium densities and a `FixedBitSet` for dense sets,
+/// which also gives conjunctions random access.
+pub struct AdaptiveDocIdSetBuilder {
+    max_doc: DocId,
+    docs: Vec<DocId>,
+    bit_set: Option<FixedBitSet>,
+    cardinality: usize,
+    last_doc: DocId,
+}
+
+impl AdaptiveDocIdSetBuilder {
+    pub fn new(max_doc: DocId) -> AdaptiveDocIdSetBuilder {
+        Self::with_cost(max_doc, 0)
+    }
+
+    /// `cost` is the expected number of docs, a set expected to be dense
+    /// goes to a bit set right away.
+    pub fn with_cost(max_doc: DocId, cost: usize) -> AdaptiveDocIdSetBuilder {
+        let mut builder = AdaptiveDocIdSetBuilder {
+            max_doc,
+            docs: Vec::new(),
+            bit_set: None,
+            cardinality: 0,
+            last_doc: -1,
+        };
+        if cost > builder.array_threshold() {
+            builder.bit_set = Some(FixedBitSet::new(max_doc as usize));
+        }
+        builder
+    }
+
+    // beyond that many docs, an array takes more memory than a bit set
+    fn array_threshold(&self) -> usize {
+        self.max_doc as usize >> 5
+    }
+
+    /// Adds a doc, greater than the docs added so far.
+    pub fn add_doc(&mut self, doc: DocId) -> Result<()> {
+        if doc <= self.last_doc || doc >= self.max_doc {
+            return Err(IllegalArgument(format!(
+                "Doc ids must be added in-order and be < max_doc={}, got {} after {}",
+                self.max_doc, doc, self.last_doc
+            )));
+        }
+        match self.bit_set {
+            Some(ref mut bit_set) => bit_set.set(doc as usize),
+            None => {
+                self.docs.push(doc);
+                if self.docs.len() > self.array_threshold() {
+                    let mut bit_set = FixedBitSet::new(self.max_doc as usize);
+                    for doc in &self.docs {
+                        bit_set.set(*doc as usize);
+                    }
+                    self.bit_set = Some(bit_set);
+                    self.docs = Vec::new();
+                }
+            }
+        }
+        self.last_doc = doc;
+        self.cardinality += 1;
+        Ok(())
+    }
+
+    /// Adds the remaining docs of `iter`.
+    pub fn add(&mut self, iter: &mut dyn DocIterator) -> Result<()> {
+        loop {
+            let doc = iter.next()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(());
+            }
+            self.add_doc(doc)?;
+        }
+    }
+
+    pub fn build(self) -> DocIdSetEnum {
+        if let Some(bit_set) = self.bit_set {
+            return compact_bit_set(bit_set, self.cardinality);
+        }
+        let mut builder = RoaringDocIdSetBuilder::new(self.max_doc);
+        for doc in &self.docs {
+            let added = builder.add_doc(*doc);
+            debug_assert!(added.is_ok());
+        }
+        let roaring = builder.build();
+        let mut docs = self.docs;
+        docs.push(NO_MORE_DOCS);
+        if docs.len() * mem::size_of::<i32>() <= roaring.ram_bytes_used() {
+            DocIdSetEnum::IntArray(IntArrayDocIdSet::new(docs, self.cardinality))
+        } else {
+            DocIdSetEnum::Roaring(roaring)
+        }
+    }
+}
+
+fn compact_bit_set(bit_set: FixedBitSet, cost: usize) -> DocIdSetEnum {
+    match to_roaring(&bit_set, cost) {
+        Some(roaring) => DocIdSetEnum::Roaring(roaring),
+        None => DocIdSetEnum::BitDocId(BitDocIdSet::new(Arc::new(bit_set), cost)),
+    }
+}
+
+// encodes the about `cost` docs of `bit_set` as a `RoaringDocIdSet` when it
+// takes less memory than a `FixedBitSet`, which requires most of the blocks
+// to be sparse
+fn to_roaring(bit_set: &impl ImmutableBitSet, cost: usize) -> Option<RoaringDocIdSet> {
+    let num_bits = bit_set.len();
+    if cost * 16 >= num_bits {
+        return None;
+    }
+    let mut builder = RoaringDocIdSetBuilder::new(num_bits as DocId);
+    let mut doc = bit_set.next_set_bit(0);
+    while doc != NO_MORE_DOCS {
+        let added = builder.add_doc(doc);
+        debug_assert!(added.is_ok());
+        if doc as usize + 1 >= num_bits {
+            break;
+        }
+        doc = bit_set.next_set_bit(doc as usize + 1);
+    }
+    let roaring = builder.build();
+    if roaring.ram_bytes_used() < bits2words(num_bits) * mem::size_of::<i64>() {
+        Some(roaring)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::DocIdSet;
+
+    fn build(max_doc: DocId, docs: &[DocId]) -> Result<(DocIdSetEnum, Vec<DocId>)> {
+        let mut builder = AdaptiveDocIdSetBuilder::new(max_doc);
+        for doc in docs {
+            builder.add_doc(*doc)?;
+        }
+        let set = builder.build();
+        let mut found = vec![];
+        if let Some(mut iter) = set.iterator()? {
+            loop {
+                let doc = iter.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                found.push(doc);
+            }
+        }
+        Ok((set, found))
+    }
+
+    #[test]
+    fn test_adaptive_doc_id_set_builder() -> Result<()> {
+        let max_doc = 1 << 20;
+        // a few docs spread over many blocks
+        let docs: Vec<DocId> = (0..8).map(|i| i * 100_000).collect();
+        let (set, found) = build(max_doc, &docs)?;
+        assert!(matches!(set, DocIdSetEnum::IntArray(_)));
+        assert_eq!(found, docs);
+
+        // 1% of the docs, too many for an array
+        let docs: Vec<DocId> = (0..max_doc).step_by(100).collect();
+        let (set, found) = build(max_doc, &docs)?;
+        assert!(matches!(set, DocIdSetEnum::Roaring(_)));
+        assert!(set.ram_bytes_used() < (max_doc / 8) as usize);
+        assert_eq!(found, docs);
+
+        let docs: Vec<DocId> = (0..max_doc).step_by(3).collect();
+        let (set, found) = build(max_doc, &docs)?;
+        assert!(matches!(set, DocIdSetEnum::BitDocId(_)));
+        assert_eq!(found, docs);
+
+        let (set, found) = build(max_doc, &[])?;
+        assert!(found.is_empty() && set.ram_bytes_used() <= 4);
+
+        let mut builder = AdaptiveDocIdSetBuilder::new(10);
+        builder.add_doc(3)?;
+        assert!(builder.add_doc(3).is_err());
+        assert!(builder.add_doc(10).is_err());
+        Ok(())
+    }
+
+    fn collect(set: &DocIdSetEnum) -> Result<Vec<DocId>> {
+        let mut found = vec![];
+        if let Some(mut iter) = set.iterator()? {
+            loop {
+                let doc = iter.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                found.push(doc);
+            }
+        }
+        Ok(found)
+    }
+
+    #[test]
+    fn test_doc_id_set_builder_sparse_bit_set() -> Result<()> {
+        let max_doc = 1 << 24;
+        // a clustered range of docs, more than the buffers can hold
+        let docs: Vec<DocId> = (1_000_000..1_200_000).collect();
+        let mut builder = DocIdSetBuilder::with_max_doc(max_doc);
+        for chunk in docs.chunks(1000).rev() {
+            builder.grow(chunk.len());
+            for doc in chunk {
+                builder.add_doc(*doc);
+            }
+        }
+        assert!(builder.bit_set.is_none());
+        assert!(builder.sparse_bit_set.is_some());
+        let set = builder.build();
+        assert!(matches!(set, DocIdSetEnum::Roaring(_)));
+        assert_eq!(collect(&set)?, docs);
+
+        // docs spread over the whole index end up in a fixed bit set
+        let docs: Vec<DocId> = (0..max_doc).step_by(7).collect();
+        let mut builder = DocIdSetBuilder::with_max_doc(max_doc);
+        for chunk in docs.chunks(1000) {
+            builder.grow(chunk.len());
+            for doc in chunk {
+                builder.add_doc(*doc);
+            }
+        }
+        assert!(builder.sparse_bit_set.is_none());
+        let set = builder.build();
+        assert!(matches!(set, DocIdSetEnum::BitDocId(_)));
+        assert_eq!(collect(&set)?, docs);
+        Ok(())
+    }
+}
@@ -96,6 +96,12 @@ impl BytesStore {
         self.len() == 0
     }
 
+    /// The bytes allocated by the blocks.
+    pub fn ram_bytes_used(&self) -> usize {
+        self.blocks.iter().map(|b| b.capacity()).sum::<usize>()
+            + self.blocks.capacity() * mem::size_of::<Vec<u8>>()
+    }
+
     #[inline(always)]
     fn write_bytes_unchecked(&mut self, idx: usize, bytes: &[u8]) {
         let cur_block = &mut self.blocks[idx];
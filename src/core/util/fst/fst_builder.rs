@@ -128,7 +128,9 @@ impl<F: OutputFactory> FstBuilder<F> {
         }
     }
 
-    // this should be call after new FstBuilder
+    /// Must be called once before `add`. The builder keeps pointers to
+    /// itself from then on, so it must not be moved, see `FSTMapBuilder` for
+    /// a builder which can.
     pub fn init(&mut self) {
         if self.do_share_suffix {
             let reader = self.fst.bytes_store.get_reverse_reader();
@@ -764,7 +766,7 @@ impl<F: OutputFactory> UnCompiledNode<F> {
     }
 
     fn add_arc(&mut self, label: i32, target: Node) {
-        assert!(label > 0);
+        assert!(label >= 0);
         assert!(self.num_arcs == 0 || label > self.arcs[self.num_arcs - 1].label);
         let new_arc = BuilderArc {
             label,
@@ -0,0 +1,304 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{check_footer, check_header, write_footer, write_header};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::store::IOContext;
+use crate::core::util::fst::{FstBuilder, InputType, OutputFactory, FST};
+use crate::core::util::{to_ints_ref, IntsRefBuilder};
+use crate::error::Error::{CorruptIndex, IllegalArgument};
+use crate::Result;
+
+const FST_MAP_CODEC_NAME: &str = "FSTMap";
+const VERSION_START: i32 = 0;
+const VERSION_CURRENT: i32 = VERSION_START;
+
+/// Builds an `FSTMap` from keys added in increasing byte order.
+///
+/// The FST is minimal: the keys share the arcs of their common prefixes,
+/// and of their common suffixes when the outputs allow it. Outputs may be
+/// of any `OutputFactory`, e.g. `PositiveIntOutputFactory` for numbers or
+/// `ByteSequenceOutputFactory` for byte strings.
+///
+/// ```rust,ignore
+/// let mut builder = FSTMapBuilder::new(PositiveIntOutputFactory::new());
+/// builder.insert(b"cat", PositiveIntOutput::new(5))?;
+/// builder.insert(b"dog", PositiveIntOutput::new(7))?;
+/// let map = builder.finish()?;
+/// map.save(&directory, "pets.fst")?;
+/// ```
+pub struct FSTMapBuilder<F: OutputFactory> {
+    // boxed since `init` keeps pointers into the builder, it must not move
+    builder: Box<FstBuilder<F>>,
+    outputs: F,
+    last_key: Option<Vec<u8>>,
+    len: usize,
+    scratch: IntsRefBuilder,
+}
+
+impl<F: OutputFactory> FSTMapBuilder<F> {
+    pub fn new(outputs: F) -> Self {
+        let mut builder = Box::new(FstBuilder::new(InputType::Byte1, outputs.clone()));
+        builder.init();
+        FSTMapBuilder {
+            builder,
+            outputs,
+            last_key: None,
+            len: 0,
+            scratch: IntsRefBuilder::new(),
+        }
+    }
+
+    /// Adds `key`, which must be greater than the keys added before it.
+    pub fn insert(&mut self, key: &[u8], value: F::Value) -> Result<()> {
+        if let Some(ref last_key) = self.last_key {
+            if key <= last_key.as_slice() {
+                return Err(IllegalArgument(format!(
+                    "FST keys must be added in increasing order, got {:?} after {:?}",
+                    key, last_key
+                )));
+            }
+        }
+        self.builder
+            .add(to_ints_ref(key, &mut self.scratch), value)?;
+        let last_key = self.last_key.get_or_insert_with(Vec::new);
+        last_key.clear();
+        last_key.extend_from_slice(key);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The number of keys added so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn finish(mut self) -> Result<FSTMap<F>> {
+        let fst = self.builder.finish()?;
+        Ok(FSTMap {
+            fst,
+            outputs: self.outputs,
+            len: self.len,
+        })
+    }
+}
+
+/// An immutable map from byte keys to the outputs of an FST, built by
+/// `FSTMapBuilder` and persisted to a `Directory` with `save` and `load`.
+pub struct FSTMap<F: OutputFactory> {
+    // none when the map is empty
+    fst: Option<FST<F>>,
+    outputs: F,
+    len: usize,
+}
+
+impl<F: OutputFactory> FSTMap<F> {
+    /// Builds a map from entries in any order, the keys must be distinct.
+    pub fn from_entries<K, I>(outputs: F, entries: I) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, F::Value)>,
+    {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        if let Some(w) = entries
+            .windows(2)
+            .find(|w| w[0].0.as_ref() == w[1].0.as_ref())
+        {
+            return Err(IllegalArgument(format!(
+                "duplicate FST key {:?}",
+                w[0].0.as_ref()
+            )));
+        }
+        let mut builder = FSTMapBuilder::new(outputs);
+        for (key, value) in entries {
+            builder.insert(key.as_ref(), value)?;
+        }
+        builder.finish()
+    }
+
+    /// Returns the output of `key`, if it is in the map.
+    pub fn get(&self, key: &[u8]) -> Result<Option<F::Value>> {
+        match self.fst {
+            Some(ref fst) => fst.get(key),
+            None => Ok(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The underlying FST, for traversals beyond exact lookups, none when
+    /// the map is empty.
+    pub fn fst(&self) -> Option<&FST<F>> {
+        self.fst.as_ref()
+    }
+
+    pub fn outputs(&self) -> &F {
+        &self.outputs
+    }
+
+    /// Writes the map to the file `name` of `directory`.
+    pub fn save<D: Directory + ?Sized>(&self, directory: &D, name: &str) -> Result<()> {
+        let mut output = directory.create_output(name, &IOContext::Default)?;
+        write_header(&mut output, FST_MAP_CODEC_NAME, VERSION_CURRENT)?;
+        output.write_vlong(self.len as i64)?;
+        match self.fst {
+            Some(ref fst) => {
+                output.write_byte(1)?;
+                fst.save(&mut output)?;
+            }
+            None => output.write_byte(0)?,
+        }
+        write_footer(&mut output)
+    }
+
+    /// Reads a map written by `save` to the file `name` of `directory`,
+    /// `outputs` must be the factory of the saved map.
+    pub fn load<D: Directory + ?Sized>(directory: &D, name: &str, outputs: F) -> Result<Self> {
+        let mut input = directory.open_checksum_input(name, &IOContext::Default)?;
+        check_header(
+            &mut input,
+            FST_MAP_CODEC_NAME,
+            VERSION_START,
+            VERSION_CURRENT,
+        )?;
+        let len = input.read_vlong()?;
+        let fst = match input.read_byte()? {
+            0 => None,
+            1 => Some(FST::from_input(&mut input, outputs.clone())?),
+            b => {
+                return Err(CorruptIndex(format!(
+                    "invalid FST map file '{}': unexpected byte {}",
+                    name, b
+                )));
+            }
+        };
+        check_footer(&mut input)?;
+        Ok(FSTMap {
+            fst,
+            outputs,
+            len: len as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+    use crate::core::util::fst::{
+        ByteSequenceOutput, ByteSequenceOutputFactory, Output, PositiveIntOutput,
+        PositiveIntOutputFactory,
+    };
+
+    #[test]
+    fn test_fst_map_build_and_get() {
+        let entries = vec![
+            ("stop", 3),
+            ("", 9),
+            ("star", 10),
+            ("station", 1),
+            ("top", 7),
+        ];
+        let map = FSTMap::from_entries(
+            PositiveIntOutputFactory::new(),
+            entries
+                .iter()
+                .map(|(k, v)| (k.as_bytes(), PositiveIntOutput::new(*v))),
+        )
+        .unwrap();
+        assert_eq!(map.len(), 5);
+        for (key, value) in &entries {
+            let output = map.get(key.as_bytes()).unwrap();
+            assert_eq!(output.map(|o| o.value()), Some(*value), "{}", key);
+        }
+        assert!(map.get(b"sta").unwrap().is_none());
+        assert!(map.get(b"stations").unwrap().is_none());
+
+        let outputs = ByteSequenceOutputFactory::new();
+        let mut builder = FSTMapBuilder::new(outputs);
+        builder
+            .insert(b"b", ByteSequenceOutput::new(b"routed".to_vec()))
+            .unwrap();
+        assert!(builder
+            .insert(b"a", ByteSequenceOutput::new(b"late".to_vec()))
+            .is_err());
+        assert!(builder
+            .insert(b"b", ByteSequenceOutput::new(b"again".to_vec()))
+            .is_err());
+        let map = builder.finish().unwrap();
+        assert_eq!(map.get(b"b").unwrap().unwrap().inner(), b"routed");
+
+        let empty = FSTMapBuilder::new(outputs).finish().unwrap();
+        assert!(empty.is_empty() && empty.fst().is_none());
+        assert!(empty.get(b"b").unwrap().is_none());
+        assert!(
+            FSTMap::from_entries(outputs, vec![(b"k", ByteSequenceOutput::empty()); 2]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_fst_map_save_and_load() {
+        let directory = ByteBuffersDirectory::new();
+
+        // a routing table from tenant to shard
+        let mut builder = FSTMapBuilder::new(ByteSequenceOutputFactory::new());
+        for (tenant, shard) in [
+            ("acme", "shard-1"),
+            ("acme-eu", "shard-2"),
+            ("globex", "shard-1"),
+        ] {
+            builder
+                .insert(tenant.as_bytes(), ByteSequenceOutput::new(shard.into()))
+                .unwrap();
+        }
+        assert!(builder
+            .insert(b"acme", ByteSequenceOutput::new(b"shard-3".to_vec()))
+            .is_err());
+        let map = builder.finish().unwrap();
+        map.save(&directory, "routing.fst").unwrap();
+        FSTMap::from_entries(ByteSequenceOutputFactory::new(), Vec::<(&[u8], _)>::new())
+            .unwrap()
+            .save(&directory, "empty.fst")
+            .unwrap();
+
+        let map =
+            FSTMap::load(&directory, "routing.fst", ByteSequenceOutputFactory::new()).unwrap();
+        assert_eq!(map.len(), 3);
+        let shard = |tenant: &str| {
+            map.get(tenant.as_bytes())
+                .unwrap()
+                .map(|o| String::from_utf8(o.value()).unwrap())
+        };
+        assert_eq!(shard("acme"), Some("shard-1".into()));
+        assert_eq!(shard("acme-eu"), Some("shard-2".into()));
+        assert_eq!(shard("globex"), Some("shard-1".into()));
+        assert_eq!(shard("acme-us"), None);
+
+        let empty =
+            FSTMap::load(&directory, "empty.fst", ByteSequenceOutputFactory::new()).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(b"acme").unwrap(), None);
+    }
+}
@@ -13,13 +13,15 @@
 
 use std::cmp::max;
 use std::io;
+use std::mem;
 
 use crate::core::codec::{check_header, write_header};
-use crate::core::store::io::{ByteArrayDataOutput, DataInput, DataOutput};
+use crate::core::store::io::{ByteArrayDataOutput, DataInput, DataOutput, IndexInput};
 use crate::core::util::fst::bytes_store::{BytesStore, StoreBytesReader};
 use crate::core::util::fst::fst_builder::{FstBuilder, Node};
-use crate::core::util::fst::DirectionalBytesReader;
 use crate::core::util::fst::{BytesReader, Output, OutputFactory};
+use crate::core::util::fst::{DirectionalBytesReader, OffHeapFSTStore, ReverseRandomAccessReader};
+use crate::core::util::Accountable;
 use crate::error::Error;
 use crate::Result;
 
@@ -52,6 +54,10 @@ const VERSION_NO_NODE_ARC_COUNTS: i32 = 5;
 // LUCENE-7531, donot support pack fst anymore
 const VERSION_PACKED_REMOVED: i32 = 6;
 const VERSION_CURRENT: i32 = VERSION_PACKED_REMOVED;
+/// The version `FST::save` writes.
+pub const FST_VERSION_CURRENT: i32 = VERSION_CURRENT;
+/// The newest version Java Lucene 6 reads, which still flags whether the FST is packed.
+pub const FST_VERSION_JAVA_LUCENE6: i32 = VERSION_NO_NODE_ARC_COUNTS;
 const FINAL_END_NODE: CompiledAddress = -1;
 const NON_FINAL_END_NODE: CompiledAddress = 0;
 
@@ -181,6 +187,8 @@ pub struct FST<F: OutputFactory> {
     bytes_array: Vec<u8>,
     // flag of whether use bytes_store or bytes_array
     use_bytes_array: bool,
+    // Used at read time instead of the above when the FST is loaded off heap.
+    off_heap_store: Option<OffHeapFSTStore>,
     start_node: CompiledAddress,
     version: i32,
     output_factory: F,
@@ -197,6 +205,7 @@ impl<F: OutputFactory> FST<F> {
             bytes_store,
             bytes_array: Vec::with_capacity(0),
             use_bytes_array: false,
+            off_heap_store: None,
             start_node: -1,
             version: VERSION_CURRENT,
             output_factory,
@@ -205,9 +214,41 @@ impl<F: OutputFactory> FST<F> {
     }
 
     pub fn from_input<I: DataInput + ?Sized>(data_in: &mut I, output_factory: F) -> Result<Self> {
-        let output_factory = output_factory;
         let max_block_bits = DEFAULT_MAX_BLOCK_BITS;
+        let (mut fst, num_bytes) = Self::read_header(data_in, output_factory)?;
 
+        if num_bytes > (1 << max_block_bits as i64) {
+            // FST is big: we need multiple pages
+            fst.bytes_store = BytesStore::new(data_in, num_bytes as usize, 1 << max_block_bits)?;
+            fst.use_bytes_array = false;
+        } else {
+            fst.bytes_array = vec![0u8; num_bytes as usize];
+            data_in.read_exact(&mut fst.bytes_array)?;
+        };
+        Ok(fst)
+    }
+
+    /// Loads an FST whose arcs stay in `data_in`, which must support random
+    /// access. Only the header is read into memory, this saves the heap for
+    /// big FSTs read from memory mapped files at the cost of slower lookups.
+    pub fn from_input_off_heap<I: IndexInput + ?Sized>(
+        data_in: &mut I,
+        output_factory: F,
+    ) -> Result<Self> {
+        let (mut fst, num_bytes) = Self::read_header(data_in, output_factory)?;
+        let offset = data_in.file_pointer();
+        let bytes = data_in.random_access_slice(offset, num_bytes)?;
+        fst.off_heap_store = Some(OffHeapFSTStore::new(bytes, num_bytes as usize));
+        data_in.seek(offset + num_bytes)?;
+        Ok(fst)
+    }
+
+    /// Reads everything but the arcs, returns an FST without bytes and
+    /// the number of bytes of its arcs.
+    fn read_header<I: DataInput + ?Sized>(
+        data_in: &mut I,
+        output_factory: F,
+    ) -> Result<(Self, i64)> {
         // Only reads most recent format; we don't have
         // back-compat promise for FSTs (they are experimental):
         let version = check_header(data_in, FILE_FORMAT_NAME, VERSION_PACKED, VERSION_CURRENT)?;
@@ -247,35 +288,26 @@ impl<F: OutputFactory> FST<F> {
         }
 
         let num_bytes = data_in.read_vlong()?;
-        let bytes_store: BytesStore;
-        let mut bytes_array: Vec<u8>;
-        let use_bytes_array: bool;
-
-        if num_bytes > (1 << max_block_bits as i64) {
-            // FST is big: we need multiple pages
-            bytes_store = BytesStore::new(data_in, num_bytes as usize, 1 << max_block_bits)?;
-            bytes_array = Vec::with_capacity(0);
-            use_bytes_array = false;
-        } else {
-            let len = num_bytes as usize;
-            bytes_array = vec![0u8; len];
-            data_in.read_exact(&mut bytes_array)?;
-            // a dummy struct
-            bytes_store = BytesStore::with_block_bits(8);
-            use_bytes_array = true;
-        };
-
-        Ok(FST {
+        let fst = FST {
             input_type,
             start_node,
             version,
             output_factory,
-            bytes_store,
-            use_bytes_array,
+            // a dummy struct
+            bytes_store: BytesStore::with_block_bits(8),
+            use_bytes_array: true,
             empty_output,
-            bytes_array,
+            bytes_array: Vec::with_capacity(0),
+            off_heap_store: None,
             cached_root_arcs: Vec::with_capacity(0),
-        })
+        };
+        Ok((fst, num_bytes))
+    }
+
+    /// Whether the arcs of this FST are read from the index input instead
+    /// of from memory.
+    pub fn is_off_heap(&self) -> bool {
+        self.off_heap_store.is_some()
     }
 
     pub fn outputs(&self) -> &F {
@@ -378,7 +410,9 @@ impl<F: OutputFactory> FST<F> {
     }
 
     pub fn bytes_reader(&self) -> FSTBytesReader {
-        if self.use_bytes_array {
+        if let Some(ref store) = self.off_heap_store {
+            FSTBytesReader::OffHeap(store.reverse_reader())
+        } else if self.use_bytes_array {
             FSTBytesReader::Directional(DirectionalBytesReader::new(&self.bytes_array, true))
         } else {
             FSTBytesReader::BytesStore(self.bytes_store.get_reverse_reader())
@@ -515,7 +549,7 @@ impl<F: OutputFactory> FST<F> {
     fn assert_root_cached_arc(&self, label: Label, arc: &Option<Arc<F::Value>>) -> Result<bool> {
         let root = self.root_arc();
         let mut input = self.bytes_reader();
-        let result = self.find_target_arc(label, &root, &mut input)?;
+        let result = self.find_target_arc_with_cache(label, &root, &mut input, false)?;
         if let Some(ref res) = result {
             if let Some(arc) = arc {
                 assert_eq!(res, arc);
@@ -840,7 +874,7 @@ impl<F: OutputFactory> FST<F> {
 
     #[allow(dead_code)]
     fn write_label(&self, out: &mut impl DataOutput, v: i32) -> Result<()> {
-        assert!(v > 0);
+        assert!(v >= 0);
         match self.input_type {
             InputType::Byte1 => {
                 assert!(v <= 255);
@@ -855,7 +889,7 @@ impl<F: OutputFactory> FST<F> {
     }
 
     fn write_label_local(&mut self, v: i32) -> Result<()> {
-        assert!(v > 0);
+        assert!(v >= 0);
         match self.input_type {
             InputType::Byte1 => {
                 assert!(v <= 255);
@@ -944,11 +978,23 @@ impl<F: OutputFactory> FST<F> {
     }
 
     pub fn save(&self, out: &mut impl DataOutput) -> Result<()> {
+        self.save_with_version(out, VERSION_CURRENT)
+    }
+
+    /// Saves this FST in `version`, which may be older than the current one for
+    /// readers that don't know it; the nodes are the same since `FST_VERSION_JAVA_LUCENE6`.
+    pub fn save_with_version(&self, out: &mut impl DataOutput, version: i32) -> Result<()> {
         if self.start_node == -1 {
             return Err(Error::IllegalState("call finish first!".into()));
         }
-        write_header(out, FILE_FORMAT_NAME, VERSION_CURRENT)?;
-        if VERSION_CURRENT < VERSION_PACKED_REMOVED {
+        if !(VERSION_NO_NODE_ARC_COUNTS..=VERSION_CURRENT).contains(&version) {
+            return Err(Error::IllegalArgument(format!(
+                "can't save FST in version {}",
+                version
+            )));
+        }
+        write_header(out, FILE_FORMAT_NAME, version)?;
+        if version < VERSION_PACKED_REMOVED {
             out.write_byte(0)?;
         }
         // TODO: really we should encode this as an arc, arriving
@@ -976,7 +1022,10 @@ impl<F: OutputFactory> FST<F> {
         };
         out.write_byte(t)?;
         out.write_vlong(self.start_node)?;
-        if self.bytes_store.get_position() > 0 {
+        if let Some(ref store) = self.off_heap_store {
+            out.write_vlong(store.num_bytes() as i64)?;
+            store.write_to(out)?;
+        } else if self.bytes_store.get_position() > 0 {
             debug_assert!(!self.use_bytes_array);
             let num_bytes = self.bytes_store.get_position();
             out.write_vlong(num_bytes as i64)?;
@@ -991,6 +1040,17 @@ impl<F: OutputFactory> FST<F> {
     }
 }
 
+impl<F: OutputFactory> Accountable for FST<F> {
+    /// The bytes of the FST held in RAM, nothing but the cached root arcs when
+    /// it is loaded off heap.
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.bytes_array.capacity()
+            + self.bytes_store.ram_bytes_used()
+            + self.cached_root_arcs.capacity() * mem::size_of::<Option<Arc<F::Value>>>()
+    }
+}
+
 // this should only be used for place holder to avoid Option
 impl<F: OutputFactory + Default> Default for FST<F> {
     fn default() -> Self {
@@ -1000,6 +1060,7 @@ impl<F: OutputFactory + Default> Default for FST<F> {
             bytes_store: BytesStore::with_block_bits(1),
             bytes_array: Vec::with_capacity(0),
             use_bytes_array: true,
+            off_heap_store: None,
             start_node: 0,
             version: VERSION_CURRENT,
             output_factory: F::default(),
@@ -1011,6 +1072,7 @@ impl<F: OutputFactory + Default> Default for FST<F> {
 pub enum FSTBytesReader {
     Directional(DirectionalBytesReader),
     BytesStore(StoreBytesReader),
+    OffHeap(ReverseRandomAccessReader),
 }
 
 impl BytesReader for FSTBytesReader {
@@ -1018,6 +1080,7 @@ impl BytesReader for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref d) => d.position(),
             FSTBytesReader::BytesStore(ref b) => b.position(),
+            FSTBytesReader::OffHeap(ref r) => r.position(),
         }
     }
 
@@ -1025,6 +1088,7 @@ impl BytesReader for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref mut d) => d.set_position(pos),
             FSTBytesReader::BytesStore(ref mut b) => b.set_position(pos),
+            FSTBytesReader::OffHeap(ref mut r) => r.set_position(pos),
         }
     }
 
@@ -1032,6 +1096,7 @@ impl BytesReader for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref d) => d.reversed(),
             FSTBytesReader::BytesStore(ref b) => b.reversed(),
+            FSTBytesReader::OffHeap(ref r) => r.reversed(),
         }
     }
 }
@@ -1041,6 +1106,7 @@ impl io::Read for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref mut d) => d.read(buf),
             FSTBytesReader::BytesStore(ref mut b) => b.read(buf),
+            FSTBytesReader::OffHeap(ref mut r) => r.read(buf),
         }
     }
 }
@@ -1050,6 +1116,7 @@ impl DataInput for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref mut d) => d.read_byte(),
             FSTBytesReader::BytesStore(ref mut b) => b.read_byte(),
+            FSTBytesReader::OffHeap(ref mut r) => r.read_byte(),
         }
     }
 
@@ -1057,6 +1124,7 @@ impl DataInput for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref mut d) => d.read_bytes(b, offset, length),
             FSTBytesReader::BytesStore(ref mut r) => r.read_bytes(b, offset, length),
+            FSTBytesReader::OffHeap(ref mut r) => r.read_bytes(b, offset, length),
         }
     }
 
@@ -1064,6 +1132,7 @@ impl DataInput for FSTBytesReader {
         match *self {
             FSTBytesReader::Directional(ref mut d) => d.skip_bytes(count),
             FSTBytesReader::BytesStore(ref mut b) => b.skip_bytes(count),
+            FSTBytesReader::OffHeap(ref mut r) => r.skip_bytes(count),
         }
     }
 }
@@ -1071,15 +1140,17 @@ impl DataInput for FSTBytesReader {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::core::store::io::{FSIndexOutput, MmapIndexInput};
     use crate::core::util::fst::bytes_output::*;
     use crate::core::util::ints_ref::IntsRefBuilder;
+    use std::io::Write;
 
-    #[test]
-    fn test_fst() {
+    fn build_test_fst(
+        input_values: &[&str],
+        output_values: &[i32],
+    ) -> FST<ByteSequenceOutputFactory> {
         let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory {});
         builder.init();
-        let input_values = vec!["cat", "dag", "dbg", "dcg", "ddg", "deg", "dog", "dogs"];
-        let output_values = vec![5, 7, 12, 13, 14, 15, 16, 17];
 
         let mut ints_ref_builder = IntsRefBuilder::new();
         for i in 0..input_values.len() {
@@ -1092,7 +1163,14 @@ mod test {
             assert!(res.is_ok());
         }
 
-        let fst: FST<ByteSequenceOutputFactory> = builder.finish().unwrap().unwrap();
+        builder.finish().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_fst() {
+        let input_values = vec!["cat", "dag", "dbg", "dcg", "ddg", "deg", "dog", "dogs"];
+        let output_values = vec![5, 7, 12, 13, 14, 15, 16, 17];
+        let fst = build_test_fst(&input_values, &output_values);
 
         // test get
         for i in 0..input_values.len() {
@@ -1105,4 +1183,34 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_off_heap_fst() {
+        let input_values = vec!["cat", "dag", "dbg", "dcg", "ddg", "deg", "dog", "dogs"];
+        let output_values = vec![5, 7, 12, 13, 14, 15, 16, 17];
+        let fst = build_test_fst(&input_values, &output_values);
+        let mut saved = vec![];
+        fst.save(&mut saved).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.fst");
+        let mut out = FSIndexOutput::new("test.fst".into(), &path).unwrap();
+        out.write_bytes(&saved, 0, saved.len()).unwrap();
+        out.write_int(42).unwrap();
+        out.flush().unwrap();
+
+        let mut input = MmapIndexInput::new(&path).unwrap();
+        let off_heap = FST::from_input_off_heap(&mut input, ByteSequenceOutputFactory {}).unwrap();
+        assert!(off_heap.is_off_heap());
+        assert_eq!(input.read_int().unwrap(), 42);
+        for (input_value, output_value) in input_values.iter().zip(output_values) {
+            let output = ByteSequenceOutput::new(vec![output_value as u8]);
+            assert_eq!(off_heap.get(input_value.as_bytes()).unwrap(), Some(output));
+        }
+        assert_eq!(off_heap.get(b"do").unwrap(), None);
+
+        let mut resaved = vec![];
+        off_heap.save(&mut resaved).unwrap();
+        assert_eq!(resaved, saved);
+    }
 }
@@ -31,11 +31,20 @@ mod fst_reader;
 
 pub use self::fst_reader::*;
 
+mod fst_map;
+
+pub use self::fst_map::{FSTMap, FSTMapBuilder};
+
+mod positive_int_output;
+
+pub use self::positive_int_output::*;
+
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::io;
+use std::sync;
 
-use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::store::io::{DataInput, DataOutput, RandomAccessInput};
 use crate::Result;
 
 pub trait Output: Clone + Eq + Hash + Debug {
@@ -188,6 +197,89 @@ impl DataInput for DirectionalBytesReader {
     }
 }
 
+/// The bytes of an FST left in a `RandomAccessInput` rather than copied to the heap.
+pub struct OffHeapFSTStore {
+    input: sync::Arc<dyn RandomAccessInput>,
+    num_bytes: usize,
+}
+
+impl OffHeapFSTStore {
+    pub fn new(input: Box<dyn RandomAccessInput>, num_bytes: usize) -> OffHeapFSTStore {
+        OffHeapFSTStore {
+            input: sync::Arc::from(input),
+            num_bytes,
+        }
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    pub fn reverse_reader(&self) -> ReverseRandomAccessReader {
+        ReverseRandomAccessReader::new(sync::Arc::clone(&self.input))
+    }
+
+    pub fn write_to(&self, out: &mut impl DataOutput) -> Result<()> {
+        for pos in 0..self.num_bytes {
+            out.write_byte(self.input.read_byte(pos as u64)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the bytes of an FST backwards straight from a `RandomAccessInput`,
+/// e.g. a slice of a memory mapped file, without copying them to the heap.
+pub struct ReverseRandomAccessReader {
+    input: sync::Arc<dyn RandomAccessInput>,
+    pos: usize,
+}
+
+impl ReverseRandomAccessReader {
+    pub fn new(input: sync::Arc<dyn RandomAccessInput>) -> ReverseRandomAccessReader {
+        ReverseRandomAccessReader { input, pos: 0 }
+    }
+}
+
+impl BytesReader for ReverseRandomAccessReader {
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn set_position(&mut self, pos: usize) {
+        self.pos = pos
+    }
+
+    fn reversed(&self) -> bool {
+        true
+    }
+}
+
+impl io::Read for ReverseRandomAccessReader {
+    fn read(&mut self, b: &mut [u8]) -> io::Result<usize> {
+        let len = b.len().min(self.pos.wrapping_add(1));
+        for v in b.iter_mut().take(len) {
+            *v = self
+                .input
+                .read_byte(self.pos as u64)
+                .map_err(|e| io::Error::new(io::ErrorKind::UnexpectedEof, e.to_string()))?;
+            self.pos = self.pos.wrapping_sub(1);
+        }
+        Ok(len)
+    }
+}
+
+impl DataInput for ReverseRandomAccessReader {
+    fn read_byte(&mut self) -> Result<u8> {
+        let b = self.input.read_byte(self.pos as u64)?;
+        self.pos = self.pos.wrapping_sub(1);
+        Ok(b)
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<()> {
+        self.pos = self.pos.wrapping_sub(count);
+        Ok(())
+    }
+}
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -0,0 +1,144 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::store::io::{DataInput, DataOutput};
+use crate::core::util::fst::{Output, OutputFactory};
+use crate::Result;
+
+use std::cmp::min;
+
+/// A non-negative `i64` output, the common prefix of two outputs is the
+/// smaller one, so the output of an arc is the minimum of all the outputs
+/// reachable through it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PositiveIntOutput {
+    value: i64,
+}
+
+impl PositiveIntOutput {
+    pub fn new(value: i64) -> PositiveIntOutput {
+        debug_assert!(value >= 0);
+        PositiveIntOutput { value }
+    }
+
+    pub fn empty() -> PositiveIntOutput {
+        PositiveIntOutput { value: 0 }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.value == 0
+    }
+}
+
+impl Output for PositiveIntOutput {
+    type Value = i64;
+
+    fn prefix(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        PositiveIntOutput::new(min(self.value, other.value))
+    }
+
+    fn cat(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        PositiveIntOutput::new(self.value + other.value)
+    }
+
+    fn concat(&mut self, other: &PositiveIntOutput) {
+        self.value += other.value;
+    }
+
+    fn subtract(&self, other: &PositiveIntOutput) -> PositiveIntOutput {
+        debug_assert!(self.value >= other.value);
+        PositiveIntOutput::new(self.value - other.value)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.value == 0
+    }
+
+    #[inline]
+    fn value(&self) -> i64 {
+        self.value
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct PositiveIntOutputFactory {}
+
+impl PositiveIntOutputFactory {
+    pub fn new() -> PositiveIntOutputFactory {
+        PositiveIntOutputFactory {}
+    }
+}
+
+impl OutputFactory for PositiveIntOutputFactory {
+    type Value = PositiveIntOutput;
+
+    fn empty(&self) -> Self::Value {
+        PositiveIntOutput::empty()
+    }
+
+    fn common(&self, o1: &PositiveIntOutput, o2: &PositiveIntOutput) -> PositiveIntOutput {
+        o1.prefix(o2)
+    }
+
+    fn subtract(&self, o1: &PositiveIntOutput, o2: &PositiveIntOutput) -> PositiveIntOutput {
+        o1.subtract(o2)
+    }
+
+    fn add(&self, prefix: &PositiveIntOutput, output: &PositiveIntOutput) -> PositiveIntOutput {
+        prefix.cat(output)
+    }
+
+    fn read<T: DataInput + ?Sized>(&self, data_in: &mut T) -> Result<PositiveIntOutput> {
+        Ok(PositiveIntOutput::new(data_in.read_vlong()?))
+    }
+
+    fn write<T: DataOutput + ?Sized>(
+        &self,
+        output: &PositiveIntOutput,
+        data_out: &mut T,
+    ) -> Result<()> {
+        data_out.write_vlong(output.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::core::util::fst::{FstBuilder, InputType};
+    use crate::core::util::{to_ints_ref, IntsRefBuilder};
+
+    #[test]
+    fn test_build_and_get() {
+        let factory = PositiveIntOutputFactory::new();
+        let mut builder = FstBuilder::new(InputType::Byte1, factory);
+        builder.init();
+        let mut scratch = IntsRefBuilder::new();
+        let entries: &[(&[u8], i64)] = &[(b"cat", 5), (b"cats", 3), (b"dog", 7), (b"dogs", 0)];
+        for (input, output) in entries {
+            builder
+                .add(
+                    to_ints_ref(input, &mut scratch),
+                    PositiveIntOutput::new(*output),
+                )
+                .unwrap();
+        }
+        let fst = builder.finish().unwrap().unwrap();
+        for (input, output) in entries {
+            assert_eq!(fst.get(input).unwrap().map(|o| o.value()), Some(*output));
+        }
+        assert!(fst.get(b"ca").unwrap().is_none());
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+/// Mean radius of the earth in meters, as defined by the WGS84 ellipsoid.
+pub const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.771_4;
+
+const LAT_SCALE: f64 = (1u64 << 32) as f64 / 180.0;
+const LAT_DECODE: f64 = 1.0 / LAT_SCALE;
+const LON_SCALE: f64 = (1u64 << 32) as f64 / 360.0;
+const LON_DECODE: f64 = 1.0 / LON_SCALE;
+
+/// Returns an error if the latitude is not in `[-90, 90]`.
+pub fn check_latitude(latitude: f64) -> Result<()> {
+    if latitude.is_nan() || !(-90.0..=90.0).contains(&latitude) {
+        return Err(IllegalArgument(format!(
+            "invalid latitude {}; must be between -90.0 and 90.0",
+            latitude
+        )));
+    }
+    Ok(())
+}
+
+/// Returns an error if the longitude is not in `[-180, 180]`.
+pub fn check_longitude(longitude: f64) -> Result<()> {
+    if longitude.is_nan() || !(-180.0..=180.0).contains(&longitude) {
+        return Err(IllegalArgument(format!(
+            "invalid longitude {}; must be between -180.0 and 180.0",
+            longitude
+        )));
+    }
+    Ok(())
+}
+
+/// Quantizes a latitude in `[-90, 90]` to a 32 bit integer, rounding down.
+pub fn encode_latitude(latitude: f64) -> i32 {
+    debug_assert!((-90.0..=90.0).contains(&latitude));
+    // 90.0 would overflow the integer range, it is encoded as the largest
+    // value below it instead
+    if latitude == 90.0 {
+        i32::MAX
+    } else {
+        (latitude / LAT_DECODE).floor() as i32
+    }
+}
+
+/// Quantizes a longitude in `[-180, 180]` to a 32 bit integer, rounding down.
+pub fn encode_longitude(longitude: f64) -> i32 {
+    debug_assert!((-180.0..=180.0).contains(&longitude));
+    if longitude == 180.0 {
+        i32::MAX
+    } else {
+        (longitude / LON_DECODE).floor() as i32
+    }
+}
+
+/// Turns a quantized latitude back into degrees.
+pub fn decode_latitude(encoded: i32) -> f64 {
+    f64::from(encoded) * LAT_DECODE
+}
+
+/// Turns a quantized longitude back into degrees.
+pub fn decode_longitude(encoded: i32) -> f64 {
+    f64::from(encoded) * LON_DECODE
+}
+
+/// Returns the great circle distance in meters between two points, using the
+/// haversine formula on a sphere of radius `EARTH_MEAN_RADIUS_METERS`.
+pub fn haversin_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_MEAN_RADIUS_METERS * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        for &(lat, lon) in &[
+            (0.0, 0.0),
+            (48.8566, 2.3522),
+            (-33.8688, 151.2093),
+            (-90.0, -180.0),
+            (90.0, 180.0),
+        ] {
+            let decoded_lat = decode_latitude(encode_latitude(lat));
+            let decoded_lon = decode_longitude(encode_longitude(lon));
+            assert!(decoded_lat <= lat && lat - decoded_lat < 1e-7);
+            assert!(decoded_lon <= lon && lon - decoded_lon < 1e-7);
+        }
+        assert!(check_latitude(90.5).is_err());
+        assert!(check_latitude(f64::NAN).is_err());
+        assert!(check_longitude(-180.5).is_err());
+        assert!(check_longitude(180.0).is_ok());
+    }
+
+    #[test]
+    fn test_haversin() {
+        assert_eq!(haversin_meters(10.0, 20.0, 10.0, 20.0), 0.0);
+        // one degree along the equator
+        let d = haversin_meters(0.0, 0.0, 0.0, 1.0);
+        assert!((d - 111_195.08).abs() < 1.0);
+        // paris to london is about 343.5 km
+        let d = haversin_meters(48.8566, 2.3522, 51.5074, -0.1278);
+        assert!((d - 343_500.0).abs() < 1_000.0);
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::util::Accountable;
+use crate::error::{Error::IllegalArgument, Result};
+
+use std::collections::HashSet;
+use std::mem;
+
+pub const HLL_MIN_PRECISION: u8 = 4;
+pub const HLL_MAX_PRECISION: u8 = 18;
+pub const HLL_DEFAULT_PRECISION: u8 = 14;
+
+/// empirical thresholds below which linear counting is more accurate than the
+/// raw estimate, indexed by `precision - HLL_MIN_PRECISION`. see the HLL++ paper.
+const LINEAR_COUNTING_THRESHOLDS: [u64; 15] = [
+    10, 20, 40, 80, 220, 400, 900, 1800, 3100, 6500, 11500, 20000, 50000, 120_000, 350_000,
+];
+
+enum Registers {
+    /// the exact set of hashes, used while the cardinality is small
+    Sparse(HashSet<u64>),
+    Dense(Vec<u8>),
+}
+
+/// A HyperLogLog++ sketch for approximate distinct counting.
+///
+/// the sketch starts with an exact (sparse) representation of the hashes
+/// and switches to `2^precision` one byte registers once the sparse set
+/// would use more memory than the registers. Values must be hashed with a
+/// good 64 bits hash function before added, see `murmur3_hash64` and
+/// `mix64`.
+pub struct HyperLogLogPlusPlus {
+    precision: u8,
+    registers: Registers,
+}
+
+impl Default for HyperLogLogPlusPlus {
+    fn default() -> Self {
+        HyperLogLogPlusPlus {
+            precision: HLL_DEFAULT_PRECISION,
+            registers: Registers::Sparse(HashSet::new()),
+        }
+    }
+}
+
+impl HyperLogLogPlusPlus {
+    pub fn new(precision: u8) -> Result<HyperLogLogPlusPlus> {
+        if !(HLL_MIN_PRECISION..=HLL_MAX_PRECISION).contains(&precision) {
+            return Err(IllegalArgument(format!(
+                "precision must be in [{}, {}], got {}",
+                HLL_MIN_PRECISION, HLL_MAX_PRECISION, precision
+            )));
+        }
+        Ok(HyperLogLogPlusPlus {
+            precision,
+            registers: Registers::Sparse(HashSet::new()),
+        })
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    #[inline]
+    fn num_registers(&self) -> usize {
+        1 << self.precision
+    }
+
+    #[inline]
+    fn sparse_threshold(&self) -> usize {
+        // each sparse entry takes ~8 bytes, each register one byte
+        self.num_registers() / 8
+    }
+
+    pub fn add_hash(&mut self, hash: u64) {
+        let threshold = self.sparse_threshold();
+        let to_dense = match &mut self.registers {
+            Registers::Sparse(set) => {
+                set.insert(hash);
+                set.len() > threshold
+            }
+            Registers::Dense(registers) => {
+                Self::add_to_registers(registers, self.precision, hash);
+                false
+            }
+        };
+        if to_dense {
+            self.convert_to_dense();
+        }
+    }
+
+    fn add_to_registers(registers: &mut [u8], precision: u8, hash: u64) {
+        let index = (hash >> (64 - precision)) as usize;
+        let w = (hash << precision) | (1 << (precision - 1));
+        let rank = w.leading_zeros() as u8 + 1;
+        if registers[index] < rank {
+            registers[index] = rank;
+        }
+    }
+
+    fn convert_to_dense(&mut self) {
+        let mut registers = vec![0u8; self.num_registers()];
+        if let Registers::Sparse(set) = &self.registers {
+            for hash in set {
+                Self::add_to_registers(&mut registers, self.precision, *hash);
+            }
+        }
+        self.registers = Registers::Dense(registers);
+    }
+
+    /// Merges the other sketch into this one, both sketches must have the
+    /// same precision.
+    pub fn merge(&mut self, other: HyperLogLogPlusPlus) -> Result<()> {
+        if self.precision != other.precision {
+            return Err(IllegalArgument(format!(
+                "can't merge a sketch of precision {} into one of precision {}",
+                other.precision, self.precision
+            )));
+        }
+        match other.registers {
+            Registers::Sparse(set) => {
+                for hash in set {
+                    self.add_hash(hash);
+                }
+            }
+            Registers::Dense(other_registers) => {
+                if let Registers::Sparse(_) = self.registers {
+                    self.convert_to_dense();
+                }
+                if let Registers::Dense(registers) = &mut self.registers {
+                    for (r, o) in registers.iter_mut().zip(other_registers) {
+                        if *r < o {
+                            *r = o;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the estimated number of distinct hashes added.
+    pub fn cardinality(&self) -> u64 {
+        match &self.registers {
+            Registers::Sparse(set) => set.len() as u64,
+            Registers::Dense(registers) => {
+                let m = registers.len() as f64;
+                let mut zeros = 0;
+                let mut inverse_sum = 0.0;
+                for r in registers {
+                    if *r == 0 {
+                        zeros += 1;
+                    }
+                    inverse_sum += 1.0 / (1u64 << *r) as f64;
+                }
+                if zeros > 0 {
+                    let linear = (m * (m / zeros as f64).ln()).round() as u64;
+                    let idx = (self.precision - HLL_MIN_PRECISION) as usize;
+                    if linear <= LINEAR_COUNTING_THRESHOLDS[idx] {
+                        return linear;
+                    }
+                }
+                let alpha = match registers.len() {
+                    16 => 0.673,
+                    32 => 0.697,
+                    64 => 0.709,
+                    _ => 0.7213 / (1.0 + 1.079 / m),
+                };
+                (alpha * m * m / inverse_sum).round() as u64
+            }
+        }
+    }
+}
+
+impl Accountable for HyperLogLogPlusPlus {
+    /// Returns the approximate memory used by this sketch in bytes.
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>()
+            + match &self.registers {
+                Registers::Sparse(set) => set.capacity() * mem::size_of::<u64>(),
+                Registers::Dense(registers) => registers.len(),
+            }
+    }
+}
+
+/// The finalization mix of murmur3, a good hash for long values.
+#[inline]
+pub fn mix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+/// The first 64 bits of the murmur3 x64 128 bits hash of the bytes.
+pub fn murmur3_hash64(bytes: &[u8], seed: u64) -> u64 {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    let mut h1 = seed;
+    let mut h2 = seed;
+    let len = bytes.len();
+    let mut chunks = bytes.chunks_exact(16);
+    for chunk in &mut chunks {
+        let mut k1 = u64::from_le_bytes(chunk[0..8].try_into().unwrap());
+        let mut k2 = u64::from_le_bytes(chunk[8..16].try_into().unwrap());
+
+        k1 = k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+
+        k2 = k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 ^= k2;
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = chunks.remainder();
+    let mut k1 = 0u64;
+    let mut k2 = 0u64;
+    for (i, b) in tail.iter().enumerate() {
+        if i < 8 {
+            k1 |= (*b as u64) << (i * 8);
+        } else {
+            k2 |= (*b as u64) << ((i - 8) * 8);
+        }
+    }
+    if tail.len() > 8 {
+        k2 = k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 ^= k2;
+    }
+    if !tail.is_empty() {
+        k1 = k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 ^= k1;
+    }
+
+    h1 ^= len as u64;
+    h2 ^= len as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = mix64(h1);
+    h2 = mix64(h2);
+    h1.wrapping_add(h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_is_exact() {
+        let mut hll = HyperLogLogPlusPlus::default();
+        for i in 0..1000u64 {
+            hll.add_hash(mix64(i % 500));
+        }
+        assert_eq!(hll.cardinality(), 500);
+    }
+
+    #[test]
+    fn test_dense_estimate() {
+        let mut hll = HyperLogLogPlusPlus::new(HLL_DEFAULT_PRECISION).unwrap();
+        let n = 200_000u64;
+        for i in 0..n {
+            hll.add_hash(mix64(i));
+        }
+        let estimate = hll.cardinality() as f64;
+        assert!((estimate - n as f64).abs() / (n as f64) < 0.05);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = HyperLogLogPlusPlus::new(10).unwrap();
+        let mut b = HyperLogLogPlusPlus::new(10).unwrap();
+        for i in 0..50_000u64 {
+            a.add_hash(murmur3_hash64(format!("a{}", i).as_bytes(), 0));
+            b.add_hash(murmur3_hash64(format!("a{}", i + 25_000).as_bytes(), 0));
+        }
+        a.merge(b).unwrap();
+        let estimate = a.cardinality() as f64;
+        assert!((estimate - 75_000.0).abs() / 75_000.0 < 0.1);
+
+        let other = HyperLogLogPlusPlus::new(12).unwrap();
+        assert!(a.merge(other).is_err());
+    }
+
+    #[test]
+    fn test_invalid_precision() {
+        assert!(HyperLogLogPlusPlus::new(HLL_MIN_PRECISION - 1).is_err());
+        assert!(HyperLogLogPlusPlus::new(HLL_MAX_PRECISION + 1).is_err());
+        assert!(HyperLogLogPlusPlus::new(HLL_MAX_PRECISION).is_ok());
+    }
+}
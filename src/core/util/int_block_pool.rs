@@ -13,6 +13,8 @@
 
 use crate::core::util::fill_slice;
 
+use std::mem;
+
 pub const INT_BLOCK_SHIFT: usize = 13;
 pub const INT_BLOCK_SIZE: usize = 1 << INT_BLOCK_SHIFT;
 pub const INT_BLOCK_MASK: usize = INT_BLOCK_SIZE - 1;
@@ -129,6 +131,11 @@ impl IntBlockPool {
         }
     }
 
+    /// The number of bytes of the buffers allocated so far.
+    pub fn bytes_used(&self) -> usize {
+        (self.buffer_upto + 1) as usize * INT_BLOCK_SIZE * mem::size_of::<i32>()
+    }
+
     pub fn current_buffer(&mut self) -> &mut [i32] {
         debug_assert!(self.buffer_upto >= 0);
         &mut self.buffers[self.buffer_upto as usize]
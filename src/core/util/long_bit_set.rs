@@ -0,0 +1,326 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::util::bit_util::{pop_array, UnsignedShift};
+
+/// BitSet of fixed length (num_bits), backed by accessible ({@link #getBits})
+/// Vec<i64>, accessed with a long index. Use it only if you intend to store more
+/// than 2.1B bits, otherwise you should use `FixedBitSet`.
+///
+/// @lucene.internal
+#[derive(Clone, Debug)]
+pub struct LongBitSet {
+    // Array of longs holding the bits
+    bits: Vec<i64>,
+    // The number of bits in use
+    num_bits: i64,
+    num_words: usize,
+}
+
+impl LongBitSet {
+    /// returns the number of 64 bit words it would take to hold numBits
+    pub fn bits2words(num_bits: i64) -> usize {
+        // I.e.: get the word-offset of the last bit and add one (make sure to use >> so 0 returns
+        // 0!)
+        (((num_bits - 1) >> 6) + 1) as usize
+    }
+
+    pub fn new(num_bits: i64) -> LongBitSet {
+        let num_words = LongBitSet::bits2words(num_bits);
+        LongBitSet {
+            bits: vec![0; num_words],
+            num_bits,
+            num_words,
+        }
+    }
+
+    /// If the given `LongBitSet` is large enough to hold `num_bits + 1`,
+    /// returns it, otherwise returns a new `LongBitSet` with the bits of
+    /// the given one and some room to grow.
+    pub fn ensure_capacity(mut bits: LongBitSet, num_bits: i64) -> LongBitSet {
+        if num_bits < bits.num_bits {
+            return bits;
+        }
+        // Depends on the ghost bits being clear!
+        // (Otherwise, they may become visible in the new instance)
+        let num_words = LongBitSet::bits2words(num_bits);
+        if num_words >= bits.bits.len() {
+            bits.bits.resize(num_words + (num_words >> 3) + 1, 0);
+        }
+        let num_bits = (bits.bits.len() as i64) << 6;
+        LongBitSet {
+            num_words: LongBitSet::bits2words(num_bits),
+            bits: bits.bits,
+            num_bits,
+        }
+    }
+
+    /// Returns the number of bits stored in this bitset.
+    pub fn len(&self) -> i64 {
+        self.num_bits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_bits == 0
+    }
+
+    /// Expert.
+    pub fn bits(&self) -> &[i64] {
+        &self.bits
+    }
+
+    /// Returns number of set bits.  NOTE: this visits every long in the
+    /// backing bits array, and the result is not internally cached.
+    pub fn cardinality(&self) -> usize {
+        pop_array(&self.bits, 0, self.num_words)
+    }
+
+    pub fn get(&self, index: i64) -> bool {
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let word_num = (index >> 6) as usize;
+        // signed shift will keep a negative index and force an
+        // array-index-out-of-bounds-exception, removing the need for an explicit check.
+        let mask = 1i64 << (index & 0x3fi64);
+        (self.bits[word_num] & mask) != 0
+    }
+
+    pub fn set(&mut self, index: i64) {
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let word_num = (index >> 6) as usize;
+        let mask = 1i64 << (index & 0x3fi64);
+        unsafe {
+            *self.bits.as_mut_ptr().add(word_num) |= mask;
+        }
+    }
+
+    /// Sets the bit and returns whether it was set before.
+    pub fn get_and_set(&mut self, index: i64) -> bool {
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let word_num = (index >> 6) as usize;
+        let mask = 1i64 << (index & 0x3fi64);
+        let val = (self.bits[word_num] & mask) != 0;
+        self.bits[word_num] |= mask;
+        val
+    }
+
+    pub fn clear(&mut self, index: i64) {
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let word_num = (index >> 6) as usize;
+        let mask = 1i64 << (index & 0x3fi64);
+        unsafe {
+            *self.bits.as_mut_ptr().add(word_num) &= !mask;
+        }
+    }
+
+    /// Returns the index of the first set bit starting at the index specified.
+    /// -1 is returned if there are no more set bits.
+    pub fn next_set_bit(&self, index: i64) -> i64 {
+        // Depends on the ghost bits being clear!
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let mut i = (index >> 6) as usize;
+        // skip all the bits to the right of index
+        let word = self.bits[i] >> (index & 0x3f);
+        if word != 0 {
+            return index + i64::from(word.trailing_zeros());
+        }
+        i += 1;
+        while i < self.num_words {
+            let word = self.bits[i];
+            if word != 0 {
+                return ((i as i64) << 6) + i64::from(word.trailing_zeros());
+            }
+            i += 1;
+        }
+        -1
+    }
+
+    /// Returns the index of the last set bit before or on the index specified.
+    /// -1 is returned if there are no more set bits.
+    pub fn prev_set_bit(&self, index: i64) -> i64 {
+        debug_assert!(index >= 0 && index < self.num_bits);
+        let mut i = (index >> 6) as usize;
+        let sub_index = index & 0x3f; // index within the word
+                                      // skip all the bits to the left of index
+        let word = self.bits[i] << (63 - sub_index);
+        if word != 0 {
+            return ((i as i64) << 6) + sub_index - i64::from(word.leading_zeros());
+        }
+        while i > 0 {
+            i -= 1;
+            let word = self.bits[i];
+            if word != 0 {
+                return ((i as i64) << 6) + 63 - i64::from(word.leading_zeros());
+            }
+        }
+        -1
+    }
+
+    /// this = this OR other
+    pub fn or(&mut self, other: &LongBitSet) {
+        debug_assert!(other.num_words <= self.num_words);
+        for (word, other) in self.bits.iter_mut().zip(&other.bits[..other.num_words]) {
+            *word |= *other;
+        }
+    }
+
+    /// this = this XOR other
+    pub fn xor(&mut self, other: &LongBitSet) {
+        debug_assert!(other.num_words <= self.num_words);
+        for (word, other) in self.bits.iter_mut().zip(&other.bits[..other.num_words]) {
+            *word ^= *other;
+        }
+    }
+
+    /// returns true if the sets have any elements in common
+    pub fn intersects(&self, other: &LongBitSet) -> bool {
+        // Depends on the ghost bits being clear!
+        let pos = self.num_words.min(other.num_words);
+        (0..pos).any(|i| (self.bits[i] & other.bits[i]) != 0)
+    }
+
+    /// this = this AND other
+    pub fn and(&mut self, other: &LongBitSet) {
+        let pos = self.num_words.min(other.num_words);
+        for i in 0..pos {
+            self.bits[i] &= other.bits[i];
+        }
+        for word in &mut self.bits[pos..self.num_words] {
+            *word = 0;
+        }
+    }
+
+    /// this = this AND NOT other
+    pub fn and_not(&mut self, other: &LongBitSet) {
+        let pos = self.num_words.min(other.num_words);
+        for i in 0..pos {
+            self.bits[i] &= !other.bits[i];
+        }
+    }
+
+    /// Flips a range of bits
+    ///
+    /// @param start_index lower index
+    /// @param end_index one-past the last bit to flip
+    pub fn flip_range(&mut self, start_index: i64, end_index: i64) {
+        self.apply_range(start_index, end_index, |word, mask| *word ^= mask);
+    }
+
+    /// Sets a range of bits
+    ///
+    /// @param start_index lower index
+    /// @param end_index one-past the last bit to set
+    pub fn set_range(&mut self, start_index: i64, end_index: i64) {
+        self.apply_range(start_index, end_index, |word, mask| *word |= mask);
+    }
+
+    /// Clears a range of bits.
+    ///
+    /// @param start_index lower index
+    /// @param end_index one-past the last bit to clear
+    pub fn clear_range(&mut self, start_index: i64, end_index: i64) {
+        self.apply_range(start_index, end_index, |word, mask| *word &= !mask);
+    }
+
+    fn apply_range(&mut self, start_index: i64, end_index: i64, op: impl Fn(&mut i64, i64)) {
+        debug_assert!(start_index >= 0 && start_index <= self.num_bits);
+        debug_assert!(end_index >= 0 && end_index <= self.num_bits);
+        if end_index <= start_index {
+            return;
+        }
+        let start_word = (start_index >> 6) as usize;
+        let end_word = ((end_index - 1) >> 6) as usize;
+
+        let start_mask = -1i64 << (start_index & 0x3f);
+        // 64-(end_index&0x3f) is the same as -end_index since only the lowest 6 bits are used
+        let end_mask = (-1i64).unsigned_shift(((64 - (end_index & 0x3f)) & 0x3f) as usize);
+
+        if start_word == end_word {
+            op(&mut self.bits[start_word], start_mask & end_mask);
+            return;
+        }
+        op(&mut self.bits[start_word], start_mask);
+        for word in &mut self.bits[start_word + 1..end_word] {
+            op(word, -1i64);
+        }
+        op(&mut self.bits[end_word], end_mask);
+    }
+
+    /// Clears all the bits.
+    pub fn clear_all(&mut self) {
+        for word in &mut self.bits {
+            *word = 0;
+        }
+    }
+}
+
+impl PartialEq for LongBitSet {
+    fn eq(&self, other: &Self) -> bool {
+        // Depends on the ghost bits being clear!
+        self.num_bits == other.num_bits
+            && self.bits[..self.num_words] == other.bits[..other.num_words]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_bit_set() {
+        let num_bits = (1i64 << 20) + 100;
+        let mut bits = LongBitSet::new(num_bits);
+        assert_eq!(bits.len(), num_bits);
+        assert_eq!(bits.next_set_bit(0), -1);
+
+        let high = (1i64 << 20) + 3;
+        bits.set(5);
+        bits.set(1 << 19);
+        assert!(!bits.get_and_set(high));
+        assert!(bits.get_and_set(high));
+        assert_eq!(bits.cardinality(), 3);
+        assert_eq!(bits.next_set_bit(6), 1 << 19);
+        assert_eq!(bits.next_set_bit((1 << 19) + 1), high);
+        assert_eq!(bits.next_set_bit(high + 1), -1);
+        assert_eq!(bits.prev_set_bit(num_bits - 1), high);
+        assert_eq!(bits.prev_set_bit(high - 1), 1 << 19);
+        assert_eq!(bits.prev_set_bit(4), -1);
+
+        bits.set_range(high - 10, high + 70);
+        assert_eq!(bits.cardinality(), 82);
+        bits.clear_range(high - 5, high + 60);
+        assert_eq!(bits.cardinality(), 17);
+        assert!(bits.get(high - 6) && !bits.get(high - 5) && bits.get(high + 60));
+        bits.clear(high - 6);
+        bits.flip_range(0, 10);
+        assert!(!bits.get(5) && bits.get(4));
+        assert_eq!(bits.cardinality(), 24);
+
+        let mut other = LongBitSet::new(num_bits);
+        other.set(1 << 19);
+        other.set(7);
+        assert!(bits.intersects(&other));
+        let mut and = bits.clone();
+        and.and(&other);
+        assert_eq!(and.cardinality(), 2);
+        bits.and_not(&other);
+        assert!(!bits.intersects(&other));
+        bits.or(&other);
+        assert_eq!(bits.cardinality(), 24);
+        bits.clear_all();
+        assert_eq!(bits.next_set_bit(0), -1);
+
+        let grown = LongBitSet::ensure_capacity(LongBitSet::new(10), 100);
+        assert!(grown.len() > 100);
+        assert_eq!(grown.cardinality(), 0);
+    }
+}
@@ -26,13 +26,19 @@ pub use numeric::{
     sortable_int2float, sortable_long2double, to_base36, Numeric,
 };
 
+mod accountable;
+
+pub use accountable::{human_readable_units, Accountable, NamedAccountable};
+
 mod variant_value;
 
 pub use variant_value::VariantValue;
 
 mod bits;
 
-pub use bits::{Bits, BitsMut, BitsRef, LiveBits, MatchAllBits, MatchNoBits, SparseBits};
+pub use bits::{
+    Bits, BitsMut, BitsRef, LiveBits, MatchAllBits, MatchNoBits, SparseBits, SparseJumpTable,
+};
 
 mod version;
 
@@ -44,25 +50,44 @@ pub use paged_bytes::{PagedBytes, PagedBytesDataInput, PagedBytesReader};
 
 mod doc_id_set_builder;
 
-pub use doc_id_set_builder::DocIdSetBuilder;
+pub use doc_id_set_builder::{AdaptiveDocIdSetBuilder, DocIdSetBuilder};
 
 mod context;
 
 pub use context::{IndexedContext, KeyedContext};
 
 mod bytes_ref;
-mod counter;
 
 pub use bytes_ref::{BytesRef, BytesRefBuilder};
 
+mod counter;
+
+pub use counter::Counter;
+
 mod bit_set;
 
 pub use bit_set::{bits2words, BitSet, BitSetIterator, FixedBitSet, ImmutableBitSet};
 
+mod long_bit_set;
+
+pub use long_bit_set::LongBitSet;
+
+mod sparse_fixed_bit_set;
+
+pub use sparse_fixed_bit_set::SparseFixedBitSet;
+
+mod priority_queue;
+
+pub use priority_queue::{DrainSorted, PriorityQueue};
+
 mod bit_util;
 
 pub use bit_util::{BitsRequired, UnsignedShift, ZigZagEncoding};
 
+mod bloom_filter;
+
+pub use bloom_filter::BloomFilter;
+
 mod byte_block_pool;
 
 pub use byte_block_pool::{ByteBlockAllocator, ByteBlockPool, DirectTrackingAllocator};
@@ -73,7 +98,9 @@ pub(crate) use byte_slice_reader::ByteSliceReader;
 
 mod bytes_ref_hash;
 
-pub use bytes_ref_hash::{BytesRefHash, BytesStartArray, DirectByteStartArray, DEFAULT_CAPACITY};
+pub use bytes_ref_hash::{
+    BytesRefHash, BytesStartArray, DirectByteStartArray, SortedBytesRefIter, DEFAULT_CAPACITY,
+};
 
 mod doc_id_set;
 
@@ -82,6 +109,20 @@ pub use doc_id_set::{
     ShortArrayDocIdSet,
 };
 
+mod roaring_doc_id_set;
+
+pub use roaring_doc_id_set::{
+    RoaringDocIdSet, RoaringDocIdSetBuilder, RoaringDocIterator, ROARING_BLOCK_SIZE,
+    ROARING_MAX_ARRAY_LENGTH,
+};
+
+mod hyper_log_log;
+
+pub use hyper_log_log::{
+    mix64, murmur3_hash64, HyperLogLogPlusPlus, HLL_DEFAULT_PRECISION, HLL_MAX_PRECISION,
+    HLL_MIN_PRECISION,
+};
+
 mod int_block_pool;
 
 pub use int_block_pool::{
@@ -96,6 +137,13 @@ mod math;
 
 pub use math::{gcd, log, long_to_int_exact};
 
+mod geo_utils;
+
+pub use geo_utils::{
+    check_latitude, check_longitude, decode_latitude, decode_longitude, encode_latitude,
+    encode_longitude, haversin_meters, EARTH_MEAN_RADIUS_METERS,
+};
+
 mod selector;
 
 mod small_float;
@@ -116,7 +164,14 @@ pub use compression::{Compress, CompressionMode, Compressor, Decompress, Decompr
 
 mod disi;
 
-pub use disi::DisiPriorityQueue;
+pub use disi::{DisiPriorityQueue, DisiWrapper};
+
+mod offline_sorter;
+
+pub use offline_sorter::{
+    ByteSequencesReader, ByteSequencesWriter, OfflineSorter, DEFAULT_MAX_TEMP_FILES,
+    DEFAULT_SORT_BUFFER_BYTES,
+};
 
 use std::ops::Deref;
 
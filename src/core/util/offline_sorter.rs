@@ -0,0 +1,396 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::codec::{check_footer, footer_length, write_footer};
+use crate::core::store::directory::Directory;
+use crate::core::store::io::{BufferedChecksumIndexInput, DataInput, IndexInput, IndexOutput};
+use crate::core::store::IOContext;
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::cmp::Ordering;
+
+/// The default RAM used by `OfflineSorter` to sort a partition of the input.
+pub const DEFAULT_SORT_BUFFER_BYTES: usize = 16 * 1024 * 1024;
+
+/// The default maximum number of sorted partitions merged at once.
+pub const DEFAULT_MAX_TEMP_FILES: usize = 10;
+
+/// Sorts a file of byte records which might not fit in RAM.
+///
+/// The input is read in partitions of about the RAM buffer size, each one is
+/// sorted in memory and written to a temp file of the directory, then the
+/// partitions are merged, at most `max_temp_files` at a time. Records are
+/// either length prefixed, or all of the same length, e.g. the points spilled
+/// by `BKDWriter`. Files are read and written with `ByteSequencesReader` and
+/// `ByteSequencesWriter`, and end with a codec footer.
+///
+/// The intermediate temp files are deleted once merged, or when the sort
+/// fails; the sorted output is left to the caller.
+pub struct OfflineSorter<'a, D: Directory, F: Fn(&[u8], &[u8]) -> Ordering> {
+    dir: &'a D,
+    temp_file_name_prefix: String,
+    comparator: F,
+    buffer_bytes: usize,
+    max_temp_files: usize,
+    value_length: Option<usize>,
+}
+
+impl<'a, D: Directory, F: Fn(&[u8], &[u8]) -> Ordering> OfflineSorter<'a, D, F> {
+    pub fn new(dir: &'a D, temp_file_name_prefix: &str, comparator: F) -> Self {
+        OfflineSorter {
+            dir,
+            temp_file_name_prefix: temp_file_name_prefix.to_string(),
+            comparator,
+            buffer_bytes: DEFAULT_SORT_BUFFER_BYTES,
+            max_temp_files: DEFAULT_MAX_TEMP_FILES,
+            value_length: None,
+        }
+    }
+
+    pub fn with_buffer_bytes(mut self, buffer_bytes: usize) -> Self {
+        self.buffer_bytes = buffer_bytes.max(1);
+        self
+    }
+
+    pub fn with_max_temp_files(mut self, max_temp_files: usize) -> Result<Self> {
+        if max_temp_files < 2 {
+            return Err(IllegalArgument(format!(
+                "max_temp_files must be >= 2, got {}",
+                max_temp_files
+            )));
+        }
+        self.max_temp_files = max_temp_files;
+        Ok(self)
+    }
+
+    /// Sorts records of exactly `value_length` bytes written back to back,
+    /// instead of length prefixed ones.
+    pub fn with_value_length(mut self, value_length: usize) -> Self {
+        self.value_length = Some(value_length);
+        self
+    }
+
+    pub fn value_length(&self) -> Option<usize> {
+        self.value_length
+    }
+
+    /// Sorts the records of the file `input_name` into a new temp file,
+    /// returning its name. The input file is left as is.
+    pub fn sort(&self, input_name: &str) -> Result<String> {
+        let mut temp_files = TempFiles {
+            dir: self.dir,
+            names: vec![],
+        };
+        let mut partitions: Vec<String> = vec![];
+        let mut input = self.reader(input_name)?;
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        loop {
+            data.clear();
+            offsets.clear();
+            while data.len() < self.buffer_bytes && input.next()? {
+                offsets.push((data.len(), input.value().len()));
+                data.extend_from_slice(input.value());
+            }
+            if offsets.is_empty() && !partitions.is_empty() {
+                break;
+            }
+            offsets.sort_unstable_by(|&(s1, l1), &(s2, l2)| {
+                (self.comparator)(&data[s1..s1 + l1], &data[s2..s2 + l2])
+            });
+
+            let mut writer = self.create_temp_writer(&mut temp_files, "sort")?;
+            for &(start, length) in &offsets {
+                writer.write(&data[start..start + length])?;
+            }
+            partitions.push(writer.finish()?);
+
+            if partitions.len() == self.max_temp_files {
+                let merged = self.merge(&partitions, &mut temp_files)?;
+                partitions = vec![merged];
+            }
+            if offsets.is_empty() {
+                break;
+            }
+        }
+        if partitions.len() > 1 {
+            let merged = self.merge(&partitions, &mut temp_files)?;
+            partitions = vec![merged];
+        }
+
+        let sorted = partitions.pop().unwrap();
+        temp_files.names.retain(|name| name != &sorted);
+        Ok(sorted)
+    }
+
+    /// Opens a reader of a file with the records of this sorter.
+    pub fn reader(&self, name: &str) -> Result<ByteSequencesReader> {
+        ByteSequencesReader::new(self.dir, name, self.value_length)
+    }
+
+    /// Creates a temp file of the directory to write the records to sort,
+    /// which is left to the caller to delete.
+    pub fn input_writer(&self) -> Result<ByteSequencesWriter<D::TempOutput>> {
+        let output = self.dir.create_temp_output(
+            &self.temp_file_name_prefix,
+            "input",
+            &IOContext::Default,
+        )?;
+        Ok(ByteSequencesWriter::new(output, self.value_length))
+    }
+
+    fn create_temp_writer(
+        &self,
+        temp_files: &mut TempFiles<D>,
+        suffix: &str,
+    ) -> Result<ByteSequencesWriter<D::TempOutput>> {
+        let output = self.dir.create_temp_output(
+            &self.temp_file_name_prefix,
+            suffix,
+            &IOContext::Default,
+        )?;
+        temp_files.names.push(output.name().to_string());
+        Ok(ByteSequencesWriter::new(output, self.value_length))
+    }
+
+    // merges the sorted `partitions` into a new temp file and deletes them
+    fn merge(&self, partitions: &[String], temp_files: &mut TempFiles<D>) -> Result<String> {
+        let mut writer = self.create_temp_writer(temp_files, "merge")?;
+        {
+            let mut readers = Vec::with_capacity(partitions.len());
+            for name in partitions {
+                let mut reader = self.reader(name)?;
+                if reader.next()? {
+                    readers.push(reader);
+                }
+            }
+            // there are at most `max_temp_files` readers, a linear scan finds
+            // the smallest one fast enough
+            while !readers.is_empty() {
+                let mut min = 0;
+                for i in 1..readers.len() {
+                    if (self.comparator)(readers[i].value(), readers[min].value()) == Ordering::Less
+                    {
+                        min = i;
+                    }
+                }
+                writer.write(readers[min].value())?;
+                if !readers[min].next()? {
+                    readers.swap_remove(min);
+                }
+            }
+        }
+        let merged = writer.finish()?;
+        for name in partitions {
+            self.dir.delete_file(name)?;
+            temp_files.names.retain(|n| n != name);
+        }
+        Ok(merged)
+    }
+}
+
+// Deletes the temp files of a sort which are still listed when dropped.
+struct TempFiles<'a, D: Directory> {
+    dir: &'a D,
+    names: Vec<String>,
+}
+
+impl<'a, D: Directory> Drop for TempFiles<'a, D> {
+    fn drop(&mut self) {
+        for name in &self.names {
+            if let Err(e) = self.dir.delete_file(name) {
+                log::warn!("failed to delete temp file {}: {:?}", name, e);
+            }
+        }
+    }
+}
+
+/// Writes the records sorted by `OfflineSorter`.
+pub struct ByteSequencesWriter<O: IndexOutput> {
+    output: O,
+    value_length: Option<usize>,
+}
+
+impl<O: IndexOutput> ByteSequencesWriter<O> {
+    /// Creates a writer of records of `value_length` bytes, or of length
+    /// prefixed ones if `None`.
+    pub fn new(output: O, value_length: Option<usize>) -> Self {
+        ByteSequencesWriter {
+            output,
+            value_length,
+        }
+    }
+
+    pub fn write(&mut self, value: &[u8]) -> Result<()> {
+        match self.value_length {
+            Some(length) if length != value.len() => {
+                return Err(IllegalArgument(format!(
+                    "record of {} bytes, expected {}",
+                    value.len(),
+                    length
+                )));
+            }
+            Some(_) => {}
+            None => self.output.write_vint(value.len() as i32)?,
+        }
+        self.output.write_bytes(value, 0, value.len())
+    }
+
+    /// The name of the file written.
+    pub fn name(&self) -> &str {
+        self.output.name()
+    }
+
+    /// Writes the footer, returning the name of the file.
+    pub fn finish(mut self) -> Result<String> {
+        write_footer(&mut self.output)?;
+        Ok(self.output.name().to_string())
+    }
+}
+
+/// Reads the records written by `ByteSequencesWriter`, verifying the
+/// checksum of the file once all of them are read.
+pub struct ByteSequencesReader {
+    input: BufferedChecksumIndexInput,
+    end: i64,
+    value_length: Option<usize>,
+    value: Vec<u8>,
+}
+
+impl ByteSequencesReader {
+    pub fn new<D: Directory>(dir: &D, name: &str, value_length: Option<usize>) -> Result<Self> {
+        let end = dir.file_length(name)? - footer_length() as i64;
+        let input = dir.open_checksum_input(name, &IOContext::READ_ONCE)?;
+        Ok(ByteSequencesReader {
+            input,
+            end,
+            value_length,
+            value: Vec::new(),
+        })
+    }
+
+    /// Moves to the next record, returns false once all are read.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<bool> {
+        let fp = self.input.file_pointer();
+        if fp >= self.end {
+            if fp == self.end {
+                check_footer(&mut self.input)?;
+                // only check once
+                self.end = -1;
+            }
+            return Ok(false);
+        }
+        let length = match self.value_length {
+            Some(length) => length,
+            None => self.input.read_vint()? as usize,
+        };
+        self.value.resize(length, 0);
+        self.input
+            .read_bytes(&mut self.value, 0, length)
+            .map(|_| true)
+    }
+
+    /// The current record.
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::ByteBuffersDirectory;
+
+    fn write_input(
+        dir: &ByteBuffersDirectory,
+        values: &[Vec<u8>],
+        value_length: Option<usize>,
+    ) -> String {
+        let output = dir
+            .create_temp_output("_0", "input", &IOContext::Default)
+            .unwrap();
+        let mut writer = ByteSequencesWriter::new(output, value_length);
+        for value in values {
+            writer.write(value).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn read_all(
+        sorter: &OfflineSorter<ByteBuffersDirectory, impl Fn(&[u8], &[u8]) -> Ordering>,
+        name: &str,
+    ) -> Vec<Vec<u8>> {
+        let mut reader = sorter.reader(name).unwrap();
+        let mut values = vec![];
+        while reader.next().unwrap() {
+            values.push(reader.value().to_vec());
+        }
+        values
+    }
+
+    #[test]
+    fn test_sort_variable_length() -> Result<()> {
+        let dir = ByteBuffersDirectory::new();
+        let values: Vec<Vec<u8>> = (0..1000u32)
+            .map(|i| format!("{}", (i * 7919) % 1000).into_bytes())
+            .collect();
+        let input = write_input(&dir, &values, None);
+
+        // small partitions and merges of 3 files force several merge rounds
+        let sorter = OfflineSorter::new(&dir, "_0", |a: &[u8], b: &[u8]| a.cmp(b))
+            .with_buffer_bytes(100)
+            .with_max_temp_files(3)?;
+        let sorted = sorter.sort(&input)?;
+
+        let mut expected = values.clone();
+        expected.sort();
+        assert_eq!(read_all(&sorter, &sorted), expected);
+        let mut files = dir.list_all()?;
+        files.sort();
+        let mut remaining = vec![input, sorted];
+        remaining.sort();
+        assert_eq!(files, remaining);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_fixed_length() -> Result<()> {
+        let dir = ByteBuffersDirectory::new();
+        // sorts by the second int, like `BKDWriter` sorts by one dimension
+        let values: Vec<Vec<u8>> = (0..500i32)
+            .map(|i| {
+                let mut value = i.to_be_bytes().to_vec();
+                value.extend_from_slice(&((i * 31) % 97).to_be_bytes());
+                value
+            })
+            .collect();
+        let input = write_input(&dir, &values, Some(8));
+        let sorter = OfflineSorter::new(&dir, "_0", |a: &[u8], b: &[u8]| {
+            a[4..].cmp(&b[4..]).then_with(|| a[..4].cmp(&b[..4]))
+        })
+        .with_value_length(8)
+        .with_buffer_bytes(256);
+        let sorted = read_all(&sorter, &sorter.sort(&input)?);
+
+        let mut expected = values.clone();
+        expected.sort_by(|a, b| a[4..].cmp(&b[4..]).then_with(|| a[..4].cmp(&b[..4])));
+        assert_eq!(sorted, expected);
+
+        // an empty input gives an empty output
+        let empty = write_input(&dir, &[], Some(8));
+        assert!(read_all(&sorter, &sorter.sort(&empty)?).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64 as simd;
+
+/// The widest values decoded by `SIMDBulkDecoder`: a value and its bit offset
+/// in its first byte must fit in the 32 bits loaded for each value.
+pub const SIMD_DECODE_MAX_BITS: usize = 24;
+
+/// Decodes the big endian packed values written by `BulkOperationPacked` 8 at
+/// a time with AVX2.
+///
+/// 8 values always take `bits_per_value` bytes, so the bytes holding each of
+/// them and the shift extracting it are the same for all the groups of 8
+/// values. The lower and upper 4 values of a group are each gathered from a
+/// 16 bytes load.
+#[derive(Clone, Debug)]
+pub struct SIMDBulkDecoder {
+    bits_per_value: usize,
+    // offset of the first byte of the upper 4 values in a group
+    upper_offset: usize,
+    shuffle: [i8; 32],
+    shifts: [i32; 8],
+    mask: i32,
+}
+
+impl SIMDBulkDecoder {
+    /// Returns `None` if the values are wider than `SIMD_DECODE_MAX_BITS` or
+    /// the CPU doesn't support AVX2.
+    pub fn new(bits_per_value: usize) -> Option<SIMDBulkDecoder> {
+        if bits_per_value == 0 || bits_per_value > SIMD_DECODE_MAX_BITS || !Self::is_support() {
+            return None;
+        }
+
+        let upper_offset = 4 * bits_per_value / 8;
+        let mut shuffle = [0i8; 32];
+        let mut shifts = [0i32; 8];
+        for lane in 0..8 {
+            let bit_offset = lane * bits_per_value;
+            let base = if lane < 4 { 0 } else { upper_offset };
+            let byte = bit_offset / 8 - base;
+            debug_assert!(byte + 3 < 16);
+            // the 4 big endian bytes holding the value, as a little endian u32
+            for i in 0..4 {
+                shuffle[lane * 4 + i] = (byte + 3 - i) as i8;
+            }
+            shifts[lane] = (32 - bits_per_value - bit_offset % 8) as i32;
+        }
+        Some(SIMDBulkDecoder {
+            bits_per_value,
+            upper_offset,
+            shuffle,
+            shifts,
+            mask: ((1u32 << bits_per_value) - 1) as i32,
+        })
+    }
+
+    pub fn is_support() -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            std::is_x86_feature_detected!("avx2")
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            false
+        }
+    }
+
+    /// The number of groups of 8 values that can be decoded without reading
+    /// past the end of `blocks`.
+    fn groups(&self, blocks_len: usize, values_len: usize) -> usize {
+        let load_end = self.upper_offset + 16;
+        if blocks_len < load_end {
+            return 0;
+        }
+        ((blocks_len - load_end) / self.bits_per_value + 1).min(values_len / 8)
+    }
+
+    /// Decodes as many groups of 8 values as possible from `blocks` and returns
+    /// the number of decoded values, the last ones are left to the caller.
+    pub fn decode_byte_to_int(&self, blocks: &[u8], values: &mut [i32]) -> usize {
+        let groups = self.groups(blocks.len(), values.len());
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            self.decode_int_groups(blocks, values, groups);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        unreachable!();
+        groups * 8
+    }
+
+    /// Same as `decode_byte_to_int` but widens the values to i64.
+    pub fn decode_byte_to_long(&self, blocks: &[u8], values: &mut [i64]) -> usize {
+        let groups = self.groups(blocks.len(), values.len());
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            self.decode_long_groups(blocks, values, groups);
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        unreachable!();
+        groups * 8
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn decode_int_groups(&self, blocks: &[u8], values: &mut [i32], groups: usize) {
+        let mut output = values.as_mut_ptr() as *mut simd::__m256i;
+        for group in 0..groups {
+            let decoded = self.decode_group(blocks.as_ptr().add(group * self.bits_per_value));
+            simd::_mm256_storeu_si256(output, decoded);
+            output = output.add(1);
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn decode_long_groups(&self, blocks: &[u8], values: &mut [i64], groups: usize) {
+        let mut output = values.as_mut_ptr() as *mut simd::__m256i;
+        for group in 0..groups {
+            let decoded = self.decode_group(blocks.as_ptr().add(group * self.bits_per_value));
+            let lower = simd::_mm256_cvtepu32_epi64(simd::_mm256_castsi256_si128(decoded));
+            let upper = simd::_mm256_cvtepu32_epi64(simd::_mm256_extracti128_si256::<1>(decoded));
+            simd::_mm256_storeu_si256(output, lower);
+            simd::_mm256_storeu_si256(output.add(1), upper);
+            output = output.add(2);
+        }
+    }
+
+    /// Decodes the 8 values packed in the `bits_per_value` bytes at `input`.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn decode_group(&self, input: *const u8) -> simd::__m256i {
+        let shuffle = simd::_mm256_loadu_si256(self.shuffle.as_ptr() as *const simd::__m256i);
+        let shifts = simd::_mm256_loadu_si256(self.shifts.as_ptr() as *const simd::__m256i);
+        let lower = simd::_mm_loadu_si128(input as *const simd::__m128i);
+        let upper = simd::_mm_loadu_si128(input.add(self.upper_offset) as *const simd::__m128i);
+        let words = simd::_mm256_shuffle_epi8(simd::_mm256_set_m128i(upper, lower), shuffle);
+        simd::_mm256_and_si256(
+            simd::_mm256_srlv_epi32(words, shifts),
+            simd::_mm256_set1_epi32(self.mask),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::util::packed::{BulkOperationPacked, PackedIntDecoder, PackedIntMeta};
+    use rand::{thread_rng, Rng};
+
+    // packs the values most significant bit first, like `BulkOperationPacked`
+    fn pack(values: &[i64], bits_per_value: usize) -> Vec<u8> {
+        let mut blocks = vec![0u8; (values.len() * bits_per_value).div_ceil(8)];
+        for (i, &v) in values.iter().enumerate() {
+            for bit in 0..bits_per_value {
+                if (v >> (bits_per_value - 1 - bit)) & 1 == 1 {
+                    let pos = i * bits_per_value + bit;
+                    blocks[pos / 8] |= 0x80 >> (pos % 8);
+                }
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_simd_bulk_decode() {
+        if !SIMDBulkDecoder::is_support() {
+            return;
+        }
+        assert!(SIMDBulkDecoder::new(SIMD_DECODE_MAX_BITS + 1).is_none());
+
+        let mut rng = thread_rng();
+        for bits_per_value in 1..=SIMD_DECODE_MAX_BITS {
+            let decoder = SIMDBulkDecoder::new(bits_per_value).unwrap();
+            let expected: Vec<i64> = (0..256)
+                .map(|_| rng.gen_range(0..1i64 << bits_per_value))
+                .collect();
+            let blocks = pack(&expected, bits_per_value);
+
+            let mut ints = vec![0i32; expected.len()];
+            let decoded = decoder.decode_byte_to_int(&blocks, &mut ints);
+            // the loads of the last groups would read past the packed bytes
+            assert!(decoded > 0 && decoded < expected.len());
+            assert_eq!(decoded % 8, 0);
+            for i in 0..decoded {
+                assert_eq!(i64::from(ints[i]), expected[i], "{} bits", bits_per_value);
+            }
+
+            let mut longs = vec![0i64; expected.len()];
+            assert_eq!(decoder.decode_byte_to_long(&blocks, &mut longs), decoded);
+            assert_eq!(&longs[..decoded], &expected[..decoded]);
+
+            // the bulk operation decodes the last values one by one
+            let bulk = BulkOperationPacked::new(bits_per_value);
+            let iterations = expected.len() / bulk.byte_value_count();
+            bulk.decode_byte_to_int(&blocks, &mut ints, iterations);
+            bulk.decode_byte_to_long(&blocks, &mut longs, iterations);
+            assert_eq!(longs, expected);
+            assert!(ints.iter().zip(&expected).all(|(&i, &e)| i64::from(i) == e));
+        }
+    }
+}
@@ -120,3 +120,49 @@ impl NumericDocValues for MixinMonotonicLongValues {
         self.get64(i64::from(doc_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::directory::{ByteBuffersDirectory, Directory};
+    use crate::core::store::IOContext;
+    use crate::core::util::packed::DirectMonotonicWriter;
+
+    #[test]
+    fn test_direct_monotonic_round_trip() -> Result<()> {
+        // blocks of 16 values: a linear one, a constant one that needs no
+        // packed deltas, an irregular one and a partial last one
+        let mut values: Vec<i64> = (0..16).map(|i| i * 7).collect();
+        values.extend(vec![112; 16]);
+        values.extend((0..16).map(|i| 200 + i * i * 3 + if i > 8 { 1000 } else { 0 }));
+        values.extend(&[5000, 5001, 5003]);
+
+        let dir = ByteBuffersDirectory::new();
+        {
+            let mut meta = dir.create_output("meta", &IOContext::Default)?;
+            let mut data = dir.create_output("data", &IOContext::Default)?;
+            let mut writer =
+                DirectMonotonicWriter::get_instance(&mut meta, &mut data, values.len() as i64, 4)?;
+            for &v in &values {
+                writer.add(v)?;
+            }
+            assert!(writer.add(0).is_err());
+            writer.finish()?;
+            assert!(writer.finish().is_err());
+        }
+
+        let mut meta_in = dir.open_input("meta", &IOContext::Default)?;
+        let meta = DirectMonotonicReader::load_meta(meta_in.as_mut(), values.len() as i64, 4)?;
+        assert_eq!(meta.num_blocks, 4);
+        assert_eq!(meta.bpvs[1], 0);
+
+        let data_in = dir.open_input("data", &IOContext::Default)?;
+        let data: Arc<dyn RandomAccessInput> =
+            Arc::from(data_in.random_access_slice(0, data_in.len() as i64)?);
+        let reader = DirectMonotonicReader::get_instance(&meta, &data)?;
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(reader.get64(i as i64)?, v);
+        }
+        Ok(())
+    }
+}
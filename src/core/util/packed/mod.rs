@@ -66,3 +66,7 @@ pub use self::elias_fano_decoder::*;
 mod packed_simd;
 
 pub use self::packed_simd::*;
+
+mod bulk_operation_simd;
+
+pub use self::bulk_operation_simd::*;
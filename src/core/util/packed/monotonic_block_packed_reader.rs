@@ -15,10 +15,11 @@ use crate::core::codec::doc_values::NumericDocValues;
 use crate::core::store::io::IndexInput;
 use crate::core::util::packed::PackedIntsNullReader;
 use crate::core::util::packed::{self, Reader, ReaderEnum};
-use crate::core::util::{DocId, LongValues};
+use crate::core::util::{Accountable, DocId, LongValues};
 use crate::error::Error::{CorruptIndex, IllegalArgument};
 use crate::Result;
 
+use std::mem;
 use std::sync::Arc;
 
 /// Provides random access to a stream written with MonotonicBlockPackedWriter
@@ -110,6 +111,20 @@ impl MonotonicBlockPackedReader {
     }
 }
 
+impl Accountable for MonotonicBlockPackedReader {
+    fn ram_bytes_used(&self) -> usize {
+        let inner = &self.inner;
+        mem::size_of::<MonotonicBlockPackedReaderInner>()
+            + inner.min_values.capacity() * mem::size_of::<i64>()
+            + inner.averages.capacity() * mem::size_of::<f32>()
+            + inner
+                .sub_readers
+                .iter()
+                .map(|r| mem::size_of::<ReaderEnum>() + r.ram_bytes_used())
+                .sum::<usize>()
+    }
+}
+
 impl LongValues for MonotonicBlockPackedReader {
     fn get64(&self, index: i64) -> Result<i64> {
         if !(index >= 0 && index < self.inner.value_count as i64) {
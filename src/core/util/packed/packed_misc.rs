@@ -15,6 +15,7 @@ use crate::core::codec::{check_header, write_header as codec_util_write_header};
 use crate::core::store::io::{DataInput, DataOutput, IndexInput};
 use crate::core::util::bit_util::{BitsRequired, UnsignedShift, ZigZagEncoding};
 use crate::core::util::packed::packed_ints_null_reader::PackedIntsNullReader;
+use crate::core::util::packed::SIMDBulkDecoder;
 
 use crate::error::Error::{IllegalArgument, UnexpectedEOF};
 
@@ -147,6 +148,24 @@ impl ReaderEnum {
             ReaderEnum::PackedIntsNull(_) => unreachable!(),
         }
     }
+
+    /// Estimates the memory used by the packed values of this reader.
+    pub fn ram_bytes_used(&self) -> usize {
+        fn packed_bytes(m: &impl Mutable) -> usize {
+            (m.size() * m.get_bits_per_value() as usize).div_ceil(8)
+        }
+        match self {
+            ReaderEnum::Direct8(m) => packed_bytes(m),
+            ReaderEnum::Direct16(m) => packed_bytes(m),
+            ReaderEnum::Direct32(m) => packed_bytes(m),
+            ReaderEnum::Direct64(m) => packed_bytes(m),
+            ReaderEnum::Packed8TB(m) => packed_bytes(m),
+            ReaderEnum::Packed16TB(m) => packed_bytes(m),
+            ReaderEnum::Packed64(m) => packed_bytes(m),
+            ReaderEnum::Packed64SB(m) => packed_bytes(m),
+            ReaderEnum::PackedIntsNull(_) => 0,
+        }
+    }
 }
 
 impl Reader for ReaderEnum {
@@ -2410,6 +2429,7 @@ pub struct BulkOperationPacked {
     byte_value_count: usize,
     mask: i64,
     int_mask: i32,
+    simd_decoder: Option<SIMDBulkDecoder>,
 }
 
 impl BulkOperationPacked {
@@ -2441,7 +2461,62 @@ impl BulkOperationPacked {
             byte_value_count,
             mask,
             int_mask: mask as i32,
+            simd_decoder: if cfg!(feature = "simd") {
+                SIMDBulkDecoder::new(bits_per_value)
+            } else {
+                None
+            },
+        }
+    }
+
+    fn unpack_byte_to_long(&self, blocks: &[u8], values: &mut [i64], iterations: usize) {
+        let mut next_value = 0i64;
+        let mut bits_left = self.bits_per_value;
+        let mut values_offset = 0;
+        for &block in &blocks[..iterations * self.byte_block_count] {
+            let bytes = i64::from(block);
+            if bits_left > 8 {
+                bits_left -= 8;
+                next_value |= bytes << bits_left;
+            } else {
+                let mut bits = 8 - bits_left;
+                values[values_offset] = next_value | bytes.unsigned_shift(bits as usize);
+                values_offset += 1;
+                while bits >= self.bits_per_value {
+                    bits -= self.bits_per_value;
+                    values[values_offset] = bytes.unsigned_shift(bits as usize) & self.mask;
+                    values_offset += 1;
+                }
+                bits_left = self.bits_per_value - bits;
+                next_value = (bytes & ((1i64 << bits) - 1)) << bits_left;
+            }
         }
+        debug_assert_eq!(bits_left, self.bits_per_value);
+    }
+
+    fn unpack_byte_to_int(&self, blocks: &[u8], values: &mut [i32], iterations: usize) {
+        let mut next_value = 0;
+        let mut bits_left = self.bits_per_value;
+        let mut values_offset = 0usize;
+        for &block in &blocks[..iterations * self.byte_block_count] {
+            let bytes = i32::from(block);
+            if bits_left > 8 {
+                bits_left -= 8;
+                next_value |= bytes << bits_left;
+            } else {
+                let mut bits: i32 = 8 - bits_left;
+                values[values_offset] = next_value | (bytes >> bits);
+                values_offset += 1;
+                while bits >= self.bits_per_value {
+                    bits -= self.bits_per_value;
+                    values[values_offset] = (bytes >> bits) & self.int_mask;
+                    values_offset += 1;
+                }
+                bits_left = self.bits_per_value - bits;
+                next_value = (bytes & ((1 << bits) - 1)) << bits_left;
+            }
+        }
+        debug_assert_eq!(bits_left, self.bits_per_value);
     }
 }
 
@@ -2601,30 +2676,20 @@ impl PackedIntDecoder for BulkOperationPacked {
     }
 
     fn decode_byte_to_long(&self, blocks: &[u8], values: &mut [i64], iterations: usize) {
-        let mut next_value = 0i64;
-        let mut bits_left = self.bits_per_value;
-        let mut blocks_offset = 0;
-        let mut values_offset = 0;
-        for _i in 0..iterations * self.byte_block_count {
-            let bytes = i64::from(blocks[blocks_offset]);
-            blocks_offset += 1;
-            if bits_left > 8 {
-                bits_left -= 8;
-                next_value |= bytes << bits_left;
-            } else {
-                let mut bits = 8 - bits_left;
-                values[values_offset] = next_value | bytes.unsigned_shift(bits as usize);
-                values_offset += 1;
-                while bits >= self.bits_per_value {
-                    bits -= self.bits_per_value;
-                    values[values_offset] = bytes.unsigned_shift(bits as usize) & self.mask;
-                    values_offset += 1;
-                }
-                bits_left = self.bits_per_value - bits;
-                next_value = (bytes & ((1i64 << bits) - 1)) << bits_left;
+        match &self.simd_decoder {
+            Some(decoder) => {
+                let count = iterations * self.byte_value_count;
+                let decoded = decoder.decode_byte_to_long(blocks, &mut values[..count]);
+                // a group of 8 values ends on a byte boundary
+                let offset = decoded * self.bits_per_value as usize / 8;
+                self.unpack_byte_to_long(
+                    &blocks[offset..],
+                    &mut values[decoded..],
+                    (count - decoded) / self.byte_value_count,
+                );
             }
+            None => self.unpack_byte_to_long(blocks, values, iterations),
         }
-        debug_assert_eq!(bits_left, self.bits_per_value);
     }
 
     fn decode_long_to_int(&self, blocks: &[i64], values: &mut [i32], iterations: usize) {
@@ -2653,30 +2718,19 @@ impl PackedIntDecoder for BulkOperationPacked {
     }
 
     fn decode_byte_to_int(&self, blocks: &[u8], values: &mut [i32], iterations: usize) {
-        let mut next_value = 0;
-        let mut bits_left = self.bits_per_value;
-        let mut values_offset = 0usize;
-        let mut blocks_offset = 0usize;
-        for _i in 0..iterations * self.byte_block_count {
-            let bytes = i32::from(blocks[blocks_offset]);
-            blocks_offset += 1;
-            if bits_left > 8 {
-                bits_left -= 8;
-                next_value |= bytes << bits_left;
-            } else {
-                let mut bits: i32 = 8 - bits_left;
-                values[values_offset] = (next_value | (bytes >> bits)) as i32;
-                values_offset += 1;
-                while bits >= self.bits_per_value {
-                    bits -= self.bits_per_value;
-                    values[values_offset] = ((bytes >> bits) as i32) & self.int_mask;
-                    values_offset += 1;
-                }
-                bits_left = self.bits_per_value - bits;
-                next_value = (bytes & ((1 << bits) - 1)) << bits_left;
+        match &self.simd_decoder {
+            Some(decoder) => {
+                let count = iterations * self.byte_value_count;
+                let decoded = decoder.decode_byte_to_int(blocks, &mut values[..count]);
+                let offset = decoded * self.bits_per_value as usize / 8;
+                self.unpack_byte_to_int(
+                    &blocks[offset..],
+                    &mut values[decoded..],
+                    (count - decoded) / self.byte_value_count,
+                );
             }
+            None => self.unpack_byte_to_int(blocks, values, iterations),
         }
-        debug_assert_eq!(bits_left, self.bits_per_value);
     }
 }
 
@@ -12,12 +12,13 @@
 // limitations under the License.
 
 use crate::core::store::io::{DataInput, DataOutput, IndexInput};
-use crate::core::util::BytesRef;
+use crate::core::util::{Accountable, BytesRef};
 
 use crate::error::Error::{IllegalArgument, IllegalState};
 use crate::Result;
 
 use std::io::{self, Read, Write};
+use std::mem;
 
 pub struct PagedBytes {
     block_size: usize,
@@ -331,6 +332,12 @@ pub struct PagedBytesReader {
     blocks: Vec<Vec<u8>>,
 }
 
+impl Accountable for PagedBytesReader {
+    fn ram_bytes_used(&self) -> usize {
+        mem::size_of::<Self>() + self.blocks.iter().map(Vec::capacity).sum::<usize>()
+    }
+}
+
 impl PagedBytesReader {
     pub fn new(paged_bytes: PagedBytes) -> Self {
         let blocks = paged_bytes.blocks;
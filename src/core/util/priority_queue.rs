@@ -0,0 +1,304 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::error::Error::IllegalState;
+use crate::Result;
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::slice;
+
+/// A bounded priority queue keeping its least element, according to
+/// `less_than`, on top.
+///
+/// The queue allocates room for `max_size` elements up front and never grows,
+/// which makes it a fit for collecting the top N hits: once full, a new
+/// element only replaces the top if it is greater.
+///
+/// A queue created with `with_sentinel` starts full of sentinel elements that
+/// must compare less than any real element. Competitive elements then simply
+/// replace the top, without checking the size of the queue.
+pub struct PriorityQueue<T, F = fn(&T, &T) -> bool> {
+    heap: Vec<T>,
+    max_size: usize,
+    less_than: F,
+}
+
+impl<T: PartialOrd> PriorityQueue<T> {
+    /// Creates a queue keeping the smallest element on top.
+    pub fn with_natural_order(max_size: usize) -> Self {
+        PriorityQueue::new(max_size, T::lt)
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> bool> PriorityQueue<T, F> {
+    /// Creates a queue of at most `max_size` elements ordered by `less_than`.
+    pub fn new(max_size: usize, less_than: F) -> Self {
+        PriorityQueue {
+            heap: Vec::with_capacity(max_size),
+            max_size,
+            less_than,
+        }
+    }
+
+    /// Creates a queue filled with `max_size` elements returned by `sentinel`.
+    pub fn with_sentinel(max_size: usize, less_than: F, mut sentinel: impl FnMut() -> T) -> Self {
+        let mut queue = Self::new(max_size, less_than);
+        for _ in 0..max_size {
+            queue.add(sentinel());
+        }
+        queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.heap.len() == self.max_size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the least element of the queue.
+    pub fn top(&self) -> Option<&T> {
+        self.heap.first()
+    }
+
+    /// Adds an element to the queue.
+    ///
+    /// Fails if the queue is full, use `insert_with_overflow` to only keep
+    /// the greatest elements.
+    pub fn push(&mut self, element: T) -> Result<()> {
+        if self.is_full() {
+            return Err(IllegalState(format!(
+                "priority queue is full: {} elements",
+                self.max_size
+            )));
+        }
+        self.add(element);
+        Ok(())
+    }
+
+    /// Adds an element to the queue if it isn't full yet, or replaces the top
+    /// if it is less than `element`.
+    ///
+    /// Returns the element that didn't make it to the queue, if any: the
+    /// former top or `element` itself.
+    pub fn insert_with_overflow(&mut self, element: T) -> Option<T> {
+        if !self.is_full() {
+            self.add(element);
+            None
+        } else if self.max_size > 0 && (self.less_than)(&self.heap[0], &element) {
+            self.replace_top(element)
+        } else {
+            Some(element)
+        }
+    }
+
+    /// Replaces the top with `element` and returns the former top.
+    ///
+    /// An empty queue takes `element` in, unless it has no room at all, in
+    /// which case `element` is handed back.
+    pub fn replace_top(&mut self, element: T) -> Option<T> {
+        if self.heap.is_empty() {
+            if self.is_full() {
+                return Some(element);
+            }
+            self.add(element);
+            return None;
+        }
+        let top = std::mem::replace(&mut self.heap[0], element);
+        self.down_heap(0);
+        Some(top)
+    }
+
+    /// Updates the top in place with `update`, then restores the order of the
+    /// queue and returns the new top.
+    ///
+    /// This is cheaper than a `pop` followed by a `push` when the top changes,
+    /// e.g. when a sub iterator on top of the queue advances.
+    pub fn update_top(&mut self, update: impl FnOnce(&mut T)) -> Option<&T> {
+        if let Some(top) = self.heap.first_mut() {
+            update(top);
+            self.down_heap(0);
+        }
+        self.heap.first()
+    }
+
+    /// Removes and returns the least element of the queue.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let top = self.heap.swap_remove(0);
+        if !self.heap.is_empty() {
+            self.down_heap(0);
+        }
+        Some(top)
+    }
+
+    /// Removes all the elements, keeping the allocated room.
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+
+    /// Iterates over the elements in no particular order.
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.heap.iter()
+    }
+
+    /// Removes the elements in order, least first. The elements that are not
+    /// consumed are dropped with the iterator.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, F> {
+        DrainSorted { queue: self }
+    }
+
+    // the caller checked that the queue isn't full
+    fn add(&mut self, element: T) {
+        self.heap.push(element);
+        self.up_heap(self.heap.len() - 1);
+    }
+
+    fn up_heap(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !(self.less_than)(&self.heap[i], &self.heap[parent]) {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn down_heap(&mut self, mut i: usize) {
+        let len = self.heap.len();
+        loop {
+            let mut least = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < len && (self.less_than)(&self.heap[child], &self.heap[least]) {
+                    least = child;
+                }
+            }
+            if least == i {
+                break;
+            }
+            self.heap.swap(i, least);
+            i = least;
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for PriorityQueue<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PriorityQueue")
+            .field("max_size", &self.max_size)
+            .field("heap", &self.heap)
+            .finish()
+    }
+}
+
+/// Iterator returned by `PriorityQueue::drain_sorted`.
+pub struct DrainSorted<'a, T, F: Fn(&T, &T) -> bool> {
+    queue: &'a mut PriorityQueue<T, F>,
+}
+
+impl<'a, T, F: Fn(&T, &T) -> bool> Iterator for DrainSorted<'a, T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.queue.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.queue.len(), Some(self.queue.len()))
+    }
+}
+
+impl<'a, T, F: Fn(&T, &T) -> bool> ExactSizeIterator for DrainSorted<'a, T, F> {}
+
+impl<'a, T, F: Fn(&T, &T) -> bool> FusedIterator for DrainSorted<'a, T, F> {}
+
+impl<'a, T, F: Fn(&T, &T) -> bool> Drop for DrainSorted<'a, T, F> {
+    fn drop(&mut self) {
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn test_priority_queue() {
+        let mut rng = thread_rng();
+        let values: Vec<i32> = (0..1000).map(|_| rng.gen_range(0..10_000)).collect();
+        let mut expected = values.clone();
+        expected.sort_unstable();
+
+        // keeps the 10 greatest values
+        let mut queue = PriorityQueue::with_natural_order(10);
+        let capacity = queue.heap.capacity();
+        for &v in &values {
+            queue.insert_with_overflow(v);
+        }
+        assert!(queue.is_full());
+        assert_eq!(queue.heap.capacity(), capacity);
+        assert_eq!(queue.top(), Some(&expected[990]));
+        let top: Vec<i32> = queue.drain_sorted().collect();
+        assert_eq!(top, &expected[990..]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        // an overflowing element is handed back
+        let mut queue = PriorityQueue::new(2, |a: &i32, b: &i32| a > b);
+        assert_eq!(queue.insert_with_overflow(5), None);
+        assert_eq!(queue.insert_with_overflow(3), None);
+        assert_eq!(queue.insert_with_overflow(7), Some(7));
+        assert_eq!(queue.insert_with_overflow(1), Some(5));
+        assert_eq!(queue.update_top(|v| *v = 0), Some(&1));
+        assert_eq!(queue.replace_top(2), Some(1));
+        assert_eq!(queue.drain_sorted().collect::<Vec<_>>(), vec![2, 0]);
+
+        // a full queue rejects a push
+        let mut queue = PriorityQueue::with_natural_order(1);
+        assert!(queue.push(1).is_ok());
+        assert!(queue.push(2).is_err());
+        assert_eq!(queue.len(), 1);
+        let mut queue = PriorityQueue::with_natural_order(0);
+        assert!(queue.push(1).is_err());
+        assert_eq!(queue.replace_top(1), Some(1));
+        assert!(queue.is_empty());
+
+        // sentinels are replaced without checking the size of the queue
+        let mut queue =
+            PriorityQueue::with_sentinel(3, |a: &f32, b: &f32| a < b, || f32::NEG_INFINITY);
+        assert!(queue.is_full());
+        for v in [0.5, 2.0, 1.0, 0.1] {
+            if *queue.top().unwrap() < v {
+                queue.update_top(|top| *top = v);
+            }
+        }
+        assert_eq!(
+            queue.drain_sorted().collect::<Vec<_>>(),
+            vec![0.5, 1.0, 2.0]
+        );
+    }
+}
@@ -0,0 +1,399 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::search::{DocIdSet, DocIterator, NO_MORE_DOCS};
+use crate::core::util::bit_set::{BitSet, FixedBitSet, ImmutableBitSet};
+use crate::core::util::doc_id_set::{
+    BitDocIdSet, BitSetDocIterator, NotDocIdSet, NotDocIterator, ShortArrayDocIdSet,
+    ShortArrayDocIterator,
+};
+use crate::core::util::{Accountable, DocId, UnsignedShift};
+use crate::error::Error::IllegalArgument;
+use crate::Result;
+
+use std::mem;
+use std::sync::Arc;
+
+/// Number of documents in a block
+pub const ROARING_BLOCK_SIZE: usize = 1 << 16;
+/// The maximum length for an array, beyond that point we switch to a bitset
+pub const ROARING_MAX_ARRAY_LENGTH: usize = 1 << 12;
+
+/// `DocIdSet` implementation inspired from http://roaringbitmap.org/
+///
+/// The space is divided into blocks of 2^16 bits and each block is encoded
+/// independently. In each block, if less than 2^12 bits are set, then
+/// documents are simply stored in a short[]. If more than 2^16-2^12 bits are
+/// set, then the inverse of the set is encoded in a simple short[]. Otherwise
+/// a `FixedBitSet` is used.
+pub struct RoaringDocIdSet {
+    blocks: Arc<[Option<RoaringBlock>]>,
+    cardinality: usize,
+    ram_bytes_used: usize,
+}
+
+impl RoaringDocIdSet {
+    /// The number of documents in this set.
+    pub fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+}
+
+impl Accountable for RoaringDocIdSet {
+    fn ram_bytes_used(&self) -> usize {
+        self.ram_bytes_used
+    }
+}
+
+impl DocIdSet for RoaringDocIdSet {
+    type Iter = RoaringDocIterator;
+
+    fn iterator(&self) -> Result<Option<Self::Iter>> {
+        if self.cardinality == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(RoaringDocIterator::new(
+                Arc::clone(&self.blocks),
+                self.cardinality,
+            )))
+        }
+    }
+}
+
+// the encoding of the docs of a block, relative to the start of the block
+enum RoaringBlock {
+    Sparse(ShortArrayDocIdSet),
+    Inverse(NotDocIdSet<ShortArrayDocIdSet>),
+    Dense(BitDocIdSet<FixedBitSet>),
+}
+
+impl RoaringBlock {
+    fn iterator(&self) -> Result<Option<RoaringBlockIterator>> {
+        Ok(match self {
+            RoaringBlock::Sparse(s) => s.iterator()?.map(RoaringBlockIterator::Sparse),
+            RoaringBlock::Inverse(s) => s.iterator()?.map(RoaringBlockIterator::Inverse),
+            RoaringBlock::Dense(s) => s.iterator()?.map(RoaringBlockIterator::Dense),
+        })
+    }
+}
+
+enum RoaringBlockIterator {
+    Sparse(ShortArrayDocIterator),
+    Inverse(NotDocIterator<ShortArrayDocIterator>),
+    Dense(BitSetDocIterator<FixedBitSet>),
+}
+
+impl RoaringBlockIterator {
+    fn next(&mut self) -> Result<DocId> {
+        match self {
+            RoaringBlockIterator::Sparse(i) => i.next(),
+            RoaringBlockIterator::Inverse(i) => i.next(),
+            RoaringBlockIterator::Dense(i) => i.next(),
+        }
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        match self {
+            RoaringBlockIterator::Sparse(i) => i.advance(target),
+            RoaringBlockIterator::Inverse(i) => i.advance(target),
+            RoaringBlockIterator::Dense(i) => i.advance(target),
+        }
+    }
+}
+
+/// Builds a `RoaringDocIdSet` from docs added in increasing order.
+pub struct RoaringDocIdSetBuilder {
+    blocks: Vec<Option<RoaringBlock>>,
+    cardinality: usize,
+    ram_bytes_used: usize,
+
+    max_doc: DocId,
+    last_doc_id: DocId,
+    current_block: i32,
+    current_block_cardinality: usize,
+
+    // We start by filling the buffer and when it's full we copy the content of
+    // the buffer to the FixedBitSet and put further documents in that bitset
+    buffer: Vec<u16>,
+    dense_buffer: Option<FixedBitSet>,
+}
+
+impl RoaringDocIdSetBuilder {
+    pub fn new(max_doc: DocId) -> RoaringDocIdSetBuilder {
+        let length = (max_doc + (1 << 16) - 1).unsigned_shift(16) as usize;
+        let mut blocks = Vec::with_capacity(length);
+        blocks.resize_with(length, || None);
+
+        RoaringDocIdSetBuilder {
+            ram_bytes_used: mem::size_of::<Option<RoaringBlock>>() * length,
+            blocks,
+            cardinality: 0,
+            max_doc,
+            last_doc_id: -1,
+            current_block: -1,
+            current_block_cardinality: 0,
+            buffer: vec![0u16; ROARING_MAX_ARRAY_LENGTH],
+            dense_buffer: None,
+        }
+    }
+
+    /// Adds a new doc id to this builder.
+    /// NOTE: doc ids must be added in increasing order.
+    pub fn add_doc(&mut self, doc_id: DocId) -> Result<()> {
+        if doc_id <= self.last_doc_id || doc_id >= self.max_doc {
+            return Err(IllegalArgument(format!(
+                "Doc ids must be added in-order and be < max_doc={}, got {} after {}",
+                self.max_doc, doc_id, self.last_doc_id
+            )));
+        }
+
+        let block = doc_id.unsigned_shift(16);
+        if block != self.current_block {
+            // we went to a different block, let's flush what we buffered and start from fresh
+            self.flush();
+            self.current_block = block;
+        }
+
+        if self.current_block_cardinality < ROARING_MAX_ARRAY_LENGTH {
+            self.buffer[self.current_block_cardinality] = doc_id as u16;
+        } else {
+            if self.dense_buffer.is_none() {
+                // the buffer is full, let's move to a fixed bit set
+                let num_bits = (1i32 << 16).min(self.max_doc - (block << 16));
+                let mut fixed_bit_set = FixedBitSet::new(num_bits as usize);
+                for doc in &self.buffer {
+                    fixed_bit_set.set(*doc as usize);
+                }
+                self.dense_buffer = Some(fixed_bit_set);
+            }
+            self.dense_buffer
+                .as_mut()
+                .unwrap()
+                .set((doc_id & 0xFFFF) as usize);
+        }
+
+        self.last_doc_id = doc_id;
+        self.current_block_cardinality += 1;
+        Ok(())
+    }
+
+    /// Adds the remaining docs of `iter`, which must be after the docs added
+    /// so far.
+    pub fn add(&mut self, iter: &mut dyn DocIterator) -> Result<()> {
+        loop {
+            let doc = iter.next()?;
+            if doc == NO_MORE_DOCS {
+                return Ok(());
+            }
+            self.add_doc(doc)?;
+        }
+    }
+
+    fn flush(&mut self) {
+        debug_assert!(self.current_block_cardinality <= ROARING_BLOCK_SIZE);
+        let cardinality = self.current_block_cardinality;
+
+        if cardinality <= ROARING_MAX_ARRAY_LENGTH {
+            // Use sparse encoding
+            debug_assert!(self.dense_buffer.is_none());
+            if cardinality > 0 {
+                let docs = self.buffer[..cardinality].to_vec();
+                self.ram_bytes_used += cardinality * mem::size_of::<u16>();
+                self.blocks[self.current_block as usize] = Some(RoaringBlock::Sparse(
+                    ShortArrayDocIdSet::new(docs, cardinality),
+                ));
+            }
+        } else {
+            let mut dense_buffer = self.dense_buffer.take().unwrap();
+            debug_assert_eq!(dense_buffer.cardinality(), cardinality);
+
+            if dense_buffer.num_bits == ROARING_BLOCK_SIZE
+                && ROARING_BLOCK_SIZE - cardinality < ROARING_MAX_ARRAY_LENGTH
+            {
+                // Doc ids are very dense, inverse the encoding
+                let num_bits = dense_buffer.num_bits;
+                dense_buffer.flip(0, num_bits);
+                let mut exclude_docs = Vec::with_capacity(ROARING_BLOCK_SIZE - cardinality);
+                let mut exclude_doc = -1;
+                for _ in 0..ROARING_BLOCK_SIZE - cardinality {
+                    exclude_doc = dense_buffer.next_set_bit((exclude_doc + 1) as usize);
+                    debug_assert_ne!(exclude_doc, NO_MORE_DOCS);
+                    exclude_docs.push(exclude_doc as u16);
+                }
+
+                let length = exclude_docs.len();
+                self.ram_bytes_used += length * mem::size_of::<u16>();
+                self.blocks[self.current_block as usize] =
+                    Some(RoaringBlock::Inverse(NotDocIdSet::new(
+                        ShortArrayDocIdSet::new(exclude_docs, length),
+                        ROARING_BLOCK_SIZE as i32,
+                    )));
+            } else {
+                // Neither sparse nor super dense, use a fixed bit set
+                self.ram_bytes_used += dense_buffer.bits.len() * mem::size_of::<i64>();
+                self.blocks[self.current_block as usize] = Some(RoaringBlock::Dense(
+                    BitDocIdSet::new(Arc::new(dense_buffer), cardinality),
+                ));
+            }
+        }
+
+        self.cardinality += cardinality;
+        self.current_block_cardinality = 0;
+    }
+
+    pub fn build(mut self) -> RoaringDocIdSet {
+        self.flush();
+        RoaringDocIdSet {
+            blocks: Arc::from(self.blocks.into_boxed_slice()),
+            cardinality: self.cardinality,
+            ram_bytes_used: self.ram_bytes_used,
+        }
+    }
+}
+
+pub struct RoaringDocIterator {
+    blocks: Arc<[Option<RoaringBlock>]>,
+    doc: DocId,
+    block: i32,
+    cardinality: usize,
+    sub: Option<RoaringBlockIterator>,
+}
+
+impl RoaringDocIterator {
+    fn new(blocks: Arc<[Option<RoaringBlock>]>, cardinality: usize) -> Self {
+        RoaringDocIterator {
+            blocks,
+            doc: -1,
+            block: -1,
+            cardinality,
+            sub: None,
+        }
+    }
+
+    fn first_doc_from_next_block(&mut self) -> Result<DocId> {
+        loop {
+            self.block += 1;
+            if self.block as usize >= self.blocks.len() {
+                self.sub = None;
+                self.doc = NO_MORE_DOCS;
+                return Ok(self.doc);
+            }
+            if let Some(ref block) = self.blocks[self.block as usize] {
+                self.sub = block.iterator()?;
+                let sub_next = self.sub.as_mut().unwrap().next()?;
+                debug_assert_ne!(sub_next, NO_MORE_DOCS);
+                self.doc = (self.block << 16) | sub_next;
+                return Ok(self.doc);
+            }
+        }
+    }
+}
+
+impl DocIterator for RoaringDocIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let sub_next = match self.sub {
+            Some(ref mut sub) => sub.next()?,
+            None => NO_MORE_DOCS,
+        };
+        if sub_next == NO_MORE_DOCS {
+            return self.first_doc_from_next_block();
+        }
+        self.doc = (self.block << 16) | sub_next;
+        Ok(self.doc)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        let target_block = target.unsigned_shift(16);
+
+        if target_block != self.block {
+            self.block = target_block;
+            if self.block as usize >= self.blocks.len() {
+                self.sub = None;
+                self.doc = NO_MORE_DOCS;
+                return Ok(self.doc);
+            }
+            match self.blocks[self.block as usize] {
+                Some(ref block) => self.sub = block.iterator()?,
+                None => return self.first_doc_from_next_block(),
+            }
+        }
+
+        let sub_next = match self.sub {
+            Some(ref mut sub) => sub.advance(target & 0xFFFF)?,
+            None => NO_MORE_DOCS,
+        };
+        if sub_next == NO_MORE_DOCS {
+            return self.first_doc_from_next_block();
+        }
+        self.doc = (self.block << 16) | sub_next;
+        Ok(self.doc)
+    }
+
+    fn cost(&self) -> usize {
+        self.cardinality
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(set: &RoaringDocIdSet) -> Result<Vec<DocId>> {
+        let mut docs = vec![];
+        if let Some(mut iter) = set.iterator()? {
+            loop {
+                let doc = iter.next()?;
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                docs.push(doc);
+            }
+        }
+        Ok(docs)
+    }
+
+    #[test]
+    fn test_roaring_blocks() -> Result<()> {
+        let max_doc = 4 * ROARING_BLOCK_SIZE as i32 + 100;
+        // a sparse block, a dense one, an inverted one, an empty one and a
+        // short trailing one
+        let mut expected: Vec<DocId> = (0..100).map(|i| i * 7).collect();
+        expected.extend((1 << 16..2 << 16).step_by(3));
+        expected.extend((2 << 16..3 << 16).filter(|d| d % 1000 != 0));
+        expected.extend(4 << 16..(4 << 16) + 100);
+
+        let mut builder = RoaringDocIdSetBuilder::new(max_doc);
+        for doc in &expected {
+            builder.add_doc(*doc)?;
+        }
+        assert!(builder.add_doc(expected[0]).is_err());
+        let set = builder.build();
+        assert_eq!(set.cardinality(), expected.len());
+        assert_eq!(collect(&set)?, expected);
+
+        let mut iter = set.iterator()?.unwrap();
+        assert_eq!(iter.advance(8)?, 14);
+        assert_eq!(iter.advance(1 << 16)?, 1 << 16);
+        assert_eq!(iter.advance(132_000)?, 132_001);
+        assert_eq!(iter.advance(3 << 16)?, 4 << 16);
+        assert_eq!(iter.advance((4 << 16) + 100)?, NO_MORE_DOCS);
+
+        let empty = RoaringDocIdSetBuilder::new(10).build();
+        assert!(empty.iterator()?.is_none());
+        Ok(())
+    }
+}
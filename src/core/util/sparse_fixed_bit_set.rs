@@ -0,0 +1,278 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::search::NO_MORE_DOCS;
+use crate::core::util::bit_set::{BitSet, FixedBitSet, ImmutableBitSet};
+use crate::core::util::{Accountable, Bits};
+
+use std::mem;
+
+/// A bit set that only stores longs that have at least one bit which is set.
+/// The way it works is that the space of bits is divided into blocks of
+/// 4096 bits, which is 64 longs. Then for each block, we have:
+/// - a long which stores which longs have at least one bit set,
+/// - an array of longs that stores the non-zero longs of the block, in order.
+///
+/// This makes it possible to build a set of doc ids over a very large
+/// `max_doc` without allocating `max_doc / 8` bytes when only a few of the
+/// docs, or a few ranges of docs, are set.
+///
+/// @lucene.internal
+pub struct SparseFixedBitSet {
+    indices: Vec<u64>,
+    bits: Vec<Vec<u64>>,
+    length: usize,
+    non_zero_long_count: usize,
+}
+
+impl SparseFixedBitSet {
+    pub fn new(length: usize) -> SparseFixedBitSet {
+        let block_count = Self::block_count(length);
+        SparseFixedBitSet {
+            indices: vec![0; block_count],
+            bits: vec![Vec::new(); block_count],
+            length,
+            non_zero_long_count: 0,
+        }
+    }
+
+    fn block_count(length: usize) -> usize {
+        let mut block_count = length >> 12;
+        if block_count << 12 < length {
+            block_count += 1;
+        }
+        block_count
+    }
+
+    /// Copies the bits of this set to a `FixedBitSet` of the same length.
+    pub fn to_fixed_bit_set(&self) -> FixedBitSet {
+        let mut fixed = FixedBitSet::new(self.length);
+        for (i4096, index) in self.indices.iter().enumerate() {
+            let mut index = *index;
+            let mut o = 0;
+            while index != 0 {
+                let i64 = index.trailing_zeros() as usize;
+                fixed.bits[(i4096 << 6) | i64] = self.bits[i4096][o] as i64;
+                index &= index - 1;
+                o += 1;
+            }
+        }
+        fixed
+    }
+
+    fn insert_block(&mut self, i4096: usize, i64: usize, i: usize) {
+        self.indices[i4096] = 1u64 << i64; // shifts are mod 64
+        self.bits[i4096] = vec![1u64 << (i & 0x3f)];
+        self.non_zero_long_count += 1;
+    }
+
+    fn insert_long(&mut self, i4096: usize, i64: usize, i: usize, index: u64) {
+        self.indices[i4096] |= 1u64 << i64;
+        // we count the number of bits that are set on the right of i64
+        // this gives us the index at which to perform the insertion
+        let o = (index & ((1u64 << i64) - 1)).count_ones() as usize;
+        self.bits[i4096].insert(o, 1u64 << (i & 0x3f));
+        self.non_zero_long_count += 1;
+    }
+
+    // applies `word & mask` on the long `i4096 << 6 | i64`, removing it
+    // when it becomes zero
+    fn and_word(&mut self, i4096: usize, i64: usize, mask: u64) {
+        let index = self.indices[i4096];
+        if index & (1u64 << i64) != 0 {
+            let o = (index & ((1u64 << i64) - 1)).count_ones() as usize;
+            let word = self.bits[i4096][o] & mask;
+            if word == 0 {
+                self.bits[i4096].remove(o);
+                self.indices[i4096] &= !(1u64 << i64);
+                self.non_zero_long_count -= 1;
+            } else {
+                self.bits[i4096][o] = word;
+            }
+        }
+    }
+
+    // returns the first set bit of the non-empty long at position `o` of block `i4096`
+    fn first_doc(&self, i4096: usize, i64: usize, o: usize) -> i32 {
+        ((i4096 << 12) | (i64 << 6) | self.bits[i4096][o].trailing_zeros() as usize) as i32
+    }
+}
+
+impl Accountable for SparseFixedBitSet {
+    /// Approximate memory usage of this set, computed in constant time.
+    fn ram_bytes_used(&self) -> usize {
+        self.indices.len() * (mem::size_of::<u64>() + mem::size_of::<Vec<u64>>())
+            + self.non_zero_long_count * mem::size_of::<u64>()
+    }
+}
+
+impl Bits for SparseFixedBitSet {
+    fn get(&self, i: usize) -> bool {
+        debug_assert!(i < self.length);
+        let i4096 = i >> 12;
+        let index = self.indices[i4096];
+        let i64 = (i >> 6) & 0x3f;
+        // first check the index, if the i64-th bit is not set, then i is not set
+        if index & (1u64 << i64) == 0 {
+            return false;
+        }
+        // if it is set, then we count the number of bits that are set on the right
+        // of i64, and that tells us the index of the long that stores the bits we
+        // are interested in
+        let o = (index & ((1u64 << i64) - 1)).count_ones() as usize;
+        self.bits[i4096][o] & (1u64 << (i & 0x3f)) != 0
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl ImmutableBitSet for SparseFixedBitSet {
+    fn cardinality(&self) -> usize {
+        self.bits
+            .iter()
+            .flat_map(|block| block.iter())
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn next_set_bit(&self, i: usize) -> i32 {
+        debug_assert!(i < self.length);
+        let i4096 = i >> 12;
+        let index = self.indices[i4096];
+        let i64 = (i >> 6) & 0x3f;
+        let mut o = (index & ((1u64 << i64) - 1)).count_ones() as usize;
+        if index & (1u64 << i64) != 0 {
+            // There is at least one bit that is set in the current long, check if
+            // one of them is after i
+            let word = self.bits[i4096][o] >> (i & 0x3f); // shifts are mod 64
+            if word != 0 {
+                return (i + word.trailing_zeros() as usize) as i32;
+            }
+            o += 1;
+        }
+        let indices = if i64 == 63 { 0 } else { index >> (i64 + 1) };
+        if indices != 0 {
+            // There is at least one bit that is set in the same block, check the
+            // next long that has one bit set
+            let next_i64 = i64 + 1 + indices.trailing_zeros() as usize;
+            return self.first_doc(i4096, next_i64, o);
+        }
+        // then check other blocks
+        for (j, index) in self.indices.iter().enumerate().skip(i4096 + 1) {
+            if *index != 0 {
+                return self.first_doc(j, index.trailing_zeros() as usize, 0);
+            }
+        }
+        NO_MORE_DOCS
+    }
+}
+
+impl BitSet for SparseFixedBitSet {
+    fn set(&mut self, i: usize) {
+        debug_assert!(i < self.length);
+        let i4096 = i >> 12;
+        let index = self.indices[i4096];
+        let i64 = (i >> 6) & 0x3f;
+        if index & (1u64 << i64) != 0 {
+            // in that case the sub 64-bits block we are interested in already exists,
+            // we just need to set a bit in an existing long: the number of ones on
+            // the right of i64 gives us the index of the long we need to update
+            let o = (index & ((1u64 << i64) - 1)).count_ones() as usize;
+            self.bits[i4096][o] |= 1u64 << (i & 0x3f);
+        } else if index == 0 {
+            // if the long is 0, it means that there is no bit set in the current
+            // block yet, and we need to allocate it
+            self.insert_block(i4096, i64, i);
+        } else {
+            // in that case we found a block of 4096 bits that has some values, but
+            // the sub-block of 64 bits that we are interested in has no value yet,
+            // so we need to insert a new long
+            self.insert_long(i4096, i64, i, index);
+        }
+    }
+
+    fn clear(&mut self, i: usize) {
+        debug_assert!(i < self.length);
+        self.and_word(i >> 12, (i >> 6) & 0x3f, !(1u64 << (i & 0x3f)));
+    }
+
+    fn clear_batch(&mut self, from: usize, to: usize) {
+        debug_assert!(to <= self.length);
+        if from >= to {
+            return;
+        }
+        let first_word = from >> 6;
+        let last_word = (to - 1) >> 6;
+        for word in first_word..=last_word {
+            if self.indices[word >> 6] == 0 {
+                continue;
+            }
+            let mut mask = !0u64;
+            if word == first_word {
+                mask <<= from & 0x3f;
+            }
+            if word == last_word {
+                mask &= !0u64 >> (63 - ((to - 1) & 0x3f));
+            }
+            self.and_word(word >> 6, word & 0x3f, !mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_fixed_bit_set() {
+        let length = 1 << 20;
+        let mut sparse = SparseFixedBitSet::new(length);
+        let mut fixed = FixedBitSet::new(length);
+        assert_eq!(sparse.next_set_bit(0), NO_MORE_DOCS);
+
+        // a dense range and a few scattered docs, set out of order
+        let docs = (100_000..104_000)
+            .chain((0..50).map(|i| i * 20_011 + 7))
+            .chain(vec![0, 63, 64, 4095, 4096, length - 1])
+            .rev();
+        for doc in docs {
+            sparse.set(doc);
+            fixed.set(doc);
+        }
+        assert_eq!(sparse.cardinality(), fixed.cardinality());
+        assert!(sparse.ram_bytes_used() < length / 8 / 4);
+
+        let check = |sparse: &SparseFixedBitSet, fixed: &FixedBitSet| {
+            for i in (0..length).step_by(13).chain(100_000..104_100) {
+                assert_eq!(sparse.get(i), fixed.get(i), "get({})", i);
+                assert_eq!(sparse.next_set_bit(i), fixed.next_set_bit(i), "next({})", i);
+            }
+        };
+        check(&sparse, &fixed);
+        assert_eq!(sparse.to_fixed_bit_set().bits, fixed.bits);
+
+        for i in &[0, 64, 101_000, 20_011 + 7] {
+            sparse.clear(*i);
+            fixed.clear(*i);
+        }
+        sparse.clear_batch(100_010, 103_900);
+        fixed.clear_batch(100_010, 103_900);
+        sparse.clear_batch(length - 1, length);
+        fixed.clear_batch(length - 1, length);
+        assert_eq!(sparse.cardinality(), fixed.cardinality());
+        check(&sparse, &fixed);
+        assert_eq!(sparse.to_fixed_bit_set().bits, fixed.bits);
+    }
+}
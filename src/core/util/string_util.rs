@@ -32,8 +32,7 @@ pub fn bytes_subtract(bytes_per_dim: usize, dim: usize, a: &[u8], b: &[u8], resu
     let start = dim * bytes_per_dim;
     let end = start + bytes_per_dim;
     let mut borrow = 0;
-    let mut i = end - 1;
-    while i >= start {
+    for i in (start..end).rev() {
         let mut diff: i32 = (a[i] as u32 as i32) - (b[i] as u32 as i32) - borrow;
         if diff < 0 {
             diff += 256;
@@ -43,7 +42,6 @@ pub fn bytes_subtract(bytes_per_dim: usize, dim: usize, a: &[u8], b: &[u8], resu
         }
 
         result[i - start] = diff as u8;
-        i -= 1;
     }
 
     if borrow != 0 {
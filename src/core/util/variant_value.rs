@@ -17,7 +17,9 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
 
+use crate::core::doc::{DateTools, Resolution};
 use crate::core::util::numeric::Numeric;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -31,6 +33,8 @@ pub enum VariantValue {
     Double(f64),
     VString(String),
     Binary(Vec<u8>),
+    /// milliseconds since the unix epoch
+    Date(i64),
     Vec(Vec<VariantValue>),
     Map(HashMap<String, VariantValue>),
 }
@@ -73,6 +77,7 @@ impl VariantValue {
             VariantValue::Long(l) => Some(Numeric::Long(l)),
             VariantValue::Float(f) => Some(Numeric::Float(f)),
             VariantValue::Double(d) => Some(Numeric::Double(d)),
+            VariantValue::Date(d) => Some(Numeric::Long(d)),
             _ => None,
         }
     }
@@ -101,6 +106,90 @@ impl VariantValue {
         }
     }
 
+    pub fn get_date(&self) -> Option<SystemTime> {
+        match self {
+            VariantValue::Date(d) => Some(DateTools::from_millis(*d)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string slice, for strings and utf8 encoded binaries.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VariantValue::VString(s) => Some(s.as_str()),
+            VariantValue::Binary(b) => ::std::str::from_utf8(b).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw bytes of a binary or string value.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            VariantValue::VString(s) => Some(s.as_bytes()),
+            VariantValue::Binary(b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Returns the value widened to `i64`, for integral values, dates and
+    /// floating point values without a fractional part.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            VariantValue::Short(s) => Some(i64::from(s)),
+            VariantValue::Int(i) => Some(i64::from(i)),
+            VariantValue::Long(l) | VariantValue::Date(l) => Some(l),
+            VariantValue::Float(f) => f64_to_i64(f64::from(f)),
+            VariantValue::Double(d) => f64_to_i64(d),
+            _ => None,
+        }
+    }
+
+    /// Returns the value widened to `f64`, for any numeric value.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            VariantValue::Short(s) => Some(f64::from(s)),
+            VariantValue::Int(i) => Some(f64::from(i)),
+            VariantValue::Long(l) => Some(l as f64),
+            VariantValue::Float(f) => Some(f64::from(f)),
+            VariantValue::Double(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            VariantValue::Short(_)
+                | VariantValue::Int(_)
+                | VariantValue::Long(_)
+                | VariantValue::Float(_)
+                | VariantValue::Double(_)
+        )
+    }
+
+    // compare two numeric values by their mathematical value, NaN sorts last
+    fn cmp_numeric(&self, other: &VariantValue) -> Ordering {
+        match (self.as_exact_i64(), other.as_exact_i64()) {
+            (Some(v1), Some(v2)) => v1.cmp(&v2),
+            (Some(v1), None) => cmp_i64_f64(v1, other.as_f64().unwrap()),
+            (None, Some(v2)) => cmp_i64_f64(v2, self.as_f64().unwrap()).reverse(),
+            (None, None) => {
+                let (v1, v2) = (self.as_f64().unwrap(), other.as_f64().unwrap());
+                v1.partial_cmp(&v2)
+                    .unwrap_or_else(|| v1.is_nan().cmp(&v2.is_nan()))
+            }
+        }
+    }
+
+    fn as_exact_i64(&self) -> Option<i64> {
+        match *self {
+            VariantValue::Short(s) => Some(i64::from(s)),
+            VariantValue::Int(i) => Some(i64::from(i)),
+            VariantValue::Long(l) => Some(l),
+            _ => None,
+        }
+    }
+
     pub fn get_utf8_string(&self) -> Option<String> {
         match self {
             VariantValue::VString(s) => Some(s.clone()),
@@ -157,6 +246,11 @@ impl fmt::Display for VariantValue {
             VariantValue::Double(d) => write!(f, "{:.6}", d),
             VariantValue::VString(ref s) => write!(f, "{}", s),
             VariantValue::Binary(ref _b) => write!(f, "Binary(unprintable)"),
+            VariantValue::Date(d) => write!(
+                f,
+                "{}",
+                DateTools::time_to_string(d, Resolution::Millisecond)
+            ),
             VariantValue::Vec(ref v) => write!(f, "{:?}", v),
             VariantValue::Map(ref m) => write!(f, "{:?}", m),
         }
@@ -178,6 +272,7 @@ impl serde::Serialize for VariantValue {
             VariantValue::Double(d) => serializer.serialize_f64(d),
             VariantValue::VString(ref s) => serializer.serialize_str(s.as_str()),
             VariantValue::Binary(ref b) => serializer.serialize_bytes(b),
+            VariantValue::Date(d) => serializer.serialize_i64(d),
             VariantValue::Vec(ref vec) => {
                 let mut seq = serializer.serialize_seq(Some(vec.len())).unwrap();
                 for v in vec {
@@ -199,16 +294,21 @@ impl serde::Serialize for VariantValue {
 
 impl Hash for VariantValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        // numeric values that compare equal must hash the same
+        if self.is_numeric() {
+            match self.as_i64() {
+                Some(v) => v.hash(state),
+                None => self.as_f64().unwrap().to_bits().hash(state),
+            }
+            return;
+        }
         match *self {
             VariantValue::Bool(ref b) => b.hash(state),
             VariantValue::Char(ref c) => c.hash(state),
-            VariantValue::Short(ref s) => s.hash(state),
-            VariantValue::Int(ref i) => i.hash(state),
-            VariantValue::Long(ref l) => l.hash(state),
-            VariantValue::Float(ref f) => f.to_bits().hash(state),
-            VariantValue::Double(ref d) => d.to_bits().hash(state),
             VariantValue::VString(ref s) => s.hash(state),
             VariantValue::Binary(ref v) => v.hash(state),
+            VariantValue::Date(ref d) => d.hash(state),
+            VariantValue::Vec(ref v) => v.hash(state),
             _ => (),
         }
     }
@@ -216,71 +316,16 @@ impl Hash for VariantValue {
 
 impl PartialEq for VariantValue {
     fn eq(&self, other: &VariantValue) -> bool {
-        match *self {
-            VariantValue::Bool(ref b) => {
-                if let VariantValue::Bool(ref o) = *other {
-                    b.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Char(ref c) => {
-                if let VariantValue::Char(ref o) = *other {
-                    c.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Short(ref s) => {
-                if let VariantValue::Short(ref o) = *other {
-                    s.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Int(ref i) => {
-                if let VariantValue::Int(ref o) = *other {
-                    i.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Long(ref l) => {
-                if let VariantValue::Long(ref o) = *other {
-                    l.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Float(ref f) => {
-                if let VariantValue::Float(ref o) = *other {
-                    f.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Double(ref d) => {
-                if let VariantValue::Double(ref o) = *other {
-                    d.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::VString(ref s) => {
-                if let VariantValue::VString(ref o) = *other {
-                    s.eq(o)
-                } else {
-                    false
-                }
-            }
-            VariantValue::Binary(ref v) => {
-                if let VariantValue::Binary(ref o) = *other {
-                    v.eq(o)
-                } else {
-                    false
-                }
-            }
-            _ => unreachable!(),
+        match (self, other) {
+            (VariantValue::Bool(b1), VariantValue::Bool(b2)) => b1 == b2,
+            (VariantValue::Char(c1), VariantValue::Char(c2)) => c1 == c2,
+            (VariantValue::VString(s1), VariantValue::VString(s2)) => s1 == s2,
+            (VariantValue::Binary(b1), VariantValue::Binary(b2)) => b1 == b2,
+            (VariantValue::Date(d1), VariantValue::Date(d2)) => d1 == d2,
+            (VariantValue::Vec(v1), VariantValue::Vec(v2)) => v1 == v2,
+            (VariantValue::Map(m1), VariantValue::Map(m2)) => m1 == m2,
+            (v1, v2) if v1.is_numeric() && v2.is_numeric() => v1.cmp_numeric(v2) == Ordering::Equal,
+            _ => false,
         }
     }
 }
@@ -290,13 +335,11 @@ impl Ord for VariantValue {
         match (self, other) {
             (&VariantValue::Bool(b1), &VariantValue::Bool(b2)) => b1.cmp(&b2),
             (&VariantValue::Char(c1), &VariantValue::Char(c2)) => c1.cmp(&c2),
-            (&VariantValue::Short(v1), &VariantValue::Short(v2)) => v1.cmp(&v2),
-            (&VariantValue::Int(v1), &VariantValue::Int(v2)) => v1.cmp(&v2),
-            (&VariantValue::Long(v1), &VariantValue::Long(v2)) => v1.cmp(&v2),
-            (&VariantValue::Float(v1), &VariantValue::Float(v2)) => v1.partial_cmp(&v2).unwrap(),
-            (&VariantValue::Double(v1), &VariantValue::Double(v2)) => v1.partial_cmp(&v2).unwrap(),
             (&VariantValue::VString(ref s1), &VariantValue::VString(ref s2)) => s1.cmp(&s2),
             (&VariantValue::Binary(ref b1), &VariantValue::Binary(ref b2)) => b1.cmp(&b2),
+            (&VariantValue::Date(d1), &VariantValue::Date(d2)) => d1.cmp(&d2),
+            (VariantValue::Vec(v1), VariantValue::Vec(v2)) => v1.cmp(v2),
+            (v1, v2) if v1.is_numeric() && v2.is_numeric() => v1.cmp_numeric(v2),
             (_, _) => panic!("Non-comparable"),
         }
     }
@@ -308,6 +351,28 @@ impl PartialOrd for VariantValue {
     }
 }
 
+// exact comparison of an integer against a double, NaN sorts last
+fn cmp_i64_f64(v: i64, d: f64) -> Ordering {
+    if d.is_nan() || d >= i64::MAX as f64 {
+        // i64::MAX as f64 rounds up to 2^63
+        return Ordering::Less;
+    }
+    if d < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    let t = d.trunc();
+    v.cmp(&(t as i64))
+        .then_with(|| 0f64.partial_cmp(&(d - t)).unwrap())
+}
+
+fn f64_to_i64(d: f64) -> Option<i64> {
+    if d.fract() == 0.0 && d >= i64::MIN as f64 && d < i64::MAX as f64 {
+        Some(d as i64)
+    } else {
+        None
+    }
+}
+
 impl From<bool> for VariantValue {
     fn from(val: bool) -> Self {
         VariantValue::Bool(val)
@@ -364,6 +429,24 @@ impl<'a> From<&'a [u8]> for VariantValue {
     }
 }
 
+impl From<String> for VariantValue {
+    fn from(val: String) -> Self {
+        VariantValue::VString(val)
+    }
+}
+
+impl From<Vec<u8>> for VariantValue {
+    fn from(val: Vec<u8>) -> Self {
+        VariantValue::Binary(val)
+    }
+}
+
+impl From<SystemTime> for VariantValue {
+    fn from(val: SystemTime) -> Self {
+        VariantValue::Date(DateTools::to_millis(val))
+    }
+}
+
 impl From<Numeric> for VariantValue {
     fn from(val: Numeric) -> Self {
         debug_assert!(!val.is_null());
@@ -382,6 +465,7 @@ impl From<Numeric> for VariantValue {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn variant_bool_test() {
@@ -473,4 +557,51 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn variant_numeric_cmp_test() {
+        assert_eq!(VariantValue::Int(1), VariantValue::Long(1));
+        assert_eq!(VariantValue::Short(2), VariantValue::Double(2.0));
+        assert_ne!(VariantValue::Long(2), VariantValue::Float(2.5));
+        assert_ne!(VariantValue::Long(1), VariantValue::VString("1".into()));
+        assert!(VariantValue::Int(3) < VariantValue::Double(3.5));
+        assert!(VariantValue::Double(-3.5) < VariantValue::Int(-3));
+        assert!(VariantValue::Long(i64::MAX) < VariantValue::Double(1e19));
+        assert!(VariantValue::Double(f64::NAN) > VariantValue::Long(i64::MAX));
+
+        let mut set = HashSet::new();
+        set.insert(VariantValue::Int(7));
+        assert!(set.contains(&VariantValue::Long(7)));
+        assert!(set.contains(&VariantValue::Double(7.0)));
+        assert!(!set.contains(&VariantValue::Date(7)));
+    }
+
+    #[test]
+    fn variant_accessor_test() {
+        assert_eq!(VariantValue::Short(3).as_i64(), Some(3));
+        assert_eq!(VariantValue::Double(4.0).as_i64(), Some(4));
+        assert_eq!(VariantValue::Double(4.5).as_i64(), None);
+        assert_eq!(VariantValue::Int(5).as_f64(), Some(5.0));
+        assert_eq!(VariantValue::Bool(true).as_f64(), None);
+
+        assert_eq!(VariantValue::from("abc").as_str(), Some("abc"));
+        assert_eq!(VariantValue::from(b"abc".to_vec()).as_str(), Some("abc"));
+        assert_eq!(VariantValue::Binary(vec![0xff]).as_str(), None);
+        assert_eq!(VariantValue::from("abc").as_bytes(), Some(&b"abc"[..]));
+    }
+
+    #[test]
+    fn variant_date_test() {
+        let time = DateTools::from_millis(1_000_000_000_123);
+        let dval = VariantValue::from(time);
+        assert_eq!(dval, VariantValue::Date(1_000_000_000_123));
+        assert_eq!(dval.get_date(), Some(time));
+        assert_eq!(dval.as_i64(), Some(1_000_000_000_123));
+        assert_eq!(
+            dval.get_numeric().map(|n| n.long_value()),
+            Some(1_000_000_000_123)
+        );
+        assert_eq!(format!("{}", dval), "20010909014640123");
+        assert!(dval < VariantValue::Date(1_000_000_000_124));
+    }
 }
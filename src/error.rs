@@ -37,6 +37,8 @@ pub enum Error {
     UnsupportedOperation(String),
     #[error("Already Closed: {0}")]
     AlreadyClosed(String),
+    #[error("Lock Obtain Failed: {0}")]
+    LockObtainFailed(String),
     #[error("Runtime Error: {0}")]
     RuntimeError(String),
     #[error("IO Error: {0}")]
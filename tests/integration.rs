@@ -1,28 +1,104 @@
 #![feature(test)]
 
-use rucene::core::codec::CodecEnum;
+use rucene::core::codec::doc_values::lucene54::Lucene54DocValuesFormat;
+use rucene::core::codec::doc_values::{
+    BinaryDocValues, BinaryDocValuesIterator, NumericDocValues, SortedDocValues,
+    SortedSetDocValues, NO_MORE_ORDS,
+};
+use rucene::core::codec::doc_values::{DocValuesFormatEnum, PerFieldDocValuesFormat};
+use rucene::core::codec::field_infos::{FieldInfo, FieldInfos};
+use rucene::core::codec::points::{IntersectVisitor, Lucene60PointsFormat, PointValues, Relation};
+use rucene::core::codec::postings::{
+    completion_payload, completion_term, BloomFilterPostingsFormat, CompletionPostingsFormat,
+    DirectPostingsFormat, FSTPostingsFormat, FieldTermsEnum, Impact, Lucene50PostingsFormat,
+    PerFieldPostingsFormat, PostingsFormatEnum,
+};
+use rucene::core::codec::segment_infos::{SegmentInfoFormat, SegmentInfos};
+use rucene::core::codec::simple_text::SimpleTextCodec;
+use rucene::core::codec::stored_fields::StoredFieldCompressMode;
+#[cfg(feature = "zstd")]
+use rucene::core::codec::stored_fields::ZSTD_DEFAULT_LEVEL;
+use rucene::core::codec::vectors::VectorSimilarity;
+use rucene::core::codec::{
+    check_header, check_index_header_suffix, codec_for_name, register_codec, Codec, CodecEnum,
+    CodecTerms, CustomCodec, Lucene62Codec, PostingIterator, PostingIteratorFlags, SeekStatus,
+    TermIterator, Terms,
+};
 
 extern crate rucene;
 extern crate test;
 
-use rucene::core::analysis::WhitespaceTokenizer;
-use rucene::core::doc::{Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField};
-use rucene::core::index::reader::IndexReader;
-use rucene::core::index::writer::{IndexWriter, IndexWriterConfig};
-use rucene::core::search::collector::TopDocsCollector;
-use rucene::core::search::query::{Query, QueryStringQueryBuilder};
-use rucene::core::search::{DefaultIndexSearcher, IndexSearcher};
-use rucene::core::store::directory::FSDirectory;
+use rucene::core::analysis::{Token, WhitespaceTokenizer};
+use rucene::core::doc::{
+    doc, from_document, BinaryDocValuesField, DateDocValuesField, DatePoint, DateRange, DateTools,
+    DocValuesType, DocumentMapping, DoubleRange, Field, FieldMapping, FieldType, Fieldable,
+    IndexOptions, LatLonDocValuesField, LongRange, NumericDocValuesField, Resolution,
+    SortedDocValuesField, SortedNumericDocValuesField, SortedSetDocValuesField, StoredField,
+    StringField, SuggestField, TextField, VectorField, STORE_FIELD_TYPE, STRING_FIELD_TYPE,
+    TEXT_FIELD_TYPE,
+};
+use rucene::core::facet::{FacetField, FacetsConfig, SortedSetDocValuesFacetField};
+use rucene::core::index::merge::{
+    LogByteSizeMergePolicy, LogDocMergePolicy, MergePolicy, NoMergePolicy, SerialMergeScheduler,
+    SoftDeletesRetentionMergePolicy, TieredMergePolicy,
+};
+use rucene::core::index::reader::{
+    list_commits, FilterDirectoryReader, FilterLeafReader, IndexReader, LeafReader,
+    LeafReaderContext, MultiReader, ParallelLeafReader, SearchLeafReader, StandardDirectoryReader,
+};
+use rucene::core::index::writer::{
+    AutoCommitPolicy, AutoCommitTrigger, CommitDurability, CommitPoint, FieldSchema,
+    IndexDeletionPolicy, IndexSplitter, IndexUpgrader, IndexWriter, IndexWriterConfig,
+    IndexWriterEventListener, KeepLastNCommitsDeletionPolicy, KeepOnlyLastCommitDeletionPolicy,
+    OpenMode, PersistentSnapshotDeletionPolicy, Schema, SegmentEventInfo, SplitMode,
+};
+use rucene::core::index::{CheckIndex, Term};
+use rucene::core::search::collector::{TopDocsCollector, TopFieldCollector};
+use rucene::core::search::query::{
+    IntPoint, MatchAllDocsQuery, PhraseQuery, Query, QueryStringQueryBuilder, TermQuery,
+};
+use rucene::core::search::similarity::BM25Similarity;
+use rucene::core::search::sort_field::{
+    ScoreDocHit, SimpleSortField, Sort, SortField, SortFieldType,
+};
+use rucene::core::search::spell::{DirectSpellChecker, PhraseSuggester, SuggestMode};
+use rucene::core::search::suggest::{
+    AnalyzingSuggester, CompletionQuery, FreeTextSuggester, FuzzySuggester, SuggestIndexSearcher,
+};
+use rucene::core::search::{
+    ControlledRealTimeReopenThread, DefaultIndexSearcher, DefaultSearcherFactory, DocIterator,
+    IndexSearcher, ReferenceManager, RefreshListener, SearcherManager, NO_MORE_DOCS,
+};
+use rucene::core::store::directory::{
+    ByteBuffersDirectory, Directory, FSDirectory, FileSwitchDirectory, InMemoryObjectStore,
+    MetricsDirectoryWrapper, MmapDirectory, NRTCachingDirectory, ObjectStore, ObjectStoreDirectory,
+    RateLimitedDirectoryWrapper, SleepingLockWrapper,
+};
+use rucene::core::store::io::{DataInput, IndexInput};
+use rucene::core::store::lock::{Lock, SimpleFSLockFactory, WRITE_LOCK_NAME};
+use rucene::core::store::{IOContext, IOContextKind};
 
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 use rucene::core::highlight::FastVectorHighlighter;
 use rucene::core::highlight::FieldQuery;
-use rucene::core::util::VariantValue;
-use rucene::error::Result;
+use rucene::core::highlight::{
+    BaseFragmentsBuilder, FieldHighlightConfig, Highlighter, OffsetSource, UnifiedHighlighter,
+    COLORED_POST_TAGS, COLORED_PRE_TAGS,
+};
+use rucene::core::util::{
+    Accountable, BitSet, BitsRef, DocId, FixedBitSet, NamedAccountable, VariantValue, Version,
+    VERSION_LATEST,
+};
+use rucene::error::{Error, Result};
 
 fn indexed_text_field_type() -> FieldType {
     let mut field_type = FieldType::default();
@@ -55,6 +131,49 @@ fn new_stored_text_field(field_name: String, text: String) -> Field {
     )
 }
 
+fn new_keyword_field(field_name: String, text: String) -> Field {
+    let mut field_type = FieldType::default();
+    field_type.index_options = IndexOptions::Docs;
+    field_type.tokenized = false;
+    field_type.omit_norms = true;
+
+    Field::new(
+        field_name,
+        field_type,
+        Some(VariantValue::VString(text)),
+        None,
+    )
+}
+
+fn new_index_dir(path: &str) -> Result<&Path> {
+    let dir_path = Path::new(path);
+    if dir_path.exists() {
+        std::fs::remove_dir_all(&dir_path)?;
+    }
+    std::fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+type TestIndexWriter = IndexWriter<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+type TestIndexReader =
+    StandardDirectoryReader<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+/// Opens a writer with the default config over a new index at `path`.
+fn new_index_writer(path: &str) -> Result<TestIndexWriter> {
+    let dir_path = new_index_dir(path)?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))
+}
+
+/// Commits the docs added to `writer` and opens a searcher over them.
+fn commit_and_search(
+    writer: &TestIndexWriter,
+) -> Result<impl IndexSearcher<CodecEnum, Reader = TestIndexReader>> {
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    Ok(DefaultIndexSearcher::new(Arc::new(reader), None))
+}
+
 struct StringReader {
     text: String,
     index: usize,
@@ -77,6 +196,102 @@ impl std::io::Read for StringReader {
     }
 }
 
+type OpenHook = Box<dyn FnMut(&str) + Send>;
+
+/// A `FSDirectory` whose writes or syncs fail on demand, like on a full disk.
+struct FailingDirectory {
+    dir: FSDirectory,
+    fail_writes: AtomicBool,
+    fail_syncs: AtomicBool,
+    // called with the name of each file before it's opened
+    before_open: Mutex<Option<OpenHook>>,
+}
+
+impl FailingDirectory {
+    fn new(dir: FSDirectory) -> Self {
+        FailingDirectory {
+            dir,
+            fail_writes: AtomicBool::new(false),
+            fail_syncs: AtomicBool::new(false),
+            before_open: Mutex::new(None),
+        }
+    }
+
+    fn check(flag: &AtomicBool, name: &str) -> Result<()> {
+        if flag.load(Ordering::Acquire) {
+            let msg = format!("no space left on device writing '{}'", name);
+            return Err(std::io::Error::other(msg).into());
+        }
+        Ok(())
+    }
+}
+
+impl Directory for FailingDirectory {
+    type IndexOutput = <FSDirectory as Directory>::IndexOutput;
+    type TempOutput = <FSDirectory as Directory>::TempOutput;
+
+    fn list_all(&self) -> Result<Vec<String>> {
+        self.dir.list_all()
+    }
+
+    fn file_length(&self, name: &str) -> Result<i64> {
+        self.dir.file_length(name)
+    }
+
+    fn create_output(&self, name: &str, context: &IOContext) -> Result<Self::IndexOutput> {
+        Self::check(&self.fail_writes, name)?;
+        self.dir.create_output(name, context)
+    }
+
+    fn open_input(&self, name: &str, ctx: &IOContext) -> Result<Box<dyn IndexInput>> {
+        if let Some(f) = self.before_open.lock().unwrap().as_mut() {
+            f(name);
+        }
+        self.dir.open_input(name, ctx)
+    }
+
+    fn create_temp_output(
+        &self,
+        prefix: &str,
+        suffix: &str,
+        ctx: &IOContext,
+    ) -> Result<Self::TempOutput> {
+        Self::check(&self.fail_writes, prefix)?;
+        self.dir.create_temp_output(prefix, suffix, ctx)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<()> {
+        self.dir.delete_file(name)
+    }
+
+    fn sync(&self, names: &HashSet<String>) -> Result<()> {
+        Self::check(&self.fail_syncs, "sync")?;
+        self.dir.sync(names)
+    }
+
+    fn sync_metadata(&self) -> Result<()> {
+        self.dir.sync_metadata()
+    }
+
+    fn rename(&self, source: &str, dest: &str) -> Result<()> {
+        self.dir.rename(source, dest)
+    }
+
+    fn obtain_lock(&self, name: &str) -> Result<Box<dyn Lock>> {
+        self.dir.obtain_lock(name)
+    }
+
+    fn resolve(&self, name: &str) -> PathBuf {
+        self.dir.resolve(name)
+    }
+}
+
+impl fmt::Display for FailingDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FailingDirectory({})", self.dir)
+    }
+}
+
 #[test]
 fn integration() -> Result<()> {
     // create index directory
@@ -179,3 +394,5550 @@ fn integration() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn update_document() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_update")?;
+
+    let new_doc = |id: &str, text: &str| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), id.into())),
+            Box::new(new_index_text_field("title".into(), text.into())),
+        ]
+    };
+    writer.add_document(new_doc("1", "alice"))?;
+    writer.add_document(new_doc("2", "rabbit"))?;
+    writer.commit()?;
+
+    // replaces the committed doc as well as a doc that is still buffered
+    writer.add_document(new_doc("3", "queen"))?;
+    let id = |v: &str| Some(Term::new("id".into(), v.as_bytes().to_vec()));
+    writer.update_document(new_doc("1", "hatter"), id("1"))?;
+    writer.update_document(new_doc("3", "king"), id("3"))?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 3);
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let count = |field: &str, text: &str| {
+        let term = Term::new(field.into(), text.as_bytes().to_vec());
+        index_searcher.count(&TermQuery::new(term, 1.0, None))
+    };
+    assert_eq!(count("id", "1")?, 1);
+    assert_eq!(count("id", "3")?, 1);
+    assert_eq!(count("title", "alice")?, 0);
+    assert_eq!(count("title", "hatter")?, 1);
+    assert_eq!(count("title", "queen")?, 0);
+    assert_eq!(count("title", "king")?, 1);
+    assert_eq!(count("title", "rabbit")?, 1);
+    Ok(())
+}
+
+#[test]
+fn delete_documents() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_delete")?;
+
+    let new_doc = |id: &str, text: &str| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), id.into())),
+            Box::new(new_index_text_field("title".into(), text.into())),
+        ]
+    };
+    let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+
+    writer.add_document(new_doc("1", "alice"))?;
+    writer.add_document(new_doc("2", "queen of hearts"))?;
+    writer.add_document(new_doc("3", "rabbit"))?;
+    writer.commit()?;
+    writer.add_document(new_doc("4", "red queen"))?;
+    writer.add_document(new_doc("5", "hatter"))?;
+    writer.add_document(new_doc("6", "cat"))?;
+    writer.commit()?;
+
+    // the deletes are buffered and applied to both segments at flush
+    writer.delete_documents_by_terms(vec![term("id", "1"), term("id", "6")])?;
+    let query: Arc<dyn Query<CodecEnum>> =
+        Arc::new(TermQuery::new(term("title", "queen"), 1.0, None));
+    writer.delete_documents_by_queries(vec![query])?;
+    // docs added after the delete are not affected by it
+    writer.add_document(new_doc("7", "white queen"))?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 3);
+    drop(reader);
+
+    // merging drops the deleted docs
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.max_doc(), 3);
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    for (id, count) in &[("1", 0), ("2", 0), ("3", 1), ("4", 0), ("5", 1), ("6", 0)] {
+        let query = TermQuery::new(term("id", id), 1.0, None);
+        assert_eq!(index_searcher.count(&query)?, *count);
+    }
+    let query = TermQuery::new(term("title", "queen"), 1.0, None);
+    assert_eq!(index_searcher.count(&query)?, 1);
+    Ok(())
+}
+
+#[test]
+fn soft_deletes() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_soft_deletes")?;
+    let min_retained = Arc::new(AtomicI64::new(0));
+    let retained = Arc::clone(&min_retained);
+    let merge_policy = SoftDeletesRetentionMergePolicy::new(
+        "seq_no",
+        move || retained.load(Ordering::Acquire),
+        TieredMergePolicy::default(),
+    );
+    let mut config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        merge_policy,
+    );
+    config.soft_deletes_field = Some("__soft_deletes".into());
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+    let new_doc = |id: &str, seq_no: i64| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), id.into())),
+            Box::new(NumericDocValuesField::new("seq_no", seq_no)),
+        ]
+    };
+    let term = |id: &str| Term::new("id".into(), id.as_bytes().to_vec());
+
+    writer.add_document(new_doc("1", 1))?;
+    writer.add_document(new_doc("2", 2))?;
+    writer.commit()?;
+    writer.soft_update_document(new_doc("1", 3), term("1"))?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 2);
+    assert_eq!(reader.max_doc(), 3);
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    assert_eq!(
+        index_searcher.count(&TermQuery::new(term("1"), 1.0, None))?,
+        1
+    );
+
+    // all the soft deleted docs are retained
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 2);
+    assert_eq!(reader.max_doc(), 3);
+
+    // the merge reclaims the soft deleted docs that are no longer retained
+    min_retained.store(2, Ordering::Release);
+    writer.add_document(new_doc("3", 4))?;
+    writer.commit()?;
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 3);
+    assert_eq!(reader.max_doc(), 3);
+    Ok(())
+}
+
+#[test]
+fn add_indexes() -> Result<()> {
+    let new_doc = |id: &str, text: &str| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), id.into())),
+            Box::new(new_index_text_field("title".into(), text.into())),
+        ]
+    };
+    let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+
+    let mut dirs = vec![];
+    for (i, path) in ["/tmp/test_rucene_add_src1", "/tmp/test_rucene_add_src2"]
+        .iter()
+        .enumerate()
+    {
+        let directory = Arc::new(FSDirectory::new(new_index_dir(path)?)?);
+        let writer = IndexWriter::new(
+            Arc::clone(&directory),
+            Arc::new(IndexWriterConfig::default()),
+        )?;
+        writer.add_document(new_doc(&format!("{}-1", i), "alice"))?;
+        writer.add_document(new_doc(&format!("{}-2", i), "rabbit"))?;
+        writer.commit()?;
+        writer.delete_documents_by_terms(vec![term("id", &format!("{}-2", i))])?;
+        writer.add_document(new_doc(&format!("{}-3", i), "queen"))?;
+        writer.close()?;
+        dirs.push(directory);
+    }
+
+    let dir_path = new_index_dir("/tmp/test_rucene_add_dest")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    writer.add_document(new_doc("dest-1", "hatter"))?;
+    assert!(writer.add_indexes(&[Arc::clone(&directory)]).is_err());
+    assert!(writer
+        .add_indexes(&[Arc::clone(&dirs[0]), Arc::clone(&dirs[0])])
+        .is_err());
+    writer.add_indexes(&dirs)?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 5);
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let count = |field: &str, text: &str| {
+        index_searcher.count(&TermQuery::new(term(field, text), 1.0, None))
+    };
+    assert_eq!(count("title", "alice")?, 2);
+    assert_eq!(count("title", "rabbit")?, 0);
+    assert_eq!(count("title", "queen")?, 2);
+    assert_eq!(count("title", "hatter")?, 1);
+    assert_eq!(count("id", "1-3")?, 1);
+    Ok(())
+}
+
+#[test]
+fn force_merge_deletes() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_force_merge_deletes")?;
+
+    for i in 0..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+    for i in 0..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), (i + 10).to_string())])?;
+    }
+    writer.commit()?;
+    let terms = (0..10)
+        .filter(|i| i % 2 == 0)
+        .map(|i: i32| Term::new("id".into(), i.to_string().into_bytes()))
+        .collect();
+    writer.delete_documents_by_terms(terms)?;
+    writer.commit()?;
+
+    writer.force_merge_deletes(true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    // only the segment with deletions is rewritten
+    assert_eq!(reader.leaves().len(), 2);
+    assert_eq!(reader.num_docs(), 15);
+    assert_eq!(reader.max_doc(), 15);
+    Ok(())
+}
+
+#[test]
+fn byte_buffers_directory() -> Result<()> {
+    let directory = Arc::new(ByteBuffersDirectory::new());
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..20 {
+        writer.add_document(vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(new_index_text_field(
+                "title".into(),
+                format!("doc {}", i % 2),
+            )),
+        ])?;
+        if i % 5 == 4 {
+            writer.commit()?;
+        }
+    }
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    writer.close()?;
+    assert!(directory.ram_bytes_used()? > 0);
+    CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory)).verify_checksums()?;
+
+    // each thread opens its own reader over the shared files
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let directory = Arc::clone(&directory);
+            std::thread::spawn(move || -> Result<i32> {
+                let reader: StandardDirectoryReader<
+                    _,
+                    CodecEnum,
+                    SerialMergeScheduler,
+                    NoMergePolicy,
+                > = StandardDirectoryReader::open(directory)?;
+                assert_eq!(reader.leaves().len(), 1);
+                let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+                let term = Term::new("title".into(), b"1".to_vec());
+                searcher.count(&TermQuery::new(term, 1.0, None))
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap()?, 10);
+    }
+    Ok(())
+}
+
+#[test]
+fn nrt_caching_directory() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_nrt_caching_directory")?;
+    let directory = Arc::new(NRTCachingDirectory::new(
+        FSDirectory::new(&dir_path)?,
+        5.0,
+        60.0,
+    ));
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+
+    // the NRT segment is only held in RAM
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 10);
+    drop(reader);
+    let cached = directory.list_cached_files()?;
+    assert!(!cached.is_empty());
+    let on_disk = directory.delegate().list_all()?;
+    assert!(cached.iter().all(|f| !on_disk.contains(f)));
+    assert!(directory.list_all()?.iter().any(|f| cached.contains(f)));
+
+    // committing moves the segment to the delegate
+    writer.commit()?;
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let cached = directory.list_cached_files()?;
+    for segment in &infos.segments {
+        for file in segment.files() {
+            assert!(!cached.contains(&file));
+            directory.delegate().file_length(&file)?;
+        }
+    }
+
+    // the commit is readable without the cache
+    let fs_directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+        StandardDirectoryReader::open(fs_directory)?;
+    assert_eq!(reader.num_docs(), 10);
+    Ok(())
+}
+
+#[test]
+fn file_switch_directory() -> Result<()> {
+    let primary_path = new_index_dir("/tmp/test_rucene_file_switch_primary")?;
+    let secondary_path = new_index_dir("/tmp/test_rucene_file_switch_secondary")?;
+    let stored_fields_extensions: HashSet<String> =
+        ["fdt", "fdx"].iter().map(|e| e.to_string()).collect();
+    let directory = Arc::new(FileSwitchDirectory::new(
+        stored_fields_extensions,
+        MmapDirectory::new(&primary_path)?,
+        FSDirectory::new(&secondary_path)?,
+    ));
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..10 {
+        writer.add_document(vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(new_stored_text_field("body".into(), format!("body {}", i))),
+        ])?;
+    }
+    writer.commit()?;
+    writer.close()?;
+
+    let primary = directory.primary().list_all()?;
+    let secondary = directory.secondary().list_all()?;
+    assert!(!primary.is_empty());
+    assert!(primary
+        .iter()
+        .all(|f| f.ends_with(".fdt") || f.ends_with(".fdx")));
+    assert!(secondary.iter().any(|f| f.starts_with("segments_")));
+    assert!(secondary
+        .iter()
+        .all(|f| !f.ends_with(".fdt") && !f.ends_with(".fdx")));
+
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    assert_eq!(reader.num_docs(), 10);
+    let doc = reader.document(3, &["body".to_string()])?;
+    let body = doc.fields[0].field.field_data().unwrap();
+    assert_eq!(body.get_string().unwrap(), "body 3");
+    Ok(())
+}
+
+#[test]
+fn object_store_directory() -> Result<()> {
+    let writer_staging = new_index_dir("/tmp/test_rucene_object_store_writer")?;
+    let reader_staging = new_index_dir("/tmp/test_rucene_object_store_reader")?;
+    let store = Arc::new(InMemoryObjectStore::new());
+
+    let directory = Arc::new(ObjectStoreDirectory::new(
+        Arc::clone(&store),
+        "index/",
+        &writer_staging,
+    )?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..500 {
+        writer.add_document(vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(new_index_text_field(
+                "body".into(),
+                format!("common body {}", i % 3),
+            )),
+            Box::new(new_stored_text_field(
+                "body".into(),
+                format!("common body {}", i % 3),
+            )),
+        ])?;
+    }
+    writer.commit()?;
+    writer.close()?;
+    let keys = store.list("index/")?;
+    assert!(keys.iter().any(|k| k.starts_with("index/segments_")));
+    assert!(keys.iter().all(|k| !k.contains("pending_segments")));
+
+    // a search node with nothing local reads through small cached blocks
+    let directory = Arc::new(
+        ObjectStoreDirectory::new(Arc::clone(&store), "index/", &reader_staging)?
+            .with_block_cache(64, 1 << 20)?,
+    );
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    assert_eq!(reader.num_docs(), 500);
+    assert!(std::fs::read_dir(reader_staging)?.next().is_none());
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let term = |text: &str| Term::new("body".into(), text.as_bytes().to_vec());
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("common"), 1.0, None))?,
+        500
+    );
+    assert_eq!(searcher.count(&TermQuery::new(term("2"), 1.0, None))?, 166);
+    let requests = store.get_requests();
+    assert!(requests > 0);
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("common"), 1.0, None))?,
+        500
+    );
+    assert_eq!(store.get_requests(), requests);
+
+    // prefetching the stored fields loads them in one batch
+    let fdt = directory
+        .list_all()?
+        .into_iter()
+        .find(|f| f.ends_with(".fdt"))
+        .unwrap();
+    directory.prefetch(&fdt, &[(0, directory.file_length(&fdt)? as u64)])?;
+    let requests = store.get_requests();
+    let doc = searcher.reader().document(401, &["body".to_string()])?;
+    let body = doc.fields[0].field.field_data().unwrap();
+    assert_eq!(body.get_string().unwrap(), "common body 2");
+    assert_eq!(store.get_requests(), requests);
+
+    // single reads spanning many blocks
+    let mut input = directory.open_input(&fdt, &IOContext::READ)?;
+    let len = input.len() as usize;
+    let mut bytes = vec![0u8; len];
+    input.read_bytes(&mut bytes, 0, len)?;
+    assert_eq!(bytes, store.get_range(&format!("index/{}", fdt), 0, len)?);
+
+    // `get_and_advance` spanning two blocks, failed reads surface on prefill
+    input.seek(60)?;
+    input.prefill(10)?;
+    let encoded = unsafe { std::slice::from_raw_parts(input.get_and_advance(10), 10) };
+    assert_eq!(encoded, &bytes[60..70]);
+    let uncached = ObjectStoreDirectory::new(Arc::clone(&store), "index/", &reader_staging)?
+        .with_block_cache(64, 1 << 20)?;
+    let mut input = uncached.open_input(&fdt, &IOContext::READ)?;
+    store.delete(&format!("index/{}", fdt))?;
+    assert!(input.prefill(10).is_err());
+    Ok(())
+}
+
+#[test]
+fn rate_limited_directory_wrapper() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_rate_limited_directory")?;
+    let directory = Arc::new(RateLimitedDirectoryWrapper::new(FSDirectory::new(
+        &dir_path,
+    )?));
+    directory.set_merge_mb_per_sec(100.0)?;
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..20 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    // lowering the limit applies to the following merges
+    directory.set_merge_mb_per_sec(50.0)?;
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 20);
+    assert_eq!(directory.merge_mb_per_sec(), 50.0);
+    Ok(())
+}
+
+#[test]
+fn metrics_directory_wrapper() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_metrics_directory")?;
+    let directory = Arc::new(MetricsDirectoryWrapper::new(FSDirectory::new(&dir_path)?));
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..20 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let term = Term::new("id".into(), b"7".to_vec());
+    assert_eq!(index_searcher.count(&TermQuery::new(term, 1.0, None))?, 1);
+
+    let stats = directory.stats();
+    assert!(stats.get(IOContextKind::Flush).bytes_written > 0);
+    assert!(stats.get(IOContextKind::Merge).bytes_written > 0);
+    assert!(stats.get(IOContextKind::Search).bytes_read > 0);
+    assert!(stats.total().open_inputs > 0);
+    assert_eq!(stats.total().open_outputs, 0);
+    Ok(())
+}
+
+#[test]
+fn write_lock() -> Result<()> {
+    fn check_exclusive<D: Directory + Send + Sync + 'static>(directory: Arc<D>) -> Result<()> {
+        let config = || Arc::new(IndexWriterConfig::default());
+        let writer = IndexWriter::new(Arc::clone(&directory), config())?;
+        writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+        writer.commit()?;
+        assert!(matches!(
+            IndexWriter::new(Arc::clone(&directory), config()),
+            Err(Error::LockObtainFailed(_))
+        ));
+        writer.close()?;
+
+        let writer = IndexWriter::new(Arc::clone(&directory), config())?;
+        assert_eq!(writer.get_reader(true, false)?.num_docs(), 1);
+        Ok(())
+    }
+
+    let dir_path = new_index_dir("/tmp/test_rucene_native_write_lock")?;
+    check_exclusive(Arc::new(FSDirectory::new(&dir_path)?))?;
+    let dir_path = new_index_dir("/tmp/test_rucene_simple_write_lock")?;
+    check_exclusive(Arc::new(FSDirectory::with_lock_factory(
+        &dir_path,
+        Arc::new(SimpleFSLockFactory),
+    )?))?;
+    assert!(!dir_path.join(WRITE_LOCK_NAME).exists());
+    check_exclusive(Arc::new(ByteBuffersDirectory::new()))?;
+
+    // removing the lock file under a writer makes it fail instead of corrupting the index
+    let dir_path = new_index_dir("/tmp/test_rucene_lost_write_lock")?;
+    let writer = IndexWriter::new(
+        Arc::new(FSDirectory::new(&dir_path)?),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    std::fs::remove_file(dir_path.join(WRITE_LOCK_NAME))?;
+    assert!(writer.commit().is_err());
+    Ok(())
+}
+
+#[test]
+fn write_lock_timeout() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_write_lock_timeout")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+
+    // a writer waiting for the lock gets it once the first one is closed
+    let waiting = {
+        let directory = Arc::clone(&directory);
+        std::thread::spawn(move || -> Result<()> {
+            let config = IndexWriterConfig {
+                write_lock_timeout: Duration::from_secs(30),
+                ..IndexWriterConfig::default()
+            };
+            let writer = IndexWriter::new(directory, Arc::new(config))?;
+            writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+            writer.close()
+        })
+    };
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    std::thread::sleep(Duration::from_millis(100));
+    writer.close()?;
+    waiting.join().unwrap()?;
+
+    // the same through the directory wrapper, giving up after the timeout
+    let directory = Arc::new(SleepingLockWrapper::new(
+        FSDirectory::new(&dir_path)?,
+        Duration::from_millis(200),
+    ));
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 2);
+    let start = Instant::now();
+    assert!(matches!(
+        IndexWriter::new(
+            Arc::clone(&directory),
+            Arc::new(IndexWriterConfig::default())
+        ),
+        Err(Error::LockObtainFailed(_))
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    Ok(())
+}
+
+#[test]
+fn commit_durability() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_commit_durability")?;
+    let directory = Arc::new(MmapDirectory::new(&dir_path)?.with_sync_threads(2));
+    let config = IndexWriterConfig {
+        commit_durability: CommitDurability::NoSync,
+        ..IndexWriterConfig::default()
+    };
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+    writer.close()?;
+
+    // make the bulk load durable once it is done
+    let files: HashSet<String> = directory.list_all()?.into_iter().collect();
+    directory.sync(&files)?;
+    directory.sync_metadata()?;
+
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 10);
+    writer.add_document(vec![new_keyword_field("id".into(), "10".into())])?;
+    writer.commit()?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 11);
+    writer.close()
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+#[test]
+fn uring_directory() -> Result<()> {
+    use rucene::core::store::directory::UringDirectory;
+
+    let dir_path = new_index_dir("/tmp/test_rucene_uring_directory")?;
+    let directory = Arc::new(UringDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    let f = File::open("tests/fixtures/alice.txt").expect("failed to open input file");
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let text = line.expect("failed to read line");
+        writer.add_document(vec![new_index_text_field("title".into(), text)])?;
+        if i % 500 == 499 {
+            writer.commit()?;
+        }
+    }
+    // merges read the flushed segments through io_uring
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    writer.close()?;
+
+    fn count<D: Directory + Send + Sync + 'static>(directory: &Arc<D>) -> Result<i32> {
+        let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+            StandardDirectoryReader::open(Arc::clone(directory))?;
+        let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term = Term::new("title".into(), b"Alice".to_vec());
+        index_searcher.count(&TermQuery::new(term, 1.0, None))
+    }
+    let uring_count = count(&directory)?;
+    assert!(uring_count > 0);
+    assert_eq!(
+        uring_count,
+        count(&Arc::new(MmapDirectory::new(&dir_path)?))?
+    );
+    Ok(())
+}
+
+#[test]
+fn compound_file_threshold() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_compound_file_threshold")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let mut merge_policy = TieredMergePolicy::default();
+    merge_policy.set_no_cfs_ratio(1.0)?;
+    merge_policy.set_max_cfs_segment_size_mb(0.01)?;
+    let mut config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        merge_policy,
+    );
+    config.use_compound_file = true;
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    writer.add_document(vec![new_keyword_field("id".into(), "0".into())])?;
+    writer.commit()?;
+    for i in 1..2000 {
+        writer.add_document(vec![
+            new_keyword_field("id".into(), i.to_string()),
+            new_stored_text_field("body".into(), format!("body of the document {}", i)),
+        ])?;
+    }
+    writer.commit()?;
+
+    let compound = |directory: &Arc<FSDirectory>| -> Result<Vec<bool>> {
+        let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(directory)?;
+        Ok(infos
+            .segments
+            .iter()
+            .map(|s| s.info.is_compound_file())
+            .collect())
+    };
+    // the second flushed segment is above the 10KB threshold
+    assert_eq!(compound(&directory)?, vec![true, false]);
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    assert_eq!(compound(&directory)?, vec![false]);
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 2000);
+    Ok(())
+}
+
+#[test]
+fn rollback() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_rollback")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    writer.commit()?;
+    writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"1".to_vec())])?;
+    // flush a segment that is never committed
+    writer.get_reader(true, false)?;
+    writer.rollback()?;
+    assert!(writer.is_closed());
+    assert!(writer
+        .add_document(vec![new_keyword_field("id".into(), "3".into())])
+        .is_err());
+
+    // only the files of the last commit are left
+    let infos = SegmentInfos::<FSDirectory, CodecEnum>::read_latest_commit(&directory)?;
+    let committed = infos.files(true);
+    for file in directory.list_all()? {
+        assert!(
+            file == "write.lock" || committed.contains(&file),
+            "uncommitted file {} was not deleted",
+            file
+        );
+    }
+
+    let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 1);
+    let index_searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let query = TermQuery::new(Term::new("id".into(), b"1".to_vec()), 1.0, None);
+    assert_eq!(index_searcher.count(&query)?, 1);
+    Ok(())
+}
+
+#[test]
+fn two_phase_commit() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_two_phase_commit")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+
+    let mut user_data = HashMap::new();
+    user_data.insert("offset".to_string(), "10".to_string());
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    writer.set_live_commit_data(user_data.clone())?;
+    let seq_no = writer.prepare_commit()?;
+    // not visible before the second phase
+    assert!(SegmentInfos::<FSDirectory, CodecEnum>::read_latest_commit(&directory).is_err());
+    assert!(writer.prepare_commit().is_err());
+    assert_eq!(writer.commit()?, seq_no);
+
+    let infos = SegmentInfos::<FSDirectory, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(infos.user_data, user_data);
+    assert_eq!(infos.total_max_doc(), 1);
+
+    // changing only the user data is a change to commit
+    user_data.insert("offset".to_string(), "20".to_string());
+    writer.set_live_commit_data(user_data.clone())?;
+    assert!(writer.commit()? > 0);
+    let infos = SegmentInfos::<FSDirectory, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(infos.user_data["offset"], "20");
+
+    // a prepared commit is discarded by rollback
+    writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    let mut rolled_back = HashMap::new();
+    rolled_back.insert("offset".to_string(), "30".to_string());
+    writer.set_live_commit_data(rolled_back)?;
+    writer.prepare_commit()?;
+    writer.rollback()?;
+
+    let infos = SegmentInfos::<FSDirectory, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(infos.user_data, user_data);
+    assert_eq!(infos.total_max_doc(), 1);
+
+    let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+    assert_eq!(writer.live_commit_data()?, user_data);
+    Ok(())
+}
+
+#[test]
+fn persistent_snapshots() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_snapshots")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let new_config = |policy: &Arc<
+        PersistentSnapshotDeletionPolicy<FSDirectory, KeepOnlyLastCommitDeletionPolicy>,
+    >| {
+        let mut config = IndexWriterConfig::default();
+        config.index_deletion_policy = policy.clone();
+        Arc::new(config)
+    };
+
+    let policy = Arc::new(PersistentSnapshotDeletionPolicy::new(
+        KeepOnlyLastCommitDeletionPolicy,
+        Arc::clone(&directory),
+        OpenMode::Create,
+    )?);
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config(&policy))?;
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    writer.commit()?;
+    let snapshot = policy.snapshot()?;
+    assert_eq!(policy.last_save_file()?, Some("snapshots_0".to_string()));
+
+    writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    writer.commit()?;
+    // the snapshotted commit is kept although it is not the last one anymore
+    let files = directory.list_all()?;
+    for file in snapshot.file_names() {
+        assert!(files.contains(file), "snapshot file {} was deleted", file);
+    }
+    writer.close()?;
+
+    // the snapshot survives a restart
+    let policy = Arc::new(PersistentSnapshotDeletionPolicy::new(
+        KeepOnlyLastCommitDeletionPolicy,
+        Arc::clone(&directory),
+        OpenMode::Append,
+    )?);
+    assert_eq!(policy.snapshot_count()?, 1);
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config(&policy))?;
+    let restored = policy.index_commit(snapshot.generation())?.unwrap();
+    assert_eq!(restored.file_names(), snapshot.file_names());
+
+    writer.add_document(vec![new_keyword_field("id".into(), "3".into())])?;
+    writer.commit()?;
+    let segments_file = snapshot.segments_file_name().to_string();
+    assert!(directory.list_all()?.contains(&segments_file));
+
+    policy.release(&restored)?;
+    assert_eq!(policy.snapshot_count()?, 0);
+    assert!(policy.release(&restored).is_err());
+    writer.delete_unused_files()?;
+    assert!(!directory.list_all()?.contains(&segments_file));
+    Ok(())
+}
+
+#[derive(Default)]
+struct RecordingListener {
+    events: Mutex<Vec<String>>,
+}
+
+impl RecordingListener {
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl IndexWriterEventListener for RecordingListener {
+    fn on_flush_start(&self) {
+        self.record("flush_start".into());
+    }
+
+    fn on_flush_end(&self) {
+        self.record("flush_end".into());
+    }
+
+    fn on_segment_published(&self, segment: &SegmentEventInfo) {
+        assert!(segment.size_in_bytes > 0);
+        self.record(format!("publish {} {}", segment.name, segment.max_doc));
+    }
+
+    fn on_merge_start(&self, _merge_id: u32, segments: &[SegmentEventInfo]) {
+        let names: Vec<_> = segments.iter().map(|s| s.name.as_str()).collect();
+        self.record(format!("merge_start {}", names.join(",")));
+    }
+
+    fn on_merge_end(&self, _merge_id: u32, merged: Option<&SegmentEventInfo>) {
+        let merged = merged.map_or(0, |s| s.max_doc);
+        self.record(format!("merge_end {}", merged));
+    }
+
+    fn on_commit(&self, segments_file_name: &str, generation: i64) {
+        self.record(format!("commit {} {}", segments_file_name, generation));
+    }
+
+    fn on_auto_commit(&self, trigger: AutoCommitTrigger, _seq_no: i64) {
+        self.record(format!("auto_commit {:?}", trigger));
+    }
+}
+
+#[test]
+fn event_listener() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_event_listener")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let listener = Arc::new(RecordingListener::default());
+    let mut config = IndexWriterConfig::default();
+    config.event_listener = Arc::clone(&listener) as Arc<dyn IndexWriterEventListener>;
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    writer.commit()?;
+    assert_eq!(
+        listener.take(),
+        vec![
+            "flush_start",
+            "publish _0 1",
+            "flush_end",
+            "commit segments_1 1",
+        ]
+    );
+
+    writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    writer.add_document(vec![new_keyword_field("id".into(), "3".into())])?;
+    writer.flush()?;
+    writer.flush()?;
+    assert_eq!(
+        listener.take(),
+        vec![
+            "flush_start",
+            "publish _1 2",
+            "flush_end",
+            "flush_start",
+            "flush_end",
+        ]
+    );
+
+    writer.force_merge(1, true)?;
+    // the merge is preceded by a (no-op) flush
+    assert_eq!(
+        listener.take(),
+        vec![
+            "flush_start",
+            "flush_end",
+            "merge_start _1,_0",
+            "merge_end 3"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn searcher_manager() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_searcher_manager")?;
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+
+    let manager = SearcherManager::from_writer(
+        &writer,
+        true,
+        false,
+        DefaultSearcherFactory::default(),
+        None::<Arc<dyn RefreshListener>>,
+    )?;
+    let query = TermQuery::new(Term::new("id".into(), b"2".to_vec()), 1.0, None);
+    let old = manager.acquire()?;
+    assert_eq!(old.reader().num_docs(), 1);
+    // held by the manager and by us
+    assert_eq!(manager.ref_count(&old), 2);
+
+    // no changes, the current searcher is kept
+    assert!(manager.maybe_refresh()?);
+    let same = manager.acquire()?;
+    assert!(Arc::ptr_eq(&old, &same));
+    manager.release(same)?;
+
+    writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    assert!(manager.maybe_refresh()?);
+    let new = manager.acquire()?;
+    assert_eq!(new.reader().num_docs(), 2);
+    assert_eq!(new.count(&query)?, 1);
+    // the replaced searcher is still usable until released
+    assert_eq!(manager.ref_count(&old), 1);
+    assert_eq!(old.count(&query)?, 0);
+    manager.release(old)?;
+
+    manager.close()?;
+    assert!(manager.acquire().is_err());
+    assert_eq!(new.count(&query)?, 1);
+    manager.release(new)?;
+    Ok(())
+}
+
+#[test]
+fn controlled_real_time_reopen() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_controlled_reopen")?;
+    let manager = Arc::new(SearcherManager::from_writer(
+        &writer,
+        true,
+        false,
+        DefaultSearcherFactory::default(),
+        None::<Arc<dyn RefreshListener + Send + Sync>>,
+    )?);
+    // only reopens on demand during the test
+    let mut reopen_thread = ControlledRealTimeReopenThread::new(
+        &writer,
+        Arc::clone(&manager),
+        Duration::from_secs(60),
+        Duration::from_millis(0),
+    )?;
+
+    let query = TermQuery::new(Term::new("id".into(), b"1".to_vec()), 1.0, None);
+    let gen = writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    assert!(reopen_thread.wait_for_generation(gen + 1, None).is_err());
+    assert!(reopen_thread.wait_for_generation(gen, Some(Duration::from_secs(10)))?);
+    assert!(reopen_thread.searching_generation() >= gen);
+    let searcher = manager.acquire()?;
+    assert_eq!(searcher.count(&query)?, 1);
+    manager.release(searcher)?;
+
+    let gen = writer.delete_documents_by_terms(vec![Term::new("id".into(), b"1".to_vec())])?;
+    assert!(reopen_thread.wait_for_generation(gen, None)?);
+    let searcher = manager.acquire()?;
+    assert_eq!(searcher.count(&query)?, 0);
+    manager.release(searcher)?;
+
+    reopen_thread.close();
+    assert!(reopen_thread.wait_for_generation(gen, None)?);
+    Ok(())
+}
+
+#[test]
+fn flush_triggers() -> Result<()> {
+    let mut config = IndexWriterConfig::default();
+    assert!(config.set_ram_buffer_size_mb(Some(0.0)).is_err());
+    assert!(config.set_max_buffered_docs(Some(1)).is_err());
+    assert!(config.set_ram_per_thread_hard_limit_mb(2048).is_err());
+
+    // flush by doc count
+    let dir_path = new_index_dir("/tmp/test_rucene_flush_by_doc_count")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let listener = Arc::new(RecordingListener::default());
+    let mut config = IndexWriterConfig::default();
+    config.set_max_buffered_docs(Some(2))?;
+    config.event_listener = Arc::clone(&listener) as Arc<dyn IndexWriterEventListener>;
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    for i in 0..5 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+    let published: Vec<_> = listener
+        .take()
+        .into_iter()
+        .filter(|e| e.starts_with("publish"))
+        .collect();
+    assert_eq!(
+        published,
+        vec!["publish _0 2", "publish _1 2", "publish _2 1"]
+    );
+
+    // flush by RAM usage
+    let dir_path = new_index_dir("/tmp/test_rucene_flush_by_ram")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let listener = Arc::new(RecordingListener::default());
+    let mut config = IndexWriterConfig::default();
+    config.set_ram_buffer_size_mb(Some(0.5))?;
+    config.event_listener = Arc::clone(&listener) as Arc<dyn IndexWriterEventListener>;
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    for i in 0..20000 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+    let published = listener
+        .take()
+        .iter()
+        .filter(|e| e.starts_with("publish"))
+        .count();
+    assert!(published > 1);
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 20000);
+    Ok(())
+}
+
+fn index_with_merge_policy<MP: MergePolicy>(path: &str, merge_policy: MP) -> Result<(usize, i32)> {
+    let dir_path = new_index_dir(path)?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        merge_policy,
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    for i in 0..5 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        writer.commit()?;
+        writer.explicit_merge()?;
+    }
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    Ok((reader.leaves().len(), reader.num_docs()))
+}
+
+#[test]
+fn merge_policies() -> Result<()> {
+    let (segments, num_docs) =
+        index_with_merge_policy("/tmp/test_rucene_no_merge_policy", NoMergePolicy)?;
+    assert_eq!(segments, 5);
+    assert_eq!(num_docs, 5);
+
+    // all the segments are below the min merge size so they are on the same
+    // level, and merged once there are merge_factor of them
+    let mut policy = LogDocMergePolicy::default();
+    assert!(policy.set_merge_factor(1).is_err());
+    policy.set_merge_factor(2)?;
+    let (segments, num_docs) =
+        index_with_merge_policy("/tmp/test_rucene_log_doc_merge_policy", policy)?;
+    assert_eq!(segments, 1);
+    assert_eq!(num_docs, 5);
+
+    let mut policy = LogByteSizeMergePolicy::default();
+    policy.set_merge_factor(3)?;
+    let (segments, num_docs) =
+        index_with_merge_policy("/tmp/test_rucene_log_byte_size_merge_policy", policy)?;
+    assert_eq!(segments, 1);
+    assert_eq!(num_docs, 5);
+    Ok(())
+}
+
+#[test]
+fn log_merge_policy_force_merge() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_log_merge_policy_force_merge")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    // a merge factor above the number of segments leaves them to force_merge
+    let mut policy = LogDocMergePolicy::default();
+    policy.set_merge_factor(10)?;
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        policy,
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    for i in 0..5 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        writer.commit()?;
+    }
+    writer.explicit_merge()?;
+    assert_eq!(writer.get_reader(true, false)?.leaves().len(), 5);
+
+    writer.force_merge(2, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert!(reader.leaves().len() <= 2);
+    assert_eq!(reader.num_docs(), 5);
+
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 5);
+    Ok(())
+}
+
+fn assert_sorted_by_price<C: Codec>(leaf: &LeafReaderContext<'_, C>) -> Result<()> {
+    let values = leaf.reader.get_numeric_doc_values("price")?;
+    for doc in 1..leaf.reader.max_doc() {
+        assert!(values.get(doc - 1)? <= values.get(doc)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn index_sort() -> Result<()> {
+    let price_sort = || {
+        Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            false,
+        ))])
+    };
+    let mut config = IndexWriterConfig::default();
+    assert!(config.set_index_sort(Some(Sort::new(vec![]))).is_err());
+    assert!(config
+        .set_index_sort(Some(Sort::new(vec![SortField::new_score()])))
+        .is_err());
+    let string_sort = SimpleSortField::new("name".into(), SortFieldType::String, false);
+    assert!(config
+        .set_index_sort(Some(Sort::new(vec![SortField::Simple(string_sort)])))
+        .is_err());
+    let mut bad_missing = SimpleSortField::new("price".into(), SortFieldType::Long, false);
+    bad_missing.set_missing_value(Some(VariantValue::Int(0)));
+    assert!(config
+        .set_index_sort(Some(Sort::new(vec![SortField::Simple(bad_missing)])))
+        .is_err());
+
+    let dir_path = new_index_dir("/tmp/test_rucene_index_sort")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    config.set_index_sort(Some(price_sort()))?;
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let new_doc = |id: i64| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), id.to_string())),
+            Box::new(NumericDocValuesField::new("price", (id * 7) % 11)),
+        ]
+    };
+    for i in 0..20 {
+        writer.add_document(new_doc(i))?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    // the sort field must be indexed with the doc values type it sorts on
+    assert!(writer
+        .add_document(vec![SortedNumericDocValuesField::new("price", 1)])
+        .is_err());
+
+    // flushed segments are sorted
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 2);
+    for leaf in reader.leaves() {
+        assert_eq!(leaf.reader.index_sort(), Some(&price_sort()));
+        assert_sorted_by_price(&leaf)?;
+    }
+
+    // and merges keep them sorted
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 20);
+    let leaf = &reader.leaves()[0];
+    assert_eq!(leaf.reader.index_sort(), Some(&price_sort()));
+    assert_sorted_by_price(leaf)?;
+    writer.close()?;
+
+    // the index sort can't be changed
+    let mut config = IndexWriterConfig::default();
+    config.set_index_sort(Some(Sort::new(vec![SortField::Simple(
+        SimpleSortField::new("price".into(), SortFieldType::Long, true),
+    )])))?;
+    assert!(IndexWriter::new(directory, Arc::new(config)).is_err());
+    Ok(())
+}
+
+#[test]
+fn document_blocks() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_document_blocks")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let mut config = IndexWriterConfig::default();
+    config.set_max_buffered_docs(Some(3))?;
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+    let new_doc = |block: i64, parent: bool| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("block_id".into(), block.to_string())),
+            Box::new(NumericDocValuesField::new("block", block)),
+            Box::new(NumericDocValuesField::new("parent", parent as i64)),
+        ]
+    };
+    // the children first and the parent last
+    let new_block = |block: i64| {
+        let mut docs: Vec<_> = (0..block % 3 + 1).map(|_| new_doc(block, false)).collect();
+        docs.push(new_doc(block, true));
+        docs
+    };
+    for block in 0..6 {
+        writer.add_documents(new_block(block))?;
+        writer.add_document(new_doc(-1, false))?;
+        if block % 2 == 1 {
+            writer.commit()?;
+        }
+    }
+    writer.update_documents(
+        new_block(2),
+        Some(Term::new("block_id".into(), b"2".to_vec())),
+    )?;
+    writer.commit()?;
+
+    // none of the docs of a failed block are added
+    let mut failed = new_block(6);
+    failed[1].push(Box::new(SortedNumericDocValuesField::new("parent", 0)));
+    assert!(writer.add_documents(failed).is_err());
+    writer.commit()?;
+
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 6 + 2 + 3 + 4 + 2 + 3 + 4);
+    assert_eq!(reader.max_doc(), reader.num_docs());
+
+    // each block is still contiguous, with its parent last
+    let leaf = &reader.leaves()[0];
+    let blocks = leaf.reader.get_numeric_doc_values("block")?;
+    let parents = leaf.reader.get_numeric_doc_values("parent")?;
+    let mut runs: Vec<(i64, i64)> = vec![];
+    for doc in 0..leaf.reader.max_doc() {
+        let block = blocks.get(doc)?;
+        if block < 0 {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((b, len)) if *b == block && parents.get(doc - 1)? == 0 => *len += 1,
+            _ => runs.push((block, 1)),
+        }
+        assert_eq!(
+            parents.get(doc)? == 1,
+            runs.last().unwrap().1 == block % 3 + 2
+        );
+    }
+    runs.sort();
+    assert_eq!(
+        runs,
+        (0..6)
+            .map(|block| (block, block % 3 + 2))
+            .collect::<Vec<_>>()
+    );
+    writer.close()?;
+
+    // the index sort would split the blocks
+    let dir_path = new_index_dir("/tmp/test_rucene_sorted_document_blocks")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let mut config = IndexWriterConfig::default();
+    config.set_index_sort(Some(Sort::new(vec![SortField::Simple(
+        SimpleSortField::new("block".into(), SortFieldType::Long, false),
+    )])))?;
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    assert!(writer.add_documents(new_block(0)).is_err());
+    writer.add_documents(vec![new_doc(0, true)])?;
+    Ok(())
+}
+
+#[test]
+fn check_index() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_check_index")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        NoMergePolicy {},
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..30 {
+        let fields: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(new_index_text_field(
+                "title".into(),
+                format!("title {} of {}", i, i % 7),
+            )),
+            Box::new(new_stored_text_field("body".into(), format!("body {}", i))),
+            Box::new(NumericDocValuesField::new("price".into(), i64::from(i) * 3)),
+        ];
+        writer.add_document(fields)?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"12".to_vec())])?;
+    writer.commit()?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    let status = checker.check_index()?;
+    assert!(status.clean);
+    assert_eq!(status.num_segments, 3);
+    let seg = &status.segment_infos[1];
+    assert!(seg.is_clean());
+    assert_eq!(seg.max_doc, 10);
+    assert_eq!(seg.live_docs.as_ref().unwrap().num_deleted, 1);
+    assert_eq!(seg.stored_fields.as_ref().unwrap().doc_count, 9);
+    assert_eq!(seg.term_vectors.as_ref().unwrap().doc_count, 9);
+    assert_eq!(seg.doc_values.as_ref().unwrap().tot_numeric_fields, 1);
+    assert!(seg.postings.as_ref().unwrap().tot_pos > 0);
+    // nothing to remove from a clean index
+    checker.exorcise_index(&status)?;
+
+    // flip a byte in the middle of the biggest file of the second segment
+    let broken = &seg.name;
+    let mut biggest: Option<(u64, std::path::PathBuf)> = None;
+    for entry in std::fs::read_dir(dir_path)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().unwrap();
+        let len = entry.metadata()?.len();
+        if name.starts_with(&format!("{}.", broken))
+            && !name.ends_with(".si")
+            && biggest.as_ref().map_or(true, |b| b.0 < len)
+        {
+            biggest = Some((len, entry.path()));
+        }
+    }
+    let (len, path) = biggest.unwrap();
+    let mut bytes = std::fs::read(&path)?;
+    bytes[len as usize / 2] ^= 0xff;
+    std::fs::write(&path, bytes)?;
+
+    let mut checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    checker.set_checksums_only(true);
+    let status = checker.check_index()?;
+    assert!(!status.clean);
+    assert_eq!(status.num_bad_segments, 1);
+    assert_eq!(status.tot_lose_doc_count, 9);
+    assert!(!status.segment_infos[1].is_clean());
+    assert!(status.segment_infos[1].stored_fields.is_none());
+    assert!(status.segment_infos[0].is_clean() && status.segment_infos[2].is_clean());
+
+    checker.exorcise_index(&status)?;
+    let status = checker.check_index()?;
+    assert!(status.clean);
+    assert_eq!(status.num_segments, 2);
+
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        NoMergePolicy {},
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 20);
+    Ok(())
+}
+
+#[test]
+fn check_integrity() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_check_integrity")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let new_config = |verify_checksums_on_open| {
+        let mut config = IndexWriterConfig::new(
+            Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+            SerialMergeScheduler {},
+            NoMergePolicy {},
+        );
+        config.verify_checksums_on_open = verify_checksums_on_open;
+        Arc::new(config)
+    };
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config(false))?;
+    for i in 0..20 {
+        writer.add_document(vec![
+            new_keyword_field("id".into(), i.to_string()),
+            new_stored_text_field("body".into(), format!("body {}", i)),
+        ])?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    let num_files = checker.verify_checksums()?;
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let segment_files: usize = infos.segments.iter().map(|s| s.files().len()).sum();
+    assert_eq!(num_files, segment_files + 1);
+    {
+        let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+            StandardDirectoryReader::open(Arc::clone(&directory))?;
+        for leaf in reader.leaves() {
+            leaf.reader.check_integrity()?;
+        }
+    }
+    IndexWriter::new(Arc::clone(&directory), new_config(true))?.close()?;
+
+    // flip a byte of the stored fields of the second segment
+    let path = dir_path.join(format!("{}.fdt", infos.segments[1].info.name));
+    let mut bytes = std::fs::read(&path)?;
+    let len = bytes.len();
+    bytes[len / 2] ^= 0xff;
+    std::fs::write(&path, bytes)?;
+
+    let is_corrupt = |r: Result<()>| matches!(r, Err(Error::CorruptIndex(_)));
+    assert!(is_corrupt(checker.verify_checksums().map(|_| ())));
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, NoMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    reader.leaves()[0].reader.check_integrity()?;
+    assert!(is_corrupt(reader.leaves()[1].reader.check_integrity()));
+    drop(reader);
+
+    assert!(is_corrupt(
+        IndexWriter::new(Arc::clone(&directory), new_config(true)).map(|_| ())
+    ));
+    // the corruption goes unnoticed when the checksums aren't verified on open
+    let writer = IndexWriter::new(directory, new_config(false))?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 20);
+    Ok(())
+}
+
+#[test]
+fn index_upgrader() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_index_upgrader")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let new_config = || {
+        IndexWriterConfig::new(
+            Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+            SerialMergeScheduler {},
+            TieredMergePolicy::default(),
+        )
+    };
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(new_config()))?;
+    for i in 0..30 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    writer.close()?;
+
+    // pretend the second segment was written by an older version
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let names: Vec<_> = infos.segments.iter().map(|s| s.info.name.clone()).collect();
+    let mut info = infos.segments[1].info.clone();
+    info.version = Version::new(6, 0, 0)?;
+    directory.delete_file(&format!("{}.si", info.name))?;
+    let codec = Arc::clone(info.codec());
+    codec
+        .segment_info_format()
+        .write(&directory, &mut info, &IOContext::Default)?;
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(infos.segments[1].info.version, Version::new(6, 0, 0)?);
+
+    IndexUpgrader::new(Arc::clone(&directory), new_config()).upgrade()?;
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(infos.segments.len(), 3);
+    assert!(infos
+        .segments
+        .iter()
+        .all(|s| s.info.version == VERSION_LATEST));
+    // only the old segment was rewritten, the new one takes its place
+    assert_eq!(infos.segments[0].info.name, names[0]);
+    assert!(!names.contains(&infos.segments[1].info.name));
+    assert_eq!(infos.segments[2].info.name, names[2]);
+    assert_eq!(infos.total_max_doc(), 30);
+
+    // an up to date index is left alone
+    IndexUpgrader::new(Arc::clone(&directory), new_config()).upgrade()?;
+    let upgraded = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let upgraded_names: Vec<_> = upgraded.segments.iter().map(|s| &s.info.name).collect();
+    let names: Vec<_> = infos.segments.iter().map(|s| &s.info.name).collect();
+    assert_eq!(upgraded_names, names);
+
+    let empty_dir = new_index_dir("/tmp/test_rucene_index_upgrader_empty")?;
+    let upgrader = IndexUpgrader::new(Arc::new(FSDirectory::new(&empty_dir)?), new_config());
+    assert!(upgrader.upgrade().is_err());
+    Ok(())
+}
+
+struct KeepAllCommitsDeletionPolicy;
+
+impl IndexDeletionPolicy for KeepAllCommitsDeletionPolicy {
+    fn on_init(&self, _commits: Vec<&mut CommitPoint>) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_commit(&self, _commits: Vec<&mut CommitPoint>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn open_commit() -> Result<()> {
+    type Reader =
+        StandardDirectoryReader<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+    let dir_path = new_index_dir("/tmp/test_rucene_open_commit")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let mut config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    config.index_deletion_policy = Arc::new(KeepAllCommitsDeletionPolicy);
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for step in 0..3 {
+        for i in 0..10 {
+            let id = (step * 10 + i).to_string();
+            writer.add_document(vec![new_keyword_field("id".into(), id)])?;
+        }
+        let mut user_data = HashMap::new();
+        user_data.insert("step".to_string(), step.to_string());
+        writer.set_live_commit_data(user_data)?;
+        writer.commit()?;
+    }
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"0".to_vec())])?;
+    writer.set_live_commit_data(HashMap::new())?;
+    writer.commit()?;
+
+    let commits = list_commits::<_, CodecEnum>(&directory)?;
+    assert!(commits.len() >= 4);
+    assert!(commits
+        .windows(2)
+        .all(|w| w[0].generation() < w[1].generation()));
+    let latest = commits.last().unwrap();
+    assert!(latest.user_data().is_empty());
+    assert_eq!(
+        Reader::open_commit(Arc::clone(&directory), latest)?.num_docs(),
+        29
+    );
+
+    let mut steps = 0;
+    for commit in &commits {
+        if let Some(step) = commit.user_data().get("step") {
+            let step: i32 = step.parse()?;
+            let reader = Reader::open_commit(Arc::clone(&directory), commit)?;
+            assert_eq!(reader.num_docs(), (step + 1) * 10);
+            // a reader on a past commit can be moved to another one
+            let latest_reader = reader.open_if_changed(Some(latest))?.unwrap();
+            assert_eq!(latest_reader.num_docs(), 29);
+            steps += 1;
+        }
+    }
+    assert_eq!(steps, 3);
+    writer.close()?;
+
+    // the default deletion policy only keeps the latest commit
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    writer.add_document(vec![new_keyword_field("id".into(), "30".into())])?;
+    writer.commit()?;
+    let commits = list_commits::<_, CodecEnum>(&directory)?;
+    assert_eq!(commits.len(), 1);
+    assert!(Reader::open_commit(Arc::clone(&directory), latest).is_err());
+
+    let empty_dir = new_index_dir("/tmp/test_rucene_open_commit_empty")?;
+    assert!(list_commits::<_, CodecEnum>(&Arc::new(FSDirectory::new(&empty_dir)?)).is_err());
+    Ok(())
+}
+
+#[test]
+fn merge_rate_limit() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_merge_rate_limit")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    assert!(writer.merge_mb_per_sec().is_infinite());
+    assert!(writer.set_merge_mb_per_sec(0.0).is_err());
+
+    for i in 0..20000 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        if i % 5000 == 4999 {
+            writer.commit()?;
+        }
+    }
+    // every merge goes through the limiter, not only the first one of the
+    // merge thread
+    writer.force_merge(2, true)?;
+    writer.commit()?;
+
+    writer.set_merge_mb_per_sec(1.0)?;
+    assert_eq!(writer.merge_mb_per_sec(), 1.0);
+    let start = Instant::now();
+    writer.force_merge(1, true)?;
+    let elapsed = start.elapsed();
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 20000);
+    let mut index_bytes = 0;
+    for entry in std::fs::read_dir(dir_path)? {
+        index_bytes += entry?.metadata()?.len();
+    }
+    // allow for the bytes written before the first pause of each file
+    let min_secs = index_bytes as f64 / 2.0 / 1024.0 / 1024.0;
+    assert!(elapsed.as_secs_f64() >= min_secs);
+    Ok(())
+}
+
+#[test]
+fn nrt_reopen() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_nrt_reopen")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        NoMergePolicy {},
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    let id = |v: &str| new_keyword_field("id".into(), v.into());
+    let leaf_ptrs = |reader: &StandardDirectoryReader<_, _, _, _>| -> Vec<*const u8> {
+        reader
+            .leaves()
+            .iter()
+            .map(|l| l.reader as *const _ as *const u8)
+            .collect()
+    };
+
+    writer.add_document(vec![id("1")])?;
+    writer.add_document(vec![id("2")])?;
+    let reader1 = writer.get_reader(true, false)?;
+    assert!(reader1.open_if_changed(None)?.is_none());
+
+    // the reader of the unchanged first segment is shared
+    writer.add_document(vec![id("3")])?;
+    writer.add_document(vec![id("4")])?;
+    let reader2 = reader1.open_if_changed(None)?.unwrap();
+    assert_eq!(reader2.num_docs(), 4);
+    let (ptrs1, ptrs2) = (leaf_ptrs(&reader1), leaf_ptrs(&reader2));
+    assert_eq!(ptrs2.len(), 2);
+    assert_eq!(ptrs1[0], ptrs2[0]);
+
+    // only the segment with the new delete is reopened
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"3".to_vec())])?;
+    let reader3 = reader2.open_if_changed(None)?.unwrap();
+    assert_eq!(reader3.num_docs(), 3);
+    assert_eq!(reader3.leaves()[1].reader.num_docs(), 1);
+    let ptrs3 = leaf_ptrs(&reader3);
+    assert_eq!(ptrs2[0], ptrs3[0]);
+    assert_ne!(ptrs2[1], ptrs3[1]);
+    // the previous reader still sees the deleted doc
+    assert_eq!(reader2.num_docs(), 4);
+    assert!(reader3.open_if_changed(None)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn multi_reader() -> Result<()> {
+    let mut sub_readers: Vec<Arc<dyn IndexReader<Codec = CodecEnum>>> = vec![];
+    let mut writers = vec![];
+    for day in 0..3 {
+        let path = format!("/tmp/test_rucene_multi_reader_{}", day);
+        let dir_path = new_index_dir(&path)?;
+        let directory = Arc::new(FSDirectory::new(&dir_path)?);
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+        // the second partition is empty
+        if day != 1 {
+            for i in 0..5 {
+                writer.add_document(vec![
+                    new_keyword_field("id".into(), format!("{}-{}", day, i)),
+                    new_stored_text_field("day".into(), day.to_string()),
+                ])?;
+            }
+        }
+        sub_readers.push(Arc::new(writer.get_reader(true, false)?));
+        writers.push(writer);
+    }
+
+    let reader = MultiReader::new(sub_readers)?;
+    assert_eq!(reader.max_doc(), 10);
+    assert_eq!(reader.num_docs(), 10);
+    let leaves = reader.leaves();
+    assert_eq!(leaves.len(), 2);
+    assert_eq!(leaves[1].ord, 1);
+    assert_eq!(leaves[1].doc_base, 5);
+
+    // the doc ids are re-based over the sub-readers
+    let day = |reader: &MultiReader<CodecEnum>, doc| -> Result<String> {
+        let doc = reader.document(doc, &["day".to_string()])?;
+        Ok(doc.fields[0]
+            .field
+            .field_data()
+            .unwrap()
+            .get_string()
+            .unwrap()
+            .to_string())
+    };
+    assert_eq!(day(&reader, 4)?, "0");
+    assert_eq!(day(&reader, 5)?, "2");
+    assert!(reader.document(10, &[]).is_err());
+
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let query = TermQuery::new(Term::new("id".into(), b"2-3".to_vec()), 1.0, None);
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(&query, &mut collector)?;
+    let top_docs = collector.top_docs();
+    assert_eq!(top_docs.total_hits(), 1);
+    assert_eq!(top_docs.score_docs()[0].doc_id(), 8);
+
+    // only the changed sub-readers are reopened
+    writers[1].add_document(vec![new_keyword_field("id".into(), "1-0".into())])?;
+    let reader = searcher.reader().refresh()?.unwrap();
+    assert_eq!(reader.max_doc(), 11);
+    assert_eq!(reader.leaves()[2].doc_base, 6);
+    Ok(())
+}
+
+#[test]
+fn parallel_leaf_reader() -> Result<()> {
+    let open_writer = |path: &str| -> Result<IndexWriter<_, _, _, _>> {
+        let directory = Arc::new(FSDirectory::new(new_index_dir(path)?)?);
+        IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))
+    };
+    // the original corpus
+    let writer1 = open_writer("/tmp/test_rucene_parallel_1")?;
+    // the field added later, indexed in the same doc order
+    let writer2 = open_writer("/tmp/test_rucene_parallel_2")?;
+    for i in 0..10 {
+        writer1.add_document(vec![new_stored_text_field(
+            "title".into(),
+            format!("title {}", i),
+        )])?;
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(new_keyword_field("tag".into(), (i % 2).to_string())),
+            Box::new(NumericDocValuesField::new("price", i * 10)),
+        ];
+        if i == 9 {
+            doc.push(Box::new(new_stored_text_field(
+                "note".into(),
+                "last".into(),
+            )));
+        }
+        writer2.add_document(doc)?;
+    }
+    writer1.add_document(vec![new_stored_text_field("title".into(), "extra".into())])?;
+    let reader1 = writer1.get_reader(true, false)?;
+    let reader2 = writer2.get_reader(true, false)?;
+
+    let leaf = |reader: &StandardDirectoryReader<_, _, _, _>| -> Arc<SearchLeafReader<CodecEnum>> {
+        Arc::clone(&reader.segment_readers()[0]) as Arc<SearchLeafReader<CodecEnum>>
+    };
+    // the docs are not aligned anymore
+    assert!(ParallelLeafReader::new(vec![leaf(&reader1), leaf(&reader2)]).is_err());
+    writer2.add_document(vec![new_keyword_field("tag".into(), "extra".into())])?;
+    writer2.force_merge(1, true)?;
+    let reader2 = writer2.get_reader(true, false)?;
+
+    let reader = ParallelLeafReader::new(vec![leaf(&reader1), leaf(&reader2)])?;
+    assert_eq!(IndexReader::max_doc(&reader), 11);
+    assert!(reader.field_info("title").is_some());
+    assert!(reader.field_info("tag").is_some());
+    let doc = IndexReader::document(&reader, 9, &["title".into(), "note".into()])?;
+    assert_eq!(doc.fields.len(), 2);
+    assert_eq!(reader.get_numeric_doc_values("price")?.get(3)?, 30);
+
+    // the added field is searchable, and the matches see the original fields
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let query = TermQuery::new(Term::new("tag".into(), b"1".to_vec()), 1.0, None);
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(&query, &mut collector)?;
+    let top_docs = collector.top_docs();
+    assert_eq!(top_docs.total_hits(), 5);
+    for hit in top_docs.score_docs() {
+        let doc = IndexReader::document(searcher.reader(), hit.doc_id(), &["title".into()])?;
+        let title = doc.fields[0]
+            .field
+            .field_data()
+            .unwrap()
+            .get_string()
+            .unwrap()
+            .to_string();
+        assert_eq!(title, format!("title {}", hit.doc_id()));
+    }
+    Ok(())
+}
+
+// hides the field `secret` and the docs with an odd `num`
+struct HideOddSecrets {
+    reader: Arc<SearchLeafReader<CodecEnum>>,
+    field_infos: Arc<FieldInfos>,
+    live_docs: BitsRef,
+    num_docs: i32,
+}
+
+impl HideOddSecrets {
+    fn new(reader: Arc<SearchLeafReader<CodecEnum>>) -> Result<Self> {
+        let infos = reader
+            .field_infos()
+            .by_number
+            .values()
+            .filter(|fi| fi.name != "secret")
+            .map(|fi| FieldInfo::clone(fi))
+            .collect();
+        let nums = reader.get_numeric_doc_values("num")?;
+        let mut live_docs = FixedBitSet::new(reader.max_doc() as usize);
+        let mut num_docs = 0;
+        for doc in 0..reader.max_doc() {
+            if reader.live_docs().get(doc as usize) && nums.get(doc)? % 2 == 0 {
+                live_docs.set(doc as usize);
+                num_docs += 1;
+            }
+        }
+        Ok(HideOddSecrets {
+            reader,
+            field_infos: Arc::new(FieldInfos::new(infos)?),
+            live_docs: Arc::new(live_docs),
+            num_docs,
+        })
+    }
+}
+
+impl FilterLeafReader for HideOddSecrets {
+    type Codec = CodecEnum;
+
+    fn delegate(&self) -> &SearchLeafReader<CodecEnum> {
+        self.reader.as_ref()
+    }
+
+    fn terms(&self, field: &str) -> Result<Option<CodecTerms<CodecEnum>>> {
+        if field == "secret" {
+            Ok(None)
+        } else {
+            self.reader.terms(field)
+        }
+    }
+
+    fn live_docs(&self) -> BitsRef {
+        Arc::clone(&self.live_docs)
+    }
+
+    fn field_infos(&self) -> &FieldInfos {
+        &self.field_infos
+    }
+
+    fn clone_field_infos(&self) -> Arc<FieldInfos> {
+        Arc::clone(&self.field_infos)
+    }
+
+    fn num_docs(&self) -> i32 {
+        self.num_docs
+    }
+}
+
+#[test]
+fn filter_readers() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_filter_readers")?;
+    let add_docs = |range: std::ops::Range<i64>| -> Result<()> {
+        for i in range {
+            writer.add_document(vec![
+                Box::new(new_keyword_field("id".into(), i.to_string())) as Box<dyn Fieldable>,
+                Box::new(new_keyword_field("secret".into(), "s3cr3t".into())),
+                Box::new(NumericDocValuesField::new("num", i)),
+            ])?;
+        }
+        Ok(())
+    };
+    add_docs(0..10)?;
+
+    let reader = FilterDirectoryReader::new(writer.get_reader(true, false)?, |leaf| {
+        Ok(Arc::new(HideOddSecrets::new(leaf)?) as Arc<SearchLeafReader<CodecEnum>>)
+    })?;
+    assert_eq!(reader.max_doc(), 10);
+    assert_eq!(reader.num_docs(), 5);
+    let leaves = reader.leaves();
+    assert!(leaves[0].reader.field_info("secret").is_none());
+    assert!(leaves[0].reader.field_info("id").is_some());
+    // the methods which are not overridden are delegated
+    assert_eq!(leaves[0].reader.get_numeric_doc_values("num")?.get(3)?, 3);
+
+    let count =
+        |reader: &FilterDirectoryReader<_, _, _, _>, field: &str, text: &str| -> Result<usize> {
+            let searcher = DefaultIndexSearcher::new(reader, None);
+            let query =
+                TermQuery::new(Term::new(field.into(), text.as_bytes().to_vec()), 1.0, None);
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(&query, &mut collector)?;
+            Ok(collector.top_docs().total_hits())
+        };
+    assert_eq!(count(&reader, "secret", "s3cr3t")?, 0);
+    assert_eq!(count(&reader, "id", "4")?, 1);
+    assert_eq!(count(&reader, "id", "5")?, 0);
+
+    // the leaves of the reopened reader are wrapped as well
+    add_docs(10..14)?;
+    let reader = reader.open_if_changed()?.unwrap();
+    assert_eq!(reader.max_doc(), 14);
+    assert_eq!(reader.num_docs(), 7);
+    assert_eq!(count(&reader, "secret", "s3cr3t")?, 0);
+    assert_eq!(count(&reader, "id", "12")?, 1);
+    Ok(())
+}
+
+#[test]
+fn index_splitter() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_index_splitter")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..20 {
+        writer.add_document(vec![
+            new_keyword_field("id".into(), i.to_string()),
+            new_keyword_field("parity".into(), (i % 2).to_string()),
+        ])?;
+        if i % 10 == 9 {
+            writer.commit()?;
+        }
+    }
+    // the deleted docs are dropped from the parts
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"0".to_vec())])?;
+    writer.commit()?;
+    writer.close()?;
+
+    let open_parts = |name: &str, n: usize| -> Result<Vec<Arc<FSDirectory>>> {
+        (0..n)
+            .map(|i| {
+                let path = format!("/tmp/test_rucene_index_splitter_{}_{}", name, i);
+                Ok(Arc::new(FSDirectory::new(new_index_dir(&path)?)?))
+            })
+            .collect()
+    };
+    let count = |directory: &Arc<FSDirectory>, field: &str, text: &str| -> Result<usize> {
+        let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+            StandardDirectoryReader::open(Arc::clone(directory))?;
+        let searcher = DefaultIndexSearcher::new(&reader, None);
+        let query = TermQuery::new(Term::new(field.into(), text.as_bytes().to_vec()), 1.0, None);
+        let mut collector = TopDocsCollector::new(20);
+        searcher.search(&query, &mut collector)?;
+        Ok(collector.top_docs().total_hits())
+    };
+
+    let splitter = IndexSplitter::new(Arc::clone(&directory), IndexWriterConfig::default());
+    let parts = open_parts("round_robin", 3)?;
+    splitter.split(&parts, &SplitMode::RoundRobin)?;
+    let num_docs: Vec<_> = parts
+        .iter()
+        .map(|dir| {
+            SegmentInfos::<_, CodecEnum>::read_latest_commit(dir).map(|sis| sis.total_max_doc())
+        })
+        .collect::<Result<_>>()?;
+    assert_eq!(num_docs, vec![7, 6, 6]);
+    assert_eq!(count(&parts[0], "id", "1")?, 1);
+    assert_eq!(count(&parts[1], "id", "2")?, 1);
+    assert_eq!(count(&parts[2], "id", "0")?, 0);
+
+    let parts = open_parts("query", 2)?;
+    let query: Arc<dyn Query<CodecEnum>> = Arc::new(TermQuery::new(
+        Term::new("parity".into(), b"1".to_vec()),
+        1.0,
+        None,
+    ));
+    splitter.split(&parts, &SplitMode::Queries(vec![query]))?;
+    assert_eq!(count(&parts[0], "parity", "1")?, 10);
+    assert_eq!(count(&parts[0], "parity", "0")?, 0);
+    assert_eq!(count(&parts[1], "parity", "0")?, 9);
+    assert_eq!(count(&parts[1], "parity", "1")?, 0);
+
+    assert!(splitter.split(&parts[..1], &SplitMode::RoundRobin).is_err());
+    assert!(splitter.split(&parts, &SplitMode::Queries(vec![])).is_err());
+    Ok(())
+}
+
+#[test]
+fn reader_statistics() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_reader_statistics")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    let texts = ["a b a", "b c", "a", "d d d"];
+    for (i, text) in texts.iter().enumerate() {
+        writer.add_document(vec![
+            new_index_text_field("body".into(), text.to_string()),
+            new_keyword_field("id".into(), i.to_string()),
+        ])?;
+        // spread the docs over two segments
+        if i == 1 {
+            writer.commit()?;
+        }
+    }
+    writer.commit()?;
+
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    assert_eq!(reader.leaves().len(), 2);
+
+    let a = Term::new("body".into(), b"a".to_vec());
+    assert_eq!(IndexReader::doc_freq(&reader, &a)?, 2);
+    assert_eq!(reader.total_term_freq(&a)?, 3);
+    let stats = reader.term_statistics(&a)?;
+    assert_eq!(stats.doc_freq, 2);
+    assert_eq!(stats.total_term_freq, 3);
+
+    let missing = Term::new("body".into(), b"z".to_vec());
+    assert_eq!(IndexReader::doc_freq(&reader, &missing)?, 0);
+    assert_eq!(reader.total_term_freq(&missing)?, 0);
+
+    assert_eq!(reader.doc_count("body")?, 4);
+    assert_eq!(reader.sum_doc_freq("body")?, 6);
+    assert_eq!(reader.sum_total_term_freq("body")?, 9);
+    assert_eq!(reader.doc_count("missing")?, 0);
+
+    let stats = reader.collection_statistics("body")?;
+    assert_eq!(stats.max_doc, 4);
+    assert_eq!(stats.doc_count, 4);
+
+    // deleted docs are still counted until they are merged away
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"3".to_vec())])?;
+    writer.commit()?;
+    writer.close()?;
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    assert_eq!(reader.num_docs(), 3);
+    assert_eq!(reader.doc_count("body")?, 4);
+    Ok(())
+}
+
+#[test]
+fn writer_metrics() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_writer_metrics")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    assert_eq!(writer.metrics(), Default::default());
+
+    for i in 0..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    let metrics = writer.metrics();
+    assert_eq!(metrics.flush_count, 0);
+    assert!(metrics.ram_bytes_used > 0);
+    writer.commit()?;
+
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"3".to_vec())])?;
+    assert_eq!(writer.metrics().buffered_delete_terms, 1);
+    for i in 10..15 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+
+    let metrics = writer.metrics();
+    assert_eq!(metrics.flush_count, 2);
+    assert_eq!(metrics.flushed_docs, 15);
+    assert!(metrics.flushed_bytes > 0);
+    assert_eq!(metrics.segment_count, 2);
+    assert_eq!(metrics.buffered_delete_terms, 0);
+    assert_eq!(metrics.applied_deletes, 1);
+    assert_eq!(metrics.ram_bytes_used, 0);
+
+    writer.force_merge(1, true)?;
+    let metrics = writer.metrics();
+    assert_eq!(metrics.merge_count, 1);
+    assert!(metrics.merged_bytes > 0);
+    assert_eq!(metrics.running_merges, 0);
+    assert_eq!(metrics.pending_merges, 0);
+    assert_eq!(metrics.segment_count, 1);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn sequence_numbers() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_sequence_numbers")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    assert_eq!(writer.last_committed_sequence_number(), -1);
+
+    let add = writer.add_document(vec![new_keyword_field("id".into(), "0".into())])?;
+    let update = writer.update_document(
+        vec![new_keyword_field("id".into(), "1".into())],
+        Some(Term::new("id".into(), b"0".to_vec())),
+    )?;
+    let delete = writer.delete_documents_by_terms(vec![Term::new("id".into(), b"1".to_vec())])?;
+    assert!(add < update && update < delete);
+    assert_eq!(writer.max_completed_sequence_number(), delete);
+
+    let commit = writer.commit()?;
+    assert!(commit >= delete as i64);
+    assert_eq!(writer.last_committed_sequence_number(), commit);
+
+    let add = writer.add_document(vec![new_keyword_field("id".into(), "2".into())])?;
+    assert!(add as i64 > commit);
+    assert_eq!(writer.max_completed_sequence_number(), add);
+    assert_eq!(writer.last_committed_sequence_number(), commit);
+
+    let commit = writer.commit()?;
+    assert!(commit >= add as i64);
+    assert_eq!(writer.last_committed_sequence_number(), commit);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn merged_segment_warmer() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_merged_segment_warmer")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let warmed = Arc::new(Mutex::new(vec![]));
+    let mut config = IndexWriterConfig::default();
+    let warmed_docs = Arc::clone(&warmed);
+    config.set_merged_segment_warmer(move |reader| {
+        let doc_freq = reader.doc_freq(&Term::new("id".into(), b"4".to_vec()))?;
+        warmed_docs
+            .lock()
+            .unwrap()
+            .push((reader.max_doc(), doc_freq));
+        Ok(())
+    });
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..9 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        if i % 3 == 2 {
+            writer.commit()?;
+        }
+    }
+    assert!(warmed.lock().unwrap().is_empty());
+
+    writer.force_merge(1, true)?;
+    assert_eq!(*warmed.lock().unwrap(), vec![(9, 1)]);
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    assert_eq!(reader.num_docs(), 9);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn tragic_events() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_tragic_events")?;
+    let directory = Arc::new(FailingDirectory::new(FSDirectory::new(&dir_path)?));
+    let open_writer = || {
+        IndexWriter::new(
+            Arc::clone(&directory),
+            Arc::new(IndexWriterConfig::default()),
+        )
+    };
+    let num_committed_docs = || -> Result<i32> {
+        let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+            StandardDirectoryReader::open(Arc::clone(&directory))?;
+        Ok(reader.num_docs())
+    };
+
+    // a failed flush loses the buffered docs
+    let writer = open_writer()?;
+    for i in 0..5 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    writer.commit()?;
+    for i in 5..10 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+    }
+    directory.fail_writes.store(true, Ordering::Release);
+    assert!(writer.commit().is_err());
+    directory.fail_writes.store(false, Ordering::Release);
+    assert!(writer.tragedy().is_some());
+    let res = writer.add_document(vec![new_keyword_field("id".into(), "10".into())]);
+    assert!(matches!(res, Err(Error::AlreadyClosed(_))));
+    assert!(writer.commit().is_err());
+    // closing rolls back to the last commit and releases the write lock
+    assert!(writer.close().is_err());
+    assert!(writer.is_closed());
+    assert_eq!(num_committed_docs()?, 5);
+
+    // a failed fsync closes the writer right away
+    let writer = open_writer()?;
+    assert_eq!(writer.max_doc(), 5);
+    writer.add_document(vec![new_keyword_field("id".into(), "5".into())])?;
+    directory.fail_syncs.store(true, Ordering::Release);
+    assert!(writer.commit().is_err());
+    directory.fail_syncs.store(false, Ordering::Release);
+    assert!(writer.tragedy().is_some());
+    assert!(writer.is_closed());
+    assert_eq!(num_committed_docs()?, 5);
+
+    let writer = open_writer()?;
+    writer.add_document(vec![new_keyword_field("id".into(), "5".into())])?;
+    writer.commit()?;
+    writer.close()?;
+    assert_eq!(num_committed_docs()?, 6);
+    Ok(())
+}
+
+#[test]
+fn keep_last_n_commits() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_keep_last_n_commits")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    assert!(KeepLastNCommitsDeletionPolicy::new(0).is_err());
+    let new_config = || -> Result<Arc<_>> {
+        Ok(Arc::new(IndexWriterConfig {
+            index_deletion_policy: Arc::new(KeepLastNCommitsDeletionPolicy::new(2)?),
+            ..Default::default()
+        }))
+    };
+
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config()?)?;
+    for i in 0..4 {
+        writer.add_document(vec![new_keyword_field("id".into(), i.to_string())])?;
+        writer.commit()?;
+    }
+    // closing would commit again
+    writer.rollback()?;
+    let commits = list_commits::<_, CodecEnum>(&directory)?;
+    assert_eq!(commits.len(), 2);
+
+    // the previous commit can still be opened
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+        StandardDirectoryReader::open_commit(Arc::clone(&directory), &commits[0])?;
+    assert_eq!(reader.num_docs(), 3);
+
+    // a writer opened with the default policy drops the older commits
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    writer.close()?;
+    assert_eq!(list_commits::<_, CodecEnum>(&directory)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn nrt_reader_pooling() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_nrt_reader_pooling")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(Lucene62Codec::default())),
+        SerialMergeScheduler {},
+        NoMergePolicy {},
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    for i in 0..10 {
+        let doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(NumericDocValuesField::new("price", i * 10)),
+        ];
+        writer.add_document(doc)?;
+    }
+
+    let mut reader = writer.get_reader(true, false)?;
+    for i in 0..5 {
+        let values = reader.leaves()[0].reader.get_numeric_doc_values("price")?;
+        assert_eq!(values.get(9)?, 90);
+        let core_key = reader.leaves()[0].reader.core_cache_key().to_string();
+
+        // each refresh only changes the deletes of the single segment, the
+        // new reader shares the core and doc values of the previous one
+        let term = Term::new("id".into(), i.to_string().into());
+        writer.delete_documents_by_terms(vec![term])?;
+        let new_reader = reader.open_if_changed(None)?.unwrap();
+        assert_eq!(new_reader.leaves()[0].reader.core_cache_key(), core_key);
+        reader = new_reader;
+        assert_eq!(reader.num_docs(), 9 - i);
+
+        let values = reader.leaves()[0].reader.get_numeric_doc_values("price")?;
+        for doc in 0..10 {
+            assert_eq!(values.get(doc)?, doc as i64 * 10);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn auto_commit() -> Result<()> {
+    let new_writer = |path: &str, policy: AutoCommitPolicy| -> Result<_> {
+        let directory = Arc::new(FSDirectory::new(new_index_dir(path)?)?);
+        let listener = Arc::new(RecordingListener::default());
+        let config = IndexWriterConfig {
+            auto_commit_policy: policy,
+            event_listener: Arc::clone(&listener) as Arc<dyn IndexWriterEventListener>,
+            ..Default::default()
+        };
+        let writer = IndexWriter::new(directory, Arc::new(config))?;
+        Ok((writer, listener))
+    };
+    let id = |v: &str| vec![new_keyword_field("id".into(), v.into())];
+    let delete = |v: &str| vec![Term::new("id".into(), v.as_bytes().to_vec())];
+
+    // commits once enough deletes were buffered
+    let (writer, listener) = new_writer(
+        "/tmp/test_rucene_auto_commit_deletes",
+        AutoCommitPolicy {
+            max_uncommitted_deletes: Some(2),
+            ..Default::default()
+        },
+    )?;
+    writer.add_document(id("1"))?;
+    writer.update_document(id("2"), Some(delete("2")[0].clone()))?;
+    assert_eq!(writer.last_committed_sequence_number(), -1);
+    let seq_no = writer.delete_documents_by_terms(delete("1"))?;
+    let committed = writer.last_committed_sequence_number();
+    assert!(committed >= seq_no as i64);
+    let events = listener.take();
+    assert_eq!(events.last().unwrap(), "auto_commit UncommittedDeletes");
+    assert!(events.contains(&"commit segments_1 1".to_string()));
+    // the counter restarts from the commit
+    writer.delete_documents_by_terms(delete("2"))?;
+    assert_eq!(writer.last_committed_sequence_number(), committed);
+
+    // commits once the indexed docs use enough bytes
+    let (writer, listener) = new_writer(
+        "/tmp/test_rucene_auto_commit_bytes",
+        AutoCommitPolicy {
+            max_uncommitted_bytes: Some(1),
+            ..Default::default()
+        },
+    )?;
+    let seq_no = writer.add_document(id("1"))?;
+    assert!(writer.last_committed_sequence_number() >= seq_no as i64);
+    assert_eq!(
+        listener.take().last().unwrap(),
+        "auto_commit UncommittedBytes"
+    );
+    assert!(!writer.has_uncommitted_changes());
+
+    // commits pending changes once the interval elapsed
+    let (writer, listener) = new_writer(
+        "/tmp/test_rucene_auto_commit_interval",
+        AutoCommitPolicy {
+            max_commit_interval: Some(Duration::from_millis(200)),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(writer.maybe_auto_commit()?, None);
+    writer.add_document(id("1"))?;
+    std::thread::sleep(Duration::from_millis(250));
+    assert_eq!(
+        writer.maybe_auto_commit()?,
+        Some(AutoCommitTrigger::Interval)
+    );
+    assert_eq!(listener.take().last().unwrap(), "auto_commit Interval");
+    assert_eq!(writer.maybe_auto_commit()?, None);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn open_reader_during_commit() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_open_reader_during_commit")?;
+    // the writer and the reader don't share the directory, as if they
+    // were in separate processes
+    let writer = IndexWriter::new(
+        Arc::new(FSDirectory::new(&dir_path)?),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    writer.add_document(vec![new_keyword_field("id".into(), "1".into())])?;
+    writer.commit()?;
+
+    // the writer commits again and deletes segments_1 just when the
+    // reader opens it
+    let directory = Arc::new(FailingDirectory::new(FSDirectory::new(&dir_path)?));
+    let committer = writer.clone();
+    *directory.before_open.lock().unwrap() = Some(Box::new(move |name: &str| {
+        if name == "segments_1" {
+            committer
+                .add_document(vec![new_keyword_field("id".into(), "2".into())])
+                .unwrap();
+            committer.commit().unwrap();
+        }
+    }));
+    let reader: StandardDirectoryReader<_, CodecEnum, SerialMergeScheduler, TieredMergePolicy> =
+        StandardDirectoryReader::open(Arc::clone(&directory))?;
+    assert_eq!(reader.num_docs(), 2);
+    *directory.before_open.lock().unwrap() = None;
+
+    // the reader keeps seeing its commit while the writer goes on
+    writer.add_document(vec![new_keyword_field("id".into(), "3".into())])?;
+    assert_eq!(reader.num_docs(), 2);
+    assert!(reader.is_current()?);
+    writer.commit()?;
+    assert!(!reader.is_current()?);
+    let reader = reader.open_if_changed(None)?.unwrap();
+    assert_eq!(reader.num_docs(), 3);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn concurrent_flushes() -> Result<()> {
+    let mut config = IndexWriterConfig::default();
+    assert!(config.set_max_concurrent_flushes(0).is_err());
+
+    // index from several threads at once, so that each one fills its own
+    // in-memory segment, the commit then flushes them in parallel; with a
+    // tiny RAM buffer the threads are also stalled until the flushes catch up
+    for (max_flushes, ram_buffer_mb) in [(1, None), (4, None), (4, Some(0.2))] {
+        let dir_path = new_index_dir("/tmp/test_rucene_concurrent_flushes")?;
+        let directory = Arc::new(FSDirectory::new(&dir_path)?);
+        let mut config = IndexWriterConfig::default();
+        config.set_max_concurrent_flushes(max_flushes)?;
+        config.set_ram_buffer_size_mb(ram_buffer_mb)?;
+        let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+        let barrier = Arc::new(std::sync::Barrier::new(4));
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let writer = writer.clone();
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || -> Result<()> {
+                    barrier.wait();
+                    for i in 0..2000 {
+                        let id = format!("{}-{}", t, i);
+                        writer.add_document(vec![new_keyword_field("id".into(), id)])?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        writer.commit()?;
+        assert!(!writer.has_uncommitted_changes());
+
+        let reader = writer.get_reader(true, false)?;
+        assert_eq!(reader.num_docs(), 8000);
+        let term = Term::new("id".into(), b"3-1999".to_vec());
+        assert_eq!(IndexReader::doc_freq(&reader, &term)?, 1);
+        writer.close()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn term_vectors() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_term_vectors")?;
+
+    // term vectors with freqs only
+    let freqs_only = FieldType {
+        index_options: IndexOptions::DocsAndFreqs,
+        store_term_vectors: true,
+        ..Default::default()
+    };
+    let tokens = WhitespaceTokenizer::new(Box::new(StringReader::new("b a b".into())));
+    let doc: Vec<Box<dyn Fieldable>> = vec![
+        Box::new(new_keyword_field("id".into(), "1".into())),
+        Box::new(new_index_text_field(
+            "title".into(),
+            "the quick fox jumps over the dog".into(),
+        )),
+        Box::new(Field::new(
+            "tags".into(),
+            freqs_only,
+            None,
+            Some(Box::new(tokens)),
+        )),
+    ];
+    writer.add_document(doc)?;
+    let reader = writer.get_reader(true, false)?;
+
+    let vector = reader.get_term_vector(0, "title")?.unwrap();
+    assert_eq!(vector.field, "title");
+    assert!(vector.has_positions && vector.has_offsets && !vector.has_payloads);
+    let texts: Vec<_> = vector
+        .terms
+        .iter()
+        .map(|t| t.text())
+        .collect::<Result<_>>()?;
+    assert_eq!(texts, vec!["dog", "fox", "jumps", "over", "quick", "the"]);
+    let the = vector.term(b"the").unwrap();
+    assert_eq!(the.freq, 2);
+    let occurrences: Vec<_> = the
+        .occurrences
+        .iter()
+        .map(|o| (o.position, o.start_offset, o.end_offset, o.payload.clone()))
+        .collect();
+    assert_eq!(
+        occurrences,
+        vec![
+            (Some(0), Some(0), Some(3), None),
+            (Some(5), Some(25), Some(28), None)
+        ]
+    );
+    assert!(vector.term(b"cat").is_none());
+
+    let vector = reader.get_term_vector(0, "tags")?.unwrap();
+    assert!(!vector.has_positions && !vector.has_offsets);
+    let freqs: Vec<_> = vector
+        .terms
+        .iter()
+        .map(|t| (t.text().unwrap(), t.freq))
+        .collect();
+    assert_eq!(freqs, vec![("a", 1), ("b", 2)]);
+    assert!(vector.terms.iter().all(|t| t.occurrences.is_empty()));
+
+    // the field isn't indexed with term vectors
+    assert!(reader.get_term_vector(0, "id")?.is_none());
+    Ok(())
+}
+
+#[test]
+fn convenience_fields() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_convenience_fields")?;
+
+    let docs = [("1", "the white rabbit"), ("2", "down the rabbit hole")];
+    for (i, (id, body)) in docs.iter().enumerate() {
+        writer.add_document(vec![
+            Box::new(StringField::new("id", id, true)) as Box<dyn Fieldable>,
+            Box::new(TextField::new("body", body, true)),
+            Box::new(StoredField::with_value("price", i as i64 * 10)),
+        ])?;
+    }
+    let token_stream = WhitespaceTokenizer::new(Box::new(StringReader::new("queen".into())));
+    writer.add_document(vec![
+        Box::new(StringField::new_bytes("id", b"3".to_vec(), false)) as Box<dyn Fieldable>,
+        Box::new(TextField::with_token_stream("body", Box::new(token_stream))),
+    ])?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = &reader.leaves()[0];
+    let fields = leaf.reader.field_infos();
+    assert!(fields.field_info_by_name("id").unwrap().omit_norms);
+    assert_eq!(
+        fields.field_info_by_name("body").unwrap().index_options,
+        IndexOptions::DocsAndFreqsAndPositions
+    );
+    assert_eq!(
+        fields.field_info_by_name("price").unwrap().index_options,
+        IndexOptions::Null
+    );
+
+    let stored = |doc, field: &str| -> Result<Option<VariantValue>> {
+        let doc = reader.document(doc, &[field.to_string()])?;
+        Ok(doc.fields.first().and_then(|f| f.field_data().cloned()))
+    };
+    assert_eq!(stored(0, "id")?, Some(VariantValue::VString("1".into())));
+    assert_eq!(stored(1, "price")?, Some(VariantValue::Long(10)));
+    assert_eq!(
+        stored(1, "body")?,
+        Some(VariantValue::VString("down the rabbit hole".into()))
+    );
+    assert_eq!(stored(2, "id")?, None);
+
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let count = |field: &str, text: &str| {
+        let term = Term::new(field.into(), text.as_bytes().to_vec());
+        searcher.count(&TermQuery::new(term, 1.0, None))
+    };
+    assert_eq!(count("id", "3")?, 1);
+    assert_eq!(count("body", "rabbit")?, 2);
+    assert_eq!(count("body", "hole")?, 1);
+    assert_eq!(count("body", "queen")?, 1);
+    assert_eq!(count("price", "0")?, 0);
+    Ok(())
+}
+
+#[test]
+fn sorted_doc_values_fields() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_sorted_doc_values")?;
+
+    let docs: [(&str, &[&str]); 3] = [
+        ("fiction", &["fantasy", "classic", "fantasy"]),
+        ("", &[]),
+        ("drama", &["classic"]),
+    ];
+    for (category, tags) in &docs {
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+        if !category.is_empty() {
+            doc.push(Box::new(SortedDocValuesField::new(
+                "category",
+                category.as_bytes(),
+            )));
+        }
+        for tag in tags.iter() {
+            doc.push(Box::new(SortedSetDocValuesField::new(
+                "tags",
+                tag.as_bytes(),
+            )));
+        }
+        if doc.is_empty() {
+            doc.push(Box::new(new_keyword_field("id".into(), "empty".into())));
+        }
+        writer.add_document(doc)?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = &reader.leaves()[0];
+
+    let mut category = leaf.reader.get_sorted_doc_values("category")?;
+    assert_eq!(category.value_count(), 2);
+    assert_eq!(category.get_ord(0)?, 1);
+    assert_eq!(category.get_ord(1)?, -1);
+    assert_eq!(category.get_ord(2)?, 0);
+    assert_eq!(category.lookup_ord(0)?, b"drama".to_vec());
+
+    let mut tags = leaf.reader.get_sorted_set_doc_values("tags")?;
+    assert_eq!(tags.get_value_count(), 2);
+    let ords = |tags: &mut Box<dyn SortedSetDocValues>, doc| -> Result<Vec<i64>> {
+        tags.set_document(doc)?;
+        let mut ords = vec![];
+        loop {
+            let ord = tags.next_ord()?;
+            if ord == NO_MORE_ORDS {
+                return Ok(ords);
+            }
+            ords.push(ord);
+        }
+    };
+    assert_eq!(ords(&mut tags, 0)?, vec![0, 1]);
+    assert!(ords(&mut tags, 1)?.is_empty());
+    assert_eq!(ords(&mut tags, 2)?, vec![0]);
+    assert_eq!(tags.lookup_ord(1)?, b"fantasy".to_vec());
+    Ok(())
+}
+
+#[test]
+fn prefix_compressed_terms_dict() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_prefix_compressed_terms_dict")?;
+
+    // enough distinct terms with long shared prefixes for the terms to be
+    // stored in prefix compressed blocks, spread over two segments
+    let num_terms = 3000;
+    let label = |i: usize| format!("facet/category/{:06}", i * 2);
+    for docs in &[0..num_terms / 2, num_terms / 2..num_terms] {
+        for i in docs.clone() {
+            let first = label(i);
+            let second = label((i * 7) % num_terms);
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(SortedDocValuesField::new("category", first.as_bytes())),
+                Box::new(SortedSetDocValuesField::new("labels", first.as_bytes())),
+                Box::new(SortedSetDocValuesField::new("labels", second.as_bytes())),
+            ];
+            writer.add_document(doc)?;
+        }
+        writer.commit()?;
+    }
+    writer.force_merge(1, true)?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = &reader.leaves()[0];
+    let mut labels = leaf.reader.get_sorted_set_doc_values("labels")?;
+    let mut category = leaf.reader.get_sorted_doc_values("category")?;
+    assert_eq!(labels.get_value_count(), num_terms);
+    assert_eq!(category.value_count(), num_terms);
+    for ord in (0..num_terms).step_by(37) {
+        let term = label(ord);
+        assert_eq!(labels.lookup_ord(ord as i64)?, term.as_bytes());
+        assert_eq!(labels.lookup_term(term.as_bytes())?, ord as i64);
+        assert_eq!(category.lookup_ord(ord as i32)?, term.as_bytes());
+        assert_eq!(category.lookup_term(term.as_bytes())?, ord as i32);
+        // a missing term returns its negated insertion point
+        let missing = format!("facet/category/{:06}", ord * 2 + 1);
+        assert_eq!(labels.lookup_term(missing.as_bytes())?, -(ord as i64) - 2);
+        assert_eq!(category.lookup_term(missing.as_bytes())?, -(ord as i32) - 2);
+    }
+    assert_eq!(labels.lookup_term(b"facet")?, -1);
+    assert_eq!(labels.lookup_term(b"zzz")?, -(num_terms as i64) - 1);
+
+    // the merge may reorder the segments, the category gives the doc's number
+    for doc in (0..num_terms as DocId).step_by(101) {
+        let i = category.get_ord(doc)? as i64;
+        let mut expected = vec![i, (i * 7) % num_terms as i64];
+        expected.sort_unstable();
+        expected.dedup();
+        labels.set_document(doc)?;
+        for ord in expected {
+            assert_eq!(labels.next_ord()?, ord);
+        }
+        assert_eq!(labels.next_ord()?, NO_MORE_ORDS);
+    }
+
+    let mut terms = labels.term_iterator()?;
+    assert_eq!(
+        terms.seek_ceil(b"facet/category/001001")?,
+        SeekStatus::NotFound
+    );
+    assert_eq!(terms.ord()?, 501);
+    assert_eq!(terms.term()?, label(501).as_bytes());
+    assert_eq!(terms.next()?, Some(label(502).into_bytes()));
+    assert_eq!(terms.seek_ceil(b"g")?, SeekStatus::End);
+    Ok(())
+}
+
+#[test]
+fn sparse_numeric_doc_values() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_sparse_numeric_doc_values")?;
+
+    // spans a few blocks of the jump table
+    let max_doc = 150_000;
+    for i in 0..max_doc {
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+        if i % 997 == 0 {
+            doc.push(Box::new(NumericDocValuesField::new(
+                "price",
+                i64::from(i) * 2,
+            )));
+        }
+        writer.add_document(doc)?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = &reader.leaves()[0];
+    let values = leaf.reader.get_numeric_doc_values("price")?;
+    let mut docs_with_field = leaf.reader.get_docs_with_field("price")?;
+    let targets = [
+        149_564, 0, 997, 70_000, 69_793, 1, 131_072, 131_601, 65_536, 65_805,
+    ];
+    for doc in targets.iter().chain(&[max_doc - 1, 3, 99_700]) {
+        let expected = if doc % 997 == 0 {
+            i64::from(*doc) * 2
+        } else {
+            0
+        };
+        assert_eq!(values.get(*doc)?, expected);
+        assert_eq!(docs_with_field.get(*doc as usize), doc % 997 == 0);
+    }
+    for doc in 0..max_doc {
+        let expected = if doc % 997 == 0 {
+            i64::from(doc) * 2
+        } else {
+            0
+        };
+        assert_eq!(values.get(doc)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_doc_values_field() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_binary_doc_values")?;
+
+    let large: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+    let values: [Option<&[u8]>; 4] = [Some(b"\x00\x01\x02"), None, Some(&large), None];
+    for (i, value) in values.iter().enumerate() {
+        let mut doc: Vec<Box<dyn Fieldable>> =
+            vec![Box::new(new_keyword_field("id".into(), i.to_string()))];
+        if let Some(value) = value {
+            doc.push(Box::new(BinaryDocValuesField::new("features", value)));
+        }
+        writer.add_document(doc)?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = &reader.leaves()[0];
+    let mut features = leaf.reader.get_binary_doc_values("features")?;
+    assert_eq!(features.get(0)?, b"\x00\x01\x02".to_vec());
+    assert!(features.get(1)?.is_empty());
+    assert_eq!(features.get(2)?, large);
+
+    let mut iter = BinaryDocValuesIterator::new("features", leaf)?;
+    assert_eq!(iter.next()?, 0);
+    assert_eq!(iter.value(), b"\x00\x01\x02");
+    assert_eq!(iter.next()?, 2);
+    assert_eq!(iter.value(), &large[..]);
+    assert_eq!(iter.next()?, NO_MORE_DOCS);
+    assert!(BinaryDocValuesIterator::new("id", leaf).is_err());
+
+    // values survive merging with a segment where every doc has a value
+    writer.add_document(vec![BinaryDocValuesField::new("features", b"last")])?;
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    let leaves = reader.leaves();
+    assert_eq!(leaves.len(), 1);
+    let mut iter = BinaryDocValuesIterator::new("features", &leaves[0])?;
+    let mut docs = vec![];
+    while iter.next()? != NO_MORE_DOCS {
+        docs.push((iter.doc_id(), iter.value().len()));
+    }
+    assert_eq!(docs, vec![(0, 3), (2, large.len()), (4, 4)]);
+    Ok(())
+}
+
+#[test]
+fn range_fields() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_range_fields")?;
+
+    // enough docs for the bkd tree to have inner cells
+    for i in 0..3000 {
+        writer.add_document(vec![LongRange::new("slot", &[i], &[i + 10])?])?;
+    }
+    let day = |d: u64| UNIX_EPOCH + Duration::from_secs(d * 86_400);
+    writer.add_document(vec![
+        Box::new(DoubleRange::new("area", &[0.0, 0.0], &[1.0, 1.0])?) as Box<dyn Fieldable>,
+        Box::new(DateRange::new_field("available", day(10), day(20))?),
+    ])?;
+    writer.add_document(vec![
+        Box::new(DoubleRange::new("area", &[0.5, -2.5], &[3.0, 0.5])?) as Box<dyn Fieldable>,
+        Box::new(DateRange::new_field("available", day(15), day(17))?),
+    ])?;
+
+    assert!(LongRange::new("slot", &[2], &[1]).is_err());
+    assert!(LongRange::new("slot", &[1, 2], &[3]).is_err());
+    assert!(DoubleRange::new("area", &[f64::NAN], &[1.0]).is_err());
+    assert!(LongRange::new("slot", &[0; 5], &[1; 5]).is_err());
+
+    let searcher = commit_and_search(&writer)?;
+    type RangeQueryFn = fn(String, &[i64], &[i64]) -> Result<Box<dyn Query<CodecEnum>>>;
+    let slot = |query: RangeQueryFn, min: i64, max: i64| {
+        searcher.count(query("slot".into(), &[min], &[max])?.as_ref())
+    };
+    assert_eq!(slot(LongRange::new_intersects_query, 100, 105)?, 16);
+    assert_eq!(slot(LongRange::new_contains_query, 100, 105)?, 6);
+    assert_eq!(slot(LongRange::new_within_query, 100, 120)?, 11);
+    assert_eq!(slot(LongRange::new_within_query, -5, 5000)?, 3000);
+    assert_eq!(slot(LongRange::new_intersects_query, 3009, 3020)?, 1);
+    assert_eq!(slot(LongRange::new_contains_query, -1, 0)?, 0);
+
+    let area = DoubleRange::new_intersects_query("area".into(), &[0.75, 0.25], &[0.8, 0.3])?;
+    assert_eq!(searcher.count(area.as_ref())?, 2);
+    let area = DoubleRange::new_within_query("area".into(), &[-1.0, -1.0], &[2.0, 2.0])?;
+    assert_eq!(searcher.count(area.as_ref())?, 1);
+    let area = DoubleRange::new_contains_query("area".into(), &[1.0, 0.0], &[2.0, 0.5])?;
+    assert_eq!(searcher.count(area.as_ref())?, 1);
+
+    let available = DateRange::new_contains_query("available".into(), day(16), day(17))?;
+    assert_eq!(searcher.count(available.as_ref())?, 2);
+    let available = DateRange::new_contains_query("available".into(), day(11), day(12))?;
+    assert_eq!(searcher.count(available.as_ref())?, 1);
+    let available = DateRange::new_intersects_query("available".into(), day(0), day(9))?;
+    assert_eq!(searcher.count(available.as_ref())?, 0);
+    assert_eq!(DateRange::from_millis(DateRange::to_millis(day(3))), day(3));
+
+    // the dimensions of the query must match the indexed ones
+    let bad = LongRange::new_intersects_query("slot".into(), &[0, 0], &[1, 1])?;
+    assert!(searcher.count(bad.as_ref()).is_err());
+
+    writer.add_document(vec![LongRange::new("slot", &[5000], &[5010])?])?;
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let query = LongRange::new_intersects_query("slot".into(), &[100], &[105])?;
+    assert_eq!(searcher.count(query.as_ref())?, 16);
+    let query = LongRange::new_within_query("slot".into(), &[-5], &[6000])?;
+    assert_eq!(searcher.count(query.as_ref())?, 3001);
+    let area = DoubleRange::new_contains_query("area".into(), &[1.0, 0.0], &[2.0, 0.5])?;
+    assert_eq!(searcher.count(area.as_ref())?, 1);
+    Ok(())
+}
+
+#[test]
+fn typed_stored_fields() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_typed_stored_fields")?;
+
+    // values without a stored representation are rejected
+    assert!(writer
+        .add_document(vec![StoredField::with_value("flag", true)])
+        .is_err());
+
+    writer.add_document(vec![
+        StoredField::with_value("title", "alice"),
+        StoredField::with_value("bytes", &[0u8, 255, 7][..]),
+        StoredField::with_value("int", -3i32),
+        StoredField::with_value("short", 12i16),
+        StoredField::with_value("long", i64::MAX),
+        StoredField::with_value("float", -1.5f32),
+        StoredField::with_value("double", -0.25f64),
+        StoredField::with_value("precise", 0.1f64),
+        StoredField::with_value("tag", "a"),
+        StoredField::with_value("tag", "b"),
+    ])?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let doc = reader.document(0, &[])?;
+    assert_eq!(doc.get_string("title"), Some("alice"));
+    assert_eq!(doc.get_binary("bytes"), Some(&[0u8, 255, 7][..]));
+    assert_eq!(doc.get_int("int"), Some(-3));
+    assert_eq!(doc.get_int("short"), Some(12));
+    assert_eq!(doc.get_long("long"), Some(i64::MAX));
+    assert_eq!(doc.get_float("float"), Some(-1.5));
+    assert_eq!(doc.get_double("double"), Some(-0.25));
+    assert_eq!(doc.get_double("precise"), Some(0.1));
+    // typed accessors don't convert between types
+    assert_eq!(doc.get_long("int"), None);
+    assert_eq!(doc.get_string("bytes"), None);
+    assert_eq!(doc.get_int("missing"), None);
+    let tags: Vec<_> = doc
+        .get_values("tag")
+        .iter()
+        .map(|v| v.get_string())
+        .collect();
+    assert_eq!(tags, vec![Some("a"), Some("b")]);
+    assert!(doc.get_field("double").is_some());
+    Ok(())
+}
+
+#[test]
+fn large_stored_document() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_large_stored_document")?;
+
+    // documents longer than twice the chunk size are compressed in slices
+    let values: Vec<String> = (0..4u8)
+        .map(|i| ((b'a' + i) as char).to_string().repeat(30_000))
+        .collect();
+    writer.add_document(vec![StoredField::with_value("title", "small")])?;
+    writer.add_document(
+        values
+            .iter()
+            .map(|v| StoredField::with_value("body", v.as_str()))
+            .collect(),
+    )?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.document(0, &[])?.get_string("title"), Some("small"));
+    let doc = reader.document(1, &[])?;
+    let bodies: Vec<_> = doc
+        .get_values("body")
+        .iter()
+        .map(|v| v.get_string())
+        .collect();
+    let expected: Vec<_> = values.iter().map(|v| Some(v.as_str())).collect();
+    assert_eq!(bodies, expected);
+    Ok(())
+}
+
+#[test]
+fn document_builder() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_document_builder")?;
+
+    for (id, title) in [
+        ("1", "alice in wonderland"),
+        ("2", "through the looking glass"),
+    ] {
+        writer.add_document(
+            doc()
+                .string("id", id)
+                .text("title", title)
+                .stored_text("title.raw", title)
+                .stored("id", id.parse::<i64>().unwrap())
+                .numeric_dv("weight", id.parse().unwrap())
+                .sorted_dv("tag", title.split(' ').next().unwrap().as_bytes())
+                .build(),
+        )?;
+    }
+
+    let searcher = commit_and_search(&writer)?;
+    let query = TermQuery::new(Term::new("title".into(), b"looking".to_vec()), 1.0, None);
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(&query, &mut collector)?;
+    let top_docs = collector.top_docs();
+    assert_eq!(top_docs.total_hits(), 1);
+    let doc_id = top_docs.score_docs()[0].doc_id();
+
+    let stored = searcher.reader().document(doc_id, &[])?;
+    assert_eq!(
+        stored.get_string("title.raw"),
+        Some("through the looking glass")
+    );
+    assert_eq!(stored.get_long("id"), Some(2));
+    assert!(stored.get_field("title").is_none());
+    Ok(())
+}
+
+#[test]
+fn document_mapping() -> Result<()> {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Author {
+        name: String,
+        born: Option<u16>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Format {
+        Paperback,
+        Ebook,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Book {
+        id: String,
+        title: String,
+        year: i32,
+        price: f64,
+        in_stock: bool,
+        tags: Vec<String>,
+        ratings: Vec<u8>,
+        author: Author,
+        format: Format,
+        isbn: Option<String>,
+    }
+
+    let writer = new_index_writer("/tmp/test_rucene_document_mapping")?;
+
+    let mapping = DocumentMapping::new()
+        .field("id", FieldMapping::keyword())
+        .field("title", FieldMapping::text())
+        .field("year", FieldMapping::stored().with_doc_values(true))
+        .field("tags", FieldMapping::keyword().with_doc_values(true));
+    let books = vec![
+        Book {
+            id: "1".into(),
+            title: "alice in wonderland".into(),
+            year: 1865,
+            price: 7.5,
+            in_stock: true,
+            tags: vec!["novel".into(), "fantasy".into()],
+            ratings: vec![5, 4, 5],
+            author: Author {
+                name: "lewis carroll".into(),
+                born: Some(1832),
+            },
+            format: Format::Paperback,
+            isbn: None,
+        },
+        Book {
+            id: "2".into(),
+            title: "through the looking glass".into(),
+            year: 1871,
+            price: -0.1,
+            in_stock: false,
+            tags: vec!["novel".into()],
+            ratings: vec![],
+            author: Author {
+                name: "lewis carroll".into(),
+                born: None,
+            },
+            format: Format::Ebook,
+            isbn: Some("978-0".into()),
+        },
+    ];
+    for book in &books {
+        writer.add_document(mapping.to_fields(book)?)?;
+    }
+    assert!(mapping.to_fields(&vec![1, 2]).is_err());
+
+    let searcher = commit_and_search(&writer)?;
+    let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("tags", "novel"), 1.0, None))?,
+        2
+    );
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("title", "glass"), 1.0, None))?,
+        1
+    );
+    // fields that aren't mapped are only stored
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("isbn", "978-0"), 1.0, None))?,
+        0
+    );
+
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(&TermQuery::new(term("id", "1"), 1.0, None), &mut collector)?;
+    let doc_id = collector.top_docs().score_docs()[0].doc_id();
+    let stored = searcher.reader().document(doc_id, &[])?;
+    // ratings is an empty sequence for the second book, so it isn't stored
+    let book: Book = from_document(&stored)?;
+    assert_eq!(book, books[0]);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Summary {
+        title: String,
+        #[serde(default)]
+        ratings: Vec<u8>,
+    }
+    let stored = searcher.reader().document(1 - doc_id, &[])?;
+    let summary: Summary = from_document(&stored)?;
+    assert_eq!(summary.title, "through the looking glass");
+    assert!(summary.ratings.is_empty());
+    assert!(from_document::<Book>(&stored).is_err());
+    Ok(())
+}
+
+#[test]
+fn date_fields() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_date_fields")?;
+    let mut config = IndexWriterConfig::default();
+    config.set_index_sort(Some(Sort::new(vec![SortField::Simple(
+        SimpleSortField::new("published".into(), SortFieldType::Long, false),
+    )])))?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+    let dates = [
+        "2020-03-01T08:00:00",
+        "2019-12-31T23:59:59.999",
+        "2020-01-15T10:30:00",
+        "2020-01-01",
+        "2020-01-31T23:00",
+    ];
+    let time = |s: &str| DateTools::from_millis(DateTools::parse(s).unwrap().0);
+    for (id, date) in dates.iter().enumerate() {
+        writer.add_document(
+            doc()
+                .field(DatePoint::new("published", time(date), Resolution::Day))
+                .field(DateDocValuesField::new(
+                    "published",
+                    time(date),
+                    Resolution::Day,
+                ))
+                .stored("id", id as i64)
+                .build(),
+        )?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    // the index is sorted by day, ties keep their insertion order
+    let ids: Vec<_> = (0..5)
+        .map(|doc_id| {
+            reader
+                .document(doc_id, &[])
+                .unwrap()
+                .get_long("id")
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(ids, vec![1, 3, 2, 4, 0]);
+
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let count = |query_string: &str| -> Result<i32> {
+        let query: Box<dyn Query<CodecEnum>> =
+            QueryStringQueryBuilder::new(query_string.into(), vec![("title".into(), 1.0)], 0, 1.0)
+                .with_date_field("published", Resolution::Day)
+                .build()?;
+        searcher.count(query.as_ref())
+    };
+    assert_eq!(count("published:[2020-01 TO 2020-01]")?, 3);
+    assert_eq!(count("published:[2020-01-01 TO 2020-01-15}")?, 1);
+    assert_eq!(count("published:{2020-01-01 TO 2020-01-31T10:00]")?, 2);
+    assert_eq!(count("published:[* TO 2020-01-01}")?, 1);
+    assert_eq!(count("published:{2020-01 TO *]")?, 1);
+    assert_eq!(count("published:[2020-01-02 TO 2020-01-01]")?, 0);
+
+    let query: Box<dyn Query<CodecEnum>> =
+        DatePoint::new_exact_query("published".into(), time("2020-01-15"))?;
+    assert_eq!(searcher.count(query.as_ref())?, 1);
+    Ok(())
+}
+
+#[test]
+fn facet_fields() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_facet_fields")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default()))?;
+
+    let mut config = FacetsConfig::new();
+    config.set_hierarchical("Publish Date", true);
+    config.set_multi_valued("Author", true);
+    config.set_index_field_name("Color", "$colors");
+
+    let new_doc = |authors: &[&str], date: &[&str], color: &str| -> Result<_> {
+        let mut doc = doc().string("id", authors[0]);
+        for author in authors {
+            doc = doc.field(FacetField::new("Author", &[author])?);
+        }
+        let doc = doc
+            .field(FacetField::new("Publish Date", date)?)
+            .field(SortedSetDocValuesFacetField::new("Color", color)?)
+            .build();
+        config.build(doc)
+    };
+    writer.add_document(new_doc(&["Bob", "Lisa"], &["2010", "10", "15"], "red")?)?;
+    writer.add_document(new_doc(&["Lisa"], &["2010", "11", "1"], "blue")?)?;
+    writer.add_document(new_doc(&["Susan"], &["2012", "1", "1"], "red")?)?;
+
+    // facet fields must go through FacetsConfig::build
+    let facet = FacetField::new("Author", &["Frank"])?;
+    assert!(writer.add_document(vec![facet]).is_err());
+    // dimensions are single valued and flat by default
+    assert!(config
+        .build(vec![
+            Box::new(FacetField::new("Color", &["red"])?),
+            Box::new(FacetField::new("Color", &["blue"])?),
+        ])
+        .is_err());
+    assert!(config
+        .build(vec![Box::new(FacetField::new("Author", &["a", "b"])?)])
+        .is_err());
+    config.set_hierarchical("Color", true);
+    assert!(config
+        .build(vec![Box::new(SortedSetDocValuesFacetField::new(
+            "Color", "red"
+        )?)])
+        .is_err());
+    assert!(FacetField::new("Author", &[""]).is_err());
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let drill_down = |field: &str, components: &[&str]| -> Result<i32> {
+        let term = Term::new(
+            field.into(),
+            FacetsConfig::path_to_string(components).into_bytes(),
+        );
+        searcher.count(&TermQuery::new(term, 1.0, None))
+    };
+    assert_eq!(drill_down("$facets", &["Author", "Lisa"])?, 2);
+    assert_eq!(drill_down("$facets", &["Author"])?, 3);
+    assert_eq!(drill_down("$facets", &["Publish Date", "2010"])?, 2);
+    assert_eq!(drill_down("$facets", &["Publish Date", "2010", "11"])?, 1);
+    assert_eq!(drill_down("$colors", &["Color", "red"])?, 2);
+    assert_eq!(drill_down("$facets", &["Color", "red"])?, 0);
+
+    // the counting values of the first document
+    let leaf = &searcher.reader().leaves()[0];
+    let mut values = leaf.reader.get_sorted_set_doc_values("$facets")?;
+    values.set_document(0)?;
+    let mut paths = vec![];
+    loop {
+        let ord = values.next_ord()?;
+        if ord == NO_MORE_ORDS {
+            break;
+        }
+        let path = String::from_utf8(values.lookup_ord(ord)?).unwrap();
+        paths.push(FacetsConfig::string_to_path(&path).join("/"));
+    }
+    assert_eq!(
+        paths,
+        vec![
+            "Author/Bob",
+            "Author/Lisa",
+            "Publish Date/2010",
+            "Publish Date/2010/10",
+            "Publish Date/2010/10/15"
+        ]
+    );
+
+    let escaped = ["a\u{1f}b", "c\u{1e}"];
+    assert_eq!(
+        FacetsConfig::string_to_path(&FacetsConfig::path_to_string(&escaped)),
+        escaped
+    );
+    Ok(())
+}
+
+#[test]
+fn lat_lon_distance_sort() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_lat_lon_distance_sort")?;
+
+    let cities = [
+        ("london", Some((51.5074, -0.1278))),
+        ("berlin", Some((52.52, 13.405))),
+        ("nowhere", None),
+        ("brussels", Some((50.8503, 4.3517))),
+    ];
+    for (i, (name, point)) in cities.iter().enumerate() {
+        let mut builder = doc().stored("name", *name);
+        if let Some((lat, lon)) = point {
+            builder = builder.field(LatLonDocValuesField::new("location", *lat, *lon)?);
+        }
+        writer.add_document(builder.build())?;
+        // spread the docs over two segments
+        if i == 1 {
+            writer.commit()?;
+        }
+    }
+    assert!(LatLonDocValuesField::new("location", 91.0, 0.0).is_err());
+    assert!(LatLonDocValuesField::new_distance_sort("location", 0.0, 181.0).is_err());
+
+    let searcher = commit_and_search(&writer)?;
+    // distances from paris
+    let sort = Sort::new(vec![LatLonDocValuesField::new_distance_sort(
+        "location", 48.8566, 2.3522,
+    )?]);
+    let mut collector = TopFieldCollector::new(sort, 3);
+    searcher.search(&MatchAllDocsQuery {}, &mut collector)?;
+    let top_docs = collector.top_docs();
+    assert_eq!(top_docs.total_hits(), 4);
+
+    let mut hits = vec![];
+    for hit in top_docs.score_docs() {
+        let name = searcher
+            .reader()
+            .document(hit.doc_id(), &[])?
+            .get_string("name")
+            .unwrap()
+            .to_string();
+        let distance = match hit {
+            ScoreDocHit::Field(f) => f.fields[0].get_double().unwrap(),
+            ScoreDocHit::Score(_) => unreachable!(),
+        };
+        hits.push((name, (distance / 1000.0).round() as i64));
+    }
+    assert_eq!(
+        hits,
+        vec![
+            ("brussels".to_string(), 264),
+            ("london".to_string(), 344),
+            ("berlin".to_string(), 877),
+        ]
+    );
+
+    // docs without a point sort last
+    let sort = Sort::new(vec![LatLonDocValuesField::new_distance_sort(
+        "location", 52.0, 13.0,
+    )?]);
+    let mut collector = TopFieldCollector::new(sort, 10);
+    searcher.search(&MatchAllDocsQuery {}, &mut collector)?;
+    let top_docs = collector.top_docs();
+    let last = top_docs.score_docs().last().unwrap();
+    assert_eq!(last.doc_id(), 2);
+    match last {
+        ScoreDocHit::Field(f) => assert_eq!(f.fields[0], VariantValue::Double(f64::INFINITY)),
+        ScoreDocHit::Score(_) => unreachable!(),
+    }
+    Ok(())
+}
+
+#[test]
+fn schema_validation() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_schema_validation")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let new_config = |schema: &Arc<Schema>| {
+        Arc::new(IndexWriterConfig {
+            schema: Some(Arc::clone(schema)),
+            ..Default::default()
+        })
+    };
+    let schema = Arc::new(Schema::new());
+    schema.define(
+        "title",
+        FieldSchema {
+            index_options: IndexOptions::DocsAndFreqsAndPositions,
+            ..Default::default()
+        },
+    )?;
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config(&schema))?;
+
+    writer.add_document(
+        doc()
+            .string("id", "1")
+            .text("title", "hello world")
+            .numeric_dv("price", 10)
+            .string("tag", "a")
+            .sorted_set_dv("tag", b"a")
+            .build(),
+    )?;
+    let price = schema.get("price").unwrap();
+    assert_eq!(price.doc_values_type, DocValuesType::Numeric);
+    assert_eq!(price.index_options, IndexOptions::Null);
+
+    // the doc values type of a field can't change
+    let res = writer.add_document(
+        doc()
+            .string("id", "2")
+            .sorted_numeric_dv("price", 5)
+            .build(),
+    );
+    assert!(res.is_err());
+    // neither can its index options, even within a document
+    assert!(writer
+        .add_document(doc().string("title", "hello").build())
+        .is_err());
+    assert!(writer
+        .add_document(doc().text("new", "a b").string("new", "c").build())
+        .is_err());
+    // a rejected document doesn't change the schema
+    assert!(schema.get("new").is_none());
+    assert!(writer
+        .add_documents(vec![
+            doc().string("id", "3").build(),
+            doc().string("id", "4").binary_dv("tag", b"b").build(),
+        ])
+        .is_err());
+    assert!(schema
+        .define(
+            "id",
+            FieldSchema {
+                index_options: IndexOptions::DocsAndFreqs,
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+    // the rejected documents didn't abort the pending segment
+    writer.add_document(doc().string("id", "5").numeric_dv("price", 20).build())?;
+    writer.commit()?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 2);
+    writer.close()?;
+
+    // a new schema is seeded with the fields of the existing segments
+    let schema = Arc::new(Schema::new());
+    let writer = IndexWriter::new(Arc::clone(&directory), new_config(&schema))?;
+    assert_eq!(
+        schema.get("tag").unwrap().doc_values_type,
+        DocValuesType::SortedSet
+    );
+    assert!(writer
+        .add_document(doc().sorted_numeric_dv("price", 1).build())
+        .is_err());
+    writer.add_document(doc().string("id", "6").numeric_dv("price", 30).build())?;
+    writer.commit()?;
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 3);
+    Ok(())
+}
+
+#[test]
+fn pre_tokenized_field() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_pre_tokenized_field")?;
+
+    // "quick" and its synonym "fast" share a position, "jumps" follows a
+    // removed stop word
+    let mut fox = Token::with_term(b"fox", 1, 10, 13);
+    fox.payload = b"animal".to_vec();
+    let tokens = vec![
+        Token::with_term(b"the", 1, 0, 3),
+        Token::with_term(b"quick", 1, 4, 9),
+        Token::with_term(b"fast", 0, 4, 9),
+        fox,
+        Token::with_term(b"jumps", 2, 18, 23),
+    ];
+    writer.add_document(vec![TextField::from_tokens("body", tokens.clone())?])?;
+    let mut field_type = TEXT_FIELD_TYPE;
+    field_type.index_options = IndexOptions::DocsAndFreqsAndPositionsAndOffsets;
+    writer.add_document(vec![Field::new_pre_tokenized(
+        "with_offsets".into(),
+        field_type,
+        tokens,
+    )?])?;
+
+    assert!(TextField::from_tokens("body", vec![Token::with_term(b"a", 0, 0, 1)]).is_err());
+    assert!(TextField::from_tokens(
+        "body",
+        vec![
+            Token::with_term(b"a", 1, 5, 6),
+            Token::with_term(b"b", 1, 0, 1)
+        ]
+    )
+    .is_err());
+    assert!(Field::new_pre_tokenized("stored".into(), STORE_FIELD_TYPE, vec![]).is_err());
+    let searcher = commit_and_search(&writer)?;
+    let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("body", "fast"), 1.0, None))?,
+        1
+    );
+    assert_eq!(
+        searcher.count(&TermQuery::new(term("with_offsets", "jumps"), 1.0, None))?,
+        1
+    );
+    let phrase = |words: &[&str], positions: Vec<i32>| -> Result<i32> {
+        let terms = words.iter().map(|w| term("body", w)).collect();
+        searcher.count(&PhraseQuery::new(terms, positions, 0, None, None)?)
+    };
+    assert_eq!(phrase(&["the", "fast", "fox"], vec![0, 1, 2])?, 1);
+    assert_eq!(phrase(&["quick", "fox"], vec![0, 1])?, 1);
+    assert_eq!(phrase(&["fox", "jumps"], vec![0, 1])?, 0);
+    assert_eq!(phrase(&["fox", "jumps"], vec![0, 2])?, 1);
+    Ok(())
+}
+
+#[test]
+fn custom_norms() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_custom_norms")?;
+
+    // a short but low quality document is indexed as a long one
+    let low_quality = i64::from(BM25Similarity::encode_norm_value(1.0, 100));
+    writer.add_document(vec![TextField::new("body", "apple pie", false)])?;
+    let mut body = TextField::new("body", "apple", false);
+    body.set_norm(low_quality);
+    writer.add_document(vec![body])?;
+    writer.add_document(vec![TextField::new("body", "apple", false)])?;
+
+    // a field without norms can't have a custom one
+    let mut id = Field::new("id".into(), STRING_FIELD_TYPE, Some("1".into()), None);
+    id.set_norm(1);
+    assert!(writer.add_document(vec![id]).is_err());
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.num_docs(), 3);
+    let norms = reader.leaves()[0].reader.norm_values("body")?.unwrap();
+    assert_eq!(norms.get(1)?, low_quality);
+    assert_eq!(
+        norms.get(2)?,
+        i64::from(BM25Similarity::encode_norm_value(1.0, 1))
+    );
+
+    let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+    let query = TermQuery::new(Term::new("body".into(), b"apple".to_vec()), 1.0, None);
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(&query, &mut collector)?;
+    let docs: Vec<_> = collector
+        .top_docs()
+        .score_docs()
+        .iter()
+        .map(|d| d.doc_id())
+        .collect();
+    assert_eq!(docs, vec![2, 0, 1]);
+    Ok(())
+}
+
+#[test]
+fn sparse_norms() -> Result<()> {
+    // returns the size of the norms of the first segment, and the norms of
+    // each field once merged with a second segment too small for sparse norms
+    fn index_norms(path: &str, codec: Lucene62Codec) -> Result<(u64, Vec<Vec<i64>>)> {
+        let dir_path = new_index_dir(path)?;
+        let directory = Arc::new(FSDirectory::new(&dir_path)?);
+        let config = IndexWriterConfig::new(
+            Arc::new(codec),
+            SerialMergeScheduler {},
+            TieredMergePolicy::default(),
+        );
+        let writer = IndexWriter::new(directory, Arc::new(config))?;
+        for docs in &[0..2000, 2000..2500] {
+            for i in docs.clone() {
+                let body = "body ".repeat(i as usize % 3 + 1);
+                let mut doc = vec![TextField::new("body", &body, false)];
+                if i % 100 == 0 {
+                    let rare = "rare ".repeat(i as usize % 7 + 1);
+                    doc.push(TextField::new("rare", &rare, false));
+                }
+                if i % 200 == 0 {
+                    doc.push(TextField::new("same", "same length", false));
+                }
+                writer.add_document(doc)?;
+            }
+            writer.commit()?;
+        }
+        let norms_size = std::fs::metadata(dir_path.join("_0.nvd"))?.len();
+
+        writer.force_merge(1, true)?;
+        let reader = writer.get_reader(true, false)?;
+        let leaf = &reader.leaves()[0].reader;
+        let mut values = vec![];
+        for field in &["body", "rare", "same"] {
+            let mut norms = leaf.norm_values(field)?.unwrap();
+            let in_order = (0..2500)
+                .map(|doc| norms.get_mut(doc))
+                .collect::<Result<Vec<_>>>()?;
+            let reversed = (0..2500)
+                .rev()
+                .map(|doc| norms.get(doc))
+                .collect::<Result<Vec<_>>>()?;
+            assert!(in_order.iter().eq(reversed.iter().rev()));
+            values.push(in_order);
+        }
+        Ok((norms_size, values))
+    }
+
+    let (sparse_size, sparse) =
+        index_norms("/tmp/test_rucene_sparse_norms", Lucene62Codec::default())?;
+    let (dense_size, dense) = index_norms(
+        "/tmp/test_rucene_sparse_norms_dense",
+        Lucene62Codec::java_lucene66(),
+    )?;
+    assert_eq!(sparse, dense);
+    // the rare and same fields take one byte per doc when dense
+    assert!(sparse_size + 3000 < dense_size);
+    assert_ne!(sparse[1][100], sparse[1][200]);
+    assert_eq!(sparse[1][101], 0);
+    assert_eq!(sparse[2][0], sparse[2][2200]);
+    assert_eq!(sparse[2][100], 0);
+    Ok(())
+}
+
+#[test]
+fn postings_impacts() -> Result<()> {
+    // checks that the impacts of each level bound the freq and norm of the docs
+    // up to its last doc, and that the ones of the first level are reached by
+    // the docs of their block; returns the max number of levels with impacts
+    fn check_impacts<C: Codec>(leaf: &LeafReaderContext<'_, C>) -> Result<usize> {
+        let norms = leaf.reader.norm_values("body")?.unwrap();
+        let terms = leaf.reader.terms("body")?.unwrap();
+        let mut iter = terms.iterator()?;
+        assert!(iter.seek_exact(b"common")?);
+        let mut impacts = iter.impacts()?.unwrap();
+        let mut postings = iter.postings_with_flags(PostingIteratorFlags::FREQS)?;
+
+        let mut max_levels = 0;
+        let mut block_impacts = vec![];
+        let mut block_up_to = -1;
+        let mut block = vec![];
+        let mut doc = postings.next()?;
+        while doc != NO_MORE_DOCS {
+            impacts.advance_shallow(doc)?;
+            let (freq, norm) = (postings.freq()?, norms.get(doc)?);
+            for level in 0..impacts.num_levels() {
+                let up_to = impacts.doc_id_up_to(level);
+                assert!(up_to >= doc);
+                let level_impacts = impacts.impacts(level)?;
+                if up_to == NO_MORE_DOCS {
+                    assert!(level_impacts.is_empty());
+                    continue;
+                }
+                max_levels = max_levels.max(level + 1);
+                assert!(level_impacts
+                    .iter()
+                    .any(|impact| impact.freq >= freq && impact.norm >= norm));
+            }
+            if doc > block_up_to {
+                for impact in &block_impacts {
+                    assert!(block.contains(impact));
+                }
+                block_impacts = impacts.impacts(0)?;
+                block_up_to = impacts.doc_id_up_to(0);
+                block.clear();
+            }
+            block.push(Impact::new(freq, norm));
+            doc = postings.next()?;
+        }
+        Ok(max_levels)
+    }
+
+    fn index_impacts(path: &str, codec: Lucene62Codec) -> Result<(Vec<usize>, usize)> {
+        let dir_path = new_index_dir(path)?;
+        let directory = Arc::new(FSDirectory::new(&dir_path)?);
+        let config = IndexWriterConfig::new(
+            Arc::new(codec),
+            SerialMergeScheduler {},
+            TieredMergePolicy::default(),
+        );
+        let writer = IndexWriter::new(directory, Arc::new(config))?;
+        for docs in &[0..1500, 1500..2500] {
+            for i in docs.clone() {
+                let body = format!(
+                    "{}{}",
+                    "common ".repeat(i % 5 + 1),
+                    "filler ".repeat(i * 7 % 11)
+                );
+                writer.add_document(vec![TextField::new("body", &body, false)])?;
+            }
+            writer.commit()?;
+        }
+        let reader = writer.get_reader(true, false)?;
+        let levels = reader
+            .leaves()
+            .iter()
+            .map(check_impacts)
+            .collect::<Result<Vec<_>>>()?;
+
+        writer.force_merge(1, true)?;
+        let reader = writer.get_reader(true, false)?;
+        let merged_levels = check_impacts(&reader.leaves()[0])?;
+        Ok((levels, merged_levels))
+    }
+
+    let (levels, merged_levels) = index_impacts(
+        "/tmp/test_rucene_postings_impacts",
+        Lucene62Codec::default(),
+    )?;
+    assert_eq!(levels, vec![2, 1]);
+    assert_eq!(merged_levels, 2);
+
+    // the postings of the Java Lucene format have no impacts
+    let (levels, merged_levels) = index_impacts(
+        "/tmp/test_rucene_postings_impacts_java",
+        Lucene62Codec::java_lucene66(),
+    )?;
+    assert_eq!(levels, vec![0, 0]);
+    assert_eq!(merged_levels, 0);
+    Ok(())
+}
+
+#[test]
+fn vector_values() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_vector_values")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(Lucene62Codec::default()),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+
+    // each vector encodes the id of its doc, one doc in ten has none
+    let new_doc = |i: usize| -> Result<Vec<Box<dyn Fieldable>>> {
+        let mut doc: Vec<Box<dyn Fieldable>> =
+            vec![Box::new(StringField::new("id", &i.to_string(), false))];
+        if i % 10 != 3 {
+            let vector = vec![i as f32, (i % 7) as f32];
+            doc.push(Box::new(VectorField::new(
+                "vec",
+                vector,
+                VectorSimilarity::Euclidean,
+            )?));
+        }
+        Ok(doc)
+    };
+    for docs in &[0..300, 300..500] {
+        for i in docs.clone() {
+            writer.add_document(new_doc(i)?)?;
+        }
+        writer.commit()?;
+    }
+    let id = |i: usize| Term::new("id".into(), i.to_string().into_bytes());
+    writer.delete_documents_by_terms(vec![id(100), id(101)])?;
+    writer.commit()?;
+
+    // a field keeps the dimension it was first indexed with
+    let wrong = VectorField::new("vec", vec![1.0, 2.0, 3.0], VectorSimilarity::Euclidean)?;
+    assert!(writer.add_document(vec![wrong]).is_err());
+
+    let check = |expected_leaves: usize| -> Result<()> {
+        let reader = writer.get_reader(true, false)?;
+        let mut ids = vec![];
+        for leaf in reader.leaves() {
+            let values = leaf.reader.vector_values("vec")?.unwrap();
+            assert_eq!(values.dimension(), 2);
+            assert_eq!(values.similarity(), VectorSimilarity::Euclidean);
+            let live_docs = leaf.reader.live_docs();
+            for ord in 0..values.size() {
+                let doc = values.doc_id(ord)?;
+                let vector = values.vector(ord)?;
+                assert_eq!(values.get(doc)?, Some(vector.clone()));
+                let i = vector[0] as usize;
+                assert_eq!(vector[1], (i % 7) as f32);
+                if live_docs.get(doc as usize) {
+                    ids.push(i);
+                }
+            }
+
+            // the graph search agrees with an exact scan of the vectors
+            let target = [250.2, 5.0];
+            let mut exact = (0..values.size())
+                .map(|ord| {
+                    let score = values.similarity().compare(&target, &values.vector(ord)?);
+                    Ok((score, values.doc_id(ord)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            exact.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then(a.1.cmp(&b.1)));
+            let hits = values.search(&target, 5, None)?;
+            assert_eq!(hits.len(), 5);
+            for (hit, (score, doc)) in hits.iter().zip(exact.iter()) {
+                assert_eq!(hit.doc, *doc);
+                assert!((hit.score - score).abs() < 1e-6);
+            }
+            assert!(values.search(&[1.0], 5, None).is_err());
+        }
+        ids.sort_unstable();
+        let expected: Vec<usize> = (0..500)
+            .filter(|i| i % 10 != 3 && *i != 100 && *i != 101)
+            .collect();
+        assert_eq!(ids, expected);
+        assert_eq!(reader.leaves().len(), expected_leaves);
+        Ok(())
+    };
+    check(2)?;
+    writer.force_merge(1, true)?;
+    check(1)?;
+
+    // the older format has no place for vectors
+    let dir_path = new_index_dir("/tmp/test_rucene_vector_values_lucene66")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(Lucene62Codec::java_lucene66()),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(directory, Arc::new(config))?;
+    writer.add_document(new_doc(0)?)?;
+    assert!(writer.commit().is_err());
+    Ok(())
+}
+
+#[test]
+fn custom_codec() -> Result<()> {
+    type Reader =
+        StandardDirectoryReader<FSDirectory, CodecEnum, SerialMergeScheduler, TieredMergePolicy>;
+
+    register_codec("IntegrationCompressed", || {
+        let codec = Lucene62Codec::default()
+            .with_stored_fields_mode(StoredFieldCompressMode::BestCompression);
+        CodecEnum::Custom(CustomCodec::new("IntegrationCompressed", codec).unwrap())
+    })?;
+
+    let dir_path = new_index_dir("/tmp/test_rucene_custom_codec")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(codec_for_name("IntegrationCompressed")?),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    writer.add_document(vec![new_stored_text_field("body".into(), "hello".into())])?;
+    writer.close()?;
+
+    // the segment records the codec name, which is resolved again on read
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(
+        infos.segments[0].info.codec().name(),
+        "IntegrationCompressed"
+    );
+
+    let reader = Reader::open(Arc::clone(&directory))?;
+    let doc = reader.document(0, &["body".to_string()])?;
+    assert_eq!(doc.get_string("body"), Some("hello"));
+    Ok(())
+}
+
+#[test]
+fn stored_fields_compression_mode() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_stored_fields_compression_mode")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let text = |i: usize| format!("document {} of a fairly repetitive stored text", i % 7);
+    let stored_size =
+        |directory: &FSDirectory, segment: &str| directory.file_length(&format!("{}.fdt", segment));
+
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..500 {
+        writer.add_document(vec![new_stored_text_field("body".into(), text(i))])?;
+    }
+    writer.close()?;
+
+    let mut config = IndexWriterConfig::default();
+    assert_eq!(
+        config.stored_fields_mode(),
+        StoredFieldCompressMode::BestSpeed
+    );
+    config.set_stored_fields_mode(StoredFieldCompressMode::BestCompression);
+    assert_eq!(
+        config.stored_fields_mode(),
+        StoredFieldCompressMode::BestCompression
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..500 {
+        writer.add_document(vec![new_stored_text_field("body".into(), text(i))])?;
+    }
+    writer.commit()?;
+
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let (fast, high) = (&infos.segments[0].info, &infos.segments[1].info);
+    assert_eq!(
+        fast.attributes["Lucene50StoredFieldsFormat.mode"],
+        "BEST_SPEED"
+    );
+    assert_eq!(
+        high.attributes["Lucene50StoredFieldsFormat.mode"],
+        "BEST_COMPRESSION"
+    );
+    assert!(stored_size(&directory, &high.name)? < stored_size(&directory, &fast.name)?);
+
+    // segments of both modes are read and merged into the writer's mode
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    for doc in [0, 499, 500, 999] {
+        let stored = reader.document(doc, &["body".to_string()])?;
+        assert_eq!(
+            stored.get_string("body"),
+            Some(text(doc as usize % 500).as_str())
+        );
+    }
+    writer.commit()?;
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    assert_eq!(
+        infos.segments[0].info.attributes["Lucene50StoredFieldsFormat.mode"],
+        "BEST_COMPRESSION"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_compression() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_zstd_compression")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let body = |i: usize| {
+        format!(
+            r#"{{"id": {}, "title": "item number {}", "tags": ["zstd", "stored", "json"]}}"#,
+            i,
+            i % 97
+        )
+    };
+    let stored_size =
+        |directory: &FSDirectory, segment: &str| directory.file_length(&format!("{}.fdt", segment));
+
+    // one default segment, then a zstd one spanning several sub-blocks
+    let writer = IndexWriter::new(
+        Arc::clone(&directory),
+        Arc::new(IndexWriterConfig::default()),
+    )?;
+    for i in 0..3000 {
+        writer.add_document(vec![new_stored_text_field("body".into(), body(i))])?;
+    }
+    writer.close()?;
+
+    let mut config = IndexWriterConfig::default();
+    config.set_stored_fields_mode(StoredFieldCompressMode::Zstd(ZSTD_DEFAULT_LEVEL));
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..3000 {
+        writer.add_document(vec![new_stored_text_field("body".into(), body(i))])?;
+    }
+    writer.commit()?;
+
+    let infos = SegmentInfos::<_, CodecEnum>::read_latest_commit(&directory)?;
+    let (fast, zstd) = (&infos.segments[0].info, &infos.segments[1].info);
+    assert_eq!(zstd.attributes["Lucene50StoredFieldsFormat.mode"], "ZSTD");
+    assert!(stored_size(&directory, &zstd.name)? * 2 < stored_size(&directory, &fast.name)?);
+
+    let check = |reader: &dyn Fn(i32) -> Result<Option<String>>| -> Result<()> {
+        for doc in [0, 1, 40, 1500, 2999, 3000, 3040, 4500, 5999] {
+            assert_eq!(reader(doc)?, Some(body(doc as usize % 3000)));
+        }
+        Ok(())
+    };
+    let reader = writer.get_reader(true, false)?;
+    check(&|doc| {
+        let stored = reader.document(doc, &["body".to_string()])?;
+        Ok(stored.get_string("body").map(String::from))
+    })?;
+
+    // merging decompresses both segments and writes a zstd one
+    writer.force_merge(1, true)?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    check(&|doc| {
+        let stored = reader.document(doc, &["body".to_string()])?;
+        Ok(stored.get_string("body").map(String::from))
+    })?;
+
+    // term vectors are compressed by a codec registered under its own name
+    register_codec("IntegrationZstd", || {
+        let codec = Lucene62Codec::default()
+            .with_stored_fields_mode(StoredFieldCompressMode::Zstd(1))
+            .with_zstd_term_vectors(1);
+        CodecEnum::Custom(CustomCodec::new("IntegrationZstd", codec).unwrap())
+    })?;
+    let dir_path = new_index_dir("/tmp/test_rucene_zstd_term_vectors")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(codec_for_name("IntegrationZstd")?),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..100 {
+        writer.add_document(vec![new_index_text_field("title".into(), body(i))])?;
+    }
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+    let vector = reader.get_term_vector(42, "title")?.unwrap();
+    assert_eq!(vector.term(b"42,").unwrap().freq, 1);
+    Ok(())
+}
+
+#[test]
+fn per_field_formats() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default().with_field_format(
+        "id",
+        PostingsFormatEnum::Lucene50(Lucene50PostingsFormat::with_block_size(16, 32)),
+    );
+    let doc_values_format = PerFieldDocValuesFormat::default().with_field_format(
+        "weight",
+        DocValuesFormatEnum::Lucene54(Lucene54DocValuesFormat::default()),
+    );
+    let codec = Lucene62Codec::default()
+        .with_postings_format(postings_format)
+        .with_doc_values_format(doc_values_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_per_field_formats")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    for i in 0..200 {
+        writer.add_document(vec![
+            Box::new(new_keyword_field("id".into(), format!("id{}", i))) as Box<dyn Fieldable>,
+            Box::new(new_index_text_field(
+                "title".into(),
+                format!("title {}", i % 10),
+            )),
+            Box::new(NumericDocValuesField::new("weight", i)),
+        ])?;
+        if i == 99 {
+            writer.commit()?;
+        }
+    }
+    writer.commit()?;
+
+    // the id field is written with its own format, in files of their own
+    let files = directory.list_all()?;
+    assert!(files.iter().any(|f| f.ends_with("_Lucene50_0.tim")));
+    assert!(files.iter().any(|f| f.ends_with("_Lucene50_1.tim")));
+
+    let check = |reader: StandardDirectoryReader<_, _, _, _>| -> Result<()> {
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        for i in (0..200).step_by(7) {
+            let id = Term::new("id".into(), format!("id{}", i).into_bytes());
+            assert_eq!(searcher.count(&TermQuery::new(id, 1.0, None))?, 1);
+        }
+        let title = Term::new("title".into(), b"3".to_vec());
+        assert_eq!(searcher.count(&TermQuery::new(title, 1.0, None))?, 20);
+        let mut weights = HashSet::new();
+        for leaf in searcher.reader().leaves() {
+            let field_infos = leaf.reader.field_infos();
+            let suffix = |field: &str| {
+                field_infos
+                    .field_info_by_name(field)
+                    .and_then(|info| info.attribute("PerFieldPostingsFormat.suffix"))
+            };
+            assert_eq!(suffix("title"), Some("0".to_string()));
+            assert_eq!(suffix("id"), Some("1".to_string()));
+            let values = leaf.reader.get_numeric_doc_values("weight")?;
+            for doc in 0..leaf.reader.max_doc() {
+                weights.insert(values.get(doc)?);
+            }
+        }
+        assert_eq!(weights, (0..200).collect());
+        Ok(())
+    };
+    check(writer.get_reader(true, false)?)?;
+
+    // merging keeps the formats of the fields
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    check(reader)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn bloom_filter_postings_format() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default().with_field_format(
+        "id",
+        PostingsFormatEnum::BloomFilter(BloomFilterPostingsFormat::new(
+            Lucene50PostingsFormat::default(),
+            0.001,
+        )?),
+    );
+    assert!(BloomFilterPostingsFormat::new(Lucene50PostingsFormat::default(), 1.5).is_err());
+    let codec = Lucene62Codec::default().with_postings_format(postings_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_bloom_filter")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let new_doc = |id: usize, text: &str| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), format!("id{}", id))),
+            Box::new(new_index_text_field("title".into(), text.into())),
+        ]
+    };
+    for i in 0..300 {
+        writer.add_document(new_doc(i, "alice"))?;
+        if i % 100 == 99 {
+            writer.commit()?;
+        }
+    }
+    let files = directory.list_all()?;
+    assert_eq!(files.iter().filter(|f| f.ends_with(".blm")).count(), 3);
+
+    // updates and deletes by id resolve through the filtered terms
+    let id = |i: usize| Term::new("id".into(), format!("id{}", i).into_bytes());
+    for i in &[5, 150, 299] {
+        writer.update_document(new_doc(*i, "hatter"), Some(id(*i)))?;
+    }
+    writer.delete_documents_by_terms(vec![id(10), id(1000)])?;
+    writer.commit()?;
+
+    let check = |reader: StandardDirectoryReader<_, _, _, _>| -> Result<()> {
+        assert_eq!(reader.num_docs(), 299);
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |term: Term| searcher.count(&TermQuery::new(term, 1.0, None));
+        for i in 0..300 {
+            assert_eq!(count(id(i))?, if i == 10 { 0 } else { 1 });
+        }
+        for i in 300..400 {
+            assert_eq!(count(id(i))?, 0);
+        }
+        let hatter = Term::new("title".into(), b"hatter".to_vec());
+        assert_eq!(count(hatter)?, 3);
+        Ok(())
+    };
+    check(writer.get_reader(true, false)?)?;
+
+    // merged segments rebuild the filters from the live terms
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    check(reader)?;
+    writer.close()?;
+    let files = directory.list_all()?;
+    assert_eq!(files.iter().filter(|f| f.ends_with(".blm")).count(), 1);
+    Ok(())
+}
+
+#[test]
+fn direct_postings_format() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default().with_field_format(
+        "tag",
+        PostingsFormatEnum::Direct(DirectPostingsFormat::default()),
+    );
+    let codec = Lucene62Codec::default().with_postings_format(postings_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_direct_postings")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let tags = ["red green blue", "green blue", "blue red", "yellow"];
+    for i in 0..200 {
+        writer.add_document(vec![
+            Box::new(new_index_text_field("tag".into(), tags[i % 4].into())),
+            Box::new(new_index_text_field("title".into(), format!("t{}", i % 3))),
+        ])?;
+        if i % 50 == 49 {
+            writer.commit()?;
+        }
+    }
+    let files = directory.list_all()?;
+    assert!(files.iter().any(|f| f.contains("_Direct_0")));
+
+    let check = |reader: StandardDirectoryReader<_, _, _, _>| -> Result<()> {
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+        let count = |text: &str| searcher.count(&TermQuery::new(term("tag", text), 1.0, None));
+        assert_eq!(count("red")?, 100);
+        assert_eq!(count("green")?, 100);
+        assert_eq!(count("blue")?, 150);
+        assert_eq!(count("yellow")?, 50);
+        assert_eq!(count("purple")?, 0);
+        let phrase = |words: &[&str]| -> Result<i32> {
+            let terms = words.iter().map(|w| term("tag", w)).collect();
+            let positions = (0..words.len() as i32).collect();
+            searcher.count(&PhraseQuery::new(terms, positions, 0, None, None)?)
+        };
+        assert_eq!(phrase(&["green", "blue"])?, 100);
+        assert_eq!(phrase(&["blue", "red"])?, 50);
+        assert_eq!(phrase(&["red", "blue"])?, 0);
+        let title = searcher.count(&TermQuery::new(term("title", "t0"), 1.0, None))?;
+        assert_eq!(title, 67);
+        Ok(())
+    };
+    check(writer.get_reader(true, false)?)?;
+
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    check(reader)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn fst_postings_format() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default()
+        .with_field_format("id", PostingsFormatEnum::FST(FSTPostingsFormat));
+    let codec = Lucene62Codec::default().with_postings_format(postings_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_fst_postings")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let new_doc = |id: usize, text: &str| -> Vec<Box<dyn Fieldable>> {
+        vec![
+            Box::new(new_keyword_field("id".into(), format!("id{:03}", id))),
+            Box::new(new_index_text_field("title".into(), text.into())),
+        ]
+    };
+    for i in 0..300 {
+        writer.add_document(new_doc(i, "alice"))?;
+        if i % 100 == 99 {
+            writer.commit()?;
+        }
+    }
+    let files = directory.list_all()?;
+    assert_eq!(
+        files.iter().filter(|f| f.ends_with("_FST50_0.tfp")).count(),
+        3
+    );
+
+    let id = |i: usize| Term::new("id".into(), format!("id{:03}", i).into_bytes());
+    for i in &[5, 150, 299] {
+        writer.update_document(new_doc(*i, "hatter"), Some(id(*i)))?;
+    }
+    writer.delete_documents_by_terms(vec![id(10), id(1000)])?;
+    writer.commit()?;
+
+    let check = |reader: StandardDirectoryReader<_, _, _, _>| -> Result<()> {
+        assert_eq!(reader.num_docs(), 299);
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |term: Term| searcher.count(&TermQuery::new(term, 1.0, None));
+        for i in 0..300 {
+            assert_eq!(count(id(i))?, if i == 10 { 0 } else { 1 });
+        }
+        assert_eq!(count(id(300))?, 0);
+        let hatter = Term::new("title".into(), b"hatter".to_vec());
+        assert_eq!(count(hatter)?, 3);
+        Ok(())
+    };
+    check(writer.get_reader(true, false)?)?;
+
+    writer.force_merge(1, true)?;
+    let reader = writer.get_reader(true, false)?;
+    assert_eq!(reader.leaves().len(), 1);
+    let terms = reader.leaves()[0].reader.terms("id")?.unwrap();
+    assert_eq!(terms.size()?, 299);
+    assert_eq!(terms.min()?, Some(b"id000".to_vec()));
+    assert_eq!(terms.max()?, Some(b"id299".to_vec()));
+    let mut iter = terms.iterator()?;
+    let mut seek_ceil = |text: &str| -> Result<(SeekStatus, Option<Vec<u8>>)> {
+        let status = iter.seek_ceil(text.as_bytes())?;
+        let term = if status == SeekStatus::End {
+            None
+        } else {
+            Some(iter.term()?.to_vec())
+        };
+        Ok((status, term))
+    };
+    assert_eq!(
+        seek_ceil("id150")?,
+        (SeekStatus::Found, Some(b"id150".to_vec()))
+    );
+    assert_eq!(
+        seek_ceil("id010")?,
+        (SeekStatus::NotFound, Some(b"id011".to_vec()))
+    );
+    assert_eq!(
+        seek_ceil("id1")?,
+        (SeekStatus::NotFound, Some(b"id100".to_vec()))
+    );
+    assert_eq!(
+        seek_ceil("")?,
+        (SeekStatus::NotFound, Some(b"id000".to_vec()))
+    );
+    assert_eq!(seek_ceil("id2999")?, (SeekStatus::End, None));
+    assert_eq!(seek_ceil("j")?, (SeekStatus::End, None));
+    assert!(iter.seek_exact(b"id042")?);
+    assert_eq!(iter.doc_freq()?, 1);
+    assert_eq!(iter.next()?, Some(b"id043".to_vec()));
+    assert!(!iter.seek_exact(b"id010")?);
+    check(reader)?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    assert!(checker.check_index()?.clean);
+    Ok(())
+}
+
+#[test]
+fn completion_postings_format() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default().with_field_format(
+        "suggest",
+        PostingsFormatEnum::Completion(CompletionPostingsFormat::default()),
+    );
+    let codec = Lucene62Codec::default().with_postings_format(postings_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_completion_postings")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let new_doc = |suggestions: &[(&str, &str, u32)]| -> Result<Vec<Box<dyn Fieldable>>> {
+        let mut tokens = vec![];
+        for (context, input, weight) in suggestions {
+            let term = completion_term(context.as_bytes(), input.as_bytes())?;
+            let mut token = Token::with_term(&term, 1, 0, 0);
+            token.payload = completion_payload(*weight);
+            tokens.push(token);
+        }
+        Ok(vec![Box::new(Field::new_pre_tokenized(
+            "suggest".into(),
+            TEXT_FIELD_TYPE,
+            tokens,
+        )?)])
+    };
+    writer.add_document(new_doc(&[("", "rust", 10), ("lang", "rust", 10)])?)?;
+    writer.add_document(new_doc(&[("", "ruby", 7), ("lang", "ruby", 7)])?)?;
+    writer.commit()?;
+    writer.add_document(new_doc(&[("", "rucene", 20), ("search", "rucene", 20)])?)?;
+    writer.add_document(new_doc(&[("", "python", 5), ("lang", "python", 5)])?)?;
+    writer.add_document(new_doc(&[("", "rust", 3)])?)?;
+    writer.commit()?;
+    let files = directory.list_all()?;
+    assert_eq!(
+        files
+            .iter()
+            .filter(|f| f.ends_with("_Completion50_0.lkp"))
+            .count(),
+        2
+    );
+    assert!(completion_term(b"a\x1db", b"c").is_err());
+
+    // (input, context, weight) of the top suggestions over all the segments
+    let lookup = |prefix: &str, contexts: &[&[u8]], num: usize| -> Result<Vec<_>> {
+        let reader = writer.get_reader(true, false)?;
+        let mut completions = vec![];
+        for leaf in reader.leaves() {
+            match leaf.reader.terms("suggest")? {
+                Some(FieldTermsEnum::Completion(terms)) => {
+                    for c in terms.lookup(prefix.as_bytes(), contexts, num)? {
+                        completions.push((
+                            String::from_utf8(c.input).unwrap(),
+                            String::from_utf8(c.context).unwrap(),
+                            c.weight,
+                        ));
+                    }
+                }
+                _ => panic!("suggest isn't a completion field"),
+            }
+        }
+        completions.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.cmp(b)));
+        completions.truncate(num);
+        Ok(completions)
+    };
+    let check = || -> Result<()> {
+        let s = |input: &str, context: &str, weight: u32| (input.into(), context.into(), weight);
+        assert_eq!(
+            lookup("ru", &[b""], 3)?,
+            vec![s("rucene", "", 20), s("rust", "", 10), s("ruby", "", 7)]
+        );
+        assert_eq!(
+            lookup("rus", &[b""], 3)?,
+            vec![s("rust", "", 10), s("rust", "", 3)]
+        );
+        assert_eq!(
+            lookup("ru", &[b"lang"], 5)?,
+            vec![s("rust", "lang", 10), s("ruby", "lang", 7)]
+        );
+        assert_eq!(
+            lookup("r", &[b"lang", b"search"], 2)?,
+            vec![s("rucene", "search", 20), s("rust", "lang", 10)]
+        );
+        assert_eq!(
+            lookup("py", &[], 5)?,
+            vec![s("python", "", 5), s("python", "lang", 5)]
+        );
+        assert_eq!(lookup("java", &[], 5)?, vec![]);
+        assert_eq!(
+            lookup("", &[b"search"], 5)?,
+            vec![s("rucene", "search", 20)]
+        );
+        Ok(())
+    };
+    check()?;
+
+    writer.force_merge(1, true)?;
+    assert_eq!(writer.get_reader(true, false)?.leaves().len(), 1);
+    check()?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    assert!(checker.check_index()?.clean);
+    Ok(())
+}
+
+#[test]
+fn suggest_field_search() -> Result<()> {
+    let postings_format = PerFieldPostingsFormat::default().with_field_format(
+        "suggest",
+        PostingsFormatEnum::Completion(CompletionPostingsFormat::default()),
+    );
+    let codec = Lucene62Codec::default().with_postings_format(postings_format);
+
+    let dir_path = new_index_dir("/tmp/test_rucene_suggest_field")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(CodecEnum::Lucene62(codec)),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let new_doc = |id: &str, input: &str, contexts: &[&str], weight: u32| -> Result<_> {
+        Ok(vec![
+            Box::new(StringField::new("id", id, true)) as Box<dyn Fieldable>,
+            Box::new(SuggestField::with_contexts(
+                "suggest", input, contexts, weight,
+            )?),
+        ])
+    };
+    writer.add_document(new_doc("1", "rust", &["lang"], 10)?)?;
+    writer.add_document(new_doc("2", "ruby", &["lang"], 7)?)?;
+    writer.add_document(new_doc("3", "rucene", &["search", "lib"], 20)?)?;
+    writer.commit()?;
+    writer.add_document(new_doc("4", "rustc", &[], 15)?)?;
+    writer.add_document(new_doc("5", "python", &["lang"], 5)?)?;
+    writer.commit()?;
+
+    let suggest = |query: CompletionQuery, num: usize| -> Result<Vec<(String, String, u32)>> {
+        let reader = writer.get_reader(true, false)?;
+        let searcher = SuggestIndexSearcher::new(reader.as_ref());
+        Ok(searcher
+            .suggest(&query, num)?
+            .into_iter()
+            .map(|s| (s.key, s.context, s.weight))
+            .collect())
+    };
+    let s = |key: &str, context: &str, weight: u32| (key.into(), context.into(), weight);
+    assert_eq!(
+        suggest(CompletionQuery::new("suggest", "ru"), 3)?,
+        vec![
+            s("rucene", "lib", 20),
+            s("rucene", "search", 20),
+            s("rustc", "", 15)
+        ]
+    );
+    assert_eq!(
+        suggest(
+            CompletionQuery::new("suggest", "ru").with_contexts(&["lang"]),
+            5
+        )?,
+        vec![s("rust", "lang", 10), s("ruby", "lang", 7)]
+    );
+    assert_eq!(
+        suggest(
+            CompletionQuery::new("suggest", "ru").with_contexts(&[""]),
+            5
+        )?,
+        vec![s("rustc", "", 15)]
+    );
+    assert!(suggest(CompletionQuery::new("id", "1"), 1).is_err());
+
+    // deleted docs are skipped, even when they take the top slots
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"3".to_vec())])?;
+    writer.commit()?;
+    let expected = vec![s("rustc", "", 15), s("rust", "lang", 10)];
+    assert_eq!(suggest(CompletionQuery::new("suggest", "ru"), 2)?, expected);
+
+    writer.force_merge(1, true)?;
+    assert_eq!(writer.get_reader(true, false)?.leaves().len(), 1);
+    assert_eq!(suggest(CompletionQuery::new("suggest", "ru"), 2)?, expected);
+    writer.close()?;
+    Ok(())
+}
+
+#[test]
+fn simple_text_codec() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_simple_text_codec")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(SimpleTextCodec::default()),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let point_type = FieldType {
+        tokenized: false,
+        dimension_count: 1,
+        index_dimension_count: 1,
+        dimension_num_bytes: 4,
+        vector_dimension: 0,
+        vector_similarity: VectorSimilarity::Euclidean,
+        ..Default::default()
+    };
+    for i in 0..20 {
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(new_keyword_field("id".into(), i.to_string())),
+            Box::new(new_index_text_field(
+                "title".into(),
+                format!("title {}\nline {}", i, i % 3),
+            )),
+            Box::new(new_stored_text_field("body".into(), format!("body\\{}", i))),
+            Box::new(NumericDocValuesField::new("price", i64::from(i) * 3)),
+            Box::new(Field::new_bytes(
+                "rank".into(),
+                IntPoint::pack(&[i - 5]),
+                point_type.clone(),
+            )),
+        ];
+        if i % 2 == 0 {
+            doc.push(Box::new(SortedDocValuesField::new(
+                "parity",
+                b"even".as_ref(),
+            )));
+            doc.push(Box::new(SortedNumericDocValuesField::new(
+                "tags",
+                i64::from(i),
+            )));
+            doc.push(Box::new(SortedNumericDocValuesField::new("tags", -1)));
+            doc.push(Box::new(SortedSetDocValuesField::new("labels", b"b\nc")));
+            doc.push(Box::new(SortedSetDocValuesField::new("labels", b"a")));
+        } else {
+            doc.push(Box::new(BinaryDocValuesField::new("features", b"odd")));
+        }
+        writer.add_document(doc)?;
+        if i % 5 == 4 {
+            writer.commit()?;
+        }
+    }
+    let files = directory.list_all()?;
+    for ext in &["pst", "dat", "dim", "fld"] {
+        assert!(files.iter().any(|f| f.ends_with(&format!(".{}", ext))));
+    }
+
+    let check = |writer: &IndexWriter<_, SimpleTextCodec, _, _>, num_docs: i32| -> Result<()> {
+        let reader = writer.get_reader(true, false)?;
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+        assert_eq!(
+            searcher.count(&TermQuery::new(term("title", "line"), 1.0, None))?,
+            num_docs
+        );
+        assert_eq!(
+            searcher.count(&TermQuery::new(term("title", "2"), 1.0, None))?,
+            6
+        );
+        assert_eq!(
+            searcher.count(&TermQuery::new(term("id", "13"), 1.0, None))?,
+            1
+        );
+        let query = IntPoint::new_range_query("rank".into(), -1, 4)?;
+        assert_eq!(searcher.count(query.as_ref())?, 6);
+
+        let mut collector = TopDocsCollector::new(1);
+        searcher.search(&TermQuery::new(term("id", "14"), 1.0, None), &mut collector)?;
+        let doc_id = collector.top_docs().score_docs()[0].doc_id();
+        let stored_doc = searcher.reader().document(doc_id, &["body".into()])?;
+        assert_eq!(
+            *stored_doc.fields[0].field.field_data().unwrap(),
+            VariantValue::VString("body\\14".into())
+        );
+        let leaf = searcher.reader().leaf_reader_for_doc(doc_id);
+        let doc = doc_id - leaf.doc_base;
+        assert_eq!(leaf.reader.get_numeric_doc_values("price")?.get(doc)?, 42);
+        let mut parity = leaf.reader.get_sorted_doc_values("parity")?;
+        assert_eq!(parity.get(doc)?, b"even".to_vec());
+        let mut tags = leaf.reader.get_sorted_numeric_doc_values("tags")?;
+        tags.set_document(doc)?;
+        assert_eq!(
+            (tags.count(), tags.value_at(0)?, tags.value_at(1)?),
+            (2, -1, 14)
+        );
+        let mut labels = leaf.reader.get_sorted_set_doc_values("labels")?;
+        labels.set_document(doc)?;
+        assert_eq!((labels.next_ord()?, labels.next_ord()?), (0, 1));
+        assert_eq!(labels.next_ord()?, NO_MORE_ORDS);
+        assert_eq!(labels.lookup_ord(1)?, b"b\nc".to_vec());
+        let mut features = leaf.reader.get_binary_doc_values("features")?;
+        assert!(features.get(doc)?.is_empty());
+        assert_eq!(features.get(doc - 1)?, b"odd".to_vec());
+        Ok(())
+    };
+    check(&writer, 20)?;
+
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"3".to_vec())])?;
+    writer.force_merge(1, true)?;
+    assert_eq!(writer.get_reader(true, false)?.leaves().len(), 1);
+    assert_eq!(writer.get_reader(true, false)?.num_docs(), 19);
+    check(&writer, 19)?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, SimpleTextCodec>::new(Arc::clone(&directory));
+    assert!(checker.check_index()?.clean);
+    Ok(())
+}
+
+/// Collects the packed values of all points of a field
+struct CollectPoints(Vec<Vec<u8>>);
+
+impl IntersectVisitor for CollectPoints {
+    fn visit(&mut self, _doc_id: DocId) -> Result<()> {
+        unreachable!()
+    }
+
+    fn visit_by_packed_value(&mut self, _doc_id: DocId, packed_value: &[u8]) -> Result<()> {
+        self.0.push(packed_value.to_vec());
+        Ok(())
+    }
+
+    fn compare(&self, _min_packed_value: &[u8], _max_packed_value: &[u8]) -> Relation {
+        Relation::CellCrossesQuery
+    }
+}
+
+#[test]
+fn points_with_unindexed_dimensions() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_points_with_unindexed_dimensions")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let codec = Lucene62Codec::default().with_points_format(Lucene60PointsFormat::new(16, 16.0)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(codec),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    // x and y are indexed, the third dimension is only stored
+    let mut point_type = FieldType {
+        tokenized: false,
+        ..Default::default()
+    };
+    point_type.set_dimensions_with_index_count(3, 2, 4)?;
+    for i in 0..500 {
+        writer.add_document(vec![Box::new(Field::new_bytes(
+            "shape".into(),
+            IntPoint::pack(&[i % 50, i / 50, i * 7]),
+            point_type.clone(),
+        ))])?;
+        if i % 200 == 199 {
+            writer.commit()?;
+        }
+    }
+    writer.commit()?;
+
+    let check = |writer: &IndexWriter<_, Lucene62Codec, _, _>| -> Result<()> {
+        let reader = writer.get_reader(true, false)?;
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let query = IntPoint::new_multi_range_query("shape".into(), &[10, 2], &[19, 5])?;
+        assert_eq!(searcher.count(query.as_ref())?, 40);
+
+        let mut values = vec![];
+        for leaf in searcher.reader().leaves() {
+            let points = leaf.reader.point_values().unwrap();
+            assert_eq!(points.num_dimensions("shape")?, 3);
+            assert_eq!(points.num_index_dimensions("shape")?, 2);
+            assert_eq!(points.min_packed_value("shape")?.len(), 8);
+            let mut collector = CollectPoints(vec![]);
+            points.intersect("shape", &mut collector)?;
+            values.extend(collector.0);
+        }
+        let mut stored: Vec<i32> = values
+            .iter()
+            .map(|v| IntPoint::decode_dimension(&v[8..]))
+            .collect();
+        stored.sort_unstable();
+        assert_eq!(stored, (0..500).map(|i| i * 7).collect::<Vec<_>>());
+        Ok(())
+    };
+    check(&writer)?;
+    writer.force_merge(1, true)?;
+    check(&writer)?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    assert!(checker.check_index()?.clean);
+    Ok(())
+}
+
+#[test]
+fn java_lucene66_codec() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_java_lucene66_codec")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(Lucene62Codec::java_lucene66()),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    let mut point_type = FieldType {
+        tokenized: false,
+        ..Default::default()
+    };
+    point_type.set_dimensions(2, 4)?;
+    let add_docs = |docs: std::ops::Range<i32>| -> Result<()> {
+        for i in docs {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(new_keyword_field("id".into(), i.to_string())),
+                Box::new(new_index_text_field(
+                    "title".into(),
+                    format!("title {}", i % 10),
+                )),
+                Box::new(Field::new_bytes(
+                    "location".into(),
+                    IntPoint::pack(&[i % 50, i / 50]),
+                    point_type.clone(),
+                )),
+            ];
+            if i % 100 == 0 {
+                doc.push(Box::new(NumericDocValuesField::new("boost", i64::from(i))));
+            }
+            writer.add_document(doc)?;
+        }
+        writer.commit()?;
+        Ok(())
+    };
+    add_docs(0..1000)?;
+
+    // the files of the first segment are in the versions Java Lucene 6.6 writes
+    let open = |suffix: &str| -> Result<Box<dyn IndexInput>> {
+        let files = directory.list_all()?;
+        let file = files
+            .iter()
+            .find(|f| f.starts_with("_0") && f.ends_with(suffix))
+            .unwrap();
+        directory.open_input(file, &IOContext::Default)
+    };
+    let version = |suffix: &str, codec: &str| -> Result<i32> {
+        check_header(open(suffix)?.as_mut(), codec, 0, i32::MAX)
+    };
+    assert_eq!(version(".fnm", "Lucene60FieldInfos")?, 0);
+    assert_eq!(version("_Lucene50_0.doc", "Lucene50PostingsWriterDoc")?, 0);
+    assert_eq!(version("_Lucene50_0.tim", "BlockTreeTermsDict")?, 3);
+    assert_eq!(version("_Lucene54_0.dvm", "Lucene54DocValuesMetadata")?, 0);
+    assert_eq!(version(".nvm", "Lucene53NormsMetadata")?, 0);
+    let mut terms_index = open("_Lucene50_0.tip")?;
+    check_header(terms_index.as_mut(), "BlockTreeTermsIndex", 0, 3)?;
+    let mut id = [0u8; 16];
+    terms_index.read_exact(&mut id)?;
+    check_index_header_suffix(terms_index.as_mut(), "Lucene50_0")?;
+    assert_eq!(check_header(terms_index.as_mut(), "FST", 0, 6)?, 5);
+
+    add_docs(1000..1500)?;
+    let check = |writer: &IndexWriter<_, Lucene62Codec, _, _>| -> Result<()> {
+        let reader = writer.get_reader(true, false)?;
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term = |field: &str, text: &str| Term::new(field.into(), text.as_bytes().to_vec());
+        assert_eq!(
+            searcher.count(&TermQuery::new(term("title", "3"), 1.0, None))?,
+            150
+        );
+        assert_eq!(
+            searcher.count(&TermQuery::new(term("id", "1234"), 1.0, None))?,
+            1
+        );
+        let query = IntPoint::new_multi_range_query("location".into(), &[10, 2], &[19, 5])?;
+        assert_eq!(searcher.count(query.as_ref())?, 40);
+        let mut boosts = vec![];
+        for leaf in searcher.reader().leaves() {
+            let values = leaf.reader.get_numeric_doc_values("boost")?;
+            for doc in 0..leaf.reader.max_doc() {
+                let value = values.get(doc)?;
+                if value != 0 {
+                    boosts.push(value);
+                }
+            }
+        }
+        boosts.sort_unstable();
+        assert_eq!(boosts, (1..15).map(|i| i * 100).collect::<Vec<_>>());
+        Ok(())
+    };
+    check(&writer)?;
+    writer.force_merge(1, true)?;
+    check(&writer)?;
+    writer.close()?;
+
+    let checker = CheckIndex::<_, CodecEnum>::new(Arc::clone(&directory));
+    assert!(checker.check_index()?.clean);
+
+    // Java Lucene 6 can't record points indexing only some of their dimensions
+    let dir_path = new_index_dir("/tmp/test_rucene_java_lucene66_codec_points")?;
+    let directory = Arc::new(FSDirectory::new(&dir_path)?);
+    let config = IndexWriterConfig::new(
+        Arc::new(Lucene62Codec::java_lucene66()),
+        SerialMergeScheduler {},
+        TieredMergePolicy::default(),
+    );
+    let writer = IndexWriter::new(Arc::clone(&directory), Arc::new(config))?;
+    point_type.set_dimensions_with_index_count(3, 2, 4)?;
+    writer.add_document(vec![Box::new(Field::new_bytes(
+        "shape".into(),
+        IntPoint::pack(&[1, 2, 3]),
+        point_type,
+    ))])?;
+    assert!(writer.commit().is_err());
+    Ok(())
+}
+
+#[test]
+fn unified_highlighter() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_unified_highlighter")?;
+
+    let texts = [
+        "Alice was beginning to get very tired. She had peeped into the book her sister was \
+         reading, but it had no pictures or conversations in it. And what is the use of a book, \
+         thought Alice.",
+        "The rabbit hole went straight on like a tunnel.",
+    ];
+    let postings_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+        ..FieldType::default()
+    };
+    let vectors_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqsAndPositions,
+        store_term_vectors: true,
+        store_term_vector_positions: true,
+        store_term_vector_offsets: true,
+        ..FieldType::default()
+    };
+    let plain_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqs,
+        ..FieldType::default()
+    };
+    for text in &texts {
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+        for (field, field_type) in &[
+            ("postings", &postings_type),
+            ("vectors", &vectors_type),
+            ("plain", &plain_type),
+        ] {
+            let token_stream =
+                WhitespaceTokenizer::new(Box::new(StringReader::new(text.to_string())));
+            doc.push(Box::new(Field::new(
+                field.to_string(),
+                (*field_type).clone(),
+                None,
+                Some(Box::new(token_stream)),
+            )));
+        }
+        doc.push(Box::new(new_stored_text_field(
+            "body".into(),
+            text.to_string(),
+        )));
+        writer.add_document(doc)?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let fields = ["postings", "vectors", "plain"];
+    let mut highlighter = UnifiedHighlighter::default().with_max_passages(2);
+    for field in &fields {
+        highlighter = highlighter.with_field_config(
+            field,
+            FieldHighlightConfig::default().with_stored_field("body"),
+        );
+    }
+    let leaf = reader.leaf_reader_for_doc(0);
+    assert_eq!(
+        highlighter.offset_source(&leaf, "postings"),
+        OffsetSource::Postings
+    );
+    assert_eq!(
+        highlighter.offset_source(&leaf, "vectors"),
+        OffsetSource::TermVectors
+    );
+    assert_eq!(
+        highlighter.offset_source(&leaf, "plain"),
+        OffsetSource::Analysis
+    );
+
+    let boosts = fields.iter().map(|f| (f.to_string(), 1.0)).collect();
+    let query: Box<dyn Query<CodecEnum>> =
+        QueryStringQueryBuilder::new("book Alice".into(), boosts, 0, 1.0).build()?;
+    let snippets = highlighter.highlight_fields(&fields, query.as_ref(), &reader, &[0, 1])?;
+    for field in &fields {
+        assert_eq!(
+            snippets[*field],
+            vec![
+                Some(
+                    "<b>Alice</b> was beginning to get very tired.... She had peeped into the \
+                     <b>book</b> her sister was reading, but it had no pictures or conversations \
+                     in it."
+                        .to_string()
+                ),
+                Some("The rabbit hole went straight on like a tunnel.".to_string()),
+            ]
+        );
+    }
+
+    // the passages with their matches, for frontends rendering their own markup
+    let highlights = highlighter.highlight_passages("postings", query.as_ref(), &reader, &[0])?;
+    let highlight = highlights[0].as_ref().unwrap();
+    let texts: Vec<_> = highlight
+        .passages
+        .iter()
+        .map(|p| highlight.passage_text(p))
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "Alice was beginning to get very tired.",
+            "She had peeped into the book her sister was reading, but it had no pictures or \
+             conversations in it.",
+        ]
+    );
+    let content: Vec<char> = highlight.content.chars().collect();
+    let matches: Vec<String> = highlight
+        .passages
+        .iter()
+        .flat_map(|p| &p.matches)
+        .map(|m| content[m.start_offset..m.end_offset].iter().collect())
+        .collect();
+    assert_eq!(matches, vec!["Alice", "book"]);
+    assert!(highlight.passages.iter().all(|p| p.score > 0.0));
+
+    let highlighter = highlighter.with_highlight_no_match(false);
+    let snippets = highlighter.highlight("plain", query.as_ref(), &reader, &[1])?;
+    assert_eq!(snippets, vec![None]);
+    Ok(())
+}
+
+#[test]
+fn plain_highlighter() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_plain_highlighter")?;
+
+    // indexed without term vectors or offsets, only the stored text is used
+    let field_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqs,
+        ..FieldType::default()
+    };
+    let fpath = "tests/fixtures/alice.txt";
+    let f = File::open(fpath).expect("failed to open input file");
+    for line in BufReader::new(f).lines().take(200) {
+        let text = line.expect("failed to read line");
+        let token_stream = WhitespaceTokenizer::new(Box::new(StringReader::new(text.clone())));
+        let doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(Field::new(
+                "body".into(),
+                field_type.clone(),
+                None,
+                Some(Box::new(token_stream)),
+            )),
+            Box::new(new_stored_text_field("body.raw".into(), text)),
+        ];
+        writer.add_document(doc)?;
+    }
+
+    let searcher = commit_and_search(&writer)?;
+    let query: Box<dyn Query<CodecEnum>> =
+        QueryStringQueryBuilder::new("+rabbit +either".into(), vec![("body".into(), 1.0)], 0, 1.0)
+            .build()?;
+    let mut collector = TopDocsCollector::new(10);
+    searcher.search(query.as_ref(), &mut collector)?;
+    let top_docs = collector.top_docs();
+    assert_eq!(top_docs.total_hits(), 1);
+    let doc_id = top_docs.score_docs()[0].doc_id();
+
+    let highlighter = Highlighter::default();
+    let fragments = highlighter.get_best_fragments_for_doc(
+        query.as_ref(),
+        searcher.reader(),
+        doc_id,
+        "body",
+        "body.raw",
+        1,
+    )?;
+    assert_eq!(
+        fragments,
+        vec!["had never before seen a <b>rabbit</b> with <b>either</b> a waistcoat-pocket, or a"]
+    );
+    Ok(())
+}
+
+#[test]
+fn fvh_postings_offsets() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_fvh_postings_offsets")?;
+
+    // offsets in the postings, no term vectors
+    let field_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+        ..FieldType::default()
+    };
+    let fpath = "tests/fixtures/alice.txt";
+    let f = File::open(fpath).expect("failed to open input file");
+    for line in BufReader::new(f).lines() {
+        let text = line.expect("failed to read line");
+        let token_stream = WhitespaceTokenizer::new(Box::new(StringReader::new(text.clone())));
+        let doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(Field::new(
+                "title".into(),
+                field_type.clone(),
+                None,
+                Some(Box::new(token_stream)),
+            )),
+            Box::new(new_stored_text_field("title.raw".into(), text)),
+        ];
+        writer.add_document(doc)?;
+    }
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let query: Box<dyn Query<CodecEnum>> = QueryStringQueryBuilder::new(
+        "the +dream of +Wonderland".into(),
+        vec![("title".into(), 1.0)],
+        0,
+        1.0,
+    )
+    .build()?;
+    let doc_id = 3400;
+    let leaf = reader.leaf_reader_for_doc(doc_id);
+    assert!(leaf.reader.term_vector(doc_id - leaf.doc_base)?.is_none());
+
+    let mut highlighter = FastVectorHighlighter::default();
+    let mut field_query = FieldQuery::new(query.as_ref(), Some(&reader), false, true)?;
+    let highlight_res = highlighter.get_best_fragments(
+        &mut field_query,
+        &leaf,
+        doc_id,
+        "title",
+        "title.raw",
+        100,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(true),
+    )?;
+    assert_eq!(
+        highlight_res,
+        vec![
+            "perhaps even with <b>the</b> <b>dream</b> <b>of</b> <b>Wonderland</b> <b>of</b> long \
+             ago: and how she"
+        ]
+    );
+
+    // a tag set per query term for the stored field
+    let fragments_builder = BaseFragmentsBuilder::new(None, None, None).with_field_tags(
+        "title.raw",
+        &COLORED_PRE_TAGS,
+        &COLORED_POST_TAGS,
+    );
+    let mut highlighter = highlighter.with_fragments_builder(fragments_builder);
+    let highlight_res = highlighter.get_best_fragments(
+        &mut field_query,
+        &leaf,
+        doc_id,
+        "title",
+        "title.raw",
+        100,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(true),
+    )?;
+    assert_eq!(
+        highlight_res,
+        vec![
+            "perhaps even with <b style=\"background:aquamarine\">the</b> \
+             <b style=\"background:yellow\">dream</b> <b style=\"background:magenta\">of</b> \
+             <b style=\"background:lawngreen\">Wonderland</b> \
+             <b style=\"background:magenta\">of</b> long ago: and how she"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn fvh_multi_valued_phrases() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_fvh_multi_valued_phrases")?;
+
+    let field_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+        ..FieldType::default()
+    };
+    let values = [
+        "Alice was beginning to get very tired",
+        "of sitting by her sister on the bank, and of her own sister",
+    ];
+    let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+    for value in &values {
+        let token_stream = WhitespaceTokenizer::new(Box::new(StringReader::new(value.to_string())));
+        doc.push(Box::new(Field::new(
+            "body".into(),
+            field_type.clone(),
+            None,
+            Some(Box::new(token_stream)),
+        )));
+        doc.push(Box::new(new_stored_text_field(
+            "body.raw".into(),
+            value.to_string(),
+        )));
+    }
+    writer.add_document(doc)?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = reader.leaf_reader_for_doc(0);
+    let term = |text: &str| Term::new("body".into(), text.as_bytes().to_vec());
+    let mut highlighter = FastVectorHighlighter::default();
+    let mut highlight = |query: &dyn Query<CodecEnum>, phrase_highlight: bool| {
+        let mut field_query = FieldQuery::new(query, Some(&reader), phrase_highlight, true)?;
+        highlighter.get_best_fragments(
+            &mut field_query,
+            &leaf,
+            0,
+            "body",
+            "body.raw",
+            100,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+
+    // the offsets of the second value follow the first one and the separator
+    let query = TermQuery::new(term("tired"), 1.0, None);
+    assert_eq!(
+        highlight(&query, true)?,
+        vec![
+            "Alice was beginning to get very <b>tired</b> of sitting by her sister on the bank, \
+             and of her own sister"
+        ]
+    );
+
+    // only the whole phrase is highlighted
+    let query = PhraseQuery::build(vec![term("her"), term("sister")], 0, None, None)?;
+    assert_eq!(
+        highlight(&query, true)?,
+        vec![
+            "Alice was beginning to get very tired of sitting by <b>her sister</b> on the bank, \
+             and of her own sister"
+        ]
+    );
+
+    // the terms of the phrase are highlighted anywhere
+    assert_eq!(
+        highlight(&query, false)?,
+        vec![
+            "Alice was beginning to get very tired of sitting by <b>her sister</b> on the bank, \
+             and of <b>her</b> own <b>sister</b>"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn fvh_matched_fields() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_fvh_matched_fields")?;
+
+    let field_type = FieldType {
+        index_options: IndexOptions::DocsAndFreqsAndPositionsAndOffsets,
+        ..FieldType::default()
+    };
+    let text = "Rabbit holes and Running rabbits";
+    // a stemmed variant of the text with offsets into the original
+    let stems = vec![
+        Token::with_term(b"rabbit", 1, 0, 6),
+        Token::with_term(b"hole", 1, 7, 12),
+        Token::with_term(b"and", 1, 13, 16),
+        Token::with_term(b"run", 1, 17, 24),
+        Token::with_term(b"rabbit", 1, 25, 32),
+    ];
+    let token_stream = WhitespaceTokenizer::new(Box::new(StringReader::new(text.to_string())));
+    let doc: Vec<Box<dyn Fieldable>> = vec![
+        Box::new(Field::new(
+            "title".into(),
+            field_type.clone(),
+            None,
+            Some(Box::new(token_stream)),
+        )),
+        Box::new(Field::new_pre_tokenized(
+            "title.stemmed".into(),
+            field_type,
+            stems,
+        )?),
+        Box::new(new_stored_text_field("title.raw".into(), text.to_string())),
+    ];
+    writer.add_document(doc)?;
+    writer.commit()?;
+
+    let reader = writer.get_reader(true, false)?;
+    let leaf = reader.leaf_reader_for_doc(0);
+    let query: Box<dyn Query<CodecEnum>> = QueryStringQueryBuilder::new(
+        "Rabbit run".into(),
+        vec![("title".into(), 1.0), ("title.stemmed".into(), 1.0)],
+        0,
+        1.0,
+    )
+    .build()?;
+
+    let mut highlighter = FastVectorHighlighter::default();
+    let mut field_query = highlighter.get_field_query(query.as_ref(), Some(&reader))?;
+    let highlight_res = highlighter.get_best_fragments(
+        &mut field_query,
+        &leaf,
+        0,
+        "title",
+        "title.raw",
+        100,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )?;
+    assert_eq!(
+        highlight_res,
+        vec!["<b>Rabbit</b> holes and Running rabbits"]
+    );
+
+    // the matches of the stemmed field are highlighted in the original text
+    let matched_fields = vec!["title".to_string(), "title.stemmed".to_string()];
+    let highlight_res = highlighter.get_best_fragments_with_tags_fields(
+        &mut field_query,
+        &leaf,
+        0,
+        "title.raw",
+        &matched_fields,
+        100,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )?;
+    let expected = vec!["<b>Rabbit</b> holes and <b>Running</b> rabbits"];
+    assert_eq!(highlight_res, expected);
+
+    let mut highlighter = highlighter.with_matched_fields("title", &["title.stemmed"]);
+    let highlight_res = highlighter.get_best_fragments(
+        &mut field_query,
+        &leaf,
+        0,
+        "title",
+        "title.raw",
+        100,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )?;
+    assert_eq!(highlight_res, expected);
+    Ok(())
+}
+
+#[test]
+fn direct_spell_checker() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_direct_spell_checker")?;
+    let texts = [
+        "lucene search engine",
+        "lucene is fast",
+        "lucent technologies",
+        "lucerne lake",
+        "the lucky number",
+    ];
+    for text in &texts {
+        writer.add_document(vec![new_index_text_field("body".into(), text.to_string())])?;
+    }
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+
+    let term = |text: &str| Term::new("body".into(), text.as_bytes().to_vec());
+    let suggest = |checker: &DirectSpellChecker, text: &str, mode: SuggestMode| {
+        checker
+            .suggest_similar(&term(text), 5, &reader, mode)
+            .map(|words| {
+                words
+                    .into_iter()
+                    .map(|w| (w.string, w.freq))
+                    .collect::<Vec<_>>()
+            })
+    };
+
+    let checker = DirectSpellChecker::default();
+    // the longer lucerne is the closest match, lucent is two edits away
+    assert_eq!(
+        suggest(&checker, "Lucenne", SuggestMode::WhenNotInIndex)?,
+        vec![
+            ("lucerne".to_string(), 1),
+            ("lucene".to_string(), 2),
+            ("lucent".to_string(), 1)
+        ]
+    );
+    let words = checker.suggest_similar(&term("lucenne"), 1, &reader, SuggestMode::default())?;
+    assert_eq!(words.len(), 1);
+    assert!((words[0].score - (1.0 - 1.0 / 7.0)).abs() < 1e-6);
+
+    // terms of the index are only corrected on demand
+    assert!(suggest(&checker, "lucent", SuggestMode::WhenNotInIndex)?.is_empty());
+    assert_eq!(
+        suggest(&checker, "lucent", SuggestMode::Always)?,
+        vec![("lucene".to_string(), 2), ("lucerne".to_string(), 1)]
+    );
+    assert_eq!(
+        suggest(&checker, "lucent", SuggestMode::MorePopular)?,
+        vec![("lucene".to_string(), 2)]
+    );
+
+    let checker = DirectSpellChecker::default()
+        .with_accuracy(0.8)
+        .with_max_edits(1)?;
+    assert_eq!(
+        suggest(&checker, "lucenne", SuggestMode::WhenNotInIndex)?,
+        vec![("lucerne".to_string(), 1), ("lucene".to_string(), 2)]
+    );
+    // short terms and misses of the min prefix get no suggestions
+    assert!(suggest(&checker, "luc", SuggestMode::WhenNotInIndex)?.is_empty());
+    assert!(suggest(&checker, "mucene", SuggestMode::WhenNotInIndex)?.is_empty());
+    let checker = checker.with_min_prefix(0);
+    assert_eq!(
+        suggest(&checker, "mucene", SuggestMode::WhenNotInIndex)?,
+        vec![("lucene".to_string(), 2)]
+    );
+    Ok(())
+}
+
+#[test]
+fn phrase_suggester() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_phrase_suggester")?;
+    let texts = [
+        "the nobel prize winner",
+        "nobel prize for physics",
+        "noble gas elements",
+        "prize winner announced",
+    ];
+    for text in &texts {
+        writer.add_document(vec![new_index_text_field("body".into(), text.to_string())])?;
+    }
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+
+    let suggest = |suggester: &PhraseSuggester, text: &str| {
+        suggester.suggest(text, 3, &reader).map(|suggestions| {
+            suggestions
+                .into_iter()
+                .map(|s| (s.text, s.collate_match))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let suggester = PhraseSuggester::new("body");
+    assert_eq!(
+        suggest(&suggester, "nobel prize winer")?,
+        vec![("nobel prize winner".to_string(), None)]
+    );
+    // spelled right, so only corrected on demand
+    assert!(suggest(&suggester, "noble prize")?.is_empty());
+    let suggester = PhraseSuggester::new("body").with_suggest_mode(SuggestMode::Always);
+    let suggestions = suggester.suggest("noble prize", 3, &reader)?;
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0].text, "nobel prize");
+    // a transposition away, then the bigram of the index
+    assert!((suggestions[0].score - 0.8 * 0.95 * 0.6 * 1.0).abs() < 1e-6);
+
+    // gas is a better fit than gass, but never follows nobel
+    let suggester = PhraseSuggester::new("body");
+    assert_eq!(
+        suggest(&suggester, "nobel gass")?,
+        vec![("nobel gas".to_string(), None)]
+    );
+    let suggester = suggester.with_collate(true);
+    assert!(suggest(&suggester, "nobel gass")?.is_empty());
+    let suggester = suggester.with_collate_prune(true);
+    assert_eq!(
+        suggest(&suggester, "nobel gass")?,
+        vec![("nobel gas".to_string(), Some(false))]
+    );
+    assert_eq!(
+        suggest(&suggester, "nobel prize winer")?,
+        vec![("nobel prize winner".to_string(), Some(true))]
+    );
+    Ok(())
+}
+
+#[test]
+fn free_text_suggester_from_index() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_free_text_suggester")?;
+    let texts = [
+        "new york city",
+        "new york times",
+        "new jersey",
+        "york minster",
+        "new delhi news",
+    ];
+    for (i, text) in texts.iter().enumerate() {
+        writer.add_document(
+            doc()
+                .string("id", &i.to_string())
+                .stored_text("body", text)
+                .build(),
+        )?;
+    }
+    writer.delete_documents_by_terms(vec![Term::new("id".into(), b"4".to_vec())])?;
+    writer.commit()?;
+    let reader = writer.get_reader(true, false)?;
+
+    let mut suggester = FreeTextSuggester::default();
+    suggester.build_from_reader(&reader, "body")?;
+    // the deleted doc isn't part of the corpus
+    assert_eq!(suggester.total_tokens(), 10);
+    let keys: Vec<_> = suggester
+        .lookup("new ", 3)?
+        .into_iter()
+        .map(|s| s.key)
+        .collect();
+    assert_eq!(keys, vec!["new york", "new jersey", "new"]);
+    assert!(suggester.lookup("new d", 3)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn suggesters_build_offline() -> Result<()> {
+    let dir_path = new_index_dir("/tmp/test_rucene_suggesters_build_offline")?;
+    let directory = FSDirectory::new(&dir_path)?;
+    let mut entries: Vec<(String, i64)> = (0..2000)
+        .map(|i| (format!("term{} group{}", i % 700, i % 7), i64::from(i)))
+        .collect();
+    entries.push(("apache lucene".into(), 30));
+    entries.push(("apache lucene".into(), 10));
+
+    // a tiny sort buffer spills many partitions, which are merged several times
+    let mut offline = AnalyzingSuggester::default().with_sort_buffer_bytes(256);
+    offline.build_offline(&directory, entries.clone())?;
+    let mut suggester = AnalyzingSuggester::default();
+    suggester.build(entries.clone())?;
+    assert_eq!(offline.count(), suggester.count());
+    for prefix in &["term1", "term69", "apache", "group"] {
+        assert_eq!(offline.lookup(prefix, 5)?, suggester.lookup(prefix, 5)?);
+    }
+    assert_eq!(offline.lookup("apache", 1)?[0].value, 30);
+    let mut fuzzy = FuzzySuggester::new(AnalyzingSuggester::default().with_sort_buffer_bytes(256));
+    fuzzy.build_offline(&directory, entries)?;
+    assert_eq!(fuzzy.lookup("apahce", 1)?[0].key, "apache lucene");
+
+    let texts: Vec<String> = (0..500)
+        .map(|i| format!("new york {} city {}", i % 13, i % 3))
+        .collect();
+    let mut offline = FreeTextSuggester::default().with_sort_buffer_bytes(128);
+    offline.build_offline(&directory, &texts)?;
+    let mut suggester = FreeTextSuggester::default();
+    suggester.build(&texts)?;
+    assert_eq!(offline.total_tokens(), suggester.total_tokens());
+    for key in &["new ", "york 1", "city "] {
+        assert_eq!(offline.lookup(key, 5)?, suggester.lookup(key, 5)?);
+    }
+
+    let mut empty = AnalyzingSuggester::default();
+    empty.build_offline(&directory, Vec::<(&str, i64)>::new())?;
+    assert_eq!(empty.count(), 0);
+    assert!(empty.lookup("apache", 1)?.is_empty());
+    // the temp files are deleted
+    assert!(directory.list_all()?.is_empty());
+
+    let mut invalid = AnalyzingSuggester::default();
+    assert!(invalid
+        .build_offline(&directory, vec![("apache", -1)])
+        .is_err());
+    assert!(directory.list_all()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn ram_usage_accounting() -> Result<()> {
+    let writer = new_index_writer("/tmp/test_rucene_accountable")?;
+    assert_eq!(writer.ram_bytes_used(), 0);
+
+    for i in 0..100 {
+        writer.add_document(vec![
+            Box::new(new_index_text_field("title".into(), format!("title {}", i)))
+                as Box<dyn Fieldable>,
+            Box::new(NumericDocValuesField::new("rank", i)),
+        ])?;
+    }
+    assert!(writer.ram_bytes_used() > 0);
+    let buffer = &writer.child_resources()[0];
+    assert_eq!(buffer.name, "indexing buffer");
+    assert_eq!(buffer.ram_bytes_used, writer.ram_bytes_used());
+    writer.commit()?;
+    assert_eq!(writer.ram_bytes_used(), 0);
+
+    let reader = writer.get_reader(true, false)?;
+    let tree = NamedAccountable::new("reader", &reader);
+    assert!(tree.ram_bytes_used > 0);
+    assert_eq!(tree.children.len(), reader.leaves().len());
+    let segment = &tree.children[0];
+    let postings = &segment.children[0];
+    assert_eq!(postings.name, "postings");
+    assert!(postings.ram_bytes_used > 0);
+    assert!(segment.children.iter().any(|c| c.name == "doc values"));
+    assert!(tree.to_string().contains("postings"));
+    Ok(())
+}